@@ -0,0 +1,9 @@
+-- Migration 006: Per-user storage usage and quotas
+-- Caches bytes used by each user's documents and vehicle images
+
+CREATE TABLE IF NOT EXISTS storage_usage (
+    user_id TEXT PRIMARY KEY,
+    used_bytes INTEGER NOT NULL DEFAULT 0,
+    quota_bytes INTEGER, -- NULL = unlimited
+    updated_at INTEGER NOT NULL
+);
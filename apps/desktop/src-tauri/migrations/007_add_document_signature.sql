@@ -0,0 +1,4 @@
+-- Migration 007: Tamper-evident document signatures
+-- Base64 HMAC-SHA256 of the file at the time it was generated (NULL = unsigned)
+
+ALTER TABLE documents ADD COLUMN signature TEXT;
@@ -0,0 +1,13 @@
+-- Migration 008: Command timing metrics
+-- Local copy of IPC command timings, written only when command_metrics_persist is on
+-- Never holds command arguments or error messages
+
+CREATE TABLE IF NOT EXISTS command_metrics (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    command TEXT NOT NULL,
+    duration_us INTEGER NOT NULL,
+    error_code TEXT, -- NULL on success
+    recorded_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_command_metrics_recorded_at ON command_metrics(recorded_at);
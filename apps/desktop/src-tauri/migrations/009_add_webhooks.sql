@@ -0,0 +1,30 @@
+-- Migration 009: Outbound webhooks
+-- Per-user webhook subscriptions and a log of delivery attempts (no payloads are stored)
+
+CREATE TABLE IF NOT EXISTS webhooks (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL, -- HMAC-SHA256 signing key, shared with the receiver
+    events TEXT NOT NULL, -- JSON array of event names
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhooks_user ON webhooks(user_id);
+
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    webhook_id TEXT NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+    delivery_id TEXT NOT NULL, -- Same for every attempt at one event
+    event TEXT NOT NULL,
+    attempt INTEGER NOT NULL,
+    status_code INTEGER, -- NULL when no response was received
+    error TEXT,
+    success INTEGER NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    attempted_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_webhook ON webhook_deliveries(webhook_id, attempted_at);
@@ -0,0 +1,17 @@
+-- Migration 010: Sales tax rates
+-- One statewide row per state (county = '') carrying the state's rules, plus optional
+-- county/local rows whose rate is added on top. Rates are parts per million
+-- (6.25% = 62500) so tax math stays in integer cents.
+
+CREATE TABLE IF NOT EXISTS tax_rates (
+    state TEXT NOT NULL, -- Two-letter code, upper case
+    county TEXT NOT NULL DEFAULT '' COLLATE NOCASE, -- '' = statewide row
+    rate_ppm INTEGER NOT NULL,
+    trade_in_credit INTEGER NOT NULL DEFAULT 1, -- Trade-in reduces the taxable base (statewide rows)
+    doc_fee_taxable INTEGER NOT NULL DEFAULT 1, -- Statewide rows
+    rounding TEXT NOT NULL DEFAULT 'half_up', -- Statewide rows: half_up, half_even or down
+    max_taxable_cents INTEGER, -- Rate only applies to this much of the base (NULL = all of it)
+    max_tax_cents INTEGER, -- Cap on the tax from this row (NULL = no cap)
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (state, county)
+);
@@ -0,0 +1,16 @@
+-- Migration 011: Deal document templates
+-- Fillable PDF templates and how their form fields map to deal data (see pdf_forms.rs)
+
+CREATE TABLE IF NOT EXISTS document_templates (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    document_type TEXT NOT NULL, -- documents.type of generated files
+    template_path TEXT NOT NULL, -- AcroForm PDF on disk
+    field_map TEXT NOT NULL DEFAULT '{}', -- JSON object: PDF field name -> value template
+    flatten INTEGER NOT NULL DEFAULT 1,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_templates_user ON document_templates(user_id);
@@ -0,0 +1,8 @@
+-- Migration 012: Per-state template packs
+-- Templates can be limited to one state (NULL = any state) and carry the pack's version,
+-- so re-importing a pack only replaces templates it has a newer copy of
+
+ALTER TABLE document_templates ADD COLUMN state TEXT;
+ALTER TABLE document_templates ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+
+CREATE INDEX IF NOT EXISTS idx_document_templates_state ON document_templates(user_id, state);
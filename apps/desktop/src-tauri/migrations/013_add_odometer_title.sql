@@ -0,0 +1,9 @@
+-- Migration 013: Odometer disclosure and title status on deals
+-- State paperwork needs the odometer reading at sale, what the seller certifies about it,
+-- and where the title stands (and who holds the lien when the deal is financed)
+
+ALTER TABLE deals ADD COLUMN odometer_at_sale INTEGER;
+ALTER TABLE deals ADD COLUMN odometer_disclosure TEXT; -- actual, exceeds (mechanical limits) or not_actual
+ALTER TABLE deals ADD COLUMN title_status TEXT;
+ALTER TABLE deals ADD COLUMN title_state TEXT; -- Two-letter code, upper case
+ALTER TABLE deals ADD COLUMN lien_holder TEXT;
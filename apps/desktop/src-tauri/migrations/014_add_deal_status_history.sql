@@ -0,0 +1,22 @@
+-- Migration 014: Deal status lifecycle
+-- Every status change (including the status a deal is created with) is recorded, with
+-- who made it and whether an admin override pushed it outside the lifecycle.
+-- Statuses older builds wrote are mapped to their canonical names; anything else is
+-- left alone and grandfathered (see deal_status.rs).
+
+CREATE TABLE IF NOT EXISTS deal_status_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    deal_id TEXT NOT NULL REFERENCES deals(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    from_status TEXT, -- NULL for the status the deal was created with
+    to_status TEXT NOT NULL,
+    overridden INTEGER NOT NULL DEFAULT 0,
+    reason TEXT,
+    changed_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_deal_status_history_deal ON deal_status_history(deal_id, changed_at);
+
+UPDATE deals SET status = 'quote' WHERE status = 'draft';
+UPDATE deals SET status = 'pending' WHERE status = 'in_progress';
+UPDATE deals SET status = 'cancelled' WHERE status = 'canceled';
@@ -0,0 +1,22 @@
+-- Migration 015: Recently opened records and pinned favorites
+-- One row per (user, record); recent_items keeps only each user's newest 200 (pruned on
+-- write). Neither table references the records directly, so rows for deleted records
+-- simply stop matching when the lists join back to clients/vehicles/deals.
+
+CREATE TABLE IF NOT EXISTS recent_items (
+    user_id TEXT NOT NULL,
+    entity_type TEXT NOT NULL, -- client, vehicle or deal
+    entity_id TEXT NOT NULL,
+    accessed_at INTEGER NOT NULL,
+    PRIMARY KEY (user_id, entity_type, entity_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_recent_items_user ON recent_items(user_id, accessed_at DESC);
+
+CREATE TABLE IF NOT EXISTS favorites (
+    user_id TEXT NOT NULL,
+    entity_type TEXT NOT NULL,
+    entity_id TEXT NOT NULL,
+    pinned_at INTEGER NOT NULL,
+    PRIMARY KEY (user_id, entity_type, entity_id)
+);
@@ -0,0 +1,106 @@
+-- Migration 016: Search index for the global search bar (see global_search.rs)
+-- One trigram FTS5 table per searchable table, so substring matches (LIKE '%abc%') use an
+-- index instead of scanning every row. Index rows share the rowid of the row they describe
+-- and are kept current by triggers; deal rows include the client's name and the vehicle's
+-- VIN/stock number, so changes to those refresh the deal's row too.
+-- NOTE: VACUUM can renumber rowids of these tables; rebuild the index after one (clear the
+-- *_search tables and re-run the INSERT ... SELECT statements below).
+
+CREATE VIRTUAL TABLE IF NOT EXISTS clients_search USING fts5(body, tokenize = 'trigram');
+CREATE VIRTUAL TABLE IF NOT EXISTS vehicles_search USING fts5(body, tokenize = 'trigram');
+CREATE VIRTUAL TABLE IF NOT EXISTS deals_search USING fts5(body, tokenize = 'trigram');
+
+-- Clients: name, email, phone as entered and as digits only
+INSERT INTO clients_search (rowid, body)
+SELECT rowid, first_name || ' ' || last_name || ' ' || COALESCE(email, '') || ' '
+    || COALESCE(phone, '') || ' ' || REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(
+    COALESCE(phone, ''), '(', ''), ')', ''), '-', ''), ' ', ''), '.', ''), '+', '')
+FROM clients;
+
+CREATE TRIGGER IF NOT EXISTS clients_search_insert AFTER INSERT ON clients BEGIN
+    INSERT INTO clients_search (rowid, body)
+    VALUES (new.rowid, new.first_name || ' ' || new.last_name || ' ' || COALESCE(new.email, '')
+        || ' ' || COALESCE(new.phone, '') || ' ' || REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(
+        REPLACE(COALESCE(new.phone, ''), '(', ''), ')', ''), '-', ''), ' ', ''), '.', ''), '+', ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS clients_search_update
+AFTER UPDATE OF first_name, last_name, email, phone ON clients BEGIN
+    DELETE FROM clients_search WHERE rowid = old.rowid;
+    INSERT INTO clients_search (rowid, body)
+    VALUES (new.rowid, new.first_name || ' ' || new.last_name || ' ' || COALESCE(new.email, '')
+        || ' ' || COALESCE(new.phone, '') || ' ' || REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(
+        REPLACE(COALESCE(new.phone, ''), '(', ''), ')', ''), '-', ''), ' ', ''), '.', ''), '+', ''));
+    DELETE FROM deals_search WHERE rowid IN (SELECT rowid FROM deals WHERE client_id = new.id);
+    INSERT INTO deals_search (rowid, body)
+    SELECT d.rowid, d.id || ' ' || d.status || ' ' || d.type || ' '
+        || new.first_name || ' ' || new.last_name || ' '
+        || COALESCE(v.vin, '') || ' ' || COALESCE(v.stock_number, '')
+    FROM deals d LEFT JOIN vehicles v ON v.id = d.vehicle_id
+    WHERE d.client_id = new.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS clients_search_delete AFTER DELETE ON clients BEGIN
+    DELETE FROM clients_search WHERE rowid = old.rowid;
+END;
+
+-- Vehicles: year make model, VIN, stock number
+INSERT INTO vehicles_search (rowid, body)
+SELECT rowid, year || ' ' || make || ' ' || model || ' ' || vin || ' ' || COALESCE(stock_number, '')
+FROM vehicles;
+
+CREATE TRIGGER IF NOT EXISTS vehicles_search_insert AFTER INSERT ON vehicles BEGIN
+    INSERT INTO vehicles_search (rowid, body)
+    VALUES (new.rowid, new.year || ' ' || new.make || ' ' || new.model || ' ' || new.vin || ' '
+        || COALESCE(new.stock_number, ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS vehicles_search_update
+AFTER UPDATE OF year, make, model, vin, stock_number ON vehicles BEGIN
+    DELETE FROM vehicles_search WHERE rowid = old.rowid;
+    INSERT INTO vehicles_search (rowid, body)
+    VALUES (new.rowid, new.year || ' ' || new.make || ' ' || new.model || ' ' || new.vin || ' '
+        || COALESCE(new.stock_number, ''));
+    DELETE FROM deals_search WHERE rowid IN (SELECT rowid FROM deals WHERE vehicle_id = new.id);
+    INSERT INTO deals_search (rowid, body)
+    SELECT d.rowid, d.id || ' ' || d.status || ' ' || d.type || ' '
+        || COALESCE(c.first_name || ' ' || c.last_name, '') || ' '
+        || new.vin || ' ' || COALESCE(new.stock_number, '')
+    FROM deals d LEFT JOIN clients c ON c.id = d.client_id
+    WHERE d.vehicle_id = new.id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS vehicles_search_delete AFTER DELETE ON vehicles BEGIN
+    DELETE FROM vehicles_search WHERE rowid = old.rowid;
+END;
+
+-- Deals: id, status, type, buyer name, vehicle VIN and stock number
+INSERT INTO deals_search (rowid, body)
+SELECT d.rowid, d.id || ' ' || d.status || ' ' || d.type || ' '
+    || COALESCE(c.first_name || ' ' || c.last_name, '') || ' '
+    || COALESCE(v.vin, '') || ' ' || COALESCE(v.stock_number, '')
+FROM deals d
+LEFT JOIN clients c ON c.id = d.client_id
+LEFT JOIN vehicles v ON v.id = d.vehicle_id;
+
+CREATE TRIGGER IF NOT EXISTS deals_search_insert AFTER INSERT ON deals BEGIN
+    INSERT INTO deals_search (rowid, body)
+    SELECT new.rowid, new.id || ' ' || new.status || ' ' || new.type || ' '
+        || COALESCE((SELECT first_name || ' ' || last_name FROM clients WHERE id = new.client_id), '')
+        || ' ' || COALESCE((SELECT vin || ' ' || COALESCE(stock_number, '') FROM vehicles
+            WHERE id = new.vehicle_id), '');
+END;
+
+CREATE TRIGGER IF NOT EXISTS deals_search_update
+AFTER UPDATE OF id, status, type, client_id, vehicle_id ON deals BEGIN
+    DELETE FROM deals_search WHERE rowid = old.rowid;
+    INSERT INTO deals_search (rowid, body)
+    SELECT new.rowid, new.id || ' ' || new.status || ' ' || new.type || ' '
+        || COALESCE((SELECT first_name || ' ' || last_name FROM clients WHERE id = new.client_id), '')
+        || ' ' || COALESCE((SELECT vin || ' ' || COALESCE(stock_number, '') FROM vehicles
+            WHERE id = new.vehicle_id), '');
+END;
+
+CREATE TRIGGER IF NOT EXISTS deals_search_delete AFTER DELETE ON deals BEGIN
+    DELETE FROM deals_search WHERE rowid = old.rowid;
+END;
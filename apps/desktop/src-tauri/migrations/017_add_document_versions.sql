@@ -0,0 +1,18 @@
+-- Migration 017: Earlier versions of regenerated documents
+-- db_update_document copies the current file into versions/{document_id}/ next to it before
+-- the path or checksum changes, so a corrected bill of sale doesn't lose the signed original.
+-- Rows (and their files) are pruned oldest-first beyond the document_versions_max setting.
+
+CREATE TABLE IF NOT EXISTS document_versions (
+    id TEXT PRIMARY KEY,
+    document_id TEXT NOT NULL REFERENCES documents(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL, -- 1, 2, ... per document; numbers aren't reused after pruning
+    file_path TEXT NOT NULL,
+    file_size INTEGER,
+    file_checksum TEXT, -- SHA-256 of the snapshot
+    created_at INTEGER NOT NULL,
+    reason TEXT,
+    UNIQUE (document_id, version)
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_versions_document ON document_versions(document_id, version);
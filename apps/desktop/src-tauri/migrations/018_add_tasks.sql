@@ -0,0 +1,19 @@
+-- Migration 018: Follow-up tasks and reminders
+-- Optionally linked to a client, vehicle or deal (entity_type/entity_id, not a foreign key,
+-- so a task survives its record being deleted). notified_at records when the "task-due"
+-- event fired, so each due task is announced once; snoozing clears it.
+
+CREATE TABLE IF NOT EXISTS tasks (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    title TEXT NOT NULL,
+    body TEXT,
+    entity_type TEXT, -- client, vehicle or deal
+    entity_id TEXT,
+    due_at INTEGER NOT NULL,
+    completed_at INTEGER,
+    notified_at INTEGER,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_tasks_user_due ON tasks(user_id, completed_at, due_at);
@@ -0,0 +1,25 @@
+-- Migration 019: Lender directory
+-- The bank or finance company that buys a deal's contract. deals.lender_id points here;
+-- deleting a lender that deals still reference is refused (reassign them first).
+-- default_reserve_bps is the dealer reserve the lender usually pays, in basis points.
+
+CREATE TABLE IF NOT EXISTS lenders (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    address TEXT,
+    city TEXT,
+    state TEXT,
+    zip_code TEXT,
+    phone TEXT,
+    ein TEXT, -- XX-XXXXXXX
+    default_reserve_bps INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_lenders_user ON lenders(user_id, name);
+
+ALTER TABLE deals ADD COLUMN lender_id TEXT REFERENCES lenders(id) ON DELETE RESTRICT;
+
+CREATE INDEX IF NOT EXISTS idx_deals_lender ON deals(lender_id);
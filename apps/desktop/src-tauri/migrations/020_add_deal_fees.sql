@@ -0,0 +1,29 @@
+-- Migration 020: Fee line items on deals
+-- Title, registration, temp tag, service contracts, GAP... one row each instead of the
+-- single doc_fee column. deals.doc_fee is kept as the sum of the deal's doc_fee lines
+-- so older readers (the QuickBooks export) keep working.
+-- taxable = 0 keeps a line out of the sales tax base; doc_fee lines marked taxable are
+-- still subject to the state's doc_fee_taxable rule.
+
+CREATE TABLE IF NOT EXISTS deal_fees (
+    id TEXT PRIMARY KEY,
+    deal_id TEXT NOT NULL REFERENCES deals(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    label TEXT NOT NULL,
+    amount_cents INTEGER NOT NULL,
+    taxable INTEGER NOT NULL DEFAULT 1,
+    category TEXT NOT NULL DEFAULT 'other', -- doc_fee, title, registration, temp_tag, service_contract, gap or other
+    sort_order INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_deal_fees_deal ON deal_fees(deal_id, sort_order);
+
+-- Existing doc fees become the deal's first fee line
+INSERT OR IGNORE INTO deal_fees (id, deal_id, user_id, label, amount_cents, taxable, category,
+                                 sort_order, created_at, updated_at)
+SELECT 'doc-fee-' || id, id, COALESCE(user_id, ''), 'Documentation fee',
+       CAST(ROUND(doc_fee * 100) AS INTEGER), 1, 'doc_fee', 0, created_at, updated_at
+FROM deals
+WHERE doc_fee IS NOT NULL AND ROUND(doc_fee * 100) != 0;
@@ -0,0 +1,34 @@
+-- Migration 021: Client communication log and message templates
+-- One row per email, text or call with a client, whether it was sent from the app or
+-- logged by hand. Sending itself happens outside the app (frontend/webhooks); it reports
+-- back with mark_communication_sent, which sets status and the provider's external_id.
+-- message_templates hold the {{placeholder}} text that render_message_template fills in.
+
+CREATE TABLE IF NOT EXISTS communications (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    client_id TEXT NOT NULL REFERENCES clients(id) ON DELETE CASCADE,
+    channel TEXT NOT NULL, -- email, sms or call
+    direction TEXT NOT NULL, -- inbound or outbound
+    subject TEXT,
+    body TEXT,
+    status TEXT NOT NULL, -- draft, queued, sent, failed or logged
+    external_id TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_communications_client ON communications(client_id, created_at);
+
+CREATE TABLE IF NOT EXISTS message_templates (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    channel TEXT NOT NULL, -- email or sms
+    subject TEXT,
+    body TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_message_templates_user ON message_templates(user_id, name);
@@ -0,0 +1,21 @@
+-- Migration 022: Dealer/company profile
+-- One row per user with the dealership details printed on document headers and included
+-- in inventory feeds. Fields are nullable so the profile can be filled in over time;
+-- get_profile_completeness reports which required ones are still missing.
+-- logo_path/logo_thumb_path point at the copies kept under the app data folder.
+
+CREATE TABLE IF NOT EXISTS dealer_profile (
+    user_id TEXT PRIMARY KEY,
+    legal_name TEXT,
+    dba TEXT,
+    license_number TEXT,
+    address TEXT,
+    city TEXT,
+    state TEXT,
+    zip_code TEXT,
+    phone TEXT,
+    email TEXT,
+    logo_path TEXT,
+    logo_thumb_path TEXT,
+    updated_at INTEGER NOT NULL
+);
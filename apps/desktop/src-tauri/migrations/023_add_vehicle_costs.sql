@@ -0,0 +1,26 @@
+-- Migration 023: Cost history on vehicles
+-- Purchase price, reconditioning, transport... one row per expense. vehicles.cost is kept
+-- as the sum of the vehicle's cost rows, in the same transaction as every change to them,
+-- so the inventory list and exports keep reading the column.
+-- Existing costs become the vehicle's purchase row.
+
+CREATE TABLE IF NOT EXISTS vehicle_costs (
+    id TEXT PRIMARY KEY,
+    vehicle_id TEXT NOT NULL REFERENCES vehicles(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    category TEXT NOT NULL DEFAULT 'other', -- purchase, recon, transport or other
+    amount_cents INTEGER NOT NULL,
+    description TEXT,
+    incurred_at INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_vehicle_costs_vehicle ON vehicle_costs(vehicle_id, incurred_at);
+
+INSERT OR IGNORE INTO vehicle_costs (id, vehicle_id, user_id, category, amount_cents,
+                                     description, incurred_at, created_at, updated_at)
+SELECT 'purchase-' || id, id, COALESCE(user_id, ''), 'purchase',
+       CAST(ROUND(cost * 100) AS INTEGER), 'Purchase price', created_at, created_at, updated_at
+FROM vehicles
+WHERE cost IS NOT NULL AND ROUND(cost * 100) != 0;
@@ -0,0 +1,17 @@
+-- Migration 024: Stock numbers are unique per user
+-- Blank stock numbers were turned into NULL, and existing duplicates renamed, before this
+-- runs (stock_numbers::fix_duplicate_stock_numbers); each rename is recorded here so the
+-- dealer can fix up paperwork that used the old number.
+
+CREATE TABLE IF NOT EXISTS stock_number_fixes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vehicle_id TEXT NOT NULL,
+    user_id TEXT,
+    old_stock_number TEXT NOT NULL,
+    new_stock_number TEXT NOT NULL,
+    fixed_at INTEGER NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_vehicles_user_stock_number
+    ON vehicles(user_id, stock_number)
+    WHERE stock_number IS NOT NULL;
@@ -0,0 +1,59 @@
+-- Migration 025: Co-buyers as rows
+-- deals.cobuyer_data was an opaque JSON object. Each co-buyer is now a deal_cobuyers row;
+-- the existing JSON is copied into rows after this runs (deal_cobuyers::migrate_cobuyer_data),
+-- and blobs that can't be read are listed in cobuyer_migration_issues with the original
+-- text. cobuyer_data itself is kept, and still written for one release (see
+-- deal_cobuyers.rs).
+
+CREATE TABLE IF NOT EXISTS deal_cobuyers (
+    id TEXT PRIMARY KEY,
+    deal_id TEXT NOT NULL REFERENCES deals(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    first_name TEXT NOT NULL DEFAULT '',
+    last_name TEXT NOT NULL DEFAULT '',
+    email TEXT,
+    phone TEXT,
+    address TEXT,
+    address_line2 TEXT,
+    city TEXT,
+    state TEXT,
+    zip_code TEXT,
+    drivers_license TEXT,
+    relationship TEXT, -- spouse, parent, business partner... free text
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_deal_cobuyers_deal ON deal_cobuyers(deal_id, created_at);
+
+CREATE TABLE IF NOT EXISTS cobuyer_migration_issues (
+    deal_id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    cobuyer_data TEXT NOT NULL,
+    error TEXT NOT NULL,
+    found_at INTEGER NOT NULL
+);
+
+-- Search index for the global search bar, as in migration 016: name, email, phone as
+-- entered and as digits only. global_search.rs maps hits to the co-buyer's deal
+CREATE VIRTUAL TABLE IF NOT EXISTS deal_cobuyers_search USING fts5(body, tokenize = 'trigram');
+
+CREATE TRIGGER IF NOT EXISTS deal_cobuyers_search_insert AFTER INSERT ON deal_cobuyers BEGIN
+    INSERT INTO deal_cobuyers_search (rowid, body)
+    VALUES (new.rowid, new.first_name || ' ' || new.last_name || ' ' || COALESCE(new.email, '')
+        || ' ' || COALESCE(new.phone, '') || ' ' || REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(
+        REPLACE(COALESCE(new.phone, ''), '(', ''), ')', ''), '-', ''), ' ', ''), '.', ''), '+', ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS deal_cobuyers_search_update
+AFTER UPDATE OF first_name, last_name, email, phone ON deal_cobuyers BEGIN
+    DELETE FROM deal_cobuyers_search WHERE rowid = old.rowid;
+    INSERT INTO deal_cobuyers_search (rowid, body)
+    VALUES (new.rowid, new.first_name || ' ' || new.last_name || ' ' || COALESCE(new.email, '')
+        || ' ' || COALESCE(new.phone, '') || ' ' || REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(
+        REPLACE(COALESCE(new.phone, ''), '(', ''), ')', ''), '-', ''), ' ', ''), '.', ''), '+', ''));
+END;
+
+CREATE TRIGGER IF NOT EXISTS deal_cobuyers_search_delete AFTER DELETE ON deal_cobuyers BEGIN
+    DELETE FROM deal_cobuyers_search WHERE rowid = old.rowid;
+END;
@@ -0,0 +1,90 @@
+-- Migration 026: Money in integer cents
+-- Every amount on vehicles and deals gets an INTEGER *_cents column, which stats and exports
+-- read (money.rs). The REAL columns stay for the frontend and sync; triggers keep the cents
+-- columns in step with them for writers that only set the REAL column.
+-- A copy of the database is taken before this runs; money_conversion records where it is
+-- and money_subcent_values the amounts that had fractions of a cent.
+
+ALTER TABLE vehicles ADD COLUMN price_cents INTEGER;
+ALTER TABLE vehicles ADD COLUMN cost_cents INTEGER;
+
+ALTER TABLE deals ADD COLUMN total_amount_cents INTEGER;
+ALTER TABLE deals ADD COLUMN sale_amount_cents INTEGER;
+ALTER TABLE deals ADD COLUMN sales_tax_cents INTEGER;
+ALTER TABLE deals ADD COLUMN doc_fee_cents INTEGER;
+ALTER TABLE deals ADD COLUMN trade_in_value_cents INTEGER;
+ALTER TABLE deals ADD COLUMN down_payment_cents INTEGER;
+ALTER TABLE deals ADD COLUMN financed_amount_cents INTEGER;
+
+-- Amounts with a fraction of a cent (e.g. 1234.565), which were rounded half away from zero
+CREATE TABLE IF NOT EXISTS money_subcent_values (
+    table_name TEXT NOT NULL,
+    row_id TEXT NOT NULL,
+    column_name TEXT NOT NULL,
+    original_value REAL NOT NULL,
+    cents INTEGER NOT NULL,
+    PRIMARY KEY (table_name, row_id, column_name)
+);
+
+-- One row: how the conversion went
+CREATE TABLE IF NOT EXISTS money_conversion (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    backup_path TEXT,            -- NULL for in-memory databases
+    converted_values INTEGER NOT NULL,
+    subcent_values INTEGER NOT NULL,
+    converted_at INTEGER NOT NULL
+);
+
+UPDATE vehicles SET
+    price_cents = CAST(ROUND(price * 100) AS INTEGER),
+    cost_cents = CAST(ROUND(cost * 100) AS INTEGER);
+
+UPDATE deals SET
+    total_amount_cents = CAST(ROUND(total_amount * 100) AS INTEGER),
+    sale_amount_cents = CAST(ROUND(sale_amount * 100) AS INTEGER),
+    sales_tax_cents = CAST(ROUND(sales_tax * 100) AS INTEGER),
+    doc_fee_cents = CAST(ROUND(doc_fee * 100) AS INTEGER),
+    trade_in_value_cents = CAST(ROUND(trade_in_value * 100) AS INTEGER),
+    down_payment_cents = CAST(ROUND(down_payment * 100) AS INTEGER),
+    financed_amount_cents = CAST(ROUND(financed_amount * 100) AS INTEGER);
+
+CREATE TRIGGER IF NOT EXISTS vehicles_money_cents_insert AFTER INSERT ON vehicles BEGIN
+    UPDATE vehicles SET
+        price_cents = CAST(ROUND(new.price * 100) AS INTEGER),
+        cost_cents = CAST(ROUND(new.cost * 100) AS INTEGER)
+    WHERE rowid = new.rowid;
+END;
+
+CREATE TRIGGER IF NOT EXISTS vehicles_money_cents_update
+AFTER UPDATE OF price, cost ON vehicles BEGIN
+    UPDATE vehicles SET
+        price_cents = CAST(ROUND(new.price * 100) AS INTEGER),
+        cost_cents = CAST(ROUND(new.cost * 100) AS INTEGER)
+    WHERE rowid = new.rowid;
+END;
+
+CREATE TRIGGER IF NOT EXISTS deals_money_cents_insert AFTER INSERT ON deals BEGIN
+    UPDATE deals SET
+        total_amount_cents = CAST(ROUND(new.total_amount * 100) AS INTEGER),
+        sale_amount_cents = CAST(ROUND(new.sale_amount * 100) AS INTEGER),
+        sales_tax_cents = CAST(ROUND(new.sales_tax * 100) AS INTEGER),
+        doc_fee_cents = CAST(ROUND(new.doc_fee * 100) AS INTEGER),
+        trade_in_value_cents = CAST(ROUND(new.trade_in_value * 100) AS INTEGER),
+        down_payment_cents = CAST(ROUND(new.down_payment * 100) AS INTEGER),
+        financed_amount_cents = CAST(ROUND(new.financed_amount * 100) AS INTEGER)
+    WHERE rowid = new.rowid;
+END;
+
+CREATE TRIGGER IF NOT EXISTS deals_money_cents_update
+AFTER UPDATE OF total_amount, sale_amount, sales_tax, doc_fee, trade_in_value, down_payment,
+    financed_amount ON deals BEGIN
+    UPDATE deals SET
+        total_amount_cents = CAST(ROUND(new.total_amount * 100) AS INTEGER),
+        sale_amount_cents = CAST(ROUND(new.sale_amount * 100) AS INTEGER),
+        sales_tax_cents = CAST(ROUND(new.sales_tax * 100) AS INTEGER),
+        doc_fee_cents = CAST(ROUND(new.doc_fee * 100) AS INTEGER),
+        trade_in_value_cents = CAST(ROUND(new.trade_in_value * 100) AS INTEGER),
+        down_payment_cents = CAST(ROUND(new.down_payment * 100) AS INTEGER),
+        financed_amount_cents = CAST(ROUND(new.financed_amount * 100) AS INTEGER)
+    WHERE rowid = new.rowid;
+END;
@@ -0,0 +1,17 @@
+-- Migration 027: Vehicle holds
+-- A salesperson holds a vehicle for a customer until expires_at. While the hold is active no
+-- one else can create a deal on the vehicle (vehicle_holds.rs); holds past expires_at no
+-- longer block and are deleted by the expiry sweep, which emits hold-expired.
+
+CREATE TABLE IF NOT EXISTS vehicle_holds (
+    id TEXT PRIMARY KEY,
+    vehicle_id TEXT NOT NULL REFERENCES vehicles(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    client_id TEXT REFERENCES clients(id) ON DELETE SET NULL,
+    expires_at INTEGER NOT NULL,
+    note TEXT,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_vehicle_holds_vehicle ON vehicle_holds(vehicle_id, expires_at);
+CREATE INDEX IF NOT EXISTS idx_vehicle_holds_expires ON vehicle_holds(expires_at);
@@ -0,0 +1,20 @@
+-- Migration 028: Data retention and the audit log
+-- retention.rs deletes or scrubs records older than the user's retention policies.
+-- A deal with retention_hold set is never touched, and neither are its documents or its
+-- client. anonymized_at marks clients and deals whose personal details were scrubbed.
+
+ALTER TABLE deals ADD COLUMN retention_hold INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE deals ADD COLUMN anonymized_at INTEGER;
+ALTER TABLE clients ADD COLUMN anonymized_at INTEGER;
+
+-- Actions on the data worth answering for later (purges, holds, policy changes)
+-- Rows are only ever inserted
+CREATE TABLE IF NOT EXISTS audit_log (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    details TEXT NOT NULL, -- JSON object
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_log_user ON audit_log(user_id, created_at);
@@ -0,0 +1,7 @@
+-- Migration 029: Encrypted sensitive fields
+-- Values we won't keep in plaintext (a co-buyer's SSN for lender submission) are stored
+-- AES-256-GCM encrypted by sensitive_fields.rs, base64 nonce + ciphertext. The key lives in
+-- the OS keyring, never in the database. These columns are left out of the search index,
+-- and every scrubbed copy clears them.
+
+ALTER TABLE deal_cobuyers ADD COLUMN ssn_encrypted TEXT;
@@ -0,0 +1,19 @@
+-- Migration 030: Vehicle events
+-- What happened to a vehicle outside its deals: status changes (written by
+-- db_update_vehicle), returns and repossessions, notes. A return or repossession puts the
+-- vehicle back in stock, so its in-stock clock starts again from occurred_at
+-- (vehicle_history.rs).
+
+CREATE TABLE IF NOT EXISTS vehicle_events (
+    id TEXT PRIMARY KEY,
+    vehicle_id TEXT NOT NULL REFERENCES vehicles(id) ON DELETE CASCADE,
+    user_id TEXT NOT NULL,
+    type TEXT NOT NULL, -- status_change, returned, repossessed or note
+    from_status TEXT,   -- status_change, returned and repossessed only
+    to_status TEXT,
+    notes TEXT,
+    occurred_at INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_vehicle_events_vehicle ON vehicle_events(vehicle_id, occurred_at);
@@ -0,0 +1,11 @@
+-- Migration 031: User roles
+-- What each local user may do (permissions.rs). Users without a row are readonly, except
+-- that while the table is empty everyone is an admin: the first set_user_role makes its
+-- caller an admin before anything else.
+
+CREATE TABLE IF NOT EXISTS user_roles (
+    user_id TEXT PRIMARY KEY,
+    role TEXT NOT NULL CHECK (role IN ('admin', 'manager', 'sales', 'readonly')),
+    updated_by TEXT,
+    updated_at INTEGER NOT NULL
+);
@@ -0,0 +1,82 @@
+-- Migration 032: Title application export formats
+-- How export_title_applications lays out the bulk e-titling file for a state DMV
+-- (title_export.rs). definition is JSON: the layout (xml or fixed_width), money and date
+-- formatting, and the fields in order with the deal value each one takes. Adding a state is
+-- adding a row. The two built-in profiles are generic starting points and can't be edited.
+
+CREATE TABLE IF NOT EXISTS title_export_formats (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    definition TEXT NOT NULL,
+    built_in INTEGER NOT NULL DEFAULT 0,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+INSERT OR IGNORE INTO title_export_formats (id, name, definition, built_in, created_at, updated_at)
+VALUES ('generic_xml', 'Generic XML', '{
+  "layout": "xml",
+  "root": "TitleApplications",
+  "record": "TitleApplication",
+  "money": "decimal",
+  "date_format": "%Y-%m-%d",
+  "fields": [
+    {"name": "DealNumber", "source": "deal.id", "required": true},
+    {"name": "SaleDate", "source": "deal.sale_date", "required": true},
+    {"name": "DealerLicenseNumber", "source": "dealer.license_number", "required": true},
+    {"name": "VIN", "source": "vehicle.vin", "required": true},
+    {"name": "Year", "source": "vehicle.year", "required": true},
+    {"name": "Make", "source": "vehicle.make", "required": true},
+    {"name": "Model", "source": "vehicle.model", "required": true},
+    {"name": "BodyStyle", "source": "vehicle.body"},
+    {"name": "Color", "source": "vehicle.color"},
+    {"name": "Odometer", "source": "deal.odometer", "required": true},
+    {"name": "OdometerBrand", "source": "deal.odometer_disclosure"},
+    {"name": "TitleState", "source": "deal.title_state"},
+    {"name": "PriorTitleNumber", "source": "vehicle.title_number"},
+    {"name": "OwnerFirstName", "source": "client.first_name", "required": true},
+    {"name": "OwnerLastName", "source": "client.last_name", "required": true},
+    {"name": "OwnerAddress", "source": "client.address", "required": true},
+    {"name": "OwnerCity", "source": "client.city", "required": true},
+    {"name": "OwnerState", "source": "client.state", "required": true},
+    {"name": "OwnerZip", "source": "client.zip_code", "required": true},
+    {"name": "OwnerLicenseNumber", "source": "client.drivers_license"},
+    {"name": "SalePrice", "source": "deal.sale_amount", "required": true},
+    {"name": "SalesTax", "source": "deal.sales_tax"},
+    {"name": "TradeInAllowance", "source": "deal.trade_in_value"},
+    {"name": "LienholderName", "source": "lender.name"},
+    {"name": "LienholderAddress", "source": "lender.address"},
+    {"name": "LienholderCity", "source": "lender.city"},
+    {"name": "LienholderState", "source": "lender.state"},
+    {"name": "LienholderZip", "source": "lender.zip_code"}
+  ]
+}', 1, 0, 0);
+
+INSERT OR IGNORE INTO title_export_formats (id, name, definition, built_in, created_at, updated_at)
+VALUES ('generic_fixed_width', 'Generic fixed-width text', '{
+  "layout": "fixed_width",
+  "money": "cents",
+  "date_format": "%m%d%Y",
+  "upper": true,
+  "fields": [
+    {"name": "RECORD_TYPE", "value": "TA", "width": 2},
+    {"name": "DEALER_LICENSE", "source": "dealer.license_number", "required": true, "width": 10},
+    {"name": "DEAL_NUMBER", "source": "deal.id", "required": true, "width": 36},
+    {"name": "SALE_DATE", "source": "deal.sale_date", "required": true, "width": 8},
+    {"name": "VIN", "source": "vehicle.vin", "required": true, "width": 17},
+    {"name": "YEAR", "source": "vehicle.year", "required": true, "width": 4},
+    {"name": "MAKE", "source": "vehicle.make", "required": true, "width": 12},
+    {"name": "MODEL", "source": "vehicle.model", "required": true, "width": 20},
+    {"name": "ODOMETER", "source": "deal.odometer", "required": true, "width": 7},
+    {"name": "ODOMETER_BRAND", "source": "deal.odometer_disclosure", "width": 10},
+    {"name": "OWNER_LAST", "source": "client.last_name", "required": true, "width": 25},
+    {"name": "OWNER_FIRST", "source": "client.first_name", "required": true, "width": 20},
+    {"name": "OWNER_ADDRESS", "source": "client.address", "required": true, "width": 35},
+    {"name": "OWNER_CITY", "source": "client.city", "required": true, "width": 20},
+    {"name": "OWNER_STATE", "source": "client.state", "required": true, "width": 2},
+    {"name": "OWNER_ZIP", "source": "client.zip_code", "required": true, "width": 10},
+    {"name": "SALE_PRICE", "source": "deal.sale_amount", "required": true, "width": 11},
+    {"name": "SALES_TAX", "source": "deal.sales_tax", "width": 9},
+    {"name": "LIENHOLDER", "source": "lender.name", "width": 30}
+  ]
+}', 1, 0, 0);
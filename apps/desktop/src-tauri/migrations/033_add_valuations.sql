@@ -0,0 +1,17 @@
+-- Migration 033: Vehicle market-value cache
+-- Responses from the valuation API (valuation.rs), one per VIN, mileage and ZIP, so repeat
+-- appraisals don't spend API credits and the last value can still be shown offline.
+-- Amounts are integer cents; as_of is when the provider priced it, fetched_at when we asked.
+
+CREATE TABLE IF NOT EXISTS valuations (
+    vin TEXT NOT NULL,
+    mileage INTEGER NOT NULL,
+    zip TEXT NOT NULL,
+    low_cents INTEGER NOT NULL,
+    average_cents INTEGER NOT NULL,
+    high_cents INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    as_of INTEGER NOT NULL,
+    fetched_at INTEGER NOT NULL,
+    PRIMARY KEY (vin, mileage, zip)
+);
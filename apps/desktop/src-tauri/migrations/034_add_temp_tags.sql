@@ -0,0 +1,27 @@
+-- Migration 034: Temporary tag inventory
+-- The dealer's temp tag numbers (temp_tags.rs): imported as available, issued to one deal
+-- or voided, never reused or deleted, since the state audits which tag went on which deal.
+-- The deal's own temp_tag_number is its current tag, cleared if that tag is voided.
+
+CREATE TABLE IF NOT EXISTS temp_tags (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    tag_number TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'available' CHECK (status IN ('available', 'issued', 'void')),
+    deal_id TEXT,
+    issued_at INTEGER,
+    expires_at INTEGER,
+    void_reason TEXT,
+    voided_at INTEGER,
+    reminder_task_id TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    UNIQUE (user_id, tag_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_temp_tags_status ON temp_tags(user_id, status, created_at);
+-- A deal holds one issued tag at a time
+CREATE UNIQUE INDEX IF NOT EXISTS idx_temp_tags_issued_deal ON temp_tags(deal_id)
+    WHERE status = 'issued';
+
+ALTER TABLE deals ADD COLUMN temp_tag_number TEXT;
@@ -0,0 +1,10 @@
+-- Migration 035: Sample data flags
+-- Rows created by db_seed_sample_data (sample_data.rs) have is_sample = 1, so
+-- db_remove_sample_data deletes exactly those and nothing the dealer entered.
+
+ALTER TABLE clients ADD COLUMN is_sample INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE vehicles ADD COLUMN is_sample INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE deals ADD COLUMN is_sample INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE documents ADD COLUMN is_sample INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tasks ADD COLUMN is_sample INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE communications ADD COLUMN is_sample INTEGER NOT NULL DEFAULT 0;
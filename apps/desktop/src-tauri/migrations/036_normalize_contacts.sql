@@ -0,0 +1,13 @@
+-- Migration 036: Normalized client phones and emails
+-- phone_e164 is the number's digits ("+12485551212"), email_normalized the trimmed,
+-- lowercase email (contact_normalization.rs); search and duplicate detection match on
+-- these. Numbers that couldn't be normalized stay in phone as entered, with
+-- phone_invalid = 1. The values for existing rows are filled in by the migration
+-- itself (backfill_contacts), which doesn't change phone or email.
+
+ALTER TABLE clients ADD COLUMN phone_e164 TEXT;
+ALTER TABLE clients ADD COLUMN phone_invalid INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE clients ADD COLUMN email_normalized TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_clients_phone_e164 ON clients(user_id, phone_e164);
+CREATE INDEX IF NOT EXISTS idx_clients_email_normalized ON clients(user_id, email_normalized);
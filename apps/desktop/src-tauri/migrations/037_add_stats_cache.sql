@@ -0,0 +1,121 @@
+-- Migration 037: Dashboard statistics cache
+-- stats_cache holds the dashboard's figures per user, in buckets (stats_cache.rs):
+--   'status'  deals with that status: count, amount_cents (summed deal totals)
+--   'month'   sold deals in that dealer-local month (YYYY-MM): count, sale_cents,
+--             cost_cents (their vehicles' summed costs), payments_cents (down payments)
+--   'stock'   available vehicles added on that day (days since 1970-01-01 UTC): count
+-- The triggers below record which buckets a change touches in stats_dirty, in the same
+-- transaction as the change; db_get_dashboard_snapshot recomputes just those buckets
+-- before reading. A dirty key is a status, a sale timestamp (its month depends on the
+-- dealer's timezone, which the triggers can't see) or a day number.
+-- stats_cache_meta records, per user, the timezone the months were bucketed in; when it
+-- no longer matches the dealer's, the user's cache is rebuilt from scratch.
+
+CREATE TABLE IF NOT EXISTS stats_cache (
+    user_id TEXT NOT NULL,
+    section TEXT NOT NULL,
+    bucket TEXT NOT NULL,
+    count INTEGER NOT NULL DEFAULT 0,
+    amount_cents INTEGER NOT NULL DEFAULT 0,
+    sale_cents INTEGER NOT NULL DEFAULT 0,
+    cost_cents INTEGER NOT NULL DEFAULT 0,
+    payments_cents INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (user_id, section, bucket)
+) WITHOUT ROWID;
+
+CREATE TABLE IF NOT EXISTS stats_cache_meta (
+    user_id TEXT PRIMARY KEY,
+    timezone TEXT NOT NULL,
+    computed_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS stats_dirty (
+    user_id TEXT NOT NULL,
+    section TEXT NOT NULL,
+    key TEXT NOT NULL,
+    PRIMARY KEY (user_id, section, key)
+) WITHOUT ROWID;
+
+-- Deals: the old and new status, and the old and new sale month of sold deals
+
+CREATE TRIGGER IF NOT EXISTS deals_stats_insert AFTER INSERT ON deals
+WHEN new.user_id IS NOT NULL BEGIN
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    VALUES (new.user_id, 'status', new.status);
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    SELECT new.user_id, 'month', CAST(COALESCE(new.sale_date, new.created_at) AS TEXT)
+    WHERE new.status = 'sold';
+END;
+
+CREATE TRIGGER IF NOT EXISTS deals_stats_update
+AFTER UPDATE OF user_id, status, vehicle_id, sale_date, created_at, total_amount_cents,
+    sale_amount_cents, down_payment_cents ON deals BEGIN
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    SELECT old.user_id, 'status', old.status WHERE old.user_id IS NOT NULL
+    UNION ALL
+    SELECT new.user_id, 'status', new.status WHERE new.user_id IS NOT NULL;
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    SELECT old.user_id, 'month', CAST(COALESCE(old.sale_date, old.created_at) AS TEXT)
+    WHERE old.user_id IS NOT NULL AND old.status = 'sold'
+    UNION ALL
+    SELECT new.user_id, 'month', CAST(COALESCE(new.sale_date, new.created_at) AS TEXT)
+    WHERE new.user_id IS NOT NULL AND new.status = 'sold';
+END;
+
+CREATE TRIGGER IF NOT EXISTS deals_stats_delete AFTER DELETE ON deals
+WHEN old.user_id IS NOT NULL BEGIN
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    VALUES (old.user_id, 'status', old.status);
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    SELECT old.user_id, 'month', CAST(COALESCE(old.sale_date, old.created_at) AS TEXT)
+    WHERE old.status = 'sold';
+END;
+
+-- Vehicle costs: the sale months of the sold deals on the vehicle
+
+CREATE TRIGGER IF NOT EXISTS vehicle_costs_stats_insert AFTER INSERT ON vehicle_costs BEGIN
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    SELECT d.user_id, 'month', CAST(COALESCE(d.sale_date, d.created_at) AS TEXT)
+    FROM deals d
+    WHERE d.vehicle_id = new.vehicle_id AND d.status = 'sold' AND d.user_id IS NOT NULL;
+END;
+
+CREATE TRIGGER IF NOT EXISTS vehicle_costs_stats_update
+AFTER UPDATE OF vehicle_id, amount_cents ON vehicle_costs BEGIN
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    SELECT d.user_id, 'month', CAST(COALESCE(d.sale_date, d.created_at) AS TEXT)
+    FROM deals d
+    WHERE d.vehicle_id IN (old.vehicle_id, new.vehicle_id) AND d.status = 'sold'
+      AND d.user_id IS NOT NULL;
+END;
+
+CREATE TRIGGER IF NOT EXISTS vehicle_costs_stats_delete AFTER DELETE ON vehicle_costs BEGIN
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    SELECT d.user_id, 'month', CAST(COALESCE(d.sale_date, d.created_at) AS TEXT)
+    FROM deals d
+    WHERE d.vehicle_id = old.vehicle_id AND d.status = 'sold' AND d.user_id IS NOT NULL;
+END;
+
+-- Vehicles: the day an available vehicle was added, before and after
+
+CREATE TRIGGER IF NOT EXISTS vehicles_stats_insert AFTER INSERT ON vehicles
+WHEN new.user_id IS NOT NULL AND new.status = 'available' BEGIN
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    VALUES (new.user_id, 'stock', CAST(new.created_at / 86400000 AS TEXT));
+END;
+
+CREATE TRIGGER IF NOT EXISTS vehicles_stats_update
+AFTER UPDATE OF user_id, status, created_at ON vehicles BEGIN
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    SELECT old.user_id, 'stock', CAST(old.created_at / 86400000 AS TEXT)
+    WHERE old.user_id IS NOT NULL AND old.status = 'available'
+    UNION ALL
+    SELECT new.user_id, 'stock', CAST(new.created_at / 86400000 AS TEXT)
+    WHERE new.user_id IS NOT NULL AND new.status = 'available';
+END;
+
+CREATE TRIGGER IF NOT EXISTS vehicles_stats_delete AFTER DELETE ON vehicles
+WHEN old.user_id IS NOT NULL AND old.status = 'available' BEGIN
+    INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+    VALUES (old.user_id, 'stock', CAST(old.created_at / 86400000 AS TEXT));
+END;
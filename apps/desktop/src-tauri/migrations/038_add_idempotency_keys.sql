@@ -0,0 +1,16 @@
+-- Migration 038: Idempotency keys for create commands
+-- db_create_client and db_create_deal take an optional key from the frontend; a retried
+-- call with the same key (a webview invoke that timed out and was sent again) gets back
+-- the row the first call created instead of a duplicate (idempotency.rs). Keys are per
+-- user and last 24 hours; expired ones are pruned at startup.
+
+CREATE TABLE IF NOT EXISTS idempotency_keys (
+    key TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    entity_type TEXT NOT NULL, -- client or deal
+    entity_id TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_idempotency_keys_key ON idempotency_keys(user_id, key);
+CREATE INDEX IF NOT EXISTS idx_idempotency_keys_created ON idempotency_keys(created_at);
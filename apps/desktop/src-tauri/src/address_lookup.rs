@@ -0,0 +1,1041 @@
+// src-tauri/src/address_lookup.rs
+//
+// ZIP -> city/state lookup and address normalization for client records
+// Title applications get rejected over a misspelled city or a state that doesn't match the
+// ZIP, so addresses can be cleaned up before they're saved:
+//   - street lines are title-cased and street suffixes/directionals spelled out
+//     ("123 n main st apt 4b" -> "123 North Main Street Apt 4B", after USPS Pub. 28)
+//   - state names become codes and unknown codes are flagged
+//   - with the ZIP dataset, an empty city/state is filled from the ZIP and a city that's
+//     spelled differently from the ZIP's is returned as a suggestion, never rewritten
+//
+// The ZIP dataset is a CSV (zip,city,state; a ZIP can have several rows, the first being
+// its preferred city) downloaded once into {app_data}/zip_codes/ and used offline after.
+// The download resumes from a .part file named after the expected checksum and is only
+// installed once its SHA-256 matches.
+
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use reqwest::{header, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+use crate::database::{db_get_setting, Client};
+use crate::documents_migration::file_sha256;
+use crate::settings_cache::set_setting;
+use crate::storage::get_app_data_dir;
+use crate::telemetry::{track, track_async};
+
+pub const ZIP_DATASET_URL_SETTING: &str = "zip_dataset_url";
+pub const ZIP_DATASET_SHA256_SETTING: &str = "zip_dataset_sha256";
+
+const DATASET_DIR: &str = "zip_codes";
+const DATASET_FILE: &str = "zip_codes.csv";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+
+static ZIP_INDEX: Lazy<RwLock<Option<Arc<ZipIndex>>>> = Lazy::new(|| RwLock::new(None));
+
+/// USPS state and territory codes (including military "states")
+const STATES: &[(&str, &str)] = &[
+    ("AL", "Alabama"),
+    ("AK", "Alaska"),
+    ("AZ", "Arizona"),
+    ("AR", "Arkansas"),
+    ("CA", "California"),
+    ("CO", "Colorado"),
+    ("CT", "Connecticut"),
+    ("DE", "Delaware"),
+    ("DC", "District of Columbia"),
+    ("FL", "Florida"),
+    ("GA", "Georgia"),
+    ("HI", "Hawaii"),
+    ("ID", "Idaho"),
+    ("IL", "Illinois"),
+    ("IN", "Indiana"),
+    ("IA", "Iowa"),
+    ("KS", "Kansas"),
+    ("KY", "Kentucky"),
+    ("LA", "Louisiana"),
+    ("ME", "Maine"),
+    ("MD", "Maryland"),
+    ("MA", "Massachusetts"),
+    ("MI", "Michigan"),
+    ("MN", "Minnesota"),
+    ("MS", "Mississippi"),
+    ("MO", "Missouri"),
+    ("MT", "Montana"),
+    ("NE", "Nebraska"),
+    ("NV", "Nevada"),
+    ("NH", "New Hampshire"),
+    ("NJ", "New Jersey"),
+    ("NM", "New Mexico"),
+    ("NY", "New York"),
+    ("NC", "North Carolina"),
+    ("ND", "North Dakota"),
+    ("OH", "Ohio"),
+    ("OK", "Oklahoma"),
+    ("OR", "Oregon"),
+    ("PA", "Pennsylvania"),
+    ("RI", "Rhode Island"),
+    ("SC", "South Carolina"),
+    ("SD", "South Dakota"),
+    ("TN", "Tennessee"),
+    ("TX", "Texas"),
+    ("UT", "Utah"),
+    ("VT", "Vermont"),
+    ("VA", "Virginia"),
+    ("WA", "Washington"),
+    ("WV", "West Virginia"),
+    ("WI", "Wisconsin"),
+    ("WY", "Wyoming"),
+    ("AS", "American Samoa"),
+    ("GU", "Guam"),
+    ("MP", "Northern Mariana Islands"),
+    ("PR", "Puerto Rico"),
+    ("VI", "Virgin Islands"),
+    ("AA", "Armed Forces Americas"),
+    ("AE", "Armed Forces Europe"),
+    ("AP", "Armed Forces Pacific"),
+];
+
+/// Street suffix spellings (USPS Pub. 28, appendix C1) -> the spelled-out suffix
+const STREET_SUFFIXES: &[(&str, &[&str])] = &[
+    ("Alley", &["ALY", "ALLEY", "ALLY", "ALLEE"]),
+    (
+        "Avenue",
+        &["AVE", "AV", "AVEN", "AVENU", "AVN", "AVNUE", "AVENUE"],
+    ),
+    ("Boulevard", &["BLVD", "BOUL", "BOULV", "BOULEVARD"]),
+    ("Center", &["CTR", "CEN", "CENT", "CENTR", "CNTR", "CENTER"]),
+    (
+        "Circle",
+        &["CIR", "CIRC", "CIRCL", "CRCL", "CRCLE", "CIRCLE"],
+    ),
+    ("Court", &["CT", "CRT", "COURT"]),
+    ("Cove", &["CV", "COVE"]),
+    ("Crossing", &["XING", "CRSSNG", "CROSSING"]),
+    ("Drive", &["DR", "DRIV", "DRV", "DRIVE"]),
+    (
+        "Expressway",
+        &["EXPY", "EXP", "EXPR", "EXPRESS", "EXPW", "EXPRESSWAY"],
+    ),
+    ("Freeway", &["FWY", "FREEWY", "FRWAY", "FRWY", "FREEWAY"]),
+    ("Heights", &["HTS", "HT", "HEIGHTS"]),
+    (
+        "Highway",
+        &["HWY", "HIGHWY", "HIWAY", "HIWY", "HWAY", "HIGHWAY"],
+    ),
+    ("Lane", &["LN", "LANE"]),
+    ("Loop", &["LOOP", "LOOPS"]),
+    ("Parkway", &["PKWY", "PKY", "PARKWY", "PKWAY", "PARKWAY"]),
+    ("Place", &["PL", "PLACE"]),
+    ("Plaza", &["PLZ", "PLZA", "PLAZA"]),
+    ("Point", &["PT", "POINT"]),
+    ("Ridge", &["RDG", "RDGE", "RIDGE"]),
+    ("Road", &["RD", "ROAD"]),
+    ("Square", &["SQ", "SQR", "SQRE", "SQU", "SQUARE"]),
+    ("Street", &["ST", "STR", "STRT", "STREET"]),
+    ("Terrace", &["TER", "TERR", "TERRACE"]),
+    ("Trail", &["TRL", "TRLS", "TRAIL", "TRAILS"]),
+    ("Turnpike", &["TPKE", "TRNPK", "TURNPK", "TURNPIKE"]),
+    ("Way", &["WAY", "WY"]),
+];
+
+const DIRECTIONALS: &[(&str, &str)] = &[
+    ("N", "North"),
+    ("S", "South"),
+    ("E", "East"),
+    ("W", "West"),
+    ("NE", "Northeast"),
+    ("NW", "Northwest"),
+    ("SE", "Southeast"),
+    ("SW", "Southwest"),
+];
+
+/// Words that start the unit part of a street line ("Apt 4B", "Suite 200", "#12")
+const UNIT_DESIGNATORS: &[&str] = &[
+    "APT",
+    "APARTMENT",
+    "BLDG",
+    "BUILDING",
+    "DEPT",
+    "FL",
+    "FLOOR",
+    "LOT",
+    "RM",
+    "ROOM",
+    "SPC",
+    "SPACE",
+    "STE",
+    "SUITE",
+    "TRLR",
+    "UNIT",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ZipPlace {
+    pub city: String,
+    pub state: String,
+}
+
+/// ZIP -> places, preferred city first
+#[derive(Debug, Default)]
+pub struct ZipIndex {
+    places: HashMap<String, Vec<ZipPlace>>,
+}
+
+impl ZipIndex {
+    /// Parse the dataset CSV; the header names the zip, city and state columns
+    pub fn parse(csv: &str) -> Result<Self, String> {
+        let mut lines = csv.lines().filter(|line| !line.trim().is_empty());
+        let header: Vec<String> = lines
+            .next()
+            .ok_or_else(|| "ZIP dataset is empty".to_string())?
+            .split(',')
+            .map(|column| column.trim().trim_matches('"').to_ascii_lowercase())
+            .collect();
+        let column = |name: &str| {
+            header
+                .iter()
+                .position(|column| column == name)
+                .ok_or_else(|| format!("ZIP dataset has no '{}' column", name))
+        };
+        let (zip_col, city_col, state_col) = (column("zip")?, column("city")?, column("state")?);
+
+        let mut index = ZipIndex::default();
+        for line in lines {
+            let cells: Vec<&str> = line
+                .split(',')
+                .map(|cell| cell.trim().trim_matches('"'))
+                .collect();
+            let cell = |i: usize| cells.get(i).copied().unwrap_or_default();
+            let zip = cell(zip_col);
+            if zip.len() != 5
+                || !zip.chars().all(|c| c.is_ascii_digit())
+                || cell(city_col).is_empty()
+            {
+                continue;
+            }
+            index
+                .places
+                .entry(zip.to_string())
+                .or_default()
+                .push(ZipPlace {
+                    city: title_case(cell(city_col)),
+                    state: cell(state_col).to_ascii_uppercase(),
+                });
+        }
+        if index.places.is_empty() {
+            return Err("ZIP dataset has no usable rows".to_string());
+        }
+        Ok(index)
+    }
+
+    /// Places for the first five digits of zip
+    pub fn lookup(&self, zip: &str) -> &[ZipPlace] {
+        zip.get(..5)
+            .and_then(|zip5| self.places.get(zip5))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.places.len()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressFields {
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub zip_code: Option<String>,
+}
+
+/// A change normalization wasn't sure enough about to make
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AddressSuggestion {
+    pub field: String,
+    pub value: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NormalizedAddress {
+    #[serde(flatten)]
+    pub fields: AddressFields,
+    /// Fields whose value was changed
+    pub changed: Vec<String>,
+    pub suggestions: Vec<AddressSuggestion>,
+    pub warnings: Vec<String>,
+    /// False when the ZIP dataset isn't downloaded (or the ZIP is malformed)
+    pub zip_checked: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ZipLookup {
+    pub zip: String,
+    pub city: String,
+    pub state: String,
+    /// Other city names the ZIP accepts
+    pub alternate_cities: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ZipDatasetStatus {
+    pub downloaded: bool,
+    pub zip_count: usize,
+    pub bytes: u64,
+    /// Bytes of an interrupted download that the next attempt resumes from
+    pub partial_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ZipDatasetDownload {
+    pub path: String,
+    pub bytes: u64,
+    pub zip_count: usize,
+    /// Where this attempt picked up an earlier one (0 for a fresh download)
+    pub resumed_from: u64,
+}
+
+fn title_word(word: &str) -> String {
+    let has_upper = word.chars().any(char::is_uppercase);
+    let has_lower = word.chars().any(char::is_lowercase);
+    // Typed with care already (McArthur, DeKalb)
+    if has_upper && has_lower {
+        return word.to_string();
+    }
+    if word.starts_with(|c: char| c.is_ascii_digit()) {
+        let lower = word.to_lowercase();
+        let ordinal = ["st", "nd", "rd", "th"].iter().any(|suffix| {
+            lower
+                .strip_suffix(suffix)
+                .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        });
+        return if ordinal { lower } else { word.to_uppercase() };
+    }
+
+    let mut out = String::with_capacity(word.len());
+    let mut start = true;
+    for c in word.chars() {
+        if start {
+            out.extend(c.to_uppercase());
+        } else {
+            out.extend(c.to_lowercase());
+        }
+        start = c == '-' || (c == '\'' && out.chars().count() == 2);
+    }
+    out
+}
+
+fn title_case(text: &str) -> String {
+    text.split_whitespace()
+        .map(title_word)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn street_suffix(word: &str) -> Option<&'static str> {
+    STREET_SUFFIXES
+        .iter()
+        .find(|(_, spellings)| spellings.contains(&word))
+        .map(|(full, _)| *full)
+}
+
+fn directional(word: &str) -> Option<&'static str> {
+    DIRECTIONALS
+        .iter()
+        .find(|(short, full)| *short == word || full.eq_ignore_ascii_case(word))
+        .map(|(_, full)| *full)
+}
+
+/// Title-case a street line and spell out its suffix and directionals
+/// Only the last word before the unit counts as a suffix, so "12 St Charles Ave" keeps "St".
+fn normalize_street(line: &str) -> String {
+    let compact = line.trim().to_ascii_lowercase();
+    for prefix in [
+        "p.o. box",
+        "p. o. box",
+        "p o box",
+        "po box",
+        "post office box",
+    ] {
+        if let Some(rest) = compact.strip_prefix(prefix) {
+            let number = line.trim()[line.trim().len() - rest.len()..].trim();
+            return format!("PO Box {}", title_case(number)).trim().to_string();
+        }
+    }
+
+    let words: Vec<&str> = line
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|word| word.trim_end_matches('.'))
+        .filter(|word| !word.is_empty())
+        .collect();
+    let upper: Vec<String> = words.iter().map(|w| w.to_ascii_uppercase()).collect();
+    let mut out: Vec<String> = words.iter().map(|w| title_word(w)).collect();
+
+    let unit_start = upper
+        .iter()
+        .position(|w| UNIT_DESIGNATORS.contains(&w.as_str()) || w.starts_with('#'))
+        .unwrap_or(words.len());
+    let name_start = usize::from(
+        words
+            .first()
+            .is_some_and(|w| w.starts_with(|c: char| c.is_ascii_digit())),
+    );
+
+    // "... Main St NW": a directional after the suffix
+    let mut end = unit_start;
+    if end >= name_start + 3 && street_suffix(&upper[end - 2]).is_some() {
+        if let Some(full) = directional(&upper[end - 1]) {
+            out[end - 1] = full.to_string();
+            end -= 1;
+        }
+    }
+    if end >= name_start + 2 {
+        if let Some(full) = street_suffix(&upper[end - 1]) {
+            out[end - 1] = full.to_string();
+        }
+    }
+    // "100 N Main St", but not "10 N St" where N is the street's name
+    let after = end.saturating_sub(name_start + 1);
+    if name_start == 1 && (after >= 2 || (after == 1 && street_suffix(&upper[end - 1]).is_none())) {
+        if let Some(full) = directional(&upper[1]) {
+            out[1] = full.to_string();
+        }
+    }
+    out.join(" ")
+}
+
+/// Comparison key for city names: "St. Louis" and "SAINT LOUIS" are the same city
+fn city_key(city: &str) -> String {
+    let upper = city.replace('.', " ").to_ascii_uppercase();
+    let words: Vec<&str> = upper.split_whitespace().collect();
+    words
+        .iter()
+        .enumerate()
+        .map(|(i, word)| match (i, *word) {
+            (0, "ST") => "SAINT",
+            (0, "FT") => "FORT",
+            (0, "MT") => "MOUNT",
+            (_, word) => word,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Two-letter code for a code or a full state name
+fn state_code(raw: &str) -> Option<&'static str> {
+    let raw = raw.split_whitespace().collect::<Vec<_>>().join(" ");
+    STATES
+        .iter()
+        .find(|(code, name)| code.eq_ignore_ascii_case(&raw) || name.eq_ignore_ascii_case(&raw))
+        .map(|(code, _)| *code)
+}
+
+/// "12345" or "12345-6789"; None if it isn't a US ZIP
+fn format_zip(raw: &str) -> Option<String> {
+    let digits: String = raw.chars().filter(char::is_ascii_digit).collect();
+    if !raw
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == '-' || c == ' ')
+    {
+        return None;
+    }
+    match digits.len() {
+        5 => Some(digits),
+        9 => Some(format!("{}-{}", &digits[..5], &digits[5..])),
+        _ => None,
+    }
+}
+
+fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// Normalize an address; index is the ZIP dataset, if it has been downloaded
+pub fn normalize(input: &AddressFields, index: Option<&ZipIndex>) -> NormalizedAddress {
+    let mut suggestions = Vec::new();
+    let mut warnings = Vec::new();
+
+    let address = non_empty(&input.address).map(normalize_street);
+    let mut city = non_empty(&input.city).map(title_case);
+    let mut state = non_empty(&input.state).map(|raw| match state_code(raw) {
+        Some(code) => code.to_string(),
+        None => {
+            warnings.push(format!("'{}' isn't a US state code", raw));
+            raw.to_string()
+        }
+    });
+    let zip_code = non_empty(&input.zip_code).map(|raw| {
+        format_zip(raw).unwrap_or_else(|| {
+            warnings.push(format!("'{}' isn't a valid ZIP code", raw));
+            raw.to_string()
+        })
+    });
+
+    let mut zip_checked = false;
+    if let (Some(index), Some(zip)) = (
+        index,
+        zip_code.as_deref().filter(|z| format_zip(z).is_some()),
+    ) {
+        zip_checked = true;
+        let places = index.lookup(zip);
+        match places.first() {
+            None => warnings.push(format!("ZIP code {} isn't in the ZIP code data", zip)),
+            Some(preferred) => {
+                let reason = format!(
+                    "ZIP {} is {}, {}",
+                    &zip[..5],
+                    preferred.city,
+                    preferred.state
+                );
+                match city.as_deref() {
+                    None => city = Some(preferred.city.clone()),
+                    Some(current) => {
+                        match places
+                            .iter()
+                            .find(|p| city_key(&p.city) == city_key(current))
+                        {
+                            Some(place) => city = Some(place.city.clone()),
+                            None => suggestions.push(AddressSuggestion {
+                                field: "city".to_string(),
+                                value: preferred.city.clone(),
+                                reason: reason.clone(),
+                            }),
+                        }
+                    }
+                }
+                match state.as_deref() {
+                    None => state = Some(preferred.state.clone()),
+                    Some(current) if !places.iter().any(|p| p.state == current) => suggestions
+                        .push(AddressSuggestion {
+                            field: "state".to_string(),
+                            value: preferred.state.clone(),
+                            reason,
+                        }),
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
+    let fields = AddressFields {
+        address,
+        city,
+        state,
+        zip_code,
+    };
+    let changed = [
+        ("address", &input.address, &fields.address),
+        ("city", &input.city, &fields.city),
+        ("state", &input.state, &fields.state),
+        ("zip_code", &input.zip_code, &fields.zip_code),
+    ]
+    .into_iter()
+    .filter(|(_, before, after)| before != after)
+    .map(|(field, _, _)| field.to_string())
+    .collect();
+
+    NormalizedAddress {
+        fields,
+        changed,
+        suggestions,
+        warnings,
+        zip_checked,
+    }
+}
+
+/// Apply the confident part of normalize() to a client before it's saved
+/// Suggestions are only logged; the client form shows them via normalize_address.
+pub(crate) fn normalize_client_address(client: &mut Client) {
+    let result = normalize(
+        &AddressFields {
+            address: client.address.clone(),
+            city: client.city.clone(),
+            state: client.state.clone(),
+            zip_code: client.zip_code.clone(),
+        },
+        zip_index().as_deref(),
+    );
+    if !result.suggestions.is_empty() || !result.warnings.is_empty() {
+        info!(
+            "Address of client {}: {} suggestions, {} warnings",
+            client.id,
+            result.suggestions.len(),
+            result.warnings.len()
+        );
+    }
+    client.address = result.fields.address;
+    client.city = result.fields.city;
+    client.state = result.fields.state;
+    client.zip_code = result.fields.zip_code;
+}
+
+fn dataset_dir() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join(DATASET_DIR))
+}
+
+/// The downloaded dataset, loaded on first use
+fn zip_index() -> Option<Arc<ZipIndex>> {
+    if let Some(index) = ZIP_INDEX
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+    {
+        return Some(index);
+    }
+    let path = dataset_dir().ok()?.join(DATASET_FILE);
+    if !path.is_file() {
+        return None;
+    }
+    match fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|csv| ZipIndex::parse(&csv))
+    {
+        Ok(index) => {
+            let index = Arc::new(index);
+            *ZIP_INDEX.write().unwrap_or_else(PoisonError::into_inner) = Some(index.clone());
+            Some(index)
+        }
+        Err(e) => {
+            warn!("⚠️  Could not load ZIP dataset {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+fn part_path(dir: &Path, sha256: &str) -> PathBuf {
+    dir.join(format!("{}.{}.part", DATASET_FILE, &sha256[..16]))
+}
+
+/// Start offset of a 206 response ("bytes 1000-1999/2000")
+fn content_range_start(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(header::CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes ")?
+        .split('-')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Download url into dir, resuming an earlier partial download of the same checksum
+async fn download_dataset(
+    client: &reqwest::Client,
+    url: &str,
+    sha256: &str,
+    dir: &Path,
+) -> Result<(ZipDatasetDownload, ZipIndex), String> {
+    let sha256 = sha256.trim().to_ascii_lowercase();
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err("ZIP dataset checksum must be a SHA-256 hex digest".to_string());
+    }
+    fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let part = part_path(dir, &sha256);
+    let offset = fs::metadata(&part).map(|m| m.len()).unwrap_or(0);
+
+    let mut request = client.get(url);
+    if offset > 0 {
+        request = request.header(header::RANGE, format!("bytes={}-", offset));
+    }
+    let mut response = request
+        .send()
+        .await
+        .map_err(|e| format!("ZIP dataset download failed: {}", e))?;
+
+    let status = response.status();
+    // 416: the part file already holds the whole dataset
+    let (resumed_from, complete) = match status {
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => (offset, true),
+        StatusCode::PARTIAL_CONTENT if content_range_start(&response) == Some(offset) => {
+            (offset, false)
+        }
+        StatusCode::PARTIAL_CONTENT => {
+            let _ = fs::remove_file(&part);
+            return Err("ZIP dataset server resumed at the wrong offset; download again".into());
+        }
+        // The server ignored the range: start over
+        status if status.is_success() => (0, false),
+        status => return Err(format!("ZIP dataset download failed: HTTP {}", status)),
+    };
+
+    if !complete {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resumed_from > 0)
+            .truncate(resumed_from == 0)
+            .open(&part)
+            .map_err(|e| format!("Failed to open {}: {}", part.display(), e))?;
+        let mut written = resumed_from;
+        while let Some(chunk) = response.chunk().await.map_err(|e| {
+            format!(
+                "ZIP dataset download interrupted after {} bytes ({}); download again to resume",
+                written, e
+            )
+        })? {
+            file.write_all(&chunk)
+                .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+            written += chunk.len() as u64;
+        }
+        file.sync_all()
+            .map_err(|e| format!("Failed to write {}: {}", part.display(), e))?;
+    }
+
+    let actual = file_sha256(&part).map_err(|e| e.to_string())?;
+    if actual != sha256 {
+        let _ = fs::remove_file(&part);
+        return Err(format!(
+            "ZIP dataset checksum mismatch (expected {}, got {})",
+            sha256, actual
+        ));
+    }
+    let index = match fs::read_to_string(&part)
+        .map_err(|e| e.to_string())
+        .and_then(|csv| ZipIndex::parse(&csv))
+    {
+        Ok(index) => index,
+        Err(e) => {
+            let _ = fs::remove_file(&part);
+            return Err(e);
+        }
+    };
+
+    let path = dir.join(DATASET_FILE);
+    fs::rename(&part, &path).map_err(|e| format!("Failed to install ZIP dataset: {}", e))?;
+    // Leftovers from downloads of other versions
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().ends_with(".part") {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    let download = ZipDatasetDownload {
+        path: path.to_string_lossy().into_owned(),
+        bytes: fs::metadata(&path).map(|m| m.len()).unwrap_or(0),
+        zip_count: index.len(),
+        resumed_from,
+    };
+    Ok((download, index))
+}
+
+/// City and state for a ZIP code (the first five digits count)
+#[tauri::command]
+pub fn lookup_zip(zip: String) -> Result<Option<ZipLookup>, String> {
+    track("lookup_zip", || {
+        let zip = format_zip(&zip).ok_or_else(|| format!("'{}' isn't a valid ZIP code", zip))?;
+        let index =
+            zip_index().ok_or_else(|| "ZIP code data hasn't been downloaded yet".to_string())?;
+        let places = index.lookup(&zip);
+        Ok(places.first().map(|preferred| ZipLookup {
+            zip: zip[..5].to_string(),
+            city: preferred.city.clone(),
+            state: preferred.state.clone(),
+            alternate_cities: places[1..].iter().map(|p| p.city.clone()).collect(),
+        }))
+    })
+}
+
+/// Normalized address fields plus suggestions for anything that needs a person to decide
+/// Works without the ZIP dataset too; zip_checked says whether city/state were verified.
+#[tauri::command]
+pub fn normalize_address(address: AddressFields) -> Result<NormalizedAddress, String> {
+    track("normalize_address", || {
+        Ok(normalize(&address, zip_index().as_deref()))
+    })
+}
+
+#[tauri::command]
+pub fn get_zip_dataset_status() -> Result<ZipDatasetStatus, String> {
+    track("get_zip_dataset_status", || {
+        let dir = dataset_dir()?;
+        let bytes = fs::metadata(dir.join(DATASET_FILE))
+            .map(|m| m.len())
+            .unwrap_or(0);
+        let partial_bytes = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|e| e.file_name().to_string_lossy().ends_with(".part"))
+                    .filter_map(|e| e.metadata().ok())
+                    .map(|m| m.len())
+                    .sum()
+            })
+            .unwrap_or(0);
+        let index = zip_index();
+        Ok(ZipDatasetStatus {
+            downloaded: index.is_some(),
+            zip_count: index.map(|index| index.len()).unwrap_or(0),
+            bytes,
+            partial_bytes,
+        })
+    })
+}
+
+/// Download (or resume downloading) the ZIP dataset
+/// url and sha256 default to the zip_dataset_url/zip_dataset_sha256 settings and are saved
+/// there once the download succeeds.
+#[tauri::command]
+pub async fn download_zip_dataset(
+    url: Option<String>,
+    sha256: Option<String>,
+) -> Result<ZipDatasetDownload, String> {
+    track_async("download_zip_dataset", async move {
+        let setting = |key: &str| db_get_setting(key.to_string()).ok().flatten();
+        let url = url
+            .filter(|url| !url.trim().is_empty())
+            .or_else(|| setting(ZIP_DATASET_URL_SETTING))
+            .ok_or_else(|| "No ZIP dataset URL configured".to_string())?;
+        let sha256 = sha256
+            .filter(|sha| !sha.trim().is_empty())
+            .or_else(|| setting(ZIP_DATASET_SHA256_SETTING))
+            .ok_or_else(|| "No ZIP dataset checksum configured".to_string())?;
+
+        let client = reqwest::Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+        let (download, index) = download_dataset(&client, &url, &sha256, &dataset_dir()?).await?;
+
+        *ZIP_INDEX.write().unwrap_or_else(PoisonError::into_inner) = Some(Arc::new(index));
+        set_setting(ZIP_DATASET_URL_SETTING, &url)?;
+        set_setting(ZIP_DATASET_SHA256_SETTING, &sha256)?;
+        info!(
+            "✅ ZIP dataset installed: {} ZIP codes ({} bytes, resumed from {})",
+            download.zip_count, download.bytes, download.resumed_from
+        );
+        Ok(download)
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use sha2::{Digest, Sha256};
+    use std::sync::mpsc;
+    use tiny_http::{Header, Response, Server};
+
+    const FIXTURE: &str = include_str!("../testdata/zip_codes/fixture.csv");
+
+    fn fixture() -> ZipIndex {
+        ZipIndex::parse(FIXTURE).unwrap()
+    }
+
+    fn fields(address: &str, city: &str, state: &str, zip: &str) -> AddressFields {
+        let value = |v: &str| (!v.is_empty()).then(|| v.to_string());
+        AddressFields {
+            address: value(address),
+            city: value(city),
+            state: value(state),
+            zip_code: value(zip),
+        }
+    }
+
+    /// Serves body with Range support (unless ignore_range) for `requests` requests and
+    /// reports each request's Range header
+    fn serve(
+        body: &'static [u8],
+        requests: usize,
+        ignore_range: bool,
+    ) -> (String, mpsc::Receiver<Option<String>>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://127.0.0.1:{}/zip_codes.csv",
+            server.server_addr().to_ip().unwrap().port()
+        );
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for _ in 0..requests {
+                let request = server.recv().unwrap();
+                let range = request
+                    .headers()
+                    .iter()
+                    .find(|h| h.field.equiv("Range"))
+                    .map(|h| h.value.to_string());
+                tx.send(range.clone()).unwrap();
+                let start = range
+                    .as_deref()
+                    .filter(|_| !ignore_range)
+                    .and_then(|r| r.strip_prefix("bytes="))
+                    .and_then(|r| r.trim_end_matches('-').parse::<usize>().ok());
+                let response = match start {
+                    Some(start) if start >= body.len() => {
+                        Response::from_data(Vec::new()).with_status_code(416)
+                    }
+                    Some(start) => Response::from_data(body[start..].to_vec())
+                        .with_status_code(206)
+                        .with_header(
+                            Header::from_bytes(
+                                "Content-Range",
+                                format!("bytes {}-{}/{}", start, body.len() - 1, body.len()),
+                            )
+                            .unwrap(),
+                        ),
+                    None => Response::from_data(body.to_vec()),
+                };
+                request.respond(response).unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    fn fetch(
+        url: &str,
+        sha256: &str,
+        dir: &Path,
+    ) -> Result<(ZipDatasetDownload, ZipIndex), String> {
+        let client = reqwest::Client::new();
+        tauri::async_runtime::block_on(download_dataset(&client, url, sha256, dir))
+    }
+
+    #[test]
+    fn test_zip_index_lookup() {
+        let index = fixture();
+        assert_eq!(index.len(), 7);
+        assert_eq!(
+            index.lookup("02134"),
+            [
+                ZipPlace {
+                    city: "Boston".into(),
+                    state: "MA".into()
+                },
+                ZipPlace {
+                    city: "Allston".into(),
+                    state: "MA".into()
+                },
+            ]
+        );
+        assert_eq!(index.lookup("78701-1234")[0].city, "Austin");
+        assert!(index.lookup("99999").is_empty());
+        assert!(index.lookup("123").is_empty());
+
+        assert!(ZipIndex::parse("postal,place\n78701,AUSTIN").is_err());
+        assert!(ZipIndex::parse("zip,city,state\n787,AUSTIN,TX").is_err());
+    }
+
+    #[test]
+    fn test_street_lines() {
+        for (raw, expected) in [
+            ("123 main st", "123 Main Street"),
+            ("123 MAIN ST.", "123 Main Street"),
+            ("456 n. oak ave apt 4b", "456 North Oak Avenue Apt 4B"),
+            ("77 Elm Blvd, Ste 200", "77 Elm Boulevard Ste 200"),
+            ("12 st charles ave", "12 St Charles Avenue"),
+            (
+                "1600 pennsylvania ave nw",
+                "1600 Pennsylvania Avenue Northwest",
+            ),
+            ("10 n st", "10 N Street"),
+            ("500 w 42nd st #12", "500 West 42nd Street #12"),
+            ("9 McArthur cir", "9 McArthur Circle"),
+            ("3 o'brien way", "3 O'Brien Way"),
+            ("p.o. box 123", "PO Box 123"),
+            ("88 county rd 12", "88 County Rd 12"),
+        ] {
+            assert_eq!(normalize_street(raw), expected, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn test_normalize_fills_from_zip_and_suggests_on_mismatch() {
+        let index = fixture();
+
+        // Empty city/state come from the ZIP; a state name becomes its code
+        let result = normalize(
+            &fields("123 main st", "", "texas", "787011234"),
+            Some(&index),
+        );
+        assert_eq!(
+            result.fields,
+            fields("123 Main Street", "Austin", "TX", "78701-1234")
+        );
+        assert_eq!(result.changed, ["address", "city", "state", "zip_code"]);
+        assert!(result.suggestions.is_empty() && result.warnings.is_empty());
+        assert!(result.zip_checked);
+
+        // Accepted alternates and St./Saint spellings match the ZIP
+        let result = normalize(&fields("", "allston", "MA", "02134"), Some(&index));
+        assert_eq!(result.fields.city.as_deref(), Some("Allston"));
+        let result = normalize(&fields("", "St. Louis", "mo", "63101"), Some(&index));
+        assert_eq!(result.fields.city.as_deref(), Some("Saint Louis"));
+        assert_eq!(result.fields.state.as_deref(), Some("MO"));
+
+        // A typo is suggested, not rewritten
+        let result = normalize(&fields("", "Austn", "CO", "78701"), Some(&index));
+        assert_eq!(result.fields.city.as_deref(), Some("Austn"));
+        assert_eq!(result.fields.state.as_deref(), Some("CO"));
+        let suggested: Vec<(&str, &str)> = result
+            .suggestions
+            .iter()
+            .map(|s| (s.field.as_str(), s.value.as_str()))
+            .collect();
+        assert_eq!(suggested, [("city", "Austin"), ("state", "TX")]);
+        assert_eq!(result.suggestions[0].reason, "ZIP 78701 is Austin, TX");
+
+        // Bad codes are flagged and kept as typed
+        let result = normalize(&fields("", "Austin", "XX", "7870"), Some(&index));
+        assert_eq!(result.fields.state.as_deref(), Some("XX"));
+        assert_eq!(result.warnings.len(), 2);
+        assert!(!result.zip_checked);
+        let result = normalize(&fields("", "Nowhere", "TX", "99999"), Some(&index));
+        assert_eq!(
+            result.warnings,
+            ["ZIP code 99999 isn't in the ZIP code data"]
+        );
+
+        // Offline without the dataset: formatting only
+        let result = normalize(&fields("", "austin", "tx", "78701"), None);
+        assert_eq!(result.fields, fields("", "Austin", "TX", "78701"));
+        assert!(!result.zip_checked);
+    }
+
+    #[test]
+    fn test_download_resumes_and_verifies_checksum() {
+        let sha256 = format!("{:x}", Sha256::digest(FIXTURE.as_bytes()));
+        let dir = temp_dir("zip", "download");
+
+        // An earlier attempt stopped partway through
+        fs::write(part_path(&dir, &sha256), &FIXTURE.as_bytes()[..40]).unwrap();
+        let (url, ranges) = serve(FIXTURE.as_bytes(), 1, false);
+        let (download, index) = fetch(&url, &sha256, &dir).unwrap();
+        assert_eq!(ranges.recv().unwrap().as_deref(), Some("bytes=40-"));
+        assert_eq!(download.resumed_from, 40);
+        assert_eq!(download.bytes, FIXTURE.len() as u64);
+        assert_eq!(index.len(), 7);
+        assert_eq!(fs::read_to_string(dir.join(DATASET_FILE)).unwrap(), FIXTURE);
+        assert!(!part_path(&dir, &sha256).exists());
+
+        // A server that ignores Range sends the whole file, which replaces the partial one
+        fs::write(part_path(&dir, &sha256), b"garbage that isn't the start").unwrap();
+        let (url, ranges) = serve(FIXTURE.as_bytes(), 1, true);
+        let (download, _) = fetch(&url, &sha256, &dir).unwrap();
+        assert!(ranges.recv().unwrap().is_some());
+        assert_eq!(download.resumed_from, 0);
+
+        // Wrong checksum: nothing installed, nothing left to resume from
+        fs::remove_file(dir.join(DATASET_FILE)).unwrap();
+        let wrong = "0".repeat(64);
+        let (url, _ranges) = serve(FIXTURE.as_bytes(), 1, false);
+        let err = fetch(&url, &wrong, &dir).unwrap_err();
+        assert!(err.contains("checksum mismatch"), "{}", err);
+        assert!(!dir.join(DATASET_FILE).exists());
+        assert!(!part_path(&dir, &wrong).exists());
+        assert!(fetch("http://127.0.0.1:1/x", "abc", &dir).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,299 @@
+// src-tauri/src/app_state.rs
+//
+// State managed by Tauri (app.manage) and shared by every command
+// SECURITY: The current user is set on the Rust side after checking their session
+// token, so a frontend bug or injected script can't read another user's data by
+// passing a different user_id.
+// Some commands (reveal_sensitive_field) also need the user to have proved who they are
+// again a moment ago: auth_reauthenticate checks their PIN and records when they last did.
+// The current user's role (permissions.rs) is loaded whenever the current user changes.
+
+use log::{info, warn};
+use serde::Serialize;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::permissions::{check_permission, load_role, Permission, Role};
+use crate::session::{adopt_legacy_session, verify_user_pin, verify_user_session};
+
+#[derive(Debug, Default)]
+pub struct AppState {
+    /// User whose session is active on this computer
+    current_user: RwLock<Option<String>>,
+    /// When the current user last re-authenticated
+    reauthenticated_at: RwLock<Option<Instant>>,
+    /// The current user's role; None while nobody is current
+    current_role: RwLock<Option<Role>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuthError {
+    NotAuthenticated,
+    InvalidSession,
+    SessionExpired,
+    /// The command needs a re-authentication within the last few minutes
+    ReauthRequired,
+    /// The current user's role is below what the command needs
+    Forbidden {
+        required_role: Role,
+    },
+    /// The user hasn't set a PIN on this computer (set_user_pin)
+    PinNotSet,
+    InvalidPin {
+        attempts_left: u32,
+    },
+    TooManyAttempts {
+        retry_after_secs: u64,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuthError::NotAuthenticated => write!(f, "Not authenticated. Please log in."),
+            AuthError::InvalidSession => write!(f, "Session is not valid for this user"),
+            AuthError::SessionExpired => write!(f, "Session expired. Please log in again."),
+            AuthError::ReauthRequired => write!(f, "Please confirm your identity to continue."),
+            AuthError::Forbidden { required_role } => {
+                write!(f, "You need the {} role to do that.", required_role)
+            }
+            AuthError::PinNotSet => write!(f, "Set a PIN first, or log in again."),
+            AuthError::InvalidPin { attempts_left } => {
+                write!(f, "Wrong PIN ({} attempts left)", attempts_left)
+            }
+            AuthError::TooManyAttempts { retry_after_secs } => write!(
+                f,
+                "Too many wrong PINs, try again in {} seconds",
+                retry_after_secs
+            ),
+            AuthError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for AuthError {
+    fn from(message: String) -> Self {
+        AuthError::Other { message }
+    }
+}
+
+impl From<AuthError> for String {
+    fn from(error: AuthError) -> Self {
+        error.to_string()
+    }
+}
+
+impl AppState {
+    pub fn current_user(&self) -> Option<String> {
+        self.current_user
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    pub fn set_current_user(&self, user_id: Option<String>) {
+        // Looked up before taking the lock: commands read the current user while holding
+        // the connection
+        let role = user_id.as_deref().map(load_role);
+        let mut current = self
+            .current_user
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        // A re-authentication only ever counts for the user who made it
+        if *current != user_id {
+            self.set_reauthenticated_at(None);
+        }
+        self.set_role(role);
+        *current = user_id;
+    }
+
+    pub(crate) fn set_role(&self, role: Option<Role>) {
+        *self
+            .current_role
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = role;
+    }
+
+    /// The current user's role. With nobody current it's readonly, legacy-user-id or not:
+    /// a user_id from JS proves nothing about who is at the keyboard
+    pub fn role(&self) -> Role {
+        let role = *self
+            .current_role
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        role.unwrap_or(Role::Readonly)
+    }
+
+    /// Fail with Forbidden unless the current role allows `permission`
+    pub fn check_permission(&self, permission: Permission) -> Result<(), AuthError> {
+        check_permission(self.role(), permission)
+    }
+
+    pub(crate) fn set_reauthenticated_at(&self, at: Option<Instant>) {
+        *self
+            .reauthenticated_at
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = at;
+    }
+
+    /// The current user, provided they re-authenticated within `window`
+    pub fn require_recent_reauth(&self, window: Duration) -> Result<String, AuthError> {
+        let user_id = self.current_user().ok_or(AuthError::NotAuthenticated)?;
+        let reauthenticated_at = *self
+            .reauthenticated_at
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        match reauthenticated_at {
+            Some(at) if at.elapsed() <= window => Ok(user_id),
+            _ => Err(AuthError::ReauthRequired),
+        }
+    }
+
+    /// User the command runs as. The authenticated user always wins; `user_id` from JS
+    /// is only honored (behind the legacy-user-id feature) while nobody is authenticated
+    pub fn require_user(&self, user_id: Option<String>) -> Result<String, AuthError> {
+        let user_id = user_id.filter(|id| !id.trim().is_empty());
+
+        if let Some(current) = self.current_user() {
+            if user_id.as_ref().is_some_and(|id| *id != current) {
+                warn!(
+                    "⚠️  [AUTH] Ignoring user_id from frontend that doesn't match the current user"
+                );
+            }
+            return Ok(current);
+        }
+
+        #[cfg(feature = "legacy-user-id")]
+        if let Some(user_id) = user_id {
+            warn!(
+                "⚠️  [AUTH] Deprecated: user_id passed from frontend; call auth_set_current_user"
+            );
+            return Ok(user_id);
+        }
+
+        Err(AuthError::NotAuthenticated)
+    }
+
+    /// The current user proved who they are with their PIN (set_user_pin)
+    pub fn reauthenticate(&self, pin: &str, now: Instant) -> Result<(), AuthError> {
+        let user_id = self.current_user().ok_or(AuthError::NotAuthenticated)?;
+        verify_user_pin(&user_id, pin, now).inspect_err(|e| {
+            warn!("⚠️  [AUTH] Re-authentication rejected: {}", e);
+        })?;
+        self.set_reauthenticated_at(Some(now));
+        info!("✅ [AUTH] User re-authenticated");
+        Ok(())
+    }
+
+    /// Check the user's stored session against the token and make them current
+    pub fn authenticate(&self, user_id: &str, session_token: &str) -> Result<(), AuthError> {
+        match verify_user_session(user_id, session_token) {
+            Ok(()) => {
+                self.set_current_user(Some(user_id.to_string()));
+                info!("✅ [AUTH] Current user set");
+                Ok(())
+            }
+            Err(e) => {
+                // A failed attempt logs out whoever was current rather than keeping them,
+                // which leaves the readonly role
+                self.set_current_user(None);
+                warn!("⚠️  [AUTH] Rejected current user: {}", e);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Set the authenticated user for all later commands, after validating their session token
+#[tauri::command]
+pub async fn auth_set_current_user(
+    user_id: String,
+    session_token: String,
+    state: State<'_, AppState>,
+) -> Result<(), AuthError> {
+    let user_id = user_id.trim();
+    // authenticate() then judges the session wherever it ended up
+    if let Err(e) = adopt_legacy_session(user_id, &session_token).await {
+        warn!("⚠️  [AUTH] Legacy session not adopted: {}", e);
+    }
+    state.authenticate(user_id, &session_token)
+}
+
+/// The current user confirmed who they are by entering their PIN again; unlocks commands
+/// that check require_recent_reauth for a few minutes. Not the session token: the webview
+/// can read that at any time
+#[tauri::command]
+pub fn auth_reauthenticate(pin: String, state: State<'_, AppState>) -> Result<(), AuthError> {
+    state.reauthenticate(&pin, Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret_store::{self, SecretKey};
+
+    #[test]
+    fn test_not_authenticated_without_current_user() {
+        let state = AppState::default();
+
+        assert_eq!(state.require_user(None), Err(AuthError::NotAuthenticated));
+        assert_eq!(
+            state.require_user(Some("  ".to_string())),
+            Err(AuthError::NotAuthenticated)
+        );
+        assert_eq!(
+            String::from(AuthError::NotAuthenticated),
+            "Not authenticated. Please log in."
+        );
+
+        #[cfg(feature = "legacy-user-id")]
+        assert_eq!(
+            state.require_user(Some("user_1".to_string())),
+            Ok("user_1".to_string())
+        );
+        #[cfg(not(feature = "legacy-user-id"))]
+        assert_eq!(
+            state.require_user(Some("user_1".to_string())),
+            Err(AuthError::NotAuthenticated)
+        );
+    }
+
+    #[test]
+    fn test_current_user_survives_across_commands() {
+        let _guard = secret_store::test_guard();
+        let _ = secret_store::remove(SecretKey::SessionToken);
+        crate::session::store_user_session("alice", "sess_alice").unwrap();
+
+        let state = AppState::default();
+        assert_eq!(
+            state.authenticate("alice", "sess_wrong"),
+            Err(AuthError::InvalidSession)
+        );
+        assert_eq!(
+            state.authenticate("mallory", "sess_alice"),
+            Err(AuthError::InvalidSession)
+        );
+        assert_eq!(state.current_user(), None);
+
+        state.authenticate("alice", "sess_alice").unwrap();
+        for _ in 0..3 {
+            assert_eq!(state.require_user(None), Ok("alice".to_string()));
+        }
+        // The frontend can't switch users by passing someone else's id
+        assert_eq!(
+            state.require_user(Some("bob".to_string())),
+            Ok("alice".to_string())
+        );
+
+        // A failed re-authentication drops the current user
+        assert!(state.authenticate("alice", "sess_stale").is_err());
+        assert_eq!(state.require_user(None), Err(AuthError::NotAuthenticated));
+
+        let _ = secret_store::remove(SecretKey::SessionToken);
+    }
+}
@@ -0,0 +1,236 @@
+// src-tauri/src/app_windows.rs
+//
+// Deals can be opened in their own window (open_deal_window), labelled deal-{id}, next to
+// the main one. Opening a deal that already has a window focuses it instead. App-wide events
+// (data-changed, deep-link-action) go to every window; anything a window started (directory
+// watches, report jobs) is stopped when it's destroyed.
+//
+// Nothing may assume "main" exists: on macOS the main window can be closed while a deal
+// window stays open. focus_window picks the deal's own window, then main, then the most
+// recently opened deal window.
+
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager, State, WebviewUrl, WebviewWindowBuilder};
+
+use crate::app_state::AppState;
+use crate::database::{get_deal_for_user, DbState};
+use crate::telemetry::track;
+
+pub const MAIN_WINDOW_LABEL: &str = "main";
+pub const DEAL_WINDOW_PREFIX: &str = "deal-";
+
+const MAX_DEAL_ID_LEN: usize = 128;
+const DEAL_WINDOW_SIZE: (f64, f64) = (1100.0, 800.0);
+const DEAL_WINDOW_MIN_SIZE: (f64, f64) = (800.0, 600.0);
+
+static REGISTRY: Lazy<Mutex<WindowRegistry>> = Lazy::new(|| Mutex::new(WindowRegistry::default()));
+
+/// Open deal windows, oldest first
+#[derive(Debug, Default)]
+pub(crate) struct WindowRegistry {
+    deals: Vec<(String, String)>,
+}
+
+impl WindowRegistry {
+    /// Record a new deal window (moves it to the end if it was already there)
+    pub fn opened(&mut self, label: &str, deal_id: &str) {
+        self.deals.retain(|(open, _)| open != label);
+        self.deals.push((label.to_string(), deal_id.to_string()));
+    }
+
+    /// Forget a destroyed window; returns the deal it showed, if it was a deal window
+    pub fn closed(&mut self, label: &str) -> Option<String> {
+        let index = self.deals.iter().position(|(open, _)| open == label)?;
+        Some(self.deals.remove(index).1)
+    }
+
+    pub fn deal_window(&self, deal_id: &str) -> Option<&str> {
+        self.deals
+            .iter()
+            .find(|(_, deal)| deal == deal_id)
+            .map(|(label, _)| label.as_str())
+    }
+
+    /// Which of the open windows to bring forward: the deal's own window, then main, then
+    /// the most recently opened deal window
+    pub fn focus_target(&self, open_labels: &[String], deal_id: Option<&str>) -> Option<String> {
+        let is_open = |label: &str| open_labels.iter().any(|open| open == label);
+
+        if let Some(label) = deal_id.and_then(|id| self.deal_window(id)) {
+            if is_open(label) {
+                return Some(label.to_string());
+            }
+        }
+        if is_open(MAIN_WINDOW_LABEL) {
+            return Some(MAIN_WINDOW_LABEL.to_string());
+        }
+        self.deals
+            .iter()
+            .rev()
+            .map(|(label, _)| label.as_str())
+            .find(|label| is_open(label))
+            .or_else(|| open_labels.first().map(String::as_str))
+            .map(str::to_string)
+    }
+}
+
+fn registry() -> std::sync::MutexGuard<'static, WindowRegistry> {
+    REGISTRY.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Window label for a deal; ids that can't be part of a label are rejected
+pub(crate) fn deal_window_label(deal_id: &str) -> Result<String, String> {
+    let valid = !deal_id.is_empty()
+        && deal_id.len() <= MAX_DEAL_ID_LEN
+        && deal_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err("Invalid deal id".to_string());
+    }
+    Ok(format!("{}{}", DEAL_WINDOW_PREFIX, deal_id))
+}
+
+/// Show, unminimize and focus the best window for deal_id (see focus_target)
+/// Returns false when no window is open
+pub fn focus_window(app: &AppHandle, deal_id: Option<&str>) -> bool {
+    let open_labels: Vec<String> = app.webview_windows().into_keys().collect();
+    let target = registry().focus_target(&open_labels, deal_id);
+
+    match target.and_then(|label| app.get_webview_window(&label)) {
+        Some(window) => {
+            let _ = window.show();
+            let _ = window.unminimize();
+            let _ = window.set_focus();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Clean up after a destroyed window: its directory watches and report jobs stop
+pub fn window_destroyed(label: &str) {
+    if let Some(deal_id) = registry().closed(label) {
+        info!("🪟 Deal window closed: {}", deal_id);
+    }
+    crate::fs_watcher::unwatch_window(label);
+    let cancelled = crate::reporting::cancel_window_jobs(label);
+    if cancelled > 0 {
+        warn!(
+            "⚠️  Cancelled {} report job(s) from closed window {}",
+            cancelled, label
+        );
+    }
+}
+
+/// Open the deal in its own window, or focus the window it's already open in
+/// Returns the window label
+#[tauri::command]
+pub async fn open_deal_window(
+    deal_id: String,
+    user_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<String, String> {
+    track("open_deal_window", || {
+        let user_id_value = state.require_user(user_id)?;
+        let label = deal_window_label(&deal_id)?;
+        get_deal_for_user(
+            db.get().map_err(|e| e.to_string())?,
+            &deal_id,
+            &user_id_value,
+        )?
+        .ok_or_else(|| "Deal not found or access denied".to_string())?;
+
+        if let Some(window) = app.get_webview_window(&label) {
+            let _ = window.show();
+            let _ = window.unminimize();
+            let _ = window.set_focus();
+            return Ok(label);
+        }
+
+        let url = WebviewUrl::App(format!("deals/{}", deal_id).into());
+        WebviewWindowBuilder::new(&app, &label, url)
+            .title(format!("Deal {}", deal_id))
+            .inner_size(DEAL_WINDOW_SIZE.0, DEAL_WINDOW_SIZE.1)
+            .min_inner_size(DEAL_WINDOW_MIN_SIZE.0, DEAL_WINDOW_MIN_SIZE.1)
+            .resizable(true)
+            .focused(true)
+            .build()
+            .map_err(|e| format!("Failed to open deal window: {}", e))?;
+        registry().opened(&label, &deal_id);
+        info!("🪟 Opened deal window {}", label);
+        Ok(label)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(labels: &[&str]) -> Vec<String> {
+        labels.iter().map(|label| label.to_string()).collect()
+    }
+
+    #[test]
+    fn test_deal_window_labels() {
+        assert_eq!(deal_window_label("d1").unwrap(), "deal-d1");
+        assert_eq!(deal_window_label("abc_DEF-9").unwrap(), "deal-abc_DEF-9");
+        assert!(deal_window_label("").is_err());
+        assert!(deal_window_label("../main").is_err());
+        assert!(deal_window_label("a b").is_err());
+        assert!(deal_window_label(&"x".repeat(MAX_DEAL_ID_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn test_registry_tracks_open_and_closed_windows() {
+        let mut registry = WindowRegistry::default();
+        registry.opened("deal-d1", "d1");
+        registry.opened("deal-d2", "d2");
+        registry.opened("deal-d1", "d1");
+        assert_eq!(registry.deal_window("d1"), Some("deal-d1"));
+        assert_eq!(registry.deal_window("d3"), None);
+
+        assert_eq!(registry.closed("deal-d1"), Some("d1".to_string()));
+        assert_eq!(registry.closed("deal-d1"), None);
+        assert_eq!(registry.closed(MAIN_WINDOW_LABEL), None);
+        assert_eq!(registry.deal_window("d1"), None);
+        assert_eq!(registry.deal_window("d2"), Some("deal-d2"));
+    }
+
+    #[test]
+    fn test_focus_target_without_main() {
+        let mut registry = WindowRegistry::default();
+        registry.opened("deal-d1", "d1");
+        registry.opened("deal-d2", "d2");
+
+        let all = labels(&["main", "deal-d1", "deal-d2"]);
+        assert_eq!(
+            registry.focus_target(&all, Some("d1")).as_deref(),
+            Some("deal-d1")
+        );
+        assert_eq!(
+            registry.focus_target(&all, Some("d9")).as_deref(),
+            Some("main")
+        );
+        assert_eq!(registry.focus_target(&all, None).as_deref(), Some("main"));
+
+        // Main closed: the most recently opened deal window
+        let deals_only = labels(&["deal-d1", "deal-d2"]);
+        assert_eq!(
+            registry.focus_target(&deals_only, None).as_deref(),
+            Some("deal-d2")
+        );
+
+        // The registry can lag the real window list; only open windows are picked
+        let d1_only = labels(&["deal-d1"]);
+        assert_eq!(
+            registry.focus_target(&d1_only, Some("d2")).as_deref(),
+            Some("deal-d1")
+        );
+        assert_eq!(registry.focus_target(&[], Some("d1")), None);
+    }
+}
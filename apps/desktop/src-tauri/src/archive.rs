@@ -0,0 +1,416 @@
+// src-tauri/src/archive.rs
+//
+// Zip archive creation and extraction for deal export
+// Lets dealers send support "everything about deal X" as a single file
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Component, Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use tauri::State;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::app_state::AppState;
+use crate::database::{db_get_client, db_get_deal, db_get_documents_by_deal, db_get_vehicle, DbState};
+use crate::telemetry::track;
+
+/// Default deflate level when the caller doesn't specify one
+const DEFAULT_COMPRESSION_LEVEL: i64 = 6;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveSummary {
+    pub path: String,
+    pub file_count: usize,
+    pub total_bytes: u64,
+    pub skipped: Vec<String>,
+}
+
+/// Create a zip archive from a list of files
+/// Entries keep their directory layout relative to the files' common root
+#[tauri::command]
+pub fn create_zip_archive(
+    file_paths: Vec<String>,
+    output_path: String,
+    compression_level: Option<i64>,
+) -> Result<ArchiveSummary, String> {
+    track("create_zip_archive", || {
+        info!(
+            "📦 Creating zip archive with {} files: {}",
+            file_paths.len(),
+            output_path
+        );
+
+        if file_paths.is_empty() {
+            return Err("No files provided for archive".to_string());
+        }
+
+        let paths: Vec<PathBuf> = file_paths.iter().map(PathBuf::from).collect();
+        for path in &paths {
+            if !path.is_file() {
+                return Err(format!("File does not exist: {}", path.display()));
+            }
+        }
+
+        let root = common_root(&paths);
+        let mut writer = create_writer(&output_path)?;
+        let options = file_options(compression_level);
+
+        let mut total_bytes = 0;
+        for path in &paths {
+            let entry_name = entry_name_for(path, &root)?;
+            total_bytes += add_file_entry(&mut writer, path, &entry_name, options)?;
+        }
+
+        finish_writer(writer)?;
+
+        info!(
+            "✅ Zip archive created: {} ({} bytes)",
+            output_path, total_bytes
+        );
+        Ok(ArchiveSummary {
+            path: output_path,
+            file_count: paths.len(),
+            total_bytes,
+            skipped: Vec::new(),
+        })
+    })
+}
+
+/// Extract a zip archive into a destination directory
+/// SECURITY: Rejects the whole archive if any entry is absolute or escapes dest_dir (zip-slip)
+#[tauri::command]
+pub fn extract_zip_archive(zip_path: String, dest_dir: String) -> Result<ArchiveSummary, String> {
+    track("extract_zip_archive", || {
+        info!("📂 Extracting zip archive: {} -> {}", zip_path, dest_dir);
+
+        let file = File::open(&zip_path).map_err(|e| format!("Failed to open archive: {}", e))?;
+        let mut archive =
+            ZipArchive::new(BufReader::new(file)).map_err(|e| format!("Invalid zip archive: {}", e))?;
+
+        // Validate every entry before writing anything to disk
+        let mut entries = Vec::with_capacity(archive.len());
+        for i in 0..archive.len() {
+            let entry = archive
+                .by_index(i)
+                .map_err(|e| format!("Failed to read archive entry {}: {}", i, e))?;
+            let relative = sanitize_entry_name(entry.name())?;
+            entries.push((i, relative, entry.is_dir()));
+        }
+
+        let dest = PathBuf::from(&dest_dir);
+        fs::create_dir_all(&dest).map_err(|e| format!("Failed to create destination: {}", e))?;
+
+        let mut file_count = 0;
+        let mut total_bytes = 0;
+        for (i, relative, is_dir) in entries {
+            let out_path = dest.join(&relative);
+
+            if is_dir {
+                fs::create_dir_all(&out_path)
+                    .map_err(|e| format!("Failed to create directory {}: {}", out_path.display(), e))?;
+                continue;
+            }
+
+            if let Some(parent) = out_path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create directory {}: {}", parent.display(), e))?;
+            }
+
+            let mut entry = archive
+                .by_index(i)
+                .map_err(|e| format!("Failed to read archive entry {}: {}", i, e))?;
+            let out_file = File::create(&out_path)
+                .map_err(|e| format!("Failed to create {}: {}", out_path.display(), e))?;
+            let mut out = BufWriter::new(out_file);
+
+            total_bytes += io::copy(&mut entry, &mut out)
+                .map_err(|e| format!("Failed to extract {}: {}", relative.display(), e))?;
+            out.flush()
+                .map_err(|e| format!("Failed to write {}: {}", out_path.display(), e))?;
+            file_count += 1;
+        }
+
+        info!("✅ Extracted {} files ({} bytes)", file_count, total_bytes);
+        Ok(ArchiveSummary {
+            path: dest_dir,
+            file_count,
+            total_bytes,
+            skipped: Vec::new(),
+        })
+    })
+}
+
+/// Bundle a deal's database rows and PDF files into a single zip for support
+/// Layout: deal.json + documents/{document_id}_{filename}
+#[tauri::command]
+pub fn export_deal_archive(
+    deal_id: String,
+    user_id: Option<String>,
+    output_path: String,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<ArchiveSummary, String> {
+    track("export_deal_archive", || {
+        info!("📦 Exporting deal archive: {}", deal_id);
+
+        let deal = db_get_deal(deal_id.clone(), user_id.clone(), state.clone(), db.clone())?
+            .ok_or_else(|| "Deal not found or access denied".to_string())?;
+        let client = db_get_client(deal.client_id.clone(), user_id.clone(), state.clone(), db.clone())?;
+        let vehicle = db_get_vehicle(deal.vehicle_id.clone(), user_id, state, db)?;
+        let documents = db_get_documents_by_deal(deal_id.clone())?;
+
+        let deal_json = serde_json::to_vec_pretty(&serde_json::json!({
+            "deal": deal,
+            "client": client,
+            "vehicle": vehicle,
+            "documents": documents,
+        }))
+        .map_err(|e| format!("Failed to serialize deal: {}", e))?;
+
+        let mut writer = create_writer(&output_path)?;
+        let options = file_options(None);
+
+        writer
+            .start_file("deal.json", options)
+            .map_err(|e| format!("Failed to add deal.json: {}", e))?;
+        writer
+            .write_all(&deal_json)
+            .map_err(|e| format!("Failed to write deal.json: {}", e))?;
+
+        let mut file_count = 1;
+        let mut total_bytes = deal_json.len() as u64;
+        let mut skipped = Vec::new();
+
+        for document in &documents {
+            let path = Path::new(&document.file_path);
+            if !path.is_file() {
+                warn!(
+                    "⚠️  Document file missing, skipping: {}",
+                    document.file_path
+                );
+                skipped.push(document.file_path.clone());
+                continue;
+            }
+
+            let entry_name = format!("documents/{}_{}", document.id, document.filename);
+            total_bytes += add_file_entry(&mut writer, path, &entry_name, options)?;
+            file_count += 1;
+        }
+
+        finish_writer(writer)?;
+
+        info!(
+            "✅ Deal archive exported: {} ({} files, {} skipped)",
+            output_path,
+            file_count,
+            skipped.len()
+        );
+        Ok(ArchiveSummary {
+            path: output_path,
+            file_count,
+            total_bytes,
+            skipped,
+        })
+    })
+}
+
+// Internal helpers
+
+pub(crate) fn file_options(compression_level: Option<i64>) -> SimpleFileOptions {
+    let level = compression_level
+        .unwrap_or(DEFAULT_COMPRESSION_LEVEL)
+        .clamp(0, 9);
+    let method = if level == 0 {
+        CompressionMethod::Stored
+    } else {
+        CompressionMethod::Deflated
+    };
+
+    SimpleFileOptions::default()
+        .compression_method(method)
+        .compression_level(if level == 0 { None } else { Some(level) })
+        .large_file(true)
+}
+
+pub(crate) fn create_writer(output_path: &str) -> Result<ZipWriter<BufWriter<File>>, String> {
+    if let Some(parent) = Path::new(output_path).parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    let file = File::create(output_path).map_err(|e| {
+        error!("❌ Failed to create archive: {}", e);
+        format!("Failed to create archive: {}", e)
+    })?;
+
+    Ok(ZipWriter::new(BufWriter::new(file)))
+}
+
+pub(crate) fn finish_writer(writer: ZipWriter<BufWriter<File>>) -> Result<(), String> {
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to finalize archive: {}", e))?
+        .flush()
+        .map_err(|e| format!("Failed to write archive: {}", e))
+}
+
+/// Stream a file into the archive without buffering it in memory
+pub(crate) fn add_file_entry(
+    writer: &mut ZipWriter<BufWriter<File>>,
+    path: &Path,
+    entry_name: &str,
+    options: SimpleFileOptions,
+) -> Result<u64, String> {
+    let mut input =
+        File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+
+    writer
+        .start_file(entry_name, options)
+        .map_err(|e| format!("Failed to add {}: {}", entry_name, e))?;
+
+    io::copy(&mut input, writer)
+        .map_err(|e| format!("Failed to compress {}: {}", path.display(), e))
+}
+
+/// Deepest directory that contains every file
+fn common_root(paths: &[PathBuf]) -> PathBuf {
+    let mut root = paths[0].parent().map(Path::to_path_buf).unwrap_or_default();
+
+    for path in &paths[1..] {
+        while !path.starts_with(&root) {
+            if !root.pop() {
+                return PathBuf::new();
+            }
+        }
+    }
+
+    root
+}
+
+/// Zip entry names always use forward slashes regardless of platform
+fn entry_name_for(path: &Path, root: &Path) -> Result<String, String> {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+
+    let parts: Vec<String> = relative
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+
+    if parts.is_empty() {
+        return Err(format!("Invalid file path: {}", path.display()));
+    }
+
+    Ok(parts.join("/"))
+}
+
+/// Turn an entry name into a relative path, rejecting absolute paths and `..`
+pub(crate) fn sanitize_entry_name(name: &str) -> Result<PathBuf, String> {
+    let normalized = name.replace('\\', "/");
+
+    if normalized.starts_with('/') || normalized.contains(':') {
+        return Err(format!("Archive entry has an absolute path: {}", name));
+    }
+
+    let mut relative = PathBuf::new();
+    for part in normalized.split('/') {
+        match part {
+            "" | "." => continue,
+            ".." => return Err(format!("Archive entry escapes destination: {}", name)),
+            _ => relative.push(part),
+        }
+    }
+
+    if relative.as_os_str().is_empty() {
+        return Err(format!("Archive entry has an empty path: {}", name));
+    }
+
+    Ok(relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_zip_roundtrip_preserves_layout() {
+        let dir = temp_dir("archive", "roundtrip");
+        let src = dir.join("src");
+        fs::create_dir_all(src.join("deal-1")).unwrap();
+        fs::write(src.join("summary.txt"), b"summary").unwrap();
+        fs::write(src.join("deal-1").join("contract.pdf"), vec![7u8; 100_000]).unwrap();
+
+        let zip_path = dir.join("out.zip");
+        let created = create_zip_archive(
+            vec![
+                src.join("summary.txt").to_string_lossy().to_string(),
+                src.join("deal-1")
+                    .join("contract.pdf")
+                    .to_string_lossy()
+                    .to_string(),
+            ],
+            zip_path.to_string_lossy().to_string(),
+            Some(9),
+        )
+        .unwrap();
+        assert_eq!(created.file_count, 2);
+
+        let dest = dir.join("dest");
+        let extracted = extract_zip_archive(
+            zip_path.to_string_lossy().to_string(),
+            dest.to_string_lossy().to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(extracted.file_count, 2);
+        assert_eq!(fs::read(dest.join("summary.txt")).unwrap(), b"summary");
+        assert_eq!(
+            fs::read(dest.join("deal-1").join("contract.pdf")).unwrap(),
+            vec![7u8; 100_000]
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_extract_rejects_zip_slip() {
+        let dir = temp_dir("archive", "zipslip");
+        let zip_path = dir.join("evil.zip");
+
+        let mut writer = create_writer(&zip_path.to_string_lossy()).unwrap();
+        writer.start_file("ok.txt", file_options(None)).unwrap();
+        writer.write_all(b"fine").unwrap();
+        writer
+            .start_file("../evil.txt", file_options(None))
+            .unwrap();
+        writer.write_all(b"gotcha").unwrap();
+        finish_writer(writer).unwrap();
+
+        let dest = dir.join("dest");
+        let result = extract_zip_archive(
+            zip_path.to_string_lossy().to_string(),
+            dest.to_string_lossy().to_string(),
+        );
+
+        assert!(result.is_err());
+        assert!(!dir.join("evil.txt").exists());
+        assert!(!dest.join("ok.txt").exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_sanitize_entry_name() {
+        assert!(sanitize_entry_name("/etc/passwd").is_err());
+        assert!(sanitize_entry_name("C:\\Windows\\evil.dll").is_err());
+        assert!(sanitize_entry_name("docs/../../evil").is_err());
+        assert_eq!(
+            sanitize_entry_name("docs\\deal\\contract.pdf").unwrap(),
+            PathBuf::from("docs").join("deal").join("contract.pdf")
+        );
+    }
+}
@@ -0,0 +1,85 @@
+// src-tauri/src/audit_log.rs
+//
+// Append-only record of actions on the data that someone may have to answer for later:
+// retention purges, retention holds, policy changes. Entries are written in the same
+// transaction as the change they describe, so one never exists without the other.
+
+use rusqlite::{params, Connection, Result as SqlResult, Row};
+use serde::Serialize;
+use serde_json::Value;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::{new_row_id, DbState};
+use crate::telemetry::track;
+
+const DEFAULT_LIMIT: u32 = 100;
+const MAX_LIMIT: u32 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEntry {
+    pub id: String,
+    pub user_id: String,
+    pub action: String,
+    pub details: Value,
+    pub created_at: i64,
+}
+
+impl AuditEntry {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        let details: String = row.get("details")?;
+        Ok(AuditEntry {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            action: row.get("action")?,
+            details: serde_json::from_str(&details).unwrap_or(Value::Null),
+            created_at: row.get("created_at")?,
+        })
+    }
+}
+
+/// Append an entry; returns its id
+pub(crate) fn record_audit(
+    conn: &Connection,
+    user_id: &str,
+    action: &str,
+    details: &Value,
+    now: i64,
+) -> SqlResult<String> {
+    let id = new_row_id();
+    conn.execute(
+        "INSERT INTO audit_log (id, user_id, action, details, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![id, user_id, action, details.to_string(), now],
+    )?;
+    Ok(id)
+}
+
+pub(crate) fn audit_entries(
+    conn: &Connection,
+    user_id: &str,
+    limit: u32,
+) -> SqlResult<Vec<AuditEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM audit_log WHERE user_id = ?1
+         ORDER BY created_at DESC, rowid DESC LIMIT ?2",
+    )?;
+    let entries = stmt.query_map(params![user_id, limit], AuditEntry::from_row)?;
+    entries.collect()
+}
+
+/// The user's audit entries, newest first
+#[tauri::command]
+pub fn get_audit_log(
+    limit: Option<u32>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<Vec<AuditEntry>, String> {
+    track("get_audit_log", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+        audit_entries(&db.conn(), &user_id_value, limit).map_err(|e| e.to_string())
+    })
+}
@@ -0,0 +1,549 @@
+// src-tauri/src/auto_lock.rs
+//
+// Auto-lock for front-desk PCs left signed in where customers can see the screen. The
+// frontend calls app_heartbeat on user activity; once nothing has for the idle timeout
+// (auto_lock_timeout_minutes setting), the app locks: the current user, their role and any
+// recent re-authentication (which is what lets reveal_sensitive_field decrypt) are dropped
+// from AppState, and "app-locked" is emitted. From then on guarded() (wrapped around the
+// invoke handler in main.rs) rejects every command but LOCKED_COMMANDS with Locked, until
+// unlock_app gets the PIN whose hash set_auto_lock saved in the keyring. Unlocking puts the
+// user back if their stored session is still good. Keyring secrets and sessions are never
+// touched; only this process forgets who is signed in.
+//
+// The timeout only applies once a PIN is saved, so the app can't lock with no way back in.
+
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::Serialize;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::ipc::Invoke;
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+use crate::app_state::{AppState, AuthError};
+use crate::encryption::verify_password;
+use crate::kiosk::{lockout_after, PinHash, MAX_PIN_ATTEMPTS};
+use crate::secret_store::{self, SecretKey};
+use crate::session::has_live_session;
+use crate::settings_cache::{get_setting, set_setting};
+use crate::telemetry::track;
+
+/// Settings key: minutes without activity before the app locks; 0 or missing turns it off
+pub const AUTO_LOCK_TIMEOUT_SETTING: &str = "auto_lock_timeout_minutes";
+
+/// Emitted with the LockStatus when the app locks and unlocks
+pub const APP_LOCKED_EVENT: &str = "app-locked";
+pub const APP_UNLOCKED_EVENT: &str = "app-unlocked";
+
+/// Longest idle timeout set_auto_lock accepts
+const MAX_TIMEOUT_MINUTES: u32 = 24 * 60;
+/// How often the idle watch checks, so "app-locked" goes out close to the timeout
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Commands still allowed while locked: the lock screen's
+const LOCKED_COMMANDS: &[&str] = &["unlock_app", "get_lock_status", "app_heartbeat"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LockStatus {
+    pub locked: bool,
+    pub locked_at: Option<i64>,
+    /// None while auto-lock is off
+    pub timeout_minutes: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LockError {
+    /// The app is locked; only unlock_app gets it going again
+    Locked {
+        command: String,
+    },
+    NotLocked,
+    /// Auto-lock needs a PIN saved first
+    NoPin,
+    InvalidPin {
+        attempts_left: u32,
+    },
+    TooManyAttempts {
+        retry_after_secs: u64,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl std::fmt::Display for LockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockError::Locked { command } => {
+                write!(f, "The app is locked; unlock it to use {}", command)
+            }
+            LockError::NotLocked => write!(f, "The app is not locked"),
+            LockError::NoPin => write!(f, "Set an unlock PIN before turning on auto-lock"),
+            LockError::InvalidPin { attempts_left } => {
+                write!(f, "Wrong PIN ({} attempts left)", attempts_left)
+            }
+            LockError::TooManyAttempts { retry_after_secs } => write!(
+                f,
+                "Too many wrong PINs, try again in {} seconds",
+                retry_after_secs
+            ),
+            LockError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for LockError {
+    fn from(message: String) -> Self {
+        LockError::Other { message }
+    }
+}
+
+impl From<AuthError> for LockError {
+    fn from(error: AuthError) -> Self {
+        LockError::Other {
+            message: error.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Locked {
+    locked_at: i64,
+    /// Current when the app locked; put back by unlock_app
+    user_id: Option<String>,
+}
+
+#[derive(Debug)]
+struct LockInner {
+    timeout: Option<Duration>,
+    last_activity: Instant,
+    locked: Option<Locked>,
+    failed_attempts: u32,
+    lockouts: u32,
+    locked_until: Option<Instant>,
+}
+
+/// Managed by Tauri (main.rs); off until configured from settings or set_auto_lock
+#[derive(Debug)]
+pub struct AutoLockState {
+    inner: Mutex<LockInner>,
+}
+
+impl Default for AutoLockState {
+    fn default() -> Self {
+        AutoLockState {
+            inner: Mutex::new(LockInner {
+                timeout: None,
+                last_activity: Instant::now(),
+                locked: None,
+                failed_attempts: 0,
+                lockouts: 0,
+                locked_until: None,
+            }),
+        }
+    }
+}
+
+impl AutoLockState {
+    fn lock(&self) -> MutexGuard<'_, LockInner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn status(&self) -> LockStatus {
+        let inner = self.lock();
+        LockStatus {
+            locked: inner.locked.is_some(),
+            locked_at: inner.locked.as_ref().map(|locked| locked.locked_at),
+            timeout_minutes: inner.timeout.map(|timeout| (timeout.as_secs() / 60) as u32),
+        }
+    }
+
+    /// Set the idle timeout (None: never lock); the idle clock starts over
+    fn configure(&self, timeout: Option<Duration>, now: Instant) {
+        let mut inner = self.lock();
+        inner.timeout = timeout;
+        inner.last_activity = now;
+    }
+
+    /// The user did something; ignored while locked
+    fn heartbeat(&self, now: Instant) {
+        let mut inner = self.lock();
+        if inner.locked.is_none() {
+            inner.last_activity = now;
+        }
+    }
+
+    /// Lock if the timeout has passed since the last activity, clearing the session from
+    /// app_state. Some(status) only when this call locked the app
+    fn lock_if_idle(&self, app_state: &AppState, now: Instant, now_ms: i64) -> Option<LockStatus> {
+        {
+            let mut inner = self.lock();
+            let timeout = inner.timeout?;
+            if inner.locked.is_some()
+                || now.saturating_duration_since(inner.last_activity) < timeout
+            {
+                return None;
+            }
+            inner.locked = Some(Locked {
+                locked_at: now_ms,
+                user_id: app_state.current_user(),
+            });
+            app_state.set_current_user(None);
+            app_state.set_reauthenticated_at(None);
+        }
+        Some(self.status())
+    }
+
+    /// Whether a command may run right now
+    pub fn check(&self, command: &str) -> Result<(), LockError> {
+        if self.lock().locked.is_none() || LOCKED_COMMANDS.contains(&command) {
+            Ok(())
+        } else {
+            Err(LockError::Locked {
+                command: command.to_string(),
+            })
+        }
+    }
+
+    /// Unlock if the PIN matches pin_hash; Ok(the user who was current when it locked).
+    /// The lock is held while the PIN is checked so parallel guesses can't slip past the
+    /// attempt count.
+    fn unlock(
+        &self,
+        pin: &str,
+        pin_hash: PinHash,
+        now: Instant,
+    ) -> Result<Option<String>, LockError> {
+        let mut inner = self.lock();
+        if inner.locked.is_none() {
+            return Err(LockError::NotLocked);
+        }
+        if let Some(until) = inner.locked_until.filter(|until| now < *until) {
+            return Err(LockError::TooManyAttempts {
+                retry_after_secs: (until - now).as_secs_f64().ceil() as u64,
+            });
+        }
+
+        let matches = verify_password(
+            pin.to_string(),
+            pin_hash.salt_base64,
+            pin_hash.key_hash_base64,
+            pin_hash.params,
+        )?;
+        if matches {
+            let locked = inner.locked.take().expect("checked above");
+            inner.last_activity = now;
+            inner.failed_attempts = 0;
+            inner.lockouts = 0;
+            inner.locked_until = None;
+            return Ok(locked.user_id);
+        }
+
+        inner.failed_attempts += 1;
+        if inner.failed_attempts < MAX_PIN_ATTEMPTS {
+            return Err(LockError::InvalidPin {
+                attempts_left: MAX_PIN_ATTEMPTS - inner.failed_attempts,
+            });
+        }
+
+        let lockout = lockout_after(inner.lockouts);
+        inner.failed_attempts = 0;
+        inner.lockouts += 1;
+        inner.locked_until = Some(now + lockout);
+        Err(LockError::TooManyAttempts {
+            retry_after_secs: lockout.as_secs(),
+        })
+    }
+}
+
+fn stored_pin() -> Result<Option<PinHash>, String> {
+    secret_store::get(SecretKey::AppLockPin)?
+        .map(|json| serde_json::from_str(&json).map_err(|e| format!("Invalid lock PIN: {}", e)))
+        .transpose()
+}
+
+/// The saved timeout, if there is one and a PIN to unlock with
+fn saved_timeout() -> Result<Option<Duration>, String> {
+    let minutes = get_setting(AUTO_LOCK_TIMEOUT_SETTING)?
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .filter(|minutes| *minutes > 0);
+    match minutes {
+        Some(minutes) if stored_pin()?.is_some() => {
+            Ok(Some(Duration::from_secs(u64::from(minutes) * 60)))
+        }
+        Some(_) => {
+            warn!("⚠️  Auto-lock is set but no unlock PIN is saved; leaving it off");
+            Ok(None)
+        }
+        None => Ok(None),
+    }
+}
+
+/// Lock now if the app has been idle too long, and tell the frontend
+pub(crate) fn lock_if_idle<R: Runtime>(app: &AppHandle<R>) {
+    let (Some(lock), Some(state)) = (
+        app.try_state::<AutoLockState>(),
+        app.try_state::<AppState>(),
+    ) else {
+        return;
+    };
+    if let Some(status) = lock.lock_if_idle(&state, Instant::now(), Utc::now().timestamp_millis()) {
+        info!("🔒 App locked after inactivity");
+        if let Err(e) = app.emit(APP_LOCKED_EVENT, &status) {
+            error!("Failed to emit app-locked: {}", e);
+        }
+    }
+}
+
+/// Load the saved timeout and lock the app whenever it's been idle that long
+pub fn start_idle_watch<R: Runtime>(app: AppHandle<R>) {
+    match saved_timeout() {
+        Ok(timeout) => {
+            if let Some(lock) = app.try_state::<AutoLockState>() {
+                lock.configure(timeout, Instant::now());
+            }
+        }
+        Err(e) => warn!("⚠️  Could not load auto-lock settings: {}", e),
+    }
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            lock_if_idle(&app);
+        }
+    });
+}
+
+/// Wrap the app's invoke handler so a locked app only answers the lock screen. An overdue
+/// lock is applied first, so commands can't slip in between checks of the idle watch.
+pub fn guarded<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke: Invoke<R>| {
+        let app = invoke.message.webview().app_handle().clone();
+        lock_if_idle(&app);
+        let blocked = app
+            .try_state::<AutoLockState>()
+            .and_then(|lock| lock.check(invoke.message.command()).err());
+
+        match blocked {
+            Some(error) => {
+                warn!("🔒 App locked, blocked {}", invoke.message.command());
+                invoke.resolver.reject(error);
+                true
+            }
+            None => handler(invoke),
+        }
+    }
+}
+
+/// The user did something in the window; keeps the app from locking
+#[tauri::command]
+pub fn app_heartbeat(lock: State<'_, AutoLockState>) -> LockStatus {
+    lock.heartbeat(Instant::now());
+    lock.status()
+}
+
+#[tauri::command]
+pub fn get_lock_status(lock: State<'_, AutoLockState>) -> LockStatus {
+    lock.status()
+}
+
+/// Turn auto-lock on (timeout_minutes > 0) or off, optionally replacing the unlock PIN.
+/// pin_hash comes from derive_key_from_password, as for kiosk mode
+#[tauri::command]
+pub fn set_auto_lock(
+    timeout_minutes: u32,
+    pin_hash: Option<PinHash>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    lock: State<'_, AutoLockState>,
+) -> Result<LockStatus, LockError> {
+    track("set_auto_lock", || {
+        state.require_user(user_id)?;
+        if timeout_minutes > MAX_TIMEOUT_MINUTES {
+            return Err(format!(
+                "The idle timeout can be at most {} minutes",
+                MAX_TIMEOUT_MINUTES
+            )
+            .into());
+        }
+        if let Some(pin_hash) = pin_hash {
+            if pin_hash.salt_base64.trim().is_empty() || pin_hash.key_hash_base64.trim().is_empty()
+            {
+                return Err(LockError::NoPin);
+            }
+            let json = serde_json::to_string(&pin_hash).map_err(|e| e.to_string())?;
+            secret_store::store(SecretKey::AppLockPin, &json)?;
+        }
+        if timeout_minutes > 0 && stored_pin()?.is_none() {
+            return Err(LockError::NoPin);
+        }
+
+        set_setting(AUTO_LOCK_TIMEOUT_SETTING, &timeout_minutes.to_string())?;
+        let timeout = Some(Duration::from_secs(u64::from(timeout_minutes) * 60))
+            .filter(|timeout| !timeout.is_zero());
+        lock.configure(timeout, Instant::now());
+        info!("🔒 Auto-lock timeout set to {} minutes", timeout_minutes);
+        Ok(lock.status())
+    })
+}
+
+/// Unlock with the PIN; the user who was signed in is signed back in if their session is
+/// still stored and unexpired (otherwise the frontend shows the login screen)
+#[tauri::command]
+pub fn unlock_app(
+    pin_or_password: String,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    lock: State<'_, AutoLockState>,
+) -> Result<LockStatus, LockError> {
+    track("unlock_app", || {
+        let pin_hash = stored_pin()?.ok_or(LockError::NoPin)?;
+        let user_id = lock.unlock(&pin_or_password, pin_hash, Instant::now())?;
+        if let Some(user_id) = user_id.filter(|user_id| has_live_session(user_id)) {
+            state.set_current_user(Some(user_id));
+        }
+        info!("🔓 App unlocked");
+        let status = lock.status();
+        let _ = app.emit(APP_UNLOCKED_EVENT, &status);
+        Ok(status)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::{derive_key_from_password, KdfParams};
+    use crate::test_support::{TestApp, TEST_USER};
+
+    const TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+    fn pin_hash(pin: &str) -> PinHash {
+        let params = KdfParams {
+            memory_kib: 8,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let derived = derive_key_from_password(pin.to_string(), None, Some(params))
+            .expect("derive PIN");
+        PinHash {
+            salt_base64: derived.salt_base64,
+            key_hash_base64: derived.key_hash_base64,
+            params: Some(params),
+        }
+    }
+
+    /// TEST_USER signed in with a recent re-authentication, auto-lock on since `start`
+    fn signed_in(start: Instant) -> (TestApp, AutoLockState) {
+        let app = TestApp::new();
+        app.state().set_reauthenticated_at(Some(Instant::now()));
+        let lock = AutoLockState::default();
+        lock.configure(Some(TIMEOUT), start);
+        (app, lock)
+    }
+
+    #[test]
+    fn test_idle_timeout_locks_and_forgets_the_session() {
+        let start = Instant::now();
+        let (app, lock) = signed_in(start);
+        let state = app.state();
+
+        // Activity pushes the lock back
+        lock.heartbeat(start + Duration::from_secs(200));
+        assert_eq!(lock.lock_if_idle(&state, start + TIMEOUT, 1), None);
+        assert_eq!(state.current_user().as_deref(), Some(TEST_USER));
+
+        let at = start + Duration::from_secs(200) + TIMEOUT;
+        let status = lock.lock_if_idle(&state, at, 42).expect("locked");
+        assert!(status.locked);
+        assert_eq!(status.locked_at, Some(42));
+        assert_eq!(status.timeout_minutes, Some(5));
+        assert_eq!(state.current_user(), None);
+        assert_eq!(state.require_user(None), Err(AuthError::NotAuthenticated));
+        assert!(state
+            .require_recent_reauth(Duration::from_secs(3600))
+            .is_err());
+        // Locks once
+        assert_eq!(lock.lock_if_idle(&state, at + TIMEOUT, 43), None);
+
+        // Off: never locks
+        let off = AutoLockState::default();
+        off.configure(None, start);
+        assert_eq!(off.lock_if_idle(&state, start + 100 * TIMEOUT, 1), None);
+    }
+
+    #[test]
+    fn test_commands_are_blocked_while_locked() {
+        let start = Instant::now();
+        let (app, lock) = signed_in(start);
+        assert_eq!(lock.check("db_get_all_clients"), Ok(()));
+        lock.lock_if_idle(&app.state(), start + TIMEOUT, 1).unwrap();
+
+        for command in [
+            "db_get_all_clients",
+            "reveal_sensitive_field",
+            "auth_set_current_user",
+        ] {
+            assert_eq!(
+                lock.check(command),
+                Err(LockError::Locked {
+                    command: command.to_string()
+                })
+            );
+        }
+        for command in LOCKED_COMMANDS {
+            assert_eq!(lock.check(command), Ok(()));
+        }
+        // A heartbeat doesn't unlock
+        lock.heartbeat(start + 2 * TIMEOUT);
+        assert!(lock.status().locked);
+    }
+
+    #[test]
+    fn test_unlock_needs_the_pin_and_returns_the_user() {
+        let start = Instant::now();
+        let (app, lock) = signed_in(start);
+        let pin = pin_hash("2468");
+        assert_eq!(
+            lock.unlock("2468", pin.clone(), start),
+            Err(LockError::NotLocked)
+        );
+        let locked_at = start + TIMEOUT;
+        lock.lock_if_idle(&app.state(), locked_at, 1).unwrap();
+
+        assert_eq!(
+            lock.unlock("1357", pin.clone(), locked_at),
+            Err(LockError::InvalidPin {
+                attempts_left: MAX_PIN_ATTEMPTS - 1
+            })
+        );
+        assert!(lock.check("db_get_all_clients").is_err());
+
+        assert_eq!(
+            lock.unlock("2468", pin.clone(), locked_at),
+            Ok(Some(TEST_USER.to_string()))
+        );
+        assert_eq!(lock.check("db_get_all_clients"), Ok(()));
+        assert!(!lock.status().locked);
+        // The idle clock starts over at the unlock
+        assert_eq!(
+            lock.lock_if_idle(&app.state(), locked_at + TIMEOUT / 2, 2),
+            None
+        );
+
+        // Wrong PINs lock out, even for the right one
+        lock.lock_if_idle(&app.state(), locked_at + TIMEOUT, 3)
+            .unwrap();
+        for _ in 0..MAX_PIN_ATTEMPTS {
+            let _ = lock.unlock("0000", pin.clone(), locked_at + TIMEOUT);
+        }
+        assert!(matches!(
+            lock.unlock("2468", pin, locked_at + TIMEOUT),
+            Err(LockError::TooManyAttempts { .. })
+        ));
+    }
+}
@@ -2,38 +2,17 @@
 // SECURITY: Specific commands for AWS credentials storage only
 // Prevents JS from accessing arbitrary secrets via generic commands
 
-use keyring::Entry;
-use log::{error, info};
+use log::{debug, error, info};
 
-use std::sync::Mutex;
-
-const SERVICE_NAME: &str = "net.universalautobrokers.dealersoftware";
-const AWS_ACCESS_KEY_ID_KEY: &str = "aws_access_key_id";
-const AWS_SECRET_ACCESS_KEY_KEY: &str = "aws_secret_access_key";
-const AWS_REGION_KEY: &str = "aws_region";
-const AWS_BUCKET_NAME_KEY: &str = "aws_bucket_name";
-
-static KEYRING_LOCK: Mutex<()> = Mutex::new(());
+use crate::logging::redact;
+use crate::secret_store::{self, SecretKey};
 
 /// Store AWS access key ID securely in OS keyring
 #[tauri::command]
 pub async fn store_aws_access_key_id(access_key_id: String) -> Result<(), String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🔐 [AWS-CONFIG] Storing AWS access key ID in secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, AWS_ACCESS_KEY_ID_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.delete_credential() {
-        Ok(_) => info!("   Deleted existing entry"),
-        Err(keyring::Error::NoEntry) => info!("   No existing entry to delete"),
-        Err(e) => info!("   Delete error (non-critical): {}", e),
-    }
-
-    std::thread::sleep(std::time::Duration::from_millis(50));
-
-    match entry.set_password(&access_key_id) {
+    match secret_store::store(SecretKey::AwsAccessKeyId, &access_key_id) {
         Ok(_) => {
             info!("✅ [AWS-CONFIG] AWS access key ID stored successfully");
             Ok(())
@@ -48,19 +27,14 @@ pub async fn store_aws_access_key_id(access_key_id: String) -> Result<(), String
 /// Retrieve AWS access key ID from OS keyring
 #[tauri::command]
 pub async fn get_aws_access_key_id() -> Result<Option<String>, String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🔍 [AWS-CONFIG] Retrieving AWS access key ID from secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, AWS_ACCESS_KEY_ID_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.get_password() {
-        Ok(key) => {
-            info!("✅ [AWS-CONFIG] AWS access key ID found");
+    match secret_store::get(SecretKey::AwsAccessKeyId) {
+        Ok(Some(key)) => {
+            debug!("✅ [AWS-CONFIG] AWS access key ID found: {}", redact(&key));
             Ok(Some(key))
         }
-        Err(keyring::Error::NoEntry) => {
+        Ok(None) => {
             info!("⚠️  [AWS-CONFIG] No AWS access key ID found");
             Ok(None)
         }
@@ -74,22 +48,9 @@ pub async fn get_aws_access_key_id() -> Result<Option<String>, String> {
 /// Store AWS secret access key securely in OS keyring
 #[tauri::command]
 pub async fn store_aws_secret_access_key(secret_access_key: String) -> Result<(), String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🔐 [AWS-CONFIG] Storing AWS secret access key in secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, AWS_SECRET_ACCESS_KEY_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.delete_credential() {
-        Ok(_) => info!("   Deleted existing entry"),
-        Err(keyring::Error::NoEntry) => info!("   No existing entry to delete"),
-        Err(e) => info!("   Delete error (non-critical): {}", e),
-    }
-
-    std::thread::sleep(std::time::Duration::from_millis(50));
-
-    match entry.set_password(&secret_access_key) {
+    match secret_store::store(SecretKey::AwsSecretAccessKey, &secret_access_key) {
         Ok(_) => {
             info!("✅ [AWS-CONFIG] AWS secret access key stored successfully");
             Ok(())
@@ -104,19 +65,14 @@ pub async fn store_aws_secret_access_key(secret_access_key: String) -> Result<()
 /// Retrieve AWS secret access key from OS keyring
 #[tauri::command]
 pub async fn get_aws_secret_access_key() -> Result<Option<String>, String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🔍 [AWS-CONFIG] Retrieving AWS secret access key from secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, AWS_SECRET_ACCESS_KEY_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.get_password() {
-        Ok(key) => {
-            info!("✅ [AWS-CONFIG] AWS secret access key found");
+    match secret_store::get(SecretKey::AwsSecretAccessKey) {
+        Ok(Some(key)) => {
+            debug!("✅ [AWS-CONFIG] AWS secret access key found: {}", redact(&key));
             Ok(Some(key))
         }
-        Err(keyring::Error::NoEntry) => {
+        Ok(None) => {
             info!("⚠️  [AWS-CONFIG] No AWS secret access key found");
             Ok(None)
         }
@@ -130,22 +86,9 @@ pub async fn get_aws_secret_access_key() -> Result<Option<String>, String> {
 /// Store AWS region securely in OS keyring
 #[tauri::command]
 pub async fn store_aws_region(region: String) -> Result<(), String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🔐 [AWS-CONFIG] Storing AWS region in secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, AWS_REGION_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.delete_credential() {
-        Ok(_) => info!("   Deleted existing entry"),
-        Err(keyring::Error::NoEntry) => info!("   No existing entry to delete"),
-        Err(e) => info!("   Delete error (non-critical): {}", e),
-    }
-
-    std::thread::sleep(std::time::Duration::from_millis(50));
-
-    match entry.set_password(&region) {
+    match secret_store::store(SecretKey::AwsRegion, &region) {
         Ok(_) => {
             info!("✅ [AWS-CONFIG] AWS region stored successfully");
             Ok(())
@@ -160,19 +103,14 @@ pub async fn store_aws_region(region: String) -> Result<(), String> {
 /// Retrieve AWS region from OS keyring
 #[tauri::command]
 pub async fn get_aws_region() -> Result<Option<String>, String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🔍 [AWS-CONFIG] Retrieving AWS region from secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, AWS_REGION_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.get_password() {
-        Ok(region) => {
-            info!("✅ [AWS-CONFIG] AWS region found");
+    match secret_store::get(SecretKey::AwsRegion) {
+        Ok(Some(region)) => {
+            debug!("✅ [AWS-CONFIG] AWS region found: {}", redact(&region));
             Ok(Some(region))
         }
-        Err(keyring::Error::NoEntry) => {
+        Ok(None) => {
             info!("⚠️  [AWS-CONFIG] No AWS region found");
             Ok(None)
         }
@@ -186,22 +124,9 @@ pub async fn get_aws_region() -> Result<Option<String>, String> {
 /// Store AWS bucket name securely in OS keyring
 #[tauri::command]
 pub async fn store_aws_bucket_name(bucket_name: String) -> Result<(), String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🔐 [AWS-CONFIG] Storing AWS bucket name in secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, AWS_BUCKET_NAME_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.delete_credential() {
-        Ok(_) => info!("   Deleted existing entry"),
-        Err(keyring::Error::NoEntry) => info!("   No existing entry to delete"),
-        Err(e) => info!("   Delete error (non-critical): {}", e),
-    }
-
-    std::thread::sleep(std::time::Duration::from_millis(50));
-
-    match entry.set_password(&bucket_name) {
+    match secret_store::store(SecretKey::AwsBucketName, &bucket_name) {
         Ok(_) => {
             info!("✅ [AWS-CONFIG] AWS bucket name stored successfully");
             Ok(())
@@ -216,19 +141,14 @@ pub async fn store_aws_bucket_name(bucket_name: String) -> Result<(), String> {
 /// Retrieve AWS bucket name from OS keyring
 #[tauri::command]
 pub async fn get_aws_bucket_name() -> Result<Option<String>, String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🔍 [AWS-CONFIG] Retrieving AWS bucket name from secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, AWS_BUCKET_NAME_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.get_password() {
-        Ok(bucket) => {
-            info!("✅ [AWS-CONFIG] AWS bucket name found");
+    match secret_store::get(SecretKey::AwsBucketName) {
+        Ok(Some(bucket)) => {
+            debug!("✅ [AWS-CONFIG] AWS bucket name found: {}", redact(&bucket));
             Ok(Some(bucket))
         }
-        Err(keyring::Error::NoEntry) => {
+        Ok(None) => {
             info!("⚠️  [AWS-CONFIG] No AWS bucket name found");
             Ok(None)
         }
@@ -238,4 +158,3 @@ pub async fn get_aws_bucket_name() -> Result<Option<String>, String> {
         }
     }
 }
-
@@ -0,0 +1,302 @@
+// src-tauri/src/client_duplicates.rs
+//
+// Duplicate client detection: same email, same phone number, or same name at the same ZIP
+// Used by contact imports, and by the client form before saving a new client
+// Saved clients are matched on their normalized columns (contact_normalization.rs)
+
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::contact_normalization::{normalize_email, parse_phone};
+use crate::database::{get_db, Client};
+use crate::telemetry::track;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateReason {
+    Email,
+    Phone,
+    NameAndZip,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DuplicateMatch {
+    pub client_id: String,
+    pub reason: DuplicateReason,
+}
+
+/// Existing clients that look like the same person as `client` (the client itself is ignored)
+#[tauri::command]
+pub fn find_duplicate_clients(
+    client: Client,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DuplicateMatch>, String> {
+    track("find_duplicate_clients", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let detector = DuplicateDetector::for_user(&db.conn(), &user_id_value)?;
+        Ok(detector.find_all(&client))
+    })
+}
+
+/// Index of clients by their identifying keys; add() lets a batch import catch
+/// duplicates within the batch itself
+#[derive(Debug, Default)]
+pub(crate) struct DuplicateDetector {
+    emails: HashMap<String, String>,
+    phones: HashMap<String, String>,
+    names: HashMap<(String, String, String), String>,
+}
+
+impl DuplicateDetector {
+    #[cfg(test)]
+    pub(crate) fn new(clients: &[Client]) -> Self {
+        let mut detector = DuplicateDetector::default();
+        for client in clients {
+            detector.add(client);
+        }
+        detector
+    }
+
+    /// The user's saved clients, newest first, by their phone_e164 and email_normalized
+    pub(crate) fn for_user(conn: &Connection, user_id_value: &str) -> Result<Self, String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, email_normalized, phone_e164, first_name, last_name, zip_code
+                 FROM clients WHERE user_id = ?1 ORDER BY created_at DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let mut rows = stmt
+            .query(params![user_id_value])
+            .map_err(|e| e.to_string())?;
+
+        let mut detector = DuplicateDetector::default();
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let get = |i: usize| row.get::<_, Option<String>>(i).map_err(|e| e.to_string());
+            let (first, last, zip) = (get(3)?.unwrap_or_default(), get(4)?, get(5)?);
+            detector.insert(
+                get(0)?.unwrap_or_default(),
+                get(1)?.filter(|email| email.contains('@')),
+                get(2)?,
+                name_key(&first, &last.unwrap_or_default(), zip.as_deref()),
+            );
+        }
+        Ok(detector)
+    }
+
+    pub(crate) fn add(&mut self, client: &Client) {
+        self.insert(
+            client.id.clone(),
+            client.email.as_deref().and_then(email_key),
+            client.phone.as_deref().and_then(phone_key),
+            client_name_key(client),
+        );
+    }
+
+    fn insert(
+        &mut self,
+        client_id: String,
+        email: Option<String>,
+        phone: Option<String>,
+        name: Option<(String, String, String)>,
+    ) {
+        if let Some(key) = email {
+            self.emails.entry(key).or_insert_with(|| client_id.clone());
+        }
+        if let Some(key) = phone {
+            self.phones.entry(key).or_insert_with(|| client_id.clone());
+        }
+        if let Some(key) = name {
+            self.names.entry(key).or_insert(client_id);
+        }
+    }
+
+    /// Strongest match first (email, then phone, then name + ZIP)
+    pub(crate) fn find(&self, client: &Client) -> Option<DuplicateMatch> {
+        self.find_all(client).into_iter().next()
+    }
+
+    pub(crate) fn find_all(&self, client: &Client) -> Vec<DuplicateMatch> {
+        let candidates = [
+            (
+                DuplicateReason::Email,
+                client
+                    .email
+                    .as_deref()
+                    .and_then(email_key)
+                    .and_then(|key| self.emails.get(&key)),
+            ),
+            (
+                DuplicateReason::Phone,
+                client
+                    .phone
+                    .as_deref()
+                    .and_then(phone_key)
+                    .and_then(|key| self.phones.get(&key)),
+            ),
+            (
+                DuplicateReason::NameAndZip,
+                client_name_key(client).and_then(|key| self.names.get(&key)),
+            ),
+        ];
+
+        let mut matches: Vec<DuplicateMatch> = Vec::new();
+        for (reason, client_id) in candidates {
+            let Some(client_id) = client_id.filter(|id| **id != client.id) else {
+                continue;
+            };
+            if matches.iter().all(|m| m.client_id != *client_id) {
+                matches.push(DuplicateMatch {
+                    client_id: client_id.clone(),
+                    reason,
+                });
+            }
+        }
+        matches
+    }
+}
+
+/// The same key as the client's phone_e164, so "+1 248-555-1212" and "(248) 555 1212"
+/// compare equal
+fn phone_key(raw: &str) -> Option<String> {
+    parse_phone(raw).e164
+}
+
+/// The same key as the client's email_normalized
+fn email_key(raw: &str) -> Option<String> {
+    normalize_email(raw).filter(|email| email.contains('@'))
+}
+
+fn client_name_key(client: &Client) -> Option<(String, String, String)> {
+    name_key(
+        &client.first_name,
+        &client.last_name,
+        client.zip_code.as_deref(),
+    )
+}
+
+fn name_key(first: &str, last: &str, zip: Option<&str>) -> Option<(String, String, String)> {
+    let zip: String = zip?
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(5)
+        .collect();
+    let first = first.trim().to_lowercase();
+    let last = last.trim().to_lowercase();
+    if zip.is_empty() || first.is_empty() || last.is_empty() {
+        return None;
+    }
+    Some((first, last, zip))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::db_create_client;
+    use crate::test_support::{make_client, TestApp, TEST_USER};
+
+    fn client(id: &str, first: &str, last: &str) -> Client {
+        Client {
+            id: id.to_string(),
+            user_id: None,
+            first_name: first.to_string(),
+            last_name: last.to_string(),
+            email: None,
+            phone: None,
+            address: None,
+            city: None,
+            state: None,
+            zip_code: None,
+            drivers_license: None,
+            created_at: 0,
+            updated_at: 0,
+            synced_at: None,
+        }
+    }
+
+    #[test]
+    fn test_matches_by_email_phone_and_name_zip() {
+        let mut ada = client("c1", "Ada", "Lovelace");
+        ada.email = Some("Ada@Example.com".to_string());
+        let mut bob = client("c2", "Bob", "Smith");
+        bob.phone = Some("(248) 555-1212".to_string());
+        let mut cy = client("c3", "Cy", "Young");
+        cy.zip_code = Some("48009-1234".to_string());
+        let detector = DuplicateDetector::new(&[ada.clone(), bob, cy]);
+
+        let mut candidate = client("new", "Someone", "Else");
+        candidate.email = Some(" ada@example.COM ".to_string());
+        candidate.phone = Some("+1 248.555.1212".to_string());
+        assert_eq!(
+            detector.find_all(&candidate),
+            vec![
+                DuplicateMatch {
+                    client_id: "c1".to_string(),
+                    reason: DuplicateReason::Email
+                },
+                DuplicateMatch {
+                    client_id: "c2".to_string(),
+                    reason: DuplicateReason::Phone
+                },
+            ]
+        );
+
+        let mut namesake = client("new", " cy ", "YOUNG");
+        namesake.zip_code = Some("48009".to_string());
+        assert_eq!(
+            detector.find(&namesake).unwrap().reason,
+            DuplicateReason::NameAndZip
+        );
+
+        // Same name without a ZIP, and the client itself, aren't duplicates
+        assert!(detector.find(&client("new", "Cy", "Young")).is_none());
+        assert!(detector.find(&ada).is_none());
+    }
+
+    #[test]
+    fn test_saved_clients_match_on_normalized_columns() {
+        let app = TestApp::new();
+        let mut ada = make_client("c1");
+        ada.email = Some(" Ada@Example.com".to_string());
+        ada.phone = Some("248.555.1212".to_string());
+        db_create_client(ada, None, None, None, app.state(), app.db()).unwrap();
+        // Saved before normalization, then backfilled by the migration
+        app.conn()
+            .execute(
+                "UPDATE clients SET phone = '(313) 555-0100', phone_e164 = '+13135550100'
+                 WHERE id = 'c1'",
+                [],
+            )
+            .unwrap();
+
+        let detector = DuplicateDetector::for_user(&app.conn(), TEST_USER).unwrap();
+        let mut candidate = client("new", "Someone", "Else");
+        candidate.phone = Some("313-555-0100 x12".to_string());
+        assert_eq!(
+            detector.find(&candidate),
+            Some(DuplicateMatch {
+                client_id: "c1".to_string(),
+                reason: DuplicateReason::Phone
+            })
+        );
+        candidate.phone = None;
+        candidate.email = Some("ADA@example.com".to_string());
+        assert_eq!(
+            detector.find(&candidate).unwrap().reason,
+            DuplicateReason::Email
+        );
+
+        let mut namesake = client("new", "Jordan", "Client c1");
+        namesake.zip_code = Some("78701".to_string());
+        assert_eq!(
+            detector.find(&namesake).unwrap().reason,
+            DuplicateReason::NameAndZip
+        );
+        let others = DuplicateDetector::for_user(&app.conn(), "someone-else").unwrap();
+        assert!(others.find(&namesake).is_none());
+    }
+}
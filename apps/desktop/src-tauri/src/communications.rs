@@ -0,0 +1,1055 @@
+// src-tauri/src/communications.rs
+//
+// Emails, texts and calls with a client, and the message templates they're written from
+// Sending happens outside the app (frontend/webhooks): the frontend renders a template with
+// render_message_template, logs the message, sends it, then calls mark_communication_sent
+// with the provider's message id. Calls and inbound messages are logged by hand.
+//
+// Template placeholders look like {{first_name}}; see PLACEHOLDERS for the names. Values are
+// HTML-escaped in email bodies and kept on one line in subjects. A placeholder without a
+// value (no deal given, client has no email...) renders empty and is listed in warnings.
+
+use chrono::{Local, TimeZone, Utc};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{get_db, new_row_id};
+use crate::pdf_forms::format_money;
+use crate::money::to_cents;
+use crate::recent_items::{owned_by, EntityType};
+use crate::telemetry::track;
+
+/// Placeholder names render_message_template fills in
+pub const PLACEHOLDERS: &[&str] = &[
+    "first_name",
+    "last_name",
+    "full_name",
+    "email",
+    "phone",
+    "vehicle",
+    "vin",
+    "stock_number",
+    "deal_status",
+    "sale_date",
+    "total_amount",
+    "balance_due",
+];
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    #[default]
+    Email,
+    Sms,
+    Call,
+}
+
+impl Channel {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Channel::Email => "email",
+            Channel::Sms => "sms",
+            Channel::Call => "call",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "email" => Some(Channel::Email),
+            "sms" => Some(Channel::Sms),
+            "call" => Some(Channel::Call),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Inbound,
+    #[default]
+    Outbound,
+}
+
+impl Direction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Direction::Inbound => "inbound",
+            Direction::Outbound => "outbound",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "inbound" => Some(Direction::Inbound),
+            "outbound" => Some(Direction::Outbound),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommunicationStatus {
+    /// Written but not sent yet
+    #[default]
+    Draft,
+    /// Handed to the sender, waiting for mark_communication_sent
+    Queued,
+    Sent,
+    Failed,
+    /// Recorded after the fact (a call, an inbound text)
+    Logged,
+}
+
+impl CommunicationStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CommunicationStatus::Draft => "draft",
+            CommunicationStatus::Queued => "queued",
+            CommunicationStatus::Sent => "sent",
+            CommunicationStatus::Failed => "failed",
+            CommunicationStatus::Logged => "logged",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "draft" => Some(CommunicationStatus::Draft),
+            "queued" => Some(CommunicationStatus::Queued),
+            "sent" => Some(CommunicationStatus::Sent),
+            "failed" => Some(CommunicationStatus::Failed),
+            "logged" => Some(CommunicationStatus::Logged),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Communication {
+    pub id: String,
+    pub user_id: String,
+    pub client_id: String,
+    pub channel: Channel,
+    pub direction: Direction,
+    pub subject: Option<String>,
+    pub body: Option<String>,
+    pub status: CommunicationStatus,
+    /// The email/SMS provider's id for the sent message
+    pub external_id: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl Communication {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        let channel: String = row.get("channel")?;
+        let direction: String = row.get("direction")?;
+        let status: String = row.get("status")?;
+        Ok(Communication {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            client_id: row.get("client_id")?,
+            channel: Channel::parse(&channel).unwrap_or_default(),
+            direction: Direction::parse(&direction).unwrap_or_default(),
+            subject: row.get("subject")?,
+            body: row.get("body")?,
+            status: CommunicationStatus::parse(&status).unwrap_or_default(),
+            external_id: row.get("external_id")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewCommunication {
+    pub client_id: String,
+    pub channel: Channel,
+    #[serde(default)]
+    pub direction: Direction,
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub status: CommunicationStatus,
+    #[serde(default)]
+    pub external_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageTemplate {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub user_id: String,
+    pub name: String,
+    pub channel: Channel,
+    #[serde(default)]
+    pub subject: Option<String>,
+    pub body: String,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+impl MessageTemplate {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        let channel: String = row.get("channel")?;
+        Ok(MessageTemplate {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            name: row.get("name")?,
+            channel: Channel::parse(&channel).unwrap_or_default(),
+            subject: row.get("subject")?,
+            body: row.get("body")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RenderedMessage {
+    pub channel: Channel,
+    pub subject: Option<String>,
+    pub body: String,
+    /// Placeholders that rendered empty ("{{vehicle}}"), each listed once
+    pub warnings: Vec<String>,
+}
+
+// Communications
+
+fn get_communication(conn: &Connection, user_id: &str, id: &str) -> Result<Communication, String> {
+    conn.query_row(
+        "SELECT * FROM communications WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+        Communication::from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Communication not found or access denied".to_string())
+}
+
+fn create_communication(
+    conn: &Connection,
+    user_id: &str,
+    communication: NewCommunication,
+    now: i64,
+) -> Result<Communication, String> {
+    owned_by(conn, user_id, EntityType::Client, &communication.client_id)?;
+
+    let id = new_row_id();
+    conn.execute(
+        "INSERT INTO communications (
+            id, user_id, client_id, channel, direction, subject, body, status, external_id,
+            created_at, updated_at
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?10)",
+        params![
+            id,
+            user_id,
+            communication.client_id,
+            communication.channel.as_str(),
+            communication.direction.as_str(),
+            communication.subject,
+            communication.body,
+            communication.status.as_str(),
+            communication.external_id,
+            now
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    get_communication(conn, user_id, &id)
+}
+
+fn update_communication(
+    conn: &Connection,
+    user_id: &str,
+    id: &str,
+    updates: &Value,
+    now: i64,
+) -> Result<Communication, String> {
+    let mut communication = get_communication(conn, user_id, id)?;
+
+    if let Some(channel) = updates.get("channel").and_then(|v| v.as_str()) {
+        communication.channel =
+            Channel::parse(channel).ok_or_else(|| format!("Unknown channel '{}'", channel))?;
+    }
+    if let Some(direction) = updates.get("direction").and_then(|v| v.as_str()) {
+        communication.direction = Direction::parse(direction)
+            .ok_or_else(|| format!("Unknown direction '{}'", direction))?;
+    }
+    if let Some(status) = updates.get("status").and_then(|v| v.as_str()) {
+        communication.status = CommunicationStatus::parse(status)
+            .ok_or_else(|| format!("Unknown status '{}'", status))?;
+    }
+    if let Some(subject) = updates.get("subject") {
+        communication.subject = subject.as_str().map(str::to_string);
+    }
+    if let Some(body) = updates.get("body") {
+        communication.body = body.as_str().map(str::to_string);
+    }
+    if let Some(external_id) = updates.get("external_id") {
+        communication.external_id = external_id.as_str().map(str::to_string);
+    }
+    communication.updated_at = now;
+
+    conn.execute(
+        "UPDATE communications SET channel = ?3, direction = ?4, subject = ?5, body = ?6,
+                                   status = ?7, external_id = ?8, updated_at = ?9
+         WHERE id = ?1 AND user_id = ?2",
+        params![
+            id,
+            user_id,
+            communication.channel.as_str(),
+            communication.direction.as_str(),
+            communication.subject,
+            communication.body,
+            communication.status.as_str(),
+            communication.external_id,
+            communication.updated_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(communication)
+}
+
+pub(crate) fn communications_by_client(
+    conn: &Connection,
+    user_id: &str,
+    client_id: &str,
+) -> Result<Vec<Communication>, String> {
+    owned_by(conn, user_id, EntityType::Client, client_id)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT * FROM communications WHERE client_id = ?1 AND user_id = ?2
+             ORDER BY created_at DESC",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![client_id, user_id], Communication::from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<SqlResult<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(rows)
+}
+
+// Message templates
+
+fn get_message_template(
+    conn: &Connection,
+    user_id: &str,
+    id: &str,
+) -> Result<MessageTemplate, String> {
+    conn.query_row(
+        "SELECT * FROM message_templates WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+        MessageTemplate::from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Message template not found or access denied".to_string())
+}
+
+/// Insert the template, or update it when template.id is one of the user's templates
+fn save_message_template(
+    conn: &Connection,
+    user_id: &str,
+    template: MessageTemplate,
+    now: i64,
+) -> Result<(MessageTemplate, Operation), String> {
+    if template.name.trim().is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    if template.channel == Channel::Call {
+        return Err("Message templates are for email or sms".to_string());
+    }
+
+    let existing = match template.id.as_str() {
+        "" => None,
+        id => Some(get_message_template(conn, user_id, id)?),
+    };
+    let (id, created_at, operation) = match existing {
+        Some(existing) => (existing.id, existing.created_at, Operation::Update),
+        None => (new_row_id(), now, Operation::Create),
+    };
+
+    conn.execute(
+        "INSERT INTO message_templates (id, user_id, name, channel, subject, body, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+         ON CONFLICT(id) DO UPDATE SET name = ?3, channel = ?4, subject = ?5, body = ?6,
+                                       updated_at = ?8",
+        params![
+            id,
+            user_id,
+            template.name.trim(),
+            template.channel.as_str(),
+            template.subject,
+            template.body,
+            created_at,
+            now
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok((get_message_template(conn, user_id, &id)?, operation))
+}
+
+// Rendering
+
+/// Placeholder values for a client and, optionally, one of their deals
+/// None means the placeholder is known but has no value here
+fn placeholder_values(
+    conn: &Connection,
+    user_id: &str,
+    client_id: &str,
+    deal_id: Option<&str>,
+) -> Result<HashMap<&'static str, Option<String>>, String> {
+    let (first_name, last_name, email, phone) = conn
+        .query_row(
+            "SELECT first_name, last_name, email, phone FROM clients
+             WHERE id = ?1 AND user_id = ?2",
+            params![client_id, user_id],
+            |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Client not found or access denied".to_string())?;
+
+    let present = |value: String| Some(value.trim().to_string()).filter(|v| !v.is_empty());
+    let full_name = format!("{} {}", first_name.trim(), last_name.trim());
+    let mut values: HashMap<&'static str, Option<String>> =
+        PLACEHOLDERS.iter().map(|name| (*name, None)).collect();
+    values.insert("first_name", present(first_name));
+    values.insert("last_name", present(last_name));
+    values.insert("full_name", present(full_name));
+    values.insert("email", email.and_then(present));
+    values.insert("phone", phone.and_then(present));
+
+    let Some(deal_id) = deal_id else {
+        return Ok(values);
+    };
+    let deal = conn
+        .query_row(
+            "SELECT d.client_id, d.status, d.sale_date, d.total_amount, d.down_payment,
+                    d.financed_amount, v.year, v.make, v.model, v.trim, v.vin, v.stock_number
+             FROM deals d LEFT JOIN vehicles v ON v.id = d.vehicle_id
+             WHERE d.id = ?1 AND d.user_id = ?2",
+            params![deal_id, user_id],
+            |row| {
+                Ok(DealRow {
+                    client_id: row.get(0)?,
+                    status: row.get(1)?,
+                    sale_date: row.get(2)?,
+                    total_amount: row.get(3)?,
+                    down_payment: row.get(4)?,
+                    financed_amount: row.get(5)?,
+                    year: row.get(6)?,
+                    make: row.get(7)?,
+                    model: row.get(8)?,
+                    trim: row.get(9)?,
+                    vin: row.get(10)?,
+                    stock_number: row.get(11)?,
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Deal not found or access denied".to_string())?;
+    if deal.client_id != client_id {
+        return Err("That deal belongs to a different client".to_string());
+    }
+
+    let vehicle = [
+        deal.year.map(|year| year.to_string()),
+        deal.make,
+        deal.model,
+        deal.trim,
+    ]
+    .into_iter()
+    .flatten()
+    .filter(|part| !part.trim().is_empty())
+    .collect::<Vec<_>>()
+    .join(" ");
+    let total_cents = to_cents(deal.total_amount);
+    let balance_cents = (total_cents
+        - deal.down_payment.map_or(0, to_cents)
+        - deal.financed_amount.map_or(0, to_cents))
+    .max(0);
+
+    values.insert("vehicle", present(vehicle));
+    values.insert("vin", deal.vin.and_then(present));
+    values.insert("stock_number", deal.stock_number.and_then(present));
+    values.insert("deal_status", present(deal.status));
+    values.insert(
+        "sale_date",
+        deal.sale_date
+            .and_then(|ms| Local.timestamp_millis_opt(ms).single())
+            .map(|date| date.format("%m/%d/%Y").to_string()),
+    );
+    values.insert(
+        "total_amount",
+        Some(format!("${}", format_money(total_cents))),
+    );
+    values.insert(
+        "balance_due",
+        Some(format!("${}", format_money(balance_cents))),
+    );
+    Ok(values)
+}
+
+struct DealRow {
+    client_id: String,
+    status: String,
+    sale_date: Option<i64>,
+    total_amount: f64,
+    down_payment: Option<f64>,
+    financed_amount: Option<f64>,
+    year: Option<i64>,
+    make: Option<String>,
+    model: Option<String>,
+    trim: Option<String>,
+    vin: Option<String>,
+    stock_number: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Escape {
+    /// SMS text: values go in as they are
+    None,
+    /// Email body: values can't add markup
+    Html,
+    /// Subject line: values can't add lines (header injection)
+    Line,
+}
+
+fn escape(value: &str, escape: Escape) -> String {
+    match escape {
+        Escape::None => value.to_string(),
+        Escape::Html => {
+            let mut out = String::with_capacity(value.len());
+            for c in value.chars() {
+                match c {
+                    '&' => out.push_str("&amp;"),
+                    '<' => out.push_str("&lt;"),
+                    '>' => out.push_str("&gt;"),
+                    '"' => out.push_str("&quot;"),
+                    '\'' => out.push_str("&#39;"),
+                    c => out.push(c),
+                }
+            }
+            out
+        }
+        Escape::Line => value.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+/// Replace every {{name}} in one pass (values are never re-scanned for placeholders);
+/// an unclosed "{{" is literal text. Placeholders without a value render empty and are
+/// added to warnings.
+fn render(
+    text: &str,
+    values: &HashMap<&'static str, Option<String>>,
+    mode: Escape,
+    warnings: &mut Vec<String>,
+) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some((start, len)) = rest
+        .find("{{")
+        .and_then(|start| Some((start, rest[start..].find("}}")?)))
+    {
+        out.push_str(&rest[..start]);
+        let name = rest[start + 2..start + len].trim();
+        match values.get(name).and_then(|value| value.as_deref()) {
+            Some(value) => out.push_str(&escape(value, mode)),
+            None => {
+                let placeholder = format!("{{{{{}}}}}", name);
+                if !warnings.contains(&placeholder) {
+                    warnings.push(placeholder);
+                }
+            }
+        }
+        rest = &rest[start + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn render_message(
+    conn: &Connection,
+    user_id: &str,
+    template_id: &str,
+    client_id: &str,
+    deal_id: Option<&str>,
+) -> Result<RenderedMessage, String> {
+    let template = get_message_template(conn, user_id, template_id)?;
+    let values = placeholder_values(conn, user_id, client_id, deal_id)?;
+
+    let mut warnings = Vec::new();
+    let subject = template
+        .subject
+        .as_deref()
+        .map(|subject| render(subject, &values, Escape::Line, &mut warnings));
+    let body_escape = match template.channel {
+        Channel::Email => Escape::Html,
+        Channel::Sms | Channel::Call => Escape::None,
+    };
+    let body = render(&template.body, &values, body_escape, &mut warnings);
+    Ok(RenderedMessage {
+        channel: template.channel,
+        subject,
+        body,
+        warnings,
+    })
+}
+
+// Commands
+
+#[tauri::command]
+pub fn db_create_communication(
+    communication: NewCommunication,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Communication, String> {
+    track("db_create_communication", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let communication = create_communication(
+            &conn,
+            &user_id_value,
+            communication,
+            Utc::now().timestamp_millis(),
+        )?;
+        data_changed(
+            &user_id_value,
+            ChangedEntity::Communication,
+            &communication.id,
+            Operation::Create,
+        );
+        Ok(communication)
+    })
+}
+
+#[tauri::command]
+pub fn db_get_communication(
+    id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<Communication>, String> {
+    track("db_get_communication", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        conn.query_row(
+            "SELECT * FROM communications WHERE id = ?1 AND user_id = ?2",
+            params![id, user_id_value],
+            Communication::from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())
+    })
+}
+
+/// A client's communications, newest first
+#[tauri::command]
+pub fn db_get_communications_by_client(
+    client_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Communication>, String> {
+    track("db_get_communications_by_client", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        communications_by_client(&conn, &user_id_value, &client_id)
+    })
+}
+
+/// Change channel, direction, subject, body, status or external_id
+#[tauri::command]
+pub fn db_update_communication(
+    id: String,
+    updates: Value,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Communication, String> {
+    track("db_update_communication", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let communication = update_communication(
+            &conn,
+            &user_id_value,
+            &id,
+            &updates,
+            Utc::now().timestamp_millis(),
+        )?;
+        data_changed(
+            &user_id_value,
+            ChangedEntity::Communication,
+            &id,
+            Operation::Update,
+        );
+        Ok(communication)
+    })
+}
+
+#[tauri::command]
+pub fn db_delete_communication(
+    id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    track("db_delete_communication", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let deleted = conn
+            .execute(
+                "DELETE FROM communications WHERE id = ?1 AND user_id = ?2",
+                params![id, user_id_value],
+            )
+            .map_err(|e| e.to_string())?;
+        if deleted == 0 {
+            return Err("Communication not found or access denied".to_string());
+        }
+        data_changed(
+            &user_id_value,
+            ChangedEntity::Communication,
+            &id,
+            Operation::Delete,
+        );
+        Ok(())
+    })
+}
+
+/// Record that the sender delivered a message, with the provider's id for it
+#[tauri::command]
+pub fn mark_communication_sent(
+    id: String,
+    external_id: Option<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Communication, String> {
+    track("mark_communication_sent", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let communication = update_communication(
+            &conn,
+            &user_id_value,
+            &id,
+            &serde_json::json!({
+                "status": CommunicationStatus::Sent.as_str(),
+                "external_id": external_id,
+            }),
+            Utc::now().timestamp_millis(),
+        )?;
+        info!("✉️  Communication {} sent", id);
+        data_changed(
+            &user_id_value,
+            ChangedEntity::Communication,
+            &id,
+            Operation::Update,
+        );
+        Ok(communication)
+    })
+}
+
+/// Create a message template, or update it when template.id exists
+#[tauri::command]
+pub fn db_save_message_template(
+    template: MessageTemplate,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<MessageTemplate, String> {
+    track("db_save_message_template", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let (template, operation) = save_message_template(
+            &conn,
+            &user_id_value,
+            template,
+            Utc::now().timestamp_millis(),
+        )?;
+        data_changed(
+            &user_id_value,
+            ChangedEntity::MessageTemplate,
+            &template.id,
+            operation,
+        );
+        Ok(template)
+    })
+}
+
+#[tauri::command]
+pub fn db_get_message_templates(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<MessageTemplate>, String> {
+    track("db_get_message_templates", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT * FROM message_templates WHERE user_id = ?1 ORDER BY name COLLATE NOCASE",
+            )
+            .map_err(|e| e.to_string())?;
+        let templates = stmt
+            .query_map(params![user_id_value], MessageTemplate::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(templates)
+    })
+}
+
+#[tauri::command]
+pub fn db_delete_message_template(
+    id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    track("db_delete_message_template", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let deleted = conn
+            .execute(
+                "DELETE FROM message_templates WHERE id = ?1 AND user_id = ?2",
+                params![id, user_id_value],
+            )
+            .map_err(|e| e.to_string())?;
+        if deleted == 0 {
+            return Err("Message template not found or access denied".to_string());
+        }
+        data_changed(
+            &user_id_value,
+            ChangedEntity::MessageTemplate,
+            &id,
+            Operation::Delete,
+        );
+        Ok(())
+    })
+}
+
+/// Fill a message template for a client (and optionally one of their deals)
+#[tauri::command]
+pub fn render_message_template(
+    template_id: String,
+    client_id: String,
+    deal_id: Option<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<RenderedMessage, String> {
+    track("render_message_template", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        render_message(
+            &conn,
+            &user_id_value,
+            &template_id,
+            &client_id,
+            deal_id.as_deref(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/021_add_communications.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, email, created_at, updated_at, user_id)
+             VALUES ('c1', 'Ada', 'Lovelace', NULL, 1, 1, 'u1'),
+                    ('c2', 'Bob', 'O''Brien <b>', 'bob@example.com', 1, 1, 'u1'),
+                    ('c3', 'Eve', 'Other', NULL, 1, 1, 'u2');
+             INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status,
+                                   created_at, updated_at, user_id)
+             VALUES ('v1', '1HGCM82633A004352', 2019, 'Honda', 'Civic', 42000, 18500, 'sold',
+                     1, 1, 'u1');
+             INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount,
+                                down_payment, financed_amount, document_ids,
+                                created_at, updated_at, user_id)
+             VALUES ('d1', 'retail', 'c1', 'v1', 'pending', 20000.50, 2000, 15000, '[]',
+                     1, 1, 'u1');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn template(conn: &Connection, channel: Channel, subject: Option<&str>, body: &str) -> String {
+        let template = MessageTemplate {
+            id: String::new(),
+            user_id: String::new(),
+            name: "Appointment".to_string(),
+            channel,
+            subject: subject.map(str::to_string),
+            body: body.to_string(),
+            created_at: 0,
+            updated_at: 0,
+        };
+        save_message_template(conn, "u1", template, 1).unwrap().0.id
+    }
+
+    #[test]
+    fn test_placeholders_resolve_from_client_and_deal() {
+        let conn = test_db();
+        let id = template(
+            &conn,
+            Channel::Sms,
+            None,
+            "Hi {{first_name}}, your {{ vehicle }} is ready. Balance due: {{balance_due}} \
+             (of {{total_amount}}).",
+        );
+        let rendered = render_message(&conn, "u1", &id, "c1", Some("d1")).unwrap();
+        assert_eq!(
+            rendered.body,
+            "Hi Ada, your 2019 Honda Civic is ready. Balance due: $3,000.50 (of $20,000.50)."
+        );
+        assert!(rendered.warnings.is_empty(), "{:?}", rendered.warnings);
+        assert_eq!(rendered.subject, None);
+    }
+
+    #[test]
+    fn test_missing_entities_and_values_become_warnings() {
+        let conn = test_db();
+        let id = template(
+            &conn,
+            Channel::Sms,
+            None,
+            "{{first_name}}: {{vehicle}} {{email}} {{vehicle}} {{no_such_field}} {{unclosed",
+        );
+
+        // No deal: deal placeholders are unresolved, each listed once; c1 has no email
+        let rendered = render_message(&conn, "u1", &id, "c1", None).unwrap();
+        assert_eq!(rendered.body, "Ada:     {{unclosed");
+        assert_eq!(
+            rendered.warnings,
+            ["{{vehicle}}", "{{email}}", "{{no_such_field}}"]
+        );
+
+        // Records that don't exist or belong to someone else are errors, not warnings
+        let err = render_message(&conn, "u1", &id, "c3", None).unwrap_err();
+        assert!(err.contains("Client not found"), "{}", err);
+        let err = render_message(&conn, "u1", &id, "c1", Some("nope")).unwrap_err();
+        assert!(err.contains("Deal not found"), "{}", err);
+        let err = render_message(&conn, "u1", &id, "c2", Some("d1")).unwrap_err();
+        assert!(err.contains("different client"), "{}", err);
+        let err = render_message(&conn, "u2", &id, "c3", None).unwrap_err();
+        assert!(err.contains("template not found"), "{}", err);
+    }
+
+    #[test]
+    fn test_values_are_escaped_for_the_channel() {
+        let conn = test_db();
+        conn.execute(
+            "UPDATE clients SET first_name = 'Bob
+Bcc: x@evil.test' WHERE id = 'c2'",
+            [],
+        )
+        .unwrap();
+        let id = template(
+            &conn,
+            Channel::Email,
+            Some("Hello {{first_name}}"),
+            "<p>Dear {{full_name}}, {{ {{last_name}} }}</p>",
+        );
+        let rendered = render_message(&conn, "u1", &id, "c2", None).unwrap();
+        assert_eq!(
+            rendered.subject.as_deref(),
+            Some("Hello Bob Bcc: x@evil.test")
+        );
+        // Template markup stays; values can't add any, and aren't re-scanned
+        assert!(rendered
+            .body
+            .starts_with("<p>Dear Bob\nBcc: x@evil.test O&#39;Brien &lt;b&gt;, "));
+        assert_eq!(rendered.warnings, ["{{{{last_name}}"]);
+
+        let sms = template(&conn, Channel::Sms, None, "{{last_name}}");
+        let rendered = render_message(&conn, "u1", &sms, "c2", None).unwrap();
+        assert_eq!(rendered.body, "O'Brien <b>");
+    }
+
+    #[test]
+    fn test_communication_log_and_mark_sent() {
+        let conn = test_db();
+        let new = |client_id: &str| NewCommunication {
+            client_id: client_id.to_string(),
+            channel: Channel::Sms,
+            direction: Direction::Outbound,
+            subject: None,
+            body: Some("See you at 3".to_string()),
+            status: CommunicationStatus::Queued,
+            external_id: None,
+        };
+        assert!(create_communication(&conn, "u1", new("c3"), 1).is_err());
+
+        let first = create_communication(&conn, "u1", new("c1"), 1).unwrap();
+        let second = create_communication(&conn, "u1", new("c1"), 2).unwrap();
+        create_communication(&conn, "u1", new("c2"), 3).unwrap();
+        let ids: Vec<String> = communications_by_client(&conn, "u1", "c1")
+            .unwrap()
+            .into_iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(ids, [second.id.clone(), first.id.clone()]);
+        assert!(communications_by_client(&conn, "u2", "c1").is_err());
+
+        let sent = update_communication(
+            &conn,
+            "u1",
+            &first.id,
+            &json!({ "status": "sent", "external_id": "SM123" }),
+            5,
+        )
+        .unwrap();
+        assert_eq!(sent.status, CommunicationStatus::Sent);
+        assert_eq!(sent.external_id.as_deref(), Some("SM123"));
+        assert_eq!(get_communication(&conn, "u1", &first.id).unwrap(), sent);
+        assert!(
+            update_communication(&conn, "u1", &first.id, &json!({ "status": "lost" }), 6).is_err()
+        );
+        assert!(update_communication(&conn, "u2", &first.id, &json!({}), 6).is_err());
+
+        // The log goes with the client
+        conn.execute("DELETE FROM clients WHERE id = 'c2'", [])
+            .unwrap();
+        let left: i64 = conn
+            .query_row("SELECT COUNT(*) FROM communications", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(left, 2);
+    }
+}
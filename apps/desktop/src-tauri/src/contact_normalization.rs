@@ -0,0 +1,383 @@
+// src-tauri/src/contact_normalization.rs
+//
+// One normal form for client phone numbers and emails, so "(248) 555-1212", "248.555.1212"
+// and "2485551212" are the same number to search and duplicate detection. Phones are kept
+// twice: clients.phone_e164 holds the digits ("+12485551212", without any extension) and
+// clients.phone the display form ("(248) 555-1212 x204"). A number that can't be
+// normalized (too short, letters) is stored as entered with phone_invalid = 1. Emails
+// are trimmed, and clients.email_normalized is the lowercase copy.
+// Migration 36 backfilled the new columns from existing rows without changing phone/email.
+
+use rusqlite::{params, Connection, Result as SqlResult};
+
+use crate::database::Client;
+
+/// Shortest and longest E.164 numbers, country code included
+const MIN_E164_DIGITS: usize = 8;
+const MAX_E164_DIGITS: usize = 15;
+
+/// Longest extension recognised after "x", "ext", "#"...
+const MAX_EXTENSION_DIGITS: usize = 6;
+
+/// Fewest digits a search has to contain to be matched against phone numbers
+const MIN_SEARCH_DIGITS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NormalizedPhone {
+    /// "+12485551212"; None when the input couldn't be normalized
+    pub e164: Option<String>,
+    /// What's stored in clients.phone: "(248) 555-1212 x204", or the input as entered
+    pub display: String,
+}
+
+/// Values of the normalized columns for one client
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct ContactColumns {
+    pub phone_e164: Option<String>,
+    pub phone_invalid: bool,
+    pub email_normalized: Option<String>,
+}
+
+/// US and Canadian numbers with or without the leading 1, "+" and "011" international
+/// numbers, and a trailing extension ("x204", "ext. 204", "extension 204", "#204",
+/// ";ext=204"). International numbers keep the spacing they were entered with
+pub(crate) fn parse_phone(raw: &str) -> NormalizedPhone {
+    let entered = raw.trim();
+    let number = strip_prefix_ignore_case(entered, "tel:").unwrap_or(entered);
+    let (number, extension) = split_extension(number);
+    let as_entered = NormalizedPhone {
+        e164: None,
+        display: entered.to_string(),
+    };
+
+    let number = number.trim();
+    let plus = number.starts_with('+');
+    let body = number.strip_prefix('+').unwrap_or(number);
+    if body.is_empty()
+        || !body
+            .chars()
+            .all(|c| c.is_ascii_digit() || " ()-./".contains(c))
+    {
+        return as_entered;
+    }
+    let digits: String = body.chars().filter(char::is_ascii_digit).collect();
+
+    let national = match digits.len() {
+        10 if !plus => Some(digits.as_str()),
+        11 if digits.starts_with('1') => Some(&digits[1..]),
+        _ => None,
+    };
+    let (e164, display) = match national {
+        Some(n) => (
+            format!("+1{}", n),
+            format!("({}) {}-{}", &n[..3], &n[3..6], &n[6..]),
+        ),
+        None => {
+            let international = if plus {
+                digits.as_str()
+            } else if let Some(rest) = digits.strip_prefix("011") {
+                rest
+            } else {
+                return as_entered;
+            };
+            if !(MIN_E164_DIGITS..=MAX_E164_DIGITS).contains(&international.len()) {
+                return as_entered;
+            }
+            (format!("+{}", international), number.to_string())
+        }
+    };
+
+    NormalizedPhone {
+        e164: Some(e164),
+        display: match extension {
+            Some(extension) => format!("{} x{}", display, extension),
+            None => display,
+        },
+    }
+}
+
+/// Display form of a usable number, None if it can't be normalized
+pub(crate) fn normalize_phone(raw: &str) -> Option<String> {
+    let phone = parse_phone(raw);
+    phone.e164.map(|_| phone.display)
+}
+
+/// Trimmed and lowercased; None when blank
+pub(crate) fn normalize_email(raw: &str) -> Option<String> {
+    let email = raw.trim();
+    (!email.is_empty()).then(|| email.to_lowercase())
+}
+
+/// Put the client's phone in display form and trim its email, returning the values for
+/// the normalized columns. Run after enforce_rules, which checks what was entered
+pub(crate) fn normalize_contact(client: &mut Client) -> ContactColumns {
+    client.email = client
+        .email
+        .as_deref()
+        .map(str::trim)
+        .filter(|email| !email.is_empty())
+        .map(str::to_string);
+    let phone = client
+        .phone
+        .as_deref()
+        .map(str::trim)
+        .filter(|phone| !phone.is_empty())
+        .map(parse_phone);
+    client.phone = phone.as_ref().map(|phone| phone.display.clone());
+
+    ContactColumns {
+        phone_invalid: phone.as_ref().is_some_and(|phone| phone.e164.is_none()),
+        phone_e164: phone.and_then(|phone| phone.e164),
+        email_normalized: client.email.as_deref().and_then(normalize_email),
+    }
+}
+
+/// The digits of a search that looks like (part of) a phone number
+pub(crate) fn phone_search_digits(query: &str) -> Option<String> {
+    let query = query.trim();
+    let digits: String = query.chars().filter(char::is_ascii_digit).collect();
+    let phone_like = query
+        .chars()
+        .all(|c| c.is_ascii_digit() || "()-. +".contains(c));
+    (phone_like && digits.len() >= MIN_SEARCH_DIGITS).then_some(digits)
+}
+
+/// Fill phone_e164, phone_invalid and email_normalized for every client (migration 36).
+/// phone and email are left as they are
+pub(crate) fn backfill_contacts(conn: &Connection) -> SqlResult<usize> {
+    let mut select = conn.prepare("SELECT id, phone, email FROM clients")?;
+    let rows = select
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?
+        .collect::<SqlResult<Vec<_>>>()?;
+
+    let mut update = conn.prepare(
+        "UPDATE clients SET phone_e164 = ?2, phone_invalid = ?3, email_normalized = ?4
+         WHERE id = ?1",
+    )?;
+    for (id, phone, email) in &rows {
+        let phone = phone
+            .as_deref()
+            .map(str::trim)
+            .filter(|phone| !phone.is_empty())
+            .map(parse_phone);
+        update.execute(params![
+            id,
+            phone.as_ref().and_then(|phone| phone.e164.as_deref()),
+            phone.as_ref().is_some_and(|phone| phone.e164.is_none()),
+            email.as_deref().and_then(normalize_email),
+        ])?;
+    }
+    Ok(rows.len())
+}
+
+/// The number without a trailing extension, and the extension's digits
+fn split_extension(number: &str) -> (&str, Option<&str>) {
+    let digits_start = number.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let extension = &number[digits_start..];
+    if extension.is_empty() || extension.len() > MAX_EXTENSION_DIGITS {
+        return (number, None);
+    }
+
+    let before =
+        number[..digits_start].trim_end_matches(|c: char| c.is_whitespace() || ".:=".contains(c));
+    let lower = before.to_ascii_lowercase();
+    for marker in ["extension", "ext", "x", "#"] {
+        if lower.ends_with(marker) {
+            let rest = before[..before.len() - marker.len()]
+                .trim_end_matches(|c: char| c.is_whitespace() || ",;".contains(c));
+            return (rest, Some(extension));
+        }
+    }
+    (number, None)
+}
+
+fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> Option<&'a str> {
+    value
+        .get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &value[prefix.len()..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+
+    fn parsed(raw: &str) -> (Option<String>, String) {
+        let phone = parse_phone(raw);
+        (phone.e164, phone.display)
+    }
+
+    #[test]
+    fn test_phone_formats() {
+        let us = |display: &str| (Some("+12485551212".to_string()), display.to_string());
+        for raw in [
+            "(248) 555-1212",
+            "248.555.1212",
+            "2485551212",
+            "248-555-1212",
+            "248 555 1212",
+            "  (248)555-1212 ",
+            "1-248-555-1212",
+            "12485551212",
+            "+1 (248) 555-1212",
+            "+12485551212",
+            "tel:+1-248-555-1212",
+            "TEL:248.555.1212",
+        ] {
+            assert_eq!(parsed(raw), us("(248) 555-1212"), "{:?}", raw);
+        }
+
+        for raw in [
+            "248-555-1212 x204",
+            "248-555-1212x204",
+            "(248) 555-1212 X 204",
+            "248.555.1212 ext. 204",
+            "248.555.1212 Ext 204",
+            "2485551212 extension 204",
+            "248-555-1212 #204",
+            "248-555-1212, x204",
+            "tel:+1-248-555-1212;ext=204",
+        ] {
+            assert_eq!(parsed(raw), us("(248) 555-1212 x204"), "{:?}", raw);
+        }
+
+        assert_eq!(
+            parsed("+44 20 7946 0958"),
+            (
+                Some("+442079460958".to_string()),
+                "+44 20 7946 0958".to_string()
+            )
+        );
+        assert_eq!(
+            parsed("011 44 20 7946 0958 x12"),
+            (
+                Some("+442079460958".to_string()),
+                "011 44 20 7946 0958 x12".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_normalize_phone() {
+        assert_eq!(
+            normalize_phone("248.555.1212").as_deref(),
+            Some("(248) 555-1212")
+        );
+        assert_eq!(
+            normalize_phone("+1 (248) 555-1212").as_deref(),
+            Some("(248) 555-1212")
+        );
+        assert_eq!(
+            normalize_phone("12485551212").as_deref(),
+            Some("(248) 555-1212")
+        );
+        assert_eq!(
+            normalize_phone("+44 20 7946 0958").as_deref(),
+            Some("+44 20 7946 0958")
+        );
+        assert_eq!(normalize_phone("555-12"), None);
+        assert_eq!(normalize_phone("n/a"), None);
+    }
+
+    #[test]
+    fn test_unusable_phones_are_kept_as_entered() {
+        for raw in [
+            "555-12",
+            "555-1212",
+            "n/a",
+            "1-800-FLOWERS",
+            "248-555-1212 (cell)",
+            "+1 248 555",
+            "+123",
+            "248-555-12120",
+            "x204",
+        ] {
+            assert_eq!(parsed(raw), (None, raw.to_string()), "{:?}", raw);
+            assert_eq!(normalize_phone(raw), None, "{:?}", raw);
+        }
+        assert_eq!(parsed("  555-12 "), (None, "555-12".to_string()));
+    }
+
+    #[test]
+    fn test_normalize_contact_and_search_digits() {
+        let mut client = crate::test_support::make_client("c1");
+        client.email = Some("  Ada.Lovelace@Example.COM ".to_string());
+        client.phone = Some("248.555.1212 ext 7".to_string());
+        let columns = normalize_contact(&mut client);
+        assert_eq!(client.email.as_deref(), Some("Ada.Lovelace@Example.COM"));
+        assert_eq!(client.phone.as_deref(), Some("(248) 555-1212 x7"));
+        assert_eq!(
+            columns,
+            ContactColumns {
+                phone_e164: Some("+12485551212".to_string()),
+                phone_invalid: false,
+                email_normalized: Some("ada.lovelace@example.com".to_string()),
+            }
+        );
+
+        client.email = Some("   ".to_string());
+        client.phone = Some("call the shop".to_string());
+        let columns = normalize_contact(&mut client);
+        assert_eq!(client.email, None);
+        assert_eq!(client.phone.as_deref(), Some("call the shop"));
+        assert!(columns.phone_invalid);
+        assert_eq!((columns.phone_e164, columns.email_normalized), (None, None));
+
+        assert_eq!(phone_search_digits("(248) 555").as_deref(), Some("248555"));
+        assert_eq!(phone_search_digits("555-1212").as_deref(), Some("5551212"));
+        assert_eq!(phone_search_digits("248"), None);
+        assert_eq!(phone_search_digits("Suite 2400"), None);
+    }
+
+    #[test]
+    fn test_backfill_leaves_originals_alone() {
+        let db = Database::init_in_memory().unwrap();
+        let conn = db.conn();
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, email, phone, created_at, updated_at)
+             VALUES ('c1', 'Ada', 'Lovelace', ' ADA@Example.com', '248.555.1212', 1, 1),
+                    ('c2', 'Bob', 'Smith', NULL, '555-12', 1, 1),
+                    ('c3', 'Cy', 'Young', NULL, NULL, 1, 1);",
+        )
+        .unwrap();
+        assert_eq!(backfill_contacts(&conn).unwrap(), 3);
+
+        let row = |id: &str| {
+            conn.query_row(
+                "SELECT phone, email, phone_e164, phone_invalid, email_normalized
+                 FROM clients WHERE id = ?1",
+                [id],
+                |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                        row.get::<_, bool>(3)?,
+                        row.get::<_, Option<String>>(4)?,
+                    ))
+                },
+            )
+            .unwrap()
+        };
+        let owned = |value: &str| Some(value.to_string());
+        assert_eq!(
+            row("c1"),
+            (
+                owned("248.555.1212"),
+                owned(" ADA@Example.com"),
+                owned("+12485551212"),
+                false,
+                owned("ada@example.com")
+            )
+        );
+        assert_eq!(row("c2"), (owned("555-12"), None, None, true, None));
+        assert_eq!(row("c3"), (None, None, None, false, None));
+    }
+}
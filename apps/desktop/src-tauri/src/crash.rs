@@ -0,0 +1,301 @@
+// src-tauri/src/crash.rs
+//
+// Panic reports for support: the panic hook writes one JSON file per panic to
+// logs/crash-reports with the message, backtrace, version, platform and the tail of the log.
+// The hook only touches the filesystem (never the database, whose mutex may be the thing
+// that's poisoned) and reads a bounded slice of the log file.
+
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::backtrace::Backtrace;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use crate::logging::{self, LOG_FILE_NAME};
+
+pub const CRASH_DIR_NAME: &str = "crash-reports";
+
+/// Log lines included with each report
+const CRASH_LOG_LINES: usize = 200;
+/// Only this much of the end of the log file is read, however large it is
+const CRASH_LOG_TAIL_BYTES: u64 = 64 * 1024;
+const MAX_MESSAGE_CHARS: usize = 4096;
+
+/// Set while a report is being written, so a panic inside the hook can't recurse
+static WRITING_REPORT: AtomicBool = AtomicBool::new(false);
+/// Keeps ids unique when several threads panic in the same millisecond
+static REPORT_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CrashReport {
+    pub id: String,
+    pub created_at: String, // RFC 3339
+    pub message: String,
+    pub location: Option<String>,
+    pub thread: Option<String>,
+    pub app_version: String,
+    pub platform: String,
+    pub backtrace: String,
+    pub log_lines: Vec<String>,
+}
+
+/// What the support screen lists; the full report goes out via export_crash_report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrashReportSummary {
+    pub id: String,
+    pub created_at: String,
+    pub message: String,
+    pub app_version: String,
+}
+
+/// Install the panic hook. Call right after logging::init in main()
+pub fn install_panic_hook() {
+    match logging::logs_dir() {
+        Ok(logs_dir) => install_panic_hook_in(logs_dir),
+        Err(e) => eprintln!("Failed to install crash reporter: {}", e),
+    }
+}
+
+fn install_panic_hook_in(logs_dir: PathBuf) {
+    let previous = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if !WRITING_REPORT.swap(true, Ordering::SeqCst) {
+            match write_crash_report(&logs_dir, info) {
+                Ok(path) => error!("💥 Panic recorded: {}", path.display()),
+                Err(e) => eprintln!("Failed to write crash report: {}", e),
+            }
+            WRITING_REPORT.store(false, Ordering::SeqCst);
+        }
+        previous(info);
+    }));
+}
+
+/// List crash reports, newest first
+#[tauri::command]
+pub fn get_crash_reports() -> Result<Vec<CrashReportSummary>, String> {
+    list_reports(&crash_dir()?)
+}
+
+#[tauri::command]
+pub fn delete_crash_report(id: String) -> Result<(), String> {
+    let path = report_path(&crash_dir()?, &id)?;
+    fs::remove_file(&path).map_err(|e| format!("Failed to delete crash report: {}", e))?;
+    info!("🗑️ Deleted crash report {}", id);
+    Ok(())
+}
+
+/// Copy a crash report to dest (e.g. a file picked in a save dialog) to send to support
+#[tauri::command]
+pub fn export_crash_report(id: String, dest: String) -> Result<(), String> {
+    let path = report_path(&crash_dir()?, &id)?;
+    fs::copy(&path, &dest).map_err(|e| format!("Failed to export crash report: {}", e))?;
+    info!("📤 Exported crash report {}", id);
+    Ok(())
+}
+
+fn crash_dir() -> Result<PathBuf, String> {
+    Ok(logging::logs_dir()?.join(CRASH_DIR_NAME))
+}
+
+/// Files of the newest `limit` crash reports (for the support bundle)
+pub(crate) fn recent_report_files(limit: usize) -> Result<Vec<PathBuf>, String> {
+    let dir = crash_dir()?;
+    list_reports(&dir)?
+        .into_iter()
+        .take(limit)
+        .map(|report| report_path(&dir, &report.id))
+        .collect()
+}
+
+fn write_crash_report(logs_dir: &Path, info: &PanicHookInfo) -> std::io::Result<PathBuf> {
+    let now = chrono::Utc::now();
+    let id = format!(
+        "crash-{}-{}",
+        now.format("%Y%m%dT%H%M%S%3fZ"),
+        REPORT_COUNTER.fetch_add(1, Ordering::SeqCst)
+    );
+
+    // The log writer can't flush for us if it's the thread that panicked
+    let thread = std::thread::current();
+    if thread.name() != Some(logging::LOG_WRITER_THREAD) {
+        logging::flush();
+    }
+
+    let report = CrashReport {
+        id: id.clone(),
+        created_at: now.to_rfc3339(),
+        message: panic_message(info)
+            .chars()
+            .take(MAX_MESSAGE_CHARS)
+            .collect(),
+        location: info
+            .location()
+            .map(|location| format!("{}:{}", location.file(), location.line())),
+        thread: thread.name().map(str::to_string),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        backtrace: Backtrace::force_capture().to_string(),
+        log_lines: tail_log(&logs_dir.join(LOG_FILE_NAME), CRASH_LOG_LINES),
+    };
+
+    let dir = logs_dir.join(CRASH_DIR_NAME);
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(format!("{}.json", id));
+    fs::write(&path, serde_json::to_vec_pretty(&report)?)?;
+    Ok(path)
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// Last `lines` lines from the end of the log, reading at most CRASH_LOG_TAIL_BYTES
+fn tail_log(path: &Path, lines: usize) -> Vec<String> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return Vec::new(),
+    };
+    let len = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+    let start = len.saturating_sub(CRASH_LOG_TAIL_BYTES);
+    if file.seek(SeekFrom::Start(start)).is_err() {
+        return Vec::new();
+    }
+
+    let mut buffer = Vec::new();
+    if file
+        .take(CRASH_LOG_TAIL_BYTES)
+        .read_to_end(&mut buffer)
+        .is_err()
+    {
+        return Vec::new();
+    }
+    let content = String::from_utf8_lossy(&buffer);
+
+    let mut tail: Vec<&str> = content.lines().collect();
+    // A read starting mid-file begins with a partial line
+    if start > 0 && !tail.is_empty() {
+        tail.remove(0);
+    }
+    let skip = tail.len().saturating_sub(lines);
+    tail[skip..].iter().map(|line| line.to_string()).collect()
+}
+
+fn list_reports(dir: &Path) -> Result<Vec<CrashReportSummary>, String> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read crash reports: {}", e)),
+    };
+
+    let mut reports: Vec<CrashReportSummary> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let content = fs::read(&path).ok()?;
+            let report: CrashReport = serde_json::from_slice(&content).ok()?;
+            Some(CrashReportSummary {
+                id: report.id,
+                created_at: report.created_at,
+                message: report.message,
+                app_version: report.app_version,
+            })
+        })
+        .collect();
+
+    reports.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(reports)
+}
+
+/// Path of a report by id; ids come from the frontend, so nothing but our own names is accepted
+fn report_path(dir: &Path, id: &str) -> Result<PathBuf, String> {
+    let valid =
+        id.starts_with("crash-") && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    if !valid {
+        return Err(format!("Invalid crash report id: {}", id));
+    }
+
+    let path = dir.join(format!("{}.json", id));
+    if !path.is_file() {
+        return Err(format!("Crash report not found: {}", id));
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_panic_in_child_thread_writes_report() {
+        let logs_dir = temp_dir("crash", "panic");
+        let log: Vec<String> = (0..250).map(|n| format!("line {}", n)).collect();
+        fs::write(logs_dir.join(LOG_FILE_NAME), log.join("\n")).unwrap();
+
+        install_panic_hook_in(logs_dir.clone());
+        let result = std::thread::Builder::new()
+            .name("crash-test".to_string())
+            .spawn(|| panic!("controlled test panic"))
+            .unwrap()
+            .join();
+        let _ = std::panic::take_hook();
+        assert!(result.is_err());
+
+        let crash_dir = logs_dir.join(CRASH_DIR_NAME);
+        let reports = list_reports(&crash_dir).unwrap();
+        let summary = reports
+            .iter()
+            .find(|report| report.message == "controlled test panic")
+            .expect("no report for the panic");
+
+        let path = report_path(&crash_dir, &summary.id).unwrap();
+        let report: CrashReport = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(report.thread.as_deref(), Some("crash-test"));
+        assert!(report.location.unwrap().contains("crash.rs"));
+        assert_eq!(report.app_version, env!("CARGO_PKG_VERSION"));
+        assert!(!report.backtrace.is_empty());
+        assert_eq!(report.log_lines.len(), CRASH_LOG_LINES);
+        assert_eq!(
+            report.log_lines.last().map(String::as_str),
+            Some("line 249")
+        );
+
+        let _ = fs::remove_dir_all(&logs_dir);
+    }
+
+    #[test]
+    fn test_report_ids_and_log_tail() {
+        let dir = temp_dir("crash", "ids");
+        fs::write(dir.join("crash-20260101T000000000Z-0.json"), "{}").unwrap();
+
+        assert!(report_path(&dir, "crash-20260101T000000000Z-0").is_ok());
+        assert!(report_path(&dir, "crash-20260101T000000000Z-1").is_err());
+        assert!(report_path(&dir, "../secrets").is_err());
+        assert!(report_path(&dir, "crash-../../etc/passwd").is_err());
+
+        // Unparseable files are skipped rather than failing the whole list
+        assert!(list_reports(&dir).unwrap().is_empty());
+        assert!(list_reports(&dir.join("missing")).unwrap().is_empty());
+
+        // Only the end of a large log is read, starting on a whole line
+        let big: String = (0..20_000).map(|n| format!("entry {}\n", n)).collect();
+        let log_path = dir.join(LOG_FILE_NAME);
+        fs::write(&log_path, big).unwrap();
+        let tail = tail_log(&log_path, 3);
+        assert_eq!(tail, vec!["entry 19997", "entry 19998", "entry 19999"]);
+        assert!(tail_log(&dir.join("missing.log"), 3).is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
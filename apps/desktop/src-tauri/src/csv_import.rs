@@ -0,0 +1,1102 @@
+// src-tauri/src/csv_import.rs
+//
+// CSV import with a user-chosen column mapping, for the import wizard. Every DMS exports
+// clients and inventory with its own headers, delimiter and encoding, so the wizard first
+// inspects the file (delimiter, UTF-8 or Windows-1252, header row, inferred column types
+// and a sample), then the user maps columns to fields. A dry run validates every row and
+// reports errors, warnings and duplicates without writing; a real run inserts the valid,
+// non-duplicate rows in one transaction through the bulk paths.
+
+use chrono::{Datelike, NaiveDate, TimeZone, Utc};
+use log::info;
+use rusqlite::params;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::client_duplicates::DuplicateDetector;
+use crate::contact_normalization::parse_phone;
+use crate::database::{
+    bulk_create_clients, bulk_create_vehicles, get_db, new_row_id, Client, Vehicle,
+};
+use crate::deep_link::is_valid_email;
+use crate::document_templates::normalize_state;
+use crate::telemetry::track;
+use crate::vcard::windows_1252_char;
+
+/// DMS exports of a whole inventory or customer base stay well under this
+const MAX_CSV_BYTES: u64 = 50 * 1024 * 1024;
+const DEFAULT_SAMPLE_ROWS: usize = 20;
+const MAX_SAMPLE_ROWS: usize = 200;
+/// Lines looked at when guessing the delimiter
+const SNIFF_LINES: usize = 20;
+const DELIMITERS: [char; 4] = [',', ';', '\t', '|'];
+const DEFAULT_VEHICLE_STATUS: &str = "available";
+
+const CLIENT_FIELDS: &[&str] = &[
+    "first_name",
+    "last_name",
+    "full_name", // "First Last" or "Last, First"; used when first/last aren't mapped
+    "email",
+    "phone",
+    "address",
+    "city",
+    "state",
+    "zip_code",
+    "drivers_license",
+];
+
+const VEHICLE_FIELDS: &[&str] = &[
+    "vin",
+    "stock_number",
+    "year",
+    "make",
+    "model",
+    "trim",
+    "body",
+    "doors",
+    "transmission",
+    "engine",
+    "cylinders",
+    "title_number",
+    "mileage",
+    "color",
+    "price",
+    "cost",
+    "status",
+    "description",
+];
+
+const VEHICLE_REQUIRED: &[&str] = &["vin", "year", "make", "model"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportEntity {
+    Clients,
+    Vehicles,
+}
+
+impl ImportEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ImportEntity::Clients => "clients",
+            ImportEntity::Vehicles => "vehicles",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "clients" => Ok(ImportEntity::Clients),
+            "vehicles" => Ok(ImportEntity::Vehicles),
+            _ => Err(format!("Unknown import entity: {}", value)),
+        }
+    }
+
+    fn fields(&self) -> &'static [&'static str] {
+        match self {
+            ImportEntity::Clients => CLIENT_FIELDS,
+            ImportEntity::Vehicles => VEHICLE_FIELDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextEncoding {
+    Utf8,
+    Windows1252,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnType {
+    Empty,
+    Integer,
+    Number,
+    Date,
+    Phone,
+    Email,
+    Vin,
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub inferred_type: ColumnType,
+    pub non_empty: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CsvPreview {
+    pub delimiter: String,
+    pub encoding: TextEncoding,
+    pub headers: Vec<String>,
+    pub columns: Vec<ColumnInfo>,
+    pub sample: Vec<Vec<String>>,
+    pub total_rows: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RowStatus {
+    Imported,
+    Duplicate,
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RowReport {
+    pub row: usize, // 1-based data row; the header isn't counted
+    pub status: RowStatus,
+    /// Id of the new record (provisional in a dry run)
+    pub record_id: Option<String>,
+    /// Existing client/vehicle id, or the record id of an earlier row in the same file
+    pub duplicate_of: Option<String>,
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CsvImportReport {
+    pub entity: ImportEntity,
+    pub dry_run: bool,
+    pub total_rows: usize,
+    pub imported: usize,
+    pub duplicates: usize,
+    pub invalid: usize,
+    pub warnings: usize,
+    pub rows: Vec<RowReport>,
+}
+
+/// Delimiter, encoding, header row, inferred column types and the first `sample_rows` rows
+#[tauri::command]
+pub fn csv_inspect(path: String, sample_rows: Option<usize>) -> Result<CsvPreview, String> {
+    track("csv_inspect", || {
+        let table = read_csv(&path)?;
+        Ok(preview(
+            &table,
+            sample_rows
+                .unwrap_or(DEFAULT_SAMPLE_ROWS)
+                .min(MAX_SAMPLE_ROWS),
+        ))
+    })
+}
+
+/// Import `entity` rows using `mapping` (column header -> field; unmapped columns are
+/// ignored). With dry_run nothing is written and the report says what would happen.
+#[tauri::command]
+pub fn csv_import_with_mapping(
+    path: String,
+    entity: String,
+    mapping: HashMap<String, String>,
+    user_id: Option<String>,
+    dry_run: bool,
+    state: State<'_, AppState>,
+) -> Result<CsvImportReport, String> {
+    track("csv_import_with_mapping", || {
+        let user_id_value = state.require_user(user_id)?;
+        let entity = ImportEntity::parse(&entity)?;
+        let table = read_csv(&path)?;
+        let columns = resolve_mapping(entity, &table.headers, &mapping)?;
+        let now = Utc::now().timestamp_millis();
+        info!(
+            "📥 CSV import of {} from {} (dry run: {})",
+            entity.as_str(),
+            path,
+            dry_run
+        );
+
+        let report = match entity {
+            ImportEntity::Clients => {
+                let db = get_db().map_err(|e| e.to_string())?;
+                let detector = DuplicateDetector::for_user(&db.conn(), &user_id_value)?;
+                let (clients, report) = plan_clients(&table, &columns, detector, now, dry_run);
+                if !dry_run {
+                    bulk_create_clients(&user_id_value, clients)?;
+                }
+                report
+            }
+            ImportEntity::Vehicles => {
+                let existing = existing_vins(&table, &columns)?;
+                let (vehicles, report) = plan_vehicles(&table, &columns, &existing, now, dry_run);
+                if !dry_run {
+                    let db = get_db().map_err(|e| e.to_string())?;
+                    bulk_create_vehicles(db, &user_id_value, vehicles)?;
+                }
+                report
+            }
+        };
+
+        info!(
+            "✅ CSV import: {} imported, {} duplicates, {} invalid",
+            report.imported, report.duplicates, report.invalid
+        );
+        Ok(report)
+    })
+}
+
+// Reading
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CsvTable {
+    delimiter: char,
+    encoding: TextEncoding,
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+fn read_csv(path: &str) -> Result<CsvTable, String> {
+    let size = fs::metadata(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?
+        .len();
+    if size > MAX_CSV_BYTES {
+        return Err(format!("File is too large to import ({} bytes)", size));
+    }
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    parse_table(&bytes)
+}
+
+fn parse_table(bytes: &[u8]) -> Result<CsvTable, String> {
+    let (text, encoding) = decode(bytes);
+    let delimiter = sniff_delimiter(&text);
+    let mut records = parse_csv(&text, delimiter).into_iter();
+    let headers: Vec<String> = records
+        .next()
+        .ok_or("The file is empty")?
+        .into_iter()
+        .map(|h| h.trim().to_string())
+        .collect();
+    Ok(CsvTable {
+        delimiter,
+        encoding,
+        headers,
+        rows: records.collect(),
+    })
+}
+
+/// UTF-8 (BOM stripped) when the bytes are valid UTF-8, else Windows-1252 (Excel's
+/// "CSV" on US Windows)
+fn decode(bytes: &[u8]) -> (String, TextEncoding) {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => (
+            text.trim_start_matches('\u{feff}').to_string(),
+            TextEncoding::Utf8,
+        ),
+        Err(_) => (
+            bytes.iter().copied().map(windows_1252_char).collect(),
+            TextEncoding::Windows1252,
+        ),
+    }
+}
+
+/// The candidate that splits the header into the most fields, preferring delimiters that
+/// give the following lines the same field count as the header
+fn sniff_delimiter(text: &str) -> char {
+    let head: String = text
+        .lines()
+        .take(SNIFF_LINES)
+        .collect::<Vec<_>>()
+        .join("\n");
+    DELIMITERS
+        .iter()
+        .copied()
+        .map(|delimiter| {
+            let records = parse_csv(&head, delimiter);
+            let width = records.first().map_or(0, Vec::len);
+            let consistent = records.iter().filter(|r| r.len() == width).count();
+            (delimiter, width, consistent)
+        })
+        .filter(|(_, width, _)| *width > 1)
+        .max_by_key(|(_, width, consistent)| (*consistent, *width))
+        .map_or(',', |(delimiter, _, _)| delimiter)
+}
+
+/// RFC 4180 records: quoted fields may contain delimiters, newlines and doubled quotes.
+/// Blank lines are skipped.
+fn parse_csv(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+
+    let mut end_record = |record: &mut Vec<String>, field: &mut String| {
+        record.push(std::mem::take(field));
+        let record = std::mem::take(record);
+        if !(record.len() == 1 && record[0].trim().is_empty()) {
+            records.push(record);
+        }
+    };
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                in_quotes = true;
+            }
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\r' | '\n' => end_record(&mut record, &mut field),
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        end_record(&mut record, &mut field);
+    }
+    records
+}
+
+// Inspecting
+
+fn preview(table: &CsvTable, sample_rows: usize) -> CsvPreview {
+    let columns = table
+        .headers
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let values: Vec<&str> = table
+                .rows
+                .iter()
+                .filter_map(|row| row.get(i))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+                .collect();
+            ColumnInfo {
+                name: name.clone(),
+                inferred_type: infer_type(&values),
+                non_empty: values.len(),
+            }
+        })
+        .collect();
+
+    CsvPreview {
+        delimiter: table.delimiter.to_string(),
+        encoding: table.encoding,
+        headers: table.headers.clone(),
+        columns,
+        sample: table.rows.iter().take(sample_rows).cloned().collect(),
+        total_rows: table.rows.len(),
+    }
+}
+
+/// The most specific type every non-empty value fits
+fn infer_type(values: &[&str]) -> ColumnType {
+    if values.is_empty() {
+        return ColumnType::Empty;
+    }
+    let all = |check: fn(&str) -> bool| values.iter().all(|value| check(value));
+    if all(|v| validate_vin(v).is_ok()) {
+        ColumnType::Vin
+    } else if all(|v| v.contains('@') && is_valid_email(v)) {
+        ColumnType::Email
+    } else if all(|v| v.parse::<i64>().is_ok()) {
+        ColumnType::Integer
+    } else if all(|v| parse_number(v).is_some()) {
+        ColumnType::Number
+    } else if all(|v| parse_date(v).is_some()) {
+        ColumnType::Date
+    } else if all(looks_like_phone) {
+        ColumnType::Phone
+    } else {
+        ColumnType::Text
+    }
+}
+
+/// "$12,500.00" -> 12500.0
+fn parse_number(value: &str) -> Option<f64> {
+    let cleaned: String = value
+        .trim()
+        .chars()
+        .filter(|c| !matches!(c, '$' | ',' | ' '))
+        .collect();
+    cleaned
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && !cleaned.is_empty())
+}
+
+/// ISO dates and US month/day/year
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    ["%Y-%m-%d", "%m/%d/%Y", "%m/%d/%y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value.trim(), format).ok())
+}
+
+fn looks_like_phone(value: &str) -> bool {
+    let digits = value.chars().filter(char::is_ascii_digit).count();
+    (10..=11).contains(&digits)
+        && value
+            .chars()
+            .all(|c| c.is_ascii_digit() || " ()-.+".contains(c))
+}
+
+// Mapping
+
+/// Field -> column index. Every problem with the mapping is reported at once.
+fn resolve_mapping(
+    entity: ImportEntity,
+    headers: &[String],
+    mapping: &HashMap<String, String>,
+) -> Result<HashMap<&'static str, usize>, String> {
+    let mut errors = Vec::new();
+    let mut columns: HashMap<&'static str, usize> = HashMap::new();
+
+    let mut entries: Vec<(&String, &String)> = mapping.iter().collect();
+    entries.sort();
+    for (column, field) in entries {
+        let field = field.trim();
+        if field.is_empty() {
+            continue;
+        }
+        let Some(index) = headers.iter().position(|h| h == column.trim()) else {
+            errors.push(format!("Column \"{}\" is not in the file", column));
+            continue;
+        };
+        let Some(field) = entity.fields().iter().copied().find(|f| *f == field) else {
+            errors.push(format!(
+                "Unknown {} field \"{}\" (column \"{}\")",
+                entity.as_str(),
+                field,
+                column
+            ));
+            continue;
+        };
+        if columns.insert(field, index).is_some() {
+            errors.push(format!("Field \"{}\" is mapped more than once", field));
+        }
+    }
+
+    match entity {
+        ImportEntity::Clients => {
+            let has_name = columns.contains_key("full_name")
+                || (columns.contains_key("first_name") && columns.contains_key("last_name"));
+            if !has_name {
+                errors.push(
+                    "Map first_name and last_name, or full_name, to import clients".to_string(),
+                );
+            }
+        }
+        ImportEntity::Vehicles => {
+            for field in VEHICLE_REQUIRED {
+                if !columns.contains_key(field) {
+                    errors.push(format!("Required field \"{}\" is not mapped", field));
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(columns)
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+/// The mapped values of one row, trimmed; empty cells are None
+struct MappedRow<'a> {
+    row: &'a [String],
+    columns: &'a HashMap<&'static str, usize>,
+}
+
+impl MappedRow<'_> {
+    fn get(&self, field: &str) -> Option<String> {
+        let value = self.row.get(*self.columns.get(field)?)?.trim();
+        (!value.is_empty()).then(|| value.to_string())
+    }
+}
+
+fn new_row_report(index: usize) -> RowReport {
+    RowReport {
+        row: index + 1,
+        status: RowStatus::Invalid,
+        record_id: None,
+        duplicate_of: None,
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    }
+}
+
+fn new_report(entity: ImportEntity, dry_run: bool, total_rows: usize) -> CsvImportReport {
+    CsvImportReport {
+        entity,
+        dry_run,
+        total_rows,
+        imported: 0,
+        duplicates: 0,
+        invalid: 0,
+        warnings: 0,
+        rows: Vec::new(),
+    }
+}
+
+fn finish_row(report: &mut CsvImportReport, row: RowReport) {
+    match row.status {
+        RowStatus::Imported => report.imported += 1,
+        RowStatus::Duplicate => report.duplicates += 1,
+        RowStatus::Invalid => report.invalid += 1,
+    }
+    report.warnings += row.warnings.len();
+    report.rows.push(row);
+}
+
+// Clients
+
+fn plan_clients(
+    table: &CsvTable,
+    columns: &HashMap<&'static str, usize>,
+    mut detector: DuplicateDetector,
+    now: i64,
+    dry_run: bool,
+) -> (Vec<Client>, CsvImportReport) {
+    let mut clients = Vec::new();
+    let mut report = new_report(ImportEntity::Clients, dry_run, table.rows.len());
+
+    for (i, row) in table.rows.iter().enumerate() {
+        let mut row_report = new_row_report(i);
+        let mapped = MappedRow { row, columns };
+        match row_to_client(&mapped, &mut row_report.warnings, now) {
+            Err(e) => row_report.errors.push(e),
+            Ok(client) => {
+                if let Some(duplicate) = detector.find(&client) {
+                    row_report.status = RowStatus::Duplicate;
+                    row_report.duplicate_of = Some(duplicate.client_id);
+                } else {
+                    detector.add(&client);
+                    row_report.status = RowStatus::Imported;
+                    row_report.record_id = Some(client.id.clone());
+                    clients.push(client);
+                }
+            }
+        }
+        finish_row(&mut report, row_report);
+    }
+
+    (clients, report)
+}
+
+fn row_to_client(row: &MappedRow, warnings: &mut Vec<String>, now: i64) -> Result<Client, String> {
+    let (mut first_name, mut last_name) = (
+        row.get("first_name").unwrap_or_default(),
+        row.get("last_name").unwrap_or_default(),
+    );
+    if first_name.is_empty() && last_name.is_empty() {
+        if let Some(full_name) = row.get("full_name") {
+            (first_name, last_name) = split_full_name(&full_name);
+        }
+    }
+    if first_name.is_empty() && last_name.is_empty() {
+        return Err("Missing name".to_string());
+    }
+
+    // Unusable numbers are kept as entered (and flagged by bulk_create_clients)
+    let phone = row.get("phone").map(|raw| {
+        let phone = parse_phone(&raw);
+        if phone.e164.is_none() {
+            warnings.push(format!(
+                "Phone \"{}\" is not a usable number; kept as entered",
+                raw
+            ));
+        }
+        phone.display
+    });
+    let email = row.get("email").and_then(|raw| {
+        if is_valid_email(&raw) && raw.contains('.') {
+            Some(raw)
+        } else {
+            warnings.push(format!("Email \"{}\" is not valid; skipped", raw));
+            None
+        }
+    });
+    let state = row
+        .get("state")
+        .and_then(|raw| match normalize_state(Some(raw.clone())) {
+            Ok(state) => state,
+            Err(_) => {
+                warnings.push(format!(
+                    "State \"{}\" is not a two-letter code; skipped",
+                    raw
+                ));
+                None
+            }
+        });
+
+    Ok(Client {
+        id: new_row_id(),
+        user_id: None,
+        first_name,
+        last_name,
+        email,
+        phone,
+        address: row.get("address"),
+        city: row.get("city"),
+        state,
+        zip_code: row.get("zip_code"),
+        drivers_license: row.get("drivers_license"),
+        created_at: now,
+        updated_at: now,
+        synced_at: None,
+    })
+}
+
+/// "Lovelace, Ada" or "Ada King Lovelace" (everything before the last word is the first name)
+fn split_full_name(full_name: &str) -> (String, String) {
+    if let Some((last, first)) = full_name.split_once(',') {
+        return (first.trim().to_string(), last.trim().to_string());
+    }
+    match full_name.trim().rsplit_once(char::is_whitespace) {
+        Some((first, last)) => (first.trim().to_string(), last.to_string()),
+        None => (full_name.trim().to_string(), String::new()),
+    }
+}
+
+// Vehicles
+
+/// VINs in the file that are already in the database (VINs are unique across accounts)
+fn existing_vins(
+    table: &CsvTable,
+    columns: &HashMap<&'static str, usize>,
+) -> Result<HashSet<String>, String> {
+    let Some(&vin_column) = columns.get("vin") else {
+        return Ok(HashSet::new());
+    };
+    let db = get_db().map_err(|e| e.to_string())?;
+    let conn = db.conn();
+    let mut stmt = conn
+        .prepare("SELECT 1 FROM vehicles WHERE vin = ?1")
+        .map_err(|e| e.to_string())?;
+
+    let mut existing = HashSet::new();
+    for row in &table.rows {
+        let Some(vin) = row.get(vin_column).map(|v| v.trim().to_ascii_uppercase()) else {
+            continue;
+        };
+        if stmt.exists(params![vin]).map_err(|e| e.to_string())? {
+            existing.insert(vin);
+        }
+    }
+    Ok(existing)
+}
+
+fn plan_vehicles(
+    table: &CsvTable,
+    columns: &HashMap<&'static str, usize>,
+    existing_vins: &HashSet<String>,
+    now: i64,
+    dry_run: bool,
+) -> (Vec<Vehicle>, CsvImportReport) {
+    let mut seen: HashMap<String, String> = HashMap::new(); // VIN -> record id
+    let mut vehicles = Vec::new();
+    let mut report = new_report(ImportEntity::Vehicles, dry_run, table.rows.len());
+
+    for (i, row) in table.rows.iter().enumerate() {
+        let mut row_report = new_row_report(i);
+        let mapped = MappedRow { row, columns };
+        match row_to_vehicle(&mapped, &mut row_report, now) {
+            None => {}
+            Some(vehicle) if existing_vins.contains(&vehicle.vin) => {
+                row_report.status = RowStatus::Duplicate;
+                row_report
+                    .warnings
+                    .push(format!("VIN {} is already in inventory", vehicle.vin));
+            }
+            Some(vehicle) => {
+                if let Some(earlier) = seen.get(&vehicle.vin) {
+                    row_report.status = RowStatus::Duplicate;
+                    row_report.duplicate_of = Some(earlier.clone());
+                } else {
+                    seen.insert(vehicle.vin.clone(), vehicle.id.clone());
+                    row_report.status = RowStatus::Imported;
+                    row_report.record_id = Some(vehicle.id.clone());
+                    vehicles.push(vehicle);
+                }
+            }
+        }
+        finish_row(&mut report, row_report);
+    }
+
+    (vehicles, report)
+}
+
+/// None (with the reasons in `report.errors`) when the row can't be imported
+fn row_to_vehicle(row: &MappedRow, report: &mut RowReport, now: i64) -> Option<Vehicle> {
+    let errors = &mut report.errors;
+    let warnings = &mut report.warnings;
+
+    let vin = row.get("vin").map(|vin| vin.to_ascii_uppercase());
+    match vin.as_deref().map(validate_vin) {
+        None => errors.push("Missing VIN".to_string()),
+        Some(Err(e)) => errors.push(e),
+        Some(Ok(VinCheck::CheckDigitMismatch)) => {
+            warnings.push("VIN check digit doesn't match (pre-1981 or non-US VIN?)".to_string())
+        }
+        Some(Ok(VinCheck::Valid)) => {}
+    }
+
+    let max_year = Utc
+        .timestamp_millis_opt(now)
+        .single()
+        .map_or(2100, |date| date.year() + 2);
+    let year = match row.get("year").map(|year| year.parse::<i32>()) {
+        None => {
+            errors.push("Missing year".to_string());
+            None
+        }
+        Some(Ok(year)) if (1900..=max_year).contains(&year) => Some(year),
+        Some(_) => {
+            errors.push(format!(
+                "Year \"{}\" is not a model year",
+                row.get("year").unwrap_or_default()
+            ));
+            None
+        }
+    };
+    let make = row.get("make");
+    let model = row.get("model");
+    if make.is_none() {
+        errors.push("Missing make".to_string());
+    }
+    if model.is_none() {
+        errors.push("Missing model".to_string());
+    }
+
+    let mut number = |field: &str| -> Option<f64> {
+        let raw = row.get(field)?;
+        let number = parse_number(&raw).filter(|n| *n >= 0.0);
+        if number.is_none() {
+            errors.push(format!("{} \"{}\" is not a number", field, raw));
+        }
+        number
+    };
+    let mileage = number("mileage").map(|n| n.round() as i32);
+    let price = number("price");
+    let cost = number("cost");
+    let doors = number("doors").map(|n| n as i32);
+    let cylinders = number("cylinders").map(|n| n as i32);
+
+    if row.get("price").is_none() {
+        warnings.push("No price; imported at $0".to_string());
+    }
+
+    if !errors.is_empty() {
+        return None;
+    }
+    Some(Vehicle {
+        id: new_row_id(),
+        vin: vin?,
+        stock_number: row.get("stock_number"),
+        year: year?,
+        make: make?,
+        model: model?,
+        trim: row.get("trim"),
+        body: row.get("body"),
+        doors,
+        transmission: row.get("transmission"),
+        engine: row.get("engine"),
+        cylinders,
+        title_number: row.get("title_number"),
+        mileage: mileage.unwrap_or(0),
+        color: row.get("color"),
+        price: price.unwrap_or(0.0),
+        cost,
+        status: row
+            .get("status")
+            .map(|s| s.to_lowercase())
+            .unwrap_or_else(|| DEFAULT_VEHICLE_STATUS.to_string()),
+        description: row.get("description"),
+        images: None,
+        created_at: now,
+        updated_at: now,
+        synced_at: None,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VinCheck {
+    Valid,
+    CheckDigitMismatch,
+}
+
+/// 17 characters without I, O or Q. A wrong check digit (position 9) is reported
+/// separately since only North American VINs are required to have one.
+fn validate_vin(vin: &str) -> Result<VinCheck, String> {
+    let vin = vin.trim().to_ascii_uppercase();
+    if vin.len() != 17 {
+        return Err(format!("VIN {} must be 17 characters", vin));
+    }
+    if let Some(c) = vin
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() || matches!(c, 'I' | 'O' | 'Q'))
+    {
+        return Err(format!("VIN {} contains an invalid character '{}'", vin, c));
+    }
+
+    if vin.chars().nth(8) == Some(vin_check_digit(&vin)) {
+        Ok(VinCheck::Valid)
+    } else {
+        Ok(VinCheck::CheckDigitMismatch)
+    }
+}
+
+/// The check digit (position 9) for a 17-character VIN of valid characters
+pub(crate) fn vin_check_digit(vin: &str) -> char {
+    const WEIGHTS: [u32; 17] = [8, 7, 6, 5, 4, 3, 2, 10, 0, 9, 8, 7, 6, 5, 4, 3, 2];
+    let value = |c: char| -> u32 {
+        match c {
+            '0'..='9' => c as u32 - '0' as u32,
+            'A'..='H' => c as u32 - 'A' as u32 + 1,
+            'J'..='R' => c as u32 - 'J' as u32 + 1,
+            _ => c as u32 - 'S' as u32 + 2,
+        }
+    };
+    let sum: u32 = vin
+        .chars()
+        .zip(WEIGHTS)
+        .map(|(c, weight)| value(c) * weight)
+        .sum();
+    match sum % 11 {
+        10 => 'X',
+        n => char::from_digit(n, 10).unwrap_or('0'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLIENTS_UTF8: &[u8] = include_bytes!("../testdata/csv_import/clients_utf8.csv");
+    const VEHICLES_1252: &[u8] = include_bytes!("../testdata/csv_import/vehicles_windows1252.csv");
+
+    fn mapping(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(column, field)| (column.to_string(), field.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_utf8_clients_inspect_and_dry_run() {
+        let table = parse_table(CLIENTS_UTF8).unwrap();
+        assert_eq!(table.encoding, TextEncoding::Utf8);
+        assert_eq!(table.delimiter, ',');
+
+        let preview = preview(&table, 2);
+        assert_eq!(
+            preview.headers,
+            vec!["Customer", "E-mail", "Cell", "Street", "St", "Zip", "Since"]
+        );
+        assert_eq!(preview.total_rows, 5);
+        assert_eq!(preview.sample.len(), 2);
+        // Quoted field with an embedded comma
+        assert_eq!(preview.sample[0][0], "Lovelace, Ada");
+        assert_eq!(preview.sample[1][3], "12 Main St\nApt 4");
+        // One bad value is enough to make a column text
+        let types: Vec<ColumnType> = preview.columns.iter().map(|c| c.inferred_type).collect();
+        assert_eq!(
+            types,
+            vec![
+                ColumnType::Text,
+                ColumnType::Text,
+                ColumnType::Text,
+                ColumnType::Text,
+                ColumnType::Text,
+                ColumnType::Integer,
+                ColumnType::Date,
+            ]
+        );
+        assert_eq!(
+            infer_type(&["248-555-1212", "(313) 555-0100"]),
+            ColumnType::Phone
+        );
+        assert_eq!(infer_type(&["ada@example.com"]), ColumnType::Email);
+
+        let columns = resolve_mapping(
+            ImportEntity::Clients,
+            &table.headers,
+            &mapping(&[
+                ("Customer", "full_name"),
+                ("E-mail", "email"),
+                ("Cell", "phone"),
+                ("Street", "address"),
+                ("St", "state"),
+                ("Zip", "zip_code"),
+                ("Since", ""),
+            ]),
+        )
+        .unwrap();
+
+        let existing = Client {
+            id: "existing".to_string(),
+            user_id: None,
+            first_name: "Grace".to_string(),
+            last_name: "Hopper".to_string(),
+            email: Some("GRACE@navy.mil".to_string()),
+            phone: None,
+            address: None,
+            city: None,
+            state: None,
+            zip_code: None,
+            drivers_license: None,
+            created_at: 0,
+            updated_at: 0,
+            synced_at: None,
+        };
+        let (clients, report) = plan_clients(
+            &table,
+            &columns,
+            DuplicateDetector::new(&[existing]),
+            1,
+            true,
+        );
+
+        assert!(report.dry_run);
+        assert_eq!(
+            (report.imported, report.duplicates, report.invalid),
+            (3, 1, 1)
+        );
+        assert_eq!(clients.len(), 3);
+        assert_eq!(
+            (
+                clients[0].first_name.as_str(),
+                clients[0].last_name.as_str()
+            ),
+            ("Ada", "Lovelace")
+        );
+        assert_eq!(clients[0].phone.as_deref(), Some("(248) 555-1212"));
+        assert_eq!(clients[1].first_name, "José");
+        assert_eq!(clients[1].address.as_deref(), Some("12 Main St\nApt 4"));
+
+        let grace = &report.rows[2];
+        assert_eq!(grace.status, RowStatus::Duplicate);
+        assert_eq!(grace.duplicate_of.as_deref(), Some("existing"));
+
+        let nameless = &report.rows[3];
+        assert_eq!(nameless.status, RowStatus::Invalid);
+        assert_eq!(nameless.errors, vec!["Missing name"]);
+
+        // Bad email and state are dropped with warnings, a bad phone is kept as entered;
+        // the client still imports
+        let messy = &report.rows[4];
+        assert_eq!(messy.status, RowStatus::Imported);
+        assert_eq!(messy.warnings.len(), 3);
+        assert_eq!(clients[2].phone.as_deref(), Some("555-12"));
+        assert_eq!(clients[2].email, None);
+        assert_eq!(clients[2].state, None);
+    }
+
+    #[test]
+    fn test_windows_1252_vehicles_with_vin_checks() {
+        let table = parse_table(VEHICLES_1252).unwrap();
+        assert_eq!(table.encoding, TextEncoding::Windows1252);
+        assert_eq!(table.delimiter, ';');
+        assert_eq!(table.rows[0][4], "Crème Brûlée");
+
+        let preview = preview(&table, 10);
+        assert_eq!(preview.columns[0].inferred_type, ColumnType::Text);
+        assert_eq!(preview.columns[2].inferred_type, ColumnType::Integer);
+        assert_eq!(preview.columns[5].inferred_type, ColumnType::Number);
+
+        let columns = resolve_mapping(
+            ImportEntity::Vehicles,
+            &table.headers,
+            &mapping(&[
+                ("VIN #", "vin"),
+                ("Stock", "stock_number"),
+                ("Yr", "year"),
+                ("Make", "make"),
+                ("Model / Color", "color"),
+                ("Asking", "price"),
+                ("Odometer", "mileage"),
+                ("Model Name", "model"),
+            ]),
+        )
+        .unwrap();
+
+        let existing: HashSet<String> = ["1FTFW1ET9DFC10312".to_string()].into();
+        let (vehicles, report) =
+            plan_vehicles(&table, &columns, &existing, 1_760_000_000_000, false);
+
+        let statuses: Vec<RowStatus> = report.rows.iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                RowStatus::Imported,
+                RowStatus::Duplicate, // already in the database
+                RowStatus::Duplicate, // repeats row 1
+                RowStatus::Invalid,   // contains an O
+                RowStatus::Invalid,   // 16 characters, bad year
+                RowStatus::Imported,  // check digit warning only
+            ]
+        );
+        assert_eq!(vehicles.len(), 2);
+        assert_eq!(vehicles[0].vin, "1HGCM82633A004352");
+        assert_eq!(vehicles[0].price, 12500.0);
+        assert_eq!(vehicles[0].mileage, 84210);
+        assert_eq!(vehicles[0].color.as_deref(), Some("Crème Brûlée"));
+        assert_eq!(vehicles[0].status, "available");
+        assert_eq!(report.rows[2].duplicate_of, Some(vehicles[0].id.clone()));
+        assert!(report.rows[3].errors[0].contains("invalid character 'O'"));
+        assert_eq!(report.rows[4].errors.len(), 2);
+        assert!(report.rows[5].warnings[0].contains("check digit"));
+    }
+
+    #[test]
+    fn test_bad_mapping_reports_every_problem() {
+        let table = parse_table(CLIENTS_UTF8).unwrap();
+        let err = resolve_mapping(
+            ImportEntity::Vehicles,
+            &table.headers,
+            &mapping(&[
+                ("Customer", "make"),
+                ("Cell", "model"),
+                ("Zip", "model"),
+                ("E-mail", "email"),
+                ("Fax", "phone"),
+            ]),
+        )
+        .unwrap_err();
+
+        assert!(err.contains("Unknown vehicles field \"email\""), "{}", err);
+        assert!(err.contains("Column \"Fax\" is not in the file"), "{}", err);
+        assert!(
+            err.contains("Field \"model\" is mapped more than once"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("Required field \"vin\" is not mapped"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("Required field \"year\" is not mapped"),
+            "{}",
+            err
+        );
+
+        let err = resolve_mapping(
+            ImportEntity::Clients,
+            &table.headers,
+            &mapping(&[("Customer", "first_name")]),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            "Map first_name and last_name, or full_name, to import clients"
+        );
+        assert!(ImportEntity::parse("deals").is_err());
+    }
+}
@@ -0,0 +1,554 @@
+// src-tauri/src/daily_activity.rs
+//
+// End-of-day desk log: what happened on one local day. Deals written and sold, their gross,
+// payments taken, new clients and documents generated, in a couple of aggregate queries.
+// Timestamps are UTC epoch millis, so the day is cut at midnight in the dealer's time zone
+// (datetime.rs), 23 or 25 hours long on DST change days.
+// Sold deals and gross follow db_get_deals_stats (vehicle_costs.rs), so the days of a month
+// add up to its stats. There's no payments ledger: payments received are the down payments
+// on the deals sold that day. Roles that don't see costs (permissions.rs) get the cost and
+// gross as zero, and a PDF without those lines.
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use log::info;
+use lopdf::{dictionary, Document as PdfDocument, Object, Stream};
+use rusqlite::{params, Connection, Result as SqlResult};
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::DEAL_STATUS_SOLD;
+use crate::datetime::{dealer_timezone, format_local, local_date, local_day_bounds};
+use crate::dealer_profile::get_dealer_profile_for_user;
+use crate::money::{current_locale, format_amount, MoneyLocale};
+use crate::pdf_forms::{escape_pdf_string, win_ansi};
+use crate::permissions::Role;
+use crate::reporting::{run_report_job, write_report_file};
+use crate::telemetry::track_async;
+use crate::vehicle_costs::{SALE_CENTS_SQL, SOLD_AT_SQL, VEHICLE_COST_CENTS_SQL};
+
+/// US Letter, in points
+const PAGE_SIZE: (i64, i64) = (612, 792);
+const MARGIN: i64 = 72;
+const VALUE_COLUMN: i64 = 380;
+const LINE_HEIGHT: i64 = 22;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DailyActivity {
+    pub date: String, // YYYY-MM-DD
+    /// The local day as [start_ms, end_ms)
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub deals_created: i64,
+    pub deals_sold: i64,
+    pub sale_cents: i64,
+    pub cost_cents: i64,
+    pub gross_profit_cents: i64,
+    /// Down payments on the deals sold that day
+    pub payments_received_cents: i64,
+    pub new_clients: i64,
+    pub documents_generated: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DailyActivityExport {
+    pub path: String,
+    pub activity: DailyActivity,
+}
+
+/// The local date containing date_ms in tz, and its [start, end) in epoch millis
+pub(crate) fn local_day<Tz: TimeZone>(
+    tz: &Tz,
+    date_ms: i64,
+) -> Result<(NaiveDate, i64, i64), String> {
+    let date = local_date(date_ms, tz).ok_or_else(|| "Invalid date".to_string())?;
+    let (start_ms, end_ms) = local_day_bounds(date, tz);
+    Ok((date, start_ms, end_ms))
+}
+
+pub(crate) fn daily_activity(
+    conn: &Connection,
+    user_id: &str,
+    date: NaiveDate,
+    start_ms: i64,
+    end_ms: i64,
+) -> SqlResult<DailyActivity> {
+    let (deals_created, new_clients, documents_generated) = conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM deals
+                 WHERE user_id = ?1 AND created_at >= ?2 AND created_at < ?3),
+                (SELECT COUNT(*) FROM clients
+                 WHERE user_id = ?1 AND created_at >= ?2 AND created_at < ?3),
+                (SELECT COUNT(*) FROM documents
+                 WHERE user_id = ?1 AND created_at >= ?2 AND created_at < ?3)",
+        params![user_id, start_ms, end_ms],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+
+    let (deals_sold, sale_cents, cost_cents, payments_received_cents): (i64, i64, i64, i64) = conn
+        .query_row(
+            &format!(
+                "SELECT COUNT(*),
+                        COALESCE(SUM({sale}), 0),
+                        COALESCE(SUM({cost}), 0),
+                        COALESCE(SUM(d.down_payment_cents), 0)
+                 FROM deals d
+                 WHERE d.user_id = ?1 AND d.status = ?2
+                   AND {sold_at} >= ?3 AND {sold_at} < ?4",
+                sale = SALE_CENTS_SQL,
+                cost = VEHICLE_COST_CENTS_SQL,
+                sold_at = SOLD_AT_SQL,
+            ),
+            params![user_id, DEAL_STATUS_SOLD, start_ms, end_ms],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+
+    Ok(DailyActivity {
+        date: date.format("%Y-%m-%d").to_string(),
+        start_ms,
+        end_ms,
+        deals_created,
+        deals_sold,
+        sale_cents,
+        cost_cents,
+        gross_profit_cents: sale_cents - cost_cents,
+        payments_received_cents,
+        new_clients,
+        documents_generated,
+    })
+}
+
+impl DailyActivity {
+    /// Zero the cost and gross figures for roles that don't see costs
+    fn redacted(mut self, role: Role) -> Self {
+        if !role.sees_costs() {
+            self.cost_cents = 0;
+            self.gross_profit_cents = 0;
+        }
+        self
+    }
+}
+
+/// One-page Helvetica summary of the day; the cost and gross lines only for show_costs
+fn render_pdf(
+    activity: &DailyActivity,
+    locale: &MoneyLocale,
+    dealer_name: Option<&str>,
+    generated_at: &str,
+    show_costs: bool,
+) -> Result<Vec<u8>, String> {
+    let date = NaiveDate::parse_from_str(&activity.date, "%Y-%m-%d")
+        .map(|date| date.format("%A, %B %-d, %Y").to_string())
+        .unwrap_or_else(|_| activity.date.clone());
+    let money = |cents: i64| format_amount(cents, locale, true);
+    let mut rows = vec![
+        ("Deals created", activity.deals_created.to_string()),
+        ("Deals sold", activity.deals_sold.to_string()),
+        ("Sales", money(activity.sale_cents)),
+    ];
+    if show_costs {
+        rows.push(("Vehicle costs", money(activity.cost_cents)));
+        rows.push(("Gross profit", money(activity.gross_profit_cents)));
+    }
+    rows.extend([
+        (
+            "Payments received (down payments)",
+            money(activity.payments_received_cents),
+        ),
+        ("New clients", activity.new_clients.to_string()),
+        (
+            "Documents generated",
+            activity.documents_generated.to_string(),
+        ),
+    ]);
+
+    let text = |font: &str, size: i64, x: i64, y: i64, value: &str| {
+        format!(
+            "BT /{} {} Tf {} {} Td ({}) Tj ET\n",
+            font,
+            size,
+            x,
+            y,
+            escape_pdf_string(&win_ansi(value))
+        )
+    };
+    let mut y = PAGE_SIZE.1 - MARGIN;
+    let mut content = String::new();
+    if let Some(name) = dealer_name {
+        content.push_str(&text("F1", 11, MARGIN, y, name));
+        y -= LINE_HEIGHT;
+    }
+    content.push_str(&text("F2", 18, MARGIN, y, "Daily Activity"));
+    y -= LINE_HEIGHT;
+    content.push_str(&text("F1", 12, MARGIN, y, &date));
+    y -= LINE_HEIGHT * 2;
+    for (label, value) in &rows {
+        content.push_str(&text("F1", 12, MARGIN, y, label));
+        content.push_str(&text("F2", 12, VALUE_COLUMN, y, value));
+        y -= LINE_HEIGHT;
+    }
+    y -= LINE_HEIGHT;
+    content.push_str(&text(
+        "F1",
+        9,
+        MARGIN,
+        y,
+        &format!("Generated {}", generated_at),
+    ));
+
+    let mut pdf = PdfDocument::with_version("1.5");
+    let pages_id = pdf.new_object_id();
+    let font = |name: &str| {
+        dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => Object::Name(name.as_bytes().to_vec()),
+            "Encoding" => "WinAnsiEncoding",
+        }
+    };
+    let regular_id = pdf.add_object(font("Helvetica"));
+    let bold_id = pdf.add_object(font("Helvetica-Bold"));
+    let content_id = pdf.add_object(Stream::new(dictionary! {}, content.into_bytes()));
+    let page_id = pdf.add_object(dictionary! {
+        "Type" => "Page",
+        "Parent" => pages_id,
+        "MediaBox" => vec![0.into(), 0.into(), PAGE_SIZE.0.into(), PAGE_SIZE.1.into()],
+        "Contents" => content_id,
+        "Resources" => dictionary! {
+            "Font" => dictionary! { "F1" => regular_id, "F2" => bold_id },
+        },
+    });
+    pdf.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }),
+    );
+    let catalog_id = pdf.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    pdf.trailer.set("Root", catalog_id);
+
+    let mut bytes = Vec::new();
+    pdf.save_to(&mut bytes)
+        .map_err(|e| format!("Failed to write PDF: {}", e))?;
+    Ok(bytes)
+}
+
+/// Activity on the dealer's day containing date_ms (a report job, see reporting.rs)
+#[tauri::command]
+pub async fn db_get_daily_activity(
+    user_id: Option<String>,
+    date_ms: i64,
+    job_id: Option<String>,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<DailyActivity, String> {
+    let user_id_value = state.require_user(user_id)?;
+    let role = state.role();
+
+    track_async("db_get_daily_activity", async move {
+        let owner = user_id_value.clone();
+        run_report_job(
+            job_id,
+            "db_get_daily_activity",
+            &owner,
+            window.label(),
+            move |_, conn| {
+                let (date, start_ms, end_ms) = local_day(&dealer_timezone(conn), date_ms)?;
+                daily_activity(conn, &user_id_value, date, start_ms, end_ms)
+                    .map(|activity| activity.redacted(role))
+                    .map_err(|e| e.to_string())
+            },
+        )
+        .await
+    })
+    .await
+}
+
+/// The day's activity as a PDF at output_path (a report job; nothing is written if it's
+/// cancelled)
+#[tauri::command]
+pub async fn export_daily_activity_pdf(
+    user_id: Option<String>,
+    date_ms: i64,
+    output_path: String,
+    job_id: Option<String>,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<DailyActivityExport, String> {
+    let user_id_value = state.require_user(user_id)?;
+    let role = state.role();
+
+    track_async("export_daily_activity_pdf", async move {
+        let path = PathBuf::from(&output_path);
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            if !parent.is_dir() {
+                return Err(format!("Folder does not exist: {}", parent.display()));
+            }
+        }
+        let locale = current_locale();
+        let dealer_name = get_dealer_profile_for_user(&user_id_value)?
+            .and_then(|profile| profile.dba.or(profile.legal_name))
+            .filter(|name| !name.trim().is_empty());
+        let owner = user_id_value.clone();
+        let activity = run_report_job(
+            job_id,
+            "export_daily_activity_pdf",
+            &owner,
+            window.label(),
+            move |job, conn| {
+                let tz = dealer_timezone(conn);
+                let (date, start_ms, end_ms) = local_day(&tz, date_ms)?;
+                let generated_at =
+                    format_local(Utc::now().timestamp_millis(), &tz, "%m/%d/%Y %-I:%M %p");
+                let activity = daily_activity(conn, &user_id_value, date, start_ms, end_ms)
+                    .map_err(|e| e.to_string())?
+                    .redacted(role);
+                let pdf = render_pdf(
+                    &activity,
+                    locale,
+                    dealer_name.as_deref(),
+                    &generated_at,
+                    role.sees_costs(),
+                )?;
+                write_report_file(job, &path, &pdf)?;
+                Ok(activity)
+            },
+        )
+        .await?;
+
+        info!("✅ Exported daily activity for {}", activity.date);
+        Ok(DailyActivityExport {
+            path: output_path,
+            activity,
+        })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{deals_stats, upsert_setting, Database};
+    use crate::datetime::TIMEZONE_SETTING;
+    use crate::money::LOCALES;
+    use chrono::{DateTime, FixedOffset};
+    use chrono_tz::America::New_York;
+
+    const HOUR_MS: i64 = 3_600_000;
+
+    fn est() -> FixedOffset {
+        FixedOffset::west_opt(5 * 3600).unwrap()
+    }
+
+    fn ms(rfc3339: &str) -> i64 {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    fn test_db() -> Database {
+        let db = Database::init_in_memory().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO clients (id, first_name, last_name, created_at, updated_at, user_id)
+                     VALUES ('c1', 'Jane', 'Doe', 0, 0, 'u1');
+                 INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status,
+                                       created_at, updated_at, user_id)
+                     VALUES ('v1', 'VIN1', 2021, 'Honda', 'Civic', 0, 20000, 'sold', 0, 0, 'u1');
+                 INSERT INTO vehicle_costs (id, vehicle_id, user_id, category, amount_cents,
+                                            incurred_at, created_at, updated_at)
+                     VALUES ('vc1', 'v1', 'u1', 'purchase', 1500000, 0, 0, 0);",
+            )
+            .unwrap();
+        db
+    }
+
+    fn deal(conn: &Connection, id: &str, status: &str, created_at: i64, sold_at: Option<i64>) {
+        conn.execute(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount,
+                                sale_amount, down_payment, sale_date, created_at, updated_at,
+                                user_id)
+             VALUES (?1, 'retail', 'c1', 'v1', ?2, 20000, 19000, 2500, ?3, ?4, ?4, 'u1')",
+            params![id, status, sold_at, created_at],
+        )
+        .unwrap();
+    }
+
+    fn activity_on<Tz: TimeZone>(conn: &Connection, tz: &Tz, date_ms: i64) -> DailyActivity {
+        let (date, start_ms, end_ms) = local_day(tz, date_ms).unwrap();
+        daily_activity(conn, "u1", date, start_ms, end_ms).unwrap()
+    }
+
+    #[test]
+    fn test_day_is_cut_at_local_midnight() {
+        let db = test_db();
+        let conn = db.conn();
+        let est = est();
+        // 2024-01-15 in New York is 05:00 UTC to 05:00 UTC the next day
+        deal(
+            &conn,
+            "first",
+            "quote",
+            ms("2024-01-15T00:00:00-05:00"),
+            None,
+        );
+        deal(
+            &conn,
+            "last",
+            "quote",
+            ms("2024-01-15T23:59:59.999-05:00"),
+            None,
+        );
+        deal(
+            &conn,
+            "next",
+            "quote",
+            ms("2024-01-16T00:00:00-05:00"),
+            None,
+        );
+        deal(
+            &conn,
+            "before",
+            "quote",
+            ms("2024-01-14T23:59:59.999-05:00"),
+            None,
+        );
+        conn.execute(
+            "INSERT INTO clients (id, first_name, last_name, created_at, updated_at, user_id)
+             VALUES ('c2', 'John', 'Roe', ?1, ?1, 'u1')",
+            params![ms("2024-01-16T03:00:00Z")],
+        )
+        .unwrap();
+
+        // Any instant in the day finds the same day
+        for at in ["2024-01-15T00:00:00-05:00", "2024-01-16T04:59:59Z"] {
+            let activity = activity_on(&conn, &est, ms(at));
+            assert_eq!(activity.date, "2024-01-15");
+            assert_eq!(activity.start_ms, ms("2024-01-15T05:00:00Z"));
+            assert_eq!(activity.end_ms, ms("2024-01-16T05:00:00Z"));
+            assert_eq!(activity.deals_created, 2);
+            assert_eq!(activity.new_clients, 1);
+        }
+
+        // The same instants on a UTC day
+        let utc = FixedOffset::east_opt(0).unwrap();
+        let activity = activity_on(&conn, &utc, ms("2024-01-15T12:00:00Z"));
+        assert_eq!(activity.deals_created, 2); // "before" and "first"
+        assert_eq!(activity.new_clients, 0);
+
+        // Another user's rows don't count
+        let (date, start, end) = local_day(&est, ms("2024-01-15T12:00:00Z")).unwrap();
+        assert_eq!(
+            daily_activity(&conn, "u2", date, start, end)
+                .unwrap()
+                .deals_created,
+            0
+        );
+    }
+
+    #[test]
+    fn test_dst_days_are_23_and_25_hours() {
+        let db = test_db();
+        let conn = db.conn();
+        let tz = New_York;
+
+        let (_, start, end) = local_day(&tz, ms("2024-03-10T12:00:00Z")).unwrap();
+        assert_eq!(start, ms("2024-03-10T00:00:00-05:00"));
+        assert_eq!(end, ms("2024-03-11T00:00:00-04:00"));
+        assert_eq!(end - start, 23 * HOUR_MS);
+
+        let (_, start, end) = local_day(&tz, ms("2024-11-03T12:00:00Z")).unwrap();
+        assert_eq!(start, ms("2024-11-03T00:00:00-04:00"));
+        assert_eq!(end, ms("2024-11-04T00:00:00-05:00"));
+        assert_eq!(end - start, 25 * HOUR_MS);
+
+        // 00:30 EDT on the 11th is 23:30 EST on the 10th: a fixed EST offset gets it wrong
+        let late = ms("2024-03-10T23:30:00-04:00");
+        deal(&conn, "spring", "sold", late, Some(late));
+        let after = ms("2024-03-11T00:30:00-04:00");
+        deal(&conn, "after", "sold", after, Some(after));
+        // 01:30 happens twice on the fall-back day; the second one (EST) is still the 3rd
+        let repeated = ms("2024-11-03T01:30:00-05:00");
+        deal(&conn, "fall", "sold", repeated, Some(repeated));
+
+        let spring = activity_on(&conn, &tz, late);
+        assert_eq!(spring.date, "2024-03-10");
+        assert_eq!((spring.deals_created, spring.deals_sold), (1, 1));
+        assert_eq!(activity_on(&conn, &tz, after).date, "2024-03-11");
+        let fixed = activity_on(&conn, &est(), late);
+        assert_eq!((fixed.date.as_str(), fixed.deals_sold), ("2024-03-10", 2));
+
+        let fall = activity_on(&conn, &tz, repeated);
+        assert_eq!(fall.date, "2024-11-03");
+        assert_eq!(fall.deals_sold, 1);
+        let day_after = activity_on(&conn, &tz, ms("2024-11-04T12:00:00Z"));
+        assert_eq!(day_after.deals_sold, 0);
+    }
+
+    #[test]
+    fn test_days_reconcile_with_deals_stats() {
+        let db = test_db();
+        let conn = db.conn();
+        let tz = New_York;
+        upsert_setting(&conn, TIMEZONE_SETTING, "America/New_York", 0).unwrap();
+        let day = |n: u32| ms(&format!("2024-06-{:02}T10:00:00-04:00", n));
+
+        deal(&conn, "d1", "sold", day(3), Some(day(4)));
+        deal(&conn, "d2", "sold", day(4), None); // no sale date: sold when created
+        deal(&conn, "d3", "quote", day(5), None);
+        deal(&conn, "d4", "sold", day(10), Some(day(12)));
+        conn.execute(
+            "INSERT INTO documents (id, deal_id, type, filename, file_path, created_at,
+                                    updated_at, user_id)
+             VALUES ('doc1', 'd1', 'bill_of_sale', 'a.pdf', '/docs/a.pdf', ?1, ?1, 'u1')",
+            params![day(4)],
+        )
+        .unwrap();
+
+        let days: Vec<DailyActivity> = (1..=30).map(|n| activity_on(&conn, &tz, day(n))).collect();
+        let sum = |field: fn(&DailyActivity) -> i64| days.iter().map(field).sum::<i64>();
+
+        let stats = deals_stats(&conn, "u1").unwrap();
+        assert_eq!(sum(|d| d.deals_created), stats["total"].as_i64().unwrap());
+        assert_eq!(sum(|d| d.deals_sold), 3);
+        assert_eq!(
+            sum(|d| d.gross_profit_cents) as f64 / 100.0,
+            stats["grossProfit"].as_f64().unwrap()
+        );
+        assert_eq!(sum(|d| d.payments_received_cents), 3 * 250_000);
+
+        let june_4 = &days[3];
+        assert_eq!((june_4.deals_created, june_4.deals_sold), (1, 2));
+        assert_eq!(june_4.sale_cents, 2 * 1_900_000);
+        assert_eq!(june_4.gross_profit_cents, 2 * (1_900_000 - 1_500_000));
+        assert_eq!(june_4.documents_generated, 1);
+    }
+
+    #[test]
+    fn test_render_pdf() {
+        let activity = DailyActivity {
+            date: "2024-06-04".to_string(),
+            deals_sold: 2,
+            gross_profit_cents: 800_000,
+            ..DailyActivity::default()
+        };
+        let bytes =
+            render_pdf(&activity, &LOCALES[0], Some("Smith (Motors)"), "now", true).unwrap();
+
+        let pdf = PdfDocument::load_mem(&bytes).unwrap();
+        assert_eq!(pdf.get_pages().len(), 1);
+        let page_id = *pdf.get_pages().values().next().unwrap();
+        let content = String::from_utf8(pdf.get_page_content(page_id).unwrap()).unwrap();
+        assert!(content.contains("(Tuesday, June 4, 2024)"), "{}", content);
+        assert!(content.contains("(Smith \\(Motors\\))"), "{}", content);
+        assert!(content.contains("($8,000.00)"), "{}", content);
+    }
+}
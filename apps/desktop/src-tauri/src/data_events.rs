@@ -0,0 +1,356 @@
+// src-tauri/src/data_events.rs
+//
+// "data-changed" events so the frontend can update the record that changed instead of
+// refetching whole lists after every save.
+// Mutating commands call data_changed() once their write has succeeded. Bulk operations
+// (contact/vehicle imports, data import) collect their changes in a ChangeBatch and emit a
+// single summary (operation "bulk", with a count) instead of one event per row.
+// The emitter is global like webhooks::notify, so helpers shared with the local API and
+// imports can report changes too; install() points it at the app once at startup.
+// data_change_events_enabled = "false" turns emission off.
+
+use log::error;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+use tauri::{AppHandle, Emitter};
+
+use crate::database::db_get_setting;
+use crate::settings_cache::set_setting;
+use crate::telemetry::track;
+
+pub const DATA_CHANGED_EVENT: &str = "data-changed";
+pub const DATA_EVENTS_SETTING: &str = "data_change_events_enabled";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangedEntity {
+    Client,
+    Vehicle,
+    Deal,
+    Document,
+    Lender,
+    Task,
+    DealFee,
+    DocumentTemplate,
+    Communication,
+    MessageTemplate,
+    DealerProfile,
+    VehicleCost,
+    DealCobuyer,
+    VehicleHold,
+    VehicleEvent,
+    /// A batch that touched more than one kind of record (see entity_types)
+    Multiple,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Operation {
+    Create,
+    Update,
+    Delete,
+    /// Summary of a batch; refetch the listed entity types
+    Bulk,
+}
+
+/// Payload of the data-changed event
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DataChanged {
+    pub entity_type: ChangedEntity,
+    /// None for a batch summary
+    pub entity_id: Option<String>,
+    pub operation: Operation,
+    pub user_id: String,
+    /// 1, or how many changes a batch summary stands for
+    pub count: usize,
+    /// Batch summaries only: every entity type the batch touched
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub entity_types: Vec<ChangedEntity>,
+}
+
+type Sink = Box<dyn Fn(&DataChanged) + Send + Sync>;
+
+pub struct ChangeEmitter {
+    enabled: AtomicBool,
+    sink: RwLock<Option<Sink>>,
+}
+
+impl ChangeEmitter {
+    const fn new() -> Self {
+        Self {
+            enabled: AtomicBool::new(true),
+            sink: RwLock::new(None),
+        }
+    }
+
+    fn set_sink(&self, sink: Sink) {
+        *self
+            .sink
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(sink);
+    }
+
+    fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    fn emit(&self, event: DataChanged) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        if let Some(sink) = self
+            .sink
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .as_ref()
+        {
+            sink(&event);
+        }
+    }
+
+    fn changed(&self, user_id: &str, entity: ChangedEntity, id: &str, operation: Operation) {
+        self.emit(DataChanged {
+            entity_type: entity,
+            entity_id: Some(id.to_string()),
+            operation,
+            user_id: user_id.to_string(),
+            count: 1,
+            entity_types: Vec::new(),
+        });
+    }
+
+    fn batch(&self, user_id: &str) -> ChangeBatch<'_> {
+        ChangeBatch {
+            emitter: self,
+            user_id: user_id.to_string(),
+            first: None,
+            count: 0,
+            entity_types: BTreeSet::new(),
+        }
+    }
+}
+
+/// Changes from one bulk operation, emitted together by finish()
+/// Record only what was committed; a batch that's dropped without finish() emits nothing.
+pub struct ChangeBatch<'a> {
+    emitter: &'a ChangeEmitter,
+    user_id: String,
+    /// Kept so a batch of one is emitted as the plain event
+    first: Option<(ChangedEntity, String, Operation)>,
+    count: usize,
+    entity_types: BTreeSet<ChangedEntity>,
+}
+
+impl ChangeBatch<'_> {
+    pub fn record(&mut self, entity: ChangedEntity, id: &str, operation: Operation) {
+        if self.first.is_none() {
+            self.first = Some((entity, id.to_string(), operation));
+        }
+        self.count += 1;
+        self.entity_types.insert(entity);
+    }
+
+    /// Record changes to one entity type by count when the ids aren't at hand (data import).
+    /// The type is listed even when count is 0, e.g. when a replace import only deleted rows
+    pub fn record_count(&mut self, entity: ChangedEntity, count: usize) {
+        self.count += count;
+        self.entity_types.insert(entity);
+    }
+
+    pub fn finish(self) {
+        if self.entity_types.is_empty() {
+            return;
+        }
+        match (self.count, self.first) {
+            (1, Some((entity, id, operation))) => {
+                self.emitter.changed(&self.user_id, entity, &id, operation)
+            }
+            (count, _) => {
+                let entity_types: Vec<ChangedEntity> = self.entity_types.into_iter().collect();
+                self.emitter.emit(DataChanged {
+                    entity_type: match entity_types.as_slice() {
+                        [only] => *only,
+                        _ => ChangedEntity::Multiple,
+                    },
+                    entity_id: None,
+                    operation: Operation::Bulk,
+                    user_id: self.user_id,
+                    count,
+                    entity_types,
+                });
+            }
+        }
+    }
+}
+
+static EMITTER: ChangeEmitter = ChangeEmitter::new();
+
+/// Report one committed change to the frontend
+pub fn data_changed(user_id: &str, entity: ChangedEntity, id: &str, operation: Operation) {
+    EMITTER.changed(user_id, entity, id, operation);
+}
+
+/// Start collecting a bulk operation's changes into one event
+pub fn change_batch(user_id: &str) -> ChangeBatch<'static> {
+    EMITTER.batch(user_id)
+}
+
+/// Send data-changed events to the app's windows (called once from setup)
+pub fn install(app: AppHandle) {
+    EMITTER.set_enabled(events_enabled());
+    EMITTER.set_sink(Box::new(move |event| {
+        if let Err(e) = app.emit(DATA_CHANGED_EVENT, event) {
+            error!("Failed to emit {}: {}", DATA_CHANGED_EVENT, e);
+        }
+    }));
+}
+
+fn events_enabled() -> bool {
+    db_get_setting(DATA_EVENTS_SETTING.to_string())
+        .ok()
+        .flatten()
+        .is_none_or(|value| value != "false")
+}
+
+#[tauri::command]
+pub fn get_data_change_events_enabled() -> Result<bool, String> {
+    track("get_data_change_events_enabled", || Ok(events_enabled()))
+}
+
+#[tauri::command]
+pub fn set_data_change_events_enabled(enabled: bool) -> Result<(), String> {
+    track("set_data_change_events_enabled", || {
+        set_setting(DATA_EVENTS_SETTING, &enabled.to_string())?;
+        EMITTER.set_enabled(enabled);
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn captured() -> (ChangeEmitter, Arc<Mutex<Vec<DataChanged>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let emitter = ChangeEmitter::new();
+        let sink = events.clone();
+        emitter.set_sink(Box::new(move |event| {
+            sink.lock().unwrap().push(event.clone())
+        }));
+        (emitter, events)
+    }
+
+    #[test]
+    fn test_each_operation_emits_one_event() {
+        let (emitter, events) = captured();
+        emitter.changed("u1", ChangedEntity::Client, "c1", Operation::Create);
+        emitter.changed("u1", ChangedEntity::Deal, "d1", Operation::Update);
+        emitter.changed("u1", ChangedEntity::Vehicle, "v1", Operation::Delete);
+
+        let events = events.lock().unwrap();
+        let summary: Vec<(ChangedEntity, Option<&str>, Operation, usize)> = events
+            .iter()
+            .map(|e| (e.entity_type, e.entity_id.as_deref(), e.operation, e.count))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (ChangedEntity::Client, Some("c1"), Operation::Create, 1),
+                (ChangedEntity::Deal, Some("d1"), Operation::Update, 1),
+                (ChangedEntity::Vehicle, Some("v1"), Operation::Delete, 1),
+            ]
+        );
+        assert!(events.iter().all(|e| e.user_id == "u1"));
+
+        let json = serde_json::to_value(&events[0]).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "entity_type": "client", "entity_id": "c1", "operation": "create",
+                "user_id": "u1", "count": 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_bulk_operations_coalesce() {
+        let (emitter, events) = captured();
+
+        let mut batch = emitter.batch("u1");
+        for i in 0..2_000 {
+            batch.record(
+                ChangedEntity::Vehicle,
+                &format!("v{}", i),
+                Operation::Create,
+            );
+        }
+        assert!(events.lock().unwrap().is_empty(), "nothing until finish");
+        batch.finish();
+
+        let mut batch = emitter.batch("u1");
+        batch.record(ChangedEntity::Deal, "d1", Operation::Create);
+        batch.record(ChangedEntity::Client, "c1", Operation::Update);
+        batch.finish();
+
+        // A batch of one is a normal event; an empty or abandoned batch is silent
+        let mut batch = emitter.batch("u1");
+        batch.record(ChangedEntity::Client, "c9", Operation::Create);
+        batch.finish();
+        emitter.batch("u1").finish();
+        let mut import = emitter.batch("u1");
+        import.record_count(ChangedEntity::Client, 40);
+        import.record_count(ChangedEntity::Document, 0);
+        import.finish();
+        let mut abandoned = emitter.batch("u1");
+        abandoned.record(ChangedEntity::Client, "c10", Operation::Create);
+        drop(abandoned);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(
+            (events[0].entity_type, events[0].operation, events[0].count),
+            (ChangedEntity::Vehicle, Operation::Bulk, 2_000)
+        );
+        assert_eq!(events[0].entity_id, None);
+        assert_eq!(events[0].entity_types, [ChangedEntity::Vehicle]);
+        assert_eq!(
+            (events[1].entity_type, events[1].count),
+            (ChangedEntity::Multiple, 2)
+        );
+        assert_eq!(
+            events[1].entity_types,
+            [ChangedEntity::Client, ChangedEntity::Deal]
+        );
+        assert_eq!(
+            (events[2].entity_id.as_deref(), events[2].operation),
+            (Some("c9"), Operation::Create)
+        );
+        assert_eq!(
+            (events[3].entity_type, events[3].operation, events[3].count),
+            (ChangedEntity::Multiple, Operation::Bulk, 40)
+        );
+        assert_eq!(
+            events[3].entity_types,
+            [ChangedEntity::Client, ChangedEntity::Document]
+        );
+    }
+
+    #[test]
+    fn test_disabled_emitter_is_silent() {
+        let (emitter, events) = captured();
+        emitter.set_enabled(false);
+        emitter.changed("u1", ChangedEntity::Client, "c1", Operation::Create);
+        let mut batch = emitter.batch("u1");
+        batch.record(ChangedEntity::Client, "c2", Operation::Create);
+        batch.record(ChangedEntity::Client, "c3", Operation::Create);
+        batch.finish();
+        assert!(events.lock().unwrap().is_empty());
+
+        emitter.set_enabled(true);
+        emitter.changed("u1", ChangedEntity::Client, "c1", Operation::Delete);
+        assert_eq!(events.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,1339 @@
+// src-tauri/src/data_export.rs
+//
+// Full export/import of a user's data for moving to a new PC
+// Export is a versioned JSON document (data.json); with include_documents it's a zip of
+// data.json + documents/{document_id}/{file}. Rows are stored as column -> value maps so an
+// export from an older schema still imports (unknown columns are dropped, missing ones default).
+// There is no separate notes table: notes live in the exported rows (descriptions, cobuyer data).
+
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, ToSql};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use zip::ZipArchive;
+
+use crate::app_state::AppState;
+use crate::archive::{
+    add_file_entry, create_writer, file_options, finish_writer, sanitize_entry_name,
+};
+use crate::data_events::{change_batch, ChangedEntity};
+use crate::database::{get_db, new_row_id};
+use crate::db_timeout::{with_query_timeout, EXPORT_QUERY_TIMEOUT};
+use crate::deal_cobuyers::backfill_cobuyers;
+use crate::deal_fees::backfill_doc_fee;
+use crate::secret_store::{self, SecretKey};
+use crate::settings_cache::flush_settings;
+use crate::storage::{get_documents_storage_path, invalidate_storage_stats};
+use crate::storage_usage::recompute_storage_usage;
+use crate::telemetry::track;
+use crate::vehicle_costs::backfill_purchase_cost;
+
+/// Bump when the layout of DataExport changes incompatibly
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+const DATA_ENTRY: &str = "data.json";
+const DOCUMENTS_PREFIX: &str = "documents";
+/// Restored documents that weren't under the old documents root
+const IMPORTED_DIR: &str = "imported";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Settings tied to this machine (ports, local folders); never exported or imported
+const MACHINE_SETTINGS: &[&str] = &[
+    "local_api_enabled",
+    "local_api_port",
+    "inventory_feed_schedule",
+];
+
+type Record = Map<String, Value>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataExport {
+    pub format_version: u32,
+    /// Highest migration applied on the exporting machine
+    pub schema_version: i64,
+    pub exported_at: String, // RFC 3339
+    pub user_id: String,
+    #[serde(default)]
+    pub documents_root: Option<String>,
+    #[serde(default)]
+    pub clients: Vec<Record>,
+    #[serde(default)]
+    pub vehicles: Vec<Record>,
+    #[serde(default)]
+    pub deals: Vec<Record>,
+    #[serde(default)]
+    pub documents: Vec<Record>,
+    #[serde(default)]
+    pub settings: Vec<Record>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExportSummary {
+    pub path: String,
+    pub clients: usize,
+    pub vehicles: usize,
+    pub deals: usize,
+    pub documents: usize,
+    pub settings: usize,
+    pub files: usize,
+    /// Document files that were missing on disk
+    pub skipped_files: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Keep existing rows; newer incoming rows update them
+    Merge,
+    /// Delete the user's clients, vehicles, deals and documents first
+    Replace,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct EntityReport {
+    pub entity: String,
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    /// Rows imported under a new id because theirs was taken by another account
+    pub remapped: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub mode: ImportMode,
+    pub entities: Vec<EntityReport>,
+    pub files_restored: usize,
+    pub warnings: Vec<String>,
+}
+
+/// Write everything the user owns (plus app settings) to output_path
+/// include_documents bundles the document files, making the output a zip
+#[tauri::command]
+pub fn export_all_data(
+    user_id: Option<String>,
+    output_path: String,
+    include_documents: bool,
+    state: State<'_, AppState>,
+) -> Result<ExportSummary, String> {
+    track("export_all_data", || {
+        let user_id_value = state.require_user(user_id)?;
+        info!("📦 Exporting all data to: {}", output_path);
+        // The export reads the settings table itself
+        flush_settings()?;
+
+        let export = with_query_timeout(EXPORT_QUERY_TIMEOUT, || {
+            let db = get_db().map_err(|e| e.to_string())?;
+            let conn = db.conn();
+            collect_export(&conn, &user_id_value, documents_root().ok())
+                .map_err(|e| format!("Failed to read data: {}", e))
+        })?;
+        let summary = write_export(&export, include_documents, Path::new(&output_path))?;
+
+        info!(
+            "✅ Data exported: {} clients, {} vehicles, {} deals, {} documents ({} files)",
+            summary.clients, summary.vehicles, summary.deals, summary.documents, summary.files
+        );
+        Ok(summary)
+    })
+}
+
+/// Import an export_all_data file into the current user's account in one transaction
+#[tauri::command]
+pub fn import_all_data(
+    path: String,
+    mode: ImportMode,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ImportReport, String> {
+    track("import_all_data", || {
+        let user_id_value = state.require_user(user_id)?;
+        info!("📥 Importing data ({:?}): {}", mode, path);
+        // Settings queued before the import mustn't overwrite imported ones afterwards
+        flush_settings()?;
+
+        let (export, mut bundle) = read_export(Path::new(&path))?;
+        let root = match &bundle {
+            Some(_) => Some(PathBuf::from(documents_root()?)),
+            None => None,
+        };
+        let entries = bundle.as_ref().map(bundled_documents).unwrap_or_default();
+
+        let report = {
+            let db = get_db().map_err(|e| e.to_string())?;
+            let mut conn = db.conn();
+            let report = import_data(
+                &mut conn,
+                &export,
+                &user_id_value,
+                mode,
+                root.as_deref().map(|root| (&entries, root)),
+                |restores| match bundle.as_mut() {
+                    Some(archive) => restore_files(archive, restores),
+                    None => Ok(0),
+                },
+            )?;
+            // The update hook already dropped what changed; start the rest over too
+            db.read_cache().clear();
+            report
+        };
+
+        // One summary event; a replace import lists every type it cleared
+        let mut changes = change_batch(&user_id_value);
+        for entity in &report.entities {
+            let changed = entity.inserted + entity.updated;
+            if changed == 0 && mode == ImportMode::Merge {
+                continue;
+            }
+            let kind = match entity.entity.as_str() {
+                "clients" => ChangedEntity::Client,
+                "vehicles" => ChangedEntity::Vehicle,
+                "deals" => ChangedEntity::Deal,
+                "documents" => ChangedEntity::Document,
+                _ => continue,
+            };
+            changes.record_count(kind, changed);
+        }
+        changes.finish();
+
+        if report.files_restored > 0 {
+            if let Err(e) = recompute_storage_usage() {
+                warn!("⚠️  Failed to recompute storage usage after import: {}", e);
+            }
+            invalidate_storage_stats();
+        }
+
+        info!(
+            "✅ Data imported: {} files restored, {} warnings",
+            report.files_restored,
+            report.warnings.len()
+        );
+        Ok(report)
+    })
+}
+
+// Export
+
+pub(crate) fn collect_export(
+    conn: &Connection,
+    user_id: &str,
+    documents_root: Option<String>,
+) -> rusqlite::Result<DataExport> {
+    let schema_version: Option<i64> =
+        conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+            row.get(0)
+        })?;
+    let owned = |table: &str| {
+        query_records(
+            conn,
+            &format!(
+                "SELECT * FROM {} WHERE user_id = ?1 ORDER BY created_at, id",
+                table
+            ),
+            &[&user_id],
+        )
+    };
+
+    let mut settings = query_records(conn, "SELECT * FROM settings ORDER BY key", &[])?;
+    settings.retain(|setting| !is_machine_setting(setting));
+
+    Ok(DataExport {
+        format_version: EXPORT_FORMAT_VERSION,
+        schema_version: schema_version.unwrap_or(0),
+        exported_at: Utc::now().to_rfc3339(),
+        user_id: user_id.to_string(),
+        documents_root,
+        clients: owned("clients")?,
+        vehicles: owned("vehicles")?,
+        deals: owned("deals")?,
+        // Documents inherit the deal's owner; their own user_id is often unset
+        documents: query_records(
+            conn,
+            "SELECT * FROM documents
+             WHERE deal_id IN (SELECT id FROM deals WHERE user_id = ?1)
+             ORDER BY created_at, id",
+            &[&user_id],
+        )?,
+        settings,
+    })
+}
+
+fn write_export(
+    export: &DataExport,
+    include_documents: bool,
+    output: &Path,
+) -> Result<ExportSummary, String> {
+    let json = serde_json::to_vec_pretty(export)
+        .map_err(|e| format!("Failed to serialize export: {}", e))?;
+    let mut summary = ExportSummary {
+        path: output.to_string_lossy().to_string(),
+        clients: export.clients.len(),
+        vehicles: export.vehicles.len(),
+        deals: export.deals.len(),
+        documents: export.documents.len(),
+        settings: export.settings.len(),
+        files: 0,
+        skipped_files: Vec::new(),
+    };
+
+    if !include_documents {
+        if let Some(parent) = output.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+        }
+        fs::write(output, json).map_err(|e| format!("Failed to write export: {}", e))?;
+        return Ok(summary);
+    }
+
+    let mut writer = create_writer(&summary.path)?;
+    let options = file_options(None);
+    writer
+        .start_file(DATA_ENTRY, options)
+        .map_err(|e| format!("Failed to add {}: {}", DATA_ENTRY, e))?;
+    writer
+        .write_all(&json)
+        .map_err(|e| format!("Failed to write {}: {}", DATA_ENTRY, e))?;
+
+    for document in &export.documents {
+        let (Some(id), Some(file_path)) = (text(document, "id"), text(document, "file_path"))
+        else {
+            continue;
+        };
+        let path = Path::new(file_path);
+        if !path.is_file() {
+            warn!("⚠️  Document file missing, skipping: {}", file_path);
+            summary.skipped_files.push(file_path.to_string());
+            continue;
+        }
+        let Some(entry) = document_entry(id, path) else {
+            summary.skipped_files.push(file_path.to_string());
+            continue;
+        };
+        add_file_entry(&mut writer, path, &entry, options)?;
+        summary.files += 1;
+    }
+
+    finish_writer(writer)?;
+    Ok(summary)
+}
+
+/// documents/{document_id}/{file name}
+fn document_entry(document_id: &str, path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+    let entry = format!("{}/{}/{}", DOCUMENTS_PREFIX, document_id, name);
+    sanitize_entry_name(&entry).ok()?;
+    Some(entry)
+}
+
+// Import
+
+type Bundle = ZipArchive<BufReader<File>>;
+
+/// A plain JSON export, or a zip with data.json and document files
+fn read_export(path: &Path) -> Result<(DataExport, Option<Bundle>), String> {
+    let mut file = File::open(path).map_err(|e| format!("Failed to open export: {}", e))?;
+    let mut magic = [0u8; 4];
+    let is_zip = file.read_exact(&mut magic).is_ok() && magic == ZIP_MAGIC;
+    let file = File::open(path).map_err(|e| format!("Failed to open export: {}", e))?;
+
+    if !is_zip {
+        let export = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Invalid export file: {}", e))?;
+        return Ok((export, None));
+    }
+
+    let mut archive = ZipArchive::new(BufReader::new(file))
+        .map_err(|e| format!("Invalid export archive: {}", e))?;
+    let export = {
+        let entry = archive
+            .by_name(DATA_ENTRY)
+            .map_err(|_| format!("Export archive has no {}", DATA_ENTRY))?;
+        serde_json::from_reader(entry).map_err(|e| format!("Invalid export file: {}", e))?
+    };
+    Ok((export, Some(archive)))
+}
+
+fn bundled_documents(archive: &Bundle) -> HashSet<String> {
+    archive
+        .file_names()
+        .filter(|name| name.starts_with(DOCUMENTS_PREFIX) && !name.ends_with('/'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// A bundled document file and where it goes under the new documents root
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct FileRestore {
+    entry: String,
+    destination: PathBuf,
+}
+
+/// Extract the files; on failure, remove what was written so the rollback leaves no strays
+fn restore_files(archive: &mut Bundle, restores: &[FileRestore]) -> Result<usize, String> {
+    let mut written: Vec<&Path> = Vec::new();
+    let result = restores.iter().try_for_each(|restore| {
+        let mut entry = archive
+            .by_name(&restore.entry)
+            .map_err(|e| format!("Failed to read {}: {}", restore.entry, e))?;
+        if let Some(parent) = restore.destination.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        let mut out = File::create(&restore.destination)
+            .map_err(|e| format!("Failed to create {}: {}", restore.destination.display(), e))?;
+        written.push(&restore.destination);
+        io::copy(&mut entry, &mut out)
+            .map_err(|e| format!("Failed to restore {}: {}", restore.entry, e))?;
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => Ok(restores.len()),
+        Err(e) => {
+            for path in written {
+                let _ = fs::remove_file(path);
+            }
+            Err(e)
+        }
+    }
+}
+
+enum Resolution {
+    Insert { id: String },
+    Update { id: String },
+    Unchanged { id: String },
+    Skip { reason: String },
+}
+
+/// Original id -> id in this database, per entity
+#[derive(Default)]
+struct IdMap(HashMap<String, String>);
+
+impl IdMap {
+    fn get(&self, id: Option<&str>) -> Option<String> {
+        id.and_then(|id| self.0.get(id)).cloned()
+    }
+}
+
+/// Import in dependency order (clients, vehicles, deals, documents, settings) in one
+/// transaction. files is (entries bundled with the export, documents root); restore is
+/// called with the files to extract just before commit and a failure rolls everything back
+pub(crate) fn import_data(
+    conn: &mut Connection,
+    export: &DataExport,
+    user_id: &str,
+    mode: ImportMode,
+    files: Option<(&HashSet<String>, &Path)>,
+    restore: impl FnOnce(&[FileRestore]) -> Result<usize, String>,
+) -> Result<ImportReport, String> {
+    validate_export(conn, export)?;
+
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("Failed to start import: {}", e))?;
+    let mut warnings = Vec::new();
+    let sql_err = |e: rusqlite::Error| format!("Import failed: {}", e);
+
+    if mode == ImportMode::Replace {
+        for sql in [
+            "DELETE FROM documents
+             WHERE user_id = ?1 OR deal_id IN (SELECT id FROM deals WHERE user_id = ?1)",
+            "DELETE FROM deals WHERE user_id = ?1",
+            "DELETE FROM vehicles WHERE user_id = ?1",
+            "DELETE FROM clients WHERE user_id = ?1",
+        ] {
+            tx.execute(sql, [user_id]).map_err(sql_err)?;
+        }
+    }
+
+    // Clients
+    let mut clients = EntityReport::named("clients");
+    let mut client_ids = IdMap::default();
+    let columns = table_columns(&tx, "clients").map_err(sql_err)?;
+    for record in &export.clients {
+        let resolution = resolve(&tx, "clients", record, user_id, mode).map_err(sql_err)?;
+        if let Some((from, to)) = apply(
+            &tx,
+            "clients",
+            &columns,
+            record.clone(),
+            resolution,
+            user_id,
+            &mut clients,
+            &mut warnings,
+        )
+        .map_err(sql_err)?
+        {
+            client_ids.0.insert(from, to);
+        }
+    }
+
+    // Vehicles (VIN is unique across accounts)
+    let mut vehicles = EntityReport::named("vehicles");
+    let mut vehicle_ids = IdMap::default();
+    let columns = table_columns(&tx, "vehicles").map_err(sql_err)?;
+    for record in &export.vehicles {
+        let resolution = resolve_vehicle(&tx, record, user_id, mode).map_err(sql_err)?;
+        if let Some((from, to)) = apply(
+            &tx,
+            "vehicles",
+            &columns,
+            record.clone(),
+            resolution,
+            user_id,
+            &mut vehicles,
+            &mut warnings,
+        )
+        .map_err(sql_err)?
+        {
+            vehicle_ids.0.insert(from, to);
+        }
+    }
+
+    // Cost rows aren't exported; rebuild the purchase cost row from the vehicle's cost
+    for vehicle_id in vehicle_ids.0.values() {
+        backfill_purchase_cost(&tx, vehicle_id, user_id).map_err(sql_err)?;
+    }
+
+    // Document ids are resolved before deals so deals.document_ids can be rewritten
+    let mut document_resolutions = Vec::with_capacity(export.documents.len());
+    let mut document_ids = IdMap::default();
+    for record in &export.documents {
+        let resolution = resolve(&tx, "documents", record, user_id, mode).map_err(sql_err)?;
+        if let (
+            Some(from),
+            Resolution::Insert { id } | Resolution::Update { id } | Resolution::Unchanged { id },
+        ) = (text(record, "id"), &resolution)
+        {
+            document_ids.0.insert(from.to_string(), id.clone());
+        }
+        document_resolutions.push(resolution);
+    }
+
+    // Deals
+    let mut deals = EntityReport::named("deals");
+    let mut deal_ids = IdMap::default();
+    let columns = table_columns(&tx, "deals").map_err(sql_err)?;
+    for record in &export.deals {
+        let mut record = record.clone();
+        let client = client_ids.get(text(&record, "client_id"));
+        let vehicle = vehicle_ids.get(text(&record, "vehicle_id"));
+        let resolution = match (client, vehicle) {
+            (Some(client), Some(vehicle)) => {
+                record.insert("client_id".into(), Value::String(client));
+                record.insert("vehicle_id".into(), Value::String(vehicle));
+                remap_document_ids(&mut record, &document_ids);
+                unlink_unknown_lender(&tx, &mut record, user_id, &mut warnings).map_err(sql_err)?;
+                resolve(&tx, "deals", &record, user_id, mode).map_err(sql_err)?
+            }
+            _ => Resolution::Skip {
+                reason: "its client or vehicle wasn't imported".to_string(),
+            },
+        };
+        if let Some((from, to)) = apply(
+            &tx,
+            "deals",
+            &columns,
+            record,
+            resolution,
+            user_id,
+            &mut deals,
+            &mut warnings,
+        )
+        .map_err(sql_err)?
+        {
+            deal_ids.0.insert(from, to);
+        }
+    }
+    // Fee lines and co-buyer rows aren't exported; rebuild them from doc_fee and
+    // cobuyer_data
+    for deal_id in deal_ids.0.values() {
+        backfill_doc_fee(&tx, deal_id, user_id).map_err(sql_err)?;
+        backfill_cobuyers(&tx, deal_id, user_id).map_err(sql_err)?;
+    }
+
+    // Documents
+    let mut documents = EntityReport::named("documents");
+    let mut restores = Vec::new();
+    let columns = table_columns(&tx, "documents").map_err(sql_err)?;
+    for (record, resolution) in export.documents.iter().zip(document_resolutions) {
+        let mut record = record.clone();
+        let resolution = match (deal_ids.get(text(&record, "deal_id")), resolution) {
+            (None, _) => Resolution::Skip {
+                reason: "its deal wasn't imported".to_string(),
+            },
+            (Some(deal_id), resolution) => {
+                record.insert("deal_id".into(), Value::String(deal_id));
+                resolution
+            }
+        };
+        if let (Resolution::Insert { id } | Resolution::Update { id }, Some((entries, root))) =
+            (&resolution, files)
+        {
+            if let Some(restore) = plan_restore(&record, id, entries, root, export) {
+                record.insert(
+                    "file_path".into(),
+                    Value::String(restore.destination.to_string_lossy().to_string()),
+                );
+                restores.push(restore);
+            }
+        }
+        apply(
+            &tx,
+            "documents",
+            &columns,
+            record,
+            resolution,
+            user_id,
+            &mut documents,
+            &mut warnings,
+        )
+        .map_err(sql_err)?;
+    }
+
+    // Settings: replace overwrites, merge only fills in missing keys
+    let mut settings = EntityReport::named("settings");
+    for setting in &export.settings {
+        let (Some(key), Some(value)) = (text(setting, "key"), text(setting, "value")) else {
+            settings.skipped += 1;
+            continue;
+        };
+        if is_machine_setting(setting) {
+            settings.skipped += 1;
+            continue;
+        }
+        let exists = tx
+            .query_row("SELECT 1 FROM settings WHERE key = ?1", [key], |_| Ok(()))
+            .optional()
+            .map_err(sql_err)?
+            .is_some();
+        match (exists, mode) {
+            (true, ImportMode::Merge) => settings.skipped += 1,
+            (exists, _) => {
+                tx.execute(
+                    "INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+                     ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = ?3",
+                    params![key, value, Utc::now().timestamp_millis()],
+                )
+                .map_err(sql_err)?;
+                if exists {
+                    settings.updated += 1;
+                } else {
+                    settings.inserted += 1;
+                }
+            }
+        }
+    }
+
+    let files_restored = restore(&restores)?;
+    tx.commit()
+        .map_err(|e| format!("Failed to commit import: {}", e))?;
+
+    Ok(ImportReport {
+        mode,
+        entities: vec![clients, vehicles, deals, documents, settings],
+        files_restored,
+        warnings,
+    })
+}
+
+fn validate_export(conn: &Connection, export: &DataExport) -> Result<(), String> {
+    if export.format_version != EXPORT_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported export format version {} (expected {})",
+            export.format_version, EXPORT_FORMAT_VERSION
+        ));
+    }
+    let local: Option<i64> = conn
+        .query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+            row.get(0)
+        })
+        .map_err(|e| e.to_string())?;
+    if export.schema_version > local.unwrap_or(0) {
+        return Err(format!(
+            "Export is from a newer version of the app (schema {}); update before importing",
+            export.schema_version
+        ));
+    }
+    Ok(())
+}
+
+impl EntityReport {
+    fn named(entity: &str) -> Self {
+        EntityReport {
+            entity: entity.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Decide what happens to a row whose id may already exist
+fn resolve(
+    conn: &Connection,
+    table: &str,
+    record: &Record,
+    user_id: &str,
+    mode: ImportMode,
+) -> rusqlite::Result<Resolution> {
+    let Some(id) = text(record, "id") else {
+        return Ok(Resolution::Skip {
+            reason: "it has no id".to_string(),
+        });
+    };
+    let owner = if table == "documents" {
+        "COALESCE(user_id, (SELECT user_id FROM deals WHERE deals.id = documents.deal_id))"
+    } else {
+        "user_id"
+    };
+    let existing: Option<(Option<String>, Option<i64>)> = conn
+        .query_row(
+            &format!("SELECT {}, updated_at FROM {} WHERE id = ?1", owner, table),
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+
+    Ok(match existing {
+        None => Resolution::Insert { id: id.to_string() },
+        Some((Some(owner), updated_at)) if owner == user_id => {
+            resolve_own(id.to_string(), record, updated_at, mode)
+        }
+        // Taken by another account (or a legacy row without one)
+        Some(_) => Resolution::Insert { id: new_row_id() },
+    })
+}
+
+fn resolve_vehicle(
+    conn: &Connection,
+    record: &Record,
+    user_id: &str,
+    mode: ImportMode,
+) -> rusqlite::Result<Resolution> {
+    let Some(vin) = text(record, "vin") else {
+        return resolve(conn, "vehicles", record, user_id, mode);
+    };
+    let existing: Option<(String, Option<String>, Option<i64>)> = conn
+        .query_row(
+            "SELECT id, user_id, updated_at FROM vehicles WHERE vin = ?1",
+            [vin],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+
+    match existing {
+        None => resolve(conn, "vehicles", record, user_id, mode),
+        // Same vehicle under another id (e.g. re-entered on the new PC): merge into it
+        Some((id, Some(owner), updated_at)) if owner == user_id => {
+            Ok(resolve_own(id, record, updated_at, mode))
+        }
+        Some(_) => Ok(Resolution::Skip {
+            reason: format!("VIN {} belongs to another account", vin),
+        }),
+    }
+}
+
+fn resolve_own(
+    id: String,
+    record: &Record,
+    existing_updated_at: Option<i64>,
+    mode: ImportMode,
+) -> Resolution {
+    let incoming = record.get("updated_at").and_then(Value::as_i64);
+    match mode {
+        ImportMode::Replace => Resolution::Update { id },
+        ImportMode::Merge if incoming > existing_updated_at => Resolution::Update { id },
+        ImportMode::Merge => Resolution::Unchanged { id },
+    }
+}
+
+/// Write the row and count it; returns (original id, id in this database) unless skipped
+#[allow(clippy::too_many_arguments)]
+fn apply(
+    conn: &Connection,
+    table: &str,
+    columns: &HashSet<String>,
+    mut record: Record,
+    resolution: Resolution,
+    user_id: &str,
+    report: &mut EntityReport,
+    warnings: &mut Vec<String>,
+) -> rusqlite::Result<Option<(String, String)>> {
+    let original = text(&record, "id").unwrap_or_default().to_string();
+    let (id, update) = match resolution {
+        Resolution::Skip { reason } => {
+            report.skipped += 1;
+            warnings.push(format!(
+                "Skipped {} {}: {}",
+                singular(table),
+                original,
+                reason
+            ));
+            return Ok(None);
+        }
+        Resolution::Unchanged { id } => {
+            report.skipped += 1;
+            return Ok(Some((original, id)));
+        }
+        Resolution::Insert { id } => (id, false),
+        Resolution::Update { id } => (id, true),
+    };
+
+    record.insert("id".into(), Value::String(id.clone()));
+    if columns.contains("user_id") {
+        record.insert("user_id".into(), Value::String(user_id.to_string()));
+    }
+    let mut names: Vec<&String> = record.keys().filter(|key| columns.contains(*key)).collect();
+    names.sort();
+    let values: Vec<SqlValue> = names.iter().map(|name| to_sql(&record[*name])).collect();
+
+    if update {
+        let assignments: Vec<String> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("\"{}\" = ?{}", name, i + 1))
+            .collect();
+        conn.execute(
+            &format!(
+                "UPDATE {} SET {} WHERE id = ?{}",
+                table,
+                assignments.join(", "),
+                names.len() + 1
+            ),
+            params_from_iter(
+                values
+                    .iter()
+                    .chain(std::iter::once(&SqlValue::Text(id.clone()))),
+            ),
+        )?;
+        report.updated += 1;
+    } else {
+        let quoted: Vec<String> = names.iter().map(|name| format!("\"{}\"", name)).collect();
+        let placeholders: Vec<String> = (1..=names.len()).map(|i| format!("?{}", i)).collect();
+        conn.execute(
+            &format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                table,
+                quoted.join(", "),
+                placeholders.join(", ")
+            ),
+            params_from_iter(values.iter()),
+        )?;
+        report.inserted += 1;
+    }
+
+    if id != original {
+        report.remapped += 1;
+    }
+    Ok(Some((original, id)))
+}
+
+fn remap_document_ids(deal: &mut Record, document_ids: &IdMap) {
+    let Some(ids) =
+        text(deal, "document_ids").and_then(|json| serde_json::from_str::<Vec<String>>(json).ok())
+    else {
+        return;
+    };
+    let ids: Vec<String> = ids
+        .into_iter()
+        .map(|id| document_ids.get(Some(&id)).unwrap_or(id))
+        .collect();
+    if let Ok(json) = serde_json::to_string(&ids) {
+        deal.insert("document_ids".into(), Value::String(json));
+    }
+}
+
+/// Files under the old documents root keep their relative layout; anything else goes
+/// to imported/{document_id}/
+fn plan_restore(
+    document: &Record,
+    new_id: &str,
+    entries: &HashSet<String>,
+    root: &Path,
+    export: &DataExport,
+) -> Option<FileRestore> {
+    let original_id = text(document, "id")?;
+    let prefix = format!("{}/{}/", DOCUMENTS_PREFIX, original_id);
+    let entry = entries
+        .iter()
+        .find(|entry| entry.starts_with(&prefix))?
+        .clone();
+    let file_name = sanitize_entry_name(&entry[prefix.len()..]).ok()?;
+
+    let relative = text(document, "file_path")
+        .zip(export.documents_root.as_deref())
+        .and_then(|(path, old_root)| {
+            let relative = Path::new(path).strip_prefix(old_root).ok()?;
+            sanitize_entry_name(&relative.to_string_lossy()).ok()
+        })
+        .unwrap_or_else(|| Path::new(IMPORTED_DIR).join(new_id).join(file_name));
+
+    Some(FileRestore {
+        entry,
+        destination: root.join(relative),
+    })
+}
+
+// Helpers
+
+pub(crate) fn documents_root() -> Result<String, String> {
+    match secret_store::get(SecretKey::DocumentsRootPath)? {
+        Some(path) => Ok(path),
+        None => get_documents_storage_path(),
+    }
+}
+
+fn query_records(
+    conn: &Connection,
+    sql: &str,
+    args: &[&dyn ToSql],
+) -> rusqlite::Result<Vec<Record>> {
+    let mut stmt = conn.prepare(sql)?;
+    let names: Vec<String> = stmt
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+    let records = stmt
+        .query_map(args, |row| {
+            let mut record = Record::new();
+            for (i, name) in names.iter().enumerate() {
+                record.insert(name.clone(), to_json(row.get_ref(i)?));
+            }
+            Ok(record)
+        })?
+        .collect();
+    records
+}
+
+fn table_columns(conn: &Connection, table: &str) -> rusqlite::Result<HashSet<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?.collect();
+    columns
+}
+
+pub(crate) fn to_json(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(n) => Value::from(n),
+        ValueRef::Real(n) => serde_json::Number::from_f64(n).map_or(Value::Null, Value::Number),
+        ValueRef::Text(text) => Value::String(String::from_utf8_lossy(text).to_string()),
+        // No BLOB columns today; keep them lossless if one appears
+        ValueRef::Blob(bytes) => Value::String(general_purpose::STANDARD.encode(bytes)),
+    }
+}
+
+fn to_sql(value: &Value) -> SqlValue {
+    match value {
+        Value::Null => SqlValue::Null,
+        Value::Bool(flag) => SqlValue::Integer(i64::from(*flag)),
+        Value::Number(n) => n
+            .as_i64()
+            .map(SqlValue::Integer)
+            .unwrap_or_else(|| SqlValue::Real(n.as_f64().unwrap_or_default())),
+        Value::String(text) => SqlValue::Text(text.clone()),
+        other => SqlValue::Text(other.to_string()),
+    }
+}
+
+/// Lenders aren't part of the export, so a deal keeps its lender_id only if this
+/// account already has that lender; otherwise the link is dropped rather than
+/// failing the foreign key
+fn unlink_unknown_lender(
+    conn: &Connection,
+    record: &mut Record,
+    user_id: &str,
+    warnings: &mut Vec<String>,
+) -> rusqlite::Result<()> {
+    let Some(lender_id) = text(record, "lender_id").map(str::to_string) else {
+        return Ok(());
+    };
+    let known = conn
+        .query_row(
+            "SELECT 1 FROM lenders WHERE id = ?1 AND user_id = ?2",
+            params![lender_id, user_id],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    if !known {
+        warnings.push(format!(
+            "Deal {} was imported without its lender ({} isn't in this account)",
+            text(record, "id").unwrap_or_default(),
+            lender_id
+        ));
+        record.insert("lender_id".into(), Value::Null);
+    }
+    Ok(())
+}
+
+fn text<'a>(record: &'a Record, key: &str) -> Option<&'a str> {
+    record.get(key).and_then(Value::as_str)
+}
+
+fn is_machine_setting(setting: &Record) -> bool {
+    text(setting, "key").is_some_and(|key| MACHINE_SETTINGS.contains(&key))
+}
+
+fn singular(table: &str) -> &str {
+    table.strip_suffix('s').unwrap_or(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    const USER: &str = "user-1";
+
+    /// Fresh database with every migration applied, in the order database.rs runs them
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/004_add_vehicle_images.sql"),
+            include_str!("../migrations/006_add_storage_usage.sql"),
+            include_str!("../migrations/007_add_document_signature.sql"),
+            include_str!("../migrations/008_add_command_metrics.sql"),
+            include_str!("../migrations/009_add_webhooks.sql"),
+            include_str!("../migrations/010_add_tax_rates.sql"),
+            include_str!("../migrations/011_add_document_templates.sql"),
+            include_str!("../migrations/012_add_template_packs.sql"),
+            include_str!("../migrations/013_add_odometer_title.sql"),
+            include_str!("../migrations/014_add_deal_status_history.sql"),
+            include_str!("../migrations/015_add_recent_items.sql"),
+            include_str!("../migrations/016_add_search_index.sql"),
+            include_str!("../migrations/017_add_document_versions.sql"),
+            include_str!("../migrations/018_add_tasks.sql"),
+            include_str!("../migrations/019_add_lenders.sql"),
+            include_str!("../migrations/020_add_deal_fees.sql"),
+            include_str!("../migrations/021_add_communications.sql"),
+            include_str!("../migrations/022_add_dealer_profile.sql"),
+            include_str!("../migrations/023_add_vehicle_costs.sql"),
+            include_str!("../migrations/024_unique_stock_numbers.sql"),
+            include_str!("../migrations/025_add_deal_cobuyers.sql"),
+            include_str!("../migrations/026_money_cents.sql"),
+            include_str!("../migrations/027_add_vehicle_holds.sql"),
+            include_str!("../migrations/028_add_retention.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute_batch(
+            "CREATE TABLE schema_migrations (version INTEGER PRIMARY KEY, applied_at TEXT NOT NULL);
+             INSERT INTO schema_migrations VALUES (28, 'now');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn seed(conn: &Connection, user: &str, suffix: &str, docs_root: &Path) {
+        conn.execute(
+            "INSERT INTO clients (id, first_name, last_name, email, created_at, updated_at, user_id)
+             VALUES (?1, 'Ada', 'Lovelace', 'ada@example.com', 1, 100, ?2)",
+            params![format!("client-{}", suffix), user],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status, images,
+                created_at, updated_at, user_id)
+             VALUES (?1, ?2, 2020, 'Honda', 'Civic', 12000, 18500.5, 'available',
+                '[\"https://cdn.test/1.jpg\"]', 1, 100, ?3)",
+            params![
+                format!("vehicle-{}", suffix),
+                format!("VIN{}", suffix),
+                user
+            ],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount, sale_amount,
+                document_ids, cobuyer_data, created_at, updated_at, user_id)
+             VALUES (?1, 'cash', ?2, ?3, 'sold', 20000.0, 19500.0, ?4, '{\"name\":\"Bob\"}', 1, 100, ?5)",
+            params![
+                format!("deal-{}", suffix),
+                format!("client-{}", suffix),
+                format!("vehicle-{}", suffix),
+                format!("[\"doc-{}\"]", suffix),
+                user
+            ],
+        )
+        .unwrap();
+
+        let file = docs_root.join("deals").join(format!("bill-{}.pdf", suffix));
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, format!("%PDF bill of sale {}", suffix)).unwrap();
+        conn.execute(
+            "INSERT INTO documents (id, deal_id, type, filename, file_path, file_size,
+                created_at, updated_at)
+             VALUES (?1, ?2, 'bill_of_sale', 'bill.pdf', ?3, 20, 1, 100)",
+            params![
+                format!("doc-{}", suffix),
+                format!("deal-{}", suffix),
+                file.to_string_lossy().to_string()
+            ],
+        )
+        .unwrap();
+    }
+
+    fn count(conn: &Connection, table: &str) -> i64 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    fn import_file(
+        conn: &mut Connection,
+        path: &Path,
+        mode: ImportMode,
+        root: &Path,
+    ) -> Result<ImportReport, String> {
+        let (export, mut bundle) = read_export(path)?;
+        let entries = bundle.as_ref().map(bundled_documents).unwrap_or_default();
+        import_data(
+            conn,
+            &export,
+            USER,
+            mode,
+            bundle.as_ref().map(|_| (&entries, root)),
+            |restores| match bundle.as_mut() {
+                Some(archive) => restore_files(archive, restores),
+                None => Ok(0),
+            },
+        )
+    }
+
+    fn entity<'a>(report: &'a ImportReport, name: &str) -> &'a EntityReport {
+        report.entities.iter().find(|e| e.entity == name).unwrap()
+    }
+
+    #[test]
+    fn test_export_wipe_import_roundtrip() {
+        let dir = temp_dir("data-export", "roundtrip");
+        let (old_root, new_root) = (dir.join("old-docs"), dir.join("new-docs"));
+        let source = test_db();
+        seed(&source, USER, "a", &old_root);
+        seed(&source, USER, "b", &old_root);
+        seed(&source, "someone-else", "c", &old_root);
+        source
+            .execute_batch(
+                "INSERT INTO settings VALUES ('dealer_name', 'Main St Motors', 1);
+                 INSERT INTO settings VALUES ('local_api_port', '5000', 1);",
+            )
+            .unwrap();
+
+        let export =
+            collect_export(&source, USER, Some(old_root.to_string_lossy().to_string())).unwrap();
+        let zip_path = dir.join("export.zip");
+        let summary = write_export(&export, true, &zip_path).unwrap();
+        assert_eq!(
+            (summary.clients, summary.vehicles, summary.deals),
+            (2, 2, 2)
+        );
+        assert_eq!(
+            (summary.documents, summary.files, summary.settings),
+            (2, 2, 1)
+        );
+
+        // "New PC": empty database, different documents root
+        let mut target = test_db();
+        let report = import_file(&mut target, &zip_path, ImportMode::Replace, &new_root).unwrap();
+        for name in ["clients", "vehicles", "deals", "documents"] {
+            assert_eq!(entity(&report, name).inserted, 2, "{}", name);
+            assert_eq!(count(&target, name), 2, "{}", name);
+        }
+        assert_eq!(entity(&report, "settings").inserted, 1);
+        assert_eq!(report.files_restored, 2);
+        assert!(report.warnings.is_empty());
+
+        // Key fields survive, and the re-export matches the original row for row
+        let again = collect_export(&target, USER, None).unwrap();
+        for (before, after) in [
+            (&export.clients, &again.clients),
+            (&export.vehicles, &again.vehicles),
+            (&export.deals, &again.deals),
+        ] {
+            assert_eq!(before, after);
+        }
+        let vin: String = target
+            .query_row(
+                "SELECT vin FROM vehicles WHERE id = 'vehicle-a'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(vin, "VINa");
+
+        // Files land under the new root with their relative layout, and file_path follows
+        let restored = new_root.join("deals").join("bill-a.pdf");
+        assert_eq!(
+            fs::read_to_string(&restored).unwrap(),
+            "%PDF bill of sale a"
+        );
+        let path: String = target
+            .query_row(
+                "SELECT file_path FROM documents WHERE id = 'doc-a'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(PathBuf::from(path), restored);
+
+        // Merging the same export again changes nothing
+        let report = import_file(&mut target, &zip_path, ImportMode::Merge, &new_root).unwrap();
+        assert_eq!(entity(&report, "deals").skipped, 2);
+        assert_eq!(
+            entity(&report, "deals").inserted + entity(&report, "deals").updated,
+            0
+        );
+        assert_eq!(count(&target, "deals"), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_merge_remaps_ids_taken_by_another_account() {
+        let dir = temp_dir("data-export", "remap");
+        let source = test_db();
+        seed(&source, USER, "a", &dir.join("docs"));
+        let export = collect_export(&source, USER, None).unwrap();
+        let json_path = dir.join("export.json");
+        write_export(&export, false, &json_path).unwrap();
+
+        // The same ids exist here, owned by someone else
+        let mut target = test_db();
+        seed(&target, "someone-else", "a", &dir.join("other"));
+        target
+            .execute(
+                "UPDATE vehicles SET vin = 'OTHERVIN' WHERE id = 'vehicle-a'",
+                [],
+            )
+            .unwrap();
+
+        let report = import_file(&mut target, &json_path, ImportMode::Merge, &dir).unwrap();
+        for name in ["clients", "vehicles", "deals", "documents"] {
+            assert_eq!(entity(&report, name).inserted, 1, "{}", name);
+            assert_eq!(entity(&report, name).remapped, 1, "{}", name);
+        }
+
+        // The imported deal points at the imported client/vehicle/document, not the originals
+        let (deal_client, deal_vehicle, document_ids): (String, String, String) = target
+            .query_row(
+                "SELECT client_id, vehicle_id, document_ids FROM deals WHERE user_id = ?1",
+                [USER],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        let client: String = target
+            .query_row("SELECT id FROM clients WHERE user_id = ?1", [USER], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        let document: String = target
+            .query_row(
+                "SELECT d.id FROM documents d JOIN deals ON deals.id = d.deal_id WHERE deals.user_id = ?1",
+                [USER],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(deal_client, client);
+        assert_ne!(deal_client, "client-a");
+        assert_ne!(deal_vehicle, "vehicle-a");
+        assert_eq!(document_ids, format!("[\"{}\"]", document));
+
+        // Without a bundle, document metadata keeps the original path
+        let path: String = target
+            .query_row(
+                "SELECT file_path FROM documents WHERE id = ?1",
+                [&document],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(
+            PathBuf::from(path),
+            dir.join("docs").join("deals").join("bill-a.pdf")
+        );
+        assert_eq!(count(&target, "deals"), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_vin_owned_by_another_account_skips_dependents() {
+        let dir = temp_dir("data-export", "vin");
+        let source = test_db();
+        seed(&source, USER, "a", &dir);
+        let export = collect_export(&source, USER, None).unwrap();
+
+        let mut target = test_db();
+        seed(&target, "someone-else", "x", &dir.join("other"));
+        target
+            .execute(
+                "UPDATE vehicles SET vin = 'VINa' WHERE id = 'vehicle-x'",
+                [],
+            )
+            .unwrap();
+
+        let report = import_data(&mut target, &export, USER, ImportMode::Merge, None, |_| {
+            Ok(0)
+        })
+        .unwrap();
+        assert_eq!(entity(&report, "clients").inserted, 1);
+        assert_eq!(entity(&report, "vehicles").skipped, 1);
+        assert_eq!(entity(&report, "deals").skipped, 1);
+        assert_eq!(entity(&report, "documents").skipped, 1);
+        assert_eq!(report.warnings.len(), 3);
+        assert!(report.warnings[0].contains("VIN VINa belongs to another account"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_rejects_unknown_versions_and_rolls_back_on_file_failure() {
+        let dir = temp_dir("data-export", "versions");
+        let source = test_db();
+        seed(&source, USER, "a", &dir);
+        let mut target = test_db();
+
+        let mut export = collect_export(&source, USER, None).unwrap();
+        export.format_version = EXPORT_FORMAT_VERSION + 1;
+        let err = import_data(&mut target, &export, USER, ImportMode::Merge, None, |_| {
+            Ok(0)
+        });
+        assert!(err
+            .unwrap_err()
+            .contains("Unsupported export format version"));
+
+        export.format_version = EXPORT_FORMAT_VERSION;
+        export.schema_version = 99;
+        let err = import_data(&mut target, &export, USER, ImportMode::Merge, None, |_| {
+            Ok(0)
+        });
+        assert!(err.unwrap_err().contains("newer version"));
+
+        // A failed file restore leaves the database untouched
+        export.schema_version = 9;
+        let err = import_data(
+            &mut target,
+            &export,
+            USER,
+            ImportMode::Replace,
+            None,
+            |_| Err("disk full".to_string()),
+        );
+        assert_eq!(err.unwrap_err(), "disk full");
+        assert_eq!(count(&target, "clients"), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
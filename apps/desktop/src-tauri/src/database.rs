@@ -3,26 +3,66 @@
 // SQLite database module for standalone operation
 // Handles schema, migrations, and all database operations
 
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
 use chrono::Utc;
-use log::info;
-use rusqlite::{params, Connection, Result as SqlResult, Row};
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Row};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 use std::fs;
+use tauri::State;
 
-use crate::storage::get_app_data_dir;
+use crate::address_lookup::normalize_client_address;
+use crate::app_state::AppState;
+use crate::contact_normalization::{backfill_contacts, normalize_contact, phone_search_digits};
+use crate::deal_status::{check_transition, normalize_status, record_transition};
+use crate::deal_validation::{completion_issues_for, validate_paperwork_fields};
+use crate::deal_fees::replace_doc_fee;
+use crate::deal_cobuyers::{cobuyers_by_deal, legacy_cobuyer, migrate_cobuyer_data, replace_first_cobuyer, DealCobuyer};
+use crate::db_busy::{retry_busy, DbError, BUSY_TIMEOUT};
+use crate::db_timeout::watch_connection;
+use crate::datetime::dealer_timezone;
+use crate::disk_space::DiskSpaceError;
+use crate::data_export::documents_root;
+use crate::document_trash::{trash_document_file, TrashedDocument};
+use crate::idempotency::{remember, replay};
+use crate::lenders::{check_lender, Lender};
+use crate::permissions::{redact_deals_stats, redact_vehicle, redact_vehicles, Permission};
+use crate::field_projection::{ListEntity, Listing, Projection};
+use crate::money::{backup_before_conversion, from_cents, record_conversion, round_to_cents};
+use crate::document_versions::{
+    max_versions, remove_version_files, snapshot_document, version_file_paths,
+};
+use crate::stock_numbers::{fix_duplicate_stock_numbers, normalize_stock_number, record_stock_number_fixes, stock_number_error};
+use crate::startup_migrations::{run_migrations, MigrationStep};
+use crate::storage::{get_app_data_dir, get_backup_path};
+use crate::storage_usage::{adjust_usage, check_quota, deal_owner};
+use crate::recent_items::EntityType;
+use crate::reporting::run_report_job;
+use crate::telemetry::{track, track_async};
+use crate::vehicle_holds::check_vehicle_hold;
+use crate::vehicle_costs::{gross_profit_by_month, set_total_cost};
+use crate::vehicle_history::record_status_change;
+use crate::validation_rules::{enforce_rules, Entity};
+use crate::settings_cache::{get_setting, set_setting};
+use crate::read_cache::ReadCache;
+use crate::data_events::{change_batch, data_changed, ChangedEntity, Operation};
+use crate::webhooks::{notify, WebhookEvent};
 
 // Database connection wrapper
 pub struct Database {
     conn: Arc<Mutex<Connection>>,
+    read_cache: Arc<ReadCache>,
 }
 
 impl Database {
     /// Get database path (internal helper)
-    fn get_db_path() -> SqlResult<PathBuf> {
+    pub(crate) fn get_db_path() -> SqlResult<PathBuf> {
         #[cfg(debug_assertions)]
         {
             // Development: use db/ folder in app root
@@ -73,13 +113,37 @@ impl Database {
         }
     }
     
-    /// Initialize database connection
+    /// Initialize database connection (the app's database, at get_db_path). Pending
+    /// migrations are preceded by a copy of the file in the backups folder
     pub fn init() -> SqlResult<Self> {
-        let db_path = Self::get_db_path()?;
-        
+        let backup_dir = get_backup_path()
+            .map_err(|e| rusqlite::Error::InvalidPath(format!("Failed to get backup dir: {}", e).into()))?;
+        Self::init_with_backups(&Self::get_db_path()?, Path::new(&backup_dir))
+    }
+    
+    /// Open (or create) the database at db_path and run migrations, backing up into a
+    /// "backups" folder next to it
+    pub fn init_with_path(db_path: &Path) -> SqlResult<Self> {
+        Self::init_with_backups(db_path, &db_path.with_file_name("backups"))
+    }
+    
+    /// Open the database at db_path and run migrations; if one fails, the copy taken into
+    /// backup_dir beforehand is put back (startup_migrations.rs)
+    pub(crate) fn init_with_backups(db_path: &Path, backup_dir: &Path) -> SqlResult<Self> {
+        let db = Self::open(db_path)?;
+        run_migrations(db, db_path, backup_dir, |db, progress| {
+            db.migrate_with_progress(i32::MAX, progress)
+        })
+    }
+    
+    /// Open (or create) the database at db_path without migrating it
+    pub(crate) fn open(db_path: &Path) -> SqlResult<Self> {
         info!("Opening SQLite database at: {}", db_path.display());
         
-        let conn = Connection::open(&db_path)?;
+        let conn = Connection::open(db_path)?;
+        
+        // Wait out short locks held by other connections instead of failing at once
+        conn.busy_timeout(BUSY_TIMEOUT)?;
         
         // Enable foreign keys
         conn.execute("PRAGMA foreign_keys = ON", [])?;
@@ -88,18 +152,47 @@ impl Database {
         // PRAGMA journal_mode returns a value, so we need to use query_row
         let _journal_mode: String = conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))?;
         
+        // Commands' time budgets and the slow-statement log
+        watch_connection(&conn);
+        let read_cache = ReadCache::watching(&conn);
+        
+        Ok(Database {
+            conn: Arc::new(Mutex::new(conn)),
+            read_cache,
+        })
+    }
+    
+    /// A private in-memory database with every migration applied (tests)
+    pub fn init_in_memory() -> SqlResult<Self> {
+        Self::init_in_memory_at(i32::MAX)
+    }
+    
+    /// An in-memory database migrated only up to `version`, for code that must cope with
+    /// older schemas
+    pub(crate) fn init_in_memory_at(version: i32) -> SqlResult<Self> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute("PRAGMA foreign_keys = ON", [])?;
+        watch_connection(&conn);
+        let read_cache = ReadCache::watching(&conn);
         let db = Database {
             conn: Arc::new(Mutex::new(conn)),
+            read_cache,
         };
-        
-        // Run migrations
-        db.migrate()?;
-        
+        db.migrate_to(version)?;
         Ok(db)
     }
     
-    /// Run database migrations
-    fn migrate(&self) -> SqlResult<()> {
+    /// Run database migrations up to and including `target`
+    pub(crate) fn migrate_to(&self, target: i32) -> SqlResult<()> {
+        self.migrate_with_progress(target, &mut |_| {})
+    }
+    
+    /// migrate_to, calling `progress` as each migration starts
+    pub(crate) fn migrate_with_progress(
+        &self,
+        target: i32,
+        progress: &mut dyn FnMut(MigrationStep),
+    ) -> SqlResult<()> {
         let conn = self.conn.lock().unwrap();
         
         // Create migrations table
@@ -112,19 +205,26 @@ impl Database {
         )?;
         
         // Get current version
-        let current_version: i32 = conn
-            .query_row(
-                "SELECT MAX(version) FROM schema_migrations",
-                [],
-                |row| row.get(0),
-            )
-            .unwrap_or(0);
+        let current_version = schema_version(&conn);
         
         info!("Current database version: {}", current_version);
+        let pending = |version: i32| current_version < version && version <= target;
+        let total = (LATEST_SCHEMA_VERSION.min(target) - current_version).max(0) as usize;
+        let mut index = 0;
+        let mut step = |version: i32, description: &str| {
+            index += 1;
+            info!("Running migration {}: {}", version, description);
+            progress(MigrationStep {
+                version,
+                step: description.to_string(),
+                index,
+                total,
+            });
+        };
         
         // Migration 1: Initial schema
-        if current_version < 1 {
-            info!("Running migration 1: Initial schema");
+        if pending(1) {
+            step(1, "Initial schema");
             conn.execute_batch(include_str!("../migrations/001_initial_schema.sql"))?;
             
             conn.execute(
@@ -134,8 +234,8 @@ impl Database {
         }
         
         // Migration 2: Add sync fields
-        if current_version < 2 {
-            info!("Running migration 2: Add sync fields");
+        if pending(2) {
+            step(2, "Add sync fields");
             conn.execute_batch(include_str!("../migrations/002_add_sync_fields.sql"))?;
             
             conn.execute(
@@ -145,8 +245,8 @@ impl Database {
         }
         
         // Migration 3: Add document file paths
-        if current_version < 3 {
-            info!("Running migration 3: Add document file paths");
+        if pending(3) {
+            step(3, "Add document file paths");
             conn.execute_batch(include_str!("../migrations/003_add_document_paths.sql"))?;
             
             conn.execute(
@@ -156,8 +256,8 @@ impl Database {
         }
         
         // Migration 5: Add user_id for user isolation
-        if current_version < 5 {
-            info!("Running migration 5: Add user_id to all tables");
+        if pending(5) {
+            step(5, "Add user_id to all tables");
             conn.execute_batch(include_str!("../migrations/005_add_user_id.sql"))?;
             
             conn.execute(
@@ -167,8 +267,8 @@ impl Database {
         }
         
         // Migration 4: Add images column to vehicles table
-        if current_version < 4 {
-            info!("Running migration 4: Add images column to vehicles");
+        if pending(4) {
+            step(4, "Add images column to vehicles");
             conn.execute_batch(include_str!("../migrations/004_add_vehicle_images.sql"))?;
             
             conn.execute(
@@ -177,14 +277,412 @@ impl Database {
             )?;
         }
         
+        // Migration 6: Per-user storage usage and quotas
+        if pending(6) {
+            step(6, "Add storage usage table");
+            conn.execute_batch(include_str!("../migrations/006_add_storage_usage.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (6, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        // Migration 7: Document signatures
+        if pending(7) {
+            step(7, "Add document signature column");
+            conn.execute_batch(include_str!("../migrations/007_add_document_signature.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (7, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        // Migration 8: Command timing metrics
+        if pending(8) {
+            step(8, "Add command metrics table");
+            conn.execute_batch(include_str!("../migrations/008_add_command_metrics.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (8, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        // Migration 9: Outbound webhooks
+        if pending(9) {
+            step(9, "Add webhook tables");
+            conn.execute_batch(include_str!("../migrations/009_add_webhooks.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (9, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        // Migration 10: Sales tax rates
+        if pending(10) {
+            step(10, "Add tax rates table");
+            conn.execute_batch(include_str!("../migrations/010_add_tax_rates.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (10, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(11) {
+            step(11, "Add document templates table");
+            conn.execute_batch(include_str!("../migrations/011_add_document_templates.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (11, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(12) {
+            step(12, "Add template pack columns");
+            conn.execute_batch(include_str!("../migrations/012_add_template_packs.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (12, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+
+        if pending(13) {
+            step(13, "Add odometer and title columns to deals");
+            conn.execute_batch(include_str!("../migrations/013_add_odometer_title.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (13, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+
+        if pending(14) {
+            step(14, "Add deal status history");
+            conn.execute_batch(include_str!("../migrations/014_add_deal_status_history.sql"))?;
+            
+            let legacy: i64 = conn.query_row(
+                "SELECT COUNT(*) FROM deals WHERE status NOT IN
+                    ('quote', 'pending', 'approved', 'sold', 'completed', 'cancelled')",
+                [],
+                |row| row.get(0),
+            )?;
+            if legacy > 0 {
+                warn!("⚠️  {} deal(s) have a status outside the lifecycle; keeping it until they next change status", legacy);
+            }
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (14, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+
+        if pending(15) {
+            step(15, "Add recent items and favorites");
+            conn.execute_batch(include_str!("../migrations/015_add_recent_items.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (15, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+
+        if pending(16) {
+            step(16, "Add search index");
+            conn.execute_batch(include_str!("../migrations/016_add_search_index.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (16, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(17) {
+            step(17, "Add document versions");
+            conn.execute_batch(include_str!("../migrations/017_add_document_versions.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (17, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(18) {
+            step(18, "Add tasks");
+            conn.execute_batch(include_str!("../migrations/018_add_tasks.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (18, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(19) {
+            step(19, "Add lenders");
+            conn.execute_batch(include_str!("../migrations/019_add_lenders.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (19, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(20) {
+            step(20, "Add deal fees");
+            conn.execute_batch(include_str!("../migrations/020_add_deal_fees.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (20, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(21) {
+            step(21, "Add communications");
+            conn.execute_batch(include_str!("../migrations/021_add_communications.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (21, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(22) {
+            step(22, "Add dealer profile");
+            conn.execute_batch(include_str!("../migrations/022_add_dealer_profile.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (22, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(23) {
+            step(23, "Add vehicle costs");
+            conn.execute_batch(include_str!("../migrations/023_add_vehicle_costs.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (23, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(24) {
+            step(24, "Unique stock numbers");
+            // Duplicates would stop the unique index being created, so rename them first
+            let fixes = fix_duplicate_stock_numbers(&conn)?;
+            conn.execute_batch(include_str!("../migrations/024_unique_stock_numbers.sql"))?;
+            record_stock_number_fixes(&conn, &fixes)?;
+            if !fixes.is_empty() {
+                warn!("⚠️  {} duplicate stock number(s) renamed; see get_stock_number_fixes", fixes.len());
+            }
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (24, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(25) {
+            step(25, "Add deal co-buyers");
+            conn.execute_batch(include_str!("../migrations/025_add_deal_cobuyers.sql"))?;
+            
+            let (copied, unreadable) = migrate_cobuyer_data(&conn)?;
+            info!("Copied co-buyer data of {} deal(s) into deal_cobuyers", copied);
+            if unreadable > 0 {
+                warn!("⚠️  {} deal(s) have co-buyer data that can't be read; see get_cobuyer_migration_issues", unreadable);
+            }
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (25, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(26) {
+            step(26, "Money in integer cents");
+            // The copy to put back if the conversion has to be undone. VACUUM INTO can't run
+            // inside a transaction, so it's taken first
+            let backup = backup_before_conversion(&conn)?;
+            let tx = conn.unchecked_transaction()?;
+            tx.execute_batch(include_str!("../migrations/026_money_cents.sql"))?;
+            
+            let (converted, subcent) = record_conversion(&tx, backup.as_deref())?;
+            info!("Converted {} amount(s) to cents", converted);
+            if subcent > 0 {
+                warn!("⚠️  {} amount(s) had a fraction of a cent and were rounded; see get_money_conversion_report", subcent);
+            }
+            
+            tx.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (26, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+            tx.commit()?;
+        }
+        
+        if pending(27) {
+            step(27, "Add vehicle holds");
+            conn.execute_batch(include_str!("../migrations/027_add_vehicle_holds.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (27, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(28) {
+            step(28, "Add data retention and the audit log");
+            conn.execute_batch(include_str!("../migrations/028_add_retention.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (28, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(29) {
+            step(29, "Add encrypted sensitive fields");
+            conn.execute_batch(include_str!("../migrations/029_add_sensitive_fields.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (29, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(30) {
+            step(30, "Add vehicle events");
+            conn.execute_batch(include_str!("../migrations/030_add_vehicle_events.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (30, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(31) {
+            step(31, "Add user roles");
+            conn.execute_batch(include_str!("../migrations/031_add_user_roles.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (31, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(32) {
+            step(32, "Add title export formats");
+            conn.execute_batch(include_str!("../migrations/032_add_title_export_formats.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (32, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(33) {
+            step(33, "Add valuation cache");
+            conn.execute_batch(include_str!("../migrations/033_add_valuations.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (33, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(34) {
+            step(34, "Add temp tags");
+            conn.execute_batch(include_str!("../migrations/034_add_temp_tags.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (34, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(35) {
+            step(35, "Add sample data flags");
+            conn.execute_batch(include_str!("../migrations/035_add_sample_data_flags.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (35, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(36) {
+            step(36, "Normalize client phones and emails");
+            let tx = conn.unchecked_transaction()?;
+            tx.execute_batch(include_str!("../migrations/036_normalize_contacts.sql"))?;
+            
+            let backfilled = backfill_contacts(&tx)?;
+            info!("Normalized the phone and email of {} client(s)", backfilled);
+            
+            tx.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (36, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+            tx.commit()?;
+        }
+        
+        if pending(37) {
+            step(37, "Add dashboard statistics cache");
+            conn.execute_batch(include_str!("../migrations/037_add_stats_cache.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (37, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
+        if pending(38) {
+            step(38, "Add idempotency keys");
+            conn.execute_batch(include_str!("../migrations/038_add_idempotency_keys.sql"))?;
+            
+            conn.execute(
+                "INSERT INTO schema_migrations (version, applied_at) VALUES (38, ?)",
+                params![Utc::now().to_rfc3339()],
+            )?;
+        }
+        
         info!("✅ Database migrations complete");
         Ok(())
     }
     
     /// Get database connection (for internal use)
-    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+    pub(crate) fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
         self.conn.lock().unwrap()
     }
+
+    /// Cached settings and dealer profiles, kept in step with this connection (read_cache.rs)
+    pub(crate) fn read_cache(&self) -> &ReadCache {
+        &self.read_cache
+    }
+
+    /// Like conn(), but reports a poisoned lock instead of panicking (diagnostics)
+    pub(crate) fn try_conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>, String> {
+        self.conn
+            .lock()
+            .map_err(|_| "Database lock poisoned by an earlier panic".to_string())
+    }
+}
+
+/// The newest migration migrate_to knows
+pub(crate) const LATEST_SCHEMA_VERSION: i32 = 38;
+
+/// Highest applied migration; 0 for a new database
+pub(crate) fn schema_version(conn: &Connection) -> i32 {
+    conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+        row.get::<_, Option<i32>>(0)
+    })
+    .ok()
+    .flatten()
+    .unwrap_or(0)
 }
 
 // Singleton database instance
@@ -192,17 +690,95 @@ static DB: once_cell::sync::OnceCell<Database> = once_cell::sync::OnceCell::new(
 
 /// Initialize database (called during Tauri startup)
 pub fn init_database() -> SqlResult<()> {
-    DB.get_or_try_init(Database::init)
+    DB.get_or_try_init(open_shared_db)
         .map_err(|e| rusqlite::Error::InvalidPath(format!("Failed to init database: {}", e).into()))?;
     Ok(())
 }
 
 /// Get or initialize database instance
 pub fn get_db() -> SqlResult<&'static Database> {
-    DB.get_or_try_init(Database::init)
+    DB.get_or_try_init(open_shared_db)
         .map_err(|e| rusqlite::Error::InvalidPath(format!("Failed to init database: {}", e).into()))
 }
 
+/// Tests never touch the on-disk database, even through code that still calls get_db()
+fn open_shared_db() -> SqlResult<Database> {
+    if cfg!(test) {
+        Database::init_in_memory()
+    } else {
+        Database::init()
+    }
+}
+
+/// The database as Tauri-managed state (app.manage). Commands take State<'_, DbState> rather
+/// than calling get_db(), so tests can hand them an in-memory database (see test_support).
+/// The app manages DbState::default(), a thin wrapper around the shared database
+#[derive(Default)]
+pub struct DbState {
+    db: Option<Database>,
+}
+
+impl DbState {
+    #[cfg(test)]
+    pub(crate) fn new(db: Database) -> Self {
+        DbState { db: Some(db) }
+    }
+
+    pub fn get(&self) -> SqlResult<&Database> {
+        match &self.db {
+            Some(db) => Ok(db),
+            None => get_db(),
+        }
+    }
+}
+
+/// Fold the WAL back into the main database file (shutdown). No-op if the database was never opened
+pub(crate) fn checkpoint_wal() -> Result<(), String> {
+    let Some(db) = DB.get() else {
+        return Ok(());
+    };
+    let conn = db.try_conn()?;
+    // (busy, wal frames, checkpointed frames); busy = 1 means readers kept it from finishing
+    let busy: i64 = conn
+        .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))
+        .map_err(|e| format!("WAL checkpoint failed: {}", e))?;
+    if busy != 0 {
+        return Err("WAL checkpoint blocked by another connection".to_string());
+    }
+    Ok(())
+}
+
+/// Full PRAGMA integrity_check (startup after an unclean shutdown)
+pub(crate) fn integrity_check() -> Result<(), String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    let conn = db.try_conn()?;
+    let result: String = conn
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| format!("Integrity check failed to run: {}", e))?;
+    if result == "ok" {
+        Ok(())
+    } else {
+        Err(result)
+    }
+}
+
+/// Random UUID v4 for rows created on the Rust side (imports, id conflicts)
+pub(crate) fn new_row_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
 // ============================================================================
 // CLIENT OPERATIONS
 // ============================================================================
@@ -255,53 +831,84 @@ impl Client {
 }
 
 #[tauri::command]
-pub fn db_create_client(client: Client, user_id: Option<String>) -> Result<Client, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
-    
-    conn.execute(
-        "INSERT INTO clients (
-            id, user_id, first_name, last_name, email, phone, address, city, state, zip_code,
-            drivers_license, created_at, updated_at
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
-        params![
-            client.id,
-            user_id_value,
-            client.first_name,
-            client.last_name,
-            client.email,
-            client.phone,
-            client.address,
-            client.city,
-            client.state,
-            client.zip_code,
-            client.drivers_license,
-            client.created_at,
-            client.updated_at,
-        ],
-    )
-    .map_err(|e| e.to_string())?;
-    
-    info!("✅ Client created: {} for user: {}", client.id, user_id_value);
-    Ok(Client {
-        user_id: Some(user_id_value.clone()),
-        ..client
+/// normalize_address cleans up the address first (see address_lookup::normalize);
+/// idempotency_key makes a retried call return the client the first one created
+/// (idempotency.rs)
+pub fn db_create_client(mut client: Client, user_id: Option<String>, normalize_address: Option<bool>, idempotency_key: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Client, DbError> {
+    track("db_create_client", || {
+        if normalize_address.unwrap_or(false) {
+            normalize_client_address(&mut client);
+        }
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        let user_id_value = &state.require_user(user_id)?;
+        let now = Utc::now().timestamp_millis();
+        let key = idempotency_key.as_deref();
+        // The key is looked up and recorded in the insert's transaction
+        let tx = begin_write(&conn, "clients")?;
+        if let Some(created) = replay(&tx, user_id_value, key, EntityType::Client, now, |id| client_for_user(&tx, id, user_id_value))? {
+            tx.commit().map_err(|e| e.to_string())?;
+            return Ok(created);
+        }
+        enforce_rules(&tx, Entity::Client, &client)?;
+        let contact = normalize_contact(&mut client);
+    
+        tx.execute(
+            "INSERT INTO clients (
+                id, user_id, first_name, last_name, email, phone, address, city, state, zip_code,
+                drivers_license, created_at, updated_at, phone_e164, phone_invalid, email_normalized
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params![
+                client.id,
+                user_id_value,
+                client.first_name,
+                client.last_name,
+                client.email,
+                client.phone,
+                client.address,
+                client.city,
+                client.state,
+                client.zip_code,
+                client.drivers_license,
+                client.created_at,
+                client.updated_at,
+                contact.phone_e164,
+                contact.phone_invalid,
+                contact.email_normalized,
+            ],
+        ).map_err(|e| e.to_string())?;
+        remember(&tx, user_id_value, key, EntityType::Client, &client.id, now).map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+    
+        info!("✅ Client created: {} for user: {}", client.id, user_id_value);
+        let client = Client {
+            user_id: Some(user_id_value.clone()),
+            ..client
+        };
+        notify(user_id_value, WebhookEvent::ClientCreated, &client);
+        data_changed(user_id_value, ChangedEntity::Client, &client.id, Operation::Create);
+        Ok(client)
     })
 }
 
 #[tauri::command]
-pub fn db_get_client(id: String, user_id: Option<String>) -> Result<Option<Client>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
+pub fn db_get_client(id: String, user_id: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Option<Client>, String> {
+    track("db_get_client", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
     
+        let user_id_value = &state.require_user(user_id)?;
+        client_for_user(&conn, &id, user_id_value)
+    })
+}
+
+/// Client by id, only if user_id owns it (also used by db_batch)
+pub(crate) fn client_for_user(conn: &Connection, id: &str, user_id_value: &str) -> Result<Option<Client>, String> {
     let mut stmt = conn
         .prepare("SELECT * FROM clients WHERE id = ?1 AND user_id = ?2")
         .map_err(|e| e.to_string())?;
-    
+
     match stmt.query_row(params![id, user_id_value], Client::from_row) {
         Ok(client) => Ok(Some(client)),
         Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
@@ -310,118 +917,250 @@ pub fn db_get_client(id: String, user_id: Option<String>) -> Result<Option<Clien
 }
 
 #[tauri::command]
-pub fn db_get_all_clients(user_id: Option<String>) -> Result<Vec<Client>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
+/// fields: only these columns, as JSON objects (see field_projection.rs)
+pub fn db_get_all_clients(user_id: Option<String>, fields: Option<Vec<String>>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Listing<Client>, String> {
+    track("db_get_all_clients", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        if let Some(fields) = fields {
+            let projection = Projection::parse(ListEntity::Client, &fields, state.role())?;
+            let rows = projection
+                .query(&db.conn(), "user_id = ?1 ORDER BY created_at DESC", params![user_id_value])
+                .map_err(|e| e.to_string())?;
+            return Ok(Listing::Fields(rows));
+        }
+        get_clients_for_user(db, &user_id_value).map(Listing::Full)
+    })
+}
+
+/// All clients owned by user_id, newest first (also used by the vCard export)
+pub(crate) fn get_clients_for_user(db: &Database, user_id_value: &str) -> Result<Vec<Client>, String> {
     let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
-    
+
     let mut stmt = conn
         .prepare("SELECT * FROM clients WHERE user_id = ?1 ORDER BY created_at DESC")
         .map_err(|e| e.to_string())?;
-    
+
     let clients = stmt
         .query_map(params![user_id_value], Client::from_row)
         .map_err(|e| e.to_string())?
         .collect::<SqlResult<Vec<_>>>()
         .map_err(|e| e.to_string())?;
-    
+
     Ok(clients)
 }
 
-#[tauri::command]
-pub fn db_update_client(id: String, updates: Value, user_id: Option<String>) -> Result<Client, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
-    
-    // Get existing client (must belong to this user)
-    let mut client: Client = db_get_client(id.clone(), Some(user_id_value.clone()))?
-        .ok_or_else(|| "Client not found or access denied".to_string())?;
-    
-    // Apply updates
-    if let Some(first_name) = updates.get("first_name").and_then(|v| v.as_str()) {
-        client.first_name = first_name.to_string();
-    }
-    if let Some(last_name) = updates.get("last_name").and_then(|v| v.as_str()) {
-        client.last_name = last_name.to_string();
-    }
-    if let Some(email) = updates.get("email").and_then(|v| v.as_str()) {
-        client.email = Some(email.to_string());
+/// Start a transaction holding the write lock, retrying while another connection has it,
+/// so the statements inside can't fail with SQLITE_BUSY halfway through
+pub(crate) fn begin_write<'conn>(conn: &'conn Connection, category: &'static str) -> Result<rusqlite::Transaction<'conn>, DbError> {
+    retry_busy(category, || {
+        rusqlite::Transaction::new_unchecked(conn, rusqlite::TransactionBehavior::Immediate)
+    })
+}
+
+/// Create many clients for user_id in one transaction (contact imports)
+pub(crate) fn bulk_create_clients(user_id_value: &str, clients: Vec<Client>) -> Result<Vec<Client>, String> {
+    let (clients, contacts): (Vec<Client>, Vec<_>) = clients
+        .into_iter()
+        .map(|client| {
+            let mut client = Client {
+                user_id: Some(user_id_value.to_string()),
+                ..client
+            };
+            let contact = normalize_contact(&mut client);
+            (client, contact)
+        })
+        .unzip();
+
+    {
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let tx = begin_write(&conn, "client_import").map_err(|e| e.to_string())?;
+        {
+            let mut insert_stmt = tx
+                .prepare(
+                    "INSERT INTO clients (
+                        id, user_id, first_name, last_name, email, phone, address, city, state, zip_code,
+                        drivers_license, created_at, updated_at, phone_e164, phone_invalid, email_normalized
+                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)"
+                )
+                .map_err(|e| e.to_string())?;
+
+            for (client, contact) in clients.iter().zip(&contacts) {
+                insert_stmt
+                    .execute(params![
+                        client.id,
+                        user_id_value,
+                        client.first_name,
+                        client.last_name,
+                        client.email,
+                        client.phone,
+                        client.address,
+                        client.city,
+                        client.state,
+                        client.zip_code,
+                        client.drivers_license,
+                        client.created_at,
+                        client.updated_at,
+                        contact.phone_e164,
+                        contact.phone_invalid,
+                        contact.email_normalized,
+                    ])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
     }
-    if let Some(phone) = updates.get("phone").and_then(|v| v.as_str()) {
-        client.phone = Some(phone.to_string());
+
+    info!("✅ {} clients created for user: {}", clients.len(), user_id_value);
+    let mut changes = change_batch(user_id_value);
+    for client in &clients {
+        notify(user_id_value, WebhookEvent::ClientCreated, client);
+        changes.record(ChangedEntity::Client, &client.id, Operation::Create);
     }
-    // ... add other fields
-    
-    client.updated_at = chrono::Utc::now().timestamp_millis();
-    
-    conn.execute(
-        "UPDATE clients SET
-            first_name = ?2, last_name = ?3, email = ?4, phone = ?5,
-            address = ?6, city = ?7, state = ?8, zip_code = ?9,
-            drivers_license = ?10, updated_at = ?11
-        WHERE id = ?1 AND user_id = ?12",
-        params![
-            client.id,
-            client.first_name,
-            client.last_name,
-            client.email,
-            client.phone,
-            client.address,
-            client.city,
-            client.state,
-            client.zip_code,
-            client.drivers_license,
-            client.updated_at,
-            user_id_value,
-        ],
-    )
-    .map_err(|e| e.to_string())?;
-    
-    Ok(client)
+    changes.finish();
+    Ok(clients)
 }
 
 #[tauri::command]
-pub fn db_delete_client(id: String, user_id: Option<String>) -> Result<(), String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
+/// normalize_address cleans up the (updated) address before saving
+pub fn db_update_client(id: String, updates: Value, user_id: Option<String>, normalize_address: Option<bool>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Client, DbError> {
+    track("db_update_client", || {
+        let user_id_value = &state.require_user(user_id)?;
+    
+        // Get existing client (must belong to this user). Done before taking the
+        // connection, since db_get_client locks it too
+        let mut client: Client = db_get_client(id.clone(), Some(user_id_value.clone()), state, db.clone())?
+            .ok_or_else(|| "Client not found or access denied".to_string())?;
+    
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        // Apply updates
+        if let Some(first_name) = updates.get("first_name").and_then(|v| v.as_str()) {
+            client.first_name = first_name.to_string();
+        }
+        if let Some(last_name) = updates.get("last_name").and_then(|v| v.as_str()) {
+            client.last_name = last_name.to_string();
+        }
+        if let Some(email) = updates.get("email").and_then(|v| v.as_str()) {
+            client.email = Some(email.to_string());
+        }
+        if let Some(phone) = updates.get("phone").and_then(|v| v.as_str()) {
+            client.phone = Some(phone.to_string());
+        }
+        if let Some(address) = updates.get("address").and_then(|v| v.as_str()) {
+            client.address = Some(address.to_string());
+        }
+        if let Some(city) = updates.get("city").and_then(|v| v.as_str()) {
+            client.city = Some(city.to_string());
+        }
+        if let Some(state_code) = updates.get("state").and_then(|v| v.as_str()) {
+            client.state = Some(state_code.to_string());
+        }
+        if let Some(zip_code) = updates.get("zip_code").and_then(|v| v.as_str()) {
+            client.zip_code = Some(zip_code.to_string());
+        }
+        if let Some(drivers_license) = updates.get("drivers_license").and_then(|v| v.as_str()) {
+            client.drivers_license = Some(drivers_license.to_string());
+        }
+        if normalize_address.unwrap_or(false) {
+            normalize_client_address(&mut client);
+        }
+        enforce_rules(&conn, Entity::Client, &client)?;
+        let contact = normalize_contact(&mut client);
+    
+        client.updated_at = chrono::Utc::now().timestamp_millis();
+    
+        retry_busy("clients", || conn.execute(
+            "UPDATE clients SET
+                first_name = ?2, last_name = ?3, email = ?4, phone = ?5,
+                address = ?6, city = ?7, state = ?8, zip_code = ?9,
+                drivers_license = ?10, updated_at = ?11,
+                phone_e164 = ?13, phone_invalid = ?14, email_normalized = ?15
+            WHERE id = ?1 AND user_id = ?12",
+            params![
+                client.id,
+                client.first_name,
+                client.last_name,
+                client.email,
+                client.phone,
+                client.address,
+                client.city,
+                client.state,
+                client.zip_code,
+                client.drivers_license,
+                client.updated_at,
+                user_id_value,
+                contact.phone_e164,
+                contact.phone_invalid,
+                contact.email_normalized,
+            ],
+        ))?;
+    
+        notify(user_id_value, WebhookEvent::ClientUpdated, &client);
+        data_changed(user_id_value, ChangedEntity::Client, &client.id, Operation::Update);
+        Ok(client)
+    })
+}
+
+#[tauri::command]
+pub fn db_delete_client(id: String, user_id: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<(), DbError> {
+    track("db_delete_client", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
     
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
+        let user_id_value = &state.require_user(user_id)?;
     
-    conn.execute("DELETE FROM clients WHERE id = ?1 AND user_id = ?2", params![id, user_id_value])
-        .map_err(|e| e.to_string())?;
+        let deleted = retry_busy("clients", || conn.execute("DELETE FROM clients WHERE id = ?1 AND user_id = ?2", params![id, user_id_value]))?;
     
-    info!("✅ Client deleted: {} for user: {}", id, user_id_value);
-    Ok(())
+        info!("✅ Client deleted: {} for user: {}", id, user_id_value);
+        if deleted > 0 {
+            notify(user_id_value, WebhookEvent::ClientDeleted, &serde_json::json!({ "id": id }));
+            data_changed(user_id_value, ChangedEntity::Client, &id, Operation::Delete);
+        }
+        Ok(())
+    })
 }
 
 #[tauri::command]
-pub fn db_search_clients(query: String, user_id: Option<String>) -> Result<Vec<Client>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
-    let search = format!("%{}%", query);
-    let mut stmt = conn
-        .prepare(
-            "SELECT * FROM clients WHERE user_id = ?1 AND (
+pub fn db_search_clients(query: String, user_id: Option<String>, fields: Option<Vec<String>>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Listing<Client>, DbError> {
+    track("db_search_clients", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        let user_id_value = &state.require_user(user_id)?;
+        let search = format!("%{}%", query);
+        // Emails and phones match on their normalized columns, so "248.555.1212" finds
+        // "(248) 555-1212"; a phone that couldn't be normalized matches as entered
+        let email_search = format!("%{}%", query.trim().to_lowercase());
+        let phone_search = phone_search_digits(&query).map(|digits| format!("%{}%", digits));
+        let filter = "user_id = ?1 AND (
                 first_name LIKE ?2 OR
                 last_name LIKE ?2 OR
-                email LIKE ?2 OR
-                phone LIKE ?2
-            ) ORDER BY created_at DESC",
-        )
-        .map_err(|e| e.to_string())?;
+                email_normalized LIKE ?3 OR
+                phone_e164 LIKE ?4 OR
+                (phone_invalid = 1 AND phone LIKE ?2)
+            ) ORDER BY created_at DESC";
+        if let Some(fields) = fields {
+            let projection = Projection::parse(ListEntity::Client, &fields, state.role())?;
+            let rows = projection
+                .query(&conn, filter, params![user_id_value, search, email_search, phone_search])
+                .map_err(|e| e.to_string())?;
+            return Ok(Listing::Fields(rows));
+        }
+        let mut stmt = conn
+            .prepare(&format!("SELECT * FROM clients WHERE {}", filter))
+            .map_err(|e| e.to_string())?;
     
-    let clients = stmt
-        .query_map(params![user_id_value, search], Client::from_row)
-        .map_err(|e| e.to_string())?
-        .collect::<SqlResult<Vec<_>>>()
-        .map_err(|e| e.to_string())?;
+        let clients = stmt
+            .query_map(params![user_id_value, search, email_search, phone_search], Client::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
     
-    Ok(clients)
+        Ok(Listing::Full(clients))
+    })
 }
 
 // ============================================================================
@@ -456,6 +1195,12 @@ pub struct Vehicle {
 }
 
 impl Vehicle {
+    /// Amounts are stored in whole cents (see money.rs)
+    pub(crate) fn round_amounts(&mut self) {
+        self.price = round_to_cents(self.price);
+        self.cost = self.cost.map(round_to_cents);
+    }
+
     fn from_row(row: &Row) -> SqlResult<Self> {
         Ok(Vehicle {
             id: row.get(0)?,
@@ -486,62 +1231,105 @@ impl Vehicle {
 }
 
 #[tauri::command]
-pub fn db_create_vehicle(vehicle: Vehicle) -> Result<Vehicle, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
+pub fn db_create_vehicle(vehicle: Vehicle, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Vehicle, DbError> {
+    track("db_create_vehicle", || {
+        let mut vehicle = vehicle;
+        // Only roles that see costs may set one
+        if vehicle.cost.is_some() {
+            state.check_permission(Permission::ViewCosts)?;
+        }
+        vehicle.stock_number = normalize_stock_number(vehicle.stock_number);
+        vehicle.round_amounts();
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        enforce_rules(&conn, Entity::Vehicle, &vehicle)?;
+    
+        // Check if VIN already exists
+        let mut check_stmt = conn
+            .prepare("SELECT id FROM vehicles WHERE vin = ?1")
+            .map_err(|e| e.to_string())?;
+    
+        let existing: Result<String, _> = check_stmt.query_row(params![vehicle.vin], |row| row.get(0));
+        if existing.is_ok() {
+            return Err(format!("Vehicle with VIN {} already exists", vehicle.vin).into());
+        }
     
-    // Check if VIN already exists
-    let mut check_stmt = conn
-        .prepare("SELECT id FROM vehicles WHERE vin = ?1")
+        let tx = begin_write(&conn, "vehicles")?;
+        tx.execute(
+            "INSERT INTO vehicles (
+                id, vin, stock_number, year, make, model, trim, body, doors,
+                transmission, engine, cylinders, title_number, mileage, color,
+                price, cost, status, description, images, created_at, updated_at, user_id
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)",
+            params![
+                vehicle.id,
+                vehicle.vin,
+                vehicle.stock_number,
+                vehicle.year,
+                vehicle.make,
+                vehicle.model,
+                vehicle.trim,
+                vehicle.body,
+                vehicle.doors,
+                vehicle.transmission,
+                vehicle.engine,
+                vehicle.cylinders,
+                vehicle.title_number,
+                vehicle.mileage,
+                vehicle.color,
+                vehicle.price,
+                vehicle.cost,
+                vehicle.status,
+                vehicle.description,
+                vehicle.images,
+                vehicle.created_at,
+                vehicle.updated_at,
+                state.current_user(),
+            ],
+        )
+        .map_err(stock_number_error)?;
+        // The cost becomes the vehicle's purchase cost row
+        let user_id_value = state.current_user().unwrap_or_default();
+        set_total_cost(&tx, &vehicle.id, &user_id_value, vehicle.cost, vehicle.created_at)?;
+        tx.commit().map_err(|e| e.to_string())?;
+    
+        info!("✅ Vehicle created: {}", vehicle.id);
+        data_changed(&user_id_value, ChangedEntity::Vehicle, &vehicle.id, Operation::Create);
+        Ok(vehicle)
+    })
+}
+
+#[tauri::command]
+pub fn db_get_vehicle(id: String, user_id: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Option<Vehicle>, String> {
+    track("db_get_vehicle", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let user_id_value = &state.require_user(user_id)?;
+        let vehicle = vehicle_for_user(&conn, &id, user_id_value)?;
+        Ok(vehicle.map(|vehicle| redact_vehicle(state.role(), vehicle)))
+    })
+}
+
+/// Vehicle by id, cost included, only if user_id owns it (also used by db_batch)
+pub(crate) fn vehicle_for_user(conn: &Connection, id: &str, user_id_value: &str) -> Result<Option<Vehicle>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, vin, stock_number, year, make, model, trim, body, doors,
+             transmission, engine, cylinders, title_number, mileage, color,
+             price, cost, status, description, images, created_at, updated_at, synced_at
+             FROM vehicles WHERE id = ?1 AND user_id = ?2"
+        )
         .map_err(|e| e.to_string())?;
-    
-    let existing: Result<String, _> = check_stmt.query_row(params![vehicle.vin], |row| row.get(0));
-    if existing.is_ok() {
-        return Err(format!("Vehicle with VIN {} already exists", vehicle.vin));
+
+    match stmt.query_row(params![id, user_id_value], Vehicle::from_row) {
+        Ok(vehicle) => Ok(Some(vehicle)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e.to_string()),
     }
-    
-    conn.execute(
-        "INSERT INTO vehicles (
-            id, vin, stock_number, year, make, model, trim, body, doors,
-            transmission, engine, cylinders, title_number, mileage, color,
-            price, cost, status, description, images, created_at, updated_at
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
-        params![
-            vehicle.id,
-            vehicle.vin,
-            vehicle.stock_number,
-            vehicle.year,
-            vehicle.make,
-            vehicle.model,
-            vehicle.trim,
-            vehicle.body,
-            vehicle.doors,
-            vehicle.transmission,
-            vehicle.engine,
-            vehicle.cylinders,
-            vehicle.title_number,
-            vehicle.mileage,
-            vehicle.color,
-            vehicle.price,
-            vehicle.cost,
-            vehicle.status,
-            vehicle.description,
-            vehicle.images,
-            vehicle.created_at,
-            vehicle.updated_at,
-        ],
-    )
-    .map_err(|e| e.to_string())?;
-    
-    info!("✅ Vehicle created: {}", vehicle.id);
-    Ok(vehicle)
 }
 
-#[tauri::command]
-pub fn db_get_vehicle(id: String) -> Result<Option<Vehicle>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
+/// Vehicle by id, cost included
+pub(crate) fn vehicle_by_id(conn: &Connection, id: &str) -> Result<Option<Vehicle>, String> {
     // Explicitly list columns to ensure correct order (images was added later)
     let mut stmt = conn
         .prepare(
@@ -551,7 +1339,7 @@ pub fn db_get_vehicle(id: String) -> Result<Option<Vehicle>, String> {
              FROM vehicles WHERE id = ?1"
         )
         .map_err(|e| e.to_string())?;
-    
+
     match stmt.query_row(params![id], Vehicle::from_row) {
         Ok(vehicle) => Ok(Some(vehicle)),
         Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
@@ -560,12 +1348,27 @@ pub fn db_get_vehicle(id: String) -> Result<Option<Vehicle>, String> {
 }
 
 #[tauri::command]
-pub fn db_get_all_vehicles(user_id: Option<String>) -> Result<Vec<Vehicle>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
+/// fields: only these columns, as JSON objects (see field_projection.rs)
+pub fn db_get_all_vehicles(user_id: Option<String>, fields: Option<Vec<String>>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Listing<Vehicle>, String> {
+    track("db_get_all_vehicles", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        if let Some(fields) = fields {
+            let projection = Projection::parse(ListEntity::Vehicle, &fields, state.role())?;
+            let rows = projection
+                .query(&db.conn(), "user_id = ?1 ORDER BY created_at DESC", params![user_id_value])
+                .map_err(|e| e.to_string())?;
+            return Ok(Listing::Fields(rows));
+        }
+        let vehicles = get_vehicles_for_user(db, &user_id_value)?;
+        Ok(Listing::Full(redact_vehicles(state.role(), vehicles)))
+    })
+}
+
+/// All vehicles owned by user_id, newest first (also used by the local API)
+pub(crate) fn get_vehicles_for_user(db: &Database, user_id_value: &str) -> Result<Vec<Vehicle>, String> {
     let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
-    
+
     // Explicitly list columns to ensure correct order (images was added later via migration)
     let mut stmt = conn
         .prepare(
@@ -575,236 +1378,380 @@ pub fn db_get_all_vehicles(user_id: Option<String>) -> Result<Vec<Vehicle>, Stri
              FROM vehicles WHERE user_id = ?1 ORDER BY created_at DESC"
         )
         .map_err(|e| e.to_string())?;
-    
+
     let vehicles = stmt
         .query_map(params![user_id_value], Vehicle::from_row)
         .map_err(|e| e.to_string())?
         .collect::<SqlResult<Vec<_>>>()
         .map_err(|e| e.to_string())?;
-    
+
     Ok(vehicles)
 }
 
+/// Create many vehicles for the current user in one transaction; any duplicate VIN fails the batch
 #[tauri::command]
-pub fn db_get_vehicle_by_vin(vin: String) -> Result<Option<Vehicle>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
+pub fn db_bulk_create_vehicles(vehicles: Vec<Vehicle>, user_id: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Vec<Vehicle>, String> {
+    track("db_bulk_create_vehicles", || {
+        let user_id_value = state.require_user(user_id)?;
+        bulk_create_vehicles(db.get().map_err(|e| e.to_string())?, &user_id_value, vehicles)
+    })
+}
+
+pub(crate) fn bulk_create_vehicles(db: &Database, user_id_value: &str, mut vehicles: Vec<Vehicle>) -> Result<Vec<Vehicle>, String> {
     let conn = db.conn();
-    
-    // Explicitly list columns to ensure correct order
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, vin, stock_number, year, make, model, trim, body, doors,
-             transmission, engine, cylinders, title_number, mileage, color,
-             price, cost, status, description, images, created_at, updated_at, synced_at
-             FROM vehicles WHERE vin = ?1"
-        )
-        .map_err(|e| e.to_string())?;
-    
-    match stmt.query_row(params![vin], Vehicle::from_row) {
-        Ok(vehicle) => Ok(Some(vehicle)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.to_string()),
+    let tx = begin_write(&conn, "vehicle_import").map_err(|e| e.to_string())?;
+
+    {
+        let mut check_stmt = tx
+            .prepare("SELECT id FROM vehicles WHERE vin = ?1")
+            .map_err(|e| e.to_string())?;
+        let mut insert_stmt = tx
+            .prepare(
+                "INSERT INTO vehicles (
+                    id, vin, stock_number, year, make, model, trim, body, doors,
+                    transmission, engine, cylinders, title_number, mileage, color,
+                    price, cost, status, description, images, created_at, updated_at, user_id
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23)"
+            )
+            .map_err(|e| e.to_string())?;
+
+        for vehicle in &mut vehicles {
+            vehicle.stock_number = normalize_stock_number(vehicle.stock_number.take());
+            vehicle.round_amounts();
+            if check_stmt.exists(params![vehicle.vin]).map_err(|e| e.to_string())? {
+                return Err(format!("Vehicle with VIN {} already exists", vehicle.vin));
+            }
+            insert_stmt
+                .execute(params![
+                    vehicle.id,
+                    vehicle.vin,
+                    vehicle.stock_number,
+                    vehicle.year,
+                    vehicle.make,
+                    vehicle.model,
+                    vehicle.trim,
+                    vehicle.body,
+                    vehicle.doors,
+                    vehicle.transmission,
+                    vehicle.engine,
+                    vehicle.cylinders,
+                    vehicle.title_number,
+                    vehicle.mileage,
+                    vehicle.color,
+                    vehicle.price,
+                    vehicle.cost,
+                    vehicle.status,
+                    vehicle.description,
+                    vehicle.images,
+                    vehicle.created_at,
+                    vehicle.updated_at,
+                    user_id_value,
+                ])
+                .map_err(|e| stock_number_error(e).to_string())?;
+            set_total_cost(&tx, &vehicle.id, user_id_value, vehicle.cost, vehicle.created_at)?;
+        }
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    info!("✅ {} vehicles created", vehicles.len());
+    let mut changes = change_batch(user_id_value);
+    for vehicle in &vehicles {
+        changes.record(ChangedEntity::Vehicle, &vehicle.id, Operation::Create);
     }
+    changes.finish();
+    Ok(vehicles)
 }
 
 #[tauri::command]
-pub fn db_get_vehicle_by_stock(stock_number: String) -> Result<Option<Vehicle>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    // Explicitly list columns to ensure correct order
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, vin, stock_number, year, make, model, trim, body, doors,
-             transmission, engine, cylinders, title_number, mileage, color,
-             price, cost, status, description, images, created_at, updated_at, synced_at
-             FROM vehicles WHERE stock_number = ?1"
-        )
-        .map_err(|e| e.to_string())?;
+pub fn db_get_vehicle_by_vin(vin: String, user_id: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Option<Vehicle>, String> {
+    track("db_get_vehicle_by_vin", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let user_id_value = &state.require_user(user_id)?;
+    
+        // Explicitly list columns to ensure correct order
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, vin, stock_number, year, make, model, trim, body, doors,
+                 transmission, engine, cylinders, title_number, mileage, color,
+                 price, cost, status, description, images, created_at, updated_at, synced_at
+                 FROM vehicles WHERE vin = ?1 AND user_id = ?2"
+            )
+            .map_err(|e| e.to_string())?;
     
-    match stmt.query_row(params![stock_number], Vehicle::from_row) {
-        Ok(vehicle) => Ok(Some(vehicle)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.to_string()),
-    }
+        match stmt.query_row(params![vin, user_id_value], Vehicle::from_row) {
+            Ok(vehicle) => Ok(Some(redact_vehicle(state.role(), vehicle))),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    })
 }
 
 #[tauri::command]
-pub fn db_update_vehicle(id: String, updates: Value) -> Result<Vehicle, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let mut vehicle: Vehicle = db_get_vehicle(id.clone())?
-        .ok_or_else(|| "Vehicle not found".to_string())?;
-    
-    // Apply updates from JSON
-    if let Some(vin) = updates.get("vin").and_then(|v| v.as_str()) {
-        vehicle.vin = vin.to_string();
-    }
-    if let Some(stock_number) = updates.get("stock_number").and_then(|v| v.as_str()) {
-        vehicle.stock_number = Some(stock_number.to_string());
-    }
-    if let Some(year) = updates.get("year").and_then(|v| v.as_i64()) {
-        vehicle.year = year as i32;
-    }
-    if let Some(make) = updates.get("make").and_then(|v| v.as_str()) {
-        vehicle.make = make.to_string();
-    }
-    if let Some(model) = updates.get("model").and_then(|v| v.as_str()) {
-        vehicle.model = model.to_string();
-    }
-    if let Some(trim) = updates.get("trim").and_then(|v| v.as_str()) {
-        vehicle.trim = Some(trim.to_string());
-    }
-    if let Some(body) = updates.get("body").and_then(|v| v.as_str()) {
-        vehicle.body = Some(body.to_string());
-    }
-    if let Some(doors) = updates.get("doors").and_then(|v| v.as_i64()) {
-        vehicle.doors = Some(doors as i32);
-    }
-    if let Some(transmission) = updates.get("transmission").and_then(|v| v.as_str()) {
-        vehicle.transmission = Some(transmission.to_string());
-    }
-    if let Some(engine) = updates.get("engine").and_then(|v| v.as_str()) {
-        vehicle.engine = Some(engine.to_string());
-    }
-    if let Some(cylinders) = updates.get("cylinders").and_then(|v| v.as_i64()) {
-        vehicle.cylinders = Some(cylinders as i32);
-    }
-    if let Some(title_number) = updates.get("title_number").and_then(|v| v.as_str()) {
-        vehicle.title_number = Some(title_number.to_string());
-    }
-    if let Some(mileage) = updates.get("mileage").and_then(|v| v.as_i64()) {
-        vehicle.mileage = mileage as i32;
-    }
-    if let Some(color) = updates.get("color").and_then(|v| v.as_str()) {
-        vehicle.color = Some(color.to_string());
-    }
-    if let Some(price) = updates.get("price").and_then(|v| v.as_f64()) {
-        vehicle.price = price;
-    }
-    if let Some(cost) = updates.get("cost").and_then(|v| v.as_f64()) {
-        vehicle.cost = Some(cost);
-    }
-    if let Some(status) = updates.get("status").and_then(|v| v.as_str()) {
-        vehicle.status = status.to_string();
-    }
-    if let Some(description) = updates.get("description").and_then(|v| v.as_str()) {
-        vehicle.description = Some(description.to_string());
-    }
-    if let Some(images) = updates.get("images") {
-        vehicle.images = Some(serde_json::to_string(images).map_err(|e| e.to_string())?);
-    }
+pub fn db_get_vehicle_by_stock(stock_number: String, user_id: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Option<Vehicle>, String> {
+    track("db_get_vehicle_by_stock", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let user_id_value = &state.require_user(user_id)?;
+    
+        // Explicitly list columns to ensure correct order
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, vin, stock_number, year, make, model, trim, body, doors,
+                 transmission, engine, cylinders, title_number, mileage, color,
+                 price, cost, status, description, images, created_at, updated_at, synced_at
+                 FROM vehicles WHERE stock_number = ?1 AND user_id = ?2"
+            )
+            .map_err(|e| e.to_string())?;
     
-    vehicle.updated_at = Utc::now().timestamp_millis();
+        match stmt.query_row(params![stock_number, user_id_value], Vehicle::from_row) {
+            Ok(vehicle) => Ok(Some(redact_vehicle(state.role(), vehicle))),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    })
+}
+
+#[tauri::command]
+pub fn db_update_vehicle(id: String, updates: Value, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Vehicle, DbError> {
+    track("db_update_vehicle", || {
+        // Only roles that see costs may change one
+        if updates.get("cost").is_some_and(Value::is_number) {
+            state.check_permission(Permission::ViewCosts)?;
+        }
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        // Read with its cost, so a role that can't see it writes it back unchanged
+        let mut vehicle: Vehicle = vehicle_by_id(&conn, &id)?
+            .ok_or_else(|| "Vehicle not found".to_string())?;
+        let previous_status = vehicle.status.clone();
+    
+        // Apply updates from JSON
+        if let Some(vin) = updates.get("vin").and_then(|v| v.as_str()) {
+            vehicle.vin = vin.to_string();
+        }
+        if let Some(stock_number) = updates.get("stock_number").and_then(|v| v.as_str()) {
+            vehicle.stock_number = normalize_stock_number(Some(stock_number.to_string()));
+        }
+        if let Some(year) = updates.get("year").and_then(|v| v.as_i64()) {
+            vehicle.year = year as i32;
+        }
+        if let Some(make) = updates.get("make").and_then(|v| v.as_str()) {
+            vehicle.make = make.to_string();
+        }
+        if let Some(model) = updates.get("model").and_then(|v| v.as_str()) {
+            vehicle.model = model.to_string();
+        }
+        if let Some(trim) = updates.get("trim").and_then(|v| v.as_str()) {
+            vehicle.trim = Some(trim.to_string());
+        }
+        if let Some(body) = updates.get("body").and_then(|v| v.as_str()) {
+            vehicle.body = Some(body.to_string());
+        }
+        if let Some(doors) = updates.get("doors").and_then(|v| v.as_i64()) {
+            vehicle.doors = Some(doors as i32);
+        }
+        if let Some(transmission) = updates.get("transmission").and_then(|v| v.as_str()) {
+            vehicle.transmission = Some(transmission.to_string());
+        }
+        if let Some(engine) = updates.get("engine").and_then(|v| v.as_str()) {
+            vehicle.engine = Some(engine.to_string());
+        }
+        if let Some(cylinders) = updates.get("cylinders").and_then(|v| v.as_i64()) {
+            vehicle.cylinders = Some(cylinders as i32);
+        }
+        if let Some(title_number) = updates.get("title_number").and_then(|v| v.as_str()) {
+            vehicle.title_number = Some(title_number.to_string());
+        }
+        if let Some(mileage) = updates.get("mileage").and_then(|v| v.as_i64()) {
+            vehicle.mileage = mileage as i32;
+        }
+        if let Some(color) = updates.get("color").and_then(|v| v.as_str()) {
+            vehicle.color = Some(color.to_string());
+        }
+        if let Some(price) = updates.get("price").and_then(|v| v.as_f64()) {
+            vehicle.price = price;
+        }
+        if let Some(cost) = updates.get("cost").and_then(|v| v.as_f64()) {
+            vehicle.cost = Some(cost);
+        }
+        if let Some(status) = updates.get("status").and_then(|v| v.as_str()) {
+            vehicle.status = status.to_string();
+        }
+        if let Some(description) = updates.get("description").and_then(|v| v.as_str()) {
+            vehicle.description = Some(description.to_string());
+        }
+        if let Some(images) = updates.get("images") {
+            vehicle.images = Some(serde_json::to_string(images).map_err(|e| e.to_string())?);
+        }
     
-    conn.execute(
-        "UPDATE vehicles SET
-            vin = ?2, stock_number = ?3, year = ?4, make = ?5, model = ?6,
-            trim = ?7, body = ?8, doors = ?9, transmission = ?10, engine = ?11,
-            cylinders = ?12, title_number = ?13, mileage = ?14, color = ?15,
-            price = ?16, cost = ?17, status = ?18, description = ?19,
-            images = ?20, updated_at = ?21
-        WHERE id = ?1",
-        params![
-            vehicle.id,
-            vehicle.vin,
-            vehicle.stock_number,
-            vehicle.year,
-            vehicle.make,
-            vehicle.model,
-            vehicle.trim,
-            vehicle.body,
-            vehicle.doors,
-            vehicle.transmission,
-            vehicle.engine,
-            vehicle.cylinders,
-            vehicle.title_number,
-            vehicle.mileage,
-            vehicle.color,
-            vehicle.price,
-            vehicle.cost,
-            vehicle.status,
-            vehicle.description,
-            vehicle.images,
+        vehicle.round_amounts();
+        vehicle.updated_at = Utc::now().timestamp_millis();
+        enforce_rules(&conn, Entity::Vehicle, &vehicle)?;
+    
+        let tx = begin_write(&conn, "vehicles").map_err(|e| e.to_string())?;
+        tx.execute(
+            "UPDATE vehicles SET
+                vin = ?2, stock_number = ?3, year = ?4, make = ?5, model = ?6,
+                trim = ?7, body = ?8, doors = ?9, transmission = ?10, engine = ?11,
+                cylinders = ?12, title_number = ?13, mileage = ?14, color = ?15,
+                price = ?16, cost = ?17, status = ?18, description = ?19,
+                images = ?20, updated_at = ?21
+            WHERE id = ?1",
+            params![
+                vehicle.id,
+                vehicle.vin,
+                vehicle.stock_number,
+                vehicle.year,
+                vehicle.make,
+                vehicle.model,
+                vehicle.trim,
+                vehicle.body,
+                vehicle.doors,
+                vehicle.transmission,
+                vehicle.engine,
+                vehicle.cylinders,
+                vehicle.title_number,
+                vehicle.mileage,
+                vehicle.color,
+                vehicle.price,
+                vehicle.cost,
+                vehicle.status,
+                vehicle.description,
+                vehicle.images,
+                vehicle.updated_at,
+            ],
+        )
+        .map_err(stock_number_error)?;
+        // A cost written here resizes the purchase cost row so the rows still add up
+        let user_id_value = state.current_user().unwrap_or_default();
+        if updates.get("cost").is_some_and(Value::is_number) {
+            set_total_cost(&tx, &vehicle.id, &user_id_value, vehicle.cost, vehicle.updated_at)?;
+        }
+        // Sold -> available is refused here without a return_reason (vehicle_history.rs)
+        let event = record_status_change(
+            &tx,
+            &vehicle.id,
+            &user_id_value,
+            &previous_status,
+            &vehicle.status,
+            &updates,
             vehicle.updated_at,
-        ],
-    )
-    .map_err(|e| e.to_string())?;
+        )?;
+        tx.commit().map_err(|e| e.to_string())?;
     
-    Ok(vehicle)
+        data_changed(&user_id_value, ChangedEntity::Vehicle, &vehicle.id, Operation::Update);
+        if let Some(event) = event {
+            data_changed(&user_id_value, ChangedEntity::VehicleEvent, &event.id, Operation::Create);
+        }
+        Ok(redact_vehicle(state.role(), vehicle))
+    })
 }
 
 #[tauri::command]
-pub fn db_delete_vehicle(id: String) -> Result<(), String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
+pub fn db_delete_vehicle(id: String, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<(), DbError> {
+    track("db_delete_vehicle", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
     
-    conn.execute("DELETE FROM vehicles WHERE id = ?1", params![id])
-        .map_err(|e| e.to_string())?;
+        let deleted = retry_busy("vehicles", || conn.execute("DELETE FROM vehicles WHERE id = ?1", params![id]))?;
     
-    info!("✅ Vehicle deleted: {}", id);
-    Ok(())
+        info!("✅ Vehicle deleted: {}", id);
+        if deleted > 0 {
+            data_changed(&state.current_user().unwrap_or_default(), ChangedEntity::Vehicle, &id, Operation::Delete);
+        }
+        Ok(())
+    })
 }
 
 #[tauri::command]
-pub fn db_search_vehicles(query: String) -> Result<Vec<Vehicle>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let search = format!("%{}%", query);
-    // Explicitly list columns to ensure correct order
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, vin, stock_number, year, make, model, trim, body, doors,
-             transmission, engine, cylinders, title_number, mileage, color,
-             price, cost, status, description, images, created_at, updated_at, synced_at
-             FROM vehicles WHERE
-                make LIKE ?1 OR
-                model LIKE ?1 OR
-                vin LIKE ?1 OR
-                stock_number LIKE ?1
-            ORDER BY created_at DESC",
-        )
-        .map_err(|e| e.to_string())?;
+pub fn db_search_vehicles(query: String, user_id: Option<String>, fields: Option<Vec<String>>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Listing<Vehicle>, DbError> {
+    track("db_search_vehicles", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        let user_id_value = &state.require_user(user_id)?;
+        let search = format!("%{}%", query);
+        if let Some(fields) = fields {
+            let projection = Projection::parse(ListEntity::Vehicle, &fields, state.role())?;
+            let rows = projection
+                .query(
+                    &conn,
+                    "user_id = ?1 AND (make LIKE ?2 OR model LIKE ?2 OR vin LIKE ?2 OR stock_number LIKE ?2)
+                     ORDER BY created_at DESC",
+                    params![user_id_value, search],
+                )
+                .map_err(|e| e.to_string())?;
+            return Ok(Listing::Fields(rows));
+        }
+        // Explicitly list columns to ensure correct order
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, vin, stock_number, year, make, model, trim, body, doors,
+                 transmission, engine, cylinders, title_number, mileage, color,
+                 price, cost, status, description, images, created_at, updated_at, synced_at
+                 FROM vehicles WHERE user_id = ?1 AND (
+                    make LIKE ?2 OR
+                    model LIKE ?2 OR
+                    vin LIKE ?2 OR
+                    stock_number LIKE ?2
+                ) ORDER BY created_at DESC",
+            )
+            .map_err(|e| e.to_string())?;
     
-    let vehicles = stmt
-        .query_map(params![search], Vehicle::from_row)
-        .map_err(|e| e.to_string())?
-        .collect::<SqlResult<Vec<_>>>()
-        .map_err(|e| e.to_string())?;
+        let vehicles = stmt
+            .query_map(params![user_id_value, search], Vehicle::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
     
-    Ok(vehicles)
+        Ok(Listing::Full(redact_vehicles(state.role(), vehicles)))
+    })
 }
 
 #[tauri::command]
-pub fn db_get_vehicles_by_status(status: String) -> Result<Vec<Vehicle>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    // Explicitly list columns to ensure correct order
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, vin, stock_number, year, make, model, trim, body, doors,
-             transmission, engine, cylinders, title_number, mileage, color,
-             price, cost, status, description, images, created_at, updated_at, synced_at
-             FROM vehicles WHERE status = ?1 ORDER BY created_at DESC"
-        )
-        .map_err(|e| e.to_string())?;
+pub fn db_get_vehicles_by_status(status: String, user_id: Option<String>, fields: Option<Vec<String>>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Listing<Vehicle>, String> {
+    track("db_get_vehicles_by_status", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        let user_id_value = &state.require_user(user_id)?;
+        if let Some(fields) = fields {
+            let projection = Projection::parse(ListEntity::Vehicle, &fields, state.role())?;
+            let rows = projection
+                .query(&conn, "user_id = ?1 AND status = ?2 ORDER BY created_at DESC", params![user_id_value, status])
+                .map_err(|e| e.to_string())?;
+            return Ok(Listing::Fields(rows));
+        }
+        // Explicitly list columns to ensure correct order
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, vin, stock_number, year, make, model, trim, body, doors,
+                 transmission, engine, cylinders, title_number, mileage, color,
+                 price, cost, status, description, images, created_at, updated_at, synced_at
+                 FROM vehicles WHERE user_id = ?1 AND status = ?2 ORDER BY created_at DESC"
+            )
+            .map_err(|e| e.to_string())?;
     
-    let vehicles = stmt
-        .query_map(params![status], Vehicle::from_row)
-        .map_err(|e| e.to_string())?
-        .collect::<SqlResult<Vec<_>>>()
-        .map_err(|e| e.to_string())?;
+        let vehicles = stmt
+            .query_map(params![user_id_value, status], Vehicle::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
     
-    Ok(vehicles)
+        Ok(Listing::Full(redact_vehicles(state.role(), vehicles)))
+    })
 }
 
 // ============================================================================
 // DEAL OPERATIONS
 // ============================================================================
 
+/// Status that fires the deal.sold webhook
+pub const DEAL_STATUS_SOLD: &str = "sold";
+/// Status that requires the deal's paperwork to validate (see deal_validation)
+pub const DEAL_STATUS_COMPLETED: &str = "completed";
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Deal {
     pub id: String,
@@ -826,10 +1773,32 @@ pub struct Deal {
     pub created_at: i64,
     pub updated_at: i64,
     pub synced_at: Option<i64>,
+    pub odometer_at_sale: Option<i64>,
+    pub odometer_disclosure: Option<String>, // actual, exceeds or not_actual
+    pub title_status: Option<String>,
+    pub title_state: Option<String>,
+    pub lien_holder: Option<String>,
+    #[serde(default)]
+    pub lender_id: Option<String>, // lenders.id of the bank that bought the contract
 }
 
 impl Deal {
-    fn from_row(row: &Row) -> SqlResult<Self> {
+    /// Amounts are stored in whole cents (see money.rs)
+    pub(crate) fn round_amounts(&mut self) {
+        self.total_amount = round_to_cents(self.total_amount);
+        for amount in [
+            &mut self.sale_amount,
+            &mut self.sales_tax,
+            &mut self.doc_fee,
+            &mut self.trade_in_value,
+            &mut self.down_payment,
+            &mut self.financed_amount,
+        ] {
+            *amount = amount.map(round_to_cents);
+        }
+    }
+
+    pub(crate) fn from_row(row: &Row) -> SqlResult<Self> {
         // user_id was added via migration, so it's at the end (after synced_at)
         // Column order: id, type, client_id, vehicle_id, status, total_amount, sale_date, sale_amount,
         // sales_tax, doc_fee, trade_in_value, down_payment, financed_amount, document_ids, cobuyer_data,
@@ -854,24 +1823,29 @@ impl Deal {
             updated_at: row.get(16)?,
             synced_at: row.get(17)?,
             user_id: row.get(18).ok(), // user_id is optional and at the end
+            // Added by migration 13, looked up by name
+            odometer_at_sale: row.get("odometer_at_sale")?,
+            odometer_disclosure: row.get("odometer_disclosure")?,
+            title_status: row.get("title_status")?,
+            title_state: row.get("title_state")?,
+            lien_holder: row.get("lien_holder")?,
+            // Added by migration 19
+            lender_id: row.get("lender_id")?,
         })
     }
 }
 
-#[tauri::command]
-pub fn db_create_deal(deal: Deal, user_id: Option<String>) -> Result<Deal, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
-    
+/// Insert a deal row owned by user_id_value (the deal's own user_id is ignored)
+pub(crate) fn insert_deal(conn: &Connection, deal: &Deal, user_id_value: &str) -> SqlResult<usize> {
     conn.execute(
         "INSERT INTO deals (
             id, user_id, type, client_id, vehicle_id, status, total_amount,
             sale_date, sale_amount, sales_tax, doc_fee, trade_in_value,
             down_payment, financed_amount, document_ids, cobuyer_data,
-            created_at, updated_at
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)",
+            created_at, updated_at, odometer_at_sale, odometer_disclosure,
+            title_status, title_state, lien_holder, lender_id
+        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18,
+            ?19, ?20, ?21, ?22, ?23, ?24)",
         params![
             deal.id,
             user_id_value,
@@ -891,25 +1865,87 @@ pub fn db_create_deal(deal: Deal, user_id: Option<String>) -> Result<Deal, Strin
             deal.cobuyer_data,
             deal.created_at,
             deal.updated_at,
+            deal.odometer_at_sale,
+            deal.odometer_disclosure,
+            deal.title_status,
+            deal.title_state,
+            deal.lien_holder,
+            deal.lender_id,
         ],
     )
-    .map_err(|e| e.to_string())?;
-    
-    info!("✅ Deal created: {}", deal.id);
-    Ok(deal)
 }
 
+/// idempotency_key makes a retried call return the deal the first one created (idempotency.rs)
 #[tauri::command]
-pub fn db_get_deal(id: String, user_id: Option<String>) -> Result<Option<Deal>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
-    
+pub fn db_create_deal(deal: Deal, user_id: Option<String>, idempotency_key: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Deal, DbError> {
+    track("db_create_deal", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        let user_id_value = &state.require_user(user_id)?;
+        let now = Utc::now().timestamp_millis();
+        let key = idempotency_key.as_deref();
+        // The key, like a hold placed meanwhile, is checked in the same transaction as the insert
+        let tx = begin_write(&conn, "deals")?;
+        if let Some(created) = replay(&tx, user_id_value, key, EntityType::Deal, now, |id| deal_for_user(&tx, id, user_id_value))? {
+            tx.commit().map_err(|e| e.to_string())?;
+            return Ok(created);
+        }
+        let mut deal = deal;
+        deal.status = normalize_status(&deal.status).map_err(String::from)?.to_string();
+        deal.round_amounts();
+        validate_paperwork_fields(&deal)?;
+        if let Some(lender_id) = deal.lender_id.as_deref() {
+            check_lender(&tx, user_id_value, lender_id)?;
+        }
+        let cobuyer = legacy_cobuyer(deal.cobuyer_data.as_deref())?;
+    
+        check_vehicle_hold(&tx, &deal.vehicle_id, user_id_value, now)?;
+        insert_deal(&tx, &deal, user_id_value).map_err(|e| e.to_string())?;
+        remember(&tx, user_id_value, key, EntityType::Deal, &deal.id, now).map_err(|e| e.to_string())?;
+        // doc_fee becomes the deal's documentation fee line
+        replace_doc_fee(&tx, &deal.id, user_id_value, deal.doc_fee, deal.created_at)
+            .map_err(|e| e.to_string())?;
+        // cobuyer_data becomes the deal's first co-buyer row
+        replace_first_cobuyer(&tx, &deal.id, user_id_value, cobuyer, deal.created_at)
+            .map_err(|e| e.to_string())?;
+        record_transition(&tx, &deal.id, user_id_value, None, &deal.status, false, None)
+            .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+    
+        info!("✅ Deal created: {}", deal.id);
+        let deal = Deal {
+            user_id: Some(user_id_value.clone()),
+            ..deal
+        };
+        notify(user_id_value, WebhookEvent::DealCreated, &deal);
+        if deal.status == DEAL_STATUS_SOLD {
+            notify(user_id_value, WebhookEvent::DealSold, &deal);
+        }
+        data_changed(user_id_value, ChangedEntity::Deal, &deal.id, Operation::Create);
+        Ok(deal)
+    })
+}
+
+#[tauri::command]
+pub fn db_get_deal(id: String, user_id: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Option<Deal>, String> {
+    track("db_get_deal", || {
+        let user_id_value = state.require_user(user_id)?;
+        get_deal_for_user(db.get().map_err(|e| e.to_string())?, &id, &user_id_value)
+    })
+}
+
+/// Deal by id, only if user_id owns it (also used by the local API)
+pub(crate) fn get_deal_for_user(db: &Database, id: &str, user_id_value: &str) -> Result<Option<Deal>, String> {
+    deal_for_user(&db.conn(), id, user_id_value)
+}
+
+/// get_deal_for_user on a connection the caller already holds (db_batch)
+pub(crate) fn deal_for_user(conn: &Connection, id: &str, user_id_value: &str) -> Result<Option<Deal>, String> {
     let mut stmt = conn
         .prepare("SELECT * FROM deals WHERE id = ?1 AND user_id = ?2")
         .map_err(|e| e.to_string())?;
-    
+
     match stmt.query_row(params![id, user_id_value], Deal::from_row) {
         Ok(deal) => Ok(Some(deal)),
         Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
@@ -918,244 +1954,464 @@ pub fn db_get_deal(id: String, user_id: Option<String>) -> Result<Option<Deal>,
 }
 
 #[tauri::command]
-pub fn db_get_all_deals(user_id: Option<String>) -> Result<Vec<Deal>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
+/// fields: only these columns, as JSON objects (see field_projection.rs)
+pub fn db_get_all_deals(user_id: Option<String>, fields: Option<Vec<String>>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Listing<Deal>, String> {
+    track("db_get_all_deals", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        let user_id_value = &state.require_user(user_id)?;
+        if let Some(fields) = fields {
+            let projection = Projection::parse(ListEntity::Deal, &fields, state.role())?;
+            let rows = projection
+                .query(&conn, "user_id = ?1 ORDER BY created_at DESC", params![user_id_value])
+                .map_err(|e| e.to_string())?;
+            return Ok(Listing::Fields(rows));
+        }
     
-    let mut stmt = conn
-        .prepare("SELECT * FROM deals WHERE user_id = ?1 ORDER BY created_at DESC")
-        .map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT * FROM deals WHERE user_id = ?1 ORDER BY created_at DESC")
+            .map_err(|e| e.to_string())?;
     
-    let deals = stmt
-        .query_map(params![user_id_value], Deal::from_row)
-        .map_err(|e| e.to_string())?
-        .collect::<SqlResult<Vec<_>>>()
-        .map_err(|e| e.to_string())?;
+        let deals = stmt
+            .query_map(params![user_id_value], Deal::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
     
-    Ok(deals)
+        Ok(Listing::Full(deals))
+    })
 }
 
-#[tauri::command]
-pub fn db_get_deals_by_client(client_id: String, user_id: Option<String>) -> Result<Vec<Deal>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
-    
-    let mut stmt = conn
-        .prepare("SELECT * FROM deals WHERE client_id = ?1 AND user_id = ?2 ORDER BY created_at DESC")
-        .map_err(|e| e.to_string())?;
-    
-    let deals = stmt
-        .query_map(params![client_id, user_id_value], Deal::from_row)
-        .map_err(|e| e.to_string())?
-        .collect::<SqlResult<Vec<_>>>()
-        .map_err(|e| e.to_string())?;
-    
-    Ok(deals)
+/// A deal with its client, vehicle, lender and co-buyers, for list screens
+/// Each is None when the deal doesn't have one (or points at a record that's gone).
+#[derive(Debug, Serialize, Clone)]
+pub struct DealWithDetails {
+    #[serde(flatten)]
+    pub deal: Deal,
+    pub client: Option<Client>,
+    pub vehicle: Option<Vehicle>,
+    pub lender: Option<Lender>,
+    pub cobuyers: Vec<DealCobuyer>,
 }
 
 #[tauri::command]
-pub fn db_get_deals_by_vehicle(vehicle_id: String, user_id: Option<String>) -> Result<Vec<Deal>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
-    
-    let mut stmt = conn
-        .prepare("SELECT * FROM deals WHERE vehicle_id = ?1 AND user_id = ?2 ORDER BY created_at DESC")
-        .map_err(|e| e.to_string())?;
-    
-    let deals = stmt
-        .query_map(params![vehicle_id, user_id_value], Deal::from_row)
-        .map_err(|e| e.to_string())?
-        .collect::<SqlResult<Vec<_>>>()
-        .map_err(|e| e.to_string())?;
-    
-    Ok(deals)
-}
+pub fn db_get_deals_with_details(user_id: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Vec<DealWithDetails>, String> {
+    track("db_get_deals_with_details", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
 
-#[tauri::command]
-pub fn db_get_deals_by_status(status: String, user_id: Option<String>) -> Result<Vec<Deal>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
-    
-    let mut stmt = conn
-        .prepare("SELECT * FROM deals WHERE status = ?1 AND user_id = ?2 ORDER BY created_at DESC")
-        .map_err(|e| e.to_string())?;
-    
-    let deals = stmt
-        .query_map(params![status, user_id_value], Deal::from_row)
-        .map_err(|e| e.to_string())?
-        .collect::<SqlResult<Vec<_>>>()
-        .map_err(|e| e.to_string())?;
-    
-    Ok(deals)
+        // One query per table instead of three lookups per deal
+        fn by_id<T>(
+            conn: &Connection,
+            sql: &str,
+            user_id: &str,
+            from_row: fn(&Row) -> SqlResult<T>,
+            id: fn(&T) -> String,
+        ) -> Result<HashMap<String, T>, String> {
+            let mut stmt = conn.prepare(sql).map_err(|e| e.to_string())?;
+            let rows = stmt
+                .query_map(params![user_id], from_row)
+                .map_err(|e| e.to_string())?
+                .collect::<SqlResult<Vec<_>>>()
+                .map_err(|e| e.to_string())?;
+            Ok(rows.into_iter().map(|row| (id(&row), row)).collect())
+        }
+        let clients = by_id(&conn, "SELECT * FROM clients WHERE user_id = ?1", &user_id_value, Client::from_row, |c| c.id.clone())?;
+        // Vehicle::from_row reads by position, and vehicles' columns aren't in struct order
+        let vehicles = by_id(
+            &conn,
+            "SELECT id, vin, stock_number, year, make, model, trim, body, doors,
+             transmission, engine, cylinders, title_number, mileage, color,
+             price, cost, status, description, images, created_at, updated_at, synced_at
+             FROM vehicles WHERE user_id = ?1",
+            &user_id_value,
+            Vehicle::from_row,
+            |v| v.id.clone(),
+        )?;
+        let lenders = by_id(&conn, "SELECT * FROM lenders WHERE user_id = ?1", &user_id_value, Lender::from_row, |l| l.id.clone())?;
+        let mut cobuyers = cobuyers_by_deal(&conn, &user_id_value).map_err(|e| e.to_string())?;
+
+        let mut stmt = conn
+            .prepare("SELECT * FROM deals WHERE user_id = ?1 ORDER BY created_at DESC")
+            .map_err(|e| e.to_string())?;
+        let deals = stmt
+            .query_map(params![user_id_value], Deal::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+
+        let role = state.role();
+        Ok(deals
+            .into_iter()
+            .map(|deal| DealWithDetails {
+                client: clients.get(&deal.client_id).cloned(),
+                vehicle: vehicles
+                    .get(&deal.vehicle_id)
+                    .cloned()
+                    .map(|vehicle| redact_vehicle(role, vehicle)),
+                lender: deal.lender_id.as_ref().and_then(|id| lenders.get(id)).cloned(),
+                cobuyers: cobuyers.remove(&deal.id).unwrap_or_default(),
+                deal,
+            })
+            .collect())
+    })
 }
 
 #[tauri::command]
-pub fn db_update_deal(id: String, updates: Value, user_id: Option<String>) -> Result<Deal, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
+pub fn db_get_deals_by_client(client_id: String, user_id: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Vec<Deal>, String> {
+    track("db_get_deals_by_client", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
     
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
+        let user_id_value = &state.require_user(user_id)?;
     
-    let mut deal: Deal = db_get_deal(id.clone(), Some(user_id_value.clone()))?
-        .ok_or_else(|| "Deal not found or access denied".to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT * FROM deals WHERE client_id = ?1 AND user_id = ?2 ORDER BY created_at DESC")
+            .map_err(|e| e.to_string())?;
     
-    // Apply updates
-    if let Some(r#type) = updates.get("type").and_then(|v| v.as_str()) {
-        deal.r#type = r#type.to_string();
-    }
-    if let Some(status) = updates.get("status").and_then(|v| v.as_str()) {
-        deal.status = status.to_string();
-    }
-    if let Some(total_amount) = updates.get("total_amount").and_then(|v| v.as_f64()) {
-        deal.total_amount = total_amount;
-    }
-    if let Some(sale_date) = updates.get("sale_date").and_then(|v| v.as_i64()) {
-        deal.sale_date = Some(sale_date);
-    }
-    if let Some(sale_amount) = updates.get("sale_amount").and_then(|v| v.as_f64()) {
-        deal.sale_amount = Some(sale_amount);
-    }
-    if let Some(sales_tax) = updates.get("sales_tax").and_then(|v| v.as_f64()) {
-        deal.sales_tax = Some(sales_tax);
-    }
-    if let Some(doc_fee) = updates.get("doc_fee").and_then(|v| v.as_f64()) {
-        deal.doc_fee = Some(doc_fee);
-    }
-    if let Some(trade_in_value) = updates.get("trade_in_value").and_then(|v| v.as_f64()) {
-        deal.trade_in_value = Some(trade_in_value);
-    }
-    if let Some(down_payment) = updates.get("down_payment").and_then(|v| v.as_f64()) {
-        deal.down_payment = Some(down_payment);
-    }
-    if let Some(financed_amount) = updates.get("financed_amount").and_then(|v| v.as_f64()) {
-        deal.financed_amount = Some(financed_amount);
-    }
-    if let Some(document_ids) = updates.get("document_ids") {
-        deal.document_ids = serde_json::to_string(document_ids).map_err(|e| e.to_string())?;
-    }
-    if let Some(cobuyer_data) = updates.get("cobuyer_data") {
-        deal.cobuyer_data = Some(serde_json::to_string(cobuyer_data).map_err(|e| e.to_string())?);
-    }
+        let deals = stmt
+            .query_map(params![client_id, user_id_value], Deal::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
     
-    deal.updated_at = Utc::now().timestamp_millis();
+        Ok(deals)
+    })
+}
+
+#[tauri::command]
+pub fn db_get_deals_by_vehicle(vehicle_id: String, user_id: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Vec<Deal>, String> {
+    track("db_get_deals_by_vehicle", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
     
-    conn.execute(
-        "UPDATE deals SET
-            type = ?2, status = ?3, total_amount = ?4, sale_date = ?5,
-            sale_amount = ?6, sales_tax = ?7, doc_fee = ?8, trade_in_value = ?9,
-            down_payment = ?10, financed_amount = ?11, document_ids = ?12,
-            cobuyer_data = ?13, updated_at = ?14
-        WHERE id = ?1 AND user_id = ?15",
-        params![
-            deal.id,
-            deal.r#type,
-            deal.status,
-            deal.total_amount,
-            deal.sale_date,
-            deal.sale_amount,
-            deal.sales_tax,
-            deal.doc_fee,
-            deal.trade_in_value,
-            deal.down_payment,
-            deal.financed_amount,
-            deal.document_ids,
-            deal.cobuyer_data,
-            deal.updated_at,
-            user_id_value,
-        ],
-    )
-    .map_err(|e| e.to_string())?;
+        let user_id_value = &state.require_user(user_id)?;
     
-    Ok(deal)
+        let mut stmt = conn
+            .prepare("SELECT * FROM deals WHERE vehicle_id = ?1 AND user_id = ?2 ORDER BY created_at DESC")
+            .map_err(|e| e.to_string())?;
+    
+        let deals = stmt
+            .query_map(params![vehicle_id, user_id_value], Deal::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+    
+        Ok(deals)
+    })
 }
 
 #[tauri::command]
-pub fn db_delete_deal(id: String) -> Result<(), String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
+pub fn db_get_deals_by_status(status: String, user_id: Option<String>, fields: Option<Vec<String>>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Listing<Deal>, String> {
+    track("db_get_deals_by_status", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        let user_id_value = &state.require_user(user_id)?;
+        let status = normalize_status(&status).unwrap_or(&status);
+        if let Some(fields) = fields {
+            let projection = Projection::parse(ListEntity::Deal, &fields, state.role())?;
+            let rows = projection
+                .query(&conn, "status = ?1 AND user_id = ?2 ORDER BY created_at DESC", params![status, user_id_value])
+                .map_err(|e| e.to_string())?;
+            return Ok(Listing::Fields(rows));
+        }
     
-    conn.execute("DELETE FROM deals WHERE id = ?1", params![id])
-        .map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare("SELECT * FROM deals WHERE status = ?1 AND user_id = ?2 ORDER BY created_at DESC")
+            .map_err(|e| e.to_string())?;
     
-    info!("✅ Deal deleted: {}", id);
-    Ok(())
+        let deals = stmt
+            .query_map(params![status, user_id_value], Deal::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+    
+        Ok(Listing::Full(deals))
+    })
 }
 
 #[tauri::command]
-pub fn db_search_deals(query: String, user_id: Option<String>) -> Result<Vec<Deal>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
+pub fn db_update_deal(id: String, updates: Value, user_id: Option<String>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Deal, String> {
+    track("db_update_deal", || {
+        let user_id_value = &state.require_user(user_id)?;
+    
+        // Fetched before taking the connection, since db_get_deal locks it too
+        let mut deal: Deal = db_get_deal(id.clone(), Some(user_id_value.clone()), state.clone(), db.clone())?
+            .ok_or_else(|| "Deal not found or access denied".to_string())?;
+        let was_sold = deal.status == DEAL_STATUS_SOLD;
+        let was_completed = deal.status == DEAL_STATUS_COMPLETED;
+        let previous_status = deal.status.clone();
+    
+        // Apply updates
+        if let Some(r#type) = updates.get("type").and_then(|v| v.as_str()) {
+            deal.r#type = r#type.to_string();
+        }
+        // Status changes must follow the lifecycle; "admin_override": true allows any
+        // change (still recorded, with the optional "status_reason")
+        let admin_override = updates.get("admin_override").and_then(|v| v.as_bool()).unwrap_or(false);
+        let mut overridden = false;
+        if let Some(status) = updates.get("status").and_then(|v| v.as_str()) {
+            let status = normalize_status(status)?;
+            overridden = check_transition(&deal.status, status, admin_override)?;
+            deal.status = status.to_string();
+        }
+        if let Some(total_amount) = updates.get("total_amount").and_then(|v| v.as_f64()) {
+            deal.total_amount = total_amount;
+        }
+        if let Some(sale_date) = updates.get("sale_date").and_then(|v| v.as_i64()) {
+            deal.sale_date = Some(sale_date);
+        }
+        if let Some(sale_amount) = updates.get("sale_amount").and_then(|v| v.as_f64()) {
+            deal.sale_amount = Some(sale_amount);
+        }
+        if let Some(sales_tax) = updates.get("sales_tax").and_then(|v| v.as_f64()) {
+            deal.sales_tax = Some(sales_tax);
+        }
+        if let Some(doc_fee) = updates.get("doc_fee").and_then(|v| v.as_f64()) {
+            deal.doc_fee = Some(doc_fee);
+        }
+        if let Some(trade_in_value) = updates.get("trade_in_value").and_then(|v| v.as_f64()) {
+            deal.trade_in_value = Some(trade_in_value);
+        }
+        if let Some(down_payment) = updates.get("down_payment").and_then(|v| v.as_f64()) {
+            deal.down_payment = Some(down_payment);
+        }
+        if let Some(financed_amount) = updates.get("financed_amount").and_then(|v| v.as_f64()) {
+            deal.financed_amount = Some(financed_amount);
+        }
+        if let Some(document_ids) = updates.get("document_ids") {
+            deal.document_ids = serde_json::to_string(document_ids).map_err(|e| e.to_string())?;
+        }
+        let mut cobuyer = None;
+        if let Some(cobuyer_data) = updates.get("cobuyer_data") {
+            deal.cobuyer_data = Some(serde_json::to_string(cobuyer_data).map_err(|e| e.to_string())?);
+            cobuyer = Some(legacy_cobuyer(deal.cobuyer_data.as_deref())?);
+        }
+        if let Some(odometer_at_sale) = updates.get("odometer_at_sale").and_then(|v| v.as_i64()) {
+            deal.odometer_at_sale = Some(odometer_at_sale);
+        }
+        if let Some(odometer_disclosure) = updates.get("odometer_disclosure").and_then(|v| v.as_str()) {
+            deal.odometer_disclosure = Some(odometer_disclosure.to_string());
+        }
+        if let Some(title_status) = updates.get("title_status").and_then(|v| v.as_str()) {
+            deal.title_status = Some(title_status.to_string());
+        }
+        if let Some(title_state) = updates.get("title_state").and_then(|v| v.as_str()) {
+            deal.title_state = Some(title_state.trim().to_uppercase());
+        }
+        if let Some(lien_holder) = updates.get("lien_holder").and_then(|v| v.as_str()) {
+            deal.lien_holder = Some(lien_holder.to_string());
+        }
+        // null unlinks the lender
+        if let Some(lender_id) = updates.get("lender_id") {
+            deal.lender_id = lender_id.as_str().map(str::to_string);
+        }
+        validate_paperwork_fields(&deal)?;
+    
+        // Completing a deal needs its paperwork in order; "force": true skips the check
+        let force = updates.get("force").and_then(|v| v.as_bool()).unwrap_or(false);
+        if !was_completed && deal.status == DEAL_STATUS_COMPLETED && !force {
+            let issues = completion_issues_for(&deal, user_id_value, state, db.clone())?;
+            if !issues.is_empty() {
+                let messages: Vec<&str> = issues.iter().map(|issue| issue.message.as_str()).collect();
+                return Err(format!("Deal can't be completed: {}", messages.join("; ")));
+            }
+        }
     
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
+        deal.round_amounts();
+        deal.updated_at = Utc::now().timestamp_millis();
     
-    let search = format!("%{}%", query);
-    let mut stmt = conn
-        .prepare(
-            "SELECT * FROM deals WHERE user_id = ?1 AND (
-                id LIKE ?2 OR
-                type LIKE ?2 OR
-                status LIKE ?2
-            ) ORDER BY created_at DESC",
+        let db = db.get().map_err(|e| e.to_string())?;
+        let mut conn = db.conn();
+        if let Some(lender_id) = deal.lender_id.as_deref() {
+            check_lender(&conn, user_id_value, lender_id)?;
+        }
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+    
+        tx.execute(
+            "UPDATE deals SET
+                type = ?2, status = ?3, total_amount = ?4, sale_date = ?5,
+                sale_amount = ?6, sales_tax = ?7, doc_fee = ?8, trade_in_value = ?9,
+                down_payment = ?10, financed_amount = ?11, document_ids = ?12,
+                cobuyer_data = ?13, updated_at = ?14, odometer_at_sale = ?16,
+                odometer_disclosure = ?17, title_status = ?18, title_state = ?19, lien_holder = ?20,
+                lender_id = ?21
+            WHERE id = ?1 AND user_id = ?15",
+            params![
+                deal.id,
+                deal.r#type,
+                deal.status,
+                deal.total_amount,
+                deal.sale_date,
+                deal.sale_amount,
+                deal.sales_tax,
+                deal.doc_fee,
+                deal.trade_in_value,
+                deal.down_payment,
+                deal.financed_amount,
+                deal.document_ids,
+                deal.cobuyer_data,
+                deal.updated_at,
+                user_id_value,
+                deal.odometer_at_sale,
+                deal.odometer_disclosure,
+                deal.title_status,
+                deal.title_state,
+                deal.lien_holder,
+                deal.lender_id,
+            ],
         )
         .map_err(|e| e.to_string())?;
+        // Setting doc_fee directly replaces the deal's documentation fee lines
+        if updates.get("doc_fee").is_some() {
+            replace_doc_fee(&tx, &deal.id, user_id_value, deal.doc_fee, deal.updated_at)
+                .map_err(|e| e.to_string())?;
+        }
+        // So does cobuyer_data the deal's first co-buyer
+        if let Some(cobuyer) = cobuyer {
+            replace_first_cobuyer(&tx, &deal.id, user_id_value, cobuyer, deal.updated_at)
+                .map_err(|e| e.to_string())?;
+        }
+        if deal.status != previous_status {
+            let reason = updates.get("status_reason").and_then(|v| v.as_str());
+            record_transition(&tx, &deal.id, user_id_value, Some(&previous_status), &deal.status, overridden, reason)
+                .map_err(|e| e.to_string())?;
+            if overridden {
+                warn!(
+                    "⚠️  Deal {} moved {} -> {} by admin override (user {})",
+                    deal.id, previous_status, deal.status, user_id_value
+                );
+            }
+        }
+        tx.commit().map_err(|e| e.to_string())?;
     
-    let deals = stmt
-        .query_map(params![user_id_value, search], Deal::from_row)
-        .map_err(|e| e.to_string())?
-        .collect::<SqlResult<Vec<_>>>()
-        .map_err(|e| e.to_string())?;
+        notify(user_id_value, WebhookEvent::DealUpdated, &deal);
+        if !was_sold && deal.status == DEAL_STATUS_SOLD {
+            notify(user_id_value, WebhookEvent::DealSold, &deal);
+        }
+        data_changed(user_id_value, ChangedEntity::Deal, &deal.id, Operation::Update);
+        Ok(deal)
+    })
+}
+
+#[tauri::command]
+pub fn db_delete_deal(id: String, db: State<'_, DbState>) -> Result<(), DbError> {
+    track("db_delete_deal", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        let owner = deal_owner(&conn, &id).map_err(|e| e.to_string())?;
+        let deleted = retry_busy("deals", || conn.execute("DELETE FROM deals WHERE id = ?1", params![id]))?;
     
-    Ok(deals)
+        info!("✅ Deal deleted: {}", id);
+        if deleted > 0 {
+            data_changed(&owner.unwrap_or_default(), ChangedEntity::Deal, &id, Operation::Delete);
+        }
+        Ok(())
+    })
 }
 
 #[tauri::command]
-pub fn db_get_deals_stats(user_id: Option<String>) -> Result<serde_json::Value, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
+pub fn db_search_deals(query: String, user_id: Option<String>, fields: Option<Vec<String>>, state: State<'_, AppState>, db: State<'_, DbState>) -> Result<Listing<Deal>, String> {
+    track("db_search_deals", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        let user_id_value = &state.require_user(user_id)?;
+    
+        let search = format!("%{}%", query);
+        if let Some(fields) = fields {
+            let projection = Projection::parse(ListEntity::Deal, &fields, state.role())?;
+            let rows = projection
+                .query(
+                    &conn,
+                    "user_id = ?1 AND (id LIKE ?2 OR type LIKE ?2 OR status LIKE ?2) ORDER BY created_at DESC",
+                    params![user_id_value, search],
+                )
+                .map_err(|e| e.to_string())?;
+            return Ok(Listing::Fields(rows));
+        }
+        let mut stmt = conn
+            .prepare(
+                "SELECT * FROM deals WHERE user_id = ?1 AND (
+                    id LIKE ?2 OR
+                    type LIKE ?2 OR
+                    status LIKE ?2
+                ) ORDER BY created_at DESC",
+            )
+            .map_err(|e| e.to_string())?;
     
-    let user_id_value = user_id.as_ref().ok_or_else(|| "User ID is required".to_string())?;
+        let deals = stmt
+            .query_map(params![user_id_value, search], Deal::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
     
+        Ok(Listing::Full(deals))
+    })
+}
+
+/// Deal counts and amounts by status, and gross profit by month (managers and admins only);
+/// runs as a report job (see reporting.rs)
+#[tauri::command]
+pub async fn db_get_deals_stats(user_id: Option<String>, job_id: Option<String>, window: tauri::Window, state: State<'_, AppState>) -> Result<serde_json::Value, String> {
+    let user_id_value = state.require_user(user_id)?;
+    let role = state.role();
+
+    track_async("db_get_deals_stats", async move {
+        let owner = user_id_value.clone();
+        run_report_job(job_id, "db_get_deals_stats", &owner, window.label(), move |_, conn| {
+            deals_stats(conn, &user_id_value).map(|stats| redact_deals_stats(role, stats))
+        })
+        .await
+    })
+    .await
+}
+
+pub(crate) fn deals_stats(conn: &Connection, user_id_value: &str) -> Result<serde_json::Value, String> {
     let mut stmt = conn
-        .prepare("SELECT status, COUNT(*), SUM(total_amount) FROM deals WHERE user_id = ?1 GROUP BY status")
+        .prepare("SELECT status, COUNT(*), SUM(total_amount_cents) FROM deals WHERE user_id = ?1 GROUP BY status")
         .map_err(|e| e.to_string())?;
-    
+
     let mut by_status: serde_json::Map<String, serde_json::Value> = serde_json::Map::new();
-    let mut total_amount = 0.0;
+    let mut total_cents: i64 = 0;
     let mut total_count = 0;
-    
+
     let rows = stmt
         .query_map(params![user_id_value], |row| {
             Ok((
                 row.get::<_, String>(0)?,
                 row.get::<_, i64>(1)?,
-                row.get::<_, Option<f64>>(2)?,
+                row.get::<_, Option<i64>>(2)?,
             ))
         })
         .map_err(|e| e.to_string())?
         .collect::<SqlResult<Vec<_>>>()
         .map_err(|e| e.to_string())?;
-    
-    for (status, count, amount) in rows {
+
+    for (status, count, cents) in rows {
         by_status.insert(status.clone(), serde_json::json!(count));
         total_count += count;
-        if let Some(amt) = amount {
-            total_amount += amt;
-        }
+        total_cents += cents.unwrap_or(0);
     }
-    
+
+    // Sold deals: sale amount minus the vehicle's summed costs (vehicle_costs.rs), by the
+    // dealer's months
+    let profit_by_month = gross_profit_by_month(conn, user_id_value, &dealer_timezone(conn)).map_err(|e| e.to_string())?;
+    let sold: i64 = profit_by_month.iter().map(|month| month.sold).sum();
+    let gross_profit_cents: i64 = profit_by_month.iter().map(|month| month.gross_profit_cents).sum();
+
     Ok(serde_json::json!({
         "total": total_count,
         "byStatus": by_status,
-        "totalAmount": total_amount,
-        "averageAmount": if total_count > 0 { total_amount / total_count as f64 } else { 0.0 },
+        "totalAmount": from_cents(total_cents),
+        "averageAmount": if total_count > 0 { from_cents(total_cents) / total_count as f64 } else { 0.0 },
+        "grossProfit": gross_profit_cents as f64 / 100.0,
+        "averageGrossProfit": if sold > 0 { gross_profit_cents as f64 / 100.0 / sold as f64 } else { 0.0 },
+        "profitByMonth": profit_by_month,
     }))
 }
 
@@ -1175,10 +2431,12 @@ pub struct Document {
     pub created_at: i64,
     pub updated_at: i64,
     pub synced_at: Option<i64>,
+    #[serde(default)]
+    pub signature: Option<String>, // Base64 HMAC-SHA256 (see signing.rs)
 }
 
 impl Document {
-    fn from_row(row: &Row) -> SqlResult<Self> {
+    pub(crate) fn from_row(row: &Row) -> SqlResult<Self> {
         Ok(Document {
             id: row.get(0)?,
             deal_id: row.get(1)?,
@@ -1190,94 +2448,139 @@ impl Document {
             created_at: row.get(7)?,
             updated_at: row.get(8)?,
             synced_at: row.get(9)?,
+            signature: row.get(10)?,
         })
     }
 }
 
 #[tauri::command]
-pub fn db_create_document(document: Document) -> Result<Document, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
+pub fn db_create_document(document: Document) -> Result<Document, DiskSpaceError> {
+    track("db_create_document", || {
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        // Enforce the deal owner's storage quota before recording the file
+        let owner = deal_owner(&conn, &document.deal_id).map_err(|e| e.to_string())?;
+        let file_size = document.file_size.unwrap_or(0).max(0);
+        if let Some(owner) = &owner {
+            check_quota(&conn, owner, file_size as u64)?;
+        }
     
-    conn.execute(
-        "INSERT INTO documents (
-            id, deal_id, type, filename, file_path, file_size, file_checksum,
-            created_at, updated_at
-        ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![
-            document.id,
-            document.deal_id,
-            document.r#type,
-            document.filename,
-            document.file_path,
-            document.file_size,
-            document.file_checksum,
-            document.created_at,
-            document.updated_at,
-        ],
-    )
-    .map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO documents (
+                id, deal_id, type, filename, file_path, file_size, file_checksum,
+                created_at, updated_at, signature
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                document.id,
+                document.deal_id,
+                document.r#type,
+                document.filename,
+                document.file_path,
+                document.file_size,
+                document.file_checksum,
+                document.created_at,
+                document.updated_at,
+                document.signature,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
     
-    info!("✅ Document created: {}", document.id);
-    Ok(document)
+        if let Some(owner) = &owner {
+            adjust_usage(&conn, owner, file_size).map_err(|e| e.to_string())?;
+        }
+    
+        info!("✅ Document created: {}", document.id);
+        data_changed(&owner.unwrap_or_default(), ChangedEntity::Document, &document.id, Operation::Create);
+        Ok(document)
+    })
 }
 
 #[tauri::command]
 pub fn db_get_document(id: String) -> Result<Option<Document>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    // Explicitly list columns to match Document::from_row order
-    let mut stmt = conn
-        .prepare(
-            "SELECT id, deal_id, type, filename, file_path, file_size, file_checksum, 
-             created_at, updated_at, synced_at 
-             FROM documents WHERE id = ?1"
-        )
-        .map_err(|e| e.to_string())?;
+    track("db_get_document", || {
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+    
+        // Explicitly list columns to match Document::from_row order
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, deal_id, type, filename, file_path, file_size, file_checksum, 
+                 created_at, updated_at, synced_at, signature 
+                 FROM documents WHERE id = ?1"
+            )
+            .map_err(|e| e.to_string())?;
     
-    match stmt.query_row(params![id], Document::from_row) {
-        Ok(doc) => Ok(Some(doc)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.to_string()),
-    }
+        match stmt.query_row(params![id], Document::from_row) {
+            Ok(doc) => Ok(Some(doc)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e.to_string()),
+        }
+    })
 }
 
 #[tauri::command]
 pub fn db_get_documents_by_deal(deal_id: String) -> Result<Vec<Document>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
+    track("db_get_documents_by_deal", || {
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let documents = documents_by_deal(&conn, &deal_id)?;
     
+        info!("✅ Retrieved {} documents for deal {}", documents.len(), deal_id);
+        Ok(documents)
+    })
+}
+
+/// A deal's documents, newest first (also used by db_batch)
+pub(crate) fn documents_by_deal(conn: &Connection, deal_id: &str) -> Result<Vec<Document>, String> {
     // Explicitly list columns to match Document::from_row order:
-    // from_row expects: id, deal_id, type, filename, file_path, file_size, file_checksum, created_at, updated_at, synced_at
-    // Table has: id, deal_id, type, filename, file_path, created_at, updated_at, synced_at, file_size, file_checksum
-    // So we need to reorder: id, deal_id, type, filename, file_path, file_size, file_checksum, created_at, updated_at, synced_at
+    // from_row expects: id, deal_id, type, filename, file_path, file_size, file_checksum, created_at, updated_at, synced_at, signature
+    // Table has: id, deal_id, type, filename, file_path, created_at, updated_at, synced_at, file_size, file_checksum, signature
+    // So we need to reorder: id, deal_id, type, filename, file_path, file_size, file_checksum, created_at, updated_at, synced_at, signature
     let mut stmt = conn
         .prepare(
             "SELECT id, deal_id, type, filename, file_path, file_size, file_checksum, 
-             created_at, updated_at, synced_at 
+             created_at, updated_at, synced_at, signature 
              FROM documents WHERE deal_id = ?1 ORDER BY created_at DESC"
         )
         .map_err(|e| e.to_string())?;
-    
+
     let documents = stmt
         .query_map(params![deal_id], Document::from_row)
         .map_err(|e| e.to_string())?
         .collect::<SqlResult<Vec<_>>>()
         .map_err(|e| e.to_string())?;
-    
-    info!("✅ Retrieved {} documents for deal {}", documents.len(), deal_id);
     Ok(documents)
 }
 
 #[tauri::command]
 pub fn db_update_document(id: String, updates: Value) -> Result<Document, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let mut document: Document = db_get_document(id.clone())?
-        .ok_or_else(|| "Document not found".to_string())?;
-    
+    track("db_update_document", || {
+        // Both lock the database themselves, so they run before taking the connection
+        let document: Document = db_get_document(id.clone())?
+            .ok_or_else(|| "Document not found".to_string())?;
+        let keep_versions = max_versions();
+
+        let db = get_db().map_err(|e| e.to_string())?;
+        let mut conn = db.conn();
+        let document = update_document(&mut conn, document, &updates, keep_versions)?;
+        let owner = deal_owner(&conn, &document.deal_id).map_err(|e| e.to_string())?;
+        data_changed(&owner.unwrap_or_default(), ChangedEntity::Document, &document.id, Operation::Update);
+        Ok(document)
+    })
+}
+
+/// Apply `updates` to a document. When the file path or checksum changes, the current file
+/// is kept as a version first (see document_versions.rs). Update the row before
+/// overwriting a file in place, or the previous content is already gone.
+pub(crate) fn update_document(
+    conn: &mut Connection,
+    previous: Document,
+    updates: &Value,
+    keep_versions: usize,
+) -> Result<Document, String> {
+    let mut document = previous.clone();
+
     if let Some(filename) = updates.get("filename").and_then(|v| v.as_str()) {
         document.filename = filename.to_string();
     }
@@ -1290,13 +2593,31 @@ pub fn db_update_document(id: String, updates: Value) -> Result<Document, String
     if let Some(file_checksum) = updates.get("file_checksum").and_then(|v| v.as_str()) {
         document.file_checksum = Some(file_checksum.to_string());
     }
-    
+    if let Some(signature) = updates.get("signature").and_then(|v| v.as_str()) {
+        document.signature = Some(signature.to_string());
+    }
+
     document.updated_at = Utc::now().timestamp_millis();
-    
-    conn.execute(
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    if document.file_path != previous.file_path
+        || document.file_checksum != previous.file_checksum
+    {
+        let reason = updates.get("version_reason").and_then(|v| v.as_str());
+        snapshot_document(
+            &tx,
+            &previous,
+            document.file_checksum.as_deref(),
+            reason,
+            keep_versions,
+        )?;
+    }
+
+    tx.execute(
         "UPDATE documents SET
             filename = ?2, file_path = ?3, file_size = ?4,
-            file_checksum = ?5, updated_at = ?6
+            file_checksum = ?5, updated_at = ?6, signature = ?7
         WHERE id = ?1",
         params![
             document.id,
@@ -1305,104 +2626,371 @@ pub fn db_update_document(id: String, updates: Value) -> Result<Document, String
             document.file_size,
             document.file_checksum,
             document.updated_at,
+            document.signature,
         ],
     )
     .map_err(|e| e.to_string())?;
-    
+
+    tx.commit().map_err(|e| e.to_string())?;
     Ok(document)
 }
 
 #[tauri::command]
 pub fn db_delete_document(id: String) -> Result<(), String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
+    track("db_delete_document", || {
+        // The document's file goes to the trash under the documents root (document_trash.rs)
+        let trash_root = match documents_root() {
+            Ok(root) => Some(PathBuf::from(root)),
+            Err(e) => {
+                warn!("⚠️  No documents root, leaving the file of document {} in place: {}", id, e);
+                None
+            }
+        };
+
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
     
-    // Get document to delete file (will be handled by TypeScript wrapper)
-    // Just delete from database here
+        // Size and owner are needed afterwards to release the storage usage
+        let row: Option<(Option<i64>, Option<String>, String, String)> = conn
+            .query_row(
+                "SELECT d.file_size, COALESCE(d.user_id, deals.user_id), d.file_path, d.deal_id
+                 FROM documents d LEFT JOIN deals ON deals.id = d.deal_id
+                 WHERE d.id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
     
-    conn.execute("DELETE FROM documents WHERE id = ?1", params![id])
-        .map_err(|e| e.to_string())?;
+        // Version rows cascade with the document; their files have to go by hand
+        let version_files = version_file_paths(&conn, &id).map_err(|e| e.to_string())?;
+
+        conn.execute("DELETE FROM documents WHERE id = ?1", params![id])
+            .map_err(|e| e.to_string())?;
+        remove_version_files(&version_files);
+    
+        if let Some((file_size, owner, file_path, deal_id)) = row {
+            if let Some(root) = &trash_root {
+                let document = TrashedDocument {
+                    document_id: &id,
+                    deal_id: &deal_id,
+                    user_id: owner.as_deref(),
+                    file_path: &file_path,
+                };
+                // The row is gone either way; a file that couldn't be moved stays where it was
+                if let Err(e) = trash_document_file(root, document, Utc::now().timestamp_millis()) {
+                    warn!("⚠️  {}", e);
+                }
+            }
+            if let Some(owner) = &owner {
+                adjust_usage(&conn, owner, -file_size.unwrap_or(0).max(0))
+                    .map_err(|e| e.to_string())?;
+            }
+            data_changed(&owner.unwrap_or_default(), ChangedEntity::Document, &id, Operation::Delete);
+        }
     
-    info!("✅ Document deleted: {}", id);
-    Ok(())
+        info!("✅ Document deleted: {}", id);
+        Ok(())
+    })
 }
 
 /// Clear all data from the database (development/testing only)
 /// WARNING: This will delete ALL data from all tables
 #[tauri::command]
 pub fn db_clear_all_data() -> Result<(), String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
+    track("db_clear_all_data", || {
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
     
-    info!("🗑️ Clearing all data from database...");
+        info!("🗑️ Clearing all data from database...");
     
-    // Delete in order to respect foreign key constraints:
-    // 1. Documents (CASCADE will handle it, but explicit is better)
-    // 2. Deals (has RESTRICT foreign keys, so must delete before clients/vehicles)
-    // 3. Vehicles
-    // 4. Clients
-    // 5. Settings (optional - keeping for now)
-    // 6. Sync log (if exists)
+        // Delete in order to respect foreign key constraints:
+        // 1. Documents (CASCADE will handle it, but explicit is better)
+        // 2. Deals (has RESTRICT foreign keys, so must delete before clients/vehicles)
+        // 3. Vehicles
+        // 4. Clients
+        // 5. Settings (optional - keeping for now)
+        // 6. Sync log (if exists)
     
-    conn.execute("DELETE FROM documents", [])
-        .map_err(|e| e.to_string())?;
-    info!("✅ Cleared documents");
+        conn.execute("DELETE FROM documents", [])
+            .map_err(|e| e.to_string())?;
+        info!("✅ Cleared documents");
     
-    conn.execute("DELETE FROM deals", [])
-        .map_err(|e| e.to_string())?;
-    info!("✅ Cleared deals");
+        conn.execute("DELETE FROM deals", [])
+            .map_err(|e| e.to_string())?;
+        info!("✅ Cleared deals");
     
-    conn.execute("DELETE FROM vehicles", [])
-        .map_err(|e| e.to_string())?;
-    info!("✅ Cleared vehicles");
+        conn.execute("DELETE FROM vehicles", [])
+            .map_err(|e| e.to_string())?;
+        info!("✅ Cleared vehicles");
     
-    conn.execute("DELETE FROM clients", [])
-        .map_err(|e| e.to_string())?;
-    info!("✅ Cleared clients");
+        conn.execute("DELETE FROM clients", [])
+            .map_err(|e| e.to_string())?;
+        info!("✅ Cleared clients");
     
-    // Optionally clear settings (commented out to preserve app settings)
-    // conn.execute("DELETE FROM settings", [])
-    //     .map_err(|e| e.to_string())?;
+        // Optionally clear settings (commented out to preserve app settings)
+        // conn.execute("DELETE FROM settings", [])
+        //     .map_err(|e| e.to_string())?;
     
-    // Clear sync log if it exists
-    let _ = conn.execute("DELETE FROM sync_log", []);
+        // Clear sync log if it exists
+        let _ = conn.execute("DELETE FROM sync_log", []);
     
-    info!("✅ All data cleared from database");
-    Ok(())
+        info!("✅ All data cleared from database");
+        Ok(())
+    })
 }
 
-/// Get a setting value by key
-#[tauri::command]
-pub fn db_get_setting(key: String) -> Result<Option<String>, String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let mut stmt = conn
-        .prepare("SELECT value FROM settings WHERE key = ?1")
-        .map_err(|e| e.to_string())?;
-    
-    match stmt.query_row(params![key], |row| row.get::<_, String>(0)) {
-        Ok(value) => Ok(Some(value)),
-        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
-        Err(e) => Err(e.to_string()),
-    }
+/// A setting as stored in the settings table (db_get_setting also sees queued values)
+pub(crate) fn read_setting(conn: &Connection, key: &str) -> SqlResult<Option<String>> {
+    conn.query_row(
+        "SELECT value FROM settings WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    )
+    .optional()
 }
 
-/// Set a setting value
-#[tauri::command]
-pub fn db_set_setting(key: String, value: String) -> Result<(), String> {
-    let db = get_db().map_err(|e| e.to_string())?;
-    let conn = db.conn();
-    
-    let now = Utc::now().timestamp_millis();
-    
+pub(crate) fn upsert_setting(conn: &Connection, key: &str, value: &str, now: i64) -> SqlResult<()> {
     conn.execute(
         "INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)
          ON CONFLICT(key) DO UPDATE SET value = ?2, updated_at = ?3",
         params![key, value, now],
-    )
-    .map_err(|e| e.to_string())?;
-    
+    )?;
     Ok(())
 }
 
+/// Get a setting value by key, including one set a moment ago and not yet written
+#[tauri::command]
+pub fn db_get_setting(key: String) -> Result<Option<String>, String> {
+    get_setting(&key)
+}
+
+/// Settings the frontend keeps for itself. Every other key changes how the app behaves,
+/// and most have their own admin-only command, so db_set_setting leaves them to admins
+const FRONTEND_SETTINGS: &[&str] = &["last_sync_at"];
+
+/// Set a setting value. Written behind, with other settings set around the same time
+/// (settings_cache.rs)
+#[tauri::command]
+pub fn db_set_setting(
+    key: String,
+    value: String,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    if !FRONTEND_SETTINGS.contains(&key.as_str()) {
+        state.check_permission(Permission::Admin)?;
+    }
+    set_setting(&key, &value)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{
+        make_client, make_deal, make_vehicle, seeded_db_at, TestApp, SEED_CLIENT, SEED_DEAL,
+        SEED_VEHICLE, TEST_USER,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn test_client_commands() {
+        let app = TestApp::new();
+        let created = db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        assert_eq!(created.user_id.as_deref(), Some(TEST_USER));
+        db_create_client(make_client("c2"), None, None, None, app.state(), app.db()).unwrap();
+
+        let fetched = db_get_client("c1".into(), None, app.state(), app.db()).unwrap().unwrap();
+        assert_eq!(fetched.last_name, "Client c1");
+
+        let updates = json!({ "phone": "555-999-0000", "city": "Dallas" });
+        let updated = db_update_client("c1".into(), updates, None, None, app.state(), app.db()).unwrap();
+        assert_eq!(updated.phone.as_deref(), Some("(555) 999-0000"));
+        assert_eq!(updated.city.as_deref(), Some("Dallas"));
+        // Fields not in the update are kept
+        assert_eq!(updated.email.as_deref(), Some("c1@example.com"));
+
+        let Listing::Full(found) = db_search_clients("999-0000".into(), None, None, app.state(), app.db()).unwrap() else {
+            panic!("expected full clients");
+        };
+        assert_eq!(found.iter().map(|c| c.id.as_str()).collect::<Vec<_>>(), ["c1"]);
+        assert_eq!(db_get_all_clients(None, None, app.state(), app.db()).unwrap().len(), 2);
+
+        // Another user can't see, change or delete them
+        app.sign_in("someone-else");
+        assert!(db_get_client("c1".into(), None, app.state(), app.db()).unwrap().is_none());
+        assert!(db_get_all_clients(None, None, app.state(), app.db()).unwrap().is_empty());
+        let updates = json!({ "city": "Waco" });
+        assert!(db_update_client("c1".into(), updates, None, None, app.state(), app.db()).is_err());
+        db_delete_client("c1".into(), None, app.state(), app.db()).unwrap();
+
+        app.sign_in(TEST_USER);
+        assert!(db_get_client("c1".into(), None, app.state(), app.db()).unwrap().is_some());
+        db_delete_client("c1".into(), None, app.state(), app.db()).unwrap();
+        assert!(db_get_client("c1".into(), None, app.state(), app.db()).unwrap().is_none());
+        assert_eq!(db_get_all_clients(None, None, app.state(), app.db()).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_client_search_matches_normalized_phones_and_emails() {
+        let app = TestApp::new();
+        let mut ada = make_client("c1");
+        ada.phone = Some("248.555.1212 x204".to_string());
+        ada.email = Some(" Ada@Example.COM ".to_string());
+        let ada = db_create_client(ada, None, None, None, app.state(), app.db()).unwrap();
+        assert_eq!(ada.phone.as_deref(), Some("(248) 555-1212 x204"));
+        assert_eq!(ada.email.as_deref(), Some("Ada@Example.COM"));
+        let mut shop = make_client("c2");
+        shop.phone = Some("ask for Sam".to_string());
+        shop.email = None;
+        db_create_client(shop, None, None, None, app.state(), app.db()).unwrap();
+
+        let search = |query: &str| {
+            let Listing::Full(found) = db_search_clients(query.into(), None, None, app.state(), app.db()).unwrap() else {
+                panic!("expected full clients");
+            };
+            found.into_iter().map(|c| c.id).collect::<Vec<_>>()
+        };
+        for query in ["2485551212", "(248) 555-1212", "248-555", "+1 248 555 1212", "ada@example.com", "ADA@"] {
+            assert_eq!(search(query), ["c1"], "{:?}", query);
+        }
+        // Unusable numbers are stored as entered, flagged, and still found as entered
+        assert_eq!(search("for Sam"), ["c2"]);
+        let (phone, invalid, e164): (String, bool, Option<String>) = app
+            .conn()
+            .query_row("SELECT phone, phone_invalid, phone_e164 FROM clients WHERE id = 'c2'", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!((phone.as_str(), invalid, e164), ("ask for Sam", true, None));
+
+        let updated = db_update_client("c1".into(), json!({ "phone": "313 555 0100" }), None, None, app.state(), app.db()).unwrap();
+        assert_eq!(updated.phone.as_deref(), Some("(313) 555-0100"));
+        assert_eq!(search("3135550100"), ["c1"]);
+        assert!(search("2485551212").is_empty());
+    }
+
+    #[test]
+    fn test_vehicle_reads_are_scoped_to_the_user() {
+        let app = TestApp::new();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+
+        let fetched = db_get_vehicle("v1".into(), None, app.state(), app.db()).unwrap().unwrap();
+        assert_eq!(fetched.vin, "TESTVIN-v1");
+        assert!(db_get_vehicle_by_vin("TESTVIN-v1".into(), None, app.state(), app.db()).unwrap().is_some());
+        assert!(db_get_vehicle_by_stock("STK-v1".into(), None, app.state(), app.db()).unwrap().is_some());
+        assert_eq!(db_search_vehicles("Camry".into(), None, None, app.state(), app.db()).unwrap().len(), 1);
+        assert_eq!(db_get_vehicles_by_status("available".into(), None, None, app.state(), app.db()).unwrap().len(), 1);
+
+        app.sign_in("someone-else");
+        assert!(db_get_vehicle("v1".into(), None, app.state(), app.db()).unwrap().is_none());
+        assert!(db_get_vehicle_by_vin("TESTVIN-v1".into(), None, app.state(), app.db()).unwrap().is_none());
+        assert!(db_get_vehicle_by_stock("STK-v1".into(), None, app.state(), app.db()).unwrap().is_none());
+        assert!(db_search_vehicles("Camry".into(), None, None, app.state(), app.db()).unwrap().is_empty());
+        let fields = Some(vec!["id".to_string()]);
+        assert!(db_search_vehicles("Camry".into(), None, fields.clone(), app.state(), app.db()).unwrap().is_empty());
+        assert!(db_get_vehicles_by_status("available".into(), None, None, app.state(), app.db()).unwrap().is_empty());
+        assert!(db_get_vehicles_by_status("available".into(), None, fields, app.state(), app.db()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_deal_commands() {
+        let app = TestApp::new();
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+
+        let mut deal = make_deal("d1", "c1", "v1");
+        deal.status = "draft".to_string();
+        deal.doc_fee = Some(199.0);
+        let created = db_create_deal(deal, None, None, app.state(), app.db()).unwrap();
+        // Old status names are stored under their canonical name
+        assert_eq!(created.status, "quote");
+        let fee_cents: i64 = app
+            .conn()
+            .query_row(
+                "SELECT amount_cents FROM deal_fees WHERE deal_id = 'd1' AND category = 'doc_fee'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(fee_cents, 19_900);
+
+        // The lifecycle can't be skipped
+        let skip = json!({ "status": "sold" });
+        assert!(db_update_deal("d1".into(), skip, None, app.state(), app.db()).is_err());
+        for status in ["pending", "approved", "sold"] {
+            let updates = json!({ "status": status });
+            let deal = db_update_deal("d1".into(), updates, None, app.state(), app.db()).unwrap();
+            assert_eq!(deal.status, status);
+        }
+        let transitions: i64 = app
+            .conn()
+            .query_row("SELECT COUNT(*) FROM deal_status_history WHERE deal_id = 'd1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(transitions, 4);
+
+        // Completing checks the paperwork (no odometer reading yet)
+        let complete = json!({ "status": "completed" });
+        let err = db_update_deal("d1".into(), complete, None, app.state(), app.db()).unwrap_err();
+        assert!(err.starts_with("Deal can't be completed"), "{}", err);
+
+        let by_client = db_get_deals_by_client("c1".into(), None, app.state(), app.db()).unwrap();
+        assert_eq!(by_client.len(), 1);
+        let sold = db_get_deals_by_status("sold".into(), None, None, app.state(), app.db()).unwrap();
+        assert_eq!(sold.len(), 1);
+        let details = db_get_deals_with_details(None, app.state(), app.db()).unwrap();
+        assert_eq!(details[0].client.as_ref().map(|c| c.id.as_str()), Some("c1"));
+
+        app.sign_in("someone-else");
+        assert!(db_get_deal("d1".into(), None, app.state(), app.db()).unwrap().is_none());
+        let updates = json!({ "total_amount": 1.0 });
+        assert!(db_update_deal("d1".into(), updates, None, app.state(), app.db()).is_err());
+
+        app.sign_in(TEST_USER);
+        db_delete_deal("d1".into(), app.db()).unwrap();
+        assert!(db_get_deal("d1".into(), None, app.state(), app.db()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_every_schema_version_upgrades_with_its_data() {
+        let latest: i32 = Database::init_in_memory()
+            .unwrap()
+            .conn()
+            .query_row("SELECT MAX(version) FROM schema_migrations", [], |row| row.get(0))
+            .unwrap();
+
+        for version in 1..=latest {
+            let db = seeded_db_at(version);
+            let at: i32 = db
+                .conn()
+                .query_row("SELECT MAX(version) FROM schema_migrations", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(at, version);
+
+            db.migrate_to(i32::MAX).unwrap();
+            let conn = db.conn();
+            let count = |sql: &str, id: &str| -> i64 {
+                conn.query_row(sql, params![id], |row| row.get(0)).unwrap()
+            };
+            let now: i32 = conn
+                .query_row("SELECT MAX(version) FROM schema_migrations", [], |row| row.get(0))
+                .unwrap();
+            assert_eq!(now, latest, "from version {}", version);
+            assert_eq!(count("SELECT COUNT(*) FROM clients WHERE id = ?1", SEED_CLIENT), 1);
+            assert_eq!(count("SELECT COUNT(*) FROM vehicles WHERE id = ?1", SEED_VEHICLE), 1);
+            assert_eq!(count("SELECT COUNT(*) FROM deals WHERE id = ?1", SEED_DEAL), 1);
+
+            // Backfills only cover rows that existed before their migration
+            let doc_fees = count("SELECT COUNT(*) FROM deal_fees WHERE deal_id = ?1", SEED_DEAL);
+            assert_eq!(doc_fees, i64::from(version < 20), "from version {}", version);
+            let costs = count("SELECT COUNT(*) FROM vehicle_costs WHERE vehicle_id = ?1", SEED_VEHICLE);
+            assert_eq!(costs, i64::from(version < 23), "from version {}", version);
+            let price_cents = count("SELECT price_cents FROM vehicles WHERE id = ?1", SEED_VEHICLE);
+            assert_eq!(price_cents, 1_200_000, "from version {}", version);
+            let owner: Option<String> = conn
+                .query_row("SELECT user_id FROM deals WHERE id = ?1", params![SEED_DEAL], |row| row.get(0))
+                .unwrap();
+            assert_eq!(owner.is_some(), version >= 5, "from version {}", version);
+        }
+    }
+}
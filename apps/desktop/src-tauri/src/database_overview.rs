@@ -0,0 +1,355 @@
+// src-tauri/src/database_overview.rs
+//
+// One-call summary for the settings "Data" page: how many of each record the user has,
+// how many haven't synced yet, database/WAL size, schema version, the latest backup and how
+// long the last startup migration took.
+// Row counts are a single UNION ALL of aggregate queries on the user_id indexes, run under
+// one lock acquisition; file sizes and the backup folder are read after the lock is released.
+// Rows are deleted outright, so the only "soft-deleted" records are documents whose files
+// are still in the trash (document_trash.rs).
+
+use log::warn;
+use rusqlite::{Connection, OptionalExtension};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_export::documents_root;
+use crate::database::{get_db, Database};
+use crate::document_trash::trashed_document_count;
+use crate::startup_migrations::{last_migration_run, MigrationRun};
+use crate::storage::get_backup_path;
+use crate::telemetry::track;
+
+/// Per-table counts for one user. Documents without a user_id belong to their deal's owner.
+/// The unsynced column is NULL for tables that don't sync.
+const COUNTS_SQL: &str = "
+    SELECT 'clients', COUNT(*), COUNT(*) - COUNT(synced_at) FROM clients WHERE user_id = ?1
+    UNION ALL
+    SELECT 'vehicles', COUNT(*), COUNT(*) - COUNT(synced_at) FROM vehicles WHERE user_id = ?1
+    UNION ALL
+    SELECT 'deals', COUNT(*), COUNT(*) - COUNT(synced_at) FROM deals WHERE user_id = ?1
+    UNION ALL
+    SELECT 'documents', COUNT(*), COUNT(*) - COUNT(d.synced_at)
+    FROM documents d LEFT JOIN deals ON deals.id = d.deal_id
+    WHERE COALESCE(d.user_id, deals.user_id) = ?1
+    UNION ALL
+    SELECT 'tasks', COUNT(*), NULL FROM tasks WHERE user_id = ?1
+    UNION ALL
+    SELECT 'communications', COUNT(*), NULL FROM communications WHERE user_id = ?1";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TableOverview {
+    pub table: String,
+    pub rows: u64,
+    /// Rows never synced (synced_at is null); None for tables that aren't synced
+    pub unsynced: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DatabaseOverview {
+    pub tables: Vec<TableOverview>,
+    /// Deleted documents whose files can still be restored from the trash
+    pub trashed_documents: usize,
+    pub database_bytes: u64,
+    pub wal_bytes: u64,
+    pub schema_version: Option<i64>,
+    /// Modification time (unix ms) of the newest file in the backups folder
+    pub last_backup_at: Option<i64>,
+    /// The last startup that applied migrations, and how long they took
+    pub last_migration: Option<MigrationRun>,
+}
+
+fn table_counts(conn: &Connection, user_id: &str) -> Result<Vec<TableOverview>, String> {
+    let mut stmt = conn.prepare_cached(COUNTS_SQL).map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([user_id], |row| {
+            Ok(TableOverview {
+                table: row.get(0)?,
+                rows: row.get(1)?,
+                unsynced: row.get(2)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())
+}
+
+fn schema_version(conn: &Connection) -> Result<Option<i64>, String> {
+    conn.query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+        row.get(0)
+    })
+    .optional()
+    .map(Option::flatten)
+    .map_err(|e| e.to_string())
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// The database file's WAL sits next to it as "{name}-wal"
+fn wal_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.as_os_str().to_owned();
+    name.push("-wal");
+    PathBuf::from(name)
+}
+
+/// Newest file modification time in dir (unix ms), looking one folder deep
+fn latest_backup(dir: &Path) -> Option<i64> {
+    let modified = |path: &Path| -> Option<i64> {
+        let modified = fs::metadata(path).ok()?.modified().ok()?;
+        Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as i64)
+    };
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .flat_map(|entry| {
+            let path = entry.path();
+            if path.is_dir() {
+                fs::read_dir(&path)
+                    .map(|inner| inner.flatten().map(|e| e.path()).collect())
+                    .unwrap_or_default()
+            } else {
+                vec![path]
+            }
+        })
+        .filter(|path| path.is_file())
+        .filter_map(|path| modified(&path))
+        .max()
+}
+
+#[tauri::command]
+pub fn db_get_database_overview(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DatabaseOverview, String> {
+    track("db_get_database_overview", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let (tables, schema_version, last_migration) = {
+            let conn = db.conn();
+            (
+                table_counts(&conn, &user_id_value)?,
+                schema_version(&conn)?,
+                last_migration_run(&conn),
+            )
+        };
+
+        let db_path = Database::get_db_path().map_err(|e| e.to_string())?;
+        let trashed_documents = documents_root()
+            .and_then(|root| trashed_document_count(Path::new(&root), &user_id_value))
+            .unwrap_or_else(|e| {
+                warn!("⚠️  Could not count trashed documents: {}", e);
+                0
+            });
+        Ok(DatabaseOverview {
+            tables,
+            trashed_documents,
+            database_bytes: file_size(&db_path),
+            wal_bytes: file_size(&wal_path(&db_path)),
+            schema_version,
+            last_backup_at: get_backup_path()
+                .ok()
+                .and_then(|dir| latest_backup(Path::new(&dir))),
+            last_migration,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use rusqlite::params;
+    use std::time::Instant;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/004_add_vehicle_images.sql"),
+            include_str!("../migrations/018_add_tasks.sql"),
+            include_str!("../migrations/021_add_communications.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute_batch(
+            "CREATE TABLE schema_migrations (version INTEGER PRIMARY KEY, applied_at TEXT NOT NULL);
+             INSERT INTO schema_migrations VALUES (20, 'then'), (21, 'now');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn client(conn: &Connection, id: &str, user: &str, synced_at: Option<i64>) {
+        conn.execute(
+            "INSERT INTO clients (id, user_id, first_name, last_name, created_at, updated_at, synced_at)
+             VALUES (?1, ?2, 'Ada', 'Lovelace', 0, 0, ?3)",
+            params![id, user, synced_at],
+        )
+        .unwrap();
+    }
+
+    fn vehicle(conn: &Connection, id: &str, user: &str, synced_at: Option<i64>) {
+        conn.execute(
+            "INSERT INTO vehicles (id, user_id, vin, year, make, model, mileage, price, status,
+                                   created_at, updated_at, synced_at)
+             VALUES (?1, ?2, ?1, 2020, 'Honda', 'Civic', 0, 0, 'available', 0, 0, ?3)",
+            params![id, user, synced_at],
+        )
+        .unwrap();
+    }
+
+    fn deal(conn: &Connection, id: &str, user: &str, synced_at: Option<i64>) {
+        conn.execute(
+            "INSERT INTO deals (id, user_id, type, client_id, vehicle_id, status, total_amount,
+                                created_at, updated_at, synced_at)
+             VALUES (?1, ?2, 'retail', 'c1', 'v1', 'pending', 0, 0, 0, ?3)",
+            params![id, user, synced_at],
+        )
+        .unwrap();
+    }
+
+    fn document(
+        conn: &Connection,
+        id: &str,
+        deal_id: &str,
+        user: Option<&str>,
+        synced_at: Option<i64>,
+    ) {
+        conn.execute(
+            "INSERT INTO documents (id, deal_id, user_id, type, filename, file_path, created_at,
+                                    updated_at, synced_at)
+             VALUES (?1, ?2, ?3, 'contract', 'a.pdf', '/docs/a.pdf', 0, 0, ?4)",
+            params![id, deal_id, user, synced_at],
+        )
+        .unwrap();
+    }
+
+    fn counts(tables: &[TableOverview]) -> Vec<(&str, u64, Option<u64>)> {
+        tables
+            .iter()
+            .map(|t| (t.table.as_str(), t.rows, t.unsynced))
+            .collect()
+    }
+
+    #[test]
+    fn test_counts_are_scoped_to_the_user() {
+        let conn = test_db();
+        client(&conn, "c1", "u1", Some(5));
+        client(&conn, "c2", "u1", None);
+        client(&conn, "c3", "u2", None);
+        vehicle(&conn, "v1", "u1", None);
+        vehicle(&conn, "v2", "u2", Some(5));
+        deal(&conn, "d1", "u1", Some(5));
+        deal(&conn, "d2", "u2", None);
+        // Documents without an owner count for the deal's owner
+        document(&conn, "doc1", "d1", Some("u1"), Some(5));
+        document(&conn, "doc2", "d1", None, None);
+        document(&conn, "doc3", "d2", None, None);
+        document(&conn, "doc4", "d2", Some("u2"), None);
+        conn.execute_batch(
+            "INSERT INTO tasks (id, user_id, title, due_at, created_at) VALUES
+                 ('t1', 'u1', 'Call back', 0, 0), ('t2', 'u1', 'Title', 0, 0),
+                 ('t3', 'u2', 'Other', 0, 0);
+             INSERT INTO communications (id, user_id, client_id, channel, direction, status,
+                                         created_at, updated_at)
+                 VALUES ('m1', 'u1', 'c1', 'call', 'inbound', 'logged', 0, 0);",
+        )
+        .unwrap();
+
+        assert_eq!(
+            counts(&table_counts(&conn, "u1").unwrap()),
+            [
+                ("clients", 2, Some(1)),
+                ("vehicles", 1, Some(1)),
+                ("deals", 1, Some(0)),
+                ("documents", 2, Some(1)),
+                ("tasks", 2, None),
+                ("communications", 1, None),
+            ]
+        );
+        assert_eq!(
+            counts(&table_counts(&conn, "u2").unwrap()),
+            [
+                ("clients", 1, Some(1)),
+                ("vehicles", 1, Some(0)),
+                ("deals", 1, Some(1)),
+                ("documents", 2, Some(2)),
+                ("tasks", 1, None),
+                ("communications", 0, None),
+            ]
+        );
+        assert!(table_counts(&conn, "nobody")
+            .unwrap()
+            .iter()
+            .all(|t| t.rows == 0 && t.unsynced.unwrap_or(0) == 0));
+        assert_eq!(schema_version(&conn).unwrap(), Some(21));
+    }
+
+    #[test]
+    fn test_counts_stay_fast_on_large_tables() {
+        let conn = test_db();
+        let tx = conn.unchecked_transaction().unwrap();
+        let rows = (0..20_000).map(|i| {
+            let user = if i % 2 == 0 { "u1" } else { "u2" };
+            (i, user, (i % 3 == 0).then_some(1))
+        });
+        // Deals point at c1/v1, so those go in first
+        for (i, user, synced) in rows.clone() {
+            client(&tx, &format!("c{}", i), user, synced);
+            vehicle(&tx, &format!("v{}", i), user, synced);
+        }
+        for (i, user, synced) in rows {
+            deal(&tx, &format!("d{}", i), user, synced);
+            document(&tx, &format!("doc{}", i), &format!("d{}", i), None, synced);
+        }
+        tx.commit().unwrap();
+
+        let started = Instant::now();
+        let tables = table_counts(&conn, "u1").unwrap();
+        assert!(
+            started.elapsed().as_millis() < 100,
+            "{:?}",
+            started.elapsed()
+        );
+        assert_eq!(tables[0].rows, 10_000);
+        assert_eq!(tables[3].rows, 10_000);
+        // Every third row is synced; 3,334 of the even ones
+        assert_eq!(tables[0].unsynced, Some(10_000 - 3_334));
+    }
+
+    #[test]
+    fn test_file_sizes_and_latest_backup() {
+        let dir = temp_dir("overview", "file-sizes");
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        assert_eq!(latest_backup(&dir), None);
+        assert_eq!(latest_backup(&dir.join("missing")), None);
+
+        fs::write(dir.join("old.db"), b"old").unwrap();
+        fs::write(dir.join("nested").join("new.zip"), b"new").unwrap();
+        let newest = fs::metadata(dir.join("nested").join("new.zip"))
+            .unwrap()
+            .modified()
+            .unwrap()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as i64;
+        assert_eq!(latest_backup(&dir), Some(newest));
+
+        let db_path = dir.join("dealer.db");
+        fs::write(wal_path(&db_path), vec![0u8; 4096]).unwrap();
+        assert_eq!(wal_path(&db_path), dir.join("dealer.db-wal"));
+        assert_eq!(file_size(&wal_path(&db_path)), 4096);
+        assert_eq!(file_size(&db_path), 0);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
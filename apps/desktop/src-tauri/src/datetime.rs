@@ -0,0 +1,245 @@
+// src-tauri/src/datetime.rs
+//
+// The dealer's calendar. Timestamps are UTC epoch millis everywhere; anything that asks
+// "which day" or "which month" (stats by month, the daily desk log, retention cut-offs,
+// export dates) converts them in the dealer's time zone, an IANA name kept in the
+// dealer_timezone setting. Without one, this computer's zone is used, and UTC if even that
+// can't be read. Days are cut at local midnight, so they're 23 or 25 hours long on DST
+// change days, and a day that starts in a DST gap starts when the gap ends.
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use log::warn;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::fmt::Display;
+
+use crate::database::read_setting;
+use crate::settings_cache::{get_setting, pending_setting, set_setting};
+use crate::telemetry::track;
+
+/// Settings key: the dealer's IANA time zone ("America/Los_Angeles"); empty or missing
+/// means this computer's zone
+pub const TIMEZONE_SETTING: &str = "dealer_timezone";
+
+/// Longest DST gap skipped looking for the first instant of a day that starts in one
+const MAX_GAP_HOURS: i64 = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DealerTimezone {
+    /// The zone in use
+    pub timezone: String,
+    /// No zone is saved, so this computer's is used
+    pub from_computer: bool,
+}
+
+/// A zone from the IANA database, by its exact name
+pub(crate) fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.trim()
+        .parse::<Tz>()
+        .map_err(|_| format!("Unknown time zone: {}", name.trim()))
+}
+
+/// This computer's zone, or UTC if it can't be read
+pub(crate) fn machine_timezone() -> Tz {
+    iana_time_zone::get_timezone()
+        .ok()
+        .and_then(|name| parse_timezone(&name).ok())
+        .unwrap_or(Tz::UTC)
+}
+
+/// The saved zone, if there is a valid one
+fn saved_timezone(value: Option<String>) -> Option<Tz> {
+    let name = value.filter(|name| !name.trim().is_empty())?;
+    match parse_timezone(&name) {
+        Ok(tz) => Some(tz),
+        Err(e) => {
+            warn!("⚠️  {}; using this computer's zone", e);
+            None
+        }
+    }
+}
+
+/// The dealer's zone, read through conn (or from a setting not yet written)
+pub(crate) fn dealer_timezone(conn: &Connection) -> Tz {
+    let value = pending_setting(TIMEZONE_SETTING)
+        .or_else(|| read_setting(conn, TIMEZONE_SETTING).ok().flatten());
+    saved_timezone(value).unwrap_or_else(machine_timezone)
+}
+
+/// First instant of the local date, as epoch millis (after the gap if it starts in one)
+fn start_of_day<Z: TimeZone>(tz: &Z, date: NaiveDate) -> i64 {
+    let midnight = date.and_time(NaiveTime::MIN);
+    (0..=MAX_GAP_HOURS)
+        .find_map(|hours| {
+            tz.from_local_datetime(&(midnight + Duration::hours(hours)))
+                .earliest()
+        })
+        .map(|start| start.timestamp_millis())
+        .unwrap_or_else(|| midnight.and_utc().timestamp_millis())
+}
+
+/// The local date as [start, end) in epoch millis
+pub(crate) fn local_day_bounds<Z: TimeZone>(date: NaiveDate, tz: &Z) -> (i64, i64) {
+    let next = date.succ_opt().unwrap_or(date);
+    (start_of_day(tz, date), start_of_day(tz, next))
+}
+
+/// The local date containing ts (epoch millis); None if ts is out of range
+pub(crate) fn local_date<Z: TimeZone>(ts: i64, tz: &Z) -> Option<NaiveDate> {
+    DateTime::<Utc>::from_timestamp_millis(ts).map(|at| at.with_timezone(tz).date_naive())
+}
+
+/// ts (epoch millis) in tz, formatted with a chrono pattern; empty if ts is out of range
+pub(crate) fn format_local<Z: TimeZone>(ts: i64, tz: &Z, pattern: &str) -> String
+where
+    Z::Offset: Display,
+{
+    DateTime::<Utc>::from_timestamp_millis(ts)
+        .map(|at| at.with_timezone(tz).format(pattern).to_string())
+        .unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn get_timezone() -> Result<DealerTimezone, String> {
+    track("get_timezone", || {
+        Ok(match saved_timezone(get_setting(TIMEZONE_SETTING)?) {
+            Some(tz) => DealerTimezone {
+                timezone: tz.name().to_string(),
+                from_computer: false,
+            },
+            None => DealerTimezone {
+                timezone: machine_timezone().name().to_string(),
+                from_computer: true,
+            },
+        })
+    })
+}
+
+/// Save the dealer's zone (an IANA name); an empty name goes back to this computer's zone
+#[tauri::command]
+pub fn set_timezone(timezone: String) -> Result<DealerTimezone, String> {
+    track("set_timezone", || {
+        let name = if timezone.trim().is_empty() {
+            ""
+        } else {
+            parse_timezone(&timezone)?.name()
+        };
+        set_setting(TIMEZONE_SETTING, name)?;
+        get_timezone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{upsert_setting, Database};
+    use chrono_tz::America::{Los_Angeles, New_York, Sao_Paulo};
+    use chrono_tz::Asia::{Kathmandu, Kolkata};
+    use chrono_tz::Pacific::{Kiritimati, Pago_Pago};
+
+    const HOUR_MS: i64 = 3_600_000;
+
+    fn ms(rfc3339: &str) -> i64 {
+        DateTime::parse_from_rfc3339(rfc3339)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    fn date(ymd: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(ymd, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn test_dst_change_days_are_23_and_25_hours() {
+        let (start, end) = local_day_bounds(date("2024-03-10"), &New_York);
+        assert_eq!(start, ms("2024-03-10T00:00:00-05:00"));
+        assert_eq!(end, ms("2024-03-11T00:00:00-04:00"));
+        assert_eq!(end - start, 23 * HOUR_MS);
+
+        let (start, end) = local_day_bounds(date("2024-11-03"), &New_York);
+        assert_eq!(start, ms("2024-11-03T00:00:00-04:00"));
+        assert_eq!(end - start, 25 * HOUR_MS);
+        // 01:30 happens twice that night; both are still the 3rd
+        for at in ["2024-11-03T01:30:00-04:00", "2024-11-03T01:30:00-05:00"] {
+            assert_eq!(local_date(ms(at), &New_York), Some(date("2024-11-03")));
+        }
+
+        // Brazil sprang forward at midnight: 2018-11-04 started at 01:00
+        let (start, end) = local_day_bounds(date("2018-11-04"), &Sao_Paulo);
+        assert_eq!(start, ms("2018-11-04T01:00:00-02:00"));
+        assert_eq!(end, ms("2018-11-05T00:00:00-02:00"));
+        assert_eq!(local_date(start - 1, &Sao_Paulo), Some(date("2018-11-03")));
+    }
+
+    #[test]
+    fn test_an_evening_sale_in_pacific_time_is_the_same_day() {
+        // 8pm on the 15th in Los Angeles is already the 16th in UTC
+        let sold_at = ms("2024-01-15T20:00:00-08:00");
+        assert_eq!(
+            format_local(sold_at, &Los_Angeles, "%Y-%m-%d"),
+            "2024-01-15"
+        );
+        assert_eq!(format_local(sold_at, &Tz::UTC, "%Y-%m-%d"), "2024-01-16");
+        // The last evening of a month belongs to that month
+        let month_end = ms("2024-01-31T23:30:00-08:00");
+        assert_eq!(format_local(month_end, &Los_Angeles, "%Y-%m"), "2024-01");
+
+        let (start, end) = local_day_bounds(date("2024-01-15"), &Los_Angeles);
+        assert!((start..end).contains(&sold_at));
+        assert_eq!(start, ms("2024-01-15T08:00:00Z"));
+        assert_eq!(format_local(i64::MAX, &Los_Angeles, "%Y"), "");
+    }
+
+    #[test]
+    fn test_offsets_far_from_utc_and_off_the_hour() {
+        let at = ms("2024-06-01T10:30:00Z");
+        // +14 and -11 are 25 hours apart: two days away from each other
+        assert_eq!(local_date(at, &Kiritimati), Some(date("2024-06-02")));
+        assert_eq!(local_date(at, &Pago_Pago), Some(date("2024-05-31")));
+        assert_eq!(
+            local_day_bounds(date("2024-06-02"), &Kiritimati).0,
+            ms("2024-06-01T10:00:00Z")
+        );
+        assert_eq!(
+            local_day_bounds(date("2024-05-31"), &Pago_Pago).1,
+            ms("2024-06-01T11:00:00Z")
+        );
+
+        assert_eq!(
+            local_day_bounds(date("2024-06-01"), &Kolkata).0,
+            ms("2024-05-31T18:30:00Z")
+        );
+        assert_eq!(
+            local_day_bounds(date("2024-06-01"), &Kathmandu).0,
+            ms("2024-05-31T18:15:00Z")
+        );
+        assert_eq!(format_local(at, &Kathmandu, "%H:%M"), "16:15");
+    }
+
+    #[test]
+    fn test_only_iana_names_are_accepted_and_the_saved_zone_wins() {
+        assert_eq!(
+            parse_timezone(" America/Denver ").unwrap().name(),
+            "America/Denver"
+        );
+        for bad in [
+            "Mars/Olympus_Mons",
+            "+05:00",
+            "Pacific Time",
+            "america/denver",
+        ] {
+            assert!(parse_timezone(bad).is_err(), "{}", bad);
+        }
+        assert!(set_timezone("Mars/Olympus_Mons".to_string()).is_err());
+
+        let db = Database::init_in_memory().unwrap();
+        let conn = db.conn();
+        assert_eq!(dealer_timezone(&conn), machine_timezone());
+        upsert_setting(&conn, TIMEZONE_SETTING, "America/Chicago", 0).unwrap();
+        assert_eq!(dealer_timezone(&conn).name(), "America/Chicago");
+        // A bad value left in settings falls back instead of failing every report
+        upsert_setting(&conn, TIMEZONE_SETTING, "Nowhere/Special", 0).unwrap();
+        assert_eq!(dealer_timezone(&conn), machine_timezone());
+    }
+}
@@ -0,0 +1,279 @@
+// src-tauri/src/db_batch.rs
+//
+// Several reads in one IPC round trip (the deal screen loads the deal, client, vehicle,
+// documents, fees and co-buyers at once). db_batch runs them in order under one lock of
+// the database connection and returns one result per request, in the same order; a read
+// that fails only fails its own entry. BatchRequest lists read-only commands and nothing
+// else, so a write (or any unknown command) in the batch fails deserialization and the
+// whole call is rejected before anything runs.
+
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::communications::communications_by_client;
+use crate::database::{
+    client_for_user, deal_for_user, documents_by_deal, vehicle_for_user, DbState,
+};
+use crate::deal_cobuyers::list_cobuyers;
+use crate::deal_fees::list_fees;
+use crate::permissions::{redact_vehicle, Role};
+use crate::recent_items::{owned_by, EntityType};
+use crate::telemetry::track;
+
+/// Requests per batch; the connection stays locked for the whole batch
+const MAX_BATCH: usize = 50;
+
+/// A read the batch may run, with the arguments of the command of the same name
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BatchRequest {
+    GetDeal { id: String },
+    GetClient { id: String },
+    GetVehicle { id: String },
+    GetDocumentsByDeal { deal_id: String },
+    GetDealFees { deal_id: String },
+    GetDealCobuyers { deal_id: String },
+    GetCommunicationsByClient { client_id: String },
+}
+
+impl BatchRequest {
+    pub fn command(&self) -> &'static str {
+        match self {
+            BatchRequest::GetDeal { .. } => "get_deal",
+            BatchRequest::GetClient { .. } => "get_client",
+            BatchRequest::GetVehicle { .. } => "get_vehicle",
+            BatchRequest::GetDocumentsByDeal { .. } => "get_documents_by_deal",
+            BatchRequest::GetDealFees { .. } => "get_deal_fees",
+            BatchRequest::GetDealCobuyers { .. } => "get_deal_cobuyers",
+            BatchRequest::GetCommunicationsByClient { .. } => "get_communications_by_client",
+        }
+    }
+}
+
+/// Outcome of one request; data is what the single command would have returned
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BatchResult {
+    Ok {
+        command: &'static str,
+        data: Value,
+    },
+    Error {
+        command: &'static str,
+        message: String,
+    },
+}
+
+fn to_json(value: impl Serialize) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| e.to_string())
+}
+
+fn run(
+    conn: &Connection,
+    user_id: &str,
+    role: Role,
+    request: &BatchRequest,
+) -> Result<Value, String> {
+    match request {
+        BatchRequest::GetDeal { id } => to_json(deal_for_user(conn, id, user_id)?),
+        BatchRequest::GetClient { id } => to_json(client_for_user(conn, id, user_id)?),
+        BatchRequest::GetVehicle { id } => to_json(
+            vehicle_for_user(conn, id, user_id)?.map(|vehicle| redact_vehicle(role, vehicle)),
+        ),
+        BatchRequest::GetDocumentsByDeal { deal_id } => {
+            owned_by(conn, user_id, EntityType::Deal, deal_id)?;
+            to_json(documents_by_deal(conn, deal_id)?)
+        }
+        BatchRequest::GetDealFees { deal_id } => {
+            owned_by(conn, user_id, EntityType::Deal, deal_id)?;
+            to_json(list_fees(conn, deal_id).map_err(|e| e.to_string())?)
+        }
+        BatchRequest::GetDealCobuyers { deal_id } => {
+            owned_by(conn, user_id, EntityType::Deal, deal_id)?;
+            to_json(list_cobuyers(conn, deal_id).map_err(|e| e.to_string())?)
+        }
+        BatchRequest::GetCommunicationsByClient { client_id } => {
+            to_json(communications_by_client(conn, user_id, client_id)?)
+        }
+    }
+}
+
+/// Run every request in order on one connection, as a user with `role`
+pub(crate) fn run_batch(
+    conn: &Connection,
+    user_id: &str,
+    role: Role,
+    requests: &[BatchRequest],
+) -> Vec<BatchResult> {
+    requests
+        .iter()
+        .map(|request| match run(conn, user_id, role, request) {
+            Ok(data) => BatchResult::Ok {
+                command: request.command(),
+                data,
+            },
+            Err(message) => BatchResult::Error {
+                command: request.command(),
+                message,
+            },
+        })
+        .collect()
+}
+
+/// Several read commands in one call; results come back in request order
+#[tauri::command]
+pub fn db_batch(
+    requests: Vec<BatchRequest>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<Vec<BatchResult>, String> {
+    track("db_batch", || {
+        let user_id_value = state.require_user(user_id)?;
+        if requests.len() > MAX_BATCH {
+            return Err(format!(
+                "A batch can hold at most {} requests ({} sent)",
+                MAX_BATCH,
+                requests.len()
+            ));
+        }
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        Ok(run_batch(&conn, &user_id_value, state.role(), &requests))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{db_create_client, db_create_deal, db_create_vehicle};
+    use crate::test_support::{make_client, make_deal, make_vehicle, TestApp};
+    use serde_json::json;
+
+    fn requests(value: Value) -> Result<Vec<BatchRequest>, serde_json::Error> {
+        serde_json::from_value(value)
+    }
+
+    fn deal_screen(app: &TestApp) {
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        let mut deal = make_deal("d1", "c1", "v1");
+        deal.doc_fee = Some(199.0);
+        db_create_deal(deal, None, None, app.state(), app.db()).unwrap();
+    }
+
+    #[test]
+    fn test_results_keep_request_order_and_errors_stay_per_item() {
+        let app = TestApp::new();
+        deal_screen(&app);
+        let batch = requests(json!([
+            { "command": "get_deal", "id": "d1" },
+            { "command": "get_client", "id": "c1" },
+            { "command": "get_vehicle", "id": "v1" },
+            { "command": "get_documents_by_deal", "deal_id": "d1" },
+            { "command": "get_deal_fees", "deal_id": "d1" },
+            { "command": "get_deal_fees", "deal_id": "missing" },
+            { "command": "get_client", "id": "missing" },
+            { "command": "get_communications_by_client", "client_id": "c1" },
+            { "command": "get_deal_cobuyers", "deal_id": "d1" },
+        ]))
+        .unwrap();
+
+        let results = db_batch(batch, None, app.state(), app.db()).unwrap();
+        assert_eq!(results.len(), 9);
+        let commands: Vec<&str> = results
+            .iter()
+            .map(|result| match result {
+                BatchResult::Ok { command, .. } | BatchResult::Error { command, .. } => *command,
+            })
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                "get_deal",
+                "get_client",
+                "get_vehicle",
+                "get_documents_by_deal",
+                "get_deal_fees",
+                "get_deal_fees",
+                "get_client",
+                "get_communications_by_client",
+                "get_deal_cobuyers",
+            ]
+        );
+
+        let data = |index: usize| match &results[index] {
+            BatchResult::Ok { data, .. } => data.clone(),
+            other => panic!("expected ok, got {:?}", other),
+        };
+        assert_eq!(data(0)["id"], "d1");
+        assert_eq!(data(1)["first_name"], "Jordan");
+        assert_eq!(data(2)["vin"], "TESTVIN-v1");
+        assert_eq!(data(3), json!([]));
+        assert_eq!(data(4)[0]["amount_cents"], 19_900);
+        assert!(matches!(
+            &results[5],
+            BatchResult::Error { message, .. } if message.contains("not found")
+        ));
+        // Missing is not an error, just as for db_get_client
+        assert_eq!(data(6), Value::Null);
+        assert_eq!(data(7), json!([]));
+
+        let json = serde_json::to_value(&results[5]).unwrap();
+        assert_eq!(json["status"], "error");
+        assert_eq!(json["command"], "get_deal_fees");
+    }
+
+    #[test]
+    fn test_another_users_records_are_not_readable_through_a_batch() {
+        let app = TestApp::new();
+        deal_screen(&app);
+        app.sign_in("other-user");
+        let batch = requests(json!([
+            { "command": "get_deal", "id": "d1" },
+            { "command": "get_documents_by_deal", "deal_id": "d1" },
+            { "command": "get_deal_cobuyers", "deal_id": "d1" },
+        ]))
+        .unwrap();
+
+        let results = db_batch(batch, None, app.state(), app.db()).unwrap();
+        assert_eq!(
+            results[0],
+            BatchResult::Ok {
+                command: "get_deal",
+                data: Value::Null
+            }
+        );
+        assert!(results[1..]
+            .iter()
+            .all(|result| matches!(result, BatchResult::Error { .. })));
+    }
+
+    #[test]
+    fn test_writes_and_unknown_commands_are_rejected_before_anything_runs() {
+        for write in [
+            json!({ "command": "delete_deal", "id": "d1" }),
+            json!({ "command": "db_update_deal", "id": "d1", "updates": {} }),
+            json!({ "command": "create_client", "client": {} }),
+            json!({ "command": "set_setting", "key": "k", "value": "v" }),
+            // A read with extra arguments isn't smuggled through either
+            json!({ "command": "get_deal", "id": "d1", "updates": { "status": "sold" } }),
+        ] {
+            let err = requests(json!([{ "command": "get_deal", "id": "d1" }, write]))
+                .unwrap_err()
+                .to_string();
+            assert!(
+                err.contains("unknown variant") || err.contains("unknown field"),
+                "{}",
+                err
+            );
+        }
+
+        let app = TestApp::new();
+        let too_many = vec![BatchRequest::GetDeal { id: "d1".into() }; MAX_BATCH + 1];
+        assert!(db_batch(too_many, None, app.state(), app.db()).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+// src-tauri/src/db_busy.rs
+//
+// "database is locked" handling for writes. Every connection gets a busy_timeout, so SQLite
+// waits out short locks by itself; a write that still comes back SQLITE_BUSY (the timeout
+// ran out, or SQLite gave up early to avoid a deadlock) is retried with backoff until the
+// retry budget is spent. Each wait is recorded in telemetry (get_command_metrics) so lock
+// contention shows up in the field, and DbError::Busy only reaches the UI once the budget
+// is exhausted.
+
+use chrono::TimeZone;
+use log::warn;
+use rusqlite::{ErrorCode, Result as SqlResult};
+use serde::Serialize;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::app_state::AuthError;
+use crate::db_timeout::{timed_out, TimedOut};
+use crate::permissions::Role;
+use crate::telemetry::record_lock_wait;
+use crate::validation_rules::RuleViolation;
+
+/// SQLite's own wait on every connection before it reports SQLITE_BUSY
+pub(crate) const BUSY_TIMEOUT: Duration = Duration::from_secs(2);
+/// Total time a write may spend waiting on locks, SQLite's waits included
+const RETRY_BUDGET: Duration = Duration::from_secs(5);
+const FIRST_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_BACKOFF: Duration = Duration::from_millis(250);
+
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DbError {
+    Busy {
+        category: String,
+        attempts: u32,
+        waited_ms: u64,
+    },
+    /// A unique column (e.g. a vehicle's stock_number) already holds the value
+    Duplicate {
+        field: String,
+    },
+    /// Another user holds the vehicle (vehicle_holds.rs) until expires_at (unix ms)
+    VehicleOnHold {
+        vehicle_id: String,
+        hold_id: String,
+        held_by: String,
+        expires_at: i64,
+    },
+    /// The command's statements ran past its time budget and were stopped (db_timeout.rs)
+    Timeout {
+        category: String,
+        timeout_ms: u64,
+    },
+    /// The record breaks the dealer's validation rules (validation_rules.rs)
+    ValidationFailed {
+        violations: Vec<RuleViolation>,
+    },
+    /// The current user's role is below what the command needs (permissions.rs)
+    Forbidden {
+        required_role: Role,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbError::Busy { waited_ms, .. } => write!(
+                f,
+                "The database is busy with another write (waited {} ms); please try again",
+                waited_ms
+            ),
+            DbError::Duplicate { field } => {
+                write!(f, "That {} is already in use", field.replace('_', " "))
+            }
+            DbError::VehicleOnHold {
+                held_by,
+                expires_at,
+                ..
+            } => {
+                let until = chrono::Local
+                    .timestamp_millis_opt(*expires_at)
+                    .single()
+                    .map(|time| time.format("%b %-d %-I:%M %p").to_string())
+                    .unwrap_or_else(|| expires_at.to_string());
+                write!(f, "That vehicle is on hold by {} until {}", held_by, until)
+            }
+            DbError::Timeout { timeout_ms, .. } => write!(
+                f,
+                "The database took longer than {} seconds and the request was stopped; try narrowing it down",
+                timeout_ms / 1000
+            ),
+            DbError::ValidationFailed { violations } => {
+                let messages: Vec<&str> = violations
+                    .iter()
+                    .map(|violation| violation.message.as_str())
+                    .collect();
+                write!(f, "{}", messages.join("; "))
+            }
+            DbError::Forbidden { required_role } => {
+                write!(f, "{}", AuthError::Forbidden { required_role: *required_role })
+            }
+            DbError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for DbError {
+    /// Once the command's time budget has run out every statement fails with "interrupted",
+    /// so any error from then on is reported as the timeout
+    fn from(message: String) -> Self {
+        match timed_out() {
+            Some(TimedOut { category, timeout }) => DbError::Timeout {
+                category: category.to_string(),
+                timeout_ms: timeout.as_millis() as u64,
+            },
+            None => DbError::Other { message },
+        }
+    }
+}
+
+impl From<AuthError> for DbError {
+    fn from(error: AuthError) -> Self {
+        match error {
+            AuthError::Forbidden { required_role } => DbError::Forbidden { required_role },
+            error => DbError::Other {
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+/// SQLITE_BUSY or SQLITE_LOCKED, including the extended codes
+pub(crate) fn is_busy(error: &rusqlite::Error) -> bool {
+    matches!(
+        error.sqlite_error_code(),
+        Some(ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+    )
+}
+
+/// Run a write, retrying while the database is locked. `category` names the kind of
+/// write ("clients", "vehicle_import", ...) in the lock diagnostics. `write` runs again
+/// from the start on each retry, so it must be one statement or open its own transaction.
+pub(crate) fn retry_busy<T>(
+    category: &'static str,
+    write: impl FnMut() -> SqlResult<T>,
+) -> Result<T, DbError> {
+    retry_busy_within(category, RETRY_BUDGET, write)
+}
+
+fn retry_busy_within<T>(
+    category: &'static str,
+    budget: Duration,
+    mut write: impl FnMut() -> SqlResult<T>,
+) -> Result<T, DbError> {
+    let started = Instant::now();
+    let mut backoff = FIRST_BACKOFF;
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+        match write() {
+            Ok(value) => {
+                if attempts > 1 {
+                    record_lock_wait(category, started.elapsed(), false);
+                }
+                return Ok(value);
+            }
+            Err(e) if is_busy(&e) => {
+                let waited = started.elapsed();
+                if waited + backoff > budget {
+                    record_lock_wait(category, waited, true);
+                    warn!(
+                        "⚠️  {} write still locked after {} attempts ({:?})",
+                        category, attempts, waited
+                    );
+                    return Err(DbError::Busy {
+                        category: category.to_string(),
+                        attempts,
+                        waited_ms: waited.as_millis() as u64,
+                    });
+                }
+                thread::sleep(backoff);
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+            Err(e) => return Err(DbError::from(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::get_command_metrics;
+    use crate::test_support::temp_dir;
+    use rusqlite::{params, Connection};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc;
+
+    fn open(path: &Path, busy_timeout: Duration) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.busy_timeout(busy_timeout).unwrap();
+        let _mode: String = conn
+            .query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))
+            .unwrap();
+        conn
+    }
+
+    /// A second connection holding the write lock until told to let go
+    fn hold_write_lock(path: &Path) -> (mpsc::Sender<()>, thread::JoinHandle<()>) {
+        let (locked_tx, locked_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let path = path.to_path_buf();
+        let holder = thread::spawn(move || {
+            let conn = open(&path, Duration::ZERO);
+            conn.execute_batch("BEGIN IMMEDIATE; INSERT INTO items (name) VALUES ('holder');")
+                .unwrap();
+            locked_tx.send(()).unwrap();
+            release_rx.recv().ok();
+            conn.execute_batch("COMMIT").unwrap();
+        });
+        locked_rx.recv().unwrap();
+        (release_tx, holder)
+    }
+
+    fn setup(name: &str) -> (PathBuf, Connection) {
+        let dir = temp_dir("busy", name);
+        // A short SQLite-level wait so the retry loop is what's being tested
+        let conn = open(&dir.join("busy.db"), Duration::from_millis(20));
+        conn.execute_batch("CREATE TABLE items (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+            .unwrap();
+        (dir, conn)
+    }
+
+    #[test]
+    fn test_write_retries_until_the_other_connection_commits() {
+        let (dir, conn) = setup("retry");
+        let (release, holder) = hold_write_lock(&dir.join("busy.db"));
+
+        // Without retrying, the contended write fails outright
+        let err = conn
+            .execute("INSERT INTO items (name) VALUES ('direct')", [])
+            .unwrap_err();
+        assert!(is_busy(&err));
+
+        let releaser = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(150));
+            release.send(()).unwrap();
+        });
+        let inserted = retry_busy_within("db_busy_test_retry", Duration::from_secs(5), || {
+            conn.execute("INSERT INTO items (name) VALUES (?1)", params!["retried"])
+        })
+        .unwrap();
+        assert_eq!(inserted, 1);
+        releaser.join().unwrap();
+        holder.join().unwrap();
+
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 2);
+        drop(conn);
+        let _ = fs::remove_dir_all(&dir);
+
+        let waits = get_command_metrics().lock_waits;
+        assert!(waits.count >= 1);
+        assert!(waits.longest_wait_ms >= 100.0, "{:?}", waits);
+    }
+
+    #[test]
+    fn test_busy_error_only_after_the_budget_runs_out() {
+        let (dir, conn) = setup("give-up");
+        let (release, holder) = hold_write_lock(&dir.join("busy.db"));
+
+        let started = Instant::now();
+        let err = retry_busy_within("db_busy_test_give_up", Duration::from_millis(200), || {
+            conn.execute("INSERT INTO items (name) VALUES ('never')", [])
+        })
+        .unwrap_err();
+        let elapsed = started.elapsed();
+        release.send(()).unwrap();
+        holder.join().unwrap();
+
+        match &err {
+            DbError::Busy {
+                category, attempts, ..
+            } => {
+                assert_eq!(category, "db_busy_test_give_up");
+                assert!(*attempts > 1);
+            }
+            other => panic!("expected Busy, got {:?}", other),
+        }
+        assert!(elapsed >= Duration::from_millis(100), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(2), "{:?}", elapsed);
+        assert_eq!(
+            serde_json::to_value(&err).unwrap()["kind"],
+            serde_json::json!("busy")
+        );
+        assert!(get_command_metrics().lock_waits.gave_up >= 1);
+
+        // Other errors come back at once, without retrying
+        let err = retry_busy_within("db_busy_test_other", Duration::from_secs(5), || {
+            conn.execute("INSERT INTO missing (name) VALUES ('x')", [])
+        })
+        .unwrap_err();
+        assert!(matches!(err, DbError::Other { .. }));
+        drop(conn);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,268 @@
+// src-tauri/src/db_timeout.rs
+//
+// Time limits and slow-statement logging for database work done by commands. track() gives
+// each command a wall-clock budget (QUERY_TIMEOUT); every connection has a progress handler
+// that interrupts the running statement once the budget is spent, and the command fails with
+// DbError::Timeout instead of freezing the app. Exports take longer and raise their budget
+// with with_query_timeout(). Statements slower than SLOW_QUERY_THRESHOLD are recorded in
+// telemetry (get_command_metrics) under the command's name; the SQL and its parameters are
+// never recorded. Work outside a command (background threads) has no budget, except report
+// jobs: they carry one onto their blocking thread (carry_budget).
+
+use log::warn;
+use rusqlite::trace::{TraceEvent, TraceEventCodes};
+use rusqlite::Connection;
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
+
+use crate::telemetry::record_slow_query;
+
+/// Budget of a command's statements, together
+pub(crate) const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+/// Budget of commands that read everything (exports)
+pub(crate) const EXPORT_QUERY_TIMEOUT: Duration = Duration::from_secs(120);
+pub(crate) const SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(250);
+/// SQLite VM instructions between deadline checks (a few ms of work)
+pub(crate) const PROGRESS_OPS: i32 = 10_000;
+
+#[derive(Debug, Clone, Copy)]
+struct Budget {
+    category: &'static str,
+    timeout: Duration,
+    deadline: Instant,
+    timed_out: bool,
+}
+
+thread_local! {
+    // The budget of the command running on this thread, if any
+    static BUDGET: RefCell<Option<Budget>> = const { RefCell::new(None) };
+}
+
+/// Which budget ran out, for DbError::Timeout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TimedOut {
+    pub category: &'static str,
+    pub timeout: Duration,
+}
+
+/// Install the deadline check and the slow-statement log. Call once per connection
+pub(crate) fn watch_connection(conn: &Connection) {
+    conn.progress_handler(PROGRESS_OPS, Some(past_deadline));
+    conn.trace_v2(
+        TraceEventCodes::SQLITE_TRACE_PROFILE,
+        Some(statement_finished),
+    );
+}
+
+/// The progress handler: true interrupts the statement
+pub(crate) fn past_deadline() -> bool {
+    BUDGET.with(|budget| match budget.borrow_mut().as_mut() {
+        Some(budget) if budget.timed_out || Instant::now() >= budget.deadline => {
+            budget.timed_out = true;
+            true
+        }
+        _ => false,
+    })
+}
+
+fn statement_finished(event: TraceEvent<'_>) {
+    if let TraceEvent::Profile(_, elapsed) = event {
+        if elapsed >= SLOW_QUERY_THRESHOLD {
+            let category = BUDGET
+                .with(|budget| budget.borrow().map(|budget| budget.category))
+                .unwrap_or("background");
+            record_slow_query(category, elapsed);
+        }
+    }
+}
+
+/// Puts the previous budget back when the body returns (or panics). A budget that ran out
+/// also ends the one it replaced
+struct Restore(Option<Budget>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let mut previous = self.0.take();
+        BUDGET.with(|budget| {
+            let mut budget = budget.borrow_mut();
+            if let (Some(previous), Some(current)) = (previous.as_mut(), budget.as_ref()) {
+                previous.timed_out |= current.timed_out;
+            }
+            *budget = previous;
+        });
+    }
+}
+
+/// Run a command body with the default budget; a command called from inside another
+/// keeps the outer budget. Returns whether the budget ran out
+pub(crate) fn with_command_budget<T>(command: &'static str, body: impl FnOnce() -> T) -> (T, bool) {
+    if BUDGET.with(|budget| budget.borrow().is_some()) {
+        return (body(), false);
+    }
+    let _restore = start(command, QUERY_TIMEOUT);
+    let value = body();
+    let ran_out = timed_out();
+    if let Some(ran_out) = ran_out {
+        warn!(
+            "⚠️  {} stopped after its {:?} database budget",
+            ran_out.category, ran_out.timeout
+        );
+    }
+    (value, ran_out.is_some())
+}
+
+/// Give the rest of the running command `timeout` instead of its default budget
+/// (exports). Outside a command the budget lasts for the body only
+pub(crate) fn with_query_timeout<T>(timeout: Duration, body: impl FnOnce() -> T) -> T {
+    let category = BUDGET
+        .with(|budget| budget.borrow().map(|budget| budget.category))
+        .unwrap_or("query");
+    let _restore = start(category, timeout);
+    body()
+}
+
+/// A budget to take to another thread
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CarriedBudget(Budget);
+
+impl CarriedBudget {
+    /// Run body on this thread under the budget
+    pub(crate) fn run<T>(self, body: impl FnOnce() -> T) -> T {
+        let _restore = Restore(BUDGET.with(|current| current.borrow_mut().replace(self.0)));
+        body()
+    }
+}
+
+/// The running command's budget, or a new one of `timeout` if there's none (async
+/// commands), for work handed to a blocking thread
+pub(crate) fn carry_budget(category: &'static str, timeout: Duration) -> CarriedBudget {
+    let current = BUDGET.with(|budget| *budget.borrow());
+    CarriedBudget(current.unwrap_or_else(|| new_budget(category, timeout)))
+}
+
+fn new_budget(category: &'static str, timeout: Duration) -> Budget {
+    Budget {
+        category,
+        timeout,
+        deadline: Instant::now() + timeout,
+        timed_out: false,
+    }
+}
+
+fn start(category: &'static str, timeout: Duration) -> Restore {
+    let budget = new_budget(category, timeout);
+    Restore(BUDGET.with(|current| current.borrow_mut().replace(budget)))
+}
+
+/// Some if this thread's budget ran out and interrupted a statement
+pub(crate) fn timed_out() -> Option<TimedOut> {
+    let budget = BUDGET.with(|budget| *budget.borrow())?;
+    if !budget.timed_out {
+        return None;
+    }
+    Some(TimedOut {
+        category: budget.category,
+        timeout: budget.timeout,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db_busy::DbError;
+    use crate::telemetry::get_command_metrics;
+
+    const ENDLESS: &str = "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n)
+                           SELECT COUNT(*) FROM n";
+
+    fn watched() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        watch_connection(&conn);
+        conn
+    }
+
+    fn endless_count(conn: &Connection) -> Result<i64, DbError> {
+        Ok(conn
+            .query_row(ENDLESS, [], |row| row.get(0))
+            .map_err(|e| e.to_string())?)
+    }
+
+    #[test]
+    fn test_runaway_statement_is_stopped_and_logged_as_slow() {
+        let conn = watched();
+        let started = Instant::now();
+        let (result, ran_out) = with_command_budget("db_timeout_test_runaway", || {
+            with_query_timeout(Duration::from_millis(400), || endless_count(&conn))
+        });
+        let elapsed = started.elapsed();
+
+        assert!(ran_out);
+        assert_eq!(
+            result,
+            Err(DbError::Timeout {
+                category: "db_timeout_test_runaway".to_string(),
+                timeout_ms: 400,
+            })
+        );
+        assert!(elapsed >= Duration::from_millis(400), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_secs(5), "{:?}", elapsed);
+
+        let report = get_command_metrics();
+        let slow = report
+            .slow_queries
+            .recent
+            .iter()
+            .find(|query| query.category == "db_timeout_test_runaway")
+            .expect("the interrupted statement is logged");
+        assert!(slow.duration_ms >= 250.0, "{:?}", slow);
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("RECURSIVE"));
+
+        // The connection is usable again once the budget is gone
+        let one: i64 = conn.query_row("SELECT 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(one, 1);
+    }
+
+    #[test]
+    fn test_fast_statements_are_neither_stopped_nor_logged() {
+        let conn = watched();
+        let (count, ran_out) = with_command_budget("db_timeout_test_fast", || {
+            conn.query_row(
+                "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 1000)
+                 SELECT COUNT(*) FROM n",
+                [],
+                |row| row.get::<_, i64>(0),
+            )
+        });
+        assert_eq!(count.unwrap(), 1000);
+        assert!(!ran_out);
+        assert!(get_command_metrics()
+            .slow_queries
+            .recent
+            .iter()
+            .all(|query| query.category != "db_timeout_test_fast"));
+
+        // Nothing outside a command is ever interrupted
+        assert!(BUDGET.with(|budget| budget.borrow().is_none()));
+        assert_eq!(timed_out(), None);
+    }
+
+    #[test]
+    fn test_nested_commands_share_the_outer_budget() {
+        let conn = watched();
+        let (result, ran_out) = with_command_budget("db_timeout_test_outer", || {
+            with_query_timeout(Duration::from_millis(100), || {
+                let (inner, inner_ran_out) =
+                    with_command_budget("db_timeout_test_inner", || endless_count(&conn));
+                assert!(!inner_ran_out);
+                inner
+            })
+        });
+        assert!(ran_out);
+        assert!(matches!(
+            result,
+            Err(DbError::Timeout { category, .. }) if category == "db_timeout_test_outer"
+        ));
+        assert!(BUDGET.with(|budget| budget.borrow().is_none()));
+    }
+}
@@ -0,0 +1,440 @@
+// src-tauri/src/deal_cloning.rs
+//
+// Start a new deal from an old one, for a customer who's back for another car. The clone is
+// a quote with a new id (so a new deal number, the id's first 8 characters) for the same
+// client, with the source's type, lender and fee lines. Sale date, amounts, odometer, title
+// status and documents always start empty. The vehicle, the co-buyers and a few paperwork
+// fields are copied only when CloneDealOptions asks for them. deals.vehicle_id is required,
+// so a clone that doesn't keep the vehicle needs the new one. Every clone gets an audit
+// entry naming its source.
+
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit_log::record_audit;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{begin_write, insert_deal, new_row_id, DbState, Deal};
+use crate::db_busy::DbError;
+use crate::deal_cobuyers::list_cobuyers;
+use crate::deal_fees::{list_fees, FeeCategory};
+use crate::deal_status::{record_transition, DealStatus};
+use crate::recent_items::{owned_by, EntityType};
+use crate::telemetry::track;
+use crate::vehicle_holds::check_vehicle_hold;
+use crate::webhooks::{notify, WebhookEvent};
+
+pub const DEAL_CLONED_ACTION: &str = "deal_cloned";
+
+/// Source fields a clone can take over as they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CloneField {
+    /// State the customer titles in
+    TitleState,
+    LienHolder,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CloneDealOptions {
+    /// Keep the source deal's vehicle
+    pub carry_vehicle: bool,
+    /// Vehicle of the new deal when the source's isn't carried over
+    pub vehicle_id: Option<String>,
+    pub copy_cobuyers: bool,
+    pub fields: Vec<CloneField>,
+}
+
+fn source_deal(conn: &Connection, user_id: &str, id: &str) -> Result<Deal, String> {
+    conn.query_row(
+        "SELECT * FROM deals WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+        Deal::from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Deal not found or access denied".to_string())
+}
+
+/// Create the clone of source_id; the caller sends the change events
+pub(crate) fn clone_deal(
+    conn: &Connection,
+    user_id: &str,
+    source_id: &str,
+    options: &CloneDealOptions,
+    now: i64,
+) -> Result<Deal, DbError> {
+    let source = source_deal(conn, user_id, source_id)?;
+    let vehicle_id = if options.carry_vehicle {
+        source.vehicle_id.clone()
+    } else {
+        let vehicle_id = options
+            .vehicle_id
+            .as_deref()
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .ok_or_else(|| {
+                "Choose the vehicle for the new deal, or carry over the old one".to_string()
+            })?;
+        owned_by(conn, user_id, EntityType::Vehicle, vehicle_id)?;
+        vehicle_id.to_string()
+    };
+    let keep = |field: CloneField| options.fields.contains(&field);
+
+    let tx = begin_write(conn, "deals")?;
+    check_vehicle_hold(&tx, &vehicle_id, user_id, now)?;
+
+    let fees = list_fees(&tx, source_id).map_err(|e| e.to_string())?;
+    let fees_cents: i64 = fees.iter().map(|fee| fee.amount_cents).sum();
+    let doc_fee_cents: i64 = fees
+        .iter()
+        .filter(|fee| fee.category == FeeCategory::DocFee)
+        .map(|fee| fee.amount_cents)
+        .sum();
+
+    let deal = Deal {
+        id: new_row_id(),
+        user_id: Some(user_id.to_string()),
+        r#type: source.r#type.clone(),
+        client_id: source.client_id.clone(),
+        vehicle_id,
+        status: DealStatus::Quote.as_str().to_string(),
+        // Nothing sold yet: the fees are the whole total
+        total_amount: fees_cents as f64 / 100.0,
+        sale_date: None,
+        sale_amount: None,
+        sales_tax: None,
+        doc_fee: (doc_fee_cents != 0).then(|| doc_fee_cents as f64 / 100.0),
+        trade_in_value: None,
+        down_payment: None,
+        financed_amount: None,
+        document_ids: "[]".to_string(),
+        cobuyer_data: if options.copy_cobuyers {
+            source.cobuyer_data.clone()
+        } else {
+            None
+        },
+        created_at: now,
+        updated_at: now,
+        synced_at: None,
+        odometer_at_sale: None,
+        odometer_disclosure: None,
+        title_status: None,
+        title_state: source.title_state.clone().filter(|_| keep(CloneField::TitleState)),
+        lien_holder: source.lien_holder.clone().filter(|_| keep(CloneField::LienHolder)),
+        lender_id: source.lender_id.clone(),
+    };
+    insert_deal(&tx, &deal, user_id).map_err(|e| e.to_string())?;
+
+    for fee in &fees {
+        tx.execute(
+            "INSERT INTO deal_fees (id, deal_id, user_id, label, amount_cents, taxable, category,
+                                    sort_order, created_at, updated_at)
+             SELECT ?1, ?2, user_id, label, amount_cents, taxable, category, sort_order, ?3, ?3
+             FROM deal_fees WHERE id = ?4",
+            params![new_row_id(), deal.id, now, fee.id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    if options.copy_cobuyers {
+        for cobuyer in list_cobuyers(&tx, source_id).map_err(|e| e.to_string())? {
+            tx.execute(
+                "INSERT INTO deal_cobuyers (id, deal_id, user_id, first_name, last_name, email,
+                     phone, address, address_line2, city, state, zip_code, drivers_license,
+                     relationship, created_at, updated_at)
+                 SELECT ?1, ?2, user_id, first_name, last_name, email, phone, address,
+                     address_line2, city, state, zip_code, drivers_license, relationship, ?3, ?3
+                 FROM deal_cobuyers WHERE id = ?4",
+                params![new_row_id(), deal.id, now, cobuyer.id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+    }
+
+    record_transition(&tx, &deal.id, user_id, None, &deal.status, false, None)
+        .map_err(|e| e.to_string())?;
+    record_audit(
+        &tx,
+        user_id,
+        DEAL_CLONED_ACTION,
+        &json!({
+            "source_deal_id": source_id,
+            "deal_id": deal.id,
+            "options": options,
+        }),
+        now,
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(deal)
+}
+
+/// New quote for the source deal's client; see CloneDealOptions for what's carried over
+#[tauri::command]
+pub fn db_clone_deal(
+    source_deal_id: String,
+    user_id: Option<String>,
+    options: Option<CloneDealOptions>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<Deal, DbError> {
+    track("db_clone_deal", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let deal = clone_deal(
+            &conn,
+            &user_id_value,
+            &source_deal_id,
+            &options.unwrap_or_default(),
+            Utc::now().timestamp_millis(),
+        )?;
+
+        info!("✅ Deal {} cloned from {}", deal.id, source_deal_id);
+        notify(&user_id_value, WebhookEvent::DealCreated, &deal);
+        data_changed(&user_id_value, ChangedEntity::Deal, &deal.id, Operation::Create);
+        Ok(deal)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit_log::audit_entries;
+    use crate::database::{db_create_client, db_create_deal, db_create_vehicle};
+    use crate::deal_cobuyers::{db_add_deal_cobuyer, NewDealCobuyer};
+    use crate::test_support::{make_client, make_deal, make_vehicle, TestApp, TEST_USER};
+
+    const NOW: i64 = 1_760_000_000_000;
+
+    /// c1 bought v1 on d1: sold, with a document, two fee lines and a co-buyer
+    fn sold_deal(app: &TestApp) {
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v2"), app.state(), app.db()).unwrap();
+        let mut deal = make_deal("d1", "c1", "v1");
+        deal.r#type = "finance".to_string();
+        deal.doc_fee = Some(199.0);
+        deal.sales_tax = Some(1_202.5);
+        deal.down_payment = Some(2_000.0);
+        deal.financed_amount = Some(16_500.0);
+        deal.sale_date = Some(NOW - 86_400_000);
+        deal.document_ids = r#"["doc1"]"#.to_string();
+        deal.odometer_at_sale = Some(42_000);
+        deal.title_status = Some("clean".to_string());
+        deal.title_state = Some("TX".to_string());
+        deal.lien_holder = Some("Lone Star Credit Union".to_string());
+        db_create_deal(deal, None, None, app.state(), app.db()).unwrap();
+
+        let conn = app.conn();
+        conn.execute(
+            "INSERT INTO deal_fees (id, deal_id, user_id, label, amount_cents, taxable, category,
+                                    sort_order, created_at, updated_at)
+             VALUES ('f-title', 'd1', ?1, 'Title', 3300, 0, 'title', 1, 0, 0)",
+            params![TEST_USER],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO documents (id, deal_id, type, filename, file_path, created_at, updated_at)
+             VALUES ('doc1', 'd1', 'bill_of_sale', 'bos.pdf', '/tmp/bos.pdf', 0, 0)",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+
+        let cobuyer = NewDealCobuyer {
+            first_name: "Sam".to_string(),
+            last_name: "Rivera".to_string(),
+            relationship: Some("spouse".to_string()),
+            ..NewDealCobuyer::default()
+        };
+        db_add_deal_cobuyer("d1".to_string(), cobuyer, None, app.state(), app.db()).unwrap();
+    }
+
+    fn clone(app: &TestApp, options: CloneDealOptions) -> Result<Deal, DbError> {
+        clone_deal(&app.conn(), TEST_USER, "d1", &options, NOW)
+    }
+
+    fn count(app: &TestApp, sql: &str, deal_id: &str) -> i64 {
+        app.conn()
+            .query_row(sql, params![deal_id], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_clone_resets_the_sale_and_keeps_client_lender_and_fees() {
+        let app = TestApp::new();
+        sold_deal(&app);
+        let deal = clone(
+            &app,
+            CloneDealOptions {
+                vehicle_id: Some("v2".to_string()),
+                ..CloneDealOptions::default()
+            },
+        )
+        .unwrap();
+
+        assert_ne!(deal.id, "d1");
+        assert_ne!(deal.id[..8], "d1"[..]);
+        assert_eq!(deal.client_id, "c1");
+        assert_eq!(deal.vehicle_id, "v2");
+        assert_eq!(deal.r#type, "finance");
+        assert_eq!(deal.status, "quote");
+        assert_eq!(deal.created_at, NOW);
+        assert_eq!(
+            (deal.sale_date, deal.sale_amount, deal.sales_tax, deal.down_payment),
+            (None, None, None, None)
+        );
+        assert_eq!((deal.odometer_at_sale, deal.title_status.as_deref()), (None, None));
+        assert_eq!((deal.title_state.as_deref(), deal.lien_holder.as_deref()), (None, None));
+        assert_eq!(deal.doc_fee, Some(199.0));
+        assert_eq!(deal.total_amount, 232.0);
+        assert_eq!(deal.cobuyer_data, None);
+
+        let fees = list_fees(&app.conn(), &deal.id).unwrap();
+        let labels: Vec<(&str, i64)> = fees
+            .iter()
+            .map(|fee| (fee.label.as_str(), fee.amount_cents))
+            .collect();
+        assert_eq!(labels, [("Documentation fee", 19_900), ("Title", 3_300)]);
+        assert!(fees.iter().all(|fee| fee.deal_id == deal.id && fee.id != "f-title"));
+        assert!(list_cobuyers(&app.conn(), &deal.id).unwrap().is_empty());
+
+        // Stored as returned, cents columns included
+        let stored = source_deal(&app.conn(), TEST_USER, &deal.id).unwrap();
+        assert_eq!(
+            serde_json::to_value(&stored).unwrap(),
+            serde_json::to_value(&deal).unwrap()
+        );
+        assert_eq!(
+            count(&app, "SELECT total_amount_cents FROM deals WHERE id = ?1", &deal.id),
+            23_200
+        );
+    }
+
+    #[test]
+    fn test_options_carry_the_vehicle_cobuyers_and_fields() {
+        let app = TestApp::new();
+        sold_deal(&app);
+        let deal = clone(
+            &app,
+            CloneDealOptions {
+                carry_vehicle: true,
+                vehicle_id: Some("v2".to_string()),
+                copy_cobuyers: true,
+                fields: vec![CloneField::TitleState, CloneField::LienHolder],
+            },
+        )
+        .unwrap();
+
+        assert_eq!(deal.vehicle_id, "v1");
+        assert_eq!(deal.title_state.as_deref(), Some("TX"));
+        assert_eq!(deal.lien_holder.as_deref(), Some("Lone Star Credit Union"));
+        let cobuyers = list_cobuyers(&app.conn(), &deal.id).unwrap();
+        assert_eq!(cobuyers.len(), 1);
+        assert_eq!(
+            (cobuyers[0].first_name.as_str(), cobuyers[0].relationship.as_deref()),
+            ("Sam", Some("spouse"))
+        );
+        assert!(deal.cobuyer_data.unwrap().contains("Sam"));
+        // The source keeps its own
+        assert_eq!(list_cobuyers(&app.conn(), "d1").unwrap().len(), 1);
+
+        let only_title = clone(
+            &app,
+            CloneDealOptions {
+                carry_vehicle: true,
+                fields: vec![CloneField::TitleState],
+                ..CloneDealOptions::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(only_title.title_state.as_deref(), Some("TX"));
+        assert_eq!(only_title.lien_holder, None);
+        assert!(list_cobuyers(&app.conn(), &only_title.id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_a_new_vehicle_is_required_and_must_be_the_users() {
+        let app = TestApp::new();
+        sold_deal(&app);
+        assert!(clone(&app, CloneDealOptions::default()).is_err());
+
+        app.sign_in("other-user");
+        db_create_vehicle(make_vehicle("v9"), app.state(), app.db()).unwrap();
+        app.sign_in(TEST_USER);
+        let err = clone(
+            &app,
+            CloneDealOptions {
+                vehicle_id: Some("v9".to_string()),
+                ..CloneDealOptions::default()
+            },
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not found"));
+        assert_eq!(count(&app, "SELECT COUNT(*) FROM deals WHERE id != ?1", "d1"), 0);
+
+        // Nor can anyone clone a deal they don't own
+        app.sign_in("other-user");
+        let err = db_clone_deal(
+            "d1".to_string(),
+            None,
+            Some(CloneDealOptions {
+                vehicle_id: Some("v9".to_string()),
+                ..CloneDealOptions::default()
+            }),
+            app.state(),
+            app.db(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("not found"));
+    }
+
+    #[test]
+    fn test_documents_are_never_cloned_and_the_clone_is_audited() {
+        let app = TestApp::new();
+        sold_deal(&app);
+        for copy_cobuyers in [false, true] {
+            for carry_vehicle in [false, true] {
+                let deal = clone(
+                    &app,
+                    CloneDealOptions {
+                        carry_vehicle,
+                        vehicle_id: Some("v2".to_string()),
+                        copy_cobuyers,
+                        fields: vec![CloneField::TitleState, CloneField::LienHolder],
+                    },
+                )
+                .unwrap();
+                assert_eq!(deal.document_ids, "[]");
+                assert_eq!(
+                    count(&app, "SELECT COUNT(*) FROM documents WHERE deal_id = ?1", &deal.id),
+                    0
+                );
+                assert_eq!(
+                    count(
+                        &app,
+                        "SELECT COUNT(*) FROM deal_status_history WHERE deal_id = ?1",
+                        &deal.id
+                    ),
+                    1
+                );
+            }
+        }
+
+        let entries = audit_entries(&app.conn(), TEST_USER, 10).unwrap();
+        assert_eq!(entries.len(), 4);
+        assert!(entries.iter().all(|entry| entry.action == DEAL_CLONED_ACTION
+            && entry.details["source_deal_id"] == "d1"));
+        assert_eq!(entries[0].details["options"]["copy_cobuyers"], true);
+        assert_eq!(
+            count(&app, "SELECT COUNT(*) FROM documents WHERE deal_id != ?1", "d1"),
+            0
+        );
+    }
+}
@@ -0,0 +1,912 @@
+// src-tauri/src/deal_cobuyers.rs
+//
+// Co-buyers on a deal, one deal_cobuyers row each (migration 25), so they can be searched
+// (global_search.rs) and filled into forms by column name. Migration 25 copied every
+// deals.cobuyer_data blob into a row; blobs it couldn't read are kept in
+// cobuyer_migration_issues with the original text (get_cobuyer_migration_issues).
+//
+// For one release deals.cobuyer_data is still written as the legacy camelCase JSON object of
+// the deal's first co-buyer, in the same transaction as every co-buyer change, so older
+// sync code and exports keep working. Setting write_legacy_cobuyer_json to "false" stops
+// that. Callers that still write the column (db_create_deal / db_update_deal) replace the
+// deal's first co-buyer with what they wrote.
+
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{begin_write, new_row_id, read_setting, DbState};
+use crate::recent_items::{owned_by, EntityType};
+use crate::settings_cache::pending_setting;
+use crate::telemetry::track;
+
+pub(crate) const LEGACY_JSON_SETTING: &str = "write_legacy_cobuyer_json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DealCobuyer {
+    pub id: String,
+    pub deal_id: String,
+    pub user_id: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub address_line2: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub zip_code: Option<String>,
+    pub drivers_license: Option<String>,
+    pub relationship: Option<String>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl DealCobuyer {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(DealCobuyer {
+            id: row.get("id")?,
+            deal_id: row.get("deal_id")?,
+            user_id: row.get("user_id")?,
+            first_name: row.get("first_name")?,
+            last_name: row.get("last_name")?,
+            email: row.get("email")?,
+            phone: row.get("phone")?,
+            address: row.get("address")?,
+            address_line2: row.get("address_line2")?,
+            city: row.get("city")?,
+            state: row.get("state")?,
+            zip_code: row.get("zip_code")?,
+            drivers_license: row.get("drivers_license")?,
+            relationship: row.get("relationship")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+
+    /// The object deals.cobuyer_data held before migration 25
+    fn to_legacy_json(&self) -> Value {
+        let mut object = Map::new();
+        object.insert("firstName".into(), json!(self.first_name));
+        object.insert("lastName".into(), json!(self.last_name));
+        for (key, value) in [
+            ("email", &self.email),
+            ("phone", &self.phone),
+            ("address", &self.address),
+            ("addressLine2", &self.address_line2),
+            ("city", &self.city),
+            ("state", &self.state),
+            ("zipCode", &self.zip_code),
+            ("driversLicense", &self.drivers_license),
+            ("relationship", &self.relationship),
+        ] {
+            if let Some(value) = value {
+                object.insert(key.into(), json!(value));
+            }
+        }
+        Value::Object(object)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct NewDealCobuyer {
+    #[serde(default)]
+    pub first_name: String,
+    #[serde(default)]
+    pub last_name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub address: Option<String>,
+    pub address_line2: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub zip_code: Option<String>,
+    pub drivers_license: Option<String>,
+    pub relationship: Option<String>,
+}
+
+impl NewDealCobuyer {
+    fn optional_fields(&mut self) -> [&mut Option<String>; 9] {
+        [
+            &mut self.email,
+            &mut self.phone,
+            &mut self.address,
+            &mut self.address_line2,
+            &mut self.city,
+            &mut self.state,
+            &mut self.zip_code,
+            &mut self.drivers_license,
+            &mut self.relationship,
+        ]
+    }
+
+    /// Trim everything; blank optional fields become None
+    fn normalized(mut self) -> Self {
+        self.first_name = self.first_name.trim().to_string();
+        self.last_name = self.last_name.trim().to_string();
+        for field in self.optional_fields() {
+            *field = blank_to_none(field.take());
+        }
+        self
+    }
+}
+
+impl From<DealCobuyer> for NewDealCobuyer {
+    fn from(cobuyer: DealCobuyer) -> Self {
+        NewDealCobuyer {
+            first_name: cobuyer.first_name,
+            last_name: cobuyer.last_name,
+            email: cobuyer.email,
+            phone: cobuyer.phone,
+            address: cobuyer.address,
+            address_line2: cobuyer.address_line2,
+            city: cobuyer.city,
+            state: cobuyer.state,
+            zip_code: cobuyer.zip_code,
+            drivers_license: cobuyer.drivers_license,
+            relationship: cobuyer.relationship,
+        }
+    }
+}
+
+/// A cobuyer_data blob that couldn't be turned into a row
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CobuyerMigrationIssue {
+    pub deal_id: String,
+    pub cobuyer_data: String,
+    pub error: String,
+    pub found_at: i64,
+}
+
+fn blank_to_none(value: Option<String>) -> Option<String> {
+    value
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+fn validate_cobuyer(cobuyer: &NewDealCobuyer) -> Result<(), String> {
+    if cobuyer.first_name.is_empty() && cobuyer.last_name.is_empty() {
+        return Err("Co-buyer name is required".to_string());
+    }
+    Ok(())
+}
+
+/// Read a legacy cobuyer_data blob. Keys may be camelCase (what the frontend wrote) or
+/// snake_case; unknown keys are ignored. Ok(None) when it holds no co-buyer (null, {}).
+pub(crate) fn parse_legacy_json(json: &str) -> Result<Option<NewDealCobuyer>, String> {
+    let value: Value = serde_json::from_str(json).map_err(|e| format!("not valid JSON ({})", e))?;
+    from_legacy_value(value, true)
+}
+
+fn from_legacy_value(value: Value, unwrap_string: bool) -> Result<Option<NewDealCobuyer>, String> {
+    let object = match value {
+        Value::Null => return Ok(None),
+        // Some writers stringified the object before it was encoded again
+        Value::String(inner) if unwrap_string => {
+            if inner.trim().is_empty() {
+                return Ok(None);
+            }
+            let value =
+                serde_json::from_str(&inner).map_err(|e| format!("not valid JSON ({})", e))?;
+            return from_legacy_value(value, false);
+        }
+        Value::Object(object) => object,
+        Value::String(_) => return Err("expected an object, found a string".to_string()),
+        Value::Array(_) => return Err("expected an object, found an array".to_string()),
+        Value::Bool(_) | Value::Number(_) => {
+            return Err("expected an object, found a scalar".to_string())
+        }
+    };
+
+    let field = |keys: &[&str]| -> Option<String> {
+        keys.iter().find_map(|key| match object.get(*key) {
+            Some(Value::String(s)) => blank_to_none(Some(s.clone())),
+            Some(Value::Number(n)) => Some(n.to_string()),
+            _ => None,
+        })
+    };
+    let cobuyer = NewDealCobuyer {
+        first_name: field(&["firstName", "first_name"]).unwrap_or_default(),
+        last_name: field(&["lastName", "last_name"]).unwrap_or_default(),
+        email: field(&["email"]),
+        phone: field(&["phone"]),
+        address: field(&["address"]),
+        address_line2: field(&["addressLine2", "address_line2"]),
+        city: field(&["city"]),
+        state: field(&["state"]),
+        zip_code: field(&["zipCode", "zip_code", "zip"]),
+        drivers_license: field(&["driversLicense", "drivers_license"]),
+        relationship: field(&["relationship"]),
+    };
+    if cobuyer == NewDealCobuyer::default() {
+        Ok(None)
+    } else {
+        Ok(Some(cobuyer))
+    }
+}
+
+/// cobuyer_data as written by db_create_deal / db_update_deal, checked before the deal is
+pub(crate) fn legacy_cobuyer(json: Option<&str>) -> Result<Option<NewDealCobuyer>, String> {
+    match json {
+        Some(json) => parse_legacy_json(json).map_err(|e| format!("Invalid co-buyer data: {}", e)),
+        None => Ok(None),
+    }
+}
+
+fn writes_legacy_json(conn: &Connection) -> SqlResult<bool> {
+    let value = match pending_setting(LEGACY_JSON_SETTING) {
+        Some(value) => Some(value),
+        None => read_setting(conn, LEGACY_JSON_SETTING)?,
+    };
+    Ok(value.is_none_or(|value| value.trim() != "false"))
+}
+
+/// Rewrite deals.cobuyer_data from the deal's first co-buyer (NULL when there are none),
+/// unless the legacy column has been switched off
+fn sync_legacy_json(conn: &Connection, deal_id: &str) -> SqlResult<()> {
+    if !writes_legacy_json(conn)? {
+        return Ok(());
+    }
+    let json = list_cobuyers(conn, deal_id)?
+        .first()
+        .map(|cobuyer| cobuyer.to_legacy_json().to_string());
+    conn.execute(
+        "UPDATE deals SET cobuyer_data = ?2 WHERE id = ?1",
+        params![deal_id, json],
+    )?;
+    Ok(())
+}
+
+/// A deal's co-buyers, first one first (ownership checked by the caller)
+pub(crate) fn list_cobuyers(conn: &Connection, deal_id: &str) -> SqlResult<Vec<DealCobuyer>> {
+    let mut stmt =
+        conn.prepare("SELECT * FROM deal_cobuyers WHERE deal_id = ?1 ORDER BY created_at, id")?;
+    let rows = stmt.query_map(params![deal_id], DealCobuyer::from_row)?;
+    rows.collect()
+}
+
+/// Every co-buyer of the user's deals, by deal id (db_get_deals_with_details)
+pub(crate) fn cobuyers_by_deal(
+    conn: &Connection,
+    user_id: &str,
+) -> SqlResult<HashMap<String, Vec<DealCobuyer>>> {
+    let mut stmt =
+        conn.prepare("SELECT * FROM deal_cobuyers WHERE user_id = ?1 ORDER BY created_at, id")?;
+    let rows = stmt.query_map(params![user_id], DealCobuyer::from_row)?;
+    let mut by_deal: HashMap<String, Vec<DealCobuyer>> = HashMap::new();
+    for cobuyer in rows {
+        let cobuyer = cobuyer?;
+        by_deal
+            .entry(cobuyer.deal_id.clone())
+            .or_default()
+            .push(cobuyer);
+    }
+    Ok(by_deal)
+}
+
+fn get_cobuyer(conn: &Connection, user_id: &str, id: &str) -> Result<DealCobuyer, String> {
+    conn.query_row(
+        "SELECT * FROM deal_cobuyers WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+        DealCobuyer::from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Co-buyer not found or access denied".to_string())
+}
+
+fn insert_cobuyer(
+    conn: &Connection,
+    id: &str,
+    deal_id: &str,
+    user_id: &str,
+    cobuyer: &NewDealCobuyer,
+    now: i64,
+) -> SqlResult<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO deal_cobuyers (id, deal_id, user_id, first_name, last_name,
+             email, phone, address, address_line2, city, state, zip_code, drivers_license,
+             relationship, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?15)",
+        params![
+            id,
+            deal_id,
+            user_id,
+            cobuyer.first_name,
+            cobuyer.last_name,
+            cobuyer.email,
+            cobuyer.phone,
+            cobuyer.address,
+            cobuyer.address_line2,
+            cobuyer.city,
+            cobuyer.state,
+            cobuyer.zip_code,
+            cobuyer.drivers_license,
+            cobuyer.relationship,
+            now
+        ],
+    )?;
+    Ok(())
+}
+
+fn write_cobuyer(conn: &Connection, id: &str, cobuyer: &NewDealCobuyer, now: i64) -> SqlResult<()> {
+    conn.execute(
+        "UPDATE deal_cobuyers SET first_name = ?2, last_name = ?3, email = ?4, phone = ?5,
+             address = ?6, address_line2 = ?7, city = ?8, state = ?9, zip_code = ?10,
+             drivers_license = ?11, relationship = ?12, updated_at = ?13
+         WHERE id = ?1",
+        params![
+            id,
+            cobuyer.first_name,
+            cobuyer.last_name,
+            cobuyer.email,
+            cobuyer.phone,
+            cobuyer.address,
+            cobuyer.address_line2,
+            cobuyer.city,
+            cobuyer.state,
+            cobuyer.zip_code,
+            cobuyer.drivers_license,
+            cobuyer.relationship,
+            now
+        ],
+    )?;
+    Ok(())
+}
+
+/// Make `cobuyer` the deal's first co-buyer (None removes it), for callers that still write
+/// the cobuyer_data column (db_create_deal / db_update_deal). Other co-buyers are kept.
+pub(crate) fn replace_first_cobuyer(
+    conn: &Connection,
+    deal_id: &str,
+    user_id: &str,
+    cobuyer: Option<NewDealCobuyer>,
+    now: i64,
+) -> SqlResult<()> {
+    let first = list_cobuyers(conn, deal_id)?.into_iter().next();
+    match (first, cobuyer.map(NewDealCobuyer::normalized)) {
+        (Some(first), Some(cobuyer)) => write_cobuyer(conn, &first.id, &cobuyer, now)?,
+        (Some(first), None) => {
+            conn.execute("DELETE FROM deal_cobuyers WHERE id = ?1", params![first.id])?;
+        }
+        (None, Some(cobuyer)) => {
+            insert_cobuyer(conn, &new_row_id(), deal_id, user_id, &cobuyer, now)?
+        }
+        (None, None) => {}
+    }
+    sync_legacy_json(conn, deal_id)
+}
+
+/// Turn a deal's cobuyer_data into a row, or record why it can't be. Returns whether the
+/// blob was unreadable.
+fn copy_legacy_json(
+    conn: &Connection,
+    deal_id: &str,
+    user_id: &str,
+    json: &str,
+    now: i64,
+) -> SqlResult<bool> {
+    match parse_legacy_json(json) {
+        Ok(Some(cobuyer)) => {
+            // A fixed id, so copying the same deal twice can't add a second row
+            let id = format!("cobuyer-{}", deal_id);
+            insert_cobuyer(conn, &id, deal_id, user_id, &cobuyer, now)?;
+            Ok(false)
+        }
+        Ok(None) => Ok(false),
+        Err(error) => {
+            // The error never quotes the blob, so it's safe to log
+            warn!(
+                "⚠️  Co-buyer data on deal {} can't be read: {}",
+                deal_id, error
+            );
+            conn.execute(
+                "INSERT OR REPLACE INTO cobuyer_migration_issues
+                     (deal_id, user_id, cobuyer_data, error, found_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![deal_id, user_id, json, error, Utc::now().timestamp_millis()],
+            )?;
+            Ok(true)
+        }
+    }
+}
+
+/// Migration 25: copy every deal's cobuyer_data into deal_cobuyers.
+/// Returns (deals copied, blobs that couldn't be read)
+pub(crate) fn migrate_cobuyer_data(conn: &Connection) -> SqlResult<(usize, usize)> {
+    let deals: Vec<(String, String, String, i64)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, COALESCE(user_id, ''), cobuyer_data, updated_at FROM deals
+             WHERE cobuyer_data IS NOT NULL AND TRIM(cobuyer_data) != ''",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+        })?;
+        rows.collect::<SqlResult<_>>()?
+    };
+
+    let mut unreadable = 0;
+    for (deal_id, user_id, json, updated_at) in &deals {
+        if copy_legacy_json(conn, deal_id, user_id, json, *updated_at)? {
+            unreadable += 1;
+        }
+    }
+    Ok((deals.len() - unreadable, unreadable))
+}
+
+/// Give a deal that has cobuyer_data but no co-buyer rows its co-buyer (deals that arrive
+/// without their rows, e.g. from a data import)
+pub(crate) fn backfill_cobuyers(conn: &Connection, deal_id: &str, user_id: &str) -> SqlResult<()> {
+    let (json, updated_at): (Option<String>, i64) = conn.query_row(
+        "SELECT cobuyer_data, updated_at FROM deals WHERE id = ?1",
+        params![deal_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    let Some(json) = json.filter(|json| !json.trim().is_empty()) else {
+        return Ok(());
+    };
+    if list_cobuyers(conn, deal_id)?.is_empty() {
+        copy_legacy_json(conn, deal_id, user_id, &json, updated_at)?;
+    }
+    Ok(())
+}
+
+/// Form-fill values for the deal's first co-buyer: its columns (cobuyer.first_name...) plus
+/// the legacy JSON keys (cobuyer.firstName...), so field maps written against the old blob
+/// keep working
+pub(crate) fn cobuyer_form_fields(
+    conn: &Connection,
+    deal_id: &str,
+) -> SqlResult<Option<Map<String, Value>>> {
+    let Some(cobuyer) = list_cobuyers(conn, deal_id)?.into_iter().next() else {
+        return Ok(None);
+    };
+    let mut fields = match cobuyer.to_legacy_json() {
+        Value::Object(legacy) => legacy,
+        _ => Map::new(),
+    };
+    if let Ok(Value::Object(columns)) = serde_json::to_value(&cobuyer) {
+        fields.extend(columns);
+    }
+    Ok(Some(fields))
+}
+
+fn notify_changed(user_id: &str, cobuyer: &DealCobuyer, operation: Operation) {
+    data_changed(user_id, ChangedEntity::DealCobuyer, &cobuyer.id, operation);
+    // cobuyer_data may have changed with it
+    data_changed(
+        user_id,
+        ChangedEntity::Deal,
+        &cobuyer.deal_id,
+        Operation::Update,
+    );
+}
+
+fn add_cobuyer(
+    conn: &Connection,
+    user_id: &str,
+    deal_id: &str,
+    cobuyer: NewDealCobuyer,
+    now: i64,
+) -> Result<DealCobuyer, String> {
+    let cobuyer = cobuyer.normalized();
+    validate_cobuyer(&cobuyer)?;
+    owned_by(conn, user_id, EntityType::Deal, deal_id)?;
+
+    let id = new_row_id();
+    let tx = begin_write(conn, "deal_cobuyers").map_err(|e| e.to_string())?;
+    insert_cobuyer(&tx, &id, deal_id, user_id, &cobuyer, now).map_err(|e| e.to_string())?;
+    sync_legacy_json(&tx, deal_id).map_err(|e| e.to_string())?;
+    let cobuyer = get_cobuyer(&tx, user_id, &id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(cobuyer)
+}
+
+fn update_cobuyer(
+    conn: &Connection,
+    user_id: &str,
+    id: &str,
+    updates: &Value,
+    now: i64,
+) -> Result<DealCobuyer, String> {
+    let existing = get_cobuyer(conn, user_id, id)?;
+    let deal_id = existing.deal_id.clone();
+    let mut cobuyer = NewDealCobuyer::from(existing);
+
+    if let Some(first_name) = updates.get("first_name").and_then(|v| v.as_str()) {
+        cobuyer.first_name = first_name.to_string();
+    }
+    if let Some(last_name) = updates.get("last_name").and_then(|v| v.as_str()) {
+        cobuyer.last_name = last_name.to_string();
+    }
+    // null clears an optional field
+    let keys = [
+        "email",
+        "phone",
+        "address",
+        "address_line2",
+        "city",
+        "state",
+        "zip_code",
+        "drivers_license",
+        "relationship",
+    ];
+    for (key, field) in keys.into_iter().zip(cobuyer.optional_fields()) {
+        match updates.get(key) {
+            Some(Value::String(value)) => *field = Some(value.clone()),
+            Some(Value::Null) => *field = None,
+            _ => {}
+        }
+    }
+    let cobuyer = cobuyer.normalized();
+    validate_cobuyer(&cobuyer)?;
+
+    let tx = begin_write(conn, "deal_cobuyers").map_err(|e| e.to_string())?;
+    write_cobuyer(&tx, id, &cobuyer, now).map_err(|e| e.to_string())?;
+    sync_legacy_json(&tx, &deal_id).map_err(|e| e.to_string())?;
+    let updated = get_cobuyer(&tx, user_id, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+fn delete_cobuyer(conn: &Connection, user_id: &str, id: &str) -> Result<DealCobuyer, String> {
+    let cobuyer = get_cobuyer(conn, user_id, id)?;
+    let tx = begin_write(conn, "deal_cobuyers").map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM deal_cobuyers WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    sync_legacy_json(&tx, &cobuyer.deal_id).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(cobuyer)
+}
+
+#[tauri::command]
+pub fn db_add_deal_cobuyer(
+    deal_id: String,
+    cobuyer: NewDealCobuyer,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<DealCobuyer, String> {
+    track("db_add_deal_cobuyer", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let cobuyer = add_cobuyer(
+            &conn,
+            &user_id_value,
+            &deal_id,
+            cobuyer,
+            Utc::now().timestamp_millis(),
+        )?;
+        info!("✅ Co-buyer added to deal {}", deal_id);
+        notify_changed(&user_id_value, &cobuyer, Operation::Create);
+        Ok(cobuyer)
+    })
+}
+
+#[tauri::command]
+pub fn db_get_deal_cobuyers(
+    deal_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<Vec<DealCobuyer>, String> {
+    track("db_get_deal_cobuyers", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        owned_by(&conn, &user_id_value, EntityType::Deal, &deal_id)?;
+        list_cobuyers(&conn, &deal_id).map_err(|e| e.to_string())
+    })
+}
+
+/// Change any of the co-buyer's fields; null clears an optional one
+#[tauri::command]
+pub fn db_update_deal_cobuyer(
+    id: String,
+    updates: Value,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<DealCobuyer, String> {
+    track("db_update_deal_cobuyer", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let cobuyer = update_cobuyer(
+            &conn,
+            &user_id_value,
+            &id,
+            &updates,
+            Utc::now().timestamp_millis(),
+        )?;
+        notify_changed(&user_id_value, &cobuyer, Operation::Update);
+        Ok(cobuyer)
+    })
+}
+
+#[tauri::command]
+pub fn db_delete_deal_cobuyer(
+    id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<(), String> {
+    track("db_delete_deal_cobuyer", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let cobuyer = delete_cobuyer(&conn, &user_id_value, &id)?;
+        notify_changed(&user_id_value, &cobuyer, Operation::Delete);
+        Ok(())
+    })
+}
+
+/// cobuyer_data blobs migration 25 (or a data import) couldn't read, with their text
+#[tauri::command]
+pub fn get_cobuyer_migration_issues(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<Vec<CobuyerMigrationIssue>, String> {
+    track("get_cobuyer_migration_issues", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT deal_id, cobuyer_data, error, found_at FROM cobuyer_migration_issues
+                 WHERE user_id = ?1 ORDER BY deal_id",
+            )
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![user_id_value], |row| {
+                Ok(CobuyerMigrationIssue {
+                    deal_id: row.get(0)?,
+                    cobuyer_data: row.get(1)?,
+                    error: row.get(2)?,
+                    found_at: row.get(3)?,
+                })
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<SqlResult<_>>().map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{
+        db_create_client, db_create_deal, db_create_vehicle, db_get_deals_with_details,
+        db_update_deal,
+    };
+    use crate::test_support::{
+        make_client, make_deal, make_vehicle, seeded_db_at, TestApp, SEED_CLIENT, SEED_VEHICLE,
+        TEST_USER,
+    };
+
+    fn legacy_json(app: &TestApp, deal_id: &str) -> Option<Value> {
+        let json: Option<String> = app
+            .conn()
+            .query_row(
+                "SELECT cobuyer_data FROM deals WHERE id = ?1",
+                params![deal_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        json.map(|json| serde_json::from_str(&json).unwrap())
+    }
+
+    #[test]
+    fn test_migration_copies_legacy_json() {
+        let db = seeded_db_at(24);
+        let blobs = [
+            (
+                "camel",
+                r#"{"firstName":"Cara","lastName":"Diaz","phone":"555-010-2020","zipCode":78701,"driversLicense":"CB-1"}"#,
+            ),
+            // snake_case keys, an unknown key and a missing last name
+            (
+                "snake",
+                r#"{"first_name":"Dev","email":"dev@example.com","favoriteColor":"blue"}"#,
+            ),
+            // Stringified before it was stored
+            ("double", r#""{\"firstName\":\"Eve\",\"lastName\":\"Ng\"}""#),
+            ("null", "null"),
+            ("empty", "{}"),
+            ("truncated", r#"{"firstName":"Fay","lastNa"#),
+            ("array", r#"["Gus","Hill"]"#),
+        ];
+        {
+            let conn = db.conn();
+            for (id, blob) in blobs {
+                conn.execute(
+                    "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount,
+                         document_ids, cobuyer_data, created_at, updated_at, user_id)
+                     VALUES (?1, 'cash', ?2, ?3, 'quote', 1000, '[]', ?4, 5, 5, ?5)",
+                    params![id, SEED_CLIENT, SEED_VEHICLE, blob, TEST_USER],
+                )
+                .unwrap();
+            }
+        }
+        db.migrate_to(i32::MAX).unwrap();
+        let app = TestApp::with_db(db);
+
+        let cobuyers = |deal_id: &str| {
+            db_get_deal_cobuyers(deal_id.to_string(), None, app.state(), app.db()).unwrap()
+        };
+        let camel = cobuyers("camel");
+        assert_eq!(camel.len(), 1);
+        assert_eq!(
+            (camel[0].first_name.as_str(), camel[0].last_name.as_str()),
+            ("Cara", "Diaz")
+        );
+        assert_eq!(camel[0].phone.as_deref(), Some("555-010-2020"));
+        assert_eq!(camel[0].zip_code.as_deref(), Some("78701"));
+        assert_eq!(camel[0].drivers_license.as_deref(), Some("CB-1"));
+        assert_eq!(camel[0].user_id, TEST_USER);
+
+        let snake = cobuyers("snake");
+        assert_eq!(snake[0].first_name, "Dev");
+        assert_eq!(snake[0].last_name, "");
+        assert_eq!(snake[0].email.as_deref(), Some("dev@example.com"));
+        assert_eq!(cobuyers("double")[0].first_name, "Eve");
+        for deal_id in ["null", "empty", "truncated", "array"] {
+            assert!(cobuyers(deal_id).is_empty(), "{}", deal_id);
+        }
+
+        // Unreadable blobs are reported with their text, and left in place
+        let issues = get_cobuyer_migration_issues(None, app.state(), app.db()).unwrap();
+        let reported: Vec<(&str, &str)> = issues
+            .iter()
+            .map(|issue| (issue.deal_id.as_str(), issue.cobuyer_data.as_str()))
+            .collect();
+        assert_eq!(
+            reported,
+            [
+                ("array", r#"["Gus","Hill"]"#),
+                ("truncated", r#"{"firstName":"Fay","lastNa"#)
+            ]
+        );
+        assert!(
+            issues[0].error.contains("found an array"),
+            "{:?}",
+            issues[0]
+        );
+        assert!(
+            issues[1].error.starts_with("not valid JSON"),
+            "{:?}",
+            issues[1]
+        );
+        assert!(!issues[1].error.contains("Fay"));
+        let kept: String = app
+            .conn()
+            .query_row(
+                "SELECT cobuyer_data FROM deals WHERE id = 'truncated'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(kept, r#"{"firstName":"Fay","lastNa"#);
+
+        // Copying again (e.g. an import of the same deals) doesn't add rows
+        backfill_cobuyers(&app.conn(), "camel", TEST_USER).unwrap();
+        assert_eq!(cobuyers("camel").len(), 1);
+    }
+
+    #[test]
+    fn test_cobuyer_rows_keep_legacy_json_in_step() {
+        let app = TestApp::new();
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        let mut deal = make_deal("d1", "c1", "v1");
+        deal.cobuyer_data = Some(r#"{"firstName":"Cara","lastName":"Diaz"}"#.to_string());
+        db_create_deal(deal, None, None, app.state(), app.db()).unwrap();
+
+        // cobuyer_data on a new deal became its first co-buyer
+        let first = db_get_deal_cobuyers("d1".into(), None, app.state(), app.db()).unwrap();
+        assert_eq!(first.len(), 1);
+        assert_eq!(first[0].first_name, "Cara");
+
+        let second = NewDealCobuyer {
+            first_name: " Dev ".to_string(),
+            last_name: "Diaz".to_string(),
+            relationship: Some("spouse".to_string()),
+            phone: Some("  ".to_string()),
+            ..Default::default()
+        };
+        let second = db_add_deal_cobuyer("d1".into(), second, None, app.state(), app.db()).unwrap();
+        assert_eq!(second.first_name, "Dev");
+        assert_eq!(second.phone, None);
+        let err = db_add_deal_cobuyer(
+            "d1".into(),
+            NewDealCobuyer::default(),
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap_err();
+        assert_eq!(err, "Co-buyer name is required");
+
+        // The legacy column follows the first co-buyer
+        db_update_deal_cobuyer(
+            first[0].id.clone(),
+            json!({ "email": "cara@example.com", "last_name": "Ruiz" }),
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        assert_eq!(
+            legacy_json(&app, "d1"),
+            Some(json!({ "firstName": "Cara", "lastName": "Ruiz", "email": "cara@example.com" }))
+        );
+        db_delete_deal_cobuyer(first[0].id.clone(), None, app.state(), app.db()).unwrap();
+        assert_eq!(legacy_json(&app, "d1").unwrap()["firstName"], "Dev");
+
+        // Writing cobuyer_data through db_update_deal replaces the first co-buyer only
+        db_update_deal(
+            "d1".into(),
+            json!({ "cobuyer_data": { "firstName": "Eve", "lastName": "Ng" } }),
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        let details = db_get_deals_with_details(None, app.state(), app.db()).unwrap();
+        let names: Vec<&str> = details[0]
+            .cobuyers
+            .iter()
+            .map(|c| c.first_name.as_str())
+            .collect();
+        assert_eq!(names, ["Eve"]);
+        let err = db_update_deal(
+            "d1".into(),
+            json!({ "cobuyer_data": ["not", "a", "co-buyer"] }),
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap_err();
+        assert!(err.starts_with("Invalid co-buyer data"), "{}", err);
+
+        // With the compatibility flag off the column is left alone
+        app.conn()
+            .execute(
+                "INSERT INTO settings (key, value, updated_at) VALUES (?1, 'false', 0)",
+                params![LEGACY_JSON_SETTING],
+            )
+            .unwrap();
+        db_add_deal_cobuyer(
+            "d1".into(),
+            NewDealCobuyer {
+                first_name: "Fay".to_string(),
+                ..Default::default()
+            },
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        db_update_deal_cobuyer(
+            details[0].cobuyers[0].id.clone(),
+            json!({ "first_name": "Evelyn" }),
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        assert_eq!(legacy_json(&app, "d1").unwrap()["firstName"], "Eve");
+
+        // Another user can't see or change them
+        app.sign_in("someone-else");
+        assert!(db_get_deal_cobuyers("d1".into(), None, app.state(), app.db()).is_err());
+        assert!(db_delete_deal_cobuyer(second.id, None, app.state(), app.db()).is_err());
+    }
+}
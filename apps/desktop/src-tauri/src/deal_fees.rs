@@ -0,0 +1,750 @@
+// src-tauri/src/deal_fees.rs
+//
+// Fee line items on a deal: doc fee, title, registration, temp tag, service contract, GAP...
+// Amounts are integer cents. deals.doc_fee is kept equal to the sum of the doc_fee lines
+// (migration 20 turned each existing doc_fee into one), so code that still reads the
+// column sees the same number. db_recalculate_deal_totals rebuilds total_amount as
+// sale + fees + sales tax - trade-in in one transaction.
+// For sales tax, lines marked taxable go into the base; doc_fee lines additionally follow
+// the state's doc_fee_taxable rule (see tax.rs).
+
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{get_db, new_row_id};
+use crate::money::to_cents;
+use crate::telemetry::track;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeCategory {
+    DocFee,
+    Title,
+    Registration,
+    TempTag,
+    ServiceContract,
+    Gap,
+    #[default]
+    Other,
+}
+
+impl FeeCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FeeCategory::DocFee => "doc_fee",
+            FeeCategory::Title => "title",
+            FeeCategory::Registration => "registration",
+            FeeCategory::TempTag => "temp_tag",
+            FeeCategory::ServiceContract => "service_contract",
+            FeeCategory::Gap => "gap",
+            FeeCategory::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "doc_fee" => Some(FeeCategory::DocFee),
+            "title" => Some(FeeCategory::Title),
+            "registration" => Some(FeeCategory::Registration),
+            "temp_tag" => Some(FeeCategory::TempTag),
+            "service_contract" => Some(FeeCategory::ServiceContract),
+            "gap" => Some(FeeCategory::Gap),
+            "other" => Some(FeeCategory::Other),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DealFee {
+    pub id: String,
+    pub deal_id: String,
+    pub user_id: String,
+    pub label: String,
+    pub amount_cents: i64,
+    pub taxable: bool,
+    pub category: FeeCategory,
+    pub sort_order: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl DealFee {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        let category: String = row.get("category")?;
+        Ok(DealFee {
+            id: row.get("id")?,
+            deal_id: row.get("deal_id")?,
+            user_id: row.get("user_id")?,
+            label: row.get("label")?,
+            amount_cents: row.get("amount_cents")?,
+            taxable: row.get("taxable")?,
+            category: FeeCategory::parse(&category).unwrap_or_default(),
+            sort_order: row.get("sort_order")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewDealFee {
+    pub label: String,
+    pub amount_cents: i64,
+    #[serde(default = "default_taxable")]
+    pub taxable: bool,
+    #[serde(default)]
+    pub category: FeeCategory,
+    /// Defaults to after the deal's last line
+    #[serde(default)]
+    pub sort_order: Option<i64>,
+}
+
+fn default_taxable() -> bool {
+    true
+}
+
+/// How a deal's total was built, in cents
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DealTotals {
+    pub sale_cents: i64,
+    pub fees_cents: i64,
+    pub sales_tax_cents: i64,
+    pub trade_in_cents: i64,
+    /// sale + fees + sales tax - trade-in, as written to total_amount
+    pub total_cents: i64,
+}
+
+/// Fee amounts the tax engine needs, split by how they're taxed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TaxableFees {
+    /// Taxable doc_fee lines (the state decides whether these are taxed)
+    pub doc_fee_cents: i64,
+    /// Every other taxable line
+    pub other_cents: i64,
+}
+
+pub(crate) fn taxable_fees(fees: &[DealFee]) -> TaxableFees {
+    fees.iter()
+        .filter(|fee| fee.taxable)
+        .fold(TaxableFees::default(), |mut taxable, fee| {
+            if fee.category == FeeCategory::DocFee {
+                taxable.doc_fee_cents += fee.amount_cents;
+            } else {
+                taxable.other_cents += fee.amount_cents;
+            }
+            taxable
+        })
+}
+
+fn validate_fee(label: &str, amount_cents: i64) -> Result<(), String> {
+    if label.trim().is_empty() {
+        return Err("Fee label is required".to_string());
+    }
+    if amount_cents < 0 {
+        return Err("Fee amount can't be negative".to_string());
+    }
+    Ok(())
+}
+
+fn check_deal(conn: &Connection, user_id: &str, deal_id: &str) -> Result<(), String> {
+    conn.query_row(
+        "SELECT 1 FROM deals WHERE id = ?1 AND user_id = ?2",
+        params![deal_id, user_id],
+        |_| Ok(()),
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Deal not found or access denied".to_string())
+}
+
+fn get_fee(conn: &Connection, user_id: &str, id: &str) -> Result<DealFee, String> {
+    conn.query_row(
+        "SELECT * FROM deal_fees WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+        DealFee::from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Fee not found or access denied".to_string())
+}
+
+/// A deal's fee lines in display order (ownership checked by the caller)
+pub(crate) fn list_fees(conn: &Connection, deal_id: &str) -> SqlResult<Vec<DealFee>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM deal_fees WHERE deal_id = ?1 ORDER BY sort_order, created_at, id",
+    )?;
+    let rows = stmt.query_map(params![deal_id], DealFee::from_row)?;
+    rows.collect()
+}
+
+/// Set deals.doc_fee to the sum of the deal's doc_fee lines (NULL when there are none)
+fn sync_doc_fee(conn: &Connection, deal_id: &str) -> SqlResult<()> {
+    conn.execute(
+        "UPDATE deals SET doc_fee = (
+             SELECT SUM(amount_cents) / 100.0 FROM deal_fees
+             WHERE deal_id = ?1 AND category = ?2
+         ) WHERE id = ?1",
+        params![deal_id, FeeCategory::DocFee.as_str()],
+    )?;
+    Ok(())
+}
+
+/// Replace the deal's doc_fee lines with a single one, for callers that still set the
+/// doc_fee column directly (db_create_deal / db_update_deal)
+pub(crate) fn replace_doc_fee(
+    conn: &Connection,
+    deal_id: &str,
+    user_id: &str,
+    doc_fee: Option<f64>,
+    now: i64,
+) -> SqlResult<()> {
+    conn.execute(
+        "DELETE FROM deal_fees WHERE deal_id = ?1 AND category = ?2",
+        params![deal_id, FeeCategory::DocFee.as_str()],
+    )?;
+    let cents = doc_fee.map_or(0, to_cents);
+    if cents != 0 {
+        conn.execute(
+            "INSERT INTO deal_fees (id, deal_id, user_id, label, amount_cents, taxable, category,
+                                    sort_order, created_at, updated_at)
+             VALUES (?1, ?2, ?3, 'Documentation fee', ?4, 1, ?5, 0, ?6, ?6)",
+            params![
+                new_row_id(),
+                deal_id,
+                user_id,
+                cents,
+                FeeCategory::DocFee.as_str(),
+                now
+            ],
+        )?;
+    }
+    sync_doc_fee(conn, deal_id)
+}
+
+/// Give a deal that has a doc_fee but no fee lines its documentation fee line
+/// (deals that arrive without their lines, e.g. from a data import)
+pub(crate) fn backfill_doc_fee(conn: &Connection, deal_id: &str, user_id: &str) -> SqlResult<()> {
+    let has_lines: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM deal_fees WHERE deal_id = ?1)",
+        params![deal_id],
+        |row| row.get(0),
+    )?;
+    if has_lines {
+        return Ok(());
+    }
+    let (doc_fee, updated_at): (Option<f64>, i64) = conn.query_row(
+        "SELECT doc_fee, updated_at FROM deals WHERE id = ?1",
+        params![deal_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    replace_doc_fee(conn, deal_id, user_id, doc_fee, updated_at)
+}
+
+fn create_fee(
+    conn: &mut Connection,
+    user_id: &str,
+    deal_id: &str,
+    fee: NewDealFee,
+    now: i64,
+) -> Result<DealFee, String> {
+    validate_fee(&fee.label, fee.amount_cents)?;
+    check_deal(conn, user_id, deal_id)?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let id = insert_fee(&tx, user_id, deal_id, &fee, now).map_err(|e| e.to_string())?;
+    let fee = get_fee(&tx, user_id, &id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(fee)
+}
+
+/// Add a fee line (validated, on a deal the user owns) inside the caller's transaction;
+/// returns its id
+pub(crate) fn insert_fee(
+    conn: &Connection,
+    user_id: &str,
+    deal_id: &str,
+    fee: &NewDealFee,
+    now: i64,
+) -> SqlResult<String> {
+    let sort_order = match fee.sort_order {
+        Some(sort_order) => sort_order,
+        None => conn.query_row(
+            "SELECT COALESCE(MAX(sort_order) + 1, 0) FROM deal_fees WHERE deal_id = ?1",
+            params![deal_id],
+            |row| row.get(0),
+        )?,
+    };
+    let id = new_row_id();
+    conn.execute(
+        "INSERT INTO deal_fees (id, deal_id, user_id, label, amount_cents, taxable, category,
+                                sort_order, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?9)",
+        params![
+            id,
+            deal_id,
+            user_id,
+            fee.label.trim(),
+            fee.amount_cents,
+            fee.taxable,
+            fee.category.as_str(),
+            sort_order,
+            now
+        ],
+    )?;
+    sync_doc_fee(conn, deal_id)?;
+    Ok(id)
+}
+
+fn update_fee(
+    conn: &mut Connection,
+    user_id: &str,
+    id: &str,
+    updates: &Value,
+    now: i64,
+) -> Result<DealFee, String> {
+    let mut fee = get_fee(conn, user_id, id)?;
+
+    if let Some(label) = updates.get("label").and_then(|v| v.as_str()) {
+        fee.label = label.trim().to_string();
+    }
+    if let Some(amount_cents) = updates.get("amount_cents").and_then(|v| v.as_i64()) {
+        fee.amount_cents = amount_cents;
+    }
+    if let Some(taxable) = updates.get("taxable").and_then(|v| v.as_bool()) {
+        fee.taxable = taxable;
+    }
+    if let Some(category) = updates.get("category").and_then(|v| v.as_str()) {
+        fee.category = FeeCategory::parse(category)
+            .ok_or_else(|| format!("Unknown fee category '{}'", category))?;
+    }
+    if let Some(sort_order) = updates.get("sort_order").and_then(|v| v.as_i64()) {
+        fee.sort_order = sort_order;
+    }
+    validate_fee(&fee.label, fee.amount_cents)?;
+    fee.updated_at = now;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE deal_fees SET label = ?3, amount_cents = ?4, taxable = ?5, category = ?6,
+                              sort_order = ?7, updated_at = ?8
+         WHERE id = ?1 AND user_id = ?2",
+        params![
+            id,
+            user_id,
+            fee.label,
+            fee.amount_cents,
+            fee.taxable,
+            fee.category.as_str(),
+            fee.sort_order,
+            fee.updated_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    sync_doc_fee(&tx, &fee.deal_id).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(fee)
+}
+
+fn delete_fee(conn: &mut Connection, user_id: &str, id: &str) -> Result<(), String> {
+    let fee = get_fee(conn, user_id, id)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM deal_fees WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    sync_doc_fee(&tx, &fee.deal_id).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Recompute total_amount from the sale amount, fee lines, sales tax and trade-in
+/// Reading and writing happen in one transaction so a concurrent fee edit can't be lost.
+fn recalculate_totals(
+    conn: &mut Connection,
+    user_id: &str,
+    deal_id: &str,
+    now: i64,
+) -> Result<DealTotals, String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let (sale_amount, sales_tax, trade_in) = tx
+        .query_row(
+            "SELECT sale_amount, sales_tax, trade_in_value FROM deals
+             WHERE id = ?1 AND user_id = ?2",
+            params![deal_id, user_id],
+            |row| {
+                Ok((
+                    row.get::<_, Option<f64>>(0)?,
+                    row.get::<_, Option<f64>>(1)?,
+                    row.get::<_, Option<f64>>(2)?,
+                ))
+            },
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Deal not found or access denied".to_string())?;
+    let sale_cents = sale_amount
+        .map(to_cents)
+        .ok_or_else(|| "Set the deal's sale amount before recalculating its total".to_string())?;
+    let fees_cents: i64 = tx
+        .query_row(
+            "SELECT COALESCE(SUM(amount_cents), 0) FROM deal_fees WHERE deal_id = ?1",
+            params![deal_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut totals = DealTotals {
+        sale_cents,
+        fees_cents,
+        sales_tax_cents: sales_tax.map_or(0, to_cents),
+        trade_in_cents: trade_in.map_or(0, to_cents),
+        total_cents: 0,
+    };
+    totals.total_cents =
+        totals.sale_cents + totals.fees_cents + totals.sales_tax_cents - totals.trade_in_cents;
+
+    tx.execute(
+        "UPDATE deals SET total_amount = ?2, updated_at = ?3 WHERE id = ?1",
+        params![deal_id, totals.total_cents as f64 / 100.0, now],
+    )
+    .map_err(|e| e.to_string())?;
+    sync_doc_fee(&tx, deal_id).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(totals)
+}
+
+#[tauri::command]
+pub fn db_create_deal_fee(
+    deal_id: String,
+    fee: NewDealFee,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DealFee, String> {
+    track("db_create_deal_fee", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let mut conn = db.conn();
+        let fee = create_fee(
+            &mut conn,
+            &user_id_value,
+            &deal_id,
+            fee,
+            Utc::now().timestamp_millis(),
+        )?;
+        data_changed(
+            &user_id_value,
+            ChangedEntity::DealFee,
+            &fee.id,
+            Operation::Create,
+        );
+        Ok(fee)
+    })
+}
+
+#[tauri::command]
+pub fn db_get_deal_fees(
+    deal_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DealFee>, String> {
+    track("db_get_deal_fees", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        check_deal(&conn, &user_id_value, &deal_id)?;
+        list_fees(&conn, &deal_id).map_err(|e| e.to_string())
+    })
+}
+
+/// Change label, amount_cents, taxable, category or sort_order
+#[tauri::command]
+pub fn db_update_deal_fee(
+    id: String,
+    updates: Value,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DealFee, String> {
+    track("db_update_deal_fee", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let mut conn = db.conn();
+        let fee = update_fee(
+            &mut conn,
+            &user_id_value,
+            &id,
+            &updates,
+            Utc::now().timestamp_millis(),
+        )?;
+        data_changed(
+            &user_id_value,
+            ChangedEntity::DealFee,
+            &id,
+            Operation::Update,
+        );
+        Ok(fee)
+    })
+}
+
+#[tauri::command]
+pub fn db_delete_deal_fee(
+    id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    track("db_delete_deal_fee", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let mut conn = db.conn();
+        delete_fee(&mut conn, &user_id_value, &id)?;
+        data_changed(
+            &user_id_value,
+            ChangedEntity::DealFee,
+            &id,
+            Operation::Delete,
+        );
+        Ok(())
+    })
+}
+
+/// Rewrite the deal's total_amount from its sale amount, fees, sales tax and trade-in
+#[tauri::command]
+pub fn db_recalculate_deal_totals(
+    deal_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DealTotals, String> {
+    track("db_recalculate_deal_totals", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let mut conn = db.conn();
+        let totals = recalculate_totals(
+            &mut conn,
+            &user_id_value,
+            &deal_id,
+            Utc::now().timestamp_millis(),
+        )?;
+        info!(
+            "✅ Deal {} total recalculated: {} cents",
+            deal_id, totals.total_cents
+        );
+        Ok(totals)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Schema up to migration 19, so 020's doc_fee conversion can run against real rows
+    fn legacy_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/013_add_odometer_title.sql"),
+            include_str!("../migrations/019_add_lenders.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, created_at, updated_at, user_id)
+                 VALUES ('c1', 'Jane', 'Doe', 0, 0, 'u1');
+             INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status,
+                                   created_at, updated_at, user_id)
+                 VALUES ('v1', 'VIN1', 2021, 'Honda', 'Civic', 0, 20000, 'sold', 0, 0, 'u1');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn migrate(conn: &Connection) {
+        conn.execute_batch(include_str!("../migrations/020_add_deal_fees.sql"))
+            .unwrap();
+    }
+
+    fn deal(
+        conn: &Connection,
+        id: &str,
+        sale: Option<f64>,
+        tax: f64,
+        doc_fee: Option<f64>,
+        trade_in: f64,
+    ) {
+        conn.execute(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount, sale_amount,
+                                sales_tax, doc_fee, trade_in_value, created_at, updated_at, user_id)
+             VALUES (?1, 'retail', 'c1', 'v1', 'sold', 0, ?2, ?3, ?4, ?5, 7, 8, 'u1')",
+            params![id, sale, tax, doc_fee, trade_in],
+        )
+        .unwrap();
+    }
+
+    fn doc_fee_column(conn: &Connection, deal_id: &str) -> Option<f64> {
+        conn.query_row(
+            "SELECT doc_fee FROM deals WHERE id = ?1",
+            params![deal_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn fee(label: &str, cents: i64, taxable: bool, category: FeeCategory) -> NewDealFee {
+        NewDealFee {
+            label: label.to_string(),
+            amount_cents: cents,
+            taxable,
+            category,
+            sort_order: None,
+        }
+    }
+
+    #[test]
+    fn test_migration_converts_legacy_doc_fee() {
+        let conn = legacy_db();
+        deal(&conn, "d1", Some(20_000.0), 0.0, Some(199.99), 0.0);
+        deal(&conn, "d2", Some(15_000.0), 0.0, None, 0.0);
+        deal(&conn, "d3", Some(15_000.0), 0.0, Some(0.0), 0.0);
+        migrate(&conn);
+
+        let fees = list_fees(&conn, "d1").unwrap();
+        assert_eq!(fees.len(), 1);
+        assert_eq!(
+            (
+                fees[0].label.as_str(),
+                fees[0].amount_cents,
+                fees[0].taxable
+            ),
+            ("Documentation fee", 19_999, true)
+        );
+        assert_eq!(fees[0].category, FeeCategory::DocFee);
+        assert_eq!((fees[0].user_id.as_str(), fees[0].created_at), ("u1", 7));
+        assert!(list_fees(&conn, "d2").unwrap().is_empty());
+        assert!(list_fees(&conn, "d3").unwrap().is_empty());
+
+        // Running it again doesn't duplicate lines
+        migrate(&conn);
+        assert_eq!(list_fees(&conn, "d1").unwrap().len(), 1);
+
+        // Fee lines cascade with their deal
+        conn.execute("DELETE FROM deals WHERE id = 'd1'", [])
+            .unwrap();
+        assert!(list_fees(&conn, "d1").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recalculate_with_mixed_taxable_fees() {
+        let mut conn = legacy_db();
+        deal(&conn, "d1", Some(18_500.0), 1_202.5, Some(199.99), 4_000.0);
+        migrate(&conn);
+
+        create_fee(
+            &mut conn,
+            "u1",
+            "d1",
+            fee("Title", 3_300, false, FeeCategory::Title),
+            1,
+        )
+        .unwrap();
+        create_fee(
+            &mut conn,
+            "u1",
+            "d1",
+            fee("Temp tag", 500, false, FeeCategory::TempTag),
+            1,
+        )
+        .unwrap();
+        let gap = create_fee(
+            &mut conn,
+            "u1",
+            "d1",
+            fee("GAP", 59_500, true, FeeCategory::Gap),
+            1,
+        )
+        .unwrap();
+        assert_eq!(gap.sort_order, 3);
+
+        // Taxable flags only change the tax split, never the total
+        let fees = list_fees(&conn, "d1").unwrap();
+        assert_eq!(
+            taxable_fees(&fees),
+            TaxableFees {
+                doc_fee_cents: 19_999,
+                other_cents: 59_500
+            }
+        );
+
+        let totals = recalculate_totals(&mut conn, "u1", "d1", 9).unwrap();
+        assert_eq!(totals.fees_cents, 19_999 + 3_300 + 500 + 59_500);
+        assert_eq!(totals.total_cents, 1_850_000 + 83_299 + 120_250 - 400_000);
+        let total: f64 = conn
+            .query_row(
+                "SELECT total_amount FROM deals WHERE id = 'd1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(to_cents(total), totals.total_cents);
+
+        // Other users can't recalculate, and a deal without a sale amount is refused
+        assert!(recalculate_totals(&mut conn, "u2", "d1", 9).is_err());
+        deal(&conn, "d2", None, 0.0, None, 0.0);
+        assert!(recalculate_totals(&mut conn, "u1", "d2", 9).is_err());
+    }
+
+    #[test]
+    fn test_doc_fee_column_follows_doc_fee_lines() {
+        let mut conn = legacy_db();
+        deal(&conn, "d1", Some(10_000.0), 0.0, Some(150.0), 0.0);
+        migrate(&conn);
+
+        let extra = create_fee(
+            &mut conn,
+            "u1",
+            "d1",
+            fee("Electronic filing", 2_500, true, FeeCategory::DocFee),
+            1,
+        )
+        .unwrap();
+        assert_eq!(doc_fee_column(&conn, "d1"), Some(175.0));
+
+        update_fee(
+            &mut conn,
+            "u1",
+            &extra.id,
+            &serde_json::json!({ "category": "registration" }),
+            2,
+        )
+        .unwrap();
+        assert_eq!(doc_fee_column(&conn, "d1"), Some(150.0));
+
+        let legacy = list_fees(&conn, "d1").unwrap().remove(0);
+        delete_fee(&mut conn, "u1", &legacy.id).unwrap();
+        assert_eq!(doc_fee_column(&conn, "d1"), None);
+
+        replace_doc_fee(&conn, "d1", "u1", Some(89.5), 3).unwrap();
+        let doc_fees: Vec<i64> = list_fees(&conn, "d1")
+            .unwrap()
+            .iter()
+            .filter(|fee| fee.category == FeeCategory::DocFee)
+            .map(|fee| fee.amount_cents)
+            .collect();
+        assert_eq!(doc_fees, [8_950]);
+
+        assert!(update_fee(
+            &mut conn,
+            "u1",
+            &extra.id,
+            &serde_json::json!({ "amount_cents": -1 }),
+            4
+        )
+        .is_err());
+        assert!(delete_fee(&mut conn, "u2", &extra.id).is_err());
+    }
+}
@@ -0,0 +1,407 @@
+// src-tauri/src/deal_status.rs
+//
+// Deal status lifecycle
+// quote -> pending -> approved -> sold -> completed, with cancelled reachable from
+// every open status and a cancelled deal reopenable as a quote. db_update_deal only
+// lets a deal move along these edges; admin_override skips the check but the change is
+// still written to deal_status_history (marked overridden) and logged.
+// Deals saved before the lifecycle existed keep whatever status they had (migration 14
+// maps the old draft/in_progress values); they may move to any canonical status once.
+
+use chrono::Utc;
+use log::warn;
+use rusqlite::{params, Connection, Result as SqlResult, Row};
+use serde::Serialize;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::{get_db, get_deal_for_user};
+use crate::telemetry::track;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DealStatus {
+    Quote,
+    Pending,
+    Approved,
+    Sold,
+    Completed,
+    Cancelled,
+}
+
+impl DealStatus {
+    pub const ALL: [DealStatus; 6] = [
+        DealStatus::Quote,
+        DealStatus::Pending,
+        DealStatus::Approved,
+        DealStatus::Sold,
+        DealStatus::Completed,
+        DealStatus::Cancelled,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DealStatus::Quote => "quote",
+            DealStatus::Pending => "pending",
+            DealStatus::Approved => "approved",
+            DealStatus::Sold => "sold",
+            DealStatus::Completed => "completed",
+            DealStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// Canonical status, also accepting the names older builds wrote (draft, in_progress)
+    pub fn parse(status: &str) -> Option<DealStatus> {
+        match status.trim().to_lowercase().as_str() {
+            "quote" | "draft" => Some(DealStatus::Quote),
+            "pending" | "in_progress" => Some(DealStatus::Pending),
+            "approved" => Some(DealStatus::Approved),
+            "sold" => Some(DealStatus::Sold),
+            "completed" => Some(DealStatus::Completed),
+            "cancelled" | "canceled" => Some(DealStatus::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Statuses a deal in this status may move to
+    pub fn allowed_next(self) -> &'static [DealStatus] {
+        use DealStatus::*;
+        match self {
+            Quote => &[Pending, Cancelled],
+            Pending => &[Quote, Approved, Cancelled],
+            Approved => &[Pending, Sold, Cancelled],
+            Sold => &[Completed, Cancelled],
+            Completed => &[],
+            Cancelled => &[Quote],
+        }
+    }
+
+    pub fn can_transition_to(self, to: DealStatus) -> bool {
+        self == to || self.allowed_next().contains(&to)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DealStatusError {
+    UnknownStatus {
+        status: String,
+    },
+    InvalidTransition {
+        from: String,
+        to: String,
+        allowed: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for DealStatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DealStatusError::UnknownStatus { status } => {
+                let known: Vec<&str> = DealStatus::ALL.iter().map(|s| s.as_str()).collect();
+                write!(
+                    f,
+                    "Unknown deal status '{}' (expected one of: {})",
+                    status,
+                    known.join(", ")
+                )
+            }
+            DealStatusError::InvalidTransition { from, to, allowed } if allowed.is_empty() => {
+                write!(
+                    f,
+                    "A deal can't move from {} to {} ({} is final)",
+                    from, to, from
+                )
+            }
+            DealStatusError::InvalidTransition { from, to, allowed } => write!(
+                f,
+                "A deal can't move from {} to {} (allowed: {})",
+                from,
+                to,
+                allowed.join(", ")
+            ),
+        }
+    }
+}
+
+impl From<DealStatusError> for String {
+    fn from(error: DealStatusError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Canonical spelling of a status a caller asked for
+pub(crate) fn normalize_status(status: &str) -> Result<&'static str, DealStatusError> {
+    DealStatus::parse(status)
+        .map(DealStatus::as_str)
+        .ok_or_else(|| DealStatusError::UnknownStatus {
+            status: status.to_string(),
+        })
+}
+
+/// Checks moving a deal from `from` to `to` (already normalized)
+/// Returns true when the move was only allowed because of admin_override
+pub(crate) fn check_transition(
+    from: &str,
+    to: &str,
+    admin_override: bool,
+) -> Result<bool, DealStatusError> {
+    let to_status = DealStatus::parse(to).ok_or_else(|| DealStatusError::UnknownStatus {
+        status: to.to_string(),
+    })?;
+    let Some(from_status) = DealStatus::parse(from) else {
+        warn!(
+            "⚠️  Deal has legacy status '{}'; allowing the move to {}",
+            from, to
+        );
+        return Ok(false);
+    };
+    if from_status.can_transition_to(to_status) {
+        return Ok(false);
+    }
+    if admin_override {
+        return Ok(true);
+    }
+    Err(DealStatusError::InvalidTransition {
+        from: from_status.as_str().to_string(),
+        to: to_status.as_str().to_string(),
+        allowed: from_status
+            .allowed_next()
+            .iter()
+            .map(|s| s.as_str().to_string())
+            .collect(),
+    })
+}
+
+/// One row of deal_status_history
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusChange {
+    pub id: i64,
+    pub deal_id: String,
+    pub user_id: String,
+    /// None for the status the deal was created with
+    pub from_status: Option<String>,
+    pub to_status: String,
+    pub overridden: bool,
+    pub reason: Option<String>,
+    pub changed_at: i64,
+}
+
+impl StatusChange {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(StatusChange {
+            id: row.get("id")?,
+            deal_id: row.get("deal_id")?,
+            user_id: row.get("user_id")?,
+            from_status: row.get("from_status")?,
+            to_status: row.get("to_status")?,
+            overridden: row.get("overridden")?,
+            reason: row.get("reason")?,
+            changed_at: row.get("changed_at")?,
+        })
+    }
+}
+
+pub(crate) fn record_transition(
+    conn: &Connection,
+    deal_id: &str,
+    user_id: &str,
+    from: Option<&str>,
+    to: &str,
+    overridden: bool,
+    reason: Option<&str>,
+) -> SqlResult<()> {
+    conn.execute(
+        "INSERT INTO deal_status_history
+            (deal_id, user_id, from_status, to_status, overridden, reason, changed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            deal_id,
+            user_id,
+            from,
+            to,
+            overridden,
+            reason,
+            Utc::now().timestamp_millis()
+        ],
+    )?;
+    Ok(())
+}
+
+fn history_for(conn: &Connection, deal_id: &str) -> SqlResult<Vec<StatusChange>> {
+    let mut stmt = conn
+        .prepare("SELECT * FROM deal_status_history WHERE deal_id = ?1 ORDER BY changed_at, id")?;
+    let rows = stmt.query_map(params![deal_id], StatusChange::from_row)?;
+    rows.collect()
+}
+
+/// Every status the deal has had, oldest first
+#[tauri::command]
+pub fn db_get_deal_status_history(
+    deal_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<StatusChange>, String> {
+    track("db_get_deal_status_history", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        get_deal_for_user(db, &deal_id, &user_id_value)?
+            .ok_or_else(|| "Deal not found or access denied".to_string())?;
+
+        let conn = db.conn();
+        history_for(&conn, &deal_id).map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use DealStatus::*;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/013_add_odometer_title.sql"),
+            include_str!("../migrations/014_add_deal_status_history.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn
+    }
+
+    #[test]
+    fn test_transition_matrix() {
+        let allowed = [
+            (Quote, Pending),
+            (Quote, Cancelled),
+            (Pending, Quote),
+            (Pending, Approved),
+            (Pending, Cancelled),
+            (Approved, Pending),
+            (Approved, Sold),
+            (Approved, Cancelled),
+            (Sold, Completed),
+            (Sold, Cancelled),
+            (Cancelled, Quote),
+        ];
+        for from in DealStatus::ALL {
+            for to in DealStatus::ALL {
+                let expected = from == to || allowed.contains(&(from, to));
+                assert_eq!(
+                    check_transition(from.as_str(), to.as_str(), false).is_ok(),
+                    expected,
+                    "{:?} -> {:?}",
+                    from,
+                    to
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_transition_lists_allowed_statuses() {
+        let err = check_transition("quote", "completed", false).unwrap_err();
+        assert_eq!(
+            err,
+            DealStatusError::InvalidTransition {
+                from: "quote".to_string(),
+                to: "completed".to_string(),
+                allowed: vec!["pending".to_string(), "cancelled".to_string()],
+            }
+        );
+        assert_eq!(
+            String::from(err),
+            "A deal can't move from quote to completed (allowed: pending, cancelled)"
+        );
+
+        let err = check_transition("completed", "quote", false).unwrap_err();
+        assert!(err.to_string().contains("completed is final"), "{}", err);
+    }
+
+    #[test]
+    fn test_legacy_names_and_statuses() {
+        // Old spellings are the same status
+        assert_eq!(normalize_status("draft").unwrap(), "quote");
+        assert_eq!(normalize_status("In_Progress").unwrap(), "pending");
+        assert!(check_transition("draft", "pending", false).is_ok());
+        assert!(check_transition("draft", "sold", false).is_err());
+
+        // Unknown current statuses are grandfathered; unknown targets never are
+        assert_eq!(check_transition("on_hold", "sold", false), Ok(false));
+        assert!(matches!(
+            check_transition("quote", "on_hold", true),
+            Err(DealStatusError::UnknownStatus { .. })
+        ));
+    }
+
+    #[test]
+    fn test_admin_override_is_flagged() {
+        assert_eq!(check_transition("completed", "pending", true), Ok(true));
+        // Allowed moves don't count as overrides even with the flag set
+        assert_eq!(check_transition("quote", "pending", true), Ok(false));
+    }
+
+    #[test]
+    fn test_records_history_in_order() {
+        let conn = test_db();
+        conn.execute(
+            "INSERT INTO clients (id, first_name, last_name, created_at, updated_at)
+             VALUES ('c1', 'Ada', 'Lovelace', 1, 1)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status, created_at, updated_at)
+             VALUES ('v1', 'VIN1', 2020, 'Honda', 'Civic', 0, 1.0, 'available', 1, 1)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount,
+                document_ids, created_at, updated_at, user_id)
+             VALUES ('d1', 'retail', 'c1', 'v1', 'quote', 1.0, '[]', 1, 1, 'u1')",
+            [],
+        )
+        .unwrap();
+
+        record_transition(&conn, "d1", "u1", None, "quote", false, None).unwrap();
+        record_transition(&conn, "d1", "u1", Some("quote"), "pending", false, None).unwrap();
+        record_transition(
+            &conn,
+            "d1",
+            "u1",
+            Some("pending"),
+            "sold",
+            true,
+            Some("cash buyer"),
+        )
+        .unwrap();
+
+        let history = history_for(&conn, "d1").unwrap();
+        let steps: Vec<(Option<&str>, &str)> = history
+            .iter()
+            .map(|change| (change.from_status.as_deref(), change.to_status.as_str()))
+            .collect();
+        assert_eq!(
+            steps,
+            [
+                (None, "quote"),
+                (Some("quote"), "pending"),
+                (Some("pending"), "sold")
+            ]
+        );
+        assert!(history[2].overridden);
+        assert_eq!(history[2].reason.as_deref(), Some("cash buyer"));
+        assert!(history.iter().all(|change| change.user_id == "u1"));
+
+        // History goes with the deal
+        conn.execute("DELETE FROM deals WHERE id = 'd1'", [])
+            .unwrap();
+        assert!(history_for(&conn, "d1").unwrap().is_empty());
+    }
+}
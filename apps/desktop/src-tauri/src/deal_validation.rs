@@ -0,0 +1,310 @@
+// src-tauri/src/deal_validation.rs
+//
+// Paperwork checks before a deal can be marked completed
+// State forms need the odometer reading and disclosure, the title status, a buyer address
+// and, for financed deals, the lien holder. completion_issues lists everything missing or
+// inconsistent; db_update_deal refuses the move to "completed" while the list is non-empty
+// unless the caller passes "force": true.
+
+use serde::Serialize;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::{
+    db_get_client, db_get_vehicle, get_deal_for_user, Client, DbState, Deal, Vehicle,
+    DEAL_STATUS_COMPLETED, DEAL_STATUS_SOLD,
+};
+use crate::telemetry::track;
+
+/// What the seller certifies about the odometer reading
+pub const ODOMETER_DISCLOSURES: [&str; 3] = ["actual", "exceeds", "not_actual"];
+
+/// One thing standing between a deal and "completed"
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CompletionIssue {
+    /// Deal or client field to fix
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl CompletionIssue {
+    fn new(field: &'static str, message: impl Into<String>) -> Self {
+        CompletionIssue {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+/// Rejects values that can't go on a form at all (checked on every save, not just completion)
+pub(crate) fn validate_paperwork_fields(deal: &Deal) -> Result<(), String> {
+    if let Some(disclosure) = deal.odometer_disclosure.as_deref() {
+        if !ODOMETER_DISCLOSURES.contains(&disclosure) {
+            return Err(format!(
+                "Unknown odometer disclosure '{}' (expected one of: {})",
+                disclosure,
+                ODOMETER_DISCLOSURES.join(", ")
+            ));
+        }
+    }
+    if deal.odometer_at_sale.is_some_and(|odometer| odometer < 0) {
+        return Err("Odometer reading can't be negative".to_string());
+    }
+    Ok(())
+}
+
+fn is_blank(value: Option<&str>) -> bool {
+    value.is_none_or(|v| v.trim().is_empty())
+}
+
+/// Everything missing or inconsistent for completing the deal, in form order
+/// client/vehicle are None when the deal points at a record that no longer exists
+pub fn completion_issues(
+    deal: &Deal,
+    client: Option<&Client>,
+    vehicle: Option<&Vehicle>,
+) -> Vec<CompletionIssue> {
+    let mut issues = Vec::new();
+
+    match deal.odometer_at_sale {
+        None => issues.push(CompletionIssue::new(
+            "odometer_at_sale",
+            "Odometer reading at sale is missing",
+        )),
+        Some(odometer) => {
+            if let Some(vehicle) = vehicle.filter(|v| odometer < i64::from(v.mileage)) {
+                issues.push(CompletionIssue::new(
+                    "odometer_at_sale",
+                    format!(
+                        "Odometer reading {} is lower than the vehicle's recorded mileage {}",
+                        odometer, vehicle.mileage
+                    ),
+                ));
+            }
+        }
+    }
+    if is_blank(deal.odometer_disclosure.as_deref()) {
+        issues.push(CompletionIssue::new(
+            "odometer_disclosure",
+            "Odometer disclosure is missing",
+        ));
+    }
+    if is_blank(deal.title_status.as_deref()) {
+        issues.push(CompletionIssue::new(
+            "title_status",
+            "Title status is missing",
+        ));
+    }
+
+    let sold = deal.status == DEAL_STATUS_SOLD || deal.status == DEAL_STATUS_COMPLETED;
+    if sold && deal.sale_date.is_none() {
+        issues.push(CompletionIssue::new(
+            "sale_date",
+            "Deal is sold but has no sale date",
+        ));
+    }
+
+    match client {
+        None => issues.push(CompletionIssue::new("client_id", "Buyer not found")),
+        Some(client) => {
+            let address = [
+                client.address.as_deref(),
+                client.city.as_deref(),
+                client.state.as_deref(),
+                client.zip_code.as_deref(),
+            ];
+            if address.into_iter().any(is_blank) {
+                issues.push(CompletionIssue::new(
+                    "client.address",
+                    "Buyer address is incomplete (street, city, state and ZIP are required)",
+                ));
+            }
+        }
+    }
+    if vehicle.is_none() {
+        issues.push(CompletionIssue::new("vehicle_id", "Vehicle not found"));
+    }
+
+    let financed = deal.financed_amount.is_some_and(|amount| amount > 0.0);
+    if financed && is_blank(deal.lien_holder.as_deref()) {
+        issues.push(CompletionIssue::new(
+            "lien_holder",
+            "Deal is financed but has no lender (lien holder)",
+        ));
+    }
+
+    issues
+}
+
+/// Loads the deal's buyer and vehicle, then checks them. Takes the connection itself, so
+/// don't hold it while calling this
+pub(crate) fn completion_issues_for(
+    deal: &Deal,
+    user_id_value: &str,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<Vec<CompletionIssue>, String> {
+    let client = db_get_client(
+        deal.client_id.clone(),
+        Some(user_id_value.to_string()),
+        state.clone(),
+        db.clone(),
+    )?;
+    let vehicle = db_get_vehicle(
+        deal.vehicle_id.clone(),
+        Some(user_id_value.to_string()),
+        state,
+        db,
+    )?;
+    Ok(completion_issues(deal, client.as_ref(), vehicle.as_ref()))
+}
+
+/// What has to be fixed before the deal can be set to "completed" (empty = ready)
+/// Checks the deal as if it were being completed, whatever its status is now
+#[tauri::command]
+pub fn validate_deal_for_completion(
+    deal_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<Vec<CompletionIssue>, String> {
+    track("validate_deal_for_completion", || {
+        let user_id_value = state.require_user(user_id)?;
+        let mut deal = get_deal_for_user(
+            db.get().map_err(|e| e.to_string())?,
+            &deal_id,
+            &user_id_value,
+        )?
+        .ok_or_else(|| "Deal not found or access denied".to_string())?;
+        deal.status = DEAL_STATUS_COMPLETED.to_string();
+        completion_issues_for(&deal, &user_id_value, state, db)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn ready_deal() -> Deal {
+        serde_json::from_value(json!({
+            "id": "deal-1", "user_id": "user-1", "type": "retail", "client_id": "client-1",
+            "vehicle_id": "vehicle-1", "status": "completed", "total_amount": 20000.0,
+            "sale_date": 1_700_000_000_000i64, "sale_amount": 20000.0, "sales_tax": null,
+            "doc_fee": null, "trade_in_value": null, "down_payment": 5000.0,
+            "financed_amount": 15000.0, "document_ids": "[]", "cobuyer_data": null,
+            "created_at": 1, "updated_at": 1, "synced_at": null,
+            "odometer_at_sale": 12050, "odometer_disclosure": "actual",
+            "title_status": "clean", "title_state": "TX", "lien_holder": "First Bank"
+        }))
+        .unwrap()
+    }
+
+    fn client() -> Client {
+        serde_json::from_value(json!({
+            "id": "client-1", "user_id": "user-1", "first_name": "Ada", "last_name": "Lovelace",
+            "email": null, "phone": null, "address": "1 Main St", "city": "Austin",
+            "state": "TX", "zip_code": "78701", "drivers_license": null,
+            "created_at": 1, "updated_at": 1, "synced_at": null
+        }))
+        .unwrap()
+    }
+
+    fn vehicle() -> Vehicle {
+        serde_json::from_value(json!({
+            "id": "vehicle-1", "vin": "1HGCM82633A004352", "stock_number": null, "year": 2021,
+            "make": "Honda", "model": "Civic", "trim": null, "body": null, "doors": 4,
+            "transmission": null, "engine": null, "cylinders": null, "title_number": null,
+            "mileage": 12000, "color": null, "price": 20000.0, "cost": null,
+            "status": "sold", "description": null, "images": null,
+            "created_at": 1, "updated_at": 1, "synced_at": null
+        }))
+        .unwrap()
+    }
+
+    fn fields(deal: &Deal, client: &Client) -> Vec<&'static str> {
+        completion_issues(deal, Some(client), Some(&vehicle()))
+            .into_iter()
+            .map(|issue| issue.field)
+            .collect()
+    }
+
+    #[test]
+    fn test_complete_paperwork_has_no_issues() {
+        assert!(fields(&ready_deal(), &client()).is_empty());
+    }
+
+    #[test]
+    fn test_odometer_below_recorded_mileage() {
+        let mut deal = ready_deal();
+        deal.odometer_at_sale = Some(11_999);
+        let issues = completion_issues(&deal, Some(&client()), Some(&vehicle()));
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "odometer_at_sale");
+        assert!(issues[0].message.contains("12000"), "{}", issues[0].message);
+
+        // Equal to the recorded mileage is fine
+        deal.odometer_at_sale = Some(12_000);
+        assert!(fields(&deal, &client()).is_empty());
+    }
+
+    #[test]
+    fn test_missing_odometer_and_title() {
+        let mut deal = ready_deal();
+        deal.odometer_at_sale = None;
+        deal.odometer_disclosure = None;
+        deal.title_status = Some("  ".to_string());
+        assert_eq!(
+            fields(&deal, &client()),
+            ["odometer_at_sale", "odometer_disclosure", "title_status"]
+        );
+    }
+
+    #[test]
+    fn test_sold_without_sale_date() {
+        let mut deal = ready_deal();
+        deal.sale_date = None;
+        assert_eq!(fields(&deal, &client()), ["sale_date"]);
+
+        deal.status = DEAL_STATUS_SOLD.to_string();
+        assert_eq!(fields(&deal, &client()), ["sale_date"]);
+
+        // A deal still being worked doesn't need one yet
+        deal.status = "pending".to_string();
+        assert!(fields(&deal, &client()).is_empty());
+    }
+
+    #[test]
+    fn test_missing_buyer_address() {
+        let mut buyer = client();
+        buyer.zip_code = None;
+        assert_eq!(fields(&ready_deal(), &buyer), ["client.address"]);
+
+        let issues = completion_issues(&ready_deal(), None, Some(&vehicle()));
+        assert_eq!(issues[0].field, "client_id");
+    }
+
+    #[test]
+    fn test_financed_without_lender() {
+        let mut deal = ready_deal();
+        deal.lien_holder = None;
+        assert_eq!(fields(&deal, &client()), ["lien_holder"]);
+
+        // Cash deals don't have a lender
+        deal.financed_amount = Some(0.0);
+        assert!(fields(&deal, &client()).is_empty());
+    }
+
+    #[test]
+    fn test_rejects_unknown_disclosure_and_negative_odometer() {
+        let mut deal = ready_deal();
+        assert!(validate_paperwork_fields(&deal).is_ok());
+        deal.odometer_disclosure = Some("guess".to_string());
+        let err = validate_paperwork_fields(&deal).unwrap_err();
+        assert!(err.contains("not_actual"), "{}", err);
+
+        deal.odometer_disclosure = Some("exceeds".to_string());
+        deal.odometer_at_sale = Some(-1);
+        assert!(validate_paperwork_fields(&deal).is_err());
+    }
+}
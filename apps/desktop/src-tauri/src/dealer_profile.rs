@@ -0,0 +1,685 @@
+// src-tauri/src/dealer_profile.rs
+//
+// The dealership's own details (legal name, license, address, logo), one row per user
+// They fill the dealer.* placeholders in PDF forms (pdf_forms.rs) and the dealer_* columns
+// in inventory feeds. Fields can be filled in over time; get_profile_completeness lists the
+// required ones that are still empty so onboarding can keep asking.
+//
+// The logo is copied into the app data folder with a thumbnail next to it:
+//   {app_data}/dealer_profile/{user_id}/logo-{id}.{ext}
+//   {app_data}/dealer_profile/{user_id}/logo-{id}_thumb.jpg
+// A new file name per upload, so a PDF viewer or webview never shows a cached old logo.
+// Like vehicle photos, it's stored upright and without EXIF (image_import.rs).
+
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Row};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::contact_normalization::normalize_phone;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{get_db, new_row_id};
+use crate::deep_link::is_valid_email;
+use crate::document_templates::normalize_state;
+use crate::image_import::{import_image, reject_heic};
+use crate::storage::get_app_data_dir;
+use crate::telemetry::track;
+use crate::thumbnails::write_thumbnail;
+
+/// Subfolder of the app data folder holding dealer logos
+pub const PROFILE_DIR: &str = "dealer_profile";
+pub const MAX_LOGO_BYTES: u64 = 2 * 1024 * 1024;
+
+const LOGO_THUMB_DIMENSION: u32 = 256;
+const LOGO_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+
+/// Text fields update_dealer_profile accepts
+pub const PROFILE_FIELDS: &[&str] = &[
+    "legal_name",
+    "dba",
+    "license_number",
+    "address",
+    "city",
+    "state",
+    "zip_code",
+    "phone",
+    "email",
+];
+
+/// Fields a finished profile must have (dba, email and the logo are optional)
+pub const REQUIRED_FIELDS: &[&str] = &[
+    "legal_name",
+    "license_number",
+    "address",
+    "city",
+    "state",
+    "zip_code",
+    "phone",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DealerProfile {
+    pub user_id: String,
+    pub legal_name: Option<String>,
+    /// "Doing business as"; shown instead of the legal name where there's one
+    pub dba: Option<String>,
+    pub license_number: Option<String>,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub zip_code: Option<String>,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub logo_path: Option<String>,
+    pub logo_thumb_path: Option<String>,
+    pub updated_at: i64,
+}
+
+impl DealerProfile {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(Self {
+            user_id: row.get("user_id")?,
+            legal_name: row.get("legal_name")?,
+            dba: row.get("dba")?,
+            license_number: row.get("license_number")?,
+            address: row.get("address")?,
+            city: row.get("city")?,
+            state: row.get("state")?,
+            zip_code: row.get("zip_code")?,
+            phone: row.get("phone")?,
+            email: row.get("email")?,
+            logo_path: row.get("logo_path")?,
+            logo_thumb_path: row.get("logo_thumb_path")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+
+    fn field(&self, name: &str) -> Option<&Option<String>> {
+        match name {
+            "legal_name" => Some(&self.legal_name),
+            "dba" => Some(&self.dba),
+            "license_number" => Some(&self.license_number),
+            "address" => Some(&self.address),
+            "city" => Some(&self.city),
+            "state" => Some(&self.state),
+            "zip_code" => Some(&self.zip_code),
+            "phone" => Some(&self.phone),
+            "email" => Some(&self.email),
+            _ => None,
+        }
+    }
+
+    fn field_mut(&mut self, name: &str) -> Option<&mut Option<String>> {
+        match name {
+            "legal_name" => Some(&mut self.legal_name),
+            "dba" => Some(&mut self.dba),
+            "license_number" => Some(&mut self.license_number),
+            "address" => Some(&mut self.address),
+            "city" => Some(&mut self.city),
+            "state" => Some(&mut self.state),
+            "zip_code" => Some(&mut self.zip_code),
+            "phone" => Some(&mut self.phone),
+            "email" => Some(&mut self.email),
+            _ => None,
+        }
+    }
+
+    /// The name customers know: the DBA if there is one, else the legal name
+    pub fn display_name(&self) -> Option<&str> {
+        self.dba.as_deref().or(self.legal_name.as_deref())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProfileCompleteness {
+    pub complete: bool,
+    /// Required fields that are still empty, in REQUIRED_FIELDS order
+    pub missing: Vec<String>,
+    pub has_logo: bool,
+}
+
+pub(crate) fn load_profile(
+    conn: &Connection,
+    user_id: &str,
+) -> Result<Option<DealerProfile>, String> {
+    conn.query_row(
+        "SELECT * FROM dealer_profile WHERE user_id = ?1",
+        [user_id],
+        DealerProfile::from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Every saved profile, to fill the read cache at startup
+pub(crate) fn load_all_profiles(conn: &Connection) -> Result<Vec<DealerProfile>, String> {
+    let mut stmt = conn
+        .prepare("SELECT * FROM dealer_profile")
+        .map_err(|e| e.to_string())?;
+    let profiles = stmt
+        .query_map([], DealerProfile::from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(profiles)
+}
+
+/// The user's profile for document and feed sources; None until one is saved. Served
+/// from the read cache (read_cache.rs)
+pub(crate) fn get_dealer_profile_for_user(user_id: &str) -> Result<Option<DealerProfile>, String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    db.read_cache().dealer_profile(db, user_id)
+}
+
+fn label(field: &str) -> String {
+    let label = field.replace('_', " ");
+    let mut chars = label.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => label,
+    }
+}
+
+/// Trimmed and normalized value for one field; None clears it
+fn normalize_field(field: &str, value: Option<&str>) -> Result<Option<String>, String> {
+    let value = match value.map(str::trim).filter(|v| !v.is_empty()) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    match field {
+        "state" => normalize_state(Some(value.to_string())),
+        "zip_code" => {
+            let digits: String = value.chars().filter(char::is_ascii_digit).collect();
+            let shape_ok = value.chars().all(|c| c.is_ascii_digit() || c == '-');
+            match digits.len() {
+                5 if shape_ok => Ok(Some(digits)),
+                9 if shape_ok => Ok(Some(format!("{}-{}", &digits[..5], &digits[5..]))),
+                _ => Err(format!("Invalid ZIP code: {}", value)),
+            }
+        }
+        "phone" => normalize_phone(value)
+            .map(Some)
+            .ok_or_else(|| format!("Invalid phone number: {}", value)),
+        "email" if is_valid_email(value) => Ok(Some(value.to_string())),
+        "email" => Err(format!("Invalid email address: {}", value)),
+        _ => Ok(Some(value.to_string())),
+    }
+}
+
+/// Apply the text fields present in updates (null or "" clears one) and save
+/// A required field can't be cleared once it's set, and the profile needs a legal name.
+fn update_profile(
+    conn: &Connection,
+    user_id: &str,
+    updates: &Value,
+    now: i64,
+) -> Result<DealerProfile, String> {
+    let mut profile = load_profile(conn, user_id)?.unwrap_or_else(|| DealerProfile {
+        user_id: user_id.to_string(),
+        ..Default::default()
+    });
+
+    for field in PROFILE_FIELDS {
+        let Some(update) = updates.get(field) else {
+            continue;
+        };
+        let value = normalize_field(field, update.as_str())?;
+        let current = profile.field_mut(field).expect("PROFILE_FIELDS are fields");
+        if value.is_none() && current.is_some() && REQUIRED_FIELDS.contains(field) {
+            return Err(format!("{} is required", label(field)));
+        }
+        *current = value;
+    }
+    if profile.legal_name.is_none() {
+        return Err("Legal name is required".to_string());
+    }
+    profile.updated_at = now;
+
+    conn.execute(
+        "INSERT INTO dealer_profile (user_id, legal_name, dba, license_number, address, city,
+                                     state, zip_code, phone, email, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+         ON CONFLICT(user_id) DO UPDATE SET
+             legal_name = excluded.legal_name, dba = excluded.dba,
+             license_number = excluded.license_number, address = excluded.address,
+             city = excluded.city, state = excluded.state, zip_code = excluded.zip_code,
+             phone = excluded.phone, email = excluded.email, updated_at = excluded.updated_at",
+        params![
+            user_id,
+            profile.legal_name,
+            profile.dba,
+            profile.license_number,
+            profile.address,
+            profile.city,
+            profile.state,
+            profile.zip_code,
+            profile.phone,
+            profile.email,
+            profile.updated_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(profile)
+}
+
+fn completeness(profile: Option<&DealerProfile>) -> ProfileCompleteness {
+    let missing: Vec<String> = REQUIRED_FIELDS
+        .iter()
+        .filter(|field| {
+            profile
+                .and_then(|p| p.field(field))
+                .is_none_or(|value| value.is_none())
+        })
+        .map(|field| field.to_string())
+        .collect();
+    ProfileCompleteness {
+        complete: missing.is_empty(),
+        missing,
+        has_logo: profile.is_some_and(|p| p.logo_path.is_some()),
+    }
+}
+
+/// Folder for a user's logo; user ids come from the session, but never trust them as paths
+fn logo_dir(root: &Path, user_id: &str) -> PathBuf {
+    let name: String = user_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    root.join(PROFILE_DIR).join(name)
+}
+
+fn save_logo_paths(
+    conn: &Connection,
+    user_id: &str,
+    logo_path: Option<&str>,
+    logo_thumb_path: Option<&str>,
+    now: i64,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO dealer_profile (user_id, logo_path, logo_thumb_path, updated_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(user_id) DO UPDATE SET
+             logo_path = excluded.logo_path, logo_thumb_path = excluded.logo_thumb_path,
+             updated_at = excluded.updated_at",
+        params![user_id, logo_path, logo_thumb_path, now],
+    )
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+/// Delete the files of a replaced or removed logo (only ones we manage)
+fn remove_logo_files(dir: &Path, profile: Option<&DealerProfile>) {
+    let Some(profile) = profile else {
+        return;
+    };
+    for file in [&profile.logo_path, &profile.logo_thumb_path]
+        .into_iter()
+        .flatten()
+    {
+        let file = Path::new(file);
+        if file.starts_with(dir) {
+            if let Err(e) = fs::remove_file(file) {
+                warn!("⚠️  Could not delete old logo {}: {}", file.display(), e);
+            }
+        }
+    }
+}
+
+/// Copy source in as the user's logo, with a thumbnail, replacing any previous one
+fn set_logo(
+    conn: &Connection,
+    root: &Path,
+    user_id: &str,
+    source: &Path,
+    now: i64,
+) -> Result<DealerProfile, String> {
+    reject_heic(source)?;
+    let extension = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .filter(|e| LOGO_EXTENSIONS.contains(&e.as_str()))
+        .ok_or_else(|| {
+            format!(
+                "Unsupported logo type (expected one of: {})",
+                LOGO_EXTENSIONS.join(", ")
+            )
+        })?;
+    let size = fs::metadata(source)
+        .ok()
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .ok_or_else(|| format!("Logo not found: {}", source.display()))?;
+    if size > MAX_LOGO_BYTES {
+        return Err(format!(
+            "Logo is {:.1} MB; the limit is {} MB",
+            size as f64 / (1024.0 * 1024.0),
+            MAX_LOGO_BYTES / (1024 * 1024)
+        ));
+    }
+
+    let previous = load_profile(conn, user_id)?;
+    let dir = logo_dir(root, user_id);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let id = new_row_id();
+    let path = dir.join(format!("logo-{}.{}", id, extension));
+    let thumb_path = dir.join(format!("logo-{}_thumb.jpg", id));
+
+    let transformations = import_image(source, &path, None)?;
+    if !transformations.is_empty() {
+        info!("🖼️  Logo imported with {:?}", transformations);
+    }
+    // Also proves the file decodes; a renamed PDF shouldn't end up on every document
+    let saved = write_thumbnail(&path, LOGO_THUMB_DIMENSION, &thumb_path)
+        .map_err(|e| e.to_string())
+        .and_then(|_| {
+            save_logo_paths(
+                conn,
+                user_id,
+                Some(&path.to_string_lossy()),
+                Some(&thumb_path.to_string_lossy()),
+                now,
+            )
+        });
+    if let Err(e) = saved {
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&thumb_path);
+        return Err(e);
+    }
+
+    remove_logo_files(&dir, previous.as_ref());
+    load_profile(conn, user_id)?.ok_or_else(|| "Dealer profile not found".to_string())
+}
+
+fn remove_logo(
+    conn: &Connection,
+    root: &Path,
+    user_id: &str,
+    now: i64,
+) -> Result<Option<DealerProfile>, String> {
+    let previous = load_profile(conn, user_id)?;
+    if previous.as_ref().is_none_or(|p| p.logo_path.is_none()) {
+        return Ok(previous);
+    }
+    save_logo_paths(conn, user_id, None, None, now)?;
+    remove_logo_files(&logo_dir(root, user_id), previous.as_ref());
+    load_profile(conn, user_id)
+}
+
+/// The user's profile, or None if it hasn't been filled in yet
+#[tauri::command]
+pub fn get_dealer_profile(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<DealerProfile>, String> {
+    track("get_dealer_profile", || {
+        let user_id_value = state.require_user(user_id)?;
+        get_dealer_profile_for_user(&user_id_value)
+    })
+}
+
+/// Set any of the PROFILE_FIELDS; creates the profile on first save
+#[tauri::command]
+pub fn update_dealer_profile(
+    updates: Value,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DealerProfile, String> {
+    track("update_dealer_profile", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let profile = update_profile(
+            &conn,
+            &user_id_value,
+            &updates,
+            Utc::now().timestamp_millis(),
+        )?;
+        db.read_cache()
+            .store_dealer_profile(&user_id_value, Some(profile.clone()));
+        data_changed(
+            &user_id_value,
+            ChangedEntity::DealerProfile,
+            &user_id_value,
+            Operation::Update,
+        );
+        info!("✅ Dealer profile saved");
+        Ok(profile)
+    })
+}
+
+#[tauri::command]
+pub fn set_dealer_logo(
+    source_path: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DealerProfile, String> {
+    track("set_dealer_logo", || {
+        let user_id_value = state.require_user(user_id)?;
+        let root = get_app_data_dir()?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let profile = set_logo(
+            &conn,
+            &root,
+            &user_id_value,
+            Path::new(&source_path),
+            Utc::now().timestamp_millis(),
+        )?;
+        db.read_cache()
+            .store_dealer_profile(&user_id_value, Some(profile.clone()));
+        data_changed(
+            &user_id_value,
+            ChangedEntity::DealerProfile,
+            &user_id_value,
+            Operation::Update,
+        );
+        info!("✅ Dealer logo updated");
+        Ok(profile)
+    })
+}
+
+#[tauri::command]
+pub fn remove_dealer_logo(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<DealerProfile>, String> {
+    track("remove_dealer_logo", || {
+        let user_id_value = state.require_user(user_id)?;
+        let root = get_app_data_dir()?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let profile = remove_logo(&conn, &root, &user_id_value, Utc::now().timestamp_millis())?;
+        db.read_cache()
+            .store_dealer_profile(&user_id_value, profile.clone());
+        data_changed(
+            &user_id_value,
+            ChangedEntity::DealerProfile,
+            &user_id_value,
+            Operation::Update,
+        );
+        Ok(profile)
+    })
+}
+
+/// Which required fields are still empty, for the onboarding checklist
+#[tauri::command]
+pub fn get_profile_completeness(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ProfileCompleteness, String> {
+    track("get_profile_completeness", || {
+        let user_id_value = state.require_user(user_id)?;
+        let profile = get_dealer_profile_for_user(&user_id_value)?;
+        Ok(completeness(profile.as_ref()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use image::{Rgb, RgbImage};
+    use serde_json::json;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/022_add_dealer_profile.sql"))
+            .unwrap();
+        conn
+    }
+
+    fn logo(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join(name);
+        RgbImage::from_pixel(800, 300, Rgb([200, 30, 30]))
+            .save(&path)
+            .unwrap();
+        path
+    }
+
+    fn files_in(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_logo_is_copied_with_thumbnail_and_replaced() {
+        let conn = test_db();
+        let source_dir = temp_dir("profile", "logo-source");
+        let root = temp_dir("profile", "logo-root");
+        let dir = logo_dir(&root, "u1");
+
+        let profile = set_logo(&conn, &root, "u1", &logo(&source_dir, "logo.PNG"), 1).unwrap();
+        let path = PathBuf::from(profile.logo_path.clone().unwrap());
+        let thumb = PathBuf::from(profile.logo_thumb_path.clone().unwrap());
+        assert!(path.starts_with(&dir) && path.extension().unwrap() == "png");
+        let (width, height) = image::image_dimensions(&thumb).unwrap();
+        assert_eq!((width, height), (256, 96));
+        // Setting a logo first creates the profile without text fields
+        assert_eq!(profile.legal_name, None);
+
+        // A new logo replaces the old files
+        let profile = set_logo(&conn, &root, "u1", &logo(&source_dir, "new.jpg"), 2).unwrap();
+        assert!(!path.exists() && !thumb.exists());
+        assert_eq!(files_in(&dir).len(), 2);
+        assert!(profile.logo_path.unwrap().ends_with(".jpg"));
+
+        let profile = remove_logo(&conn, &root, "u1", 3).unwrap().unwrap();
+        assert_eq!((profile.logo_path, profile.logo_thumb_path), (None, None));
+        assert!(files_in(&dir).is_empty());
+
+        let _ = fs::remove_dir_all(&source_dir);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_bad_logos_are_rejected_without_leftovers() {
+        let conn = test_db();
+        let source_dir = temp_dir("profile", "bad-source");
+        let root = temp_dir("profile", "bad-root");
+        let dir = logo_dir(&root, "u1");
+        set_logo(&conn, &root, "u1", &logo(&source_dir, "ok.png"), 1).unwrap();
+        let kept = files_in(&dir);
+
+        let pdf = source_dir.join("logo.pdf");
+        fs::write(&pdf, b"%PDF-1.4").unwrap();
+        let err = set_logo(&conn, &root, "u1", &pdf, 2).unwrap_err();
+        assert!(err.contains("Unsupported logo type"), "{}", err);
+
+        // Right extension, not an image
+        let fake = source_dir.join("fake.png");
+        fs::write(&fake, b"not really a png").unwrap();
+        assert!(set_logo(&conn, &root, "u1", &fake, 2).is_err());
+
+        let huge = source_dir.join("huge.jpg");
+        fs::write(&huge, vec![0u8; MAX_LOGO_BYTES as usize + 1]).unwrap();
+        let err = set_logo(&conn, &root, "u1", &huge, 2).unwrap_err();
+        assert!(err.contains("limit is 2 MB"), "{}", err);
+
+        let missing = source_dir.join("missing.png");
+        assert!(set_logo(&conn, &root, "u1", &missing, 2)
+            .unwrap_err()
+            .contains("not found"));
+
+        // The previous logo is untouched
+        assert_eq!(files_in(&dir), kept);
+        let profile = load_profile(&conn, "u1").unwrap().unwrap();
+        assert_eq!(profile.updated_at, 1);
+
+        let _ = fs::remove_dir_all(&source_dir);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_update_validates_and_reports_completeness() {
+        let conn = test_db();
+        assert_eq!(completeness(None).missing.len(), REQUIRED_FIELDS.len());
+
+        let err = update_profile(&conn, "u1", &json!({"dba": "Main St Motors"}), 1).unwrap_err();
+        assert_eq!(err, "Legal name is required");
+
+        let profile = update_profile(
+            &conn,
+            "u1",
+            &json!({
+                "legal_name": " Main Street Auto LLC ", "dba": "Main St Motors",
+                "state": "tx", "zip_code": "787011234", "phone": "512.555.0100"
+            }),
+            1,
+        )
+        .unwrap();
+        assert_eq!(profile.legal_name.as_deref(), Some("Main Street Auto LLC"));
+        assert_eq!(profile.state.as_deref(), Some("TX"));
+        assert_eq!(profile.zip_code.as_deref(), Some("78701-1234"));
+        assert_eq!(profile.phone.as_deref(), Some("(512) 555-0100"));
+        assert_eq!(profile.display_name(), Some("Main St Motors"));
+
+        let report = completeness(Some(&profile));
+        assert!(!report.complete);
+        assert_eq!(report.missing, ["license_number", "address", "city"]);
+
+        for bad in [
+            json!({"state": "Texas"}),
+            json!({"zip_code": "7870"}),
+            json!({"phone": "555"}),
+            json!({"email": "sales-at-example.com"}),
+            json!({"legal_name": ""}),
+            json!({"phone": null}),
+        ] {
+            assert!(update_profile(&conn, "u1", &bad, 2).is_err(), "{}", bad);
+        }
+
+        // Optional fields can be cleared
+        let profile = update_profile(
+            &conn,
+            "u1",
+            &json!({
+                "dba": null, "license_number": "D-12345", "address": "1 Main St",
+                "city": "Austin", "email": "sales@mainstreet.example"
+            }),
+            3,
+        )
+        .unwrap();
+        assert_eq!(profile.display_name(), Some("Main Street Auto LLC"));
+        assert_eq!(load_profile(&conn, "u1").unwrap(), Some(profile.clone()));
+        assert!(completeness(Some(&profile)).complete);
+    }
+}
@@ -0,0 +1,391 @@
+// src-tauri/src/deep_link.rs
+//
+// dealer-sign:// deep links, parsed and validated before anything reaches the webview
+// SECURITY: Deep links come from any web page or app on the machine, so only the
+// known actions with well-formed parameters are forwarded, as structured events.
+//
+// Supported links:
+//   dealer-sign://sign?dealId=..&envelopeId=..&token=..
+//   dealer-sign://open?dealId=..&token=..
+//   dealer-sign://auth/callback?token=..&state=..
+//   dealer-sign://subscription-success?session_id=..&email=..
+//   dealer-sign://subscription-canceled
+
+use log::{error, info, warn};
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter};
+use url::Url;
+
+use crate::app_windows::focus_window;
+use crate::database::db_get_setting;
+
+pub const DEEP_LINK_SCHEME: &str = "dealer-sign";
+pub const DEEP_LINK_ACTION_EVENT: &str = "deep-link-action";
+
+/// Raw-string event the frontend listened to before deep-link-action (remove next release)
+pub const LEGACY_DEEP_LINK_EVENT: &str = "deep-link";
+/// Settings key: "false" stops emitting LEGACY_DEEP_LINK_EVENT
+pub const LEGACY_DEEP_LINK_SETTING: &str = "deep_link_legacy_event";
+
+const MAX_URL_LEN: usize = 2048;
+const MAX_ID_LEN: usize = 128;
+const MAX_TOKEN_LEN: usize = 1024;
+const MAX_EMAIL_LEN: usize = 254;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeepLinkAction {
+    SignRequest {
+        deal_id: String,
+        envelope_id: String,
+        token: String,
+    },
+    OpenDeal {
+        deal_id: String,
+        token: String,
+    },
+    AuthCallback {
+        token: String,
+        state: String,
+    },
+    SubscriptionSuccess {
+        session_id: Option<String>,
+        email: Option<String>,
+    },
+    SubscriptionCanceled,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeepLinkError {
+    TooLong { length: usize },
+    InvalidUrl { message: String },
+    WrongScheme { scheme: String },
+    UnknownAction { action: String },
+    MissingParam { name: &'static str },
+    InvalidParam { name: &'static str },
+    DuplicateParam { name: String },
+}
+
+impl std::fmt::Display for DeepLinkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeepLinkError::TooLong { length } => {
+                write!(f, "Deep link too long ({} > {} chars)", length, MAX_URL_LEN)
+            }
+            DeepLinkError::InvalidUrl { message } => write!(f, "Invalid deep link: {}", message),
+            DeepLinkError::WrongScheme { scheme } => write!(f, "Unexpected scheme: {}", scheme),
+            DeepLinkError::UnknownAction { action } => {
+                write!(f, "Unknown deep link action: {}", action)
+            }
+            DeepLinkError::MissingParam { name } => write!(f, "Missing parameter: {}", name),
+            DeepLinkError::InvalidParam { name } => write!(f, "Invalid parameter: {}", name),
+            DeepLinkError::DuplicateParam { name } => write!(f, "Duplicate parameter: {}", name),
+        }
+    }
+}
+
+/// Parse and validate every URL, emit the valid ones to every window, and bring one forward
+/// (the deal's own window for links to a deal, see app_windows.rs)
+/// Invalid links are logged and dropped; they never reach the frontend
+pub fn handle_deep_links(app: &AppHandle, urls: &[String]) {
+    let emit_legacy = legacy_event_enabled();
+    let mut emitted: Option<DeepLinkAction> = None;
+
+    for raw in urls {
+        let action = match parse_deep_link(raw) {
+            Ok(action) => action,
+            Err(e) => {
+                // The URL itself may carry tokens; log only the reason
+                warn!("⚠️  Ignoring deep link: {}", e);
+                continue;
+            }
+        };
+        info!("✅ Deep link action: {}", action.name());
+
+        match app.emit(DEEP_LINK_ACTION_EVENT, &action) {
+            Ok(_) => emitted = Some(action),
+            Err(e) => error!("❌ Emit failed: {}", e),
+        }
+        if emit_legacy {
+            if let Err(e) = app.emit(LEGACY_DEEP_LINK_EVENT, raw) {
+                error!("❌ Legacy emit failed: {}", e);
+            }
+        }
+    }
+
+    if let Some(action) = emitted {
+        if !focus_window(app, action.deal_id()) {
+            warn!("⚠️  No window open to show the deep link");
+        }
+    }
+}
+
+fn legacy_event_enabled() -> bool {
+    db_get_setting(LEGACY_DEEP_LINK_SETTING.to_string())
+        .ok()
+        .flatten()
+        .is_none_or(|value| value.trim() != "false")
+}
+
+pub fn parse_deep_link(raw: &str) -> Result<DeepLinkAction, DeepLinkError> {
+    if raw.len() > MAX_URL_LEN {
+        return Err(DeepLinkError::TooLong { length: raw.len() });
+    }
+
+    let url = Url::parse(raw).map_err(|e| DeepLinkError::InvalidUrl {
+        message: e.to_string(),
+    })?;
+    if url.scheme() != DEEP_LINK_SCHEME {
+        return Err(DeepLinkError::WrongScheme {
+            scheme: url.scheme().to_string(),
+        });
+    }
+
+    // dealer-sign://auth/callback parses as host "auth" + path "/callback"
+    let action = format!(
+        "{}{}",
+        url.host_str().unwrap_or_default(),
+        url.path().trim_end_matches('/')
+    );
+    let mut params = Params::from_url(&url)?;
+
+    match action.as_str() {
+        "sign" => Ok(DeepLinkAction::SignRequest {
+            deal_id: params.id("dealId")?,
+            envelope_id: params.id("envelopeId")?,
+            token: params.token("token")?,
+        }),
+        "open" => Ok(DeepLinkAction::OpenDeal {
+            deal_id: params.id("dealId")?,
+            token: params.token("token")?,
+        }),
+        "auth/callback" => Ok(DeepLinkAction::AuthCallback {
+            token: params.token("token")?,
+            state: params.token("state")?,
+        }),
+        "subscription-success" => Ok(DeepLinkAction::SubscriptionSuccess {
+            session_id: params.optional("session_id", is_valid_id)?,
+            email: params.optional("email", is_valid_email)?,
+        }),
+        "subscription-canceled" => Ok(DeepLinkAction::SubscriptionCanceled),
+        _ => Err(DeepLinkError::UnknownAction {
+            action: action.chars().take(MAX_ID_LEN).collect(),
+        }),
+    }
+}
+
+impl DeepLinkAction {
+    /// The deal the link is about, if any
+    fn deal_id(&self) -> Option<&str> {
+        match self {
+            DeepLinkAction::SignRequest { deal_id, .. }
+            | DeepLinkAction::OpenDeal { deal_id, .. } => Some(deal_id),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            DeepLinkAction::SignRequest { .. } => "sign_request",
+            DeepLinkAction::OpenDeal { .. } => "open_deal",
+            DeepLinkAction::AuthCallback { .. } => "auth_callback",
+            DeepLinkAction::SubscriptionSuccess { .. } => "subscription_success",
+            DeepLinkAction::SubscriptionCanceled => "subscription_canceled",
+        }
+    }
+}
+
+/// Decoded query parameters; a repeated name is rejected rather than guessing which wins
+struct Params(HashMap<String, String>);
+
+impl Params {
+    fn from_url(url: &Url) -> Result<Self, DeepLinkError> {
+        let mut params = HashMap::new();
+        for (name, value) in url.query_pairs() {
+            if params.insert(name.to_string(), value.to_string()).is_some() {
+                return Err(DeepLinkError::DuplicateParam {
+                    name: name.chars().take(MAX_ID_LEN).collect(),
+                });
+            }
+        }
+        Ok(Self(params))
+    }
+
+    fn required(
+        &mut self,
+        name: &'static str,
+        valid: fn(&str) -> bool,
+    ) -> Result<String, DeepLinkError> {
+        self.optional(name, valid)?
+            .ok_or(DeepLinkError::MissingParam { name })
+    }
+
+    fn optional(
+        &mut self,
+        name: &'static str,
+        valid: fn(&str) -> bool,
+    ) -> Result<Option<String>, DeepLinkError> {
+        match self.0.remove(name).filter(|value| !value.is_empty()) {
+            Some(value) if valid(&value) => Ok(Some(value)),
+            Some(_) => Err(DeepLinkError::InvalidParam { name }),
+            None => Ok(None),
+        }
+    }
+
+    fn id(&mut self, name: &'static str) -> Result<String, DeepLinkError> {
+        self.required(name, is_valid_id)
+    }
+
+    fn token(&mut self, name: &'static str) -> Result<String, DeepLinkError> {
+        self.required(name, is_valid_token)
+    }
+}
+
+/// Database / Convex ids: letters, digits, '-' and '_'
+fn is_valid_id(value: &str) -> bool {
+    value.len() <= MAX_ID_LEN
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// JWTs, base64 and base64url tokens
+fn is_valid_token(value: &str) -> bool {
+    value.len() <= MAX_TOKEN_LEN
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.~+/=".contains(c))
+}
+
+pub(crate) fn is_valid_email(value: &str) -> bool {
+    value.len() <= MAX_EMAIL_LEN
+        && value.split('@').count() == 2
+        && !value.starts_with('@')
+        && !value.ends_with('@')
+        && value
+            .chars()
+            .all(|c| c.is_ascii_graphic() && !"<>\"'`\\".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_request_and_open_deal() {
+        assert_eq!(
+            parse_deep_link(
+                "dealer-sign://sign?dealId=deal_123&envelopeId=env-9&token=abc.def.ghi"
+            ),
+            Ok(DeepLinkAction::SignRequest {
+                deal_id: "deal_123".to_string(),
+                envelope_id: "env-9".to_string(),
+                token: "abc.def.ghi".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_deep_link("dealer-sign://open?dealId=k57abc&token=eyJh%2Bbc%3D"),
+            Ok(DeepLinkAction::OpenDeal {
+                deal_id: "k57abc".to_string(),
+                token: "eyJh+bc=".to_string(),
+            })
+        );
+        // Trailing slash after the action is fine
+        assert!(parse_deep_link("dealer-sign://open/?dealId=k57abc&token=t").is_ok());
+    }
+
+    #[test]
+    fn test_auth_callback_and_subscription_links() {
+        assert_eq!(
+            parse_deep_link("dealer-sign://auth/callback?token=jwt.payload.sig&state=abc123"),
+            Ok(DeepLinkAction::AuthCallback {
+                token: "jwt.payload.sig".to_string(),
+                state: "abc123".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_deep_link(
+                "dealer-sign://subscription-success?session_id=cs_test_1&email=owner%40dealer.com"
+            ),
+            Ok(DeepLinkAction::SubscriptionSuccess {
+                session_id: Some("cs_test_1".to_string()),
+                email: Some("owner@dealer.com".to_string()),
+            })
+        );
+        assert_eq!(
+            parse_deep_link("dealer-sign://subscription-success"),
+            Ok(DeepLinkAction::SubscriptionSuccess {
+                session_id: None,
+                email: None,
+            })
+        );
+        assert_eq!(
+            parse_deep_link("dealer-sign://subscription-canceled"),
+            Ok(DeepLinkAction::SubscriptionCanceled)
+        );
+
+        let json = serde_json::to_value(DeepLinkAction::SubscriptionCanceled).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "action": "subscription_canceled" })
+        );
+    }
+
+    #[test]
+    fn test_malformed_links_rejected() {
+        assert!(matches!(
+            parse_deep_link("https://evil.example/open?dealId=1&token=t"),
+            Err(DeepLinkError::WrongScheme { .. })
+        ));
+        assert!(matches!(
+            parse_deep_link("dealer-sign://delete-everything?confirm=1"),
+            Err(DeepLinkError::UnknownAction { .. })
+        ));
+        assert!(matches!(
+            parse_deep_link("not a url"),
+            Err(DeepLinkError::InvalidUrl { .. })
+        ));
+        assert_eq!(
+            parse_deep_link("dealer-sign://open?dealId=abc"),
+            Err(DeepLinkError::MissingParam { name: "token" })
+        );
+        assert_eq!(
+            parse_deep_link("dealer-sign://open?dealId=&token=t"),
+            Err(DeepLinkError::MissingParam { name: "dealId" })
+        );
+        assert_eq!(
+            parse_deep_link("dealer-sign://open?dealId=../../etc&token=t"),
+            Err(DeepLinkError::InvalidParam { name: "dealId" })
+        );
+        assert_eq!(
+            parse_deep_link("dealer-sign://auth/callback?token=%3Cscript%3E&state=s"),
+            Err(DeepLinkError::InvalidParam { name: "token" })
+        );
+        assert_eq!(
+            parse_deep_link("dealer-sign://subscription-success?email=a%40b%40c"),
+            Err(DeepLinkError::InvalidParam { name: "email" })
+        );
+        assert!(matches!(
+            parse_deep_link("dealer-sign://open?dealId=a&dealId=b&token=t"),
+            Err(DeepLinkError::DuplicateParam { .. })
+        ));
+
+        let oversized = format!(
+            "dealer-sign://open?dealId=a&token={}",
+            "x".repeat(MAX_URL_LEN)
+        );
+        assert!(matches!(
+            parse_deep_link(&oversized),
+            Err(DeepLinkError::TooLong { .. })
+        ));
+        let long_id = format!(
+            "dealer-sign://open?dealId={}&token=t",
+            "a".repeat(MAX_ID_LEN + 1)
+        );
+        assert_eq!(
+            parse_deep_link(&long_id),
+            Err(DeepLinkError::InvalidParam { name: "dealId" })
+        );
+    }
+}
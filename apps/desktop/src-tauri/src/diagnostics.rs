@@ -0,0 +1,563 @@
+// src-tauri/src/diagnostics.rs
+//
+// One-click health report for support calls: database, keyring, documents folder,
+// AWS, license and sync, each with its own ok/warn/error status.
+// Checks run on their own threads with individual timeouts, so a hung probe (a stalled
+// network share, an unreachable S3 endpoint) shows up as a timed-out check instead of
+// stalling the whole report.
+
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::json;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::database::{get_db, Database};
+use crate::disk_space::{min_free_bytes, query_disk_space};
+use crate::license::{get_license_status, LicenseState};
+use crate::secret_store::{self, SecretKey, StorageBackend};
+use crate::storage::get_documents_storage_path;
+
+const DEFAULT_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+const AWS_PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Ok,
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub status: CheckStatus,
+    pub message: String,
+    pub details: serde_json::Value,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DiagnosticsReport {
+    pub generated_at: String, // RFC 3339
+    pub app_version: String,
+    pub platform: String,
+    /// Worst status of any check
+    pub overall: CheckStatus,
+    pub checks: Vec<CheckResult>,
+}
+
+/// What a check function returns; the runner adds the name and timing
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct CheckOutcome {
+    status: CheckStatus,
+    message: String,
+    details: serde_json::Value,
+}
+
+impl CheckOutcome {
+    fn new(status: CheckStatus, message: impl Into<String>, details: serde_json::Value) -> Self {
+        Self {
+            status,
+            message: message.into(),
+            details,
+        }
+    }
+
+    fn error(message: impl Into<String>) -> Self {
+        Self::new(CheckStatus::Error, message, serde_json::Value::Null)
+    }
+}
+
+type CheckFn = Box<dyn FnOnce() -> CheckOutcome + Send>;
+
+pub(crate) struct Check {
+    name: &'static str,
+    timeout: Duration,
+    run: CheckFn,
+}
+
+impl Check {
+    fn new(
+        name: &'static str,
+        timeout: Duration,
+        run: impl FnOnce() -> CheckOutcome + Send + 'static,
+    ) -> Self {
+        Self {
+            name,
+            timeout,
+            run: Box::new(run),
+        }
+    }
+}
+
+/// Run every health check and return the report
+/// probe_aws: also make a request to the S3 bucket (slow when offline)
+#[tauri::command]
+pub async fn run_diagnostics(probe_aws: Option<bool>) -> Result<DiagnosticsReport, String> {
+    let probe_aws = probe_aws.unwrap_or(false);
+    tauri::async_runtime::spawn_blocking(move || build_report(default_checks(probe_aws)))
+        .await
+        .map_err(|e| format!("Diagnostics failed: {}", e))
+}
+
+/// Run the diagnostics and save the report as JSON to `path`
+#[tauri::command]
+pub async fn export_diagnostics(
+    path: String,
+    probe_aws: Option<bool>,
+) -> Result<DiagnosticsReport, String> {
+    let report = run_diagnostics(probe_aws).await?;
+    let json = serde_json::to_string_pretty(&report)
+        .map_err(|e| format!("Failed to serialize diagnostics: {}", e))?;
+    fs::write(&path, json).map_err(|e| format!("Failed to write diagnostics: {}", e))?;
+
+    info!("📋 Diagnostics exported");
+    Ok(report)
+}
+
+fn default_checks(probe_aws: bool) -> Vec<Check> {
+    vec![
+        Check::new("database", DEFAULT_CHECK_TIMEOUT, check_database),
+        Check::new("keyring", DEFAULT_CHECK_TIMEOUT, check_keyring),
+        Check::new(
+            "documents_root",
+            DEFAULT_CHECK_TIMEOUT,
+            check_documents_root,
+        ),
+        Check::new(
+            "aws",
+            if probe_aws {
+                AWS_PROBE_TIMEOUT
+            } else {
+                DEFAULT_CHECK_TIMEOUT
+            },
+            move || check_aws(probe_aws),
+        ),
+        Check::new("license", DEFAULT_CHECK_TIMEOUT, check_license),
+        Check::new("sync", DEFAULT_CHECK_TIMEOUT, check_last_sync),
+    ]
+}
+
+fn build_report(checks: Vec<Check>) -> DiagnosticsReport {
+    let checks = run_checks(checks);
+    let overall = checks
+        .iter()
+        .map(|check| check.status)
+        .max()
+        .unwrap_or(CheckStatus::Ok);
+
+    DiagnosticsReport {
+        generated_at: chrono::Utc::now().to_rfc3339(),
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        overall,
+        checks,
+    }
+}
+
+/// Run all checks concurrently, in input order in the result
+/// A check that overruns its timeout is reported as an error and its thread left to finish
+fn run_checks(checks: Vec<Check>) -> Vec<CheckResult> {
+    let started = Instant::now();
+    let (sender, receiver) = mpsc::channel();
+    let mut pending: Vec<(&'static str, Instant)> = Vec::with_capacity(checks.len());
+    let mut results: Vec<Option<CheckResult>> = Vec::with_capacity(checks.len());
+
+    for (index, check) in checks.into_iter().enumerate() {
+        pending.push((check.name, started + check.timeout));
+        results.push(None);
+
+        let sender = sender.clone();
+        let run = check.run;
+        let spawned = thread::Builder::new()
+            .name(format!("diagnostics-{}", check.name))
+            .spawn(move || {
+                let check_started = Instant::now();
+                let outcome = panic::catch_unwind(AssertUnwindSafe(run))
+                    .unwrap_or_else(|_| CheckOutcome::error("Check panicked"));
+                let _ = sender.send((index, outcome, check_started.elapsed()));
+            });
+        if let Err(e) = spawned {
+            results[index] = Some(result(
+                check.name,
+                CheckOutcome::error(format!("Failed to start check: {}", e)),
+                Duration::ZERO,
+            ));
+        }
+    }
+    drop(sender);
+
+    loop {
+        let now = Instant::now();
+        for (index, (name, deadline)) in pending.iter().enumerate() {
+            if results[index].is_none() && *deadline <= now {
+                warn!("⚠️  [DIAGNOSTICS] {} check timed out", name);
+                results[index] = Some(result(
+                    name,
+                    CheckOutcome::error(format!(
+                        "Timed out after {}s",
+                        deadline.duration_since(started).as_secs_f32()
+                    )),
+                    now.duration_since(started),
+                ));
+            }
+        }
+
+        let next_deadline = pending
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| results[*index].is_none())
+            .map(|(_, (_, deadline))| *deadline)
+            .min();
+        let next_deadline = match next_deadline {
+            Some(deadline) => deadline,
+            None => break,
+        };
+
+        match receiver.recv_timeout(next_deadline.saturating_duration_since(now)) {
+            Ok((index, outcome, elapsed)) => {
+                if results[index].is_none() {
+                    results[index] = Some(result(pending[index].0, outcome, elapsed));
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // Every thread has exited, so nothing else is coming
+            Err(RecvTimeoutError::Disconnected) => {
+                for (index, (name, _)) in pending.iter().enumerate() {
+                    if results[index].is_none() {
+                        results[index] = Some(result(
+                            name,
+                            CheckOutcome::error("Check stopped without a result"),
+                            started.elapsed(),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    results.into_iter().flatten().collect()
+}
+
+fn result(name: &str, outcome: CheckOutcome, elapsed: Duration) -> CheckResult {
+    CheckResult {
+        name: name.to_string(),
+        status: outcome.status,
+        message: outcome.message,
+        details: outcome.details,
+        duration_ms: elapsed.as_millis() as u64,
+    }
+}
+
+fn check_database() -> CheckOutcome {
+    let path = match Database::get_db_path() {
+        Ok(path) => path,
+        Err(e) => return CheckOutcome::error(format!("Database path unavailable: {}", e)),
+    };
+    let size_bytes = file_size(&path) + file_size(&wal_path(&path));
+
+    let db = match get_db() {
+        Ok(db) => db,
+        Err(e) => return CheckOutcome::error(format!("Failed to open database: {}", e)),
+    };
+    let conn = match db.try_conn() {
+        Ok(conn) => conn,
+        Err(e) => return CheckOutcome::error(e),
+    };
+
+    let quick_check: Result<String, _> = conn.query_row("PRAGMA quick_check", [], |row| row.get(0));
+    let migration_version: Option<i64> = conn
+        .query_row("SELECT MAX(version) FROM schema_migrations", [], |row| {
+            row.get(0)
+        })
+        .ok()
+        .flatten();
+
+    let details = json!({
+        "path": path.to_string_lossy(),
+        "size_bytes": size_bytes,
+        "migration_version": migration_version,
+        "quick_check": quick_check.as_ref().ok(),
+    });
+    match quick_check {
+        Ok(result) if result == "ok" => CheckOutcome::new(CheckStatus::Ok, "Database OK", details),
+        Ok(result) => CheckOutcome::new(
+            CheckStatus::Error,
+            format!("Integrity check failed: {}", result),
+            details,
+        ),
+        Err(e) => CheckOutcome::new(
+            CheckStatus::Error,
+            format!("Integrity check failed to run: {}", e),
+            details,
+        ),
+    }
+}
+
+fn check_keyring() -> CheckOutcome {
+    let storage = secret_store::check_secure_storage_available();
+    let details = json!({
+        "backend": storage.backend,
+        "keyring_available": storage.keyring_available,
+    });
+
+    match storage.backend {
+        StorageBackend::Keyring if storage.keyring_available => {
+            CheckOutcome::new(CheckStatus::Ok, "OS keyring available", details)
+        }
+        _ => CheckOutcome::new(
+            CheckStatus::Warn,
+            storage
+                .message
+                .unwrap_or_else(|| "Secrets are not stored in the OS keyring".to_string()),
+            details,
+        ),
+    }
+}
+
+fn check_documents_root() -> CheckOutcome {
+    let (path, configured) = match secret_store::get(SecretKey::DocumentsRootPath) {
+        Ok(Some(path)) => (path, true),
+        Ok(None) => match get_documents_storage_path() {
+            Ok(path) => (path, false),
+            Err(e) => return CheckOutcome::error(e),
+        },
+        Err(e) => return CheckOutcome::error(format!("Failed to read documents root: {}", e)),
+    };
+    documents_root_outcome(Path::new(&path), configured, min_free_bytes())
+}
+
+fn documents_root_outcome(path: &Path, configured: bool, min_free: u64) -> CheckOutcome {
+    let exists = path.is_dir();
+    let writable = exists && is_writable(path);
+    let space = query_disk_space(path).ok();
+
+    let details = json!({
+        "path": path.to_string_lossy(),
+        "configured": configured,
+        "exists": exists,
+        "writable": writable,
+        "available_bytes": space.map(|space| space.available_bytes),
+        "total_bytes": space.map(|space| space.total_bytes),
+    });
+
+    if !exists {
+        return CheckOutcome::new(CheckStatus::Error, "Documents folder is missing", details);
+    }
+    if !writable {
+        return CheckOutcome::new(
+            CheckStatus::Error,
+            "Documents folder is not writable",
+            details,
+        );
+    }
+    match space {
+        Some(space) if space.available_bytes < min_free => CheckOutcome::new(
+            CheckStatus::Warn,
+            "Documents folder is low on disk space",
+            details,
+        ),
+        Some(_) => CheckOutcome::new(CheckStatus::Ok, "Documents folder OK", details),
+        None => CheckOutcome::new(CheckStatus::Warn, "Couldn't read free disk space", details),
+    }
+}
+
+fn check_aws(probe: bool) -> CheckOutcome {
+    let keys = [
+        SecretKey::AwsAccessKeyId,
+        SecretKey::AwsSecretAccessKey,
+        SecretKey::AwsRegion,
+        SecretKey::AwsBucketName,
+    ];
+    let mut missing = Vec::new();
+    for key in keys {
+        match secret_store::get(key) {
+            Ok(Some(_)) => {}
+            Ok(None) => missing.push(key),
+            Err(e) => return CheckOutcome::error(format!("Failed to read AWS settings: {}", e)),
+        }
+    }
+
+    let configured = missing.is_empty();
+    if !configured {
+        return CheckOutcome::new(
+            CheckStatus::Warn,
+            "AWS is not configured; documents won't sync to S3",
+            json!({ "configured": false, "missing": missing }),
+        );
+    }
+    if !probe {
+        return CheckOutcome::new(
+            CheckStatus::Ok,
+            "AWS configured",
+            json!({ "configured": true, "probed": false }),
+        );
+    }
+
+    match tauri::async_runtime::block_on(crate::s3_service::probe_bucket()) {
+        Ok(()) => CheckOutcome::new(
+            CheckStatus::Ok,
+            "S3 bucket reachable",
+            json!({ "configured": true, "probed": true }),
+        ),
+        Err(e) => CheckOutcome::new(
+            CheckStatus::Error,
+            e,
+            json!({ "configured": true, "probed": true }),
+        ),
+    }
+}
+
+fn check_license() -> CheckOutcome {
+    let status = get_license_status();
+    let details = json!({
+        "state": status.state,
+        "grace_expires_at": status.grace_expires_at,
+        "trial": status.trial,
+    });
+
+    match status.state {
+        LicenseState::Valid => CheckOutcome::new(CheckStatus::Ok, "License valid", details),
+        LicenseState::GracePeriod => CheckOutcome::new(
+            CheckStatus::Warn,
+            "License in grace period; activation heartbeat failing",
+            details,
+        ),
+        LicenseState::Missing => CheckOutcome::new(CheckStatus::Warn, "No license", details),
+        LicenseState::Expired => CheckOutcome::new(CheckStatus::Error, "License expired", details),
+        LicenseState::Invalid => CheckOutcome::new(CheckStatus::Error, "License invalid", details),
+    }
+}
+
+fn check_last_sync() -> CheckOutcome {
+    let db = match get_db() {
+        Ok(db) => db,
+        Err(e) => return CheckOutcome::error(format!("Failed to open database: {}", e)),
+    };
+    let conn = match db.try_conn() {
+        Ok(conn) => conn,
+        Err(e) => return CheckOutcome::error(e),
+    };
+
+    let last_sync: Option<i64> = match conn.query_row(
+        "SELECT MAX(synced_at) FROM sync_log WHERE success = 1",
+        [],
+        |row| row.get(0),
+    ) {
+        Ok(last_sync) => last_sync,
+        Err(e) => return CheckOutcome::error(format!("Failed to read sync log: {}", e)),
+    };
+
+    match last_sync {
+        Some(last_sync) => CheckOutcome::new(
+            CheckStatus::Ok,
+            "Last sync recorded",
+            json!({ "last_sync_at": last_sync }),
+        ),
+        None => CheckOutcome::new(
+            CheckStatus::Warn,
+            "No successful sync recorded",
+            json!({ "last_sync_at": null }),
+        ),
+    }
+}
+
+fn wal_path(db_path: &Path) -> PathBuf {
+    let mut name = db_path.as_os_str().to_os_string();
+    name.push("-wal");
+    PathBuf::from(name)
+}
+
+fn file_size(path: &Path) -> u64 {
+    fs::metadata(path)
+        .map(|metadata| metadata.len())
+        .unwrap_or(0)
+}
+
+fn is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".diagnostics-write-test-{}", std::process::id()));
+    let written = fs::write(&probe, b"ok").is_ok();
+    let _ = fs::remove_file(&probe);
+    written
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn outcome(status: CheckStatus) -> CheckOutcome {
+        CheckOutcome::new(status, format!("{:?}", status), serde_json::Value::Null)
+    }
+
+    #[test]
+    fn test_report_aggregates_worst_status_in_order() {
+        let report = build_report(vec![
+            Check::new("a", Duration::from_secs(5), || outcome(CheckStatus::Ok)),
+            Check::new("b", Duration::from_secs(5), || outcome(CheckStatus::Warn)),
+            Check::new("c", Duration::from_secs(5), || outcome(CheckStatus::Ok)),
+        ]);
+        let names: Vec<&str> = report.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["a", "b", "c"]);
+        assert_eq!(report.overall, CheckStatus::Warn);
+
+        let report = build_report(vec![
+            Check::new("ok", Duration::from_secs(5), || outcome(CheckStatus::Ok)),
+            Check::new("panics", Duration::from_secs(5), || panic!("probe bug")),
+        ]);
+        assert_eq!(report.overall, CheckStatus::Error);
+        assert_eq!(report.checks[1].message, "Check panicked");
+
+        assert_eq!(build_report(Vec::new()).overall, CheckStatus::Ok);
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["checks"][0]["status"], "ok");
+    }
+
+    #[test]
+    fn test_hung_check_times_out_without_stalling_others() {
+        let started = Instant::now();
+        let results = run_checks(vec![
+            Check::new("hung", Duration::from_millis(200), || {
+                thread::sleep(Duration::from_secs(10));
+                outcome(CheckStatus::Ok)
+            }),
+            Check::new("slow", Duration::from_secs(5), || {
+                thread::sleep(Duration::from_millis(300));
+                outcome(CheckStatus::Ok)
+            }),
+            Check::new("fast", Duration::from_secs(5), || outcome(CheckStatus::Ok)),
+        ]);
+
+        // Concurrent: bounded by the slowest finishing check, not the sum or the hung one
+        assert!(started.elapsed() < Duration::from_secs(3));
+        assert_eq!(results[0].status, CheckStatus::Error);
+        assert!(results[0].message.starts_with("Timed out"));
+        assert_eq!(results[1].status, CheckStatus::Ok);
+        assert!(results[1].duration_ms >= 300);
+        assert_eq!(results[2].status, CheckStatus::Ok);
+    }
+
+    #[test]
+    fn test_documents_root_outcome() {
+        let dir = temp_dir("diagnostics", "documents-root");
+
+        let ok = documents_root_outcome(&dir, true, 0);
+        assert_eq!(ok.status, CheckStatus::Ok);
+        assert_eq!(ok.details["writable"], true);
+
+        let low = documents_root_outcome(&dir, true, u64::MAX);
+        assert_eq!(low.status, CheckStatus::Warn);
+
+        let missing = documents_root_outcome(&dir.join("missing"), false, 0);
+        assert_eq!(missing.status, CheckStatus::Error);
+        assert_eq!(missing.details["exists"], false);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,255 @@
+// src-tauri/src/disk_space.rs
+//
+// Free disk space checks for the volumes we write to
+// Refuses large writes on a nearly full disk instead of leaving half-written PDFs
+
+use log::{info, warn};
+use serde::Serialize;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::database::db_get_setting;
+
+/// Settings key holding the low-space threshold in megabytes
+pub const MIN_FREE_SPACE_SETTING: &str = "min_free_space_mb";
+
+/// Default low-space threshold (200 MB)
+pub const DEFAULT_MIN_FREE_BYTES: u64 = 200 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DiskSpace {
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+}
+
+/// Reaches JS as a { kind, ... } object; src/lib/disk-space.ts turns it back into an Error
+#[derive(Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DiskSpaceError {
+    InsufficientSpace {
+        path: String,
+        available_bytes: u64,
+        required_bytes: u64,
+    },
+    QuotaExceeded {
+        user_id: String,
+        used_bytes: u64,
+        quota_bytes: u64,
+        incoming_bytes: u64,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl std::fmt::Display for DiskSpaceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DiskSpaceError::InsufficientSpace {
+                path,
+                available_bytes,
+                required_bytes,
+            } => write!(
+                f,
+                "Not enough disk space at {}: {} bytes available, {} bytes required",
+                path, available_bytes, required_bytes
+            ),
+            DiskSpaceError::QuotaExceeded {
+                user_id,
+                used_bytes,
+                quota_bytes,
+                incoming_bytes,
+            } => write!(
+                f,
+                "Storage quota exceeded for {}: {} of {} bytes used, {} bytes requested",
+                user_id, used_bytes, quota_bytes, incoming_bytes
+            ),
+            DiskSpaceError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for DiskSpaceError {
+    fn from(message: String) -> Self {
+        DiskSpaceError::Other { message }
+    }
+}
+
+/// Get total/available bytes for the volume containing the given path
+#[tauri::command]
+pub fn get_disk_space(path: String) -> Result<DiskSpace, String> {
+    info!("💽 Checking disk space for: {}", path);
+
+    query_disk_space(Path::new(&path)).map_err(|e| format!("Failed to read disk space: {}", e))
+}
+
+/// Refuse to start a write when the volume would drop below the configured threshold
+pub fn ensure_free_space(path: &Path, incoming_bytes: u64) -> Result<(), DiskSpaceError> {
+    let space = match query_disk_space(path) {
+        Ok(space) => space,
+        Err(e) => {
+            // Never block a write because the platform query itself failed
+            warn!("⚠️  Could not read disk space for {:?}: {}", path, e);
+            return Ok(());
+        }
+    };
+
+    check_free_space(path, space, incoming_bytes, min_free_bytes())
+}
+
+/// Configured low-space threshold, falling back to the default
+pub fn min_free_bytes() -> u64 {
+    db_get_setting(MIN_FREE_SPACE_SETTING.to_string())
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .map(|mb| mb.saturating_mul(1024 * 1024))
+        .unwrap_or(DEFAULT_MIN_FREE_BYTES)
+}
+
+fn check_free_space(
+    path: &Path,
+    space: DiskSpace,
+    incoming_bytes: u64,
+    threshold_bytes: u64,
+) -> Result<(), DiskSpaceError> {
+    let required_bytes = threshold_bytes.saturating_add(incoming_bytes);
+
+    if space.available_bytes < required_bytes {
+        warn!(
+            "⚠️  Low disk space at {:?}: {} available, {} required",
+            path, space.available_bytes, required_bytes
+        );
+        return Err(DiskSpaceError::InsufficientSpace {
+            path: path.to_string_lossy().to_string(),
+            available_bytes: space.available_bytes,
+            required_bytes,
+        });
+    }
+
+    Ok(())
+}
+
+/// Walk up to the nearest existing ancestor so checks work for files not yet created
+fn existing_ancestor(path: &Path) -> PathBuf {
+    let mut current = path;
+    loop {
+        if current.exists() {
+            return current.to_path_buf();
+        }
+        match current.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => current = parent,
+            _ => return PathBuf::from("."),
+        }
+    }
+}
+
+pub fn query_disk_space(path: &Path) -> io::Result<DiskSpace> {
+    platform_disk_space(&existing_ancestor(path))
+}
+
+#[cfg(unix)]
+fn platform_disk_space(path: &Path) -> io::Result<DiskSpace> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = CString::new(path.as_os_str().as_bytes())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let mut stats: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stats) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let fragment_size = stats.f_frsize as u64;
+    Ok(DiskSpace {
+        total_bytes: (stats.f_blocks as u64).saturating_mul(fragment_size),
+        available_bytes: (stats.f_bavail as u64).saturating_mul(fragment_size),
+    })
+}
+
+#[cfg(windows)]
+fn platform_disk_space(path: &Path) -> io::Result<DiskSpace> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+
+    let mut available_bytes = 0u64;
+    let mut total_bytes = 0u64;
+    let mut total_free_bytes = 0u64;
+
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut available_bytes,
+            &mut total_bytes,
+            &mut total_free_bytes,
+        )
+    };
+    if ok == 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(DiskSpace {
+        total_bytes,
+        available_bytes,
+    })
+}
+
+#[cfg(not(any(unix, windows)))]
+fn platform_disk_space(_path: &Path) -> io::Result<DiskSpace> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "Disk space query not supported on this platform",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MB: u64 = 1024 * 1024;
+
+    fn space(available_bytes: u64) -> DiskSpace {
+        DiskSpace {
+            total_bytes: 1024 * MB,
+            available_bytes,
+        }
+    }
+
+    #[test]
+    fn test_threshold_includes_incoming_bytes() {
+        let path = Path::new("/docs/deal.pdf");
+
+        assert!(check_free_space(path, space(500 * MB), 10 * MB, 200 * MB).is_ok());
+        assert!(check_free_space(path, space(210 * MB), 10 * MB, 200 * MB).is_ok());
+
+        assert_eq!(
+            check_free_space(path, space(205 * MB), 10 * MB, 200 * MB),
+            Err(DiskSpaceError::InsufficientSpace {
+                path: "/docs/deal.pdf".to_string(),
+                available_bytes: 205 * MB,
+                required_bytes: 210 * MB,
+            })
+        );
+        assert!(check_free_space(path, space(0), 0, 0).is_ok());
+        assert!(check_free_space(path, space(u64::MAX - 1), u64::MAX, 200 * MB).is_err());
+    }
+
+    #[test]
+    fn test_query_resolves_missing_paths_to_existing_volume() {
+        let missing = std::env::temp_dir()
+            .join("dealer-disk-space-missing")
+            .join("nested")
+            .join("file.pdf");
+
+        let space = query_disk_space(&missing).unwrap();
+        assert!(space.total_bytes > 0);
+        assert!(space.available_bytes <= space.total_bytes);
+    }
+}
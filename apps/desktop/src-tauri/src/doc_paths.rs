@@ -0,0 +1,626 @@
+// src-tauri/src/doc_paths.rs
+//
+// Where a deal's documents live, so the frontend stops composing paths by hand:
+//   {documents_root}/{dealer}/{year}/{deal number}/{doc type} - {file name}
+// dealer is the dealer profile's display name (the user id until a profile is saved), year
+// the sale date's (the creation date's until the deal is sold) and the deal number the id's
+// first 8 characters. Every segment goes through sanitize_segment, which makes names that
+// are valid on Windows too, and every path is checked to stay under the documents root,
+// also once symlinks are resolved. ensure_document_path numbers a name that's taken
+// ("Contract (2).pdf"). When the dealer name or year changes, rename_deal_folder moves the
+// deal's old folders to the current one and rewrites documents.file_path to match.
+
+use chrono::{Datelike, Local, TimeZone, Utc};
+use log::{info, warn};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_export::documents_root;
+use crate::database::{deal_for_user, DbState, Deal};
+use crate::dealer_profile::load_profile;
+use crate::storage::invalidate_storage_stats;
+use crate::telemetry::track;
+
+/// Characters Windows doesn't allow in file names
+const INVALID_CHARS: &str = "/\\:*?\"<>|";
+
+/// Names Windows reserves for devices, with or without an extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Longest segment kept, in characters; long template names would otherwise push paths
+/// past Windows' 260-character limit
+const MAX_SEGMENT_CHARS: usize = 120;
+
+const DEAL_NUMBER_CHARS: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FolderMove {
+    /// The deal's folder now
+    pub folder: String,
+    /// Folders whose contents were moved into it
+    pub moved_from: Vec<String>,
+    pub documents_updated: usize,
+}
+
+/// name as one path segment: Windows-invalid and control characters become '_', trailing
+/// dots and spaces are dropped and reserved device names get a '_' in front
+pub(crate) fn sanitize_segment(name: &str) -> String {
+    let replaced: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if INVALID_CHARS.contains(c) || c.is_control() {
+                '_'
+            } else {
+                c
+            }
+        })
+        .take(MAX_SEGMENT_CHARS)
+        .collect();
+    let trimmed = replaced.trim_end_matches(['.', ' ']);
+    let stem = trimmed.split('.').next().unwrap_or_default();
+    if trimmed.is_empty() {
+        "_".to_string()
+    } else if RESERVED_NAMES
+        .iter()
+        .any(|r| r.eq_ignore_ascii_case(stem.trim_end()))
+    {
+        format!("_{}", trimmed)
+    } else {
+        trimmed.to_string()
+    }
+}
+
+fn deal_number(deal_id: &str) -> String {
+    deal_id.chars().take(DEAL_NUMBER_CHARS).collect()
+}
+
+fn deal_year(deal: &Deal) -> i32 {
+    let at = deal.sale_date.unwrap_or(deal.created_at);
+    Local
+        .timestamp_millis_opt(at)
+        .single()
+        .map_or(1970, |date| date.year())
+}
+
+fn dealer_segment(conn: &Connection, user_id: &str) -> Result<String, String> {
+    let profile = load_profile(conn, user_id)?;
+    let name = profile
+        .as_ref()
+        .and_then(|profile| profile.display_name())
+        .unwrap_or(user_id);
+    Ok(sanitize_segment(name))
+}
+
+/// Refuse paths that could leave root: only plain segments below it
+fn guard(root: &Path, path: &Path) -> Result<(), String> {
+    let inside = path
+        .strip_prefix(root)
+        .is_ok_and(|rest| rest.components().all(|c| matches!(c, Component::Normal(_))));
+    if inside {
+        Ok(())
+    } else {
+        Err(format!("{} is outside the documents root", path.display()))
+    }
+}
+
+/// guard, for a path that exists, once symlinks are resolved
+fn guard_resolved(root: &Path, path: &Path) -> Result<(), String> {
+    let root = root.canonicalize().map_err(|e| e.to_string())?;
+    let resolved = path.canonicalize().map_err(|e| e.to_string())?;
+    if resolved.starts_with(&root) {
+        Ok(())
+    } else {
+        Err(format!("{} is outside the documents root", path.display()))
+    }
+}
+
+/// The deal's folder, without creating it
+fn deal_folder(
+    conn: &Connection,
+    root: &Path,
+    user_id: &str,
+    deal_id: &str,
+) -> Result<PathBuf, String> {
+    let deal = deal_for_user(conn, deal_id, user_id)?
+        .ok_or_else(|| "Deal not found or access denied".to_string())?;
+    let folder = root
+        .join(dealer_segment(conn, user_id)?)
+        .join(deal_year(&deal).to_string())
+        .join(sanitize_segment(&deal_number(&deal.id)));
+    guard(root, &folder)?;
+    Ok(folder)
+}
+
+/// The deal's folder, created if needed
+pub(crate) fn create_deal_folder(
+    conn: &Connection,
+    root: &Path,
+    user_id: &str,
+    deal_id: &str,
+) -> Result<PathBuf, String> {
+    let folder = deal_folder(conn, root, user_id, deal_id)?;
+    fs::create_dir_all(&folder)
+        .map_err(|e| format!("Failed to create {}: {}", folder.display(), e))?;
+    guard_resolved(root, &folder)?;
+    Ok(folder)
+}
+
+/// "{doc type} - {file name}", leaving out the type when the name already starts with it
+fn document_file_name(doc_type: &str, filename: &str) -> String {
+    let filename = sanitize_segment(filename);
+    let comparable = |s: &str| s.to_lowercase().replace(['_', '-'], " ");
+    if doc_type.trim().is_empty() || comparable(&filename).starts_with(&comparable(doc_type.trim()))
+    {
+        filename
+    } else {
+        sanitize_segment(&format!("{} - {}", doc_type, filename))
+    }
+}
+
+/// dir/name, or "stem (2).ext", "stem (3).ext"... if that's taken
+fn unused_path(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    if !path.exists() {
+        return path;
+    }
+    let (stem, extension) = match name.rsplit_once('.') {
+        Some((stem, extension)) if !stem.is_empty() => (stem, format!(".{}", extension)),
+        _ => (name, String::new()),
+    };
+    (2..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, extension)))
+        .find(|path| !path.exists())
+        .expect("some number is free")
+}
+
+/// A path in the deal's folder for a new document that no file has yet
+pub(crate) fn document_path(
+    conn: &Connection,
+    root: &Path,
+    user_id: &str,
+    deal_id: &str,
+    doc_type: &str,
+    filename: &str,
+) -> Result<PathBuf, String> {
+    let folder = create_deal_folder(conn, root, user_id, deal_id)?;
+    let path = unused_path(&folder, &document_file_name(doc_type, filename));
+    guard(root, &path)?;
+    Ok(path)
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+                .map(|entry| entry.path())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// {root}/*/*/{deal number} folders other than the current one
+fn old_deal_folders(root: &Path, current: &Path) -> Vec<PathBuf> {
+    let Some(number) = current.file_name() else {
+        return Vec::new();
+    };
+    let mut folders: Vec<PathBuf> = subdirs(root)
+        .into_iter()
+        .flat_map(|dealer| subdirs(&dealer))
+        .map(|year| year.join(number))
+        .filter(|folder| folder.is_dir() && folder != current)
+        .collect();
+    folders.sort();
+    folders
+}
+
+/// Move from's contents into to, file by file when to already exists; returns the moves
+/// made, for rewriting paths (or undoing them)
+fn merge_folder(from: &Path, to: &Path) -> Result<Vec<(PathBuf, PathBuf)>, String> {
+    if !to.exists() {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::rename(from, to).map_err(|e| format!("Failed to move {}: {}", from.display(), e))?;
+        return Ok(vec![(from.to_path_buf(), to.to_path_buf())]);
+    }
+    let mut moves = Vec::new();
+    let entries = fs::read_dir(from).map_err(|e| e.to_string())?;
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let target = unused_path(to, &name);
+        if let Err(e) = fs::rename(entry.path(), &target) {
+            undo(&moves);
+            return Err(format!("Failed to move {}: {}", entry.path().display(), e));
+        }
+        moves.push((entry.path(), target));
+    }
+    let _ = fs::remove_dir(from);
+    Ok(moves)
+}
+
+fn undo(moves: &[(PathBuf, PathBuf)]) {
+    for (from, to) in moves.iter().rev() {
+        if let Some(parent) = from.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        if let Err(e) = fs::rename(to, from) {
+            warn!("⚠️  Could not move {} back: {}", to.display(), e);
+        }
+    }
+}
+
+/// Where a moved path ended up, if one of the moves covers it
+fn moved_path(path: &Path, moves: &[(PathBuf, PathBuf)]) -> Option<PathBuf> {
+    moves.iter().find_map(|(from, to)| {
+        path.strip_prefix(from).ok().map(|rest| {
+            if rest.as_os_str().is_empty() {
+                to.clone()
+            } else {
+                to.join(rest)
+            }
+        })
+    })
+}
+
+/// Move the deal's old folders into its current one and point its documents at the new
+/// paths. The files go back if the database can't be updated
+pub(crate) fn move_deal_folder(
+    conn: &Connection,
+    root: &Path,
+    user_id: &str,
+    deal_id: &str,
+) -> Result<FolderMove, String> {
+    let folder = deal_folder(conn, root, user_id, deal_id)?;
+    let old_folders = old_deal_folders(root, &folder);
+    let mut moves = Vec::new();
+    for old in &old_folders {
+        let merged = guard_resolved(root, old).and_then(|_| merge_folder(old, &folder));
+        match merged {
+            Ok(merged) => moves.extend(merged),
+            Err(e) => {
+                undo(&moves);
+                return Err(e);
+            }
+        }
+    }
+    for old in &old_folders {
+        // The emptied year and dealer folders, if nothing else is in them
+        for empty in old.ancestors().skip(1).take(2) {
+            let _ = fs::remove_dir(empty);
+        }
+    }
+
+    let documents_updated = match rewrite_document_paths(conn, deal_id, &moves) {
+        Ok(updated) => updated,
+        Err(e) => {
+            undo(&moves);
+            return Err(e);
+        }
+    };
+    if !moves.is_empty() {
+        invalidate_storage_stats();
+    }
+    Ok(FolderMove {
+        folder: folder.to_string_lossy().into_owned(),
+        moved_from: old_folders
+            .iter()
+            .map(|old| old.to_string_lossy().into_owned())
+            .collect(),
+        documents_updated,
+    })
+}
+
+fn rewrite_document_paths(
+    conn: &Connection,
+    deal_id: &str,
+    moves: &[(PathBuf, PathBuf)],
+) -> Result<usize, String> {
+    if moves.is_empty() {
+        return Ok(0);
+    }
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    let documents: Vec<(String, String)> = {
+        let mut stmt = tx
+            .prepare("SELECT id, file_path FROM documents WHERE deal_id = ?1")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([deal_id], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())?
+    };
+    let now = Utc::now().timestamp_millis();
+    let mut updated = 0;
+    for (id, file_path) in documents {
+        let Some(moved) = moved_path(Path::new(&file_path), moves) else {
+            continue;
+        };
+        let filename = moved
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        tx.execute(
+            "UPDATE documents SET file_path = ?1, filename = ?2, updated_at = ?3 WHERE id = ?4",
+            params![moved.to_string_lossy(), filename, now, id],
+        )
+        .map_err(|e| e.to_string())?;
+        updated += 1;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+/// The deal's folder under the documents root, created if needed
+#[tauri::command]
+pub fn get_deal_folder(
+    deal_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<String, String> {
+    track("get_deal_folder", || {
+        let user_id_value = state.require_user(user_id)?;
+        let root = PathBuf::from(documents_root()?);
+        let db = db.get().map_err(|e| e.to_string())?;
+        let folder = create_deal_folder(&db.conn(), &root, &user_id_value, &deal_id)?;
+        Ok(folder.to_string_lossy().into_owned())
+    })
+}
+
+/// Where to save a new document of the deal: a free name in its folder
+#[tauri::command]
+pub fn ensure_document_path(
+    deal_id: String,
+    doc_type: String,
+    filename: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<String, String> {
+    track("ensure_document_path", || {
+        let user_id_value = state.require_user(user_id)?;
+        let root = PathBuf::from(documents_root()?);
+        let db = db.get().map_err(|e| e.to_string())?;
+        let path = document_path(
+            &db.conn(),
+            &root,
+            &user_id_value,
+            &deal_id,
+            &doc_type,
+            &filename,
+        )?;
+        Ok(path.to_string_lossy().into_owned())
+    })
+}
+
+/// Move the deal's documents to its current folder (after the dealer name or sale year
+/// changed)
+#[tauri::command]
+pub fn rename_deal_folder(
+    deal_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<FolderMove, String> {
+    track("rename_deal_folder", || {
+        let user_id_value = state.require_user(user_id)?;
+        let root = PathBuf::from(documents_root()?);
+        let db = db.get().map_err(|e| e.to_string())?;
+        let moved = move_deal_folder(&db.conn(), &root, &user_id_value, &deal_id)?;
+        if !moved.moved_from.is_empty() {
+            info!(
+                "✅ Deal {} folder moved to {} ({} documents updated)",
+                deal_id, moved.folder, moved.documents_updated
+            );
+        }
+        Ok(moved)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{db_create_client, db_create_deal, db_create_vehicle};
+    use crate::test_support::{make_client, make_deal, make_vehicle, temp_dir, TestApp, TEST_USER};
+
+    // 2024-06-15 and 2025-06-15, midday UTC: the same year in any time zone
+    const SOLD_2024: i64 = 1_718_452_800_000;
+    const SOLD_2025: i64 = 1_749_988_800_000;
+    const DEAL: &str = "3f2a9c1e-0000-4000-8000-000000000001";
+
+    fn app_with_deal(sale_date: i64) -> TestApp {
+        let app = TestApp::new();
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        let mut deal = make_deal(DEAL, "c1", "v1");
+        deal.sale_date = Some(sale_date);
+        db_create_deal(deal, None, None, app.state(), app.db()).unwrap();
+        app
+    }
+
+    fn set_dealer_name(app: &TestApp, name: &str) {
+        app.conn()
+            .execute(
+                "INSERT INTO dealer_profile (user_id, legal_name, updated_at) VALUES (?1, ?2, 1)
+                 ON CONFLICT(user_id) DO UPDATE SET legal_name = excluded.legal_name",
+                [TEST_USER, name],
+            )
+            .unwrap();
+    }
+
+    fn add_document(app: &TestApp, id: &str, path: &Path) {
+        fs::write(path, id).unwrap();
+        app.conn()
+            .execute(
+                "INSERT INTO documents (id, deal_id, type, filename, file_path, created_at,
+                     updated_at)
+                 VALUES (?1, ?2, 'contract', ?3, ?4, 1, 1)",
+                params![
+                    id,
+                    DEAL,
+                    path.file_name().unwrap().to_string_lossy(),
+                    path.to_string_lossy()
+                ],
+            )
+            .unwrap();
+    }
+
+    fn file_path_of(app: &TestApp, id: &str) -> PathBuf {
+        let path: String = app
+            .conn()
+            .query_row(
+                "SELECT file_path FROM documents WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        PathBuf::from(path)
+    }
+
+    #[test]
+    fn test_segments_are_valid_windows_names() {
+        assert_eq!(
+            sanitize_segment("Contract: A/B \"final\"?"),
+            "Contract_ A_B _final__"
+        );
+        assert_eq!(sanitize_segment("back\\slash*<>|"), "back_slash____");
+        assert_eq!(sanitize_segment("  notes.  "), "notes");
+        assert_eq!(sanitize_segment("tab\there"), "tab_here");
+        assert_eq!(sanitize_segment("con"), "_con");
+        assert_eq!(sanitize_segment("LPT1.pdf"), "_LPT1.pdf");
+        assert_eq!(sanitize_segment("Console.pdf"), "Console.pdf");
+        assert_eq!(sanitize_segment(".."), "_");
+        assert_eq!(sanitize_segment(""), "_");
+        assert_eq!(sanitize_segment(&"x".repeat(300)).len(), MAX_SEGMENT_CHARS);
+
+        assert_eq!(
+            document_file_name("bill_of_sale", "Bill of Sale.pdf"),
+            "Bill of Sale.pdf"
+        );
+        assert_eq!(
+            document_file_name("odometer", "Smith/Jones.pdf"),
+            "odometer - Smith_Jones.pdf"
+        );
+    }
+
+    #[test]
+    fn test_folder_follows_dealer_year_and_deal_number() {
+        let root = temp_dir("doc-paths", "layout");
+        let app = app_with_deal(SOLD_2024);
+        let conn = app.conn();
+
+        let folder = create_deal_folder(&conn, &root, TEST_USER, DEAL).unwrap();
+        assert_eq!(folder, root.join(TEST_USER).join("2024").join("3f2a9c1e"));
+        assert!(folder.is_dir());
+        drop(conn);
+
+        set_dealer_name(&app, "Main St. Motors: Used Cars");
+        let folder = create_deal_folder(&app.conn(), &root, TEST_USER, DEAL).unwrap();
+        assert_eq!(
+            folder,
+            root.join("Main St. Motors_ Used Cars")
+                .join("2024")
+                .join("3f2a9c1e")
+        );
+
+        // Another user's deal doesn't get a folder
+        let err = create_deal_folder(&app.conn(), &root, "someone-else", DEAL).unwrap_err();
+        assert!(err.contains("not found"), "{}", err);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_taken_names_get_numbered() {
+        let root = temp_dir("doc-paths", "collisions");
+        let app = app_with_deal(SOLD_2024);
+        let conn = app.conn();
+
+        let first = document_path(&conn, &root, TEST_USER, DEAL, "contract", "Deal?.pdf").unwrap();
+        assert_eq!(first.file_name().unwrap(), "contract - Deal_.pdf");
+        fs::write(&first, b"1").unwrap();
+        let second = document_path(&conn, &root, TEST_USER, DEAL, "contract", "Deal?.pdf").unwrap();
+        assert_eq!(second.file_name().unwrap(), "contract - Deal_ (2).pdf");
+        fs::write(&second, b"2").unwrap();
+        let third = document_path(&conn, &root, TEST_USER, DEAL, "contract", "Deal?.pdf").unwrap();
+        assert_eq!(third.file_name().unwrap(), "contract - Deal_ (3).pdf");
+        assert_eq!(first.parent(), third.parent());
+
+        let folder = first.parent().unwrap();
+        fs::write(folder.join("README"), b"").unwrap();
+        assert_eq!(unused_path(folder, "README"), folder.join("README (2)"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_rename_moves_files_and_rewrites_paths() {
+        let root = temp_dir("doc-paths", "rename");
+        let app = app_with_deal(SOLD_2024);
+        let old = create_deal_folder(&app.conn(), &root, TEST_USER, DEAL).unwrap();
+        add_document(&app, "doc-1", &old.join("contract - Deal.pdf"));
+        add_document(&app, "doc-2", &old.join("odometer - Deal.pdf"));
+
+        // Nothing to move while the folder is current
+        let unchanged = move_deal_folder(&app.conn(), &root, TEST_USER, DEAL).unwrap();
+        assert!(unchanged.moved_from.is_empty());
+        assert_eq!(unchanged.documents_updated, 0);
+
+        set_dealer_name(&app, "Main St Motors");
+        app.conn()
+            .execute(
+                "UPDATE deals SET sale_date = ?1 WHERE id = ?2",
+                params![SOLD_2025, DEAL],
+            )
+            .unwrap();
+        // A document already saved in the new folder under the same name keeps it
+        let new = create_deal_folder(&app.conn(), &root, TEST_USER, DEAL).unwrap();
+        assert_eq!(
+            new,
+            root.join("Main St Motors").join("2025").join("3f2a9c1e")
+        );
+        fs::write(new.join("contract - Deal.pdf"), b"newer").unwrap();
+
+        let moved = move_deal_folder(&app.conn(), &root, TEST_USER, DEAL).unwrap();
+        assert_eq!(moved.moved_from, [old.to_string_lossy()]);
+        assert_eq!(moved.documents_updated, 2);
+        assert_eq!(
+            file_path_of(&app, "doc-1"),
+            new.join("contract - Deal (2).pdf")
+        );
+        assert_eq!(file_path_of(&app, "doc-2"), new.join("odometer - Deal.pdf"));
+        assert_eq!(
+            fs::read(new.join("contract - Deal (2).pdf")).unwrap(),
+            b"doc-1"
+        );
+        assert_eq!(fs::read(new.join("contract - Deal.pdf")).unwrap(), b"newer");
+        // The emptied year and dealer folders are gone
+        assert!(!root.join(TEST_USER).exists());
+
+        // A folder that isn't there yet is moved whole
+        app.conn()
+            .execute(
+                "UPDATE deals SET sale_date = ?1 WHERE id = ?2",
+                params![SOLD_2024, DEAL],
+            )
+            .unwrap();
+        let moved = move_deal_folder(&app.conn(), &root, TEST_USER, DEAL).unwrap();
+        let back = root.join("Main St Motors").join("2024").join("3f2a9c1e");
+        assert_eq!(moved.folder, back.to_string_lossy());
+        assert_eq!(moved.documents_updated, 2);
+        assert_eq!(
+            file_path_of(&app, "doc-2"),
+            back.join("odometer - Deal.pdf")
+        );
+        assert!(back.join("contract - Deal.pdf").exists());
+        assert!(!new.exists());
+        let _ = fs::remove_dir_all(&root);
+    }
+}
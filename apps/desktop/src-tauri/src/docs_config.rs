@@ -2,39 +2,20 @@
 // SECURITY: Specific commands for documents root path storage only
 // Prevents JS from accessing arbitrary secrets via generic commands
 
-use keyring::Entry;
-use log::{error, info};
-use std::sync::Mutex;
+use log::{debug, error, info};
 
-const SERVICE_NAME: &str = "net.universalautobrokers.dealersoftware";
-const DOCS_ROOT_KEY: &str = "documents_root_path";
-
-static KEYRING_LOCK: Mutex<()> = Mutex::new(());
+use crate::logging::redact;
+use crate::secret_store::{self, SecretKey};
 
 /// Store documents root path securely in OS keyring
 /// SECURITY: This command only works for documents root path - no arbitrary keys allowed
 #[tauri::command]
 pub async fn store_documents_root_path(path: String) -> Result<(), String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🔐 [DOCS-CONFIG] Storing documents root path in secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, DOCS_ROOT_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    // Delete existing entry (ignore errors)
-    match entry.delete_credential() {
-        Ok(_) => info!("   Deleted existing entry"),
-        Err(keyring::Error::NoEntry) => info!("   No existing entry to delete"),
-        Err(e) => info!("   Delete error (non-critical): {}", e),
-    }
-
-    std::thread::sleep(std::time::Duration::from_millis(50));
-
-    // Store new value
-    match entry.set_password(&path) {
+    match secret_store::store(SecretKey::DocumentsRootPath, &path) {
         Ok(_) => {
-            info!("✅ [DOCS-CONFIG] Documents root path stored successfully: {}", path);
+            info!("✅ [DOCS-CONFIG] Documents root path stored successfully: {}", redact(&path));
             Ok(())
         }
         Err(e) => {
@@ -48,19 +29,14 @@ pub async fn store_documents_root_path(path: String) -> Result<(), String> {
 /// SECURITY: This command only works for documents root path - no arbitrary keys allowed
 #[tauri::command]
 pub async fn get_documents_root_path() -> Result<Option<String>, String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🔍 [DOCS-CONFIG] Retrieving documents root path from secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, DOCS_ROOT_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.get_password() {
-        Ok(path) => {
-            info!("✅ [DOCS-CONFIG] Documents root path retrieved: {}", path);
+    match secret_store::get(SecretKey::DocumentsRootPath) {
+        Ok(Some(path)) => {
+            debug!("✅ [DOCS-CONFIG] Documents root path retrieved: {}", redact(&path));
             Ok(Some(path))
         }
-        Err(keyring::Error::NoEntry) => {
+        Ok(None) => {
             info!("ℹ️ [DOCS-CONFIG] No documents root path found in secure storage");
             Ok(None)
         }
@@ -75,19 +51,14 @@ pub async fn get_documents_root_path() -> Result<Option<String>, String> {
 /// SECURITY: This command only works for documents root path - no arbitrary keys allowed
 #[tauri::command]
 pub async fn remove_documents_root_path() -> Result<(), String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🗑️ [DOCS-CONFIG] Removing documents root path from secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, DOCS_ROOT_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.delete_credential() {
-        Ok(_) => {
+    match secret_store::remove(SecretKey::DocumentsRootPath) {
+        Ok(true) => {
             info!("✅ [DOCS-CONFIG] Documents root path removed successfully");
             Ok(())
         }
-        Err(keyring::Error::NoEntry) => {
+        Ok(false) => {
             info!("ℹ️ [DOCS-CONFIG] No documents root path to remove");
             Ok(())
         }
@@ -0,0 +1,890 @@
+// src-tauri/src/document_templates.rs
+//
+// Fillable PDF templates for deal documents, and per-state template packs
+// Template PDFs are copied under app data (templates/{template_id}/v{version}.pdf, see
+// storage.rs) so they survive the original file moving. Generated documents are separate
+// copies in the documents folder, so deleting a template never touches them.
+//
+// A pack is a zip with pack.json at its root plus the PDFs it names:
+//   { "state": "MI", "templates": [ { "name": "Bill of Sale", "document_type": "bill_of_sale",
+//     "file": "bill_of_sale.pdf", "version": 2, "field_map": { "buyer": "{client.last_name}" } } ] }
+// Every template is checked before anything is installed: the PDF must be a form with the
+// mapped fields, and placeholders must name real deal/client/vehicle columns.
+
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use tauri::State;
+use zip::ZipArchive;
+
+use crate::app_state::AppState;
+use crate::archive::sanitize_entry_name;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{get_db, new_row_id};
+use crate::pdf_forms::{load_pdf, placeholders, read_form, FILTERS};
+use crate::storage::get_templates_path;
+use crate::telemetry::track;
+
+const TEMPLATE_COLUMNS: &str = "id, name, state, document_type, template_path, field_map, \
+     version, flatten, created_at, updated_at";
+const PACK_MANIFEST: &str = "pack.json";
+/// Placeholder prefixes checked against table columns
+const SOURCE_TABLES: &[(&str, &str)] = &[
+    ("deal", "deals"),
+    ("client", "clients"),
+    ("vehicle", "vehicles"),
+    ("dealer", "dealer_profile"),
+];
+/// Placeholders that aren't table columns (cobuyer.* is free-form JSON)
+const EXTRA_PLACEHOLDERS: &[&str] = &["today"];
+const FREE_FORM_PREFIXES: &[&str] = &["cobuyer."];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentTemplate {
+    #[serde(default)]
+    pub id: String,
+    pub name: String,
+    /// Two-letter state this template is for; None = any state
+    #[serde(default)]
+    pub state: Option<String>,
+    /// documents.type for generated documents (bill_of_sale, odometer, ...)
+    pub document_type: String,
+    pub template_path: String,
+    /// PDF field name -> value template
+    pub field_map: BTreeMap<String, String>,
+    #[serde(default = "default_version")]
+    pub version: i64,
+    #[serde(default = "default_flatten")]
+    pub flatten: bool,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+fn default_version() -> i64 {
+    1
+}
+
+fn default_flatten() -> bool {
+    true
+}
+
+impl DocumentTemplate {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let field_map: String = row.get(5)?;
+        Ok(DocumentTemplate {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            state: row.get(2)?,
+            document_type: row.get(3)?,
+            template_path: row.get(4)?,
+            field_map: serde_json::from_str(&field_map).unwrap_or_default(),
+            version: row.get(6)?,
+            flatten: row.get(7)?,
+            created_at: row.get(8)?,
+            updated_at: row.get(9)?,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PackManifest {
+    #[serde(default)]
+    state: Option<String>,
+    templates: Vec<PackTemplate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackTemplate {
+    name: String,
+    /// Overrides the pack's state
+    #[serde(default)]
+    state: Option<String>,
+    document_type: String,
+    file: String,
+    #[serde(default = "default_version")]
+    version: i64,
+    #[serde(default = "default_flatten")]
+    flatten: bool,
+    field_map: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PackImport {
+    /// New templates and newer versions of installed ones
+    pub installed: Vec<DocumentTemplate>,
+    /// Templates already installed at the same or a newer version
+    pub skipped: Vec<String>,
+}
+
+/// Create a template, or update it when template.id exists; the PDF is copied into the
+/// templates folder unless it's already there
+#[tauri::command]
+pub fn db_save_document_template(
+    template: DocumentTemplate,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<DocumentTemplate, String> {
+    track("db_save_document_template", || {
+        let user_id_value = state.require_user(user_id)?;
+        let templates_dir = PathBuf::from(get_templates_path()?);
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let operation = match find_template(&conn, &template.id, &user_id_value)? {
+            Some(_) => Operation::Update,
+            None => Operation::Create,
+        };
+        let template = save_template(&conn, &templates_dir, &user_id_value, template)?;
+        info!("✅ Document template saved: {}", template.id);
+        data_changed(
+            &user_id_value,
+            ChangedEntity::DocumentTemplate,
+            &template.id,
+            operation,
+        );
+        Ok(template)
+    })
+}
+
+/// Templates for a state plus the ones for any state; every template when state_code is None
+#[tauri::command]
+pub fn list_templates(
+    state_code: Option<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DocumentTemplate>, String> {
+    track("list_templates", || {
+        let user_id_value = state.require_user(user_id)?;
+        let state_code = normalize_state(state_code)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        query_templates(&conn, &user_id_value, state_code.as_deref())
+    })
+}
+
+#[tauri::command]
+pub fn db_delete_document_template(
+    id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    track("db_delete_document_template", || {
+        let user_id_value = state.require_user(user_id)?;
+        let templates_dir = PathBuf::from(get_templates_path()?);
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        delete_template(&conn, &templates_dir, &user_id_value, &id)?;
+        info!("🗑️ Document template deleted: {}", id);
+        data_changed(
+            &user_id_value,
+            ChangedEntity::DocumentTemplate,
+            &id,
+            Operation::Delete,
+        );
+        Ok(())
+    })
+}
+
+/// Install a zip of PDFs + pack.json; nothing is installed if any template fails validation
+#[tauri::command]
+pub fn import_template_pack(
+    zip_path: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<PackImport, String> {
+    track("import_template_pack", || {
+        let user_id_value = state.require_user(user_id)?;
+        let templates_dir = PathBuf::from(get_templates_path()?);
+        let db = get_db().map_err(|e| e.to_string())?;
+        let mut conn = db.conn();
+        let result = install_pack(
+            &mut conn,
+            &templates_dir,
+            &user_id_value,
+            Path::new(&zip_path),
+        )?;
+        info!(
+            "✅ Template pack imported: {} installed, {} skipped",
+            result.installed.len(),
+            result.skipped.len()
+        );
+        Ok(result)
+    })
+}
+
+pub(crate) fn get_template(id: &str, user_id_value: &str) -> Result<DocumentTemplate, String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    let conn = db.conn();
+    find_template(&conn, id, user_id_value)?
+        .ok_or_else(|| "Template not found or access denied".to_string())
+}
+
+fn find_template(
+    conn: &Connection,
+    id: &str,
+    user_id_value: &str,
+) -> Result<Option<DocumentTemplate>, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM document_templates WHERE id = ?1 AND user_id = ?2",
+            TEMPLATE_COLUMNS
+        ),
+        params![id, user_id_value],
+        DocumentTemplate::from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+fn query_templates(
+    conn: &Connection,
+    user_id_value: &str,
+    state_code: Option<&str>,
+) -> Result<Vec<DocumentTemplate>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM document_templates
+             WHERE user_id = ?1 AND (?2 IS NULL OR state IS NULL OR state = ?2)
+             ORDER BY name, state",
+            TEMPLATE_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let templates = stmt
+        .query_map(
+            params![user_id_value, state_code],
+            DocumentTemplate::from_row,
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(templates)
+}
+
+fn save_template(
+    conn: &Connection,
+    templates_dir: &Path,
+    user_id_value: &str,
+    template: DocumentTemplate,
+) -> Result<DocumentTemplate, String> {
+    if template.name.trim().is_empty() {
+        return Err("Template name is required".to_string());
+    }
+    let existing = match template.id.as_str() {
+        "" => None,
+        id => {
+            let owner: Option<String> = conn
+                .query_row(
+                    "SELECT user_id FROM document_templates WHERE id = ?1",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?;
+            if owner.is_some_and(|owner| owner != user_id_value) {
+                return Err("Template not found or access denied".to_string());
+            }
+            find_template(conn, id, user_id_value)?
+        }
+    };
+
+    let pdf = load_pdf(Path::new(&template.template_path))?;
+    let errors = check_field_map(&template.field_map, &read_form(&pdf), &known_fields(conn)?);
+    if !errors.is_empty() {
+        return Err(errors.join("; "));
+    }
+
+    let now = Utc::now().timestamp_millis();
+    let mut template = DocumentTemplate {
+        id: existing
+            .as_ref()
+            .map(|t| t.id.clone())
+            .unwrap_or_else(|| format!("tpl_{}", new_row_id())),
+        name: template.name.trim().to_string(),
+        state: normalize_state(template.state)?,
+        version: template.version.max(1),
+        created_at: existing.as_ref().map_or(now, |t| t.created_at),
+        updated_at: now,
+        ..template
+    };
+
+    let source = PathBuf::from(&template.template_path);
+    if !source.starts_with(templates_dir) {
+        let managed = managed_path(templates_dir, &template.id, template.version);
+        copy_template_file(&source, &managed)?;
+        template.template_path = managed.to_string_lossy().into_owned();
+    }
+    upsert_template(conn, user_id_value, &template)?;
+
+    if let Some(old) = existing.filter(|old| old.template_path != template.template_path) {
+        remove_managed_file(templates_dir, Path::new(&old.template_path));
+    }
+    Ok(template)
+}
+
+fn upsert_template(
+    conn: &Connection,
+    user_id_value: &str,
+    template: &DocumentTemplate,
+) -> Result<(), String> {
+    let field_map = serde_json::to_string(&template.field_map).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO document_templates (id, user_id, name, state, document_type, template_path,
+            field_map, version, flatten, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+         ON CONFLICT(id) DO UPDATE SET
+            name = ?3, state = ?4, document_type = ?5, template_path = ?6, field_map = ?7,
+            version = ?8, flatten = ?9, updated_at = ?11",
+        params![
+            template.id,
+            user_id_value,
+            template.name,
+            template.state,
+            template.document_type,
+            template.template_path,
+            field_map,
+            template.version,
+            template.flatten,
+            template.created_at,
+            template.updated_at,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Removes the row and its managed PDF; documents generated from it are independent files
+fn delete_template(
+    conn: &Connection,
+    templates_dir: &Path,
+    user_id_value: &str,
+    id: &str,
+) -> Result<(), String> {
+    let template = find_template(conn, id, user_id_value)?
+        .ok_or_else(|| "Template not found or access denied".to_string())?;
+    conn.execute(
+        "DELETE FROM document_templates WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id_value],
+    )
+    .map_err(|e| e.to_string())?;
+    remove_managed_file(templates_dir, Path::new(&template.template_path));
+    Ok(())
+}
+
+fn install_pack(
+    conn: &mut Connection,
+    templates_dir: &Path,
+    user_id_value: &str,
+    zip_path: &Path,
+) -> Result<PackImport, String> {
+    let (manifest, files) = read_pack(zip_path)?;
+    let known = known_fields(conn)?;
+    let errors = validate_pack(&manifest, &files, &known);
+    if !errors.is_empty() {
+        return Err(format!("Template pack is invalid: {}", errors.join("; ")));
+    }
+
+    let now = Utc::now().timestamp_millis();
+    let mut result = PackImport::default();
+    let mut written: Vec<PathBuf> = Vec::new();
+    let mut replaced: Vec<PathBuf> = Vec::new();
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let outcome = (|| -> Result<(), String> {
+        for entry in &manifest.templates {
+            let state = normalize_state(entry.state.clone().or(manifest.state.clone()))?;
+            let existing: Option<DocumentTemplate> = tx
+                .query_row(
+                    &format!(
+                        "SELECT {} FROM document_templates
+                         WHERE user_id = ?1 AND name = ?2 AND state IS ?3",
+                        TEMPLATE_COLUMNS
+                    ),
+                    params![user_id_value, entry.name.trim(), state],
+                    DocumentTemplate::from_row,
+                )
+                .optional()
+                .map_err(|e| e.to_string())?;
+
+            if let Some(existing) = &existing {
+                if existing.version >= entry.version {
+                    result.skipped.push(format!(
+                        "{} ({}) v{} is already installed",
+                        existing.name,
+                        state.as_deref().unwrap_or("any state"),
+                        existing.version
+                    ));
+                    continue;
+                }
+            }
+
+            let id = existing
+                .as_ref()
+                .map(|t| t.id.clone())
+                .unwrap_or_else(|| format!("tpl_{}", new_row_id()));
+            let path = managed_path(templates_dir, &id, entry.version);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| format!("Failed to create templates folder: {}", e))?;
+            }
+            fs::write(&path, &files[&entry_key(&entry.file)?])
+                .map_err(|e| format!("Failed to install {}: {}", entry.file, e))?;
+            written.push(path.clone());
+
+            let template = DocumentTemplate {
+                id,
+                name: entry.name.trim().to_string(),
+                state,
+                document_type: entry.document_type.clone(),
+                template_path: path.to_string_lossy().into_owned(),
+                field_map: entry.field_map.clone(),
+                version: entry.version,
+                flatten: entry.flatten,
+                created_at: existing.as_ref().map_or(now, |t| t.created_at),
+                updated_at: now,
+            };
+            upsert_template(&tx, user_id_value, &template)?;
+            if let Some(existing) = existing {
+                replaced.push(PathBuf::from(existing.template_path));
+            }
+            result.installed.push(template);
+        }
+        Ok(())
+    })();
+
+    match outcome.and_then(|_| tx.commit().map_err(|e| e.to_string())) {
+        Ok(()) => {
+            for path in replaced {
+                remove_managed_file(templates_dir, &path);
+            }
+            Ok(result)
+        }
+        Err(e) => {
+            for path in written {
+                remove_managed_file(templates_dir, &path);
+            }
+            Err(e)
+        }
+    }
+}
+
+/// pack.json and the bytes of every other file, keyed by normalized entry name
+fn read_pack(zip_path: &Path) -> Result<(PackManifest, HashMap<String, Vec<u8>>), String> {
+    let file = File::open(zip_path).map_err(|e| format!("Failed to open template pack: {}", e))?;
+    let mut archive = ZipArchive::new(BufReader::new(file))
+        .map_err(|e| format!("Template pack is not a zip file: {}", e))?;
+
+    let mut manifest = None;
+    let mut files = HashMap::new();
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Failed to read template pack entry {}: {}", i, e))?;
+        if entry.is_dir() {
+            continue;
+        }
+        let key = entry_key(entry.name())?;
+        let mut bytes = Vec::new();
+        entry
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Failed to read {}: {}", key, e))?;
+        if key == PACK_MANIFEST {
+            manifest = Some(
+                serde_json::from_slice::<PackManifest>(&bytes)
+                    .map_err(|e| format!("Invalid {}: {}", PACK_MANIFEST, e))?,
+            );
+        } else {
+            files.insert(key, bytes);
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| format!("Template pack has no {}", PACK_MANIFEST))?;
+    Ok((manifest, files))
+}
+
+fn entry_key(name: &str) -> Result<String, String> {
+    Ok(sanitize_entry_name(name)?
+        .components()
+        .map(|part| part.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// Every problem with the pack, so the dealer can fix them in one go
+fn validate_pack(
+    manifest: &PackManifest,
+    files: &HashMap<String, Vec<u8>>,
+    known: &HashSet<String>,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    if manifest.templates.is_empty() {
+        errors.push("Pack has no templates".to_string());
+    }
+
+    let mut seen = HashSet::new();
+    for entry in &manifest.templates {
+        let label = if entry.name.trim().is_empty() {
+            entry.file.clone()
+        } else {
+            entry.name.trim().to_string()
+        };
+        let mut problem = |message: String| errors.push(format!("{}: {}", label, message));
+
+        if entry.name.trim().is_empty() {
+            problem("name is required".to_string());
+        }
+        if entry.document_type.trim().is_empty() {
+            problem("document_type is required".to_string());
+        }
+        if entry.version < 1 {
+            problem("version must be 1 or higher".to_string());
+        }
+        let state = entry.state.clone().or(manifest.state.clone());
+        match normalize_state(state) {
+            Ok(state) => {
+                if !seen.insert((entry.name.trim().to_lowercase(), state)) {
+                    problem("listed twice for the same state".to_string());
+                }
+            }
+            Err(e) => problem(e),
+        }
+
+        let Some(bytes) = entry_key(&entry.file).ok().and_then(|key| files.get(&key)) else {
+            problem(format!("{} is not in the pack", entry.file));
+            continue;
+        };
+        let form = match lopdf::Document::load_mem(bytes) {
+            Ok(pdf) => read_form(&pdf),
+            Err(e) => {
+                problem(format!("{} is not a readable PDF: {}", entry.file, e));
+                continue;
+            }
+        };
+        if form.is_empty() {
+            problem(format!("{} has no form fields", entry.file));
+        }
+        for error in check_field_map(&entry.field_map, &form, known) {
+            problem(error);
+        }
+    }
+    errors
+}
+
+/// Mapping keys must be fields in the PDF; placeholders must name known data fields
+fn check_field_map(
+    field_map: &BTreeMap<String, String>,
+    form: &BTreeMap<String, String>,
+    known: &HashSet<String>,
+) -> Vec<String> {
+    let mut errors = Vec::new();
+    for (field, template) in field_map {
+        if !form.contains_key(field) {
+            errors.push(format!("form has no field named \"{}\"", field));
+        }
+        for (key, filter) in placeholders(template) {
+            let known_key = known.contains(key)
+                || EXTRA_PLACEHOLDERS.contains(&key)
+                || FREE_FORM_PREFIXES
+                    .iter()
+                    .any(|prefix| key.len() > prefix.len() && key.starts_with(prefix));
+            if !known_key {
+                errors.push(format!("\"{}\" maps unknown field {{{}}}", field, key));
+            }
+            if let Some(filter) = filter.filter(|f| !FILTERS.contains(f)) {
+                errors.push(format!("\"{}\" uses unknown filter \"{}\"", field, filter));
+            }
+        }
+    }
+    errors
+}
+
+/// deal.*, client.* and vehicle.* placeholders, from the tables' columns
+fn known_fields(conn: &Connection) -> Result<HashSet<String>, String> {
+    let mut known = HashSet::new();
+    for (prefix, table) in SOURCE_TABLES {
+        let mut stmt = conn
+            .prepare(&format!("PRAGMA table_info({})", table))
+            .map_err(|e| e.to_string())?;
+        let columns = stmt
+            .query_map([], |row| row.get::<_, String>(1))
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        known.extend(columns.into_iter().map(|c| format!("{}.{}", prefix, c)));
+    }
+    Ok(known)
+}
+
+pub(crate) fn normalize_state(state: Option<String>) -> Result<Option<String>, String> {
+    match state.map(|s| s.trim().to_ascii_uppercase()) {
+        None => Ok(None),
+        Some(s) if s.is_empty() => Ok(None),
+        Some(s) if s.len() == 2 && s.chars().all(|c| c.is_ascii_alphabetic()) => Ok(Some(s)),
+        Some(s) => Err(format!("Invalid state code: {}", s)),
+    }
+}
+
+fn managed_path(templates_dir: &Path, id: &str, version: i64) -> PathBuf {
+    templates_dir.join(id).join(format!("v{}.pdf", version))
+}
+
+fn copy_template_file(source: &Path, destination: &Path) -> Result<(), String> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create templates folder: {}", e))?;
+    }
+    fs::copy(source, destination)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to copy template {}: {}", source.display(), e))
+}
+
+/// Only files inside the templates folder are ours to delete
+fn remove_managed_file(templates_dir: &Path, path: &Path) {
+    if !path.starts_with(templates_dir) {
+        return;
+    }
+    let _ = fs::remove_file(path);
+    if let Some(parent) = path.parent().filter(|parent| *parent != templates_dir) {
+        // Only succeeds once the template's folder is empty
+        let _ = fs::remove_dir(parent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    const BILL_OF_SALE: &[u8] = include_bytes!("../testdata/pdf_forms/bill_of_sale.pdf");
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/004_add_vehicle_images.sql"),
+            include_str!("../migrations/011_add_document_templates.sql"),
+            include_str!("../migrations/012_add_template_packs.sql"),
+            include_str!("../migrations/022_add_dealer_profile.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn
+    }
+
+    fn write_pack(path: &Path, manifest: &str, files: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        zip.start_file(PACK_MANIFEST, SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(manifest.as_bytes()).unwrap();
+        for (name, bytes) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(bytes).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn manifest(version: i64) -> String {
+        format!(
+            r#"{{ "state": "mi", "templates": [{{
+                "name": "Bill of Sale", "document_type": "bill_of_sale",
+                "file": "forms/bill_of_sale.pdf", "version": {},
+                "field_map": {{
+                    "buyer_name": "{{client.first_name}} {{client.last_name}}",
+                    "vehicle.vin": "{{vehicle.vin|upper}}",
+                    "vehicle.price": "{{deal.sale_amount|money}}",
+                    "as_is": "{{cobuyer.as_is}}"
+                }}
+            }}] }}"#,
+            version
+        )
+    }
+
+    #[test]
+    fn test_import_pack_installs_and_upgrades() {
+        let dir = temp_dir("templates", "import");
+        let templates_dir = dir.join("templates");
+        let mut conn = test_db();
+        let pack = dir.join("pack.zip");
+
+        write_pack(
+            &pack,
+            &manifest(1),
+            &[("forms/bill_of_sale.pdf", BILL_OF_SALE)],
+        );
+        let first = install_pack(&mut conn, &templates_dir, "user-1", &pack).unwrap();
+        assert_eq!(first.installed.len(), 1);
+        let template = &first.installed[0];
+        assert_eq!(template.state.as_deref(), Some("MI"));
+        assert_eq!(
+            fs::read(&template.template_path).unwrap(),
+            BILL_OF_SALE.to_vec()
+        );
+
+        // Same version again is skipped; a newer one replaces the file in place
+        let again = install_pack(&mut conn, &templates_dir, "user-1", &pack).unwrap();
+        assert!(again.installed.is_empty());
+        assert_eq!(again.skipped.len(), 1);
+
+        write_pack(
+            &pack,
+            &manifest(2),
+            &[("forms/bill_of_sale.pdf", BILL_OF_SALE)],
+        );
+        let upgrade = install_pack(&mut conn, &templates_dir, "user-1", &pack).unwrap();
+        assert_eq!(upgrade.installed[0].id, template.id);
+        assert_eq!(upgrade.installed[0].version, 2);
+        assert!(!Path::new(&template.template_path).exists());
+
+        // Listed for Michigan and for all states, not for Ohio or another user
+        assert_eq!(
+            query_templates(&conn, "user-1", Some("MI")).unwrap().len(),
+            1
+        );
+        assert_eq!(query_templates(&conn, "user-1", None).unwrap().len(), 1);
+        assert!(query_templates(&conn, "user-1", Some("OH"))
+            .unwrap()
+            .is_empty());
+        assert!(query_templates(&conn, "user-2", None).unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_pack_installs_nothing() {
+        let dir = temp_dir("templates", "invalid");
+        let templates_dir = dir.join("templates");
+        let mut conn = test_db();
+        let pack = dir.join("pack.zip");
+
+        let manifest = r#"{ "state": "Michigan", "templates": [
+            { "name": "Bill of Sale", "document_type": "bill_of_sale", "file": "bill_of_sale.pdf",
+              "field_map": {
+                  "buyer_name": "{client.shoe_size}",
+                  "odometer": "{vehicle.mileage}",
+                  "vehicle.price": "{deal.sale_amount|roman}"
+              } },
+            { "name": "Odometer", "document_type": "odometer", "file": "missing.pdf",
+              "field_map": {} },
+            { "name": "Notes", "document_type": "other", "file": "notes.pdf", "field_map": {} }
+        ] }"#;
+        write_pack(
+            &pack,
+            manifest,
+            &[
+                ("bill_of_sale.pdf", BILL_OF_SALE),
+                ("notes.pdf", b"not a pdf"),
+            ],
+        );
+
+        let error = install_pack(&mut conn, &templates_dir, "user-1", &pack).unwrap_err();
+        for expected in [
+            "Invalid state code: MICHIGAN",
+            "maps unknown field {client.shoe_size}",
+            "form has no field named \"odometer\"",
+            "unknown filter \"roman\"",
+            "Odometer: missing.pdf is not in the pack",
+            "Notes: notes.pdf is not a readable PDF",
+        ] {
+            assert!(error.contains(expected), "{} not in {}", expected, error);
+        }
+        // Known fields aren't flagged
+        assert!(!error.contains("vehicle.mileage"));
+        assert!(query_templates(&conn, "user-1", None).unwrap().is_empty());
+        assert!(!templates_dir.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_field_map() {
+        let conn = test_db();
+        let known = known_fields(&conn).unwrap();
+        let form: BTreeMap<String, String> = [("buyer".to_string(), String::new())].into();
+        let map = |value: &str| -> BTreeMap<String, String> {
+            [("buyer".to_string(), value.to_string())].into()
+        };
+
+        assert!(check_field_map(
+            &map("{client.first_name} {deal.sale_date|date} {today|date} {cobuyer.name}"),
+            &form,
+            &known
+        )
+        .is_empty());
+        assert_eq!(
+            check_field_map(&map("{client.nickname} {cobuyer.}"), &form, &known),
+            [
+                "\"buyer\" maps unknown field {client.nickname}",
+                "\"buyer\" maps unknown field {cobuyer.}",
+            ]
+        );
+        // Literal text and unclosed braces need no data
+        assert!(check_field_map(&map("As is {no warranty"), &form, &known).is_empty());
+
+        // Dealer profile columns are sources too
+        assert!(check_field_map(
+            &map("{dealer.legal_name} Lic. {dealer.license_number|upper} {dealer.zip_code}"),
+            &form,
+            &known
+        )
+        .is_empty());
+        assert_eq!(
+            check_field_map(&map("{dealer.fax}"), &form, &known),
+            ["\"buyer\" maps unknown field {dealer.fax}"]
+        );
+    }
+
+    #[test]
+    fn test_delete_keeps_generated_documents() {
+        let dir = temp_dir("templates", "delete");
+        let templates_dir = dir.join("templates");
+        let conn = test_db();
+        let source = dir.join("bill_of_sale.pdf");
+        fs::write(&source, BILL_OF_SALE).unwrap();
+
+        let template = save_template(
+            &conn,
+            &templates_dir,
+            "user-1",
+            DocumentTemplate {
+                id: String::new(),
+                name: "Bill of Sale".to_string(),
+                state: None,
+                document_type: "bill_of_sale".to_string(),
+                template_path: source.to_string_lossy().into_owned(),
+                field_map: [("buyer_name".to_string(), "{client.last_name}".to_string())].into(),
+                version: 1,
+                flatten: true,
+                created_at: 0,
+                updated_at: 0,
+            },
+        )
+        .unwrap();
+        let managed = PathBuf::from(&template.template_path);
+        assert!(managed.starts_with(&templates_dir));
+
+        // A document generated earlier lives in the documents folder
+        let generated = dir.join("documents").join("bill_of_sale.pdf");
+        fs::create_dir_all(generated.parent().unwrap()).unwrap();
+        fs::copy(&managed, &generated).unwrap();
+
+        assert!(delete_template(&conn, &templates_dir, "user-2", &template.id).is_err());
+        delete_template(&conn, &templates_dir, "user-1", &template.id).unwrap();
+        assert!(!managed.exists());
+        assert!(generated.exists());
+        // The file the template was created from isn't ours to delete
+        assert!(source.exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,396 @@
+// src-tauri/src/document_trash.rs
+//
+// Recycle folder for the files of deleted documents
+// db_delete_document moves the document's file to .trash/{document_id}/ under the documents
+// root and records it in .trash/manifest.json, so a wrong delete can be undone with
+// restore_deleted_document_file. purge_document_trash (also run on startup) removes entries
+// older than the retention period for good.
+
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_export::documents_root;
+use crate::telemetry::track;
+
+const TRASH_DIR: &str = ".trash";
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Days a deleted document's file stays in the trash before the startup purge removes it
+pub const DEFAULT_TRASH_RETENTION_DAYS: u64 = 30;
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Serializes manifest read-modify-write cycles
+static MANIFEST_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrashEntry {
+    pub document_id: String,
+    pub deal_id: String,
+    /// Owner of the deleted document's deal, when known
+    pub user_id: Option<String>,
+    pub original_path: String,
+    pub trashed_path: String,
+    pub deleted_at: i64,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct TrashPurge {
+    pub removed: usize,
+    pub failed: usize,
+    pub bytes_freed: u64,
+    /// Manifest entries dropped because their file was already gone
+    pub missing: usize,
+}
+
+/// What to record about a document whose file is being trashed
+pub(crate) struct TrashedDocument<'a> {
+    pub document_id: &'a str,
+    pub deal_id: &'a str,
+    pub user_id: Option<&'a str>,
+    pub file_path: &'a str,
+}
+
+fn trash_dir(root: &Path) -> PathBuf {
+    root.join(TRASH_DIR)
+}
+
+fn read_manifest(root: &Path) -> Result<Vec<TrashEntry>, String> {
+    let path = trash_dir(root).join(MANIFEST_FILE);
+    match fs::read_to_string(&path) {
+        Ok(json) => {
+            serde_json::from_str(&json).map_err(|e| format!("Failed to read trash manifest: {}", e))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read trash manifest: {}", e)),
+    }
+}
+
+fn write_manifest(root: &Path, entries: &[TrashEntry]) -> Result<(), String> {
+    let dir = trash_dir(root);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create trash folder: {}", e))?;
+    let json = serde_json::to_string_pretty(entries).map_err(|e| e.to_string())?;
+    let tmp_path = dir.join(format!("{}.tmp", MANIFEST_FILE));
+    fs::write(&tmp_path, json).map_err(|e| format!("Failed to write trash manifest: {}", e))?;
+    fs::rename(&tmp_path, dir.join(MANIFEST_FILE))
+        .map_err(|e| format!("Failed to write trash manifest: {}", e))
+}
+
+/// Rename, falling back to copy + remove when the paths are on different volumes
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    if let Err(e) = fs::remove_file(from) {
+        let _ = fs::remove_file(to);
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Folder name for a document id; ids come from the database, but never trust them as paths
+fn entry_dir_name(document_id: &str) -> String {
+    document_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Move a deleted document's file into the trash. A file that's already gone is not an error
+/// (Ok(None)); the entry is only recorded once the file has moved.
+pub(crate) fn trash_document_file(
+    root: &Path,
+    document: TrashedDocument,
+    now: i64,
+) -> Result<Option<TrashEntry>, String> {
+    let original = Path::new(document.file_path);
+    if !original.is_file() {
+        warn!(
+            "⚠️  File for deleted document {} is missing: {:?}",
+            document.document_id, original
+        );
+        return Ok(None);
+    }
+
+    let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+    let mut entries = read_manifest(root)?;
+
+    let file_name = original
+        .file_name()
+        .map(|name| name.to_os_string())
+        .unwrap_or_else(|| "document".into());
+    let target = trash_dir(root)
+        .join(entry_dir_name(document.document_id))
+        .join(format!("{}-{}", now, file_name.to_string_lossy()));
+    move_file(original, &target)
+        .map_err(|e| format!("Failed to move {:?} to the trash: {}", original, e))?;
+
+    let entry = TrashEntry {
+        document_id: document.document_id.to_string(),
+        deal_id: document.deal_id.to_string(),
+        user_id: document.user_id.map(str::to_string),
+        original_path: document.file_path.to_string(),
+        trashed_path: target.to_string_lossy().to_string(),
+        deleted_at: now,
+    };
+    entries.push(entry.clone());
+    if let Err(e) = write_manifest(root, &entries) {
+        // Without a manifest entry the file couldn't be restored or purged; put it back
+        let _ = move_file(&target, original);
+        return Err(e);
+    }
+    Ok(Some(entry))
+}
+
+/// Move the newest trashed file of document_id back to where it was. Refuses to overwrite
+/// a file that has since been created at that path.
+pub(crate) fn restore_document_file(
+    root: &Path,
+    document_id: &str,
+    user_id: &str,
+) -> Result<TrashEntry, String> {
+    let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+    let mut entries = read_manifest(root)?;
+
+    let index = entries
+        .iter()
+        .rposition(|entry| {
+            entry.document_id == document_id
+                && entry
+                    .user_id
+                    .as_deref()
+                    .is_none_or(|owner| owner == user_id)
+        })
+        .ok_or_else(|| "No deleted file found for this document".to_string())?;
+    let entry = entries[index].clone();
+
+    let original = Path::new(&entry.original_path);
+    if original.exists() {
+        return Err(format!(
+            "A file already exists at {}; move it before restoring",
+            entry.original_path
+        ));
+    }
+    let trashed = Path::new(&entry.trashed_path);
+    if !trashed.is_file() {
+        entries.remove(index);
+        write_manifest(root, &entries)?;
+        return Err("The deleted file is no longer in the trash".to_string());
+    }
+
+    move_file(trashed, original)
+        .map_err(|e| format!("Failed to restore {}: {}", entry.original_path, e))?;
+    remove_empty_parent(trashed);
+    entries.remove(index);
+    write_manifest(root, &entries)?;
+    Ok(entry)
+}
+
+/// How many of a user's deleted documents still have their file in the trash
+pub(crate) fn trashed_document_count(root: &Path, user_id: &str) -> Result<usize, String> {
+    let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+    Ok(read_manifest(root)?
+        .iter()
+        .filter(|entry| entry.user_id.as_deref() == Some(user_id))
+        .count())
+}
+
+/// Permanently delete trash entries deleted at or before older_than_ms ago
+pub(crate) fn purge_trash(root: &Path, older_than_ms: i64, now: i64) -> Result<TrashPurge, String> {
+    let _guard = MANIFEST_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+    let entries = read_manifest(root)?;
+    if entries.is_empty() {
+        return Ok(TrashPurge::default());
+    }
+
+    let mut result = TrashPurge::default();
+    let mut kept = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let trashed = Path::new(&entry.trashed_path);
+        let size = match fs::metadata(trashed) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => {
+                result.missing += 1;
+                continue;
+            }
+        };
+        if now - entry.deleted_at < older_than_ms {
+            kept.push(entry);
+            continue;
+        }
+        match fs::remove_file(trashed) {
+            Ok(()) => {
+                result.removed += 1;
+                result.bytes_freed += size;
+                remove_empty_parent(trashed);
+            }
+            Err(e) => {
+                warn!("⚠️  Failed to purge trashed file {:?}: {}", trashed, e);
+                result.failed += 1;
+                kept.push(entry);
+            }
+        }
+    }
+
+    write_manifest(root, &kept)?;
+    Ok(result)
+}
+
+/// Drops the per-document folder once it's empty
+fn remove_empty_parent(path: &Path) {
+    if let Some(parent) = path.parent() {
+        let _ = fs::remove_dir(parent);
+    }
+}
+
+/// Put a deleted document's file back at its original path (the document row is not
+/// recreated); returns that path
+#[tauri::command]
+pub fn restore_deleted_document_file(
+    document_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<String, String> {
+    track("restore_deleted_document_file", || {
+        let user_id_value = state.require_user(user_id)?;
+        let root = PathBuf::from(documents_root()?);
+        let entry = restore_document_file(&root, &document_id, &user_id_value)?;
+        info!(
+            "♻️  Restored file of deleted document {}: {}",
+            document_id, entry.original_path
+        );
+        Ok(entry.original_path)
+    })
+}
+
+/// Permanently delete trashed document files; older_than_days defaults to 30
+#[tauri::command]
+pub fn purge_document_trash(older_than_days: Option<u64>) -> Result<TrashPurge, String> {
+    track("purge_document_trash", || {
+        let days = older_than_days.unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+        let root = PathBuf::from(documents_root()?);
+        let result = purge_trash(
+            &root,
+            (days as i64).saturating_mul(DAY_MS),
+            Utc::now().timestamp_millis(),
+        )?;
+        info!(
+            "✅ Document trash purged: {} removed, {} failed, {} bytes freed, {} already missing",
+            result.removed, result.failed, result.bytes_freed, result.missing
+        );
+        Ok(result)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn document<'a>(id: &'a str, path: &'a str) -> TrashedDocument<'a> {
+        TrashedDocument {
+            document_id: id,
+            deal_id: "deal-1",
+            user_id: Some("u1"),
+            file_path: path,
+        }
+    }
+
+    #[test]
+    fn test_trash_and_restore_roundtrip() {
+        let root = temp_dir("docs-trash", "roundtrip");
+        let path = root.join("deals/bill_of_sale.pdf");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, b"signed").unwrap();
+        let path_str = path.to_string_lossy().to_string();
+
+        let entry = trash_document_file(&root, document("doc-1", &path_str), 1_000)
+            .unwrap()
+            .unwrap();
+        assert!(!path.exists());
+        assert!(Path::new(&entry.trashed_path).starts_with(root.join(TRASH_DIR)));
+        assert_eq!(fs::read(&entry.trashed_path).unwrap(), b"signed");
+        assert_eq!(read_manifest(&root).unwrap(), vec![entry.clone()]);
+
+        // Someone else's document isn't theirs to restore
+        assert!(restore_document_file(&root, "doc-1", "u2").is_err());
+
+        // A file recreated at the original path is never overwritten
+        fs::write(&path, b"new").unwrap();
+        let err = restore_document_file(&root, "doc-1", "u1").unwrap_err();
+        assert!(err.contains("already exists"), "{}", err);
+        fs::remove_file(&path).unwrap();
+
+        let restored = restore_document_file(&root, "doc-1", "u1").unwrap();
+        assert_eq!(restored, entry);
+        assert_eq!(fs::read(&path).unwrap(), b"signed");
+        assert!(!Path::new(&entry.trashed_path).exists());
+        assert!(read_manifest(&root).unwrap().is_empty());
+        assert!(restore_document_file(&root, "doc-1", "u1").is_err());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_missing_file_is_not_an_error() {
+        let root = temp_dir("docs-trash", "missing");
+        let path = root.join("gone.pdf").to_string_lossy().to_string();
+        assert_eq!(
+            trash_document_file(&root, document("doc-1", &path), 1_000).unwrap(),
+            None
+        );
+        assert!(read_manifest(&root).unwrap().is_empty());
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_purge_removes_old_entries_and_keeps_manifest_consistent() {
+        let root = temp_dir("docs-trash", "purge");
+        let mut trashed = Vec::new();
+        for (id, deleted_at) in [("old", 0), ("recent", 9 * DAY_MS), ("vanished", 0)] {
+            let path = root.join(format!("{}.pdf", id));
+            fs::write(&path, b"12345").unwrap();
+            let path = path.to_string_lossy().to_string();
+            let entry = trash_document_file(&root, document(id, &path), deleted_at)
+                .unwrap()
+                .unwrap();
+            trashed.push(entry);
+        }
+        // Removed by hand; purge drops its entry instead of failing
+        fs::remove_file(&trashed[2].trashed_path).unwrap();
+
+        let result = purge_trash(&root, 7 * DAY_MS, 10 * DAY_MS).unwrap();
+        assert_eq!(
+            result,
+            TrashPurge {
+                removed: 1,
+                failed: 0,
+                bytes_freed: 5,
+                missing: 1,
+            }
+        );
+        assert!(!Path::new(&trashed[0].trashed_path).exists());
+        assert!(!root.join(TRASH_DIR).join("old").exists());
+        assert!(Path::new(&trashed[1].trashed_path).exists());
+        assert_eq!(read_manifest(&root).unwrap(), vec![trashed[1].clone()]);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
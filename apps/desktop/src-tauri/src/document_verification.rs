@@ -0,0 +1,670 @@
+// src-tauri/src/document_verification.rs
+//
+// Checksum scan over the documents folder (catches files corrupted on disk or a NAS)
+// Every file is re-hashed by a small worker pool and compared with documents.file_checksum.
+// Files are streamed through the hasher, never read into memory. Mismatches can then either
+// be accepted (refresh_document_checksums, after the user confirms) or replaced with the
+// synced copy from S3 (redownload_mismatched_documents).
+
+use chrono::Utc;
+use log::{error, info, warn};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::app_state::AppState;
+use crate::database::get_db;
+use crate::documents_migration::file_sha256;
+use crate::s3_service::{download_to_file, generate_s3_key};
+use crate::storage_usage::adjust_usage;
+use crate::telemetry::{track, track_async};
+
+/// Event emitted to the frontend once per checked document
+pub const VERIFY_PROGRESS_EVENT: &str = "documents-verify-progress";
+
+/// Files are mostly waiting on disk/network, but a NAS doesn't like many readers at once
+const MAX_WORKERS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VerifyStatus {
+    Ok,
+    Mismatch,
+    Missing,
+    Unreadable,
+    /// No checksum was ever stored, so there's nothing to compare against
+    NoChecksum,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentCheck {
+    pub document_id: String,
+    pub deal_id: String,
+    pub filename: String,
+    pub file_path: String,
+    pub status: VerifyStatus,
+    pub expected_checksum: Option<String>,
+    pub actual_checksum: Option<String>,
+    pub error: Option<String>,
+    /// Synced to S3, so redownload_mismatched_documents can restore it
+    pub can_redownload: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyProgress {
+    pub document_id: String,
+    /// Documents checked so far (they finish out of order)
+    pub checked: usize,
+    pub total: usize,
+    pub status: VerifyStatus,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct VerificationReport {
+    pub total: usize,
+    pub ok: usize,
+    pub mismatched: usize,
+    pub missing: usize,
+    pub unreadable: usize,
+    pub no_checksum: usize,
+    /// One entry per document, in the order they were stored
+    pub documents: Vec<DocumentCheck>,
+}
+
+impl VerificationReport {
+    fn from_checks(documents: Vec<DocumentCheck>) -> Self {
+        let mut report = VerificationReport {
+            total: documents.len(),
+            ..Default::default()
+        };
+        for check in &documents {
+            match check.status {
+                VerifyStatus::Ok => report.ok += 1,
+                VerifyStatus::Mismatch => report.mismatched += 1,
+                VerifyStatus::Missing => report.missing += 1,
+                VerifyStatus::Unreadable => report.unreadable += 1,
+                VerifyStatus::NoChecksum => report.no_checksum += 1,
+            }
+        }
+        report.documents = documents;
+        report
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StoredDocument {
+    id: String,
+    deal_id: String,
+    filename: String,
+    file_path: String,
+    file_checksum: Option<String>,
+    file_size: Option<i64>,
+    owner: String,
+    synced: bool,
+}
+
+/// Hash every document of the user (optionally just one deal) and compare with the stored
+/// checksums. Emits documents-verify-progress as files finish. Read-only.
+#[tauri::command]
+pub async fn verify_all_documents(
+    user_id: Option<String>,
+    deal_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<VerificationReport, String> {
+    let user_id_value = state.require_user(user_id)?;
+
+    track_async("verify_all_documents", async move {
+        let report = tauri::async_runtime::spawn_blocking(move || {
+            let documents = {
+                let db = get_db().map_err(|e| e.to_string())?;
+                let conn = db.conn();
+                load_documents(&conn, &user_id_value, deal_id.as_deref())
+                    .map_err(|e| e.to_string())?
+            };
+            info!("🔍 Verifying {} document checksums", documents.len());
+
+            let checks = verify_documents(&documents, worker_count(), |progress| {
+                if let Err(e) = app.emit(VERIFY_PROGRESS_EVENT, progress) {
+                    error!("❌ Failed to emit verify progress: {}", e);
+                }
+            });
+            Ok::<_, String>(VerificationReport::from_checks(checks))
+        })
+        .await
+        .map_err(|e| format!("Document verification task failed: {}", e))??;
+
+        info!(
+            "✅ Document verification finished: {} ok, {} mismatched, {} missing, {} unreadable, {} without checksum",
+            report.ok, report.mismatched, report.missing, report.unreadable, report.no_checksum
+        );
+        Ok(report)
+    })
+    .await
+}
+
+/// Accept the files as they are now: store their current checksum and size
+/// Only call after the user confirms. The HMAC signature (if any) is left alone, so a
+/// tampered file still fails signature verification.
+#[tauri::command]
+pub fn refresh_document_checksums(
+    document_ids: Vec<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DocumentCheck>, String> {
+    track("refresh_document_checksums", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+
+        let documents = selected_documents(&db.conn(), &user_id_value, &document_ids)?;
+        // Hash without holding the connection; the files may be on a slow share
+        let checks: Vec<DocumentCheck> = documents.iter().map(check_document).collect();
+
+        let mut conn = db.conn();
+        let checks = store_checksums(&mut conn, &documents, checks)?;
+        info!("✅ Refreshed checksums for {} documents", checks.len());
+        Ok(checks)
+    })
+}
+
+/// Replace mismatched (or missing) files with the copy synced to S3
+/// The download goes to a temporary file and only replaces the local file if it matches
+/// the stored checksum; otherwise the local file is left untouched.
+#[tauri::command]
+pub async fn redownload_mismatched_documents(
+    document_ids: Vec<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<DocumentCheck>, String> {
+    let user_id_value = state.require_user(user_id)?;
+
+    track_async("redownload_mismatched_documents", async move {
+        let documents = {
+            let db = get_db().map_err(|e| e.to_string())?;
+            let conn = db.conn();
+            selected_documents(&conn, &user_id_value, &document_ids)?
+        };
+
+        let mut results = Vec::with_capacity(documents.len());
+        for document in &documents {
+            let mut check = check_document(document);
+            if matches!(check.status, VerifyStatus::Mismatch | VerifyStatus::Missing) {
+                if let Err(e) = redownload(document).await {
+                    error!("❌ Failed to re-download document {}: {}", document.id, e);
+                    check.error = Some(e);
+                } else {
+                    check = check_document(document);
+                }
+            }
+            results.push(check);
+        }
+
+        let restored = results
+            .iter()
+            .filter(|check| check.status == VerifyStatus::Ok)
+            .count();
+        info!(
+            "✅ Re-download finished: {} of {} documents ok",
+            restored,
+            results.len()
+        );
+        Ok(results)
+    })
+    .await
+}
+
+async fn redownload(document: &StoredDocument) -> Result<(), String> {
+    if !document.synced {
+        return Err("Document was never synced to S3".to_string());
+    }
+    let expected = document
+        .file_checksum
+        .as_deref()
+        .ok_or_else(|| "No stored checksum to verify the download against".to_string())?;
+
+    let target = PathBuf::from(&document.file_path);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let s3_key = generate_s3_key(
+        &document.owner,
+        &document.deal_id,
+        &document.id,
+        &document.filename,
+    );
+    let download = download_path(&target);
+    if let Err(e) = download_to_file(&s3_key, &download).await {
+        let _ = fs::remove_file(&download);
+        return Err(e);
+    }
+    replace_if_matches(&download, &target, expected)
+}
+
+fn download_path(target: &Path) -> PathBuf {
+    let mut name = target.as_os_str().to_os_string();
+    name.push(".download");
+    PathBuf::from(name)
+}
+
+/// Move `download` over `target` if its hash is `expected`; the download is removed either way
+fn replace_if_matches(download: &Path, target: &Path, expected: &str) -> Result<(), String> {
+    let actual = match file_sha256(download) {
+        Ok(actual) => actual,
+        Err(e) => {
+            let _ = fs::remove_file(download);
+            return Err(format!("Failed to hash download: {}", e));
+        }
+    };
+    if !checksums_match(expected, &actual) {
+        let _ = fs::remove_file(download);
+        return Err("The S3 copy doesn't match the stored checksum either".to_string());
+    }
+    fs::rename(download, target).map_err(|e| {
+        let _ = fs::remove_file(download);
+        format!("Failed to replace {}: {}", target.display(), e)
+    })
+}
+
+fn worker_count() -> usize {
+    thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(MAX_WORKERS)
+}
+
+/// Check every document on up to `workers` threads, reporting each as it finishes
+/// Results come back in the same order as `documents`
+fn verify_documents(
+    documents: &[StoredDocument],
+    workers: usize,
+    mut on_progress: impl FnMut(&VerifyProgress),
+) -> Vec<DocumentCheck> {
+    let total = documents.len();
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    let mut checks: Vec<Option<DocumentCheck>> = vec![None; total];
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, total.max(1)) {
+            let sender = sender.clone();
+            let next = &next;
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(document) = documents.get(index) else {
+                    break;
+                };
+                if sender.send((index, check_document(document))).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        for (checked, (index, check)) in receiver.iter().enumerate() {
+            on_progress(&VerifyProgress {
+                document_id: check.document_id.clone(),
+                checked: checked + 1,
+                total,
+                status: check.status,
+            });
+            checks[index] = Some(check);
+        }
+    });
+
+    checks.into_iter().flatten().collect()
+}
+
+fn check_document(document: &StoredDocument) -> DocumentCheck {
+    let mut check = DocumentCheck {
+        document_id: document.id.clone(),
+        deal_id: document.deal_id.clone(),
+        filename: document.filename.clone(),
+        file_path: document.file_path.clone(),
+        status: VerifyStatus::Ok,
+        expected_checksum: document.file_checksum.clone(),
+        actual_checksum: None,
+        error: None,
+        can_redownload: document.synced && document.file_checksum.is_some(),
+    };
+
+    match file_sha256(Path::new(&document.file_path)) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            warn!("⚠️  Document file missing: {}", document.file_path);
+            check.status = VerifyStatus::Missing;
+        }
+        Err(e) => {
+            warn!("⚠️  Document file unreadable {}: {}", document.file_path, e);
+            check.status = VerifyStatus::Unreadable;
+            check.error = Some(e.to_string());
+        }
+        Ok(actual) => {
+            check.status = match document.file_checksum.as_deref() {
+                None => VerifyStatus::NoChecksum,
+                Some(expected) if checksums_match(expected, &actual) => VerifyStatus::Ok,
+                Some(_) => {
+                    warn!("⚠️  Checksum mismatch: {}", document.file_path);
+                    VerifyStatus::Mismatch
+                }
+            };
+            check.actual_checksum = Some(actual);
+        }
+    }
+    check
+}
+
+fn checksums_match(expected: &str, actual: &str) -> bool {
+    expected.trim().eq_ignore_ascii_case(actual)
+}
+
+/// The user's documents (documents inherit the deal's owner when they have none)
+fn load_documents(
+    conn: &Connection,
+    user_id: &str,
+    deal_id: Option<&str>,
+) -> rusqlite::Result<Vec<StoredDocument>> {
+    let mut stmt = conn.prepare(
+        "SELECT d.id, d.deal_id, d.filename, d.file_path, d.file_checksum, d.file_size,
+                COALESCE(d.user_id, deals.user_id), d.synced_at IS NOT NULL
+         FROM documents d LEFT JOIN deals ON deals.id = d.deal_id
+         WHERE COALESCE(d.user_id, deals.user_id) = ?1 AND (?2 IS NULL OR d.deal_id = ?2)
+         ORDER BY d.created_at, d.id",
+    )?;
+
+    let documents = stmt
+        .query_map(params![user_id, deal_id], |row| {
+            Ok(StoredDocument {
+                id: row.get(0)?,
+                deal_id: row.get(1)?,
+                filename: row.get(2)?,
+                file_path: row.get(3)?,
+                file_checksum: row.get(4)?,
+                file_size: row.get(5)?,
+                owner: row.get(6)?,
+                synced: row.get(7)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(documents)
+}
+
+fn selected_documents(
+    conn: &Connection,
+    user_id: &str,
+    document_ids: &[String],
+) -> Result<Vec<StoredDocument>, String> {
+    let documents: Vec<StoredDocument> = load_documents(conn, user_id, None)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|document| document_ids.contains(&document.id))
+        .collect();
+
+    if documents.len() != document_ids.len() {
+        return Err("Document not found or access denied".to_string());
+    }
+    Ok(documents)
+}
+
+/// Write the freshly computed checksums (and sizes) for readable files, keeping storage
+/// usage in step with any size change. Unreadable/missing files are returned unchanged.
+fn store_checksums(
+    conn: &mut Connection,
+    documents: &[StoredDocument],
+    checks: Vec<DocumentCheck>,
+) -> Result<Vec<DocumentCheck>, String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let now = Utc::now().timestamp_millis();
+
+    let mut updated = Vec::with_capacity(checks.len());
+    for (document, mut check) in documents.iter().zip(checks) {
+        let Some(actual) = check.actual_checksum.clone() else {
+            updated.push(check);
+            continue;
+        };
+        let size = fs::metadata(&document.file_path)
+            .map(|metadata| metadata.len() as i64)
+            .map_err(|e| format!("Failed to read {}: {}", document.file_path, e))?;
+
+        tx.execute(
+            "UPDATE documents SET file_checksum = ?2, file_size = ?3, updated_at = ?4 WHERE id = ?1",
+            params![document.id, actual, size, now],
+        )
+        .map_err(|e| e.to_string())?;
+        adjust_usage(&tx, &document.owner, size - document.file_size.unwrap_or(0))
+            .map_err(|e| e.to_string())?;
+
+        check.expected_checksum = Some(actual);
+        check.status = VerifyStatus::Ok;
+        updated.push(check);
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(updated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn sha256(content: &[u8]) -> String {
+        use sha2::{Digest, Sha256};
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/006_add_storage_usage.sql"),
+            include_str!("../migrations/007_add_document_signature.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO clients (id, user_id, first_name, last_name, created_at, updated_at)
+             VALUES ('c1', 'user-1', 'Ada', 'Lovelace', 1, 1);
+             INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status, created_at, updated_at)
+             VALUES ('v1', '1HGCM82633A004352', 2021, 'Honda', 'Civic', 100, 1000, 'sold', 1, 1);
+             INSERT INTO deals (id, user_id, type, client_id, vehicle_id, status, total_amount, created_at, updated_at)
+             VALUES ('deal-1', 'user-1', 'retail', 'c1', 'v1', 'pending', 1000, 1, 1),
+                    ('deal-2', 'user-2', 'retail', 'c1', 'v1', 'pending', 1000, 1, 1);",
+        )
+        .unwrap();
+        conn
+    }
+
+    /// Writes the file (unless content is None) and records `checksum` for it
+    fn seed(
+        conn: &Connection,
+        dir: &Path,
+        id: &str,
+        deal_id: &str,
+        content: Option<&[u8]>,
+        checksum: Option<String>,
+    ) {
+        let path = dir.join(format!("{}.pdf", id));
+        if let Some(content) = content {
+            fs::write(&path, content).unwrap();
+        }
+        conn.execute(
+            "INSERT INTO documents (id, deal_id, type, filename, file_path, file_size, file_checksum,
+                                    created_at, updated_at, synced_at)
+             VALUES (?1, ?2, 'bill_of_sale', ?3, ?4, ?5, ?6,
+                     (SELECT COUNT(*) FROM documents), 1, 5)",
+            params![
+                id,
+                deal_id,
+                format!("{}.pdf", id),
+                path.to_string_lossy().to_string(),
+                content.map(|c| c.len() as i64),
+                checksum
+            ],
+        )
+        .unwrap();
+    }
+
+    /// ok, corrupted on disk, missing, never hashed
+    fn seeded(dir: &Path) -> Connection {
+        let conn = test_db();
+        seed(
+            &conn,
+            dir,
+            "doc-ok",
+            "deal-1",
+            Some(b"%PDF-1.7 good"),
+            Some(sha256(b"%PDF-1.7 good")),
+        );
+        seed(
+            &conn,
+            dir,
+            "doc-bad",
+            "deal-1",
+            Some(b"%PDF-1.7 g\0\0d"),
+            Some(sha256(b"%PDF-1.7 good")),
+        );
+        seed(
+            &conn,
+            dir,
+            "doc-gone",
+            "deal-1",
+            None,
+            Some(sha256(b"%PDF-1.7 gone")),
+        );
+        seed(&conn, dir, "doc-new", "deal-1", Some(b"%PDF-1.7 new"), None);
+        seed(
+            &conn,
+            dir,
+            "doc-other",
+            "deal-2",
+            Some(b"%PDF-1.7 other"),
+            None,
+        );
+        conn
+    }
+
+    #[test]
+    fn test_reports_each_document_in_order() {
+        let dir = temp_dir("docs-verify", "report");
+        let conn = seeded(&dir);
+
+        let documents = load_documents(&conn, "user-1", None).unwrap();
+        let mut progress = Vec::new();
+        let checks = verify_documents(&documents, 3, |p| progress.push(p.clone()));
+
+        let statuses: Vec<_> = checks
+            .iter()
+            .map(|check| (check.document_id.as_str(), check.status))
+            .collect();
+        // doc-other belongs to another user
+        assert_eq!(
+            statuses,
+            [
+                ("doc-ok", VerifyStatus::Ok),
+                ("doc-bad", VerifyStatus::Mismatch),
+                ("doc-gone", VerifyStatus::Missing),
+                ("doc-new", VerifyStatus::NoChecksum),
+            ]
+        );
+        assert_eq!(checks[1].actual_checksum, Some(sha256(b"%PDF-1.7 g\0\0d")));
+        assert!(checks[1].can_redownload);
+
+        // One event per document, counting up to the total
+        assert_eq!(progress.len(), 4);
+        assert_eq!(
+            progress.iter().map(|p| p.checked).collect::<Vec<_>>(),
+            [1, 2, 3, 4]
+        );
+        assert!(progress.iter().all(|p| p.total == 4));
+
+        let report = VerificationReport::from_checks(checks);
+        assert_eq!(
+            (
+                report.ok,
+                report.mismatched,
+                report.missing,
+                report.no_checksum
+            ),
+            (1, 1, 1, 1)
+        );
+
+        // Scoped to one deal
+        assert!(load_documents(&conn, "user-1", Some("deal-2"))
+            .unwrap()
+            .is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_unreadable_file() {
+        let dir = temp_dir("docs-verify", "unreadable");
+        let conn = test_db();
+        seed(&conn, &dir, "doc-dir", "deal-1", None, Some(sha256(b"x")));
+        // A directory where the file should be can't be read as one
+        fs::create_dir_all(dir.join("doc-dir.pdf")).unwrap();
+
+        let documents = load_documents(&conn, "user-1", None).unwrap();
+        let checks = verify_documents(&documents, 1, |_| {});
+        assert_eq!(checks[0].status, VerifyStatus::Unreadable);
+        assert!(checks[0].error.is_some());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_refresh_stores_current_checksum() {
+        let dir = temp_dir("docs-verify", "refresh");
+        let mut conn = seeded(&dir);
+
+        let ids = ["doc-bad".to_string(), "doc-gone".to_string()];
+        let documents = selected_documents(&conn, "user-1", &ids).unwrap();
+        let checks = documents.iter().map(check_document).collect();
+        let checks = store_checksums(&mut conn, &documents, checks).unwrap();
+        assert_eq!(checks[0].status, VerifyStatus::Ok);
+        assert_eq!(checks[1].status, VerifyStatus::Missing);
+
+        let stored: String = conn
+            .query_row(
+                "SELECT file_checksum FROM documents WHERE id = 'doc-bad'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(stored, sha256(b"%PDF-1.7 g\0\0d"));
+
+        let documents = load_documents(&conn, "user-1", None).unwrap();
+        let checks = verify_documents(&documents, 2, |_| {});
+        assert_eq!(checks[1].status, VerifyStatus::Ok);
+
+        // Other users' documents can't be touched
+        let ids = ["doc-other".to_string()];
+        assert!(selected_documents(&conn, "user-1", &ids).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_download_replaces_only_when_it_matches() {
+        let dir = temp_dir("docs-verify", "replace");
+        let target = dir.join("doc.pdf");
+        fs::write(&target, b"corrupt").unwrap();
+
+        let download = download_path(&target);
+        fs::write(&download, b"also wrong").unwrap();
+        assert!(replace_if_matches(&download, &target, &sha256(b"good")).is_err());
+        assert_eq!(fs::read(&target).unwrap(), b"corrupt");
+        assert!(!download.exists());
+
+        fs::write(&download, b"good").unwrap();
+        replace_if_matches(&download, &target, &sha256(b"good").to_uppercase()).unwrap();
+        assert_eq!(fs::read(&target).unwrap(), b"good");
+        assert!(!download.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
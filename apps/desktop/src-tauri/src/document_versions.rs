@@ -0,0 +1,520 @@
+// src-tauri/src/document_versions.rs
+//
+// Earlier versions of documents that were regenerated (corrected bill of sale, re-signed form)
+// Before db_update_document points a document at a new file or checksum, the current file
+// is copied to versions/{document_id}/v{n}_{name} next to it and recorded here. Only the
+// newest document_versions_max versions are kept; older ones are pruned with their files.
+
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::database::{db_get_setting, get_db, new_row_id, Document};
+use crate::documents_migration::file_sha256;
+use crate::telemetry::track;
+
+/// Settings key holding how many versions to keep per document (0 turns versioning off)
+pub const MAX_VERSIONS_SETTING: &str = "document_versions_max";
+
+pub const DEFAULT_MAX_VERSIONS: usize = 10;
+
+const VERSIONS_DIR: &str = "versions";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DocumentVersion {
+    pub id: String,
+    pub document_id: String,
+    pub version: i64,
+    pub file_path: String,
+    pub file_size: Option<i64>,
+    pub file_checksum: Option<String>,
+    pub created_at: i64,
+    pub reason: Option<String>,
+}
+
+/// Configured versions to keep per document, falling back to the default
+pub fn max_versions() -> usize {
+    db_get_setting(MAX_VERSIONS_SETTING.to_string())
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse::<usize>().ok())
+        .unwrap_or(DEFAULT_MAX_VERSIONS)
+}
+
+/// Versions of a document, newest first
+#[tauri::command]
+pub fn db_get_document_versions(document_id: String) -> Result<Vec<DocumentVersion>, String> {
+    track("db_get_document_versions", || {
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        versions_for(&conn, &document_id).map_err(|e| e.to_string())
+    })
+}
+
+/// Put a version's file back as the document's current file
+/// The file being replaced is kept as a version itself, so a restore can be undone. The
+/// signature is cleared because it was made for the replaced content.
+#[tauri::command]
+pub fn db_restore_document_version(document_id: String, version: i64) -> Result<Document, String> {
+    track("db_restore_document_version", || {
+        let keep_versions = max_versions();
+        let db = get_db().map_err(|e| e.to_string())?;
+        let mut conn = db.conn();
+        let document = restore_version(&mut conn, &document_id, version, keep_versions)?;
+
+        info!("✅ Document {} restored to version {}", document_id, version);
+        Ok(document)
+    })
+}
+
+/// Copy the document's current file into its versions folder and record it
+/// Returns None when there's nothing to keep: versioning is off, the file is gone, or it
+/// already has `incoming_checksum` (it was overwritten before the update came in).
+pub(crate) fn snapshot_document(
+    conn: &Connection,
+    document: &Document,
+    incoming_checksum: Option<&str>,
+    reason: Option<&str>,
+    keep_versions: usize,
+) -> Result<Option<DocumentVersion>, String> {
+    if keep_versions == 0 {
+        return Ok(None);
+    }
+    let source = Path::new(&document.file_path);
+    if !source.is_file() {
+        warn!(
+            "⚠️  No file to keep as a version for document {}: {}",
+            document.id, document.file_path
+        );
+        return Ok(None);
+    }
+
+    let version: i64 = conn
+        .query_row(
+            "SELECT COALESCE(MAX(version), 0) + 1 FROM document_versions WHERE document_id = ?1",
+            params![document.id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let file_name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| document.filename.clone());
+    let destination =
+        versions_dir(source, &document.id).join(format!("v{}_{}", version, file_name));
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    let file_size = fs::copy(source, &destination)
+        .map_err(|e| format!("Failed to keep previous version: {}", e))?;
+    let checksum = match file_sha256(&destination) {
+        Ok(checksum) => checksum,
+        Err(e) => {
+            let _ = fs::remove_file(&destination);
+            return Err(format!("Failed to hash previous version: {}", e));
+        }
+    };
+
+    if incoming_checksum.is_some_and(|incoming| incoming.eq_ignore_ascii_case(&checksum)) {
+        warn!(
+            "⚠️  Document {} was overwritten before it was updated; previous version not kept",
+            document.id
+        );
+        let _ = fs::remove_file(&destination);
+        return Ok(None);
+    }
+
+    let snapshot = DocumentVersion {
+        id: new_row_id(),
+        document_id: document.id.clone(),
+        version,
+        file_path: destination.to_string_lossy().to_string(),
+        file_size: Some(file_size as i64),
+        file_checksum: Some(checksum),
+        created_at: Utc::now().timestamp_millis(),
+        reason: reason.map(str::to_string),
+    };
+    if let Err(e) = conn.execute(
+        "INSERT INTO document_versions (id, document_id, version, file_path, file_size,
+                                        file_checksum, created_at, reason)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            snapshot.id,
+            snapshot.document_id,
+            snapshot.version,
+            snapshot.file_path,
+            snapshot.file_size,
+            snapshot.file_checksum,
+            snapshot.created_at,
+            snapshot.reason,
+        ],
+    ) {
+        let _ = fs::remove_file(&destination);
+        return Err(e.to_string());
+    }
+
+    prune_versions(conn, &document.id, keep_versions).map_err(|e| e.to_string())?;
+    info!("📚 Kept version {} of document {}", version, document.id);
+    Ok(Some(snapshot))
+}
+
+/// Files of every version of a document (to remove after the document is deleted)
+pub(crate) fn version_file_paths(
+    conn: &Connection,
+    document_id: &str,
+) -> rusqlite::Result<Vec<PathBuf>> {
+    let mut stmt =
+        conn.prepare("SELECT file_path FROM document_versions WHERE document_id = ?1")?;
+    let paths = stmt
+        .query_map(params![document_id], |row| {
+            row.get::<_, String>(0).map(PathBuf::from)
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(paths)
+}
+
+pub(crate) fn remove_version_files(paths: &[PathBuf]) {
+    for path in paths {
+        if let Err(e) = fs::remove_file(path) {
+            warn!("⚠️  Failed to remove document version {:?}: {}", path, e);
+        }
+        // Drops the per-document folder once it's empty
+        if let Some(parent) = path.parent() {
+            let _ = fs::remove_dir(parent);
+        }
+    }
+}
+
+fn versions_dir(file_path: &Path, document_id: &str) -> PathBuf {
+    file_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .join(VERSIONS_DIR)
+        .join(document_id)
+}
+
+/// Drop all but the newest `keep` versions, oldest first, along with their files
+fn prune_versions(conn: &Connection, document_id: &str, keep: usize) -> rusqlite::Result<()> {
+    let pruned: Vec<(String, PathBuf)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, file_path FROM document_versions WHERE document_id = ?1
+             ORDER BY version DESC LIMIT -1 OFFSET ?2",
+        )?;
+        let rows = stmt
+            .query_map(params![document_id, keep as i64], |row| {
+                Ok((row.get(0)?, PathBuf::from(row.get::<_, String>(1)?)))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows
+    };
+
+    for (id, _) in &pruned {
+        conn.execute("DELETE FROM document_versions WHERE id = ?1", params![id])?;
+    }
+    let files: Vec<PathBuf> = pruned.into_iter().map(|(_, path)| path).collect();
+    remove_version_files(&files);
+    Ok(())
+}
+
+fn version_from_row(row: &rusqlite::Row) -> rusqlite::Result<DocumentVersion> {
+    Ok(DocumentVersion {
+        id: row.get(0)?,
+        document_id: row.get(1)?,
+        version: row.get(2)?,
+        file_path: row.get(3)?,
+        file_size: row.get(4)?,
+        file_checksum: row.get(5)?,
+        created_at: row.get(6)?,
+        reason: row.get(7)?,
+    })
+}
+
+fn versions_for(conn: &Connection, document_id: &str) -> rusqlite::Result<Vec<DocumentVersion>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, document_id, version, file_path, file_size, file_checksum, created_at, reason
+         FROM document_versions WHERE document_id = ?1 ORDER BY version DESC",
+    )?;
+    let versions = stmt
+        .query_map(params![document_id], version_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(versions)
+}
+
+fn restore_version(
+    conn: &mut Connection,
+    document_id: &str,
+    version: i64,
+    keep_versions: usize,
+) -> Result<Document, String> {
+    let mut document = conn
+        .query_row(
+            "SELECT id, deal_id, type, filename, file_path, file_size, file_checksum,
+                    created_at, updated_at, synced_at, signature
+             FROM documents WHERE id = ?1",
+            params![document_id],
+            Document::from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Document not found".to_string())?;
+    let restored = conn
+        .query_row(
+            "SELECT id, document_id, version, file_path, file_size, file_checksum, created_at, reason
+             FROM document_versions WHERE document_id = ?1 AND version = ?2",
+            params![document_id, version],
+            version_from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Version {} of this document not found", version))?;
+
+    // Staged next to the target first: keeping the current file may prune this very version
+    let target = PathBuf::from(&document.file_path);
+    let mut staged = target.as_os_str().to_os_string();
+    staged.push(".restore");
+    let staged = PathBuf::from(staged);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+    fs::copy(&restored.file_path, &staged)
+        .map_err(|e| format!("Failed to read version {}: {}", version, e))?;
+
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let reason = format!("Replaced by restoring version {}", version);
+    let result = snapshot_document(
+        &tx,
+        &document,
+        restored.file_checksum.as_deref(),
+        Some(&reason),
+        keep_versions,
+    )
+    .and_then(|_| {
+        document.file_size = restored.file_size;
+        document.file_checksum = restored.file_checksum.clone();
+        document.signature = None;
+        document.updated_at = Utc::now().timestamp_millis();
+        tx.execute(
+            "UPDATE documents SET file_size = ?2, file_checksum = ?3, signature = NULL,
+                                  updated_at = ?4
+             WHERE id = ?1",
+            params![
+                document.id,
+                document.file_size,
+                document.file_checksum,
+                document.updated_at
+            ],
+        )
+        .map_err(|e| e.to_string())
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_file(&staged);
+        return Err(e);
+    }
+
+    fs::rename(&staged, &target).map_err(|e| {
+        let _ = fs::remove_file(&staged);
+        format!("Failed to restore version {}: {}", version, e)
+    })?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(document)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::update_document;
+    use crate::test_support::temp_dir;
+    use serde_json::json;
+
+    fn sha256(content: &[u8]) -> String {
+        use sha2::{Digest, Sha256};
+        format!("{:x}", Sha256::digest(content))
+    }
+
+    fn test_db(dir: &Path, content: &[u8]) -> (Connection, Document) {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/007_add_document_signature.sql"),
+            include_str!("../migrations/017_add_document_versions.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+
+        let path = dir.join("bill_of_sale.pdf");
+        fs::write(&path, content).unwrap();
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, created_at, updated_at)
+             VALUES ('c1', 'Ada', 'Lovelace', 1, 1);
+             INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status, created_at, updated_at)
+             VALUES ('v1', '1HGCM82633A004352', 2021, 'Honda', 'Civic', 0, 1.0, 'sold', 1, 1);
+             INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount, created_at, updated_at)
+             VALUES ('deal-1', 'retail', 'c1', 'v1', 'pending', 1.0, 1, 1);",
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO documents (id, deal_id, type, filename, file_path, file_size, file_checksum,
+                                    created_at, updated_at, signature)
+             VALUES ('doc-1', 'deal-1', 'bill_of_sale', 'bill_of_sale.pdf', ?1, ?2, ?3, 1, 1, 'sig')",
+            params![
+                path.to_string_lossy().to_string(),
+                content.len() as i64,
+                sha256(content)
+            ],
+        )
+        .unwrap();
+
+        let document = load(&conn);
+        (conn, document)
+    }
+
+    fn load(conn: &Connection) -> Document {
+        conn.query_row(
+            "SELECT id, deal_id, type, filename, file_path, file_size, file_checksum,
+                    created_at, updated_at, synced_at, signature
+             FROM documents WHERE id = 'doc-1'",
+            [],
+            Document::from_row,
+        )
+        .unwrap()
+    }
+
+    /// What the frontend does when regenerating in place: update the row, then write the file
+    fn regenerate(conn: &mut Connection, content: &[u8], keep: usize) -> Document {
+        let document = load(conn);
+        let updated = update_document(
+            conn,
+            document.clone(),
+            &json!({ "file_checksum": sha256(content), "file_size": content.len(),
+                     "version_reason": "Corrected price" }),
+            keep,
+        )
+        .unwrap();
+        fs::write(&document.file_path, content).unwrap();
+        updated
+    }
+
+    #[test]
+    fn test_update_keeps_previous_file() {
+        let dir = temp_dir("docs-versions", "snapshot");
+        let (mut conn, original) = test_db(&dir, b"signed original");
+
+        regenerate(&mut conn, b"corrected", 10);
+
+        let versions = versions_for(&conn, "doc-1").unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(versions[0].version, 1);
+        assert_eq!(versions[0].reason.as_deref(), Some("Corrected price"));
+        assert_eq!(versions[0].file_checksum, original.file_checksum);
+        assert_eq!(
+            fs::read(&versions[0].file_path).unwrap(),
+            b"signed original"
+        );
+        assert!(versions[0].file_path.contains("versions"));
+        assert_eq!(fs::read(&original.file_path).unwrap(), b"corrected");
+
+        // Only the filename changing isn't a new version
+        let document = load(&conn);
+        update_document(
+            &mut conn,
+            document,
+            &json!({ "filename": "renamed.pdf" }),
+            10,
+        )
+        .unwrap();
+        assert_eq!(versions_for(&conn, "doc-1").unwrap().len(), 1);
+
+        // A file already overwritten with the new content has nothing left to keep
+        let document = load(&conn);
+        fs::write(&document.file_path, b"third").unwrap();
+        update_document(
+            &mut conn,
+            document,
+            &json!({ "file_checksum": sha256(b"third") }),
+            10,
+        )
+        .unwrap();
+        assert_eq!(versions_for(&conn, "doc-1").unwrap().len(), 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_prunes_oldest_versions_and_files() {
+        let dir = temp_dir("docs-versions", "prune");
+        let (mut conn, _) = test_db(&dir, b"v1");
+
+        regenerate(&mut conn, b"v2", 2);
+        let oldest = versions_for(&conn, "doc-1").unwrap().remove(0);
+        regenerate(&mut conn, b"v3", 2);
+        regenerate(&mut conn, b"v4", 2);
+
+        let versions = versions_for(&conn, "doc-1").unwrap();
+        assert_eq!(
+            versions.iter().map(|v| v.version).collect::<Vec<_>>(),
+            [3, 2]
+        );
+        assert!(!Path::new(&oldest.file_path).exists());
+        assert_eq!(fs::read(&versions[0].file_path).unwrap(), b"v3");
+
+        // 0 turns versioning off
+        regenerate(&mut conn, b"v5", 0);
+        assert_eq!(versions_for(&conn, "doc-1").unwrap().len(), 2);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_restore_puts_version_back_and_keeps_current() {
+        let dir = temp_dir("docs-versions", "restore");
+        let (mut conn, original) = test_db(&dir, b"signed original");
+        regenerate(&mut conn, b"corrected", 10);
+
+        let restored = restore_version(&mut conn, "doc-1", 1, 10).unwrap();
+        assert_eq!(fs::read(&original.file_path).unwrap(), b"signed original");
+        assert_eq!(restored.file_checksum, original.file_checksum);
+        assert_eq!(restored.signature, None);
+        assert_eq!(load(&conn).file_checksum, original.file_checksum);
+
+        // The corrected file became version 2, so the restore can be undone
+        let versions = versions_for(&conn, "doc-1").unwrap();
+        assert_eq!(versions[0].version, 2);
+        assert_eq!(fs::read(&versions[0].file_path).unwrap(), b"corrected");
+
+        // Restoring with a cap of one prunes the restored version itself, after copying it
+        restore_version(&mut conn, "doc-1", 2, 1).unwrap();
+        assert_eq!(fs::read(&original.file_path).unwrap(), b"corrected");
+        let versions = versions_for(&conn, "doc-1").unwrap();
+        assert_eq!(versions.len(), 1);
+        assert_eq!(
+            fs::read(&versions[0].file_path).unwrap(),
+            b"signed original"
+        );
+
+        assert!(restore_version(&mut conn, "doc-1", 99, 10).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_deleting_the_document_drops_versions() {
+        let dir = temp_dir("docs-versions", "delete");
+        let (mut conn, _) = test_db(&dir, b"v1");
+        regenerate(&mut conn, b"v2", 10);
+
+        let files = version_file_paths(&conn, "doc-1").unwrap();
+        conn.execute("DELETE FROM documents WHERE id = 'doc-1'", [])
+            .unwrap();
+        remove_version_files(&files);
+
+        assert!(versions_for(&conn, "doc-1").unwrap().is_empty());
+        assert!(!files[0].exists());
+        assert!(!dir.join("versions").join("doc-1").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,437 @@
+// src-tauri/src/documents_migration.rs
+//
+// Move/copy existing documents when the user picks a new documents root
+// Keeps documents.file_path pointing at files the app can actually find
+
+use chrono::Utc;
+use log::{error, info, warn};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::MutexGuard;
+use tauri::{AppHandle, Emitter};
+
+use crate::database::get_db;
+use crate::storage::invalidate_storage_stats;
+
+/// Event emitted to the frontend once per document
+pub const MIGRATION_PROGRESS_EVENT: &str = "documents-migration-progress";
+
+/// Abort (and roll back) on the first copy failure unless the caller allows more
+const DEFAULT_MAX_FAILURES: usize = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MigrationStatus {
+    Copied,
+    Missing,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationProgress {
+    pub document_id: String,
+    pub index: usize,
+    pub total: usize,
+    pub status: MigrationStatus,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationFailure {
+    pub document_id: String,
+    pub path: String,
+    pub error: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct MigrationReport {
+    pub migrated: usize,
+    /// Referenced files that weren't in the old root (left untouched)
+    pub missing: Vec<String>,
+    /// Documents stored outside the old root (left untouched)
+    pub skipped: usize,
+    pub failed: Vec<MigrationFailure>,
+    /// True when failures exceeded max_failures and nothing was changed
+    pub rolled_back: bool,
+}
+
+struct CopiedDocument {
+    id: String,
+    source: PathBuf,
+    destination: PathBuf,
+}
+
+/// Copy (or move) every document under old_path into new_path and rewrite file_path
+/// Files are copied and checksum-verified first; the DB is only updated if at most
+/// max_failures files failed, otherwise the copies are removed and nothing changes.
+/// Call store_documents_root_path afterwards to switch the app to the new root.
+#[tauri::command]
+pub async fn migrate_documents_root(
+    old_path: String,
+    new_path: String,
+    move_files: bool,
+    max_failures: Option<usize>,
+    app: AppHandle,
+) -> Result<MigrationReport, String> {
+    info!(
+        "📦 Migrating documents root: {} -> {} (move: {})",
+        old_path, new_path, move_files
+    );
+
+    let old_root = PathBuf::from(&old_path);
+    let new_root = PathBuf::from(&new_path);
+
+    if !old_root.is_dir() {
+        return Err(format!(
+            "Old documents directory does not exist: {}",
+            old_path
+        ));
+    }
+    if new_root.starts_with(&old_root) || old_root.starts_with(&new_root) {
+        return Err(
+            "New documents directory must not be inside the old one (or vice versa)".to_string(),
+        );
+    }
+    fs::create_dir_all(&new_root)
+        .map_err(|e| format!("Failed to create new documents directory: {}", e))?;
+
+    let max_failures = max_failures.unwrap_or(DEFAULT_MAX_FAILURES);
+
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        let db = get_db().map_err(|e| e.to_string())?;
+        run_migration(
+            || db.conn(),
+            &old_root,
+            &new_root,
+            move_files,
+            max_failures,
+            |progress| {
+                if let Err(e) = app.emit(MIGRATION_PROGRESS_EVENT, progress) {
+                    error!("❌ Failed to emit migration progress: {}", e);
+                }
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("Documents migration task failed: {}", e))??;
+
+    invalidate_storage_stats();
+
+    info!(
+        "✅ Documents migration finished: {} migrated, {} missing, {} failed, rolled back: {}",
+        report.migrated,
+        report.missing.len(),
+        report.failed.len(),
+        report.rolled_back
+    );
+    Ok(report)
+}
+
+/// Takes a lock function rather than a connection so the DB isn't held during file copies
+fn run_migration<'a>(
+    lock: impl Fn() -> MutexGuard<'a, Connection>,
+    old_root: &Path,
+    new_root: &Path,
+    move_files: bool,
+    max_failures: usize,
+    mut on_progress: impl FnMut(&MigrationProgress),
+) -> Result<MigrationReport, String> {
+    let documents = load_document_paths(&lock())?;
+    let total = documents.len();
+
+    let mut report = MigrationReport::default();
+    let mut copied = Vec::new();
+
+    for (index, (id, file_path)) in documents.into_iter().enumerate() {
+        let source = PathBuf::from(&file_path);
+
+        let status = match source.strip_prefix(old_root) {
+            Err(_) => {
+                report.skipped += 1;
+                MigrationStatus::Skipped
+            }
+            Ok(_) if !source.is_file() => {
+                warn!("⚠️  Document file missing from old root: {}", file_path);
+                report.missing.push(file_path.clone());
+                MigrationStatus::Missing
+            }
+            Ok(relative) => {
+                let destination = new_root.join(relative);
+                match copy_verified(&source, &destination) {
+                    Ok(()) => {
+                        copied.push(CopiedDocument {
+                            id: id.clone(),
+                            source,
+                            destination,
+                        });
+                        MigrationStatus::Copied
+                    }
+                    Err(e) => {
+                        error!("❌ Failed to copy {}: {}", file_path, e);
+                        report.failed.push(MigrationFailure {
+                            document_id: id.clone(),
+                            path: file_path.clone(),
+                            error: e,
+                        });
+                        MigrationStatus::Failed
+                    }
+                }
+            }
+        };
+
+        on_progress(&MigrationProgress {
+            document_id: id,
+            index: index + 1,
+            total,
+            status,
+        });
+    }
+
+    if report.failed.len() > max_failures {
+        warn!(
+            "⚠️  {} files failed (limit {}), rolling back migration",
+            report.failed.len(),
+            max_failures
+        );
+        remove_copies(&copied);
+        report.rolled_back = true;
+        return Ok(report);
+    }
+
+    if let Err(e) = rewrite_paths(&mut lock(), &copied) {
+        remove_copies(&copied);
+        return Err(e);
+    }
+    report.migrated = copied.len();
+
+    // Only drop the originals once the DB points at the new copies
+    if move_files {
+        for document in &copied {
+            if let Err(e) = fs::remove_file(&document.source) {
+                warn!(
+                    "⚠️  Copied but could not remove original {:?}: {}",
+                    document.source, e
+                );
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+fn load_document_paths(conn: &Connection) -> Result<Vec<(String, String)>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id, file_path FROM documents ORDER BY created_at")
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows)
+}
+
+fn rewrite_paths(conn: &mut Connection, copied: &[CopiedDocument]) -> Result<(), String> {
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+    let now = Utc::now().timestamp_millis();
+
+    for document in copied {
+        tx.execute(
+            "UPDATE documents SET file_path = ?2, updated_at = ?3 WHERE id = ?1",
+            params![
+                document.id,
+                document.destination.to_string_lossy().to_string(),
+                now
+            ],
+        )
+        .map_err(|e| format!("Failed to update document {}: {}", document.id, e))?;
+    }
+
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// fs::copy works across drives (unlike rename); the copy is hashed against the source
+fn copy_verified(source: &Path, destination: &Path) -> Result<(), String> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create directory: {}", e))?;
+    }
+
+    fs::copy(source, destination).map_err(|e| format!("Failed to copy file: {}", e))?;
+
+    let expected = file_sha256(source).map_err(|e| format!("Failed to hash source: {}", e))?;
+    let actual = file_sha256(destination).map_err(|e| format!("Failed to hash copy: {}", e))?;
+
+    if expected != actual {
+        let _ = fs::remove_file(destination);
+        return Err("Checksum mismatch after copy".to_string());
+    }
+
+    Ok(())
+}
+
+fn remove_copies(copied: &[CopiedDocument]) {
+    for document in copied {
+        if let Err(e) = fs::remove_file(&document.destination) {
+            warn!(
+                "⚠️  Failed to remove copied file {:?}: {}",
+                document.destination, e
+            );
+        }
+    }
+}
+
+pub(crate) fn file_sha256(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::sync::Mutex;
+
+    fn seeded_db(documents: &[(&str, &Path)]) -> Mutex<Connection> {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE documents (
+                id TEXT PRIMARY KEY,
+                deal_id TEXT NOT NULL,
+                type TEXT NOT NULL,
+                filename TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            )",
+        )
+        .unwrap();
+
+        for (n, (id, path)) in documents.iter().enumerate() {
+            conn.execute(
+                "INSERT INTO documents (id, deal_id, type, filename, file_path, created_at, updated_at)
+                 VALUES (?1, 'deal-1', 'bill_of_sale', 'doc.pdf', ?2, ?3, ?3)",
+                params![id, path.to_string_lossy().to_string(), n as i64],
+            )
+            .unwrap();
+        }
+
+        Mutex::new(conn)
+    }
+
+    fn file_path_of(db: &Mutex<Connection>, id: &str) -> PathBuf {
+        let path: String = db
+            .lock()
+            .unwrap()
+            .query_row(
+                "SELECT file_path FROM documents WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        PathBuf::from(path)
+    }
+
+    fn write(path: &Path, content: &[u8]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_move_rewrites_paths_and_reports_missing() {
+        let dir = temp_dir("docs-migration", "move");
+        let old_root = dir.join("old");
+        let new_root = dir.join("new");
+        let outside = dir.join("elsewhere").join("other.pdf");
+
+        let a = old_root.join("deal-1").join("a.pdf");
+        let b = old_root.join("deal-2").join("b.pdf");
+        let gone = old_root.join("deal-3").join("gone.pdf");
+        write(&a, b"alpha");
+        write(&b, b"bravo");
+        write(&outside, b"other");
+
+        let db = seeded_db(&[("a", &a), ("b", &b), ("gone", &gone), ("out", &outside)]);
+        let mut events = Vec::new();
+
+        let report = run_migration(
+            || db.lock().unwrap(),
+            &old_root,
+            &new_root,
+            true,
+            0,
+            |p| events.push((p.document_id.clone(), p.status)),
+        )
+        .unwrap();
+
+        assert_eq!(report.migrated, 2);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.missing, vec![gone.to_string_lossy().to_string()]);
+        assert!(!report.rolled_back);
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[2], ("gone".to_string(), MigrationStatus::Missing));
+
+        let new_a = new_root.join("deal-1").join("a.pdf");
+        assert_eq!(file_path_of(&db, "a"), new_a);
+        assert_eq!(fs::read(&new_a).unwrap(), b"alpha");
+        assert!(!a.exists());
+        assert_eq!(file_path_of(&db, "gone"), gone);
+        assert_eq!(file_path_of(&db, "out"), outside);
+        assert!(outside.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failures_over_limit_roll_back() {
+        let dir = temp_dir("docs-migration", "rollback");
+        let old_root = dir.join("old");
+        let new_root = dir.join("new");
+
+        let a = old_root.join("deal-1").join("a.pdf");
+        let b = old_root.join("deal-2").join("b.pdf");
+        write(&a, b"alpha");
+        write(&b, b"bravo");
+
+        // A plain file where deal-2's folder should go makes that copy fail
+        write(&new_root.join("deal-2"), b"not a directory");
+
+        let db = seeded_db(&[("a", &a), ("b", &b)]);
+        let report =
+            run_migration(|| db.lock().unwrap(), &old_root, &new_root, true, 0, |_| {}).unwrap();
+
+        assert!(report.rolled_back);
+        assert_eq!(report.migrated, 0);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].document_id, "b");
+        assert_eq!(file_path_of(&db, "a"), a);
+        assert!(a.exists() && b.exists());
+        assert!(!new_root.join("deal-1").join("a.pdf").exists());
+
+        // Allowing one failure lets the rest migrate
+        let report = run_migration(
+            || db.lock().unwrap(),
+            &old_root,
+            &new_root,
+            false,
+            1,
+            |_| {},
+        )
+        .unwrap();
+        assert!(!report.rolled_back);
+        assert_eq!(report.migrated, 1);
+        assert_eq!(
+            file_path_of(&db, "a"),
+            new_root.join("deal-1").join("a.pdf")
+        );
+        assert!(a.exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
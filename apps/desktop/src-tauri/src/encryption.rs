@@ -1,27 +1,82 @@
 // src-tauri/src/encryption.rs - AES-256 encryption for session tokens
 use aes_gcm::aead::rand_core::RngCore;
 use aes_gcm::{
-    aead::{Aead, KeyInit, OsRng},
+    aead::{Aead, KeyInit, OsRng, Payload},
     Aes256Gcm, Nonce,
 };
+use argon2::{Algorithm, Argon2, Params, Version};
 use base64::{engine::general_purpose, Engine as _};
-use log::info;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use subtle::ConstantTimeEq;
 
 const NONCE_SIZE: usize = 12; // GCM standard nonce size
+const TAG_SIZE: usize = 16; // GCM authentication tag size
+
+// Encrypted file format (version 1):
+//   header: magic "DSEF" | version u8 | chunk size u32 LE | 7-byte random nonce prefix
+//   body:   chunks of AES-256-GCM ciphertext + tag, each chunk_size plaintext bytes
+//           except the last (which may be empty)
+// Chunk nonce = prefix | counter u32 BE | last-chunk flag, and the header is the
+// AAD of every chunk, so reordering, truncation and header edits all fail auth.
+const FILE_MAGIC: &[u8; 4] = b"DSEF";
+const FILE_VERSION: u8 = 1;
+const FILE_NONCE_PREFIX_SIZE: usize = 7;
+const FILE_HEADER_SIZE: usize = 4 + 1 + 4 + FILE_NONCE_PREFIX_SIZE;
+const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+const MAX_CHUNK_SIZE: usize = 16 * 1024 * 1024;
+
+const KDF_SALT_SIZE: usize = 16;
+const KDF_ALGORITHM: &str = "argon2id";
+
+/// Argon2id cost parameters, stored next to the salt so they can be raised
+/// later without breaking keys derived with older settings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    // OWASP recommended minimum for Argon2id (19 MiB, 2 passes, 1 lane)
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DerivedKey {
+    /// 32-byte key in the same base64 format as generate_encryption_key
+    pub key_base64: String,
+    pub salt_base64: String,
+    /// SHA-256 of the key, safe to store for verify_password
+    pub key_hash_base64: String,
+    pub algorithm: String,
+    pub params: KdfParams,
+}
 
 /// Generate a new 256-bit encryption key
 #[tauri::command]
 pub fn generate_encryption_key() -> Result<String, String> {
-    info!("🔑 Generating new 256-bit encryption key...");
+    debug!("🔑 Generating new 256-bit encryption key...");
 
     let mut key_bytes = [0u8; 32]; // 256 bits = 32 bytes
     OsRng.fill_bytes(&mut key_bytes);
 
     let key_base64 = general_purpose::STANDARD.encode(key_bytes);
 
-    info!("✅ Encryption key generated");
-    info!("   Length: 32 bytes (256 bits)");
-    info!("   Base64 length: {} chars", key_base64.len());
+    debug!("✅ Encryption key generated");
+    debug!("   Length: 32 bytes (256 bits)");
+    debug!("   Base64 length: {} chars", key_base64.len());
 
     Ok(key_base64)
 }
@@ -29,8 +84,8 @@ pub fn generate_encryption_key() -> Result<String, String> {
 /// Encrypt data using AES-256-GCM
 #[tauri::command]
 pub fn encrypt_data(data: String, key: String) -> Result<String, String> {
-    info!("🔒 Encrypting data...");
-    info!("   Data length: {} chars", data.len());
+    debug!("🔒 Encrypting data...");
+    debug!("   Data length: {} chars", data.len());
 
     // Decode base64 key
     let key_bytes = general_purpose::STANDARD
@@ -64,9 +119,9 @@ pub fn encrypt_data(data: String, key: String) -> Result<String, String> {
 
     let encrypted_base64 = general_purpose::STANDARD.encode(combined);
 
-    info!("✅ Data encrypted");
-    info!("   Ciphertext length: {} bytes", ciphertext.len());
-    info!("   Base64 output: {} chars", encrypted_base64.len());
+    debug!("✅ Data encrypted");
+    debug!("   Ciphertext length: {} bytes", ciphertext.len());
+    debug!("   Base64 output: {} chars", encrypted_base64.len());
 
     Ok(encrypted_base64)
 }
@@ -74,8 +129,8 @@ pub fn encrypt_data(data: String, key: String) -> Result<String, String> {
 /// Decrypt data using AES-256-GCM
 #[tauri::command]
 pub fn decrypt_data(encrypted_data: String, key: String) -> Result<String, String> {
-    info!("🔓 Decrypting data...");
-    info!("   Encrypted data length: {} chars", encrypted_data.len());
+    debug!("🔓 Decrypting data...");
+    debug!("   Encrypted data length: {} chars", encrypted_data.len());
 
     // Decode base64 key
     let key_bytes = general_purpose::STANDARD
@@ -117,15 +172,490 @@ pub fn decrypt_data(encrypted_data: String, key: String) -> Result<String, Strin
     let decrypted_string = String::from_utf8(plaintext)
         .map_err(|e| format!("Invalid UTF-8 in decrypted data: {}", e))?;
 
-    info!("✅ Data decrypted");
-    info!("   Plaintext length: {} chars", decrypted_string.len());
+    debug!("✅ Data decrypted");
+    debug!("   Plaintext length: {} chars", decrypted_string.len());
 
     Ok(decrypted_string)
 }
 
+/// Derive an encryption key from a password with Argon2id
+/// Pass the stored salt (and params) to re-derive an existing key; omit the salt for a new one
+#[tauri::command]
+pub fn derive_key_from_password(
+    password: String,
+    salt_base64: Option<String>,
+    params: Option<KdfParams>,
+) -> Result<DerivedKey, String> {
+    debug!("🔑 Deriving key from password...");
+
+    let params = params.unwrap_or_default();
+    let salt = match salt_base64 {
+        Some(salt) => general_purpose::STANDARD
+            .decode(&salt)
+            .map_err(|e| format!("Invalid salt format: {}", e))?,
+        None => {
+            let mut salt = vec![0u8; KDF_SALT_SIZE];
+            OsRng.fill_bytes(&mut salt);
+            salt
+        }
+    };
+
+    let key = argon2_key(password.as_bytes(), &salt, params)?;
+
+    debug!("✅ Key derived");
+    Ok(DerivedKey {
+        key_base64: general_purpose::STANDARD.encode(key),
+        salt_base64: general_purpose::STANDARD.encode(&salt),
+        key_hash_base64: general_purpose::STANDARD.encode(Sha256::digest(key)),
+        algorithm: KDF_ALGORITHM.to_string(),
+        params,
+    })
+}
+
+/// Check a password against a stored salt and key hash (constant-time comparison)
+#[tauri::command]
+pub fn verify_password(
+    password: String,
+    salt: String,
+    expected_key_hash: String,
+    params: Option<KdfParams>,
+) -> Result<bool, String> {
+    let salt = general_purpose::STANDARD
+        .decode(&salt)
+        .map_err(|e| format!("Invalid salt format: {}", e))?;
+    let expected = general_purpose::STANDARD
+        .decode(&expected_key_hash)
+        .map_err(|e| format!("Invalid key hash format: {}", e))?;
+
+    let key = argon2_key(password.as_bytes(), &salt, params.unwrap_or_default())?;
+    let actual = Sha256::digest(key);
+
+    Ok(bool::from(actual.as_slice().ct_eq(&expected)))
+}
+
+fn argon2_key(password: &[u8], salt: &[u8], params: KdfParams) -> Result<[u8; 32], String> {
+    let argon2_params = Params::new(
+        params.memory_kib,
+        params.iterations,
+        params.parallelism,
+        Some(32),
+    )
+    .map_err(|e| format!("Invalid key derivation parameters: {}", e))?;
+
+    let mut key = [0u8; 32];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, argon2_params)
+        .hash_password_into(password, salt, &mut key)
+        .map_err(|e| format!("Key derivation failed: {}", e))?;
+
+    Ok(key)
+}
+
+/// Encrypt a file on disk in chunks (for PII scans such as driver's licenses)
+/// Streams the input, so large files are never loaded into memory
+#[tauri::command]
+pub fn encrypt_file(input_path: String, output_path: String, key: String) -> Result<(), String> {
+    info!("🔒 Encrypting file: {}", input_path);
+
+    let cipher = cipher_from_key(&key)?;
+    let input = File::open(&input_path).map_err(|e| format!("Failed to open input file: {}", e))?;
+
+    write_atomically(Path::new(&output_path), |writer| {
+        encrypt_stream(&cipher, BufReader::new(input), writer, DEFAULT_CHUNK_SIZE)
+    })?;
+
+    info!("✅ File encrypted: {}", output_path);
+    Ok(())
+}
+
+/// Decrypt a file produced by encrypt_file
+/// Output is only created if every chunk authenticates
+#[tauri::command]
+pub fn decrypt_file(input_path: String, output_path: String, key: String) -> Result<(), String> {
+    info!("🔓 Decrypting file: {}", input_path);
+
+    let cipher = cipher_from_key(&key)?;
+    let input = File::open(&input_path).map_err(|e| format!("Failed to open input file: {}", e))?;
+
+    write_atomically(Path::new(&output_path), |writer| {
+        decrypt_stream(&cipher, BufReader::new(input), writer)
+    })?;
+
+    info!("✅ File decrypted: {}", output_path);
+    Ok(())
+}
+
+/// Check whether a file starts with the encrypted file header
+#[tauri::command]
+pub fn is_file_encrypted(path: String) -> Result<bool, String> {
+    let mut file = File::open(&path).map_err(|e| format!("Failed to open file: {}", e))?;
+
+    let mut header = [0u8; FILE_HEADER_SIZE];
+    match read_full(&mut file, &mut header) {
+        Ok(n) if n == FILE_HEADER_SIZE => Ok(parse_header(&header).is_ok()),
+        Ok(_) => Ok(false),
+        Err(e) => Err(format!("Failed to read file: {}", e)),
+    }
+}
+
+pub(crate) fn cipher_from_key(key: &str) -> Result<Aes256Gcm, String> {
+    let key_bytes = general_purpose::STANDARD
+        .decode(key)
+        .map_err(|e| format!("Invalid key format: {}", e))?;
+
+    if key_bytes.len() != 32 {
+        return Err(format!(
+            "Invalid key length: {} (expected 32)",
+            key_bytes.len()
+        ));
+    }
+
+    Aes256Gcm::new_from_slice(&key_bytes).map_err(|e| format!("Failed to create cipher: {}", e))
+}
+
+/// Write to a temporary sibling file and rename it into place only on success
+fn write_atomically(
+    output: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), String>,
+) -> Result<(), String> {
+    let mut partial = output.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+
+    let result = File::create(&partial)
+        .map_err(|e| format!("Failed to create output file: {}", e))
+        .and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            write(&mut writer)?;
+            writer
+                .flush()
+                .map_err(|e| format!("Failed to write output file: {}", e))
+        })
+        .and_then(|_| {
+            fs::rename(&partial, output).map_err(|e| format!("Failed to move output file: {}", e))
+        });
+
+    if let Err(e) = &result {
+        error!("❌ File encryption failed: {}", e);
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+fn chunk_nonce(prefix: &[u8], counter: u32, last: bool) -> Nonce<aes_gcm::aead::consts::U12> {
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce[..FILE_NONCE_PREFIX_SIZE].copy_from_slice(prefix);
+    nonce[FILE_NONCE_PREFIX_SIZE..NONCE_SIZE - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[NONCE_SIZE - 1] = last as u8;
+    Nonce::from(nonce)
+}
+
+fn parse_header(
+    header: &[u8; FILE_HEADER_SIZE],
+) -> Result<(usize, [u8; FILE_NONCE_PREFIX_SIZE]), String> {
+    if &header[..4] != FILE_MAGIC {
+        return Err("Not an encrypted file".to_string());
+    }
+    if header[4] != FILE_VERSION {
+        return Err(format!("Unsupported encrypted file version: {}", header[4]));
+    }
+
+    let chunk_size = u32::from_le_bytes([header[5], header[6], header[7], header[8]]) as usize;
+    if chunk_size == 0 || chunk_size > MAX_CHUNK_SIZE {
+        return Err(format!("Invalid chunk size: {}", chunk_size));
+    }
+
+    let mut prefix = [0u8; FILE_NONCE_PREFIX_SIZE];
+    prefix.copy_from_slice(&header[9..]);
+    Ok((chunk_size, prefix))
+}
+
+fn file_header(chunk_size: usize, prefix: &[u8; FILE_NONCE_PREFIX_SIZE]) -> [u8; FILE_HEADER_SIZE] {
+    let mut header = [0u8; FILE_HEADER_SIZE];
+    header[..4].copy_from_slice(FILE_MAGIC);
+    header[4] = FILE_VERSION;
+    header[5..9].copy_from_slice(&(chunk_size as u32).to_le_bytes());
+    header[9..].copy_from_slice(prefix);
+    header
+}
+
+/// Read until the buffer is full or EOF; returns bytes read
+fn read_full(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn encrypt_stream(
+    cipher: &Aes256Gcm,
+    mut reader: impl Read,
+    writer: &mut impl Write,
+    chunk_size: usize,
+) -> Result<(), String> {
+    let mut prefix = [0u8; FILE_NONCE_PREFIX_SIZE];
+    OsRng.fill_bytes(&mut prefix);
+
+    let header = file_header(chunk_size, &prefix);
+    writer
+        .write_all(&header)
+        .map_err(|e| format!("Failed to write header: {}", e))?;
+
+    // Read one chunk ahead so we know which chunk is the last one
+    let mut current = vec![0u8; chunk_size];
+    let mut next = vec![0u8; chunk_size];
+    let mut current_len =
+        read_full(&mut reader, &mut current).map_err(|e| format!("Failed to read input: {}", e))?;
+    let mut counter: u32 = 0;
+
+    loop {
+        let next_len = if current_len == chunk_size {
+            read_full(&mut reader, &mut next).map_err(|e| format!("Failed to read input: {}", e))?
+        } else {
+            0
+        };
+        let last = next_len == 0;
+
+        let ciphertext = cipher
+            .encrypt(
+                &chunk_nonce(&prefix, counter, last),
+                Payload {
+                    msg: &current[..current_len],
+                    aad: &header,
+                },
+            )
+            .map_err(|e| format!("Encryption failed: {}", e))?;
+        writer
+            .write_all(&ciphertext)
+            .map_err(|e| format!("Failed to write output: {}", e))?;
+
+        if last {
+            return Ok(());
+        }
+
+        counter = counter
+            .checked_add(1)
+            .ok_or_else(|| "File too large to encrypt".to_string())?;
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+    }
+}
+
+fn decrypt_stream(
+    cipher: &Aes256Gcm,
+    mut reader: impl Read,
+    writer: &mut impl Write,
+) -> Result<(), String> {
+    let (header, chunk_size, prefix) = read_header(&mut reader)?;
+
+    for_each_decrypted_chunk(
+        cipher,
+        reader,
+        &header,
+        chunk_size,
+        &prefix,
+        |plaintext, _, _| {
+            writer
+                .write_all(plaintext)
+                .map_err(|e| format!("Failed to write output: {}", e))
+        },
+    )
+}
+
+/// Re-encrypt a stream under a new key chunk by chunk, so no plaintext touches disk
+/// Chunk boundaries, counters and last-chunk flags carry over; only the nonce prefix is new
+fn reencrypt_stream(
+    old_cipher: &Aes256Gcm,
+    new_cipher: &Aes256Gcm,
+    mut reader: impl Read,
+    writer: &mut impl Write,
+) -> Result<(), String> {
+    let (old_header, chunk_size, old_prefix) = read_header(&mut reader)?;
+
+    let mut new_prefix = [0u8; FILE_NONCE_PREFIX_SIZE];
+    OsRng.fill_bytes(&mut new_prefix);
+    let new_header = file_header(chunk_size, &new_prefix);
+    writer
+        .write_all(&new_header)
+        .map_err(|e| format!("Failed to write header: {}", e))?;
+
+    for_each_decrypted_chunk(
+        old_cipher,
+        reader,
+        &old_header,
+        chunk_size,
+        &old_prefix,
+        |plaintext, counter, last| {
+            let ciphertext = new_cipher
+                .encrypt(
+                    &chunk_nonce(&new_prefix, counter, last),
+                    Payload {
+                        msg: plaintext,
+                        aad: &new_header,
+                    },
+                )
+                .map_err(|e| format!("Encryption failed: {}", e))?;
+            writer
+                .write_all(&ciphertext)
+                .map_err(|e| format!("Failed to write output: {}", e))
+        },
+    )
+}
+
+fn read_header(
+    reader: &mut impl Read,
+) -> Result<([u8; FILE_HEADER_SIZE], usize, [u8; FILE_NONCE_PREFIX_SIZE]), String> {
+    let mut header = [0u8; FILE_HEADER_SIZE];
+    let header_len =
+        read_full(reader, &mut header).map_err(|e| format!("Failed to read input: {}", e))?;
+    if header_len < FILE_HEADER_SIZE {
+        return Err("Not an encrypted file".to_string());
+    }
+    let (chunk_size, prefix) = parse_header(&header)?;
+    Ok((header, chunk_size, prefix))
+}
+
+fn for_each_decrypted_chunk(
+    cipher: &Aes256Gcm,
+    mut reader: impl Read,
+    header: &[u8],
+    chunk_size: usize,
+    prefix: &[u8],
+    mut on_chunk: impl FnMut(&[u8], u32, bool) -> Result<(), String>,
+) -> Result<(), String> {
+    let block_size = chunk_size + TAG_SIZE;
+    let mut current = vec![0u8; block_size];
+    let mut next = vec![0u8; block_size];
+    let mut current_len =
+        read_full(&mut reader, &mut current).map_err(|e| format!("Failed to read input: {}", e))?;
+    let mut counter: u32 = 0;
+
+    loop {
+        let next_len = if current_len == block_size {
+            read_full(&mut reader, &mut next).map_err(|e| format!("Failed to read input: {}", e))?
+        } else {
+            0
+        };
+        let last = next_len == 0;
+
+        let plaintext = cipher
+            .decrypt(
+                &chunk_nonce(prefix, counter, last),
+                Payload {
+                    msg: &current[..current_len],
+                    aad: header,
+                },
+            )
+            .map_err(|_| {
+                "Decryption failed: file is corrupt, truncated or the key is wrong".to_string()
+            })?;
+        on_chunk(&plaintext, counter, last)?;
+
+        if last {
+            return Ok(());
+        }
+
+        counter = counter
+            .checked_add(1)
+            .ok_or_else(|| "Encrypted file has too many chunks".to_string())?;
+        std::mem::swap(&mut current, &mut next);
+        current_len = next_len;
+    }
+}
+
+/// Replace an encrypted file in place with a copy encrypted under new_key
+pub(crate) fn reencrypt_file(path: &Path, old_key: &str, new_key: &str) -> Result<(), String> {
+    let old_cipher = cipher_from_key(old_key)?;
+    let new_cipher = cipher_from_key(new_key)?;
+    let input = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+
+    write_atomically(path, |writer| {
+        reencrypt_stream(&old_cipher, &new_cipher, BufReader::new(input), writer)
+    })
+}
+
+/// True if the file fully authenticates under the given key
+pub(crate) fn file_decrypts_with(path: &Path, key: &str) -> bool {
+    let Ok(cipher) = cipher_from_key(key) else {
+        return false;
+    };
+    let Ok(input) = File::open(path) else {
+        return false;
+    };
+    decrypt_stream(&cipher, BufReader::new(input), &mut std::io::sink()).is_ok()
+}
+
+/// encrypt_data with additional authenticated data, so the ciphertext only decrypts
+/// for the same aad (e.g. the row and column it was written to). Nothing is logged
+pub(crate) fn encrypt_bound(plaintext: &str, key: &str, aad: &[u8]) -> Result<String, String> {
+    let cipher = cipher_from_key(key)?;
+    let mut nonce_bytes = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce_bytes);
+    let ciphertext = cipher
+        .encrypt(
+            &Nonce::from(nonce_bytes),
+            Payload {
+                msg: plaintext.as_bytes(),
+                aad,
+            },
+        )
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+
+    let mut combined = nonce_bytes.to_vec();
+    combined.extend_from_slice(&ciphertext);
+    Ok(general_purpose::STANDARD.encode(combined))
+}
+
+/// Reverse encrypt_bound; fails unless key and aad are the ones it was encrypted with
+pub(crate) fn decrypt_bound(encrypted: &str, key: &str, aad: &[u8]) -> Result<String, String> {
+    let cipher = cipher_from_key(key)?;
+    let combined = general_purpose::STANDARD
+        .decode(encrypted)
+        .map_err(|e| format!("Invalid encrypted data format: {}", e))?;
+    if combined.len() < NONCE_SIZE + TAG_SIZE {
+        return Err("Encrypted data too short".to_string());
+    }
+
+    let (nonce_bytes, ciphertext) = combined.split_at(NONCE_SIZE);
+    let nonce_array: [u8; NONCE_SIZE] = nonce_bytes
+        .try_into()
+        .map_err(|_| "Encrypted data nonce length invalid".to_string())?;
+    let plaintext = cipher
+        .decrypt(
+            &Nonce::from(nonce_array),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| "Decryption failed".to_string())?;
+    String::from_utf8(plaintext).map_err(|e| format!("Invalid UTF-8 in decrypted data: {}", e))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_bound_encryption_needs_the_same_aad() {
+        let key = generate_encryption_key().unwrap();
+        let encrypted = encrypt_bound("123456789", &key, b"deal_cobuyers.ssn:cb1").unwrap();
+
+        assert!(!encrypted.contains("123456789"));
+        assert_eq!(
+            decrypt_bound(&encrypted, &key, b"deal_cobuyers.ssn:cb1").unwrap(),
+            "123456789"
+        );
+        assert!(decrypt_bound(&encrypted, &key, b"deal_cobuyers.ssn:cb2").is_err());
+        let other_key = generate_encryption_key().unwrap();
+        assert!(decrypt_bound(&encrypted, &other_key, b"deal_cobuyers.ssn:cb1").is_err());
+    }
 
     #[test]
     fn test_encryption_roundtrip() {
@@ -149,4 +679,137 @@ mod tests {
 
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_password_key_is_stable_for_fixed_salt() {
+        let salt = general_purpose::STANDARD.encode(b"fixed-salt-16byt");
+        let params = KdfParams {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+
+        let first =
+            derive_key_from_password("hunter2".to_string(), Some(salt.clone()), Some(params))
+                .unwrap();
+        let second =
+            derive_key_from_password("hunter2".to_string(), Some(salt.clone()), Some(params))
+                .unwrap();
+        assert_eq!(first.key_base64, second.key_base64);
+        assert_eq!(
+            first.key_base64,
+            "UOLPnjGJAJ9kG12OJyPKTldfZyTd2+0umtEq15pV/1s="
+        );
+        assert_eq!(first.salt_base64, salt);
+
+        // Different params must give a different key for the same password + salt
+        let stronger = KdfParams {
+            iterations: 2,
+            ..params
+        };
+        let other =
+            derive_key_from_password("hunter2".to_string(), Some(salt), Some(stronger)).unwrap();
+        assert_ne!(first.key_base64, other.key_base64);
+
+        // Derived keys plug straight into encrypt_data/decrypt_data
+        let encrypted = encrypt_data("secret".to_string(), first.key_base64.clone()).unwrap();
+        assert_eq!(decrypt_data(encrypted, first.key_base64).unwrap(), "secret");
+    }
+
+    #[test]
+    fn test_verify_password() {
+        let params = KdfParams {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        };
+        let derived =
+            derive_key_from_password("correct horse".to_string(), None, Some(params)).unwrap();
+        assert_eq!(
+            general_purpose::STANDARD
+                .decode(&derived.salt_base64)
+                .unwrap()
+                .len(),
+            KDF_SALT_SIZE
+        );
+
+        let verify = |password: &str| {
+            verify_password(
+                password.to_string(),
+                derived.salt_base64.clone(),
+                derived.key_hash_base64.clone(),
+                Some(params),
+            )
+            .unwrap()
+        };
+        assert!(verify("correct horse"));
+        assert!(!verify("correct horse "));
+        assert!(!verify(""));
+    }
+
+    fn encrypt_bytes(cipher: &Aes256Gcm, data: &[u8], chunk_size: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        encrypt_stream(cipher, data, &mut out, chunk_size).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_file_stream_roundtrip_multi_chunk() {
+        let cipher = cipher_from_key(&generate_encryption_key().unwrap()).unwrap();
+
+        // Partial last chunk, exact multiple of the chunk size, and empty input
+        for len in [3 * 1024 + 100, 4 * 1024, 0] {
+            let data: Vec<u8> = (0..len).map(|i| (i % 251) as u8).collect();
+            let encrypted = encrypt_bytes(&cipher, &data, 1024);
+            assert_eq!(&encrypted[..4], FILE_MAGIC);
+
+            let mut decrypted = Vec::new();
+            decrypt_stream(&cipher, encrypted.as_slice(), &mut decrypted).unwrap();
+            assert_eq!(decrypted, data);
+        }
+    }
+
+    #[test]
+    fn test_file_tamper_and_truncation_fail_auth() {
+        let cipher = cipher_from_key(&generate_encryption_key().unwrap()).unwrap();
+        let data = vec![7u8; 2500];
+        let encrypted = encrypt_bytes(&cipher, &data, 1024);
+
+        let mut tampered = encrypted.clone();
+        tampered[FILE_HEADER_SIZE + 1500] ^= 0x01;
+        assert!(decrypt_stream(&cipher, tampered.as_slice(), &mut Vec::new()).is_err());
+
+        // Dropping the final chunk must not decrypt as a shorter valid file
+        let truncated = &encrypted[..FILE_HEADER_SIZE + 2 * (1024 + TAG_SIZE)];
+        assert!(decrypt_stream(&cipher, truncated, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_encrypt_file_commands() {
+        let dir = temp_dir("encrypt", "file-commands");
+
+        let plain = dir.join("license.jpg");
+        let sealed = dir.join("license.jpg.enc");
+        let opened = dir.join("license-out.jpg");
+        fs::write(&plain, vec![42u8; 200_000]).unwrap();
+
+        let key = generate_encryption_key().unwrap();
+        let path = |p: &PathBuf| p.to_string_lossy().to_string();
+
+        encrypt_file(path(&plain), path(&sealed), key.clone()).unwrap();
+        assert!(is_file_encrypted(path(&sealed)).unwrap());
+        assert!(!is_file_encrypted(path(&plain)).unwrap());
+
+        decrypt_file(path(&sealed), path(&opened), key).unwrap();
+        assert_eq!(fs::read(&opened).unwrap(), fs::read(&plain).unwrap());
+
+        // Wrong key: no output file (partial or final) is left behind
+        let failed = dir.join("failed.jpg");
+        let wrong = generate_encryption_key().unwrap();
+        assert!(decrypt_file(path(&sealed), path(&failed), wrong).is_err());
+        assert!(!failed.exists());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 3);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
@@ -0,0 +1,341 @@
+// src-tauri/src/field_projection.rs
+//
+// Optional `fields` on the client, vehicle and deal list/search commands, so a grid that
+// shows eight columns doesn't get every vehicle's description and images JSON over IPC.
+// Requested names are checked against the entity's allowlist (the fields of its struct in
+// database.rs) and only the allowlist's own strings are put into the SQL, never the
+// caller's. Rows come back as JSON objects holding just those keys, plus id; without
+// `fields` the commands return full records as before. Cost stays hidden from roles that
+// don't see costs, as in redact_vehicle.
+
+use rusqlite::{Connection, Result as SqlResult, ToSql};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::data_export::to_json;
+use crate::permissions::Role;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ListEntity {
+    Client,
+    Vehicle,
+    Deal,
+}
+
+impl ListEntity {
+    fn table(self) -> &'static str {
+        match self {
+            ListEntity::Client => "clients",
+            ListEntity::Vehicle => "vehicles",
+            ListEntity::Deal => "deals",
+        }
+    }
+
+    /// Columns that may be requested; each is also the JSON key of the full record
+    fn fields(self) -> &'static [&'static str] {
+        match self {
+            ListEntity::Client => &[
+                "id",
+                "user_id",
+                "first_name",
+                "last_name",
+                "email",
+                "phone",
+                "address",
+                "city",
+                "state",
+                "zip_code",
+                "drivers_license",
+                "created_at",
+                "updated_at",
+                "synced_at",
+            ],
+            ListEntity::Vehicle => &[
+                "id",
+                "vin",
+                "stock_number",
+                "year",
+                "make",
+                "model",
+                "trim",
+                "body",
+                "doors",
+                "transmission",
+                "engine",
+                "cylinders",
+                "title_number",
+                "mileage",
+                "color",
+                "price",
+                "cost",
+                "status",
+                "description",
+                "images",
+                "created_at",
+                "updated_at",
+                "synced_at",
+            ],
+            ListEntity::Deal => &[
+                "id",
+                "user_id",
+                "type",
+                "client_id",
+                "vehicle_id",
+                "status",
+                "total_amount",
+                "sale_date",
+                "sale_amount",
+                "sales_tax",
+                "doc_fee",
+                "trade_in_value",
+                "down_payment",
+                "financed_amount",
+                "document_ids",
+                "cobuyer_data",
+                "created_at",
+                "updated_at",
+                "synced_at",
+                "odometer_at_sale",
+                "odometer_disclosure",
+                "title_status",
+                "title_state",
+                "lien_holder",
+                "lender_id",
+            ],
+        }
+    }
+}
+
+/// A list command's result: full records, or only the requested fields
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum Listing<T> {
+    Full(Vec<T>),
+    Fields(Vec<Value>),
+}
+
+#[cfg(test)]
+impl<T> Listing<T> {
+    pub fn len(&self) -> usize {
+        match self {
+            Listing::Full(rows) => rows.len(),
+            Listing::Fields(rows) => rows.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Validated field names for one entity, in the order asked for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Projection {
+    entity: ListEntity,
+    columns: Vec<&'static str>,
+    hide_cost: bool,
+}
+
+impl Projection {
+    pub(crate) fn parse(entity: ListEntity, fields: &[String], role: Role) -> Result<Self, String> {
+        if fields.is_empty() {
+            return Err("Ask for at least one field".to_string());
+        }
+        let allowed = entity.fields();
+        let mut columns = vec!["id"];
+        for field in fields {
+            let column = allowed
+                .iter()
+                .find(|column| **column == field.as_str())
+                .ok_or_else(|| format!("Unknown {} field: {:?}", entity.table(), field))?;
+            if !columns.contains(column) {
+                columns.push(column);
+            }
+        }
+        Ok(Projection {
+            entity,
+            columns,
+            hide_cost: entity == ListEntity::Vehicle && !role.sees_costs(),
+        })
+    }
+
+    fn select_list(&self) -> String {
+        self.columns
+            .iter()
+            .map(|column| match *column {
+                "cost" if self.hide_cost => "NULL AS cost".to_string(),
+                // "type" is a keyword
+                column => format!("\"{}\"", column),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// The projected rows matching `filter` (a WHERE clause with its ORDER BY, written by
+    /// the calling command, using args)
+    pub(crate) fn query(
+        &self,
+        conn: &Connection,
+        filter: &str,
+        args: &[&dyn ToSql],
+    ) -> SqlResult<Vec<Value>> {
+        let sql = format!(
+            "SELECT {} FROM {} WHERE {}",
+            self.select_list(),
+            self.entity.table(),
+            filter
+        );
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt
+            .query_map(args, |row| {
+                let mut record = Map::new();
+                for (i, column) in self.columns.iter().enumerate() {
+                    record.insert(column.to_string(), to_json(row.get_ref(i)?));
+                }
+                Ok(Value::Object(record))
+            })?
+            .collect();
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{
+        db_create_deal, db_create_vehicle, db_get_all_deals, db_get_all_vehicles, db_search_clients,
+    };
+    use crate::test_support::{make_deal, make_vehicle, TestApp};
+    use serde_json::json;
+
+    fn fields(names: &[&str]) -> Option<Vec<String>> {
+        Some(names.iter().map(|name| name.to_string()).collect())
+    }
+
+    #[test]
+    fn test_projected_lists_are_much_smaller() {
+        let app = TestApp::new();
+        for n in 0..20 {
+            let mut vehicle = make_vehicle(&format!("v{}", n));
+            vehicle.description = Some("Clean title, one owner, new tires. ".repeat(40));
+            let images: Vec<String> = (0..12)
+                .map(|i| format!("vehicles/v{}/photo-{:02}-full-resolution.jpg", n, i))
+                .collect();
+            vehicle.images = Some(serde_json::to_string(&images).unwrap());
+            db_create_vehicle(vehicle, app.state(), app.db()).unwrap();
+        }
+
+        let grid = [
+            "stock_number",
+            "year",
+            "make",
+            "model",
+            "mileage",
+            "price",
+            "status",
+            "vin",
+        ];
+        let full = db_get_all_vehicles(None, None, app.state(), app.db()).unwrap();
+        let projected = db_get_all_vehicles(None, fields(&grid), app.state(), app.db()).unwrap();
+        assert!(matches!(full, Listing::Full(_)));
+        assert_eq!(projected.len(), 20);
+
+        let full_bytes = serde_json::to_vec(&full).unwrap().len();
+        let projected_bytes = serde_json::to_vec(&projected).unwrap().len();
+        assert!(
+            projected_bytes * 10 < full_bytes,
+            "{} bytes projected vs {} full",
+            projected_bytes,
+            full_bytes
+        );
+
+        // Same values under the same keys as the full record
+        let (Listing::Full(full), Listing::Fields(projected)) = (full, projected) else {
+            panic!("unexpected listing shapes");
+        };
+        let first = serde_json::to_value(&full[0]).unwrap();
+        let row = projected[0].as_object().unwrap();
+        assert_eq!(row.len(), grid.len() + 1);
+        for (key, value) in row {
+            assert_eq!(&first[key], value, "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_unknown_fields_are_rejected() {
+        let app = TestApp::new();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+
+        for bad in [
+            "vin; DROP TABLE vehicles",
+            "vin FROM vehicles --",
+            "VIN",
+            "price_cents",
+            "*",
+            "",
+        ] {
+            let err = db_get_all_vehicles(None, fields(&[bad]), app.state(), app.db()).unwrap_err();
+            assert!(err.starts_with("Unknown vehicles field"), "{}", err);
+        }
+        assert!(db_get_all_vehicles(None, Some(Vec::new()), app.state(), app.db()).is_err());
+        let err = db_search_clients("x".into(), None, fields(&["make"]), app.state(), app.db())
+            .unwrap_err();
+        assert!(err.to_string().starts_with("Unknown clients field"));
+        assert_eq!(
+            db_get_all_vehicles(None, None, app.state(), app.db())
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_projection_keeps_costs_hidden_and_quotes_keywords() {
+        let app = TestApp::new();
+        crate::database::db_create_client(
+            crate::test_support::make_client("c1"),
+            None,
+            None,
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        db_create_deal(
+            make_deal("d1", "c1", "v1"),
+            None,
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+
+        let deals = db_get_all_deals(None, fields(&["type", "status"]), app.state(), app.db());
+        let Listing::Fields(deals) = deals.unwrap() else {
+            panic!("expected projected deals");
+        };
+        assert_eq!(
+            deals,
+            [json!({ "id": "d1", "type": "cash", "status": "quote" })]
+        );
+
+        let cost = fields(&["cost", "price"]);
+        let Listing::Fields(rows) =
+            db_get_all_vehicles(None, cost.clone(), app.state(), app.db()).unwrap()
+        else {
+            panic!("expected projected vehicles");
+        };
+        assert_eq!(rows[0]["cost"], json!(14000.0));
+
+        app.sign_in_as(crate::test_support::TEST_USER, Role::Sales);
+        let Listing::Fields(rows) = db_get_all_vehicles(None, cost, app.state(), app.db()).unwrap()
+        else {
+            panic!("expected projected vehicles");
+        };
+        assert_eq!(
+            rows[0],
+            json!({ "id": "v1", "cost": null, "price": 18500.0 })
+        );
+    }
+}
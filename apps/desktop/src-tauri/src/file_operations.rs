@@ -1,9 +1,24 @@
 // src-tauri/src/file_operations.rs
-use log::{error, info};
+use log::{error, info, warn};
+use serde::Serialize;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tauri::AppHandle;
 
 use tauri_plugin_opener::OpenerExt;
 
+use crate::database::get_db;
+use crate::disk_space::{ensure_free_space, DiskSpaceError};
+use crate::storage::invalidate_storage_stats;
+use crate::storage_usage::check_quota;
+
+/// Prefix for temporary print directories in the OS temp dir
+const PRINT_DIR_PREFIX: &str = "dealer-print-";
+
+/// Print directories older than this are considered abandoned
+const DEFAULT_STALE_PRINT_DIR_HOURS: u64 = 24;
+
 /// Get the default downloads directory for the user
 #[tauri::command]
 pub fn get_downloads_dir() -> Result<String, String> {
@@ -61,15 +76,18 @@ pub async fn open_file_with_default_app(file_path: String, app: AppHandle) -> Re
 #[tauri::command]
 pub async fn print_pdf(file_path: String) -> Result<(), String> {
     info!("🖨️  Printing PDF: {}", file_path);
+
+    if !Path::new(&file_path).is_file() {
+        error!("❌ PDF not found: {}", file_path);
+        return Err(format!("File not found: {}", file_path));
+    }
     
     #[cfg(target_os = "windows")]
     {
-        use std::process::Command;
-        
-        match Command::new("cmd")
-            .args(&["/C", "start", "/min", "", &file_path])
-            .spawn()
-        {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", "/min", "", &file_path]);
+
+        match run_launcher(command, LAUNCH_TIMEOUT).await {
             Ok(_) => {
                 info!("✅ PDF opened for printing (Windows)");
                 Ok(())
@@ -83,9 +101,10 @@ pub async fn print_pdf(file_path: String) -> Result<(), String> {
     
     #[cfg(target_os = "macos")]
     {
-        use std::process::Command;
-        
-        match Command::new("open").arg(&file_path).spawn() {
+        let mut command = Command::new("open");
+        command.arg(&file_path);
+
+        match run_launcher(command, LAUNCH_TIMEOUT).await {
             Ok(_) => {
                 info!("✅ PDF opened for printing (macOS)");
                 Ok(())
@@ -99,23 +118,92 @@ pub async fn print_pdf(file_path: String) -> Result<(), String> {
     
     #[cfg(target_os = "linux")]
     {
-        use std::process::Command;
-        
         // Try common Linux PDF viewers
-        let viewers = vec!["xdg-open", "evince", "okular", "atril"];
+        let viewers = ["xdg-open", "evince", "okular", "atril"];
+        let mut last_error = LaunchError::NotFound("xdg-open".to_string());
         
         for viewer in viewers {
-            match Command::new(viewer).arg(&file_path).spawn() {
+            let mut command = Command::new(viewer);
+            command.arg(&file_path);
+
+            match run_launcher(command, LAUNCH_TIMEOUT).await {
                 Ok(_) => {
                     info!("✅ PDF opened with {} (Linux)", viewer);
                     return Ok(());
                 }
-                Err(_) => continue,
+                Err(e) => {
+                    warn!("⚠️  {} could not open PDF: {}", viewer, e);
+                    last_error = e;
+                }
             }
         }
         
-        error!("❌ No PDF viewer found");
-        Err("No PDF viewer found on system".to_string())
+        error!("❌ No PDF viewer could open the file: {}", last_error);
+        Err(format!("Failed to print PDF: {}", last_error))
+    }
+}
+
+/// How long a launcher process gets to fail before we assume it succeeded
+/// (GUI apps like `open`/`xdg-open` may keep running after handing off the file)
+const LAUNCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, PartialEq, Eq)]
+enum LaunchError {
+    NotFound(String),
+    SpawnFailed(String),
+    NonZeroExit { program: String, code: Option<i32> },
+}
+
+impl std::fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LaunchError::NotFound(program) => write!(f, "{} is not installed", program),
+            LaunchError::SpawnFailed(msg) => write!(f, "failed to start process: {}", msg),
+            LaunchError::NonZeroExit { program, code: Some(code) } => {
+                write!(f, "{} exited with status {}", program, code)
+            }
+            LaunchError::NonZeroExit { program, code: None } => {
+                write!(f, "{} was terminated by a signal", program)
+            }
+        }
+    }
+}
+
+/// Spawn a launcher and wait briefly for it, mapping a missing binary or a
+/// non-zero exit status into an error. Still running at the deadline = success, and a
+/// detached thread waits for it from then on.
+async fn run_launcher(mut command: Command, timeout: Duration) -> Result<(), LaunchError> {
+    let program = command.get_program().to_string_lossy().to_string();
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => LaunchError::NotFound(program.clone()),
+            _ => LaunchError::SpawnFailed(e.to_string()),
+        })?;
+
+    let deadline = tokio::time::Instant::now() + timeout;
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) if status.success() => return Ok(()),
+            Ok(Some(status)) => {
+                return Err(LaunchError::NonZeroExit {
+                    program,
+                    code: status.code(),
+                })
+            }
+            Ok(None) if tokio::time::Instant::now() >= deadline => {
+                // Still running (e.g. a viewer that stays open): reap it whenever it
+                // exits, so it doesn't linger as a zombie until the app quits
+                std::thread::spawn(move || child.wait());
+                return Ok(());
+            }
+            Ok(None) => tokio::time::sleep(Duration::from_millis(50)).await,
+            Err(e) => return Err(LaunchError::SpawnFailed(e.to_string())),
+        }
     }
 }
 
@@ -125,14 +213,13 @@ pub fn create_temp_print_dir() -> Result<String, String> {
     info!("📁 Creating temporary print directory...");
     
     use std::fs;
-    use std::time::{SystemTime, UNIX_EPOCH};
     
     let timestamp = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs();
     
-    let temp_dir = std::env::temp_dir().join(format!("dealer-print-{}", timestamp));
+    let temp_dir = std::env::temp_dir().join(format!("{}{}", PRINT_DIR_PREFIX, timestamp));
     
     match fs::create_dir_all(&temp_dir) {
         Ok(_) => {
@@ -167,6 +254,112 @@ pub fn cleanup_temp_print_dir(dir_path: String) -> Result<(), String> {
     }
 }
 
+#[derive(Debug, Default, Serialize)]
+pub struct PrintDirCleanup {
+    pub removed: usize,
+    pub failed: usize,
+    pub bytes_freed: u64,
+}
+
+/// Remove dealer-print-* directories left behind by crashes
+/// Runs on startup; max_age_hours defaults to 24
+#[tauri::command]
+pub fn cleanup_stale_print_dirs(max_age_hours: Option<u64>) -> Result<PrintDirCleanup, String> {
+    let hours = max_age_hours.unwrap_or(DEFAULT_STALE_PRINT_DIR_HOURS);
+    let max_age = Duration::from_secs(hours.saturating_mul(60 * 60));
+
+    info!("🧹 Sweeping stale print directories (older than {:?})...", max_age);
+
+    let result = sweep_print_dirs(&std::env::temp_dir(), max_age, SystemTime::now());
+
+    info!(
+        "✅ Stale print dirs: {} removed, {} failed, {} bytes freed",
+        result.removed, result.failed, result.bytes_freed
+    );
+    Ok(result)
+}
+
+fn sweep_print_dirs(temp_root: &Path, max_age: Duration, now: SystemTime) -> PrintDirCleanup {
+    use std::fs;
+
+    let mut result = PrintDirCleanup::default();
+
+    let entries = match fs::read_dir(temp_root) {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!("⚠️  Could not read temp dir {:?}: {}", temp_root, e);
+            return result;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(suffix) = name.strip_prefix(PRINT_DIR_PREFIX) else {
+            continue;
+        };
+
+        // file_type() does not follow symlinks, so a link pointing out of
+        // the temp dir is never treated as one of our directories
+        match entry.file_type() {
+            Ok(file_type) if file_type.is_dir() => {}
+            _ => continue,
+        }
+
+        let path = entry.path();
+        let Some(created) = print_dir_created_at(&path, suffix) else {
+            continue;
+        };
+
+        if now.duration_since(created).unwrap_or_default() < max_age {
+            continue;
+        }
+
+        let size = directory_size_no_follow(&path);
+        match fs::remove_dir_all(&path) {
+            Ok(_) => {
+                result.removed += 1;
+                result.bytes_freed += size;
+                info!("   Removed stale print dir: {:?}", path);
+            }
+            Err(e) => {
+                result.failed += 1;
+                warn!("⚠️  Failed to remove stale print dir {:?}: {}", path, e);
+            }
+        }
+    }
+
+    result
+}
+
+/// Creation time from the dealer-print-{unix_secs} name, falling back to mtime
+fn print_dir_created_at(path: &Path, suffix: &str) -> Option<SystemTime> {
+    if let Ok(secs) = suffix.parse::<u64>() {
+        return Some(UNIX_EPOCH + Duration::from_secs(secs));
+    }
+
+    std::fs::symlink_metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+}
+
+fn directory_size_no_follow(path: &Path) -> u64 {
+    let mut size = 0;
+
+    if let Ok(entries) = std::fs::read_dir(path) {
+        for entry in entries.flatten() {
+            if let Ok(metadata) = std::fs::symlink_metadata(entry.path()) {
+                if metadata.is_dir() {
+                    size += directory_size_no_follow(&entry.path());
+                } else if metadata.is_file() {
+                    size += metadata.len();
+                }
+            }
+        }
+    }
+
+    size
+}
+
 /// Batch print multiple PDFs
 #[tauri::command]
 pub async fn batch_print_pdfs(file_paths: Vec<String>) -> Result<usize, String> {
@@ -194,30 +387,45 @@ pub async fn batch_print_pdfs(file_paths: Vec<String>) -> Result<usize, String>
 }
 
 /// Write file data to a path (bypasses Tauri FS scope restrictions)
+/// user_id (optional) enforces that user's storage quota; usage itself is
+/// recorded when the document row is created
 #[tauri::command]
-pub fn write_file_to_path(file_path: String, file_data: Vec<u8>) -> Result<(), String> {
+pub fn write_file_to_path(
+    file_path: String,
+    file_data: Vec<u8>,
+    user_id: Option<String>,
+) -> Result<(), DiskSpaceError> {
     info!("💾 Writing file to path: {}", file_path);
     
     use std::fs;
     use std::path::Path;
     
-    // Get parent directory and create if it doesn't exist
+    // Refuse to start on a nearly full disk rather than leave a truncated file
     let path = Path::new(&file_path);
+    ensure_free_space(path, file_data.len() as u64)?;
+    
+    if let Some(user_id) = &user_id {
+        let db = get_db().map_err(|e| e.to_string())?;
+        check_quota(&db.conn(), user_id, file_data.len() as u64)?;
+    }
+    
+    // Get parent directory and create if it doesn't exist
     if let Some(parent) = path.parent() {
         if let Err(e) = fs::create_dir_all(parent) {
             error!("❌ Failed to create directory: {}", e);
-            return Err(format!("Failed to create directory: {}", e));
+            return Err(format!("Failed to create directory: {}", e).into());
         }
     }
     
     match fs::write(&file_path, file_data) {
         Ok(_) => {
             info!("✅ File written successfully: {}", file_path);
+            invalidate_storage_stats();
             Ok(())
         }
         Err(e) => {
             error!("❌ Failed to write file: {}", e);
-            Err(format!("Failed to write file: {}", e))
+            Err(format!("Failed to write file: {}", e).into())
         }
     }
 }
@@ -251,6 +459,7 @@ pub fn remove_file(file_path: String) -> Result<(), String> {
     match fs::remove_file(&file_path) {
         Ok(_) => {
             info!("✅ File removed successfully: {}", file_path);
+            invalidate_storage_stats();
             Ok(())
         }
         Err(e) => {
@@ -295,18 +504,23 @@ pub async fn open_url(url: String, app: AppHandle) -> Result<(), String> {
 
 /// Reveal file in file explorer
 #[tauri::command]
-pub fn reveal_in_explorer(file_path: String) -> Result<(), String> {
+pub async fn reveal_in_explorer(file_path: String) -> Result<(), String> {
     info!("📂 Revealing file in explorer: {}", file_path);
+
+    if !Path::new(&file_path).exists() {
+        error!("❌ Cannot reveal missing file: {}", file_path);
+        return Err(format!("File not found: {}", file_path));
+    }
     
     #[cfg(target_os = "windows")]
     {
-        use std::process::Command;
-        
-        match Command::new("explorer")
-            .args(&["/select,", &file_path])
-            .spawn()
-        {
-            Ok(_) => {
+        // explorer.exe returns exit code 1 even on success, so only spawn
+        // failures are meaningful here (the path was validated above)
+        let mut command = Command::new("explorer");
+        command.args(["/select,", &file_path]);
+
+        match run_launcher(command, LAUNCH_TIMEOUT).await {
+            Ok(_) | Err(LaunchError::NonZeroExit { .. }) => {
                 info!("✅ File revealed in explorer");
                 Ok(())
             }
@@ -319,12 +533,10 @@ pub fn reveal_in_explorer(file_path: String) -> Result<(), String> {
     
     #[cfg(target_os = "macos")]
     {
-        use std::process::Command;
-        
-        match Command::new("open")
-            .args(&["-R", &file_path])
-            .spawn()
-        {
+        let mut command = Command::new("open");
+        command.args(["-R", &file_path]);
+
+        match run_launcher(command, LAUNCH_TIMEOUT).await {
             Ok(_) => {
                 info!("✅ File revealed in Finder");
                 Ok(())
@@ -338,16 +550,38 @@ pub fn reveal_in_explorer(file_path: String) -> Result<(), String> {
     
     #[cfg(target_os = "linux")]
     {
-        use std::process::Command;
-        use std::path::Path;
-        
         let path = Path::new(&file_path);
+
+        // Prefer the FileManager1 D-Bus interface, which selects the file
+        // (Nautilus, Dolphin, Nemo, Caja, Thunar all implement it)
+        let absolute = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        if let Ok(uri) = tauri::Url::from_file_path(&absolute) {
+            let mut command = Command::new("dbus-send");
+            command.args([
+                "--session",
+                "--print-reply",
+                "--dest=org.freedesktop.FileManager1",
+                "--type=method_call",
+                "/org/freedesktop/FileManager1",
+                "org.freedesktop.FileManager1.ShowItems",
+                &format!("array:string:{}", uri),
+                "string:",
+            ]);
+
+            match run_launcher(command, LAUNCH_TIMEOUT).await {
+                Ok(_) => {
+                    info!("✅ File revealed via FileManager1");
+                    return Ok(());
+                }
+                Err(e) => warn!("⚠️  FileManager1 unavailable, falling back to xdg-open: {}", e),
+            }
+        }
+
         let dir = path.parent().unwrap_or(path);
-        
-        match Command::new("xdg-open")
-            .arg(dir)
-            .spawn()
-        {
+        let mut command = Command::new("xdg-open");
+        command.arg(dir);
+
+        match run_launcher(command, LAUNCH_TIMEOUT).await {
             Ok(_) => {
                 info!("✅ Directory opened in file manager");
                 Ok(())
@@ -358,4 +592,92 @@ pub fn reveal_in_explorer(file_path: String) -> Result<(), String> {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::fs;
+
+    #[test]
+    fn test_sweep_removes_only_stale_print_dirs() {
+        let root = temp_dir("fileops", "sweep");
+        let now = SystemTime::now();
+        let now_secs = now.duration_since(UNIX_EPOCH).unwrap().as_secs();
+
+        let two_days_ago = now_secs - 2 * 24 * 60 * 60;
+
+        let stale = root.join(format!("{}{}", PRINT_DIR_PREFIX, two_days_ago));
+        let fresh = root.join(format!("{}{}", PRINT_DIR_PREFIX, now_secs - 60));
+        let unrelated = root.join("other-app-123");
+        fs::create_dir_all(&stale).unwrap();
+        fs::create_dir_all(&fresh).unwrap();
+        fs::create_dir_all(&unrelated).unwrap();
+        fs::write(stale.join("deal.pdf"), vec![0u8; 1024]).unwrap();
+
+        let result = sweep_print_dirs(&root, Duration::from_secs(24 * 60 * 60), now);
+
+        assert_eq!(result.removed, 1);
+        assert_eq!(result.failed, 0);
+        assert_eq!(result.bytes_freed, 1024);
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        assert!(unrelated.exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_launcher_maps_exit_status() {
+        let run = |program: &str, args: &[&str], timeout_ms: u64| {
+            let mut command = Command::new(program);
+            command.args(args);
+            tauri::async_runtime::block_on(run_launcher(
+                command,
+                Duration::from_millis(timeout_ms),
+            ))
+        };
+
+        assert_eq!(run("sh", &["-c", "exit 0"], 2000), Ok(()));
+        assert_eq!(
+            run("sh", &["-c", "exit 3"], 2000),
+            Err(LaunchError::NonZeroExit {
+                program: "sh".to_string(),
+                code: Some(3)
+            })
+        );
+        assert_eq!(
+            run("dealer-no-such-binary", &[], 2000),
+            Err(LaunchError::NotFound("dealer-no-such-binary".to_string()))
+        );
+        // Still running at the deadline counts as a successful launch
+        assert_eq!(run("sleep", &["5"], 100), Ok(()));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sweep_does_not_follow_symlinks() {
+        let root = temp_dir("fileops", "symlink");
+        let outside = temp_dir("fileops", "symlink-target");
+        fs::write(outside.join("keep.pdf"), b"important").unwrap();
+
+        let link = root.join(format!("{}{}", PRINT_DIR_PREFIX, 1));
+        std::os::unix::fs::symlink(&outside, &link).unwrap();
+
+        let result = sweep_print_dirs(&root, Duration::from_secs(60), SystemTime::now());
+
+        assert_eq!(result.removed, 0);
+        assert!(outside.join("keep.pdf").exists());
+
+        let _ = fs::remove_dir_all(&root);
+        let _ = fs::remove_dir_all(&outside);
+    }
+
+    #[test]
+    fn test_huge_max_age_does_not_overflow() {
+        let result = cleanup_stale_print_dirs(Some(u64::MAX)).unwrap();
+        assert_eq!(result.removed, 0);
+    }
+}
@@ -2,6 +2,7 @@
 use log::info;
 #[warn(unused_imports)]
 use std::fs;
+use std::path::Path;
 use tauri::{AppHandle, Manager};
 
 #[cfg(unix)]
@@ -25,30 +26,43 @@ pub fn set_file_permissions(filename: String, app: AppHandle) -> Result<(), Stri
         return Err(format!("File does not exist: {:?}", file_path));
     }
 
+    restrict_to_owner(&file_path)?;
+
+    #[cfg(unix)]
+    {
+        info!("✅ File permissions set to 600 (owner read/write only)");
+        info!("   Path: {:?}", file_path);
+    }
+
+    #[cfg(not(unix))]
+    {
+        info!("⚠️  File permissions not set (Windows doesn't use Unix permissions)");
+        info!("   Using Windows ACLs instead (handled by OS)");
+    }
+
+    Ok(())
+}
+
+/// Restrict a file to its owner (600). No-op on Windows, where ACLs apply
+pub fn restrict_to_owner(path: &Path) -> Result<(), String> {
     #[cfg(unix)]
     {
         // Set permissions to 600 (rw-------)
         // Owner: read + write
         // Group: none
         // Others: none
-        let mut perms = fs::metadata(&file_path)
+        let mut perms = fs::metadata(path)
             .map_err(|e| format!("Failed to get file metadata: {}", e))?
             .permissions();
 
         perms.set_mode(0o600);
 
-        fs::set_permissions(&file_path, perms)
+        fs::set_permissions(path, perms)
             .map_err(|e| format!("Failed to set permissions: {}", e))?;
-
-        info!("✅ File permissions set to 600 (owner read/write only)");
-        info!("   Path: {:?}", file_path);
     }
 
     #[cfg(not(unix))]
-    {
-        info!("⚠️  File permissions not set (Windows doesn't use Unix permissions)");
-        info!("   Using Windows ACLs instead (handled by OS)");
-    }
+    let _ = path;
 
     Ok(())
 }
@@ -0,0 +1,342 @@
+// src-tauri/src/finance.rs
+//
+// Loan math for finance deals, so every screen quotes the same payment
+// Monthly payments, actuarial method per Reg Z Appendix J: months count as equal unit
+// periods and an odd first period is whole months plus days/30 of simple interest.
+// Interest is rounded to the cent on every row and the last payment absorbs the rounding,
+// so the balance lands exactly on zero. Nothing here is stored.
+
+use serde::Serialize;
+
+use crate::telemetry::track;
+
+/// Days in a Reg Z unit-period month
+const DAYS_PER_PERIOD: u32 = 30;
+const MAX_TERM_MONTHS: u32 = 360;
+const MAX_APR_BPS: u32 = 10_000;
+const MAX_DAYS_TO_FIRST_PAYMENT: u32 = 365;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PaymentQuote {
+    pub monthly_payment_cents: i64,
+    /// Differs from the monthly payment by the accumulated rounding
+    pub final_payment_cents: i64,
+    pub number_of_payments: u32,
+    pub days_to_first_payment: u32,
+    pub total_interest_cents: i64,
+    pub total_of_payments_cents: i64,
+    // Truth in Lending disclosure box
+    pub amount_financed_cents: i64,
+    pub finance_charge_cents: i64,
+    /// APR recomputed from the rounded payments, to 0.01%
+    pub disclosed_apr_bps: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AmortizationRow {
+    pub number: u32,
+    pub payment_cents: i64,
+    pub interest_cents: i64,
+    pub principal_cents: i64,
+    pub balance_cents: i64,
+}
+
+#[tauri::command]
+pub fn finance_calculate_payment(
+    principal_cents: i64,
+    apr_bps: u32,
+    term_months: u32,
+    days_to_first_payment: Option<u32>,
+) -> Result<PaymentQuote, String> {
+    track("finance_calculate_payment", || {
+        let loan = Loan::new(principal_cents, apr_bps, term_months, days_to_first_payment)?;
+        Ok(loan.quote(&loan.schedule()))
+    })
+}
+
+#[tauri::command]
+pub fn finance_amortization_schedule(
+    principal_cents: i64,
+    apr_bps: u32,
+    term_months: u32,
+    days_to_first_payment: Option<u32>,
+) -> Result<Vec<AmortizationRow>, String> {
+    track("finance_amortization_schedule", || {
+        let loan = Loan::new(principal_cents, apr_bps, term_months, days_to_first_payment)?;
+        Ok(loan.schedule())
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Loan {
+    principal_cents: i64,
+    apr_bps: u32,
+    term_months: u32,
+    days_to_first_payment: u32,
+}
+
+impl Loan {
+    fn new(
+        principal_cents: i64,
+        apr_bps: u32,
+        term_months: u32,
+        days_to_first_payment: Option<u32>,
+    ) -> Result<Self, String> {
+        let days_to_first_payment = days_to_first_payment.unwrap_or(DAYS_PER_PERIOD);
+        if principal_cents <= 0 {
+            return Err("Amount financed must be greater than zero".to_string());
+        }
+        if apr_bps > MAX_APR_BPS {
+            return Err(format!("APR can't exceed {}%", MAX_APR_BPS / 100));
+        }
+        if !(1..=MAX_TERM_MONTHS).contains(&term_months) {
+            return Err(format!(
+                "Term must be between 1 and {} months",
+                MAX_TERM_MONTHS
+            ));
+        }
+        if !(1..=MAX_DAYS_TO_FIRST_PAYMENT).contains(&days_to_first_payment) {
+            return Err(format!(
+                "First payment must be due within {} days",
+                MAX_DAYS_TO_FIRST_PAYMENT
+            ));
+        }
+
+        Ok(Loan {
+            principal_cents,
+            apr_bps,
+            term_months,
+            days_to_first_payment,
+        })
+    }
+
+    /// Whole unit periods and the leftover fraction before the first payment
+    fn first_period(&self) -> (i32, f64) {
+        let whole = self.days_to_first_payment / DAYS_PER_PERIOD;
+        let odd_days = self.days_to_first_payment % DAYS_PER_PERIOD;
+        (
+            whole as i32,
+            f64::from(odd_days) / f64::from(DAYS_PER_PERIOD),
+        )
+    }
+
+    fn monthly_rate(&self) -> f64 {
+        f64::from(self.apr_bps) / 10_000.0 / 12.0
+    }
+
+    /// Level payment that amortizes the loan (before last-payment rounding)
+    fn level_payment(&self) -> i64 {
+        let principal = self.principal_cents as f64;
+        let n = self.term_months;
+        let i = self.monthly_rate();
+        if i == 0.0 {
+            return (principal / f64::from(n)).round() as i64;
+        }
+        let (t, f) = self.first_period();
+        // principal = pmt * sum(1 / ((1 + f*i)(1 + i)^(t + k))) for k in 0..n
+        let growth = (1.0 + f * i) * (1.0 + i).powi(t);
+        let annuity: f64 = (0..n).map(|k| (1.0 + i).powi(-(k as i32))).sum();
+        (principal * growth / annuity).round() as i64
+    }
+
+    /// Interest for one row on balance_cents, rounded half up to the cent
+    fn interest(&self, balance_cents: i64, first: bool) -> i64 {
+        if self.apr_bps == 0 {
+            return 0;
+        }
+        if first && self.days_to_first_payment != DAYS_PER_PERIOD {
+            let (t, f) = self.first_period();
+            let i = self.monthly_rate();
+            let factor = (1.0 + f * i) * (1.0 + i).powi(t) - 1.0;
+            return (balance_cents as f64 * factor).round() as i64;
+        }
+        // balance * apr_bps / 10_000 / 12, exactly
+        let numerator = i128::from(balance_cents) * i128::from(self.apr_bps);
+        ((numerator + 60_000) / 120_000) as i64
+    }
+
+    fn schedule(&self) -> Vec<AmortizationRow> {
+        let level = self.level_payment();
+        let mut balance = self.principal_cents;
+        let mut rows = Vec::with_capacity(self.term_months as usize);
+
+        for number in 1..=self.term_months {
+            let interest_cents = self.interest(balance, number == 1);
+            let payment_cents = if number == self.term_months {
+                balance + interest_cents
+            } else {
+                // Tiny loans can pay off early; never pay past zero
+                level.min(balance + interest_cents)
+            };
+            let principal_cents = payment_cents - interest_cents;
+            balance -= principal_cents;
+            rows.push(AmortizationRow {
+                number,
+                payment_cents,
+                interest_cents,
+                principal_cents,
+                balance_cents: balance,
+            });
+        }
+        rows
+    }
+
+    fn quote(&self, rows: &[AmortizationRow]) -> PaymentQuote {
+        let total_of_payments_cents: i64 = rows.iter().map(|row| row.payment_cents).sum();
+        let total_interest_cents: i64 = rows.iter().map(|row| row.interest_cents).sum();
+
+        PaymentQuote {
+            monthly_payment_cents: rows.first().map_or(0, |row| row.payment_cents),
+            final_payment_cents: rows.last().map_or(0, |row| row.payment_cents),
+            number_of_payments: self.term_months,
+            days_to_first_payment: self.days_to_first_payment,
+            total_interest_cents,
+            total_of_payments_cents,
+            amount_financed_cents: self.principal_cents,
+            finance_charge_cents: total_interest_cents,
+            disclosed_apr_bps: self.disclosed_apr_bps(rows),
+        }
+    }
+
+    /// Solve the Appendix J equation for the rate that discounts the actual (rounded)
+    /// payments back to the amount financed
+    fn disclosed_apr_bps(&self, rows: &[AmortizationRow]) -> i64 {
+        let (t, f) = self.first_period();
+        let amount_financed = self.principal_cents as f64;
+        let present_value = |i: f64| -> f64 {
+            rows.iter()
+                .enumerate()
+                .map(|(k, row)| {
+                    row.payment_cents as f64 / ((1.0 + f * i) * (1.0 + i).powi(t + k as i32))
+                })
+                .sum()
+        };
+
+        // Present value falls as the rate rises; bisect on the monthly rate
+        let (mut low, mut high) = (0.0_f64, 1.0_f64);
+        if present_value(low) <= amount_financed {
+            return 0;
+        }
+        for _ in 0..100 {
+            let mid = (low + high) / 2.0;
+            if present_value(mid) > amount_financed {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        ((low + high) / 2.0 * 12.0 * 10_000.0).round() as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn schedule(principal: i64, apr_bps: u32, term: u32, days: u32) -> Vec<AmortizationRow> {
+        Loan::new(principal, apr_bps, term, Some(days))
+            .unwrap()
+            .schedule()
+    }
+
+    #[test]
+    fn test_standard_loan() {
+        // $25,000 at 6.9% for 60 months
+        let quote = finance_calculate_payment(2_500_000, 690, 60, None).unwrap();
+        assert_eq!(quote.monthly_payment_cents, 49_385);
+        assert_eq!(quote.number_of_payments, 60);
+        assert_eq!(
+            quote.total_of_payments_cents,
+            quote.amount_financed_cents + quote.finance_charge_cents
+        );
+        assert!((quote.final_payment_cents - quote.monthly_payment_cents).abs() <= 60);
+        assert_eq!(quote.disclosed_apr_bps, 690);
+    }
+
+    #[test]
+    fn test_zero_interest() {
+        let rows = schedule(1_000_000, 0, 36, 30);
+        assert!(rows.iter().all(|row| row.interest_cents == 0));
+        assert_eq!(rows[0].payment_cents, 27_778);
+        // 35 * 277.78 = 9722.30, leaving 277.70
+        assert_eq!(rows[35].payment_cents, 27_770);
+        assert_eq!(rows[35].balance_cents, 0);
+
+        let quote = finance_calculate_payment(1_000_000, 0, 36, Some(45)).unwrap();
+        assert_eq!(quote.finance_charge_cents, 0);
+        assert_eq!(quote.disclosed_apr_bps, 0);
+
+        // Fewer cents than payments: pays off early, never overpays
+        let rows = schedule(5, 0, 10, 30);
+        assert_eq!(rows.iter().map(|row| row.payment_cents).sum::<i64>(), 5);
+        assert!(rows.iter().all(|row| row.balance_cents >= 0));
+    }
+
+    #[test]
+    fn test_odd_first_period() {
+        let regular = schedule(2_000_000, 900, 48, 30);
+        let long = schedule(2_000_000, 900, 48, 45);
+        let short = schedule(2_000_000, 900, 48, 20);
+
+        // 45 days: a month plus half a month of simple interest
+        assert_eq!(regular[0].interest_cents, 15_000);
+        assert_eq!(long[0].interest_cents, 22_556);
+        assert_eq!(short[0].interest_cents, 10_000);
+        assert!(long[0].payment_cents > regular[0].payment_cents);
+        assert!(short[0].payment_cents < regular[0].payment_cents);
+
+        // The disclosed APR still matches the note rate within the Reg Z tolerance (1/8%)
+        for days in [20, 30, 45, 59] {
+            let quote = finance_calculate_payment(2_000_000, 900, 48, Some(days)).unwrap();
+            assert!((quote.disclosed_apr_bps - 900).abs() <= 12, "days {}", days);
+        }
+    }
+
+    #[test]
+    fn test_rejects_bad_input() {
+        assert!(finance_calculate_payment(0, 500, 60, None).is_err());
+        assert!(finance_calculate_payment(100_000, 10_001, 60, None).is_err());
+        assert!(finance_calculate_payment(100_000, 500, 0, None).is_err());
+        assert!(finance_calculate_payment(100_000, 500, 361, None).is_err());
+        assert!(finance_calculate_payment(100_000, 500, 60, Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_schedule_sums_exactly_property() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        for _ in 0..2_000 {
+            let principal = rng.random_range(1..=20_000_000);
+            let apr_bps = rng.random_range(0..=3_000);
+            let term = rng.random_range(1..=96);
+            let days = rng.random_range(1..=60);
+            let loan = Loan::new(principal, apr_bps, term, Some(days)).unwrap();
+            let rows = loan.schedule();
+            let quote = loan.quote(&rows);
+            let case = format!(
+                "{} cents, {} bps, {} months, {} days",
+                principal, apr_bps, term, days
+            );
+
+            assert_eq!(rows.len(), term as usize, "{}", case);
+            assert_eq!(rows.last().unwrap().balance_cents, 0, "{}", case);
+            assert_eq!(
+                rows.iter().map(|row| row.principal_cents).sum::<i64>(),
+                principal,
+                "{}",
+                case
+            );
+            assert_eq!(
+                quote.total_of_payments_cents,
+                principal + quote.total_interest_cents,
+                "{}",
+                case
+            );
+            for row in &rows {
+                assert_eq!(row.payment_cents, row.interest_cents + row.principal_cents);
+                assert!(row.balance_cents >= 0, "{}", case);
+            }
+        }
+    }
+}
@@ -0,0 +1,386 @@
+// src-tauri/src/fs_watcher.rs
+//
+// Watch the documents folder for changes made outside the app
+// (e.g. scanner software dropping PDFs into a deal folder)
+
+use log::{error, info, warn};
+use notify::event::{ModifyKind, RenameMode};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Window};
+
+use crate::docs_config::get_documents_root_path;
+use crate::storage::get_documents_storage_path;
+
+/// Event name emitted to the frontend
+const FS_CHANGE_EVENT: &str = "fs-change";
+
+/// Quiet period before a change is reported (coalesces scanner save/rename bursts)
+const DEBOUNCE_WINDOW: Duration = Duration::from_millis(500);
+
+struct ActiveWatch {
+    window_label: String,
+    // Dropping the watcher closes the event channel and stops the debounce thread
+    _watcher: RecommendedWatcher,
+}
+
+static WATCHES: Lazy<Mutex<HashMap<u32, ActiveWatch>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_WATCH_ID: AtomicU32 = AtomicU32::new(1);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Modified,
+    Removed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsChangeEvent {
+    pub watch_id: u32,
+    pub path: String,
+    pub kind: ChangeKind,
+}
+
+/// Coalesces raw notify events per path until the path has been quiet for `window`
+/// A `None` kind marks a temp file that appeared and vanished within the window
+struct Debouncer {
+    window: Duration,
+    pending: HashMap<PathBuf, (Option<ChangeKind>, Instant)>,
+}
+
+impl Debouncer {
+    fn new(window: Duration) -> Self {
+        Debouncer {
+            window,
+            pending: HashMap::new(),
+        }
+    }
+
+    fn push(&mut self, path: PathBuf, kind: ChangeKind, now: Instant) {
+        let merged = match (self.pending.get(&path).map(|(k, _)| *k), kind) {
+            (None, kind) => Some(kind),
+            // Created then removed within the window never happened
+            (Some(Some(ChangeKind::Created)), ChangeKind::Removed) => None,
+            (Some(Some(ChangeKind::Created)), _) => Some(ChangeKind::Created),
+            (Some(None), ChangeKind::Removed) => None,
+            (Some(None), _) => Some(ChangeKind::Created),
+            // Replaced in place (delete + recreate, or rename over an existing file)
+            (Some(Some(_)), ChangeKind::Created) => Some(ChangeKind::Modified),
+            (Some(Some(_)), kind) => Some(kind),
+        };
+
+        self.pending.insert(path, (merged, now));
+    }
+
+    /// Remove and return every change that has been quiet for the full window
+    fn drain_ready(&mut self, now: Instant) -> Vec<(PathBuf, ChangeKind)> {
+        let window = self.window;
+        let ready: Vec<PathBuf> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, seen))| now.duration_since(*seen) >= window)
+            .map(|(path, _)| path.clone())
+            .collect();
+
+        let mut changes: Vec<(PathBuf, ChangeKind)> = ready
+            .into_iter()
+            .filter_map(|path| match self.pending.remove(&path) {
+                Some((Some(kind), _)) => Some((path, kind)),
+                _ => None,
+            })
+            .collect();
+        changes.sort();
+        changes
+    }
+
+    fn drain_all(&mut self) -> Vec<(PathBuf, ChangeKind)> {
+        let mut changes: Vec<(PathBuf, ChangeKind)> = self
+            .pending
+            .drain()
+            .filter_map(|(path, (kind, _))| kind.map(|kind| (path, kind)))
+            .collect();
+        changes.sort();
+        changes
+    }
+}
+
+/// Map a raw notify event onto (path, kind) pairs
+fn classify(event: &notify::Event) -> Vec<(PathBuf, ChangeKind)> {
+    match &event.kind {
+        EventKind::Create(_) => event
+            .paths
+            .iter()
+            .map(|p| (p.clone(), ChangeKind::Created))
+            .collect(),
+        EventKind::Remove(_) => event
+            .paths
+            .iter()
+            .map(|p| (p.clone(), ChangeKind::Removed))
+            .collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => vec![
+            (event.paths[0].clone(), ChangeKind::Removed),
+            (event.paths[1].clone(), ChangeKind::Created),
+        ],
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) => event
+            .paths
+            .iter()
+            .map(|p| (p.clone(), ChangeKind::Removed))
+            .collect(),
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => event
+            .paths
+            .iter()
+            .map(|p| (p.clone(), ChangeKind::Created))
+            .collect(),
+        EventKind::Modify(_) | EventKind::Any => event
+            .paths
+            .iter()
+            .map(|p| (p.clone(), ChangeKind::Modified))
+            .collect(),
+        EventKind::Access(_) | EventKind::Other => Vec::new(),
+    }
+}
+
+/// Start a watcher whose debounced changes are delivered to `sink`
+fn start_watch<F>(
+    watch_id: u32,
+    path: &Path,
+    recursive: bool,
+    window: Duration,
+    sink: F,
+) -> Result<RecommendedWatcher, String>
+where
+    F: Fn(FsChangeEvent) + Send + 'static,
+{
+    let (tx, rx) = mpsc::channel::<notify::Result<notify::Event>>();
+
+    let mut watcher = notify::recommended_watcher(move |res| {
+        let _ = tx.send(res);
+    })
+    .map_err(|e| format!("Failed to create file watcher: {}", e))?;
+
+    let mode = if recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher
+        .watch(path, mode)
+        .map_err(|e| format!("Failed to watch {}: {}", path.display(), e))?;
+
+    std::thread::spawn(move || {
+        let mut debouncer = Debouncer::new(window);
+        let tick = window / 4;
+
+        let emit = |changes: Vec<(PathBuf, ChangeKind)>| {
+            for (path, kind) in changes {
+                sink(FsChangeEvent {
+                    watch_id,
+                    path: path.to_string_lossy().to_string(),
+                    kind,
+                });
+            }
+        };
+
+        loop {
+            match rx.recv_timeout(tick) {
+                Ok(Ok(event)) => {
+                    let now = Instant::now();
+                    for (path, kind) in classify(&event) {
+                        debouncer.push(path, kind, now);
+                    }
+                }
+                Ok(Err(e)) => warn!("⚠️  [FS-WATCH] Watch {} error: {}", watch_id, e),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    emit(debouncer.drain_all());
+                    info!("🛑 [FS-WATCH] Watch {} stopped", watch_id);
+                    break;
+                }
+            }
+
+            emit(debouncer.drain_ready(Instant::now()));
+        }
+    });
+
+    Ok(watcher)
+}
+
+/// Canonical documents roots the watcher is allowed to observe
+async fn allowed_roots() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+
+    if let Ok(Some(custom)) = get_documents_root_path().await {
+        roots.push(PathBuf::from(custom));
+    }
+    if let Ok(default) = get_documents_storage_path() {
+        roots.push(PathBuf::from(default));
+    }
+
+    roots
+        .into_iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .collect()
+}
+
+/// Watch a directory under the documents root and emit debounced "fs-change" events
+/// SECURITY: Only paths inside the documents root can be watched
+#[tauri::command]
+pub async fn watch_directory(path: String, recursive: bool, window: Window) -> Result<u32, String> {
+    info!(
+        "👀 [FS-WATCH] Watching directory: {} (recursive: {})",
+        path, recursive
+    );
+
+    let target = PathBuf::from(&path)
+        .canonicalize()
+        .map_err(|e| format!("Invalid watch path {}: {}", path, e))?;
+
+    if !target.is_dir() {
+        return Err(format!("Not a directory: {}", path));
+    }
+
+    let roots = allowed_roots().await;
+    if !roots.iter().any(|root| target.starts_with(root)) {
+        error!(
+            "❌ [FS-WATCH] Refusing to watch path outside documents root: {}",
+            path
+        );
+        return Err("Path is outside the documents root".to_string());
+    }
+
+    let watch_id = NEXT_WATCH_ID.fetch_add(1, Ordering::SeqCst);
+    let window_label = window.label().to_string();
+    let emitter = window.clone();
+    let label = window_label.clone();
+
+    let watcher = start_watch(
+        watch_id,
+        &target,
+        recursive,
+        DEBOUNCE_WINDOW,
+        move |change| {
+            if let Err(e) = emitter.emit_to(&label, FS_CHANGE_EVENT, &change) {
+                error!("❌ [FS-WATCH] Failed to emit change event: {}", e);
+            }
+        },
+    )?;
+
+    WATCHES.lock().unwrap().insert(
+        watch_id,
+        ActiveWatch {
+            window_label,
+            _watcher: watcher,
+        },
+    );
+
+    info!("✅ [FS-WATCH] Watch {} started", watch_id);
+    Ok(watch_id)
+}
+
+/// Stop a directory watch
+#[tauri::command]
+pub fn unwatch_directory(watch_id: u32) -> Result<(), String> {
+    match WATCHES.lock().unwrap().remove(&watch_id) {
+        Some(_) => {
+            info!("✅ [FS-WATCH] Watch {} removed", watch_id);
+            Ok(())
+        }
+        None => Err(format!("No active watch with id {}", watch_id)),
+    }
+}
+
+/// Drop every watch owned by a window (called when the window closes)
+pub fn unwatch_window(window_label: &str) {
+    let mut watches = WATCHES.lock().unwrap();
+    let before = watches.len();
+    watches.retain(|_, watch| watch.window_label != window_label);
+
+    let removed = before - watches.len();
+    if removed > 0 {
+        info!(
+            "🧹 [FS-WATCH] Removed {} watches for closed window {}",
+            removed, window_label
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_debounce_waits_for_quiet_period() {
+        let mut debouncer = Debouncer::new(Duration::from_millis(500));
+        let start = Instant::now();
+        let path = PathBuf::from("/docs/deal-1/scan.pdf");
+
+        debouncer.push(path.clone(), ChangeKind::Created, start);
+        debouncer.push(
+            path.clone(),
+            ChangeKind::Modified,
+            start + Duration::from_millis(300),
+        );
+
+        assert!(debouncer
+            .drain_ready(start + Duration::from_millis(600))
+            .is_empty());
+        assert_eq!(
+            debouncer.drain_ready(start + Duration::from_millis(800)),
+            vec![(path, ChangeKind::Created)]
+        );
+    }
+
+    #[test]
+    fn test_debounce_coalesces_save_rename() {
+        let mut debouncer = Debouncer::new(Duration::from_millis(500));
+        let now = Instant::now();
+        let temp = PathBuf::from("/docs/deal-1/~scan.tmp");
+        let final_path = PathBuf::from("/docs/deal-1/scan.pdf");
+
+        debouncer.push(temp.clone(), ChangeKind::Created, now);
+        debouncer.push(temp.clone(), ChangeKind::Modified, now);
+        debouncer.push(temp.clone(), ChangeKind::Removed, now);
+        // inotify reports a rename both as From/To halves and as a paired event
+        debouncer.push(temp, ChangeKind::Removed, now);
+        debouncer.push(final_path.clone(), ChangeKind::Created, now);
+        debouncer.push(final_path.clone(), ChangeKind::Created, now);
+
+        assert_eq!(
+            debouncer.drain_all(),
+            vec![(final_path, ChangeKind::Created)]
+        );
+    }
+
+    #[test]
+    fn test_watch_temp_dir_emits_single_event() {
+        let dir = temp_dir("watch", "single-event");
+
+        let (tx, rx) = mpsc::channel();
+        let watcher = start_watch(1, &dir, true, Duration::from_millis(200), move |change| {
+            let _ = tx.send(change);
+        })
+        .unwrap();
+
+        let temp = dir.join("scan.tmp");
+        std::fs::write(&temp, b"partial").unwrap();
+        std::fs::write(&temp, b"complete").unwrap();
+        std::fs::rename(&temp, dir.join("scan.pdf")).unwrap();
+
+        let change = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(change.path.ends_with("scan.pdf"));
+        assert_eq!(change.kind, ChangeKind::Created);
+        assert!(rx.recv_timeout(Duration::from_millis(600)).is_err());
+
+        drop(watcher);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,503 @@
+// src-tauri/src/global_search.rs
+//
+// One search across clients, vehicles and deals for the top search bar
+// Each table gets one ranked query over its trigram search index, all run under a single
+// connection lock (a few ms on 50k rows per table). Queries that look like an identifier
+// are also matched exactly, and exact hits rank above partial ones:
+//   - 17-character VIN            -> vehicles.vin (and deals for that vehicle)
+//   - phone number                -> clients.phone, compared digits only
+//   - single token (stock/deal #) -> vehicles.stock_number, deals.id (or an 8+ char prefix)
+// Deals are also found by their co-buyers' names, emails and phones (deal_cobuyers_search).
+
+use rusqlite::{params, Connection, Result as SqlResult, Row};
+use serde::Serialize;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::get_db;
+use crate::db_busy::DbError;
+use crate::recent_items::EntityType;
+use crate::telemetry::track;
+
+const DEFAULT_LIMIT_PER_TYPE: u32 = 5;
+const MAX_LIMIT_PER_TYPE: u32 = 50;
+
+/// Exact identifier match
+pub const RANK_EXACT: i64 = 100;
+/// Deal whose vehicle has the exact VIN
+pub const RANK_RELATED_EXACT: i64 = 60;
+/// Name/identifier starts with the query
+pub const RANK_PREFIX: i64 = 50;
+/// Query appears somewhere in a searched field
+pub const RANK_CONTAINS: i64 = 10;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchResult {
+    pub entity_type: EntityType,
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub rank: i64,
+    /// Tie-break between equal ranks (newer first)
+    #[serde(skip)]
+    updated_at: i64,
+}
+
+/// The identifier forms a query could be, beyond plain text
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct QueryPatterns {
+    /// Upper-cased, when the query is a well-formed VIN
+    pub vin: Option<String>,
+    /// Digits only, when the query is a phone number
+    pub phone_digits: Option<String>,
+    /// Stock or deal number: the query as one token, without a leading '#'
+    pub token: Option<String>,
+}
+
+impl QueryPatterns {
+    pub fn detect(query: &str) -> QueryPatterns {
+        let query = query.trim();
+        let mut patterns = QueryPatterns::default();
+
+        // VINs are 17 characters and never use I, O or Q
+        if query.len() == 17
+            && query.chars().all(|c| c.is_ascii_alphanumeric())
+            && !query
+                .chars()
+                .any(|c| matches!(c.to_ascii_uppercase(), 'I' | 'O' | 'Q'))
+        {
+            patterns.vin = Some(query.to_ascii_uppercase());
+        }
+
+        let digits: String = query.chars().filter(|c| c.is_ascii_digit()).collect();
+        let phone_like = query
+            .chars()
+            .all(|c| c.is_ascii_digit() || "()-. +".contains(c));
+        if phone_like && (7..=15).contains(&digits.len()) {
+            patterns.phone_digits = Some(digits);
+        }
+
+        let token = query.trim_start_matches('#');
+        if !token.is_empty() && !token.contains(char::is_whitespace) {
+            patterns.token = Some(token.to_string());
+        }
+        patterns
+    }
+}
+
+/// Phone column with the usual punctuation stripped
+const PHONE_DIGITS: &str = "REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(REPLACE(
+    COALESCE(c.phone, ''), '(', ''), ')', ''), '-', ''), ' ', ''), '.', ''), '+', '')";
+
+/// Index matches considered per table, newest first. Only queries matching more rows
+/// than this (e.g. a common make) rank a subset; exact identifier hits are looked up
+/// separately, so they're never cut off
+const MAX_CANDIDATES: u32 = 2000;
+
+// Parameters shared by the three queries:
+// ?1 user_id, ?2 '%term%', ?3 'term%', ?4 VIN, ?5 phone digits, ?6 token, ?7 limit
+// Candidates come from the *_search trigram indexes (migration 016); CROSS JOIN keeps
+// SQLite from probing the index once per row instead
+fn clients_sql() -> String {
+    format!(
+        "WITH candidates AS (
+            SELECT rowid FROM (
+                SELECT rowid FROM clients_search WHERE body LIKE ?2
+                ORDER BY rowid DESC LIMIT {cap}
+            )
+        )
+        SELECT c.id, c.first_name || ' ' || c.last_name AS title,
+            COALESCE(c.phone, c.email) AS subtitle, c.updated_at,
+            CASE
+                WHEN ?5 IS NOT NULL AND {phone} LIKE '%' || ?5 THEN {exact}
+                WHEN c.first_name LIKE ?3 OR c.last_name LIKE ?3
+                    OR c.first_name || ' ' || c.last_name LIKE ?3 OR c.email LIKE ?3 THEN {prefix}
+                ELSE {contains}
+            END AS rank
+        FROM candidates k CROSS JOIN clients c ON c.rowid = k.rowid
+        WHERE c.user_id = ?1
+        ORDER BY rank DESC, c.updated_at DESC
+        LIMIT ?7",
+        cap = MAX_CANDIDATES,
+        phone = PHONE_DIGITS,
+        exact = RANK_EXACT,
+        prefix = RANK_PREFIX,
+        contains = RANK_CONTAINS
+    )
+}
+
+fn vehicles_sql() -> String {
+    format!(
+        "WITH candidates AS (
+            SELECT rowid FROM (
+                SELECT rowid FROM vehicles_search WHERE body LIKE ?2
+                ORDER BY rowid DESC LIMIT {cap}
+            )
+            UNION SELECT rowid FROM vehicles WHERE vin = ?4
+            UNION SELECT rowid FROM vehicles WHERE stock_number = ?6
+        )
+        SELECT v.id, v.year || ' ' || v.make || ' ' || v.model AS title,
+            COALESCE('Stock #' || v.stock_number || ' · ', '') || v.vin AS subtitle, v.updated_at,
+            CASE
+                WHEN ?4 IS NOT NULL AND v.vin = ?4 COLLATE NOCASE THEN {exact}
+                WHEN ?6 IS NOT NULL AND v.stock_number = ?6 COLLATE NOCASE THEN {exact}
+                WHEN v.vin LIKE ?3 OR v.stock_number LIKE ?3 OR v.make LIKE ?3
+                    OR v.model LIKE ?3 OR v.year || ' ' || v.make || ' ' || v.model LIKE ?3
+                    THEN {prefix}
+                ELSE {contains}
+            END AS rank
+        FROM candidates k CROSS JOIN vehicles v ON v.rowid = k.rowid
+        WHERE v.user_id = ?1
+        ORDER BY rank DESC, v.updated_at DESC
+        LIMIT ?7",
+        cap = MAX_CANDIDATES,
+        exact = RANK_EXACT,
+        prefix = RANK_PREFIX,
+        contains = RANK_CONTAINS
+    )
+}
+
+fn deals_sql() -> String {
+    format!(
+        "WITH candidates AS (
+            SELECT rowid FROM (
+                SELECT rowid FROM deals_search WHERE body LIKE ?2
+                ORDER BY rowid DESC LIMIT {cap}
+            )
+            UNION SELECT rowid FROM deals WHERE id = ?6
+            UNION SELECT d.rowid FROM vehicles v JOIN deals d ON d.vehicle_id = v.id
+                WHERE v.vin = ?4
+            UNION SELECT d.rowid FROM (
+                SELECT rowid FROM deal_cobuyers_search WHERE body LIKE ?2
+                ORDER BY rowid DESC LIMIT {cap}
+            ) s CROSS JOIN deal_cobuyers b ON b.rowid = s.rowid
+            JOIN deals d ON d.id = b.deal_id
+        )
+        SELECT d.id, COALESCE(c.first_name || ' ' || c.last_name, 'Deal ' || d.id) AS title,
+            COALESCE(v.year || ' ' || v.make || ' ' || v.model || ' · ', '') || d.status
+                AS subtitle,
+            d.updated_at,
+            CASE
+                WHEN ?6 IS NOT NULL AND (d.id = ?6 COLLATE NOCASE
+                    OR (length(?6) >= 8 AND d.id LIKE ?6 || '%')) THEN {exact}
+                WHEN ?4 IS NOT NULL AND v.vin = ?4 COLLATE NOCASE THEN {related}
+                WHEN c.first_name LIKE ?3 OR c.last_name LIKE ?3
+                    OR c.first_name || ' ' || c.last_name LIKE ?3 THEN {prefix}
+                WHEN EXISTS (SELECT 1 FROM deal_cobuyers b WHERE b.deal_id = d.id
+                    AND (b.first_name LIKE ?3 OR b.last_name LIKE ?3
+                        OR b.first_name || ' ' || b.last_name LIKE ?3)) THEN {prefix}
+                ELSE {contains}
+            END AS rank
+        FROM candidates k CROSS JOIN deals d ON d.rowid = k.rowid
+        LEFT JOIN clients c ON c.id = d.client_id
+        LEFT JOIN vehicles v ON v.id = d.vehicle_id
+        WHERE d.user_id = ?1
+        ORDER BY rank DESC, d.updated_at DESC
+        LIMIT ?7",
+        cap = MAX_CANDIDATES,
+        exact = RANK_EXACT,
+        related = RANK_RELATED_EXACT,
+        prefix = RANK_PREFIX,
+        contains = RANK_CONTAINS
+    )
+}
+
+fn search(
+    conn: &Connection,
+    user_id: &str,
+    query: &str,
+    limit_per_type: u32,
+) -> SqlResult<Vec<SearchResult>> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let patterns = QueryPatterns::detect(query);
+    // Phone numbers are indexed as digits too; '#' before a stock/deal number isn't stored
+    let term = patterns
+        .phone_digits
+        .as_deref()
+        .or(patterns.token.as_deref())
+        .unwrap_or(query);
+    let contains = format!("%{}%", term);
+    let prefix = format!("{}%", term);
+
+    let mut results = Vec::new();
+    for (entity_type, sql) in [
+        (EntityType::Client, clients_sql()),
+        (EntityType::Vehicle, vehicles_sql()),
+        (EntityType::Deal, deals_sql()),
+    ] {
+        let mut stmt = conn.prepare_cached(&sql)?;
+        let rows = stmt.query_map(
+            params![
+                user_id,
+                contains,
+                prefix,
+                patterns.vin,
+                patterns.phone_digits,
+                patterns.token,
+                limit_per_type
+            ],
+            |row: &Row| {
+                Ok(SearchResult {
+                    entity_type,
+                    id: row.get("id")?,
+                    title: row.get("title")?,
+                    subtitle: row.get("subtitle")?,
+                    rank: row.get("rank")?,
+                    updated_at: row.get("updated_at")?,
+                })
+            },
+        )?;
+        for row in rows {
+            results.push(row?);
+        }
+    }
+
+    // Stable sort keeps clients, vehicles, deals order among exact ties
+    results.sort_by(|a, b| {
+        b.rank
+            .cmp(&a.rank)
+            .then_with(|| b.updated_at.cmp(&a.updated_at))
+    });
+    Ok(results)
+}
+
+/// Search clients, vehicles and deals at once, best matches first
+/// limit_per_type caps each kind (default 5, at most 50)
+#[tauri::command]
+pub fn db_global_search(
+    query: String,
+    user_id: Option<String>,
+    limit_per_type: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SearchResult>, DbError> {
+    track("db_global_search", || {
+        let user_id_value = state.require_user(user_id)?;
+        let limit = limit_per_type
+            .unwrap_or(DEFAULT_LIMIT_PER_TYPE)
+            .clamp(1, MAX_LIMIT_PER_TYPE);
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        Ok(search(&conn, &user_id_value, &query, limit).map_err(|e| e.to_string())?)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIN: &str = "1HGCM82633A004352";
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/016_add_search_index.sql"),
+            include_str!("../migrations/025_add_deal_cobuyers.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, email, phone, created_at, updated_at, user_id)
+             VALUES
+                ('c1', 'Ada', 'Lovelace', 'ada@example.com', '(555) 010-0100', 1, 10, 'u1'),
+                ('c2', 'Adam', 'Smith', NULL, '555.010.0199', 1, 20, 'u1'),
+                ('c3', 'Ada', 'Other', NULL, '5550100100', 1, 30, 'u2');
+             INSERT INTO vehicles (id, vin, stock_number, year, make, model, mileage, price, status,
+                created_at, updated_at, user_id)
+             VALUES
+                ('v1', '1HGCM82633A004352', 'A12', 2021, 'Honda', 'Civic', 0, 1.0, 'available', 1, 10, 'u1'),
+                ('v2', '1HGCM82633A004353', 'A123', 2022, 'Honda', 'Accord', 0, 1.0, 'available', 1, 20, 'u1');
+             INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount, document_ids,
+                created_at, updated_at, user_id)
+             VALUES
+                ('3f2a9c1e-0000-4000-8000-000000000001', 'retail', 'c1', 'v1', 'pending', 1.0, '[]', 1, 15, 'u1'),
+                ('7b4d0e2f-0000-4000-8000-000000000002', 'retail', 'c2', 'v2', 'sold', 1.0, '[]', 1, 25, 'u1');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn ids(results: &[SearchResult]) -> Vec<(&str, i64)> {
+        results.iter().map(|r| (r.id.as_str(), r.rank)).collect()
+    }
+
+    #[test]
+    fn test_detects_identifier_patterns() {
+        let vin = QueryPatterns::detect(&VIN.to_lowercase());
+        assert_eq!(vin.vin.as_deref(), Some(VIN));
+
+        // I/O/Q never appear in VINs
+        assert_eq!(QueryPatterns::detect("1HGCM82633A00435O").vin, None);
+        assert_eq!(
+            QueryPatterns::detect("(555) 010-0100")
+                .phone_digits
+                .as_deref(),
+            Some("5550100100")
+        );
+        assert_eq!(QueryPatterns::detect("12345").phone_digits, None);
+        assert_eq!(QueryPatterns::detect("#A12").token.as_deref(), Some("A12"));
+        assert_eq!(QueryPatterns::detect("Ada Lovelace").token, None);
+    }
+
+    #[test]
+    fn test_exact_vin_ranks_first() {
+        let conn = test_db();
+        let results = search(&conn, "u1", VIN, 5).unwrap();
+        assert_eq!(
+            ids(&results),
+            [
+                ("v1", RANK_EXACT),
+                ("3f2a9c1e-0000-4000-8000-000000000001", RANK_RELATED_EXACT)
+            ]
+        );
+        assert_eq!(results[0].entity_type, EntityType::Vehicle);
+    }
+
+    #[test]
+    fn test_exact_stock_number_beats_newer_prefix_match() {
+        let conn = test_db();
+        let results = search(&conn, "u1", "a12", 5).unwrap();
+        let vehicles: Vec<_> = results
+            .iter()
+            .filter(|r| r.entity_type == EntityType::Vehicle)
+            .collect();
+        assert_eq!(
+            ids(&vehicles.into_iter().cloned().collect::<Vec<_>>()),
+            [("v1", RANK_EXACT), ("v2", RANK_PREFIX)]
+        );
+        assert_eq!(results[0].id, "v1");
+    }
+
+    #[test]
+    fn test_phone_matches_on_digits_only() {
+        let conn = test_db();
+        let results = search(&conn, "u1", "555-010-0100", 5).unwrap();
+        // Stored as "(555) 010-0100"; u2's client with the same number stays hidden
+        assert_eq!(ids(&results), [("c1", RANK_EXACT)]);
+        assert_eq!(results[0].subtitle.as_deref(), Some("(555) 010-0100"));
+    }
+
+    #[test]
+    fn test_deal_number_prefix() {
+        let conn = test_db();
+        let results = search(&conn, "u1", "#3f2a9c1e", 5).unwrap();
+        assert_eq!(
+            ids(&results),
+            [("3f2a9c1e-0000-4000-8000-000000000001", RANK_EXACT)]
+        );
+        assert_eq!(results[0].title, "Ada Lovelace");
+        assert_eq!(
+            results[0].subtitle.as_deref(),
+            Some("2021 Honda Civic · pending")
+        );
+
+        // Too short to be a deal number: only an ordinary partial match
+        assert_eq!(
+            ids(&search(&conn, "u1", "3f2a", 5).unwrap()),
+            [("3f2a9c1e-0000-4000-8000-000000000001", RANK_CONTAINS)]
+        );
+    }
+
+    #[test]
+    fn test_mixes_types_by_rank_then_recency() {
+        let conn = test_db();
+        let results = search(&conn, "u1", "ada", 5).unwrap();
+        let found: Vec<(EntityType, &str)> = results
+            .iter()
+            .map(|r| (r.entity_type, r.id.as_str()))
+            .collect();
+        // All prefix matches (Ada, Adam and their deals): newest first across types
+        assert_eq!(
+            found,
+            [
+                (EntityType::Deal, "7b4d0e2f-0000-4000-8000-000000000002"),
+                (EntityType::Client, "c2"),
+                (EntityType::Deal, "3f2a9c1e-0000-4000-8000-000000000001"),
+                (EntityType::Client, "c1"),
+            ]
+        );
+        assert!(results.iter().all(|r| r.rank == RANK_PREFIX));
+
+        let limited = search(&conn, "u1", "honda", 1).unwrap();
+        let vehicles = limited
+            .iter()
+            .filter(|r| r.entity_type == EntityType::Vehicle)
+            .count();
+        assert_eq!(vehicles, 1);
+        assert!(search(&conn, "u1", "  ", 5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_index_follows_edits() {
+        let conn = test_db();
+        conn.execute("UPDATE clients SET last_name = 'Byron' WHERE id = 'c1'", [])
+            .unwrap();
+        let found: Vec<(EntityType, String)> = search(&conn, "u1", "byron", 5)
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.entity_type, r.id))
+            .collect();
+        // The client's deal is found under the new name too
+        assert_eq!(
+            found,
+            [
+                (
+                    EntityType::Deal,
+                    "3f2a9c1e-0000-4000-8000-000000000001".to_string()
+                ),
+                (EntityType::Client, "c1".to_string()),
+            ]
+        );
+        assert!(search(&conn, "u1", "lovelace", 5).unwrap().is_empty());
+
+        conn.execute("DELETE FROM deals WHERE vehicle_id = 'v2'", [])
+            .unwrap();
+        conn.execute("DELETE FROM vehicles WHERE id = 'v2'", [])
+            .unwrap();
+        assert!(search(&conn, "u1", "accord", 5).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_cobuyer_finds_their_deal() {
+        let conn = test_db();
+        conn.execute(
+            "INSERT INTO deal_cobuyers (id, deal_id, user_id, first_name, last_name, phone,
+                 created_at, updated_at)
+             VALUES ('cb1', '7b4d0e2f-0000-4000-8000-000000000002', 'u1', 'Grace', 'Hopper',
+                 '(555) 222-3333', 1, 1)",
+            [],
+        )
+        .unwrap();
+        let deal = "7b4d0e2f-0000-4000-8000-000000000002";
+
+        assert_eq!(
+            ids(&search(&conn, "u1", "hopper", 5).unwrap()),
+            [(deal, RANK_PREFIX)]
+        );
+        assert_eq!(
+            ids(&search(&conn, "u1", "grace hop", 5).unwrap()),
+            [(deal, RANK_PREFIX)]
+        );
+        // The deal is still titled with the buyer's name
+        let by_phone = search(&conn, "u1", "555-222-3333", 5).unwrap();
+        assert_eq!(ids(&by_phone), [(deal, RANK_CONTAINS)]);
+        assert_eq!(by_phone[0].title, "Adam Smith");
+        assert!(search(&conn, "u2", "hopper", 5).unwrap().is_empty());
+
+        conn.execute(
+            "UPDATE deal_cobuyers SET last_name = 'Murray' WHERE id = 'cb1'",
+            [],
+        )
+        .unwrap();
+        assert!(search(&conn, "u1", "hopper", 5).unwrap().is_empty());
+        conn.execute("DELETE FROM deal_cobuyers WHERE id = 'cb1'", [])
+            .unwrap();
+        assert!(search(&conn, "u1", "murray", 5).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,283 @@
+// src-tauri/src/idempotency.rs
+//
+// Idempotency keys for create commands. When the webview retries an invoke that timed out,
+// the first call may well have gone through, and a second insert (the frontend picks a new
+// id each time) leaves a duplicate client or deal. db_create_client and db_create_deal take
+// an optional idempotency_key: the first call records which row it created under the key,
+// and a call with the same key within 24 hours gets that row back without inserting. The
+// lookup and the record happen in the insert's write transaction, so two calls racing with
+// one key can't both insert. Keys are per user. Expired keys are pruned at startup, and an
+// expired key that's sent again simply creates a new row.
+
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult};
+
+use crate::database::get_db;
+use crate::recent_items::EntityType;
+
+/// How long a key is remembered
+pub(crate) const IDEMPOTENCY_WINDOW_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// Longest key accepted (UUIDs and the like are far shorter)
+const MAX_KEY_LENGTH: usize = 200;
+
+fn check_key(key: &str) -> Result<(), String> {
+    if key.trim().is_empty() {
+        return Err("Idempotency key can't be empty".to_string());
+    }
+    if key.len() > MAX_KEY_LENGTH {
+        return Err(format!(
+            "Idempotency key is longer than {} characters",
+            MAX_KEY_LENGTH
+        ));
+    }
+    Ok(())
+}
+
+/// What an earlier call created under key, if it's less than a day old. load reads the
+/// row by id; call inside the write transaction of the insert
+pub(crate) fn replay<T>(
+    conn: &Connection,
+    user_id: &str,
+    key: Option<&str>,
+    entity: EntityType,
+    now: i64,
+    load: impl FnOnce(&str) -> Result<Option<T>, String>,
+) -> Result<Option<T>, String> {
+    let Some(key) = key else {
+        return Ok(None);
+    };
+    check_key(key)?;
+    let earlier: Option<(String, String)> = conn
+        .query_row(
+            "SELECT entity_type, entity_id FROM idempotency_keys
+             WHERE user_id = ?1 AND key = ?2 AND created_at > ?3",
+            params![user_id, key, now - IDEMPOTENCY_WINDOW_MS],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some((entity_type, entity_id)) = earlier else {
+        return Ok(None);
+    };
+    if entity_type != entity.as_str() {
+        return Err(format!(
+            "Idempotency key {} was already used to create a {}",
+            key, entity_type
+        ));
+    }
+    match load(&entity_id)? {
+        Some(row) => {
+            info!(
+                "↩️  Replayed {} {} for idempotency key {}",
+                entity_type, entity_id, key
+            );
+            Ok(Some(row))
+        }
+        None => Err(format!(
+            "The {} created with idempotency key {} no longer exists",
+            entity_type, key
+        )),
+    }
+}
+
+/// Record that entity_id was created under key (replacing an expired use of the key)
+pub(crate) fn remember(
+    conn: &Connection,
+    user_id: &str,
+    key: Option<&str>,
+    entity: EntityType,
+    entity_id: &str,
+    now: i64,
+) -> SqlResult<()> {
+    let Some(key) = key else {
+        return Ok(());
+    };
+    conn.execute(
+        "INSERT INTO idempotency_keys (key, user_id, entity_type, entity_id, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(user_id, key) DO UPDATE SET
+             entity_type = excluded.entity_type,
+             entity_id = excluded.entity_id,
+             created_at = excluded.created_at",
+        params![key, user_id, entity.as_str(), entity_id, now],
+    )?;
+    Ok(())
+}
+
+/// Delete keys older than the window; returns how many
+pub(crate) fn prune_expired_keys(conn: &Connection, now: i64) -> SqlResult<usize> {
+    conn.execute(
+        "DELETE FROM idempotency_keys WHERE created_at <= ?1",
+        params![now - IDEMPOTENCY_WINDOW_MS],
+    )
+}
+
+/// Prune the shared database's expired keys (startup maintenance)
+pub fn prune_idempotency_keys() {
+    let pruned = get_db().map_err(|e| e.to_string()).and_then(|db| {
+        prune_expired_keys(&db.conn(), Utc::now().timestamp_millis()).map_err(|e| e.to_string())
+    });
+    match pruned {
+        Ok(0) => {}
+        Ok(pruned) => info!("✅ Pruned {} expired idempotency key(s)", pruned),
+        Err(e) => warn!("⚠️  Failed to prune idempotency keys: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{db_create_client, db_create_deal, db_create_vehicle};
+    use crate::test_support::{make_client, make_deal, make_vehicle, TestApp};
+
+    fn count(app: &TestApp, table: &str) -> i64 {
+        app.conn()
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    fn key(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[test]
+    fn test_a_retried_create_returns_the_first_row() {
+        let app = TestApp::new();
+        let first = db_create_client(
+            make_client("c1"),
+            None,
+            None,
+            key("k-client"),
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        // The retry comes with a new id, as the frontend picks one per call
+        let mut retry = make_client("c1-retry");
+        retry.first_name = "Casey".to_string();
+        let replayed =
+            db_create_client(retry, None, None, key("k-client"), app.state(), app.db()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&replayed).unwrap(),
+            serde_json::to_value(&first).unwrap()
+        );
+        assert_eq!(count(&app, "clients"), 1);
+
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        let first = db_create_deal(
+            make_deal("d1", "c1", "v1"),
+            None,
+            key("k-deal"),
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        let replayed = db_create_deal(
+            make_deal("d1-retry", "c1", "v1"),
+            None,
+            key("k-deal"),
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        assert_eq!(replayed.id, "d1");
+        assert_eq!(
+            serde_json::to_value(&replayed).unwrap(),
+            serde_json::to_value(&first).unwrap()
+        );
+        assert_eq!(count(&app, "deals"), 1);
+
+        // A client's key isn't a deal's
+        let err = db_create_deal(
+            make_deal("d2", "c1", "v1"),
+            None,
+            key("k-client"),
+            app.state(),
+            app.db(),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("already used to create a client"));
+
+        // Another user's key of the same name is theirs
+        app.sign_in("other-user");
+        db_create_client(
+            make_client("c2"),
+            None,
+            None,
+            key("k-client"),
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        assert_eq!(count(&app, "clients"), 2);
+    }
+
+    #[test]
+    fn test_different_keys_create_different_rows_and_keys_expire() {
+        let app = TestApp::new();
+        db_create_client(
+            make_client("c1"),
+            None,
+            None,
+            key("k1"),
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        db_create_client(
+            make_client("c2"),
+            None,
+            None,
+            key("k2"),
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        db_create_client(make_client("c3"), None, None, None, app.state(), app.db()).unwrap();
+        assert_eq!(count(&app, "clients"), 3);
+        assert_eq!(count(&app, "idempotency_keys"), 2);
+
+        // A day later k1 is forgotten: sent again, it creates a new row
+        app.conn()
+            .execute(
+                "UPDATE idempotency_keys SET created_at = created_at - ?1 WHERE key = 'k1'",
+                [IDEMPOTENCY_WINDOW_MS],
+            )
+            .unwrap();
+        let again = db_create_client(
+            make_client("c4"),
+            None,
+            None,
+            key("k1"),
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        assert_eq!(again.id, "c4");
+        assert_eq!(count(&app, "clients"), 4);
+
+        app.conn()
+            .execute(
+                "UPDATE idempotency_keys SET created_at = created_at - ?1 WHERE key = 'k2'",
+                [IDEMPOTENCY_WINDOW_MS],
+            )
+            .unwrap();
+        let now = Utc::now().timestamp_millis();
+        assert_eq!(prune_expired_keys(&app.conn(), now).unwrap(), 1);
+        assert_eq!(count(&app, "idempotency_keys"), 1);
+
+        assert!(db_create_client(
+            make_client("c5"),
+            None,
+            None,
+            key("  "),
+            app.state(),
+            app.db()
+        )
+        .is_err());
+    }
+}
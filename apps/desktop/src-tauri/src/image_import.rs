@@ -0,0 +1,612 @@
+// src-tauri/src/image_import.rs
+//
+// Copying photos into the managed folders. Phone photos are often stored sideways with an
+// EXIF orientation tag, and carry GPS and camera details that shouldn't travel with a
+// listing. On import the pixels are turned upright, EXIF is dropped except for the capture
+// timestamps (kept on JPEGs), and photos larger than a max dimension are scaled down.
+// A photo that needs none of that is copied byte for byte.
+//
+// No HEIC decoder is bundled, so HEIC photos are rejected with a message saying how to
+// get a JPEG instead, whatever their extension says.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader};
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+const JPEG_QUALITY: u8 = 90;
+
+/// ISO base media brands used by HEIC/HEIF files
+const HEIC_BRANDS: [&[u8; 4]; 8] = [
+    b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1", b"msf1",
+];
+
+const TAG_ORIENTATION: u16 = 0x0112;
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+/// DateTimeOriginal, DateTimeDigitized and their OffsetTime* zones
+const EXIF_TIMESTAMP_TAGS: [u16; 5] = [0x9003, 0x9004, 0x9010, 0x9011, 0x9012];
+
+/// What import did to a photo on its way into a managed folder
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Transformation {
+    /// Turned or flipped upright per its EXIF orientation (2-8)
+    Oriented { orientation: u16 },
+    /// EXIF removed; the capture timestamps are kept when the stored file is a JPEG
+    MetadataStripped { gps: bool, kept_timestamps: bool },
+    /// Scaled down to fit the max dimension
+    Downscaled {
+        from_width: u32,
+        from_height: u32,
+        width: u32,
+        height: u32,
+    },
+}
+
+/// The EXIF fields import cares about
+#[derive(Debug, Default, PartialEq)]
+struct ExifSummary {
+    orientation: Option<u16>,
+    gps: bool,
+    /// (tag, value) of DateTime and the EXIF_TIMESTAMP_TAGS present
+    timestamps: Vec<(u16, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TagValue {
+    Short(u16),
+    Long(u32),
+    Ascii(String),
+}
+
+impl TagValue {
+    fn kind(&self) -> u16 {
+        match self {
+            TagValue::Ascii(_) => 2,
+            TagValue::Short(_) => 3,
+            TagValue::Long(_) => 4,
+        }
+    }
+
+    fn count(&self) -> u32 {
+        match self {
+            TagValue::Ascii(s) => s.len() as u32 + 1,
+            TagValue::Short(_) | TagValue::Long(_) => 1,
+        }
+    }
+
+    fn bytes(&self) -> Vec<u8> {
+        match self {
+            TagValue::Short(v) => v.to_le_bytes().to_vec(),
+            TagValue::Long(v) => v.to_le_bytes().to_vec(),
+            TagValue::Ascii(s) => s.bytes().chain([0]).collect(),
+        }
+    }
+}
+
+/// Bounds-checked reads from a TIFF structure (the body of an EXIF block)
+struct Tiff<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let data = data.strip_prefix(b"Exif\0\0").unwrap_or(data);
+        let little_endian = match data.get(..4)? {
+            b"II*\0" => true,
+            b"MM\0*" => false,
+            _ => return None,
+        };
+        Some(Tiff {
+            data,
+            little_endian,
+        })
+    }
+
+    fn u16_at(&self, at: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self.data.get(at..at + 2)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u16::from_le_bytes(bytes)
+        } else {
+            u16::from_be_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, at: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self.data.get(at..at + 4)?.try_into().ok()?;
+        Some(if self.little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    /// (tag, type, count, offset of the value field) of each entry in the IFD at offset
+    fn entries(&self, offset: usize) -> Vec<(u16, u16, u32, usize)> {
+        let count = self.u16_at(offset).unwrap_or(0) as usize;
+        (0..count)
+            .map_while(|i| {
+                let at = offset + 2 + i * 12;
+                Some((
+                    self.u16_at(at)?,
+                    self.u16_at(at + 2)?,
+                    self.u32_at(at + 4)?,
+                    at + 8,
+                ))
+            })
+            .collect()
+    }
+
+    fn ascii(&self, count: u32, value_at: usize) -> Option<String> {
+        let count = count as usize;
+        let start = if count <= 4 {
+            value_at
+        } else {
+            self.u32_at(value_at)? as usize
+        };
+        let bytes = self.data.get(start..start.checked_add(count)?)?;
+        let text = bytes.split(|b| *b == 0).next().unwrap_or_default();
+        Some(String::from_utf8_lossy(text).to_string())
+    }
+}
+
+fn read_exif(chunk: &[u8]) -> ExifSummary {
+    let mut summary = ExifSummary::default();
+    let Some(tiff) = Tiff::new(chunk) else {
+        return summary;
+    };
+    let Some(ifd0) = tiff.u32_at(4) else {
+        return summary;
+    };
+
+    let mut exif_ifd = None;
+    for (tag, kind, count, value_at) in tiff.entries(ifd0 as usize) {
+        match (tag, kind) {
+            (TAG_ORIENTATION, 3) => summary.orientation = tiff.u16_at(value_at),
+            (TAG_DATE_TIME, 2) => summary
+                .timestamps
+                .extend(tiff.ascii(count, value_at).map(|v| (tag, v))),
+            (TAG_EXIF_IFD, 4) => exif_ifd = tiff.u32_at(value_at),
+            (TAG_GPS_IFD, _) => summary.gps = true,
+            _ => {}
+        }
+    }
+    // Guards against an EXIF IFD pointing back at IFD0
+    if let Some(offset) = exif_ifd.filter(|offset| *offset != ifd0) {
+        for (tag, kind, count, value_at) in tiff.entries(offset as usize) {
+            if kind == 2 && EXIF_TIMESTAMP_TAGS.contains(&tag) {
+                summary
+                    .timestamps
+                    .extend(tiff.ascii(count, value_at).map(|v| (tag, v)));
+            }
+        }
+    }
+    summary
+}
+
+fn ifd_len(entries: &[(u16, TagValue)]) -> usize {
+    let spilled: usize = entries
+        .iter()
+        .map(|(_, value)| value.bytes().len())
+        .filter(|len| *len > 4)
+        .map(|len| len + len % 2)
+        .sum();
+    2 + 12 * entries.len() + 4 + spilled
+}
+
+/// One IFD (with the values that don't fit in an entry after it) placed at offset
+fn encode_ifd(entries: &mut [(u16, TagValue)], offset: usize) -> Vec<u8> {
+    entries.sort_by_key(|(tag, _)| *tag);
+    let mut data_at = offset + 2 + 12 * entries.len() + 4;
+    let mut out = (entries.len() as u16).to_le_bytes().to_vec();
+    let mut data = Vec::new();
+    for (tag, value) in entries.iter() {
+        out.extend(tag.to_le_bytes());
+        out.extend(value.kind().to_le_bytes());
+        out.extend(value.count().to_le_bytes());
+        let mut bytes = value.bytes();
+        if bytes.len() <= 4 {
+            bytes.resize(4, 0);
+            out.extend(bytes);
+        } else {
+            out.extend((data_at as u32).to_le_bytes());
+            if bytes.len() % 2 == 1 {
+                bytes.push(0);
+            }
+            data_at += bytes.len();
+            data.extend(bytes);
+        }
+    }
+    out.extend(0u32.to_le_bytes());
+    out.extend(data);
+    out
+}
+
+/// A little-endian TIFF block with IFD0 and, if they have entries, EXIF and GPS IFDs
+fn encode_exif(
+    mut ifd0: Vec<(u16, TagValue)>,
+    mut exif: Vec<(u16, TagValue)>,
+    mut gps: Vec<(u16, TagValue)>,
+) -> Vec<u8> {
+    if !exif.is_empty() {
+        ifd0.push((TAG_EXIF_IFD, TagValue::Long(0)));
+    }
+    if !gps.is_empty() {
+        ifd0.push((TAG_GPS_IFD, TagValue::Long(0)));
+    }
+    let exif_at = 8 + ifd_len(&ifd0);
+    let gps_at = exif_at + if exif.is_empty() { 0 } else { ifd_len(&exif) };
+    for (tag, value) in ifd0.iter_mut() {
+        match *tag {
+            TAG_EXIF_IFD => *value = TagValue::Long(exif_at as u32),
+            TAG_GPS_IFD => *value = TagValue::Long(gps_at as u32),
+            _ => {}
+        }
+    }
+
+    let mut out = b"II*\0".to_vec();
+    out.extend(8u32.to_le_bytes());
+    out.extend(encode_ifd(&mut ifd0, 8));
+    if !exif.is_empty() {
+        out.extend(encode_ifd(&mut exif, exif_at));
+    }
+    if !gps.is_empty() {
+        out.extend(encode_ifd(&mut gps, gps_at));
+    }
+    out
+}
+
+/// The JPEG with an EXIF (APP1) segment right after its start-of-image marker
+fn with_exif_segment(jpeg: &[u8], tiff: &[u8]) -> Result<Vec<u8>, String> {
+    let body = jpeg
+        .strip_prefix(&[0xFF, 0xD8])
+        .ok_or_else(|| "Encoded image is not a JPEG".to_string())?;
+    let length = u16::try_from(2 + 6 + tiff.len()).map_err(|_| "EXIF block too large")?;
+    let mut out = Vec::with_capacity(jpeg.len() + tiff.len() + 10);
+    out.extend([0xFF, 0xD8, 0xFF, 0xE1]);
+    out.extend(length.to_be_bytes());
+    out.extend(b"Exif\0\0");
+    out.extend(tiff);
+    out.extend(body);
+    Ok(out)
+}
+
+/// The timestamps alone, as an EXIF block saying the pixels are upright
+fn timestamps_exif(timestamps: &[(u16, String)]) -> Vec<u8> {
+    let (mut ifd0, exif): (Vec<_>, Vec<_>) = timestamps
+        .iter()
+        .map(|(tag, value)| (*tag, TagValue::Ascii(value.clone())))
+        .partition(|(tag, _)| *tag == TAG_DATE_TIME);
+    ifd0.push((TAG_ORIENTATION, TagValue::Short(1)));
+    encode_exif(ifd0, exif, Vec::new())
+}
+
+/// Upright pixels for an EXIF orientation (1-8); other values are left alone
+fn apply_orientation(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Err with what to do instead if source is a HEIC/HEIF photo (by extension or content)
+pub(crate) fn reject_heic(source: &Path) -> Result<(), String> {
+    let by_extension = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| e.eq_ignore_ascii_case("heic") || e.eq_ignore_ascii_case("heif"));
+    let mut header = [0u8; 12];
+    let by_content = File::open(source)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok()
+        && &header[4..8] == b"ftyp"
+        && HEIC_BRANDS.iter().any(|brand| header[8..12] == brand[..]);
+    if by_extension || by_content {
+        return Err(format!(
+            "{} is a HEIC photo, which can't be imported on this computer. Save it as JPEG \
+             first (on an iPhone, Settings > Camera > Formats > Most Compatible takes JPEGs)",
+            source.file_name().unwrap_or_default().to_string_lossy()
+        ));
+    }
+    Ok(())
+}
+
+/// Copy source to destination (format from its extension), turned upright, scrubbed of
+/// EXIF and scaled to fit max_dimension. Nothing is written if source doesn't decode
+pub(crate) fn import_image(
+    source: &Path,
+    destination: &Path,
+    max_dimension: Option<u32>,
+) -> Result<Vec<Transformation>, String> {
+    reject_heic(source)?;
+    let format = ImageFormat::from_path(destination).map_err(|e| e.to_string())?;
+    let mut decoder = ImageReader::open(source)
+        .map_err(|e| format!("Failed to open image: {}", e))?
+        .with_guessed_format()
+        .map_err(|e| format!("Failed to open image: {}", e))?
+        .into_decoder()
+        .map_err(|e| format!("Unreadable image: {}", e))?;
+    let exif = decoder.exif_metadata().ok().flatten();
+    let summary = exif.as_deref().map(read_exif).unwrap_or_default();
+    let (from_width, from_height) = decoder.dimensions();
+
+    let orientation = summary.orientation.filter(|o| (2..=8).contains(o));
+    let limit = max_dimension.filter(|max| *max > 0 && from_width.max(from_height) > *max);
+    if exif.is_none() && orientation.is_none() && limit.is_none() {
+        // Decodes: the header was read above, and the thumbnail reads the rest
+        fs::copy(source, destination).map_err(|e| format!("Failed to copy image: {}", e))?;
+        return Ok(Vec::new());
+    }
+
+    let mut image =
+        DynamicImage::from_decoder(decoder).map_err(|e| format!("Unreadable image: {}", e))?;
+    let mut transformations = Vec::new();
+    if let Some(orientation) = orientation {
+        image = apply_orientation(image, orientation);
+        transformations.push(Transformation::Oriented { orientation });
+    }
+    if let Some(max) = limit {
+        let (width, height) = (image.width(), image.height());
+        image = image.resize(max, max, FilterType::Lanczos3);
+        transformations.push(Transformation::Downscaled {
+            from_width: width,
+            from_height: height,
+            width: image.width(),
+            height: image.height(),
+        });
+    }
+
+    let kept_timestamps = format == ImageFormat::Jpeg && !summary.timestamps.is_empty();
+    let written = if format == ImageFormat::Jpeg {
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+            .encode_image(&DynamicImage::ImageRgb8(image.to_rgb8()))
+            .map_err(|e| format!("Failed to encode image: {}", e))?;
+        if kept_timestamps {
+            jpeg = with_exif_segment(&jpeg, &timestamps_exif(&summary.timestamps))?;
+        }
+        fs::write(destination, jpeg).map_err(|e| e.to_string())
+    } else {
+        image
+            .save_with_format(destination, format)
+            .map_err(|e| e.to_string())
+    };
+    if let Err(e) = written {
+        let _ = fs::remove_file(destination);
+        return Err(format!("Failed to save image: {}", e));
+    }
+    if exif.is_some() {
+        transformations.push(Transformation::MetadataStripped {
+            gps: summary.gps,
+            kept_timestamps,
+        });
+    }
+
+    // Keeps "newest first" sorting of the folder meaningful for re-encoded photos
+    if let Ok(modified) = fs::metadata(source).and_then(|m| m.modified()) {
+        let _ = File::options()
+            .write(true)
+            .open(destination)
+            .and_then(|file| file.set_modified(modified));
+    }
+    Ok(transformations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use image::{Rgb, RgbImage};
+
+    const TAKEN_AT: &str = "2024:05:01 10:30:00";
+
+    /// 64x32, red in the top-left quarter, blue elsewhere: any turn or flip moves the red
+    fn upright() -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::from_fn(64, 32, |x, y| {
+            if x < 32 && y < 16 {
+                Rgb([220, 30, 30])
+            } else {
+                Rgb([30, 30, 220])
+            }
+        }))
+    }
+
+    fn write_jpeg(path: &Path, image: &DynamicImage, tiff: &[u8]) {
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, 95)
+            .encode_image(image)
+            .unwrap();
+        fs::write(path, with_exif_segment(&jpeg, tiff).unwrap()).unwrap();
+    }
+
+    fn stored_exif(path: &Path) -> ExifSummary {
+        let mut decoder = ImageReader::open(path)
+            .unwrap()
+            .with_guessed_format()
+            .unwrap()
+            .into_decoder()
+            .unwrap();
+        decoder
+            .exif_metadata()
+            .unwrap()
+            .as_deref()
+            .map(read_exif)
+            .unwrap_or_default()
+    }
+
+    fn is_red(image: &RgbImage, x: u32, y: u32) -> bool {
+        let Rgb([r, _, b]) = *image.get_pixel(x, y);
+        r > b
+    }
+
+    #[test]
+    fn test_every_orientation_is_stored_upright() {
+        let dir = temp_dir("image-import", "orientation");
+        for orientation in 1..=8u16 {
+            // The camera's sensor-order pixels: undo what the tag says to do
+            let inverse = match orientation {
+                6 => 8,
+                8 => 6,
+                other => other,
+            };
+            let source = dir.join(format!("o{}.jpg", orientation));
+            let tiff = encode_exif(
+                vec![(TAG_ORIENTATION, TagValue::Short(orientation))],
+                Vec::new(),
+                Vec::new(),
+            );
+            write_jpeg(&source, &apply_orientation(upright(), inverse), &tiff);
+            assert_eq!(read_exif(&tiff).orientation, Some(orientation));
+
+            let destination = dir.join(format!("stored{}.jpg", orientation));
+            let applied = import_image(&source, &destination, None).unwrap();
+            let mut expected = Vec::new();
+            if orientation != 1 {
+                expected.push(Transformation::Oriented { orientation });
+            }
+            expected.push(Transformation::MetadataStripped {
+                gps: false,
+                kept_timestamps: false,
+            });
+            assert_eq!(applied, expected, "orientation {}", orientation);
+
+            let stored = image::open(&destination).unwrap().to_rgb8();
+            assert_eq!(stored.dimensions(), (64, 32), "orientation {}", orientation);
+            assert!(is_red(&stored, 8, 4), "orientation {}", orientation);
+            assert!(!is_red(&stored, 56, 4), "orientation {}", orientation);
+            assert!(!is_red(&stored, 8, 28), "orientation {}", orientation);
+            assert_eq!(stored_exif(&destination).orientation, None);
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_gps_and_camera_details_are_dropped_but_timestamps_kept() {
+        let dir = temp_dir("image-import", "gps");
+        let source = dir.join("lot.jpg");
+        let tiff = encode_exif(
+            vec![
+                (0x010F, TagValue::Ascii("Apple".to_string())),
+                (0x0110, TagValue::Ascii("iPhone 15 Pro".to_string())),
+                (TAG_ORIENTATION, TagValue::Short(1)),
+                (TAG_DATE_TIME, TagValue::Ascii(TAKEN_AT.to_string())),
+            ],
+            vec![
+                (0x9003, TagValue::Ascii(TAKEN_AT.to_string())),
+                (0x9011, TagValue::Ascii("-07:00".to_string())),
+                (0xA431, TagValue::Ascii("SERIAL-123456".to_string())),
+            ],
+            vec![
+                (0x0001, TagValue::Ascii("N".to_string())),
+                (0x0003, TagValue::Ascii("W".to_string())),
+                (0x001D, TagValue::Ascii("2024:05:01".to_string())),
+            ],
+        );
+        write_jpeg(&source, &upright(), &tiff);
+        let before = read_exif(&tiff);
+        assert!(before.gps);
+        assert_eq!(before.timestamps.len(), 3);
+
+        let destination = dir.join("stored.jpg");
+        let applied = import_image(&source, &destination, None).unwrap();
+        assert_eq!(
+            applied,
+            [Transformation::MetadataStripped {
+                gps: true,
+                kept_timestamps: true,
+            }]
+        );
+
+        let after = stored_exif(&destination);
+        assert!(!after.gps);
+        assert_eq!(after.orientation, Some(1));
+        assert_eq!(after.timestamps, before.timestamps);
+        let bytes = fs::read(&destination).unwrap();
+        for secret in [&b"iPhone"[..], b"SERIAL-123456", b"Apple"] {
+            assert!(
+                !bytes.windows(secret.len()).any(|w| w == secret),
+                "{}",
+                String::from_utf8_lossy(secret)
+            );
+        }
+
+        // PNGs are stored without any EXIF
+        let png = dir.join("stored.png");
+        let applied = import_image(&source, &png, None).unwrap();
+        assert_eq!(
+            applied,
+            [Transformation::MetadataStripped {
+                gps: true,
+                kept_timestamps: false,
+            }]
+        );
+        assert_eq!(stored_exif(&png), ExifSummary::default());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_plain_photos_are_copied_and_big_ones_scaled_down() {
+        let dir = temp_dir("image-import", "resize");
+        let source = dir.join("plain.png");
+        RgbImage::from_pixel(640, 480, Rgb([0, 90, 200]))
+            .save(&source)
+            .unwrap();
+
+        let copied = dir.join("copied.png");
+        assert!(import_image(&source, &copied, Some(640))
+            .unwrap()
+            .is_empty());
+        assert_eq!(fs::read(&copied).unwrap(), fs::read(&source).unwrap());
+
+        let scaled = dir.join("scaled.png");
+        let applied = import_image(&source, &scaled, Some(100)).unwrap();
+        assert_eq!(
+            applied,
+            [Transformation::Downscaled {
+                from_width: 640,
+                from_height: 480,
+                width: 100,
+                height: 75,
+            }]
+        );
+        assert_eq!(image::image_dimensions(&scaled).unwrap(), (100, 75));
+        // 0 means no limit
+        assert!(import_image(&source, &dir.join("zero.png"), Some(0))
+            .unwrap()
+            .is_empty());
+
+        let fake = dir.join("notes.jpg");
+        fs::write(&fake, b"not a jpeg").unwrap();
+        assert!(import_image(&fake, &dir.join("fake.jpg"), None).is_err());
+        assert!(!dir.join("fake.jpg").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_heic_is_rejected_with_a_way_out() {
+        let dir = temp_dir("image-import", "heic");
+        let mut heic = vec![0, 0, 0, 24];
+        heic.extend(b"ftypheic\0\0\0\0mif1heic");
+        // iPhone exports sometimes keep HEIC data under a .jpg name
+        for name in ["IMG_0001.HEIC", "IMG_0002.jpg"] {
+            let source = dir.join(name);
+            fs::write(&source, &heic).unwrap();
+            let err = import_image(&source, &dir.join("out.jpg"), None).unwrap_err();
+            assert!(err.contains("HEIC") && err.contains("JPEG"), "{}", err);
+        }
+        assert!(!dir.join("out.jpg").exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,674 @@
+// src-tauri/src/inventory_feed.rs
+//
+// Inventory feeds for marketplaces (vAuto/Homenet style): available vehicles rendered as a
+// delimited file with a fixed header. Column mappings are JSON templates; two built-ins
+// ("homenet", comma and "vauto", pipe) can be overridden or extended via the
+// inventory_feed_templates setting. dealer_* fields come from the dealer profile, for
+// marketplaces that want the dealership's name and address on every row.
+// A schedule (inventory_feed_schedule setting) regenerates the feed into a folder on an
+// interval so an FTP uploader can pick it up. Files are written to a temp name and renamed,
+// so the uploader never sees a half-written feed.
+
+use log::{error, info};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::{db_get_setting, get_db, get_vehicles_for_user, Vehicle};
+use crate::dealer_profile::{get_dealer_profile_for_user, DealerProfile};
+use crate::settings_cache::set_setting;
+use crate::telemetry::track;
+
+pub const FEED_TEMPLATES_SETTING: &str = "inventory_feed_templates";
+pub const FEED_SCHEDULE_SETTING: &str = "inventory_feed_schedule";
+
+const FEED_VEHICLE_STATUS: &str = "available";
+const DEFAULT_FEED_FILE_NAME: &str = "inventory.csv";
+const MIN_INTERVAL_MINUTES: u32 = 15;
+const SCHEDULE_TICK: Duration = Duration::from_secs(60);
+const LINE_ENDING: &str = "\r\n";
+
+static LAST_RUN: Lazy<Mutex<FeedRunStatus>> = Lazy::new(|| Mutex::new(FeedRunStatus::default()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedField {
+    Vin,
+    StockNumber,
+    Year,
+    Make,
+    Model,
+    Trim,
+    Body,
+    Doors,
+    Transmission,
+    Engine,
+    Cylinders,
+    Mileage,
+    Color,
+    Price,
+    Description,
+    /// Photo URLs from the images column, joined with the template's photo_separator
+    Photos,
+    /// DBA, or the legal name if there's no DBA
+    DealerName,
+    DealerLicense,
+    DealerAddress,
+    DealerCity,
+    DealerState,
+    DealerZip,
+    DealerPhone,
+    DealerEmail,
+}
+
+/// One output column: a vehicle field, or a fixed value (e.g. the dealer's feed id)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedColumn {
+    pub header: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field: Option<FeedField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedTemplate {
+    /// Single character, usually "," or "|"
+    pub delimiter: String,
+    #[serde(default = "default_photo_separator")]
+    pub photo_separator: String,
+    #[serde(default = "default_include_header")]
+    pub include_header: bool,
+    pub columns: Vec<FeedColumn>,
+}
+
+fn default_photo_separator() -> String {
+    ",".to_string()
+}
+
+fn default_include_header() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedSchedule {
+    pub user_id: String,
+    pub template: String,
+    pub folder: String,
+    pub file_name: String,
+    pub interval_minutes: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FeedRunStatus {
+    pub last_run_at: Option<i64>, // unix ms
+    pub last_vehicle_count: Option<usize>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeedScheduleStatus {
+    pub schedule: Option<FeedSchedule>,
+    #[serde(flatten)]
+    pub status: FeedRunStatus,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeedExport {
+    pub path: String,
+    pub vehicle_count: usize,
+}
+
+/// Built-in and custom templates by name (custom ones win)
+#[tauri::command]
+pub fn get_inventory_feed_templates() -> Result<BTreeMap<String, FeedTemplate>, String> {
+    let mut templates = builtin_templates();
+    templates.extend(custom_templates()?);
+    Ok(templates)
+}
+
+#[tauri::command]
+pub fn save_inventory_feed_template(name: String, template: FeedTemplate) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Template name can't be empty".to_string());
+    }
+    validate_template(&template)?;
+
+    let mut templates = custom_templates()?;
+    templates.insert(name.clone(), template);
+    let json = serde_json::to_string(&templates).map_err(|e| e.to_string())?;
+    set_setting(FEED_TEMPLATES_SETTING, &json)?;
+    info!("✅ Saved inventory feed template: {}", name);
+    Ok(())
+}
+
+/// Write the user's available vehicles to output_path using the named template
+#[tauri::command]
+pub fn export_inventory_feed(
+    user_id: Option<String>,
+    template: String,
+    output_path: String,
+    state: State<'_, AppState>,
+) -> Result<FeedExport, String> {
+    track("export_inventory_feed", || {
+        let user_id_value = state.require_user(user_id)?;
+        generate_feed(&user_id_value, &template, Path::new(&output_path))
+    })
+}
+
+#[tauri::command]
+pub fn get_inventory_feed_schedule() -> Result<FeedScheduleStatus, String> {
+    Ok(FeedScheduleStatus {
+        schedule: saved_schedule()?,
+        status: LAST_RUN
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone(),
+    })
+}
+
+/// Regenerate the feed into folder every interval_minutes for the current user; 0 turns it off
+#[tauri::command]
+pub fn set_inventory_feed_schedule(
+    template: String,
+    folder: String,
+    file_name: Option<String>,
+    interval_minutes: u32,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<FeedScheduleStatus, String> {
+    if interval_minutes == 0 {
+        set_setting(FEED_SCHEDULE_SETTING, "")?;
+        info!("⏹️ Scheduled inventory feed turned off");
+        return get_inventory_feed_schedule();
+    }
+    if interval_minutes < MIN_INTERVAL_MINUTES {
+        return Err(format!(
+            "Interval must be at least {} minutes",
+            MIN_INTERVAL_MINUTES
+        ));
+    }
+    if !Path::new(&folder).is_dir() {
+        return Err(format!("Folder does not exist: {}", folder));
+    }
+    let file_name = file_name
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| DEFAULT_FEED_FILE_NAME.to_string());
+    if file_name.contains(['/', '\\']) || file_name.starts_with('.') {
+        return Err(format!("Invalid file name: {}", file_name));
+    }
+    find_template(&template)?;
+
+    let schedule = FeedSchedule {
+        user_id: state.require_user(user_id)?,
+        template,
+        folder,
+        file_name,
+        interval_minutes,
+    };
+    let json = serde_json::to_string(&schedule).map_err(|e| e.to_string())?;
+    set_setting(FEED_SCHEDULE_SETTING, &json)?;
+    info!(
+        "⏱️ Inventory feed scheduled every {} minutes",
+        schedule.interval_minutes
+    );
+    get_inventory_feed_schedule()
+}
+
+/// Background loop for the scheduled feed. Reads the schedule each tick, so changes
+/// apply without a restart; the first run happens a minute after startup
+pub fn start_scheduled_feed() {
+    std::thread::spawn(|| {
+        let mut last_run: Option<Instant> = None;
+        loop {
+            std::thread::sleep(SCHEDULE_TICK);
+            let schedule = match saved_schedule() {
+                Ok(Some(schedule)) => schedule,
+                Ok(None) => continue,
+                Err(e) => {
+                    error!("❌ Failed to read inventory feed schedule: {}", e);
+                    continue;
+                }
+            };
+
+            let interval = Duration::from_secs(u64::from(schedule.interval_minutes) * 60);
+            if last_run.is_some_and(|at| at.elapsed() < interval) {
+                continue;
+            }
+            last_run = Some(Instant::now());
+
+            let path = Path::new(&schedule.folder).join(&schedule.file_name);
+            let result = generate_feed(&schedule.user_id, &schedule.template, &path);
+            let mut status = LAST_RUN.lock().unwrap_or_else(PoisonError::into_inner);
+            status.last_run_at = Some(chrono::Utc::now().timestamp_millis());
+            match result {
+                Ok(export) => {
+                    status.last_vehicle_count = Some(export.vehicle_count);
+                    status.last_error = None;
+                }
+                Err(e) => {
+                    error!("❌ Scheduled inventory feed failed: {}", e);
+                    status.last_error = Some(e);
+                }
+            }
+        }
+    });
+}
+
+fn generate_feed(user_id: &str, template_name: &str, path: &Path) -> Result<FeedExport, String> {
+    let template = find_template(template_name)?;
+    let db = get_db().map_err(|e| e.to_string())?;
+    let vehicles: Vec<Vehicle> = get_vehicles_for_user(db, user_id)?
+        .into_iter()
+        .filter(|vehicle| vehicle.status == FEED_VEHICLE_STATUS)
+        .collect();
+    let dealer = get_dealer_profile_for_user(user_id)?;
+
+    write_atomically(
+        path,
+        render_feed(&template, &vehicles, dealer.as_ref()).as_bytes(),
+    )?;
+    info!(
+        "✅ Inventory feed written: {} vehicles -> {}",
+        vehicles.len(),
+        path.display()
+    );
+    Ok(FeedExport {
+        path: path.to_string_lossy().to_string(),
+        vehicle_count: vehicles.len(),
+    })
+}
+
+fn render_feed(
+    template: &FeedTemplate,
+    vehicles: &[Vehicle],
+    dealer: Option<&DealerProfile>,
+) -> String {
+    let delimiter = template.delimiter.chars().next().unwrap_or(',');
+    let join = |cells: Vec<String>| -> String {
+        cells
+            .iter()
+            .map(|cell| escape(cell, delimiter))
+            .collect::<Vec<_>>()
+            .join(&delimiter.to_string())
+    };
+
+    let mut lines = Vec::with_capacity(vehicles.len() + 1);
+    if template.include_header {
+        lines.push(join(
+            template
+                .columns
+                .iter()
+                .map(|column| column.header.clone())
+                .collect(),
+        ));
+    }
+    for vehicle in vehicles {
+        lines.push(join(
+            template
+                .columns
+                .iter()
+                .map(|column| match (&column.field, &column.value) {
+                    (Some(field), _) => field_value(vehicle, dealer, *field, template),
+                    (None, Some(value)) => value.clone(),
+                    (None, None) => String::new(),
+                })
+                .collect(),
+        ));
+    }
+
+    lines.join(LINE_ENDING) + LINE_ENDING
+}
+
+/// Dealer fields are empty until the dealer profile is filled in
+fn field_value(
+    vehicle: &Vehicle,
+    dealer: Option<&DealerProfile>,
+    field: FeedField,
+    template: &FeedTemplate,
+) -> String {
+    let text = |value: &Option<String>| value.clone().unwrap_or_default();
+    let dealer_text = |value: fn(&DealerProfile) -> &Option<String>| {
+        dealer.map(|d| text(value(d))).unwrap_or_default()
+    };
+    let number = |value: Option<i32>| value.map(|n| n.to_string()).unwrap_or_default();
+
+    match field {
+        FeedField::Vin => vehicle.vin.clone(),
+        FeedField::StockNumber => text(&vehicle.stock_number),
+        FeedField::Year => vehicle.year.to_string(),
+        FeedField::Make => vehicle.make.clone(),
+        FeedField::Model => vehicle.model.clone(),
+        FeedField::Trim => text(&vehicle.trim),
+        FeedField::Body => text(&vehicle.body),
+        FeedField::Doors => number(vehicle.doors),
+        FeedField::Transmission => text(&vehicle.transmission),
+        FeedField::Engine => text(&vehicle.engine),
+        FeedField::Cylinders => number(vehicle.cylinders),
+        FeedField::Mileage => vehicle.mileage.to_string(),
+        FeedField::Color => text(&vehicle.color),
+        FeedField::Price => format_price(vehicle.price),
+        // Feeds are single-line records
+        FeedField::Description => text(&vehicle.description)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+        FeedField::Photos => photo_urls(vehicle.images.as_deref()).join(&template.photo_separator),
+        FeedField::DealerName => dealer
+            .and_then(DealerProfile::display_name)
+            .unwrap_or_default()
+            .to_string(),
+        FeedField::DealerLicense => dealer_text(|d| &d.license_number),
+        FeedField::DealerAddress => dealer_text(|d| &d.address),
+        FeedField::DealerCity => dealer_text(|d| &d.city),
+        FeedField::DealerState => dealer_text(|d| &d.state),
+        FeedField::DealerZip => dealer_text(|d| &d.zip_code),
+        FeedField::DealerPhone => dealer_text(|d| &d.phone),
+        FeedField::DealerEmail => dealer_text(|d| &d.email),
+    }
+}
+
+/// Public photo URLs from the images JSON; local file paths mean nothing to a marketplace
+fn photo_urls(images: Option<&str>) -> Vec<String> {
+    images
+        .and_then(|json| serde_json::from_str::<Vec<String>>(json).ok())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|image| image.starts_with("https://") || image.starts_with("http://"))
+        .collect()
+}
+
+/// Whole dollars when there are no cents ("18500"), otherwise two decimals
+fn format_price(price: f64) -> String {
+    let cents = (price * 100.0).round() as i64;
+    if cents % 100 == 0 {
+        (cents / 100).to_string()
+    } else {
+        format!("{}.{:02}", cents / 100, (cents % 100).abs())
+    }
+}
+
+/// Quote a cell if it contains the delimiter, a quote or a line break (quotes are doubled)
+fn escape(cell: &str, delimiter: char) -> String {
+    if cell.contains(delimiter) || cell.contains(['"', '\r', '\n']) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+fn write_atomically(path: &Path, content: &[u8]) -> Result<(), String> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| format!("Invalid output path: {}", path.display()))?;
+    let mut temp_name = std::ffi::OsString::from(".");
+    temp_name.push(file_name);
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+
+    fs::write(&temp_path, content).map_err(|e| format!("Failed to write feed: {}", e))?;
+    fs::rename(&temp_path, path).map_err(|e| {
+        let _ = fs::remove_file(&temp_path);
+        format!("Failed to write feed: {}", e)
+    })
+}
+
+fn validate_template(template: &FeedTemplate) -> Result<(), String> {
+    let mut delimiter = template.delimiter.chars();
+    match (delimiter.next(), delimiter.next()) {
+        (Some(c), None) if !matches!(c, '"' | '\r' | '\n') => {}
+        _ => return Err("Delimiter must be a single character other than a quote".to_string()),
+    }
+    if template.columns.is_empty() {
+        return Err("Template needs at least one column".to_string());
+    }
+    for column in &template.columns {
+        if column.field.is_some() == column.value.is_some() {
+            return Err(format!(
+                "Column \"{}\" needs either a field or a fixed value",
+                column.header
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn find_template(name: &str) -> Result<FeedTemplate, String> {
+    get_inventory_feed_templates()?
+        .remove(name)
+        .ok_or_else(|| format!("Unknown inventory feed template: {}", name))
+}
+
+fn custom_templates() -> Result<BTreeMap<String, FeedTemplate>, String> {
+    match db_get_setting(FEED_TEMPLATES_SETTING.to_string())? {
+        Some(json) if !json.trim().is_empty() => serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid inventory feed templates setting: {}", e)),
+        _ => Ok(BTreeMap::new()),
+    }
+}
+
+fn saved_schedule() -> Result<Option<FeedSchedule>, String> {
+    match db_get_setting(FEED_SCHEDULE_SETTING.to_string())? {
+        Some(json) if !json.trim().is_empty() => serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| format!("Invalid inventory feed schedule setting: {}", e)),
+        _ => Ok(None),
+    }
+}
+
+fn builtin_templates() -> BTreeMap<String, FeedTemplate> {
+    let columns = [
+        ("VIN", FeedField::Vin),
+        ("Stock", FeedField::StockNumber),
+        ("Year", FeedField::Year),
+        ("Make", FeedField::Make),
+        ("Model", FeedField::Model),
+        ("Trim", FeedField::Trim),
+        ("Miles", FeedField::Mileage),
+        ("Price", FeedField::Price),
+        ("Photo URLs", FeedField::Photos),
+        ("Description", FeedField::Description),
+    ]
+    .map(|(header, field)| FeedColumn {
+        header: header.to_string(),
+        field: Some(field),
+        value: None,
+    })
+    .to_vec();
+
+    let template = |delimiter: &str| FeedTemplate {
+        delimiter: delimiter.to_string(),
+        photo_separator: default_photo_separator(),
+        include_header: true,
+        columns: columns.clone(),
+    };
+    BTreeMap::from([
+        ("homenet".to_string(), template(",")),
+        ("vauto".to_string(), template("|")),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn vehicle(vin: &str, description: Option<&str>, images: Option<&str>) -> Vehicle {
+        Vehicle {
+            id: format!("veh-{}", vin),
+            vin: vin.to_string(),
+            stock_number: Some("A1001".to_string()),
+            year: 2021,
+            make: "Honda".to_string(),
+            model: "Civic".to_string(),
+            trim: Some("EX".to_string()),
+            body: None,
+            doors: Some(4),
+            transmission: None,
+            engine: None,
+            cylinders: None,
+            title_number: None,
+            mileage: 12_345,
+            color: Some("Blue".to_string()),
+            price: 18_500.0,
+            cost: None,
+            status: "available".to_string(),
+            description: description.map(str::to_string),
+            images: images.map(str::to_string),
+            created_at: 0,
+            updated_at: 0,
+            synced_at: None,
+        }
+    }
+
+    #[test]
+    fn test_builtin_templates_render_fixed_header_and_photos() {
+        let templates = builtin_templates();
+        for template in templates.values() {
+            validate_template(template).unwrap();
+        }
+
+        let images = r#"["https://cdn.example.com/a.jpg","C:\\photos\\local.jpg","http://cdn.example.com/b.jpg"]"#;
+        let vehicles = [vehicle("1HGCM82633A004352", Some("Clean"), Some(images))];
+        let feed = render_feed(&templates["vauto"], &vehicles, None);
+
+        assert_eq!(
+            feed,
+            "VIN|Stock|Year|Make|Model|Trim|Miles|Price|Photo URLs|Description\r\n\
+             1HGCM82633A004352|A1001|2021|Honda|Civic|EX|12345|18500|\
+             https://cdn.example.com/a.jpg,http://cdn.example.com/b.jpg|Clean\r\n"
+        );
+    }
+
+    #[test]
+    fn test_custom_mapping_with_fixed_values() {
+        let template: FeedTemplate = serde_json::from_str(
+            r#"{
+                "delimiter": "\t",
+                "photo_separator": " ",
+                "include_header": false,
+                "columns": [
+                    {"header": "DealerId", "value": "D-42"},
+                    {"header": "Vin", "field": "vin"},
+                    {"header": "Asking", "field": "price"},
+                    {"header": "Images", "field": "photos"},
+                    {"header": "Doors", "field": "doors"},
+                    {"header": "Engine", "field": "engine"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        validate_template(&template).unwrap();
+
+        let mut car = vehicle(
+            "VIN1",
+            None,
+            Some(r#"["https://x.test/1.jpg","https://x.test/2.jpg"]"#),
+        );
+        car.price = 9_999.5;
+        assert_eq!(
+            render_feed(&template, &[car], None),
+            "D-42\tVIN1\t9999.50\thttps://x.test/1.jpg https://x.test/2.jpg\t4\t\r\n"
+        );
+
+        // A column must be exactly one of field/value, and the delimiter one character
+        let mut bad = template.clone();
+        bad.columns[0].field = Some(FeedField::Vin);
+        assert!(validate_template(&bad).is_err());
+        let mut bad = template.clone();
+        bad.delimiter = "||".to_string();
+        assert!(validate_template(&bad).is_err());
+        bad.delimiter = "\"".to_string();
+        assert!(validate_template(&bad).is_err());
+        bad.delimiter = ",".to_string();
+        bad.columns.clear();
+        assert!(validate_template(&bad).is_err());
+    }
+
+    #[test]
+    fn test_dealer_columns_come_from_the_profile() {
+        let template: FeedTemplate = serde_json::from_str(
+            r#"{
+                "delimiter": ",",
+                "columns": [
+                    {"header": "Vin", "field": "vin"},
+                    {"header": "Dealer", "field": "dealer_name"},
+                    {"header": "Address", "field": "dealer_address"},
+                    {"header": "City", "field": "dealer_city"},
+                    {"header": "State", "field": "dealer_state"},
+                    {"header": "Zip", "field": "dealer_zip"},
+                    {"header": "Phone", "field": "dealer_phone"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        validate_template(&template).unwrap();
+        let vehicles = [vehicle("VIN3", None, None)];
+
+        let mut dealer = DealerProfile {
+            user_id: "u1".to_string(),
+            legal_name: Some("Main Street Auto LLC".to_string()),
+            address: Some("1 Main St, Suite 2".to_string()),
+            city: Some("Austin".to_string()),
+            state: Some("TX".to_string()),
+            zip_code: Some("78701".to_string()),
+            phone: Some("(512) 555-0100".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            render_feed(&template, &vehicles, Some(&dealer)),
+            "Vin,Dealer,Address,City,State,Zip,Phone\r\n\
+             VIN3,Main Street Auto LLC,\"1 Main St, Suite 2\",Austin,TX,78701,(512) 555-0100\r\n"
+        );
+
+        // The DBA wins; no profile yet means empty cells rather than an error
+        dealer.dba = Some("Main St Motors".to_string());
+        let row = render_feed(&template, &vehicles, Some(&dealer));
+        assert!(row.contains("VIN3,Main St Motors,"));
+        assert!(render_feed(&template, &vehicles, None).ends_with("VIN3,,,,,,\r\n"));
+    }
+
+    #[test]
+    fn test_fields_with_delimiters_quotes_and_newlines_are_quoted() {
+        let description = "Loaded | leather, \"sunroof\"\nOne owner";
+        let vehicles = [vehicle("VIN2", Some(description), None)];
+        let templates = builtin_templates();
+
+        // Newlines are flattened; the delimiter and quotes force quoting
+        let pipe = render_feed(&templates["vauto"], &vehicles, None);
+        let row = pipe.lines().nth(1).unwrap();
+        assert!(row.ends_with(r#"|"Loaded | leather, ""sunroof"" One owner""#));
+
+        let comma = render_feed(&templates["homenet"], &vehicles, None);
+        let row = comma.lines().nth(1).unwrap();
+        assert!(row.ends_with(r#",,"Loaded | leather, ""sunroof"" One owner""#));
+
+        assert_eq!(escape("plain", ','), "plain");
+        assert_eq!(escape("a,b", '|'), "a,b");
+        assert_eq!(escape("a|b", '|'), "\"a|b\"");
+        assert_eq!(escape("say \"hi\"", ','), "\"say \"\"hi\"\"\"");
+        assert_eq!(format_price(18_500.0), "18500");
+        assert_eq!(format_price(0.1 + 0.2), "0.30");
+    }
+
+    #[test]
+    fn test_atomic_write_replaces_feed() {
+        let dir = temp_dir("feed", "atomic-write");
+        let path = dir.join("inventory.csv");
+
+        write_atomically(&path, b"old").unwrap();
+        write_atomically(&path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
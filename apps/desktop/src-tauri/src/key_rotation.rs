@@ -0,0 +1,544 @@
+// src-tauri/src/key_rotation.rs
+//
+// Encryption key rotation for settings values and encrypted document files
+// Progress is journaled so a crash mid-rotation resumes instead of stranding data
+
+use chrono::Utc;
+use log::{error, info, warn};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::MutexGuard;
+
+use crate::database::get_db;
+use crate::docs_config::get_documents_root_path;
+use crate::encryption::{
+    cipher_from_key, decrypt_data, encrypt_data, file_decrypts_with, is_file_encrypted,
+    reencrypt_file,
+};
+use crate::settings_cache::flush_settings;
+use crate::storage::{get_app_data_dir, get_documents_storage_path};
+
+const JOURNAL_FILE_NAME: &str = "key_rotation_journal.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationItemKind {
+    Setting,
+    File,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RotationStatus {
+    Pending,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotationItem {
+    pub kind: RotationItemKind,
+    /// Settings key or absolute file path
+    pub id: String,
+    pub status: RotationStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RotationJournal {
+    // Key fingerprints (SHA-256 prefix) so a resume can't mix up keys
+    old_key_fingerprint: String,
+    new_key_fingerprint: String,
+    started_at: i64,
+    items: Vec<RotationItem>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RotationReport {
+    pub items: Vec<RotationItem>,
+    pub resumed: bool,
+    /// False if any item failed; the journal is kept so the rotation can be retried
+    pub completed: bool,
+}
+
+/// Re-encrypt everything encrypted with old_key under new_key
+/// Covers encrypted settings values and encrypted files under the documents root.
+/// (If the database itself is ever encrypted, rekeying belongs here too.)
+/// Calling again after a crash or failure resumes from the journal.
+#[tauri::command]
+pub async fn rotate_encryption_key(
+    old_key: String,
+    new_key: String,
+) -> Result<RotationReport, String> {
+    info!("🔑 Rotating encryption key...");
+
+    let journal_path = get_app_data_dir()?.join(JOURNAL_FILE_NAME);
+
+    let mut roots = Vec::new();
+    if let Ok(Some(custom)) = get_documents_root_path().await {
+        roots.push(PathBuf::from(custom));
+    }
+    if let Ok(default) = get_documents_storage_path() {
+        roots.push(PathBuf::from(default));
+    }
+
+    let report = tauri::async_runtime::spawn_blocking(move || {
+        // Settings still queued would be missed by the rotation
+        flush_settings()?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        run_rotation(
+            || db.conn(),
+            &roots,
+            &journal_path,
+            &old_key,
+            &new_key,
+            None,
+        )
+    })
+    .await
+    .map_err(|e| format!("Key rotation task failed: {}", e))??;
+
+    if report.completed {
+        info!("✅ Key rotation complete: {} items", report.items.len());
+    } else {
+        warn!("⚠️  Key rotation incomplete, some items failed");
+    }
+    Ok(report)
+}
+
+/// stop_after simulates a crash after that many items (tests only pass Some)
+fn run_rotation<'a>(
+    lock: impl Fn() -> MutexGuard<'a, Connection>,
+    documents_roots: &[PathBuf],
+    journal_path: &Path,
+    old_key: &str,
+    new_key: &str,
+    stop_after: Option<usize>,
+) -> Result<RotationReport, String> {
+    cipher_from_key(old_key)?;
+    cipher_from_key(new_key)?;
+    if old_key == new_key {
+        return Err("New key must differ from the old key".to_string());
+    }
+
+    let old_fingerprint = key_fingerprint(old_key);
+    let new_fingerprint = key_fingerprint(new_key);
+
+    let (mut journal, resumed) = match read_journal(journal_path)? {
+        Some(journal) => {
+            if journal.old_key_fingerprint != old_fingerprint
+                || journal.new_key_fingerprint != new_fingerprint
+            {
+                return Err(
+                    "A key rotation with different keys is in progress; resume it first"
+                        .to_string(),
+                );
+            }
+            info!(
+                "Resuming key rotation started at {} ({} items)",
+                journal.started_at,
+                journal.items.len()
+            );
+            (journal, true)
+        }
+        None => {
+            let journal = RotationJournal {
+                old_key_fingerprint: old_fingerprint,
+                new_key_fingerprint: new_fingerprint,
+                started_at: Utc::now().timestamp_millis(),
+                items: plan_items(&lock(), documents_roots, old_key)?,
+            };
+            write_journal(journal_path, &journal)?;
+            (journal, false)
+        }
+    };
+
+    let mut processed = 0;
+    for index in 0..journal.items.len() {
+        if journal.items[index].status == RotationStatus::Done {
+            continue;
+        }
+        if stop_after == Some(processed) {
+            return Err("Key rotation interrupted".to_string());
+        }
+
+        let item = &journal.items[index];
+        let result = match item.kind {
+            RotationItemKind::Setting => rotate_setting(&lock(), &item.id, old_key, new_key),
+            RotationItemKind::File => rotate_file(Path::new(&item.id), old_key, new_key),
+        };
+
+        let item = &mut journal.items[index];
+        match result {
+            Ok(()) => {
+                item.status = RotationStatus::Done;
+                item.error = None;
+            }
+            Err(e) => {
+                error!("❌ Failed to rotate {:?} {}: {}", item.kind, item.id, e);
+                item.status = RotationStatus::Failed;
+                item.error = Some(e);
+            }
+        }
+
+        // Journal after every item so a crash loses at most one step
+        write_journal(journal_path, &journal)?;
+        processed += 1;
+    }
+
+    let completed = journal
+        .items
+        .iter()
+        .all(|item| item.status == RotationStatus::Done);
+    if completed {
+        fs::remove_file(journal_path)
+            .map_err(|e| format!("Failed to remove rotation journal: {}", e))?;
+    }
+
+    Ok(RotationReport {
+        items: journal.items,
+        resumed,
+        completed,
+    })
+}
+
+fn key_fingerprint(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))[..16].to_string()
+}
+
+/// Settings encrypted with the old key, plus encrypted files under the documents roots
+fn plan_items(
+    conn: &Connection,
+    documents_roots: &[PathBuf],
+    old_key: &str,
+) -> Result<Vec<RotationItem>, String> {
+    let mut items = Vec::new();
+
+    let mut stmt = conn
+        .prepare("SELECT key, value FROM settings ORDER BY key")
+        .map_err(|e| e.to_string())?;
+    let settings = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    // GCM authentication means only values really encrypted with old_key decrypt
+    for (key, value) in settings {
+        if decrypt_data(value, old_key.to_string()).is_ok() {
+            items.push(pending(RotationItemKind::Setting, key));
+        }
+    }
+
+    let mut seen = std::collections::HashSet::new();
+    for root in documents_roots {
+        let Ok(root) = root.canonicalize() else {
+            continue;
+        };
+        if seen.insert(root.clone()) {
+            collect_encrypted_files(&root, &mut items);
+        }
+    }
+
+    Ok(items)
+}
+
+fn pending(kind: RotationItemKind, id: String) -> RotationItem {
+    RotationItem {
+        kind,
+        id,
+        status: RotationStatus::Pending,
+        error: None,
+    }
+}
+
+fn collect_encrypted_files(dir: &Path, items: &mut Vec<RotationItem>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        warn!("⚠️  Could not read {:?} while planning key rotation", dir);
+        return;
+    };
+
+    for entry in entries.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let path = entry.path();
+        if file_type.is_dir() {
+            collect_encrypted_files(&path, items);
+        } else if file_type.is_file() {
+            let path = path.to_string_lossy().to_string();
+            if is_file_encrypted(path.clone()).unwrap_or(false) {
+                items.push(pending(RotationItemKind::File, path));
+            }
+        }
+    }
+}
+
+fn rotate_setting(
+    conn: &Connection,
+    key: &str,
+    old_key: &str,
+    new_key: &str,
+) -> Result<(), String> {
+    let value: String = conn
+        .query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Failed to read setting: {}", e))?;
+
+    match decrypt_data(value.clone(), old_key.to_string()) {
+        Ok(plaintext) => {
+            let rotated = encrypt_data(plaintext, new_key.to_string())?;
+            conn.execute(
+                "UPDATE settings SET value = ?2, updated_at = ?3 WHERE key = ?1",
+                params![key, rotated, Utc::now().timestamp_millis()],
+            )
+            .map_err(|e| format!("Failed to update setting: {}", e))?;
+            Ok(())
+        }
+        // Rotated before a crash, journal just didn't record it
+        Err(_) if decrypt_data(value, new_key.to_string()).is_ok() => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn rotate_file(path: &Path, old_key: &str, new_key: &str) -> Result<(), String> {
+    match reencrypt_file(path, old_key, new_key) {
+        Ok(()) => Ok(()),
+        Err(_) if file_decrypts_with(path, new_key) => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn read_journal(path: &Path) -> Result<Option<RotationJournal>, String> {
+    match fs::read(path) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| format!("Corrupt rotation journal: {}", e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read rotation journal: {}", e)),
+    }
+}
+
+fn write_journal(path: &Path, journal: &RotationJournal) -> Result<(), String> {
+    let bytes = serde_json::to_vec_pretty(journal)
+        .map_err(|e| format!("Failed to serialize rotation journal: {}", e))?;
+
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, bytes).map_err(|e| format!("Failed to write rotation journal: {}", e))?;
+    fs::rename(&tmp, path).map_err(|e| format!("Failed to write rotation journal: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::{encrypt_file, generate_encryption_key};
+    use crate::test_support::temp_dir;
+    use std::sync::Mutex;
+
+    struct Fixture {
+        dir: PathBuf,
+        db: Mutex<Connection>,
+        old_key: String,
+        new_key: String,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let dir = temp_dir("key-rotation", name);
+            fs::create_dir_all(dir.join("docs").join("deal-1")).unwrap();
+
+            let old_key = generate_encryption_key().unwrap();
+            let new_key = generate_encryption_key().unwrap();
+
+            let conn = Connection::open_in_memory().unwrap();
+            conn.execute_batch(
+                "CREATE TABLE settings (key TEXT PRIMARY KEY, value TEXT NOT NULL, updated_at INTEGER NOT NULL)",
+            )
+            .unwrap();
+            for (key, value) in [
+                (
+                    "api_secret",
+                    encrypt_data("s1".into(), old_key.clone()).unwrap(),
+                ),
+                (
+                    "smtp_password",
+                    encrypt_data("s2".into(), old_key.clone()).unwrap(),
+                ),
+                ("theme", "\"dark\"".to_string()),
+            ] {
+                conn.execute(
+                    "INSERT INTO settings VALUES (?1, ?2, 0)",
+                    params![key, value],
+                )
+                .unwrap();
+            }
+
+            for name in ["license-a", "license-b"] {
+                let plain = dir.join(format!("{}.jpg", name));
+                fs::write(&plain, vec![9u8; 100_000]).unwrap();
+                encrypt_file(
+                    plain.to_string_lossy().to_string(),
+                    dir.join("docs")
+                        .join("deal-1")
+                        .join(format!("{}.enc", name))
+                        .to_string_lossy()
+                        .to_string(),
+                    old_key.clone(),
+                )
+                .unwrap();
+            }
+            fs::write(
+                dir.join("docs").join("deal-1").join("contract.pdf"),
+                b"%PDF",
+            )
+            .unwrap();
+
+            Fixture {
+                dir,
+                db: Mutex::new(conn),
+                old_key,
+                new_key,
+            }
+        }
+
+        fn run(&self, stop_after: Option<usize>) -> Result<RotationReport, String> {
+            run_rotation(
+                || self.db.lock().unwrap(),
+                &[self.dir.join("docs")],
+                &self.journal(),
+                &self.old_key,
+                &self.new_key,
+                stop_after,
+            )
+        }
+
+        fn journal(&self) -> PathBuf {
+            self.dir.join(JOURNAL_FILE_NAME)
+        }
+
+        fn setting(&self, key: &str) -> String {
+            self.db
+                .lock()
+                .unwrap()
+                .query_row(
+                    "SELECT value FROM settings WHERE key = ?1",
+                    params![key],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        }
+
+        fn encrypted_file(&self, name: &str) -> PathBuf {
+            self.dir
+                .join("docs")
+                .join("deal-1")
+                .join(format!("{}.enc", name))
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn test_rotation_resumes_after_interruption() {
+        let fixture = Fixture::new("resume");
+
+        // Crash after two of the four items
+        assert!(fixture.run(Some(2)).is_err());
+        assert!(fixture.journal().exists());
+        let journal = read_journal(&fixture.journal()).unwrap().unwrap();
+        assert_eq!(journal.items.len(), 4);
+        assert_eq!(
+            journal
+                .items
+                .iter()
+                .filter(|i| i.status == RotationStatus::Done)
+                .count(),
+            2
+        );
+
+        // Simulate a crash between rotating a file and journaling it
+        reencrypt_file(
+            &fixture.encrypted_file("license-a"),
+            &fixture.old_key,
+            &fixture.new_key,
+        )
+        .unwrap();
+
+        // A different key pair can't hijack the in-progress rotation
+        let other = generate_encryption_key().unwrap();
+        assert!(run_rotation(
+            || fixture.db.lock().unwrap(),
+            &[fixture.dir.join("docs")],
+            &fixture.journal(),
+            &fixture.old_key,
+            &other,
+            None,
+        )
+        .is_err());
+
+        let report = fixture.run(None).unwrap();
+        assert!(report.resumed);
+        assert!(report.completed);
+        assert!(!fixture.journal().exists());
+
+        assert_eq!(
+            decrypt_data(fixture.setting("api_secret"), fixture.new_key.clone()).unwrap(),
+            "s1"
+        );
+        assert_eq!(
+            decrypt_data(fixture.setting("smtp_password"), fixture.new_key.clone()).unwrap(),
+            "s2"
+        );
+        assert_eq!(fixture.setting("theme"), "\"dark\"");
+        for name in ["license-a", "license-b"] {
+            assert!(file_decrypts_with(
+                &fixture.encrypted_file(name),
+                &fixture.new_key
+            ));
+        }
+    }
+
+    #[test]
+    fn test_rotation_refuses_to_finish_with_failures() {
+        let fixture = Fixture::new("failure");
+
+        // A file encrypted with an unrelated key can't be rotated
+        let stranger = generate_encryption_key().unwrap();
+        let plain = fixture.dir.join("plain.bin");
+        fs::write(&plain, b"data").unwrap();
+        encrypt_file(
+            plain.to_string_lossy().to_string(),
+            fixture
+                .encrypted_file("foreign")
+                .to_string_lossy()
+                .to_string(),
+            stranger,
+        )
+        .unwrap();
+
+        let report = fixture.run(None).unwrap();
+        assert!(!report.completed);
+        assert!(fixture.journal().exists());
+
+        let failed: Vec<_> = report
+            .items
+            .iter()
+            .filter(|i| i.status == RotationStatus::Failed)
+            .collect();
+        assert_eq!(failed.len(), 1);
+        assert!(failed[0].id.ends_with("foreign.enc"));
+        assert!(failed[0].error.is_some());
+    }
+}
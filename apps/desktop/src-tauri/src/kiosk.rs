@@ -0,0 +1,561 @@
+// src-tauri/src/kiosk.rs
+//
+// Kiosk signing mode: the salesperson hands the tablet to the customer with one deal open
+// for signing. enter_kiosk_mode records that deal and a hash of the salesperson's PIN in
+// KioskState, and from then on guarded() (wrapped around the invoke handler in main.rs)
+// rejects every app command not in KIOSK_COMMANDS with KioskLocked, whatever the frontend
+// asks for. The commands that remain only reach the kiosk deal: db_get_deal and
+// db_get_documents_by_deal are checked by argument, the kiosk document commands check for
+// themselves. exit_kiosk_mode needs the PIN, and stops accepting guesses for a while after
+// MAX_PIN_ATTEMPTS wrong ones.
+// Plugin commands (dialog, fs, ...) don't go through the app's invoke handler; the
+// capabilities granted to the main window decide those.
+
+use chrono::Utc;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use tauri::ipc::{Invoke, InvokeBody};
+use tauri::{AppHandle, Emitter, Manager, Runtime, State};
+
+use crate::app_state::{AppState, AuthError};
+use crate::database::{
+    db_get_document, get_db, get_deal_for_user, update_document, DbState, Document,
+};
+use crate::document_versions::max_versions;
+use crate::encryption::{verify_password, KdfParams};
+use crate::telemetry::track;
+
+/// Emitted with the new KioskStatus when the mode is entered or left
+pub const KIOSK_MODE_EVENT: &str = "kiosk-mode-changed";
+
+/// Wrong PINs accepted before exit_kiosk_mode (or unlock_app) locks out
+pub(crate) const MAX_PIN_ATTEMPTS: u32 = 5;
+/// First lockout; each further one doubles, up to MAX_LOCKOUT
+const BASE_LOCKOUT: Duration = Duration::from_secs(30);
+const MAX_LOCKOUT: Duration = Duration::from_secs(15 * 60);
+
+/// Commands still allowed in kiosk mode, with the argument that must name the kiosk deal
+/// (None: the command needs no deal, or checks it itself)
+const KIOSK_COMMANDS: &[(&str, Option<&str>)] = &[
+    ("get_kiosk_status", None),
+    ("exit_kiosk_mode", None),
+    ("db_get_deal", Some("id")),
+    ("db_get_documents_by_deal", Some("dealId")),
+    ("read_kiosk_document", None),
+    ("save_kiosk_signed_document", None),
+];
+
+/// Salt and hash from derive_key_from_password; the PIN itself never reaches Rust
+/// until exit_kiosk_mode
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PinHash {
+    pub salt_base64: String,
+    pub key_hash_base64: String,
+    #[serde(default)]
+    pub params: Option<KdfParams>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KioskStatus {
+    pub active: bool,
+    pub deal_id: Option<String>,
+    pub started_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum KioskError {
+    /// The command isn't available in kiosk mode, or not for this deal
+    KioskLocked {
+        command: String,
+    },
+    AlreadyActive,
+    NotActive,
+    InvalidPin {
+        attempts_left: u32,
+    },
+    TooManyAttempts {
+        retry_after_secs: u64,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl std::fmt::Display for KioskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KioskError::KioskLocked { command } => {
+                write!(f, "{} is not available in kiosk mode", command)
+            }
+            KioskError::AlreadyActive => write!(f, "Kiosk mode is already active"),
+            KioskError::NotActive => write!(f, "Kiosk mode is not active"),
+            KioskError::InvalidPin { attempts_left } => {
+                write!(f, "Wrong PIN ({} attempts left)", attempts_left)
+            }
+            KioskError::TooManyAttempts { retry_after_secs } => write!(
+                f,
+                "Too many wrong PINs, try again in {} seconds",
+                retry_after_secs
+            ),
+            KioskError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for KioskError {
+    fn from(message: String) -> Self {
+        KioskError::Other { message }
+    }
+}
+
+impl From<AuthError> for KioskError {
+    fn from(error: AuthError) -> Self {
+        KioskError::Other {
+            message: error.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct KioskMode {
+    deal_id: String,
+    pin: PinHash,
+    started_at: i64,
+}
+
+#[derive(Debug, Default)]
+struct KioskInner {
+    mode: Option<KioskMode>,
+    failed_attempts: u32,
+    lockouts: u32,
+    locked_until: Option<Instant>,
+}
+
+/// Managed by Tauri (main.rs); inactive until enter_kiosk_mode
+#[derive(Debug, Default)]
+pub struct KioskState {
+    inner: Mutex<KioskInner>,
+}
+
+impl KioskState {
+    fn lock(&self) -> MutexGuard<'_, KioskInner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn status(&self) -> KioskStatus {
+        let inner = self.lock();
+        KioskStatus {
+            active: inner.mode.is_some(),
+            deal_id: inner.mode.as_ref().map(|mode| mode.deal_id.clone()),
+            started_at: inner.mode.as_ref().map(|mode| mode.started_at),
+        }
+    }
+
+    fn enter(&self, deal_id: String, pin: PinHash, now: i64) -> Result<KioskStatus, KioskError> {
+        {
+            let mut inner = self.lock();
+            if inner.mode.is_some() {
+                return Err(KioskError::AlreadyActive);
+            }
+            *inner = KioskInner {
+                mode: Some(KioskMode {
+                    deal_id,
+                    pin,
+                    started_at: now,
+                }),
+                ..KioskInner::default()
+            };
+        }
+        Ok(self.status())
+    }
+
+    /// Whether a command may run right now; args are the invoke payload (camelCase keys)
+    pub fn check(&self, command: &str, args: Option<&Value>) -> Result<(), KioskError> {
+        let inner = self.lock();
+        let Some(mode) = &inner.mode else {
+            return Ok(());
+        };
+
+        let allowed = match KIOSK_COMMANDS.iter().find(|(name, _)| *name == command) {
+            Some((_, None)) => true,
+            Some((_, Some(key))) => {
+                args.and_then(|args| args.get(key)).and_then(Value::as_str)
+                    == Some(mode.deal_id.as_str())
+            }
+            None => false,
+        };
+
+        if allowed {
+            Ok(())
+        } else {
+            Err(KioskError::KioskLocked {
+                command: command.to_string(),
+            })
+        }
+    }
+
+    /// The kiosk deal, for commands that only exist in kiosk mode
+    fn deal_id(&self) -> Result<String, KioskError> {
+        self.lock()
+            .mode
+            .as_ref()
+            .map(|mode| mode.deal_id.clone())
+            .ok_or(KioskError::NotActive)
+    }
+
+    /// Leave kiosk mode if the PIN matches. The lock is held while the PIN is checked so
+    /// parallel guesses can't slip past the attempt count.
+    fn exit(&self, pin: &str, now: Instant) -> Result<(), KioskError> {
+        let mut inner = self.lock();
+        let pin_hash = match &inner.mode {
+            Some(mode) => mode.pin.clone(),
+            None => return Err(KioskError::NotActive),
+        };
+        if let Some(until) = inner.locked_until.filter(|until| now < *until) {
+            return Err(KioskError::TooManyAttempts {
+                retry_after_secs: (until - now).as_secs_f64().ceil() as u64,
+            });
+        }
+
+        let matches = verify_password(
+            pin.to_string(),
+            pin_hash.salt_base64,
+            pin_hash.key_hash_base64,
+            pin_hash.params,
+        )?;
+        if matches {
+            *inner = KioskInner::default();
+            return Ok(());
+        }
+
+        inner.failed_attempts += 1;
+        if inner.failed_attempts < MAX_PIN_ATTEMPTS {
+            return Err(KioskError::InvalidPin {
+                attempts_left: MAX_PIN_ATTEMPTS - inner.failed_attempts,
+            });
+        }
+
+        let lockout = lockout_after(inner.lockouts);
+        inner.failed_attempts = 0;
+        inner.lockouts += 1;
+        inner.locked_until = Some(now + lockout);
+        Err(KioskError::TooManyAttempts {
+            retry_after_secs: lockout.as_secs(),
+        })
+    }
+}
+
+/// How long PIN entry is refused after `lockouts` earlier lockouts
+pub(crate) fn lockout_after(lockouts: u32) -> Duration {
+    BASE_LOCKOUT
+        .saturating_mul(1 << lockouts.min(16))
+        .min(MAX_LOCKOUT)
+}
+
+/// Wrap the app's invoke handler so kiosk mode is enforced before any command runs
+pub fn guarded<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke: Invoke<R>| {
+        let webview = invoke.message.webview();
+        let blocked = webview.try_state::<KioskState>().and_then(|kiosk| {
+            let args = match invoke.message.payload() {
+                InvokeBody::Json(args) => Some(args),
+                InvokeBody::Raw(_) => None,
+            };
+            kiosk.check(invoke.message.command(), args).err()
+        });
+
+        match blocked {
+            Some(error) => {
+                warn!("🔒 Kiosk mode blocked {}", invoke.message.command());
+                invoke.resolver.reject(error);
+                true
+            }
+            None => handler(invoke),
+        }
+    }
+}
+
+fn check_pin_hash(pin: &PinHash) -> Result<(), KioskError> {
+    if pin.salt_base64.trim().is_empty() || pin.key_hash_base64.trim().is_empty() {
+        return Err("A PIN is required to enter kiosk mode".to_string().into());
+    }
+    Ok(())
+}
+
+/// Document of the kiosk deal, or KioskLocked for any other
+fn kiosk_document(
+    kiosk: &KioskState,
+    document_id: &str,
+    command: &str,
+) -> Result<Document, KioskError> {
+    let deal_id = kiosk.deal_id()?;
+    db_get_document(document_id.to_string())?
+        .filter(|document| document.deal_id == deal_id)
+        .ok_or_else(|| KioskError::KioskLocked {
+            command: command.to_string(),
+        })
+}
+
+/// Lock the app to one deal until exit_kiosk_mode is given the PIN behind pin_hash
+#[tauri::command]
+pub fn enter_kiosk_mode(
+    allowed_deal_id: String,
+    pin_hash: PinHash,
+    user_id: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+    kiosk: State<'_, KioskState>,
+) -> Result<KioskStatus, KioskError> {
+    track("enter_kiosk_mode", || {
+        let user_id_value = state.require_user(user_id)?;
+        check_pin_hash(&pin_hash)?;
+
+        let db = db.get().map_err(|e| e.to_string())?;
+        if get_deal_for_user(db, &allowed_deal_id, &user_id_value)?.is_none() {
+            return Err("Deal not found".to_string().into());
+        }
+
+        let status = kiosk.enter(allowed_deal_id, pin_hash, Utc::now().timestamp_millis())?;
+        info!("🔒 Kiosk mode entered for deal {:?}", status.deal_id);
+        let _ = app.emit(KIOSK_MODE_EVENT, &status);
+        Ok(status)
+    })
+}
+
+#[tauri::command]
+pub fn exit_kiosk_mode(
+    pin: String,
+    app: AppHandle,
+    kiosk: State<'_, KioskState>,
+) -> Result<KioskStatus, KioskError> {
+    track("exit_kiosk_mode", || {
+        kiosk.exit(&pin, Instant::now())?;
+        info!("🔓 Kiosk mode exited");
+        let status = kiosk.status();
+        let _ = app.emit(KIOSK_MODE_EVENT, &status);
+        Ok(status)
+    })
+}
+
+#[tauri::command]
+pub fn get_kiosk_status(kiosk: State<'_, KioskState>) -> KioskStatus {
+    kiosk.status()
+}
+
+/// Contents of a document of the kiosk deal, for the signing view
+#[tauri::command]
+pub fn read_kiosk_document(
+    document_id: String,
+    kiosk: State<'_, KioskState>,
+) -> Result<Vec<u8>, KioskError> {
+    track("read_kiosk_document", || {
+        let document = kiosk_document(&kiosk, &document_id, "read_kiosk_document")?;
+        fs::read(&document.file_path).map_err(|e| format!("Failed to read document: {}", e).into())
+    })
+}
+
+/// Replace a document of the kiosk deal with its signed version. The unsigned file is kept
+/// as a document version; signature is the HMAC of the new file when the frontend has one.
+#[tauri::command]
+pub fn save_kiosk_signed_document(
+    document_id: String,
+    contents: Vec<u8>,
+    signature: Option<String>,
+    kiosk: State<'_, KioskState>,
+) -> Result<Document, KioskError> {
+    track("save_kiosk_signed_document", || {
+        let previous = kiosk_document(&kiosk, &document_id, "save_kiosk_signed_document")?;
+        let keep_versions = max_versions();
+
+        let mut updates = json!({
+            "file_size": contents.len() as i64,
+            "file_checksum": format!("{:x}", Sha256::digest(&contents)),
+            "version_reason": "Signed in kiosk mode",
+        });
+        if let Some(signature) = signature {
+            updates["signature"] = Value::String(signature);
+        }
+
+        // Written beside the original first: the version snapshot taken by update_document
+        // copies the unsigned file, which is only replaced once the row is updated
+        let path = Path::new(&previous.file_path).to_path_buf();
+        let partial = path.with_extension("signing");
+        fs::write(&partial, &contents)
+            .map_err(|e| format!("Failed to save signed document: {}", e))?;
+
+        let updated = get_db().map_err(|e| e.to_string()).and_then(|db| {
+            let mut conn = db.conn();
+            update_document(&mut conn, previous, &updates, keep_versions)
+        });
+        let document = match updated {
+            Ok(document) => document,
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                return Err(e.into());
+            }
+        };
+        fs::rename(&partial, &path)
+            .map_err(|e| format!("Failed to save signed document: {}", e))?;
+
+        info!("✍️  Saved signed document {}", document.id);
+        Ok(document)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encryption::derive_key_from_password;
+
+    fn cheap_params() -> KdfParams {
+        KdfParams {
+            memory_kib: 8,
+            iterations: 1,
+            parallelism: 1,
+        }
+    }
+
+    fn pin_hash(pin: &str) -> PinHash {
+        let derived = derive_key_from_password(pin.to_string(), None, Some(cheap_params()))
+            .expect("derive PIN");
+        PinHash {
+            salt_base64: derived.salt_base64,
+            key_hash_base64: derived.key_hash_base64,
+            params: Some(cheap_params()),
+        }
+    }
+
+    fn kiosk_for(deal_id: &str) -> KioskState {
+        let kiosk = KioskState::default();
+        kiosk
+            .enter(deal_id.to_string(), pin_hash("2468"), 1_000)
+            .expect("enter kiosk mode");
+        kiosk
+    }
+
+    #[test]
+    fn test_everything_runs_outside_kiosk_mode() {
+        let kiosk = KioskState::default();
+        assert_eq!(kiosk.check("db_delete_client", None), Ok(()));
+        assert!(!kiosk.status().active);
+    }
+
+    #[test]
+    fn test_non_whitelisted_commands_are_locked() {
+        let kiosk = kiosk_for("deal-1");
+
+        for command in [
+            "db_get_all_deals",
+            "db_delete_document",
+            "write_file_to_path",
+        ] {
+            assert_eq!(
+                kiosk.check(command, Some(&json!({ "dealId": "deal-1" }))),
+                Err(KioskError::KioskLocked {
+                    command: command.to_string()
+                })
+            );
+        }
+        assert_eq!(
+            kiosk.enter("deal-2".into(), pin_hash("1111"), 2_000),
+            Err(KioskError::AlreadyActive)
+        );
+    }
+
+    #[test]
+    fn test_whitelisted_commands_only_reach_the_kiosk_deal() {
+        let kiosk = kiosk_for("deal-1");
+
+        assert_eq!(
+            kiosk.check("db_get_deal", Some(&json!({ "id": "deal-1" }))),
+            Ok(())
+        );
+        assert!(kiosk
+            .check("db_get_deal", Some(&json!({ "id": "deal-2" })))
+            .is_err());
+        assert!(kiosk.check("db_get_deal", None).is_err());
+        assert_eq!(
+            kiosk.check(
+                "db_get_documents_by_deal",
+                Some(&json!({ "dealId": "deal-1" }))
+            ),
+            Ok(())
+        );
+        assert!(kiosk
+            .check(
+                "db_get_documents_by_deal",
+                Some(&json!({ "dealId": "deal-2" }))
+            )
+            .is_err());
+        assert_eq!(kiosk.check("exit_kiosk_mode", None), Ok(()));
+    }
+
+    #[test]
+    fn test_exit_requires_the_pin() {
+        let kiosk = kiosk_for("deal-1");
+        let now = Instant::now();
+
+        assert_eq!(
+            kiosk.exit("1357", now),
+            Err(KioskError::InvalidPin {
+                attempts_left: MAX_PIN_ATTEMPTS - 1
+            })
+        );
+        assert!(kiosk.status().active);
+
+        assert_eq!(kiosk.exit("2468", now), Ok(()));
+        assert!(!kiosk.status().active);
+        assert_eq!(kiosk.check("db_get_all_deals", None), Ok(()));
+        assert_eq!(kiosk.exit("2468", now), Err(KioskError::NotActive));
+    }
+
+    #[test]
+    fn test_wrong_pins_lock_out_with_growing_delays() {
+        let kiosk = kiosk_for("deal-1");
+        let start = Instant::now();
+
+        for _ in 1..MAX_PIN_ATTEMPTS {
+            assert!(matches!(
+                kiosk.exit("0000", start),
+                Err(KioskError::InvalidPin { .. })
+            ));
+        }
+        assert_eq!(
+            kiosk.exit("0000", start),
+            Err(KioskError::TooManyAttempts {
+                retry_after_secs: 30
+            })
+        );
+        // Even the right PIN is refused until the lockout ends
+        assert!(matches!(
+            kiosk.exit("2468", start + Duration::from_secs(10)),
+            Err(KioskError::TooManyAttempts {
+                retry_after_secs: 20
+            })
+        ));
+
+        let later = start + BASE_LOCKOUT;
+        for _ in 0..MAX_PIN_ATTEMPTS {
+            let _ = kiosk.exit("0000", later);
+        }
+        assert!(matches!(
+            kiosk.exit("2468", later + Duration::from_secs(59)),
+            Err(KioskError::TooManyAttempts {
+                retry_after_secs: 1
+            })
+        ));
+        assert_eq!(kiosk.exit("2468", later + Duration::from_secs(60)), Ok(()));
+    }
+}
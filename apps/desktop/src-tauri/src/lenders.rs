@@ -0,0 +1,760 @@
+// src-tauri/src/lenders.rs
+//
+// Lender directory: the banks and finance companies that buy the dealer's contracts
+// Deals link to a lender through deals.lender_id. A lender that deals still point at
+// can't be deleted; db_delete_lender takes reassign_to to move those deals first.
+// db_get_lender_stats sums what each lender funded this quarter (sold and completed
+// deals, by sale date in local time), in cents like the QuickBooks export.
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{get_db, new_row_id, DEAL_STATUS_COMPLETED, DEAL_STATUS_SOLD};
+use crate::reporting::run_report_job;
+use crate::telemetry::{track, track_async};
+
+/// 100% in basis points
+const MAX_RESERVE_BPS: i64 = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Lender {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub zip_code: Option<String>,
+    pub phone: Option<String>,
+    pub ein: Option<String>,
+    pub default_reserve_bps: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl Lender {
+    pub(crate) fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(Lender {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            name: row.get("name")?,
+            address: row.get("address")?,
+            city: row.get("city")?,
+            state: row.get("state")?,
+            zip_code: row.get("zip_code")?,
+            phone: row.get("phone")?,
+            ein: row.get("ein")?,
+            default_reserve_bps: row.get("default_reserve_bps")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NewLender {
+    pub name: String,
+    pub address: Option<String>,
+    pub city: Option<String>,
+    pub state: Option<String>,
+    pub zip_code: Option<String>,
+    pub phone: Option<String>,
+    pub ein: Option<String>,
+    pub default_reserve_bps: i64,
+}
+
+/// One lender's funding for the quarter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LenderStats {
+    pub lender_id: String,
+    pub name: String,
+    pub deal_count: u32,
+    /// Sum of financed_amount
+    pub funded_cents: i64,
+    /// funded_cents at the lender's default reserve rate
+    pub reserve_cents: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LenderStatsReport {
+    /// Quarter bounds in epoch ms (inclusive start, exclusive end)
+    pub start_ms: i64,
+    pub end_ms: i64,
+    /// Every lender, most funded first
+    pub lenders: Vec<LenderStats>,
+}
+
+/// Canonical XX-XXXXXXX form of an EIN typed with or without the dash
+fn normalize_ein(ein: &str) -> Result<String, String> {
+    let digits: String = ein.chars().filter(|c| c.is_ascii_digit()).collect();
+    let only_digits_and_dash = ein.trim().chars().all(|c| c.is_ascii_digit() || c == '-');
+    if digits.len() != 9 || !only_digits_and_dash {
+        return Err(format!("EIN '{}' should be 9 digits (XX-XXXXXXX)", ein));
+    }
+    Ok(format!("{}-{}", &digits[..2], &digits[2..]))
+}
+
+fn validate_reserve(bps: i64) -> Result<(), String> {
+    if !(0..=MAX_RESERVE_BPS).contains(&bps) {
+        return Err(format!(
+            "Reserve must be between 0 and {} basis points",
+            MAX_RESERVE_BPS
+        ));
+    }
+    Ok(())
+}
+
+/// Trimmed value, with blank treated as not set
+fn optional_text(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn get_lender(conn: &Connection, user_id: &str, id: &str) -> Result<Lender, String> {
+    conn.query_row(
+        "SELECT * FROM lenders WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+        Lender::from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Lender not found or access denied".to_string())
+}
+
+/// Errors unless lender_id is one of user_id's lenders (used when linking a deal)
+pub(crate) fn check_lender(
+    conn: &Connection,
+    user_id: &str,
+    lender_id: &str,
+) -> Result<(), String> {
+    get_lender(conn, user_id, lender_id).map(|_| ())
+}
+
+fn create_lender(
+    conn: &Connection,
+    user_id: &str,
+    lender: NewLender,
+    now: i64,
+) -> Result<Lender, String> {
+    let name = lender.name.trim();
+    if name.is_empty() {
+        return Err("Lender name is required".to_string());
+    }
+    validate_reserve(lender.default_reserve_bps)?;
+    let ein = optional_text(lender.ein.as_deref())
+        .map(|ein| normalize_ein(&ein))
+        .transpose()?;
+
+    let id = new_row_id();
+    conn.execute(
+        "INSERT INTO lenders (id, user_id, name, address, city, state, zip_code, phone, ein,
+                              default_reserve_bps, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11)",
+        params![
+            id,
+            user_id,
+            name,
+            optional_text(lender.address.as_deref()),
+            optional_text(lender.city.as_deref()),
+            optional_text(lender.state.as_deref()).map(|state| state.to_uppercase()),
+            optional_text(lender.zip_code.as_deref()),
+            optional_text(lender.phone.as_deref()),
+            ein,
+            lender.default_reserve_bps,
+            now
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    get_lender(conn, user_id, &id)
+}
+
+fn update_lender(
+    conn: &Connection,
+    user_id: &str,
+    id: &str,
+    updates: &Value,
+    now: i64,
+) -> Result<Lender, String> {
+    let mut lender = get_lender(conn, user_id, id)?;
+
+    if let Some(name) = updates.get("name").and_then(|v| v.as_str()) {
+        if name.trim().is_empty() {
+            return Err("Lender name is required".to_string());
+        }
+        lender.name = name.trim().to_string();
+    }
+    // A key that's present replaces the field; null or "" clears it
+    for (key, field) in [
+        ("address", &mut lender.address),
+        ("city", &mut lender.city),
+        ("state", &mut lender.state),
+        ("zip_code", &mut lender.zip_code),
+        ("phone", &mut lender.phone),
+        ("ein", &mut lender.ein),
+    ] {
+        if let Some(value) = updates.get(key) {
+            *field = optional_text(value.as_str());
+        }
+    }
+    lender.state = lender.state.map(|state| state.to_uppercase());
+    lender.ein = lender.ein.map(|ein| normalize_ein(&ein)).transpose()?;
+    if let Some(bps) = updates.get("default_reserve_bps").and_then(|v| v.as_i64()) {
+        validate_reserve(bps)?;
+        lender.default_reserve_bps = bps;
+    }
+    lender.updated_at = now;
+
+    conn.execute(
+        "UPDATE lenders SET name = ?3, address = ?4, city = ?5, state = ?6, zip_code = ?7,
+                            phone = ?8, ein = ?9, default_reserve_bps = ?10, updated_at = ?11
+         WHERE id = ?1 AND user_id = ?2",
+        params![
+            id,
+            user_id,
+            lender.name,
+            lender.address,
+            lender.city,
+            lender.state,
+            lender.zip_code,
+            lender.phone,
+            lender.ein,
+            lender.default_reserve_bps,
+            lender.updated_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(lender)
+}
+
+/// Delete a lender, first moving its deals to reassign_to when given
+/// Without reassign_to, a lender that deals still reference is refused.
+fn delete_lender(
+    conn: &mut Connection,
+    user_id: &str,
+    id: &str,
+    reassign_to: Option<&str>,
+    now: i64,
+) -> Result<(), String> {
+    get_lender(conn, user_id, id)?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    if let Some(target) = reassign_to {
+        if target == id {
+            return Err("Can't reassign deals to the lender being deleted".to_string());
+        }
+        check_lender(&tx, user_id, target)?;
+        let moved = tx
+            .execute(
+                "UPDATE deals SET lender_id = ?2, updated_at = ?3 WHERE lender_id = ?1",
+                params![id, target, now],
+            )
+            .map_err(|e| e.to_string())?;
+        info!("Moved {} deals from lender {} to {}", moved, id, target);
+    }
+
+    let referenced: i64 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM deals WHERE lender_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if referenced > 0 {
+        return Err(format!(
+            "Lender is on {} deal{}; reassign them to another lender before deleting",
+            referenced,
+            if referenced == 1 { "" } else { "s" }
+        ));
+    }
+
+    tx.execute(
+        "DELETE FROM lenders WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Local-time bounds of the calendar quarter containing now, in epoch ms
+fn quarter_bounds<Tz: TimeZone>(now: &DateTime<Tz>) -> (i64, i64) {
+    let first_month = (now.month0() / 3) * 3 + 1;
+    let start = NaiveDate::from_ymd_opt(now.year(), first_month, 1).expect("valid date");
+    let end = if first_month == 10 {
+        NaiveDate::from_ymd_opt(now.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(now.year(), first_month + 3, 1)
+    }
+    .expect("valid date");
+
+    // Quarters start at midnight on the 1st, which no DST change skips
+    let local_ms = |date: NaiveDate| {
+        now.timezone()
+            .from_local_datetime(&date.and_time(NaiveTime::MIN))
+            .earliest()
+            .map(|start| start.timestamp_millis())
+            .unwrap_or_else(|| date.and_time(NaiveTime::MIN).and_utc().timestamp_millis())
+    };
+    (local_ms(start), local_ms(end))
+}
+
+fn lender_stats(
+    conn: &Connection,
+    user_id: &str,
+    start_ms: i64,
+    end_ms: i64,
+) -> SqlResult<Vec<LenderStats>> {
+    // LEFT JOIN so lenders with nothing funded this quarter still show up with zeros
+    let mut stmt = conn.prepare(
+        "SELECT l.id, l.name, l.default_reserve_bps, d.id, d.financed_amount_cents
+         FROM lenders l
+         LEFT JOIN deals d ON d.lender_id = l.id AND d.user_id = l.user_id
+             AND d.status IN (?2, ?3)
+             AND COALESCE(d.sale_date, d.created_at) >= ?4
+             AND COALESCE(d.sale_date, d.created_at) < ?5
+         WHERE l.user_id = ?1
+         ORDER BY l.name, l.id",
+    )?;
+    let rows = stmt.query_map(
+        params![
+            user_id,
+            DEAL_STATUS_SOLD,
+            DEAL_STATUS_COMPLETED,
+            start_ms,
+            end_ms
+        ],
+        |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<i64>>(4)?,
+            ))
+        },
+    )?;
+
+    let mut stats: Vec<(LenderStats, i64)> = Vec::new();
+    for row in rows {
+        let (lender_id, name, reserve_bps, deal_id, financed) = row?;
+        if stats
+            .last()
+            .is_none_or(|(last, _)| last.lender_id != lender_id)
+        {
+            stats.push((
+                LenderStats {
+                    lender_id,
+                    name,
+                    deal_count: 0,
+                    funded_cents: 0,
+                    reserve_cents: 0,
+                },
+                reserve_bps,
+            ));
+        }
+        let (current, _) = stats.last_mut().expect("pushed above");
+        if deal_id.is_some() {
+            current.deal_count += 1;
+            current.funded_cents += financed.unwrap_or(0);
+        }
+    }
+
+    let mut stats: Vec<LenderStats> = stats
+        .into_iter()
+        .map(|(mut lender, reserve_bps)| {
+            // Rounded to the nearest cent, once, on the quarter's total
+            lender.reserve_cents =
+                (lender.funded_cents * reserve_bps + MAX_RESERVE_BPS / 2) / MAX_RESERVE_BPS;
+            lender
+        })
+        .collect();
+    stats.sort_by_key(|lender| std::cmp::Reverse(lender.funded_cents));
+    Ok(stats)
+}
+
+#[tauri::command]
+pub fn db_create_lender(
+    lender: NewLender,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Lender, String> {
+    track("db_create_lender", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let lender = create_lender(&conn, &user_id_value, lender, Utc::now().timestamp_millis())?;
+        info!("✅ Lender created: {}", lender.id);
+        data_changed(
+            &user_id_value,
+            ChangedEntity::Lender,
+            &lender.id,
+            Operation::Create,
+        );
+        Ok(lender)
+    })
+}
+
+#[tauri::command]
+pub fn db_get_lender(
+    id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Option<Lender>, String> {
+    track("db_get_lender", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        conn.query_row(
+            "SELECT * FROM lenders WHERE id = ?1 AND user_id = ?2",
+            params![id, user_id_value],
+            Lender::from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())
+    })
+}
+
+#[tauri::command]
+pub fn db_get_lenders(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Lender>, String> {
+    track("db_get_lenders", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let mut stmt = conn
+            .prepare("SELECT * FROM lenders WHERE user_id = ?1 ORDER BY name COLLATE NOCASE")
+            .map_err(|e| e.to_string())?;
+        let lenders = stmt
+            .query_map(params![user_id_value], Lender::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<SqlResult<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(lenders)
+    })
+}
+
+#[tauri::command]
+pub fn db_update_lender(
+    id: String,
+    updates: Value,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Lender, String> {
+    track("db_update_lender", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let lender = update_lender(
+            &conn,
+            &user_id_value,
+            &id,
+            &updates,
+            Utc::now().timestamp_millis(),
+        )?;
+        data_changed(
+            &user_id_value,
+            ChangedEntity::Lender,
+            &id,
+            Operation::Update,
+        );
+        Ok(lender)
+    })
+}
+
+/// Delete a lender; reassign_to moves its deals to another lender first
+#[tauri::command]
+pub fn db_delete_lender(
+    id: String,
+    reassign_to: Option<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    track("db_delete_lender", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let mut conn = db.conn();
+        delete_lender(
+            &mut conn,
+            &user_id_value,
+            &id,
+            reassign_to.as_deref(),
+            Utc::now().timestamp_millis(),
+        )?;
+        info!("🗑️  Lender deleted: {}", id);
+        data_changed(
+            &user_id_value,
+            ChangedEntity::Lender,
+            &id,
+            Operation::Delete,
+        );
+        Ok(())
+    })
+}
+
+/// Deal count and funded amount per lender for the current quarter (a report job)
+#[tauri::command]
+pub async fn db_get_lender_stats(
+    user_id: Option<String>,
+    job_id: Option<String>,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<LenderStatsReport, String> {
+    let user_id_value = state.require_user(user_id)?;
+
+    track_async("db_get_lender_stats", async move {
+        let (start_ms, end_ms) = quarter_bounds(&Local::now());
+        let owner = user_id_value.clone();
+        let lenders = run_report_job(
+            job_id,
+            "db_get_lender_stats",
+            &owner,
+            window.label(),
+            move |_, conn| {
+                lender_stats(conn, &user_id_value, start_ms, end_ms).map_err(|e| e.to_string())
+            },
+        )
+        .await?;
+        Ok(LenderStatsReport {
+            start_ms,
+            end_ms,
+            lenders,
+        })
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/013_add_odometer_title.sql"),
+            include_str!("../migrations/019_add_lenders.sql"),
+            include_str!("../migrations/026_money_cents.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, created_at, updated_at, user_id)
+                 VALUES ('c1', 'Jane', 'Doe', 0, 0, 'u1');
+             INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status,
+                                   created_at, updated_at, user_id)
+                 VALUES ('v1', 'VIN1', 2021, 'Honda', 'Civic', 0, 20000, 'sold', 0, 0, 'u1');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn lender(conn: &Connection, user_id: &str, name: &str, reserve_bps: i64) -> Lender {
+        create_lender(
+            conn,
+            user_id,
+            NewLender {
+                name: name.to_string(),
+                default_reserve_bps: reserve_bps,
+                ..NewLender::default()
+            },
+            0,
+        )
+        .unwrap()
+    }
+
+    fn deal(
+        conn: &Connection,
+        id: &str,
+        lender_id: &str,
+        status: &str,
+        sold_at: i64,
+        financed: f64,
+    ) {
+        conn.execute(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount,
+                                financed_amount, sale_date, created_at, updated_at, user_id, lender_id)
+             VALUES (?1, 'retail', 'c1', 'v1', ?2, ?3, ?3, ?4, 0, 0, 'u1', ?5)",
+            params![id, status, financed, sold_at, lender_id],
+        )
+        .unwrap();
+    }
+
+    fn lender_of(conn: &Connection, deal_id: &str) -> Option<String> {
+        conn.query_row(
+            "SELECT lender_id FROM deals WHERE id = ?1",
+            params![deal_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_delete_refused_while_deals_reference_lender() {
+        let mut conn = test_db();
+        let bank = lender(&conn, "u1", "First Bank", 0);
+        let credit_union = lender(&conn, "u1", "Credit Union", 0);
+        let other = lender(&conn, "u2", "Someone Else's Bank", 0);
+        deal(&conn, "d1", &bank.id, "sold", 0, 10_000.0);
+        deal(&conn, "d2", &bank.id, "quote", 0, 5_000.0);
+
+        let err = delete_lender(&mut conn, "u1", &bank.id, None, 1).unwrap_err();
+        assert!(err.contains("2 deals"), "{}", err);
+        assert!(get_lender(&conn, "u1", &bank.id).is_ok());
+
+        // Another user's lender isn't a valid target, and nothing moves
+        assert!(delete_lender(&mut conn, "u1", &bank.id, Some(&other.id), 1).is_err());
+        assert!(delete_lender(&mut conn, "u1", &bank.id, Some(&bank.id), 1).is_err());
+        assert_eq!(lender_of(&conn, "d1").as_deref(), Some(bank.id.as_str()));
+
+        delete_lender(&mut conn, "u1", &bank.id, Some(&credit_union.id), 1).unwrap();
+        assert!(get_lender(&conn, "u1", &bank.id).is_err());
+        assert_eq!(
+            lender_of(&conn, "d1").as_deref(),
+            Some(credit_union.id.as_str())
+        );
+        assert_eq!(
+            lender_of(&conn, "d2").as_deref(),
+            Some(credit_union.id.as_str())
+        );
+
+        // The foreign key backs the guard up for anything that bypasses it
+        assert!(conn
+            .execute(
+                "DELETE FROM lenders WHERE id = ?1",
+                params![credit_union.id]
+            )
+            .is_err());
+
+        // Unreferenced lenders delete normally; other users can't delete them
+        let spare = lender(&conn, "u1", "Spare", 0);
+        assert!(delete_lender(&mut conn, "u2", &spare.id, None, 1).is_err());
+        delete_lender(&mut conn, "u1", &spare.id, None, 1).unwrap();
+    }
+
+    #[test]
+    fn test_stats_group_by_lender_within_quarter() {
+        let conn = test_db();
+        let bank = lender(&conn, "u1", "First Bank", 150);
+        let credit_union = lender(&conn, "u1", "Credit Union", 0);
+        let idle = lender(&conn, "u1", "Idle Finance", 100);
+        let (start, end) = (1_000, 2_000);
+
+        deal(&conn, "d1", &bank.id, "sold", 1_000, 10_000.10);
+        deal(&conn, "d2", &bank.id, "completed", 1_999, 20_000.20);
+        deal(&conn, "d3", &credit_union.id, "sold", 1_500, 5_000.0);
+        // Not funded yet, or outside the quarter
+        deal(&conn, "d4", &bank.id, "approved", 1_500, 99_999.0);
+        deal(&conn, "d5", &bank.id, "cancelled", 1_500, 99_999.0);
+        deal(&conn, "d6", &bank.id, "sold", 999, 99_999.0);
+        deal(&conn, "d7", &credit_union.id, "sold", 2_000, 99_999.0);
+
+        let stats = lender_stats(&conn, "u1", start, end).unwrap();
+        let summary: Vec<(&str, u32, i64, i64)> = stats
+            .iter()
+            .map(|s| {
+                (
+                    s.lender_id.as_str(),
+                    s.deal_count,
+                    s.funded_cents,
+                    s.reserve_cents,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (bank.id.as_str(), 2, 3_000_030, 45_000),
+                (credit_union.id.as_str(), 1, 500_000, 0),
+                (idle.id.as_str(), 0, 0, 0),
+            ]
+        );
+        assert!(lender_stats(&conn, "u2", start, end).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_quarter_bounds_follow_local_calendar() {
+        let tz = FixedOffset::west_opt(5 * 3600).unwrap();
+        let now = tz.with_ymd_and_hms(2026, 11, 20, 9, 0, 0).unwrap();
+        let (start, end) = quarter_bounds(&now);
+        assert_eq!(
+            start,
+            tz.with_ymd_and_hms(2026, 10, 1, 0, 0, 0)
+                .unwrap()
+                .timestamp_millis()
+        );
+        assert_eq!(
+            end,
+            tz.with_ymd_and_hms(2027, 1, 1, 0, 0, 0)
+                .unwrap()
+                .timestamp_millis()
+        );
+
+        let now = tz.with_ymd_and_hms(2026, 3, 31, 23, 59, 0).unwrap();
+        let (start, end) = quarter_bounds(&now);
+        assert_eq!(
+            start,
+            tz.with_ymd_and_hms(2026, 1, 1, 0, 0, 0)
+                .unwrap()
+                .timestamp_millis()
+        );
+        assert_eq!(
+            end,
+            tz.with_ymd_and_hms(2026, 4, 1, 0, 0, 0)
+                .unwrap()
+                .timestamp_millis()
+        );
+    }
+
+    #[test]
+    fn test_ein_and_reserve_validation() {
+        assert_eq!(normalize_ein("123456789").unwrap(), "12-3456789");
+        assert_eq!(normalize_ein(" 12-3456789 ").unwrap(), "12-3456789");
+        assert!(normalize_ein("12-345678").is_err());
+        assert!(normalize_ein("12-34567a89").is_err());
+
+        let conn = test_db();
+        let bank = lender(&conn, "u1", "First Bank", 0);
+        let updated = update_lender(
+            &conn,
+            "u1",
+            &bank.id,
+            &serde_json::json!({ "ein": "987654321", "state": "tx", "default_reserve_bps": 250 }),
+            5,
+        )
+        .unwrap();
+        assert_eq!(updated.ein.as_deref(), Some("98-7654321"));
+        assert_eq!(updated.state.as_deref(), Some("TX"));
+        assert_eq!(updated.default_reserve_bps, 250);
+        assert!(update_lender(
+            &conn,
+            "u1",
+            &bank.id,
+            &serde_json::json!({ "default_reserve_bps": 10_001 }),
+            6
+        )
+        .is_err());
+        assert!(update_lender(
+            &conn,
+            "u1",
+            &bank.id,
+            &serde_json::json!({ "name": " " }),
+            6
+        )
+        .is_err());
+    }
+}
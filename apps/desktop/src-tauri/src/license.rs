@@ -2,57 +2,107 @@
 //
 // License management and machine identification for desktop app
 
-use keyring::Entry;
-use log::{error, info};
+use base64::{engine::general_purpose, Engine as _};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use log::{error, info, warn};
+use once_cell::sync::OnceCell;
 use serde::{Deserialize, Serialize};
 use std::process::Command;
-use tauri::command;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+use tauri::{command, AppHandle, Emitter};
 
-const SERVICE_NAME: &str = "net.universalautobrokers.dealersoftware";
-const LICENSE_KEY_NAME: &str = "license_key";
+use crate::license_activation::apply_grace_period;
+use crate::secret_store::{self, SecretKey};
+use crate::trial::{get_trial_status, TrialStatus};
+
+/// Fingerprint components that must agree for two fingerprints to be the same machine
+pub const MIN_MATCHING_COMPONENTS: usize = 2;
+
+/// Ladder for the primary id: the first component that answered wins,
+/// and only when all of them fail do we fall back to hostname hashing
+#[cfg(target_os = "windows")]
+const PRIMARY_ID_LADDER: &[&str] = &["machine_guid", "product_uuid", "volume_serial"];
+#[cfg(target_os = "macos")]
+const PRIMARY_ID_LADDER: &[&str] = &["platform_uuid", "board_serial"];
+#[cfg(target_os = "linux")]
+const PRIMARY_ID_LADDER: &[&str] = &["product_uuid", "machine_id"];
+
+/// Source recorded when every rung of the ladder failed
+const FALLBACK_SOURCE: &str = "hostname_fallback";
+
+/// Longest we wait on fingerprint probes (a hung WMI service mustn't block startup)
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Values firmware vendors ship instead of a real serial
+const PLACEHOLDER_VALUES: &[&str] = &[
+    "to be filled by o.e.m.",
+    "default string",
+    "system serial number",
+    "not specified",
+    "not applicable",
+    "none",
+];
+
+static MACHINE_ID: OnceCell<ResolvedMachineId> = OnceCell::new();
+static MACHINE_COMPONENTS: OnceCell<Vec<MachineComponent>> = OnceCell::new();
+
+/// Product identifier every license for this app must carry
+pub const LICENSE_PRODUCT: &str = "dealer-software";
+
+/// Event emitted at startup (and whenever the status is recomputed)
+pub const LICENSE_STATUS_EVENT: &str = "license-status";
+
+/// Allowed difference between our clock and the license server's (5 minutes)
+pub const CLOCK_SKEW_TOLERANCE_SECS: i64 = 5 * 60;
+
+/// Base64 Ed25519 public key of the license issuer, embedded at build time
+const LICENSE_PUBLIC_KEY: Option<&str> = option_env!("DEALER_LICENSE_PUBLIC_KEY");
 
 /// Get unique machine ID
-/// Uses platform-specific methods to generate a stable machine identifier
+/// Resolved once from hardware fingerprints, then remembered in the keyring so it
+/// survives hostname changes and reinstalls
 #[command]
 pub fn get_machine_id() -> Result<String, String> {
-    #[cfg(target_os = "windows")]
-    {
-        // Windows: Use machine GUID
-        match get_windows_machine_guid() {
-            Ok(guid) => Ok(guid),
-            Err(e) => {
-                error!("Failed to get Windows machine GUID: {}", e);
-                // Fallback to hostname + username hash
-                Ok(get_fallback_machine_id())
-            }
-        }
-    }
+    resolved_machine_id().map(|resolved| resolved.machine_id.clone())
+}
 
-    #[cfg(target_os = "macos")]
-    {
-        // macOS: Use hardware UUID
-        match get_macos_hardware_uuid() {
-            Ok(uuid) => Ok(uuid),
-            Err(e) => {
-                error!("Failed to get macOS hardware UUID: {}", e);
-                Ok(get_fallback_machine_id())
-            }
-        }
-    }
+fn resolved_machine_id() -> Result<&'static ResolvedMachineId, String> {
+    MACHINE_ID.get_or_try_init(|| resolve_machine_id(machine_components()))
+}
 
+/// Machine ID as computed before composite fingerprints (kept for licensed machines)
+/// Deliberately not the probe ladder: existing licenses are bound to exactly these values
+fn legacy_machine_id() -> ResolvedMachineId {
+    #[cfg(target_os = "windows")]
+    let (source, result) = ("machine_guid", get_windows_machine_guid());
+    #[cfg(target_os = "macos")]
+    let (source, result) = ("platform_uuid", get_macos_hardware_uuid());
     #[cfg(target_os = "linux")]
-    {
-        // Linux: Use machine-id
-        match get_linux_machine_id() {
-            Ok(id) => Ok(id),
-            Err(e) => {
-                error!("Failed to get Linux machine-id: {}", e);
-                Ok(get_fallback_machine_id())
+    let (source, result) = ("machine_id", get_linux_machine_id());
+
+    match result {
+        Ok(machine_id) => ResolvedMachineId {
+            machine_id,
+            source: source.to_string(),
+        },
+        Err(e) => {
+            error!("Failed to get legacy machine id from {}: {}", source, e);
+            // Fallback to hostname + username hash
+            ResolvedMachineId {
+                machine_id: get_fallback_machine_id(),
+                source: FALLBACK_SOURCE.to_string(),
             }
         }
     }
 }
 
+/// OS-provided machine id, read without touching the keyring
+/// Keys the encrypted secrets fallback, which is used exactly when the keyring is broken
+pub(crate) fn os_machine_id() -> String {
+    legacy_machine_id().machine_id
+}
+
 /// Get platform name
 #[command]
 pub fn get_platform() -> String {
@@ -77,11 +127,7 @@ pub fn get_hostname() -> Result<String, String> {
 /// Store license key securely
 #[command]
 pub fn store_license(license_key: String) -> Result<(), String> {
-    let entry = Entry::new(SERVICE_NAME, LICENSE_KEY_NAME)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    entry
-        .set_password(&license_key)
+    secret_store::store(SecretKey::LicenseKey, &license_key)
         .map_err(|e| format!("Failed to store license: {}", e))?;
 
     info!("License key stored securely");
@@ -91,28 +137,494 @@ pub fn store_license(license_key: String) -> Result<(), String> {
 /// Retrieve stored license key
 #[command]
 pub fn get_stored_license() -> Result<String, String> {
-    let entry = Entry::new(SERVICE_NAME, LICENSE_KEY_NAME)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    entry
-        .get_password()
-        .map_err(|e| format!("No license found: {}", e))
+    match secret_store::get(SecretKey::LicenseKey) {
+        Ok(Some(key)) => Ok(key),
+        Ok(None) => Err("No license found".to_string()),
+        Err(e) => Err(format!("No license found: {}", e)),
+    }
 }
 
 /// Remove stored license key
 #[command]
 pub fn remove_stored_license() -> Result<(), String> {
-    let entry = Entry::new(SERVICE_NAME, LICENSE_KEY_NAME)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    entry
-        .delete_credential()
-        .map_err(|e| format!("Failed to remove license: {}", e))?;
+    if !secret_store::remove(SecretKey::LicenseKey)
+        .map_err(|e| format!("Failed to remove license: {}", e))?
+    {
+        return Err("Failed to remove license: no license stored".to_string());
+    }
 
     info!("License key removed");
     Ok(())
 }
 
+// ============================================================================
+// SIGNED LICENSES
+// ============================================================================
+//
+// A license key is "<payload>.<signature>", both base64url without padding:
+// payload is the JSON-encoded LicenseClaims, signature is Ed25519 over the payload bytes
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LicenseClaims {
+    pub product: String,
+    pub dealer_id: String,
+    pub plan: String,
+    pub seats: u32,
+    pub issued_at: i64, // Unix seconds
+    #[serde(default)]
+    pub not_before: Option<i64>,
+    pub expires_at: i64,
+    #[serde(default)]
+    pub features: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LicenseError {
+    Malformed { message: String },
+    BadSignature,
+    Expired { expires_at: i64 },
+    NotYetValid { not_before: i64 },
+    WrongProduct { product: String },
+    Other { message: String },
+}
+
+impl std::fmt::Display for LicenseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LicenseError::Malformed { message } => write!(f, "Malformed license: {}", message),
+            LicenseError::BadSignature => write!(f, "License signature is invalid"),
+            LicenseError::Expired { expires_at } => write!(f, "License expired at {}", expires_at),
+            LicenseError::NotYetValid { not_before } => {
+                write!(f, "License is not valid until {}", not_before)
+            }
+            LicenseError::WrongProduct { product } => {
+                write!(f, "License is for a different product: {}", product)
+            }
+            LicenseError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for LicenseError {
+    fn from(message: String) -> Self {
+        LicenseError::Other { message }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LicenseState {
+    Valid,
+    GracePeriod,
+    Expired,
+    Invalid,
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LicenseStatus {
+    pub state: LicenseState,
+    pub claims: Option<LicenseClaims>,
+    pub error: Option<LicenseError>,
+    pub checked_at: i64,
+    /// Set once activation heartbeats have been failing (see license_activation.rs)
+    pub grace_expires_at: Option<i64>,
+    /// Trial state, so the UI can offer the trial when no license is present
+    pub trial: Option<TrialStatus>,
+}
+
+/// Decode and verify a license key offline
+#[command]
+pub fn validate_license(license_key: String) -> Result<LicenseClaims, LicenseError> {
+    let public_key = license_public_key()?;
+    verify_license(&license_key, &public_key, chrono::Utc::now().timestamp())
+}
+
+/// Current license state for the stored key (the UI polls this)
+#[command]
+pub fn get_license_status() -> LicenseStatus {
+    compute_license_status()
+}
+
+/// Validate the stored license and broadcast the result as a "license-status" event
+pub fn emit_license_status(app: &AppHandle) {
+    let status = compute_license_status();
+    info!("🔑 License status: {:?}", status.state);
+
+    if let Err(e) = app.emit(LICENSE_STATUS_EVENT, &status) {
+        error!("❌ Failed to emit license status: {}", e);
+    }
+}
+
+fn compute_license_status() -> LicenseStatus {
+    let result = match stored_license_key() {
+        Ok(Some(key)) => Some(validate_license(key)),
+        Ok(None) => None,
+        Err(e) => Some(Err(LicenseError::from(e))),
+    };
+
+    let mut status = license_status_from(result, chrono::Utc::now().timestamp());
+    apply_grace_period(&mut status);
+    status.trial = Some(get_trial_status());
+    status
+}
+
+fn license_status_from(
+    result: Option<Result<LicenseClaims, LicenseError>>,
+    checked_at: i64,
+) -> LicenseStatus {
+    let (state, claims, error) = match result {
+        None => (LicenseState::Missing, None, None),
+        Some(Ok(claims)) => (LicenseState::Valid, Some(claims), None),
+        Some(Err(error @ LicenseError::Expired { .. })) => {
+            (LicenseState::Expired, None, Some(error))
+        }
+        Some(Err(error)) => {
+            warn!("⚠️  Stored license rejected: {}", error);
+            (LicenseState::Invalid, None, Some(error))
+        }
+    };
+
+    LicenseStatus {
+        state,
+        claims,
+        error,
+        checked_at,
+        grace_expires_at: None,
+        trial: None,
+    }
+}
+
+fn stored_license_key() -> Result<Option<String>, String> {
+    secret_store::get(SecretKey::LicenseKey).map_err(|e| format!("Failed to read license: {}", e))
+}
+
+fn license_public_key() -> Result<VerifyingKey, LicenseError> {
+    let encoded = LICENSE_PUBLIC_KEY.ok_or_else(|| {
+        LicenseError::from("License verification key not configured in this build".to_string())
+    })?;
+
+    let bytes: [u8; 32] = general_purpose::STANDARD
+        .decode(encoded.trim())
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| LicenseError::from("Embedded license public key is invalid".to_string()))?;
+
+    VerifyingKey::from_bytes(&bytes)
+        .map_err(|e| LicenseError::from(format!("Embedded license public key is invalid: {}", e)))
+}
+
+fn verify_license(
+    license_key: &str,
+    public_key: &VerifyingKey,
+    now: i64,
+) -> Result<LicenseClaims, LicenseError> {
+    let malformed = |message: &str| LicenseError::Malformed {
+        message: message.to_string(),
+    };
+
+    let (payload_b64, signature_b64) = license_key
+        .trim()
+        .split_once('.')
+        .ok_or_else(|| malformed("expected <payload>.<signature>"))?;
+
+    let payload = general_purpose::URL_SAFE_NO_PAD
+        .decode(payload_b64)
+        .map_err(|_| malformed("payload is not base64url"))?;
+    let signature_bytes: [u8; 64] = general_purpose::URL_SAFE_NO_PAD
+        .decode(signature_b64)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| malformed("signature is not a base64url Ed25519 signature"))?;
+
+    // Check the signature before trusting anything in the payload
+    public_key
+        .verify(&payload, &Signature::from_bytes(&signature_bytes))
+        .map_err(|_| LicenseError::BadSignature)?;
+
+    let claims: LicenseClaims = serde_json::from_slice(&payload)
+        .map_err(|e| malformed(&format!("invalid claims: {}", e)))?;
+
+    if claims.product != LICENSE_PRODUCT {
+        return Err(LicenseError::WrongProduct {
+            product: claims.product,
+        });
+    }
+    if let Some(not_before) = claims.not_before {
+        if now + CLOCK_SKEW_TOLERANCE_SECS < not_before {
+            return Err(LicenseError::NotYetValid { not_before });
+        }
+    }
+    if now - CLOCK_SKEW_TOLERANCE_SECS > claims.expires_at {
+        return Err(LicenseError::Expired {
+            expires_at: claims.expires_at,
+        });
+    }
+
+    Ok(claims)
+}
+
+// ============================================================================
+// MACHINE FINGERPRINT
+// ============================================================================
+//
+// Each source is hashed on its own so the server can match fuzzily
+// ("2 of 3 components match = same machine") without seeing raw serials
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MachineComponent {
+    pub name: String,
+    pub hash: String, // SHA-256 hex
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ResolvedMachineId {
+    machine_id: String,
+    source: String, // Component name, or FALLBACK_SOURCE
+}
+
+/// What we remember in the keyring: the id handed out, and the fingerprint it belongs to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MachineIdRecord {
+    machine_id: String,
+    #[serde(default)]
+    source: String,
+    components: Vec<MachineComponent>,
+}
+
+/// A single fingerprint source, run on its own thread so it can be abandoned on timeout
+type Probe = Box<dyn FnOnce() -> Result<String, String> + Send>;
+
+fn machine_components() -> &'static [MachineComponent] {
+    MACHINE_COMPONENTS.get_or_init(|| collect_components(machine_probes(), PROBE_TIMEOUT))
+}
+
+/// Run all probes in parallel and hash whatever answered before the deadline
+/// A failed or hung probe only loses its own component
+fn collect_components(
+    probes: Vec<(&'static str, Probe)>,
+    timeout: Duration,
+) -> Vec<MachineComponent> {
+    let deadline = Instant::now() + timeout;
+
+    let pending: Vec<_> = probes
+        .into_iter()
+        .map(|(name, probe)| {
+            let (tx, rx) = mpsc::channel();
+            std::thread::spawn(move || {
+                let _ = tx.send(probe());
+            });
+            (name, rx)
+        })
+        .collect();
+
+    pending
+        .into_iter()
+        .filter_map(|(name, rx)| {
+            match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(Ok(value)) => hash_component(name, &value),
+                Ok(Err(e)) => {
+                    warn!("⚠️  Machine id probe {} failed: {}", name, e);
+                    None
+                }
+                Err(_) => {
+                    warn!("⚠️  Machine id probe {} timed out", name);
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+fn resolve_machine_id(components: &[MachineComponent]) -> Result<ResolvedMachineId, String> {
+    if let Some(record) = read_machine_id_record() {
+        // A record copied from another machine (synced keychain, cloned disk) doesn't count.
+        // Hostnames change when IT renames laptops, so only hardware/OS ids decide here
+        if is_same_machine(
+            &without_hostname(&record.components),
+            &without_hostname(components),
+        ) {
+            return Ok(ResolvedMachineId {
+                machine_id: record.machine_id,
+                source: record.source,
+            });
+        }
+        warn!("⚠️  Stored machine fingerprint does not match this machine; issuing a new id");
+    }
+
+    // Machines licensed before composite fingerprints keep the id their license is bound to
+    let resolved = if stored_license_key().ok().flatten().is_some() {
+        info!("🔑 Keeping legacy machine id for existing license");
+        legacy_machine_id()
+    } else {
+        primary_machine_id(components, PRIMARY_ID_LADDER)
+    };
+    info!("🔑 Machine id source: {}", resolved.source);
+
+    let record = MachineIdRecord {
+        machine_id: resolved.machine_id.clone(),
+        source: resolved.source.clone(),
+        components: components.to_vec(),
+    };
+    if let Err(e) = store_machine_id_record(&record) {
+        warn!("⚠️  Failed to remember machine id: {}", e);
+    }
+
+    Ok(resolved)
+}
+
+fn without_hostname(components: &[MachineComponent]) -> Vec<MachineComponent> {
+    components
+        .iter()
+        .filter(|component| component.name != "hostname")
+        .cloned()
+        .collect()
+}
+
+/// Hash of the first ladder component that answered, else the hostname fallback
+fn primary_machine_id(components: &[MachineComponent], ladder: &[&str]) -> ResolvedMachineId {
+    ladder
+        .iter()
+        .find_map(|name| components.iter().find(|c| c.name == *name))
+        .map(|component| ResolvedMachineId {
+            machine_id: component.hash.clone(),
+            source: component.name.clone(),
+        })
+        .unwrap_or_else(|| ResolvedMachineId {
+            machine_id: get_fallback_machine_id(),
+            source: FALLBACK_SOURCE.to_string(),
+        })
+}
+
+/// Hash one fingerprint source; None for empty or placeholder values
+fn hash_component(name: &str, value: &str) -> Option<MachineComponent> {
+    use sha2::{Digest, Sha256};
+
+    // Tools disagree on UUID case; normalize so the same hardware hashes the same
+    let normalized = value.trim().to_lowercase();
+    let digits: String = normalized.chars().filter(|c| *c != '-').collect();
+    let all_same = digits.chars().all(|c| c == '0') || digits.chars().all(|c| c == 'f');
+    if normalized.is_empty() || all_same || PLACEHOLDER_VALUES.contains(&normalized.as_str()) {
+        return None;
+    }
+
+    let mut hasher = Sha256::new();
+    // Salt with the component name so equal values from different sources don't collide
+    hasher.update(format!("{}:{}", name, normalized).as_bytes());
+
+    Some(MachineComponent {
+        name: name.to_string(),
+        hash: format!("{:x}", hasher.finalize()),
+    })
+}
+
+/// Number of components present in both fingerprints with the same hash
+pub fn matching_components(a: &[MachineComponent], b: &[MachineComponent]) -> usize {
+    a.iter()
+        .filter(|component| b.iter().any(|other| other == *component))
+        .count()
+}
+
+/// Fuzzy comparison: MIN_MATCHING_COMPONENTS must agree, or all of them when
+/// fewer than that are available on both sides
+pub fn is_same_machine(a: &[MachineComponent], b: &[MachineComponent]) -> bool {
+    let comparable = a
+        .iter()
+        .filter(|component| b.iter().any(|other| other.name == component.name))
+        .count();
+    let required = MIN_MATCHING_COMPONENTS.min(comparable).max(1);
+
+    matching_components(a, b) >= required
+}
+
+fn read_machine_id_record() -> Option<MachineIdRecord> {
+    let json = secret_store::get(SecretKey::MachineFingerprint).ok()??;
+    serde_json::from_str(&json).ok()
+}
+
+fn store_machine_id_record(record: &MachineIdRecord) -> Result<(), String> {
+    let json = serde_json::to_string(record)
+        .map_err(|e| format!("Failed to serialize machine id: {}", e))?;
+
+    secret_store::store(SecretKey::MachineFingerprint, &json)
+        .map_err(|e| format!("Failed to store machine id: {}", e))
+}
+
+fn hostname_component() -> Result<String, String> {
+    hostname::get()
+        .map(|name| name.to_string_lossy().to_string())
+        .map_err(|e| format!("Failed to get hostname: {}", e))
+}
+
+/// Fingerprint sources for this platform (values are hashed before leaving this module)
+#[cfg(target_os = "windows")]
+fn machine_probes() -> Vec<(&'static str, Probe)> {
+    vec![
+        ("machine_guid", Box::new(get_windows_machine_guid)),
+        ("product_uuid", Box::new(get_windows_product_uuid)),
+        ("volume_serial", Box::new(get_windows_volume_serial)),
+        ("bios_serial", Box::new(get_windows_bios_serial)),
+        ("hostname", Box::new(hostname_component)),
+    ]
+}
+
+#[cfg(target_os = "macos")]
+fn machine_probes() -> Vec<(&'static str, Probe)> {
+    vec![
+        ("platform_uuid", Box::new(get_macos_hardware_uuid)),
+        ("board_serial", Box::new(get_macos_board_serial)),
+        ("hostname", Box::new(hostname_component)),
+    ]
+}
+
+#[cfg(target_os = "linux")]
+fn machine_probes() -> Vec<(&'static str, Probe)> {
+    vec![
+        ("machine_id", Box::new(get_linux_machine_id)),
+        ("product_uuid", Box::new(get_linux_product_uuid)),
+        ("hostname", Box::new(hostname_component)),
+    ]
+}
+
+/// Run a command, killing it if it doesn't finish in time, and return trimmed stdout
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn command_output(command: &mut Command, timeout: Duration) -> Result<String, String> {
+    use std::io::Read;
+    use std::process::Stdio;
+
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to execute {}: {}", program, e))?;
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() >= deadline => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("{} timed out", program));
+            }
+            Ok(None) => std::thread::sleep(Duration::from_millis(50)),
+            Err(e) => return Err(format!("Failed to wait for {}: {}", program, e)),
+        }
+    };
+
+    let mut stdout = String::new();
+    if let Some(mut pipe) = child.stdout.take() {
+        let _ = pipe.read_to_string(&mut stdout);
+    }
+
+    if !status.success() {
+        return Err(format!("{} exited with {}", program, status));
+    }
+    Ok(stdout.trim().to_string())
+}
+
 // Platform-specific implementations
 
 #[cfg(target_os = "windows")]
@@ -132,18 +644,90 @@ fn get_windows_machine_guid() -> Result<String, String> {
     Ok(guid)
 }
 
+#[cfg(target_os = "windows")]
+fn get_windows_product_uuid() -> Result<String, String> {
+    wmi_value("(Get-CimInstance -ClassName Win32_ComputerSystemProduct).UUID")
+}
+
+#[cfg(target_os = "windows")]
+fn get_windows_bios_serial() -> Result<String, String> {
+    wmi_value("(Get-CimInstance -ClassName Win32_BIOS).SerialNumber")
+}
+
+/// Query one WMI property through PowerShell (no console window, bounded by PROBE_TIMEOUT)
+#[cfg(target_os = "windows")]
+fn wmi_value(query: &str) -> Result<String, String> {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let value = command_output(
+        Command::new("powershell")
+            .args(["-NoProfile", "-NonInteractive", "-Command", query])
+            .creation_flags(CREATE_NO_WINDOW),
+        PROBE_TIMEOUT,
+    )?;
+
+    if value.is_empty() {
+        return Err(format!("WMI returned no value for {}", query));
+    }
+    Ok(value)
+}
+
+/// Serial number of the system drive's volume (changes on reformat, but survives renames)
+#[cfg(target_os = "windows")]
+fn get_windows_volume_serial() -> Result<String, String> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetVolumeInformationW;
+
+    let drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+    let root: Vec<u16> = std::ffi::OsStr::new(&format!("{}\\", drive))
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+
+    let mut serial = 0u32;
+    let ok = unsafe {
+        GetVolumeInformationW(
+            root.as_ptr(),
+            std::ptr::null_mut(),
+            0,
+            &mut serial,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if ok == 0 {
+        return Err(format!(
+            "Failed to read volume serial: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    Ok(format!("{:08X}", serial))
+}
+
 #[cfg(target_os = "macos")]
 fn get_macos_hardware_uuid() -> Result<String, String> {
-    let output = Command::new("ioreg")
-        .args(&["-rd1", "-c", "IOPlatformExpertDevice"])
-        .output()
-        .map_err(|e| format!("Failed to execute ioreg: {}", e))?;
+    ioreg_platform_value("IOPlatformUUID")
+}
 
-    let output_str = String::from_utf8_lossy(&output.stdout);
+#[cfg(target_os = "macos")]
+fn get_macos_board_serial() -> Result<String, String> {
+    ioreg_platform_value("IOPlatformSerialNumber")
+}
 
-    // Parse UUID from output
-    for line in output_str.lines() {
-        if line.contains("IOPlatformUUID") {
+#[cfg(target_os = "macos")]
+fn ioreg_platform_value(key: &str) -> Result<String, String> {
+    let output = command_output(
+        Command::new("ioreg").args(["-rd1", "-c", "IOPlatformExpertDevice"]),
+        PROBE_TIMEOUT,
+    )?;
+
+    // Lines look like: "IOPlatformUUID" = "XXXXXXXX-..."
+    for line in output.lines() {
+        if line.contains(key) {
             let parts: Vec<&str> = line.split('"').collect();
             if parts.len() >= 4 {
                 return Ok(parts[3].to_string());
@@ -151,7 +735,7 @@ fn get_macos_hardware_uuid() -> Result<String, String> {
         }
     }
 
-    Err("UUID not found in ioreg output".to_string())
+    Err(format!("{} not found in ioreg output", key))
 }
 
 #[cfg(target_os = "linux")]
@@ -169,6 +753,14 @@ fn get_linux_machine_id() -> Result<String, String> {
     Err("Machine ID not found".to_string())
 }
 
+#[cfg(target_os = "linux")]
+fn get_linux_product_uuid() -> Result<String, String> {
+    // Usually root-only; unreadable just means one fewer component
+    std::fs::read_to_string("/sys/class/dmi/id/product_uuid")
+        .map(|uuid| uuid.trim().to_string())
+        .map_err(|e| format!("Failed to read product_uuid: {}", e))
+}
+
 /// Fallback machine ID using hostname + username hash
 fn get_fallback_machine_id() -> String {
     use sha2::{Digest, Sha256};
@@ -191,21 +783,297 @@ fn get_fallback_machine_id() -> String {
     format!("{:x}", result)
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MachineInfo {
     pub machine_id: String,
     pub platform: String,
     pub hostname: String,
     pub app_version: String,
+    /// Which probe produced machine_id (e.g. "machine_guid", "hostname_fallback"), for support
+    #[serde(default)]
+    pub machine_id_source: String,
+    /// Hashed fingerprint components for server-side fuzzy matching
+    #[serde(default)]
+    pub components: Vec<MachineComponent>,
 }
 
 /// Get all machine info at once
 #[command]
 pub fn get_machine_info() -> Result<MachineInfo, String> {
+    let resolved = resolved_machine_id()?;
+
     Ok(MachineInfo {
-        machine_id: get_machine_id()?,
+        machine_id: resolved.machine_id.clone(),
+        machine_id_source: resolved.source.clone(),
         platform: get_platform(),
         hostname: get_hostname().unwrap_or_else(|_| "Unknown".to_string()),
         app_version: get_app_version(),
+        components: machine_components().to_vec(),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const NOW: i64 = 1_767_225_600; // 2026-01-01T00:00:00Z
+
+    fn signing_key() -> SigningKey {
+        SigningKey::from_bytes(&[7u8; 32])
+    }
+
+    fn claims() -> LicenseClaims {
+        LicenseClaims {
+            product: LICENSE_PRODUCT.to_string(),
+            dealer_id: "dealer_123".to_string(),
+            plan: "pro".to_string(),
+            seats: 3,
+            issued_at: NOW - 86_400,
+            not_before: Some(NOW - 60),
+            expires_at: NOW + 30 * 86_400,
+            features: vec!["s3_sync".to_string()],
+        }
+    }
+
+    fn encode(payload: &[u8], key: &SigningKey) -> String {
+        format!(
+            "{}.{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(payload),
+            general_purpose::URL_SAFE_NO_PAD.encode(key.sign(payload).to_bytes())
+        )
+    }
+
+    fn issue(claims: &LicenseClaims) -> String {
+        encode(&serde_json::to_vec(claims).unwrap(), &signing_key())
+    }
+
+    fn component(name: &str, value: &str) -> MachineComponent {
+        hash_component(name, value).unwrap()
+    }
+
+    #[test]
+    fn test_component_hashing() {
+        let uuid = component("product_uuid", "4C4C4544-0042-3510-8051-B4C04F565931");
+
+        // Case and surrounding whitespace don't change the hash
+        assert_eq!(
+            component("product_uuid", "  4c4c4544-0042-3510-8051-b4c04f565931\n"),
+            uuid
+        );
+        assert_eq!(uuid.hash.len(), 64);
+
+        // Same value under a different source name hashes differently
+        assert_ne!(
+            component("machine_id", "4C4C4544-0042-3510-8051-B4C04F565931").hash,
+            uuid.hash
+        );
+
+        // Empty and vendor placeholder values are skipped
+        assert!(hash_component("bios_serial", "  ").is_none());
+        assert!(hash_component("bios_serial", "To be filled by O.E.M.").is_none());
+        assert!(hash_component("product_uuid", "00000000-0000-0000-0000-000000000000").is_none());
+        assert!(hash_component("product_uuid", "FFFFFFFF-FFFF-FFFF-FFFF-FFFFFFFFFFFF").is_none());
+
+        // Primary id prefers hardware over OS ids over hostname
+        let components = vec![
+            component("machine_id", "abc123"),
+            component("hostname", "front-desk"),
+            uuid.clone(),
+        ];
+        let ladder = &["product_uuid", "machine_id"];
+        assert_eq!(
+            primary_machine_id(&components, ladder).machine_id,
+            uuid.hash
+        );
+        assert_eq!(
+            primary_machine_id(&components[..2], ladder).machine_id,
+            components[0].hash
+        );
+    }
+
+    #[test]
+    fn test_probe_ladder_degrades_per_component() {
+        const LADDER: &[&str] = &["machine_guid", "product_uuid", "volume_serial"];
+
+        fn probes(
+            guid: Result<&'static str, &'static str>,
+            wmi_hangs: bool,
+            volume: Result<&'static str, &'static str>,
+        ) -> Vec<(&'static str, Probe)> {
+            let wrap = |result: Result<&'static str, &'static str>| -> Probe {
+                Box::new(move || result.map(String::from).map_err(String::from))
+            };
+            vec![
+                ("machine_guid", wrap(guid)),
+                (
+                    "product_uuid",
+                    Box::new(move || {
+                        if wmi_hangs {
+                            std::thread::sleep(Duration::from_secs(3));
+                        }
+                        Ok("4C4C4544-0042-3510".to_string())
+                    }),
+                ),
+                ("volume_serial", wrap(volume)),
+                ("hostname", wrap(Ok("front-desk"))),
+            ]
+        }
+        let timeout = Duration::from_millis(300);
+
+        // Registry readable: MachineGuid wins
+        let components = collect_components(probes(Ok("guid-1"), false, Ok("1A2B")), timeout);
+        assert_eq!(
+            primary_machine_id(&components, LADDER).source,
+            "machine_guid"
+        );
+
+        // Locked-down image: registry denied, WMI still works
+        let components =
+            collect_components(probes(Err("Access is denied"), false, Ok("1A2B")), timeout);
+        assert_eq!(
+            primary_machine_id(&components, LADDER).source,
+            "product_uuid"
+        );
+        assert_eq!(components.len(), 3);
+
+        // Registry denied and WMI hung: volume serial, without waiting on WMI
+        let started = Instant::now();
+        let components =
+            collect_components(probes(Err("Access is denied"), true, Ok("1A2B")), timeout);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(
+            primary_machine_id(&components, LADDER).source,
+            "volume_serial"
+        );
+
+        // Everything failed: hostname fallback
+        let components = collect_components(probes(Err("denied"), true, Err("no volume")), timeout);
+        let resolved = primary_machine_id(&components, LADDER);
+        assert_eq!(resolved.source, FALLBACK_SOURCE);
+        assert_eq!(resolved.machine_id, get_fallback_machine_id());
+    }
+
+    #[test]
+    fn test_partial_fingerprint_match() {
+        let laptop = vec![
+            component("machine_id", "abc123"),
+            component("product_uuid", "4c4c4544-0042"),
+            component("hostname", "front-desk"),
+        ];
+
+        // IT renamed the laptop: 2 of 3 still match
+        let renamed = vec![
+            laptop[0].clone(),
+            laptop[1].clone(),
+            component("hostname", "sales-01"),
+        ];
+        assert_eq!(matching_components(&laptop, &renamed), 2);
+        assert!(is_same_machine(&laptop, &renamed));
+
+        // OS reinstalled and renamed: only the hardware uuid matches
+        let reinstalled = vec![
+            component("machine_id", "def456"),
+            laptop[1].clone(),
+            component("hostname", "sales-01"),
+        ];
+        assert!(!is_same_machine(&laptop, &reinstalled));
+
+        // Only one comparable component (product_uuid unreadable without root)
+        let limited = vec![laptop[0].clone()];
+        assert!(is_same_machine(&laptop, &limited));
+        assert!(!is_same_machine(
+            &limited,
+            &[component("machine_id", "other")]
+        ));
+        assert!(!is_same_machine(&laptop, &[]));
+    }
+
+    #[test]
+    fn test_valid_and_tampered_licenses() {
+        let public_key = signing_key().verifying_key();
+
+        assert_eq!(
+            verify_license(&issue(&claims()), &public_key, NOW),
+            Ok(claims())
+        );
+
+        // Payload edited after signing (more seats)
+        let mut upgraded = claims();
+        upgraded.seats = 50;
+        let forged = format!(
+            "{}.{}",
+            general_purpose::URL_SAFE_NO_PAD.encode(serde_json::to_vec(&upgraded).unwrap()),
+            issue(&claims()).split_once('.').unwrap().1
+        );
+        assert_eq!(
+            verify_license(&forged, &public_key, NOW),
+            Err(LicenseError::BadSignature)
+        );
+
+        // Signed by someone else
+        let other = SigningKey::from_bytes(&[9u8; 32]);
+        let foreign = encode(&serde_json::to_vec(&claims()).unwrap(), &other);
+        assert_eq!(
+            verify_license(&foreign, &public_key, NOW),
+            Err(LicenseError::BadSignature)
+        );
+
+        let mut wrong_product = claims();
+        wrong_product.product = "other-app".to_string();
+        assert_eq!(
+            verify_license(&issue(&wrong_product), &public_key, NOW),
+            Err(LicenseError::WrongProduct {
+                product: "other-app".to_string()
+            })
+        );
+
+        assert!(matches!(
+            verify_license("not-a-license", &public_key, NOW),
+            Err(LicenseError::Malformed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_clock_skew_tolerance() {
+        let public_key = signing_key().verifying_key();
+        let license = issue(&claims());
+        let expires_at = claims().expires_at;
+        let not_before = claims().not_before.unwrap();
+
+        // Within tolerance on either side
+        assert!(verify_license(
+            &license,
+            &public_key,
+            expires_at + CLOCK_SKEW_TOLERANCE_SECS
+        )
+        .is_ok());
+        assert!(verify_license(
+            &license,
+            &public_key,
+            not_before - CLOCK_SKEW_TOLERANCE_SECS
+        )
+        .is_ok());
+
+        assert_eq!(
+            verify_license(
+                &license,
+                &public_key,
+                expires_at + CLOCK_SKEW_TOLERANCE_SECS + 1
+            ),
+            Err(LicenseError::Expired { expires_at })
+        );
+        assert_eq!(
+            verify_license(
+                &license,
+                &public_key,
+                not_before - CLOCK_SKEW_TOLERANCE_SECS - 1
+            ),
+            Err(LicenseError::NotYetValid { not_before })
+        );
+
+        let status = license_status_from(Some(Err(LicenseError::Expired { expires_at })), NOW);
+        assert_eq!(status.state, LicenseState::Expired);
+        assert_eq!(license_status_from(None, NOW).state, LicenseState::Missing);
+    }
+}
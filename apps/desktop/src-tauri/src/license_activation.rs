@@ -0,0 +1,517 @@
+// src-tauri/src/license_activation.rs
+//
+// Online license activation bound to this machine's machine_id
+// Activation tokens live in the OS keyring; a heartbeat refreshes them and
+// the app keeps working offline for a grace period before the license lapses
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::AppHandle;
+
+use crate::database::db_get_setting;
+use crate::license::{
+    emit_license_status, get_machine_info, store_license, LicenseState, LicenseStatus, MachineInfo,
+};
+use crate::secret_store::{self, SecretKey};
+use crate::settings_cache::set_setting;
+
+/// Settings keys
+pub const LICENSE_SERVER_URL_SETTING: &str = "license_server_url";
+pub const LAST_HEARTBEAT_SETTING: &str = "license_last_heartbeat";
+pub const GRACE_PERIOD_DAYS_SETTING: &str = "license_grace_period_days";
+pub const LICENSE_PROXY_SETTING: &str = "license_proxy_url";
+
+/// Offline grace period when no setting is stored
+pub const DEFAULT_GRACE_PERIOD_DAYS: i64 = 14;
+
+/// How often the heartbeat refreshes the activation (6 hours)
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(20);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ActivationError {
+    SeatLimitReached { seats: Option<u32> },
+    AlreadyActivated,
+    NotActivated,
+    InvalidLicense { message: String },
+    Network { message: String },
+    Server { status: u16, message: String },
+    Other { message: String },
+}
+
+impl std::fmt::Display for ActivationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActivationError::SeatLimitReached { seats: Some(seats) } => {
+                write!(f, "All {} seats for this license are in use", seats)
+            }
+            ActivationError::SeatLimitReached { seats: None } => {
+                write!(f, "All seats for this license are in use")
+            }
+            ActivationError::AlreadyActivated => {
+                write!(f, "License is already activated on this machine")
+            }
+            ActivationError::NotActivated => write!(f, "License is not activated on this machine"),
+            ActivationError::InvalidLicense { message } => {
+                write!(f, "Invalid license: {}", message)
+            }
+            ActivationError::Network { message } => {
+                write!(f, "Could not reach the license server: {}", message)
+            }
+            ActivationError::Server { status, message } => {
+                write!(f, "License server error ({}): {}", status, message)
+            }
+            ActivationError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for ActivationError {
+    fn from(message: String) -> Self {
+        ActivationError::Other { message }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ActivationRequest<'a> {
+    license_key: &'a str,
+    machine: &'a MachineInfo,
+}
+
+#[derive(Debug, Serialize)]
+struct TokenRequest<'a> {
+    activation_token: &'a str,
+    machine_id: &'a str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct ActivationResponse {
+    activation_token: String,
+}
+
+/// Error body returned by the licensing server: {"error": "<code>", "message": "...", "seats": n}
+#[derive(Debug, Default, Deserialize)]
+struct ErrorResponse {
+    #[serde(default)]
+    error: String,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    seats: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ActivationInfo {
+    pub machine_id: String,
+    pub activated_at: i64,
+}
+
+/// Activate a license for this machine and remember the activation token
+#[tauri::command]
+pub async fn activate_license(
+    license_key: String,
+    server_url: String,
+) -> Result<ActivationInfo, ActivationError> {
+    info!("🔑 [LICENSE] Activating license on this machine");
+
+    let machine = get_machine_info()?;
+    let client = http_client()?;
+    let response = post_activation(&client, &server_url, &license_key, &machine).await?;
+
+    store_activation_token(&response.activation_token)?;
+    store_license(license_key)?;
+    set_setting(LICENSE_SERVER_URL_SETTING, server_url.trim_end_matches('/'))?;
+
+    let now = chrono::Utc::now().timestamp();
+    record_heartbeat(now)?;
+
+    info!("✅ [LICENSE] License activated");
+    Ok(ActivationInfo {
+        machine_id: machine.machine_id,
+        activated_at: now,
+    })
+}
+
+/// Release this machine's seat and forget the activation
+#[tauri::command]
+pub async fn deactivate_license() -> Result<(), ActivationError> {
+    info!("🔑 [LICENSE] Deactivating license on this machine");
+
+    let token = get_activation_token()?.ok_or(ActivationError::NotActivated)?;
+    let server_url = server_url()?;
+    let machine_id = get_machine_info()?.machine_id;
+    let client = http_client()?;
+
+    match post_token(&client, &server_url, "deactivate", &token, &machine_id).await {
+        // Server already forgot this activation; clean up locally either way
+        Ok(_) | Err(ActivationError::NotActivated) => {}
+        Err(e) => return Err(e),
+    }
+
+    remove_activation_token()?;
+    set_setting(LAST_HEARTBEAT_SETTING, "")?;
+
+    info!("✅ [LICENSE] License deactivated");
+    Ok(())
+}
+
+/// Refresh the activation periodically for as long as the app runs
+pub fn start_heartbeat(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = heartbeat().await {
+                warn!("⚠️  [LICENSE] Heartbeat failed: {}", e);
+            }
+            emit_license_status(&app);
+            tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+        }
+    });
+}
+
+async fn heartbeat() -> Result<(), ActivationError> {
+    let token = match get_activation_token()? {
+        Some(token) => token,
+        None => return Ok(()), // Never activated; nothing to refresh
+    };
+
+    let server_url = server_url()?;
+    let machine_id = get_machine_info()?.machine_id;
+    let client = http_client()?;
+
+    match post_token(&client, &server_url, "heartbeat", &token, &machine_id).await {
+        Ok(response) => {
+            if !response.activation_token.is_empty() {
+                store_activation_token(&response.activation_token)?;
+            }
+            record_heartbeat(chrono::Utc::now().timestamp())?;
+            info!("✅ [LICENSE] Activation refreshed");
+            Ok(())
+        }
+        Err(ActivationError::NotActivated) => {
+            // Seat was released elsewhere (e.g. from the web dashboard)
+            warn!("⚠️  [LICENSE] Activation revoked by server");
+            remove_activation_token()?;
+            Err(ActivationError::NotActivated)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Downgrade a valid license once heartbeats have been failing for too long
+/// Licenses that were never activated online are left as-is
+pub fn apply_grace_period(status: &mut LicenseStatus) {
+    if status.state != LicenseState::Valid {
+        return;
+    }
+
+    let last_heartbeat = db_get_setting(LAST_HEARTBEAT_SETTING.to_string())
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse::<i64>().ok());
+
+    if let Some(last_heartbeat) = last_heartbeat {
+        let (state, grace_expires_at) =
+            grace_state(last_heartbeat, status.checked_at, grace_period_secs());
+        status.state = state;
+        status.grace_expires_at = grace_expires_at;
+    }
+}
+
+fn grace_state(last_heartbeat: i64, now: i64, grace_secs: i64) -> (LicenseState, Option<i64>) {
+    let offline_for = now - last_heartbeat;
+    // One missed heartbeat is normal (laptop asleep); beyond that we're in grace
+    let stale_after = 2 * HEARTBEAT_INTERVAL.as_secs() as i64;
+    let grace_expires_at = last_heartbeat + grace_secs;
+
+    if offline_for <= stale_after {
+        (LicenseState::Valid, None)
+    } else if now <= grace_expires_at {
+        (LicenseState::GracePeriod, Some(grace_expires_at))
+    } else {
+        (LicenseState::Expired, Some(grace_expires_at))
+    }
+}
+
+fn grace_period_secs() -> i64 {
+    let days = db_get_setting(GRACE_PERIOD_DAYS_SETTING.to_string())
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_GRACE_PERIOD_DAYS);
+    days * 24 * 60 * 60
+}
+
+fn record_heartbeat(timestamp: i64) -> Result<(), String> {
+    set_setting(LAST_HEARTBEAT_SETTING, &timestamp.to_string())
+}
+
+fn server_url() -> Result<String, ActivationError> {
+    db_get_setting(LICENSE_SERVER_URL_SETTING.to_string())?
+        .filter(|url| !url.is_empty())
+        .ok_or(ActivationError::NotActivated)
+}
+
+/// HTTP client honoring HTTP(S)_PROXY from the environment, or an explicit proxy setting
+fn http_client() -> Result<reqwest::Client, ActivationError> {
+    let mut builder = reqwest::Client::builder().timeout(REQUEST_TIMEOUT);
+
+    if let Some(proxy_url) = db_get_setting(LICENSE_PROXY_SETTING.to_string())
+        .ok()
+        .flatten()
+        .filter(|url| !url.trim().is_empty())
+    {
+        let proxy = reqwest::Proxy::all(proxy_url.trim())
+            .map_err(|e| format!("Invalid proxy URL: {}", e))?;
+        builder = builder.proxy(proxy);
+    }
+
+    builder
+        .build()
+        .map_err(|e| ActivationError::from(format!("Failed to create HTTP client: {}", e)))
+}
+
+async fn post_activation(
+    client: &reqwest::Client,
+    server_url: &str,
+    license_key: &str,
+    machine: &MachineInfo,
+) -> Result<ActivationResponse, ActivationError> {
+    let body = ActivationRequest {
+        license_key,
+        machine,
+    };
+    send(client, &endpoint(server_url, "activate"), &body).await
+}
+
+async fn post_token(
+    client: &reqwest::Client,
+    server_url: &str,
+    action: &str,
+    activation_token: &str,
+    machine_id: &str,
+) -> Result<ActivationResponse, ActivationError> {
+    let body = TokenRequest {
+        activation_token,
+        machine_id,
+    };
+    send(client, &endpoint(server_url, action), &body).await
+}
+
+fn endpoint(server_url: &str, action: &str) -> String {
+    format!(
+        "{}/api/licenses/{}",
+        server_url.trim_end_matches('/'),
+        action
+    )
+}
+
+async fn send<T: Serialize>(
+    client: &reqwest::Client,
+    url: &str,
+    body: &T,
+) -> Result<ActivationResponse, ActivationError> {
+    let response =
+        client
+            .post(url)
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| ActivationError::Network {
+                message: e.to_string(),
+            })?;
+
+    let status = response.status();
+    let text = response
+        .text()
+        .await
+        .map_err(|e| ActivationError::Network {
+            message: e.to_string(),
+        })?;
+
+    if status.is_success() {
+        // deactivate answers with an empty body
+        if text.trim().is_empty() {
+            return Ok(ActivationResponse {
+                activation_token: String::new(),
+            });
+        }
+        return serde_json::from_str(&text).map_err(|e| ActivationError::Server {
+            status: status.as_u16(),
+            message: format!("Unexpected response: {}", e),
+        });
+    }
+
+    let body: ErrorResponse = serde_json::from_str(&text).unwrap_or_default();
+    error!(
+        "❌ [LICENSE] Server rejected request ({}): {}",
+        status, body.error
+    );
+    Err(map_error(status.as_u16(), body))
+}
+
+fn map_error(status: u16, body: ErrorResponse) -> ActivationError {
+    let message = body.message.unwrap_or_else(|| body.error.clone());
+
+    match body.error.as_str() {
+        "seat_limit_reached" => ActivationError::SeatLimitReached { seats: body.seats },
+        "already_activated" => ActivationError::AlreadyActivated,
+        "not_activated" | "activation_not_found" => ActivationError::NotActivated,
+        "invalid_license" | "license_expired" | "license_revoked" => {
+            ActivationError::InvalidLicense { message }
+        }
+        _ => ActivationError::Server { status, message },
+    }
+}
+
+fn store_activation_token(token: &str) -> Result<(), String> {
+    secret_store::store(SecretKey::LicenseActivationToken, token).map_err(|e| format!("Failed to store activation token: {}", e))
+}
+
+fn get_activation_token() -> Result<Option<String>, String> {
+    secret_store::get(SecretKey::LicenseActivationToken)
+        .map_err(|e| format!("Failed to read activation token: {}", e))
+}
+
+fn remove_activation_token() -> Result<(), String> {
+    secret_store::remove(SecretKey::LicenseActivationToken)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to remove activation token: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    /// One-shot HTTP server: answers the first request with the given status/body
+    /// and hands the request body back for inspection
+    fn mock_server(status: &'static str, body: &'static str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+
+            // Read headers, then exactly Content-Length bytes of body
+            let body_start = loop {
+                let n = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..n]);
+                if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                    break pos + 4;
+                }
+            };
+            let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+            let content_length: usize = headers
+                .lines()
+                .find_map(|line| line.strip_prefix("content-length:"))
+                .map(|value| value.trim().parse().unwrap())
+                .unwrap_or(0);
+            while request.len() < body_start + content_length {
+                let n = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..n]);
+            }
+            let _ = tx.send(String::from_utf8_lossy(&request[body_start..]).to_string());
+
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        (url, rx)
+    }
+
+    fn machine() -> MachineInfo {
+        MachineInfo {
+            machine_id: "machine-abc".to_string(),
+            platform: "linux".to_string(),
+            hostname: "front-desk".to_string(),
+            app_version: "1.0.0".to_string(),
+            machine_id_source: "machine_id".to_string(),
+            components: Vec::new(),
+        }
+    }
+
+    fn activate(url: &str) -> Result<ActivationResponse, ActivationError> {
+        let client = reqwest::Client::new();
+        tauri::async_runtime::block_on(post_activation(&client, url, "LICENSE-KEY", &machine()))
+    }
+
+    #[test]
+    fn test_activation_against_mock_server() {
+        let (url, request) = mock_server("200 OK", r#"{"activation_token":"act.sig"}"#);
+        assert_eq!(
+            activate(&url),
+            Ok(ActivationResponse {
+                activation_token: "act.sig".to_string()
+            })
+        );
+
+        let sent: serde_json::Value = serde_json::from_str(&request.recv().unwrap()).unwrap();
+        assert_eq!(sent["license_key"], "LICENSE-KEY");
+        assert_eq!(sent["machine"]["machine_id"], "machine-abc");
+
+        let (url, _) = mock_server(
+            "409 Conflict",
+            r#"{"error":"seat_limit_reached","message":"No seats left","seats":3}"#,
+        );
+        assert_eq!(
+            activate(&url),
+            Err(ActivationError::SeatLimitReached { seats: Some(3) })
+        );
+
+        let (url, _) = mock_server("409 Conflict", r#"{"error":"already_activated"}"#);
+        assert_eq!(activate(&url), Err(ActivationError::AlreadyActivated));
+
+        let (url, _) = mock_server("502 Bad Gateway", "<html>proxy error</html>");
+        assert!(matches!(
+            activate(&url),
+            Err(ActivationError::Server { status: 502, .. })
+        ));
+
+        // Nothing listening: reported as a network error so the UI can say "offline"
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", closed.local_addr().unwrap());
+        drop(closed);
+        assert!(matches!(
+            activate(&url),
+            Err(ActivationError::Network { .. })
+        ));
+    }
+
+    #[test]
+    fn test_grace_period_transitions() {
+        let day = 24 * 60 * 60;
+        let grace = 14 * day;
+        let last = 1_767_225_600;
+
+        assert_eq!(
+            grace_state(last, last + 60, grace),
+            (LicenseState::Valid, None)
+        );
+        assert_eq!(
+            grace_state(last, last + 3 * day, grace),
+            (LicenseState::GracePeriod, Some(last + grace))
+        );
+        assert_eq!(
+            grace_state(last, last + grace, grace),
+            (LicenseState::GracePeriod, Some(last + grace))
+        );
+        assert_eq!(
+            grace_state(last, last + grace + 1, grace),
+            (LicenseState::Expired, Some(last + grace))
+        );
+    }
+}
@@ -0,0 +1,654 @@
+// src-tauri/src/local_api.rs
+//
+// Optional HTTP API on 127.0.0.1 for third-party integrations (a dealer's DMS pushing inventory).
+// Off by default (local_api_enabled); the port comes from local_api_port.
+// SECURITY: binds to loopback only, every request (including /health) needs the bearer token
+// from settings, which lives in the SecretStore, and data is always scoped to the user signed
+// in to the app. Nobody signed in, or the app auto-locked, means no data. The signed-in
+// user's role applies as it does in the app: vehicle costs only go to managers and up.
+//
+//   GET  /health          -> {"status": "ok", "version": ...}
+//   GET  /vehicles        -> current user's vehicles
+//   POST /vehicles        -> JSON array of vehicles, created in one transaction
+//   GET  /deals/{id}      -> one of the current user's deals
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use base64::{engine::general_purpose, Engine as _};
+use log::{error, info, warn};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::Read;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::JoinHandle;
+use subtle::ConstantTimeEq;
+use tauri::{AppHandle, Manager};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::app_state::AppState;
+use crate::auto_lock::{self, AutoLockState};
+use crate::database::{
+    bulk_create_vehicles, db_get_setting, get_deal_for_user, get_vehicles_for_user, DbState, Deal,
+    Vehicle,
+};
+use crate::permissions::{check_command, check_permission, redact_vehicles, Permission, Role};
+use crate::secret_store::{self, SecretKey};
+use crate::settings_cache::set_setting;
+
+pub const LOCAL_API_ENABLED_SETTING: &str = "local_api_enabled";
+pub const LOCAL_API_PORT_SETTING: &str = "local_api_port";
+
+pub const DEFAULT_PORT: u16 = 47615;
+/// Largest request body accepted (a few thousand vehicles)
+const MAX_BODY_BYTES: u64 = 5 * 1024 * 1024;
+const MAX_VEHICLES_PER_REQUEST: usize = 1000;
+
+static SERVER: Mutex<Option<LocalApiServer>> = Mutex::new(None);
+
+/// What the API reads and writes. The app uses the database and managed state;
+/// tests swap in an in-memory store
+pub trait ApiData: Send + Sync + 'static {
+    /// User signed in to the app, if any
+    fn current_user(&self) -> Option<String>;
+    /// Role of the signed-in user
+    fn role(&self) -> Role;
+    /// Whether the app is auto-locked
+    fn locked(&self) -> bool;
+    fn vehicles(&self, user_id: &str) -> Result<Vec<Vehicle>, String>;
+    fn create_vehicles(
+        &self,
+        user_id: &str,
+        vehicles: Vec<Vehicle>,
+    ) -> Result<Vec<Vehicle>, String>;
+    fn deal(&self, user_id: &str, id: &str) -> Result<Option<Deal>, String>;
+}
+
+struct AppData {
+    app: AppHandle,
+}
+
+impl ApiData for AppData {
+    fn current_user(&self) -> Option<String> {
+        // Only an authenticated user; the legacy user_id fallback doesn't apply here
+        self.app.state::<AppState>().current_user()
+    }
+
+    fn role(&self) -> Role {
+        self.app.state::<AppState>().role()
+    }
+
+    fn locked(&self) -> bool {
+        // An overdue lock is applied first, as it is for commands
+        auto_lock::lock_if_idle(&self.app);
+        self.app
+            .try_state::<AutoLockState>()
+            .is_some_and(|lock| lock.status().locked)
+    }
+
+    fn vehicles(&self, user_id: &str) -> Result<Vec<Vehicle>, String> {
+        get_vehicles_for_user(
+            self.app
+                .state::<DbState>()
+                .get()
+                .map_err(|e| e.to_string())?,
+            user_id,
+        )
+    }
+
+    fn create_vehicles(
+        &self,
+        user_id: &str,
+        vehicles: Vec<Vehicle>,
+    ) -> Result<Vec<Vehicle>, String> {
+        bulk_create_vehicles(
+            self.app
+                .state::<DbState>()
+                .get()
+                .map_err(|e| e.to_string())?,
+            user_id,
+            vehicles,
+        )
+    }
+
+    fn deal(&self, user_id: &str, id: &str) -> Result<Option<Deal>, String> {
+        get_deal_for_user(
+            self.app
+                .state::<DbState>()
+                .get()
+                .map_err(|e| e.to_string())?,
+            id,
+            user_id,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LocalApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Port the server is listening on; None when it's stopped
+    pub running_port: Option<u16>,
+    /// Shown in settings so the dealer can paste it into their DMS
+    pub token: Option<String>,
+}
+
+/// A running server; dropping it stops the listener and waits for the worker thread
+pub struct LocalApiServer {
+    server: Arc<Server>,
+    port: u16,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl LocalApiServer {
+    /// Listen on 127.0.0.1:port (0 picks a free port)
+    pub fn start(port: u16, token: String, data: Arc<dyn ApiData>) -> Result<Self, String> {
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let server = Arc::new(
+            Server::http(addr)
+                .map_err(|e| format!("Failed to start local API on {}: {}", addr, e))?,
+        );
+        let port = server
+            .server_addr()
+            .to_ip()
+            .map(|addr| addr.port())
+            .unwrap_or(port);
+
+        let worker = {
+            let server = server.clone();
+            std::thread::Builder::new()
+                .name("local-api".to_string())
+                .spawn(move || {
+                    // Ends when stop() unblocks the listener
+                    for request in server.incoming_requests() {
+                        handle_request(request, &token, data.as_ref());
+                    }
+                })
+                .map_err(|e| format!("Failed to start local API thread: {}", e))?
+        };
+
+        info!("🔌 [API] Local API listening on 127.0.0.1:{}", port);
+        Ok(Self {
+            server,
+            port,
+            worker: Some(worker),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+}
+
+impl Drop for LocalApiServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+        info!("🔌 [API] Local API stopped");
+    }
+}
+
+/// Start the server if it's turned on. Call once the database is initialized
+pub fn start_from_settings(app: &AppHandle) {
+    if !enabled() {
+        return;
+    }
+    if let Err(e) = restart(app) {
+        error!("❌ [API] {}", e);
+    }
+}
+
+/// Stop the server if it's running (app shutdown)
+pub fn stop() {
+    let server = SERVER.lock().unwrap_or_else(PoisonError::into_inner).take();
+    drop(server);
+}
+
+#[tauri::command]
+pub fn get_local_api_settings() -> Result<LocalApiSettings, String> {
+    Ok(LocalApiSettings {
+        enabled: enabled(),
+        port: port(),
+        running_port: SERVER
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map(LocalApiServer::port),
+        token: secret_store::get(SecretKey::LocalApiToken)?,
+    })
+}
+
+/// Turn the API on or off and change its port; restarts the server to match
+#[tauri::command]
+pub fn set_local_api_settings(
+    app: AppHandle,
+    enabled: bool,
+    port: u16,
+) -> Result<LocalApiSettings, String> {
+    if port < 1024 {
+        return Err(format!("Port must be 1024 or higher (got {})", port));
+    }
+    set_setting(LOCAL_API_ENABLED_SETTING, &enabled.to_string())?;
+    set_setting(LOCAL_API_PORT_SETTING, &port.to_string())?;
+
+    if enabled {
+        restart(&app)?;
+    } else {
+        stop();
+    }
+    get_local_api_settings()
+}
+
+/// Replace the token; integrations using the old one stop working immediately
+#[tauri::command]
+pub fn regenerate_local_api_token(app: AppHandle) -> Result<LocalApiSettings, String> {
+    secret_store::store(SecretKey::LocalApiToken, &generate_token())?;
+    info!("🔑 [API] Local API token regenerated");
+    if enabled() {
+        restart(&app)?;
+    }
+    get_local_api_settings()
+}
+
+fn restart(app: &AppHandle) -> Result<(), String> {
+    let token = match secret_store::get(SecretKey::LocalApiToken)? {
+        Some(token) => token,
+        None => {
+            let token = generate_token();
+            secret_store::store(SecretKey::LocalApiToken, &token)?;
+            token
+        }
+    };
+
+    let mut server = SERVER.lock().unwrap_or_else(PoisonError::into_inner);
+    // The old listener has to release the port before the new one binds
+    drop(server.take());
+    *server = Some(LocalApiServer::start(
+        port(),
+        token,
+        Arc::new(AppData { app: app.clone() }),
+    )?);
+    Ok(())
+}
+
+fn enabled() -> bool {
+    db_get_setting(LOCAL_API_ENABLED_SETTING.to_string())
+        .ok()
+        .flatten()
+        .is_some_and(|value| value == "true")
+}
+
+fn port() -> u16 {
+    db_get_setting(LOCAL_API_PORT_SETTING.to_string())
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_PORT)
+}
+
+fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn handle_request(mut request: Request, token: &str, data: &dyn ApiData) {
+    let (status, body) = respond(&mut request, token, data);
+    let mut response = Response::from_string(body.to_string())
+        .with_status_code(status)
+        .with_header(header("Content-Type", "application/json"));
+    if status == 401 {
+        response.add_header(header("WWW-Authenticate", "Bearer"));
+    }
+    if let Err(e) = request.respond(response) {
+        warn!("⚠️  [API] Failed to send response: {}", e);
+    }
+}
+
+fn respond(request: &mut Request, token: &str, data: &dyn ApiData) -> (u16, Value) {
+    if !authorized(request, token) {
+        warn!("⚠️  [API] Rejected request without a valid token");
+        return error_body(401, "Missing or invalid bearer token");
+    }
+
+    let method = request.method().clone();
+    let path = request.url().split('?').next().unwrap_or("").to_string();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    if segments == ["health"] {
+        return match method {
+            Method::Get => (
+                200,
+                json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }),
+            ),
+            _ => error_body(405, "Method not allowed"),
+        };
+    }
+
+    let known = matches!(segments.as_slice(), ["vehicles"] | ["deals", _]);
+    if !known {
+        return error_body(404, "Not found");
+    }
+    if data.locked() {
+        return error_body(423, "The app is locked");
+    }
+    let Some(user_id) = data.current_user() else {
+        return error_body(403, "No user is signed in to the app");
+    };
+    let role = data.role();
+
+    let result = match (&method, segments.as_slice()) {
+        (Method::Get, ["vehicles"]) => data
+            .vehicles(&user_id)
+            .map(|vehicles| (200, json!(redact_vehicles(role, vehicles)))),
+        (Method::Post, ["vehicles"]) => {
+            if let Err(e) = check_command(role, "db_bulk_create_vehicles") {
+                return error_body(403, &e.to_string());
+            }
+            let vehicles: Vec<Vehicle> = match read_json(request) {
+                Ok(vehicles) => vehicles,
+                Err(response) => return response,
+            };
+            if vehicles.len() > MAX_VEHICLES_PER_REQUEST {
+                return error_body(
+                    413,
+                    &format!("At most {} vehicles per request", MAX_VEHICLES_PER_REQUEST),
+                );
+            }
+            // Only roles that see costs may set one, as in db_create_vehicle
+            if vehicles.iter().any(|vehicle| vehicle.cost.is_some()) {
+                if let Err(e) = check_permission(role, Permission::ViewCosts) {
+                    return error_body(403, &e.to_string());
+                }
+            }
+            // Duplicate VINs and other validation failures are the caller's problem
+            match data.create_vehicles(&user_id, vehicles) {
+                Ok(created) => Ok((201, json!(redact_vehicles(role, created)))),
+                Err(e) => return error_body(400, &e),
+            }
+        }
+        (Method::Get, ["deals", id]) => data.deal(&user_id, id).map(|deal| match deal {
+            Some(deal) => (200, json!(deal)),
+            None => error_body(404, "Deal not found"),
+        }),
+        _ => return error_body(405, "Method not allowed"),
+    };
+
+    result.unwrap_or_else(|e| {
+        error!("❌ [API] {} {} failed: {}", method, path, e);
+        error_body(500, "Internal error")
+    })
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Authorization"))
+        .and_then(|header| header.value.as_str().strip_prefix("Bearer "))
+        .is_some_and(|given| bool::from(given.trim().as_bytes().ct_eq(token.as_bytes())))
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(request: &mut Request) -> Result<T, (u16, Value)> {
+    if request
+        .body_length()
+        .is_some_and(|len| len as u64 > MAX_BODY_BYTES)
+    {
+        return Err(error_body(413, "Request body too large"));
+    }
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_BODY_BYTES + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| error_body(400, &format!("Failed to read request body: {}", e)))?;
+    if body.len() as u64 > MAX_BODY_BYTES {
+        return Err(error_body(413, "Request body too large"));
+    }
+    serde_json::from_slice(&body).map_err(|e| error_body(400, &format!("Invalid JSON: {}", e)))
+}
+
+fn error_body(status: u16, message: &str) -> (u16, Value) {
+    (status, json!({ "error": message }))
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("static header is valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    const TOKEN: &str = "test-token-123";
+
+    #[derive(Default)]
+    struct MemoryData {
+        user: Mutex<Option<String>>,
+        /// None: roles aren't set up, so everyone is an admin
+        role: Mutex<Option<Role>>,
+        locked: Mutex<bool>,
+        vehicles: Mutex<HashMap<String, Vec<Vehicle>>>,
+    }
+
+    impl ApiData for MemoryData {
+        fn current_user(&self) -> Option<String> {
+            self.user.lock().unwrap().clone()
+        }
+
+        fn role(&self) -> Role {
+            self.role.lock().unwrap().unwrap_or(Role::Admin)
+        }
+
+        fn locked(&self) -> bool {
+            *self.locked.lock().unwrap()
+        }
+
+        fn vehicles(&self, user_id: &str) -> Result<Vec<Vehicle>, String> {
+            Ok(self
+                .vehicles
+                .lock()
+                .unwrap()
+                .get(user_id)
+                .cloned()
+                .unwrap_or_default())
+        }
+
+        fn create_vehicles(
+            &self,
+            user_id: &str,
+            vehicles: Vec<Vehicle>,
+        ) -> Result<Vec<Vehicle>, String> {
+            let mut all = self.vehicles.lock().unwrap();
+            let owned = all.entry(user_id.to_string()).or_default();
+            if let Some(duplicate) = vehicles
+                .iter()
+                .find(|vehicle| owned.iter().any(|existing| existing.vin == vehicle.vin))
+            {
+                return Err(format!("Vehicle with VIN {} already exists", duplicate.vin));
+            }
+            owned.extend(vehicles.iter().cloned());
+            Ok(vehicles)
+        }
+
+        fn deal(&self, _user_id: &str, _id: &str) -> Result<Option<Deal>, String> {
+            Ok(None)
+        }
+    }
+
+    fn vehicle(vin: &str) -> Value {
+        json!({
+            "id": format!("veh-{}", vin), "vin": vin, "stock_number": null, "year": 2021,
+            "make": "Honda", "model": "Civic", "trim": null, "body": null, "doors": 4,
+            "transmission": null, "engine": null, "cylinders": null, "title_number": null,
+            "mileage": 12000, "color": "Blue", "price": 18500.0, "cost": null,
+            "status": "available", "description": null, "images": null,
+            "created_at": 1_700_000_000_000i64, "updated_at": 1_700_000_000_000i64, "synced_at": null
+        })
+    }
+
+    fn start(data: Arc<MemoryData>) -> (LocalApiServer, String) {
+        let server = LocalApiServer::start(0, TOKEN.to_string(), data).unwrap();
+        let base = format!("http://127.0.0.1:{}", server.port());
+        (server, base)
+    }
+
+    fn send(request: reqwest::RequestBuilder) -> (u16, Value) {
+        tauri::async_runtime::block_on(async move {
+            let response = request.send().await.unwrap();
+            let status = response.status().as_u16();
+            (status, response.json().await.unwrap_or(Value::Null))
+        })
+    }
+
+    #[test]
+    fn test_every_request_needs_the_token() {
+        let (server, base) = start(Arc::new(MemoryData::default()));
+        let client = reqwest::Client::new();
+
+        for path in ["/health", "/vehicles", "/deals/deal-1", "/nope"] {
+            let (status, body) = send(client.get(format!("{}{}", base, path)));
+            assert_eq!(status, 401, "{}", path);
+            assert!(body["error"].is_string());
+
+            let (status, _) = send(client.get(format!("{}{}", base, path)).bearer_auth("wrong"));
+            assert_eq!(status, 401, "{}", path);
+        }
+        let (status, _) = send(
+            client
+                .get(format!("{}/health", base))
+                .header("Authorization", TOKEN),
+        );
+        assert_eq!(status, 401);
+
+        let (status, body) = send(client.get(format!("{}/health", base)).bearer_auth(TOKEN));
+        assert_eq!(status, 200);
+        assert_eq!(body["status"], "ok");
+
+        drop(server);
+        // Stopped cleanly: nothing listens on the port any more
+        let stopped = tauri::async_runtime::block_on(
+            client
+                .get(format!("{}/health", base))
+                .bearer_auth(TOKEN)
+                .send(),
+        );
+        assert!(stopped.is_err());
+    }
+
+    #[test]
+    fn test_vehicle_round_trip_for_current_user() {
+        let data = Arc::new(MemoryData::default());
+        let (_server, base) = start(data.clone());
+        let client = reqwest::Client::new();
+        let vehicles_url = format!("{}/vehicles", base);
+
+        // Signed out: authenticated requests still get no data
+        let (status, _) = send(client.get(&vehicles_url).bearer_auth(TOKEN));
+        assert_eq!(status, 403);
+
+        *data.user.lock().unwrap() = Some("user-a".to_string());
+        let batch = json!([vehicle("1HGCM82633A000001"), vehicle("1HGCM82633A000002")]);
+        let (status, created) = send(client.post(&vehicles_url).bearer_auth(TOKEN).json(&batch));
+        assert_eq!(status, 201);
+        assert_eq!(created.as_array().unwrap().len(), 2);
+
+        let (status, listed) = send(client.get(&vehicles_url).bearer_auth(TOKEN));
+        assert_eq!(status, 200);
+        let vins: Vec<&str> = listed
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|vehicle| vehicle["vin"].as_str().unwrap())
+            .collect();
+        assert_eq!(vins, ["1HGCM82633A000001", "1HGCM82633A000002"]);
+
+        // Duplicate VIN and malformed bodies are rejected
+        let (status, body) = send(
+            client
+                .post(&vehicles_url)
+                .bearer_auth(TOKEN)
+                .json(&json!([vehicle("1HGCM82633A000001")])),
+        );
+        assert_eq!(status, 400);
+        assert!(body["error"].as_str().unwrap().contains("already exists"));
+        let (status, _) = send(
+            client
+                .post(&vehicles_url)
+                .bearer_auth(TOKEN)
+                .body("{not json"),
+        );
+        assert_eq!(status, 400);
+
+        // Another user sees only their own inventory
+        *data.user.lock().unwrap() = Some("user-b".to_string());
+        let (_, listed) = send(client.get(&vehicles_url).bearer_auth(TOKEN));
+        assert_eq!(listed, json!([]));
+
+        let (status, _) = send(
+            client
+                .get(format!("{}/deals/deal-1", base))
+                .bearer_auth(TOKEN),
+        );
+        assert_eq!(status, 404);
+        let (status, _) = send(client.delete(&vehicles_url).bearer_auth(TOKEN));
+        assert_eq!(status, 405);
+    }
+
+    #[test]
+    fn test_role_and_lock_apply_to_the_api() {
+        let data = Arc::new(MemoryData::default());
+        *data.user.lock().unwrap() = Some("user-a".to_string());
+        let (_server, base) = start(data.clone());
+        let client = reqwest::Client::new();
+        let vehicles_url = format!("{}/vehicles", base);
+
+        let mut costed = vehicle("1HGCM82633A000003");
+        costed["cost"] = json!(14_000.0);
+        let (status, created) = send(
+            client
+                .post(&vehicles_url)
+                .bearer_auth(TOKEN)
+                .json(&json!([costed])),
+        );
+        assert_eq!(status, 201);
+        assert_eq!(created[0]["cost"], 14_000.0);
+
+        // Sales: cost hidden on read and refused on write
+        *data.role.lock().unwrap() = Some(Role::Sales);
+        let (_, listed) = send(client.get(&vehicles_url).bearer_auth(TOKEN));
+        assert_eq!(listed[0]["cost"], Value::Null);
+        let mut costed = vehicle("1HGCM82633A000004");
+        costed["cost"] = json!(9_000.0);
+        let (status, _) = send(
+            client
+                .post(&vehicles_url)
+                .bearer_auth(TOKEN)
+                .json(&json!([costed])),
+        );
+        assert_eq!(status, 403);
+
+        // Readonly can't write at all
+        *data.role.lock().unwrap() = Some(Role::Readonly);
+        let (status, _) = send(
+            client
+                .post(&vehicles_url)
+                .bearer_auth(TOKEN)
+                .json(&json!([vehicle("1HGCM82633A000005")])),
+        );
+        assert_eq!(status, 403);
+        assert_eq!(data.vehicles("user-a").unwrap().len(), 1);
+
+        // Locked: nothing but /health answers
+        *data.role.lock().unwrap() = Some(Role::Manager);
+        *data.locked.lock().unwrap() = true;
+        let (status, body) = send(client.get(&vehicles_url).bearer_auth(TOKEN));
+        assert_eq!(status, 423);
+        assert_eq!(body["error"], "The app is locked");
+        let (status, _) = send(client.get(format!("{}/health", base)).bearer_auth(TOKEN));
+        assert_eq!(status, 200);
+    }
+}
@@ -0,0 +1,545 @@
+// src-tauri/src/logging.rs
+//
+// File logging for support: writes log lines to dealer-software.log in the logs dir
+// Rotates by size and keeps a few archives; writes happen on a background thread
+// Secret values (tokens, keys, paths) go through redact() before they reach a log line
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use once_cell::sync::OnceCell;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use crate::archive::{create_zip_archive, ArchiveSummary};
+use crate::database::db_get_setting;
+use crate::settings_cache::set_setting;
+use crate::storage::get_logs_path;
+
+pub const LOG_FILE_NAME: &str = "dealer-software.log";
+
+/// Rotate the active log file once it reaches this size (5 MB)
+pub const DEFAULT_MAX_LOG_BYTES: u64 = 5 * 1024 * 1024;
+
+/// Number of rotated archives kept (dealer-software.log.1 .. .N)
+pub const DEFAULT_MAX_ARCHIVES: usize = 5;
+
+/// Settings key holding the runtime log level ("error" .. "trace")
+pub const LOG_LEVEL_SETTING: &str = "log_level";
+
+/// Name of the background writer thread
+pub const LOG_WRITER_THREAD: &str = "log-writer";
+
+/// How long buffered lines may sit before the writer flushes them
+const FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+
+static LOGGER: OnceCell<FileLogger> = OnceCell::new();
+
+#[derive(Debug, Clone, Copy)]
+pub struct LogConfig {
+    pub max_bytes: u64,
+    pub max_archives: usize,
+    pub level: LevelFilter,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_LOG_BYTES,
+            max_archives: DEFAULT_MAX_ARCHIVES,
+            level: if cfg!(debug_assertions) {
+                LevelFilter::Debug
+            } else {
+                LevelFilter::Info
+            },
+        }
+    }
+}
+
+impl LogConfig {
+    /// Defaults, overridable with DEALER_LOG_MAX_MB / DEALER_LOG_ARCHIVES / DEALER_LOG_LEVEL
+    pub fn from_env() -> Self {
+        let mut config = Self::default();
+
+        if let Some(mb) = env_var::<u64>("DEALER_LOG_MAX_MB").filter(|mb| *mb > 0) {
+            config.max_bytes = mb.saturating_mul(1024 * 1024);
+        }
+        if let Some(archives) = env_var::<usize>("DEALER_LOG_ARCHIVES") {
+            config.max_archives = archives;
+        }
+        if let Some(level) = env_var::<LevelFilter>("DEALER_LOG_LEVEL") {
+            config.level = level;
+        }
+
+        config
+    }
+}
+
+fn env_var<T: FromStr>(name: &str) -> Option<T> {
+    std::env::var(name).ok()?.trim().parse().ok()
+}
+
+enum LogMessage {
+    Line(String),
+    Flush(mpsc::SyncSender<()>),
+}
+
+struct FileLogger {
+    dir: PathBuf,
+    // Sender::send never blocks, so logging from a command can't stall on disk I/O
+    sender: Sender<LogMessage>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        // Follows log::max_level() so set_log_level takes effect without a restart
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = format_line(
+            &chrono::Utc::now()
+                .format("%Y-%m-%dT%H:%M:%S%.3fZ")
+                .to_string(),
+            record.level(),
+            record.target(),
+            &record.args().to_string(),
+        );
+
+        if cfg!(debug_assertions) {
+            eprintln!("{}", line);
+        }
+        let _ = self.sender.send(LogMessage::Line(line));
+    }
+
+    fn flush(&self) {
+        let (ack_tx, ack_rx) = mpsc::sync_channel(1);
+        if self.sender.send(LogMessage::Flush(ack_tx)).is_ok() {
+            let _ = ack_rx.recv_timeout(Duration::from_secs(2));
+        }
+    }
+}
+
+/// Install the file logger. Call once at the top of main()
+pub fn init(config: LogConfig) -> Result<PathBuf, String> {
+    let dir = PathBuf::from(get_logs_path()?);
+    let writer = RotatingWriter::open(&dir, config.max_bytes, config.max_archives)
+        .map_err(|e| format!("Failed to open log file: {}", e))?;
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::Builder::new()
+        .name(LOG_WRITER_THREAD.to_string())
+        .spawn(move || run_writer(writer, receiver))
+        .map_err(|e| format!("Failed to start log writer: {}", e))?;
+
+    let logger = LOGGER
+        .try_insert(FileLogger {
+            dir: dir.clone(),
+            sender,
+        })
+        .map_err(|_| "Logger already initialized".to_string())?;
+
+    log::set_logger(logger).map_err(|e| format!("Failed to install logger: {}", e))?;
+    log::set_max_level(config.level);
+
+    Ok(dir.join(LOG_FILE_NAME))
+}
+
+/// Apply the log level saved in settings. Call once the database is initialized
+/// DEALER_LOG_LEVEL still wins so support can raise verbosity without touching the DB
+pub fn apply_saved_log_level() {
+    if env_var::<LevelFilter>("DEALER_LOG_LEVEL").is_some() {
+        return;
+    }
+
+    let saved = db_get_setting(LOG_LEVEL_SETTING.to_string()).ok().flatten();
+    if let Some(level) = saved.and_then(|value| LevelFilter::from_str(value.trim()).ok()) {
+        log::set_max_level(level);
+    }
+}
+
+/// Elide a sensitive value for logging: at most the first and last 2 chars survive
+/// Short values are hidden entirely since 4 visible chars would be most of the secret
+pub fn redact(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() <= 8 {
+        return "***".to_string();
+    }
+
+    let head: String = chars[..2].iter().collect();
+    let tail: String = chars[chars.len() - 2..].iter().collect();
+    format!("{}…{}", head, tail)
+}
+
+/// Flush buffered log lines to disk (used before reading or exporting logs)
+pub fn flush() {
+    if let Some(logger) = LOGGER.get() {
+        logger.flush();
+    }
+}
+
+fn run_writer(mut writer: RotatingWriter, receiver: Receiver<LogMessage>) {
+    loop {
+        match receiver.recv_timeout(FLUSH_INTERVAL) {
+            Ok(LogMessage::Line(line)) => {
+                if let Err(e) = writer.write_line(&line) {
+                    eprintln!("Failed to write log line: {}", e);
+                }
+            }
+            Ok(LogMessage::Flush(ack)) => {
+                let _ = writer.flush();
+                let _ = ack.send(());
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                let _ = writer.flush();
+            }
+            Err(RecvTimeoutError::Disconnected) => {
+                let _ = writer.flush();
+                return;
+            }
+        }
+    }
+}
+
+fn format_line(timestamp: &str, level: Level, target: &str, message: &str) -> String {
+    // Keep every entry on one line so tailing and level filtering stay line-based
+    let message = message.replace('\n', "\\n");
+    format!("{} {:<5} [{}] {}", timestamp, level, target, message)
+}
+
+/// Level of a formatted log line (the second whitespace-separated field)
+fn line_level(line: &str) -> Option<Level> {
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn archive_path(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("{}.{}", LOG_FILE_NAME, index))
+}
+
+/// Active log file followed by archives, newest first
+pub(crate) fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = vec![dir.join(LOG_FILE_NAME)];
+    let mut index = 1;
+    loop {
+        let archive = archive_path(dir, index);
+        if !archive.is_file() {
+            break;
+        }
+        files.push(archive);
+        index += 1;
+    }
+    files.retain(|path| path.is_file());
+    files
+}
+
+struct RotatingWriter {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_archives: usize,
+    file: BufWriter<File>,
+    size: u64,
+}
+
+impl RotatingWriter {
+    fn open(dir: &Path, max_bytes: u64, max_archives: usize) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(LOG_FILE_NAME))?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            max_archives,
+            file: BufWriter::new(file),
+            size,
+        })
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let len = line.len() as u64 + 1;
+
+        // Rotate before a line would push the file past the limit
+        // (an oversized line still gets written to a fresh file)
+        if self.size > 0 && self.size + len > self.max_bytes {
+            self.rotate()?;
+        }
+
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+
+        let active = self.dir.join(LOG_FILE_NAME);
+        if self.max_archives == 0 {
+            fs::remove_file(&active)?;
+        } else {
+            let _ = fs::remove_file(archive_path(&self.dir, self.max_archives));
+            for index in (1..self.max_archives).rev() {
+                let from = archive_path(&self.dir, index);
+                if from.exists() {
+                    fs::rename(&from, archive_path(&self.dir, index + 1))?;
+                }
+            }
+            fs::rename(&active, archive_path(&self.dir, 1))?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&active)?;
+        self.file = BufWriter::new(file);
+        self.size = 0;
+        Ok(())
+    }
+}
+
+/// Last `lines` log lines at or above the minimum level, oldest first
+fn tail_lines(dir: &Path, lines: usize, min_level: Option<Level>) -> Result<Vec<String>, String> {
+    let mut collected: Vec<String> = Vec::new();
+
+    for path in log_files(dir) {
+        if collected.len() >= lines {
+            break;
+        }
+
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        for line in content.lines().rev() {
+            if collected.len() >= lines {
+                break;
+            }
+            let matches = match min_level {
+                Some(min) => line_level(line).map(|level| level <= min).unwrap_or(false),
+                None => true,
+            };
+            if matches {
+                collected.push(line.to_string());
+            }
+        }
+    }
+
+    collected.reverse();
+    Ok(collected)
+}
+
+pub(crate) fn logs_dir() -> Result<PathBuf, String> {
+    match LOGGER.get() {
+        Some(logger) => Ok(logger.dir.clone()),
+        None => get_logs_path().map(PathBuf::from),
+    }
+}
+
+/// Get the most recent log lines, optionally only those at or above a level
+/// level_filter: "error", "warn", "info", "debug" or "trace"
+#[tauri::command]
+pub fn get_recent_logs(lines: usize, level_filter: Option<String>) -> Result<Vec<String>, String> {
+    let min_level = match level_filter.as_deref() {
+        Some(filter) => {
+            Some(Level::from_str(filter).map_err(|_| format!("Unknown log level: {}", filter))?)
+        }
+        None => None,
+    };
+
+    flush();
+    tail_lines(&logs_dir()?, lines, min_level)
+}
+
+/// Current runtime log level
+#[tauri::command]
+pub fn get_log_level() -> String {
+    log::max_level().to_string().to_lowercase()
+}
+
+/// Change the runtime log level and persist it for the next launch
+/// level: "off", "error", "warn", "info", "debug" or "trace"
+#[tauri::command]
+pub fn set_log_level(level: String) -> Result<(), String> {
+    let filter =
+        LevelFilter::from_str(level.trim()).map_err(|_| format!("Unknown log level: {}", level))?;
+
+    set_setting(LOG_LEVEL_SETTING, &filter.to_string().to_lowercase())?;
+    log::set_max_level(filter);
+    log::info!("📝 Log level set to {}", filter);
+    Ok(())
+}
+
+/// Bundle the active log file and all archives into a zip for support
+#[tauri::command]
+pub fn export_logs_zip(output_path: String) -> Result<ArchiveSummary, String> {
+    flush();
+
+    let files: Vec<String> = log_files(&logs_dir()?)
+        .into_iter()
+        .map(|path| path.to_string_lossy().to_string())
+        .collect();
+
+    if files.is_empty() {
+        return Err("No log files to export".to_string());
+    }
+
+    create_zip_archive(files, output_path, None)
+}
+
+/// Test-only logger that keeps every emitted line in memory
+/// Lets tests assert what would have reached the log file
+#[cfg(test)]
+pub(crate) mod capture {
+    use super::*;
+    use std::sync::{Mutex, Once};
+
+    static LINES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+    static INSTALL: Once = Once::new();
+    static LOGGER: CaptureLogger = CaptureLogger;
+
+    struct CaptureLogger;
+
+    impl Log for CaptureLogger {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &Record) {
+            let line = format_line(
+                "test",
+                record.level(),
+                record.target(),
+                &record.args().to_string(),
+            );
+            LINES.lock().unwrap().push(line);
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Install the capture logger at trace level (idempotent across tests)
+    pub(crate) fn install() {
+        INSTALL.call_once(|| {
+            log::set_logger(&LOGGER).expect("logger already installed");
+            log::set_max_level(LevelFilter::Trace);
+        });
+    }
+
+    pub(crate) fn lines() -> Vec<String> {
+        LINES.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn line(level: Level, n: usize) -> String {
+        format_line(
+            "2026-01-01T00:00:00.000Z",
+            level,
+            "test",
+            &format!("entry {}", n),
+        )
+    }
+
+    #[test]
+    fn test_redact_shows_at_most_two_chars_each_side() {
+        assert_eq!(redact(""), "***");
+        assert_eq!(redact("abcdefgh"), "***");
+        assert_eq!(redact("abcdefghi"), "ab…hi");
+        assert_eq!(redact("eyJhbGciOiJIUzI1NiJ9.secret"), "ey…et");
+        assert_eq!(redact("ßßßßßßßßßé"), "ßß…ßé");
+    }
+
+    #[test]
+    fn test_rotation_boundaries() {
+        let dir = temp_dir("logging", "rotation");
+        let entry = line(Level::Info, 0);
+        let entry_bytes = entry.len() as u64 + 1;
+
+        // Exactly two lines fit; the third rotates
+        let mut writer = RotatingWriter::open(&dir, entry_bytes * 2, 2).unwrap();
+        writer.write_line(&entry).unwrap();
+        writer.write_line(&entry).unwrap();
+        writer.flush().unwrap();
+        assert!(!archive_path(&dir, 1).exists());
+        assert_eq!(
+            fs::metadata(dir.join(LOG_FILE_NAME)).unwrap().len(),
+            entry_bytes * 2
+        );
+
+        writer.write_line(&entry).unwrap();
+        writer.flush().unwrap();
+        assert!(archive_path(&dir, 1).exists());
+        assert_eq!(
+            fs::metadata(dir.join(LOG_FILE_NAME)).unwrap().len(),
+            entry_bytes
+        );
+
+        // Older archives shift up and the oldest beyond max_archives is dropped
+        for _ in 0..6 {
+            writer.write_line(&entry).unwrap();
+        }
+        writer.flush().unwrap();
+        assert!(archive_path(&dir, 2).exists());
+        assert!(!archive_path(&dir, 3).exists());
+        assert_eq!(log_files(&dir).len(), 3);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_tail_spans_archives_and_filters_level() {
+        let dir = temp_dir("logging", "tail");
+        let entry_bytes = line(Level::Info, 0).len() as u64 + 1;
+        let mut writer = RotatingWriter::open(&dir, entry_bytes * 3, 3).unwrap();
+
+        for n in 0..7 {
+            let level = if n % 3 == 0 {
+                Level::Error
+            } else {
+                Level::Info
+            };
+            writer.write_line(&line(level, n)).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let last = tail_lines(&dir, 4, None).unwrap();
+        assert_eq!(
+            last,
+            (3..7)
+                .map(|n| line(
+                    if n % 3 == 0 {
+                        Level::Error
+                    } else {
+                        Level::Info
+                    },
+                    n
+                ))
+                .collect::<Vec<_>>()
+        );
+
+        let errors = tail_lines(&dir, 10, Some(Level::Warn)).unwrap();
+        assert_eq!(
+            errors,
+            vec![
+                line(Level::Error, 0),
+                line(Level::Error, 3),
+                line(Level::Error, 6)
+            ]
+        );
+
+        assert!(tail_lines(&dir, 0, None).unwrap().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -6,26 +6,248 @@ mod file_permissions;
 mod file_operations;
 mod storage;
 mod license;
+mod license_activation;
+mod trial;
 mod database;
+mod secret_store;
+mod app_state;
 mod session;
 mod dealership_auth;
 mod docs_config;
 mod aws_config;
 mod s3_service;
+mod archive;
+mod fs_watcher;
+mod thumbnails;
+mod disk_space;
+mod logging;
+mod crash;
+mod diagnostics;
+mod telemetry;
+mod documents_migration;
+mod storage_usage;
+mod key_rotation;
+mod signing;
+mod deep_link;
+mod updater;
+mod shutdown;
+mod local_api;
+mod webhooks;
+mod quickbooks;
+mod inventory_feed;
+mod data_export;
+mod client_duplicates;
+mod vcard;
+mod tax;
+mod finance;
+mod pdf_forms;
+mod document_templates;
+mod deal_validation;
+mod deal_status;
+mod vehicle_images;
+mod recent_items;
+mod global_search;
+mod document_verification;
+mod document_versions;
+mod tasks;
+mod lenders;
+mod deal_fees;
+mod reporting;
+mod data_events;
+mod document_trash;
+mod communications;
+mod dealer_profile;
+mod address_lookup;
+mod database_overview;
+mod csv_import;
+mod db_busy;
+mod db_timeout;
+mod vehicle_costs;
+mod scrubbed_export;
+mod stock_numbers;
+mod deal_cobuyers;
+mod app_windows;
+mod money;
+mod vehicle_holds;
+mod daily_activity;
+mod audit_log;
+mod retention;
+mod kiosk;
+mod print_jobs;
+mod support_bundle;
+mod deal_cloning;
+mod sensitive_fields;
+mod db_batch;
+mod vehicle_history;
+mod settings_cache;
+mod sql_console;
+mod startup_migrations;
+mod datetime;
+mod permissions;
+mod validation_rules;
+mod image_import;
+mod auto_lock;
+mod title_export;
+mod valuation;
+mod temp_tags;
+mod sample_data;
+mod field_projection;
+mod contact_normalization;
+mod read_cache;
+mod doc_paths;
+mod templating;
+mod stats_cache;
+mod idempotency;
+#[cfg(test)]
+mod test_support;
 
-use encryption::{decrypt_data, encrypt_data, generate_encryption_key};
+use archive::{create_zip_archive, export_deal_archive, extract_zip_archive};
+use diagnostics::{export_diagnostics, run_diagnostics};
+use disk_space::get_disk_space;
+use documents_migration::migrate_documents_root;
+use encryption::{
+    decrypt_data, decrypt_file, derive_key_from_password, encrypt_data, encrypt_file,
+    generate_encryption_key, is_file_encrypted, verify_password,
+};
 use file_permissions::{check_file_permissions, get_storage_file_path, set_file_permissions};
+use key_rotation::rotate_encryption_key;
+use signing::{hmac_sign_file, hmac_verify_file, verify_signature_file, write_signature_file};
+use fs_watcher::{unwatch_directory, watch_directory};
 use file_operations::{
-    batch_print_pdfs, cleanup_temp_print_dir, create_temp_print_dir, get_documents_dir,
-    get_downloads_dir, join_path, open_file_with_default_app, open_url, print_pdf,
-    read_binary_file, remove_file, reveal_in_explorer, write_file_to_path,
+    batch_print_pdfs, cleanup_stale_print_dirs, cleanup_temp_print_dir, create_temp_print_dir,
+    get_documents_dir, get_downloads_dir, join_path, open_file_with_default_app, open_url,
+    print_pdf, read_binary_file, remove_file, reveal_in_explorer, write_file_to_path,
 };
 use license::{
-    get_app_version, get_hostname, get_machine_id, get_machine_info, get_platform,
-    get_stored_license, remove_stored_license, store_license,
+    get_app_version, get_hostname, get_license_status, get_machine_id, get_machine_info,
+    get_platform, get_stored_license, remove_stored_license, store_license, validate_license,
+};
+use license_activation::{activate_license, deactivate_license};
+use local_api::{get_local_api_settings, regenerate_local_api_token, set_local_api_settings};
+use quickbooks::{export_deals_quickbooks, get_quickbooks_accounts, set_quickbooks_accounts};
+use inventory_feed::{
+    export_inventory_feed, get_inventory_feed_schedule, get_inventory_feed_templates,
+    save_inventory_feed_template, set_inventory_feed_schedule,
+};
+use data_export::{export_all_data, import_all_data};
+use client_duplicates::find_duplicate_clients;
+use vcard::{export_clients_vcf, import_clients_vcf};
+use tax::{
+    calculate_deal_tax, calculate_taxes_for_deal, db_delete_tax_rate, db_get_tax_rates,
+    db_upsert_tax_rate,
+};
+use finance::{finance_amortization_schedule, finance_calculate_payment};
+use pdf_forms::{fill_pdf_form, generate_deal_document, get_pdf_form_fields};
+use document_templates::{
+    db_delete_document_template, db_save_document_template, import_template_pack,
+    list_templates,
+};
+use deal_validation::validate_deal_for_completion;
+use deal_status::db_get_deal_status_history;
+use vehicle_images::{
+    cleanup_orphan_vehicle_images, db_add_vehicle_image, db_remove_vehicle_image,
+    db_reorder_vehicle_images,
+};
+use recent_items::{db_get_favorites, db_get_recent_items, db_set_favorite, record_access};
+use global_search::db_global_search;
+use tasks::{
+    db_complete_task, db_create_task, db_delete_task, db_get_tasks, db_snooze_task, db_update_task,
+};
+use lenders::{
+    db_create_lender, db_delete_lender, db_get_lender, db_get_lender_stats, db_get_lenders,
+    db_update_lender,
+};
+use reporting::{cancel_report_job, list_report_jobs};
+use data_events::{get_data_change_events_enabled, set_data_change_events_enabled};
+use document_trash::{purge_document_trash, restore_deleted_document_file};
+use communications::{
+    db_create_communication, db_delete_communication, db_delete_message_template,
+    db_get_communication, db_get_communications_by_client, db_get_message_templates,
+    db_save_message_template, db_update_communication, mark_communication_sent,
+    render_message_template,
+};
+use address_lookup::{download_zip_dataset, get_zip_dataset_status, lookup_zip, normalize_address};
+use database_overview::db_get_database_overview;
+use csv_import::{csv_import_with_mapping, csv_inspect};
+use scrubbed_export::{export_scrubbed_database, get_scrub_profiles, save_scrub_profile};
+use stock_numbers::{generate_next_stock_number, get_stock_number_fixes};
+use deal_cobuyers::{
+    db_add_deal_cobuyer, db_delete_deal_cobuyer, db_get_deal_cobuyers, db_update_deal_cobuyer,
+    get_cobuyer_migration_issues,
+};
+use app_windows::open_deal_window;
+use money::{
+    format_money, get_money_conversion_report, get_money_format, get_money_locales, parse_money,
+    set_money_locale,
+};
+use vehicle_holds::{get_active_holds, place_hold, release_hold};
+use daily_activity::{db_get_daily_activity, export_daily_activity_pdf};
+use audit_log::get_audit_log;
+use retention::{
+    apply_retention_purge, get_retention_settings, preview_retention_purge, set_deal_retention_hold,
+    set_retention_settings,
+};
+use kiosk::{
+    enter_kiosk_mode, exit_kiosk_mode, get_kiosk_status, read_kiosk_document,
+    save_kiosk_signed_document,
+};
+use print_jobs::{cancel_print_job, get_print_job_status, submit_print_job};
+use support_bundle::create_support_bundle;
+use deal_cloning::db_clone_deal;
+use sensitive_fields::{reveal_sensitive_field, store_sensitive_field};
+use db_batch::db_batch;
+use vehicle_history::{db_get_vehicle_history, db_record_vehicle_event};
+use settings_cache::db_flush_settings;
+use sql_console::db_execute_readonly_query;
+use startup_migrations::get_migration_failure;
+use datetime::{get_timezone, set_timezone};
+use permissions::{get_current_role, get_user_roles, set_user_role};
+use validation_rules::{get_validation_rules, set_validation_rules, validate_entity};
+use auto_lock::{app_heartbeat, get_lock_status, set_auto_lock, unlock_app};
+use title_export::{
+    export_title_applications, get_title_export_formats, save_title_export_format,
+};
+use valuation::{get_valuation_api_status, get_vehicle_valuation, set_valuation_api};
+use temp_tags::{get_tag_report, import_temp_tags, issue_tag_to_deal, void_tag};
+use sample_data::{db_remove_sample_data, db_seed_sample_data};
+use vehicle_costs::{
+    db_add_vehicle_cost, db_delete_vehicle_cost, db_get_vehicle_cost_breakdown,
+    db_get_vehicle_costs, db_update_vehicle_cost,
+};
+use dealer_profile::{
+    get_dealer_profile, get_profile_completeness, remove_dealer_logo, set_dealer_logo,
+    update_dealer_profile,
+};
+use deal_fees::{
+    db_create_deal_fee, db_delete_deal_fee, db_get_deal_fees, db_recalculate_deal_totals,
+    db_update_deal_fee,
+};
+use document_versions::{db_get_document_versions, db_restore_document_version};
+use document_verification::{
+    redownload_mismatched_documents, refresh_document_checksums, verify_all_documents,
+};
+use webhooks::{
+    create_webhook, delete_webhook, get_webhook_deliveries, get_webhooks, update_webhook,
+};
+use trial::{get_trial_status, start_trial};
+use updater::{
+    check_for_update, download_and_install_update, get_update_preferences, restart_to_update,
+    set_update_preferences,
+};
+use log::{error, info, warn};
+use tauri::Manager;
+use crash::{delete_crash_report, export_crash_report, get_crash_reports};
+use logging::{export_logs_zip, get_log_level, get_recent_logs, set_log_level};
+use app_state::{auth_reauthenticate, auth_set_current_user};
+use secret_store::{
+    check_secure_storage_available, clear_all_user_secrets, list_stored_secret_keys,
+    migrate_secrets_to_keyring,
+};
+use session::{
+    get_session_status, get_session_token, list_sessions, remove_session_for_user,
+    remove_session_token, set_user_pin, store_session, store_session_for_user,
+    store_session_token, switch_active_session,
 };
-use log::{error, info};
-use session::{get_session_token, remove_session_token, store_session_token};
 use dealership_auth::{get_dealership_auth_token, remove_dealership_auth_token, store_dealership_auth_token};
 use docs_config::{get_documents_root_path, remove_documents_root_path, store_documents_root_path};
 use aws_config::{
@@ -33,23 +255,31 @@ use aws_config::{
     store_aws_access_key_id, store_aws_bucket_name, store_aws_region, store_aws_secret_access_key,
 };
 use s3_service::{
-    s3_delete_document, s3_document_exists, s3_download_document, s3_upload_document,
+    s3_delete_document, s3_document_exists, s3_download_document, s3_get_storage_usage,
+    s3_upload_document,
 };
+use telemetry::{get_command_metrics, set_command_metrics_enabled};
+use read_cache::cache_stats;
+use doc_paths::{ensure_document_path, get_deal_folder, rename_deal_folder};
+use templating::{render_template, validate_template};
+use stats_cache::{db_get_dashboard_snapshot, rebuild_stats_cache};
+use thumbnails::{generate_thumbnail, get_or_create_thumbnail};
+use storage_usage::{get_storage_usage, recompute_storage_usage, set_storage_quota};
 use storage::{
     cleanup_cache, get_all_storage_paths, get_backup_path, get_cache_path,
     get_database_path, get_documents_storage_path, get_logs_path, get_storage_stats,
-    prompt_select_documents_directory, set_custom_documents_path,
+    get_templates_path, prompt_select_documents_directory, set_custom_documents_path,
 };
 use database::{
     // Client commands
     db_create_client, db_get_client, db_get_all_clients, db_update_client,
     db_delete_client, db_search_clients,
     // Vehicle commands
-    db_create_vehicle, db_get_vehicle, db_get_all_vehicles, db_get_vehicle_by_vin,
+    db_create_vehicle, db_get_vehicle, db_get_all_vehicles, db_bulk_create_vehicles, db_get_vehicle_by_vin,
     db_get_vehicle_by_stock, db_update_vehicle, db_delete_vehicle,
     db_search_vehicles, db_get_vehicles_by_status,
     // Deal commands
-    db_create_deal, db_get_deal, db_get_all_deals, db_get_deals_by_client,
+    db_create_deal, db_get_deal, db_get_all_deals, db_get_deals_with_details, db_get_deals_by_client,
     db_get_deals_by_vehicle, db_get_deals_by_status, db_update_deal,
     db_delete_deal, db_search_deals, db_get_deals_stats,
     // Document commands
@@ -63,9 +293,14 @@ use database::{
     // Database initialization
     init_database,
 };
-use tauri::{Emitter, Manager};
 
 fn main() {
+    match logging::init(logging::LogConfig::from_env()) {
+        Ok(log_file) => info!("📝 Logging to {:?}", log_file),
+        Err(e) => eprintln!("Failed to initialize file logging: {}", e),
+    }
+    crash::install_panic_hook();
+
     info!("🚀 Tauri app starting...");
 
     let mut builder = tauri::Builder::default().plugin(tauri_plugin_fs::init());
@@ -74,8 +309,12 @@ fn main() {
     #[cfg(desktop)]
     {
         info!("🔧 Registering single instance plugin...");
-        builder = builder.plugin(tauri_plugin_single_instance::init(|_app, argv, _cwd| {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, _cwd| {
             info!("📱 New app instance: {:?}", argv);
+            // Bring an existing window forward; main may have been closed
+            if !app_windows::focus_window(app, None) {
+                warn!("⚠️  No window open to focus");
+            }
         }));
     }
 
@@ -88,14 +327,29 @@ fn main() {
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_store::Builder::default().build())
+        .manage(app_state::AppState::default())
+        .manage(database::DbState::default())
+        .manage(kiosk::KioskState::default())
+        .manage(auto_lock::AutoLockState::default())
+        .manage(print_jobs::PrintJobs::default())
         .setup(|app| {
             info!("🔗 Setting up deep link handler...");
             
             // Initialize SQLite database early in Tauri startup
             info!("💾 Initializing SQLite database...");
+            // migration-progress / migration-failed events, once the window is up
+            startup_migrations::forward_migration_events(app.handle().clone());
             match init_database() {
                 Ok(_) => {
                     info!("✅ SQLite database initialized successfully");
+                    // Settings and dealer profiles, read by most of what follows
+                    read_cache::warm_read_cache();
+                    logging::apply_saved_log_level();
+                    telemetry::apply_saved_settings();
+                    trial::record_run();
+                    shutdown::check_previous_shutdown();
+                    // Coalesce db_set_setting writes from the UI
+                    settings_cache::start_settings_flusher();
                 }
                 Err(e) => {
                     error!("❌ Failed to initialize SQLite database: {}", e);
@@ -103,6 +357,53 @@ fn main() {
                 }
             }
 
+            // data-changed events after mutations, unless turned off in settings
+            data_events::install(app.handle().clone());
+
+            // Validate the stored license offline and tell the UI
+            let license_app = app.handle().clone();
+            std::thread::spawn(move || license::emit_license_status(&license_app));
+
+            // Keep the online activation fresh (no-op until the license is activated)
+            license_activation::start_heartbeat(app.handle().clone());
+
+            // Warn the frontend (or refresh) before the session expires
+            session::start_session_watch(app.handle().clone());
+
+            // Lock the app after the idle timeout from settings (app-locked)
+            auto_lock::start_idle_watch(app.handle().clone());
+
+            // Announce follow-up tasks as they come due (task-due)
+            tasks::start_task_reminders(app.handle().clone());
+
+            // Delete expired vehicle holds (hold-expired)
+            vehicle_holds::start_hold_expiry(app.handle().clone());
+            retention::start_retention_reminders(app.handle().clone());
+
+            // Copy command timings to the local metrics table when that's turned on
+            telemetry::start_persistence();
+
+            // Background update checks on the interval from settings (update-available)
+            updater::start_update_checks(app.handle().clone());
+
+            // Localhost API for DMS integrations, if the dealer turned it on
+            local_api::start_from_settings(app.handle());
+
+            // Regenerate the scheduled inventory feed for the FTP uploader
+            inventory_feed::start_scheduled_feed();
+
+            // Remove print directories left behind by previous crashes, expired document
+            // trash and expired idempotency keys (off the startup path)
+            std::thread::spawn(|| {
+                if let Err(e) = cleanup_stale_print_dirs(None) {
+                    error!("⚠️  Failed to clean up stale print directories: {}", e);
+                }
+                if let Err(e) = purge_document_trash(None) {
+                    error!("⚠️  Failed to purge document trash: {}", e);
+                }
+                idempotency::prune_idempotency_keys();
+            });
+
             use tauri_plugin_deep_link::DeepLinkExt;
 
             // Register deep links at runtime for Linux/Windows dev
@@ -139,41 +440,38 @@ fn main() {
                 info!("═══════════════════════════════════");
                 info!("📦 URLs: {:?}", urls);
 
-                if let Some(url) = urls.first() {
-                    let url_str = url.to_string();
-                    info!("🔍 URL: {}", url_str);
-
-                    if url_str.starts_with("dealer-sign://") {
-                        info!("✅ Valid dealer-sign protocol");
-
-                        if let Some(window) = app_handle.get_webview_window("main") {
-                            info!("✅ Main window found");
-                            info!("📤 Emitting to frontend...");
-
-                            match window.emit("deep-link", &url_str) {
-                                Ok(_) => {
-                                    info!("✅ Event emitted!");
-                                    let _ = window.set_focus();
-                                    let _ = window.show();
-                                    let _ = window.unminimize();
-                                }
-                                Err(e) => error!("❌ Emit failed: {}", e),
-                            }
-                        } else {
-                            error!("❌ Window not found");
-                        }
-                    }
-                }
+                let urls: Vec<String> = urls.iter().map(|url| url.to_string()).collect();
+                deep_link::handle_deep_links(&app_handle, &urls);
             });
 
             info!("✅ Deep link handler setup complete");
             Ok(())
         })
-        .invoke_handler(tauri::generate_handler![
+        .on_window_event(|window, event| match event {
+            // Closing the main window quits, so hold it open until shutdown has finished
+            tauri::WindowEvent::CloseRequested { api, .. }
+                if window.label() == app_windows::MAIN_WINDOW_LABEL && !shutdown::request_exit(window.app_handle()) =>
+            {
+                api.prevent_close()
+            }
+            // Stop the watches and report jobs the window started
+            tauri::WindowEvent::Destroyed => app_windows::window_destroyed(window.label()),
+            _ => {}
+        })
+        .invoke_handler(auto_lock::guarded(kiosk::guarded(permissions::guarded(tauri::generate_handler![
             // Session token storage (OS Keyring) - SECURITY: Scoped to session tokens only
             store_session_token,
             get_session_token,
             remove_session_token,
+            store_session,
+            get_session_status,
+            store_session_for_user,
+            auth_set_current_user,
+            auth_reauthenticate,
+            list_sessions,
+            switch_active_session,
+            set_user_pin,
+            remove_session_for_user,
             // Dealership auth token storage (OS Keyring) - SECURITY: Scoped to dealership auth tokens only
             store_dealership_auth_token,
             get_dealership_auth_token,
@@ -182,10 +480,24 @@ fn main() {
             store_documents_root_path,
             get_documents_root_path,
             remove_documents_root_path,
+            migrate_documents_root,
+            verify_all_documents,
+            refresh_document_checksums,
+            redownload_mismatched_documents,
             // Encryption (AES-256)
             generate_encryption_key,
             encrypt_data,
             decrypt_data,
+            encrypt_file,
+            decrypt_file,
+            is_file_encrypted,
+            derive_key_from_password,
+            verify_password,
+            rotate_encryption_key,
+            hmac_sign_file,
+            hmac_verify_file,
+            write_signature_file,
+            verify_signature_file,
             // File permissions
             set_file_permissions,
             check_file_permissions,
@@ -199,11 +511,22 @@ fn main() {
             batch_print_pdfs,
             create_temp_print_dir,
             cleanup_temp_print_dir,
+            cleanup_stale_print_dirs,
             reveal_in_explorer,
             write_file_to_path,
             read_binary_file,
             remove_file,
             join_path,
+            // Archives
+            create_zip_archive,
+            extract_zip_archive,
+            export_deal_archive,
+            // Documents folder watching
+            watch_directory,
+            unwatch_directory,
+            // Thumbnails
+            generate_thumbnail,
+            get_or_create_thumbnail,
             // Storage paths
             get_database_path,
             get_documents_storage_path,
@@ -212,9 +535,29 @@ fn main() {
             get_cache_path,
             get_logs_path,
             get_backup_path,
+            get_templates_path,
             get_all_storage_paths,
             cleanup_cache,
             get_storage_stats,
+            get_disk_space,
+            // Per-user storage quotas
+            get_storage_usage,
+            set_storage_quota,
+            recompute_storage_usage,
+            // Logs
+            get_recent_logs,
+            export_logs_zip,
+            get_log_level,
+            set_log_level,
+            get_crash_reports,
+            delete_crash_report,
+            export_crash_report,
+            // Diagnostics
+            run_diagnostics,
+            export_diagnostics,
+            get_command_metrics,
+            set_command_metrics_enabled,
+            cache_stats,
             // License management
             get_machine_id,
             get_platform,
@@ -224,6 +567,12 @@ fn main() {
             store_license,
             get_stored_license,
             remove_stored_license,
+            validate_license,
+            get_license_status,
+            activate_license,
+            deactivate_license,
+            start_trial,
+            get_trial_status,
             // Database - Clients
             db_create_client,
             db_get_client,
@@ -235,16 +584,22 @@ fn main() {
             db_create_vehicle,
             db_get_vehicle,
             db_get_all_vehicles,
+            db_bulk_create_vehicles,
             db_get_vehicle_by_vin,
             db_get_vehicle_by_stock,
             db_update_vehicle,
             db_delete_vehicle,
             db_search_vehicles,
             db_get_vehicles_by_status,
+            db_add_vehicle_image,
+            db_remove_vehicle_image,
+            db_reorder_vehicle_images,
+            cleanup_orphan_vehicle_images,
             // Database - Deals
             db_create_deal,
             db_get_deal,
             db_get_all_deals,
+            db_get_deals_with_details,
             db_get_deals_by_client,
             db_get_deals_by_vehicle,
             db_get_deals_by_status,
@@ -252,17 +607,26 @@ fn main() {
             db_delete_deal,
             db_search_deals,
             db_get_deals_stats,
+            validate_deal_for_completion,
+            db_get_deal_status_history,
             // Database - Documents
             db_create_document,
             db_get_document,
             db_get_documents_by_deal,
             db_update_document,
             db_delete_document,
+            db_get_document_versions,
+            db_restore_document_version,
             // Database - Utility
             db_clear_all_data,
             // Database - Settings
             db_get_setting,
             db_set_setting,
+            // Secure storage backend
+            check_secure_storage_available,
+            migrate_secrets_to_keyring,
+            clear_all_user_secrets,
+            list_stored_secret_keys,
             // AWS Configuration (OS Keyring) - SECURITY: Scoped to AWS credentials only
             store_aws_access_key_id,
             get_aws_access_key_id,
@@ -277,10 +641,244 @@ fn main() {
             s3_download_document,
             s3_delete_document,
             s3_document_exists,
-        ]);
+            s3_get_storage_usage,
+            // Updates
+            check_for_update,
+            download_and_install_update,
+            restart_to_update,
+            get_update_preferences,
+            set_update_preferences,
+            // Local integration API
+            get_local_api_settings,
+            set_local_api_settings,
+            regenerate_local_api_token,
+            // Webhooks
+            create_webhook,
+            get_webhooks,
+            update_webhook,
+            delete_webhook,
+            get_webhook_deliveries,
+            // QuickBooks export
+            export_deals_quickbooks,
+            get_quickbooks_accounts,
+            set_quickbooks_accounts,
+            // Inventory feeds
+            get_inventory_feed_templates,
+            save_inventory_feed_template,
+            export_inventory_feed,
+            get_inventory_feed_schedule,
+            set_inventory_feed_schedule,
+            // Full data export/import
+            export_all_data,
+            import_all_data,
+            // Contacts
+            find_duplicate_clients,
+            import_clients_vcf,
+            export_clients_vcf,
+            // Sales tax
+            db_upsert_tax_rate,
+            db_get_tax_rates,
+            db_delete_tax_rate,
+            calculate_deal_tax,
+            calculate_taxes_for_deal,
+            // Finance calculator
+            finance_calculate_payment,
+            finance_amortization_schedule,
+            // PDF form templates
+            fill_pdf_form,
+            get_pdf_form_fields,
+            db_save_document_template,
+            list_templates,
+            db_delete_document_template,
+            import_template_pack,
+            generate_deal_document,
+            // Deal folders
+            get_deal_folder,
+            ensure_document_path,
+            rename_deal_folder,
+            // Text templates
+            render_template,
+            validate_template,
+            // Dashboard statistics
+            db_get_dashboard_snapshot,
+            rebuild_stats_cache,
+            // Recent items and favorites
+            record_access,
+            db_get_recent_items,
+            db_set_favorite,
+            db_get_favorites,
+            // Search
+            db_global_search,
+            // Tasks and reminders
+            db_create_task,
+            db_update_task,
+            db_delete_task,
+            db_get_tasks,
+            db_complete_task,
+            db_snooze_task,
+            // Lenders
+            db_create_lender,
+            db_get_lender,
+            db_get_lenders,
+            db_update_lender,
+            db_delete_lender,
+            db_get_lender_stats,
+            // Deal fee lines
+            db_create_deal_fee,
+            db_get_deal_fees,
+            db_update_deal_fee,
+            db_delete_deal_fee,
+            db_recalculate_deal_totals,
+            // Report jobs
+            list_report_jobs,
+            cancel_report_job,
+            // Data change events
+            get_data_change_events_enabled,
+            set_data_change_events_enabled,
+            // Document trash
+            restore_deleted_document_file,
+            purge_document_trash,
+            // Client communications
+            db_create_communication,
+            db_get_communication,
+            db_get_communications_by_client,
+            db_update_communication,
+            db_delete_communication,
+            mark_communication_sent,
+            db_save_message_template,
+            db_get_message_templates,
+            db_delete_message_template,
+            render_message_template,
+            // Dealer profile
+            get_dealer_profile,
+            update_dealer_profile,
+            set_dealer_logo,
+            remove_dealer_logo,
+            get_profile_completeness,
+            // Address lookup
+            lookup_zip,
+            normalize_address,
+            get_zip_dataset_status,
+            download_zip_dataset,
+            // Database overview
+            db_get_database_overview,
+            // CSV import
+            csv_inspect,
+            csv_import_with_mapping,
+            // Vehicle costs
+            db_add_vehicle_cost,
+            db_get_vehicle_costs,
+            db_update_vehicle_cost,
+            db_delete_vehicle_cost,
+            db_get_vehicle_cost_breakdown,
+            // Scrubbed database export
+            get_scrub_profiles,
+            save_scrub_profile,
+            export_scrubbed_database,
+            // Stock numbers
+            generate_next_stock_number,
+            get_stock_number_fixes,
+            // Deal co-buyers
+            db_add_deal_cobuyer,
+            db_get_deal_cobuyers,
+            db_update_deal_cobuyer,
+            db_delete_deal_cobuyer,
+            get_cobuyer_migration_issues,
+            // Windows
+            open_deal_window,
+            // Money formatting and the cents conversion
+            format_money,
+            parse_money,
+            get_money_format,
+            get_money_locales,
+            set_money_locale,
+            get_money_conversion_report,
+            // Vehicle holds
+            place_hold,
+            release_hold,
+            get_active_holds,
+            // Daily activity report
+            db_get_daily_activity,
+            export_daily_activity_pdf,
+            // Data retention and the audit log
+            get_retention_settings,
+            set_retention_settings,
+            set_deal_retention_hold,
+            preview_retention_purge,
+            apply_retention_purge,
+            get_audit_log,
+            // Kiosk signing mode
+            enter_kiosk_mode,
+            exit_kiosk_mode,
+            get_kiosk_status,
+            read_kiosk_document,
+            save_kiosk_signed_document,
+            // Print job tracking
+            submit_print_job,
+            get_print_job_status,
+            cancel_print_job,
+            // Support bundle
+            create_support_bundle,
+            // Deal cloning
+            db_clone_deal,
+            // Sensitive fields
+            store_sensitive_field,
+            reveal_sensitive_field,
+            // Batched reads
+            db_batch,
+            // Vehicle history
+            db_record_vehicle_event,
+            db_get_vehicle_history,
+            // Settings write-behind
+            db_flush_settings,
+            // Support SQL console
+            db_execute_readonly_query,
+            // Startup migrations
+            get_migration_failure,
+            // Dealer time zone
+            get_timezone,
+            set_timezone,
+            // User roles
+            get_current_role,
+            get_user_roles,
+            set_user_role,
+            // Validation rules
+            get_validation_rules,
+            set_validation_rules,
+            validate_entity,
+            // Auto-lock
+            app_heartbeat,
+            get_lock_status,
+            set_auto_lock,
+            unlock_app,
+            // Title applications
+            export_title_applications,
+            get_title_export_formats,
+            save_title_export_format,
+            // Vehicle valuations
+            get_vehicle_valuation,
+            get_valuation_api_status,
+            set_valuation_api,
+            // Temp tags
+            import_temp_tags,
+            issue_tag_to_deal,
+            void_tag,
+            get_tag_report,
+            // Sample data
+            db_seed_sample_data,
+            db_remove_sample_data,
+        ]))));
 
     info!("🚀 Starting Tauri runtime...");
     builder
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| match event {
+            // Finish uploads, checkpoint the database and mark a clean exit first
+            tauri::RunEvent::ExitRequested { api, .. } if !shutdown::request_exit(app) => {
+                api.prevent_exit()
+            }
+            tauri::RunEvent::Exit => shutdown::on_exit(),
+            _ => {}
+        });
 }
\ No newline at end of file
@@ -0,0 +1,585 @@
+// src-tauri/src/money.rs
+//
+// Money is integer cents. Migration 26 gave every amount on vehicles and deals a *_cents
+// column; stats and exports read those, and the f64 amounts the frontend sends are rounded
+// to whole cents (to_cents) before they're stored. The REAL columns are still written for
+// the frontend and sync, and triggers keep the cents columns in step with them.
+//
+// Before converting, migration 26 copies the database next to itself (backup_before_conversion):
+// putting that file back in place of the database undoes the conversion. The conversion is
+// checked value by value (record_conversion) and amounts that had a fraction of a cent are
+// listed in money_subcent_values (get_money_conversion_report).
+//
+// format_money / parse_money format and read amounts for the dealer's locale (setting
+// money_locale), so the deal screen and the exports round the same way. The locale is cached
+// for a minute rather than read from settings on every amount.
+
+use chrono::Utc;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection, Result as SqlResult};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::{db_get_setting, DbState};
+use crate::settings_cache::set_setting;
+use crate::telemetry::track;
+
+pub(crate) const MONEY_LOCALE_SETTING: &str = "money_locale";
+
+/// Amount columns converted by migration 26; each has a "{column}_cents" partner
+pub(crate) const MONEY_COLUMNS: &[(&str, &[&str])] = &[
+    ("vehicles", &["price", "cost"]),
+    (
+        "deals",
+        &[
+            "total_amount",
+            "sale_amount",
+            "sales_tax",
+            "doc_fee",
+            "trade_in_value",
+            "down_payment",
+            "financed_amount",
+        ],
+    ),
+];
+
+/// How far (in cents) a REAL amount can be from whole cents and still count as whole: f64
+/// can't hold most decimal amounts exactly (0.1 + 0.2), which isn't a fraction of a cent
+const SUBCENT_TOLERANCE: f64 = 0.0001;
+/// How long the money_locale setting is served from cache
+const LOCALE_TTL: Duration = Duration::from_secs(60);
+
+static LOCALE_CACHE: Lazy<Mutex<Option<(Instant, &'static MoneyLocale)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+/// Nearest whole cent, half away from zero (as SQLite's ROUND does)
+pub(crate) fn to_cents(amount: f64) -> i64 {
+    (amount * 100.0).round() as i64
+}
+
+pub(crate) fn from_cents(cents: i64) -> f64 {
+    cents as f64 / 100.0
+}
+
+/// An amount from the frontend, rounded to whole cents before it's stored
+pub(crate) fn round_to_cents(amount: f64) -> f64 {
+    from_cents(to_cents(amount))
+}
+
+/// How amounts are written in a locale
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MoneyLocale {
+    pub code: &'static str,
+    pub symbol: &'static str,
+    /// "1 234,56 $" rather than "$1,234.56"
+    pub symbol_after: bool,
+    pub decimal_separator: char,
+    pub group_separator: char,
+}
+
+const fn locale(
+    code: &'static str,
+    symbol: &'static str,
+    symbol_after: bool,
+    decimal_separator: char,
+    group_separator: char,
+) -> MoneyLocale {
+    MoneyLocale {
+        code,
+        symbol,
+        symbol_after,
+        decimal_separator,
+        group_separator,
+    }
+}
+
+/// The first is the default
+pub(crate) static LOCALES: [MoneyLocale; 6] = [
+    locale("en-US", "$", false, '.', ','),
+    locale("es-US", "$", false, '.', ','),
+    locale("en-CA", "$", false, '.', ','),
+    locale("fr-CA", "$", true, ',', ' '),
+    locale("es-MX", "$", false, '.', ','),
+    locale("en-GB", "£", false, '.', ','),
+];
+
+pub(crate) fn find_locale(code: &str) -> Result<&'static MoneyLocale, String> {
+    LOCALES
+        .iter()
+        .find(|locale| locale.code.eq_ignore_ascii_case(code.trim()))
+        .ok_or_else(|| format!("Unsupported money locale: {}", code))
+}
+
+/// The dealer's locale (money_locale), cached for LOCALE_TTL
+pub(crate) fn current_locale() -> &'static MoneyLocale {
+    let mut cache = LOCALE_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((read_at, locale)) = *cache {
+        if read_at.elapsed() < LOCALE_TTL {
+            return locale;
+        }
+    }
+    let locale = match db_get_setting(MONEY_LOCALE_SETTING.to_string()) {
+        Ok(Some(code)) => find_locale(&code).unwrap_or_else(|e| {
+            warn!("⚠️  {}; using {}", e, LOCALES[0].code);
+            &LOCALES[0]
+        }),
+        Ok(None) => &LOCALES[0],
+        Err(e) => {
+            warn!("⚠️  Failed to read the money locale: {}", e);
+            &LOCALES[0]
+        }
+    };
+    *cache = Some((Instant::now(), locale));
+    locale
+}
+
+fn resolve_locale(code: Option<&str>) -> Result<&'static MoneyLocale, String> {
+    match code {
+        Some(code) => find_locale(code),
+        None => Ok(current_locale()),
+    }
+}
+
+/// 123456 -> "$1,234.56" (en-US) or "1 234,56 $" (fr-CA); credits get a leading minus
+pub(crate) fn format_amount(cents: i64, locale: &MoneyLocale, with_symbol: bool) -> String {
+    let digits = (cents.unsigned_abs() / 100).to_string();
+    let mut number = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            number.push(locale.group_separator);
+        }
+        number.push(c);
+    }
+    number.push(locale.decimal_separator);
+    number.push_str(&format!("{:02}", cents.unsigned_abs() % 100));
+
+    let sign = if cents < 0 { "-" } else { "" };
+    match (with_symbol, locale.symbol_after) {
+        (false, _) => format!("{}{}", sign, number),
+        (true, false) => format!("{}{}{}", sign, locale.symbol, number),
+        (true, true) => format!("{}{} {}", sign, number, locale.symbol),
+    }
+}
+
+/// Read an amount typed in the locale's format: symbol and group separators optional, a
+/// leading minus or parentheses for credits. More than two decimals is an error rather than
+/// being rounded away
+pub(crate) fn parse_amount(input: &str, locale: &MoneyLocale) -> Result<i64, String> {
+    let invalid = || format!("Not an amount: {}", input.trim());
+    let mut text = input.trim();
+    let mut negative = false;
+    if let Some(inner) = text.strip_prefix('(').and_then(|t| t.strip_suffix(')')) {
+        negative = true;
+        text = inner.trim();
+    }
+    if let Some(rest) = text.strip_prefix('-') {
+        if negative {
+            return Err(invalid());
+        }
+        negative = true;
+        text = rest.trim();
+    }
+    let text = text
+        .strip_prefix(locale.symbol)
+        .or_else(|| text.strip_suffix(locale.symbol))
+        .unwrap_or(text)
+        .trim();
+
+    let mut whole = String::new();
+    let mut fraction: Option<String> = None;
+    for c in text.chars() {
+        match fraction.as_mut() {
+            None if c == locale.decimal_separator => fraction = Some(String::new()),
+            None if c == locale.group_separator || c.is_whitespace() => {}
+            None if c.is_ascii_digit() => whole.push(c),
+            Some(digits) if c.is_ascii_digit() => digits.push(c),
+            _ => return Err(invalid()),
+        }
+    }
+    let fraction = fraction.unwrap_or_default();
+    if whole.is_empty() && fraction.is_empty() {
+        return Err(invalid());
+    }
+    if fraction.len() > 2 {
+        return Err(format!("{} has a fraction of a cent", input.trim()));
+    }
+
+    let dollars: i64 = if whole.is_empty() {
+        0
+    } else {
+        whole.parse().map_err(|_| invalid())?
+    };
+    let cents: i64 = format!("{:0<2}", fraction).parse().map_err(|_| invalid())?;
+    let total = dollars
+        .checked_mul(100)
+        .and_then(|amount| amount.checked_add(cents))
+        .ok_or_else(|| format!("{} is too large", input.trim()))?;
+    Ok(if negative { -total } else { total })
+}
+
+// Migration 26
+
+/// Copy a file-backed database next to itself before its amounts are converted, unless it
+/// has no vehicles or deals yet. Returns where the copy went
+pub(crate) fn backup_before_conversion(conn: &Connection) -> SqlResult<Option<PathBuf>> {
+    let path = match conn.path().filter(|path| !path.is_empty()) {
+        Some(path) => PathBuf::from(path),
+        None => return Ok(None),
+    };
+    let rows: i64 = conn.query_row(
+        "SELECT (SELECT COUNT(*) FROM vehicles) + (SELECT COUNT(*) FROM deals)",
+        [],
+        |row| row.get(0),
+    )?;
+    if rows == 0 {
+        return Ok(None);
+    }
+
+    let mut backup = path.into_os_string();
+    backup.push(format!(
+        ".before-money-cents-{}.bak",
+        Utc::now().format("%Y%m%d%H%M%S")
+    ));
+    let backup = PathBuf::from(backup);
+    conn.execute("VACUUM INTO ?1", params![backup.to_string_lossy()])?;
+    info!(
+        "💾 Backed up the database to {:?} before converting money to cents",
+        backup
+    );
+    Ok(Some(backup))
+}
+
+/// Check every converted amount against its REAL original and record the conversion
+/// A cents value that isn't the rounded original fails the migration (it runs in a
+/// transaction, so nothing is kept)
+pub(crate) fn record_conversion(conn: &Connection, backup: Option<&Path>) -> SqlResult<(i64, i64)> {
+    let mut converted = 0;
+    for (table, columns) in MONEY_COLUMNS {
+        for column in columns.iter() {
+            let mismatched: i64 = conn.query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM {table} WHERE ({column} IS NULL) != ({column}_cents IS NULL)
+                         OR ABS({column} * 100 - {column}_cents) > 0.5 + ?1",
+                ),
+                params![SUBCENT_TOLERANCE],
+                |row| row.get(0),
+            )?;
+            if mismatched > 0 {
+                return Err(rusqlite::Error::SqliteFailure(
+                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ABORT),
+                    Some(format!(
+                        "{} {}.{} value(s) didn't convert to cents",
+                        mismatched, table, column
+                    )),
+                ));
+            }
+            converted +=
+                conn.query_row(&format!("SELECT COUNT({column}) FROM {table}"), [], |row| {
+                    row.get::<_, i64>(0)
+                })?;
+            conn.execute(
+                &format!(
+                    "INSERT OR REPLACE INTO money_subcent_values
+                         (table_name, row_id, column_name, original_value, cents)
+                     SELECT ?1, id, ?2, {column}, {column}_cents FROM {table}
+                     WHERE {column} IS NOT NULL
+                         AND ABS({column} * 100 - ROUND({column} * 100)) > ?3",
+                ),
+                params![table, column, SUBCENT_TOLERANCE],
+            )?;
+        }
+    }
+
+    let subcent: i64 = conn.query_row("SELECT COUNT(*) FROM money_subcent_values", [], |row| {
+        row.get(0)
+    })?;
+    conn.execute(
+        "INSERT OR REPLACE INTO money_conversion
+             (id, backup_path, converted_values, subcent_values, converted_at)
+         VALUES (1, ?1, ?2, ?3, ?4)",
+        params![
+            backup.map(|path| path.to_string_lossy().into_owned()),
+            converted,
+            subcent,
+            Utc::now().timestamp_millis()
+        ],
+    )?;
+    Ok((converted, subcent))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SubcentValue {
+    pub table_name: String,
+    pub row_id: String,
+    pub column_name: String,
+    pub original_value: f64,
+    pub cents: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MoneyConversionReport {
+    /// Restoring this copy undoes the conversion
+    pub backup_path: Option<String>,
+    pub converted_values: i64,
+    pub converted_at: i64,
+    /// The user's amounts that had a fraction of a cent
+    pub subcent_values: Vec<SubcentValue>,
+}
+
+fn conversion_report(conn: &Connection, user_id: &str) -> SqlResult<MoneyConversionReport> {
+    let (backup_path, converted_values, converted_at) = conn.query_row(
+        "SELECT backup_path, converted_values, converted_at FROM money_conversion WHERE id = 1",
+        [],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )?;
+    let mut stmt = conn.prepare(
+        "SELECT s.table_name, s.row_id, s.column_name, s.original_value, s.cents
+         FROM money_subcent_values s
+         LEFT JOIN vehicles v ON s.table_name = 'vehicles' AND v.id = s.row_id
+         LEFT JOIN deals d ON s.table_name = 'deals' AND d.id = s.row_id
+         WHERE COALESCE(v.user_id, d.user_id) = ?1
+         ORDER BY s.table_name, s.row_id, s.column_name",
+    )?;
+    let subcent_values = stmt
+        .query_map(params![user_id], |row| {
+            Ok(SubcentValue {
+                table_name: row.get(0)?,
+                row_id: row.get(1)?,
+                column_name: row.get(2)?,
+                original_value: row.get(3)?,
+                cents: row.get(4)?,
+            })
+        })?
+        .collect::<SqlResult<Vec<_>>>()?;
+    Ok(MoneyConversionReport {
+        backup_path,
+        converted_values,
+        converted_at,
+        subcent_values,
+    })
+}
+
+// Commands
+
+/// Format cents for the given locale, or the dealer's (money_locale) when there's none
+#[tauri::command]
+pub fn format_money(cents: i64, locale: Option<String>) -> Result<String, String> {
+    track("format_money", || {
+        Ok(format_amount(
+            cents,
+            resolve_locale(locale.as_deref())?,
+            true,
+        ))
+    })
+}
+
+/// Read a typed amount as cents (see parse_amount)
+#[tauri::command]
+pub fn parse_money(input: String, locale: Option<String>) -> Result<i64, String> {
+    track("parse_money", || {
+        parse_amount(&input, resolve_locale(locale.as_deref())?)
+    })
+}
+
+/// The dealer's money format, for formatting long lists in the frontend
+#[tauri::command]
+pub fn get_money_format() -> Result<MoneyLocale, String> {
+    track("get_money_format", || Ok(current_locale().clone()))
+}
+
+#[tauri::command]
+pub fn get_money_locales() -> Vec<MoneyLocale> {
+    LOCALES.to_vec()
+}
+
+#[tauri::command]
+pub fn set_money_locale(locale: String) -> Result<MoneyLocale, String> {
+    track("set_money_locale", || {
+        let locale = find_locale(&locale)?;
+        set_setting(MONEY_LOCALE_SETTING, locale.code)?;
+        *LOCALE_CACHE.lock().unwrap_or_else(|e| e.into_inner()) = Some((Instant::now(), locale));
+        info!("💲 Money locale set to {}", locale.code);
+        Ok(locale.clone())
+    })
+}
+
+/// How migration 26 went: the backup it took and the user's amounts it had to round
+#[tauri::command]
+pub fn get_money_conversion_report(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<MoneyConversionReport, String> {
+    track("get_money_conversion_report", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        conversion_report(&conn, &user_id_value).map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::test_support::{seeded_db_at, temp_dir, SEED_CLIENT, SEED_VEHICLE, TEST_USER};
+    use std::fs;
+
+    fn en_us() -> &'static MoneyLocale {
+        find_locale("en-US").unwrap()
+    }
+
+    #[test]
+    fn test_format_and_parse() {
+        let fr_ca = find_locale("fr-ca").unwrap();
+        assert_eq!(format_amount(123456789, en_us(), true), "$1,234,567.89");
+        assert_eq!(format_amount(-5, en_us(), true), "-$0.05");
+        assert_eq!(format_amount(100000, en_us(), false), "1,000.00");
+        assert_eq!(format_amount(123456, fr_ca, true), "1 234,56 $");
+        assert!(find_locale("xx-XX").is_err());
+
+        assert_eq!(parse_amount("$1,234.56", en_us()), Ok(123456));
+        assert_eq!(parse_amount(" 1234.5 ", en_us()), Ok(123450));
+        assert_eq!(parse_amount(".07", en_us()), Ok(7));
+        assert_eq!(parse_amount("($20)", en_us()), Ok(-2000));
+        assert_eq!(parse_amount("-$20.00", en_us()), Ok(-2000));
+        assert_eq!(parse_amount("1 234,56 $", fr_ca), Ok(123456));
+        assert!(parse_amount("12.345", en_us()).is_err());
+        assert!(parse_amount("1.234,56", en_us()).is_err());
+        assert!(parse_amount("", en_us()).is_err());
+        assert!(parse_amount("-(5)", en_us()).is_err());
+        assert!(parse_amount("99999999999999999999", en_us()).is_err());
+
+        for cents in [0, 1, 99, 100, 123456, -987654321] {
+            assert_eq!(
+                parse_amount(&format_amount(cents, fr_ca, true), fr_ca),
+                Ok(cents)
+            );
+        }
+    }
+
+    /// Totals computed from the REAL columns before migration 26 and from the cents
+    /// columns after it agree to the cent
+    #[test]
+    fn test_stats_match_across_conversion() {
+        let db = seeded_db_at(25);
+        let amounts = [
+            0.1,
+            0.2,
+            19.99,
+            1234.56,
+            18_500.0,
+            0.30000000000000004,
+            99.995,
+        ];
+        {
+            let conn = db.conn();
+            for (i, amount) in amounts.iter().enumerate() {
+                conn.execute(
+                    "INSERT INTO deals (id, user_id, type, client_id, vehicle_id, status,
+                         total_amount, sale_amount, financed_amount, document_ids, created_at, updated_at)
+                     VALUES (?1, ?2, 'cash', ?3, ?4, 'sold', ?5, ?5, ?5, '[]', 1, 1)",
+                    params![format!("d{}", i), TEST_USER, SEED_CLIENT, SEED_VEHICLE, amount],
+                )
+                .unwrap();
+            }
+        }
+        let before_cents = |conn: &Connection, column: &str| -> i64 {
+            let amounts: Vec<Option<f64>> = conn
+                .prepare(&format!("SELECT {} FROM deals WHERE user_id = ?1", column))
+                .unwrap()
+                .query_map(params![TEST_USER], |row| row.get(0))
+                .unwrap()
+                .collect::<SqlResult<_>>()
+                .unwrap();
+            amounts.into_iter().flatten().map(to_cents).sum()
+        };
+        let (total_before, financed_before) = {
+            let conn = db.conn();
+            (
+                before_cents(&conn, "total_amount"),
+                before_cents(&conn, "financed_amount"),
+            )
+        };
+
+        db.migrate_to(26).unwrap();
+        let conn = db.conn();
+        let stats = crate::database::deals_stats(&conn, TEST_USER).unwrap();
+        assert_eq!(
+            to_cents(stats["totalAmount"].as_f64().unwrap()),
+            total_before
+        );
+        let financed_after: i64 = conn
+            .query_row(
+                "SELECT SUM(financed_amount_cents) FROM deals WHERE user_id = ?1",
+                params![TEST_USER],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(financed_after, financed_before);
+
+        // Only 99.995 had a fraction of a cent (three columns of one deal); float noise
+        // like 0.30000000000000004 isn't flagged
+        let report = conversion_report(&conn, TEST_USER).unwrap();
+        assert_eq!(report.backup_path, None);
+        assert_eq!(report.subcent_values.len(), 3);
+        assert!(report
+            .subcent_values
+            .iter()
+            .all(|value| value.row_id == "d6"));
+        assert_eq!(report.subcent_values[0].original_value, 99.995);
+        assert!(conversion_report(&conn, "someone-else")
+            .unwrap()
+            .subcent_values
+            .is_empty());
+
+        // Writers that only set the REAL column still get cents
+        conn.execute("UPDATE deals SET total_amount = 10.01 WHERE id = 'd0'", [])
+            .unwrap();
+        let cents: i64 = conn
+            .query_row(
+                "SELECT total_amount_cents FROM deals WHERE id = 'd0'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(cents, 1001);
+    }
+
+    #[test]
+    fn test_backup_taken_before_conversion() {
+        let dir = temp_dir("money", "backup");
+        let path = dir.join("dealer.db");
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(
+                "CREATE TABLE vehicles (id TEXT, price REAL);
+                 CREATE TABLE deals (id TEXT);",
+            )
+            .unwrap();
+            assert_eq!(backup_before_conversion(&conn).unwrap(), None);
+
+            conn.execute("INSERT INTO vehicles VALUES ('v1', 12.345)", [])
+                .unwrap();
+            let backup = backup_before_conversion(&conn).unwrap().unwrap();
+            assert_eq!(backup.parent(), Some(dir.as_path()));
+            let copy = Connection::open(&backup).unwrap();
+            let price: f64 = copy
+                .query_row("SELECT price FROM vehicles WHERE id = 'v1'", [], |row| {
+                    row.get(0)
+                })
+                .unwrap();
+            assert_eq!(price, 12.345);
+        }
+        assert_eq!(
+            backup_before_conversion(&Database::init_in_memory().unwrap().conn()).unwrap(),
+            None
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
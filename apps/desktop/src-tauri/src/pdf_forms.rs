@@ -0,0 +1,1232 @@
+// src-tauri/src/pdf_forms.rs
+//
+// Deal documents from fillable (AcroForm) PDF templates
+// A template is a PDF plus a field map: PDF field name -> text with {placeholders}, e.g.
+// "buyer_name": "{client.first_name} {client.last_name}" or "price": "{deal.sale_amount|money}".
+// Placeholders read the deal, client and vehicle rows (deal.*, client.*, vehicle.*), the
+// deal's first co-buyer (cobuyer.first_name..., or the old JSON keys cobuyer.firstName...),
+// the dealer profile (dealer.legal_name, dealer.license_number...)
+// and {today}; trade-in figures are deal columns (deal.trade_in_value).
+// Filters: money (1,234.50), date (MM/DD/YYYY from a timestamp), upper.
+// Templates themselves are managed in document_templates.rs.
+//
+// Text and choice fields get a Helvetica appearance stream so they render without the
+// viewer's help; flattening stamps every widget's appearance into the page and drops the form.
+// Form fields the template maps but the PDF doesn't have, and placeholders with no data,
+// are reported back rather than skipped.
+
+use chrono::{Local, TimeZone, Utc};
+use log::info;
+use lopdf::{
+    decode_text_string, dictionary, text_string, Dictionary, Document as PdfDocument, Object,
+    ObjectId, Stream,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_export::documents_root;
+use crate::database::{
+    db_create_document, db_get_client, db_get_vehicle, get_deal_for_user, new_row_id, DbState,
+    Document,
+};
+use crate::deal_cobuyers::cobuyer_form_fields;
+use crate::dealer_profile::get_dealer_profile_for_user;
+use crate::doc_paths::document_path;
+use crate::document_templates::get_template;
+use crate::money::{format_amount, to_cents, LOCALES};
+use crate::storage::invalidate_storage_stats;
+use crate::telemetry::track;
+
+/// Field flags (PDF 32000-1, 12.7.4.2.1)
+const FF_RADIO: i64 = 1 << 15;
+const FF_PUSHBUTTON: i64 = 1 << 16;
+/// Annotation flag: hidden
+const F_HIDDEN: i64 = 1 << 1;
+const DEFAULT_FONT_SIZE: f32 = 12.0;
+const MIN_FONT_SIZE: f32 = 4.0;
+/// Average Helvetica glyph width in ems, for auto-sizing text to the field
+const AVG_GLYPH_WIDTH: f32 = 0.55;
+/// Guards against Parent/Kids cycles in damaged forms
+const MAX_FIELD_DEPTH: usize = 32;
+pub(crate) const FILTERS: &[&str] = &["money", "date", "upper"];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FillReport {
+    pub filled: Vec<String>,
+    /// Names that aren't fillable fields in the PDF (absent, push buttons, signatures, or a
+    /// radio value that matches no option)
+    pub missing_fields: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MissingValue {
+    pub field: String,
+    pub placeholder: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GeneratedDocument {
+    pub document: Document,
+    pub missing_fields: Vec<String>,
+    /// Placeholders with no data on the deal; those fields were filled without them
+    pub missing_values: Vec<MissingValue>,
+}
+
+/// Fill the named fields of an AcroForm PDF and write the result to output_path
+#[tauri::command]
+pub fn fill_pdf_form(
+    template_path: String,
+    field_values: HashMap<String, String>,
+    output_path: String,
+    flatten: bool,
+) -> Result<FillReport, String> {
+    track("fill_pdf_form", || {
+        let mut pdf = load_pdf(Path::new(&template_path))?;
+        let report = fill_form(&mut pdf, &field_values, flatten)?;
+        save_pdf(&mut pdf, Path::new(&output_path))?;
+        info!(
+            "✅ PDF form filled: {} fields, {} missing -> {}",
+            report.filled.len(),
+            report.missing_fields.len(),
+            output_path
+        );
+        Ok(report)
+    })
+}
+
+/// Field names and current values of a PDF form, for building a template's field map
+#[tauri::command]
+pub fn get_pdf_form_fields(template_path: String) -> Result<BTreeMap<String, String>, String> {
+    track("get_pdf_form_fields", || {
+        let pdf = load_pdf(Path::new(&template_path))?;
+        Ok(read_form(&pdf))
+    })
+}
+
+/// Fill a template from a deal and attach the PDF to the deal
+/// Without output_path the file goes to the deal's folder (doc_paths.rs), named after the
+/// template
+#[tauri::command]
+pub fn generate_deal_document(
+    deal_id: String,
+    template_id: String,
+    output_path: Option<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<GeneratedDocument, String> {
+    track("generate_deal_document", || {
+        let user_id_value = state.require_user(user_id)?;
+        let template = get_template(&template_id, &user_id_value)?;
+        let deal = get_deal_for_user(
+            db.get().map_err(|e| e.to_string())?,
+            &deal_id,
+            &user_id_value,
+        )?
+        .ok_or_else(|| "Deal not found or access denied".to_string())?;
+        let client = db_get_client(
+            deal.client_id.clone(),
+            Some(user_id_value.clone()),
+            state.clone(),
+            db.clone(),
+        )?;
+        let vehicle = db_get_vehicle(
+            deal.vehicle_id.clone(),
+            Some(user_id_value.clone()),
+            state.clone(),
+            db.clone(),
+        )?;
+
+        let mut sources = Map::new();
+        add_source(&mut sources, "deal", &deal)?;
+        if let Some(client) = &client {
+            add_source(&mut sources, "client", client)?;
+        }
+        if let Some(vehicle) = &vehicle {
+            add_source(&mut sources, "vehicle", vehicle)?;
+        }
+        let cobuyer = {
+            let db = db.get().map_err(|e| e.to_string())?;
+            let conn = db.conn();
+            cobuyer_form_fields(&conn, &deal.id).map_err(|e| e.to_string())?
+        };
+        if let Some(cobuyer) = &cobuyer {
+            add_source(&mut sources, "cobuyer", cobuyer)?;
+        }
+        if let Some(dealer) = get_dealer_profile_for_user(&user_id_value)? {
+            add_source(&mut sources, "dealer", &dealer)?;
+        }
+        sources.insert(
+            "today".to_string(),
+            Value::from(Utc::now().timestamp_millis()),
+        );
+
+        let (field_values, missing_values) = render_field_map(&template.field_map, &sources);
+
+        let output_path = match output_path.filter(|path| !path.trim().is_empty()) {
+            Some(path) => PathBuf::from(path),
+            None => {
+                let root = PathBuf::from(documents_root()?);
+                let db = db.get().map_err(|e| e.to_string())?;
+                document_path(
+                    &db.conn(),
+                    &root,
+                    &user_id_value,
+                    &deal.id,
+                    &template.document_type,
+                    &format!("{}.pdf", template.name),
+                )?
+            }
+        };
+
+        let mut pdf = load_pdf(Path::new(&template.template_path))?;
+        let report = fill_form(&mut pdf, &field_values, template.flatten)?;
+        let bytes = save_pdf(&mut pdf, &output_path)?;
+
+        let now = Utc::now().timestamp_millis();
+        let document = Document {
+            id: format!("doc_{}", new_row_id()),
+            deal_id: deal.id.clone(),
+            r#type: template.document_type.clone(),
+            filename: output_path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            file_path: output_path.to_string_lossy().into_owned(),
+            file_size: Some(bytes.len() as i64),
+            file_checksum: Some(format!("{:x}", Sha256::digest(&bytes))),
+            created_at: now,
+            updated_at: now,
+            synced_at: None,
+            signature: None,
+        };
+        let document = match db_create_document(document) {
+            Ok(document) => document,
+            Err(e) => {
+                let _ = fs::remove_file(&output_path);
+                return Err(e.to_string());
+            }
+        };
+        invalidate_storage_stats();
+
+        info!(
+            "✅ Generated {} for deal {} ({} missing fields, {} missing values)",
+            template.name,
+            deal.id,
+            report.missing_fields.len(),
+            missing_values.len()
+        );
+        Ok(GeneratedDocument {
+            document,
+            missing_fields: report.missing_fields,
+            missing_values,
+        })
+    })
+}
+
+fn add_source(
+    sources: &mut Map<String, Value>,
+    prefix: &str,
+    row: &impl Serialize,
+) -> Result<(), String> {
+    if let Value::Object(columns) = serde_json::to_value(row).map_err(|e| e.to_string())? {
+        for (column, value) in columns {
+            sources.insert(format!("{}.{}", prefix, column), value);
+        }
+    }
+    Ok(())
+}
+
+// Field map templates
+
+/// Render every field's template; returns the values and the placeholders that had no data
+fn render_field_map(
+    field_map: &BTreeMap<String, String>,
+    sources: &Map<String, Value>,
+) -> (HashMap<String, String>, Vec<MissingValue>) {
+    let mut values = HashMap::new();
+    let mut missing = Vec::new();
+    for (field, template) in field_map {
+        let (value, unresolved) = render_template(template, sources);
+        missing.extend(unresolved.into_iter().map(|placeholder| MissingValue {
+            field: field.clone(),
+            placeholder,
+        }));
+        values.insert(field.clone(), value);
+    }
+    (values, missing)
+}
+
+fn render_template(template: &str, sources: &Map<String, Value>) -> (String, Vec<String>) {
+    let mut out = String::new();
+    let mut missing = Vec::new();
+    for segment in segments(template) {
+        match segment {
+            Segment::Text(text) => out.push_str(text),
+            Segment::Placeholder { raw, key, filter } => match sources
+                .get(key)
+                .and_then(|value| format_value(value, filter))
+            {
+                Some(value) => out.push_str(&value),
+                None => missing.push(raw.to_string()),
+            },
+        }
+    }
+
+    // "{first} {last}" with a missing part shouldn't leave stray spaces
+    (
+        out.split_whitespace().collect::<Vec<_>>().join(" "),
+        missing,
+    )
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Placeholder {
+        raw: &'a str,
+        key: &'a str,
+        filter: Option<&'a str>,
+    },
+}
+
+/// Split "Sold to {client.first_name}" into literal text and {key|filter} placeholders;
+/// an unclosed brace is literal text
+fn segments(template: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some((start, len)) = rest
+        .find('{')
+        .and_then(|start| Some((start, rest[start..].find('}')?)))
+    {
+        segments.push(Segment::Text(&rest[..start]));
+        let raw = rest[start + 1..start + len].trim();
+        let (key, filter) = match raw.split_once('|') {
+            Some((key, filter)) => (key.trim(), Some(filter.trim())),
+            None => (raw, None),
+        };
+        segments.push(Segment::Placeholder { raw, key, filter });
+        rest = &rest[start + len + 1..];
+    }
+    segments.push(Segment::Text(rest));
+    segments
+}
+
+/// The (key, filter) of every placeholder in a field map value
+pub(crate) fn placeholders(template: &str) -> Vec<(&str, Option<&str>)> {
+    segments(template)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Placeholder { key, filter, .. } => Some((key, filter)),
+            Segment::Text(_) => None,
+        })
+        .collect()
+}
+
+fn format_value(value: &Value, filter: Option<&str>) -> Option<String> {
+    let text = match (value, filter) {
+        (Value::Null, _) => return None,
+        (Value::String(s), _) if s.trim().is_empty() => return None,
+        (Value::Number(n), Some("money")) => format_money(to_cents(n.as_f64()?)),
+        (Value::Number(n), Some("date")) => Local
+            .timestamp_millis_opt(n.as_i64()?)
+            .single()?
+            .format("%m/%d/%Y")
+            .to_string(),
+        (Value::Number(n), _) => match (n.as_i64(), n.as_f64()) {
+            (Some(i), _) => i.to_string(),
+            (None, Some(f)) if f.fract() == 0.0 => format!("{:.0}", f),
+            (None, Some(f)) => format!("{:.2}", f),
+            _ => n.to_string(),
+        },
+        (Value::Bool(b), _) => if *b { "Yes" } else { "No" }.to_string(),
+        (Value::String(s), _) => s.trim().to_string(),
+        (other, _) => other.to_string(),
+    };
+    Some(match filter {
+        Some("upper") => text.to_uppercase(),
+        _ => text,
+    })
+}
+
+/// 123456 -> "1,234.56"
+pub(crate) fn format_money(cents: i64) -> String {
+    format_amount(cents, &LOCALES[0], false)
+}
+
+// AcroForm filling
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FieldKind {
+    Text,
+    Choice,
+    Checkbox,
+    Radio,
+    /// Push buttons and signature fields hold no fillable value
+    Other,
+}
+
+#[derive(Debug)]
+struct FormField {
+    name: String,
+    id: ObjectId,
+    kind: FieldKind,
+    /// Default appearance ("/Helv 0 Tf 0 g"), inherited from parents and the AcroForm
+    da: Option<String>,
+    widgets: Vec<ObjectId>,
+}
+
+/// Attributes a field inherits from its parents
+#[derive(Clone, Default)]
+struct Inherited {
+    ft: Option<Vec<u8>>,
+    ff: i64,
+    da: Option<String>,
+}
+
+pub(crate) fn load_pdf(path: &Path) -> Result<PdfDocument, String> {
+    PdfDocument::load(path).map_err(|e| format!("Failed to read PDF {}: {}", path.display(), e))
+}
+
+fn save_pdf(pdf: &mut PdfDocument, path: &Path) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    pdf.save_to(&mut bytes)
+        .map_err(|e| format!("Failed to write PDF: {}", e))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create folder: {}", e))?;
+    }
+    fs::write(path, &bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    Ok(bytes)
+}
+
+fn acroform_id(pdf: &PdfDocument) -> Option<ObjectId> {
+    pdf.catalog()
+        .ok()?
+        .get(b"AcroForm")
+        .ok()?
+        .as_reference()
+        .ok()
+}
+
+fn acroform(pdf: &PdfDocument) -> Option<&Dictionary> {
+    let catalog = pdf.catalog().ok()?;
+    catalog.get_deref(b"AcroForm", pdf).ok()?.as_dict().ok()
+}
+
+fn acroform_mut(pdf: &mut PdfDocument) -> Option<&mut Dictionary> {
+    match acroform_id(pdf) {
+        Some(id) => pdf.get_dictionary_mut(id).ok(),
+        None => pdf
+            .catalog_mut()
+            .ok()?
+            .get_mut(b"AcroForm")
+            .ok()?
+            .as_dict_mut()
+            .ok(),
+    }
+}
+
+fn form_fields(pdf: &PdfDocument) -> Vec<FormField> {
+    let Some(form) = acroform(pdf) else {
+        return Vec::new();
+    };
+    let inherited = Inherited {
+        da: form
+            .get(b"DA")
+            .ok()
+            .and_then(|da| decode_text_string(da).ok()),
+        ..Inherited::default()
+    };
+    let roots = form
+        .get_deref(b"Fields", pdf)
+        .and_then(Object::as_array)
+        .map(|fields| {
+            fields
+                .iter()
+                .filter_map(|f| f.as_reference().ok())
+                .collect()
+        })
+        .unwrap_or_else(|_| Vec::new());
+
+    let mut fields = Vec::new();
+    let mut seen = HashSet::new();
+    for id in roots {
+        collect_fields(pdf, id, "", &inherited, 0, &mut seen, &mut fields);
+    }
+    fields
+}
+
+fn collect_fields(
+    pdf: &PdfDocument,
+    id: ObjectId,
+    parent_name: &str,
+    parent: &Inherited,
+    depth: usize,
+    seen: &mut HashSet<ObjectId>,
+    out: &mut Vec<FormField>,
+) {
+    if depth > MAX_FIELD_DEPTH || !seen.insert(id) {
+        return;
+    }
+    let Ok(dict) = pdf.get_dictionary(id) else {
+        return;
+    };
+
+    let partial = dict.get(b"T").ok().and_then(|t| decode_text_string(t).ok());
+    let name = match (&partial, parent_name.is_empty()) {
+        (Some(partial), true) => partial.clone(),
+        (Some(partial), false) => format!("{}.{}", parent_name, partial),
+        (None, _) => parent_name.to_string(),
+    };
+    let inherited = Inherited {
+        ft: dict
+            .get(b"FT")
+            .and_then(Object::as_name)
+            .map(<[u8]>::to_vec)
+            .ok()
+            .or_else(|| parent.ft.clone()),
+        ff: dict
+            .get(b"Ff")
+            .and_then(Object::as_i64)
+            .unwrap_or(parent.ff),
+        da: dict
+            .get(b"DA")
+            .ok()
+            .and_then(|da| decode_text_string(da).ok())
+            .or_else(|| parent.da.clone()),
+    };
+
+    let kids: Vec<ObjectId> = dict
+        .get_deref(b"Kids", pdf)
+        .and_then(Object::as_array)
+        .map(|kids| kids.iter().filter_map(|k| k.as_reference().ok()).collect())
+        .unwrap_or_default();
+    // Kids with their own name are child fields; the rest are this field's widgets
+    let (child_fields, widgets): (Vec<ObjectId>, Vec<ObjectId>) =
+        kids.into_iter().partition(|kid| {
+            pdf.get_dictionary(*kid)
+                .map(|kid| kid.has(b"T"))
+                .unwrap_or(false)
+        });
+
+    for child in &child_fields {
+        collect_fields(pdf, *child, &name, &inherited, depth + 1, seen, out);
+    }
+    if !child_fields.is_empty() && widgets.is_empty() {
+        return;
+    }
+    if name.is_empty() {
+        return;
+    }
+
+    let kind = match inherited.ft.as_deref() {
+        Some(b"Tx") => FieldKind::Text,
+        Some(b"Ch") => FieldKind::Choice,
+        Some(b"Btn") if inherited.ff & FF_PUSHBUTTON != 0 => FieldKind::Other,
+        Some(b"Btn") if inherited.ff & FF_RADIO != 0 => FieldKind::Radio,
+        Some(b"Btn") => FieldKind::Checkbox,
+        _ => FieldKind::Other,
+    };
+    out.push(FormField {
+        name,
+        id,
+        kind,
+        da: inherited.da,
+        // A field with no kids is merged with its only widget
+        widgets: if widgets.is_empty() {
+            vec![id]
+        } else {
+            widgets
+        },
+    });
+}
+
+pub(crate) fn read_form(pdf: &PdfDocument) -> BTreeMap<String, String> {
+    form_fields(pdf)
+        .into_iter()
+        .map(|field| {
+            let value = pdf
+                .get_dictionary(field.id)
+                .ok()
+                .and_then(|dict| dict.get(b"V").ok())
+                .and_then(|value| match value {
+                    Object::Name(name) => Some(String::from_utf8_lossy(name).into_owned()),
+                    other => decode_text_string(other).ok(),
+                })
+                .unwrap_or_default();
+            (field.name, value)
+        })
+        .collect()
+}
+
+fn fill_form(
+    pdf: &mut PdfDocument,
+    values: &HashMap<String, String>,
+    flatten: bool,
+) -> Result<FillReport, String> {
+    let fields = form_fields(pdf);
+    let mut report = FillReport::default();
+    let mut font = None;
+
+    let mut names: Vec<&String> = values.keys().collect();
+    names.sort();
+    for name in names {
+        let value = &values[name];
+        let filled = match fields.iter().find(|field| field.name == *name) {
+            Some(field) => match field.kind {
+                FieldKind::Text | FieldKind::Choice => {
+                    set_text(pdf, field, value, &mut font)?;
+                    true
+                }
+                FieldKind::Checkbox | FieldKind::Radio => set_button(pdf, field, value)?,
+                FieldKind::Other => false,
+            },
+            None => false,
+        };
+        if filled {
+            report.filled.push(name.clone());
+        } else {
+            report.missing_fields.push(name.clone());
+        }
+    }
+
+    if let Some(form) = acroform_mut(pdf) {
+        // XFA data would override the AcroForm values in Acrobat
+        form.remove(b"XFA");
+        form.set("NeedAppearances", true);
+    }
+
+    if flatten {
+        // Fields left untouched still need an appearance to stamp
+        for field in &fields {
+            let is_text = matches!(field.kind, FieldKind::Text | FieldKind::Choice);
+            if is_text && !values.contains_key(&field.name) && !has_appearance(pdf, field) {
+                let value = pdf
+                    .get_dictionary(field.id)
+                    .ok()
+                    .and_then(|dict| dict.get(b"V").ok())
+                    .and_then(|value| decode_text_string(value).ok());
+                if let Some(value) = value {
+                    for widget in &field.widgets {
+                        set_text_appearance(pdf, *widget, field.da.as_deref(), &value, &mut font)?;
+                    }
+                }
+            }
+        }
+        flatten_form(pdf, &fields)?;
+    }
+    Ok(report)
+}
+
+fn set_text(
+    pdf: &mut PdfDocument,
+    field: &FormField,
+    value: &str,
+    font: &mut Option<ObjectId>,
+) -> Result<(), String> {
+    pdf.get_dictionary_mut(field.id)
+        .map_err(|e| e.to_string())?
+        .set("V", text_string(value));
+    for widget in &field.widgets {
+        set_text_appearance(pdf, *widget, field.da.as_deref(), value, font)?;
+    }
+    Ok(())
+}
+
+/// Checkboxes take yes/true/x/1/on (or the on-state name); radios take an option name
+/// Returns false when a radio value matches none of its options
+fn set_button(pdf: &mut PdfDocument, field: &FormField, value: &str) -> Result<bool, String> {
+    let value = value.trim();
+    let truthy = ["yes", "y", "true", "x", "1", "on", "checked"]
+        .iter()
+        .any(|t| value.eq_ignore_ascii_case(t));
+
+    let states: Vec<(ObjectId, Option<Vec<u8>>)> = field
+        .widgets
+        .iter()
+        .map(|widget| (*widget, on_state(pdf, *widget)))
+        .collect();
+    let selected: Option<Vec<u8>> = match field.kind {
+        FieldKind::Radio => {
+            let option = states
+                .iter()
+                .filter_map(|(_, state)| state.clone())
+                .find(|state| state == value.as_bytes());
+            if option.is_none() && !value.is_empty() && !value.eq_ignore_ascii_case("off") {
+                return Ok(false);
+            }
+            option
+        }
+        _ if truthy
+            || states
+                .iter()
+                .any(|(_, s)| s.as_deref() == Some(value.as_bytes())) =>
+        {
+            Some(
+                states
+                    .iter()
+                    .find_map(|(_, state)| state.clone())
+                    .unwrap_or_else(|| b"Yes".to_vec()),
+            )
+        }
+        _ => None,
+    };
+
+    let v = selected.clone().unwrap_or_else(|| b"Off".to_vec());
+    pdf.get_dictionary_mut(field.id)
+        .map_err(|e| e.to_string())?
+        .set("V", Object::Name(v));
+    for (widget, state) in states {
+        let appearance = match (&selected, state) {
+            (Some(selected), Some(state)) if *selected == state => state,
+            _ => b"Off".to_vec(),
+        };
+        pdf.get_dictionary_mut(widget)
+            .map_err(|e| e.to_string())?
+            .set("AS", Object::Name(appearance));
+    }
+    Ok(true)
+}
+
+/// The widget's "on" appearance name (anything in /AP /N besides Off)
+fn on_state(pdf: &PdfDocument, widget: ObjectId) -> Option<Vec<u8>> {
+    let normal = normal_appearance(pdf, widget)?;
+    normal
+        .as_dict()
+        .ok()?
+        .iter()
+        .map(|(name, _)| name)
+        .find(|name| name.as_slice() != b"Off")
+        .cloned()
+}
+
+fn normal_appearance(pdf: &PdfDocument, widget: ObjectId) -> Option<&Object> {
+    let ap = pdf
+        .get_dictionary(widget)
+        .ok()?
+        .get_deref(b"AP", pdf)
+        .ok()?
+        .as_dict()
+        .ok()?;
+    ap.get_deref(b"N", pdf).ok()
+}
+
+fn has_appearance(pdf: &PdfDocument, field: &FormField) -> bool {
+    field
+        .widgets
+        .iter()
+        .all(|widget| normal_appearance(pdf, *widget).is_some())
+}
+
+fn widget_rect(pdf: &PdfDocument, widget: ObjectId) -> Option<[f32; 4]> {
+    let rect = pdf
+        .get_dictionary(widget)
+        .ok()?
+        .get_deref(b"Rect", pdf)
+        .ok()?
+        .as_array()
+        .ok()?;
+    let n: Vec<f32> = rect.iter().filter_map(|v| v.as_float().ok()).collect();
+    (n.len() == 4).then(|| {
+        [
+            n[0].min(n[2]),
+            n[1].min(n[3]),
+            n[0].max(n[2]),
+            n[1].max(n[3]),
+        ]
+    })
+}
+
+/// Single-line Helvetica appearance; size comes from DA, 0 (auto) fits the box
+fn set_text_appearance(
+    pdf: &mut PdfDocument,
+    widget: ObjectId,
+    da: Option<&str>,
+    value: &str,
+    font: &mut Option<ObjectId>,
+) -> Result<(), String> {
+    let Some([x1, y1, x2, y2]) = widget_rect(pdf, widget) else {
+        return Ok(());
+    };
+    let (width, height) = (x2 - x1, y2 - y1);
+    let text = win_ansi(value);
+
+    let size = match da_font_size(da) {
+        Some(size) if size > 0.0 => size,
+        _ => {
+            let fit_height = (height - 4.0) * 0.8;
+            let fit_width = (width - 4.0) / (AVG_GLYPH_WIDTH * text.len().max(1) as f32);
+            fit_height.min(fit_width).min(DEFAULT_FONT_SIZE)
+        }
+    }
+    .max(MIN_FONT_SIZE);
+    let baseline = ((height - size) / 2.0 + size * 0.22).max(1.0);
+
+    let content = format!(
+        "/Tx BMC\nq\n1 1 {:.2} {:.2} re W n\nBT\n/Helv {:.2} Tf\n0 g\n2 {:.2} Td\n({}) Tj\nET\nQ\nEMC\n",
+        (width - 2.0).max(0.0),
+        (height - 2.0).max(0.0),
+        size,
+        baseline,
+        escape_pdf_string(&text)
+    );
+
+    let font_id = *font.get_or_insert_with(|| {
+        pdf.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+            "Encoding" => "WinAnsiEncoding",
+        })
+    });
+    let stream = Stream::new(
+        dictionary! {
+            "Type" => "XObject",
+            "Subtype" => "Form",
+            "BBox" => vec![0.into(), 0.into(), width.into(), height.into()],
+            "Resources" => dictionary! {
+                "Font" => dictionary! { "Helv" => font_id },
+            },
+        },
+        content.into_bytes(),
+    );
+    let stream_id = pdf.add_object(stream);
+    pdf.get_dictionary_mut(widget)
+        .map_err(|e| e.to_string())?
+        .set("AP", dictionary! { "N" => stream_id });
+    Ok(())
+}
+
+fn da_font_size(da: Option<&str>) -> Option<f32> {
+    let tokens: Vec<&str> = da?.split_whitespace().collect();
+    let tf = tokens.iter().position(|token| *token == "Tf")?;
+    tokens.get(tf.checked_sub(1)?)?.parse().ok()
+}
+
+/// Helvetica's WinAnsi encoding matches Latin-1 above 0xA0; anything else becomes '?'
+pub(crate) fn win_ansi(value: &str) -> Vec<u8> {
+    value
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| match c as u32 {
+            0x20..=0x7E | 0xA0..=0xFF => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+pub(crate) fn escape_pdf_string(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for &b in bytes {
+        match b {
+            b'(' | b')' | b'\\' => {
+                out.push('\\');
+                out.push(b as char);
+            }
+            0x20..=0x7E => out.push(b as char),
+            _ => out.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    out
+}
+
+/// Stamp each widget's current appearance into its page, then remove the widgets and the form
+fn flatten_form(pdf: &mut PdfDocument, fields: &[FormField]) -> Result<(), String> {
+    let widgets: HashSet<ObjectId> = fields
+        .iter()
+        .flat_map(|field| field.widgets.iter().copied())
+        .collect();
+
+    for page_id in pdf.get_pages().into_values() {
+        let annots: Vec<Object> = match pdf
+            .get_dictionary(page_id)
+            .and_then(|page| page.get_deref(b"Annots", pdf))
+            .and_then(Object::as_array)
+        {
+            Ok(annots) => annots.clone(),
+            Err(_) => continue,
+        };
+
+        let mut content = String::new();
+        let mut kept = Vec::new();
+        for annot in annots {
+            let Some(widget) = annot.as_reference().ok().filter(|id| widgets.contains(id)) else {
+                kept.push(annot);
+                continue;
+            };
+            if let Some((stream_id, placement)) = widget_placement(pdf, widget) {
+                let name = format!("Flat{}_{}", stream_id.0, stream_id.1);
+                add_page_xobject(pdf, page_id, &name, stream_id)?;
+                content.push_str(&format!("q {} cm /{} Do Q\n", placement, name));
+            }
+        }
+
+        let page = pdf.get_dictionary_mut(page_id).map_err(|e| e.to_string())?;
+        if kept.is_empty() {
+            page.remove(b"Annots");
+        } else {
+            page.set("Annots", kept);
+        }
+        if !content.is_empty() {
+            // Isolate the page's own graphics state from the stamped fields
+            pdf.add_page_contents(page_id, format!("Q\n{}", content).into_bytes())
+                .map_err(|e| e.to_string())?;
+            prepend_page_content(pdf, page_id, b"q\n".to_vec())?;
+        }
+    }
+
+    pdf.catalog_mut()
+        .map_err(|e| e.to_string())?
+        .remove(b"AcroForm");
+    Ok(())
+}
+
+/// The appearance stream to draw for a widget and the "a b c d e f" matrix mapping its
+/// BBox onto the widget's Rect; None for hidden widgets or ones with nothing to draw
+fn widget_placement(pdf: &PdfDocument, widget: ObjectId) -> Option<(ObjectId, String)> {
+    let dict = pdf.get_dictionary(widget).ok()?;
+    if dict.get(b"F").and_then(Object::as_i64).unwrap_or(0) & F_HIDDEN != 0 {
+        return None;
+    }
+    let normal = dict
+        .get_deref(b"AP", pdf)
+        .ok()?
+        .as_dict()
+        .ok()?
+        .get(b"N")
+        .ok()?;
+    let stream_id = match normal {
+        Object::Reference(id) => match pdf.get_object(*id).ok()? {
+            Object::Stream(_) => *id,
+            // Per-state appearances (checkboxes): pick the one named by AS
+            Object::Dictionary(states) => {
+                let state = dict.get(b"AS").and_then(Object::as_name).ok()?;
+                states.get(state).ok()?.as_reference().ok()?
+            }
+            _ => return None,
+        },
+        Object::Dictionary(states) => {
+            let state = dict.get(b"AS").and_then(Object::as_name).ok()?;
+            states.get(state).ok()?.as_reference().ok()?
+        }
+        _ => return None,
+    };
+
+    let [x1, y1, x2, y2] = widget_rect(pdf, widget)?;
+    let stream = pdf.get_object(stream_id).ok()?.as_stream().ok()?;
+    let bbox: Vec<f32> = stream
+        .dict
+        .get_deref(b"BBox", pdf)
+        .and_then(Object::as_array)
+        .map(|b| b.iter().filter_map(|v| v.as_float().ok()).collect())
+        .unwrap_or_default();
+    let (bx, by, bw, bh) = match bbox.as_slice() {
+        [a, b, c, d] if (c - a).abs() > 0.0 && (d - b).abs() > 0.0 => {
+            (a.min(*c), b.min(*d), (c - a).abs(), (d - b).abs())
+        }
+        _ => (0.0, 0.0, x2 - x1, y2 - y1),
+    };
+    let (sx, sy) = ((x2 - x1) / bw, (y2 - y1) / bh);
+    Some((
+        stream_id,
+        format!(
+            "{:.4} 0 0 {:.4} {:.2} {:.2}",
+            sx,
+            sy,
+            x1 - bx * sx,
+            y1 - by * sy
+        ),
+    ))
+}
+
+fn add_page_xobject(
+    pdf: &mut PdfDocument,
+    page_id: ObjectId,
+    name: &str,
+    stream_id: ObjectId,
+) -> Result<(), String> {
+    let page = pdf.get_dictionary(page_id).map_err(|e| e.to_string())?;
+    // Inherited resources are copied onto the page before adding to them
+    let resources_id = match page.get(b"Resources") {
+        Ok(Object::Reference(id)) => Some(*id),
+        Ok(Object::Dictionary(_)) => None,
+        _ => {
+            let (_, inherited) = pdf.get_page_resources(page_id).map_err(|e| e.to_string())?;
+            let copied = inherited
+                .first()
+                .and_then(|id| pdf.get_dictionary(*id).ok())
+                .cloned()
+                .unwrap_or_default();
+            pdf.get_dictionary_mut(page_id)
+                .map_err(|e| e.to_string())?
+                .set("Resources", copied);
+            None
+        }
+    };
+
+    let resources = match resources_id {
+        Some(id) => pdf.get_dictionary(id),
+        None => pdf
+            .get_dictionary(page_id)
+            .and_then(|page| page.get(b"Resources"))
+            .and_then(Object::as_dict),
+    }
+    .map_err(|e| e.to_string())?;
+    let xobjects_id = resources
+        .get(b"XObject")
+        .and_then(Object::as_reference)
+        .ok();
+
+    let xobjects: &mut Dictionary = match xobjects_id {
+        Some(id) => pdf.get_dictionary_mut(id).map_err(|e| e.to_string())?,
+        None => {
+            let resources = match resources_id {
+                Some(id) => pdf.get_dictionary_mut(id),
+                None => pdf
+                    .get_dictionary_mut(page_id)
+                    .and_then(|page| page.get_mut(b"Resources"))
+                    .and_then(Object::as_dict_mut),
+            }
+            .map_err(|e| e.to_string())?;
+            if !resources.has(b"XObject") {
+                resources.set("XObject", Dictionary::new());
+            }
+            resources
+                .get_mut(b"XObject")
+                .and_then(Object::as_dict_mut)
+                .map_err(|e| e.to_string())?
+        }
+    };
+    xobjects.set(name, stream_id);
+    Ok(())
+}
+
+fn prepend_page_content(
+    pdf: &mut PdfDocument,
+    page_id: ObjectId,
+    content: Vec<u8>,
+) -> Result<(), String> {
+    let existing: Vec<Object> = match pdf
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Contents"))
+    {
+        Ok(Object::Reference(id)) => vec![Object::Reference(*id)],
+        Ok(Object::Array(contents)) => contents.clone(),
+        _ => Vec::new(),
+    };
+    let id = pdf.add_object(Stream::new(Dictionary::new(), content));
+    let mut contents = vec![Object::Reference(id)];
+    contents.extend(existing);
+    pdf.get_dictionary_mut(page_id)
+        .map_err(|e| e.to_string())?
+        .set("Contents", contents);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dealer_profile::DealerProfile;
+
+    const BILL_OF_SALE: &[u8] = include_bytes!("../testdata/pdf_forms/bill_of_sale.pdf");
+
+    fn fixture() -> PdfDocument {
+        PdfDocument::load_mem(BILL_OF_SALE).unwrap()
+    }
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn page_text(pdf: &PdfDocument) -> String {
+        let mut text = String::new();
+        for page_id in pdf.get_pages().into_values() {
+            text.push_str(&String::from_utf8_lossy(
+                &pdf.get_page_content(page_id).unwrap(),
+            ));
+            // Stamped appearance streams
+            let page = pdf.get_dictionary(page_id).unwrap();
+            let resources = page
+                .get_deref(b"Resources", pdf)
+                .unwrap()
+                .as_dict()
+                .unwrap();
+            if let Ok(xobjects) = resources
+                .get_deref(b"XObject", pdf)
+                .and_then(Object::as_dict)
+            {
+                for (_, xobject) in xobjects.iter() {
+                    let id = xobject.as_reference().unwrap();
+                    let stream = pdf.get_object(id).unwrap().as_stream().unwrap();
+                    text.push_str(&String::from_utf8_lossy(&stream.content));
+                }
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn test_fills_fields_and_reports_missing() {
+        let mut pdf = fixture();
+        assert_eq!(
+            read_form(&pdf).keys().collect::<Vec<_>>(),
+            ["as_is", "buyer_name", "vehicle.price", "vehicle.vin"]
+        );
+
+        let report = fill_form(
+            &mut pdf,
+            &values(&[
+                ("buyer_name", "Zoë O'Neil (Jr)"),
+                ("vehicle.vin", "1HGCM82633A004352"),
+                ("vehicle.price", "18,500.00"),
+                ("as_is", "Yes"),
+                ("odometer", "42000"),
+            ]),
+            false,
+        )
+        .unwrap();
+        assert_eq!(report.missing_fields, ["odometer"]);
+        assert_eq!(report.filled.len(), 4);
+
+        // Round-trip through a saved file
+        let mut bytes = Vec::new();
+        pdf.save_to(&mut bytes).unwrap();
+        let pdf = PdfDocument::load_mem(&bytes).unwrap();
+        let form = read_form(&pdf);
+        assert_eq!(form["buyer_name"], "Zoë O'Neil (Jr)");
+        assert_eq!(form["vehicle.vin"], "1HGCM82633A004352");
+        assert_eq!(form["vehicle.price"], "18,500.00");
+        assert_eq!(form["as_is"], "Yes");
+        assert!(acroform(&pdf).unwrap().has(b"NeedAppearances"));
+
+        let checkbox = form_fields(&pdf)
+            .into_iter()
+            .find(|f| f.name == "as_is")
+            .unwrap();
+        let widget = pdf.get_dictionary(checkbox.widgets[0]).unwrap();
+        assert_eq!(widget.get(b"AS").unwrap().as_name().unwrap(), b"Yes");
+    }
+
+    #[test]
+    fn test_flatten_stamps_values_and_removes_form() {
+        let mut pdf = fixture();
+        fill_form(
+            &mut pdf,
+            &values(&[("buyer_name", "Ada (Lovelace)"), ("as_is", "x")]),
+            true,
+        )
+        .unwrap();
+
+        let mut bytes = Vec::new();
+        pdf.save_to(&mut bytes).unwrap();
+        let pdf = PdfDocument::load_mem(&bytes).unwrap();
+
+        assert!(acroform(&pdf).is_none());
+        assert!(read_form(&pdf).is_empty());
+        let page_id = pdf.get_pages()[&1];
+        assert!(!pdf.get_dictionary(page_id).unwrap().has(b"Annots"));
+
+        let text = page_text(&pdf);
+        assert!(text.contains("(Bill of Sale) Tj"));
+        assert!(text.contains("(Ada \\(Lovelace\\)) Tj"));
+        // The template's existing price value is stamped too, and the checked box;
+        // the empty VIN field has nothing to draw
+        assert!(text.contains("(0.00) Tj"));
+        assert!(text.contains("/ZaDb 12 Tf"));
+        assert_eq!(text.matches(" Do Q").count(), 3);
+    }
+
+    #[test]
+    fn test_render_field_map() {
+        let mut sources = Map::new();
+        sources.insert("client.first_name".into(), Value::from("Ada"));
+        sources.insert("client.last_name".into(), Value::from("Lovelace"));
+        sources.insert("client.email".into(), Value::Null);
+        sources.insert("deal.sale_amount".into(), Value::from(1_234_567.5));
+        sources.insert("vehicle.year".into(), Value::from(2021));
+        sources.insert("vehicle.make".into(), Value::from("honda"));
+
+        let field_map: BTreeMap<String, String> = [
+            (
+                "buyer",
+                "{client.first_name} {client.middle_name} {client.last_name}",
+            ),
+            ("email", "{client.email}"),
+            ("price", "${deal.sale_amount|money}"),
+            ("vehicle", "{vehicle.year} {vehicle.make|upper}"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let (values, missing) = render_field_map(&field_map, &sources);
+        assert_eq!(values["buyer"], "Ada Lovelace");
+        assert_eq!(values["email"], "");
+        assert_eq!(values["price"], "$1,234,567.50");
+        assert_eq!(values["vehicle"], "2021 HONDA");
+        assert_eq!(
+            missing,
+            [
+                MissingValue {
+                    field: "buyer".into(),
+                    placeholder: "client.middle_name".into()
+                },
+                MissingValue {
+                    field: "email".into(),
+                    placeholder: "client.email".into()
+                },
+            ]
+        );
+
+        assert_eq!(format_money(5), "0.05");
+        assert_eq!(format_money(-100_000), "-1,000.00");
+    }
+
+    #[test]
+    fn test_dealer_profile_fills_header_fields() {
+        let dealer = DealerProfile {
+            user_id: "u1".into(),
+            legal_name: Some("Main Street Auto LLC".into()),
+            license_number: Some("d-12345".into()),
+            city: Some("Austin".into()),
+            state: Some("TX".into()),
+            zip_code: Some("78701".into()),
+            ..Default::default()
+        };
+        let mut sources = Map::new();
+        add_source(&mut sources, "dealer", &dealer).unwrap();
+
+        let field_map: BTreeMap<String, String> = [
+            ("seller", "{dealer.legal_name}"),
+            ("license", "{dealer.license_number|upper}"),
+            (
+                "city_state_zip",
+                "{dealer.city}, {dealer.state} {dealer.zip_code}",
+            ),
+            ("seller_phone", "{dealer.phone}"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        let (values, missing) = render_field_map(&field_map, &sources);
+        assert_eq!(values["seller"], "Main Street Auto LLC");
+        assert_eq!(values["license"], "D-12345");
+        assert_eq!(values["city_state_zip"], "Austin, TX 78701");
+        assert_eq!(
+            missing,
+            [MissingValue {
+                field: "seller_phone".into(),
+                placeholder: "dealer.phone".into()
+            }]
+        );
+    }
+}
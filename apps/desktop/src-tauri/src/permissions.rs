@@ -0,0 +1,917 @@
+// src-tauri/src/permissions.rs
+//
+// Local user roles. Each user on this machine is an admin, manager, sales or readonly user
+// (the user_roles table; a user without a row is readonly). Until anyone has been given a
+// role everyone is an admin, so an install that never sets roles up keeps working as before;
+// the first set_user_role makes its caller the first admin.
+// AppState loads the current user's role whenever the current user changes; with nobody
+// current it's readonly. guarded() (wrapped around the invoke handler in main.rs, like kiosk
+// mode) rejects a command with Forbidden when the role is below what its category in
+// COMMAND_PERMISSIONS needs. Every command is listed there: one that isn't is refused below
+// admin. Commands that return vehicle costs or profit figures hide them from roles below
+// manager themselves (redact_vehicle, redact_deals_stats), since those reads stay open to
+// sales.
+
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tauri::ipc::Invoke;
+use tauri::{Manager, Runtime, State};
+
+use crate::app_state::{AppState, AuthError};
+use crate::audit_log::record_audit;
+use crate::database::{begin_write, get_db, DbState, Vehicle};
+use crate::telemetry::track;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Readonly,
+    Sales,
+    Manager,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Readonly => "readonly",
+            Role::Sales => "sales",
+            Role::Manager => "manager",
+            Role::Admin => "admin",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Role> {
+        [Role::Readonly, Role::Sales, Role::Manager, Role::Admin]
+            .into_iter()
+            .find(|role| role.as_str() == value)
+    }
+
+    /// Vehicle costs and profit figures are for managers and admins
+    pub fn sees_costs(&self) -> bool {
+        *self >= Permission::ViewCosts.required_role()
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Command categories
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Read records and settings, sign in, and use this computer (print, open files)
+    Read,
+    /// Create and change records, write files, upload documents
+    Write,
+    /// Delete records, files and uploaded documents
+    Delete,
+    /// Vehicle costs and the figures derived from them
+    ViewCosts,
+    /// Wipe or purge data, the SQL console, roles
+    Admin,
+}
+
+impl Permission {
+    pub fn required_role(&self) -> Role {
+        match self {
+            Permission::Read => Role::Readonly,
+            Permission::Write => Role::Sales,
+            Permission::Delete | Permission::ViewCosts => Role::Manager,
+            Permission::Admin => Role::Admin,
+        }
+    }
+}
+
+/// What each command needs. Commands missing from the list are refused for everyone below
+/// admin, so a new command stays locked down until it's classified here
+const COMMAND_PERMISSIONS: &[(&str, Permission)] = &[
+    // Read: signing in, licensing and this machine (these run before anyone is current)
+    ("auth_set_current_user", Permission::Read),
+    ("auth_reauthenticate", Permission::Read),
+    ("get_current_role", Permission::Read),
+    ("store_session_token", Permission::Read),
+    ("store_session", Permission::Read),
+    ("store_session_for_user", Permission::Read),
+    ("get_session_token", Permission::Read),
+    ("get_session_status", Permission::Read),
+    ("list_sessions", Permission::Read),
+    ("switch_active_session", Permission::Read),
+    ("set_user_pin", Permission::Read),
+    ("remove_session_token", Permission::Read),
+    ("remove_session_for_user", Permission::Read),
+    ("store_dealership_auth_token", Permission::Read),
+    ("get_dealership_auth_token", Permission::Read),
+    ("remove_dealership_auth_token", Permission::Read),
+    ("app_heartbeat", Permission::Read),
+    ("get_lock_status", Permission::Read),
+    ("unlock_app", Permission::Read),
+    ("get_kiosk_status", Permission::Read),
+    ("exit_kiosk_mode", Permission::Read),
+    ("read_kiosk_document", Permission::Read),
+    ("activate_license", Permission::Read),
+    ("validate_license", Permission::Read),
+    ("store_license", Permission::Read),
+    ("get_stored_license", Permission::Read),
+    ("get_license_status", Permission::Read),
+    ("start_trial", Permission::Read),
+    ("get_trial_status", Permission::Read),
+    ("get_machine_id", Permission::Read),
+    ("get_machine_info", Permission::Read),
+    ("get_hostname", Permission::Read),
+    ("get_platform", Permission::Read),
+    ("get_app_version", Permission::Read),
+    ("check_for_update", Permission::Read),
+    ("get_update_preferences", Permission::Read),
+    ("check_secure_storage_available", Permission::Read),
+    ("list_stored_secret_keys", Permission::Read),
+    ("get_migration_failure", Permission::Read),
+    // Read: records
+    ("db_get_all_clients", Permission::Read),
+    ("db_get_client", Permission::Read),
+    ("db_search_clients", Permission::Read),
+    ("find_duplicate_clients", Permission::Read),
+    ("db_get_all_vehicles", Permission::Read),
+    ("db_get_vehicle", Permission::Read),
+    ("db_get_vehicle_by_vin", Permission::Read),
+    ("db_get_vehicle_by_stock", Permission::Read),
+    ("db_get_vehicles_by_status", Permission::Read),
+    ("db_search_vehicles", Permission::Read),
+    ("db_get_vehicle_history", Permission::Read),
+    ("get_active_holds", Permission::Read),
+    ("get_vehicle_valuation", Permission::Read),
+    ("db_get_all_deals", Permission::Read),
+    ("db_get_deal", Permission::Read),
+    ("db_get_deals_by_client", Permission::Read),
+    ("db_get_deals_by_vehicle", Permission::Read),
+    ("db_get_deals_by_status", Permission::Read),
+    ("db_get_deals_with_details", Permission::Read),
+    ("db_search_deals", Permission::Read),
+    ("db_get_deals_stats", Permission::Read),
+    ("db_get_deal_status_history", Permission::Read),
+    ("db_get_deal_fees", Permission::Read),
+    ("db_get_deal_cobuyers", Permission::Read),
+    ("validate_deal_for_completion", Permission::Read),
+    ("db_get_document", Permission::Read),
+    ("db_get_documents_by_deal", Permission::Read),
+    ("db_get_document_versions", Permission::Read),
+    ("db_get_communication", Permission::Read),
+    ("db_get_communications_by_client", Permission::Read),
+    ("db_get_message_templates", Permission::Read),
+    ("render_message_template", Permission::Read),
+    ("db_get_lender", Permission::Read),
+    ("db_get_lenders", Permission::Read),
+    ("db_get_lender_stats", Permission::Read),
+    ("db_get_tax_rates", Permission::Read),
+    ("db_get_tasks", Permission::Read),
+    ("db_get_daily_activity", Permission::Read),
+    ("db_get_dashboard_snapshot", Permission::Read),
+    ("db_get_database_overview", Permission::Read),
+    ("db_global_search", Permission::Read),
+    ("db_batch", Permission::Read),
+    ("db_get_recent_items", Permission::Read),
+    ("db_get_favorites", Permission::Read),
+    ("record_access", Permission::Read),
+    ("db_set_favorite", Permission::Read),
+    ("db_get_setting", Permission::Read),
+    // The command itself leaves every key but the frontend's own to admins
+    ("db_set_setting", Permission::Read),
+    ("db_flush_settings", Permission::Read),
+    ("get_audit_log", Permission::Read),
+    ("get_tag_report", Permission::Read),
+    ("get_cobuyer_migration_issues", Permission::Read),
+    ("get_stock_number_fixes", Permission::Read),
+    ("get_money_conversion_report", Permission::Read),
+    ("list_report_jobs", Permission::Read),
+    ("cancel_report_job", Permission::Read),
+    ("export_clients_vcf", Permission::Read),
+    ("export_deal_archive", Permission::Read),
+    ("export_daily_activity_pdf", Permission::Read),
+    ("export_inventory_feed", Permission::Read),
+    // Read: settings, formats and calculations
+    ("get_dealer_profile", Permission::Read),
+    ("get_profile_completeness", Permission::Read),
+    ("get_timezone", Permission::Read),
+    ("get_money_format", Permission::Read),
+    ("get_money_locales", Permission::Read),
+    ("format_money", Permission::Read),
+    ("parse_money", Permission::Read),
+    ("get_validation_rules", Permission::Read),
+    ("validate_entity", Permission::Read),
+    ("get_retention_settings", Permission::Read),
+    ("get_title_export_formats", Permission::Read),
+    ("get_quickbooks_accounts", Permission::Read),
+    ("get_inventory_feed_templates", Permission::Read),
+    ("get_inventory_feed_schedule", Permission::Read),
+    ("validate_template", Permission::Read),
+    ("get_scrub_profiles", Permission::Read),
+    ("get_valuation_api_status", Permission::Read),
+    ("get_data_change_events_enabled", Permission::Read),
+    ("get_log_level", Permission::Read),
+    ("get_recent_logs", Permission::Read),
+    ("get_command_metrics", Permission::Read),
+    ("cache_stats", Permission::Read),
+    ("calculate_deal_tax", Permission::Read),
+    ("finance_calculate_payment", Permission::Read),
+    ("finance_amortization_schedule", Permission::Read),
+    ("lookup_zip", Permission::Read),
+    ("normalize_address", Permission::Read),
+    ("get_zip_dataset_status", Permission::Read),
+    ("list_templates", Permission::Read),
+    ("render_template", Permission::Read),
+    ("get_pdf_form_fields", Permission::Read),
+    ("encrypt_data", Permission::Read),
+    ("decrypt_data", Permission::Read),
+    ("generate_encryption_key", Permission::Read),
+    ("derive_key_from_password", Permission::Read),
+    ("verify_password", Permission::Read),
+    ("is_file_encrypted", Permission::Read),
+    ("hmac_verify_file", Permission::Read),
+    ("verify_signature_file", Permission::Read),
+    // Read: files, folders and this computer
+    ("get_all_storage_paths", Permission::Read),
+    ("get_database_path", Permission::Read),
+    ("get_backup_path", Permission::Read),
+    ("get_cache_path", Permission::Read),
+    ("get_logs_path", Permission::Read),
+    ("get_templates_path", Permission::Read),
+    ("get_documents_dir", Permission::Read),
+    ("get_documents_storage_path", Permission::Read),
+    ("get_documents_root_path", Permission::Read),
+    ("get_downloads_dir", Permission::Read),
+    ("get_storage_file_path", Permission::Read),
+    ("prompt_select_documents_directory", Permission::Read),
+    ("get_deal_folder", Permission::Read),
+    ("join_path", Permission::Read),
+    ("read_binary_file", Permission::Read),
+    ("check_file_permissions", Permission::Read),
+    ("open_file_with_default_app", Permission::Read),
+    ("open_url", Permission::Read),
+    ("reveal_in_explorer", Permission::Read),
+    ("open_deal_window", Permission::Read),
+    ("watch_directory", Permission::Read),
+    ("unwatch_directory", Permission::Read),
+    ("generate_thumbnail", Permission::Read),
+    ("get_or_create_thumbnail", Permission::Read),
+    ("cleanup_cache", Permission::Read),
+    ("create_temp_print_dir", Permission::Read),
+    ("cleanup_temp_print_dir", Permission::Read),
+    ("cleanup_stale_print_dirs", Permission::Read),
+    ("print_pdf", Permission::Read),
+    ("batch_print_pdfs", Permission::Read),
+    ("submit_print_job", Permission::Read),
+    ("get_print_job_status", Permission::Read),
+    ("cancel_print_job", Permission::Read),
+    ("get_disk_space", Permission::Read),
+    ("get_storage_stats", Permission::Read),
+    ("get_storage_usage", Permission::Read),
+    ("s3_document_exists", Permission::Read),
+    ("s3_get_storage_usage", Permission::Read),
+    ("get_aws_region", Permission::Read),
+    ("get_aws_bucket_name", Permission::Read),
+    ("verify_all_documents", Permission::Read),
+    ("run_diagnostics", Permission::Read),
+    ("export_diagnostics", Permission::Read),
+    ("export_logs_zip", Permission::Read),
+    ("get_crash_reports", Permission::Read),
+    ("export_crash_report", Permission::Read),
+    ("create_support_bundle", Permission::Read),
+    // Write
+    ("db_create_client", Permission::Write),
+    ("db_update_client", Permission::Write),
+    ("db_create_vehicle", Permission::Write),
+    ("db_bulk_create_vehicles", Permission::Write),
+    ("db_update_vehicle", Permission::Write),
+    ("db_add_vehicle_image", Permission::Write),
+    ("db_remove_vehicle_image", Permission::Write),
+    ("db_reorder_vehicle_images", Permission::Write),
+    ("db_record_vehicle_event", Permission::Write),
+    ("generate_next_stock_number", Permission::Write),
+    ("place_hold", Permission::Write),
+    ("release_hold", Permission::Write),
+    ("db_create_deal", Permission::Write),
+    ("db_update_deal", Permission::Write),
+    ("db_clone_deal", Permission::Write),
+    ("db_recalculate_deal_totals", Permission::Write),
+    ("calculate_taxes_for_deal", Permission::Write),
+    ("db_create_deal_fee", Permission::Write),
+    ("db_update_deal_fee", Permission::Write),
+    ("db_add_deal_cobuyer", Permission::Write),
+    ("db_update_deal_cobuyer", Permission::Write),
+    ("store_sensitive_field", Permission::Write),
+    ("reveal_sensitive_field", Permission::Write),
+    ("db_create_document", Permission::Write),
+    ("db_update_document", Permission::Write),
+    ("db_restore_document_version", Permission::Write),
+    ("ensure_document_path", Permission::Write),
+    ("db_create_communication", Permission::Write),
+    ("db_update_communication", Permission::Write),
+    ("mark_communication_sent", Permission::Write),
+    ("db_create_lender", Permission::Write),
+    ("db_update_lender", Permission::Write),
+    ("db_upsert_tax_rate", Permission::Write),
+    ("db_create_task", Permission::Write),
+    ("db_update_task", Permission::Write),
+    ("db_complete_task", Permission::Write),
+    ("db_snooze_task", Permission::Write),
+    ("db_save_document_template", Permission::Write),
+    ("db_save_message_template", Permission::Write),
+    ("generate_deal_document", Permission::Write),
+    ("rename_deal_folder", Permission::Write),
+    ("rebuild_stats_cache", Permission::Write),
+    ("recompute_storage_usage", Permission::Write),
+    ("fill_pdf_form", Permission::Write),
+    ("import_clients_vcf", Permission::Write),
+    ("csv_inspect", Permission::Write),
+    ("csv_import_with_mapping", Permission::Write),
+    ("write_file_to_path", Permission::Write),
+    ("set_file_permissions", Permission::Write),
+    ("create_zip_archive", Permission::Write),
+    ("extract_zip_archive", Permission::Write),
+    ("encrypt_file", Permission::Write),
+    ("decrypt_file", Permission::Write),
+    ("hmac_sign_file", Permission::Write),
+    ("write_signature_file", Permission::Write),
+    ("restore_deleted_document_file", Permission::Write),
+    ("redownload_mismatched_documents", Permission::Write),
+    ("s3_upload_document", Permission::Write),
+    ("s3_download_document", Permission::Write),
+    ("export_title_applications", Permission::Write),
+    ("import_temp_tags", Permission::Write),
+    ("issue_tag_to_deal", Permission::Write),
+    ("enter_kiosk_mode", Permission::Write),
+    ("save_kiosk_signed_document", Permission::Write),
+    ("db_seed_sample_data", Permission::Write),
+    // Delete
+    ("db_delete_client", Permission::Delete),
+    ("db_delete_vehicle", Permission::Delete),
+    ("cleanup_orphan_vehicle_images", Permission::Delete),
+    ("db_delete_deal", Permission::Delete),
+    ("db_delete_deal_fee", Permission::Delete),
+    ("db_delete_deal_cobuyer", Permission::Delete),
+    ("set_deal_retention_hold", Permission::Delete),
+    ("db_delete_document", Permission::Delete),
+    ("db_delete_communication", Permission::Delete),
+    ("db_delete_lender", Permission::Delete),
+    ("db_delete_tax_rate", Permission::Delete),
+    ("db_delete_task", Permission::Delete),
+    ("db_delete_document_template", Permission::Delete),
+    ("db_delete_message_template", Permission::Delete),
+    ("remove_file", Permission::Delete),
+    ("purge_document_trash", Permission::Delete),
+    ("s3_delete_document", Permission::Delete),
+    ("void_tag", Permission::Delete),
+    ("delete_crash_report", Permission::Delete),
+    ("db_remove_sample_data", Permission::Delete),
+    // Costs
+    ("db_add_vehicle_cost", Permission::ViewCosts),
+    ("db_get_vehicle_costs", Permission::ViewCosts),
+    ("db_update_vehicle_cost", Permission::ViewCosts),
+    ("db_delete_vehicle_cost", Permission::ViewCosts),
+    ("db_get_vehicle_cost_breakdown", Permission::ViewCosts),
+    ("export_deals_quickbooks", Permission::ViewCosts),
+    ("export_all_data", Permission::ViewCosts),
+    ("export_scrubbed_database", Permission::ViewCosts),
+    // Admin
+    ("db_clear_all_data", Permission::Admin),
+    ("import_all_data", Permission::Admin),
+    ("set_retention_settings", Permission::Admin),
+    ("set_validation_rules", Permission::Admin),
+    ("set_auto_lock", Permission::Admin),
+    ("save_title_export_format", Permission::Admin),
+    ("set_valuation_api", Permission::Admin),
+    ("preview_retention_purge", Permission::Admin),
+    ("apply_retention_purge", Permission::Admin),
+    ("db_execute_readonly_query", Permission::Admin),
+    ("get_user_roles", Permission::Admin),
+    ("set_user_role", Permission::Admin),
+    ("update_dealer_profile", Permission::Admin),
+    ("set_dealer_logo", Permission::Admin),
+    ("remove_dealer_logo", Permission::Admin),
+    ("set_timezone", Permission::Admin),
+    ("set_money_locale", Permission::Admin),
+    ("set_quickbooks_accounts", Permission::Admin),
+    ("save_inventory_feed_template", Permission::Admin),
+    ("set_inventory_feed_schedule", Permission::Admin),
+    ("save_scrub_profile", Permission::Admin),
+    ("import_template_pack", Permission::Admin),
+    ("download_zip_dataset", Permission::Admin),
+    ("get_webhooks", Permission::Admin),
+    ("get_webhook_deliveries", Permission::Admin),
+    ("create_webhook", Permission::Admin),
+    ("update_webhook", Permission::Admin),
+    ("delete_webhook", Permission::Admin),
+    ("get_local_api_settings", Permission::Admin),
+    ("set_local_api_settings", Permission::Admin),
+    ("regenerate_local_api_token", Permission::Admin),
+    ("set_storage_quota", Permission::Admin),
+    ("store_documents_root_path", Permission::Admin),
+    ("remove_documents_root_path", Permission::Admin),
+    ("set_custom_documents_path", Permission::Admin),
+    ("migrate_documents_root", Permission::Admin),
+    ("refresh_document_checksums", Permission::Admin),
+    ("rotate_encryption_key", Permission::Admin),
+    ("migrate_secrets_to_keyring", Permission::Admin),
+    ("clear_all_user_secrets", Permission::Admin),
+    ("store_aws_access_key_id", Permission::Admin),
+    ("store_aws_secret_access_key", Permission::Admin),
+    ("store_aws_region", Permission::Admin),
+    ("store_aws_bucket_name", Permission::Admin),
+    ("get_aws_access_key_id", Permission::Admin),
+    ("get_aws_secret_access_key", Permission::Admin),
+    ("deactivate_license", Permission::Admin),
+    ("remove_stored_license", Permission::Admin),
+    ("download_and_install_update", Permission::Admin),
+    ("restart_to_update", Permission::Admin),
+    ("set_update_preferences", Permission::Admin),
+    ("set_log_level", Permission::Admin),
+    ("set_command_metrics_enabled", Permission::Admin),
+    ("set_data_change_events_enabled", Permission::Admin),
+];
+
+/// The permission a command needs; None for commands nobody has classified
+pub(crate) fn command_permission(command: &str) -> Option<Permission> {
+    COMMAND_PERMISSIONS
+        .iter()
+        .find(|(name, _)| *name == command)
+        .map(|(_, permission)| *permission)
+}
+
+pub(crate) fn check_permission(role: Role, permission: Permission) -> Result<(), AuthError> {
+    let required_role = permission.required_role();
+    if role >= required_role {
+        Ok(())
+    } else {
+        Err(AuthError::Forbidden { required_role })
+    }
+}
+
+/// Whether a user with `role` may run `command`; unclassified commands need an admin
+pub(crate) fn check_command(role: Role, command: &str) -> Result<(), AuthError> {
+    match command_permission(command) {
+        Some(permission) => check_permission(role, permission),
+        None => {
+            warn!("🔒 {} isn't in COMMAND_PERMISSIONS", command);
+            check_permission(role, Permission::Admin)
+        }
+    }
+}
+
+/// Wrap the app's invoke handler so roles are enforced before any command runs
+pub fn guarded<R: Runtime>(
+    handler: impl Fn(Invoke<R>) -> bool + Send + Sync + 'static,
+) -> impl Fn(Invoke<R>) -> bool + Send + Sync + 'static {
+    move |invoke: Invoke<R>| {
+        let webview = invoke.message.webview();
+        let forbidden = webview
+            .try_state::<AppState>()
+            .and_then(|state| check_command(state.role(), invoke.message.command()).err());
+
+        match forbidden {
+            Some(error) => {
+                warn!("🔒 {} needs a higher role", invoke.message.command());
+                invoke.resolver.reject(error);
+                true
+            }
+            None => handler(invoke),
+        }
+    }
+}
+
+/// The user's role. While nobody has a role everyone is an admin
+pub(crate) fn role_for_user(conn: &Connection, user_id: &str) -> SqlResult<Role> {
+    let role: Option<String> = conn
+        .query_row(
+            "SELECT role FROM user_roles WHERE user_id = ?1",
+            params![user_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(role) = role {
+        return Ok(Role::parse(&role).unwrap_or(Role::Readonly));
+    }
+    let anyone: bool = conn.query_row("SELECT EXISTS (SELECT 1 FROM user_roles)", [], |row| {
+        row.get(0)
+    })?;
+    Ok(if anyone { Role::Readonly } else { Role::Admin })
+}
+
+/// The user's role for AppState; readonly if it can't be read
+pub(crate) fn load_role(user_id: &str) -> Role {
+    let role = get_db().and_then(|db| role_for_user(&db.conn(), user_id));
+    role.unwrap_or_else(|e| {
+        warn!("⚠️  Failed to load the user's role, using readonly: {}", e);
+        Role::Readonly
+    })
+}
+
+/// Hide the vehicle's cost from roles below manager
+pub(crate) fn redact_vehicle(role: Role, mut vehicle: Vehicle) -> Vehicle {
+    if !role.sees_costs() {
+        vehicle.cost = None;
+    }
+    vehicle
+}
+
+pub(crate) fn redact_vehicles(role: Role, vehicles: Vec<Vehicle>) -> Vec<Vehicle> {
+    vehicles
+        .into_iter()
+        .map(|vehicle| redact_vehicle(role, vehicle))
+        .collect()
+}
+
+/// Drop the gross profit figures from db_get_deals_stats for roles below manager
+pub(crate) fn redact_deals_stats(role: Role, mut stats: Value) -> Value {
+    if !role.sees_costs() {
+        if let Some(stats) = stats.as_object_mut() {
+            for key in ["grossProfit", "averageGrossProfit", "profitByMonth"] {
+                stats.remove(key);
+            }
+        }
+    }
+    stats
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserRole {
+    pub user_id: String,
+    pub role: Role,
+    pub updated_by: Option<String>,
+    pub updated_at: i64,
+}
+
+fn user_roles(conn: &Connection) -> SqlResult<Vec<UserRole>> {
+    let mut stmt = conn
+        .prepare("SELECT user_id, role, updated_by, updated_at FROM user_roles ORDER BY user_id")?;
+    let roles = stmt.query_map([], |row| {
+        let role: String = row.get(1)?;
+        Ok(UserRole {
+            user_id: row.get(0)?,
+            role: Role::parse(&role).unwrap_or(Role::Readonly),
+            updated_by: row.get(2)?,
+            updated_at: row.get(3)?,
+        })
+    })?;
+    roles.collect()
+}
+
+fn upsert_role(
+    conn: &Connection,
+    user_id: &str,
+    role: Role,
+    updated_by: &str,
+    now: i64,
+) -> SqlResult<()> {
+    conn.execute(
+        "INSERT INTO user_roles (user_id, role, updated_by, updated_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(user_id) DO UPDATE SET
+            role = excluded.role, updated_by = excluded.updated_by, updated_at = excluded.updated_at",
+        params![user_id, role.as_str(), updated_by, now],
+    )?;
+    Ok(())
+}
+
+/// Give target_user_id a role. The first call also records its caller as an admin, and the
+/// last admin can't be demoted
+fn assign_role(
+    conn: &Connection,
+    actor: &str,
+    target_user_id: &str,
+    role: Role,
+    now: i64,
+) -> Result<(), String> {
+    let tx = begin_write(conn, "user_roles").map_err(|e| e.to_string())?;
+    let first = user_roles(&tx).map_err(|e| e.to_string())?.is_empty();
+    if first {
+        upsert_role(&tx, actor, Role::Admin, actor, now).map_err(|e| e.to_string())?;
+    }
+    upsert_role(&tx, target_user_id, role, actor, now).map_err(|e| e.to_string())?;
+    let admins: i64 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM user_roles WHERE role = 'admin'",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if admins == 0 {
+        return Err("At least one user has to stay an admin".to_string());
+    }
+    record_audit(
+        &tx,
+        actor,
+        "set_user_role",
+        &json!({ "target_user_id": target_user_id, "role": role }),
+        now,
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_current_role(state: State<'_, AppState>) -> Role {
+    state.role()
+}
+
+/// Every user with a role
+#[tauri::command]
+pub fn get_user_roles(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<Vec<UserRole>, AuthError> {
+    track("get_user_roles", || {
+        state.require_user(user_id)?;
+        state.check_permission(Permission::Admin)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        user_roles(&db.conn()).map_err(|e| e.to_string().into())
+    })
+}
+
+#[tauri::command]
+pub fn set_user_role(
+    target_user_id: String,
+    role: Role,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<(), AuthError> {
+    track("set_user_role", || {
+        let user_id_value = state.require_user(user_id)?;
+        state.check_permission(Permission::Admin)?;
+        let target_user_id = target_user_id.trim();
+        if target_user_id.is_empty() {
+            return Err("A user id is required".to_string().into());
+        }
+        let db = db.get().map_err(|e| e.to_string())?;
+        assign_role(
+            &db.conn(),
+            &user_id_value,
+            target_user_id,
+            role,
+            Utc::now().timestamp_millis(),
+        )?;
+        info!("✅ Role set to {}", role);
+        if target_user_id == user_id_value {
+            state.set_role(Some(role));
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{
+        db_create_client, db_create_deal, db_create_vehicle, db_get_all_vehicles,
+        db_get_deals_with_details, db_get_vehicle, db_set_setting, db_update_vehicle,
+    };
+    use crate::db_busy::DbError;
+    use crate::field_projection::Listing;
+    use crate::secret_store::{self, SecretKey};
+    use crate::test_support::{make_client, make_deal, make_vehicle, TestApp, TEST_USER};
+
+    fn forbidden(required_role: Role) -> Result<(), AuthError> {
+        Err(AuthError::Forbidden { required_role })
+    }
+
+    #[test]
+    fn test_readonly_users_can_only_read() {
+        for command in ["db_get_all_vehicles", "db_get_deal", "db_search_clients"] {
+            assert_eq!(check_command(Role::Readonly, command), Ok(()));
+        }
+        assert_eq!(
+            check_command(Role::Readonly, "db_create_client"),
+            forbidden(Role::Sales)
+        );
+        assert_eq!(
+            check_command(Role::Readonly, "s3_upload_document"),
+            forbidden(Role::Sales)
+        );
+        assert_eq!(
+            check_command(Role::Readonly, "db_delete_vehicle"),
+            forbidden(Role::Manager)
+        );
+
+        // Only the frontend's own settings; the rest have admin-only setters
+        let app = TestApp::new();
+        app.sign_in_as(TEST_USER, Role::Readonly);
+        assert_eq!(
+            db_set_setting("validation_rules".into(), "[]".into(), app.state()),
+            Err(String::from(AuthError::Forbidden {
+                required_role: Role::Admin
+            }))
+        );
+        db_set_setting("last_sync_at".into(), "1".into(), app.state()).unwrap();
+    }
+
+    #[test]
+    fn test_every_command_is_classified() {
+        let main = include_str!("main.rs");
+        let start = main.find("generate_handler![").unwrap() + "generate_handler![".len();
+        let end = start + main[start..].find(']').unwrap();
+        let commands: Vec<&str> = main[start..end]
+            .lines()
+            .map(|line| line.split("//").next().unwrap().trim())
+            .map(|name| name.trim_end_matches(','))
+            .filter(|name| !name.is_empty())
+            .collect();
+        assert!(commands.len() > 300);
+
+        let unclassified: Vec<&str> = commands
+            .into_iter()
+            .filter(|command| command_permission(command).is_none())
+            .collect();
+        assert!(
+            unclassified.is_empty(),
+            "not in COMMAND_PERMISSIONS: {:?}",
+            unclassified
+        );
+        assert_eq!(
+            check_command(Role::Manager, "some_new_command"),
+            forbidden(Role::Admin)
+        );
+    }
+
+    #[test]
+    fn test_failed_authentication_then_admin_command() {
+        let _guard = secret_store::test_guard();
+        let _ = secret_store::remove(SecretKey::SessionToken);
+        let app = TestApp::new();
+        app.sign_in_as(TEST_USER, Role::Sales);
+
+        // A bogus token logs the sales user out, which leaves readonly rather than admin
+        assert!(app.state().authenticate("admin", "sess_bogus").is_err());
+        assert_eq!(app.state().role(), Role::Readonly);
+        for command in [
+            "db_clear_all_data",
+            "apply_retention_purge",
+            "db_execute_readonly_query",
+        ] {
+            assert_eq!(
+                check_command(app.state().role(), command),
+                forbidden(Role::Admin)
+            );
+        }
+        // Whatever user_id JS claims to be
+        assert!(set_user_role(
+            "u2".into(),
+            Role::Admin,
+            Some("admin".into()),
+            app.state(),
+            app.db()
+        )
+        .is_err());
+        assert!(user_roles(&app.conn()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sales_can_write_but_not_delete_or_see_costs() {
+        for command in ["db_create_vehicle", "db_update_deal", "db_create_document"] {
+            assert_eq!(check_command(Role::Sales, command), Ok(()));
+        }
+        for command in [
+            "db_delete_deal",
+            "s3_delete_document",
+            "db_get_vehicle_costs",
+        ] {
+            assert_eq!(
+                check_command(Role::Sales, command),
+                forbidden(Role::Manager)
+            );
+        }
+
+        let app = TestApp::new();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        app.sign_in_as(TEST_USER, Role::Sales);
+        let vehicle = db_get_vehicle("v1".into(), None, app.state(), app.db())
+            .unwrap()
+            .unwrap();
+        assert_eq!(vehicle.cost, None);
+        let Listing::Full(vehicles) =
+            db_get_all_vehicles(None, None, app.state(), app.db()).unwrap()
+        else {
+            panic!("expected full vehicles");
+        };
+        assert_eq!(vehicles[0].cost, None);
+
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_deal(
+            make_deal("d1", "c1", "v1"),
+            None,
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        let details = db_get_deals_with_details(None, app.state(), app.db()).unwrap();
+        assert_eq!(details[0].vehicle.as_ref().unwrap().id, "v1");
+        assert_eq!(details[0].vehicle.as_ref().unwrap().cost, None);
+
+        // Changing anything else keeps the hidden cost; changing the cost is refused
+        let updated = db_update_vehicle(
+            "v1".into(),
+            json!({ "mileage": 43_000 }),
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        assert_eq!(updated.cost, None);
+        let stored: Option<f64> = app
+            .conn()
+            .query_row("SELECT cost FROM vehicles WHERE id = 'v1'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(stored, Some(14_000.0));
+        assert_eq!(
+            db_update_vehicle("v1".into(), json!({ "cost": 1.0 }), app.state(), app.db())
+                .unwrap_err(),
+            DbError::Forbidden {
+                required_role: Role::Manager
+            }
+        );
+    }
+
+    #[test]
+    fn test_managers_see_costs_but_admin_commands_need_an_admin() {
+        for command in ["db_delete_client", "db_get_vehicle_cost_breakdown"] {
+            assert_eq!(check_command(Role::Manager, command), Ok(()));
+        }
+        for command in [
+            "db_clear_all_data",
+            "preview_retention_purge",
+            "apply_retention_purge",
+            "db_execute_readonly_query",
+            "set_user_role",
+        ] {
+            assert_eq!(
+                check_command(Role::Manager, command),
+                forbidden(Role::Admin)
+            );
+            assert_eq!(check_command(Role::Admin, command), Ok(()));
+        }
+
+        let app = TestApp::new();
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        db_create_deal(
+            make_deal("d1", "c1", "v1"),
+            None,
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        app.sign_in_as(TEST_USER, Role::Manager);
+        let vehicle = db_get_vehicle("v1".into(), None, app.state(), app.db())
+            .unwrap()
+            .unwrap();
+        assert_eq!(vehicle.cost, Some(14_000.0));
+        let details = db_get_deals_with_details(None, app.state(), app.db()).unwrap();
+        assert_eq!(details[0].vehicle.as_ref().unwrap().cost, Some(14_000.0));
+
+        let stats = json!({ "total": 1, "grossProfit": 4500.0, "profitByMonth": [] });
+        assert_eq!(redact_deals_stats(Role::Manager, stats.clone()), stats);
+        assert_eq!(
+            redact_deals_stats(Role::Sales, stats),
+            json!({ "total": 1 })
+        );
+    }
+
+    #[test]
+    fn test_everyone_is_an_admin_until_roles_are_set_up() {
+        let app = TestApp::new();
+        assert_eq!(role_for_user(&app.conn(), "anyone").unwrap(), Role::Admin);
+
+        set_user_role("u2".into(), Role::Sales, None, app.state(), app.db()).unwrap();
+        assert_eq!(role_for_user(&app.conn(), TEST_USER).unwrap(), Role::Admin);
+        assert_eq!(role_for_user(&app.conn(), "u2").unwrap(), Role::Sales);
+        // Users nobody gave a role to are readonly from now on
+        assert_eq!(
+            role_for_user(&app.conn(), "anyone").unwrap(),
+            Role::Readonly
+        );
+
+        // The only admin can't demote themselves
+        assert!(
+            set_user_role(TEST_USER.into(), Role::Manager, None, app.state(), app.db()).is_err()
+        );
+        assert_eq!(role_for_user(&app.conn(), TEST_USER).unwrap(), Role::Admin);
+        set_user_role("u2".into(), Role::Admin, None, app.state(), app.db()).unwrap();
+        set_user_role(TEST_USER.into(), Role::Manager, None, app.state(), app.db()).unwrap();
+        assert_eq!(app.state().role(), Role::Manager);
+        assert_eq!(
+            set_user_role("u3".into(), Role::Sales, None, app.state(), app.db()),
+            Err(AuthError::Forbidden {
+                required_role: Role::Admin
+            })
+        );
+        let roles = get_user_roles(None, app.state(), app.db()).unwrap();
+        assert_eq!(roles.len(), 2);
+    }
+}
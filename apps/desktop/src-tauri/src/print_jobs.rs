@@ -0,0 +1,756 @@
+// src-tauri/src/print_jobs.rs
+//
+// Print jobs sent straight to a printer, tracked file by file. submit_print_job returns
+// a job id right away; a background task hands each file to the OS spooler, then polls
+// the spooler until every file is completed, failed or cancelled, emitting
+// PRINT_JOB_PROGRESS_EVENT whenever something changes. Jobs live in managed state for the
+// app session and are pruned an hour after they finish.
+// The spooler is the Spooler trait: CUPS (lp/lpstat/cancel) on macOS and Linux. Windows
+// has no backend yet, so submissions there fail per file and batch_print_pdfs remains the
+// way to print.
+
+use chrono::Utc;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::database::new_row_id;
+use crate::telemetry::{track, track_async};
+
+/// Emitted with the whole PrintJob whenever one of its files changes state
+pub const PRINT_JOB_PROGRESS_EVENT: &str = "print-job-progress";
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Finished jobs are kept this long for get_print_job_status
+const KEEP_FINISHED_MS: i64 = 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrintOptions {
+    pub copies: Option<u32>,
+    /// Print on both sides (long edge)
+    #[serde(default)]
+    pub duplex: bool,
+    /// e.g. "1-3,7"
+    pub page_ranges: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PrintFileState {
+    /// Not handed to the spooler yet
+    Queued,
+    /// Accepted by the spooler, waiting or printing
+    Spooled,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl PrintFileState {
+    fn is_finished(self) -> bool {
+        matches!(
+            self,
+            PrintFileState::Completed | PrintFileState::Failed | PrintFileState::Cancelled
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PrintFile {
+    pub path: String,
+    pub state: PrintFileState,
+    /// Job id given by the spooler once the file is spooled
+    pub spooler_job_id: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PrintJob {
+    pub id: String,
+    pub printer: String,
+    pub options: PrintOptions,
+    pub files: Vec<PrintFile>,
+    pub created_at: i64,
+    /// Set once every file is completed, failed or cancelled
+    pub finished_at: Option<i64>,
+}
+
+impl PrintJob {
+    /// Move a file to a new state. Finished files never change again, and a queued
+    /// file only leaves the queue through the submitter or a cancel.
+    fn set_state(
+        &mut self,
+        index: usize,
+        state: PrintFileState,
+        error: Option<String>,
+        now: i64,
+    ) -> bool {
+        let Some(file) = self.files.get_mut(index) else {
+            return false;
+        };
+        if file.state.is_finished() || file.state == state {
+            return false;
+        }
+        file.state = state;
+        file.error = error;
+        if self.finished_at.is_none() && self.files.iter().all(|file| file.state.is_finished()) {
+            self.finished_at = Some(now);
+        }
+        true
+    }
+}
+
+/// What the spooler reports for a job it accepted
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpoolerState {
+    Pending,
+    Completed,
+    Failed(String),
+}
+
+/// The OS print spooler; tests use a scripted one
+pub trait Spooler: Send + Sync + 'static {
+    /// Hand a file to the printer's queue; returns the spooler's job id
+    fn submit(&self, printer: &str, path: &str, options: &PrintOptions) -> Result<String, String>;
+    fn state(&self, printer: &str, job_id: &str) -> Result<SpoolerState, String>;
+    fn cancel(&self, job_id: &str) -> Result<(), String>;
+}
+
+#[cfg(unix)]
+mod cups {
+    use std::process::Command;
+
+    use super::{PrintOptions, Spooler, SpoolerState};
+
+    pub struct CupsSpooler;
+
+    fn run(program: &str, args: &[String]) -> Result<String, String> {
+        let output = Command::new(program)
+            .args(args)
+            .output()
+            .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("{} failed: {}", program, stderr.trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    impl Spooler for CupsSpooler {
+        fn submit(
+            &self,
+            printer: &str,
+            path: &str,
+            options: &PrintOptions,
+        ) -> Result<String, String> {
+            let mut args = vec!["-d".to_string(), printer.to_string()];
+            if let Some(copies) = options.copies.filter(|copies| *copies > 1) {
+                args.extend(["-n".to_string(), copies.to_string()]);
+            }
+            if options.duplex {
+                args.extend(["-o".to_string(), "sides=two-sided-long-edge".to_string()]);
+            }
+            if let Some(ranges) = &options.page_ranges {
+                args.extend(["-P".to_string(), ranges.clone()]);
+            }
+            args.extend(["--".to_string(), path.to_string()]);
+
+            let output = run("lp", &args)?;
+            parse_request_id(&output)
+                .ok_or_else(|| format!("Unexpected output from lp: {}", output.trim()))
+        }
+
+        fn state(&self, printer: &str, job_id: &str) -> Result<SpoolerState, String> {
+            let args = |which: &str| {
+                ["-W", which, "-o", printer]
+                    .iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<_>>()
+            };
+            if lists_job(&run("lpstat", &args("not-completed"))?, job_id) {
+                return Ok(SpoolerState::Pending);
+            }
+            if lists_job(&run("lpstat", &args("completed"))?, job_id) {
+                return Ok(SpoolerState::Completed);
+            }
+            Ok(SpoolerState::Failed(
+                "The job is no longer in the print queue".to_string(),
+            ))
+        }
+
+        fn cancel(&self, job_id: &str) -> Result<(), String> {
+            run("cancel", &[job_id.to_string()]).map(|_| ())
+        }
+    }
+
+    /// "request id is Office-42 (1 file(s))" -> "Office-42"
+    pub(super) fn parse_request_id(output: &str) -> Option<String> {
+        let rest = output.trim().strip_prefix("request id is ")?;
+        rest.split_whitespace().next().map(str::to_string)
+    }
+
+    /// Whether lpstat -o output has a line for the job (the id is the first column)
+    pub(super) fn lists_job(output: &str, job_id: &str) -> bool {
+        output
+            .lines()
+            .any(|line| line.split_whitespace().next() == Some(job_id))
+    }
+}
+
+#[cfg(not(unix))]
+struct UnsupportedSpooler;
+
+#[cfg(not(unix))]
+impl Spooler for UnsupportedSpooler {
+    fn submit(&self, _: &str, _: &str, _: &PrintOptions) -> Result<String, String> {
+        Err("Direct printing isn't available on this platform yet".to_string())
+    }
+
+    fn state(&self, _: &str, _: &str) -> Result<SpoolerState, String> {
+        Err("Direct printing isn't available on this platform yet".to_string())
+    }
+
+    fn cancel(&self, _: &str) -> Result<(), String> {
+        Err("Direct printing isn't available on this platform yet".to_string())
+    }
+}
+
+fn system_spooler() -> Box<dyn Spooler> {
+    #[cfg(unix)]
+    {
+        Box::new(cups::CupsSpooler)
+    }
+    #[cfg(not(unix))]
+    {
+        Box::new(UnsupportedSpooler)
+    }
+}
+
+/// Print jobs of this app session; managed by Tauri (main.rs)
+pub struct PrintJobs {
+    jobs: Mutex<HashMap<String, PrintJob>>,
+    spooler: Box<dyn Spooler>,
+}
+
+impl Default for PrintJobs {
+    fn default() -> Self {
+        PrintJobs::new(system_spooler())
+    }
+}
+
+impl PrintJobs {
+    pub fn new(spooler: Box<dyn Spooler>) -> Self {
+        PrintJobs {
+            jobs: Mutex::new(HashMap::new()),
+            spooler,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, PrintJob>> {
+        self.jobs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn get(&self, id: &str) -> Option<PrintJob> {
+        self.lock().get(id).cloned()
+    }
+
+    fn create(
+        &self,
+        files: Vec<String>,
+        printer: String,
+        options: PrintOptions,
+        now: i64,
+    ) -> PrintJob {
+        let job = PrintJob {
+            id: format!("print_{}", new_row_id()),
+            printer,
+            options,
+            files: files
+                .into_iter()
+                .map(|path| PrintFile {
+                    path,
+                    state: PrintFileState::Queued,
+                    spooler_job_id: None,
+                    error: None,
+                })
+                .collect(),
+            created_at: now,
+            finished_at: None,
+        };
+
+        let mut jobs = self.lock();
+        prune(&mut jobs, now);
+        jobs.insert(job.id.clone(), job.clone());
+        job
+    }
+
+    /// Hand the job's queued files to the spooler one by one; the lock isn't held while
+    /// the spooler runs, so a cancel can still catch the files that haven't gone yet
+    fn submit_queued(&self, id: &str, now: impl Fn() -> i64) {
+        loop {
+            let next = self.lock().get(id).and_then(|job| {
+                job.files
+                    .iter()
+                    .position(|file| file.state == PrintFileState::Queued)
+                    .map(|index| {
+                        let file = &job.files[index];
+                        (
+                            index,
+                            file.path.clone(),
+                            job.printer.clone(),
+                            job.options.clone(),
+                        )
+                    })
+            });
+            let Some((index, path, printer, options)) = next else {
+                return;
+            };
+
+            let submitted = if Path::new(&path).is_file() {
+                self.spooler.submit(&printer, &path, &options)
+            } else {
+                Err(format!("File not found: {}", path))
+            };
+
+            let mut jobs = self.lock();
+            let Some(job) = jobs.get_mut(id) else {
+                return;
+            };
+            match submitted {
+                Ok(spooler_id) => {
+                    if job.files[index].state == PrintFileState::Cancelled {
+                        // Cancelled while the spooler had it
+                        drop(jobs);
+                        if let Err(e) = self.spooler.cancel(&spooler_id) {
+                            warn!("⚠️  Could not cancel print job {}: {}", spooler_id, e);
+                        }
+                        continue;
+                    }
+                    job.files[index].spooler_job_id = Some(spooler_id);
+                    job.set_state(index, PrintFileState::Spooled, None, now());
+                }
+                Err(e) => {
+                    error!("❌ Failed to print {}: {}", path, e);
+                    job.set_state(index, PrintFileState::Failed, Some(e), now());
+                }
+            }
+        }
+    }
+
+    /// Ask the spooler about every spooled file; returns the job and whether it changed
+    fn refresh(&self, id: &str, now: i64) -> Option<(PrintJob, bool)> {
+        let (printer, spooled) = {
+            let jobs = self.lock();
+            let job = jobs.get(id)?;
+            let spooled: Vec<(usize, String)> = job
+                .files
+                .iter()
+                .enumerate()
+                .filter(|(_, file)| file.state == PrintFileState::Spooled)
+                .filter_map(|(index, file)| Some((index, file.spooler_job_id.clone()?)))
+                .collect();
+            (job.printer.clone(), spooled)
+        };
+
+        let states: Vec<(usize, Result<SpoolerState, String>)> = spooled
+            .into_iter()
+            .map(|(index, spooler_id)| (index, self.spooler.state(&printer, &spooler_id)))
+            .collect();
+
+        let mut jobs = self.lock();
+        let job = jobs.get_mut(id)?;
+        let mut changed = false;
+        for (index, state) in states {
+            changed |= match state {
+                Ok(SpoolerState::Pending) => false,
+                Ok(SpoolerState::Completed) => {
+                    job.set_state(index, PrintFileState::Completed, None, now)
+                }
+                Ok(SpoolerState::Failed(e)) => {
+                    job.set_state(index, PrintFileState::Failed, Some(e), now)
+                }
+                Err(e) => {
+                    // Often transient (spooler restarting); try again on the next poll
+                    warn!("⚠️  Could not read print queue of {}: {}", printer, e);
+                    false
+                }
+            };
+        }
+        Some((job.clone(), changed))
+    }
+
+    /// Cancel every file that hasn't finished. Queued files are dropped here; spooled
+    /// ones are cancelled in the spooler, and stay spooled if it refuses.
+    fn cancel(&self, id: &str, now: i64) -> Result<PrintJob, String> {
+        let spooled: Vec<(usize, String)> = {
+            let mut jobs = self.lock();
+            let job = jobs
+                .get_mut(id)
+                .ok_or_else(|| "Print job not found".to_string())?;
+            for index in 0..job.files.len() {
+                if job.files[index].state == PrintFileState::Queued {
+                    job.set_state(index, PrintFileState::Cancelled, None, now);
+                }
+            }
+            job.files
+                .iter()
+                .enumerate()
+                .filter(|(_, file)| file.state == PrintFileState::Spooled)
+                .filter_map(|(index, file)| Some((index, file.spooler_job_id.clone()?)))
+                .collect()
+        };
+
+        let results: Vec<(usize, Result<(), String>)> = spooled
+            .into_iter()
+            .map(|(index, spooler_id)| (index, self.spooler.cancel(&spooler_id)))
+            .collect();
+
+        let mut jobs = self.lock();
+        let job = jobs
+            .get_mut(id)
+            .ok_or_else(|| "Print job not found".to_string())?;
+        for (index, result) in results {
+            match result {
+                Ok(()) => {
+                    job.set_state(index, PrintFileState::Cancelled, None, now);
+                }
+                Err(e) => {
+                    warn!("⚠️  Could not cancel {}: {}", job.files[index].path, e);
+                    job.files[index].error = Some(e);
+                }
+            }
+        }
+        Ok(job.clone())
+    }
+}
+
+/// Drop jobs that finished more than KEEP_FINISHED_MS ago
+fn prune(jobs: &mut HashMap<String, PrintJob>, now: i64) {
+    jobs.retain(|_, job| {
+        job.finished_at
+            .is_none_or(|finished_at| now - finished_at < KEEP_FINISHED_MS)
+    });
+}
+
+fn now_ms() -> i64 {
+    Utc::now().timestamp_millis()
+}
+
+/// Submit the job's files, then poll until it finishes
+async fn run_print_job(app: AppHandle, id: String) {
+    let submit_app = app.clone();
+    let submit_id = id.clone();
+    let submitted = tauri::async_runtime::spawn_blocking(move || {
+        let jobs = submit_app.state::<PrintJobs>();
+        jobs.submit_queued(&submit_id, now_ms);
+        jobs.get(&submit_id)
+    })
+    .await;
+    if let Ok(Some(job)) = submitted {
+        let _ = app.emit(PRINT_JOB_PROGRESS_EVENT, &job);
+    }
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let poll_app = app.clone();
+        let poll_id = id.clone();
+        let refreshed = tauri::async_runtime::spawn_blocking(move || {
+            poll_app.state::<PrintJobs>().refresh(&poll_id, now_ms())
+        })
+        .await;
+
+        let Ok(Some((job, changed))) = refreshed else {
+            return;
+        };
+        if changed {
+            let _ = app.emit(PRINT_JOB_PROGRESS_EVENT, &job);
+        }
+        if job.finished_at.is_some() {
+            info!("🖨️  Print job {} finished", job.id);
+            return;
+        }
+    }
+}
+
+/// Send files to a printer; returns the job id to poll or listen for
+#[tauri::command]
+pub async fn submit_print_job(
+    files: Vec<String>,
+    printer: String,
+    options: Option<PrintOptions>,
+    app: AppHandle,
+    jobs: State<'_, PrintJobs>,
+) -> Result<String, String> {
+    track_async("submit_print_job", async {
+        if files.is_empty() {
+            return Err("No files to print".to_string());
+        }
+        if printer.trim().is_empty() {
+            return Err("A printer is required".to_string());
+        }
+
+        info!("🖨️  Printing {} files on {}", files.len(), printer);
+        let job = jobs.create(files, printer, options.unwrap_or_default(), now_ms());
+        tauri::async_runtime::spawn(run_print_job(app, job.id.clone()));
+        Ok(job.id)
+    })
+    .await
+}
+
+/// Current state of each file, asking the spooler first
+#[tauri::command]
+pub async fn get_print_job_status(id: String, app: AppHandle) -> Result<PrintJob, String> {
+    track_async("get_print_job_status", async {
+        tauri::async_runtime::spawn_blocking(move || {
+            app.state::<PrintJobs>()
+                .refresh(&id, now_ms())
+                .map(|(job, _)| job)
+                .ok_or_else(|| "Print job not found".to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn cancel_print_job(
+    id: String,
+    app: AppHandle,
+    jobs: State<'_, PrintJobs>,
+) -> Result<PrintJob, String> {
+    track("cancel_print_job", || {
+        let job = jobs.cancel(&id, now_ms())?;
+        let _ = app.emit(PRINT_JOB_PROGRESS_EVENT, &job);
+        Ok(job)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::sync::Arc;
+
+    /// Spooler whose job states the test sets
+    #[derive(Default)]
+    struct ScriptedSpooler {
+        submitted: Mutex<Vec<String>>,
+        states: Mutex<HashMap<String, SpoolerState>>,
+        cancelled: Mutex<Vec<String>>,
+        refuse: Mutex<Vec<String>>,
+    }
+
+    impl Spooler for Arc<ScriptedSpooler> {
+        fn submit(&self, _: &str, path: &str, _: &PrintOptions) -> Result<String, String> {
+            if self.refuse.lock().unwrap().iter().any(|p| p == path) {
+                return Err("printer offline".to_string());
+            }
+            let mut submitted = self.submitted.lock().unwrap();
+            submitted.push(path.to_string());
+            let id = format!("office-{}", submitted.len());
+            self.states
+                .lock()
+                .unwrap()
+                .insert(id.clone(), SpoolerState::Pending);
+            Ok(id)
+        }
+
+        fn state(&self, _: &str, job_id: &str) -> Result<SpoolerState, String> {
+            self.states
+                .lock()
+                .unwrap()
+                .get(job_id)
+                .cloned()
+                .ok_or_else(|| "unknown job".to_string())
+        }
+
+        fn cancel(&self, job_id: &str) -> Result<(), String> {
+            self.cancelled.lock().unwrap().push(job_id.to_string());
+            Ok(())
+        }
+    }
+
+    impl ScriptedSpooler {
+        fn set(&self, job_id: &str, state: SpoolerState) {
+            self.states
+                .lock()
+                .unwrap()
+                .insert(job_id.to_string(), state);
+        }
+    }
+
+    fn pdfs(dir: &Path, names: &[&str]) -> Vec<String> {
+        names
+            .iter()
+            .map(|name| {
+                let path = dir.join(name);
+                std::fs::write(&path, b"%PDF-1.4").unwrap();
+                path.to_string_lossy().into_owned()
+            })
+            .collect()
+    }
+
+    fn registry() -> (Arc<ScriptedSpooler>, PrintJobs) {
+        let spooler = Arc::new(ScriptedSpooler::default());
+        (spooler.clone(), PrintJobs::new(Box::new(spooler)))
+    }
+
+    fn states(job: &PrintJob) -> Vec<PrintFileState> {
+        job.files.iter().map(|file| file.state).collect()
+    }
+
+    #[test]
+    fn test_files_move_from_queued_to_completed() {
+        let dir = temp_dir("print-jobs", "completed");
+        let (spooler, jobs) = registry();
+        let files = pdfs(&dir, &["a.pdf", "b.pdf"]);
+        let job = jobs.create(files, "Office".into(), PrintOptions::default(), 1_000);
+        assert_eq!(states(&job), [PrintFileState::Queued; 2]);
+
+        jobs.submit_queued(&job.id, || 2_000);
+        let job = jobs.get(&job.id).unwrap();
+        assert_eq!(states(&job), [PrintFileState::Spooled; 2]);
+        assert_eq!(job.files[1].spooler_job_id.as_deref(), Some("office-2"));
+
+        let (_, changed) = jobs.refresh(&job.id, 3_000).unwrap();
+        assert!(!changed);
+
+        spooler.set("office-1", SpoolerState::Completed);
+        let (job, changed) = jobs.refresh(&job.id, 4_000).unwrap();
+        assert!(changed);
+        assert_eq!(
+            states(&job),
+            [PrintFileState::Completed, PrintFileState::Spooled]
+        );
+        assert_eq!(job.finished_at, None);
+
+        spooler.set("office-2", SpoolerState::Failed("paper jam".into()));
+        let (job, _) = jobs.refresh(&job.id, 5_000).unwrap();
+        assert_eq!(
+            states(&job),
+            [PrintFileState::Completed, PrintFileState::Failed]
+        );
+        assert_eq!(job.files[1].error.as_deref(), Some("paper jam"));
+        assert_eq!(job.finished_at, Some(5_000));
+
+        // Finished files don't change again
+        spooler.set("office-1", SpoolerState::Failed("late".into()));
+        let (_, changed) = jobs.refresh(&job.id, 6_000).unwrap();
+        assert!(!changed);
+    }
+
+    #[test]
+    fn test_a_failed_submission_does_not_stop_the_rest() {
+        let dir = temp_dir("print-jobs", "failed_submission");
+        let (spooler, jobs) = registry();
+        let mut files = pdfs(&dir, &["a.pdf", "b.pdf"]);
+        spooler.refuse.lock().unwrap().push(files[0].clone());
+        files.insert(1, dir.join("missing.pdf").to_string_lossy().into_owned());
+
+        let job = jobs.create(files, "Office".into(), PrintOptions::default(), 1_000);
+        jobs.submit_queued(&job.id, || 2_000);
+        let job = jobs.get(&job.id).unwrap();
+
+        assert_eq!(
+            states(&job),
+            [
+                PrintFileState::Failed,
+                PrintFileState::Failed,
+                PrintFileState::Spooled
+            ]
+        );
+        assert_eq!(job.files[0].error.as_deref(), Some("printer offline"));
+        assert!(job.files[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("File not found"));
+    }
+
+    #[test]
+    fn test_cancel_drops_queued_files_and_cancels_spooled_ones() {
+        let dir = temp_dir("print-jobs", "cancel");
+        let (spooler, jobs) = registry();
+        let files = pdfs(&dir, &["a.pdf", "b.pdf", "c.pdf"]);
+        let job = jobs.create(files, "Office".into(), PrintOptions::default(), 1_000);
+
+        // Only the first file reached the spooler before the cancel
+        {
+            let mut all = jobs.lock();
+            let job = all.get_mut(&job.id).unwrap();
+            job.files[0].spooler_job_id = Some("office-9".into());
+            job.set_state(0, PrintFileState::Spooled, None, 1_500);
+        }
+
+        let job = jobs.cancel(&job.id, 2_000).unwrap();
+        assert_eq!(states(&job), [PrintFileState::Cancelled; 3]);
+        assert_eq!(*spooler.cancelled.lock().unwrap(), ["office-9"]);
+        assert_eq!(job.finished_at, Some(2_000));
+
+        // Nothing left for the submitter
+        jobs.submit_queued(&job.id, || 3_000);
+        assert!(spooler.submitted.lock().unwrap().is_empty());
+        assert!(jobs.cancel("print_unknown", 3_000).is_err());
+    }
+
+    #[test]
+    fn test_finished_jobs_are_pruned_after_an_hour() {
+        let dir = temp_dir("print-jobs", "prune");
+        let (_, jobs) = registry();
+        let finished = jobs.create(
+            pdfs(&dir, &["a.pdf"]),
+            "Office".into(),
+            PrintOptions::default(),
+            0,
+        );
+        jobs.cancel(&finished.id, 1_000).unwrap();
+        let running = jobs.create(
+            pdfs(&dir, &["b.pdf"]),
+            "Office".into(),
+            PrintOptions::default(),
+            0,
+        );
+
+        jobs.create(
+            Vec::new(),
+            "Office".into(),
+            PrintOptions::default(),
+            1_000 + KEEP_FINISHED_MS - 1,
+        );
+        assert!(jobs.get(&finished.id).is_some());
+
+        jobs.create(
+            Vec::new(),
+            "Office".into(),
+            PrintOptions::default(),
+            1_000 + KEEP_FINISHED_MS,
+        );
+        assert!(jobs.get(&finished.id).is_none());
+        assert!(jobs.get(&running.id).is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cups_output_is_parsed() {
+        assert_eq!(
+            cups::parse_request_id("request id is Office-42 (1 file(s))\n").as_deref(),
+            Some("Office-42")
+        );
+        assert_eq!(
+            cups::parse_request_id("lp: Error - no default destination"),
+            None
+        );
+
+        let lpstat = "Office-41  dealer  10240  Mon 12 Oct 2026 09:00:00\n\
+                      Office-42  dealer  20480  Mon 12 Oct 2026 09:01:00\n";
+        assert!(cups::lists_job(lpstat, "Office-42"));
+        assert!(!cups::lists_job(lpstat, "Office-4"));
+    }
+}
@@ -0,0 +1,506 @@
+// src-tauri/src/quickbooks.rs
+//
+// Export sold deals for QuickBooks, so the accountant stops re-keying them by hand.
+// IIF: one INVOICE per deal. The TRNS line debits receivables for what the customer owes;
+// SPL lines credit sales, sales tax and doc fees and debit the trade-in, so each
+// transaction balances to zero. For financed deals the sale split's memo names the lender.
+// CSV: one row per deal for anything else.
+// All money is handled in integer cents, read from the deals' *_cents columns (money.rs).
+// Account names come from settings (quickbooks_*_account) so they match the dealer's chart.
+
+use chrono::NaiveDate;
+use log::info;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::{db_get_setting, DEAL_STATUS_SOLD};
+use crate::datetime::{dealer_timezone, local_date};
+use crate::reporting::{run_report_job, write_report_file};
+use crate::settings_cache::set_setting;
+use crate::telemetry::track_async;
+
+const RECEIVABLE_ACCOUNT_SETTING: &str = "quickbooks_receivable_account";
+const SALES_ACCOUNT_SETTING: &str = "quickbooks_sales_account";
+const SALES_TAX_ACCOUNT_SETTING: &str = "quickbooks_sales_tax_account";
+const DOC_FEE_ACCOUNT_SETTING: &str = "quickbooks_doc_fee_account";
+const TRADE_IN_ACCOUNT_SETTING: &str = "quickbooks_trade_in_account";
+
+const LINE_ENDING: &str = "\r\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuickBooksFormat {
+    Iif,
+    Csv,
+}
+
+/// Account names used in the IIF export
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuickBooksAccounts {
+    pub receivable: String,
+    pub sales: String,
+    pub sales_tax: String,
+    pub doc_fee: String,
+    pub trade_in: String,
+}
+
+impl Default for QuickBooksAccounts {
+    fn default() -> Self {
+        Self {
+            receivable: "Accounts Receivable".to_string(),
+            sales: "Vehicle Sales".to_string(),
+            sales_tax: "Sales Tax Payable".to_string(),
+            doc_fee: "Documentation Fees".to_string(),
+            trade_in: "Trade-In Inventory".to_string(),
+        }
+    }
+}
+
+/// Sums over the exported deals, in cents, for reconciling against QuickBooks
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct QuickBooksTotals {
+    pub deal_count: usize,
+    pub sale_cents: i64,
+    pub sales_tax_cents: i64,
+    pub doc_fee_cents: i64,
+    pub trade_in_cents: i64,
+    /// Sale + tax + doc fee - trade-in (the receivable)
+    pub total_cents: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QuickBooksExport {
+    pub path: String,
+    pub format: QuickBooksFormat,
+    pub totals: QuickBooksTotals,
+}
+
+/// One sold deal with the client and vehicle details the export needs
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ExportDeal {
+    deal_id: String,
+    date: NaiveDate,
+    customer: String,
+    vehicle: String,
+    vin: String,
+    stock_number: String,
+    /// Name of the lender that bought the contract, empty for cash deals
+    lender: String,
+    sale_cents: i64,
+    sales_tax_cents: i64,
+    doc_fee_cents: i64,
+    trade_in_cents: i64,
+}
+
+impl ExportDeal {
+    fn total_cents(&self) -> i64 {
+        self.sale_cents + self.sales_tax_cents + self.doc_fee_cents - self.trade_in_cents
+    }
+}
+
+/// Export the user's sold deals with a sale date in [start_ms, end_ms] to output_path
+/// Runs as a report job (see reporting.rs): job_id lets the UI cancel it, in which case
+/// no file is written.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_deals_quickbooks(
+    user_id: Option<String>,
+    start_ms: i64,
+    end_ms: i64,
+    format: QuickBooksFormat,
+    output_path: String,
+    job_id: Option<String>,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<QuickBooksExport, String> {
+    let user_id_value = state.require_user(user_id)?;
+
+    track_async("export_deals_quickbooks", async move {
+        if end_ms < start_ms {
+            return Err("End date is before start date".to_string());
+        }
+        let path = PathBuf::from(&output_path);
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            if !parent.is_dir() {
+                return Err(format!("Folder does not exist: {}", parent.display()));
+            }
+        }
+        let accounts = match format {
+            QuickBooksFormat::Iif => Some(get_quickbooks_accounts()?),
+            QuickBooksFormat::Csv => None,
+        };
+
+        let owner = user_id_value.clone();
+        let totals = run_report_job(
+            job_id,
+            "export_deals_quickbooks",
+            &owner,
+            window.label(),
+            move |job, conn| {
+                let deals = sold_deals(conn, &user_id_value, start_ms, end_ms)?;
+                let content = match &accounts {
+                    Some(accounts) => to_iif(&deals, accounts),
+                    None => to_csv(&deals),
+                };
+                write_report_file(job, &path, content.as_bytes())?;
+                Ok(totals(&deals))
+            },
+        )
+        .await?;
+
+        info!(
+            "✅ Exported {} sold deals for QuickBooks ({:?})",
+            totals.deal_count, format
+        );
+        Ok(QuickBooksExport {
+            path: output_path,
+            format,
+            totals,
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn get_quickbooks_accounts() -> Result<QuickBooksAccounts, String> {
+    let defaults = QuickBooksAccounts::default();
+    let setting = |key: &str, default: String| -> Result<String, String> {
+        Ok(db_get_setting(key.to_string())?
+            .filter(|value| !value.trim().is_empty())
+            .unwrap_or(default))
+    };
+
+    Ok(QuickBooksAccounts {
+        receivable: setting(RECEIVABLE_ACCOUNT_SETTING, defaults.receivable)?,
+        sales: setting(SALES_ACCOUNT_SETTING, defaults.sales)?,
+        sales_tax: setting(SALES_TAX_ACCOUNT_SETTING, defaults.sales_tax)?,
+        doc_fee: setting(DOC_FEE_ACCOUNT_SETTING, defaults.doc_fee)?,
+        trade_in: setting(TRADE_IN_ACCOUNT_SETTING, defaults.trade_in)?,
+    })
+}
+
+#[tauri::command]
+pub fn set_quickbooks_accounts(accounts: QuickBooksAccounts) -> Result<(), String> {
+    for (key, value) in [
+        (RECEIVABLE_ACCOUNT_SETTING, &accounts.receivable),
+        (SALES_ACCOUNT_SETTING, &accounts.sales),
+        (SALES_TAX_ACCOUNT_SETTING, &accounts.sales_tax),
+        (DOC_FEE_ACCOUNT_SETTING, &accounts.doc_fee),
+        (TRADE_IN_ACCOUNT_SETTING, &accounts.trade_in),
+    ] {
+        if value.trim().is_empty() {
+            return Err("Account names can't be empty".to_string());
+        }
+        set_setting(key, value.trim())?;
+    }
+    Ok(())
+}
+
+fn sold_deals(
+    conn: &Connection,
+    user_id: &str,
+    start_ms: i64,
+    end_ms: i64,
+) -> Result<Vec<ExportDeal>, String> {
+    // Dates are the dealer's calendar days (datetime.rs)
+    let tz = dealer_timezone(conn);
+    let mut stmt = conn
+        .prepare(
+            "SELECT d.id, COALESCE(d.sale_date, d.created_at) AS sold_at,
+                    COALESCE(d.sale_amount_cents, d.total_amount_cents), d.sales_tax_cents,
+                    d.doc_fee_cents, d.trade_in_value_cents,
+                    c.first_name, c.last_name, v.year, v.make, v.model, v.vin, v.stock_number,
+                    l.name
+             FROM deals d
+             LEFT JOIN clients c ON c.id = d.client_id
+             LEFT JOIN vehicles v ON v.id = d.vehicle_id
+             LEFT JOIN lenders l ON l.id = d.lender_id
+             WHERE d.user_id = ?1 AND d.status = ?2 AND sold_at BETWEEN ?3 AND ?4
+             ORDER BY sold_at, d.id",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map(
+            params![user_id, DEAL_STATUS_SOLD, start_ms, end_ms],
+            |row| {
+                let sold_at: i64 = row.get(1)?;
+                let customer = [row.get::<_, Option<String>>(6)?, row.get(7)?]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let vehicle = match row.get::<_, Option<i32>>(8)? {
+                    Some(year) => format!(
+                        "{} {} {}",
+                        year,
+                        row.get::<_, String>(9)?,
+                        row.get::<_, String>(10)?
+                    ),
+                    None => String::new(),
+                };
+
+                Ok(ExportDeal {
+                    deal_id: row.get(0)?,
+                    date: local_date(sold_at, &tz).unwrap_or_default(),
+                    customer,
+                    vehicle,
+                    vin: row.get::<_, Option<String>>(11)?.unwrap_or_default(),
+                    stock_number: row.get::<_, Option<String>>(12)?.unwrap_or_default(),
+                    lender: row.get::<_, Option<String>>(13)?.unwrap_or_default(),
+                    sale_cents: row.get(2)?,
+                    sales_tax_cents: row.get::<_, Option<i64>>(3)?.unwrap_or(0),
+                    doc_fee_cents: row.get::<_, Option<i64>>(4)?.unwrap_or(0),
+                    trade_in_cents: row.get::<_, Option<i64>>(5)?.unwrap_or(0),
+                })
+            },
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(rows)
+}
+
+/// "1234.5" style amount for cents, with a leading minus for credits
+pub(crate) fn format_cents(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+}
+
+fn totals(deals: &[ExportDeal]) -> QuickBooksTotals {
+    deals
+        .iter()
+        .fold(QuickBooksTotals::default(), |mut totals, deal| {
+            totals.deal_count += 1;
+            totals.sale_cents += deal.sale_cents;
+            totals.sales_tax_cents += deal.sales_tax_cents;
+            totals.doc_fee_cents += deal.doc_fee_cents;
+            totals.trade_in_cents += deal.trade_in_cents;
+            totals.total_cents += deal.total_cents();
+            totals
+        })
+}
+
+fn to_iif(deals: &[ExportDeal], accounts: &QuickBooksAccounts) -> String {
+    let mut lines = vec![
+        "!TRNS\tTRNSID\tTRNSTYPE\tDATE\tACCNT\tNAME\tAMOUNT\tDOCNUM\tMEMO".to_string(),
+        "!SPL\tSPLID\tTRNSTYPE\tDATE\tACCNT\tNAME\tAMOUNT\tDOCNUM\tMEMO".to_string(),
+        "!ENDTRNS".to_string(),
+    ];
+
+    for deal in deals {
+        let date = deal.date.format("%m/%d/%Y").to_string();
+        let line = |kind: &str, account: &str, cents: i64, memo: &str| {
+            [
+                kind,
+                "",
+                "INVOICE",
+                &date,
+                &iif_field(account),
+                &iif_field(&deal.customer),
+                &format_cents(cents),
+                &iif_field(&deal.deal_id),
+                &iif_field(memo),
+            ]
+            .join("\t")
+        };
+
+        let memo = [deal.vehicle.as_str(), deal.vin.as_str()]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(" VIN ");
+        lines.push(line(
+            "TRNS",
+            &accounts.receivable,
+            deal.total_cents(),
+            &memo,
+        ));
+
+        let sale_memo = if deal.lender.is_empty() {
+            "Vehicle sale".to_string()
+        } else {
+            format!("Vehicle sale, financed by {}", deal.lender)
+        };
+
+        // Credits are negative in IIF; the trade-in is a debit against what's owed
+        let splits = [
+            (&accounts.sales, -deal.sale_cents, sale_memo.as_str()),
+            (&accounts.sales_tax, -deal.sales_tax_cents, "Sales tax"),
+            (&accounts.doc_fee, -deal.doc_fee_cents, "Documentation fee"),
+            (
+                &accounts.trade_in,
+                deal.trade_in_cents,
+                "Trade-in allowance",
+            ),
+        ];
+        for (account, cents, memo) in splits {
+            if cents != 0 {
+                lines.push(line("SPL", account, cents, memo));
+            }
+        }
+        lines.push("ENDTRNS".to_string());
+    }
+
+    lines.join(LINE_ENDING) + LINE_ENDING
+}
+
+fn to_csv(deals: &[ExportDeal]) -> String {
+    let mut lines = vec![[
+        "Date",
+        "Deal ID",
+        "Customer",
+        "Vehicle",
+        "VIN",
+        "Stock Number",
+        "Sale Amount",
+        "Sales Tax",
+        "Doc Fee",
+        "Trade-In",
+        "Total",
+        "Lender",
+    ]
+    .join(",")];
+
+    for deal in deals {
+        let row = [
+            deal.date.format("%Y-%m-%d").to_string(),
+            csv_field(&deal.deal_id),
+            csv_field(&deal.customer),
+            csv_field(&deal.vehicle),
+            csv_field(&deal.vin),
+            csv_field(&deal.stock_number),
+            format_cents(deal.sale_cents),
+            format_cents(deal.sales_tax_cents),
+            format_cents(deal.doc_fee_cents),
+            format_cents(deal.trade_in_cents),
+            format_cents(deal.total_cents()),
+            csv_field(&deal.lender),
+        ];
+        lines.push(row.join(","));
+    }
+
+    lines.join(LINE_ENDING) + LINE_ENDING
+}
+
+/// IIF is tab-separated with no escaping, so tabs, newlines and quotes are dropped
+fn iif_field(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c == '\t' || c == '\r' || c == '\n' {
+                ' '
+            } else {
+                c
+            }
+        })
+        .filter(|c| *c != '"')
+        .collect()
+}
+
+/// RFC 4180 quoting; text that a spreadsheet would run as a formula gets a leading '
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::money::to_cents;
+
+    fn deals() -> Vec<ExportDeal> {
+        vec![
+            ExportDeal {
+                deal_id: "deal-1001".to_string(),
+                date: NaiveDate::from_ymd_opt(2026, 3, 2).unwrap(),
+                customer: "Jane Doe".to_string(),
+                vehicle: "2021 Honda Civic".to_string(),
+                vin: "1HGCM82633A004352".to_string(),
+                stock_number: "A1001".to_string(),
+                lender: "First Bank".to_string(),
+                sale_cents: to_cents(18_500.0),
+                sales_tax_cents: to_cents(1_202.5),
+                doc_fee_cents: to_cents(199.99),
+                trade_in_cents: to_cents(4_000.0),
+            },
+            // Cash deal with no trade-in or doc fee, and text that needs escaping
+            ExportDeal {
+                deal_id: "deal-1002".to_string(),
+                date: NaiveDate::from_ymd_opt(2026, 3, 15).unwrap(),
+                customer: "Smith, \"Bob\"\tJr".to_string(),
+                vehicle: "2019 Ford F-150".to_string(),
+                vin: "1FTEW1EP5KFA00001".to_string(),
+                stock_number: "=B2002".to_string(),
+                lender: String::new(),
+                sale_cents: to_cents(32_999.99),
+                sales_tax_cents: to_cents(2_144.99),
+                doc_fee_cents: 0,
+                trade_in_cents: 0,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_iif_matches_golden_file() {
+        let expected = include_str!("../testdata/quickbooks/sold_deals.iif");
+        assert_eq!(to_iif(&deals(), &QuickBooksAccounts::default()), expected);
+    }
+
+    #[test]
+    fn test_csv_matches_golden_file() {
+        let expected = include_str!("../testdata/quickbooks/sold_deals.csv");
+        assert_eq!(to_csv(&deals()), expected);
+    }
+
+    #[test]
+    fn test_iif_transactions_balance_and_totals_add_up() {
+        let deals = deals();
+        let iif = to_iif(&deals, &QuickBooksAccounts::default());
+        let mut balance = 0i64;
+        for line in iif.lines() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            match fields[0] {
+                "TRNS" | "SPL" => {
+                    let amount = fields[6].replace('.', "");
+                    balance += amount.parse::<i64>().unwrap();
+                }
+                "ENDTRNS" => assert_eq!(balance, 0, "unbalanced transaction"),
+                _ => {}
+            }
+        }
+
+        let totals = totals(&deals);
+        assert_eq!(totals.deal_count, 2);
+        assert_eq!(totals.sale_cents, 5_149_999);
+        assert_eq!(totals.total_cents, 5_149_999 + 334_749 + 19_999 - 400_000);
+    }
+
+    #[test]
+    fn test_cents_do_not_drift() {
+        // 0.1 + 0.2 style inputs round to the nearest cent once, then stay integers
+        assert_eq!(to_cents(0.1 + 0.2), 30);
+        assert_eq!(to_cents(1_202.499_999_9), 120_250);
+        let sum: i64 = std::iter::repeat_n(to_cents(0.01), 1000).sum();
+        assert_eq!(format_cents(sum), "10.00");
+        assert_eq!(format_cents(-5), "-0.05");
+        assert_eq!(format_cents(-123_456), "-1234.56");
+        assert_eq!(format_cents(0), "0.00");
+    }
+}
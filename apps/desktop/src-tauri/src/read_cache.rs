@@ -0,0 +1,380 @@
+// src-tauri/src/read_cache.rs
+//
+// Read-side cache of settings and dealer profiles. Document generation and exports read the
+// dealer profile and a dozen settings each time, and every read was a query under the
+// connection's mutex. Each Database has one ReadCache (so the one DbState hands out in
+// managed state, and each test's own database), filled at startup by warm_read_cache() and
+// on a miss. db_get_setting and get_dealer_profile_for_user answer from it without taking
+// the connection.
+//
+// Staying correct: an update hook on the connection drops a table's entries whenever a row
+// of settings or dealer_profile is inserted, updated or deleted, whoever writes it (the
+// commands, upsert_setting callers, key rotation, imports), and a rollback hook drops
+// everything, since an entry may have been read inside the rolled-back transaction. The
+// profile commands store the saved profile again afterwards, and import_all_data clears
+// the cache once it commits. A restore from backup happens before the database is opened
+// (startup_migrations.rs), so it always starts from a new, empty cache.
+//
+// Lock order: the connection, then the cache. Misses are loaded and stored while holding
+// the connection, so no write can land between the read and the store.
+
+use log::{info, warn};
+use rusqlite::hooks::Action;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError, RwLock};
+use tauri::State;
+
+use crate::database::{get_db, read_setting, Database, DbState};
+use crate::dealer_profile::{load_all_profiles, load_profile, DealerProfile};
+use crate::telemetry::track;
+
+/// Entries by key; None records a key that isn't set, so a missing setting is a hit too
+struct Entries<K, V> {
+    values: RwLock<HashMap<K, Option<V>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl<K: Eq + Hash, V: Clone> Default for Entries<K, V> {
+    fn default() -> Self {
+        Entries {
+            values: RwLock::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<K: Eq + Hash, V: Clone> Entries<K, V> {
+    fn get(&self, key: &K) -> Option<Option<V>> {
+        let values = self.values.read().unwrap_or_else(PoisonError::into_inner);
+        let value = values.get(key).cloned();
+        let counter = if value.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+
+    fn store(&self, key: K, value: Option<V>) {
+        self.values
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key, value);
+    }
+
+    fn clear(&self) {
+        self.values
+            .write()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    fn stats(&self) -> EntryStats {
+        EntryStats {
+            entries: self
+                .values
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EntryStats {
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// For the diagnostics screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CacheStats {
+    pub settings: EntryStats,
+    pub dealer_profiles: EntryStats,
+    /// Times entries were dropped because their table changed or a transaction rolled back
+    pub invalidations: u64,
+}
+
+#[derive(Default)]
+pub(crate) struct ReadCache {
+    settings: Entries<String, String>,
+    profiles: Entries<String, DealerProfile>,
+    invalidations: AtomicU64,
+}
+
+impl ReadCache {
+    /// A cache for conn, kept correct by hooks on it
+    pub(crate) fn watching(conn: &Connection) -> Arc<Self> {
+        let cache = Arc::new(ReadCache::default());
+        let on_update = Arc::clone(&cache);
+        conn.update_hook(Some(
+            move |_: Action, _: &str, table: &str, _: i64| match table {
+                "settings" => on_update.invalidate(&on_update.settings),
+                "dealer_profile" => on_update.invalidate(&on_update.profiles),
+                _ => {}
+            },
+        ));
+        let on_rollback = Arc::clone(&cache);
+        conn.rollback_hook(Some(move || on_rollback.clear()));
+        cache
+    }
+
+    /// The stored value of a setting (callers check the write-behind queue first)
+    pub(crate) fn setting(&self, db: &Database, key: &str) -> Result<Option<String>, String> {
+        if let Some(value) = self.settings.get(&key.to_string()) {
+            return Ok(value);
+        }
+        let conn = db.conn();
+        let value = read_setting(&conn, key).map_err(|e| e.to_string())?;
+        self.settings.store(key.to_string(), value.clone());
+        Ok(value)
+    }
+
+    pub(crate) fn dealer_profile(
+        &self,
+        db: &Database,
+        user_id: &str,
+    ) -> Result<Option<DealerProfile>, String> {
+        if let Some(profile) = self.profiles.get(&user_id.to_string()) {
+            return Ok(profile);
+        }
+        let conn = db.conn();
+        let profile = load_profile(&conn, user_id)?;
+        self.profiles.store(user_id.to_string(), profile.clone());
+        Ok(profile)
+    }
+
+    /// Store a profile just saved; call while still holding the connection
+    pub(crate) fn store_dealer_profile(&self, user_id: &str, profile: Option<DealerProfile>) {
+        self.profiles.store(user_id.to_string(), profile);
+    }
+
+    /// Load every setting and profile
+    pub(crate) fn warm(&self, conn: &Connection) -> Result<(usize, usize), String> {
+        let mut stmt = conn
+            .prepare("SELECT key, value FROM settings")
+            .map_err(|e| e.to_string())?;
+        let settings = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        let profiles = load_all_profiles(conn)?;
+
+        let counts = (settings.len(), profiles.len());
+        for (key, value) in settings {
+            self.settings.store(key, Some(value));
+        }
+        for profile in profiles {
+            self.profiles.store(profile.user_id.clone(), Some(profile));
+        }
+        Ok(counts)
+    }
+
+    pub(crate) fn clear(&self) {
+        self.invalidate(&self.settings);
+        self.invalidate(&self.profiles);
+    }
+
+    fn invalidate<K: Eq + Hash, V: Clone>(&self, entries: &Entries<K, V>) {
+        entries.clear();
+        self.invalidations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn stats(&self) -> CacheStats {
+        CacheStats {
+            settings: self.settings.stats(),
+            dealer_profiles: self.profiles.stats(),
+            invalidations: self.invalidations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Fill the shared database's cache. Call once the database is initialized
+pub fn warm_read_cache() {
+    let loaded = get_db().map_err(|e| e.to_string()).and_then(|db| {
+        let conn = db.conn();
+        db.read_cache().warm(&conn)
+    });
+    match loaded {
+        Ok((settings, profiles)) => info!(
+            "✅ Read cache loaded ({} settings, {} dealer profiles)",
+            settings, profiles
+        ),
+        Err(e) => warn!("⚠️  Read cache starts empty: {}", e),
+    }
+}
+
+/// Hit and miss counts of the settings and dealer profile cache
+#[tauri::command]
+pub fn cache_stats(db: State<'_, DbState>) -> Result<CacheStats, String> {
+    track("cache_stats", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        Ok(db.read_cache().stats())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_export::{collect_export, import_data, ImportMode};
+    use crate::database::upsert_setting;
+    use crate::test_support::{TestApp, TEST_USER};
+
+    fn profile_row(conn: &Connection, user_id: &str, legal_name: &str) {
+        conn.execute(
+            "INSERT INTO dealer_profile (user_id, legal_name, updated_at) VALUES (?1, ?2, 1)
+             ON CONFLICT(user_id) DO UPDATE SET legal_name = excluded.legal_name",
+            [user_id, legal_name],
+        )
+        .unwrap();
+    }
+
+    fn legal_name(cache: &ReadCache, db: &Database, user_id: &str) -> Option<String> {
+        cache
+            .dealer_profile(db, user_id)
+            .unwrap()
+            .and_then(|profile| profile.legal_name)
+    }
+
+    #[test]
+    fn test_reads_are_served_from_the_cache_until_a_write() {
+        let app = TestApp::new();
+        let db = app.db().inner().get().unwrap();
+        let cache = db.read_cache();
+        upsert_setting(&db.conn(), "dealer_name", "Main St Motors", 1).unwrap();
+        profile_row(&db.conn(), "u1", "Main St Motors LLC");
+        assert_eq!(cache.warm(&db.conn()).unwrap(), (1, 1));
+
+        for _ in 0..3 {
+            assert_eq!(
+                cache.setting(db, "dealer_name").unwrap().as_deref(),
+                Some("Main St Motors")
+            );
+            assert_eq!(
+                legal_name(cache, db, "u1").as_deref(),
+                Some("Main St Motors LLC")
+            );
+        }
+        // Missing keys are cached as missing
+        assert_eq!(cache.setting(db, "doc_fee").unwrap(), None);
+        assert_eq!(cache.setting(db, "doc_fee").unwrap(), None);
+        let stats = cache.stats();
+        assert_eq!((stats.settings.hits, stats.settings.misses), (4, 1));
+        assert_eq!(
+            (stats.dealer_profiles.hits, stats.dealer_profiles.misses),
+            (3, 0)
+        );
+
+        // Writes through plain SQL, not only through the commands
+        upsert_setting(&db.conn(), "dealer_name", "Elm St Motors", 2).unwrap();
+        upsert_setting(&db.conn(), "doc_fee", "199", 2).unwrap();
+        profile_row(&db.conn(), "u1", "Elm St Motors LLC");
+        assert_eq!(
+            cache.setting(db, "dealer_name").unwrap().as_deref(),
+            Some("Elm St Motors")
+        );
+        assert_eq!(
+            cache.setting(db, "doc_fee").unwrap().as_deref(),
+            Some("199")
+        );
+        assert_eq!(
+            legal_name(cache, db, "u1").as_deref(),
+            Some("Elm St Motors LLC")
+        );
+        assert!(cache.stats().invalidations >= 3);
+
+        // A value read inside a transaction that's rolled back isn't kept
+        {
+            let conn = db.conn();
+            let tx = conn.unchecked_transaction().unwrap();
+            upsert_setting(&tx, "dealer_name", "Never Saved", 3).unwrap();
+            cache.settings.store(
+                "dealer_name".to_string(),
+                read_setting(&tx, "dealer_name").unwrap(),
+            );
+            tx.rollback().unwrap();
+        }
+        assert_eq!(
+            cache.setting(db, "dealer_name").unwrap().as_deref(),
+            Some("Elm St Motors")
+        );
+    }
+
+    #[test]
+    fn test_a_restore_replaces_stale_cached_values() {
+        let app = TestApp::new();
+        let db = app.db().inner().get().unwrap();
+        let cache = db.read_cache();
+        upsert_setting(&db.conn(), "dealer_name", "From Backup", 1).unwrap();
+        upsert_setting(&db.conn(), "money_locale", "en-CA", 1).unwrap();
+        let backup = collect_export(&db.conn(), TEST_USER, None).unwrap();
+
+        // Changed since the backup, and cached
+        upsert_setting(&db.conn(), "dealer_name", "Before Restore", 2).unwrap();
+        db.conn()
+            .execute("DELETE FROM settings WHERE key = 'money_locale'", [])
+            .unwrap();
+        upsert_setting(&db.conn(), "doc_fee", "150", 2).unwrap();
+        cache.warm(&db.conn()).unwrap();
+        assert_eq!(
+            cache.setting(db, "dealer_name").unwrap().as_deref(),
+            Some("Before Restore")
+        );
+        assert_eq!(cache.setting(db, "money_locale").unwrap(), None);
+
+        // A failed restore rolls back, and nothing it wrote stays cached
+        {
+            let mut conn = db.conn();
+            let failed = import_data(
+                &mut conn,
+                &backup,
+                TEST_USER,
+                ImportMode::Replace,
+                None,
+                |_| Err("disk full".to_string()),
+            );
+            assert!(failed.is_err());
+        }
+        assert_eq!(
+            cache.setting(db, "dealer_name").unwrap().as_deref(),
+            Some("Before Restore")
+        );
+        assert_eq!(cache.setting(db, "money_locale").unwrap(), None);
+
+        {
+            let mut conn = db.conn();
+            import_data(
+                &mut conn,
+                &backup,
+                TEST_USER,
+                ImportMode::Replace,
+                None,
+                |_| Ok(0),
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            cache.setting(db, "dealer_name").unwrap().as_deref(),
+            Some("From Backup")
+        );
+        assert_eq!(
+            cache.setting(db, "money_locale").unwrap().as_deref(),
+            Some("en-CA")
+        );
+        assert_eq!(
+            cache.setting(db, "doc_fee").unwrap().as_deref(),
+            Some("150")
+        );
+    }
+}
@@ -0,0 +1,451 @@
+// src-tauri/src/recent_items.rs
+//
+// Recently opened clients/vehicles/deals and pinned favorites, for quick navigation
+// The frontend calls record_access when a record's page opens. Lists join back to the
+// record for its display name, so records that were deleted drop out on their own.
+
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::get_db;
+use crate::telemetry::track;
+
+/// Recent items kept per user; older ones are pruned on every access
+pub const MAX_RECENT_ITEMS: u32 = 200;
+const DEFAULT_LIMIT: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityType {
+    Client,
+    Vehicle,
+    Deal,
+}
+
+impl EntityType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntityType::Client => "client",
+            EntityType::Vehicle => "vehicle",
+            EntityType::Deal => "deal",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "client" => Some(EntityType::Client),
+            "vehicle" => Some(EntityType::Vehicle),
+            "deal" => Some(EntityType::Deal),
+            _ => None,
+        }
+    }
+
+    fn table(self) -> &'static str {
+        match self {
+            EntityType::Client => "clients",
+            EntityType::Vehicle => "vehicles",
+            EntityType::Deal => "deals",
+        }
+    }
+}
+
+/// A recent item or favorite, with what to show for it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuickItem {
+    pub entity_type: String,
+    pub entity_id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    /// accessed_at for recent items, pinned_at for favorites
+    pub at: i64,
+}
+
+impl QuickItem {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(QuickItem {
+            entity_type: row.get("entity_type")?,
+            entity_id: row.get("entity_id")?,
+            title: row.get("title")?,
+            subtitle: row.get("subtitle")?,
+            at: row.get("at")?,
+        })
+    }
+}
+
+/// recent_items or favorites rows joined to their records, one SELECT per entity type
+/// Parameters: ?1 user_id, ?2 entity_type filter (NULL = all), ?3 limit
+fn joined_sql(table: &str, time_column: &str) -> String {
+    format!(
+        "SELECT * FROM (
+            SELECT r.entity_type, r.entity_id, c.first_name || ' ' || c.last_name AS title,
+                COALESCE(c.phone, c.email) AS subtitle, r.{time} AS at
+            FROM {table} r
+            JOIN clients c ON c.id = r.entity_id AND c.user_id = r.user_id
+            WHERE r.user_id = ?1 AND r.entity_type = 'client'
+            UNION ALL
+            SELECT r.entity_type, r.entity_id,
+                v.year || ' ' || v.make || ' ' || v.model AS title,
+                COALESCE('Stock #' || v.stock_number || ' · ', '') || v.vin AS subtitle,
+                r.{time} AS at
+            FROM {table} r
+            JOIN vehicles v ON v.id = r.entity_id AND v.user_id = r.user_id
+            WHERE r.user_id = ?1 AND r.entity_type = 'vehicle'
+            UNION ALL
+            SELECT r.entity_type, r.entity_id,
+                COALESCE(c.first_name || ' ' || c.last_name, 'Deal ' || d.id) AS title,
+                COALESCE(v.year || ' ' || v.make || ' ' || v.model || ' · ', '') || d.status
+                    AS subtitle,
+                r.{time} AS at
+            FROM {table} r
+            JOIN deals d ON d.id = r.entity_id AND d.user_id = r.user_id
+            LEFT JOIN clients c ON c.id = d.client_id
+            LEFT JOIN vehicles v ON v.id = d.vehicle_id
+            WHERE r.user_id = ?1 AND r.entity_type = 'deal'
+        )
+        WHERE ?2 IS NULL OR entity_type = ?2
+        ORDER BY at DESC
+        LIMIT ?3",
+        table = table,
+        time = time_column
+    )
+}
+
+/// Err unless the record exists and belongs to the user
+pub(crate) fn owned_by(
+    conn: &Connection,
+    user_id: &str,
+    entity_type: EntityType,
+    entity_id: &str,
+) -> Result<(), String> {
+    let sql = format!(
+        "SELECT 1 FROM {} WHERE id = ?1 AND user_id = ?2",
+        entity_type.table()
+    );
+    conn.query_row(&sql, params![entity_id, user_id], |_| Ok(()))
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{} not found or access denied", entity_type.as_str()))
+}
+
+fn touch(
+    conn: &Connection,
+    user_id: &str,
+    entity_type: EntityType,
+    entity_id: &str,
+    accessed_at: i64,
+) -> SqlResult<()> {
+    conn.execute(
+        "INSERT INTO recent_items (user_id, entity_type, entity_id, accessed_at)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(user_id, entity_type, entity_id) DO UPDATE SET accessed_at = ?4",
+        params![user_id, entity_type.as_str(), entity_id, accessed_at],
+    )?;
+    conn.execute(
+        "DELETE FROM recent_items WHERE user_id = ?1 AND rowid NOT IN (
+            SELECT rowid FROM recent_items WHERE user_id = ?1
+            ORDER BY accessed_at DESC LIMIT ?2
+        )",
+        params![user_id, MAX_RECENT_ITEMS],
+    )?;
+    Ok(())
+}
+
+fn list(
+    conn: &Connection,
+    table: &str,
+    time_column: &str,
+    user_id: &str,
+    entity_type: Option<EntityType>,
+    limit: u32,
+) -> SqlResult<Vec<QuickItem>> {
+    let mut stmt = conn.prepare(&joined_sql(table, time_column))?;
+    let rows = stmt.query_map(
+        params![user_id, entity_type.map(EntityType::as_str), limit],
+        QuickItem::from_row,
+    )?;
+    rows.collect()
+}
+
+fn set_favorite(
+    conn: &Connection,
+    user_id: &str,
+    entity_type: EntityType,
+    entity_id: &str,
+    favorite: bool,
+) -> Result<(), String> {
+    if favorite {
+        owned_by(conn, user_id, entity_type, entity_id)?;
+        conn.execute(
+            "INSERT OR IGNORE INTO favorites (user_id, entity_type, entity_id, pinned_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                user_id,
+                entity_type.as_str(),
+                entity_id,
+                Utc::now().timestamp_millis()
+            ],
+        )
+    } else {
+        conn.execute(
+            "DELETE FROM favorites WHERE user_id = ?1 AND entity_type = ?2 AND entity_id = ?3",
+            params![user_id, entity_type.as_str(), entity_id],
+        )
+    }
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Note that the user opened a client, vehicle or deal
+#[tauri::command]
+pub fn record_access(
+    entity_type: EntityType,
+    entity_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    track("record_access", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        owned_by(&conn, &user_id_value, entity_type, &entity_id)?;
+        touch(
+            &conn,
+            &user_id_value,
+            entity_type,
+            &entity_id,
+            Utc::now().timestamp_millis(),
+        )
+        .map_err(|e| e.to_string())
+    })
+}
+
+/// Most recently opened records, newest first (default 20)
+#[tauri::command]
+pub fn db_get_recent_items(
+    user_id: Option<String>,
+    entity_type: Option<EntityType>,
+    limit: Option<u32>,
+    state: State<'_, AppState>,
+) -> Result<Vec<QuickItem>, String> {
+    track("db_get_recent_items", || {
+        let user_id_value = state.require_user(user_id)?;
+        let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_RECENT_ITEMS);
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        list(
+            &conn,
+            "recent_items",
+            "accessed_at",
+            &user_id_value,
+            entity_type,
+            limit,
+        )
+        .map_err(|e| e.to_string())
+    })
+}
+
+/// Pin (favorite = true) or unpin a record
+#[tauri::command]
+pub fn db_set_favorite(
+    entity_type: EntityType,
+    entity_id: String,
+    favorite: bool,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    track("db_set_favorite", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        set_favorite(&conn, &user_id_value, entity_type, &entity_id, favorite)
+    })
+}
+
+/// Pinned records, most recently pinned first
+#[tauri::command]
+pub fn db_get_favorites(
+    user_id: Option<String>,
+    entity_type: Option<EntityType>,
+    state: State<'_, AppState>,
+) -> Result<Vec<QuickItem>, String> {
+    track("db_get_favorites", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        list(
+            &conn,
+            "favorites",
+            "pinned_at",
+            &user_id_value,
+            entity_type,
+            u32::MAX,
+        )
+        .map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/015_add_recent_items.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, phone, created_at, updated_at, user_id)
+                VALUES ('c1', 'Ada', 'Lovelace', '555-0100', 1, 1, 'u1');
+             INSERT INTO vehicles (id, vin, stock_number, year, make, model, mileage, price,
+                status, created_at, updated_at, user_id)
+                VALUES ('v1', '1HGCM82633A004352', 'A12', 2021, 'Honda', 'Civic', 0, 1.0,
+                'available', 1, 1, 'u1');
+             INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount,
+                document_ids, created_at, updated_at, user_id)
+                VALUES ('d1', 'retail', 'c1', 'v1', 'pending', 1.0, '[]', 1, 1, 'u1');
+             INSERT INTO clients (id, first_name, last_name, created_at, updated_at, user_id)
+                VALUES ('c2', 'Grace', 'Hopper', 1, 1, 'u2');",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn recent(conn: &Connection, user: &str) -> Vec<QuickItem> {
+        list(conn, "recent_items", "accessed_at", user, None, 500).unwrap()
+    }
+
+    #[test]
+    fn test_join_shapes() {
+        let conn = test_db();
+        touch(&conn, "u1", EntityType::Client, "c1", 10).unwrap();
+        touch(&conn, "u1", EntityType::Vehicle, "v1", 20).unwrap();
+        touch(&conn, "u1", EntityType::Deal, "d1", 30).unwrap();
+
+        let items = recent(&conn, "u1");
+        let shapes: Vec<(&str, &str, Option<&str>)> = items
+            .iter()
+            .map(|item| {
+                (
+                    item.entity_type.as_str(),
+                    item.title.as_str(),
+                    item.subtitle.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            shapes,
+            [
+                ("deal", "Ada Lovelace", Some("2021 Honda Civic · pending")),
+                (
+                    "vehicle",
+                    "2021 Honda Civic",
+                    Some("Stock #A12 · 1HGCM82633A004352")
+                ),
+                ("client", "Ada Lovelace", Some("555-0100")),
+            ]
+        );
+
+        let vehicles = list(
+            &conn,
+            "recent_items",
+            "accessed_at",
+            "u1",
+            Some(EntityType::Vehicle),
+            20,
+        )
+        .unwrap();
+        assert_eq!(vehicles.len(), 1);
+        assert_eq!(vehicles[0].entity_id, "v1");
+    }
+
+    #[test]
+    fn test_reaccess_moves_to_top_without_duplicates() {
+        let conn = test_db();
+        touch(&conn, "u1", EntityType::Client, "c1", 10).unwrap();
+        touch(&conn, "u1", EntityType::Vehicle, "v1", 20).unwrap();
+        touch(&conn, "u1", EntityType::Client, "c1", 30).unwrap();
+
+        let items = recent(&conn, "u1");
+        assert_eq!(items.len(), 2);
+        assert_eq!((items[0].entity_id.as_str(), items[0].at), ("c1", 30));
+    }
+
+    #[test]
+    fn test_prunes_to_the_cap_per_user() {
+        let conn = test_db();
+        touch(&conn, "u2", EntityType::Client, "c2", 0).unwrap();
+        for i in 0..(MAX_RECENT_ITEMS as i64 + 5) {
+            touch(&conn, "u1", EntityType::Client, &format!("gone-{}", i), i).unwrap();
+        }
+
+        let count = |user: &str| -> u32 {
+            conn.query_row(
+                "SELECT COUNT(*) FROM recent_items WHERE user_id = ?1",
+                [user],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        assert_eq!(count("u1"), MAX_RECENT_ITEMS);
+        // The oldest five went; other users are untouched
+        let oldest: i64 = conn
+            .query_row(
+                "SELECT MIN(accessed_at) FROM recent_items WHERE user_id = 'u1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(oldest, 5);
+        assert_eq!(count("u2"), 1);
+    }
+
+    #[test]
+    fn test_deleted_records_drop_out_of_both_lists() {
+        let conn = test_db();
+        touch(&conn, "u1", EntityType::Client, "c1", 10).unwrap();
+        touch(&conn, "u1", EntityType::Deal, "d1", 20).unwrap();
+        set_favorite(&conn, "u1", EntityType::Deal, "d1", true).unwrap();
+        set_favorite(&conn, "u1", EntityType::Client, "c1", true).unwrap();
+        let favorites = |conn: &Connection| {
+            list(conn, "favorites", "pinned_at", "u1", None, 50)
+                .unwrap()
+                .len()
+        };
+        assert_eq!(favorites(&conn), 2);
+
+        conn.execute("DELETE FROM deals WHERE id = 'd1'", [])
+            .unwrap();
+        assert_eq!(recent(&conn, "u1").len(), 1);
+        assert_eq!(favorites(&conn), 1);
+
+        set_favorite(&conn, "u1", EntityType::Client, "c1", false).unwrap();
+        assert_eq!(favorites(&conn), 0);
+    }
+
+    #[test]
+    fn test_favorites_are_per_user() {
+        let conn = test_db();
+        // u1 can't pin (or see) u2's client
+        assert!(set_favorite(&conn, "u1", EntityType::Client, "c2", true).is_err());
+        set_favorite(&conn, "u2", EntityType::Client, "c2", true).unwrap();
+        // Pinning twice keeps one row
+        set_favorite(&conn, "u2", EntityType::Client, "c2", true).unwrap();
+
+        let pinned = list(&conn, "favorites", "pinned_at", "u2", None, 50).unwrap();
+        assert_eq!(pinned.len(), 1);
+        assert_eq!(pinned[0].title, "Grace Hopper");
+        assert_eq!(pinned[0].subtitle, None);
+        assert!(list(&conn, "favorites", "pinned_at", "u1", None, 50)
+            .unwrap()
+            .is_empty());
+    }
+}
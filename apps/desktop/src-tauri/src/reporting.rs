@@ -0,0 +1,465 @@
+// src-tauri/src/reporting.rs
+//
+// Reports and exports run on their own read-only connection so they don't hold the main
+// connection's mutex for the length of a big scan. WAL mode lets that reader see a
+// consistent snapshot while the app keeps writing on the main connection.
+// Each run is a job: run_report_job registers it under a job id (the caller's or a new
+// one), runs it on a blocking thread, and installs a SQLite progress handler that aborts
+// the running statement once cancel_report_job sets the job's flag or the job's time
+// budget (db_timeout.rs; EXPORT_QUERY_TIMEOUT unless the caller has one) is spent. The
+// reader logs slow statements like every other connection. Files are written next to
+// their destination and renamed into place only if the job wasn't cancelled, so a
+// cancelled export leaves nothing behind. Jobs belong to the window that started them
+// and are cancelled when it closes (app_windows.rs).
+
+use chrono::Utc;
+use log::{info, warn};
+use once_cell::sync::{Lazy, OnceCell};
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::{get_db, new_row_id, Database};
+use crate::db_busy::DbError;
+use crate::db_timeout::{
+    carry_budget, past_deadline, watch_connection, EXPORT_QUERY_TIMEOUT, PROGRESS_OPS,
+};
+use crate::telemetry::track;
+
+/// Error returned by a job that was cancelled
+pub const REPORT_CANCELLED: &str = "Report cancelled";
+
+/// How long the reader waits on a checkpoint before giving up
+const READ_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A running report, as listed for the UI
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReportJob {
+    pub id: String,
+    pub kind: String,
+    pub user_id: String,
+    /// Label of the window that started it
+    pub window_label: String,
+    pub started_at: i64,
+    pub cancel_requested: bool,
+}
+
+struct ActiveJob {
+    job: ReportJob,
+    cancel: Arc<AtomicBool>,
+}
+
+static JOBS: Lazy<Mutex<HashMap<String, ActiveJob>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static REPORT_CONN: OnceCell<Mutex<Connection>> = OnceCell::new();
+
+fn jobs() -> MutexGuard<'static, HashMap<String, ActiveJob>> {
+    JOBS.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// A registered job; dropping it removes the job from the list
+pub(crate) struct JobHandle {
+    id: String,
+    cancel: Arc<AtomicBool>,
+}
+
+impl JobHandle {
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Err(REPORT_CANCELLED) once the job has been cancelled (for work between queries)
+    pub(crate) fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            return Err(REPORT_CANCELLED.to_string());
+        }
+        Ok(())
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        jobs().remove(&self.id);
+    }
+}
+
+fn register_job(
+    job_id: Option<String>,
+    kind: &str,
+    user_id: &str,
+    window_label: &str,
+) -> Result<JobHandle, String> {
+    let id = job_id
+        .filter(|id| !id.trim().is_empty())
+        .unwrap_or_else(new_row_id);
+    let mut jobs = jobs();
+    if jobs.contains_key(&id) {
+        return Err(format!("A report with job id {} is already running", id));
+    }
+    let cancel = Arc::new(AtomicBool::new(false));
+    jobs.insert(
+        id.clone(),
+        ActiveJob {
+            job: ReportJob {
+                id: id.clone(),
+                kind: kind.to_string(),
+                user_id: user_id.to_string(),
+                window_label: window_label.to_string(),
+                started_at: Utc::now().timestamp_millis(),
+                cancel_requested: false,
+            },
+            cancel: cancel.clone(),
+        },
+    );
+    Ok(JobHandle { id, cancel })
+}
+
+/// Flag a job for cancellation; false if no such job is running for the user
+fn cancel_job(job_id: &str, user_id: &str) -> bool {
+    match jobs().get_mut(job_id) {
+        Some(active) if active.job.user_id == user_id => {
+            active.cancel.store(true, Ordering::Relaxed);
+            active.job.cancel_requested = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Cancel every job the window started (it closed); returns how many were running
+pub(crate) fn cancel_window_jobs(window_label: &str) -> usize {
+    let mut cancelled = 0;
+    for active in jobs().values_mut() {
+        if active.job.window_label == window_label && !active.job.cancel_requested {
+            active.cancel.store(true, Ordering::Relaxed);
+            active.job.cancel_requested = true;
+            cancelled += 1;
+        }
+    }
+    cancelled
+}
+
+fn list_jobs(user_id: &str) -> Vec<ReportJob> {
+    let mut listed: Vec<ReportJob> = jobs()
+        .values()
+        .filter(|active| active.job.user_id == user_id)
+        .map(|active| active.job.clone())
+        .collect();
+    listed.sort_by(|a, b| {
+        a.started_at
+            .cmp(&b.started_at)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    listed
+}
+
+/// Read-only connection to the database file (needs the writer to have enabled WAL)
+pub(crate) fn open_report_connection(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+            | OpenFlags::SQLITE_OPEN_NO_MUTEX
+            | OpenFlags::SQLITE_OPEN_URI,
+    )?;
+    conn.busy_timeout(READ_BUSY_TIMEOUT)?;
+    watch_connection(&conn);
+    Ok(conn)
+}
+
+fn report_conn() -> Result<MutexGuard<'static, Connection>, String> {
+    let conn = REPORT_CONN.get_or_try_init(|| {
+        // The main connection creates the file, runs migrations and turns on WAL first
+        get_db().map_err(|e| e.to_string())?;
+        let path = Database::get_db_path().map_err(|e| e.to_string())?;
+        info!("Opening read-only reporting connection");
+        open_report_connection(&path)
+            .map(Mutex::new)
+            .map_err(|e| format!("Failed to open reporting connection: {}", e))
+    })?;
+    Ok(conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+}
+
+/// Run body on conn with the job's cancel flag wired into SQLite's progress handler,
+/// next to the deadline check of the thread's time budget.
+/// Any error after cancellation (usually SQLITE_INTERRUPT) comes back as REPORT_CANCELLED,
+/// and any error once the budget ran out as the timeout.
+pub(crate) fn with_cancellation<T>(
+    conn: &Connection,
+    job: &JobHandle,
+    body: impl FnOnce(&Connection) -> Result<T, String>,
+) -> Result<T, String> {
+    let cancel = job.cancel.clone();
+    conn.progress_handler(
+        PROGRESS_OPS,
+        Some(move || cancel.load(Ordering::Relaxed) || past_deadline()),
+    );
+    let result = body(conn);
+    // Back to the deadline check alone (watch_connection)
+    conn.progress_handler(PROGRESS_OPS, Some(past_deadline));
+
+    match result {
+        Err(_) if job.is_cancelled() => Err(REPORT_CANCELLED.to_string()),
+        result => result.map_err(|e| DbError::from(e).to_string()),
+    }
+}
+
+/// Register a job and run body on the reporting connection on a blocking thread
+pub(crate) async fn run_report_job<T, F>(
+    job_id: Option<String>,
+    kind: &'static str,
+    user_id: &str,
+    window_label: &str,
+    body: F,
+) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&JobHandle, &Connection) -> Result<T, String> + Send + 'static,
+{
+    let job = register_job(job_id, kind, user_id, window_label)?;
+    info!("📊 Report job {} ({}) started", job.id(), kind);
+    let budget = carry_budget(kind, EXPORT_QUERY_TIMEOUT);
+
+    let result = tauri::async_runtime::spawn_blocking(move || {
+        budget.run(|| {
+            // Jobs queue here for the one reader; a job cancelled while waiting stops at once
+            let conn = report_conn()?;
+            job.check()?;
+            let result = with_cancellation(&conn, &job, |conn| body(&job, conn));
+            match &result {
+                Ok(_) => info!("✅ Report job {} ({}) finished", job.id(), kind),
+                Err(e) => warn!("⚠️  Report job {} ({}) stopped: {}", job.id(), kind, e),
+            }
+            result
+        })
+    })
+    .await
+    .map_err(|e| format!("Report task failed: {}", e))?;
+    result
+}
+
+/// Write an export's output: to a temporary file beside path, renamed into place unless
+/// the job was cancelled meanwhile. Nothing is left at either path on failure.
+pub(crate) fn write_report_file(
+    job: &JobHandle,
+    path: &Path,
+    content: &[u8],
+) -> Result<(), String> {
+    let partial = partial_path(path);
+    let result = fs::write(&partial, content)
+        .map_err(|e| format!("Failed to write export: {}", e))
+        .and_then(|_| job.check())
+        .and_then(|_| {
+            fs::rename(&partial, path).map_err(|e| format!("Failed to write export: {}", e))
+        });
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    path.with_file_name(name)
+}
+
+/// Reports and exports currently running for the user
+#[tauri::command]
+pub fn list_report_jobs(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<ReportJob>, String> {
+    track("list_report_jobs", || {
+        let user_id_value = state.require_user(user_id)?;
+        Ok(list_jobs(&user_id_value))
+    })
+}
+
+/// Stop a running report; it fails with "Report cancelled" and removes partial output.
+/// Returns false if the job had already finished.
+#[tauri::command]
+pub fn cancel_report_job(
+    job_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<bool, String> {
+    track("cancel_report_job", || {
+        let user_id_value = state.require_user(user_id)?;
+        let cancelled = cancel_job(&job_id, &user_id_value);
+        if cancelled {
+            info!("🛑 Report job {} cancellation requested", job_id);
+        }
+        Ok(cancelled)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::get_command_metrics;
+    use crate::test_support::temp_dir;
+    use std::sync::mpsc;
+    use std::thread;
+
+    /// Scans far longer than any test should take unless it's cancelled
+    const ENDLESS_SCAN: &str = "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n)
+                                SELECT COUNT(*) FROM n, items";
+
+    fn temp_db(name: &str) -> (PathBuf, Connection) {
+        let dir = temp_dir("reporting", name);
+        let path = dir.join("dealer.db");
+        let writer = Connection::open(&path).unwrap();
+        let _mode: String = writer
+            .query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))
+            .unwrap();
+        writer
+            .execute_batch(
+                "CREATE TABLE items (id INTEGER PRIMARY KEY, label TEXT);
+                 INSERT INTO items (label) VALUES ('a'), ('b');",
+            )
+            .unwrap();
+        (dir, writer)
+    }
+
+    /// Start ENDLESS_SCAN on a reader thread; returns once the scan is executing
+    fn start_scan(
+        path: PathBuf,
+        job: JobHandle,
+    ) -> (thread::JoinHandle<Result<i64, String>>, Arc<AtomicBool>) {
+        let done = Arc::new(AtomicBool::new(false));
+        let (started_tx, started_rx) = mpsc::channel();
+        let scan_done = done.clone();
+        let handle = thread::spawn(move || {
+            let reader = open_report_connection(&path).unwrap();
+            let result = with_cancellation(&reader, &job, |conn| {
+                let mut stmt = conn.prepare(ENDLESS_SCAN).map_err(|e| e.to_string())?;
+                started_tx.send(()).unwrap();
+                stmt.query_row([], |row| row.get(0))
+                    .map_err(|e| e.to_string())
+            });
+            scan_done.store(true, Ordering::SeqCst);
+            result
+        });
+        started_rx.recv().unwrap();
+        (handle, done)
+    }
+
+    #[test]
+    fn test_write_completes_during_long_read_and_cancel_stops_it() {
+        let (dir, writer) = temp_db("concurrent");
+        let job = register_job(Some("scan-1".to_string()), "test_scan", "u1", "main").unwrap();
+        let (handle, done) = start_scan(dir.join("dealer.db"), job);
+        // Give the scan time to be well into its statement
+        thread::sleep(Duration::from_millis(50));
+
+        writer
+            .execute(
+                "INSERT INTO items (label) VALUES ('written during scan')",
+                [],
+            )
+            .unwrap();
+        assert!(!done.load(Ordering::SeqCst), "scan ended before the write");
+        let count: i64 = writer
+            .query_row("SELECT COUNT(*) FROM items", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 3);
+
+        // Someone else's cancel is ignored; the owner's stops the scan
+        assert!(!cancel_job("scan-1", "someone-else"));
+        assert_eq!(list_jobs("u1")[0].id, "scan-1");
+        assert!(cancel_job("scan-1", "u1"));
+        assert!(list_jobs("u1")[0].cancel_requested);
+        assert_eq!(handle.join().unwrap(), Err(REPORT_CANCELLED.to_string()));
+
+        // The handle was dropped with the thread, so the job is gone
+        assert!(list_jobs("u1").iter().all(|job| job.id != "scan-1"));
+        assert!(!cancel_job("scan-1", "u1"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cancelled_export_leaves_no_file() {
+        let (dir, _writer) = temp_db("export");
+        let output = dir.join("export.csv");
+
+        let job = register_job(None, "test_export", "u2", "main").unwrap();
+        write_report_file(&job, &output, b"a,b\r\n").unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"a,b\r\n");
+        drop(job);
+
+        let cancelled_output = dir.join("cancelled.csv");
+        let job = register_job(Some("export-2".to_string()), "test_export", "u2", "main").unwrap();
+        assert!(register_job(Some("export-2".to_string()), "test_export", "u2", "main").is_err());
+        assert!(cancel_job("export-2", "u2"));
+        assert_eq!(
+            write_report_file(&job, &cancelled_output, b"partial"),
+            Err(REPORT_CANCELLED.to_string())
+        );
+        assert!(!cancelled_output.exists());
+        assert!(!partial_path(&cancelled_output).exists());
+
+        // The reader can't write, whatever the job does
+        let reader = open_report_connection(&dir.join("dealer.db")).unwrap();
+        assert!(reader
+            .execute("INSERT INTO items (label) VALUES ('nope')", [])
+            .is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_report_job_gets_the_statement_timeout() {
+        let (dir, _writer) = temp_db("timeout");
+        let path = dir.join("dealer.db");
+        let job = register_job(None, "test_report_timeout", "u4", "main").unwrap();
+        let budget = carry_budget("test_report_timeout", Duration::from_millis(300));
+
+        let result = thread::spawn(move || {
+            budget.run(|| {
+                let reader = open_report_connection(&path).unwrap();
+                with_cancellation(&reader, &job, |conn| {
+                    conn.query_row(ENDLESS_SCAN, [], |row| row.get::<_, i64>(0))
+                        .map_err(|e| e.to_string())
+                })
+            })
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(
+            result,
+            Err(DbError::Timeout {
+                category: "test_report_timeout".to_string(),
+                timeout_ms: 300,
+            }
+            .to_string())
+        );
+        assert!(get_command_metrics()
+            .slow_queries
+            .recent
+            .iter()
+            .any(|query| query.category == "test_report_timeout"));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_closing_a_window_cancels_only_its_jobs() {
+        let deal_job = register_job(Some("w-1".to_string()), "test_scan", "u3", "deal-d1").unwrap();
+        let main_job = register_job(Some("w-2".to_string()), "test_scan", "u3", "main").unwrap();
+
+        assert_eq!(cancel_window_jobs("deal-d1"), 1);
+        assert!(deal_job.is_cancelled());
+        assert!(!main_job.is_cancelled());
+        // Already cancelled jobs aren't counted again
+        assert_eq!(cancel_window_jobs("deal-d1"), 0);
+    }
+}
@@ -0,0 +1,1058 @@
+// src-tauri/src/retention.rs
+//
+// Data retention: after so many years, clients, deals, documents and communications are
+// deleted or have their personal details scrubbed, as set per entity in the user's
+// retention settings (stored in settings). preview_retention_purge lists exactly what a
+// purge would touch, with a confirm token that hashes that list. apply_retention_purge
+// recomputes the list inside its write transaction and goes ahead only if the token still
+// matches, so it does what was previewed or nothing. The purge is written to the audit log
+// (audit_log.rs) in the same transaction.
+//
+// Age is last activity: a deal's sale date or last update, a client's last update or their
+// deals', a document's or communication's creation. A deal with a retention hold keeps
+// itself, its documents and its client (with the client's communications) out of purges.
+// Deleting a deal takes its documents with it, and their files go to the document trash
+// (document_trash.rs). A client is deleted only when all their deals go in the same purge
+// and scrubbed otherwise. Documents can only be deleted.
+// With monthly_reminder on, the signed-in user gets a retention-purge-due event when a
+// purge would touch something, at most once every 30 days.
+
+use chrono::{Months, TimeZone, Utc};
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, ToSql};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::app_state::AppState;
+use crate::audit_log::record_audit;
+use crate::data_events::{change_batch, data_changed, ChangedEntity, Operation};
+use crate::data_export::documents_root;
+use crate::database::{begin_write, get_db, DbState};
+use crate::datetime::{dealer_timezone, local_date, local_day_bounds};
+use crate::db_busy::DbError;
+use crate::document_trash::{trash_document_file, TrashedDocument};
+use crate::document_versions::{remove_version_files, version_file_paths};
+use crate::recent_items::{owned_by, EntityType};
+use crate::storage_usage::adjust_usage;
+use crate::telemetry::track;
+
+pub const RETENTION_REMINDER_EVENT: &str = "retention-purge-due";
+
+const SETTINGS_PREFIX: &str = "retention_settings";
+const LAST_REMINDER_PREFIX: &str = "retention_last_reminder";
+const REMINDER_CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+const REMINDER_EVERY_MS: i64 = 30 * 24 * 60 * 60 * 1000;
+const MAX_YEARS: u32 = 100;
+/// Hex digits of the plan's SHA-256 in a confirm token
+const TOKEN_LEN: usize = 16;
+/// Name a scrubbed client is left with (first and last name are required)
+const SCRUBBED_NAME: (&str, &str) = ("Removed", "Client");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionEntity {
+    Clients,
+    Deals,
+    Documents,
+    Communications,
+}
+
+impl RetentionEntity {
+    fn as_str(self) -> &'static str {
+        match self {
+            RetentionEntity::Clients => "clients",
+            RetentionEntity::Deals => "deals",
+            RetentionEntity::Documents => "documents",
+            RetentionEntity::Communications => "communications",
+        }
+    }
+
+    fn changed_entity(self) -> ChangedEntity {
+        match self {
+            RetentionEntity::Clients => ChangedEntity::Client,
+            RetentionEntity::Deals => ChangedEntity::Deal,
+            RetentionEntity::Documents => ChangedEntity::Document,
+            RetentionEntity::Communications => ChangedEntity::Communication,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetentionAction {
+    Delete,
+    /// Keep the record but clear its personal details
+    Scrub,
+}
+
+impl RetentionAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            RetentionAction::Delete => "delete",
+            RetentionAction::Scrub => "scrub",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    pub years: u32,
+    pub action: RetentionAction,
+}
+
+/// Entities without a policy are kept forever
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionSettings {
+    #[serde(default)]
+    pub policies: BTreeMap<RetentionEntity, RetentionPolicy>,
+    #[serde(default)]
+    pub monthly_reminder: bool,
+}
+
+/// One record a purge deletes or scrubs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PurgeItem {
+    pub entity: RetentionEntity,
+    pub id: String,
+    pub action: RetentionAction,
+    /// The deal or client whose deletion takes this record with it
+    pub deleted_with: Option<String>,
+    /// A document's deal
+    pub deal_id: Option<String>,
+    /// A deleted document's file (to the trash) and the files of its old versions (removed)
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PurgePreview {
+    pub items: Vec<PurgeItem>,
+    /// Deals under a retention hold, left out with their documents and clients
+    pub held_deals: Vec<String>,
+    pub confirm_token: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PurgeResult {
+    pub items: Vec<PurgeItem>,
+    pub audit_id: String,
+    /// Document files that couldn't be moved to the trash (their rows are gone either way)
+    pub file_errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct PurgeReminder {
+    user_id: String,
+    records: usize,
+}
+
+fn settings_key(user_id: &str) -> String {
+    format!("{}:{}", SETTINGS_PREFIX, user_id)
+}
+
+fn last_reminder_key(user_id: &str) -> String {
+    format!("{}:{}", LAST_REMINDER_PREFIX, user_id)
+}
+
+fn read_setting(conn: &Connection, key: &str) -> SqlResult<Option<String>> {
+    conn.query_row(
+        "SELECT value FROM settings WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+fn write_setting(conn: &Connection, key: &str, value: &str, now: i64) -> SqlResult<()> {
+    conn.execute(
+        "INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        params![key, value, now],
+    )?;
+    Ok(())
+}
+
+pub(crate) fn load_settings(conn: &Connection, user_id: &str) -> Result<RetentionSettings, String> {
+    match read_setting(conn, &settings_key(user_id)).map_err(|e| e.to_string())? {
+        Some(value) => {
+            serde_json::from_str(&value).map_err(|e| format!("Invalid retention settings: {}", e))
+        }
+        None => Ok(RetentionSettings::default()),
+    }
+}
+
+fn validate_settings(settings: &RetentionSettings) -> Result<(), String> {
+    for (entity, policy) in &settings.policies {
+        if policy.years == 0 || policy.years > MAX_YEARS {
+            return Err(format!(
+                "Retention period must be between 1 and {} years",
+                MAX_YEARS
+            ));
+        }
+        if *entity == RetentionEntity::Documents && policy.action == RetentionAction::Scrub {
+            return Err("Documents can only be deleted".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Records last active before this are past the policy: the start of the dealer's day
+/// `years` before today
+fn cutoff_ms<Tz: TimeZone>(now: i64, years: u32, tz: &Tz) -> i64 {
+    local_date(now, tz)
+        .and_then(|today| today.checked_sub_months(Months::new(years * 12)))
+        .map(|date| local_day_bounds(date, tz).0)
+        .unwrap_or(i64::MIN)
+}
+
+fn query_ids(conn: &Connection, sql: &str, args: &[&dyn ToSql]) -> SqlResult<Vec<String>> {
+    let mut stmt = conn.prepare(sql)?;
+    let ids = stmt.query_map(args, |row| row.get(0))?;
+    ids.collect()
+}
+
+fn held_deals(conn: &Connection, user_id: &str) -> SqlResult<Vec<String>> {
+    query_ids(
+        conn,
+        "SELECT id FROM deals WHERE user_id = ?1 AND retention_hold = 1 ORDER BY id",
+        &[&user_id],
+    )
+}
+
+/// Everything a purge under settings would delete or scrub, by entity then id
+pub(crate) fn plan_purge(
+    conn: &Connection,
+    user_id: &str,
+    settings: &RetentionSettings,
+    now: i64,
+) -> SqlResult<Vec<PurgeItem>> {
+    let tz = dealer_timezone(conn);
+    let policy = |entity: RetentionEntity| {
+        settings
+            .policies
+            .get(&entity)
+            .map(|policy| (policy.action, cutoff_ms(now, policy.years, &tz)))
+    };
+    let item = |entity, id: String, action, deleted_with: Option<&str>| PurgeItem {
+        entity,
+        id,
+        action,
+        deleted_with: deleted_with.map(str::to_string),
+        deal_id: None,
+        files: Vec::new(),
+    };
+    let mut items = Vec::new();
+
+    let mut deleted_deals = BTreeSet::new();
+    if let Some((action, before)) = policy(RetentionEntity::Deals) {
+        let deletes = action == RetentionAction::Delete;
+        for id in query_ids(
+            conn,
+            "SELECT id FROM deals
+             WHERE user_id = ?1 AND retention_hold = 0
+               AND MAX(COALESCE(sale_date, created_at), updated_at) < ?2
+               AND (?3 OR anonymized_at IS NULL)
+             ORDER BY id",
+            &[&user_id, &before, &deletes],
+        )? {
+            if deletes {
+                deleted_deals.insert(id.clone());
+            }
+            items.push(item(RetentionEntity::Deals, id, action, None));
+        }
+    }
+
+    // Documents past their own policy, and those of deleted deals (which cascade)
+    let mut documents: BTreeSet<String> = BTreeSet::new();
+    if let Some((_, before)) = policy(RetentionEntity::Documents) {
+        documents.extend(query_ids(
+            conn,
+            "SELECT doc.id FROM documents doc JOIN deals d ON d.id = doc.deal_id
+             WHERE d.user_id = ?1 AND d.retention_hold = 0 AND doc.created_at < ?2",
+            &[&user_id, &before],
+        )?);
+    }
+    for deal_id in &deleted_deals {
+        documents.extend(query_ids(
+            conn,
+            "SELECT id FROM documents WHERE deal_id = ?1",
+            &[deal_id],
+        )?);
+    }
+    for id in documents {
+        let (deal_id, file_path): (String, String) = conn.query_row(
+            "SELECT deal_id, file_path FROM documents WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let mut files = vec![file_path];
+        files.extend(
+            version_file_paths(conn, &id)?
+                .into_iter()
+                .map(|path| path.to_string_lossy().to_string()),
+        );
+        let deleted_with = deleted_deals.contains(&deal_id).then_some(deal_id.as_str());
+        items.push(PurgeItem {
+            deal_id: Some(deal_id.clone()),
+            files,
+            ..item(
+                RetentionEntity::Documents,
+                id,
+                RetentionAction::Delete,
+                deleted_with,
+            )
+        });
+    }
+
+    let mut communications: BTreeMap<String, (RetentionAction, Option<String>)> = BTreeMap::new();
+    if let Some((action, before)) = policy(RetentionEntity::Clients) {
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.anonymized_at IS NOT NULL FROM clients c
+             WHERE c.user_id = ?1 AND c.updated_at < ?2
+               AND NOT EXISTS (SELECT 1 FROM deals d WHERE d.client_id = c.id
+                               AND (d.retention_hold = 1
+                                    OR (d.anonymized_at IS NULL AND d.updated_at >= ?2)))
+             ORDER BY c.id",
+        )?;
+        let clients = stmt
+            .query_map(params![user_id, before], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))
+            })?
+            .collect::<SqlResult<Vec<_>>>()?;
+        for (id, anonymized) in clients {
+            let deals = query_ids(conn, "SELECT id FROM deals WHERE client_id = ?1", &[&id])?;
+            let action = if action == RetentionAction::Delete
+                && deals.iter().all(|deal| deleted_deals.contains(deal))
+            {
+                RetentionAction::Delete
+            } else {
+                RetentionAction::Scrub
+            };
+            if action == RetentionAction::Scrub && anonymized {
+                continue;
+            }
+            if action == RetentionAction::Delete {
+                for message in query_ids(
+                    conn,
+                    "SELECT id FROM communications WHERE client_id = ?1",
+                    &[&id],
+                )? {
+                    communications.insert(message, (RetentionAction::Delete, Some(id.clone())));
+                }
+            }
+            items.push(item(RetentionEntity::Clients, id, action, None));
+        }
+    }
+
+    if let Some((action, before)) = policy(RetentionEntity::Communications) {
+        for id in query_ids(
+            conn,
+            "SELECT m.id FROM communications m
+             WHERE m.user_id = ?1 AND m.created_at < ?2
+               AND NOT EXISTS (SELECT 1 FROM deals d
+                               WHERE d.client_id = m.client_id AND d.retention_hold = 1)
+               AND (?3 OR m.subject IS NOT NULL OR m.body IS NOT NULL)",
+            &[&user_id, &before, &(action == RetentionAction::Delete)],
+        )? {
+            // Going with a deleted client wins over a scrub
+            communications.entry(id).or_insert((action, None));
+        }
+    }
+    for (id, (action, client_id)) in communications {
+        items.push(item(
+            RetentionEntity::Communications,
+            id,
+            action,
+            client_id.as_deref(),
+        ));
+    }
+
+    items.sort_by(|a, b| (a.entity, &a.id).cmp(&(b.entity, &b.id)));
+    Ok(items)
+}
+
+pub(crate) fn confirm_token(items: &[PurgeItem]) -> String {
+    let plan = serde_json::to_string(items).unwrap_or_default();
+    let hex = format!("{:x}", Sha256::digest(plan.as_bytes()));
+    hex[..TOKEN_LEN].to_string()
+}
+
+pub(crate) fn preview(conn: &Connection, user_id: &str, now: i64) -> Result<PurgePreview, String> {
+    let settings = load_settings(conn, user_id)?;
+    let items = plan_purge(conn, user_id, &settings, now).map_err(|e| e.to_string())?;
+    Ok(PurgePreview {
+        confirm_token: confirm_token(&items),
+        held_deals: held_deals(conn, user_id).map_err(|e| e.to_string())?,
+        items,
+    })
+}
+
+fn purge_item(conn: &Connection, user_id: &str, item: &PurgeItem, now: i64) -> SqlResult<()> {
+    use RetentionAction::{Delete, Scrub};
+    match (item.entity, item.action) {
+        (RetentionEntity::Communications, Delete) => {
+            conn.execute(
+                "DELETE FROM communications WHERE id = ?1 AND user_id = ?2",
+                params![item.id, user_id],
+            )?;
+        }
+        (RetentionEntity::Communications, Scrub) => {
+            conn.execute(
+                "UPDATE communications SET subject = NULL, body = NULL, updated_at = ?3
+                 WHERE id = ?1 AND user_id = ?2",
+                params![item.id, user_id, now],
+            )?;
+        }
+        (RetentionEntity::Documents, _) => {
+            let file_size: Option<i64> = conn
+                .query_row(
+                    "SELECT file_size FROM documents WHERE id = ?1",
+                    params![item.id],
+                    |row| row.get(0),
+                )
+                .optional()?
+                .flatten();
+            conn.execute("DELETE FROM documents WHERE id = ?1", params![item.id])?;
+            adjust_usage(conn, user_id, -file_size.unwrap_or(0).max(0))?;
+        }
+        (RetentionEntity::Deals, Delete) => {
+            conn.execute(
+                "DELETE FROM deals WHERE id = ?1 AND user_id = ?2",
+                params![item.id, user_id],
+            )?;
+        }
+        (RetentionEntity::Deals, Scrub) => {
+            conn.execute(
+                "DELETE FROM deal_cobuyers WHERE deal_id = ?1",
+                params![item.id],
+            )?;
+            conn.execute(
+                "UPDATE deals SET cobuyer_data = NULL, anonymized_at = ?3, updated_at = ?3
+                 WHERE id = ?1 AND user_id = ?2",
+                params![item.id, user_id, now],
+            )?;
+        }
+        (RetentionEntity::Clients, Delete) => {
+            conn.execute(
+                "DELETE FROM clients WHERE id = ?1 AND user_id = ?2",
+                params![item.id, user_id],
+            )?;
+        }
+        (RetentionEntity::Clients, Scrub) => {
+            conn.execute(
+                "UPDATE clients SET first_name = ?3, last_name = ?4, email = NULL, phone = NULL,
+                        email_normalized = NULL, phone_e164 = NULL, phone_invalid = 0,
+                        address = NULL, city = NULL, state = NULL, zip_code = NULL,
+                        drivers_license = NULL, anonymized_at = ?5, updated_at = ?5
+                 WHERE id = ?1 AND user_id = ?2",
+                params![item.id, user_id, SCRUBBED_NAME.0, SCRUBBED_NAME.1, now],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Recompute the plan in a write transaction and carry it out if it still hashes to
+/// confirm_token. Files are left for the caller, after the commit.
+pub(crate) fn apply_purge(
+    conn: &Connection,
+    user_id: &str,
+    token: &str,
+    now: i64,
+) -> Result<(Vec<PurgeItem>, String), DbError> {
+    let tx = begin_write(conn, "retention")?;
+    let settings = load_settings(&tx, user_id)?;
+    let items = plan_purge(&tx, user_id, &settings, now).map_err(|e| e.to_string())?;
+    if confirm_token(&items) != token {
+        return Err("Records changed since the preview; preview the purge again"
+            .to_string()
+            .into());
+    }
+    if items.is_empty() {
+        return Err("Nothing to purge".to_string().into());
+    }
+
+    // Dependents first, so a client goes after their deals and a deal after its documents
+    for entity in [
+        RetentionEntity::Communications,
+        RetentionEntity::Documents,
+        RetentionEntity::Deals,
+        RetentionEntity::Clients,
+    ] {
+        for item in items.iter().filter(|item| item.entity == entity) {
+            purge_item(&tx, user_id, item, now).map_err(|e| e.to_string())?;
+        }
+    }
+
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    for item in &items {
+        let key = format!("{}_{}", item.entity.as_str(), item.action.as_str());
+        *counts.entry(key).or_default() += 1;
+    }
+    let details = json!({
+        "policies": settings.policies,
+        "counts": counts,
+        "items": items
+            .iter()
+            .map(|item| json!({ "entity": item.entity, "id": item.id, "action": item.action }))
+            .collect::<Vec<_>>(),
+    });
+    let audit_id =
+        record_audit(&tx, user_id, "retention_purge", &details, now).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok((items, audit_id))
+}
+
+/// Move purged documents' files to the trash and remove their old versions
+fn remove_files(user_id: &str, items: &[PurgeItem], now: i64) -> Vec<String> {
+    let trash_root = match documents_root() {
+        Ok(root) => Some(PathBuf::from(root)),
+        Err(e) => {
+            warn!(
+                "⚠️  No documents root, leaving purged document files in place: {}",
+                e
+            );
+            None
+        }
+    };
+    let mut errors = Vec::new();
+    for item in items
+        .iter()
+        .filter(|item| item.entity == RetentionEntity::Documents)
+    {
+        let Some((file_path, versions)) = item.files.split_first() else {
+            continue;
+        };
+        if let Some(root) = &trash_root {
+            let document = TrashedDocument {
+                document_id: &item.id,
+                deal_id: item.deal_id.as_deref().unwrap_or_default(),
+                user_id: Some(user_id),
+                file_path,
+            };
+            if let Err(e) = trash_document_file(root, document, now) {
+                warn!("⚠️  {}", e);
+                errors.push(e);
+            }
+        }
+        remove_version_files(&versions.iter().map(PathBuf::from).collect::<Vec<_>>());
+    }
+    errors
+}
+
+fn reminder_due(settings: &RetentionSettings, last_reminded_at: Option<i64>, now: i64) -> bool {
+    settings.monthly_reminder
+        && !settings.policies.is_empty()
+        && last_reminded_at.is_none_or(|last| now - last >= REMINDER_EVERY_MS)
+}
+
+/// Count what a purge would touch for the signed-in user, if a reminder is due
+fn check_reminder(conn: &Connection, user_id: &str, now: i64) -> Result<Option<usize>, String> {
+    let settings = load_settings(conn, user_id)?;
+    let last = read_setting(conn, &last_reminder_key(user_id))
+        .map_err(|e| e.to_string())?
+        .and_then(|value| value.parse().ok());
+    if !reminder_due(&settings, last, now) {
+        return Ok(None);
+    }
+    let records = plan_purge(conn, user_id, &settings, now)
+        .map_err(|e| e.to_string())?
+        .len();
+    if records == 0 {
+        return Ok(None);
+    }
+    write_setting(conn, &last_reminder_key(user_id), &now.to_string(), now)
+        .map_err(|e| e.to_string())?;
+    Ok(Some(records))
+}
+
+/// Background check for the monthly retention-purge-due reminder (called once from setup)
+pub fn start_retention_reminders(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(REMINDER_CHECK_INTERVAL).await;
+
+            let Some(user_id) = app.state::<AppState>().current_user() else {
+                continue;
+            };
+            let due = match get_db() {
+                Ok(db) => check_reminder(&db.conn(), &user_id, Utc::now().timestamp_millis()),
+                Err(e) => Err(e.to_string()),
+            };
+            match due {
+                Ok(Some(records)) => {
+                    info!("🗓️  Retention purge due: {} record(s)", records);
+                    let reminder = PurgeReminder { user_id, records };
+                    if let Err(e) = app.emit(RETENTION_REMINDER_EVENT, reminder) {
+                        warn!("⚠️  Failed to emit {}: {}", RETENTION_REMINDER_EVENT, e);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!("⚠️  Retention reminder check failed: {}", e),
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_retention_settings(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<RetentionSettings, String> {
+    track("get_retention_settings", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        load_settings(&conn, &user_id_value)
+    })
+}
+
+/// Replace the user's retention settings; the change is written to the audit log
+#[tauri::command]
+pub fn set_retention_settings(
+    settings: RetentionSettings,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<RetentionSettings, DbError> {
+    track("set_retention_settings", || {
+        let user_id_value = state.require_user(user_id)?;
+        validate_settings(&settings)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let now = Utc::now().timestamp_millis();
+
+        let tx = begin_write(&conn, "settings")?;
+        let value = serde_json::to_string(&settings).map_err(|e| e.to_string())?;
+        write_setting(&tx, &settings_key(&user_id_value), &value, now)
+            .map_err(|e| e.to_string())?;
+        record_audit(
+            &tx,
+            &user_id_value,
+            "retention_settings_changed",
+            &json!(settings),
+            now,
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        info!("✅ Retention settings updated");
+        Ok(settings)
+    })
+}
+
+/// Keep a deal, its documents and its client out of retention purges (or stop keeping them)
+#[tauri::command]
+pub fn set_deal_retention_hold(
+    deal_id: String,
+    hold: bool,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<(), DbError> {
+    track("set_deal_retention_hold", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let now = Utc::now().timestamp_millis();
+
+        let tx = begin_write(&conn, "deals")?;
+        owned_by(&tx, &user_id_value, EntityType::Deal, &deal_id)?;
+        tx.execute(
+            "UPDATE deals SET retention_hold = ?2, updated_at = ?3 WHERE id = ?1",
+            params![deal_id, hold, now],
+        )
+        .map_err(|e| e.to_string())?;
+        let action = if hold {
+            "retention_hold_set"
+        } else {
+            "retention_hold_released"
+        };
+        record_audit(
+            &tx,
+            &user_id_value,
+            action,
+            &json!({ "deal_id": deal_id }),
+            now,
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+
+        info!("✅ Retention hold on deal {}: {}", deal_id, hold);
+        data_changed(
+            &user_id_value,
+            ChangedEntity::Deal,
+            &deal_id,
+            Operation::Update,
+        );
+        Ok(())
+    })
+}
+
+/// What apply_retention_purge would delete and scrub right now, and the token to confirm it
+#[tauri::command]
+pub fn preview_retention_purge(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<PurgePreview, String> {
+    track("preview_retention_purge", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        preview(&conn, &user_id_value, Utc::now().timestamp_millis())
+    })
+}
+
+/// Carry out the previewed purge; fails without changing anything if the records have
+/// changed since the preview that returned confirm_token
+#[tauri::command]
+pub fn apply_retention_purge(
+    confirm_token: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<PurgeResult, DbError> {
+    track("apply_retention_purge", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let now = Utc::now().timestamp_millis();
+        let (items, audit_id) = apply_purge(&db.conn(), &user_id_value, &confirm_token, now)?;
+
+        let file_errors = remove_files(&user_id_value, &items, now);
+        let mut changes = change_batch(&user_id_value);
+        for item in &items {
+            let operation = match item.action {
+                RetentionAction::Delete => Operation::Delete,
+                RetentionAction::Scrub => Operation::Update,
+            };
+            changes.record(item.entity.changed_entity(), &item.id, operation);
+        }
+        changes.finish();
+
+        info!("✅ Retention purge: {} record(s)", items.len());
+        Ok(PurgeResult {
+            items,
+            audit_id,
+            file_errors,
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit_log::audit_entries;
+    use crate::database::Database;
+
+    const YEAR_MS: i64 = 365 * 24 * 60 * 60 * 1000;
+    /// 2024-06-01
+    const NOW: i64 = 1_717_200_000_000;
+    const OLD: i64 = NOW - 10 * YEAR_MS;
+    const RECENT: i64 = NOW - YEAR_MS;
+
+    fn test_db() -> Database {
+        let db = Database::init_in_memory().unwrap();
+        db.conn()
+            .execute_batch(
+                "INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status,
+                                       created_at, updated_at, user_id)
+                     VALUES ('v1', 'VIN1', 2010, 'Honda', 'Civic', 0, 9000, 'sold', 0, 0, 'u1');",
+            )
+            .unwrap();
+        db
+    }
+
+    fn client(conn: &Connection, id: &str, updated_at: i64) {
+        conn.execute(
+            "INSERT INTO clients (id, first_name, last_name, email, phone, created_at,
+                                  updated_at, user_id)
+             VALUES (?1, 'Jane', 'Doe', 'jane@example.com', '555-0100', ?2, ?2, 'u1')",
+            params![id, updated_at],
+        )
+        .unwrap();
+    }
+
+    fn deal(conn: &Connection, id: &str, client_id: &str, updated_at: i64) {
+        conn.execute(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount,
+                                sale_date, cobuyer_data, created_at, updated_at, user_id)
+             VALUES (?1, 'retail', ?2, 'v1', 'sold', 9000, ?3, '{\"firstName\":\"Al\"}',
+                     ?3, ?3, 'u1')",
+            params![id, client_id, updated_at],
+        )
+        .unwrap();
+    }
+
+    fn document(conn: &Connection, id: &str, deal_id: &str, created_at: i64) {
+        conn.execute(
+            "INSERT INTO documents (id, deal_id, type, filename, file_path, file_size,
+                                    created_at, updated_at, user_id)
+             VALUES (?1, ?2, 'bill_of_sale', 'a.pdf', ?3, 100, ?4, ?4, 'u1')",
+            params![id, deal_id, format!("/missing/{}.pdf", id), created_at],
+        )
+        .unwrap();
+    }
+
+    fn message(conn: &Connection, id: &str, client_id: &str, created_at: i64) {
+        conn.execute(
+            "INSERT INTO communications (id, user_id, client_id, channel, direction, subject,
+                                         body, status, created_at, updated_at)
+             VALUES (?1, 'u1', ?2, 'email', 'outbound', 'Hi', 'Your SSN is...', 'sent', ?3, ?3)",
+            params![id, client_id, created_at],
+        )
+        .unwrap();
+    }
+
+    fn set_settings(conn: &Connection, policies: &[(RetentionEntity, u32, RetentionAction)]) {
+        let settings = RetentionSettings {
+            policies: policies
+                .iter()
+                .map(|&(entity, years, action)| (entity, RetentionPolicy { years, action }))
+                .collect(),
+            monthly_reminder: true,
+        };
+        validate_settings(&settings).unwrap();
+        let value = serde_json::to_string(&settings).unwrap();
+        write_setting(conn, &settings_key("u1"), &value, 0).unwrap();
+    }
+
+    fn planned(items: &[PurgeItem]) -> Vec<(RetentionEntity, &str, RetentionAction)> {
+        items
+            .iter()
+            .map(|item| (item.entity, item.id.as_str(), item.action))
+            .collect()
+    }
+
+    fn exists(conn: &Connection, table: &str, id: &str) -> bool {
+        conn.query_row(
+            &format!("SELECT 1 FROM {} WHERE id = ?1", table),
+            params![id],
+            |_| Ok(()),
+        )
+        .optional()
+        .unwrap()
+        .is_some()
+    }
+
+    fn seed(conn: &Connection) {
+        use RetentionAction::{Delete, Scrub};
+        use RetentionEntity::*;
+        set_settings(
+            conn,
+            &[
+                (Clients, 7, Delete),
+                (Deals, 7, Delete),
+                (Documents, 7, Delete),
+                (Communications, 3, Scrub),
+            ],
+        );
+        // c1: old with an old deal, all of which goes
+        client(conn, "c1", OLD);
+        deal(conn, "d1", "c1", OLD);
+        document(conn, "doc1", "d1", OLD);
+        message(conn, "m1", "c1", OLD);
+        // c2: old, but with a recent deal; only the old message is scrubbed
+        client(conn, "c2", OLD);
+        deal(conn, "d2", "c2", RECENT);
+        document(conn, "doc2", "d2", RECENT);
+        message(conn, "m2", "c2", OLD);
+        message(conn, "m3", "c2", RECENT);
+    }
+
+    #[test]
+    fn test_apply_does_what_preview_showed() {
+        use RetentionAction::{Delete, Scrub};
+        use RetentionEntity::*;
+        let db = test_db();
+        let conn = db.conn();
+        seed(&conn);
+
+        let preview = preview(&conn, "u1", NOW).unwrap();
+        assert_eq!(
+            planned(&preview.items),
+            vec![
+                (Clients, "c1", Delete),
+                (Deals, "d1", Delete),
+                (Documents, "doc1", Delete),
+                (Communications, "m1", Delete),
+                (Communications, "m2", Scrub),
+            ]
+        );
+        let doc1 = &preview.items[2];
+        assert_eq!(doc1.deleted_with.as_deref(), Some("d1"));
+        assert_eq!(doc1.files, vec!["/missing/doc1.pdf".to_string()]);
+        assert_eq!(preview.items[3].deleted_with.as_deref(), Some("c1"));
+
+        // Another user can't apply it, and a changed plan needs a new preview
+        assert!(apply_purge(&conn, "u2", &preview.confirm_token, NOW).is_err());
+        assert!(apply_purge(&conn, "u1", "0000000000000000", NOW).is_err());
+        assert!(exists(&conn, "deals", "d1"));
+
+        let (items, audit_id) = apply_purge(&conn, "u1", &preview.confirm_token, NOW).unwrap();
+        assert_eq!(items, preview.items);
+        for (table, id) in [
+            ("clients", "c1"),
+            ("deals", "d1"),
+            ("documents", "doc1"),
+            ("communications", "m1"),
+        ] {
+            assert!(!exists(&conn, table, id), "{} {}", table, id);
+        }
+        for (table, id) in [
+            ("clients", "c2"),
+            ("deals", "d2"),
+            ("documents", "doc2"),
+            ("communications", "m3"),
+        ] {
+            assert!(exists(&conn, table, id), "{} {}", table, id);
+        }
+        let m2: (Option<String>, Option<String>) = conn
+            .query_row(
+                "SELECT subject, body FROM communications WHERE id = 'm2'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(m2, (None, None));
+
+        let audit = audit_entries(&conn, "u1", 10).unwrap();
+        assert_eq!(audit[0].id, audit_id);
+        assert_eq!(audit[0].action, "retention_purge");
+        assert_eq!(audit[0].details["counts"]["deals_delete"], 1);
+        assert_eq!(audit[0].details["counts"]["communications_scrub"], 1);
+
+        // Nothing left to do
+        let again = super::preview(&conn, "u1", NOW).unwrap();
+        assert!(again.items.is_empty());
+        assert!(apply_purge(&conn, "u1", &again.confirm_token, NOW).is_err());
+    }
+
+    #[test]
+    fn test_stale_token_changes_nothing() {
+        let db = test_db();
+        let conn = db.conn();
+        seed(&conn);
+        let preview = preview(&conn, "u1", NOW).unwrap();
+
+        client(&conn, "c3", OLD);
+        let err = apply_purge(&conn, "u1", &preview.confirm_token, NOW).unwrap_err();
+        assert!(err.to_string().contains("preview"), "{}", err);
+        assert!(exists(&conn, "clients", "c1"));
+        assert!(exists(&conn, "deals", "d1"));
+        assert!(audit_entries(&conn, "u1", 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_held_deals_are_excluded() {
+        use RetentionAction::{Delete, Scrub};
+        use RetentionEntity::*;
+        let db = test_db();
+        let conn = db.conn();
+        seed(&conn);
+        conn.execute("UPDATE deals SET retention_hold = 1 WHERE id = 'd1'", [])
+            .unwrap();
+
+        // d1 keeps its document, its client and the client's messages
+        let preview = preview(&conn, "u1", NOW).unwrap();
+        assert_eq!(preview.held_deals, vec!["d1".to_string()]);
+        assert_eq!(planned(&preview.items), vec![(Communications, "m2", Scrub)]);
+
+        // With a client policy that scrubs, c1 still isn't touched while d1 is held
+        set_settings(&conn, &[(Clients, 7, Scrub), (Deals, 7, Scrub)]);
+        assert!(
+            plan_purge(&conn, "u1", &load_settings(&conn, "u1").unwrap(), NOW)
+                .unwrap()
+                .is_empty()
+        );
+
+        // Released, the deal goes with everything that hangs off it; the client can't be
+        // deleted while d1 is only scrubbed, so it's scrubbed as well
+        conn.execute("UPDATE deals SET retention_hold = 0 WHERE id = 'd1'", [])
+            .unwrap();
+        set_settings(&conn, &[(Clients, 7, Delete), (Deals, 7, Scrub)]);
+        let preview = super::preview(&conn, "u1", NOW).unwrap();
+        assert_eq!(
+            planned(&preview.items),
+            vec![(Clients, "c1", Scrub), (Deals, "d1", Scrub)]
+        );
+        apply_purge(&conn, "u1", &preview.confirm_token, NOW).unwrap();
+        let (name, email, anonymized): (String, Option<String>, Option<i64>) = conn
+            .query_row(
+                "SELECT first_name, email, anonymized_at FROM clients WHERE id = 'c1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(
+            (name.as_str(), email, anonymized),
+            ("Removed", None, Some(NOW))
+        );
+        let cobuyer: Option<String> = conn
+            .query_row(
+                "SELECT cobuyer_data FROM deals WHERE id = 'd1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(cobuyer, None);
+        assert!(super::preview(&conn, "u1", NOW).unwrap().items.is_empty());
+    }
+
+    #[test]
+    fn test_settings_and_reminder() {
+        let documents_scrubbed = RetentionSettings {
+            policies: [(
+                RetentionEntity::Documents,
+                RetentionPolicy {
+                    years: 7,
+                    action: RetentionAction::Scrub,
+                },
+            )]
+            .into(),
+            monthly_reminder: false,
+        };
+        assert!(validate_settings(&documents_scrubbed).is_err());
+
+        let db = test_db();
+        let conn = db.conn();
+        assert_eq!(
+            load_settings(&conn, "u1").unwrap(),
+            RetentionSettings::default()
+        );
+        seed(&conn);
+        assert_eq!(check_reminder(&conn, "u1", NOW).unwrap(), Some(5));
+        assert_eq!(
+            check_reminder(&conn, "u1", NOW + YEAR_MS / 24).unwrap(),
+            None
+        );
+        assert!(check_reminder(&conn, "u1", NOW + REMINDER_EVERY_MS)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn test_cutoff_is_the_dealers_midnight() {
+        use chrono::DateTime;
+        use chrono_tz::America::Los_Angeles;
+        let ms = |rfc3339: &str| {
+            DateTime::parse_from_rfc3339(rfc3339)
+                .unwrap()
+                .timestamp_millis()
+        };
+        // 10pm on May 31 in Los Angeles is June 1 in UTC
+        let now = ms("2024-06-01T05:00:00Z");
+        assert_eq!(
+            cutoff_ms(now, 7, &Los_Angeles),
+            ms("2017-05-31T00:00:00-07:00")
+        );
+        assert_eq!(
+            cutoff_ms(now, 7, &chrono_tz::UTC),
+            ms("2017-06-01T00:00:00Z")
+        );
+    }
+}
@@ -1,11 +1,43 @@
 // src-tauri/src/s3_service.rs
 // S3 service for document upload/download sync
+//
+// Standalone dealers pay their own S3 bill, so s3_get_storage_usage counts what they have
+// under standalone/{userId}/ (deal documents and everything else) and estimates the monthly
+// cost at s3_price_per_gb_month. Listing a large bucket takes a while, so the count is kept
+// in settings and reused for a day unless the caller forces a recount; pages are added up
+// as they arrive rather than collected.
 
 use aws_credential_types::Credentials;
-use aws_sdk_s3::{Client as S3Client, Config, config::Region};
+use aws_sdk_s3::{config::Region, Client as S3Client, Config};
+use chrono::Utc;
 use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use tauri::State;
 
+use crate::app_state::AppState;
 use crate::aws_config;
+use crate::disk_space::{ensure_free_space, DiskSpaceError};
+use crate::docs_config::get_documents_root_path;
+use crate::logging::redact;
+use crate::money::round_to_cents;
+use crate::settings_cache::{get_setting, set_setting};
+use crate::shutdown::TASKS;
+use crate::storage::get_documents_storage_path;
+use crate::telemetry::track_async;
+
+/// Settings key: S3 storage price in dollars per GB-month, for the cost estimate
+pub const S3_PRICE_PER_GB_SETTING: &str = "s3_price_per_gb_month";
+/// S3 Standard in us-east-1
+const DEFAULT_PRICE_PER_GB: f64 = 0.023;
+/// Settings key prefix of a user's last object count (JSON), followed by the user id
+const USAGE_CACHE_SETTING: &str = "s3_storage_usage";
+/// How long a count is reused
+const USAGE_CACHE_TTL_MS: i64 = 24 * 60 * 60 * 1000;
+/// S3 bills by the binary GB
+const BYTES_PER_GB: f64 = 1024.0 * 1024.0 * 1024.0;
 
 /// Get S3 client configured with stored credentials
 async fn get_s3_client() -> Result<S3Client, String> {
@@ -49,9 +81,28 @@ async fn get_bucket_name() -> Result<String, String> {
         .ok_or_else(|| "AWS bucket name not configured".to_string())
 }
 
+/// Check that the stored credentials can reach the bucket (used by diagnostics)
+pub(crate) async fn probe_bucket() -> Result<(), String> {
+    let client = get_s3_client().await?;
+    let bucket = get_bucket_name().await?;
+
+    client
+        .head_bucket()
+        .bucket(&bucket)
+        .send()
+        .await
+        .map(|_| ())
+        .map_err(|e| format!("Failed to reach bucket: {}", e))
+}
+
 /// Generate S3 key for standalone document
 /// Format: standalone/{userId}/deals/{dealId}/documents/{documentId}_{filename}
-fn generate_s3_key(user_id: &str, deal_id: &str, document_id: &str, filename: &str) -> String {
+pub(crate) fn generate_s3_key(
+    user_id: &str,
+    deal_id: &str,
+    document_id: &str,
+    filename: &str,
+) -> String {
     format!(
         "standalone/{}/deals/{}/documents/{}_{}",
         user_id, deal_id, document_id, filename
@@ -67,122 +118,522 @@ pub async fn s3_upload_document(
     filename: String,
     file_data: Vec<u8>,
 ) -> Result<String, String> {
-    info!("📤 [S3] Uploading document to S3: {}", filename);
+    track_async("s3_upload_document", async move {
+        // Registered so shutdown waits for the upload (or aborts it) before exiting
+        let task = TASKS.begin("s3_upload_document")?;
+        info!("📤 [S3] Uploading document to S3: {}", redact(&filename));
 
-    let client = get_s3_client().await?;
-    let bucket = get_bucket_name().await?;
-    let s3_key = generate_s3_key(&user_id, &deal_id, &document_id, &filename);
+        let client = get_s3_client().await?;
+        let bucket = get_bucket_name().await?;
+        let s3_key = generate_s3_key(&user_id, &deal_id, &document_id, &filename);
 
-    let body = aws_sdk_s3::primitives::ByteStream::from(file_data);
+        let body = aws_sdk_s3::primitives::ByteStream::from(file_data);
 
-    match client
-        .put_object()
-        .bucket(&bucket)
-        .key(&s3_key)
-        .body(body)
-        .content_type("application/pdf")
-        .send()
-        .await
-    {
-        Ok(_) => {
-            info!("✅ [S3] Document uploaded successfully: {}", s3_key);
-            Ok(s3_key)
-        }
-        Err(e) => {
-            error!("❌ [S3] Failed to upload document: {}", e);
-            Err(format!("Failed to upload document to S3: {}", e))
+        let request = client
+            .put_object()
+            .bucket(&bucket)
+            .key(&s3_key)
+            .body(body)
+            .content_type("application/pdf")
+            .send();
+
+        match task.until_aborted(request).await? {
+            Ok(_) => {
+                info!(
+                    "✅ [S3] Document uploaded successfully: {}",
+                    redact(&s3_key)
+                );
+                Ok(s3_key)
+            }
+            Err(e) => {
+                error!("❌ [S3] Failed to upload document: {}", e);
+                Err(format!("Failed to upload document to S3: {}", e))
+            }
         }
-    }
+    })
+    .await
 }
 
 /// Download document from S3
 #[tauri::command]
-pub async fn s3_download_document(s3_key: String) -> Result<Vec<u8>, String> {
-    info!("📥 [S3] Downloading document from S3: {}", s3_key);
+pub async fn s3_download_document(s3_key: String) -> Result<Vec<u8>, DiskSpaceError> {
+    track_async("s3_download_document", async move {
+        let task = TASKS.begin("s3_download_document")?;
+        info!("📥 [S3] Downloading document from S3: {}", redact(&s3_key));
 
-    let client = get_s3_client().await?;
-    let bucket = get_bucket_name().await?;
+        let client = get_s3_client().await?;
+        let bucket = get_bucket_name().await?;
 
-    match client
-        .get_object()
-        .bucket(&bucket)
-        .key(&s3_key)
-        .send()
-        .await
-    {
-        Ok(response) => {
-            let mut data = Vec::new();
-            let mut body_stream = response.body;
-            while let Some(chunk_result) = body_stream.next().await {
-                match chunk_result {
-                    Ok(chunk) => data.extend_from_slice(&chunk),
-                    Err(e) => {
-                        error!("❌ [S3] Error reading response body: {}", e);
-                        return Err(format!("Failed to read S3 response: {}", e));
+        let request = client.get_object().bucket(&bucket).key(&s3_key).send();
+
+        match task.until_aborted(request).await? {
+            Ok(response) => {
+                // Downloaded documents land in the documents folder; don't start
+                // pulling the body if that volume is already nearly full
+                let documents_dir = match get_documents_root_path().await {
+                    Ok(Some(custom)) => custom,
+                    _ => get_documents_storage_path()?,
+                };
+                let incoming_bytes = response.content_length().unwrap_or(0).max(0) as u64;
+                ensure_free_space(Path::new(&documents_dir), incoming_bytes)?;
+
+                let mut data = Vec::new();
+                let mut body_stream = response.body;
+                while let Some(chunk_result) = task.until_aborted(body_stream.next()).await? {
+                    match chunk_result {
+                        Ok(chunk) => data.extend_from_slice(&chunk),
+                        Err(e) => {
+                            error!("❌ [S3] Error reading response body: {}", e);
+                            return Err(format!("Failed to read S3 response: {}", e).into());
+                        }
                     }
                 }
-            }
 
-            info!("✅ [S3] Document downloaded successfully: {} bytes", data.len());
-            Ok(data)
-        }
-        Err(e) => {
-            error!("❌ [S3] Failed to download document: {}", e);
-            Err(format!("Failed to download document from S3: {}", e))
+                info!(
+                    "✅ [S3] Document downloaded successfully: {} bytes",
+                    data.len()
+                );
+                Ok(data)
+            }
+            Err(e) => {
+                error!("❌ [S3] Failed to download document: {}", e);
+                Err(format!("Failed to download document from S3: {}", e).into())
+            }
         }
+    })
+    .await
+}
+
+/// Stream an object straight to a file (never buffers the whole body), returning its size
+/// Used for re-downloading documents that may be larger than we'd want in memory
+pub(crate) async fn download_to_file(s3_key: &str, destination: &Path) -> Result<u64, String> {
+    let task = TASKS.begin("s3_download_to_file")?;
+    info!("📥 [S3] Downloading to file: {}", redact(s3_key));
+
+    let client = get_s3_client().await?;
+    let bucket = get_bucket_name().await?;
+
+    let request = client.get_object().bucket(&bucket).key(s3_key).send();
+    let response = task
+        .until_aborted(request)
+        .await?
+        .map_err(|e| format!("Failed to download document from S3: {}", e))?;
+
+    let incoming_bytes = response.content_length().unwrap_or(0).max(0) as u64;
+    if let Some(parent) = destination.parent() {
+        ensure_free_space(parent, incoming_bytes).map_err(|e| e.to_string())?;
     }
+
+    let mut file = File::create(destination)
+        .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+    let mut written = 0u64;
+    let mut body_stream = response.body;
+    while let Some(chunk_result) = task.until_aborted(body_stream.next()).await? {
+        let chunk = chunk_result.map_err(|e| format!("Failed to read S3 response: {}", e))?;
+        file.write_all(&chunk)
+            .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))?;
+        written += chunk.len() as u64;
+    }
+    file.sync_all()
+        .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))?;
+
+    info!("✅ [S3] Downloaded {} bytes to file", written);
+    Ok(written)
 }
 
 /// Delete document from S3
 #[tauri::command]
 pub async fn s3_delete_document(s3_key: String) -> Result<(), String> {
-    info!("🗑️ [S3] Deleting document from S3: {}", s3_key);
+    track_async("s3_delete_document", async move {
+        let task = TASKS.begin("s3_delete_document")?;
+        info!("🗑️ [S3] Deleting document from S3: {}", redact(&s3_key));
 
-    let client = get_s3_client().await?;
-    let bucket = get_bucket_name().await?;
+        let client = get_s3_client().await?;
+        let bucket = get_bucket_name().await?;
 
-    match client
-        .delete_object()
-        .bucket(&bucket)
-        .key(&s3_key)
-        .send()
-        .await
-    {
-        Ok(_) => {
-            info!("✅ [S3] Document deleted successfully: {}", s3_key);
-            Ok(())
-        }
-        Err(e) => {
-            error!("❌ [S3] Failed to delete document: {}", e);
-            Err(format!("Failed to delete document from S3: {}", e))
+        let request = client.delete_object().bucket(&bucket).key(&s3_key).send();
+
+        match task.until_aborted(request).await? {
+            Ok(_) => {
+                info!("✅ [S3] Document deleted successfully: {}", redact(&s3_key));
+                Ok(())
+            }
+            Err(e) => {
+                error!("❌ [S3] Failed to delete document: {}", e);
+                Err(format!("Failed to delete document from S3: {}", e))
+            }
         }
-    }
+    })
+    .await
 }
 
 /// Check if document exists in S3
 #[tauri::command]
 pub async fn s3_document_exists(s3_key: String) -> Result<bool, String> {
-    let client = get_s3_client().await?;
-    let bucket = get_bucket_name().await?;
+    track_async("s3_document_exists", async move {
+        let client = get_s3_client().await?;
+        let bucket = get_bucket_name().await?;
 
-    match client
-        .head_object()
-        .bucket(&bucket)
-        .key(&s3_key)
-        .send()
-        .await
-    {
-        Ok(_) => Ok(true),
-        Err(e) => {
-            // Check if error is "NoSuchKey" by checking the error message
-            let error_msg = e.to_string();
-            if error_msg.contains("NoSuchKey") || error_msg.contains("not found") {
-                Ok(false)
+        match client
+            .head_object()
+            .bucket(&bucket)
+            .key(&s3_key)
+            .send()
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                // Check if error is "NoSuchKey" by checking the error message
+                let error_msg = e.to_string();
+                if error_msg.contains("NoSuchKey") || error_msg.contains("not found") {
+                    Ok(false)
+                } else {
+                    error!("❌ [S3] Error checking document existence: {}", e);
+                    Err(format!("Failed to check document existence: {}", e))
+                }
+            }
+        }
+    })
+    .await
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrefixUsage {
+    pub objects: u64,
+    pub bytes: u64,
+}
+
+impl PrefixUsage {
+    fn add(&mut self, size: i64) {
+        self.objects += 1;
+        self.bytes += size.max(0) as u64;
+    }
+}
+
+/// What a listing counted, as cached in settings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ObjectCounts {
+    /// Under deals/ (documents)
+    pub deals: PrefixUsage,
+    pub other: PrefixUsage,
+    pub counted_at: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct S3StorageUsage {
+    pub prefix: String,
+    pub deals: PrefixUsage,
+    pub other: PrefixUsage,
+    pub total: PrefixUsage,
+    /// Dollars per GB-month the estimate uses
+    pub price_per_gb: f64,
+    /// Dollars, storage only (requests and transfer aren't counted)
+    pub estimated_monthly_cost: f64,
+    /// When the objects were counted (epoch millis)
+    pub counted_at: i64,
+    /// The count is from the cache, not a listing just now
+    pub from_cache: bool,
+}
+
+/// One page of a ListObjectsV2 listing: (key, size in bytes) of each object
+pub(crate) struct ObjectPage {
+    pub objects: Vec<(String, i64)>,
+    /// Continuation token of the next page; None on the last one
+    pub next_token: Option<String>,
+}
+
+/// Pages of a listing (the bucket, or a mock in tests)
+pub(crate) trait ObjectLister {
+    async fn list_page(
+        &mut self,
+        prefix: &str,
+        token: Option<String>,
+    ) -> Result<ObjectPage, String>;
+}
+
+struct BucketLister {
+    client: S3Client,
+    bucket: String,
+}
+
+impl ObjectLister for BucketLister {
+    async fn list_page(
+        &mut self,
+        prefix: &str,
+        token: Option<String>,
+    ) -> Result<ObjectPage, String> {
+        let output = self
+            .client
+            .list_objects_v2()
+            .bucket(&self.bucket)
+            .prefix(prefix)
+            .set_continuation_token(token)
+            .send()
+            .await
+            .map_err(|e| format!("Failed to list S3 objects: {}", e))?;
+        let objects = output
+            .contents()
+            .iter()
+            .map(|object| {
+                (
+                    object.key().unwrap_or_default().to_string(),
+                    object.size().unwrap_or(0),
+                )
+            })
+            .collect();
+        let next_token = output
+            .next_continuation_token()
+            .filter(|_| output.is_truncated().unwrap_or(false))
+            .map(str::to_string);
+        Ok(ObjectPage {
+            objects,
+            next_token,
+        })
+    }
+}
+
+/// Everything a user has in the bucket is under this prefix
+fn user_prefix(user_id: &str) -> String {
+    format!("standalone/{}/", user_id)
+}
+
+/// Count the objects under prefix, one page at a time
+pub(crate) async fn count_objects(
+    lister: &mut impl ObjectLister,
+    prefix: &str,
+    now: i64,
+) -> Result<ObjectCounts, String> {
+    let deals_prefix = format!("{}deals/", prefix);
+    let mut counts = ObjectCounts {
+        counted_at: now,
+        ..ObjectCounts::default()
+    };
+    let mut token = None;
+    loop {
+        let page = lister.list_page(prefix, token.clone()).await?;
+        for (key, size) in page.objects {
+            if key.starts_with(&deals_prefix) {
+                counts.deals.add(size);
             } else {
-                error!("❌ [S3] Error checking document existence: {}", e);
-                Err(format!("Failed to check document existence: {}", e))
+                counts.other.add(size);
+            }
+        }
+        match page.next_token {
+            // A token that doesn't move on would list the same page forever
+            Some(next) if token.as_ref() == Some(&next) => {
+                return Err("S3 listing returned the same page twice".to_string());
             }
+            Some(next) => token = Some(next),
+            None => return Ok(counts),
         }
     }
 }
 
+fn usage_cache_key(user_id: &str) -> String {
+    format!("{}:{}", USAGE_CACHE_SETTING, user_id)
+}
+
+/// The user's last count, if it's less than a day old
+fn cached_counts(user_id: &str, now: i64) -> Option<ObjectCounts> {
+    get_setting(&usage_cache_key(user_id))
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str::<ObjectCounts>(&json).ok())
+        .filter(|counts| (0..USAGE_CACHE_TTL_MS).contains(&(now - counts.counted_at)))
+}
+
+fn store_counts(user_id: &str, counts: &ObjectCounts) -> Result<(), String> {
+    let json = serde_json::to_string(counts).map_err(|e| e.to_string())?;
+    set_setting(&usage_cache_key(user_id), &json)
+}
+
+/// The price setting, or S3 Standard's when it's missing or not a price
+fn price_per_gb() -> f64 {
+    get_setting(S3_PRICE_PER_GB_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse::<f64>().ok())
+        .filter(|price| price.is_finite() && *price >= 0.0)
+        .unwrap_or(DEFAULT_PRICE_PER_GB)
+}
+
+fn usage_from(
+    prefix: String,
+    counts: ObjectCounts,
+    price_per_gb: f64,
+    from_cache: bool,
+) -> S3StorageUsage {
+    let total = PrefixUsage {
+        objects: counts.deals.objects + counts.other.objects,
+        bytes: counts.deals.bytes + counts.other.bytes,
+    };
+    S3StorageUsage {
+        prefix,
+        deals: counts.deals,
+        other: counts.other,
+        total,
+        price_per_gb,
+        estimated_monthly_cost: round_to_cents(total.bytes as f64 / BYTES_PER_GB * price_per_gb),
+        counted_at: counts.counted_at,
+        from_cache,
+    }
+}
+
+/// Objects and bytes the user has in S3, deal documents and the rest, with an estimated
+/// monthly storage cost. A count from the last day is reused unless force_refresh
+#[tauri::command]
+pub async fn s3_get_storage_usage(
+    user_id: Option<String>,
+    force_refresh: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<S3StorageUsage, String> {
+    let user_id_value = state.require_user(user_id)?;
+
+    track_async("s3_get_storage_usage", async move {
+        let prefix = user_prefix(&user_id_value);
+        let now = Utc::now().timestamp_millis();
+        if !force_refresh.unwrap_or(false) {
+            if let Some(counts) = cached_counts(&user_id_value, now) {
+                return Ok(usage_from(prefix, counts, price_per_gb(), true));
+            }
+        }
+
+        info!("📊 [S3] Counting objects under {}", redact(&prefix));
+        let mut lister = BucketLister {
+            client: get_s3_client().await?,
+            bucket: get_bucket_name().await?,
+        };
+        let counts = count_objects(&mut lister, &prefix, now).await?;
+        store_counts(&user_id_value, &counts)?;
+        info!(
+            "✅ [S3] {} objects, {} bytes under {}",
+            counts.deals.objects + counts.other.objects,
+            counts.deals.bytes + counts.other.bytes,
+            redact(&prefix)
+        );
+        Ok(usage_from(prefix, counts, price_per_gb(), false))
+    })
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE_SIZE: usize = 1000;
+
+    /// A listing of `pages` full pages, made up page by page like the real one: every
+    /// third object is outside deals/, and object i is i bytes
+    struct MockLister {
+        pages: usize,
+        fail_on_page: Option<usize>,
+        requests: Vec<(String, Option<String>)>,
+    }
+
+    impl MockLister {
+        fn new(pages: usize) -> Self {
+            MockLister {
+                pages,
+                fail_on_page: None,
+                requests: Vec::new(),
+            }
+        }
+    }
+
+    impl ObjectLister for MockLister {
+        async fn list_page(
+            &mut self,
+            prefix: &str,
+            token: Option<String>,
+        ) -> Result<ObjectPage, String> {
+            self.requests.push((prefix.to_string(), token.clone()));
+            let page = match token {
+                None => 0,
+                Some(token) => token.strip_prefix("page-").unwrap().parse().unwrap(),
+            };
+            if self.fail_on_page == Some(page) {
+                return Err("Failed to list S3 objects: timed out".to_string());
+            }
+            let objects = (page * PAGE_SIZE..(page + 1) * PAGE_SIZE)
+                .map(|i| {
+                    let key = if i % 3 == 0 {
+                        format!("{}logo/{}.png", prefix, i)
+                    } else {
+                        format!("{}deals/d{}/documents/{}_contract.pdf", prefix, i % 7, i)
+                    };
+                    (key, i as i64)
+                })
+                .collect();
+            Ok(ObjectPage {
+                objects,
+                next_token: (page + 1 < self.pages).then(|| format!("page-{}", page + 1)),
+            })
+        }
+    }
+
+    #[test]
+    fn test_counts_add_up_across_pages() {
+        let mut lister = MockLister::new(3);
+        let prefix = user_prefix("u1");
+        let counts =
+            tauri::async_runtime::block_on(count_objects(&mut lister, &prefix, 42)).unwrap();
+
+        let objects = 3 * PAGE_SIZE as u64;
+        let (other_objects, other_bytes) = (0..objects)
+            .filter(|i| i % 3 == 0)
+            .fold((0, 0), |(n, bytes), i| (n + 1, bytes + i));
+        let all_bytes = objects * (objects - 1) / 2;
+        assert_eq!(
+            counts,
+            ObjectCounts {
+                deals: PrefixUsage {
+                    objects: objects - other_objects,
+                    bytes: all_bytes - other_bytes,
+                },
+                other: PrefixUsage {
+                    objects: other_objects,
+                    bytes: other_bytes,
+                },
+                counted_at: 42,
+            }
+        );
+        assert_eq!(
+            lister.requests,
+            vec![
+                ("standalone/u1/".to_string(), None),
+                ("standalone/u1/".to_string(), Some("page-1".to_string())),
+                ("standalone/u1/".to_string(), Some("page-2".to_string())),
+            ]
+        );
+
+        // A page that fails fails the count
+        let mut failing = MockLister::new(3);
+        failing.fail_on_page = Some(2);
+        assert!(tauri::async_runtime::block_on(count_objects(&mut failing, &prefix, 42)).is_err());
+    }
+
+    #[test]
+    fn test_usage_estimates_cost_and_reuses_a_recent_count() {
+        let counts = ObjectCounts {
+            deals: PrefixUsage {
+                objects: 120_000,
+                bytes: 150 * 1024 * 1024 * 1024,
+            },
+            other: PrefixUsage {
+                objects: 30,
+                bytes: 50 * 1024 * 1024 * 1024,
+            },
+            counted_at: 1_000,
+        };
+        let usage = usage_from(user_prefix("u1"), counts, 0.023, false);
+        assert_eq!(usage.total.objects, 120_030);
+        // 200 GB at $0.023
+        assert_eq!(usage.estimated_monthly_cost, 4.6);
+
+        let user = "s3-usage-test-user";
+        store_counts(user, &counts).unwrap();
+        assert_eq!(cached_counts(user, 1_000 + 60_000), Some(counts));
+        assert_eq!(cached_counts(user, 1_000 + USAGE_CACHE_TTL_MS), None);
+        assert_eq!(cached_counts("someone-else", 1_000), None);
+    }
+}
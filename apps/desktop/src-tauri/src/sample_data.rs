@@ -0,0 +1,984 @@
+// src-tauri/src/sample_data.rs
+//
+// Sample records for sales demos, for new users who want to look around before importing
+// their own inventory, and in bulk for performance tests. db_seed_sample_data writes
+// clients, vehicles (VINs with valid check digits, a purchase cost row each), deals in every
+// status with amounts that add up, documents pointing at one bundled PDF copied into the
+// documents root, call notes (logged communications) and tasks.
+// Everything comes from an RNG seeded with the profile and user id, so a user gets the same
+// data every time and two users' VINs don't collide. Only ids differ between runs.
+// Rows are flagged is_sample (migration 35) and db_remove_sample_data deletes only those.
+// A sample record the dealer's own data now depends on (a real deal on a sample vehicle, a
+// real document on a sample deal) is kept and unflagged instead.
+
+use chrono::Utc;
+use log::{info, warn};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rusqlite::{params, Connection, Transaction};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit_log::record_audit;
+use crate::csv_import::vin_check_digit;
+use crate::data_events::{change_batch, ChangedEntity};
+use crate::data_export::documents_root;
+use crate::database::{begin_write, new_row_id, DbState};
+use crate::db_busy::DbError;
+use crate::deal_status::DealStatus;
+use crate::documents_migration::file_sha256;
+use crate::money::from_cents;
+use crate::storage_usage::adjust_usage;
+use crate::telemetry::track;
+
+const SAMPLE_PDF: &[u8] = include_bytes!("../sample_data/sample_document.pdf");
+/// Under the documents root
+const SAMPLE_DIR: &str = "sample_data";
+const SAMPLE_FILE: &str = "sample_document.pdf";
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+/// Sample deals were started within this many days before seeding
+const HISTORY_DAYS: i64 = 180;
+const DOC_FEE_CENTS: i64 = 199_00;
+const SALES_TAX_BPS: i64 = 625;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleProfile {
+    /// About 50 rows, to look around
+    Small,
+    /// About 500 rows, for sales demos
+    Demo,
+    /// About 50,000 rows, for performance tests
+    Stress,
+}
+
+impl SampleProfile {
+    fn as_str(self) -> &'static str {
+        match self {
+            SampleProfile::Small => "small",
+            SampleProfile::Demo => "demo",
+            SampleProfile::Stress => "stress",
+        }
+    }
+
+    fn counts(self) -> SampleCounts {
+        let (clients, vehicles, deals, documents, notes, tasks) = match self {
+            SampleProfile::Small => (12, 15, 8, 5, 6, 4),
+            SampleProfile::Demo => (100, 140, 90, 60, 70, 40),
+            SampleProfile::Stress => (10_000, 12_000, 9_000, 6_000, 8_000, 5_000),
+        };
+        SampleCounts {
+            clients,
+            vehicles,
+            deals,
+            documents,
+            notes,
+            tasks,
+        }
+    }
+}
+
+/// Rows per table, seeded or removed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct SampleCounts {
+    pub clients: usize,
+    pub vehicles: usize,
+    pub deals: usize,
+    pub documents: usize,
+    pub notes: usize,
+    pub tasks: usize,
+}
+
+impl SampleCounts {
+    fn total(&self) -> usize {
+        self.clients + self.vehicles + self.deals + self.documents + self.notes + self.tasks
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SampleDataRemoval {
+    pub removed: SampleCounts,
+    /// Sample records kept as regular ones because the dealer's own records use them
+    pub kept: usize,
+}
+
+const FIRST_NAMES: &[&str] = &[
+    "James", "Maria", "Robert", "Linda", "Michael", "Ana", "David", "Jennifer", "Carlos",
+    "Patricia", "Daniel", "Aisha", "Kevin", "Emily", "Luis", "Grace", "Brian", "Nicole", "Tyrone",
+    "Mei", "Jose", "Hannah", "Anthony", "Sofia",
+];
+const LAST_NAMES: &[&str] = &[
+    "Johnson",
+    "Garcia",
+    "Smith",
+    "Nguyen",
+    "Williams",
+    "Martinez",
+    "Brown",
+    "Lee",
+    "Davis",
+    "Hernandez",
+    "Wilson",
+    "Lopez",
+    "Anderson",
+    "Patel",
+    "Thomas",
+    "Robinson",
+    "Clark",
+    "Walker",
+    "Young",
+    "Kim",
+];
+const STREETS: &[&str] = &[
+    "Oak St",
+    "Maple Ave",
+    "Cedar Ln",
+    "Pecan Dr",
+    "Main St",
+    "Elm St",
+    "Bluebonnet Trl",
+    "Ranch Rd",
+    "Lakeview Dr",
+    "Mesquite Ct",
+];
+/// City, state, ZIP
+const CITIES: &[(&str, &str, &str)] = &[
+    ("Austin", "TX", "78704"),
+    ("Round Rock", "TX", "78664"),
+    ("San Marcos", "TX", "78666"),
+    ("Georgetown", "TX", "78626"),
+    ("Pflugerville", "TX", "78660"),
+    ("Waco", "TX", "76706"),
+];
+/// Make, world manufacturer identifier, models with their price new in dollars
+const MAKES: &[(&str, &str, &[(&str, i64)])] = &[
+    (
+        "Honda",
+        "1HG",
+        &[("Civic", 24_000), ("Accord", 29_000), ("CR-V", 31_000)],
+    ),
+    (
+        "Toyota",
+        "4T1",
+        &[("Corolla", 23_000), ("Camry", 28_000), ("RAV4", 32_000)],
+    ),
+    (
+        "Ford",
+        "1FT",
+        &[("F-150", 42_000), ("Ranger", 34_000), ("Escape", 30_000)],
+    ),
+    (
+        "Chevrolet",
+        "1G1",
+        &[
+            ("Malibu", 26_000),
+            ("Equinox", 29_000),
+            ("Silverado 1500", 44_000),
+        ],
+    ),
+    (
+        "Nissan",
+        "1N4",
+        &[("Sentra", 21_000), ("Altima", 27_000), ("Rogue", 30_000)],
+    ),
+    (
+        "Jeep",
+        "1C4",
+        &[
+            ("Wrangler", 38_000),
+            ("Cherokee", 31_000),
+            ("Grand Cherokee", 42_000),
+        ],
+    ),
+    (
+        "Hyundai",
+        "5NP",
+        &[("Elantra", 22_000), ("Sonata", 27_000), ("Tucson", 29_000)],
+    ),
+    (
+        "Subaru",
+        "4S3",
+        &[
+            ("Impreza", 23_000),
+            ("Forester", 30_000),
+            ("Outback", 32_000),
+        ],
+    ),
+];
+const COLORS: &[&str] = &["White", "Black", "Silver", "Gray", "Blue", "Red", "Green"];
+/// Characters allowed in a VIN (no I, O or Q)
+const VIN_CHARS: &[u8] = b"ABCDEFGHJKLMNPRSTUVWXYZ0123456789";
+/// Model year codes (position 10) starting with 2010
+const YEAR_CODES: &[u8] = b"ABCDEFGHJKLMNPRSTVWXY";
+const FIRST_YEAR: i32 = 2014;
+const LAST_YEAR: i32 = 2024;
+const NOTES: &[&str] = &[
+    "Called about the test drive, coming in Saturday morning.",
+    "Left a voicemail with the payment options we discussed.",
+    "Asked for the trade-in number in writing.",
+    "Wants to bring their spouse to see the vehicle.",
+    "Checking with their credit union before deciding.",
+    "Confirmed insurance is set up for pickup.",
+];
+const TASKS: &[&str] = &[
+    "Follow up on the test drive",
+    "Get the signed bill of sale",
+    "Send the payoff request to the lender",
+    "Schedule delivery",
+    "Call about the extended warranty",
+];
+
+/// The same profile and user always get the same sequence
+fn sample_rng(profile: SampleProfile, user_id: &str) -> StdRng {
+    let digest = Sha256::digest(format!("sample-data:{}:{}", profile.as_str(), user_id));
+    StdRng::from_seed(digest.into())
+}
+
+fn pick<'a, T>(rng: &mut StdRng, items: &'a [T]) -> &'a T {
+    &items[rng.random_range(0..items.len())]
+}
+
+/// A 17-character VIN for the make and model year, with a correct check digit
+fn sample_vin(rng: &mut StdRng, wmi: &str, year: i32, serial: u32) -> String {
+    let mut vin: Vec<u8> = wmi.bytes().collect();
+    vin.extend((0..5).map(|_| *pick(rng, VIN_CHARS)));
+    vin.push(b'0');
+    vin.push(YEAR_CODES[(year - 2010) as usize]);
+    vin.push(*pick(rng, &VIN_CHARS[..24]));
+    vin.extend(format!("{:06}", serial).bytes());
+    let mut vin = String::from_utf8(vin).unwrap_or_default();
+    let check = vin_check_digit(&vin);
+    vin.replace_range(8..9, &check.to_string());
+    vin
+}
+
+struct SampleVehicle {
+    id: String,
+    price_cents: i64,
+}
+
+/// Deal statuses in rough proportion to a real lot's
+fn random_status(rng: &mut StdRng) -> DealStatus {
+    match rng.random_range(0..20) {
+        0..=2 => DealStatus::Quote,
+        3..=5 => DealStatus::Pending,
+        6..=7 => DealStatus::Approved,
+        8..=13 => DealStatus::Sold,
+        14..=17 => DealStatus::Completed,
+        _ => DealStatus::Cancelled,
+    }
+}
+
+/// Write the profile's rows for user_id inside tx; the PDF must already be at pdf_path
+fn insert_rows(
+    tx: &Transaction,
+    user_id: &str,
+    profile: SampleProfile,
+    pdf_path: &Path,
+    now: i64,
+) -> Result<SampleCounts, String> {
+    let counts = profile.counts();
+    let mut rng = sample_rng(profile, user_id);
+    let sql = |e: rusqlite::Error| e.to_string();
+
+    let mut clients = Vec::with_capacity(counts.clients);
+    {
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO clients (id, user_id, first_name, last_name, email, phone, address,
+                                      city, state, zip_code, created_at, updated_at, is_sample,
+                                      email_normalized, phone_e164)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?11, 1, ?5, ?12)",
+            )
+            .map_err(sql)?;
+        for n in 0..counts.clients {
+            let (first, last) = (*pick(&mut rng, FIRST_NAMES), *pick(&mut rng, LAST_NAMES));
+            let (city, state, zip) = *pick(&mut rng, CITIES);
+            let id = new_row_id();
+            insert
+                .execute(params![
+                    id,
+                    user_id,
+                    first,
+                    last,
+                    format!("{}.{}{}@example.com", first, last, n + 1).to_lowercase(),
+                    // 555-01xx numbers are reserved for fiction
+                    format!("(512) 555-01{:02}", n % 100),
+                    format!(
+                        "{} {}",
+                        rng.random_range(100..9900),
+                        pick(&mut rng, STREETS)
+                    ),
+                    city,
+                    state,
+                    zip,
+                    now - rng.random_range(HISTORY_DAYS..HISTORY_DAYS * 2) * DAY_MS,
+                    format!("+151255501{:02}", n % 100),
+                ])
+                .map_err(sql)?;
+            clients.push((id, format!("{} {}", first, last)));
+        }
+    }
+
+    let mut vehicles = Vec::with_capacity(counts.vehicles);
+    {
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO vehicles (id, user_id, vin, stock_number, year, make, model,
+                                       transmission, mileage, color, price, cost, status,
+                                       created_at, updated_at, is_sample)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 'Automatic', ?8, ?9, ?10, ?11,
+                         'available', ?12, ?12, 1)",
+            )
+            .map_err(sql)?;
+        let mut purchase = tx
+            .prepare(
+                "INSERT INTO vehicle_costs (id, vehicle_id, user_id, category, amount_cents,
+                                            description, incurred_at, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, 'purchase', ?4, 'Purchase price', ?5, ?5, ?5)",
+            )
+            .map_err(sql)?;
+        let first_serial = rng.random_range(100_000..800_000);
+        for n in 0..counts.vehicles {
+            let (make, wmi, models) = *pick(&mut rng, MAKES);
+            let (model, new_price) = *pick(&mut rng, models);
+            let year = rng.random_range(FIRST_YEAR..=LAST_YEAR);
+            let age = i64::from(LAST_YEAR + 1 - year);
+            let mileage = age * 11_000 + rng.random_range(-4_000..4_000);
+            // Priced to end in 95, bought for 75-88% of that
+            let price = (new_price - age * 1_800 - mileage / 1_000 * 30).max(4_500);
+            let price_cents = ((price / 500) * 500 - 5) * 100;
+            let cost_cents = price_cents * rng.random_range(75..=88) / 100 / 5_000 * 5_000;
+            let created_at = now - rng.random_range(HISTORY_DAYS..HISTORY_DAYS * 2) * DAY_MS;
+            let id = new_row_id();
+            insert
+                .execute(params![
+                    id,
+                    user_id,
+                    sample_vin(&mut rng, wmi, year, first_serial + n as u32),
+                    format!("SMP{:05}", n + 1),
+                    year,
+                    make,
+                    model,
+                    mileage.max(5),
+                    pick(&mut rng, COLORS),
+                    from_cents(price_cents),
+                    from_cents(cost_cents),
+                    created_at,
+                ])
+                .map_err(sql)?;
+            purchase
+                .execute(params![new_row_id(), id, user_id, cost_cents, created_at])
+                .map_err(sql)?;
+            vehicles.push(SampleVehicle { id, price_cents });
+        }
+    }
+
+    // One deal per vehicle, so no vehicle is sold twice
+    let mut deals = Vec::with_capacity(counts.deals);
+    {
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO deals (id, user_id, type, client_id, vehicle_id, status,
+                                    total_amount, sale_date, sale_amount, sales_tax, doc_fee,
+                                    trade_in_value, down_payment, financed_amount, document_ids,
+                                    created_at, updated_at, is_sample)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, '[]',
+                         ?15, ?16, 1)",
+            )
+            .map_err(sql)?;
+        let mut vehicle_status = tx
+            .prepare("UPDATE vehicles SET status = ?2 WHERE id = ?1")
+            .map_err(sql)?;
+        for (n, vehicle) in vehicles.iter().take(counts.deals).enumerate() {
+            let (client_id, _) = &clients[n % clients.len()];
+            let status = random_status(&mut rng);
+            let sale = vehicle.price_cents - rng.random_range(0..=8) * 250_00;
+            let trade_in = if rng.random_bool(0.35) {
+                rng.random_range(4..=36) * 250_00
+            } else {
+                0
+            };
+            let tax = ((sale - trade_in).max(0) * SALES_TAX_BPS + 5_000) / 10_000;
+            let total = sale + tax + DOC_FEE_CENTS - trade_in;
+            let financed = rng.random_bool(0.6);
+            let down = if financed {
+                (total * rng.random_range(5..=20) / 100 / 100_00) * 100_00
+            } else {
+                total
+            };
+            let created_at = now - rng.random_range(1..HISTORY_DAYS) * DAY_MS;
+            let sold = matches!(status, DealStatus::Sold | DealStatus::Completed);
+            let sale_date = sold.then(|| (created_at + rng.random_range(0..5) * DAY_MS).min(now));
+            let id = new_row_id();
+            insert
+                .execute(params![
+                    id,
+                    user_id,
+                    if financed { "finance" } else { "cash" },
+                    client_id,
+                    vehicle.id,
+                    status.as_str(),
+                    from_cents(total),
+                    sale_date,
+                    from_cents(sale),
+                    from_cents(tax),
+                    from_cents(DOC_FEE_CENTS),
+                    (trade_in > 0).then(|| from_cents(trade_in)),
+                    from_cents(down),
+                    financed.then(|| from_cents(total - down)),
+                    created_at,
+                    sale_date.unwrap_or(created_at),
+                ])
+                .map_err(sql)?;
+            let on_lot = match status {
+                _ if sold => "sold",
+                DealStatus::Pending | DealStatus::Approved => "pending",
+                _ => "available",
+            };
+            vehicle_status
+                .execute(params![vehicle.id, on_lot])
+                .map_err(sql)?;
+            deals.push((id, client_id.clone(), sale_date.unwrap_or(created_at)));
+        }
+    }
+
+    {
+        let file_path = pdf_path.to_string_lossy().to_string();
+        let checksum = file_sha256(pdf_path).map_err(|e| e.to_string())?;
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO documents (id, deal_id, user_id, type, filename, file_path,
+                                        file_size, file_checksum, created_at, updated_at,
+                                        is_sample)
+                 VALUES (?1, ?2, ?3, 'bill_of_sale', ?4, ?5, ?6, ?7, ?8, ?8, 1)",
+            )
+            .map_err(sql)?;
+        for (n, (deal_id, _, at)) in deals.iter().take(counts.documents).enumerate() {
+            insert
+                .execute(params![
+                    new_row_id(),
+                    deal_id,
+                    user_id,
+                    format!("Bill of Sale {:05}.pdf", n + 1),
+                    file_path,
+                    SAMPLE_PDF.len() as i64,
+                    checksum,
+                    at,
+                ])
+                .map_err(sql)?;
+        }
+        let size = (SAMPLE_PDF.len() * counts.documents.min(deals.len())) as i64;
+        adjust_usage(tx, user_id, size).map_err(sql)?;
+    }
+
+    {
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO communications (id, user_id, client_id, channel, direction, body,
+                                             status, created_at, updated_at, is_sample)
+                 VALUES (?1, ?2, ?3, 'call', ?4, ?5, 'logged', ?6, ?6, 1)",
+            )
+            .map_err(sql)?;
+        for n in 0..counts.notes {
+            let (client_id, _) = &clients[n % clients.len()];
+            insert
+                .execute(params![
+                    new_row_id(),
+                    user_id,
+                    client_id,
+                    if rng.random_bool(0.5) {
+                        "inbound"
+                    } else {
+                        "outbound"
+                    },
+                    pick(&mut rng, NOTES),
+                    now - rng.random_range(1..HISTORY_DAYS) * DAY_MS,
+                ])
+                .map_err(sql)?;
+        }
+    }
+
+    {
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO tasks (id, user_id, title, entity_type, entity_id, due_at,
+                                    completed_at, created_at, is_sample)
+                 VALUES (?1, ?2, ?3, 'deal', ?4, ?5, ?6, ?7, 1)",
+            )
+            .map_err(sql)?;
+        for n in 0..counts.tasks {
+            let (deal_id, client_id, _) = &deals[n % deals.len()];
+            let client = clients
+                .iter()
+                .find(|(id, _)| id == client_id)
+                .map(|(_, name)| name.as_str())
+                .unwrap_or_default();
+            // About a third already done, the rest due over the next two weeks
+            let due_at = now + rng.random_range(-7..14) * DAY_MS;
+            let completed_at = rng.random_bool(0.33).then_some(due_at.min(now));
+            insert
+                .execute(params![
+                    new_row_id(),
+                    user_id,
+                    format!("{} ({})", pick(&mut rng, TASKS), client),
+                    deal_id,
+                    due_at,
+                    completed_at,
+                    due_at.min(now) - DAY_MS,
+                ])
+                .map_err(sql)?;
+        }
+    }
+
+    Ok(SampleCounts {
+        deals: deals.len(),
+        documents: counts.documents.min(deals.len()),
+        ..counts
+    })
+}
+
+/// Delete user_id's sample rows inside tx, keeping (and unflagging) those that the user's
+/// own records depend on. Returns the counts, how many were kept and the bytes freed
+fn delete_rows(tx: &Transaction, user_id: &str) -> Result<(SampleDataRemoval, i64), String> {
+    let run = |sql: &str| tx.execute(sql, params![user_id]).map_err(|e| e.to_string());
+
+    let mut kept = run(
+        "UPDATE deals SET is_sample = 0 WHERE user_id = ?1 AND is_sample = 1 AND EXISTS (
+             SELECT 1 FROM documents WHERE deal_id = deals.id AND is_sample = 0)",
+    )?;
+    let freed: i64 = tx
+        .query_row(
+            "SELECT COALESCE(SUM(file_size), 0) FROM documents WHERE user_id = ?1 AND is_sample = 1",
+            params![user_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    let documents = run("DELETE FROM documents WHERE user_id = ?1 AND is_sample = 1")?;
+    let tasks = run("DELETE FROM tasks WHERE user_id = ?1 AND is_sample = 1")?;
+    let notes = run("DELETE FROM communications WHERE user_id = ?1 AND is_sample = 1")?;
+    let deals = run("DELETE FROM deals WHERE user_id = ?1 AND is_sample = 1")?;
+
+    // Deals reference clients and vehicles with ON DELETE RESTRICT
+    kept += run(
+        "UPDATE clients SET is_sample = 0 WHERE user_id = ?1 AND is_sample = 1 AND EXISTS (
+             SELECT 1 FROM deals WHERE client_id = clients.id)",
+    )?;
+    kept += run(
+        "UPDATE vehicles SET is_sample = 0 WHERE user_id = ?1 AND is_sample = 1 AND EXISTS (
+             SELECT 1 FROM deals WHERE vehicle_id = vehicles.id)",
+    )?;
+    let clients = run("DELETE FROM clients WHERE user_id = ?1 AND is_sample = 1")?;
+    let vehicles = run("DELETE FROM vehicles WHERE user_id = ?1 AND is_sample = 1")?;
+
+    if freed > 0 {
+        adjust_usage(tx, user_id, -freed).map_err(|e| e.to_string())?;
+    }
+    let removed = SampleCounts {
+        clients,
+        vehicles,
+        deals,
+        documents,
+        notes,
+        tasks,
+    };
+    Ok((SampleDataRemoval { removed, kept }, freed))
+}
+
+/// The sample PDF under docs_root, written if it isn't there yet
+fn copy_sample_pdf(docs_root: &Path) -> Result<PathBuf, String> {
+    let dir = docs_root.join(SAMPLE_DIR);
+    let path = dir.join(SAMPLE_FILE);
+    if fs::read(&path).is_ok_and(|bytes| bytes == SAMPLE_PDF) {
+        return Ok(path);
+    }
+    fs::create_dir_all(&dir).map_err(|e| format!("Can't create {}: {}", dir.display(), e))?;
+    fs::write(&path, SAMPLE_PDF).map_err(|e| format!("Can't write {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Remove the sample PDF once no document row points at it
+fn remove_sample_pdf(conn: &Connection, docs_root: &Path) {
+    let path = docs_root.join(SAMPLE_DIR).join(SAMPLE_FILE);
+    let in_use = conn
+        .query_row(
+            "SELECT 1 FROM documents WHERE file_path = ?1 LIMIT 1",
+            params![path.to_string_lossy()],
+            |_| Ok(()),
+        )
+        .is_ok();
+    if in_use || !path.exists() {
+        return;
+    }
+    if let Err(e) = fs::remove_file(&path) {
+        warn!("⚠️  Failed to remove sample PDF {:?}: {}", path, e);
+    }
+    // Only goes if empty
+    let _ = fs::remove_dir(docs_root.join(SAMPLE_DIR));
+}
+
+/// Replace user_id's sample data with a fresh set. Refuses when the user already has
+/// clients, vehicles or deals of their own, unless force
+pub(crate) fn seed(
+    conn: &Connection,
+    user_id: &str,
+    profile: SampleProfile,
+    docs_root: &Path,
+    force: bool,
+    now: i64,
+) -> Result<SampleCounts, DbError> {
+    let pdf_path = copy_sample_pdf(docs_root)?;
+    let tx = begin_write(conn, "sample_data")?;
+    if !force {
+        let has_own_data = tx
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM clients WHERE user_id = ?1 AND is_sample = 0)
+                     OR EXISTS (SELECT 1 FROM vehicles WHERE user_id = ?1 AND is_sample = 0)
+                     OR EXISTS (SELECT 1 FROM deals WHERE user_id = ?1 AND is_sample = 0)",
+                params![user_id],
+                |row| row.get::<_, bool>(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if has_own_data {
+            return Err("You already have your own clients, vehicles or deals; \
+                        sample data is only added to an empty account unless forced"
+                .to_string()
+                .into());
+        }
+    }
+    delete_rows(&tx, user_id)?;
+    let counts = insert_rows(&tx, user_id, profile, &pdf_path, now)?;
+    record_audit(
+        &tx,
+        user_id,
+        "sample_data_seeded",
+        &json!({ "profile": profile.as_str(), "rows": counts.total(), "forced": force }),
+        now,
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(counts)
+}
+
+pub(crate) fn remove(
+    conn: &Connection,
+    user_id: &str,
+    docs_root: &Path,
+    now: i64,
+) -> Result<SampleDataRemoval, DbError> {
+    let tx = begin_write(conn, "sample_data")?;
+    let (removal, _) = delete_rows(&tx, user_id)?;
+    record_audit(
+        &tx,
+        user_id,
+        "sample_data_removed",
+        &json!({ "rows": removal.removed.total(), "kept": removal.kept }),
+        now,
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    remove_sample_pdf(conn, docs_root);
+    Ok(removal)
+}
+
+fn report_changes(user_id: &str, counts: &SampleCounts) {
+    let mut batch = change_batch(user_id);
+    for (entity, count) in [
+        (ChangedEntity::Client, counts.clients),
+        (ChangedEntity::Vehicle, counts.vehicles),
+        (ChangedEntity::Deal, counts.deals),
+        (ChangedEntity::Document, counts.documents),
+        (ChangedEntity::Communication, counts.notes),
+        (ChangedEntity::Task, counts.tasks),
+    ] {
+        if count > 0 {
+            batch.record_count(entity, count);
+        }
+    }
+    batch.finish();
+}
+
+/// Fill the account with sample data (see the module notes). force adds it next to the
+/// user's own records
+#[tauri::command]
+pub fn db_seed_sample_data(
+    profile: SampleProfile,
+    force: Option<bool>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<SampleCounts, DbError> {
+    track("db_seed_sample_data", || {
+        let user_id_value = state.require_user(user_id)?;
+        let docs_root = PathBuf::from(documents_root()?);
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let counts = seed(
+            &conn,
+            &user_id_value,
+            profile,
+            &docs_root,
+            force.unwrap_or(false),
+            Utc::now().timestamp_millis(),
+        )?;
+        report_changes(&user_id_value, &counts);
+        info!(
+            "✅ Seeded {} sample rows ({}) for user: {}",
+            counts.total(),
+            profile.as_str(),
+            user_id_value
+        );
+        Ok(counts)
+    })
+}
+
+#[tauri::command]
+pub fn db_remove_sample_data(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<SampleDataRemoval, DbError> {
+    track("db_remove_sample_data", || {
+        let user_id_value = state.require_user(user_id)?;
+        let docs_root = PathBuf::from(documents_root()?);
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let removal = remove(
+            &conn,
+            &user_id_value,
+            &docs_root,
+            Utc::now().timestamp_millis(),
+        )?;
+        report_changes(&user_id_value, &removal.removed);
+        info!(
+            "✅ Removed {} sample rows ({} kept) for user: {}",
+            removal.removed.total(),
+            removal.kept,
+            user_id_value
+        );
+        Ok(removal)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Database;
+    use crate::test_support::{temp_dir, TEST_USER};
+
+    const NOW: i64 = 1_790_000_000_000;
+
+    /// Everything seeded except ids, in a stable order
+    fn snapshot(conn: &Connection, user_id: &str) -> Vec<String> {
+        let queries = [
+            "SELECT first_name || '|' || last_name || '|' || email || '|' || phone || '|'
+                    || address || '|' || created_at
+             FROM clients WHERE user_id = ?1 ORDER BY email",
+            "SELECT vin || '|' || stock_number || '|' || year || '|' || make || '|' || model
+                    || '|' || mileage || '|' || price_cents || '|' || cost_cents || '|' || status
+             FROM vehicles WHERE user_id = ?1 ORDER BY stock_number",
+            "SELECT v.stock_number || '|' || c.email || '|' || d.type || '|' || d.status || '|'
+                    || d.total_amount_cents || '|' || COALESCE(d.sale_date, '') || '|'
+                    || COALESCE(d.financed_amount_cents, '')
+             FROM deals d JOIN clients c ON c.id = d.client_id
+             JOIN vehicles v ON v.id = d.vehicle_id
+             WHERE d.user_id = ?1 ORDER BY v.stock_number",
+            "SELECT t.title || '|' || t.due_at || '|' || COALESCE(t.completed_at, '')
+             FROM tasks t WHERE t.user_id = ?1 ORDER BY t.title, t.due_at",
+        ];
+        let mut rows = Vec::new();
+        for sql in queries {
+            let mut stmt = conn.prepare(sql).unwrap();
+            rows.extend(
+                stmt.query_map([user_id], |row| row.get::<_, String>(0))
+                    .unwrap()
+                    .map(Result::unwrap),
+            );
+        }
+        rows
+    }
+
+    fn count(conn: &Connection, table: &str, user_id: &str) -> i64 {
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM {} WHERE user_id = ?1", table),
+            [user_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_seeding_is_deterministic_and_coherent() {
+        let docs = temp_dir("sample-data", "deterministic");
+        let first = Database::init_in_memory().unwrap();
+        let second = Database::init_in_memory().unwrap();
+        let counts = seed(
+            &first.conn(),
+            TEST_USER,
+            SampleProfile::Small,
+            &docs,
+            false,
+            NOW,
+        )
+        .unwrap();
+        seed(
+            &second.conn(),
+            TEST_USER,
+            SampleProfile::Small,
+            &docs,
+            false,
+            NOW,
+        )
+        .unwrap();
+        assert_eq!(counts.total(), 50);
+
+        let (first, second) = (first.conn(), second.conn());
+        let rows = snapshot(&first, TEST_USER);
+        assert_eq!(rows.len(), 12 + 15 + 8 + 4);
+        assert_eq!(rows, snapshot(&second, TEST_USER));
+
+        // Seeding again replaces the sample set with the same data
+        seed(&first, TEST_USER, SampleProfile::Small, &docs, false, NOW).unwrap();
+        assert_eq!(snapshot(&first, TEST_USER), rows);
+        assert_eq!(count(&first, "vehicles", TEST_USER), 15);
+
+        // Another user gets different VINs
+        seed(
+            &first,
+            "other-user",
+            SampleProfile::Small,
+            &docs,
+            false,
+            NOW,
+        )
+        .unwrap();
+        assert_ne!(snapshot(&first, "other-user")[12], rows[12]);
+
+        let mut stmt = first.prepare("SELECT vin FROM vehicles").unwrap();
+        for vin in stmt.query_map([], |row| row.get::<_, String>(0)).unwrap() {
+            let vin = vin.unwrap();
+            assert_eq!(vin.len(), 17);
+            assert_eq!(vin.chars().nth(8), Some(vin_check_digit(&vin)), "{}", vin);
+        }
+        // Deal totals add up and each vehicle's cost matches its cost rows
+        let bad_deals: i64 = first
+            .query_row(
+                "SELECT COUNT(*) FROM deals
+                 WHERE total_amount_cents != sale_amount_cents + sales_tax_cents + doc_fee_cents
+                                             - COALESCE(trade_in_value_cents, 0)
+                    OR (type = 'finance' AND financed_amount_cents + down_payment_cents
+                                             != total_amount_cents)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(bad_deals, 0);
+        let bad_costs: i64 = first
+            .query_row(
+                "SELECT COUNT(*) FROM vehicles v WHERE cost_cents != (
+                     SELECT SUM(amount_cents) FROM vehicle_costs WHERE vehicle_id = v.id)",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(bad_costs, 0);
+        assert!(docs.join(SAMPLE_DIR).join(SAMPLE_FILE).exists());
+        let _ = fs::remove_dir_all(&docs);
+    }
+
+    #[test]
+    fn test_removal_deletes_only_sample_rows() {
+        let docs = temp_dir("sample-data", "removal");
+        let db = Database::init_in_memory().unwrap();
+        let conn = db.conn();
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, created_at, updated_at, user_id)
+             VALUES ('real-client', 'Jane', 'Doe', 1, 1, 'test-user');",
+        )
+        .unwrap();
+
+        let err = seed(&conn, TEST_USER, SampleProfile::Demo, &docs, false, NOW).unwrap_err();
+        assert!(err.to_string().contains("unless forced"));
+        assert_eq!(count(&conn, "vehicles", TEST_USER), 0);
+
+        let counts = seed(&conn, TEST_USER, SampleProfile::Demo, &docs, true, NOW).unwrap();
+        assert_eq!(counts.total(), 500);
+        // The dealer sells one of the sample vehicles for real
+        let vehicle_id: String = conn
+            .query_row(
+                "SELECT id FROM vehicles WHERE is_sample = 1 AND status = 'available' LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        conn.execute(
+            "INSERT INTO deals (id, user_id, type, client_id, vehicle_id, status, total_amount,
+                                document_ids, created_at, updated_at)
+             VALUES ('real-deal', 'test-user', 'cash', 'real-client', ?1, 'quote', 1000, '[]', 1, 1)",
+            [&vehicle_id],
+        )
+        .unwrap();
+        let used: i64 = conn
+            .query_row(
+                "SELECT used_bytes FROM storage_usage WHERE user_id = 'test-user'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(used, 60 * SAMPLE_PDF.len() as i64);
+
+        let removal = remove(&conn, TEST_USER, &docs, NOW).unwrap();
+        assert_eq!(removal.kept, 1);
+        assert_eq!(
+            removal.removed,
+            SampleCounts {
+                vehicles: 139,
+                ..SampleProfile::Demo.counts()
+            }
+        );
+        for table in [
+            "clients",
+            "vehicles",
+            "deals",
+            "documents",
+            "communications",
+            "tasks",
+        ] {
+            let left: i64 = conn
+                .query_row(
+                    &format!("SELECT COUNT(*) FROM {} WHERE is_sample = 1", table),
+                    [],
+                    |row| row.get(0),
+                )
+                .unwrap();
+            assert_eq!(left, 0, "{}", table);
+        }
+        assert_eq!(count(&conn, "clients", TEST_USER), 1);
+        assert_eq!(count(&conn, "vehicles", TEST_USER), 1);
+        assert_eq!(count(&conn, "deals", TEST_USER), 1);
+        let orphaned_costs: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM vehicle_costs WHERE vehicle_id != ?1",
+                [&vehicle_id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(orphaned_costs, 0);
+        let used: i64 = conn
+            .query_row(
+                "SELECT used_bytes FROM storage_usage WHERE user_id = 'test-user'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(used, 0);
+        assert!(!docs.join(SAMPLE_DIR).exists());
+        let _ = fs::remove_dir_all(&docs);
+    }
+}
@@ -0,0 +1,739 @@
+// src-tauri/src/scrubbed_export.rs
+//
+// Read-only snapshot of the database for outside access (the accountant): the SQLite
+// backup API copies the live database to a new file, then the copy is scrubbed in place.
+// What gets scrubbed is a scrub profile, a JSON list of "table.column" (or
+// "table.column.key" for a key inside a JSON column such as deals.cobuyer_data) -> action
+// rules. Two built-in profiles can be overridden or extended via the scrub_profiles setting.
+// Settings, webhooks (signing secrets), the search index (copies of names and phones) and
+// unreadable co-buyer blobs kept by migration 25 are dropped from every copy. After VACUUM the output file is scanned for the original
+// values; a copy that still contains one is deleted instead of being handed out.
+// Encrypted columns (sensitive_fields.rs) are cleared in every copy too.
+
+use log::{info, warn};
+use rusqlite::backup::Backup;
+use rusqlite::types::ValueRef;
+use rusqlite::{params, Connection, Result as SqlResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::{db_get_setting, get_db};
+use crate::db_timeout::{with_query_timeout, EXPORT_QUERY_TIMEOUT};
+use crate::sensitive_fields::ENCRYPTED_COLUMNS;
+use crate::settings_cache::set_setting;
+use crate::telemetry::track;
+
+pub const SCRUB_PROFILES_SETTING: &str = "scrub_profiles";
+
+/// Dropped from every copy, whatever the profile says
+const DROPPED_TABLES: &[&str] = &[
+    "settings",
+    "webhook_deliveries",
+    "webhooks",
+    "clients_search",
+    "vehicles_search",
+    "deals_search",
+    "deal_cobuyers_search",
+    "cobuyer_migration_issues",
+    // Names the path of the pre-migration backup
+    "money_conversion",
+];
+/// Shorter originals are too likely to match unrelated bytes to be worth scanning for
+const MIN_SCAN_LEN: usize = 4;
+/// Hex digits kept from the salted SHA-256; plenty to join on, nothing to reverse
+const HASH_LEN: usize = 16;
+const BACKUP_PAGES_PER_STEP: i32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrubAction {
+    /// Replace the value with NULL
+    Null,
+    /// Replace the value with a salted hash, the same for equal values within one copy
+    Hash,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrubRule {
+    /// "table.column", or "table.column.key" for a key of a JSON object column
+    pub column: String,
+    pub action: ScrubAction,
+}
+
+pub type ScrubProfile = Vec<ScrubRule>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleReport {
+    pub column: String,
+    pub action: ScrubAction,
+    /// Rows whose value was replaced
+    pub rows: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ScrubReport {
+    pub path: String,
+    pub profile: String,
+    pub rules: Vec<RuleReport>,
+    pub dropped_tables: Vec<String>,
+    /// Distinct original values confirmed absent from the output file
+    pub values_verified: usize,
+    pub size_bytes: u64,
+}
+
+/// Where a rule points, checked against the copy's schema
+struct Target {
+    table: String,
+    column: String,
+    json_key: Option<String>,
+}
+
+/// Built-in and custom profiles by name (custom ones win)
+#[tauri::command]
+pub fn get_scrub_profiles() -> Result<BTreeMap<String, ScrubProfile>, String> {
+    let mut profiles = builtin_profiles();
+    profiles.extend(custom_profiles()?);
+    Ok(profiles)
+}
+
+#[tauri::command]
+pub fn save_scrub_profile(name: String, profile: ScrubProfile) -> Result<(), String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Profile name can't be empty".to_string());
+    }
+    for rule in &profile {
+        split_column(&rule.column)?;
+    }
+
+    let mut profiles = custom_profiles()?;
+    profiles.insert(name.clone(), profile);
+    let json = serde_json::to_string(&profiles).map_err(|e| e.to_string())?;
+    set_setting(SCRUB_PROFILES_SETTING, &json)?;
+    info!("✅ Saved scrub profile: {}", name);
+    Ok(())
+}
+
+/// Copy the database to output_path with the named profile's columns scrubbed
+#[tauri::command]
+pub fn export_scrubbed_database(
+    user_id: Option<String>,
+    output_path: String,
+    scrub_profile: String,
+    state: State<'_, AppState>,
+) -> Result<ScrubReport, String> {
+    track("export_scrubbed_database", || {
+        state.require_user(user_id)?;
+        info!(
+            "🧹 Exporting scrubbed database ({}) to: {}",
+            scrub_profile, output_path
+        );
+        let report = with_query_timeout(EXPORT_QUERY_TIMEOUT, || {
+            scrubbed_copy(&scrub_profile, Path::new(&output_path))
+        })?;
+
+        info!(
+            "✅ Scrubbed database exported: {} rules, {} values verified absent",
+            report.rules.len(),
+            report.values_verified
+        );
+        Ok(report)
+    })
+}
+
+/// Scrubbed copy of the live database at output (also used by the support bundle)
+pub(crate) fn scrubbed_copy(scrub_profile: &str, output: &Path) -> Result<ScrubReport, String> {
+    let rules = get_scrub_profiles()?
+        .remove(scrub_profile)
+        .ok_or_else(|| format!("Unknown scrub profile: {}", scrub_profile))?;
+
+    let partial = partial_path(output);
+    {
+        // The live database is only locked while it's copied
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        backup_to(&conn, &partial)?;
+    }
+    let mut report = scrub_copy(&partial, &rules, output)?;
+    report.profile = scrub_profile.to_string();
+    Ok(report)
+}
+
+fn custom_profiles() -> Result<BTreeMap<String, ScrubProfile>, String> {
+    match db_get_setting(SCRUB_PROFILES_SETTING.to_string())? {
+        Some(json) if !json.trim().is_empty() => serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid scrub profiles setting: {}", e)),
+        _ => Ok(BTreeMap::new()),
+    }
+}
+
+fn builtin_profiles() -> BTreeMap<String, ScrubProfile> {
+    let rule = |column: &str, action| ScrubRule {
+        column: column.to_string(),
+        action,
+    };
+    let accountant = vec![
+        rule("clients.drivers_license", ScrubAction::Null),
+        rule("deals.cobuyer_data.driversLicense", ScrubAction::Null),
+        rule("deals.cobuyer_data.ssn", ScrubAction::Null),
+        rule("deal_cobuyers.drivers_license", ScrubAction::Null),
+    ];
+    let mut no_contact = accountant.clone();
+    no_contact.extend([
+        rule("clients.phone", ScrubAction::Hash),
+        rule("clients.phone_e164", ScrubAction::Hash),
+        rule("clients.email", ScrubAction::Hash),
+        rule("clients.email_normalized", ScrubAction::Hash),
+        rule("deals.cobuyer_data.phone", ScrubAction::Hash),
+        rule("deals.cobuyer_data.email", ScrubAction::Hash),
+        rule("deal_cobuyers.phone", ScrubAction::Hash),
+        rule("deal_cobuyers.email", ScrubAction::Hash),
+        rule("communications.body", ScrubAction::Null),
+        rule("vehicle_holds.note", ScrubAction::Null),
+        rule("vehicle_events.notes", ScrubAction::Null),
+    ]);
+
+    BTreeMap::from([
+        ("accountant".to_string(), accountant),
+        ("accountant_no_contact".to_string(), no_contact),
+    ])
+}
+
+fn split_column(column: &str) -> Result<(&str, &str, Option<&str>), String> {
+    let parts: Vec<&str> = column.split('.').map(str::trim).collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(format!("Invalid scrub column: {:?}", column));
+    }
+    match parts[..] {
+        [table, column] => Ok((table, column, None)),
+        [table, column, key] => Ok((table, column, Some(key))),
+        _ => Err(format!(
+            "Invalid scrub column {:?}: expected table.column or table.column.key",
+            column
+        )),
+    }
+}
+
+/// The copy is built under a .partial name and only renamed into place once verified
+fn partial_path(output_path: &Path) -> PathBuf {
+    let mut partial = output_path.as_os_str().to_owned();
+    partial.push(".partial");
+    PathBuf::from(partial)
+}
+
+/// Copy `source` to path with the backup API
+fn backup_to(source: &Connection, path: &Path) -> Result<(), String> {
+    let _ = fs::remove_file(path);
+    let mut copy = Connection::open(path).map_err(|e| format!("Failed to copy database: {}", e))?;
+    let copied = Backup::new(source, &mut copy)
+        .and_then(|backup| backup.run_to_completion(BACKUP_PAGES_PER_STEP, Duration::ZERO, None))
+        // A single self-contained file: no -wal next to it
+        .and_then(|_| {
+            copy.query_row("PRAGMA journal_mode = DELETE", [], |row| {
+                row.get::<_, String>(0)
+            })
+        });
+    drop(copy);
+    if let Err(e) = copied {
+        let _ = fs::remove_file(path);
+        return Err(format!("Failed to copy database: {}", e));
+    }
+    Ok(())
+}
+
+/// Scrub, verify and move a backup_to copy to output_path. The copy is deleted on failure
+fn scrub_copy(
+    partial: &Path,
+    rules: &[ScrubRule],
+    output_path: &Path,
+) -> Result<ScrubReport, String> {
+    let result = scrub_in_place(partial, rules).and_then(|(mut report, originals)| {
+        report.values_verified = verify_scrubbed(partial, &originals)?;
+        fs::rename(partial, output_path)
+            .map_err(|e| format!("Failed to move scrubbed copy into place: {}", e))?;
+        Ok(report)
+    });
+    let mut report = match result {
+        Ok(report) => report,
+        Err(e) => {
+            let _ = fs::remove_file(partial);
+            return Err(e);
+        }
+    };
+
+    report.path = output_path.to_string_lossy().to_string();
+    report.size_bytes = fs::metadata(output_path).map(|m| m.len()).unwrap_or(0);
+    Ok(report)
+}
+
+/// Scrub the copy at path. Returns the report plus each rule's original values
+fn scrub_in_place(
+    path: &Path,
+    rules: &[ScrubRule],
+) -> Result<(ScrubReport, Vec<(String, String)>), String> {
+    let sql_err = |e: rusqlite::Error| format!("Failed to scrub copy: {}", e);
+    let mut copy = Connection::open(path).map_err(sql_err)?;
+
+    // The copy is never written again, so triggers only get in the way of the scrub
+    // (the search triggers would write into tables that are about to go)
+    let triggers: Vec<String> = copy
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'trigger'")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get(0))?
+                .collect::<SqlResult<_>>()
+        })
+        .map_err(sql_err)?;
+    for trigger in triggers {
+        copy.execute_batch(&format!("DROP TRIGGER {}", quote(&trigger)))
+            .map_err(sql_err)?;
+    }
+    let mut dropped_tables = Vec::new();
+    for table in DROPPED_TABLES {
+        if table_exists(&copy, table).map_err(sql_err)? {
+            copy.execute_batch(&format!("DROP TABLE {}", quote(table)))
+                .map_err(sql_err)?;
+            dropped_tables.push(table.to_string());
+        }
+    }
+
+    let salt: [u8; 16] = rand::random();
+    let mut rule_reports = Vec::new();
+    let mut originals = Vec::new();
+    let tx = copy.transaction().map_err(sql_err)?;
+    // Ciphertext is no use without the key, which never leaves this machine
+    for (table, column) in ENCRYPTED_COLUMNS {
+        if column_exists(&tx, table, column).map_err(sql_err)? {
+            tx.execute_batch(&format!(
+                "UPDATE {table} SET {column} = NULL",
+                table = quote(table),
+                column = quote(column)
+            ))
+            .map_err(sql_err)?;
+        }
+    }
+    for rule in rules {
+        let target = resolve_target(&tx, rule)?;
+        let (rows, values) = apply_rule(&tx, &target, rule.action, &salt).map_err(sql_err)?;
+        originals.extend(values.into_iter().map(|v| (rule.column.clone(), v)));
+        rule_reports.push(RuleReport {
+            column: rule.column.clone(),
+            action: rule.action,
+            rows,
+        });
+    }
+    tx.commit().map_err(sql_err)?;
+    // Rewrites the file so freed pages holding the old values are gone too
+    copy.execute_batch("VACUUM").map_err(sql_err)?;
+    copy.close().map_err(|(_, e)| sql_err(e))?;
+
+    let report = ScrubReport {
+        path: String::new(),
+        profile: String::new(),
+        rules: rule_reports,
+        dropped_tables,
+        values_verified: 0,
+        size_bytes: 0,
+    };
+    Ok((report, originals))
+}
+
+fn table_exists(conn: &Connection, table: &str) -> SqlResult<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+}
+
+fn column_exists(conn: &Connection, table: &str, column: &str) -> SqlResult<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|count| count > 0)
+}
+
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+/// A rule naming a column the copy doesn't have is an error, not a no-op: a typo must
+/// not quietly leave the real column unscrubbed
+fn resolve_target(conn: &Connection, rule: &ScrubRule) -> Result<Target, String> {
+    let (table, column, json_key) = split_column(&rule.column)?;
+    let not_null: Option<bool> = conn
+        .prepare(&format!("PRAGMA table_info({})", quote(table)))
+        .and_then(|mut stmt| {
+            let columns = stmt
+                .query_map([], |row| {
+                    Ok((row.get::<_, String>(1)?, row.get::<_, i64>(3)? != 0))
+                })?
+                .collect::<SqlResult<Vec<_>>>()?;
+            Ok(columns
+                .into_iter()
+                .find(|(name, _)| name == column)
+                .map(|(_, not_null)| not_null))
+        })
+        .map_err(|e| format!("Scrub rule {}: {}", rule.column, e))?;
+
+    match not_null {
+        None => Err(format!("Scrub rule {}: no such column", rule.column)),
+        Some(true) if rule.action == ScrubAction::Null && json_key.is_none() => Err(format!(
+            "Scrub rule {}: column can't be NULL, use hash",
+            rule.column
+        )),
+        Some(_) => Ok(Target {
+            table: table.to_string(),
+            column: column.to_string(),
+            json_key: json_key.map(str::to_string),
+        }),
+    }
+}
+
+fn scrub_value(original: &str, action: ScrubAction, salt: &[u8]) -> Option<String> {
+    match action {
+        ScrubAction::Null => None,
+        ScrubAction::Hash => {
+            let mut hasher = Sha256::new();
+            hasher.update(salt);
+            hasher.update(original.as_bytes());
+            let hex = format!("{:x}", hasher.finalize());
+            Some(hex[..HASH_LEN].to_string())
+        }
+    }
+}
+
+/// Scrub one column (or JSON key) in every row. Returns the rows changed and the
+/// original values
+fn apply_rule(
+    conn: &Connection,
+    target: &Target,
+    action: ScrubAction,
+    salt: &[u8],
+) -> SqlResult<(usize, Vec<String>)> {
+    let rows: Vec<(i64, String)> = {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT rowid, {col} FROM {table} WHERE {col} IS NOT NULL",
+            col = quote(&target.column),
+            table = quote(&target.table)
+        ))?;
+        let rows = stmt.query_map([], |row| {
+            let value = match row.get_ref(1)? {
+                ValueRef::Text(text) => Some(String::from_utf8_lossy(text).to_string()),
+                ValueRef::Integer(n) => Some(n.to_string()),
+                ValueRef::Real(n) => Some(n.to_string()),
+                ValueRef::Null | ValueRef::Blob(_) => None,
+            };
+            let rowid: i64 = row.get(0)?;
+            Ok(value.map(|value| (rowid, value)))
+        })?;
+        rows.filter_map(|row| row.transpose())
+            .collect::<SqlResult<_>>()?
+    };
+
+    let mut update = conn.prepare(&format!(
+        "UPDATE {} SET {} = ?1 WHERE rowid = ?2",
+        quote(&target.table),
+        quote(&target.column)
+    ))?;
+    let mut changed = 0;
+    let mut originals = Vec::new();
+    for (rowid, value) in rows {
+        let replacement = match &target.json_key {
+            None => {
+                let replacement = scrub_value(&value, action, salt);
+                originals.push(value);
+                replacement
+            }
+            Some(key) => match serde_json::from_str::<Value>(&value) {
+                Ok(Value::Object(mut object)) => {
+                    let original = match object.get(key) {
+                        None | Some(Value::Null) => continue,
+                        Some(Value::String(s)) => s.clone(),
+                        Some(other) => other.to_string(),
+                    };
+                    let scrubbed = scrub_value(&original, action, salt)
+                        .map(Value::String)
+                        .unwrap_or(Value::Null);
+                    object.insert(key.clone(), scrubbed);
+                    originals.push(original);
+                    Some(Value::Object(object).to_string())
+                }
+                // Not an object, so the key can't be picked out: scrub the whole value
+                _ => {
+                    let replacement = scrub_value(&value, action, salt);
+                    originals.push(value);
+                    replacement
+                }
+            },
+        };
+        update.execute(params![replacement, rowid])?;
+        changed += 1;
+    }
+    Ok((changed, originals))
+}
+
+/// Scan the copy's bytes for every original value. Needles are bucketed by their first
+/// MIN_SCAN_LEN bytes so the file is walked once however many values there are
+fn verify_scrubbed(path: &Path, originals: &[(String, String)]) -> Result<usize, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read scrubbed copy: {}", e))?;
+
+    let mut seen = HashSet::new();
+    let mut buckets: HashMap<&[u8], Vec<(&[u8], &str)>> = HashMap::new();
+    for (column, value) in originals {
+        let needle = value.trim().as_bytes();
+        if needle.len() < MIN_SCAN_LEN || !seen.insert(needle) {
+            continue;
+        }
+        buckets
+            .entry(&needle[..MIN_SCAN_LEN])
+            .or_default()
+            .push((needle, column));
+    }
+
+    for start in 0..bytes.len().saturating_sub(MIN_SCAN_LEN - 1) {
+        let Some(candidates) = buckets.get(&bytes[start..start + MIN_SCAN_LEN]) else {
+            continue;
+        };
+        if let Some((_, column)) = candidates
+            .iter()
+            .find(|(needle, _)| bytes[start..].starts_with(needle))
+        {
+            // Never log the value itself
+            warn!("⚠️  Scrubbed copy still contains a value from {}", column);
+            return Err(format!(
+                "Scrub verification failed: a value from {} is still in the copy (it is \
+                 stored in another column too; add that column to the profile)",
+                column
+            ));
+        }
+    }
+    Ok(seen.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn test_db(path: &Path) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        let _mode: String = conn
+            .query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))
+            .unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/004_add_vehicle_images.sql"),
+            include_str!("../migrations/009_add_webhooks.sql"),
+            include_str!("../migrations/016_add_search_index.sql"),
+            include_str!("../migrations/021_add_communications.sql"),
+            include_str!("../migrations/025_add_deal_cobuyers.sql"),
+            include_str!("../migrations/027_add_vehicle_holds.sql"),
+            include_str!("../migrations/029_add_sensitive_fields.sql"),
+            include_str!("../migrations/030_add_vehicle_events.sql"),
+            include_str!("../migrations/036_normalize_contacts.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, email, phone, drivers_license,
+                 created_at, updated_at, user_id, email_normalized, phone_e164)
+             VALUES ('c1', 'Ana', 'Diaz', 'ana@example.com', '(555) 201-3344', 'D1234-5678-90',
+                 1, 1, 'u1', 'ana@example.com', '+15552013344'),
+                    ('c2', 'Ben', 'Cole', NULL, '555-777-0101', 'TX99887766', 1, 1, 'u1',
+                 NULL, '+15557770101');
+             INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status,
+                 created_at, updated_at, user_id)
+             VALUES ('v1', '1HGCM82633A004352', 2019, 'Honda', 'Accord', 41000, 21000, 'sold',
+                 1, 1, 'u1');
+             INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount,
+                 cobuyer_data, created_at, updated_at, user_id)
+             VALUES ('d1', 'cash', 'c1', 'v1', 'completed', 21000,
+                 '{\"firstName\":\"Cara\",\"lastName\":\"Diaz\",\"driversLicense\":\"CB-4411-0090\",\"ssn\":\"123-45-6789\",\"phone\":\"555-010-2020\"}',
+                 1, 1, 'u1');
+             INSERT INTO deal_cobuyers (id, deal_id, user_id, first_name, last_name, phone,
+                 drivers_license, ssn_encrypted, created_at, updated_at)
+             VALUES ('cb1', 'd1', 'u1', 'Cara', 'Diaz', '555-010-2020', 'CB-4411-0090',
+                 'c3NuLWNpcGhlcnRleHQtY2Ix', 1, 1);
+             INSERT INTO settings (key, value, updated_at) VALUES ('api_token', 'tok-secret', 1);
+             INSERT INTO webhooks (id, user_id, url, secret, events, created_at, updated_at)
+             VALUES ('w1', 'u1', 'https://example.com/hook', 'whsec-abc123', '[]', 1, 1);",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn setup(name: &str) -> (PathBuf, Connection) {
+        let dir = temp_dir("scrub", name);
+        let conn = test_db(&dir.join("live.db"));
+        (dir, conn)
+    }
+
+    fn scrubbed_copy(
+        live: &Connection,
+        rules: &[ScrubRule],
+        output: &Path,
+    ) -> Result<ScrubReport, String> {
+        let partial = partial_path(output);
+        backup_to(live, &partial)?;
+        scrub_copy(&partial, rules, output)
+    }
+
+    fn contains(haystack: &[u8], needle: &str) -> bool {
+        haystack
+            .windows(needle.len())
+            .any(|window| window == needle.as_bytes())
+    }
+
+    #[test]
+    fn test_scrubbed_values_are_absent_and_original_untouched() {
+        let (dir, live) = setup("profile");
+        let output = dir.join("accountant.db");
+        let rules = builtin_profiles().remove("accountant_no_contact").unwrap();
+
+        let report = scrubbed_copy(&live, &rules, &output).unwrap();
+        assert!(report.values_verified >= 6, "{:?}", report);
+        assert!(report.dropped_tables.contains(&"settings".to_string()));
+        assert!(report
+            .dropped_tables
+            .contains(&"clients_search".to_string()));
+        assert!(!dir.join("accountant.db.partial").exists());
+        assert!(!dir.join("accountant.db-wal").exists());
+        let rows = |column: &str| {
+            report
+                .rules
+                .iter()
+                .find(|rule| rule.column == column)
+                .unwrap()
+                .rows
+        };
+        assert_eq!(rows("clients.drivers_license"), 2);
+        assert_eq!(rows("deals.cobuyer_data.ssn"), 1);
+        assert_eq!(rows("deal_cobuyers.drivers_license"), 1);
+        assert_eq!(rows("clients.email"), 1);
+        assert_eq!(rows("clients.phone_e164"), 2);
+
+        let bytes = fs::read(&output).unwrap();
+        for secret in [
+            "D1234-5678-90",
+            "TX99887766",
+            "CB-4411-0090",
+            "123-45-6789",
+            "(555) 201-3344",
+            "5552013344",
+            "ana@example.com",
+            "555-010-2020",
+            "tok-secret",
+            "whsec-abc123",
+            // Encrypted columns go whatever the profile
+            "c3NuLWNpcGhlcnRleHQtY2Ix",
+        ] {
+            assert!(!contains(&bytes, secret), "{} left in the copy", secret);
+        }
+
+        let copy = Connection::open(&output).unwrap();
+        let (license, phone, first_name): (Option<String>, String, String) = copy
+            .query_row(
+                "SELECT drivers_license, phone, first_name FROM clients WHERE id = 'c1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!(license, None);
+        assert_eq!(phone.len(), HASH_LEN);
+        assert_eq!(first_name, "Ana");
+        let cobuyer: String = copy
+            .query_row(
+                "SELECT cobuyer_data FROM deals WHERE id = 'd1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let cobuyer: Value = serde_json::from_str(&cobuyer).unwrap();
+        assert_eq!(cobuyer["driversLicense"], Value::Null);
+        assert_eq!(cobuyer["firstName"], "Cara");
+        assert!(!table_exists(&copy, "settings").unwrap());
+        assert!(!table_exists(&copy, "webhooks").unwrap());
+        drop(copy);
+
+        // The live database keeps everything
+        let (license, phone): (String, String) = live
+            .query_row(
+                "SELECT drivers_license, phone FROM clients WHERE id = 'c1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(license, "D1234-5678-90");
+        assert_eq!(phone, "(555) 201-3344");
+        let cobuyer: String = live
+            .query_row(
+                "SELECT cobuyer_data FROM deals WHERE id = 'd1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(cobuyer.contains("123-45-6789"));
+        assert!(table_exists(&live, "settings").unwrap());
+        let searchable: i64 = live
+            .query_row(
+                "SELECT COUNT(*) FROM clients_search WHERE body LIKE '%201-3344%'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(searchable, 1);
+
+        drop(live);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_leftover_copies_fail_verification() {
+        let (dir, live) = setup("leak");
+        // The license also sits in a column the profile doesn't cover
+        live.execute(
+            "UPDATE clients SET address = 'License D1234-5678-90 on file' WHERE id = 'c1'",
+            [],
+        )
+        .unwrap();
+        let output = dir.join("accountant.db");
+        let rules = builtin_profiles().remove("accountant").unwrap();
+
+        let err = scrubbed_copy(&live, &rules, &output).unwrap_err();
+        assert!(err.contains("clients.drivers_license"), "{}", err);
+        assert!(!err.contains("D1234"), "{}", err);
+        assert!(!output.exists());
+        assert!(!dir.join("accountant.db.partial").exists());
+
+        // Rules must name real columns
+        let typo = vec![ScrubRule {
+            column: "clients.driver_license".to_string(),
+            action: ScrubAction::Null,
+        }];
+        let err = scrubbed_copy(&live, &typo, &output).unwrap_err();
+        assert!(err.contains("no such column"), "{}", err);
+        let not_null = vec![ScrubRule {
+            column: "clients.first_name".to_string(),
+            action: ScrubAction::Null,
+        }];
+        assert!(scrubbed_copy(&live, &not_null, &output).is_err());
+        assert!(split_column("clients").is_err());
+        assert!(split_column("deals..ssn").is_err());
+
+        drop(live);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,839 @@
+// src-tauri/src/secret_store.rs
+//
+// Typed access to the OS keyring
+// SECURITY: Only the keys listed in SecretKey can be read or written, so the
+// per-secret Tauri commands stay the only way JS can reach a secret.
+// Adding a secret = one SecretKey variant + its account name.
+//
+// When the keyring is unusable (e.g. Linux without a Secret Service daemon) secrets
+// fall back to an AES-256-GCM file in app data, keyed by the OS machine id. That only
+// stops casual copying of the file to another machine, so it's reported as reduced security.
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::{
+    aead::{Aead, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use keyring::Entry;
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::file_permissions::restrict_to_owner;
+use crate::storage::get_app_data_dir;
+
+const SERVICE_NAME: &str = "net.universalautobrokers.dealersoftware";
+
+/// Throwaway account written and deleted to find out whether the keyring works
+const PROBE_ACCOUNT: &str = "secure_storage_probe";
+
+const FALLBACK_FILE_NAME: &str = "secrets.enc";
+const FALLBACK_MAGIC: &[u8; 4] = b"DSSF";
+const FALLBACK_KEY_CONTEXT: &[u8] = b"dealer-software/secret-store/v1";
+const NONCE_SIZE: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecretKey {
+    SessionToken,
+    DealershipAuthToken,
+    DocumentsRootPath,
+    AwsAccessKeyId,
+    AwsSecretAccessKey,
+    AwsRegion,
+    AwsBucketName,
+    LicenseKey,
+    LicenseActivationToken,
+    TrialStartedAt,
+    MachineFingerprint,
+    LocalApiToken,
+    /// Key of the encrypted database columns (sensitive_fields.rs)
+    FieldEncryptionKey,
+    /// Hash of the PIN that unlocks the app after the idle timeout (auto_lock.rs)
+    AppLockPin,
+    /// Market-value lookups (valuation.rs)
+    ValuationApiUrl,
+    ValuationApiKey,
+}
+
+impl SecretKey {
+    pub const ALL: [SecretKey; 16] = [
+        SecretKey::SessionToken,
+        SecretKey::DealershipAuthToken,
+        SecretKey::DocumentsRootPath,
+        SecretKey::AwsAccessKeyId,
+        SecretKey::AwsSecretAccessKey,
+        SecretKey::AwsRegion,
+        SecretKey::AwsBucketName,
+        SecretKey::LicenseKey,
+        SecretKey::LicenseActivationToken,
+        SecretKey::TrialStartedAt,
+        SecretKey::MachineFingerprint,
+        SecretKey::LocalApiToken,
+        SecretKey::FieldEncryptionKey,
+        SecretKey::AppLockPin,
+        SecretKey::ValuationApiUrl,
+        SecretKey::ValuationApiKey,
+    ];
+
+    /// Keyring account name (must never change, or existing installs lose the secret)
+    pub fn account(self) -> &'static str {
+        match self {
+            SecretKey::SessionToken => "standalone_session_token",
+            SecretKey::DealershipAuthToken => "dealer_auth_token",
+            SecretKey::DocumentsRootPath => "documents_root_path",
+            SecretKey::AwsAccessKeyId => "aws_access_key_id",
+            SecretKey::AwsSecretAccessKey => "aws_secret_access_key",
+            SecretKey::AwsRegion => "aws_region",
+            SecretKey::AwsBucketName => "aws_bucket_name",
+            SecretKey::LicenseKey => "license_key",
+            SecretKey::LicenseActivationToken => "license_activation_token",
+            SecretKey::TrialStartedAt => "trial_started_at",
+            SecretKey::MachineFingerprint => "machine_fingerprint",
+            SecretKey::LocalApiToken => "local_api_token",
+            SecretKey::FieldEncryptionKey => "field_encryption_key",
+            SecretKey::AppLockPin => "app_lock_pin",
+            SecretKey::ValuationApiUrl => "valuation_api_url",
+            SecretKey::ValuationApiKey => "valuation_api_key",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// One slot per key: the lock serializes access to that secret only, and the
+/// cached Entry is reused instead of being rebuilt for every call
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SLOT: Mutex<Option<Entry>> = Mutex::new(None);
+static SLOTS: [Mutex<Option<Entry>>; SecretKey::ALL.len()] = [EMPTY_SLOT; SecretKey::ALL.len()];
+
+fn with_entry<T>(key: SecretKey, f: impl FnOnce(&Entry) -> Result<T, String>) -> Result<T, String> {
+    let mut slot = SLOTS[key.index()]
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    if slot.is_none() {
+        let entry = Entry::new(SERVICE_NAME, key.account())
+            .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
+        *slot = Some(entry);
+    }
+
+    f(slot.as_ref().expect("entry initialized above"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageBackend {
+    Keyring,
+    EncryptedFile,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SecureStorageStatus {
+    /// Where secrets are read from and written to right now
+    pub backend: StorageBackend,
+    pub keyring_available: bool,
+    pub reduced_security: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SecretMigrationReport {
+    pub migrated: Vec<SecretKey>,
+    pub failed: Vec<SecretKey>,
+    pub status: SecureStorageStatus,
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    Keyring,
+    File(FallbackFile),
+}
+
+impl Backend {
+    fn kind(&self) -> StorageBackend {
+        match self {
+            Backend::Keyring => StorageBackend::Keyring,
+            Backend::File(_) => StorageBackend::EncryptedFile,
+        }
+    }
+}
+
+/// Chosen on first use and kept for the process; only migration switches it back
+static BACKEND: Mutex<Option<Backend>> = Mutex::new(None);
+
+fn active_backend() -> Backend {
+    let mut backend = BACKEND
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    backend.get_or_insert_with(select_backend).clone()
+}
+
+fn set_backend(backend: Backend) {
+    *BACKEND
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(backend);
+}
+
+fn select_backend() -> Backend {
+    let error = match probe_keyring() {
+        Ok(()) => return Backend::Keyring,
+        Err(e) => e,
+    };
+
+    match FallbackFile::default_location() {
+        Ok(file) => {
+            warn!(
+                "⚠️  [SECRETS] OS keyring unavailable ({}); using encrypted file (reduced security)",
+                error
+            );
+            Backend::File(file)
+        }
+        Err(e) => {
+            // Nowhere better to go; keyring calls will report the real error
+            warn!("⚠️  [SECRETS] Encrypted file fallback unavailable: {}", e);
+            Backend::Keyring
+        }
+    }
+}
+
+/// Write, read back and delete a throwaway entry
+fn probe_keyring() -> Result<(), String> {
+    let entry = Entry::new(SERVICE_NAME, PROBE_ACCOUNT).map_err(|e| e.to_string())?;
+    let value = format!("probe-{}", std::process::id());
+
+    entry.set_password(&value).map_err(|e| e.to_string())?;
+    let read = entry.get_password().map_err(|e| e.to_string());
+    let _ = entry.delete_credential();
+
+    if read? != value {
+        return Err("Keyring returned a different value than was stored".to_string());
+    }
+    Ok(())
+}
+
+fn status(backend: StorageBackend, keyring: Result<(), String>) -> SecureStorageStatus {
+    let message = match (backend, &keyring) {
+        (StorageBackend::Keyring, Ok(())) => None,
+        (StorageBackend::Keyring, Err(e)) => Some(format!("OS keyring unavailable: {}", e)),
+        (StorageBackend::EncryptedFile, Ok(())) => Some(
+            "OS keyring is available again; migrate secrets to restore full security".to_string(),
+        ),
+        (StorageBackend::EncryptedFile, Err(_)) => Some(
+            "OS keyring unavailable; secrets are kept in an encrypted file in app data (reduced security)"
+                .to_string(),
+        ),
+    };
+
+    SecureStorageStatus {
+        backend,
+        keyring_available: keyring.is_ok(),
+        reduced_security: backend == StorageBackend::EncryptedFile,
+        message,
+    }
+}
+
+/// Report which backend holds secrets and whether the OS keyring currently works
+#[tauri::command]
+pub fn check_secure_storage_available() -> SecureStorageStatus {
+    let backend = active_backend().kind();
+    status(backend, probe_keyring())
+}
+
+/// Move secrets from the encrypted fallback file into the OS keyring
+/// The file is only deleted once every secret made it across
+#[tauri::command]
+pub fn migrate_secrets_to_keyring() -> Result<SecretMigrationReport, String> {
+    probe_keyring().map_err(|e| format!("OS keyring is still unavailable: {}", e))?;
+
+    let file = match active_backend() {
+        Backend::File(file) => file,
+        Backend::Keyring => FallbackFile::default_location()?,
+    };
+
+    info!("🔁 [SECRETS] Migrating secrets from encrypted file to OS keyring");
+    let mut migrated = Vec::new();
+    let mut failed = Vec::new();
+
+    for (key, value) in file.entries()? {
+        match keyring_store(key, &value).and_then(|_| file.remove(key)) {
+            Ok(_) => migrated.push(key),
+            Err(e) => {
+                warn!("⚠️  [SECRETS] Failed to migrate {:?}: {}", key, e);
+                failed.push(key);
+            }
+        }
+    }
+
+    if failed.is_empty() {
+        set_backend(Backend::Keyring);
+        info!(
+            "✅ [SECRETS] Migrated {} secret(s) to OS keyring",
+            migrated.len()
+        );
+    }
+
+    Ok(SecretMigrationReport {
+        migrated,
+        failed,
+        status: check_secure_storage_available(),
+    })
+}
+
+/// Outcome of removing one secret during logout
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum SecretRemoval {
+    Removed,
+    NotPresent,
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SecretRemovalResult {
+    pub key: SecretKey,
+    #[serde(flatten)]
+    pub result: SecretRemoval,
+}
+
+/// Always cleared on logout
+const USER_SECRETS: &[SecretKey] = &[
+    SecretKey::SessionToken,
+    SecretKey::DealershipAuthToken,
+    SecretKey::DocumentsRootPath,
+];
+
+const AWS_SECRETS: &[SecretKey] = &[
+    SecretKey::AwsAccessKeyId,
+    SecretKey::AwsSecretAccessKey,
+    SecretKey::AwsRegion,
+    SecretKey::AwsBucketName,
+];
+
+const LICENSE_SECRETS: &[SecretKey] = &[SecretKey::LicenseKey, SecretKey::LicenseActivationToken];
+
+/// Remove everything a user left behind, in one call (logout on a shared computer)
+/// Trial, machine fingerprint, local API token, the field encryption key and the app lock
+/// PIN belong to the machine and are never cleared (without the key the encrypted columns
+/// are unreadable)
+#[tauri::command]
+pub fn clear_all_user_secrets(
+    include_aws: bool,
+    include_license: bool,
+) -> Vec<SecretRemovalResult> {
+    info!(
+        "🧹 [SECRETS] Clearing user secrets (aws: {}, license: {})",
+        include_aws, include_license
+    );
+
+    let mut keys = USER_SECRETS.to_vec();
+    if include_aws {
+        keys.extend_from_slice(AWS_SECRETS);
+    }
+    if include_license {
+        keys.extend_from_slice(LICENSE_SECRETS);
+    }
+
+    keys.into_iter()
+        .map(|key| SecretRemovalResult {
+            key,
+            result: match remove(key) {
+                Ok(true) => SecretRemoval::Removed,
+                Ok(false) => SecretRemoval::NotPresent,
+                Err(e) => {
+                    error!("❌ [SECRETS] Failed to remove {:?}: {}", key, e);
+                    SecretRemoval::Error { message: e }
+                }
+            },
+        })
+        .collect()
+}
+
+/// Which known secrets are currently stored. Never returns values
+#[tauri::command]
+pub fn list_stored_secret_keys() -> Result<Vec<SecretKey>, String> {
+    let mut present = Vec::new();
+    for key in SecretKey::ALL {
+        if get(key)
+            .map_err(|e| format!("Failed to read {:?}: {}", key, e))?
+            .is_some()
+        {
+            present.push(key);
+        }
+    }
+    Ok(present)
+}
+
+/// Store a secret, replacing any existing value
+pub fn store(key: SecretKey, value: &str) -> Result<(), String> {
+    match active_backend() {
+        Backend::Keyring => keyring_store(key, value),
+        Backend::File(file) => file.store(key, value),
+    }
+}
+
+/// Read a secret; Ok(None) when it was never stored
+pub fn get(key: SecretKey) -> Result<Option<String>, String> {
+    match active_backend() {
+        Backend::Keyring => keyring_get(key),
+        Backend::File(file) => file.get(key),
+    }
+}
+
+/// Remove a secret; Ok(false) when there was nothing to remove
+pub fn remove(key: SecretKey) -> Result<bool, String> {
+    match active_backend() {
+        Backend::Keyring => keyring_remove(key),
+        Backend::File(file) => file.remove(key),
+    }
+}
+
+/// set_password overwrites in place on every backend we ship (Keychain, Credential
+/// Manager, Secret Service), so there's no delete-then-wait dance blocking the caller
+fn keyring_store(key: SecretKey, value: &str) -> Result<(), String> {
+    with_entry(key, |entry| {
+        entry.set_password(value).map_err(|e| e.to_string())
+    })
+}
+
+fn keyring_get(key: SecretKey) -> Result<Option<String>, String> {
+    with_entry(key, |entry| match entry.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    })
+}
+
+fn keyring_remove(key: SecretKey) -> Result<bool, String> {
+    with_entry(key, |entry| match entry.delete_credential() {
+        Ok(_) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(e.to_string()),
+    })
+}
+
+/// Fallback file: magic | nonce | AES-256-GCM(JSON map of account -> value)
+/// The whole map is rewritten on every change; there are only a handful of secrets
+#[derive(Debug, Clone)]
+struct FallbackFile {
+    path: PathBuf,
+    key: [u8; 32],
+}
+
+/// Read-modify-write of the fallback file must not interleave
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
+impl FallbackFile {
+    fn default_location() -> Result<Self, String> {
+        let machine_id = crate::license::os_machine_id();
+        Ok(Self::new(
+            get_app_data_dir()?.join(FALLBACK_FILE_NAME),
+            machine_id.as_bytes(),
+        ))
+    }
+
+    fn new(path: PathBuf, key_material: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(FALLBACK_KEY_CONTEXT);
+        hasher.update(key_material);
+
+        Self {
+            path,
+            key: hasher.finalize().into(),
+        }
+    }
+
+    fn store(&self, key: SecretKey, value: &str) -> Result<(), String> {
+        let _lock = FILE_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+        let mut secrets = self.load()?;
+        secrets.insert(key.account().to_string(), value.to_string());
+        self.save(&secrets)
+    }
+
+    fn get(&self, key: SecretKey) -> Result<Option<String>, String> {
+        let _lock = FILE_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+        Ok(self.load()?.remove(key.account()))
+    }
+
+    fn remove(&self, key: SecretKey) -> Result<bool, String> {
+        let _lock = FILE_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+        let mut secrets = self.load()?;
+        if secrets.remove(key.account()).is_none() {
+            return Ok(false);
+        }
+        self.save(&secrets)?;
+        Ok(true)
+    }
+
+    fn entries(&self) -> Result<Vec<(SecretKey, String)>, String> {
+        let _lock = FILE_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+        let mut secrets = self.load()?;
+        Ok(SecretKey::ALL
+            .iter()
+            .filter_map(|key| secrets.remove(key.account()).map(|value| (*key, value)))
+            .collect())
+    }
+
+    fn load(&self) -> Result<BTreeMap<String, String>, String> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(e) => return Err(format!("Failed to read secrets file: {}", e)),
+        };
+
+        let body = data
+            .strip_prefix(FALLBACK_MAGIC.as_slice())
+            .filter(|body| body.len() >= NONCE_SIZE)
+            .ok_or("Secrets file is corrupted")?;
+        let (nonce, ciphertext) = body.split_at(NONCE_SIZE);
+
+        let plaintext = self
+            .cipher()
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: FALLBACK_MAGIC,
+                },
+            )
+            .map_err(|_| "Secrets file could not be decrypted on this machine".to_string())?;
+
+        serde_json::from_slice(&plaintext).map_err(|e| format!("Secrets file is corrupted: {}", e))
+    }
+
+    fn save(&self, secrets: &BTreeMap<String, String>) -> Result<(), String> {
+        if secrets.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(format!("Failed to remove secrets file: {}", e))
+                }
+                _ => Ok(()),
+            };
+        }
+
+        let plaintext = serde_json::to_vec(secrets)
+            .map_err(|e| format!("Failed to serialize secrets: {}", e))?;
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+
+        let ciphertext = self
+            .cipher()
+            .encrypt(
+                &Nonce::from(nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: FALLBACK_MAGIC,
+                },
+            )
+            .map_err(|e| format!("Failed to encrypt secrets: {}", e))?;
+
+        let mut data = FALLBACK_MAGIC.to_vec();
+        data.extend_from_slice(&nonce);
+        data.extend_from_slice(&ciphertext);
+
+        write_private(&self.path, &data)
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new(&self.key.into())
+    }
+}
+
+/// Write via a 600 temp file and rename, so the secrets never sit in a readable
+/// or half-written file
+fn write_private(path: &Path, data: &[u8]) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    }
+
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, []).map_err(|e| format!("Failed to write secrets file: {}", e))?;
+    restrict_to_owner(&tmp_path)?;
+    fs::write(&tmp_path, data).map_err(|e| format!("Failed to write secrets file: {}", e))?;
+    fs::rename(&tmp_path, path).map_err(|e| format!("Failed to write secrets file: {}", e))
+}
+
+/// Tests touching the keyring share the process-wide mock store; hold this to avoid
+/// one test's secret showing up in another's assertions
+#[cfg(test)]
+pub(crate) fn test_guard() -> std::sync::MutexGuard<'static, ()> {
+    static GUARD: Mutex<()> = Mutex::new(());
+    static MOCK: std::sync::Once = std::sync::Once::new();
+
+    MOCK.call_once(|| {
+        keyring::set_default_credential_builder(keyring::mock::default_credential_builder())
+    });
+    GUARD
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_round_trip_every_key() {
+        let _guard = test_guard();
+
+        for key in SecretKey::ALL {
+            let value = format!("value-for-{:?}", key);
+            let _ = remove(key);
+
+            assert_eq!(get(key), Ok(None), "{:?}", key);
+            store(key, &value).unwrap();
+            assert_eq!(get(key), Ok(Some(value.clone())), "{:?}", key);
+
+            // Overwrite replaces the old value
+            store(key, "replaced").unwrap();
+            assert_eq!(get(key), Ok(Some("replaced".to_string())), "{:?}", key);
+
+            assert_eq!(remove(key), Ok(true), "{:?}", key);
+            assert_eq!(remove(key), Ok(false), "{:?}", key);
+            assert_eq!(get(key), Ok(None), "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn test_accounts_unique_and_indexes_match() {
+        let accounts: HashSet<_> = SecretKey::ALL.iter().map(|key| key.account()).collect();
+        assert_eq!(accounts.len(), SecretKey::ALL.len());
+
+        for (index, key) in SecretKey::ALL.iter().enumerate() {
+            assert_eq!(key.index(), index);
+        }
+    }
+
+    #[test]
+    fn test_stores_dont_sleep_or_share_one_lock() {
+        use std::sync::mpsc;
+        use std::time::{Duration, Instant};
+
+        let _guard = test_guard();
+
+        // Hold the session token's slot for the whole test, as a slow keyring call would
+        let (held_tx, held_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let holder = std::thread::spawn(move || {
+            with_entry(SecretKey::SessionToken, |_| {
+                held_tx.send(()).unwrap();
+                let _ = release_rx.recv();
+                Ok(())
+            })
+        });
+        held_rx.recv().unwrap();
+
+        // Saving the AWS settings screen: four stores, none of them waiting on the session slot
+        let (done_tx, done_rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let started = Instant::now();
+            let result = tauri::async_runtime::block_on(async {
+                crate::aws_config::store_aws_access_key_id("AKIA-TEST".into()).await?;
+                crate::aws_config::store_aws_secret_access_key("secret".into()).await?;
+                crate::aws_config::store_aws_region("us-east-1".into()).await?;
+                crate::aws_config::store_aws_bucket_name("dealer-docs".into()).await
+            });
+            let _ = done_tx.send((result, started.elapsed()));
+        });
+
+        let (result, elapsed) = done_rx
+            .recv_timeout(Duration::from_secs(5))
+            .expect("stores of other secrets blocked behind the session token");
+        assert_eq!(result, Ok(()));
+        // The old delete/sleep/set dance cost at least 50ms per secret
+        assert!(elapsed < Duration::from_millis(150), "took {:?}", elapsed);
+
+        release_tx.send(()).unwrap();
+        holder.join().unwrap().unwrap();
+
+        for key in [
+            SecretKey::AwsAccessKeyId,
+            SecretKey::AwsSecretAccessKey,
+            SecretKey::AwsRegion,
+            SecretKey::AwsBucketName,
+        ] {
+            let _ = remove(key);
+        }
+    }
+
+    fn temp_fallback(name: &str) -> FallbackFile {
+        let dir = std::env::temp_dir().join(format!(
+            "dealer-secrets-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        FallbackFile::new(dir.join(FALLBACK_FILE_NAME), b"machine-abc")
+    }
+
+    #[test]
+    fn test_fallback_file_round_trip() {
+        let file = temp_fallback("round-trip");
+        assert_eq!(file.get(SecretKey::SessionToken), Ok(None));
+
+        file.store(SecretKey::SessionToken, "sess_secret_value")
+            .unwrap();
+        file.store(SecretKey::AwsRegion, "us-east-1").unwrap();
+        assert_eq!(
+            file.get(SecretKey::SessionToken),
+            Ok(Some("sess_secret_value".to_string()))
+        );
+        assert_eq!(
+            file.get(SecretKey::AwsRegion),
+            Ok(Some("us-east-1".to_string()))
+        );
+
+        // Encrypted at rest and owner-only
+        let raw = fs::read(&file.path).unwrap();
+        assert!(!String::from_utf8_lossy(&raw).contains("sess_secret_value"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&file.path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        // A copy of the file is useless on another machine
+        let other_machine = FallbackFile::new(file.path.clone(), b"machine-xyz");
+        assert!(other_machine.get(SecretKey::SessionToken).is_err());
+
+        assert_eq!(file.remove(SecretKey::SessionToken), Ok(true));
+        assert_eq!(file.remove(SecretKey::SessionToken), Ok(false));
+
+        // Removing the last secret removes the file
+        assert_eq!(file.remove(SecretKey::AwsRegion), Ok(true));
+        assert!(!file.path.exists());
+
+        let _ = fs::remove_dir_all(file.path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_commands_use_fallback_then_migrate_to_keyring() {
+        let _guard = test_guard();
+        let file = temp_fallback("migrate");
+        let _ = keyring_remove(SecretKey::SessionToken);
+        let _ = keyring_remove(SecretKey::AwsRegion);
+
+        set_backend(Backend::File(file.clone()));
+        let status = check_secure_storage_available();
+        assert_eq!(status.backend, StorageBackend::EncryptedFile);
+        assert!(status.reduced_security);
+        assert!(status.keyring_available);
+
+        // Existing commands go to the file without knowing it
+        crate::session::store_user_session("alice", "sess_from_file").unwrap();
+        tauri::async_runtime::block_on(crate::aws_config::store_aws_region("us-west-2".into()))
+            .unwrap();
+        assert_eq!(
+            tauri::async_runtime::block_on(crate::session::get_session_token()),
+            Ok(Some("sess_from_file".to_string()))
+        );
+        assert_eq!(keyring_get(SecretKey::SessionToken), Ok(None));
+        assert!(file.path.exists());
+
+        let report = migrate_secrets_to_keyring().unwrap();
+        assert_eq!(
+            report.migrated,
+            vec![SecretKey::SessionToken, SecretKey::AwsRegion]
+        );
+        assert!(report.failed.is_empty());
+        assert_eq!(report.status.backend, StorageBackend::Keyring);
+        assert!(!report.status.reduced_security);
+        assert!(!file.path.exists());
+
+        // Same commands, now served from the keyring
+        assert!(keyring_get(SecretKey::SessionToken).unwrap().is_some());
+        assert_eq!(
+            tauri::async_runtime::block_on(crate::session::get_session_token()),
+            Ok(Some("sess_from_file".to_string()))
+        );
+        assert_eq!(
+            tauri::async_runtime::block_on(crate::aws_config::get_aws_region()),
+            Ok(Some("us-west-2".to_string()))
+        );
+
+        let _ = remove(SecretKey::SessionToken);
+        let _ = remove(SecretKey::AwsRegion);
+        let _ = fs::remove_dir_all(file.path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_clear_all_user_secrets_flags() {
+        let _guard = test_guard();
+        let fill = || {
+            for key in SecretKey::ALL {
+                store(key, &format!("secret-{}", key.account())).unwrap();
+            }
+        };
+        let removed = |report: &[SecretRemovalResult]| -> Vec<SecretKey> {
+            report
+                .iter()
+                .filter(|entry| entry.result == SecretRemoval::Removed)
+                .map(|entry| entry.key)
+                .collect()
+        };
+
+        let cases = [
+            (false, false, USER_SECRETS.to_vec()),
+            (true, false, [USER_SECRETS, AWS_SECRETS].concat()),
+            (false, true, [USER_SECRETS, LICENSE_SECRETS].concat()),
+            (
+                true,
+                true,
+                [USER_SECRETS, AWS_SECRETS, LICENSE_SECRETS].concat(),
+            ),
+        ];
+        for (include_aws, include_license, expected) in cases {
+            fill();
+            let report = clear_all_user_secrets(include_aws, include_license);
+            assert_eq!(removed(&report), expected);
+
+            let remaining = list_stored_secret_keys().unwrap();
+            for key in SecretKey::ALL {
+                assert_eq!(
+                    remaining.contains(&key),
+                    !expected.contains(&key),
+                    "{:?}",
+                    key
+                );
+            }
+            // Machine-bound records survive every logout
+            assert!(remaining.contains(&SecretKey::TrialStartedAt));
+            assert!(remaining.contains(&SecretKey::MachineFingerprint));
+            assert!(remaining.contains(&SecretKey::FieldEncryptionKey));
+        }
+
+        // Second logout: nothing left to remove
+        let report = clear_all_user_secrets(true, true);
+        assert!(report
+            .iter()
+            .all(|entry| entry.result == SecretRemoval::NotPresent));
+
+        for key in SecretKey::ALL {
+            let _ = remove(key);
+        }
+        assert_eq!(list_stored_secret_keys(), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_inventory_and_report_never_contain_values() {
+        let _guard = test_guard();
+        store(SecretKey::SessionToken, "sess_do_not_leak_me").unwrap();
+        store(SecretKey::AwsSecretAccessKey, "aws_do_not_leak_me").unwrap();
+
+        let inventory = serde_json::to_string(&list_stored_secret_keys().unwrap()).unwrap();
+        assert!(inventory.contains("session_token"));
+        assert!(!inventory.contains("leak_me"));
+
+        let report = serde_json::to_string(&clear_all_user_secrets(true, false)).unwrap();
+        assert!(report.contains(r#"{"key":"session_token","status":"removed"}"#));
+        assert!(!report.contains("leak_me"));
+    }
+}
@@ -0,0 +1,483 @@
+// src-tauri/src/sensitive_fields.rs
+//
+// Values we won't keep in plaintext, such as a co-buyer's SSN for lender submission. They're
+// encrypted (AES-256-GCM, encryption.rs) before they reach SQLite and stored in a dedicated
+// column (deal_cobuyers.ssn_encrypted, migration 29); the key is generated on first use and
+// kept in the OS keyring. The ciphertext is bound to its table, column and row, so it can't
+// be copied onto another record. Nothing else reads these columns: the search index,
+// exports and the co-buyer commands leave them out, and scrubbed copies clear them.
+//
+// reveal_sensitive_field needs a re-authentication within REAUTH_WINDOW
+// (auth_reauthenticate) and is audited whether or not it's allowed. Values and ciphertext
+// are never logged, audited or echoed back in an error.
+
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::time::Duration;
+use tauri::State;
+
+use crate::app_state::{AppState, AuthError};
+use crate::audit_log::record_audit;
+use crate::database::{begin_write, DbState};
+use crate::encryption::{decrypt_bound, encrypt_bound, generate_encryption_key};
+use crate::secret_store::{self, SecretKey};
+use crate::telemetry::track;
+
+pub const SENSITIVE_FIELD_STORED_ACTION: &str = "sensitive_field_stored";
+pub const SENSITIVE_FIELD_REVEALED_ACTION: &str = "sensitive_field_revealed";
+/// How long a re-authentication unlocks reveal_sensitive_field
+pub(crate) const REAUTH_WINDOW: Duration = Duration::from_secs(5 * 60);
+/// Every encrypted column, as (table, column)
+pub(crate) const ENCRYPTED_COLUMNS: &[(&str, &str)] = &[("deal_cobuyers", "ssn_encrypted")];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitiveEntity {
+    DealCobuyer,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SensitiveField {
+    Ssn,
+}
+
+/// Whether the field holds a value; never the value itself
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SensitiveFieldStatus {
+    pub entity: SensitiveEntity,
+    pub id: String,
+    pub field: SensitiveField,
+    pub is_set: bool,
+}
+
+/// (table, encrypted column) holding the entity's field
+fn field_column(entity: SensitiveEntity, field: SensitiveField) -> (&'static str, &'static str) {
+    match (entity, field) {
+        (SensitiveEntity::DealCobuyer, SensitiveField::Ssn) => ENCRYPTED_COLUMNS[0],
+    }
+}
+
+/// Ties a ciphertext to the one cell it was written to
+fn associated_data(table: &str, column: &str, id: &str) -> Vec<u8> {
+    format!("{}.{}:{}", table, column, id).into_bytes()
+}
+
+/// The field key, created on first use
+fn field_key() -> Result<String, String> {
+    if let Some(key) = secret_store::get(SecretKey::FieldEncryptionKey)? {
+        return Ok(key);
+    }
+    let key = generate_encryption_key()?;
+    secret_store::store(SecretKey::FieldEncryptionKey, &key)?;
+    info!("🔑 Created the field encryption key");
+    Ok(key)
+}
+
+/// The value as stored (before encryption). Errors never repeat the value
+fn normalize(field: SensitiveField, value: &str) -> Result<String, String> {
+    match field {
+        SensitiveField::Ssn => {
+            let digits: String = value.chars().filter(|c| !matches!(c, '-' | ' ')).collect();
+            if digits.len() != 9 || !digits.chars().all(|c| c.is_ascii_digit()) {
+                return Err("An SSN has 9 digits".to_string());
+            }
+            Ok(format!(
+                "{}-{}-{}",
+                &digits[..3],
+                &digits[3..5],
+                &digits[5..]
+            ))
+        }
+    }
+}
+
+/// The encrypted cell of a record the user owns; Ok(None) when it's empty
+fn stored_ciphertext(
+    conn: &Connection,
+    user_id: &str,
+    table: &str,
+    column: &str,
+    id: &str,
+) -> Result<Option<String>, String> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM {} WHERE id = ?1 AND user_id = ?2",
+            column, table
+        ),
+        params![id, user_id],
+        |row| row.get::<_, Option<String>>(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Record not found or access denied".to_string())
+}
+
+/// Encrypt and store value (None or blank clears the field)
+pub(crate) fn store_field(
+    conn: &Connection,
+    user_id: &str,
+    entity: SensitiveEntity,
+    id: &str,
+    field: SensitiveField,
+    value: Option<&str>,
+    now: i64,
+) -> Result<SensitiveFieldStatus, String> {
+    let (table, column) = field_column(entity, field);
+    let value = value.map(str::trim).filter(|value| !value.is_empty());
+    let ciphertext = match value {
+        Some(value) => Some(encrypt_bound(
+            &normalize(field, value)?,
+            &field_key()?,
+            &associated_data(table, column, id),
+        )?),
+        None => None,
+    };
+
+    let tx = begin_write(conn, "sensitive_fields").map_err(|e| e.to_string())?;
+    stored_ciphertext(&tx, user_id, table, column, id)?;
+    tx.execute(
+        &format!(
+            "UPDATE {} SET {} = ?1, updated_at = ?2 WHERE id = ?3 AND user_id = ?4",
+            table, column
+        ),
+        params![ciphertext, now, id, user_id],
+    )
+    .map_err(|e| e.to_string())?;
+    record_audit(
+        &tx,
+        user_id,
+        SENSITIVE_FIELD_STORED_ACTION,
+        &json!({
+            "entity": entity,
+            "id": id,
+            "field": field,
+            "cleared": ciphertext.is_none(),
+        }),
+        now,
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(SensitiveFieldStatus {
+        entity,
+        id: id.to_string(),
+        field,
+        is_set: ciphertext.is_some(),
+    })
+}
+
+/// Decrypt the field for a user who re-authenticated within REAUTH_WINDOW. Every attempt
+/// on a record the user owns is audited, refused ones included
+pub(crate) fn reveal_field(
+    conn: &Connection,
+    state: &AppState,
+    entity: SensitiveEntity,
+    id: &str,
+    field: SensitiveField,
+    now: i64,
+) -> Result<Option<String>, AuthError> {
+    let user_id = state.require_user(None)?;
+    let (table, column) = field_column(entity, field);
+    let ciphertext = stored_ciphertext(conn, &user_id, table, column, id)?;
+    let allowed = state.require_recent_reauth(REAUTH_WINDOW);
+    record_audit(
+        conn,
+        &user_id,
+        SENSITIVE_FIELD_REVEALED_ACTION,
+        &json!({
+            "entity": entity,
+            "id": id,
+            "field": field,
+            "allowed": allowed.is_ok(),
+        }),
+        now,
+    )
+    .map_err(|e| e.to_string())?;
+    if let Err(e) = allowed {
+        warn!(
+            "⚠️  Refused to reveal a {:?} without re-authentication",
+            field
+        );
+        return Err(e);
+    }
+
+    let Some(ciphertext) = ciphertext else {
+        return Ok(None);
+    };
+    let value = decrypt_bound(
+        &ciphertext,
+        &field_key()?,
+        &associated_data(table, column, id),
+    )
+    .map_err(|_| "The stored value can't be decrypted on this computer".to_string())?;
+    Ok(Some(value))
+}
+
+/// Encrypt and save a sensitive value (e.g. a co-buyer's SSN); null or "" clears it
+#[tauri::command]
+pub fn store_sensitive_field(
+    entity: SensitiveEntity,
+    id: String,
+    field: SensitiveField,
+    value: Option<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<SensitiveFieldStatus, String> {
+    track("store_sensitive_field", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let status = store_field(
+            &conn,
+            &user_id_value,
+            entity,
+            &id,
+            field,
+            value.as_deref(),
+            Utc::now().timestamp_millis(),
+        )?;
+
+        info!("🔐 Stored {:?} of {:?} {}", field, entity, id);
+        Ok(status)
+    })
+}
+
+/// The decrypted value. Needs auth_reauthenticate within the last few minutes
+/// (ReauthRequired otherwise) and is always audited
+#[tauri::command]
+pub fn reveal_sensitive_field(
+    entity: SensitiveEntity,
+    id: String,
+    field: SensitiveField,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<Option<String>, AuthError> {
+    track("reveal_sensitive_field", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        reveal_field(
+            &conn,
+            &state,
+            entity,
+            &id,
+            field,
+            Utc::now().timestamp_millis(),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit_log::audit_entries;
+    use crate::database::{db_create_client, db_create_deal, db_create_vehicle, Database};
+    use crate::deal_cobuyers::{db_add_deal_cobuyer, db_get_deal_cobuyers, NewDealCobuyer};
+    use crate::session::save_user_pin;
+    use crate::test_support::{
+        make_client, make_deal, make_vehicle, pin_hash, temp_dir, TestApp, TEST_USER,
+    };
+    use std::fs;
+    use std::path::Path;
+    use std::time::Instant;
+
+    const NOW: i64 = 1_760_000_000_000;
+    const SSN: &str = "123-45-6789";
+
+    /// Deal d1 with co-buyer id returned
+    fn cobuyer(app: &TestApp) -> String {
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        db_create_deal(
+            make_deal("d1", "c1", "v1"),
+            None,
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        let cobuyer = NewDealCobuyer {
+            first_name: "Sam".to_string(),
+            last_name: "Rivera".to_string(),
+            ..NewDealCobuyer::default()
+        };
+        db_add_deal_cobuyer("d1".to_string(), cobuyer, None, app.state(), app.db())
+            .unwrap()
+            .id
+    }
+
+    fn store(app: &TestApp, id: &str, value: Option<&str>) -> Result<SensitiveFieldStatus, String> {
+        store_field(
+            &app.conn(),
+            TEST_USER,
+            SensitiveEntity::DealCobuyer,
+            id,
+            SensitiveField::Ssn,
+            value,
+            NOW,
+        )
+    }
+
+    fn reveal(app: &TestApp, id: &str) -> Result<Option<String>, AuthError> {
+        reveal_field(
+            &app.conn(),
+            &app.state(),
+            SensitiveEntity::DealCobuyer,
+            id,
+            SensitiveField::Ssn,
+            NOW,
+        )
+    }
+
+    fn contains(path: &Path, needle: &str) -> bool {
+        fs::read(path).is_ok_and(|bytes| {
+            bytes
+                .windows(needle.len())
+                .any(|window| window == needle.as_bytes())
+        })
+    }
+
+    #[test]
+    fn test_only_ciphertext_reaches_the_database_file() {
+        let _guard = secret_store::test_guard();
+        let dir = temp_dir("sensitive", "ciphertext-only");
+        let path = dir.join("dealer.db");
+        let app = TestApp::with_db(Database::init_with_path(&path).unwrap());
+        let id = cobuyer(&app);
+
+        let status = store(&app, &id, Some("123 45 6789")).unwrap();
+        assert!(status.is_set);
+        let stored: String = app
+            .conn()
+            .query_row(
+                "SELECT ssn_encrypted FROM deal_cobuyers WHERE id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(!stored.contains("6789"));
+        let key = secret_store::get(SecretKey::FieldEncryptionKey)
+            .unwrap()
+            .unwrap();
+        let aad = associated_data("deal_cobuyers", "ssn_encrypted", &id);
+        assert_eq!(decrypt_bound(&stored, &key, &aad).unwrap(), SSN);
+        // Bound to its row
+        assert!(decrypt_bound(
+            &stored,
+            &key,
+            &associated_data("deal_cobuyers", "ssn_encrypted", "x")
+        )
+        .is_err());
+
+        app.conn()
+            .execute_batch("PRAGMA wal_checkpoint(TRUNCATE)")
+            .unwrap();
+        for file in ["dealer.db", "dealer.db-wal"] {
+            assert!(!contains(&dir.join(file), SSN), "{}", file);
+            assert!(!contains(&dir.join(file), "123456789"), "{}", file);
+        }
+
+        // Neither the co-buyer commands nor the audit log carry it
+        let listed = db_get_deal_cobuyers("d1".to_string(), None, app.state(), app.db()).unwrap();
+        let listed = serde_json::to_string(&listed).unwrap();
+        assert!(!listed.contains("6789") && !listed.contains(&stored));
+        let audit =
+            serde_json::to_string(&audit_entries(&app.conn(), TEST_USER, 10).unwrap()).unwrap();
+        assert!(!audit.contains("6789") && !audit.contains(&stored));
+
+        drop(app);
+        let _ = secret_store::remove(SecretKey::FieldEncryptionKey);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_reveal_needs_a_recent_reauth_and_is_always_audited() {
+        let _guard = secret_store::test_guard();
+        let app = TestApp::new();
+        let id = cobuyer(&app);
+        store(&app, &id, Some(SSN)).unwrap();
+
+        assert_eq!(reveal(&app, &id), Err(AuthError::ReauthRequired));
+        let mut expected = vec![false];
+        if let Some(long_ago) = Instant::now().checked_sub(REAUTH_WINDOW + Duration::from_secs(1)) {
+            app.state().set_reauthenticated_at(Some(long_ago));
+            assert_eq!(reveal(&app, &id), Err(AuthError::ReauthRequired));
+            expected.push(false);
+        }
+
+        app.state().set_reauthenticated_at(Some(Instant::now()));
+        assert_eq!(reveal(&app, &id), Ok(Some(SSN.to_string())));
+        expected.push(true);
+
+        // Another user's re-authentication doesn't carry over, nor do they see the record
+        app.sign_in("other-user");
+        app.state().set_reauthenticated_at(Some(Instant::now()));
+        assert!(matches!(reveal(&app, &id), Err(AuthError::Other { .. })));
+        app.sign_in(TEST_USER);
+        assert_eq!(reveal(&app, &id), Err(AuthError::ReauthRequired));
+        expected.push(false);
+
+        let mut allowed: Vec<bool> = audit_entries(&app.conn(), TEST_USER, 20)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.action == SENSITIVE_FIELD_REVEALED_ACTION)
+            .map(|entry| entry.details["allowed"].as_bool().unwrap())
+            .collect();
+        // Newest first
+        allowed.reverse();
+        assert_eq!(allowed, expected);
+
+        let _ = secret_store::remove(SecretKey::FieldEncryptionKey);
+    }
+
+    #[test]
+    fn test_wrong_pin_does_not_unlock_the_reveal() {
+        let _guard = secret_store::test_guard();
+        let _ = secret_store::remove(SecretKey::SessionToken);
+        let app = TestApp::new();
+        let id = cobuyer(&app);
+        store(&app, &id, Some(SSN)).unwrap();
+
+        // No PIN set: nothing to re-authenticate with
+        assert_eq!(
+            app.state().reauthenticate("2468", Instant::now()),
+            Err(AuthError::PinNotSet)
+        );
+        save_user_pin(TEST_USER, pin_hash("2468"), None, Instant::now()).unwrap();
+
+        assert!(matches!(
+            app.state().reauthenticate("1357", Instant::now()),
+            Err(AuthError::InvalidPin { .. })
+        ));
+        assert_eq!(reveal(&app, &id), Err(AuthError::ReauthRequired));
+
+        app.state().reauthenticate("2468", Instant::now()).unwrap();
+        assert_eq!(reveal(&app, &id), Ok(Some(SSN.to_string())));
+
+        let _ = secret_store::remove(SecretKey::FieldEncryptionKey);
+        let _ = secret_store::remove(SecretKey::SessionToken);
+    }
+
+    #[test]
+    fn test_bad_values_are_rejected_without_being_echoed_and_blank_clears() {
+        let _guard = secret_store::test_guard();
+        let app = TestApp::new();
+        let id = cobuyer(&app);
+
+        let err = store(&app, &id, Some("123-45-678X")).unwrap_err();
+        assert!(!err.contains("678"));
+        assert!(store(&app, "missing", Some(SSN)).is_err());
+
+        store(&app, &id, Some(SSN)).unwrap();
+        let status = store(&app, &id, Some("  ")).unwrap();
+        assert!(!status.is_set);
+        app.state().set_reauthenticated_at(Some(Instant::now()));
+        assert_eq!(reveal(&app, &id), Ok(None));
+
+        let _ = secret_store::remove(SecretKey::FieldEncryptionKey);
+    }
+}
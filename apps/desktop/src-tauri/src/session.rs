@@ -1,69 +1,222 @@
 // src-tauri/src/session.rs
 // SECURITY: Specific commands for session token storage only
 // Prevents JS from accessing arbitrary secrets via generic commands
+//
+// The keyring holds one encrypted entry with a session envelope
+// {token, expires_at, refresh_token} per user, plus which user is active, so several
+// salespeople can share the front-desk PC without logging each other out.
+// Older single-session entries (bare token or one envelope) are still read, as the
+// session of DEFAULT_USER_ID.
+// A token is only stored once the server that issued it (validateStandaloneSession) says
+// whose it is, so a script can't file a made-up token under someone else's user id and
+// then authenticate as them with it.
+// Each user can also keep a PIN here (set_user_pin). Switching to another stored session
+// takes that user's PIN, so whoever is at the desk can't step into a manager's session.
 
-use keyring::Entry;
-use log::{error, info};
+use base64::{engine::general_purpose, Engine as _};
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use tauri::{AppHandle, Emitter, State};
 
-use std::sync::Mutex;
+use crate::app_state::{AppState, AuthError};
+use crate::database::db_get_setting;
+use crate::encryption::{decrypt_data, encrypt_data, verify_password};
+use crate::kiosk::{lockout_after, PinHash, MAX_PIN_ATTEMPTS};
+use crate::logging::redact;
+use crate::secret_store::{self, SecretKey};
 
-const SERVICE_NAME: &str = "net.universalautobrokers.dealersoftware";
-const SESSION_TOKEN_KEY: &str = "standalone_session_token";
+/// Settings key: endpoint that exchanges a refresh token for a new session.
+/// When unset, the frontend refreshes on the "session-expiring" event instead
+pub const SESSION_REFRESH_URL_SETTING: &str = "session_refresh_url";
 
-static KEYRING_LOCK: Mutex<()> = Mutex::new(());
+pub const SESSION_EXPIRING_EVENT: &str = "session-expiring";
 
-/// Store session token securely in OS keyring
-/// SECURITY: This command only works for session tokens - no arbitrary keys allowed
-#[tauri::command]
-pub async fn store_session_token(token: String) -> Result<(), String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
+/// Warn (or refresh) this long before the session expires
+pub const SESSION_EXPIRING_WINDOW_SECS: i64 = 10 * 60;
 
-    info!("🔐 [SESSION] Storing session token in secure storage");
+const SESSION_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const REFRESH_TIMEOUT: Duration = Duration::from_secs(20);
 
-    let entry = Entry::new(SERVICE_NAME, SESSION_TOKEN_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
+/// Deployment that issues sessions, embedded at build time (the frontend's Convex URL)
+const SESSION_SERVER_URL: Option<&str> = option_env!("VITE_CONVEX_URL");
+/// Query that says which user a session token belongs to, and until when
+const VALIDATE_SESSION_QUERY: &str = "standaloneAuth:validateStandaloneSession";
 
-    // Delete existing entry (ignore errors)
-    match entry.delete_credential() {
-        Ok(_) => info!("   Deleted existing entry"),
-        Err(keyring::Error::NoEntry) => info!("   No existing entry to delete"),
-        Err(e) => info!("   Delete error (non-critical): {}", e),
-    }
+/// Marks an encrypted multi-user session map
+const SESSIONS_PREFIX: &str = "enc2:";
+/// Marks an encrypted single envelope; anything else in the keyring is a legacy bare token
+const ENVELOPE_PREFIX: &str = "enc1:";
+const ENVELOPE_KEY_CONTEXT: &[u8] = b"dealer-software/session/v1";
 
-    std::thread::sleep(std::time::Duration::from_millis(50));
+/// Owner of sessions stored without a user id (older versions, store_session_token)
+pub const DEFAULT_USER_ID: &str = "default";
 
-    // Store new value
-    match entry.set_password(&token) {
-        Ok(_) => {
-            info!("✅ [SESSION] Session token stored successfully");
-            Ok(())
-        }
-        Err(e) => {
-            error!("❌ [SESSION] Failed to store session token: {}", e);
-            Err(format!("Failed to store session token: {}", e))
+/// Read-modify-write of the session map must not interleave
+static SESSION_LOCK: Mutex<()> = Mutex::new(());
+
+/// Wrong PINs per user since their last right one; forgotten on restart
+static PIN_ATTEMPTS: Mutex<BTreeMap<String, PinAttempts>> = Mutex::new(BTreeMap::new());
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionEnvelope {
+    pub token: String,
+    pub expires_at: Option<i64>, // Unix seconds; None for legacy bare tokens
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionState {
+    Valid,
+    ExpiringSoon,
+    Expired,
+    Missing,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionStatus {
+    pub state: SessionState,
+    pub expires_at: Option<i64>,
+    pub has_refresh_token: bool,
+}
+
+/// One entry of list_sessions; never includes tokens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SessionSummary {
+    pub user_id: String,
+    pub active: bool,
+    #[serde(flatten)]
+    pub status: SessionStatus,
+}
+
+/// Everything kept under the SessionToken keyring entry
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct SessionStore {
+    active_user_id: Option<String>,
+    sessions: BTreeMap<String, SessionEnvelope>,
+    /// Per user; kept when they log out so the PIN still works after they log back in
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pins: BTreeMap<String, PinHash>,
+}
+
+#[derive(Debug, Default)]
+struct PinAttempts {
+    failed: u32,
+    lockouts: u32,
+    locked_until: Option<Instant>,
+}
+
+impl SessionStore {
+    fn single(envelope: SessionEnvelope) -> Self {
+        Self {
+            active_user_id: Some(DEFAULT_USER_ID.to_string()),
+            sessions: BTreeMap::from([(DEFAULT_USER_ID.to_string(), envelope)]),
+            pins: BTreeMap::new(),
         }
     }
+
+    fn active(&self) -> Option<&SessionEnvelope> {
+        self.active_user_id
+            .as_ref()
+            .and_then(|user_id| self.sessions.get(user_id))
+    }
+}
+
+#[derive(Deserialize)]
+struct RefreshResponse {
+    token: String,
+    expires_at: i64,
+    refresh_token: Option<String>,
+}
+
+/// What the session server vouched for
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ServerSession {
+    user_id: String,
+    expires_at: i64, // Unix seconds
+}
+
+/// Reply of the Convex HTTP query API
+#[derive(Deserialize)]
+struct QueryResponse {
+    status: String,
+    #[serde(default)]
+    value: Option<ValidatedSession>,
+    #[serde(default, rename = "errorMessage")]
+    error_message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ValidatedSession {
+    user: ValidatedUser,
+    session: ValidatedExpiry,
+}
+
+#[derive(Deserialize)]
+struct ValidatedUser {
+    id: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ValidatedExpiry {
+    expires_at: i64, // Unix milliseconds
+}
+
+/// Store session token securely in OS keyring
+/// SECURITY: This command only works for session tokens - no arbitrary keys allowed
+/// Stored for the user the server says the token belongs to, who becomes active
+#[tauri::command]
+pub async fn store_session_token(token: String) -> Result<(), String> {
+    store_verified(None, token, None, None).await
+}
+
+/// Store a session along with its expiry (Unix seconds) and optional refresh token
+/// The server's expiry wins if it's earlier
+#[tauri::command]
+pub async fn store_session(
+    token: String,
+    expires_at: i64,
+    refresh_token: Option<String>,
+) -> Result<(), String> {
+    store_verified(None, token, Some(expires_at), refresh_token).await
+}
+
+/// Whether the active user's session is still usable
+#[tauri::command]
+pub fn get_session_status() -> Result<SessionStatus, String> {
+    let store = read_store()?;
+    Ok(session_status(
+        store.active(),
+        chrono::Utc::now().timestamp(),
+    ))
 }
 
 /// Retrieve session token from OS keyring
 /// SECURITY: This command only works for session tokens - no arbitrary keys allowed
+/// Returns the active user's token
 #[tauri::command]
 pub async fn get_session_token() -> Result<Option<String>, String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🔍 [SESSION] Retrieving session token from secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, SESSION_TOKEN_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.get_password() {
-        Ok(token) => {
-            info!("✅ [SESSION] Session token found");
-            Ok(Some(token))
-        }
-        Err(keyring::Error::NoEntry) => {
-            info!("⚠️  [SESSION] No session token found (normal on first launch or after logout)");
-            Ok(None)
+    match read_store() {
+        Ok(store) => {
+            match store.active() {
+                Some(SessionEnvelope { token, .. }) => {
+                    debug!("✅ [SESSION] Session token found: {}", redact(token));
+                    Ok(Some(token.clone()))
+                }
+                None => {
+                    info!("⚠️  [SESSION] No session token found (normal on first launch or after logout)");
+                    Ok(None)
+                }
+            }
         }
         Err(e) => {
             error!("❌ [SESSION] Failed to retrieve session token: {}", e);
@@ -74,21 +227,17 @@ pub async fn get_session_token() -> Result<Option<String>, String> {
 
 /// Remove session token from OS keyring
 /// SECURITY: This command only works for session tokens - no arbitrary keys allowed
+/// Removes the active user's session; other users stay logged in
 #[tauri::command]
 pub async fn remove_session_token() -> Result<(), String> {
-    let _lock = KEYRING_LOCK.lock().unwrap();
-
     info!("🗑️ [SESSION] Removing session token from secure storage");
 
-    let entry = Entry::new(SERVICE_NAME, SESSION_TOKEN_KEY)
-        .map_err(|e| format!("Failed to create keyring entry: {}", e))?;
-
-    match entry.delete_credential() {
-        Ok(_) => {
+    match remove_session(None) {
+        Ok(true) => {
             info!("✅ [SESSION] Session token removed successfully");
             Ok(())
         }
-        Err(keyring::Error::NoEntry) => {
+        Ok(false) => {
             info!("⚠️  [SESSION] No session token to remove (already removed)");
             Ok(())
         }
@@ -99,3 +248,914 @@ pub async fn remove_session_token() -> Result<(), String> {
     }
 }
 
+/// Store a session for one user and make it the active session; refused unless the server
+/// says the token is that user's. The user only becomes current for db commands after
+/// auth_set_current_user
+#[tauri::command]
+pub async fn store_session_for_user(user_id: String, token: String) -> Result<(), String> {
+    let user_id = require_user_id(user_id)?;
+    store_verified(Some(user_id.as_str()), token, None, None).await
+}
+
+/// Users with a stored session on this computer
+#[tauri::command]
+pub fn list_sessions() -> Result<Vec<SessionSummary>, String> {
+    let store = read_store()?;
+    let now = chrono::Utc::now().timestamp();
+
+    Ok(store
+        .sessions
+        .iter()
+        .map(|(user_id, envelope)| SessionSummary {
+            user_id: user_id.clone(),
+            active: store.active_user_id.as_deref() == Some(user_id.as_str()),
+            status: session_status(Some(envelope), now),
+        })
+        .collect())
+}
+
+/// Make another stored session the active one (switch user without logging out); needs
+/// that user's PIN
+#[tauri::command]
+pub fn switch_active_session(
+    user_id: String,
+    pin: String,
+    state: State<'_, AppState>,
+) -> Result<SessionStatus, AuthError> {
+    let user_id = require_user_id(user_id)?;
+    switch_to(&state, &user_id, &pin, Instant::now())
+}
+
+/// Set the current user's PIN. pin_hash comes from derive_key_from_password, as for kiosk
+/// mode; replacing a PIN needs the old one
+#[tauri::command]
+pub fn set_user_pin(
+    pin_hash: PinHash,
+    current_pin: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), AuthError> {
+    let user_id = state.current_user().ok_or(AuthError::NotAuthenticated)?;
+    save_user_pin(&user_id, pin_hash, current_pin.as_deref(), Instant::now())
+}
+
+/// Log one user out. Removing the active user leaves nobody active, so the next
+/// person at the desk has to pick their own session
+#[tauri::command]
+pub fn remove_session_for_user(user_id: String, state: State<'_, AppState>) -> Result<(), String> {
+    let user_id = require_user_id(user_id)?;
+    remove_session(Some(&user_id))?;
+
+    if state.current_user().as_deref() == Some(user_id.as_str()) {
+        state.set_current_user(None);
+    }
+    Ok(())
+}
+
+/// Check that `token` is the stored session of `user_id` and make it the active session
+pub(crate) fn verify_user_session(user_id: &str, token: &str) -> Result<(), AuthError> {
+    let now = chrono::Utc::now().timestamp();
+
+    modify_store(|store| {
+        let envelope = match store.sessions.get(user_id) {
+            Some(envelope) if token_matches(envelope, token) => envelope.clone(),
+            _ => return Ok(Err(AuthError::InvalidSession)),
+        };
+
+        if session_status(Some(&envelope), now).state == SessionState::Expired {
+            return Ok(Err(AuthError::SessionExpired));
+        }
+        store.active_user_id = Some(user_id.to_string());
+        Ok(Ok(()))
+    })?
+}
+
+/// A session stored before multi-user support (DEFAULT_USER_ID) that holds `token` is
+/// filed under `user_id`, once the server confirms the token is theirs. Anything else is
+/// left alone for verify_user_session to judge
+pub(crate) async fn adopt_legacy_session(user_id: &str, token: &str) -> Result<(), AuthError> {
+    let store = read_store()?;
+    let is_legacy = !store.sessions.contains_key(user_id)
+        && store
+            .sessions
+            .get(DEFAULT_USER_ID)
+            .is_some_and(|envelope| token_matches(envelope, token));
+    if !is_legacy {
+        return Ok(());
+    }
+
+    let session = validate_with_server(token).await?;
+    if session.user_id != user_id {
+        return Err(AuthError::InvalidSession);
+    }
+    modify_store(|store| {
+        if store
+            .sessions
+            .get(DEFAULT_USER_ID)
+            .is_some_and(|envelope| token_matches(envelope, token))
+        {
+            let envelope = store
+                .sessions
+                .remove(DEFAULT_USER_ID)
+                .expect("checked above");
+            store.sessions.insert(user_id.to_string(), envelope);
+        }
+        Ok(())
+    })?;
+    info!("🔀 [SESSION] Legacy session moved to its user");
+    Ok(())
+}
+
+fn token_matches(envelope: &SessionEnvelope, token: &str) -> bool {
+    envelope.token.as_bytes().ct_eq(token.as_bytes()).into()
+}
+
+/// Whether user_id has a stored session that hasn't expired
+pub(crate) fn has_live_session(user_id: &str) -> bool {
+    let now = chrono::Utc::now().timestamp();
+    read_store().is_ok_and(|store| {
+        store.sessions.get(user_id).is_some_and(|envelope| {
+            session_status(Some(envelope), now).state != SessionState::Expired
+        })
+    })
+}
+
+#[cfg(test)]
+pub(crate) fn store_user_session(user_id: &str, token: &str) -> Result<(), String> {
+    store_envelope(
+        user_id,
+        SessionEnvelope {
+            token: token.to_string(),
+            expires_at: None,
+            refresh_token: None,
+        },
+    )
+}
+
+fn require_user_id(user_id: String) -> Result<String, String> {
+    let user_id = user_id.trim().to_string();
+    if user_id.is_empty() {
+        return Err("User ID is required".to_string());
+    }
+    Ok(user_id)
+}
+
+/// Ask the server whose `token` is and store it as their session. `user_id`, when given,
+/// must be who the server says; `expires_at` is only honored if it's earlier than the
+/// server's expiry
+async fn store_verified(
+    user_id: Option<&str>,
+    token: String,
+    expires_at: Option<i64>,
+    refresh_token: Option<String>,
+) -> Result<(), String> {
+    let session = validate_with_server(&token).await?;
+    if user_id.is_some_and(|user_id| user_id != session.user_id) {
+        warn!("⚠️  [SESSION] Refused to store a token for someone else's user id");
+        return Err(AuthError::InvalidSession.into());
+    }
+
+    store_envelope(
+        &session.user_id,
+        SessionEnvelope {
+            token,
+            expires_at: Some(
+                expires_at.map_or(session.expires_at, |at| at.min(session.expires_at)),
+            ),
+            refresh_token,
+        },
+    )
+}
+
+/// Store for the given user and make them active
+fn store_envelope(user_id: &str, envelope: SessionEnvelope) -> Result<(), String> {
+    info!("🔐 [SESSION] Storing session token in secure storage");
+    let token = redact(&envelope.token);
+
+    let result = modify_store(|store| {
+        store.sessions.insert(user_id.to_string(), envelope);
+        store.active_user_id = Some(user_id.to_string());
+        Ok(())
+    });
+
+    match result {
+        Ok(_) => {
+            info!("✅ [SESSION] Session token stored successfully");
+            debug!("   Token: {}", token);
+            Ok(())
+        }
+        Err(e) => {
+            error!("❌ [SESSION] Failed to store session token: {}", e);
+            Err(format!("Failed to store session token: {}", e))
+        }
+    }
+}
+
+/// Check user_id's PIN, then authenticate them with their stored session. A wrong PIN
+/// leaves whoever is current alone; an expired session logs them out, as authenticate does
+fn switch_to(
+    state: &AppState,
+    user_id: &str,
+    pin: &str,
+    now: Instant,
+) -> Result<SessionStatus, AuthError> {
+    verify_user_pin(user_id, pin, now)?;
+    let token = read_store()?
+        .sessions
+        .get(user_id)
+        .map(|envelope| envelope.token.clone())
+        .ok_or_else(|| format!("No session stored for user {}", user_id))?;
+
+    state.authenticate(user_id, &token)?;
+    info!("🔀 [SESSION] Switched active session");
+    Ok(get_session_status()?)
+}
+
+pub(crate) fn save_user_pin(
+    user_id: &str,
+    pin_hash: PinHash,
+    current_pin: Option<&str>,
+    now: Instant,
+) -> Result<(), AuthError> {
+    if pin_hash.salt_base64.trim().is_empty() || pin_hash.key_hash_base64.trim().is_empty() {
+        return Err("A PIN is required".to_string().into());
+    }
+    if read_store()?.pins.contains_key(user_id) {
+        verify_user_pin(user_id, current_pin.unwrap_or_default(), now)?;
+    }
+
+    modify_store(|store| {
+        store.pins.insert(user_id.to_string(), pin_hash);
+        Ok(())
+    })?;
+    info!("🔑 [SESSION] PIN saved");
+    Ok(())
+}
+
+/// Check `pin` against user_id's saved PIN. Wrong PINs lock the user out for a while, as
+/// for kiosk mode; the attempt count is held while the PIN is checked so parallel guesses
+/// can't slip past it
+pub(crate) fn verify_user_pin(user_id: &str, pin: &str, now: Instant) -> Result<(), AuthError> {
+    let pin_hash = read_store()?
+        .pins
+        .get(user_id)
+        .cloned()
+        .ok_or(AuthError::PinNotSet)?;
+
+    let mut attempts = PIN_ATTEMPTS.lock().unwrap_or_else(PoisonError::into_inner);
+    let entry = attempts.entry(user_id.to_string()).or_default();
+    if let Some(until) = entry.locked_until.filter(|until| now < *until) {
+        return Err(AuthError::TooManyAttempts {
+            retry_after_secs: (until - now).as_secs_f64().ceil() as u64,
+        });
+    }
+
+    let matches = verify_password(
+        pin.to_string(),
+        pin_hash.salt_base64,
+        pin_hash.key_hash_base64,
+        pin_hash.params,
+    )?;
+    if matches {
+        attempts.remove(user_id);
+        return Ok(());
+    }
+
+    entry.failed += 1;
+    warn!("⚠️  [SESSION] Wrong PIN");
+    if entry.failed < MAX_PIN_ATTEMPTS {
+        return Err(AuthError::InvalidPin {
+            attempts_left: MAX_PIN_ATTEMPTS - entry.failed,
+        });
+    }
+
+    let lockout = lockout_after(entry.lockouts);
+    entry.failed = 0;
+    entry.lockouts += 1;
+    entry.locked_until = Some(now + lockout);
+    Err(AuthError::TooManyAttempts {
+        retry_after_secs: lockout.as_secs(),
+    })
+}
+
+/// Remove the given user's session, or the active one when None. Ok(false) if absent
+fn remove_session(user_id: Option<&str>) -> Result<bool, String> {
+    modify_store(|store| {
+        let user_id = match user_id.map(str::to_string).or(store.active_user_id.clone()) {
+            Some(user_id) => user_id,
+            None => return Ok(false),
+        };
+
+        if store.active_user_id.as_deref() == Some(user_id.as_str()) {
+            store.active_user_id = None;
+        }
+        Ok(store.sessions.remove(&user_id).is_some())
+    })
+}
+
+/// Watch the session expiry; refresh it when an endpoint is configured, otherwise
+/// tell the frontend so it can refresh before the next API call fails
+pub fn start_session_watch(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut notified_for = None;
+
+        loop {
+            tokio::time::sleep(SESSION_CHECK_INTERVAL).await;
+
+            let status = match get_session_status() {
+                Ok(status) => status,
+                Err(e) => {
+                    warn!("⚠️  [SESSION] Expiry check failed: {}", e);
+                    continue;
+                }
+            };
+            if !matches!(
+                status.state,
+                SessionState::ExpiringSoon | SessionState::Expired
+            ) {
+                continue;
+            }
+
+            if status.state == SessionState::ExpiringSoon {
+                match refresh_session().await {
+                    Ok(true) => continue,
+                    Ok(false) => {}
+                    Err(e) => warn!("⚠️  [SESSION] Refresh failed: {}", e),
+                }
+            }
+
+            // Once per session, not every minute until it's refreshed
+            if notified_for != Some(status.expires_at) {
+                info!("⏰ [SESSION] Session expiring, notifying frontend");
+                if let Err(e) = app.emit(SESSION_EXPIRING_EVENT, &status) {
+                    error!("Failed to emit session status: {}", e);
+                }
+                notified_for = Some(status.expires_at);
+            }
+        }
+    });
+}
+
+/// Exchange the refresh token for a new session. Ok(false) when there's nothing to
+/// refresh with (no endpoint configured or no refresh token)
+async fn refresh_session() -> Result<bool, String> {
+    let url = match db_get_setting(SESSION_REFRESH_URL_SETTING.to_string())?
+        .filter(|url| !url.trim().is_empty())
+    {
+        Some(url) => url,
+        None => return Ok(false),
+    };
+    let store = read_store()?;
+    let (user_id, refresh_token) = match (
+        store.active_user_id.clone(),
+        store
+            .active()
+            .and_then(|envelope| envelope.refresh_token.clone()),
+    ) {
+        (Some(user_id), Some(refresh_token)) => (user_id, refresh_token),
+        _ => return Ok(false),
+    };
+
+    info!("🔄 [SESSION] Refreshing session");
+    let client = reqwest::Client::builder()
+        .timeout(REFRESH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let envelope = request_refresh(&client, url.trim(), &refresh_token).await?;
+
+    // The user may have switched away meanwhile; refresh their session, not the new one's
+    modify_store(|store| {
+        if let Some(session) = store.sessions.get_mut(&user_id) {
+            *session = envelope;
+        }
+        Ok(())
+    })?;
+    info!("✅ [SESSION] Session refreshed");
+    Ok(true)
+}
+
+async fn request_refresh(
+    client: &reqwest::Client,
+    url: &str,
+    refresh_token: &str,
+) -> Result<SessionEnvelope, String> {
+    let response = client
+        .post(url)
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .map_err(|e| format!("Refresh request failed: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Refresh rejected: HTTP {}", response.status()));
+    }
+
+    let refreshed: RefreshResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid refresh response: {}", e))?;
+
+    Ok(SessionEnvelope {
+        token: refreshed.token,
+        expires_at: Some(refreshed.expires_at),
+        // Servers that don't rotate refresh tokens leave this out
+        refresh_token: refreshed.refresh_token.or(Some(refresh_token.to_string())),
+    })
+}
+
+fn session_server_url() -> Result<String, AuthError> {
+    SESSION_SERVER_URL
+        .map(str::to_string)
+        .or_else(|| std::env::var("VITE_CONVEX_URL").ok())
+        .filter(|url| !url.trim().is_empty())
+        .ok_or_else(|| AuthError::from("No session server configured for this build".to_string()))
+}
+
+/// Which user the server says `token` belongs to. An unknown or expired token is
+/// InvalidSession
+async fn validate_with_server(token: &str) -> Result<ServerSession, AuthError> {
+    let url = session_server_url()?;
+    let client = reqwest::Client::builder()
+        .timeout(REFRESH_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    request_validation(&client, url.trim(), token).await
+}
+
+async fn request_validation(
+    client: &reqwest::Client,
+    url: &str,
+    token: &str,
+) -> Result<ServerSession, AuthError> {
+    info!("🔍 [SESSION] Checking session token with the server");
+    let response = client
+        .post(format!("{}/api/query", url.trim_end_matches('/')))
+        .json(&serde_json::json!({
+            "path": VALIDATE_SESSION_QUERY,
+            "args": { "token": token },
+            "format": "json",
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Could not reach the session server: {}", e))?;
+
+    if !response.status().is_success() {
+        return Err(format!("Session server error: HTTP {}", response.status()).into());
+    }
+    let reply: QueryResponse = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid session server response: {}", e))?;
+    if reply.status != "success" {
+        return Err(format!(
+            "Session server error: {}",
+            reply.error_message.unwrap_or(reply.status)
+        )
+        .into());
+    }
+
+    match reply.value {
+        Some(validated) if !validated.user.id.trim().is_empty() => Ok(ServerSession {
+            user_id: validated.user.id,
+            expires_at: validated.session.expires_at / 1000,
+        }),
+        _ => {
+            warn!("⚠️  [SESSION] Server rejected the session token");
+            Err(AuthError::InvalidSession)
+        }
+    }
+}
+
+fn session_status(envelope: Option<&SessionEnvelope>, now: i64) -> SessionStatus {
+    let envelope = match envelope {
+        Some(envelope) => envelope,
+        None => {
+            return SessionStatus {
+                state: SessionState::Missing,
+                expires_at: None,
+                has_refresh_token: false,
+            }
+        }
+    };
+
+    let state = match envelope.expires_at {
+        // Legacy token: expiry unknown until the server says otherwise
+        None => SessionState::Valid,
+        Some(expires_at) if now >= expires_at => SessionState::Expired,
+        Some(expires_at) if now + SESSION_EXPIRING_WINDOW_SECS >= expires_at => {
+            SessionState::ExpiringSoon
+        }
+        Some(_) => SessionState::Valid,
+    };
+
+    SessionStatus {
+        state,
+        expires_at: envelope.expires_at,
+        has_refresh_token: envelope.refresh_token.is_some(),
+    }
+}
+
+fn read_store() -> Result<SessionStore, String> {
+    let stored = match secret_store::get(SecretKey::SessionToken)? {
+        Some(stored) => stored,
+        None => return Ok(SessionStore::default()),
+    };
+
+    match decode_store(&stored) {
+        Ok(store) => Ok(store),
+        Err(e) => {
+            // Machine id changed or the entry was tampered with; users just log in again
+            warn!(
+                "⚠️  [SESSION] Stored session unreadable, treating as logged out: {}",
+                e
+            );
+            Ok(SessionStore::default())
+        }
+    }
+}
+
+fn modify_store<T>(f: impl FnOnce(&mut SessionStore) -> Result<T, String>) -> Result<T, String> {
+    let _lock = SESSION_LOCK
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+    let mut store = read_store()?;
+    let result = f(&mut store)?;
+
+    if store.sessions.is_empty() {
+        secret_store::remove(SecretKey::SessionToken)?;
+    } else {
+        secret_store::store(SecretKey::SessionToken, &encode_store(&store)?)?;
+    }
+    Ok(result)
+}
+
+fn encode_store(store: &SessionStore) -> Result<String, String> {
+    let json =
+        serde_json::to_string(store).map_err(|e| format!("Failed to serialize session: {}", e))?;
+    Ok(format!(
+        "{}{}",
+        SESSIONS_PREFIX,
+        encrypt_data(json, envelope_key())?
+    ))
+}
+
+fn decode_store(stored: &str) -> Result<SessionStore, String> {
+    if let Some(encrypted) = stored.strip_prefix(SESSIONS_PREFIX) {
+        let json = decrypt_data(encrypted.to_string(), envelope_key())?;
+        return serde_json::from_str(&json).map_err(|e| format!("Invalid session store: {}", e));
+    }
+
+    decode_envelope(stored).map(SessionStore::single)
+}
+
+fn decode_envelope(stored: &str) -> Result<SessionEnvelope, String> {
+    let encrypted = match stored.strip_prefix(ENVELOPE_PREFIX) {
+        Some(encrypted) => encrypted,
+        None => {
+            return Ok(SessionEnvelope {
+                token: stored.to_string(),
+                expires_at: None,
+                refresh_token: None,
+            })
+        }
+    };
+
+    let json = decrypt_data(encrypted.to_string(), envelope_key())?;
+    serde_json::from_str(&json).map_err(|e| format!("Invalid session envelope: {}", e))
+}
+
+/// Keyed to this machine, so a keyring entry synced elsewhere can't be replayed
+fn envelope_key() -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(ENVELOPE_KEY_CONTEXT);
+    hasher.update(crate::license::os_machine_id().as_bytes());
+    general_purpose::STANDARD.encode(hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::logging::capture;
+    use crate::test_support::pin_hash;
+    use std::io::Read;
+    use std::sync::mpsc;
+    use tiny_http::{Response, Server};
+
+    #[test]
+    fn test_token_round_trip_never_logs_secret() {
+        let _guard = crate::secret_store::test_guard();
+        capture::install();
+        let token = "sess_9f8e7d6c5b4a39281706f5e4d3c2b1a0".to_string();
+
+        // The keyring backend may be unavailable in CI; only the log output matters here
+        let _ = store_envelope(
+            DEFAULT_USER_ID,
+            SessionEnvelope {
+                token: token.clone(),
+                expires_at: None,
+                refresh_token: None,
+            },
+        );
+        let _ = tauri::async_runtime::block_on(get_session_token());
+
+        let lines = capture::lines();
+        assert!(lines.iter().any(|line| line.contains("[SESSION]")));
+        for line in &lines {
+            assert!(!line.contains(&token), "secret leaked: {}", line);
+            assert!(
+                !line.contains(&token[2..token.len() - 2]),
+                "secret leaked: {}",
+                line
+            );
+        }
+    }
+
+    const NOW: i64 = 1_767_225_600; // 2026-01-01T00:00:00Z
+
+    fn envelope(token: &str, expires_at: Option<i64>) -> SessionEnvelope {
+        SessionEnvelope {
+            token: token.to_string(),
+            expires_at,
+            refresh_token: None,
+        }
+    }
+
+    #[test]
+    fn test_store_round_trip_is_encrypted() {
+        let mut store = SessionStore::single(SessionEnvelope {
+            token: "sess_envelope_token".to_string(),
+            expires_at: Some(NOW + 3600),
+            refresh_token: Some("refresh_abc".to_string()),
+        });
+        store
+            .sessions
+            .insert("user_2".to_string(), envelope("sess_second_user", None));
+
+        let stored = encode_store(&store).unwrap();
+        assert!(stored.starts_with(SESSIONS_PREFIX));
+        assert!(!stored.contains("sess_envelope_token"));
+        assert!(!stored.contains("refresh_abc"));
+        assert!(!stored.contains("user_2"));
+        assert_eq!(decode_store(&stored), Ok(store));
+
+        // Tampered ciphertext is rejected, not read as a legacy token
+        let mut tampered = stored.clone();
+        tampered.push('A');
+        assert!(decode_store(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_legacy_single_session_entries() {
+        // Bare token from before envelopes
+        let store = decode_store("sess_legacy_bare_token").unwrap();
+        assert_eq!(store.active_user_id.as_deref(), Some(DEFAULT_USER_ID));
+        assert_eq!(
+            store.active(),
+            Some(&envelope("sess_legacy_bare_token", None))
+        );
+
+        // No known expiry: usable until the server rejects it
+        let status = session_status(store.active(), NOW);
+        assert_eq!(status.state, SessionState::Valid);
+        assert!(!status.has_refresh_token);
+
+        // Single encrypted envelope from before multi-user sessions
+        let single = envelope("sess_single_envelope", Some(NOW + 3600));
+        let json = serde_json::to_string(&single).unwrap();
+        let stored = format!(
+            "{}{}",
+            ENVELOPE_PREFIX,
+            encrypt_data(json, envelope_key()).unwrap()
+        );
+        assert_eq!(decode_store(&stored), Ok(SessionStore::single(single)));
+
+        let _guard = crate::secret_store::test_guard();
+        secret_store::store(SecretKey::SessionToken, "sess_legacy_bare_token").unwrap();
+        assert_eq!(
+            tauri::async_runtime::block_on(get_session_token()),
+            Ok(Some("sess_legacy_bare_token".to_string()))
+        );
+        let _ = tauri::async_runtime::block_on(remove_session_token());
+    }
+
+    #[test]
+    fn test_switching_between_user_sessions() {
+        let _guard = crate::secret_store::test_guard();
+        let _ = secret_store::remove(SecretKey::SessionToken);
+
+        store_envelope("alice", envelope("sess_alice", None)).unwrap();
+        store_envelope("bob", envelope("sess_bob", Some(NOW))).unwrap();
+
+        // The last user to log in is active; get_session_token follows the active user
+        let active_token = || tauri::async_runtime::block_on(get_session_token()).unwrap();
+        assert_eq!(active_token().as_deref(), Some("sess_bob"));
+
+        verify_user_session("alice", "sess_alice").unwrap();
+        assert_eq!(get_session_status().unwrap().state, SessionState::Valid);
+        assert_eq!(active_token().as_deref(), Some("sess_alice"));
+
+        // A new session for the active user replaces the old one
+        store_envelope("alice", envelope("sess_alice_2", None)).unwrap();
+        assert_eq!(active_token().as_deref(), Some("sess_alice_2"));
+
+        let sessions = list_sessions().unwrap();
+        let summary: Vec<_> = sessions
+            .iter()
+            .map(|session| (session.user_id.as_str(), session.active))
+            .collect();
+        assert_eq!(summary, vec![("alice", true), ("bob", false)]);
+        assert_eq!(sessions[1].status.state, SessionState::Expired);
+        assert!(!serde_json::to_string(&sessions).unwrap().contains("sess_"));
+
+        assert!(verify_user_session("carol", "sess_alice_2").is_err());
+        assert_eq!(active_token().as_deref(), Some("sess_alice_2"));
+
+        let _ = secret_store::remove(SecretKey::SessionToken);
+    }
+
+    #[test]
+    fn test_switching_needs_the_users_pin() {
+        let _guard = crate::secret_store::test_guard();
+        let _ = secret_store::remove(SecretKey::SessionToken);
+        store_envelope("pin_manager", envelope("sess_manager", None)).unwrap();
+        store_envelope("pin_sales", envelope("sess_sales", None)).unwrap();
+        let state = AppState::default();
+        state.authenticate("pin_sales", "sess_sales").unwrap();
+        let active_token = || tauri::async_runtime::block_on(get_session_token()).unwrap();
+        let now = Instant::now();
+
+        // Nobody can step into a session whose owner hasn't set a PIN
+        assert_eq!(
+            switch_to(&state, "pin_manager", "4321", now),
+            Err(AuthError::PinNotSet)
+        );
+        save_user_pin("pin_manager", pin_hash("4321"), None, now).unwrap();
+
+        // A wrong PIN leaves the current user and the active session alone
+        assert_eq!(
+            switch_to(&state, "pin_manager", "1234", now),
+            Err(AuthError::InvalidPin {
+                attempts_left: MAX_PIN_ATTEMPTS - 1
+            })
+        );
+        assert_eq!(state.current_user().as_deref(), Some("pin_sales"));
+        assert_eq!(active_token().as_deref(), Some("sess_sales"));
+
+        let status = switch_to(&state, "pin_manager", "4321", now).unwrap();
+        assert_eq!(status.state, SessionState::Valid);
+        assert_eq!(state.current_user().as_deref(), Some("pin_manager"));
+        assert_eq!(active_token().as_deref(), Some("sess_manager"));
+
+        // Replacing the PIN takes the old one
+        assert!(save_user_pin("pin_manager", pin_hash("0000"), Some("1111"), now).is_err());
+        save_user_pin("pin_manager", pin_hash("0000"), Some("4321"), now).unwrap();
+
+        // Guessing locks the user out, right PIN or not
+        for _ in 1..MAX_PIN_ATTEMPTS {
+            let _ = switch_to(&state, "pin_manager", "9999", now);
+        }
+        assert!(matches!(
+            switch_to(&state, "pin_manager", "9999", now),
+            Err(AuthError::TooManyAttempts { .. })
+        ));
+        assert!(matches!(
+            switch_to(&state, "pin_manager", "0000", now),
+            Err(AuthError::TooManyAttempts { .. })
+        ));
+
+        let _ = secret_store::remove(SecretKey::SessionToken);
+    }
+
+    #[test]
+    fn test_removing_the_active_session() {
+        let _guard = crate::secret_store::test_guard();
+        let _ = secret_store::remove(SecretKey::SessionToken);
+
+        store_envelope("bob", envelope("sess_bob", None)).unwrap();
+        store_envelope("alice", envelope("sess_alice", None)).unwrap();
+
+        // Nobody is active afterwards; bob has to pick his own session
+        assert_eq!(remove_session(Some("alice")), Ok(true));
+        assert_eq!(
+            tauri::async_runtime::block_on(get_session_token()),
+            Ok(None)
+        );
+        assert_eq!(get_session_status().unwrap().state, SessionState::Missing);
+        assert_eq!(list_sessions().unwrap().len(), 1);
+
+        // Removing someone else's session leaves the active user alone
+        verify_user_session("bob", "sess_bob").unwrap();
+        store_envelope("alice", envelope("sess_alice", None)).unwrap();
+        verify_user_session("bob", "sess_bob").unwrap();
+        assert_eq!(remove_session(Some("alice")), Ok(true));
+        assert_eq!(remove_session(Some("alice")), Ok(false));
+        assert_eq!(
+            tauri::async_runtime::block_on(get_session_token()),
+            Ok(Some("sess_bob".to_string()))
+        );
+
+        // Last session gone: the keyring entry goes too
+        assert_eq!(remove_session(None), Ok(true));
+        assert_eq!(secret_store::get(SecretKey::SessionToken), Ok(None));
+
+        let state = AppState::default();
+        state.set_current_user(Some("bob".to_string()));
+        assert_eq!(state.current_user().as_deref(), Some("bob"));
+    }
+
+    /// Session server that answers one request with `body`, handing back what was asked
+    fn session_server(body: &'static str) -> (String, mpsc::Receiver<serde_json::Value>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut request = server.recv().unwrap();
+            let mut sent = String::new();
+            request.as_reader().read_to_string(&mut sent).unwrap();
+            let _ = tx.send(serde_json::json!({
+                "url": request.url(),
+                "body": serde_json::from_str::<serde_json::Value>(&sent).unwrap(),
+            }));
+            request.respond(Response::from_string(body)).unwrap();
+        });
+        (url, rx)
+    }
+
+    fn validate(url: &str, token: &str) -> Result<ServerSession, AuthError> {
+        let client = reqwest::Client::new();
+        tauri::async_runtime::block_on(request_validation(&client, url, token))
+    }
+
+    #[test]
+    fn test_server_says_whose_token_it_is() {
+        let (url, request) = session_server(
+            r#"{"status":"success","value":{"user":{"id":"user_alice"},"session":{"expiresAt":1767229200000}},"logLines":[]}"#,
+        );
+        assert_eq!(
+            validate(&url, "sess_alice"),
+            Ok(ServerSession {
+                user_id: "user_alice".to_string(),
+                expires_at: NOW + 3600,
+            })
+        );
+        let sent = request.recv().unwrap();
+        assert_eq!(sent["url"], "/api/query");
+        assert_eq!(sent["body"]["path"], VALIDATE_SESSION_QUERY);
+        assert_eq!(sent["body"]["args"]["token"], "sess_alice");
+
+        // Made-up or expired tokens come back as null
+        let (url, _) = session_server(r#"{"status":"success","value":null}"#);
+        assert_eq!(
+            validate(&url, "sess_made_up"),
+            Err(AuthError::InvalidSession)
+        );
+
+        let (url, _) = session_server(r#"{"status":"error","errorMessage":"boom"}"#);
+        assert!(matches!(
+            validate(&url, "sess_alice"),
+            Err(AuthError::Other { message }) if message.contains("boom")
+        ));
+    }
+
+    #[test]
+    fn test_legacy_session_is_not_adopted_without_the_server() {
+        let _guard = crate::secret_store::test_guard();
+        let _ = secret_store::remove(SecretKey::SessionToken);
+        secret_store::store(SecretKey::SessionToken, "sess_legacy_bare_token").unwrap();
+
+        // Holding the token isn't enough to claim the legacy session for any user id
+        assert_eq!(
+            verify_user_session("user_admin", "sess_legacy_bare_token"),
+            Err(AuthError::InvalidSession)
+        );
+        assert_eq!(
+            list_sessions().unwrap()[0].user_id,
+            DEFAULT_USER_ID.to_string()
+        );
+
+        let _ = secret_store::remove(SecretKey::SessionToken);
+    }
+
+    #[test]
+    fn test_session_status_transitions() {
+        let envelope = |expires_at| SessionEnvelope {
+            token: "t".to_string(),
+            expires_at: Some(expires_at),
+            refresh_token: None,
+        };
+
+        assert_eq!(session_status(None, NOW).state, SessionState::Missing);
+        assert_eq!(
+            session_status(Some(&envelope(NOW + 3600)), NOW).state,
+            SessionState::Valid
+        );
+        assert_eq!(
+            session_status(Some(&envelope(NOW + SESSION_EXPIRING_WINDOW_SECS)), NOW).state,
+            SessionState::ExpiringSoon
+        );
+        assert_eq!(
+            session_status(Some(&envelope(NOW)), NOW).state,
+            SessionState::Expired
+        );
+    }
+}
@@ -0,0 +1,309 @@
+// src-tauri/src/settings_cache.rs
+//
+// Write-behind cache for db_set_setting. The UI saves window layout and column widths on
+// every resize tick; instead of one write per call, db_set_setting queues the value here and
+// the flusher thread writes the dirty keys at most once per flush interval
+// (settings_flush_interval_ms, default 500ms) in one transaction, so a burst of sets to one
+// key is one write. db_get_setting reads the queue first, so a value is visible as soon as
+// it's set, then the read cache (read_cache.rs). Code that reads the settings table with its own SQL (exports, key rotation)
+// calls flush_settings() first; the UI can call db_flush_settings, and the ordered shutdown
+// flushes before the WAL checkpoint. When no flusher is running (before setup, after
+// shutdown, in tests) db_set_setting writes straight through.
+//
+// Lock order: the connection, then the queue. Nothing takes the connection while holding
+// the queue.
+
+use chrono::Utc;
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use crate::database::{begin_write, db_get_setting, get_db, upsert_setting, Database};
+use crate::telemetry::track;
+
+/// Settings key: milliseconds between flushes of queued settings
+pub const SETTINGS_FLUSH_INTERVAL_SETTING: &str = "settings_flush_interval_ms";
+
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_millis(500);
+const MIN_FLUSH_INTERVAL: Duration = Duration::from_millis(50);
+const MAX_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+static SETTINGS: Lazy<SettingsCache> = Lazy::new(SettingsCache::default);
+
+#[derive(Default)]
+struct Queue {
+    /// Latest unwritten value of each key
+    pending: BTreeMap<String, String>,
+    /// A flusher thread is running; without one, sets aren't queued
+    running: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct SettingsCache {
+    queue: Mutex<Queue>,
+    /// Signalled when a key is queued or the flusher should stop
+    changed: Condvar,
+    /// Rows written by flushes, ever
+    rows_written: AtomicU64,
+}
+
+impl SettingsCache {
+    fn lock(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Allow queueing; call before starting run() on another thread
+    pub(crate) fn start(&self) {
+        self.lock().running = true;
+    }
+
+    /// The setting's value: the queued one, else the stored one (through the read cache)
+    pub(crate) fn get(&self, db: &Database, key: &str) -> Result<Option<String>, String> {
+        if let Some(value) = self.pending(key) {
+            return Ok(Some(value));
+        }
+        db.read_cache().setting(db, key)
+    }
+
+    /// Queue the value, or write it now when no flusher is running
+    pub(crate) fn set(&self, db: &Database, key: &str, value: &str) -> Result<(), String> {
+        if self.queue(key, value) {
+            return Ok(());
+        }
+        upsert_setting(&db.conn(), key, value, Utc::now().timestamp_millis())
+            .map_err(|e| e.to_string())
+    }
+
+    /// Queue value for key. False when no flusher is running: the caller writes it itself
+    fn queue(&self, key: &str, value: &str) -> bool {
+        let mut queue = self.lock();
+        if !queue.running {
+            return false;
+        }
+        queue.pending.insert(key.to_string(), value.to_string());
+        self.changed.notify_all();
+        true
+    }
+
+    /// The queued value of key, if it hasn't been written yet
+    pub(crate) fn pending(&self, key: &str) -> Option<String> {
+        self.lock().pending.get(key).cloned()
+    }
+
+    /// Write every queued key in one transaction; returns how many were written. A failed
+    /// write leaves them queued
+    pub(crate) fn flush(&self, conn: &Connection) -> Result<usize, String> {
+        let mut queue = self.lock();
+        if queue.pending.is_empty() {
+            return Ok(0);
+        }
+        let now = Utc::now().timestamp_millis();
+        let tx = begin_write(conn, "settings").map_err(|e| e.to_string())?;
+        for (key, value) in &queue.pending {
+            upsert_setting(&tx, key, value, now).map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        let written = queue.pending.len();
+        queue.pending.clear();
+        self.rows_written
+            .fetch_add(written as u64, Ordering::Relaxed);
+        Ok(written)
+    }
+
+    /// The flusher: waits for a queued key, lets more sets arrive for `interval`, then
+    /// writes them all. Returns once stop() is called (stop() does the last flush), or at
+    /// once if start() wasn't
+    pub(crate) fn run(&self, db: &Database, interval: Duration) {
+        loop {
+            let queue = self.lock();
+            let queue = self
+                .changed
+                .wait_while(queue, |queue| queue.running && queue.pending.is_empty())
+                .unwrap_or_else(PoisonError::into_inner);
+            if !queue.running {
+                return;
+            }
+            let (queue, _) = self
+                .changed
+                .wait_timeout_while(queue, interval, |queue| queue.running)
+                .unwrap_or_else(PoisonError::into_inner);
+            if !queue.running {
+                return;
+            }
+            drop(queue);
+            if let Err(e) = self.flush(&db.conn()) {
+                warn!("⚠️  Failed to write settings: {}", e);
+            }
+        }
+    }
+
+    /// Stop queueing (sets write through from now on), end the flusher and write what's
+    /// queued
+    pub(crate) fn stop(&self, db: &Database) -> Result<usize, String> {
+        {
+            let mut queue = self.lock();
+            queue.running = false;
+            self.changed.notify_all();
+        }
+        self.flush(&db.conn())
+    }
+}
+
+fn flush_interval() -> Duration {
+    db_get_setting(SETTINGS_FLUSH_INTERVAL_SETTING.to_string())
+        .ok()
+        .flatten()
+        .and_then(|ms| ms.trim().parse().ok())
+        .map_or(DEFAULT_FLUSH_INTERVAL, Duration::from_millis)
+        .clamp(MIN_FLUSH_INTERVAL, MAX_FLUSH_INTERVAL)
+}
+
+/// Start queueing db_set_setting writes. Call once the database is initialized
+pub fn start_settings_flusher() {
+    let db = match get_db() {
+        Ok(db) => db,
+        Err(e) => {
+            warn!("⚠️  Settings are written straight through: {}", e);
+            return;
+        }
+    };
+    let interval = flush_interval();
+    SETTINGS.start();
+    std::thread::spawn(move || SETTINGS.run(db, interval));
+    info!("✅ Settings write-behind started ({:?})", interval);
+}
+
+/// db_get_setting: sees values that are queued but not yet written
+pub(crate) fn get_setting(key: &str) -> Result<Option<String>, String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    SETTINGS.get(db, key)
+}
+
+/// db_set_setting
+pub(crate) fn set_setting(key: &str, value: &str) -> Result<(), String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    SETTINGS.set(db, key, value)
+}
+
+/// The value set for key and not yet written, for readers of the settings table
+pub(crate) fn pending_setting(key: &str) -> Option<String> {
+    SETTINGS.pending(key)
+}
+
+/// Write queued settings now, before reading the settings table with SQL.
+/// Don't call it while holding the connection
+pub(crate) fn flush_settings() -> Result<usize, String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    let conn = db.conn();
+    SETTINGS.flush(&conn)
+}
+
+/// Shutdown: stop the flusher and write what's queued
+pub(crate) fn stop_settings_flusher() -> Result<usize, String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    SETTINGS.stop(db)
+}
+
+/// Write queued settings now; returns how many were written
+#[tauri::command]
+pub fn db_flush_settings() -> Result<usize, String> {
+    track("db_flush_settings", flush_settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::read_setting;
+    use std::thread;
+    use std::time::Instant;
+
+    fn stored(db: &Database, key: &str) -> Option<String> {
+        read_setting(&db.conn(), key).unwrap()
+    }
+
+    fn wait_for(condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn test_rapid_sets_are_coalesced_into_one_write() {
+        let db = Database::init_in_memory().unwrap();
+        let cache = SettingsCache::default();
+        cache.start();
+        thread::scope(|scope| {
+            scope.spawn(|| cache.run(&db, Duration::from_millis(200)));
+            for width in 0..100 {
+                cache
+                    .set(&db, "column_widths", &format!("[{}]", width))
+                    .unwrap();
+            }
+            wait_for(|| cache.rows_written.load(Ordering::Relaxed) > 0);
+            assert_eq!(cache.rows_written.load(Ordering::Relaxed), 1);
+            assert_eq!(stored(&db, "column_widths").as_deref(), Some("[99]"));
+            assert_eq!(cache.pending("column_widths"), None);
+
+            cache.stop(&db).unwrap();
+        });
+        assert_eq!(cache.rows_written.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_queued_values_are_read_before_they_are_written() {
+        let db = Database::init_in_memory().unwrap();
+        let cache = SettingsCache::default();
+        // No flusher: written straight through
+        cache.set(&db, "window_layout", "old").unwrap();
+        assert_eq!(stored(&db, "window_layout").as_deref(), Some("old"));
+
+        cache.start();
+        cache.set(&db, "window_layout", "new").unwrap();
+        assert_eq!(stored(&db, "window_layout").as_deref(), Some("old"));
+        assert_eq!(
+            cache.get(&db, "window_layout").unwrap().as_deref(),
+            Some("new")
+        );
+        assert_eq!(cache.get(&db, "missing").unwrap(), None);
+
+        assert_eq!(cache.flush(&db.conn()).unwrap(), 1);
+        assert_eq!(cache.pending("window_layout"), None);
+        assert_eq!(stored(&db, "window_layout").as_deref(), Some("new"));
+        assert_eq!(
+            cache.get(&db, "window_layout").unwrap().as_deref(),
+            Some("new")
+        );
+        assert_eq!(cache.flush(&db.conn()).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_stopping_writes_what_is_queued_and_ends_the_flusher() {
+        let db = Database::init_in_memory().unwrap();
+        let cache = SettingsCache::default();
+        cache.start();
+        thread::scope(|scope| {
+            // Far longer than the test: only stop() can write these
+            let flusher = scope.spawn(|| cache.run(&db, Duration::from_secs(3600)));
+            cache.set(&db, "window_layout", "{\"split\":0.4}").unwrap();
+            cache.set(&db, "theme", "dark").unwrap();
+            assert_eq!(stored(&db, "theme"), None);
+
+            assert_eq!(cache.stop(&db).unwrap(), 2);
+            flusher.join().unwrap();
+        });
+        assert_eq!(
+            stored(&db, "window_layout").as_deref(),
+            Some("{\"split\":0.4}")
+        );
+        assert_eq!(stored(&db, "theme").as_deref(), Some("dark"));
+        // Once stopped, sets write straight through
+        cache.set(&db, "theme", "light").unwrap();
+        assert_eq!(stored(&db, "theme").as_deref(), Some("light"));
+    }
+}
@@ -0,0 +1,412 @@
+// src-tauri/src/shutdown.rs
+//
+// Ordered shutdown when the window closes or the app is asked to quit:
+//   1. stop the local API and stop accepting new S3 transfers
+//   2. give in-flight transfers a grace period (shutdown_grace_secs, default 10s) to finish
+//   3. abort whatever is left (a dropped PutObject leaves nothing behind in the bucket)
+//   4. write queued settings (settings_cache.rs) and checkpoint the SQLite WAL into the
+//      main database file
+//   5. write the clean-shutdown marker
+// Sync state (sync_log, synced_at columns) lives in SQLite, so the checkpoint is what persists it.
+// Startup removes the marker; if it wasn't there, the last session crashed or was killed
+// and the database gets a full integrity check.
+// Progress goes to the UI as "shutdown-progress" events so it can show "finishing uploads…".
+
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::watch;
+
+use crate::database::{checkpoint_wal, db_get_setting, integrity_check};
+use crate::storage::get_app_data_dir;
+
+pub const SHUTDOWN_PROGRESS_EVENT: &str = "shutdown-progress";
+
+/// Settings key: seconds to wait for in-flight transfers before aborting them
+pub const SHUTDOWN_GRACE_SETTING: &str = "shutdown_grace_secs";
+
+const DEFAULT_GRACE: Duration = Duration::from_secs(10);
+/// How long aborted tasks get to unwind before we stop waiting for them
+const ABORT_WAIT: Duration = Duration::from_secs(2);
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+const CLEAN_SHUTDOWN_MARKER: &str = ".clean_shutdown";
+
+const RUNNING: u8 = 0;
+const SHUTTING_DOWN: u8 = 1;
+const FINISHED: u8 = 2;
+
+static STATE: AtomicU8 = AtomicU8::new(RUNNING);
+
+/// In-flight work the app waits for on exit
+pub static TASKS: Lazy<TaskTracker> = Lazy::new(TaskTracker::new);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShutdownStage {
+    WaitingForTasks,
+    AbortingTasks,
+    CheckpointingDatabase,
+    Done,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShutdownProgress {
+    pub stage: ShutdownStage,
+    /// Names of the tasks still running
+    pub in_flight: Vec<String>,
+}
+
+/// Tracks work (S3 transfers) that should finish before the process exits
+pub struct TaskTracker {
+    accepting: AtomicBool,
+    tasks: Mutex<BTreeMap<u64, &'static str>>,
+    idle: Condvar,
+    next_id: AtomicU64,
+    abort: watch::Sender<bool>,
+}
+
+/// Held for the duration of a task; dropping it marks the task finished
+pub struct TaskGuard<'a> {
+    tracker: &'a TaskTracker,
+    id: u64,
+}
+
+impl TaskTracker {
+    pub fn new() -> Self {
+        Self {
+            accepting: AtomicBool::new(true),
+            tasks: Mutex::new(BTreeMap::new()),
+            idle: Condvar::new(),
+            next_id: AtomicU64::new(0),
+            abort: watch::Sender::new(false),
+        }
+    }
+
+    /// Register a task. Fails once shutdown has started
+    pub fn begin(&self, name: &'static str) -> Result<TaskGuard<'_>, String> {
+        let mut tasks = self.lock();
+        if !self.accepting.load(Ordering::SeqCst) {
+            return Err("The app is shutting down".to_string());
+        }
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        tasks.insert(id, name);
+        Ok(TaskGuard { tracker: self, id })
+    }
+
+    fn stop_accepting(&self) {
+        let _tasks = self.lock();
+        self.accepting.store(false, Ordering::SeqCst);
+    }
+
+    fn in_flight(&self) -> Vec<String> {
+        self.lock().values().map(|name| name.to_string()).collect()
+    }
+
+    /// Wait up to `timeout` for every task to finish; true when none are left
+    fn wait_idle(&self, timeout: Duration) -> bool {
+        let tasks = self.lock();
+        let (tasks, _) = self
+            .idle
+            .wait_timeout_while(tasks, timeout, |tasks| !tasks.is_empty())
+            .unwrap_or_else(PoisonError::into_inner);
+        tasks.is_empty()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<u64, &'static str>> {
+        self.tasks.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for TaskTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TaskGuard<'_> {
+    /// Run `work`, giving up with an error if shutdown aborts outstanding tasks
+    pub async fn until_aborted<T>(&self, work: impl Future<Output = T>) -> Result<T, String> {
+        let mut abort = self.tracker.abort.subscribe();
+        let mut work = pin!(work);
+        let mut aborted = pin!(abort.wait_for(|aborted| *aborted));
+
+        std::future::poll_fn(|cx| {
+            if let Poll::Ready(output) = work.as_mut().poll(cx) {
+                return Poll::Ready(Ok(output));
+            }
+            match aborted.as_mut().poll(cx) {
+                Poll::Ready(_) => {
+                    Poll::Ready(Err("Cancelled: the app is shutting down".to_string()))
+                }
+                Poll::Pending => Poll::Pending,
+            }
+        })
+        .await
+    }
+}
+
+impl Drop for TaskGuard<'_> {
+    fn drop(&mut self) {
+        let mut tasks = self.tracker.lock();
+        tasks.remove(&self.id);
+        if tasks.is_empty() {
+            self.tracker.idle.notify_all();
+        }
+    }
+}
+
+/// Called for window close and exit requests. Returns true when the app may exit now;
+/// otherwise the caller must prevent the exit and the shutdown thread exits when done
+pub fn request_exit(app: &AppHandle) -> bool {
+    match STATE.compare_exchange(RUNNING, SHUTTING_DOWN, Ordering::SeqCst, Ordering::SeqCst) {
+        Ok(_) => {
+            let app = app.clone();
+            std::thread::spawn(move || {
+                run_shutdown(&app);
+                app.exit(0);
+            });
+            false
+        }
+        Err(state) => state == FINISHED,
+    }
+}
+
+/// Last chance on RunEvent::Exit when the ordered shutdown didn't run (e.g. forced exit)
+pub fn on_exit() {
+    if STATE.swap(FINISHED, Ordering::SeqCst) == RUNNING {
+        warn!("⚠️  Exiting without an ordered shutdown");
+        crate::local_api::stop();
+        finalize();
+    }
+}
+
+/// Startup check: run a full integrity check if the last session didn't shut down cleanly.
+/// Call once the database is initialized
+pub fn check_previous_shutdown() {
+    let marker = match marker_path() {
+        Ok(marker) => marker,
+        Err(e) => {
+            warn!("⚠️  Can't check previous shutdown: {}", e);
+            return;
+        }
+    };
+
+    let clean = marker.exists();
+    // Removed now so a crash during this session leaves it missing
+    let _ = fs::remove_file(&marker);
+    if clean {
+        return;
+    }
+
+    warn!("⚠️  Previous session didn't shut down cleanly, checking database integrity");
+    match integrity_check() {
+        Ok(()) => info!("✅ Database integrity check passed"),
+        Err(e) => error!("❌ Database integrity check failed: {}", e),
+    }
+}
+
+fn run_shutdown(app: &AppHandle) {
+    info!("🛑 Shutting down");
+    crate::local_api::stop();
+    let grace = db_get_setting(SHUTDOWN_GRACE_SETTING.to_string())
+        .ok()
+        .flatten()
+        .and_then(|secs| secs.trim().parse().ok())
+        .map_or(DEFAULT_GRACE, Duration::from_secs);
+
+    let emit = |progress: ShutdownProgress| {
+        let _ = app.emit(SHUTDOWN_PROGRESS_EVENT, &progress);
+    };
+    let abandoned = shutdown_tasks(&TASKS, grace, ABORT_WAIT, emit);
+    if !abandoned.is_empty() {
+        warn!("⚠️  Exiting with tasks still running: {:?}", abandoned);
+    }
+
+    emit(ShutdownProgress {
+        stage: ShutdownStage::CheckpointingDatabase,
+        in_flight: Vec::new(),
+    });
+    finalize();
+    STATE.store(FINISHED, Ordering::SeqCst);
+
+    emit(ShutdownProgress {
+        stage: ShutdownStage::Done,
+        in_flight: Vec::new(),
+    });
+    info!("✅ Shutdown complete");
+}
+
+/// Stop new tasks, wait out the grace period, then abort. Returns the tasks that
+/// still hadn't finished after the abort
+fn shutdown_tasks(
+    tracker: &TaskTracker,
+    grace: Duration,
+    abort_wait: Duration,
+    mut progress: impl FnMut(ShutdownProgress),
+) -> Vec<String> {
+    tracker.stop_accepting();
+
+    let deadline = Instant::now() + grace;
+    loop {
+        let in_flight = tracker.in_flight();
+        if in_flight.is_empty() {
+            return Vec::new();
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        progress(ShutdownProgress {
+            stage: ShutdownStage::WaitingForTasks,
+            in_flight,
+        });
+        tracker.wait_idle(PROGRESS_INTERVAL.min(deadline - now));
+    }
+
+    progress(ShutdownProgress {
+        stage: ShutdownStage::AbortingTasks,
+        in_flight: tracker.in_flight(),
+    });
+    tracker.abort.send_replace(true);
+    tracker.wait_idle(abort_wait);
+    tracker.in_flight()
+}
+
+/// Database and marker steps; safe to run with tasks abandoned
+fn finalize() {
+    // Queued settings go into the WAL before it's checkpointed
+    if let Err(e) = crate::settings_cache::stop_settings_flusher() {
+        error!("❌ Failed to write queued settings: {}", e);
+    }
+    if let Err(e) = checkpoint_wal() {
+        error!("❌ WAL checkpoint failed: {}", e);
+    }
+    match marker_path().and_then(|marker| {
+        fs::write(&marker, chrono::Utc::now().to_rfc3339())
+            .map_err(|e| format!("Failed to write shutdown marker: {}", e))
+    }) {
+        Ok(()) => {}
+        Err(e) => error!("❌ {}", e),
+    }
+    crate::logging::flush();
+}
+
+fn marker_path() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join(CLEAN_SHUTDOWN_MARKER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn stages(progress: &[ShutdownProgress]) -> Vec<ShutdownStage> {
+        let mut stages: Vec<ShutdownStage> = progress.iter().map(|p| p.stage).collect();
+        stages.dedup();
+        stages
+    }
+
+    #[test]
+    fn test_idle_shutdown_is_immediate_and_rejects_new_work() {
+        let tracker = TaskTracker::new();
+        drop(tracker.begin("s3_upload").unwrap());
+
+        let mut progress = Vec::new();
+        let started = Instant::now();
+        let abandoned = shutdown_tasks(
+            &tracker,
+            Duration::from_secs(10),
+            Duration::from_secs(2),
+            |p| progress.push(p),
+        );
+        assert!(abandoned.is_empty());
+        assert!(progress.is_empty());
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(tracker.begin("s3_upload").is_err());
+    }
+
+    #[test]
+    fn test_task_finishing_within_grace_period() {
+        let tracker = Arc::new(TaskTracker::new());
+        let worker = {
+            let tracker = tracker.clone();
+            let guard_ready = Arc::new(std::sync::Barrier::new(2));
+            let ready = guard_ready.clone();
+            let handle = std::thread::spawn(move || {
+                let _guard = tracker.begin("s3_upload").unwrap();
+                ready.wait();
+                std::thread::sleep(Duration::from_millis(200));
+            });
+            guard_ready.wait();
+            handle
+        };
+
+        let mut progress = Vec::new();
+        let abandoned = shutdown_tasks(
+            &tracker,
+            Duration::from_secs(5),
+            Duration::from_secs(1),
+            |p| progress.push(p),
+        );
+        worker.join().unwrap();
+
+        assert!(abandoned.is_empty());
+        assert_eq!(stages(&progress), vec![ShutdownStage::WaitingForTasks]);
+        assert_eq!(progress[0].in_flight, vec!["s3_upload".to_string()]);
+    }
+
+    #[test]
+    fn test_timeout_aborts_and_abandons_stubborn_task() {
+        let tracker = Arc::new(TaskTracker::new());
+        let ready = Arc::new(std::sync::Barrier::new(3));
+
+        // Honors the abort signal
+        let cooperative = {
+            let (tracker, ready) = (tracker.clone(), ready.clone());
+            std::thread::spawn(move || {
+                let guard = tracker.begin("s3_upload").unwrap();
+                ready.wait();
+                tauri::async_runtime::block_on(guard.until_aborted(std::future::pending::<()>()))
+            })
+        };
+        // Refuses to finish
+        let stubborn = {
+            let (tracker, ready) = (tracker.clone(), ready.clone());
+            std::thread::spawn(move || {
+                let _guard = tracker.begin("s3_download").unwrap();
+                ready.wait();
+                std::thread::sleep(Duration::from_secs(2));
+            })
+        };
+        ready.wait();
+
+        let mut progress = Vec::new();
+        let started = Instant::now();
+        let abandoned = shutdown_tasks(
+            &tracker,
+            Duration::from_millis(150),
+            Duration::from_millis(150),
+            |p| progress.push(p),
+        );
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(abandoned, vec!["s3_download".to_string()]);
+        assert_eq!(
+            stages(&progress),
+            vec![ShutdownStage::WaitingForTasks, ShutdownStage::AbortingTasks]
+        );
+        assert!(cooperative.join().unwrap().is_err());
+        stubborn.join().unwrap();
+        assert!(tracker.in_flight().is_empty());
+    }
+}
@@ -0,0 +1,208 @@
+// src-tauri/src/signing.rs
+//
+// HMAC-SHA256 signatures for exported documents (deal archives, contract PDFs)
+// Lets us prove later that a file hasn't been altered since we produced it
+
+use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+type HmacSha256 = Hmac<Sha256>;
+
+pub const SIGNATURE_ALGORITHM: &str = "HMAC-SHA256";
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Contents of a `<file>.sig` sidecar
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureFile {
+    pub algorithm: String,
+    pub signed_at: String, // RFC 3339
+    pub signature: String, // Base64
+}
+
+/// Sign a file with HMAC-SHA256, returning the base64 signature
+/// key: base64-encoded secret
+#[tauri::command]
+pub fn hmac_sign_file(file_path: String, key: String) -> Result<String, String> {
+    info!("✍️ Signing file: {}", file_path);
+
+    let mac = mac_file(Path::new(&file_path), &decode_key(&key)?)?;
+    Ok(general_purpose::STANDARD.encode(mac.finalize().into_bytes()))
+}
+
+/// Check a file against a base64 HMAC-SHA256 signature (constant-time compare)
+#[tauri::command]
+pub fn hmac_verify_file(file_path: String, key: String, signature: String) -> Result<bool, String> {
+    let expected = match general_purpose::STANDARD.decode(signature.trim()) {
+        Ok(expected) => expected,
+        Err(_) => return Ok(false),
+    };
+
+    let mac = mac_file(Path::new(&file_path), &decode_key(&key)?)?;
+    let valid = mac.verify_slice(&expected).is_ok();
+
+    if !valid {
+        warn!("⚠️  Signature mismatch for: {}", file_path);
+    }
+    Ok(valid)
+}
+
+/// Sign a file and write the signature next to it as `<file>.sig`
+#[tauri::command]
+pub fn write_signature_file(file_path: String, key: String) -> Result<SignatureFile, String> {
+    let signature = hmac_sign_file(file_path.clone(), key)?;
+    let sidecar = SignatureFile {
+        algorithm: SIGNATURE_ALGORITHM.to_string(),
+        signed_at: chrono::Utc::now().to_rfc3339(),
+        signature,
+    };
+
+    let json = serde_json::to_string_pretty(&sidecar)
+        .map_err(|e| format!("Failed to serialize signature: {}", e))?;
+    let sig_path = signature_path(Path::new(&file_path));
+    fs::write(&sig_path, json).map_err(|e| format!("Failed to write signature file: {}", e))?;
+
+    info!("✅ Signature written: {}", sig_path.display());
+    Ok(sidecar)
+}
+
+/// Verify a file against its `<file>.sig` sidecar
+#[tauri::command]
+pub fn verify_signature_file(file_path: String, key: String) -> Result<bool, String> {
+    let sidecar = read_signature_file(Path::new(&file_path))?;
+
+    if sidecar.algorithm != SIGNATURE_ALGORITHM {
+        return Err(format!(
+            "Unsupported signature algorithm: {}",
+            sidecar.algorithm
+        ));
+    }
+
+    hmac_verify_file(file_path, key, sidecar.signature)
+}
+
+/// Base64 HMAC-SHA256 of an in-memory payload (webhook bodies)
+pub(crate) fn hmac_sign_bytes(data: &[u8], key: &[u8]) -> String {
+    let mac = mac_reader(data, key).expect("reading from a slice can't fail");
+    general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+}
+
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(SIGNATURE_EXTENSION);
+    PathBuf::from(name)
+}
+
+fn read_signature_file(path: &Path) -> Result<SignatureFile, String> {
+    let content = fs::read_to_string(signature_path(path))
+        .map_err(|e| format!("Failed to read signature file: {}", e))?;
+
+    serde_json::from_str(&content).map_err(|e| format!("Invalid signature file: {}", e))
+}
+
+fn decode_key(key: &str) -> Result<Vec<u8>, String> {
+    let key = general_purpose::STANDARD
+        .decode(key.trim())
+        .map_err(|e| format!("Invalid signing key encoding: {}", e))?;
+
+    if key.is_empty() {
+        return Err("Signing key must not be empty".to_string());
+    }
+    Ok(key)
+}
+
+/// Stream the file through the MAC so large archives aren't loaded into memory
+fn mac_file(path: &Path, key: &[u8]) -> Result<HmacSha256, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    mac_reader(BufReader::new(file), key).map_err(|e| format!("Failed to read file: {}", e))
+}
+
+fn mac_reader<R: Read>(mut reader: R, key: &[u8]) -> io::Result<HmacSha256> {
+    // HMAC accepts keys of any length, so new_from_slice can't fail here
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts any key length");
+    let mut buffer = vec![0u8; READ_BUFFER_SIZE];
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        mac.update(&buffer[..read]);
+    }
+
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn temp_file(name: &str, content: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "dealer-signing-test-{}-{}",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_rfc4231_vectors() {
+        // Test case 2: short key
+        let mac = mac_reader(&b"what do ya want for nothing?"[..], b"Jefe").unwrap();
+        assert_eq!(
+            hex(&mac.finalize().into_bytes()),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+
+        // Test case 6: key longer than the block size
+        let data = b"Test Using Larger Than Block-Size Key - Hash Key First";
+        let mac = mac_reader(&data[..], &[0xaa; 131]).unwrap();
+        assert_eq!(
+            hex(&mac.finalize().into_bytes()),
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54"
+        );
+    }
+
+    #[test]
+    fn test_sign_verify_and_tamper_detection() {
+        let path = temp_file("contract.pdf", b"%PDF-1.7 retail installment contract");
+        let file_path = path.to_string_lossy().to_string();
+        let key = general_purpose::STANDARD.encode(b"dealer-signing-key");
+
+        let signature = hmac_sign_file(file_path.clone(), key.clone()).unwrap();
+        assert!(hmac_verify_file(file_path.clone(), key.clone(), signature.clone()).unwrap());
+
+        let sidecar = write_signature_file(file_path.clone(), key.clone()).unwrap();
+        assert_eq!(sidecar.signature, signature);
+        assert!(verify_signature_file(file_path.clone(), key.clone()).unwrap());
+
+        // Wrong key and garbage signatures are rejected, not errors
+        let other_key = general_purpose::STANDARD.encode(b"another-key");
+        assert!(!hmac_verify_file(file_path.clone(), other_key, signature.clone()).unwrap());
+        assert!(!hmac_verify_file(file_path.clone(), key.clone(), "not base64!".into()).unwrap());
+
+        // A single changed byte invalidates the signature
+        fs::write(&path, b"%PDF-1.7 retail installment contracT").unwrap();
+        assert!(!hmac_verify_file(file_path.clone(), key.clone(), signature).unwrap());
+        assert!(!verify_signature_file(file_path, key).unwrap());
+
+        let _ = fs::remove_file(signature_path(&path));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,356 @@
+// src-tauri/src/sql_console.rs
+//
+// Read-only SQL console for support, so a one-off query on a dealer's machine doesn't mean
+// installing a database browser. Off unless the support_mode_enabled setting is "true".
+// Each query runs on its own read-only connection to the database file, and an authorizer
+// refuses anything that isn't reading: writes, schema changes, ATTACH, transactions and
+// pragmas outside a read-only list. Only one statement is accepted, it must start with
+// SELECT, WITH, EXPLAIN or PRAGMA, at most max_rows rows come back, and the command's
+// database budget (db_timeout.rs) stops runaway queries. Encrypted columns read as NULL.
+// Every query is written to the audit log with its SQL text before it runs.
+
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::hooks::{AuthAction, AuthContext, Authorization};
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit_log::record_audit;
+use crate::data_export::to_json;
+use crate::database::{db_get_setting, DbState};
+use crate::db_busy::DbError;
+use crate::db_timeout::watch_connection;
+use crate::sensitive_fields::ENCRYPTED_COLUMNS;
+use crate::telemetry::track;
+
+/// Settings key: "true" turns the console on
+pub const SUPPORT_MODE_SETTING: &str = "support_mode_enabled";
+pub const SUPPORT_MODE_DISABLED: &str = "The SQL console is only available in support mode";
+
+const DEFAULT_MAX_ROWS: usize = 100;
+const MAX_ROWS_LIMIT: usize = 1000;
+const ALLOWED_STATEMENTS: [&str; 4] = ["SELECT", "WITH", "EXPLAIN", "PRAGMA"];
+
+/// Pragmas that only report; the first group may name a table or index
+const PRAGMAS_WITH_ARGUMENT: &[&str] = &[
+    "table_info",
+    "table_xinfo",
+    "index_list",
+    "index_info",
+    "index_xinfo",
+    "foreign_key_list",
+    "foreign_key_check",
+    "integrity_check",
+    "quick_check",
+];
+const PRAGMAS_WITHOUT_ARGUMENT: &[&str] = &[
+    "table_list",
+    "database_list",
+    "compile_options",
+    "function_list",
+    "pragma_list",
+    "collation_list",
+    "user_version",
+    "schema_version",
+    "application_id",
+    "encoding",
+    "journal_mode",
+    "page_size",
+    "page_count",
+    "freelist_count",
+    "foreign_keys",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    /// One array per row, in column order
+    pub rows: Vec<Vec<Value>>,
+    /// More rows matched than max_rows
+    pub truncated: bool,
+    pub elapsed_ms: u64,
+}
+
+fn authorize(context: AuthContext<'_>) -> Authorization {
+    match context.action {
+        AuthAction::Select | AuthAction::Function { .. } | AuthAction::Recursive => {
+            Authorization::Allow
+        }
+        AuthAction::Read {
+            table_name,
+            column_name,
+        } => {
+            if ENCRYPTED_COLUMNS.contains(&(table_name, column_name)) {
+                // Reads as NULL
+                Authorization::Ignore
+            } else {
+                Authorization::Allow
+            }
+        }
+        AuthAction::Pragma {
+            pragma_name,
+            pragma_value,
+        } => {
+            let name = pragma_name.to_ascii_lowercase();
+            let allowed = PRAGMAS_WITH_ARGUMENT.contains(&name.as_str())
+                || (pragma_value.is_none() && PRAGMAS_WITHOUT_ARGUMENT.contains(&name.as_str()));
+            if allowed {
+                Authorization::Allow
+            } else {
+                Authorization::Deny
+            }
+        }
+        _ => Authorization::Deny,
+    }
+}
+
+/// A read-only connection to the database file with the console's authorizer
+pub(crate) fn open_console_connection(path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    conn.execute_batch("PRAGMA query_only = ON")?;
+    // Installed after the setup above, which it would refuse
+    conn.authorizer(Some(authorize));
+    watch_connection(&conn);
+    Ok(conn)
+}
+
+/// Run one read-only statement on a console connection
+pub(crate) fn run_query(
+    conn: &Connection,
+    sql: &str,
+    max_rows: usize,
+) -> Result<QueryResult, String> {
+    let keyword = sql
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_ascii_uppercase();
+    if !ALLOWED_STATEMENTS.contains(&keyword.as_str()) {
+        return Err(format!(
+            "Only {} statements can be run here",
+            ALLOWED_STATEMENTS.join(", ")
+        ));
+    }
+
+    let started = std::time::Instant::now();
+    let mut stmt = conn.prepare(sql).map_err(|e| match e {
+        rusqlite::Error::MultipleStatement => "Run one statement at a time".to_string(),
+        e => format!("Query refused: {}", e),
+    })?;
+    if !stmt.readonly() {
+        return Err("Query refused: only reads are allowed".to_string());
+    }
+    let columns: Vec<String> = stmt
+        .column_names()
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+
+    let mut rows = Vec::new();
+    let mut truncated = false;
+    let mut cursor = stmt.query([]).map_err(|e| e.to_string())?;
+    while let Some(row) = cursor.next().map_err(|e| e.to_string())? {
+        if rows.len() == max_rows {
+            truncated = true;
+            break;
+        }
+        let values = (0..columns.len())
+            .map(|i| row.get_ref(i).map(to_json))
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        rows.push(values);
+    }
+
+    Ok(QueryResult {
+        columns,
+        rows,
+        truncated,
+        elapsed_ms: started.elapsed().as_millis() as u64,
+    })
+}
+
+fn support_mode_enabled() -> Result<bool, String> {
+    Ok(db_get_setting(SUPPORT_MODE_SETTING.to_string())?
+        .is_some_and(|value| value.trim() == "true"))
+}
+
+/// Run a read-only query for support (support mode only). Returns the column names and up
+/// to max_rows rows (default 100, at most 1000)
+#[tauri::command]
+pub fn db_execute_readonly_query(
+    sql: String,
+    max_rows: Option<usize>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<QueryResult, DbError> {
+    track("db_execute_readonly_query", || {
+        let user_id_value = state.require_user(user_id)?;
+        if !support_mode_enabled()? {
+            warn!("⚠️  SQL console used outside support mode");
+            return Err(SUPPORT_MODE_DISABLED.to_string().into());
+        }
+        let max_rows = max_rows
+            .unwrap_or(DEFAULT_MAX_ROWS)
+            .clamp(1, MAX_ROWS_LIMIT);
+
+        let db = db.get().map_err(|e| e.to_string())?;
+        let path = {
+            let conn = db.conn();
+            record_audit(
+                &conn,
+                &user_id_value,
+                "support_query",
+                &json!({ "sql": sql, "max_rows": max_rows }),
+                Utc::now().timestamp_millis(),
+            )
+            .map_err(|e| e.to_string())?;
+            conn.path()
+                .filter(|path| !path.is_empty())
+                .map(str::to_string)
+                .ok_or_else(|| "The SQL console needs a database file".to_string())?
+        };
+
+        let conn = open_console_connection(Path::new(&path))
+            .map_err(|e| format!("Failed to open read-only connection: {}", e))?;
+        let result = run_query(&conn, &sql, max_rows)?;
+        info!(
+            "🔎 Support query returned {} rows in {}ms",
+            result.rows.len(),
+            result.elapsed_ms
+        );
+        Ok(result)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit_log::audit_entries;
+    use crate::database::{db_create_client, Database};
+    use crate::settings_cache::set_setting;
+    use crate::test_support::{make_client, temp_dir, TestApp, TEST_USER};
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn database_file(name: &str) -> (PathBuf, Database) {
+        let dir = temp_dir("sql-console", name);
+        let db = Database::init_with_path(&dir.join("dealer.db")).unwrap();
+        (dir, db)
+    }
+
+    #[test]
+    fn test_writes_attach_and_multiple_statements_are_refused() {
+        let (dir, db) = database_file("refused");
+        db.conn()
+            .execute_batch(
+                "PRAGMA foreign_keys = OFF;
+                 INSERT INTO settings (key, value, updated_at) VALUES ('theme', 'dark', 1);
+                 INSERT INTO deal_cobuyers (id, deal_id, user_id, first_name, last_name,
+                     ssn_encrypted, created_at, updated_at)
+                 VALUES ('cb1', 'd1', 'u1', 'Cara', 'Diaz', 'c2VjcmV0', 1, 1);
+                 PRAGMA foreign_keys = ON;",
+            )
+            .unwrap();
+        let conn = open_console_connection(&dir.join("dealer.db")).unwrap();
+
+        for sql in [
+            "UPDATE settings SET value = 'light'",
+            "DELETE FROM settings",
+            "INSERT INTO settings (key, value, updated_at) VALUES ('x', 'y', 1)",
+            "DROP TABLE settings",
+            "ATTACH DATABASE ':memory:' AS other",
+            "VACUUM",
+            // Past the keyword check, stopped by the authorizer or as a second statement
+            "WITH doomed AS (SELECT key FROM settings) DELETE FROM settings",
+            "SELECT 1; DELETE FROM settings",
+            "PRAGMA journal_mode = DELETE",
+            "PRAGMA writable_schema = ON",
+            "EXPLAIN DELETE FROM settings",
+        ] {
+            assert!(run_query(&conn, sql, 10).is_err(), "{} was allowed", sql);
+        }
+        let theme = run_query(&conn, "SELECT value FROM settings WHERE key = 'theme'", 10).unwrap();
+        assert_eq!(theme.rows, vec![vec![json!("dark")]]);
+
+        let tables = run_query(&conn, "PRAGMA table_info(settings)", 10).unwrap();
+        assert!(tables.columns.contains(&"name".to_string()));
+        let explained = run_query(&conn, "EXPLAIN QUERY PLAN SELECT * FROM clients", 10);
+        assert!(explained.is_ok(), "{:?}", explained);
+
+        let cobuyers = run_query(
+            &conn,
+            "SELECT first_name, ssn_encrypted FROM deal_cobuyers",
+            10,
+        )
+        .unwrap();
+        assert_eq!(cobuyers.rows, vec![vec![json!("Cara"), Value::Null]]);
+        drop(conn);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_row_cap_truncates_the_result() {
+        let (dir, _db) = database_file("row-cap");
+        let conn = open_console_connection(&dir.join("dealer.db")).unwrap();
+        let sql = "WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 50)
+                   SELECT x, 'row ' || x AS label FROM n";
+
+        let capped = run_query(&conn, sql, 20).unwrap();
+        assert_eq!(capped.columns, ["x", "label"]);
+        assert_eq!(capped.rows.len(), 20);
+        assert!(capped.truncated);
+        assert_eq!(capped.rows[19], vec![json!(20), json!("row 20")]);
+
+        let all = run_query(&conn, sql, 50).unwrap();
+        assert_eq!(all.rows.len(), 50);
+        assert!(!all.truncated);
+        drop(conn);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_console_needs_support_mode_and_audits_every_query() {
+        let (dir, db) = database_file("audited");
+        let app = TestApp::with_db(db);
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        let query = |sql: &str, max_rows| {
+            db_execute_readonly_query(sql.to_string(), max_rows, None, app.state(), app.db())
+        };
+
+        set_setting(SUPPORT_MODE_SETTING, "false").unwrap();
+        assert_eq!(
+            query("SELECT * FROM clients", None),
+            Err(DbError::from(SUPPORT_MODE_DISABLED.to_string()))
+        );
+
+        set_setting(SUPPORT_MODE_SETTING, "true").unwrap();
+        let clients = query("SELECT id, first_name FROM clients", Some(5)).unwrap();
+        assert_eq!(clients.rows, vec![vec![json!("c1"), json!("Jordan")]]);
+        assert!(query("DELETE FROM clients", None).is_err());
+        set_setting(SUPPORT_MODE_SETTING, "false").unwrap();
+
+        let audited: Vec<Value> = audit_entries(&app.conn(), TEST_USER, 10)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.action == "support_query")
+            .map(|entry| entry.details["sql"].clone())
+            .collect();
+        assert_eq!(audited.len(), 2);
+        assert!(audited.contains(&json!("SELECT id, first_name FROM clients")));
+        assert!(audited.contains(&json!("DELETE FROM clients")));
+        let count: i64 = app
+            .conn()
+            .query_row("SELECT COUNT(*) FROM clients", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,390 @@
+// src-tauri/src/startup_migrations.rs
+//
+// Schema migrations at startup. Before running any pending migration, Database::init copies
+// the database file into the backups folder; nothing else has it open yet, so a plain file
+// copy (after folding the WAL in) is a consistent snapshot. Migrations then run one by one,
+// each announced as a "migration-progress" event. The database opens during setup, before the
+// window's page can listen, so events go through a channel and a thread forwards them once
+// the main window exists. If a migration fails, the connection is closed, the copy is put
+// back and a "migration-failed" event describes what happened (get_migration_failure returns
+// the same for a page that loaded too late). A successful run is recorded in the
+// last_schema_migration setting for the database overview.
+
+use chrono::Utc;
+use log::{error, info, warn};
+use once_cell::sync::Lazy;
+use rusqlite::{Connection, Result as SqlResult};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::app_windows::MAIN_WINDOW_LABEL;
+use crate::database::{
+    read_setting, schema_version, upsert_setting, Database, LATEST_SCHEMA_VERSION,
+};
+
+/// Emitted as each migration starts, with a MigrationStep
+pub const SCHEMA_MIGRATION_PROGRESS_EVENT: &str = "migration-progress";
+/// Emitted when a migration failed, with a MigrationFailure
+pub const SCHEMA_MIGRATION_FAILED_EVENT: &str = "migration-failed";
+/// Settings key: the last successful migration run (MigrationRun as JSON)
+pub const LAST_MIGRATION_SETTING: &str = "last_schema_migration";
+
+/// How long the forwarder waits for the main window before emitting anyway
+const WINDOW_WAIT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MigrationStep {
+    pub version: i32,
+    /// What the migration does ("Add vehicle events")
+    pub step: String,
+    /// 1-based position among the migrations this run applies
+    pub index: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MigrationFailure {
+    /// The migration that failed; None if it failed before the first one started
+    pub version: Option<i32>,
+    pub step: Option<String>,
+    pub error: String,
+    /// The pre-migration copy; None for a new database (nothing to lose)
+    pub backup_path: Option<String>,
+    /// The copy was put back, so the database is as it was before startup
+    pub restored: bool,
+    pub restore_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationRun {
+    pub from_version: i32,
+    pub to_version: i32,
+    pub duration_ms: u64,
+    /// Unix ms
+    pub finished_at: i64,
+    pub backup_path: Option<String>,
+}
+
+enum MigrationEvent {
+    Step(MigrationStep),
+    Failed(MigrationFailure),
+}
+
+/// Where the next run_migrations sends its events; set by forward_migration_events
+static EVENTS: Lazy<Mutex<Option<Sender<MigrationEvent>>>> = Lazy::new(|| Mutex::new(None));
+static LAST_FAILURE: Lazy<Mutex<Option<MigrationFailure>>> = Lazy::new(|| Mutex::new(None));
+
+/// Send the next migration run's events to the main window. Call before the database is
+/// initialized; the forwarding thread ends when the run does
+pub fn forward_migration_events(app: AppHandle) {
+    let (sender, receiver) = mpsc::channel();
+    *EVENTS.lock().unwrap_or_else(PoisonError::into_inner) = Some(sender);
+    std::thread::spawn(move || {
+        let deadline = Instant::now() + WINDOW_WAIT;
+        for event in receiver {
+            while app.get_webview_window(MAIN_WINDOW_LABEL).is_none() && Instant::now() < deadline {
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            let emitted = match &event {
+                MigrationEvent::Step(step) => app.emit(SCHEMA_MIGRATION_PROGRESS_EVENT, step),
+                MigrationEvent::Failed(failure) => app.emit(SCHEMA_MIGRATION_FAILED_EVENT, failure),
+            };
+            if let Err(e) = emitted {
+                error!("❌ Failed to emit migration progress: {}", e);
+            }
+        }
+    });
+}
+
+/// "{path}{suffix}", for the WAL and shared-memory files next to a database
+fn sibling(db_path: &Path, suffix: &str) -> PathBuf {
+    let mut name = db_path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Copy the database file into backup_dir. The WAL is checkpointed first so the file alone
+/// holds everything
+fn backup_database(
+    conn: &Connection,
+    db_path: &Path,
+    backup_dir: &Path,
+    from_version: i32,
+) -> SqlResult<PathBuf> {
+    let io_error = |e: std::io::Error| {
+        rusqlite::Error::InvalidPath(format!("Failed to back up the database: {}", e).into())
+    };
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    fs::create_dir_all(backup_dir).map_err(io_error)?;
+    let stem = db_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("dealer");
+    let backup = backup_dir.join(format!(
+        "{}-before-migration-v{}-{}.db",
+        stem,
+        from_version,
+        Utc::now().format("%Y%m%d%H%M%S")
+    ));
+    fs::copy(db_path, &backup).map_err(io_error)?;
+    info!("💾 Backed up the database to {:?} before migrating", backup);
+    Ok(backup)
+}
+
+/// Put the backup in place of the database; its connection must be closed. The WAL and
+/// shared-memory files belong to the failed run, so they go first
+fn restore_backup(db_path: &Path, backup: &Path) -> Result<(), String> {
+    for suffix in ["-wal", "-shm"] {
+        let path = sibling(db_path, suffix);
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to remove {:?}: {}", path, e))?;
+        }
+    }
+    fs::copy(backup, db_path).map_err(|e| format!("Failed to restore {:?}: {}", backup, e))?;
+    Ok(())
+}
+
+/// Back up the database if `migrate` has anything to do, run it, and put the backup back if
+/// it fails. `migrate` reports each migration as it starts
+pub(crate) fn run_migrations(
+    db: Database,
+    db_path: &Path,
+    backup_dir: &Path,
+    migrate: impl FnOnce(&Database, &mut dyn FnMut(MigrationStep)) -> SqlResult<()>,
+) -> SqlResult<Database> {
+    // This run's events; dropping the sender ends the forwarder
+    let events = EVENTS.lock().unwrap_or_else(PoisonError::into_inner).take();
+    let send = |event: MigrationEvent| {
+        if let Some(events) = &events {
+            let _ = events.send(event);
+        }
+    };
+
+    let from_version = schema_version(&db.conn());
+    // A new database has nothing to lose
+    let backup = if from_version > 0 && from_version < LATEST_SCHEMA_VERSION {
+        Some(backup_database(
+            &db.conn(),
+            db_path,
+            backup_dir,
+            from_version,
+        )?)
+    } else {
+        None
+    };
+
+    let started = Instant::now();
+    let mut last_step: Option<MigrationStep> = None;
+    let result = migrate(&db, &mut |step| {
+        last_step = Some(step.clone());
+        send(MigrationEvent::Step(step));
+    });
+
+    match result {
+        Ok(()) => {
+            if last_step.is_some() {
+                let conn = db.conn();
+                let run = MigrationRun {
+                    from_version,
+                    to_version: schema_version(&conn),
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    finished_at: Utc::now().timestamp_millis(),
+                    backup_path: backup
+                        .as_ref()
+                        .map(|path| path.to_string_lossy().into_owned()),
+                };
+                info!(
+                    "✅ Migrated from version {} to {} in {}ms",
+                    run.from_version, run.to_version, run.duration_ms
+                );
+                let json = serde_json::to_string(&run)
+                    .map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                upsert_setting(&conn, LAST_MIGRATION_SETTING, &json, run.finished_at)?;
+            }
+            Ok(db)
+        }
+        Err(e) => {
+            error!("❌ Migration failed: {}", e);
+            // Close the connection before touching its files
+            drop(db);
+            let restore_error = backup
+                .as_deref()
+                .and_then(|backup| restore_backup(db_path, backup).err());
+            match (&backup, &restore_error) {
+                (Some(backup), None) => warn!("⚠️  Restored the database from {:?}", backup),
+                (Some(_), Some(restore_error)) => error!("❌ {}", restore_error),
+                (None, _) => {}
+            }
+            let failure = MigrationFailure {
+                version: last_step.as_ref().map(|step| step.version),
+                step: last_step.map(|step| step.step),
+                error: e.to_string(),
+                backup_path: backup
+                    .as_ref()
+                    .map(|path| path.to_string_lossy().into_owned()),
+                restored: backup.is_some() && restore_error.is_none(),
+                restore_error,
+            };
+            *LAST_FAILURE.lock().unwrap_or_else(PoisonError::into_inner) = Some(failure.clone());
+            send(MigrationEvent::Failed(failure));
+            Err(e)
+        }
+    }
+}
+
+/// The last successful migration run, for the database overview
+pub(crate) fn last_migration_run(conn: &Connection) -> Option<MigrationRun> {
+    read_setting(conn, LAST_MIGRATION_SETTING)
+        .ok()
+        .flatten()
+        .and_then(|json| serde_json::from_str(&json).ok())
+}
+
+/// Why this startup's migrations failed, if they did
+#[tauri::command]
+pub fn get_migration_failure() -> Option<MigrationFailure> {
+    LAST_FAILURE
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn files_in(dir: &Path) -> Vec<PathBuf> {
+        fs::read_dir(dir)
+            .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn test_pending_migrations_are_reported_and_recorded_after_a_backup() {
+        let dir = temp_dir("startup-migrations", "pending");
+        let path = dir.join("dealer.db");
+        let backups = dir.join("backups");
+        {
+            let db = Database::open(&path).unwrap();
+            db.migrate_to(LATEST_SCHEMA_VERSION - 2).unwrap();
+        }
+
+        let mut steps = Vec::new();
+        let db = run_migrations(
+            Database::open(&path).unwrap(),
+            &path,
+            &backups,
+            |db, progress| {
+                db.migrate_with_progress(i32::MAX, &mut |step| {
+                    steps.push(step.clone());
+                    progress(step);
+                })
+            },
+        )
+        .unwrap();
+
+        let versions: Vec<(i32, usize, usize)> = steps
+            .iter()
+            .map(|step| (step.version, step.index, step.total))
+            .collect();
+        assert_eq!(
+            versions,
+            [
+                (LATEST_SCHEMA_VERSION - 1, 1, 2),
+                (LATEST_SCHEMA_VERSION, 2, 2)
+            ]
+        );
+        let run = last_migration_run(&db.conn()).unwrap();
+        assert_eq!(run.from_version, LATEST_SCHEMA_VERSION - 2);
+        assert_eq!(run.to_version, LATEST_SCHEMA_VERSION);
+        let backup = PathBuf::from(run.backup_path.unwrap());
+        assert_eq!(files_in(&backups), [backup.clone()]);
+        // The copy is the database as it was: two migrations behind
+        let copy = Connection::open(&backup).unwrap();
+        assert_eq!(schema_version(&copy), LATEST_SCHEMA_VERSION - 2);
+
+        // Up to date: no backup, no run recorded
+        drop(db);
+        let db = Database::init_with_path(&path).unwrap();
+        assert_eq!(
+            last_migration_run(&db.conn()).unwrap().from_version,
+            LATEST_SCHEMA_VERSION - 2
+        );
+        assert_eq!(files_in(&backups).len(), 1);
+        drop(db);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_a_failed_migration_puts_the_backup_back() {
+        let dir = temp_dir("startup-migrations", "failed");
+        let path = dir.join("dealer.db");
+        let backups = dir.join("backups");
+        {
+            let db = Database::init_with_path(&path).unwrap();
+            db.conn()
+                .execute_batch(&format!(
+                    "INSERT INTO clients (id, first_name, last_name, created_at, updated_at, user_id)
+                         VALUES ('c1', 'Jane', 'Doe', 0, 0, 'u1');
+                     DELETE FROM schema_migrations WHERE version = {};",
+                    LATEST_SCHEMA_VERSION
+                ))
+                .unwrap();
+        }
+
+        // Half of a migration runs, then it fails
+        let err = run_migrations(
+            Database::open(&path).unwrap(),
+            &path,
+            &backups,
+            |db, progress| {
+                progress(MigrationStep {
+                    version: LATEST_SCHEMA_VERSION,
+                    step: "Broken".to_string(),
+                    index: 1,
+                    total: 1,
+                });
+                db.conn().execute_batch(
+                    "DELETE FROM clients;
+                 CREATE TABLE half_done (id TEXT);
+                 INSERT INTO no_such_table VALUES (1);",
+                )
+            },
+        )
+        .map(|_| ())
+        .unwrap_err();
+        assert!(err.to_string().contains("no_such_table"), "{}", err);
+
+        let failure = get_migration_failure().unwrap();
+        assert_eq!(failure.version, Some(LATEST_SCHEMA_VERSION));
+        assert_eq!(failure.step.as_deref(), Some("Broken"));
+        assert!(failure.restored, "{:?}", failure.restore_error);
+        assert_eq!(
+            files_in(&backups),
+            [PathBuf::from(failure.backup_path.unwrap())]
+        );
+
+        let conn = Connection::open(&path).unwrap();
+        let clients: i64 = conn
+            .query_row("SELECT COUNT(*) FROM clients", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(clients, 1);
+        let half_done: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM sqlite_master WHERE name = 'half_done'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(half_done, 0);
+        assert_eq!(schema_version(&conn), LATEST_SCHEMA_VERSION - 1);
+        drop(conn);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
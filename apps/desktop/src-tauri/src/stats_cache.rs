@@ -0,0 +1,878 @@
+// src-tauri/src/stats_cache.rs
+//
+// Dashboard figures from a cache instead of full-table aggregates on every focus. The
+// stats_cache table (migration 37) holds per-user buckets: deals by status, sold deals by
+// dealer-local month (sale, cost and down payments, as in db_get_deals_stats and the daily
+// desk log) and available vehicles by the day they were added. Triggers on deals,
+// vehicle_costs and vehicles mark the buckets a change touches in stats_dirty, in the
+// change's own transaction, so a rolled-back write leaves no mark and no write is missed,
+// whichever command or import made it. db_get_dashboard_snapshot recomputes the marked
+// buckets with the same queries a full rebuild uses, then reads the user's buckets in one
+// query. Inventory aging is bucketed from the per-day counts when read, since it moves with
+// the clock. A change of the dealer's time zone moves month boundaries, so the user's cache
+// is rebuilt from scratch; rebuild_stats_cache does the same on request.
+
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use chrono_tz::Tz;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::{begin_write, DbState, DEAL_STATUS_SOLD};
+use crate::datetime::{dealer_timezone, local_date, local_day_bounds};
+use crate::permissions::Role;
+use crate::telemetry::track;
+use crate::vehicle_costs::{SALE_CENTS_SQL, SOLD_AT_SQL, VEHICLE_COST_CENTS_SQL};
+
+const SECTION_STATUS: &str = "status";
+const SECTION_MONTH: &str = "month";
+const SECTION_STOCK: &str = "stock";
+
+/// Vehicle status of inventory on the lot
+const IN_STOCK_STATUS: &str = "available";
+
+pub(crate) const DAY_MS: i64 = 86_400_000;
+
+/// Inventory aging buckets, in whole days on the lot: (from, to inclusive; None = no limit)
+pub(crate) const AGING_BUCKETS: [(i64, Option<i64>); 4] =
+    [(0, Some(30)), (31, Some(60)), (61, Some(90)), (91, None)];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DealTotals {
+    pub total: i64,
+    pub by_status: BTreeMap<String, i64>,
+    /// Summed deal totals, all statuses
+    pub amount_cents: i64,
+}
+
+/// Sold deals in one dealer-local month
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthFigures {
+    pub month: String, // YYYY-MM
+    pub sold: i64,
+    pub sale_cents: i64,
+    /// None for roles that don't see costs
+    pub cost_cents: Option<i64>,
+    pub gross_profit_cents: Option<i64>,
+    /// Down payments on the month's sold deals (there's no payments ledger)
+    pub payments_cents: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgingBucket {
+    pub from_days: i64,
+    pub to_days: Option<i64>,
+    pub vehicles: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DashboardSnapshot {
+    pub deals: DealTotals,
+    /// Oldest first
+    pub months: Vec<MonthFigures>,
+    pub gross_profit_cents: Option<i64>,
+    pub payments_received_cents: i64,
+    pub in_stock: i64,
+    pub inventory_aging: Vec<AgingBucket>,
+    /// The zone months are cut in
+    pub timezone: String,
+    /// When the cache was last brought up to date (epoch millis)
+    pub computed_at: i64,
+}
+
+/// Whole days between the day numbers (days since 1970-01-01 UTC) of two timestamps, the
+/// way the triggers number them; a vehicle added "in the future" is 0 days old
+pub(crate) fn days_on_lot(added_day: i64, now: i64) -> i64 {
+    (now / DAY_MS - added_day).max(0)
+}
+
+pub(crate) fn aging_bucket_index(days: i64) -> usize {
+    AGING_BUCKETS
+        .iter()
+        .position(|&(_, to)| to.is_none_or(|to| days <= to))
+        .unwrap_or(AGING_BUCKETS.len() - 1)
+}
+
+/// Mark every bucket the user has data in, and drop their cached rows
+fn mark_all(conn: &Connection, user_id: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM stats_cache WHERE user_id = ?1",
+        params![user_id],
+    )?;
+    conn.execute(
+        &format!(
+            "INSERT OR IGNORE INTO stats_dirty (user_id, section, key)
+             SELECT user_id, '{status}', status FROM deals d WHERE d.user_id = ?1
+             UNION
+             SELECT user_id, '{month}', CAST({sold_at} AS TEXT) FROM deals d
+             WHERE d.user_id = ?1 AND d.status = ?2
+             UNION
+             SELECT user_id, '{stock}', CAST(created_at / {day} AS TEXT) FROM vehicles
+             WHERE user_id = ?1 AND status = ?3",
+            status = SECTION_STATUS,
+            month = SECTION_MONTH,
+            stock = SECTION_STOCK,
+            sold_at = SOLD_AT_SQL,
+            day = DAY_MS,
+        ),
+        params![user_id, DEAL_STATUS_SOLD, IN_STOCK_STATUS],
+    )?;
+    Ok(())
+}
+
+/// Replace a bucket's row, or remove it once the bucket is empty. values are count,
+/// amount_cents, sale_cents, cost_cents and payments_cents
+fn store_bucket(
+    conn: &Connection,
+    user_id: &str,
+    section: &str,
+    bucket: &str,
+    values: [i64; 5],
+) -> rusqlite::Result<()> {
+    if values[0] == 0 {
+        conn.execute(
+            "DELETE FROM stats_cache WHERE user_id = ?1 AND section = ?2 AND bucket = ?3",
+            params![user_id, section, bucket],
+        )?;
+    } else {
+        conn.execute(
+            "INSERT OR REPLACE INTO stats_cache
+                 (user_id, section, bucket, count, amount_cents, sale_cents, cost_cents,
+                  payments_cents)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                user_id, section, bucket, values[0], values[1], values[2], values[3], values[4]
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn recompute_status(conn: &Connection, user_id: &str, status: &str) -> rusqlite::Result<()> {
+    let (count, amount_cents) = conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(total_amount_cents), 0) FROM deals
+         WHERE user_id = ?1 AND status = ?2",
+        params![user_id, status],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    store_bucket(
+        conn,
+        user_id,
+        SECTION_STATUS,
+        status,
+        [count, amount_cents, 0, 0, 0],
+    )
+}
+
+/// month is the first day of the month
+fn recompute_month(
+    conn: &Connection,
+    user_id: &str,
+    month: NaiveDate,
+    tz: &Tz,
+) -> rusqlite::Result<()> {
+    let next = month
+        .checked_add_months(Months::new(1))
+        .unwrap_or(NaiveDate::MAX);
+    let (start, _) = local_day_bounds(month, tz);
+    let (end, _) = local_day_bounds(next, tz);
+    let (sold, sale_cents, cost_cents, payments_cents) = conn.query_row(
+        &format!(
+            "SELECT COUNT(*), COALESCE(SUM({sale}), 0), COALESCE(SUM({cost}), 0),
+                    COALESCE(SUM(d.down_payment_cents), 0)
+             FROM deals d
+             WHERE d.user_id = ?1 AND d.status = ?2 AND {sold_at} >= ?3 AND {sold_at} < ?4",
+            sale = SALE_CENTS_SQL,
+            cost = VEHICLE_COST_CENTS_SQL,
+            sold_at = SOLD_AT_SQL,
+        ),
+        params![user_id, DEAL_STATUS_SOLD, start, end],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+    )?;
+    store_bucket(
+        conn,
+        user_id,
+        SECTION_MONTH,
+        &month.format("%Y-%m").to_string(),
+        [sold, 0, sale_cents, cost_cents, payments_cents],
+    )
+}
+
+fn recompute_stock(conn: &Connection, user_id: &str, day: i64) -> rusqlite::Result<()> {
+    let count = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM vehicles
+             WHERE user_id = ?1 AND status = ?2 AND created_at / {day} = ?3",
+            day = DAY_MS,
+        ),
+        params![user_id, IN_STOCK_STATUS, day],
+        |row| row.get(0),
+    )?;
+    store_bucket(
+        conn,
+        user_id,
+        SECTION_STOCK,
+        &day.to_string(),
+        [count, 0, 0, 0, 0],
+    )
+}
+
+/// Recompute the user's dirty buckets (all of them when the zone changed or nothing is
+/// cached yet). Returns how many buckets were recomputed
+pub(crate) fn refresh_stats_cache(
+    conn: &Connection,
+    user_id: &str,
+    now: i64,
+) -> Result<usize, String> {
+    let tz = dealer_timezone(conn);
+    let cached_zone: Option<String> = conn
+        .query_row(
+            "SELECT timezone FROM stats_cache_meta WHERE user_id = ?1",
+            params![user_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let rebuild = cached_zone.as_deref() != Some(tz.name());
+    if !rebuild {
+        let dirty: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM stats_dirty WHERE user_id = ?1",
+                params![user_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if dirty == 0 {
+            return Ok(0);
+        }
+    }
+
+    let tx = begin_write(conn, "stats_cache").map_err(|e| e.to_string())?;
+    if rebuild {
+        mark_all(&tx, user_id).map_err(|e| e.to_string())?;
+    }
+    let dirty = {
+        let mut stmt = tx
+            .prepare("SELECT section, key FROM stats_dirty WHERE user_id = ?1")
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map(params![user_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| e.to_string())?;
+        rows.collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?
+    };
+
+    let mut statuses = BTreeSet::new();
+    let mut months = BTreeSet::new();
+    let mut days = BTreeSet::new();
+    for (section, key) in dirty {
+        match section.as_str() {
+            SECTION_STATUS => {
+                statuses.insert(key);
+            }
+            SECTION_MONTH => {
+                // Several sale dates usually fall in one month
+                let month = key
+                    .parse::<i64>()
+                    .ok()
+                    .and_then(|sold_at| local_date(sold_at, &tz))
+                    .and_then(|date| date.with_day(1));
+                months.extend(month);
+            }
+            SECTION_STOCK => {
+                days.extend(key.parse::<i64>().ok());
+            }
+            _ => {}
+        }
+    }
+    let recomputed = statuses.len() + months.len() + days.len();
+    for status in &statuses {
+        recompute_status(&tx, user_id, status).map_err(|e| e.to_string())?;
+    }
+    for month in months {
+        recompute_month(&tx, user_id, month, &tz).map_err(|e| e.to_string())?;
+    }
+    for day in days {
+        recompute_stock(&tx, user_id, day).map_err(|e| e.to_string())?;
+    }
+
+    tx.execute(
+        "DELETE FROM stats_dirty WHERE user_id = ?1",
+        params![user_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.execute(
+        "INSERT INTO stats_cache_meta (user_id, timezone, computed_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(user_id) DO UPDATE SET timezone = ?2, computed_at = ?3",
+        params![user_id, tz.name(), now],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    if rebuild {
+        info!(
+            "Rebuilt the statistics cache of {} ({} buckets, {})",
+            user_id,
+            recomputed,
+            tz.name()
+        );
+    }
+    Ok(recomputed)
+}
+
+/// The dashboard's figures from the user's cached buckets, refreshed first
+pub(crate) fn dashboard_snapshot(
+    conn: &Connection,
+    user_id: &str,
+    role: Role,
+    now: i64,
+) -> Result<DashboardSnapshot, String> {
+    refresh_stats_cache(conn, user_id, now)?;
+
+    let (timezone, computed_at) = conn
+        .query_row(
+            "SELECT timezone, computed_at FROM stats_cache_meta WHERE user_id = ?1",
+            params![user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT section, bucket, count, amount_cents, sale_cents, cost_cents, payments_cents
+             FROM stats_cache WHERE user_id = ?1 ORDER BY section, bucket",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(params![user_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                [
+                    row.get::<_, i64>(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                    row.get(5)?,
+                    row.get(6)?,
+                ],
+            ))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let sees_costs = role.sees_costs();
+    let mut deals = DealTotals::default();
+    let mut months = Vec::new();
+    let mut aging: Vec<AgingBucket> = AGING_BUCKETS
+        .iter()
+        .map(|&(from_days, to_days)| AgingBucket {
+            from_days,
+            to_days,
+            vehicles: 0,
+        })
+        .collect();
+    for row in rows {
+        let (section, bucket, [count, amount_cents, sale_cents, cost_cents, payments_cents]) =
+            row.map_err(|e| e.to_string())?;
+        match section.as_str() {
+            SECTION_STATUS => {
+                deals.total += count;
+                deals.amount_cents += amount_cents;
+                deals.by_status.insert(bucket, count);
+            }
+            SECTION_MONTH => months.push(MonthFigures {
+                month: bucket,
+                sold: count,
+                sale_cents,
+                cost_cents: sees_costs.then_some(cost_cents),
+                gross_profit_cents: sees_costs.then_some(sale_cents - cost_cents),
+                payments_cents,
+            }),
+            SECTION_STOCK => {
+                let day = bucket.parse::<i64>().map_err(|e| e.to_string())?;
+                aging[aging_bucket_index(days_on_lot(day, now))].vehicles += count;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(DashboardSnapshot {
+        gross_profit_cents: sees_costs.then(|| {
+            months
+                .iter()
+                .filter_map(|month| month.gross_profit_cents)
+                .sum()
+        }),
+        payments_received_cents: months.iter().map(|month| month.payments_cents).sum(),
+        in_stock: aging.iter().map(|bucket| bucket.vehicles).sum(),
+        deals,
+        months,
+        inventory_aging: aging,
+        timezone,
+        computed_at,
+    })
+}
+
+/// Deal counts by status, monthly sales and gross (managers and admins), payments received
+/// and inventory aging, from the statistics cache
+#[tauri::command]
+pub fn db_get_dashboard_snapshot(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<DashboardSnapshot, String> {
+    track("db_get_dashboard_snapshot", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        dashboard_snapshot(
+            &db.conn(),
+            &user_id_value,
+            state.role(),
+            Utc::now().timestamp_millis(),
+        )
+    })
+}
+
+/// Recompute the user's statistics cache from scratch; returns the number of buckets
+#[tauri::command]
+pub fn rebuild_stats_cache(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<usize, String> {
+    track("rebuild_stats_cache", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        conn.execute(
+            "DELETE FROM stats_cache_meta WHERE user_id = ?1",
+            params![user_id_value],
+        )
+        .map_err(|e| e.to_string())?;
+        refresh_stats_cache(&conn, &user_id_value, Utc::now().timestamp_millis())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::upsert_setting;
+    use crate::datetime::format_local;
+    use crate::test_support::{TestApp, TEST_USER};
+    use crate::vehicle_costs::gross_profit_by_month;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const OTHER_USER: &str = "other-user";
+    /// 2024-01-01T00:00:00Z
+    const START: i64 = 1_704_067_200_000;
+    const NOW: i64 = START + 400 * DAY_MS;
+    const STATUSES: [&str; 4] = ["quote", "pending", "sold", "cancelled"];
+    const ZONES: [&str; 3] = ["UTC", "America/New_York", "Asia/Kolkata"];
+
+    /// The snapshot computed straight from the tables, the way db_get_deals_stats does
+    fn from_scratch(conn: &Connection, user_id: &str) -> DashboardSnapshot {
+        let tz = dealer_timezone(conn);
+        let mut deals = DealTotals::default();
+        let mut stmt = conn
+            .prepare(
+                "SELECT status, COUNT(*), COALESCE(SUM(total_amount_cents), 0) FROM deals
+                 WHERE user_id = ?1 GROUP BY status",
+            )
+            .unwrap();
+        let rows = stmt
+            .query_map([user_id], |row| {
+                Ok((row.get::<_, String>(0)?, row.get(1)?, row.get::<_, i64>(2)?))
+            })
+            .unwrap();
+        for row in rows {
+            let (status, count, amount_cents) = row.unwrap();
+            deals.total += count;
+            deals.amount_cents += amount_cents;
+            deals.by_status.insert(status, count);
+        }
+
+        let mut payments: BTreeMap<String, i64> = BTreeMap::new();
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {}, COALESCE(d.down_payment_cents, 0) FROM deals d
+                 WHERE d.user_id = ?1 AND d.status = 'sold'",
+                SOLD_AT_SQL
+            ))
+            .unwrap();
+        let rows = stmt
+            .query_map([user_id], |row| Ok((row.get(0)?, row.get::<_, i64>(1)?)))
+            .unwrap();
+        for row in rows {
+            let (sold_at, cents) = row.unwrap();
+            *payments
+                .entry(format_local(sold_at, &tz, "%Y-%m"))
+                .or_default() += cents;
+        }
+        let months: Vec<MonthFigures> = gross_profit_by_month(conn, user_id, &tz)
+            .unwrap()
+            .into_iter()
+            .map(|month| MonthFigures {
+                payments_cents: payments[&month.month],
+                month: month.month,
+                sold: month.sold,
+                sale_cents: month.sale_cents,
+                cost_cents: Some(month.cost_cents),
+                gross_profit_cents: Some(month.gross_profit_cents),
+            })
+            .collect();
+
+        let mut aging: Vec<AgingBucket> = AGING_BUCKETS
+            .iter()
+            .map(|&(from_days, to_days)| AgingBucket {
+                from_days,
+                to_days,
+                vehicles: 0,
+            })
+            .collect();
+        let mut stmt = conn
+            .prepare("SELECT created_at FROM vehicles WHERE user_id = ?1 AND status = 'available'")
+            .unwrap();
+        let added = stmt
+            .query_map([user_id], |row| row.get::<_, i64>(0))
+            .unwrap();
+        for created_at in added {
+            // Calendar days (UTC), not 24-hour periods
+            let days = (NOW / DAY_MS - created_at.unwrap() / DAY_MS).max(0);
+            let bucket = aging
+                .iter_mut()
+                .find(|bucket| bucket.to_days.is_none_or(|to| days <= to))
+                .unwrap();
+            bucket.vehicles += 1;
+        }
+
+        DashboardSnapshot {
+            gross_profit_cents: Some(months.iter().map(|m| m.gross_profit_cents.unwrap()).sum()),
+            payments_received_cents: months.iter().map(|m| m.payments_cents).sum(),
+            in_stock: aging.iter().map(|bucket| bucket.vehicles).sum(),
+            deals,
+            months,
+            inventory_aging: aging,
+            timezone: tz.name().to_string(),
+            computed_at: 0,
+        }
+    }
+
+    fn assert_matches_scratch(conn: &Connection, step: usize) {
+        for user_id in [TEST_USER, OTHER_USER] {
+            let mut cached = dashboard_snapshot(conn, user_id, Role::Admin, NOW).unwrap();
+            cached.computed_at = 0;
+            assert_eq!(
+                cached,
+                from_scratch(conn, user_id),
+                "{} after step {}",
+                user_id,
+                step
+            );
+        }
+    }
+
+    /// A random row id of the table, if it has any
+    fn pick(conn: &Connection, rng: &mut StdRng, table: &str) -> Option<String> {
+        let count: i64 = conn
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        if count == 0 {
+            return None;
+        }
+        let offset = rng.random_range(0..count);
+        conn.query_row(
+            &format!("SELECT id FROM {} ORDER BY id LIMIT 1 OFFSET ?1", table),
+            [offset],
+            |row| row.get(0),
+        )
+        .ok()
+    }
+
+    fn random_time(rng: &mut StdRng) -> i64 {
+        START + rng.random_range(0..400 * DAY_MS)
+    }
+
+    fn insert_vehicle(conn: &Connection, rng: &mut StdRng, id: &str) {
+        let user_id = if rng.random_bool(0.8) {
+            TEST_USER
+        } else {
+            OTHER_USER
+        };
+        let status = ["available", "available", "pending", "sold"][rng.random_range(0..4)];
+        conn.execute(
+            "INSERT INTO vehicles (id, vin, year, make, model, mileage, price, cost, status,
+                 user_id, created_at, updated_at)
+             VALUES (?1, 'VIN-' || ?1, 2020, 'Toyota', 'Camry', 40000, 18500, NULL, ?2, ?3,
+                 ?4, ?4)",
+            params![id, status, user_id, random_time(rng)],
+        )
+        .unwrap();
+    }
+
+    fn insert_deal(conn: &Connection, rng: &mut StdRng, id: &str, vehicle_id: &str) {
+        let user_id = if rng.random_bool(0.8) {
+            TEST_USER
+        } else {
+            OTHER_USER
+        };
+        let sale_date = rng.random_bool(0.7).then(|| random_time(rng));
+        let sale_amount = rng
+            .random_bool(0.8)
+            .then(|| rng.random_range(5_000..40_000) as f64);
+        conn.execute(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount,
+                 sale_amount, sale_date, down_payment, document_ids, user_id, created_at,
+                 updated_at)
+             VALUES (?1, 'cash', 'client', ?2, ?3, ?4, ?5, ?6, ?7, '[]', ?8, ?9, ?9)",
+            params![
+                id,
+                vehicle_id,
+                STATUSES[rng.random_range(0..STATUSES.len())],
+                rng.random_range(5_000..40_000) as f64,
+                sale_amount,
+                sale_date,
+                rng.random_range(0..5_000) as f64,
+                user_id,
+                random_time(rng),
+            ],
+        )
+        .unwrap();
+    }
+
+    /// One random write, through plain SQL so every path the triggers see is covered
+    fn mutate(conn: &Connection, rng: &mut StdRng, step: usize) {
+        let deal = pick(conn, rng, "deals");
+        let vehicle = pick(conn, rng, "vehicles");
+        let cost = pick(conn, rng, "vehicle_costs");
+        match rng.random_range(0..13) {
+            0 | 1 => {
+                if let Some(vehicle) = vehicle {
+                    insert_deal(conn, rng, &format!("deal-{}", step), &vehicle);
+                }
+            }
+            2 => {
+                if let Some(deal) = deal {
+                    let status = STATUSES[rng.random_range(0..STATUSES.len())];
+                    conn.execute(
+                        "UPDATE deals SET status = ?2 WHERE id = ?1",
+                        params![deal, status],
+                    )
+                    .unwrap();
+                }
+            }
+            3 => {
+                if let Some(deal) = deal {
+                    let sale_date = rng.random_bool(0.8).then(|| random_time(rng));
+                    conn.execute(
+                        "UPDATE deals SET sale_date = ?2, sale_amount = ?3, total_amount = ?4,
+                             down_payment = ?5
+                         WHERE id = ?1",
+                        params![
+                            deal,
+                            sale_date,
+                            rng.random_range(5_000..40_000) as f64,
+                            rng.random_range(5_000..40_000) as f64,
+                            rng.random_range(0..5_000) as f64,
+                        ],
+                    )
+                    .unwrap();
+                }
+            }
+            4 => {
+                if let (Some(deal), Some(vehicle)) = (deal, vehicle) {
+                    conn.execute(
+                        "UPDATE deals SET vehicle_id = ?2 WHERE id = ?1",
+                        params![deal, vehicle],
+                    )
+                    .unwrap();
+                }
+            }
+            5 => {
+                if let Some(deal) = deal {
+                    conn.execute("DELETE FROM deals WHERE id = ?1", [deal])
+                        .unwrap();
+                }
+            }
+            6 => {
+                if let Some(vehicle) = vehicle {
+                    conn.execute(
+                        "INSERT INTO vehicle_costs (id, vehicle_id, user_id, category,
+                             amount_cents, incurred_at, created_at, updated_at)
+                         VALUES (?1, ?2, '', 'recon', ?3, ?4, ?4, ?4)",
+                        params![
+                            format!("cost-{}", step),
+                            vehicle,
+                            rng.random_range(0..1_500_000),
+                            random_time(rng)
+                        ],
+                    )
+                    .unwrap();
+                }
+            }
+            7 => {
+                if let Some(cost) = cost {
+                    if rng.random_bool(0.5) {
+                        conn.execute(
+                            "UPDATE vehicle_costs SET amount_cents = ?2 WHERE id = ?1",
+                            params![cost, rng.random_range(0..1_500_000)],
+                        )
+                        .unwrap();
+                    } else {
+                        conn.execute("DELETE FROM vehicle_costs WHERE id = ?1", [cost])
+                            .unwrap();
+                    }
+                }
+            }
+            8 => insert_vehicle(conn, rng, &format!("vehicle-{}", step)),
+            9 => {
+                if let Some(vehicle) = vehicle {
+                    let status = ["available", "pending", "sold"][rng.random_range(0..3)];
+                    conn.execute(
+                        "UPDATE vehicles SET status = ?2, created_at = ?3 WHERE id = ?1",
+                        params![vehicle, status, random_time(rng)],
+                    )
+                    .unwrap();
+                }
+            }
+            10 => {
+                // Deals hold on to their vehicle; costs go with it
+                if let Some(vehicle) = vehicle {
+                    conn.execute(
+                        "DELETE FROM vehicles WHERE id = ?1
+                         AND NOT EXISTS (SELECT 1 FROM deals WHERE vehicle_id = ?1)",
+                        [vehicle],
+                    )
+                    .unwrap();
+                }
+            }
+            11 => {
+                // Never committed
+                let tx = conn.unchecked_transaction().unwrap();
+                tx.execute("UPDATE deals SET status = 'sold', sale_date = ?1", [NOW])
+                    .unwrap();
+                tx.execute("DELETE FROM vehicle_costs", []).unwrap();
+                tx.rollback().unwrap();
+            }
+            _ => {
+                let zone = ZONES[rng.random_range(0..ZONES.len())];
+                upsert_setting(conn, "dealer_timezone", zone, NOW).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_cached_figures_match_a_recomputation_after_random_writes() {
+        let app = TestApp::new();
+        let conn = app.conn();
+        upsert_setting(&conn, "dealer_timezone", "America/New_York", NOW).unwrap();
+        conn.execute(
+            "INSERT INTO clients (id, first_name, last_name, created_at, updated_at)
+             VALUES ('client', 'Jordan', 'Client', ?1, ?1)",
+            [START],
+        )
+        .unwrap();
+        let mut rng = StdRng::seed_from_u64(406);
+        for i in 0..12 {
+            insert_vehicle(&conn, &mut rng, &format!("seed-vehicle-{:02}", i));
+        }
+        assert_matches_scratch(&conn, 0);
+
+        for step in 1..=600 {
+            mutate(&conn, &mut rng, step);
+            // Sometimes several writes pile up between two reads
+            if rng.random_bool(0.3) {
+                assert_matches_scratch(&conn, step);
+            }
+        }
+        assert_matches_scratch(&conn, 600);
+
+        let deals: i64 = conn
+            .query_row(
+                "SELECT COUNT(*) FROM deals WHERE status = 'sold'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(deals > 5, "the sequence should leave sold deals behind");
+    }
+
+    #[test]
+    fn test_reads_only_recompute_what_changed_and_hide_costs_from_sales() {
+        let app = TestApp::new();
+        let conn = app.conn();
+        upsert_setting(&conn, "dealer_timezone", "UTC", NOW).unwrap();
+        conn.execute(
+            "INSERT INTO clients (id, first_name, last_name, created_at, updated_at)
+             VALUES ('client', 'Jordan', 'Client', ?1, ?1)",
+            [START],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO vehicles (id, vin, year, make, model, mileage, price, cost, status,
+                 user_id, created_at, updated_at)
+             VALUES ('v1', 'VIN-v1', 2020, 'Toyota', 'Camry', 40000, 18500, NULL, 'available',
+                 ?1, ?2, ?2)",
+            params![TEST_USER, NOW - 45 * DAY_MS],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount,
+                 sale_amount, sale_date, down_payment, document_ids, user_id, created_at,
+                 updated_at)
+             VALUES ('d1', 'cash', 'client', 'v1', 'sold', 20000, 20000, ?1, 2500, '[]', ?2,
+                 ?1, ?1)",
+            params![START + 10 * DAY_MS, TEST_USER],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO vehicle_costs (id, vehicle_id, user_id, category, amount_cents,
+                 incurred_at, created_at, updated_at)
+             VALUES ('c1', 'v1', ?1, 'purchase', 1500000, ?2, ?2, ?2)",
+            params![TEST_USER, START],
+        )
+        .unwrap();
+
+        // First read builds the cache: a status, a month and a stock day
+        assert_eq!(refresh_stats_cache(&conn, TEST_USER, NOW).unwrap(), 3);
+        assert_eq!(refresh_stats_cache(&conn, TEST_USER, NOW).unwrap(), 0);
+        let snapshot = dashboard_snapshot(&conn, TEST_USER, Role::Manager, NOW).unwrap();
+        assert_eq!(snapshot.gross_profit_cents, Some(500_000));
+        assert_eq!(snapshot.payments_received_cents, 250_000);
+        assert_eq!(snapshot.inventory_aging[1].vehicles, 1);
+        assert_eq!(snapshot.computed_at, NOW);
+
+        // A recon cost only touches the deal's month
+        conn.execute(
+            "INSERT INTO vehicle_costs (id, vehicle_id, user_id, category, amount_cents,
+                 incurred_at, created_at, updated_at)
+             VALUES ('c2', 'v1', ?1, 'recon', 40000, ?2, ?2, ?2)",
+            params![TEST_USER, START],
+        )
+        .unwrap();
+        assert_eq!(refresh_stats_cache(&conn, TEST_USER, NOW + 1).unwrap(), 1);
+
+        let sales = dashboard_snapshot(&conn, TEST_USER, Role::Sales, NOW + 2).unwrap();
+        assert_eq!(sales.gross_profit_cents, None);
+        assert_eq!(sales.months[0].cost_cents, None);
+        assert_eq!(sales.months[0].gross_profit_cents, None);
+        assert_eq!(sales.months[0].sale_cents, 2_000_000);
+        assert_eq!(sales.computed_at, NOW + 1);
+
+        // A damaged cache is put right by a rebuild
+        conn.execute("UPDATE stats_cache SET sale_cents = 1", [])
+            .unwrap();
+        conn.execute("DELETE FROM stats_cache_meta", []).unwrap();
+        let rebuilt = dashboard_snapshot(&conn, TEST_USER, Role::Admin, NOW + 3).unwrap();
+        assert_eq!(rebuilt.gross_profit_cents, Some(460_000));
+        assert_eq!(rebuilt.months[0].sale_cents, 2_000_000);
+    }
+}
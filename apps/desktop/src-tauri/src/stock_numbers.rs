@@ -0,0 +1,407 @@
+// src-tauri/src/stock_numbers.rs
+//
+// Stock numbers are unique per user (migration 24: a unique index on
+// vehicles(user_id, stock_number) where stock_number is set). Writes that break it come back
+// as DbError::Duplicate { field: "stock_number" } instead of a raw constraint message.
+//
+// generate_next_stock_number hands out "<prefix><n>" from a per-user, per-prefix counter kept
+// in settings. The counter is read and bumped inside one write transaction, and numbers a
+// vehicle already uses (typed in by hand, imported) are skipped, so two windows asking at
+// once never get the same number. A number is used up once handed out, even if the vehicle
+// is never saved.
+//
+// Databases from before the index can already hold duplicates. Migration 24 keeps the
+// oldest vehicle's number, renames the later ones to "<number>-2", "<number>-3"... and
+// records each rename in stock_number_fixes, which get_stock_number_fixes reports.
+
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension, Result as SqlResult};
+use serde::Serialize;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::{begin_write, DbState};
+use crate::db_busy::DbError;
+use crate::telemetry::track;
+
+pub(crate) const STOCK_NUMBER_FIELD: &str = "stock_number";
+const SEQUENCE_SETTING_PREFIX: &str = "stock_number_sequence";
+const MAX_PREFIX_LEN: usize = 10;
+/// Digits in a generated number ("A0042"); longer numbers just grow past it
+const NUMBER_WIDTH: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StockNumberFix {
+    pub vehicle_id: String,
+    pub user_id: Option<String>,
+    pub old_stock_number: String,
+    pub new_stock_number: String,
+    pub fixed_at: i64,
+}
+
+/// Blank stock numbers are stored as NULL, so they never collide with each other
+pub(crate) fn normalize_stock_number(stock_number: Option<String>) -> Option<String> {
+    stock_number
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Turn a violation of the stock number index into DbError::Duplicate
+pub(crate) fn stock_number_error(error: rusqlite::Error) -> DbError {
+    let duplicate = match &error {
+        rusqlite::Error::SqliteFailure(e, Some(message)) => {
+            e.code == ErrorCode::ConstraintViolation && message.contains("vehicles.stock_number")
+        }
+        _ => false,
+    };
+    if duplicate {
+        DbError::Duplicate {
+            field: STOCK_NUMBER_FIELD.to_string(),
+        }
+    } else {
+        DbError::Other {
+            message: error.to_string(),
+        }
+    }
+}
+
+fn stock_number_taken(conn: &Connection, user_id: &str, stock_number: &str) -> SqlResult<bool> {
+    conn.prepare_cached("SELECT 1 FROM vehicles WHERE user_id = ?1 AND stock_number = ?2")?
+        .exists(params![user_id, stock_number])
+}
+
+fn validate_prefix(prefix: &str) -> Result<(), String> {
+    if prefix.len() > MAX_PREFIX_LEN {
+        return Err(format!(
+            "Stock number prefix can be at most {} characters",
+            MAX_PREFIX_LEN
+        ));
+    }
+    if !prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-')
+    {
+        return Err("Stock number prefix can only contain letters, digits and '-'".to_string());
+    }
+    Ok(())
+}
+
+fn sequence_key(user_id: &str, prefix: &str) -> String {
+    format!("{}:{}:{}", SEQUENCE_SETTING_PREFIX, user_id, prefix)
+}
+
+/// Bump the user's counter for `prefix` past any number already in use and return it
+fn next_stock_number(conn: &Connection, user_id: &str, prefix: &str) -> Result<String, DbError> {
+    let key = sequence_key(user_id, prefix);
+    let tx = begin_write(conn, "stock_numbers")?;
+    let mut last: u64 = tx
+        .query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            params![key],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .map(|value| value.parse().unwrap_or(0))
+        .unwrap_or(0);
+
+    let stock_number = loop {
+        last += 1;
+        let candidate = format!("{}{:0width$}", prefix, last, width = NUMBER_WIDTH);
+        if !stock_number_taken(&tx, user_id, &candidate).map_err(|e| e.to_string())? {
+            break candidate;
+        }
+    };
+
+    tx.execute(
+        "INSERT INTO settings (key, value, updated_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        params![key, last.to_string(), Utc::now().timestamp_millis()],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(stock_number)
+}
+
+/// Migration 24, before the unique index: blank numbers become NULL, and within each
+/// user's duplicates the oldest vehicle keeps the number while the rest get a suffix
+pub(crate) fn fix_duplicate_stock_numbers(conn: &Connection) -> SqlResult<Vec<StockNumberFix>> {
+    conn.execute(
+        "UPDATE vehicles SET stock_number = NULL WHERE TRIM(stock_number) = ''",
+        [],
+    )?;
+
+    let duplicates: Vec<(String, Option<String>, String)> = {
+        let mut stmt = conn.prepare(
+            "SELECT id, user_id, stock_number FROM (
+                 SELECT id, user_id, stock_number,
+                        ROW_NUMBER() OVER (PARTITION BY user_id, stock_number
+                                           ORDER BY created_at, rowid) AS n
+                 FROM vehicles WHERE stock_number IS NOT NULL)
+             WHERE n > 1
+             ORDER BY user_id, stock_number, n",
+        )?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))?;
+        rows.collect::<SqlResult<_>>()?
+    };
+
+    let fixed_at = Utc::now().timestamp_millis();
+    let mut fixes = Vec::with_capacity(duplicates.len());
+    for (vehicle_id, user_id, old_stock_number) in duplicates {
+        let mut suffix = 2;
+        let new_stock_number = loop {
+            let candidate = format!("{}-{}", old_stock_number, suffix);
+            let taken = conn
+                .prepare_cached("SELECT 1 FROM vehicles WHERE user_id IS ?1 AND stock_number = ?2")?
+                .exists(params![user_id, candidate])?;
+            if !taken {
+                break candidate;
+            }
+            suffix += 1;
+        };
+        conn.execute(
+            "UPDATE vehicles SET stock_number = ?2 WHERE id = ?1",
+            params![vehicle_id, new_stock_number],
+        )?;
+        warn!(
+            "⚠️  Duplicate stock number {} on vehicle {}: renamed to {}",
+            old_stock_number, vehicle_id, new_stock_number
+        );
+        fixes.push(StockNumberFix {
+            vehicle_id,
+            user_id,
+            old_stock_number,
+            new_stock_number,
+            fixed_at,
+        });
+    }
+    Ok(fixes)
+}
+
+/// Migration 24, once stock_number_fixes exists
+pub(crate) fn record_stock_number_fixes(
+    conn: &Connection,
+    fixes: &[StockNumberFix],
+) -> SqlResult<()> {
+    let mut stmt = conn.prepare(
+        "INSERT INTO stock_number_fixes
+             (vehicle_id, user_id, old_stock_number, new_stock_number, fixed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for fix in fixes {
+        stmt.execute(params![
+            fix.vehicle_id,
+            fix.user_id,
+            fix.old_stock_number,
+            fix.new_stock_number,
+            fix.fixed_at
+        ])?;
+    }
+    Ok(())
+}
+
+fn list_fixes(conn: &Connection, user_id: &str) -> SqlResult<Vec<StockNumberFix>> {
+    let mut stmt = conn.prepare(
+        "SELECT vehicle_id, user_id, old_stock_number, new_stock_number, fixed_at
+         FROM stock_number_fixes WHERE user_id = ?1
+         ORDER BY old_stock_number, new_stock_number",
+    )?;
+    let rows = stmt.query_map(params![user_id], |row| {
+        Ok(StockNumberFix {
+            vehicle_id: row.get(0)?,
+            user_id: row.get(1)?,
+            old_stock_number: row.get(2)?,
+            new_stock_number: row.get(3)?,
+            fixed_at: row.get(4)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Next free stock number for the user, e.g. prefix "A" gives "A0001", "A0002"...
+#[tauri::command]
+pub fn generate_next_stock_number(
+    user_id: Option<String>,
+    prefix: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<String, DbError> {
+    track("generate_next_stock_number", || {
+        let user_id_value = state.require_user(user_id)?;
+        let prefix = prefix.unwrap_or_default().trim().to_uppercase();
+        validate_prefix(&prefix)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let stock_number = next_stock_number(&conn, &user_id_value, &prefix)?;
+        info!("✅ Stock number generated: {}", stock_number);
+        Ok(stock_number)
+    })
+}
+
+/// Stock numbers migration 24 renamed because another vehicle already had them
+#[tauri::command]
+pub fn get_stock_number_fixes(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<Vec<StockNumberFix>, String> {
+    track("get_stock_number_fixes", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        list_fixes(&conn, &user_id_value).map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{db_create_vehicle, db_update_vehicle, Vehicle};
+    use crate::test_support::{make_vehicle, seeded_db_at, TestApp, TEST_USER};
+    use serde_json::json;
+
+    fn vehicle_with_stock(id: &str, stock_number: &str) -> Vehicle {
+        let mut vehicle = make_vehicle(id);
+        vehicle.stock_number = Some(stock_number.to_string());
+        vehicle
+    }
+
+    #[test]
+    fn test_duplicate_stock_number_is_a_typed_error() {
+        let app = TestApp::new();
+        db_create_vehicle(vehicle_with_stock("v1", "A100"), app.state(), app.db()).unwrap();
+
+        let err =
+            db_create_vehicle(vehicle_with_stock("v2", "A100"), app.state(), app.db()).unwrap_err();
+        assert_eq!(
+            err,
+            DbError::Duplicate {
+                field: "stock_number".to_string()
+            }
+        );
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            json!({ "kind": "duplicate", "field": "stock_number" })
+        );
+
+        // Blank numbers aren't numbers, so any number of vehicles can have one
+        db_create_vehicle(vehicle_with_stock("v3", " "), app.state(), app.db()).unwrap();
+        db_create_vehicle(vehicle_with_stock("v4", ""), app.state(), app.db()).unwrap();
+
+        // Updating onto a number in use fails too, and leaves the vehicle alone
+        let err = db_update_vehicle(
+            "v3".to_string(),
+            json!({ "stock_number": "A100" }),
+            app.state(),
+            app.db(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, DbError::Duplicate { .. }));
+        let stored: Option<String> = app
+            .conn()
+            .query_row(
+                "SELECT stock_number FROM vehicles WHERE id = 'v3'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(stored, None);
+
+        // Another user can use the same number
+        app.sign_in("other-user");
+        db_create_vehicle(vehicle_with_stock("v5", "A100"), app.state(), app.db()).unwrap();
+    }
+
+    #[test]
+    fn test_generator_skips_numbers_in_use() {
+        let app = TestApp::new();
+        let next = |prefix: &str| {
+            generate_next_stock_number(None, Some(prefix.to_string()), app.state(), app.db())
+                .unwrap()
+        };
+        assert_eq!(next("a"), "A0001");
+        assert_eq!(next("A"), "A0002");
+        // Each prefix has its own sequence
+        assert_eq!(next(""), "0001");
+
+        // Numbers typed in by hand are skipped
+        db_create_vehicle(vehicle_with_stock("v1", "A0003"), app.state(), app.db()).unwrap();
+        db_create_vehicle(vehicle_with_stock("v2", "A0004"), app.state(), app.db()).unwrap();
+        assert_eq!(next("A"), "A0005");
+
+        // Each user has their own sequence
+        app.sign_in("other-user");
+        assert_eq!(next("A"), "A0001");
+
+        let err =
+            generate_next_stock_number(None, Some("BAD PREFIX".to_string()), app.state(), app.db())
+                .unwrap_err();
+        assert!(matches!(err, DbError::Other { .. }));
+    }
+
+    #[test]
+    fn test_migration_suffixes_legacy_duplicates() {
+        let db = seeded_db_at(23);
+        {
+            let conn = db.conn();
+            let insert = |id: &str, user: &str, stock: &str, created_at: i64| {
+                conn.execute(
+                    "INSERT INTO vehicles (id, user_id, vin, stock_number, year, make, model,
+                         mileage, price, status, created_at, updated_at)
+                     VALUES (?1, ?2, ?1, ?3, 2019, 'Ford', 'F-150', 10, 30000, 'available',
+                         ?4, ?4)",
+                    params![id, user, stock, created_at],
+                )
+                .unwrap();
+            };
+            insert("oldest", TEST_USER, "B7", 1);
+            insert("newer", TEST_USER, "B7", 2);
+            insert("newest", TEST_USER, "B7", 3);
+            // Already holds the first suffix, so "newer" has to skip it
+            insert("suffixed", TEST_USER, "B7-2", 4);
+            insert("other-user", "other-user", "B7", 5);
+            insert("blank-1", TEST_USER, "", 6);
+            insert("blank-2", TEST_USER, "", 7);
+        }
+        db.migrate_to(i32::MAX).unwrap();
+
+        let app = TestApp::with_db(db);
+        let stock = |id: &str| -> Option<String> {
+            app.conn()
+                .query_row(
+                    "SELECT stock_number FROM vehicles WHERE id = ?1",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        assert_eq!(stock("oldest").as_deref(), Some("B7"));
+        assert_eq!(stock("newer").as_deref(), Some("B7-3"));
+        assert_eq!(stock("newest").as_deref(), Some("B7-4"));
+        assert_eq!(stock("suffixed").as_deref(), Some("B7-2"));
+        assert_eq!(stock("other-user").as_deref(), Some("B7"));
+        assert_eq!(stock("blank-1"), None);
+
+        let fixes = get_stock_number_fixes(None, app.state(), app.db()).unwrap();
+        let renamed: Vec<(&str, &str, &str)> = fixes
+            .iter()
+            .map(|f| {
+                (
+                    f.vehicle_id.as_str(),
+                    f.old_stock_number.as_str(),
+                    f.new_stock_number.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            renamed,
+            vec![("newer", "B7", "B7-3"), ("newest", "B7", "B7-4")]
+        );
+
+        // The index is in place from here on
+        let err =
+            db_create_vehicle(vehicle_with_stock("v1", "B7"), app.state(), app.db()).unwrap_err();
+        assert!(matches!(err, DbError::Duplicate { .. }));
+    }
+}
@@ -5,7 +5,11 @@
 
 use dirs;
 use log::{error, info};
-use std::path::PathBuf;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tauri::command;
 use tauri_plugin_dialog::DialogExt;
 
@@ -241,6 +245,25 @@ pub fn get_backup_path() -> Result<String, String> {
         .map(|s| s.to_string())
 }
 
+/// Get the document templates path (fillable PDFs installed from template packs)
+#[command]
+pub fn get_templates_path() -> Result<String, String> {
+    let data_dir = get_app_data_dir()?;
+    let templates_path = data_dir.join("templates");
+
+    // Create directory if it doesn't exist
+    if !templates_path.exists() {
+        std::fs::create_dir_all(&templates_path)
+            .map_err(|e| format!("Failed to create templates directory: {}", e))?;
+        info!("Created templates directory: {:?}", templates_path);
+    }
+
+    templates_path
+        .to_str()
+        .ok_or_else(|| "Invalid path encoding".to_string())
+        .map(|s| s.to_string())
+}
+
 /// Get all storage paths
 #[command]
 pub fn get_all_storage_paths() -> Result<serde_json::Value, String> {
@@ -250,6 +273,7 @@ pub fn get_all_storage_paths() -> Result<serde_json::Value, String> {
         "cache": get_cache_path()?,
         "logs": get_logs_path()?,
         "backup": get_backup_path()?,
+        "templates": get_templates_path()?,
     });
 
     Ok(paths)
@@ -266,7 +290,7 @@ pub fn cleanup_cache() -> Result<String, String> {
     }
 
     // Get cache size before cleanup
-    let size_before = get_directory_size(&path)?;
+    let size_before = get_directory_size(&path).bytes;
 
     // Remove files older than 30 days
     let cutoff_time = std::time::SystemTime::now()
@@ -275,18 +299,42 @@ pub fn cleanup_cache() -> Result<String, String> {
     let mut removed_count = 0;
     let mut failed_count = 0;
 
-    if let Ok(entries) = std::fs::read_dir(&path) {
+    // Recurses into subfolders such as thumbnails/
+    remove_old_cache_files(&path, cutoff_time, &mut removed_count, &mut failed_count);
+
+    let size_after = get_directory_size(&path).bytes;
+    let freed = size_before.saturating_sub(size_after);
+    invalidate_storage_stats();
+
+    Ok(format!(
+        "Removed {} files, {} failed. Freed {} bytes.",
+        removed_count, failed_count, freed
+    ))
+}
+
+/// Remove files older than the cutoff, descending into subdirectories
+fn remove_old_cache_files(
+    dir: &PathBuf,
+    cutoff_time: std::time::SystemTime,
+    removed_count: &mut usize,
+    failed_count: &mut usize,
+) {
+    if let Ok(entries) = std::fs::read_dir(dir) {
         for entry in entries.flatten() {
             if let Ok(metadata) = entry.metadata() {
+                if metadata.is_dir() {
+                    remove_old_cache_files(&entry.path(), cutoff_time, removed_count, failed_count);
+                    continue;
+                }
                 if let Ok(modified) = metadata.modified() {
                     if modified < cutoff_time {
                         match std::fs::remove_file(entry.path()) {
                             Ok(_) => {
-                                removed_count += 1;
+                                *removed_count += 1;
                                 info!("Removed old cache file: {:?}", entry.path());
                             }
                             Err(e) => {
-                                failed_count += 1;
+                                *failed_count += 1;
                                 error!("Failed to remove cache file: {:?} - {}", entry.path(), e);
                             }
                         }
@@ -295,49 +343,215 @@ pub fn cleanup_cache() -> Result<String, String> {
             }
         }
     }
+}
 
-    let size_after = get_directory_size(&path)?;
-    let freed = size_before.saturating_sub(size_after);
+/// How long computed storage stats are served from cache
+const STORAGE_STATS_TTL: Duration = Duration::from_secs(5 * 60);
 
-    Ok(format!(
-        "Removed {} files, {} failed. Freed {} bytes.",
-        removed_count, failed_count, freed
-    ))
+static STORAGE_STATS_CACHE: Lazy<Mutex<Option<(Instant, StorageStats)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+/// Size of a directory tree; `partial` is set when some entries couldn't be read
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DirectoryUsage {
+    pub bytes: u64,
+    pub file_count: u64,
+    pub partial: bool,
+    pub error_count: u64,
 }
 
-/// Get directory size in bytes
-fn get_directory_size(path: &PathBuf) -> Result<u64, String> {
-    let mut size: u64 = 0;
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageStats {
+    pub database: DirectoryUsage,
+    pub documents: DirectoryUsage,
+    pub cache: DirectoryUsage,
+    pub logs: DirectoryUsage,
+    /// Unix timestamp (ms) of when the walk ran, so the UI can show staleness
+    pub computed_at: i64,
+}
 
-    if let Ok(entries) = std::fs::read_dir(path) {
-        for entry in entries.flatten() {
-            if let Ok(metadata) = entry.metadata() {
-                if metadata.is_file() {
-                    size += metadata.len();
-                } else if metadata.is_dir() {
-                    size += get_directory_size(&entry.path())?;
-                }
+/// Get directory size in bytes (a plain file reports its own size)
+fn get_directory_size(path: &Path) -> DirectoryUsage {
+    let mut usage = DirectoryUsage::default();
+
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => {
+            usage.bytes = metadata.len();
+            usage.file_count = 1;
+        }
+        Ok(_) => add_directory_size(path, &mut usage),
+        // Not created yet - nothing to count
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            error!("Failed to read {:?}: {}", path, e);
+            usage.error_count += 1;
+        }
+    }
+
+    usage.partial = usage.error_count > 0;
+    usage
+}
+
+fn add_directory_size(path: &Path, usage: &mut DirectoryUsage) {
+    let entries = match std::fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            error!("Failed to read directory {:?}: {}", path, e);
+            usage.error_count += 1;
+            return;
+        }
+    };
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                error!("Failed to read entry in {:?}: {}", path, e);
+                usage.error_count += 1;
+                continue;
+            }
+        };
+
+        match entry.metadata() {
+            Ok(metadata) if metadata.is_file() => {
+                usage.bytes += metadata.len();
+                usage.file_count += 1;
+            }
+            Ok(metadata) if metadata.is_dir() => add_directory_size(&entry.path(), usage),
+            Ok(_) => {}
+            Err(e) => {
+                error!("Failed to read metadata for {:?}: {}", entry.path(), e);
+                usage.error_count += 1;
             }
         }
     }
+}
 
-    Ok(size)
+/// Drop cached storage stats (call after writing or deleting documents)
+pub fn invalidate_storage_stats() {
+    if let Ok(mut cache) = STORAGE_STATS_CACHE.lock() {
+        *cache = None;
+    }
 }
 
-/// Get storage usage statistics
-#[command]
-pub fn get_storage_stats() -> Result<serde_json::Value, String> {
+fn compute_storage_stats() -> Result<StorageStats, String> {
     let database_path = PathBuf::from(get_database_path()?);
     let documents_path = PathBuf::from(get_documents_storage_path()?);
     let cache_path = PathBuf::from(get_cache_path()?);
     let logs_path = PathBuf::from(get_logs_path()?);
 
-    let stats = serde_json::json!({
-        "database_size": get_directory_size(&database_path).unwrap_or(0),
-        "documents_size": get_directory_size(&documents_path).unwrap_or(0),
-        "cache_size": get_directory_size(&cache_path).unwrap_or(0),
-        "logs_size": get_directory_size(&logs_path).unwrap_or(0),
-    });
+    Ok(StorageStats {
+        database: get_directory_size(&database_path),
+        documents: get_directory_size(&documents_path),
+        cache: get_directory_size(&cache_path),
+        logs: get_directory_size(&logs_path),
+        computed_at: chrono::Utc::now().timestamp_millis(),
+    })
+}
+
+fn cached_storage_stats(force_refresh: bool) -> Option<StorageStats> {
+    if force_refresh {
+        return None;
+    }
+
+    let cache = STORAGE_STATS_CACHE.lock().ok()?;
+    match cache.as_ref() {
+        Some((cached_at, stats)) if cached_at.elapsed() < STORAGE_STATS_TTL => Some(stats.clone()),
+        _ => None,
+    }
+}
+
+/// Get storage usage statistics
+/// The directory walk runs on a blocking thread and is cached for a few minutes
+#[command]
+pub async fn get_storage_stats(force_refresh: Option<bool>) -> Result<StorageStats, String> {
+    if let Some(stats) = cached_storage_stats(force_refresh.unwrap_or(false)) {
+        return Ok(stats);
+    }
+
+    let stats = tauri::async_runtime::spawn_blocking(compute_storage_stats)
+        .await
+        .map_err(|e| format!("Storage stats task failed: {}", e))??;
 
+    if let Ok(mut cache) = STORAGE_STATS_CACHE.lock() {
+        *cache = Some((Instant::now(), stats.clone()));
+    }
+
+    info!(
+        "Storage stats computed: {} document files ({} bytes)",
+        stats.documents.file_count, stats.documents.bytes
+    );
     Ok(stats)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn build_tree(root: &Path, depth: usize, files_per_dir: usize) -> (u64, u64) {
+        std::fs::create_dir_all(root).unwrap();
+        let mut bytes = 0;
+        let mut files = 0;
+
+        for i in 0..files_per_dir {
+            let content = vec![b'x'; (i + 1) * 10];
+            std::fs::write(root.join(format!("doc-{}.pdf", i)), &content).unwrap();
+            bytes += content.len() as u64;
+            files += 1;
+        }
+
+        if depth > 0 {
+            for branch in 0..2 {
+                let (b, f) = build_tree(&root.join(format!("d{}", branch)), depth - 1, files_per_dir);
+                bytes += b;
+                files += f;
+            }
+        }
+
+        (bytes, files)
+    }
+
+    #[test]
+    fn test_directory_size_on_deep_tree() {
+        let root = temp_dir("storage", "deep-tree");
+
+        let (bytes, files) = build_tree(&root, 6, 5);
+        let usage = get_directory_size(&root);
+
+        assert_eq!(
+            usage,
+            DirectoryUsage {
+                bytes,
+                file_count: files,
+                partial: false,
+                error_count: 0,
+            }
+        );
+
+        // A single file reports itself; a missing path is empty, not an error
+        let single = get_directory_size(&root.join("doc-0.pdf"));
+        assert_eq!((single.bytes, single.file_count), (10, 1));
+        assert_eq!(get_directory_size(&root.join("missing")), DirectoryUsage::default());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_stats_cache_invalidation() {
+        let stats = StorageStats {
+            database: DirectoryUsage::default(),
+            documents: DirectoryUsage::default(),
+            cache: DirectoryUsage::default(),
+            logs: DirectoryUsage::default(),
+            computed_at: 42,
+        };
+        *STORAGE_STATS_CACHE.lock().unwrap() = Some((Instant::now(), stats));
+
+        assert_eq!(cached_storage_stats(false).map(|s| s.computed_at), Some(42));
+        assert!(cached_storage_stats(true).is_none());
+
+        invalidate_storage_stats();
+        assert!(cached_storage_stats(false).is_none());
+    }
+}
@@ -0,0 +1,331 @@
+// src-tauri/src/storage_usage.rs
+//
+// Per-user storage accounting and quotas for shared machines
+// Usage is cached in the storage_usage table and updated as documents come and go
+
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::database::get_db;
+use crate::disk_space::DiskSpaceError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StorageUsage {
+    pub user_id: String,
+    pub used_bytes: u64,
+    /// None = unlimited
+    pub quota_bytes: Option<u64>,
+    pub updated_at: i64,
+}
+
+/// Get cached storage usage and quota for a user
+#[tauri::command]
+pub fn get_storage_usage(user_id: String) -> Result<StorageUsage, String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    let conn = db.conn();
+
+    usage_for(&conn, &user_id).map_err(|e| e.to_string())
+}
+
+/// Set (or clear with None) a user's storage quota in bytes
+#[tauri::command]
+pub fn set_storage_quota(user_id: String, bytes: Option<u64>) -> Result<StorageUsage, String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    let conn = db.conn();
+
+    conn.execute(
+        "INSERT INTO storage_usage (user_id, used_bytes, quota_bytes, updated_at)
+         VALUES (?1, 0, ?2, ?3)
+         ON CONFLICT(user_id) DO UPDATE SET quota_bytes = ?2, updated_at = ?3",
+        params![
+            user_id,
+            bytes.map(to_sql_bytes),
+            Utc::now().timestamp_millis()
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    info!("✅ Storage quota for {} set to {:?}", user_id, bytes);
+    usage_for(&conn, &user_id).map_err(|e| e.to_string())
+}
+
+/// Rebuild the usage cache from the files on disk (use when it drifts)
+#[tauri::command]
+pub fn recompute_storage_usage() -> Result<Vec<StorageUsage>, String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    let mut conn = db.conn();
+
+    rebuild_usage(&mut conn).map_err(|e| e.to_string())
+}
+
+/// Owner of a deal's documents (documents inherit the deal's user)
+pub(crate) fn deal_owner(conn: &Connection, deal_id: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT user_id FROM deals WHERE id = ?1",
+        params![deal_id],
+        |row| row.get::<_, Option<String>>(0),
+    )
+    .optional()
+    .map(Option::flatten)
+}
+
+/// Fail with QuotaExceeded if adding incoming_bytes would exceed the user's quota
+pub(crate) fn check_quota(
+    conn: &Connection,
+    user_id: &str,
+    incoming_bytes: u64,
+) -> Result<(), DiskSpaceError> {
+    let usage = usage_for(conn, user_id).map_err(|e| DiskSpaceError::from(e.to_string()))?;
+
+    if let Some(quota_bytes) = usage.quota_bytes {
+        if usage.used_bytes.saturating_add(incoming_bytes) > quota_bytes {
+            warn!(
+                "⚠️  Storage quota exceeded for {}: {} + {} > {}",
+                user_id, usage.used_bytes, incoming_bytes, quota_bytes
+            );
+            return Err(DiskSpaceError::QuotaExceeded {
+                user_id: user_id.to_string(),
+                used_bytes: usage.used_bytes,
+                quota_bytes,
+                incoming_bytes,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Apply an incremental change to a user's cached usage (never drops below zero)
+pub(crate) fn adjust_usage(conn: &Connection, user_id: &str, delta: i64) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO storage_usage (user_id, used_bytes, quota_bytes, updated_at)
+         VALUES (?1, MAX(?2, 0), NULL, ?3)
+         ON CONFLICT(user_id) DO UPDATE SET
+            used_bytes = MAX(used_bytes + ?2, 0), updated_at = ?3",
+        params![user_id, delta, Utc::now().timestamp_millis()],
+    )?;
+    Ok(())
+}
+
+fn usage_for(conn: &Connection, user_id: &str) -> rusqlite::Result<StorageUsage> {
+    let usage = conn
+        .query_row(
+            "SELECT used_bytes, quota_bytes, updated_at FROM storage_usage WHERE user_id = ?1",
+            params![user_id],
+            |row| {
+                Ok(StorageUsage {
+                    user_id: user_id.to_string(),
+                    used_bytes: row.get::<_, i64>(0)?.max(0) as u64,
+                    quota_bytes: row.get::<_, Option<i64>>(1)?.map(|q| q.max(0) as u64),
+                    updated_at: row.get(2)?,
+                })
+            },
+        )
+        .optional()?;
+
+    Ok(usage.unwrap_or(StorageUsage {
+        user_id: user_id.to_string(),
+        used_bytes: 0,
+        quota_bytes: None,
+        updated_at: 0,
+    }))
+}
+
+fn to_sql_bytes(bytes: u64) -> i64 {
+    i64::try_from(bytes).unwrap_or(i64::MAX)
+}
+
+/// Size on disk, falling back to the recorded size when the file is gone
+fn file_size_or(path: &str, recorded: Option<i64>) -> u64 {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.is_file() => metadata.len(),
+        _ => recorded.unwrap_or(0).max(0) as u64,
+    }
+}
+
+/// Walk documents and vehicle images and sum sizes per owner
+fn compute_usage(conn: &Connection) -> rusqlite::Result<HashMap<String, u64>> {
+    let mut totals: HashMap<String, u64> = HashMap::new();
+
+    let mut stmt = conn.prepare(
+        "SELECT COALESCE(d.user_id, deals.user_id), d.file_path, d.file_size
+         FROM documents d LEFT JOIN deals ON deals.id = d.deal_id",
+    )?;
+    let documents = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, Option<String>>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<i64>>(2)?,
+        ))
+    })?;
+    for document in documents {
+        let (owner, file_path, file_size) = document?;
+        if let Some(owner) = owner {
+            *totals.entry(owner).or_default() += file_size_or(&file_path, file_size);
+        }
+    }
+
+    // Vehicle images are a JSON array of URLs/paths; only local files count
+    let mut stmt = conn.prepare(
+        "SELECT user_id, images FROM vehicles WHERE user_id IS NOT NULL AND images IS NOT NULL",
+    )?;
+    let vehicles = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for vehicle in vehicles {
+        let (owner, images) = vehicle?;
+        let paths: Vec<String> = serde_json::from_str(&images).unwrap_or_default();
+        let bytes: u64 = paths
+            .iter()
+            .filter(|path| Path::new(path).is_file())
+            .map(|path| file_size_or(path, None))
+            .sum();
+        *totals.entry(owner).or_default() += bytes;
+    }
+
+    Ok(totals)
+}
+
+fn rebuild_usage(conn: &mut Connection) -> rusqlite::Result<Vec<StorageUsage>> {
+    let totals = compute_usage(conn)?;
+    let now = Utc::now().timestamp_millis();
+
+    let tx = conn.transaction()?;
+    // Keep quotas; only the cached byte counts are rebuilt
+    tx.execute(
+        "UPDATE storage_usage SET used_bytes = 0, updated_at = ?1",
+        params![now],
+    )?;
+    for (user_id, bytes) in &totals {
+        tx.execute(
+            "INSERT INTO storage_usage (user_id, used_bytes, quota_bytes, updated_at)
+             VALUES (?1, ?2, NULL, ?3)
+             ON CONFLICT(user_id) DO UPDATE SET used_bytes = ?2, updated_at = ?3",
+            params![user_id, to_sql_bytes(*bytes), now],
+        )?;
+    }
+    tx.commit()?;
+
+    let mut stmt = conn.prepare("SELECT user_id FROM storage_usage ORDER BY user_id")?;
+    let users = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    info!("✅ Storage usage recomputed for {} users", users.len());
+    users.iter().map(|user| usage_for(conn, user)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE deals (id TEXT PRIMARY KEY, user_id TEXT);
+             CREATE TABLE documents (
+                id TEXT PRIMARY KEY,
+                deal_id TEXT NOT NULL,
+                file_path TEXT NOT NULL,
+                file_size INTEGER,
+                user_id TEXT
+             );
+             CREATE TABLE vehicles (id TEXT PRIMARY KEY, images TEXT, user_id TEXT);",
+        )
+        .unwrap();
+        conn.execute_batch(include_str!("../migrations/006_add_storage_usage.sql"))
+            .unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_incremental_usage_and_quota_enforcement() {
+        let conn = test_db();
+        conn.execute("INSERT INTO deals VALUES ('deal-1', 'user-a')", [])
+            .unwrap();
+        assert_eq!(
+            deal_owner(&conn, "deal-1").unwrap(),
+            Some("user-a".to_string())
+        );
+        assert_eq!(deal_owner(&conn, "missing").unwrap(), None);
+
+        // No quota: anything goes
+        check_quota(&conn, "user-a", u64::MAX).unwrap();
+
+        adjust_usage(&conn, "user-a", 600).unwrap();
+        adjust_usage(&conn, "user-a", 300).unwrap();
+        assert_eq!(usage_for(&conn, "user-a").unwrap().used_bytes, 900);
+
+        conn.execute(
+            "UPDATE storage_usage SET quota_bytes = 1000 WHERE user_id = 'user-a'",
+            [],
+        )
+        .unwrap();
+        check_quota(&conn, "user-a", 100).unwrap();
+        assert_eq!(
+            check_quota(&conn, "user-a", 101),
+            Err(DiskSpaceError::QuotaExceeded {
+                user_id: "user-a".to_string(),
+                used_bytes: 900,
+                quota_bytes: 1000,
+                incoming_bytes: 101,
+            })
+        );
+
+        // Deletes free space again and never go negative
+        adjust_usage(&conn, "user-a", -500).unwrap();
+        check_quota(&conn, "user-a", 101).unwrap();
+        adjust_usage(&conn, "user-a", -5000).unwrap();
+        assert_eq!(usage_for(&conn, "user-a").unwrap().used_bytes, 0);
+    }
+
+    #[test]
+    fn test_recompute_from_disk_keeps_quotas() {
+        let dir = temp_dir("usage", "recompute");
+        let pdf = dir.join("deal.pdf");
+        let photo = dir.join("car.jpg");
+        std::fs::write(&pdf, vec![0u8; 1200]).unwrap();
+        std::fs::write(&photo, vec![0u8; 300]).unwrap();
+
+        let mut conn = test_db();
+        conn.execute("INSERT INTO deals VALUES ('deal-1', 'user-a')", [])
+            .unwrap();
+        conn.execute(
+            "INSERT INTO documents VALUES ('doc-1', 'deal-1', ?1, 10, NULL)",
+            params![pdf.to_string_lossy().to_string()],
+        )
+        .unwrap();
+        // Missing file falls back to the recorded size
+        conn.execute(
+            "INSERT INTO documents VALUES ('doc-2', 'deal-1', '/nowhere/gone.pdf', 50, NULL)",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO vehicles VALUES ('veh-1', ?1, 'user-b')",
+            params![serde_json::json!([photo, "https://cdn.example.com/x.jpg"]).to_string()],
+        )
+        .unwrap();
+
+        // Drifted cache with a quota that must survive the rebuild
+        conn.execute(
+            "INSERT INTO storage_usage VALUES ('user-a', 999999, 5000, 0)",
+            [],
+        )
+        .unwrap();
+
+        let usage = rebuild_usage(&mut conn).unwrap();
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0].user_id, "user-a");
+        assert_eq!(usage[0].used_bytes, 1250);
+        assert_eq!(usage[0].quota_bytes, Some(5000));
+        assert_eq!(usage[1].used_bytes, 300);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,520 @@
+// src-tauri/src/support_bundle.rs
+//
+// One zip for support instead of screenshots: diagnostics report, the newest log files and
+// crash reports, command metrics, applied migrations, storage stats and, only when the user
+// confirms it, a copy of the database scrubbed with the accountant_no_contact profile.
+// Everything is staged in a temp directory first. Before anything is zipped, every staged
+// file is scanned for the values of the keyring secrets, the webhook signing secrets and
+// text that only appears in credentials; one hit and no bundle is written.
+
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::archive::{add_file_entry, create_writer, file_options, finish_writer};
+use crate::crash::recent_report_files;
+use crate::database::{get_db, new_row_id};
+use crate::diagnostics::run_diagnostics;
+use crate::logging::{self, log_files, logs_dir};
+use crate::scrubbed_export::scrubbed_copy;
+use crate::secret_store::{self, SecretKey};
+use crate::storage::get_storage_stats;
+use crate::telemetry::{get_command_metrics, track_async};
+
+const LOG_FILES: usize = 3;
+const CRASH_REPORTS: usize = 5;
+const DB_COPY_PROFILE: &str = "accountant_no_contact";
+const DB_COPY_NAME: &str = "database-scrubbed.sqlite";
+const MANIFEST_NAME: &str = "manifest.json";
+
+/// Keyring entries whose values must never be in a bundle
+const SECRET_KEYS: &[SecretKey] = &[
+    SecretKey::SessionToken,
+    SecretKey::DealershipAuthToken,
+    SecretKey::AwsAccessKeyId,
+    SecretKey::AwsSecretAccessKey,
+    SecretKey::LicenseKey,
+    SecretKey::LicenseActivationToken,
+    SecretKey::LocalApiToken,
+    SecretKey::FieldEncryptionKey,
+    SecretKey::ValuationApiKey,
+];
+/// Text that only shows up in credentials, whoever they belong to. Labels end up in
+/// logs and command metrics, which later bundles scan, so none may contain a marker
+const SECRET_MARKERS: &[(&str, &str)] = &[
+    ("-----BEGIN", "a PEM key or certificate"),
+    ("PRIVATE KEY", "a private key"),
+    ("Bearer ", "a bearer token"),
+];
+/// Shorter secret values are too likely to match unrelated bytes to be worth scanning for
+const MIN_SECRET_LEN: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BundleEntry {
+    /// Path inside the zip
+    pub name: String,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SupportBundle {
+    pub path: String,
+    pub size_bytes: u64,
+    pub entries: Vec<BundleEntry>,
+    /// Sections that couldn't be collected, with the reason; the bundle is still useful
+    pub skipped: Vec<String>,
+}
+
+/// Something that must not leave the machine, and how to name it without showing it
+struct Needle {
+    bytes: Vec<u8>,
+    label: String,
+}
+
+/// Files collected for the bundle, under a temp directory removed on drop
+struct Stage {
+    dir: PathBuf,
+    entries: Vec<BundleEntry>,
+    skipped: Vec<String>,
+}
+
+impl Stage {
+    fn new(dir: PathBuf) -> Result<Self, String> {
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create staging folder: {}", e))?;
+        Ok(Stage {
+            dir,
+            entries: Vec::new(),
+            skipped: Vec::new(),
+        })
+    }
+
+    fn path_of(&self, name: &str) -> Result<PathBuf, String> {
+        let path = self.dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to stage {}: {}", name, e))?;
+        }
+        Ok(path)
+    }
+
+    fn added(&mut self, name: &str) {
+        let size_bytes = fs::metadata(self.dir.join(name))
+            .map(|m| m.len())
+            .unwrap_or(0);
+        self.entries.push(BundleEntry {
+            name: name.to_string(),
+            size_bytes,
+        });
+    }
+
+    fn add_json(&mut self, name: &str, value: &impl Serialize) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(value).map_err(|e| e.to_string())?;
+        fs::write(self.path_of(name)?, json)
+            .map_err(|e| format!("Failed to stage {}: {}", name, e))?;
+        self.added(name);
+        Ok(())
+    }
+
+    fn add_file(&mut self, name: &str, source: &Path) -> Result<(), String> {
+        fs::copy(source, self.path_of(name)?)
+            .map_err(|e| format!("Failed to stage {}: {}", name, e))?;
+        self.added(name);
+        Ok(())
+    }
+
+    /// Record a section that failed instead of failing the bundle
+    fn collect(&mut self, section: &str, result: Result<(), String>) {
+        if let Err(e) = result {
+            warn!("⚠️  Support bundle: no {}: {}", section, e);
+            self.skipped.push(format!("{}: {}", section, e));
+        }
+    }
+
+    /// Fail if any staged file contains one of the needles
+    fn scan(&self, needles: &[Needle]) -> Result<(), String> {
+        for entry in &self.entries {
+            let bytes = fs::read(self.dir.join(&entry.name))
+                .map_err(|e| format!("Failed to scan {}: {}", entry.name, e))?;
+            if let Some(needle) = needles
+                .iter()
+                .find(|needle| contains(&bytes, &needle.bytes))
+            {
+                // Never log the value itself
+                warn!(
+                    "⚠️  Support bundle: {} contains {}",
+                    entry.name, needle.label
+                );
+                return Err(format!(
+                    "Support bundle not created: {} contains {}",
+                    entry.name, needle.label
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Zip the staged files (manifest first) to output_path
+    fn write_zip(&self, output_path: &Path) -> Result<u64, String> {
+        let mut writer = create_writer(&output_path.to_string_lossy())?;
+        let options = file_options(None);
+        for entry in &self.entries {
+            add_file_entry(
+                &mut writer,
+                &self.dir.join(&entry.name),
+                &entry.name,
+                options,
+            )?;
+        }
+        finish_writer(writer)?;
+        fs::metadata(output_path)
+            .map(|m| m.len())
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl Drop for Stage {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            warn!("⚠️  Failed to remove support bundle staging folder: {}", e);
+        }
+    }
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    !needle.is_empty()
+        && haystack
+            .windows(needle.len())
+            .any(|window| window == needle)
+}
+
+/// Values and markers no bundle may contain; stored reads a keyring secret
+fn secret_needles(
+    conn: &Connection,
+    stored: impl Fn(SecretKey) -> Result<Option<String>, String>,
+) -> Result<Vec<Needle>, String> {
+    let mut needles: Vec<Needle> = SECRET_MARKERS
+        .iter()
+        .map(|(marker, label)| Needle {
+            bytes: marker.as_bytes().to_vec(),
+            label: label.to_string(),
+        })
+        .collect();
+
+    let mut secret = |value: String, label: String| {
+        let value = value.trim();
+        if value.len() >= MIN_SECRET_LEN {
+            needles.push(Needle {
+                bytes: value.as_bytes().to_vec(),
+                label,
+            });
+        }
+    };
+    // A secret we can't read is one we can't scan for, so that fails the bundle too
+    for key in SECRET_KEYS {
+        if let Some(value) = stored(*key)? {
+            secret(value, format!("the stored {}", key.account()));
+        }
+    }
+    let mut stmt = conn
+        .prepare("SELECT secret FROM webhooks")
+        .map_err(|e| e.to_string())?;
+    let webhook_secrets = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?;
+    for value in webhook_secrets {
+        secret(
+            value.map_err(|e| e.to_string())?,
+            "a webhook signing secret".to_string(),
+        );
+    }
+    Ok(needles)
+}
+
+fn applied_migrations(conn: &Connection) -> Result<Value, String> {
+    let mut stmt = conn
+        .prepare("SELECT version, applied_at FROM schema_migrations ORDER BY version")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(json!({
+                "version": row.get::<_, i64>(0)?,
+                "applied_at": row.get::<_, String>(1)?,
+            }))
+        })
+        .map_err(|e| e.to_string())?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map(Value::Array)
+        .map_err(|e| e.to_string())
+}
+
+fn stage_logs(stage: &mut Stage) -> Result<(), String> {
+    logging::flush();
+    for path in log_files(&logs_dir()?).into_iter().take(LOG_FILES) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        stage.add_file(&format!("logs/{}", name), &path)?;
+    }
+    Ok(())
+}
+
+fn stage_crash_reports(stage: &mut Stage) -> Result<(), String> {
+    for path in recent_report_files(CRASH_REPORTS)? {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        stage.add_file(&format!("crash-reports/{}", name), &path)?;
+    }
+    Ok(())
+}
+
+/// Zip diagnostics, logs, crash reports and metrics to output_path for support.
+/// include_db_copy adds a scrubbed database copy; it needs confirm_db_copy as well, after
+/// the user has been told what the copy still contains.
+#[tauri::command]
+pub async fn create_support_bundle(
+    output_path: String,
+    include_db_copy: bool,
+    confirm_db_copy: Option<bool>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<SupportBundle, String> {
+    track_async("create_support_bundle", async {
+        if include_db_copy {
+            if confirm_db_copy != Some(true) {
+                return Err(
+                    "Including the database copy has to be confirmed (confirm_db_copy)".to_string(),
+                );
+            }
+            state.require_user(user_id)?;
+        }
+        info!("🧰 Creating support bundle: {}", output_path);
+
+        let mut stage =
+            Stage::new(std::env::temp_dir().join(format!("dealer-support-{}", new_row_id())))?;
+        let result = match run_diagnostics(Some(false)).await {
+            Ok(report) => stage.add_json("diagnostics.json", &report),
+            Err(e) => Err(e),
+        };
+        stage.collect("diagnostics", result);
+        let result = match get_storage_stats(Some(false)).await {
+            Ok(stats) => stage.add_json("storage-stats.json", &stats),
+            Err(e) => Err(e),
+        };
+        stage.collect("storage stats", result);
+
+        tauri::async_runtime::spawn_blocking(move || {
+            build_bundle(stage, Path::new(&output_path), include_db_copy)
+        })
+        .await
+        .map_err(|e| format!("Support bundle failed: {}", e))?
+    })
+    .await
+}
+
+fn build_bundle(
+    mut stage: Stage,
+    output_path: &Path,
+    include_db_copy: bool,
+) -> Result<SupportBundle, String> {
+    let result = stage_logs(&mut stage);
+    stage.collect("logs", result);
+    let result = stage_crash_reports(&mut stage);
+    stage.collect("crash reports", result);
+    let result = stage.add_json("command-metrics.json", &get_command_metrics());
+    stage.collect("command metrics", result);
+
+    let needles = {
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let result = applied_migrations(&conn)
+            .and_then(|migrations| stage.add_json("schema-migrations.json", &migrations));
+        stage.collect("schema migrations", result);
+        secret_needles(&conn, secret_store::get)?
+    };
+
+    if include_db_copy {
+        let path = stage.path_of(DB_COPY_NAME)?;
+        scrubbed_copy(DB_COPY_PROFILE, &path)?;
+        stage.added(DB_COPY_NAME);
+    }
+
+    finish_bundle(stage, output_path, &needles)
+}
+
+/// Write the manifest, scan everything and zip it
+fn finish_bundle(
+    mut stage: Stage,
+    output_path: &Path,
+    needles: &[Needle],
+) -> Result<SupportBundle, String> {
+    let manifest = json!({
+        "created_at": Utc::now().to_rfc3339(),
+        "app_version": env!("CARGO_PKG_VERSION"),
+        "platform": format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        "entries": stage.entries,
+        "skipped": stage.skipped,
+    });
+    stage.add_json(MANIFEST_NAME, &manifest)?;
+    // The manifest goes first so support sees it on opening the zip
+    stage.entries.rotate_right(1);
+
+    stage.scan(needles)?;
+    let size_bytes = match stage.write_zip(output_path) {
+        Ok(size) => size,
+        Err(e) => {
+            let _ = fs::remove_file(output_path);
+            return Err(e);
+        }
+    };
+
+    info!(
+        "✅ Support bundle created: {} files, {} bytes",
+        stage.entries.len(),
+        size_bytes
+    );
+    Ok(SupportBundle {
+        path: output_path.to_string_lossy().to_string(),
+        size_bytes,
+        entries: stage.entries.clone(),
+        skipped: stage.skipped.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use std::io::Read;
+
+    const AWS_SECRET: &str = "wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY";
+
+    /// Needles for the given webhook secrets, with AWS_SECRET as the only keyring secret
+    fn needles(webhook_secrets: &[&str]) -> Vec<Needle> {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE webhooks (secret TEXT NOT NULL);")
+            .unwrap();
+        for secret in webhook_secrets {
+            conn.execute("INSERT INTO webhooks (secret) VALUES (?1)", [secret])
+                .unwrap();
+        }
+        secret_needles(&conn, |key| {
+            Ok((key == SecretKey::AwsSecretAccessKey).then(|| AWS_SECRET.to_string()))
+        })
+        .unwrap()
+    }
+
+    fn zip_names(path: &Path) -> Vec<String> {
+        let mut archive = zip::ZipArchive::new(fs::File::open(path).unwrap()).unwrap();
+        (0..archive.len())
+            .map(|i| archive.by_index(i).unwrap().name().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_bundle_lists_its_contents_in_the_manifest() {
+        let dir = temp_dir("support-bundle", "manifest");
+        let mut stage = Stage::new(dir.join("stage")).unwrap();
+        stage
+            .add_json("diagnostics.json", &json!({ "overall": "ok" }))
+            .unwrap();
+        fs::write(dir.join("app.log"), "2026-10-16 INFO started\n").unwrap();
+        stage
+            .add_file("logs/app.log", &dir.join("app.log"))
+            .unwrap();
+        stage.collect("crash reports", Err("no logs folder".to_string()));
+
+        let output = dir.join("bundle.zip");
+        let bundle = finish_bundle(stage, &output, &needles(&["whsec_0123456789"])).unwrap();
+
+        assert_eq!(
+            zip_names(&output),
+            ["manifest.json", "diagnostics.json", "logs/app.log"]
+        );
+        assert_eq!(bundle.size_bytes, fs::metadata(&output).unwrap().len());
+        assert_eq!(bundle.skipped, ["crash reports: no logs folder"]);
+
+        let mut archive = zip::ZipArchive::new(fs::File::open(&output).unwrap()).unwrap();
+        let mut manifest = String::new();
+        archive
+            .by_name("manifest.json")
+            .unwrap()
+            .read_to_string(&mut manifest)
+            .unwrap();
+        let manifest: Value = serde_json::from_str(&manifest).unwrap();
+        assert_eq!(manifest["entries"][1]["name"], "logs/app.log");
+        assert!(!dir.join("stage").exists());
+    }
+
+    #[test]
+    fn test_bundle_with_a_secret_value_is_refused() {
+        let dir = temp_dir("support-bundle", "secret_value");
+        fs::write(
+            dir.join("app.log"),
+            "INFO delivering webhook with key whsec_0123456789\n",
+        )
+        .unwrap();
+        let mut stage = Stage::new(dir.join("stage")).unwrap();
+        stage
+            .add_file("logs/app.log", &dir.join("app.log"))
+            .unwrap();
+
+        let output = dir.join("bundle.zip");
+        let error = finish_bundle(stage, &output, &needles(&["whsec_0123456789"]))
+            .expect_err("bundle with a secret was written");
+        assert!(error.contains("logs/app.log"));
+        assert!(error.contains("webhook signing secret"));
+        assert!(!error.contains("whsec_0123456789"));
+        assert!(!output.exists());
+        assert!(!dir.join("stage").exists());
+    }
+
+    #[test]
+    fn test_keyring_secrets_are_refused_in_a_database_copy() {
+        let dir = temp_dir("support-bundle", "keyring_secret");
+        // Bytes of a SQLite page, not just text files, are scanned
+        let mut page = vec![0u8; 4096];
+        page[1000..1000 + AWS_SECRET.len()].copy_from_slice(AWS_SECRET.as_bytes());
+        fs::write(dir.join("copy.sqlite"), &page).unwrap();
+        let mut stage = Stage::new(dir.join("stage")).unwrap();
+        stage
+            .add_file(DB_COPY_NAME, &dir.join("copy.sqlite"))
+            .unwrap();
+
+        let error = finish_bundle(stage, &dir.join("bundle.zip"), &needles(&[]))
+            .expect_err("keyring secret not caught");
+        assert!(error.contains(DB_COPY_NAME));
+        assert!(error.contains("aws_secret_access_key"));
+        assert!(!dir.join("bundle.zip").exists());
+    }
+
+    #[test]
+    fn test_credential_markers_are_refused_wherever_they_are() {
+        let dir = temp_dir("support-bundle", "markers");
+        for (marker, label) in SECRET_MARKERS {
+            let mut stage = Stage::new(dir.join("stage")).unwrap();
+            stage
+                .add_json(
+                    "command-metrics.json",
+                    &json!({ "error": format!("x {} y", marker) }),
+                )
+                .unwrap();
+            let error = finish_bundle(stage, &dir.join("bundle.zip"), &needles(&[]))
+                .expect_err("marker not caught");
+            assert!(error.contains(label), "{}", error);
+        }
+        assert!(!dir.join("bundle.zip").exists());
+    }
+
+    #[test]
+    fn test_short_secrets_are_not_scanned_for() {
+        let needles = needles(&["abc", "  whsec_0123456789  "]);
+        let webhook: Vec<&[u8]> = needles
+            .iter()
+            .filter(|needle| needle.label == "a webhook signing secret")
+            .map(|needle| needle.bytes.as_slice())
+            .collect();
+        assert_eq!(webhook, [b"whsec_0123456789".as_slice()]);
+    }
+}
@@ -0,0 +1,627 @@
+// src-tauri/src/tasks.rs
+//
+// Follow-up tasks ("call back Tuesday about the F-150"), optionally linked to a record
+// start_task_reminders checks every minute while the app runs and emits "task-due" for the
+// signed-in user's tasks that have come due. notified_at makes that once per task (also
+// across restarts); snoozing moves due_at and clears it so the task fires again.
+
+use chrono::{DateTime, Local, NaiveTime, Offset, TimeZone, Utc};
+use log::{error, info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::app_state::AppState;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{get_db, new_row_id};
+use crate::recent_items::{owned_by, EntityType};
+use crate::telemetry::track;
+
+/// Event emitted once per task when it comes due (payload: Task)
+pub const TASK_DUE_EVENT: &str = "task-due";
+
+const TASK_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Task {
+    pub id: String,
+    pub user_id: String,
+    pub title: String,
+    pub body: Option<String>,
+    pub entity_type: Option<EntityType>,
+    pub entity_id: Option<String>,
+    pub due_at: i64,
+    pub completed_at: Option<i64>,
+    pub notified_at: Option<i64>,
+    pub created_at: i64,
+}
+
+impl Task {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        let entity_type: Option<String> = row.get("entity_type")?;
+        Ok(Task {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            title: row.get("title")?,
+            body: row.get("body")?,
+            entity_type: entity_type.as_deref().and_then(EntityType::parse),
+            entity_id: row.get("entity_id")?,
+            due_at: row.get("due_at")?,
+            completed_at: row.get("completed_at")?,
+            notified_at: row.get("notified_at")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewTask {
+    pub title: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub entity_type: Option<EntityType>,
+    #[serde(default)]
+    pub entity_id: Option<String>,
+    pub due_at: i64,
+}
+
+/// Which tasks db_get_tasks returns. Open tasks fall in exactly one of the first three
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskFilter {
+    /// Due before now
+    Overdue,
+    /// Due between now and local midnight
+    Today,
+    /// Due after today
+    Upcoming,
+    Completed,
+}
+
+/// Start of the next local day, in epoch ms
+fn end_of_day<Tz: TimeZone>(now: &DateTime<Tz>) -> i64 {
+    let midnight = now
+        .date_naive()
+        .succ_opt()
+        .expect("date in range")
+        .and_time(NaiveTime::MIN);
+    match now.timezone().from_local_datetime(&midnight).earliest() {
+        Some(end) => end.timestamp_millis(),
+        // Midnight skipped by a DST change; today's offset is close enough
+        None => {
+            let offset_ms = i64::from(now.offset().fix().local_minus_utc()) * 1000;
+            midnight.and_utc().timestamp_millis() - offset_ms
+        }
+    }
+}
+
+/// Due-window bounds (inclusive start, exclusive end) for a filter
+fn window<Tz: TimeZone>(filter: TaskFilter, now: &DateTime<Tz>) -> (i64, i64) {
+    let now_ms = now.timestamp_millis();
+    match filter {
+        TaskFilter::Overdue => (i64::MIN, now_ms),
+        TaskFilter::Today => (now_ms, end_of_day(now)),
+        TaskFilter::Upcoming => (end_of_day(now), i64::MAX),
+        TaskFilter::Completed => (i64::MIN, i64::MAX),
+    }
+}
+
+fn list_tasks<Tz: TimeZone>(
+    conn: &Connection,
+    user_id: &str,
+    filter: TaskFilter,
+    now: &DateTime<Tz>,
+) -> SqlResult<Vec<Task>> {
+    let sql = if filter == TaskFilter::Completed {
+        "SELECT * FROM tasks WHERE user_id = ?1 AND completed_at IS NOT NULL
+            AND due_at >= ?2 AND due_at < ?3
+         ORDER BY completed_at DESC"
+    } else {
+        "SELECT * FROM tasks WHERE user_id = ?1 AND completed_at IS NULL
+            AND due_at >= ?2 AND due_at < ?3
+         ORDER BY due_at, created_at"
+    };
+    let (start, end) = window(filter, now);
+    let mut stmt = conn.prepare(sql)?;
+    let rows = stmt.query_map(params![user_id, start, end], Task::from_row)?;
+    rows.collect()
+}
+
+fn get_task(conn: &Connection, user_id: &str, id: &str) -> Result<Task, String> {
+    conn.query_row(
+        "SELECT * FROM tasks WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+        Task::from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Task not found or access denied".to_string())
+}
+
+fn validate_link(
+    conn: &Connection,
+    user_id: &str,
+    entity_type: Option<EntityType>,
+    entity_id: Option<&str>,
+) -> Result<(), String> {
+    match (entity_type, entity_id) {
+        (None, None) => Ok(()),
+        (Some(entity_type), Some(entity_id)) => owned_by(conn, user_id, entity_type, entity_id),
+        _ => Err("A linked task needs both entity_type and entity_id".to_string()),
+    }
+}
+
+pub(crate) fn create_task(
+    conn: &Connection,
+    user_id: &str,
+    task: NewTask,
+    now: i64,
+) -> Result<Task, String> {
+    let title = task.title.trim();
+    if title.is_empty() {
+        return Err("Task title is required".to_string());
+    }
+    validate_link(conn, user_id, task.entity_type, task.entity_id.as_deref())?;
+
+    let id = new_row_id();
+    conn.execute(
+        "INSERT INTO tasks (id, user_id, title, body, entity_type, entity_id, due_at, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            id,
+            user_id,
+            title,
+            task.body,
+            task.entity_type.map(EntityType::as_str),
+            task.entity_id,
+            task.due_at,
+            now
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    get_task(conn, user_id, &id)
+}
+
+fn update_task(
+    conn: &Connection,
+    user_id: &str,
+    id: &str,
+    updates: &Value,
+) -> Result<Task, String> {
+    let mut task = get_task(conn, user_id, id)?;
+
+    if let Some(title) = updates.get("title").and_then(|v| v.as_str()) {
+        if title.trim().is_empty() {
+            return Err("Task title is required".to_string());
+        }
+        task.title = title.trim().to_string();
+    }
+    if let Some(body) = updates.get("body") {
+        task.body = body.as_str().map(str::to_string);
+    }
+    if updates.get("entity_type").is_some() || updates.get("entity_id").is_some() {
+        task.entity_type = updates
+            .get("entity_type")
+            .and_then(|v| v.as_str())
+            .map(|value| {
+                EntityType::parse(value).ok_or_else(|| format!("Unknown entity type '{}'", value))
+            })
+            .transpose()?;
+        task.entity_id = updates
+            .get("entity_id")
+            .and_then(|v| v.as_str())
+            .map(str::to_string);
+        validate_link(conn, user_id, task.entity_type, task.entity_id.as_deref())?;
+    }
+    if let Some(due_at) = updates.get("due_at").and_then(|v| v.as_i64()) {
+        if due_at != task.due_at {
+            task.due_at = due_at;
+            task.notified_at = None;
+        }
+    }
+
+    conn.execute(
+        "UPDATE tasks SET title = ?3, body = ?4, entity_type = ?5, entity_id = ?6,
+                          due_at = ?7, notified_at = ?8
+         WHERE id = ?1 AND user_id = ?2",
+        params![
+            id,
+            user_id,
+            task.title,
+            task.body,
+            task.entity_type.map(EntityType::as_str),
+            task.entity_id,
+            task.due_at,
+            task.notified_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(task)
+}
+
+fn complete_task(
+    conn: &Connection,
+    user_id: &str,
+    id: &str,
+    completed: bool,
+    now: i64,
+) -> Result<Task, String> {
+    get_task(conn, user_id, id)?;
+    conn.execute(
+        "UPDATE tasks SET completed_at = ?3 WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id, completed.then_some(now)],
+    )
+    .map_err(|e| e.to_string())?;
+    get_task(conn, user_id, id)
+}
+
+fn snooze_task(
+    conn: &Connection,
+    user_id: &str,
+    id: &str,
+    due_at: i64,
+    now: i64,
+) -> Result<Task, String> {
+    if due_at <= now {
+        return Err("Snooze until a time in the future".to_string());
+    }
+    get_task(conn, user_id, id)?;
+    conn.execute(
+        "UPDATE tasks SET due_at = ?3, notified_at = NULL WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id, due_at],
+    )
+    .map_err(|e| e.to_string())?;
+    get_task(conn, user_id, id)
+}
+
+/// Open tasks that are due and haven't been announced yet, marked as announced
+/// Marking and reading happen in one transaction, so a task is only ever returned once.
+fn claim_due_tasks(conn: &mut Connection, user_id: &str, now: i64) -> SqlResult<Vec<Task>> {
+    let tx = conn.transaction()?;
+    let due = {
+        let mut stmt = tx.prepare(
+            "SELECT * FROM tasks WHERE user_id = ?1 AND completed_at IS NULL
+                AND notified_at IS NULL AND due_at <= ?2
+             ORDER BY due_at",
+        )?;
+        let rows = stmt.query_map(params![user_id, now], Task::from_row)?;
+        rows.collect::<SqlResult<Vec<_>>>()?
+    };
+    for task in &due {
+        tx.execute(
+            "UPDATE tasks SET notified_at = ?2 WHERE id = ?1",
+            params![task.id, now],
+        )?;
+    }
+    tx.commit()?;
+    Ok(due
+        .into_iter()
+        .map(|task| Task {
+            notified_at: Some(now),
+            ..task
+        })
+        .collect())
+}
+
+/// Announce due tasks while the app is running (no-op while nobody is signed in)
+/// Tasks that came due while the app was closed are announced on the first check.
+pub fn start_task_reminders(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(TASK_CHECK_INTERVAL).await;
+
+            let Some(user_id) = app.state::<AppState>().current_user() else {
+                continue;
+            };
+            let due = match get_db() {
+                Ok(db) => claim_due_tasks(&mut db.conn(), &user_id, Utc::now().timestamp_millis()),
+                Err(e) => Err(e),
+            };
+            let due = match due {
+                Ok(due) => due,
+                Err(e) => {
+                    warn!("⚠️  Task reminder check failed: {}", e);
+                    continue;
+                }
+            };
+
+            for task in &due {
+                info!("⏰ Task due: {}", task.id);
+                if let Err(e) = app.emit(TASK_DUE_EVENT, task) {
+                    error!("Failed to emit task-due: {}", e);
+                }
+            }
+        }
+    });
+}
+
+#[tauri::command]
+pub fn db_create_task(
+    task: NewTask,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Task, String> {
+    track("db_create_task", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let task = create_task(&conn, &user_id_value, task, Utc::now().timestamp_millis())?;
+        data_changed(
+            &user_id_value,
+            ChangedEntity::Task,
+            &task.id,
+            Operation::Create,
+        );
+        Ok(task)
+    })
+}
+
+/// Change title, body, link or due_at (a new due_at re-arms the reminder)
+#[tauri::command]
+pub fn db_update_task(
+    id: String,
+    updates: Value,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Task, String> {
+    track("db_update_task", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let task = update_task(&conn, &user_id_value, &id, &updates)?;
+        data_changed(&user_id_value, ChangedEntity::Task, &id, Operation::Update);
+        Ok(task)
+    })
+}
+
+#[tauri::command]
+pub fn db_delete_task(
+    id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    track("db_delete_task", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let deleted = conn
+            .execute(
+                "DELETE FROM tasks WHERE id = ?1 AND user_id = ?2",
+                params![id, user_id_value],
+            )
+            .map_err(|e| e.to_string())?;
+        if deleted == 0 {
+            return Err("Task not found or access denied".to_string());
+        }
+        data_changed(&user_id_value, ChangedEntity::Task, &id, Operation::Delete);
+        Ok(())
+    })
+}
+
+/// Tasks in a due window (local time): overdue, today, upcoming, or completed
+#[tauri::command]
+pub fn db_get_tasks(
+    user_id: Option<String>,
+    filter: TaskFilter,
+    state: State<'_, AppState>,
+) -> Result<Vec<Task>, String> {
+    track("db_get_tasks", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        list_tasks(&conn, &user_id_value, filter, &Local::now()).map_err(|e| e.to_string())
+    })
+}
+
+/// Mark a task done (completed = false reopens it)
+#[tauri::command]
+pub fn db_complete_task(
+    id: String,
+    completed: Option<bool>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Task, String> {
+    track("db_complete_task", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let task = complete_task(
+            &conn,
+            &user_id_value,
+            &id,
+            completed.unwrap_or(true),
+            Utc::now().timestamp_millis(),
+        )?;
+        data_changed(&user_id_value, ChangedEntity::Task, &id, Operation::Update);
+        Ok(task)
+    })
+}
+
+/// Push a task's due time out; it will be announced again when the new time comes
+#[tauri::command]
+pub fn db_snooze_task(
+    id: String,
+    due_at: i64,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Task, String> {
+    track("db_snooze_task", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let task = snooze_task(
+            &conn,
+            &user_id_value,
+            &id,
+            due_at,
+            Utc::now().timestamp_millis(),
+        )?;
+        data_changed(&user_id_value, ChangedEntity::Task, &id, Operation::Update);
+        Ok(task)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+    use serde_json::json;
+
+    const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/018_add_tasks.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, created_at, updated_at, user_id)
+             VALUES ('c1', 'Ada', 'Lovelace', 1, 1, 'u1'), ('c2', 'Bob', 'Other', 1, 1, 'u2');",
+        )
+        .unwrap();
+        conn
+    }
+
+    /// 23:30 on 2026-03-10 in UTC-5, which is already 04:30 on the 11th in UTC
+    fn late_evening() -> DateTime<FixedOffset> {
+        FixedOffset::west_opt(5 * 3600)
+            .unwrap()
+            .with_ymd_and_hms(2026, 3, 10, 23, 30, 0)
+            .unwrap()
+    }
+
+    fn task(title: &str, due_at: i64) -> NewTask {
+        NewTask {
+            title: title.to_string(),
+            body: None,
+            entity_type: None,
+            entity_id: None,
+            due_at,
+        }
+    }
+
+    fn titles(tasks: Vec<Task>) -> Vec<String> {
+        tasks.into_iter().map(|task| task.title).collect()
+    }
+
+    #[test]
+    fn test_due_windows_follow_the_local_day() {
+        let conn = test_db();
+        let now = late_evening();
+        let now_ms = now.timestamp_millis();
+        let minute = 60 * 1000;
+
+        create_task(&conn, "u1", task("yesterday", now_ms - DAY_MS), 0).unwrap();
+        create_task(&conn, "u1", task("earlier tonight", now_ms - minute), 0).unwrap();
+        create_task(
+            &conn,
+            "u1",
+            task("before midnight", now_ms + 29 * minute),
+            0,
+        )
+        .unwrap();
+        create_task(&conn, "u1", task("at midnight", now_ms + 30 * minute), 0).unwrap();
+        create_task(&conn, "u1", task("next week", now_ms + 7 * DAY_MS), 0).unwrap();
+        create_task(&conn, "u2", task("someone else's", now_ms), 0).unwrap();
+
+        let list = |filter| titles(list_tasks(&conn, "u1", filter, &now).unwrap());
+        assert_eq!(list(TaskFilter::Overdue), ["yesterday", "earlier tonight"]);
+        // Local midnight, not UTC midnight (which passed four and a half hours ago)
+        assert_eq!(list(TaskFilter::Today), ["before midnight"]);
+        assert_eq!(list(TaskFilter::Upcoming), ["at midnight", "next week"]);
+        assert!(list(TaskFilter::Completed).is_empty());
+
+        // Half an hour later it's a new day
+        let after_midnight = now + chrono::Duration::minutes(30);
+        let today = list_tasks(&conn, "u1", TaskFilter::Today, &after_midnight).unwrap();
+        assert_eq!(titles(today), ["at midnight"]);
+    }
+
+    #[test]
+    fn test_completed_tasks_leave_the_open_lists() {
+        let conn = test_db();
+        let now = late_evening();
+        let id = create_task(
+            &conn,
+            "u1",
+            task("call back", now.timestamp_millis() - 1),
+            0,
+        )
+        .unwrap()
+        .id;
+
+        complete_task(&conn, "u1", &id, true, 5).unwrap();
+        assert!(list_tasks(&conn, "u1", TaskFilter::Overdue, &now)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            titles(list_tasks(&conn, "u1", TaskFilter::Completed, &now).unwrap()),
+            ["call back"]
+        );
+
+        // Other users can't touch it
+        assert!(complete_task(&conn, "u2", &id, false, 5).is_err());
+        assert!(update_task(&conn, "u2", &id, &json!({ "title": "x" })).is_err());
+    }
+
+    #[test]
+    fn test_due_tasks_are_announced_once() {
+        let mut conn = test_db();
+        let id = create_task(&conn, "u1", task("call back", 1_000), 0)
+            .unwrap()
+            .id;
+        create_task(&conn, "u1", task("later", 5_000), 0).unwrap();
+        let done = create_task(&conn, "u1", task("done", 1_000), 0).unwrap().id;
+        complete_task(&conn, "u1", &done, true, 0).unwrap();
+
+        assert!(claim_due_tasks(&mut conn, "u1", 999).unwrap().is_empty());
+        let due = claim_due_tasks(&mut conn, "u1", 1_000).unwrap();
+        assert_eq!(titles(due.clone()), ["call back"]);
+        assert_eq!(due[0].notified_at, Some(1_000));
+        assert!(claim_due_tasks(&mut conn, "u1", 2_000).unwrap().is_empty());
+        assert!(claim_due_tasks(&mut conn, "u2", 2_000).unwrap().is_empty());
+
+        // Snoozing re-arms it for the new time
+        assert!(snooze_task(&conn, "u1", &id, 2_000, 3_000).is_err());
+        snooze_task(&conn, "u1", &id, 4_000, 3_000).unwrap();
+        assert!(claim_due_tasks(&mut conn, "u1", 3_500).unwrap().is_empty());
+        assert_eq!(
+            titles(claim_due_tasks(&mut conn, "u1", 5_000).unwrap()),
+            ["call back", "later"]
+        );
+
+        // So does moving due_at through an update; other edits don't
+        update_task(&conn, "u1", &id, &json!({ "title": "call back again" })).unwrap();
+        assert!(claim_due_tasks(&mut conn, "u1", 6_000).unwrap().is_empty());
+        update_task(&conn, "u1", &id, &json!({ "due_at": 6_500 })).unwrap();
+        assert_eq!(
+            titles(claim_due_tasks(&mut conn, "u1", 7_000).unwrap()),
+            ["call back again"]
+        );
+    }
+
+    #[test]
+    fn test_links_must_point_at_own_records() {
+        let conn = test_db();
+        let mut linked = task("follow up", 0);
+        linked.entity_type = Some(EntityType::Client);
+        linked.entity_id = Some("c1".to_string());
+        let created = create_task(&conn, "u1", linked.clone(), 0).unwrap();
+        assert_eq!(created.entity_type, Some(EntityType::Client));
+
+        linked.entity_id = Some("c2".to_string());
+        assert!(create_task(&conn, "u1", linked.clone(), 0).is_err());
+        linked.entity_id = None;
+        assert!(create_task(&conn, "u1", linked, 0).is_err());
+        assert!(create_task(&conn, "u1", task("  ", 0), 0).is_err());
+    }
+}
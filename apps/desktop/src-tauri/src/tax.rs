@@ -0,0 +1,631 @@
+// src-tauri/src/tax.rs
+//
+// Sales tax for deals, computed instead of typed by hand
+// Taxable base = sale price + doc fee (if the state taxes it) + other taxable fee lines
+// - trade-in (if the state gives trade-in credit), never below zero. For a saved deal the
+// fees come from its deal_fees lines; lines marked non-taxable are left out. The statewide rate applies first, then an optional
+// county/local rate on top; either can be limited to the first N dollars of the base
+// (max_taxable_cents) or capped outright (max_tax_cents).
+// All math is integer cents; rates are parts per million and each line rounds once with
+// the state's rounding mode.
+
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::{db_get_client, db_get_deal, db_update_deal, get_db, DbState};
+use crate::deal_fees::{list_fees, taxable_fees};
+use crate::money::to_cents;
+use crate::telemetry::track;
+
+const PPM: i128 = 1_000_000;
+/// Nothing in the US comes close; catches percent-vs-ppm mix-ups
+const MAX_RATE_PPM: i64 = 250_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rounding {
+    /// Half a cent rounds up (most states)
+    #[default]
+    HalfUp,
+    /// Half a cent rounds to the even cent
+    HalfEven,
+    /// Fractions of a cent are dropped
+    Down,
+}
+
+impl Rounding {
+    fn as_str(self) -> &'static str {
+        match self {
+            Rounding::HalfUp => "half_up",
+            Rounding::HalfEven => "half_even",
+            Rounding::Down => "down",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "half_even" => Rounding::HalfEven,
+            "down" => Rounding::Down,
+            _ => Rounding::HalfUp,
+        }
+    }
+}
+
+/// State-level rules; come from the statewide tax_rates row unless overridden
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaxRules {
+    pub trade_in_credit: bool,
+    pub doc_fee_taxable: bool,
+    #[serde(default)]
+    pub rounding: Rounding,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaxRate {
+    pub state: String,
+    /// None for the statewide row
+    #[serde(default)]
+    pub county: Option<String>,
+    pub rate_ppm: i64, // 6.25% = 62500
+    #[serde(default = "default_true")]
+    pub trade_in_credit: bool,
+    #[serde(default = "default_true")]
+    pub doc_fee_taxable: bool,
+    #[serde(default)]
+    pub rounding: Rounding,
+    #[serde(default)]
+    pub max_taxable_cents: Option<i64>,
+    #[serde(default)]
+    pub max_tax_cents: Option<i64>,
+    #[serde(default)]
+    pub updated_at: i64,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl TaxRate {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let county: String = row.get("county")?;
+        let rounding: String = row.get("rounding")?;
+        Ok(TaxRate {
+            state: row.get("state")?,
+            county: (!county.is_empty()).then_some(county),
+            rate_ppm: row.get("rate_ppm")?,
+            trade_in_credit: row.get("trade_in_credit")?,
+            doc_fee_taxable: row.get("doc_fee_taxable")?,
+            rounding: Rounding::parse(&rounding),
+            max_taxable_cents: row.get("max_taxable_cents")?,
+            max_tax_cents: row.get("max_tax_cents")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+
+    fn rules(&self) -> TaxRules {
+        TaxRules {
+            trade_in_credit: self.trade_in_credit,
+            doc_fee_taxable: self.doc_fee_taxable,
+            rounding: self.rounding,
+        }
+    }
+
+    fn jurisdiction(&self) -> String {
+        match &self.county {
+            Some(county) => format!("{} ({})", county, self.state),
+            None => self.state.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DealAmounts {
+    pub sale_amount_cents: i64,
+    #[serde(default)]
+    pub trade_in_cents: i64,
+    #[serde(default)]
+    pub doc_fee_cents: i64,
+    /// Taxable fees other than the doc fee (title, GAP...), always in the base
+    #[serde(default)]
+    pub taxable_fees_cents: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaxLine {
+    pub jurisdiction: String,
+    pub rate_ppm: i64,
+    pub base_cents: i64,
+    pub tax_cents: i64,
+    /// max_taxable_cents or max_tax_cents limited this line
+    pub capped: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TaxBreakdown {
+    pub state: String,
+    pub county: Option<String>,
+    pub rules: TaxRules,
+    pub trade_in_credit_cents: i64,
+    pub doc_fee_taxed_cents: i64,
+    pub fees_taxed_cents: i64,
+    pub taxable_cents: i64,
+    pub lines: Vec<TaxLine>,
+    pub total_tax_cents: i64,
+}
+
+/// Add or replace the rate for a state (county None) or a county within it
+#[tauri::command]
+pub fn db_upsert_tax_rate(rate: TaxRate) -> Result<TaxRate, String> {
+    track("db_upsert_tax_rate", || {
+        let rate = validate_rate(rate)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+
+        conn.execute(
+            "INSERT INTO tax_rates (state, county, rate_ppm, trade_in_credit, doc_fee_taxable,
+                rounding, max_taxable_cents, max_tax_cents, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+             ON CONFLICT(state, county) DO UPDATE SET
+                rate_ppm = ?3, trade_in_credit = ?4, doc_fee_taxable = ?5, rounding = ?6,
+                max_taxable_cents = ?7, max_tax_cents = ?8, updated_at = ?9",
+            params![
+                rate.state,
+                rate.county.as_deref().unwrap_or(""),
+                rate.rate_ppm,
+                rate.trade_in_credit,
+                rate.doc_fee_taxable,
+                rate.rounding.as_str(),
+                rate.max_taxable_cents,
+                rate.max_tax_cents,
+                rate.updated_at,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+
+        info!("✅ Tax rate saved: {}", rate.jurisdiction());
+        Ok(rate)
+    })
+}
+
+/// All rates, or one state's (statewide row first, then counties by name)
+#[tauri::command]
+pub fn db_get_tax_rates(state: Option<String>) -> Result<Vec<TaxRate>, String> {
+    track("db_get_tax_rates", || {
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT * FROM tax_rates WHERE ?1 IS NULL OR state = ?1
+                 ORDER BY state, county",
+            )
+            .map_err(|e| e.to_string())?;
+        let rates = stmt
+            .query_map(
+                params![state.map(|s| s.trim().to_ascii_uppercase())],
+                TaxRate::from_row,
+            )
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(rates)
+    })
+}
+
+#[tauri::command]
+pub fn db_delete_tax_rate(state: String, county: Option<String>) -> Result<(), String> {
+    track("db_delete_tax_rate", || {
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+
+        conn.execute(
+            "DELETE FROM tax_rates WHERE state = ?1 AND county = ?2",
+            params![
+                state.trim().to_ascii_uppercase(),
+                county.as_deref().map(str::trim).unwrap_or("")
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    })
+}
+
+/// Tax for arbitrary amounts; rules overrides the state's stored rules (what-if quotes)
+#[tauri::command]
+pub fn calculate_deal_tax(
+    deal_amounts: DealAmounts,
+    state: String,
+    county: Option<String>,
+    rules: Option<TaxRules>,
+) -> Result<TaxBreakdown, String> {
+    track("calculate_deal_tax", || {
+        let db = get_db().map_err(|e| e.to_string())?;
+        let (state_rate, county_rate) = {
+            let conn = db.conn();
+            load_rates(&conn, &state, county.as_deref())?
+        };
+        compute_tax(deal_amounts, &state_rate, county_rate.as_ref(), rules)
+    })
+}
+
+/// Tax for a saved deal, in the buyer's state unless tax_state is given
+/// write_back stores the total in the deal's sales_tax
+#[tauri::command]
+pub fn calculate_taxes_for_deal(
+    deal_id: String,
+    tax_state: Option<String>,
+    county: Option<String>,
+    write_back: Option<bool>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<TaxBreakdown, String> {
+    track("calculate_taxes_for_deal", || {
+        let user_id_value = state.require_user(user_id)?;
+        let deal = db_get_deal(
+            deal_id.clone(),
+            Some(user_id_value.clone()),
+            state.clone(),
+            db.clone(),
+        )?
+        .ok_or_else(|| "Deal not found or access denied".to_string())?;
+
+        let tax_state = match tax_state.filter(|s| !s.trim().is_empty()) {
+            Some(tax_state) => tax_state,
+            None => db_get_client(
+                deal.client_id.clone(),
+                Some(user_id_value.clone()),
+                state.clone(),
+                db.clone(),
+            )?
+            .and_then(|client| client.state)
+            .filter(|s| !s.trim().is_empty())
+            .ok_or_else(|| "Client has no state on file; choose the tax state".to_string())?,
+        };
+
+        let breakdown = {
+            let db = db.get().map_err(|e| e.to_string())?;
+            let conn = db.conn();
+            let fees = taxable_fees(&list_fees(&conn, &deal.id).map_err(|e| e.to_string())?);
+            let amounts = DealAmounts {
+                sale_amount_cents: to_cents(deal.sale_amount.unwrap_or(deal.total_amount)),
+                trade_in_cents: to_cents(deal.trade_in_value.unwrap_or(0.0)),
+                doc_fee_cents: fees.doc_fee_cents,
+                taxable_fees_cents: fees.other_cents,
+            };
+            let (state_rate, county_rate) = load_rates(&conn, &tax_state, county.as_deref())?;
+            compute_tax(amounts, &state_rate, county_rate.as_ref(), None)?
+        };
+
+        if write_back.unwrap_or(false) {
+            db_update_deal(
+                deal_id,
+                json!({ "sales_tax": breakdown.total_tax_cents as f64 / 100.0 }),
+                Some(user_id_value),
+                state,
+                db,
+            )?;
+            info!(
+                "✅ Sales tax written to deal {}: {} cents",
+                deal.id, breakdown.total_tax_cents
+            );
+        }
+        Ok(breakdown)
+    })
+}
+
+/// Statewide rate (required) and the county rate if one is configured
+fn load_rates(
+    conn: &Connection,
+    state: &str,
+    county: Option<&str>,
+) -> Result<(TaxRate, Option<TaxRate>), String> {
+    let state = state.trim().to_ascii_uppercase();
+    let find = |county: &str| {
+        conn.query_row(
+            "SELECT * FROM tax_rates WHERE state = ?1 AND county = ?2",
+            params![state, county],
+            TaxRate::from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())
+    };
+
+    let state_rate =
+        find("")?.ok_or_else(|| format!("No sales tax rate configured for {}", state))?;
+    let county_rate = match county.map(str::trim).filter(|c| !c.is_empty()) {
+        Some(county) => find(county)?,
+        None => None,
+    };
+    Ok((state_rate, county_rate))
+}
+
+fn compute_tax(
+    amounts: DealAmounts,
+    state_rate: &TaxRate,
+    county_rate: Option<&TaxRate>,
+    rules: Option<TaxRules>,
+) -> Result<TaxBreakdown, String> {
+    if amounts.sale_amount_cents < 0
+        || amounts.trade_in_cents < 0
+        || amounts.doc_fee_cents < 0
+        || amounts.taxable_fees_cents < 0
+    {
+        return Err("Deal amounts can't be negative".to_string());
+    }
+    let rules = rules.unwrap_or_else(|| state_rate.rules());
+
+    let doc_fee_taxed_cents = if rules.doc_fee_taxable {
+        amounts.doc_fee_cents
+    } else {
+        0
+    };
+    let fees_taxed_cents = amounts.taxable_fees_cents;
+    let gross = amounts.sale_amount_cents + doc_fee_taxed_cents + fees_taxed_cents;
+    // Credit can't exceed the price; the rest of the trade-in isn't a refund
+    let trade_in_credit_cents = if rules.trade_in_credit {
+        amounts.trade_in_cents.min(gross)
+    } else {
+        0
+    };
+    let taxable_cents = gross - trade_in_credit_cents;
+
+    let lines: Vec<TaxLine> = std::iter::once(state_rate)
+        .chain(county_rate)
+        .map(|rate| tax_line(rate, taxable_cents, rules.rounding))
+        .collect();
+    let total_tax_cents = lines.iter().map(|line| line.tax_cents).sum();
+
+    Ok(TaxBreakdown {
+        state: state_rate.state.clone(),
+        county: county_rate.and_then(|rate| rate.county.clone()),
+        rules,
+        trade_in_credit_cents,
+        doc_fee_taxed_cents,
+        fees_taxed_cents,
+        taxable_cents,
+        lines,
+        total_tax_cents,
+    })
+}
+
+fn tax_line(rate: &TaxRate, taxable_cents: i64, rounding: Rounding) -> TaxLine {
+    let base_cents = rate
+        .max_taxable_cents
+        .map_or(taxable_cents, |max| taxable_cents.min(max));
+    let uncapped = apply_rate(base_cents, rate.rate_ppm, rounding);
+    let tax_cents = rate.max_tax_cents.map_or(uncapped, |max| uncapped.min(max));
+
+    TaxLine {
+        jurisdiction: rate.jurisdiction(),
+        rate_ppm: rate.rate_ppm,
+        base_cents,
+        tax_cents,
+        capped: base_cents < taxable_cents || tax_cents < uncapped,
+    }
+}
+
+/// cents * rate_ppm / 1_000_000, rounded once (i128 so large deals can't overflow)
+fn apply_rate(cents: i64, rate_ppm: i64, rounding: Rounding) -> i64 {
+    let numerator = i128::from(cents) * i128::from(rate_ppm);
+    let (quotient, remainder) = (numerator / PPM, numerator % PPM);
+    let round_up = match rounding {
+        Rounding::HalfUp => remainder * 2 >= PPM,
+        Rounding::HalfEven => remainder * 2 > PPM || (remainder * 2 == PPM && quotient % 2 == 1),
+        Rounding::Down => false,
+    };
+    (quotient + i128::from(round_up)) as i64
+}
+
+fn validate_rate(rate: TaxRate) -> Result<TaxRate, String> {
+    let state = rate.state.trim().to_ascii_uppercase();
+    if state.len() != 2 || !state.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(format!("Invalid state code: {}", rate.state));
+    }
+    if !(0..=MAX_RATE_PPM).contains(&rate.rate_ppm) {
+        return Err(format!(
+            "Rate must be between 0 and {} ppm (6.25% = 62500)",
+            MAX_RATE_PPM
+        ));
+    }
+    if rate.max_taxable_cents.is_some_and(|cents| cents < 0)
+        || rate.max_tax_cents.is_some_and(|cents| cents < 0)
+    {
+        return Err("Tax caps can't be negative".to_string());
+    }
+
+    Ok(TaxRate {
+        state,
+        county: rate
+            .county
+            .map(|county| county.trim().to_string())
+            .filter(|county| !county.is_empty()),
+        updated_at: Utc::now().timestamp_millis(),
+        ..rate
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rate(state: &str, county: Option<&str>, rate_ppm: i64) -> TaxRate {
+        TaxRate {
+            state: state.to_string(),
+            county: county.map(str::to_string),
+            rate_ppm,
+            trade_in_credit: true,
+            doc_fee_taxable: true,
+            rounding: Rounding::HalfUp,
+            max_taxable_cents: None,
+            max_tax_cents: None,
+            updated_at: 0,
+        }
+    }
+
+    fn amounts(sale: i64, trade_in: i64, doc_fee: i64) -> DealAmounts {
+        DealAmounts {
+            sale_amount_cents: sale,
+            trade_in_cents: trade_in,
+            doc_fee_cents: doc_fee,
+            taxable_fees_cents: 0,
+        }
+    }
+
+    #[test]
+    fn test_trade_in_credit_reduces_base() {
+        // 6%, trade-in credit, doc fee taxable
+        let state = rate("MI", None, 60_000);
+        let tax = compute_tax(amounts(2_500_000, 800_000, 26_000), &state, None, None).unwrap();
+
+        assert_eq!(tax.trade_in_credit_cents, 800_000);
+        assert_eq!(tax.doc_fee_taxed_cents, 26_000);
+        assert_eq!(tax.taxable_cents, 1_726_000);
+        assert_eq!(tax.total_tax_cents, 103_560);
+    }
+
+    #[test]
+    fn test_no_trade_in_credit_with_county_rate() {
+        // 7.25% state with no trade-in credit, plus 1% county
+        let state = TaxRate {
+            trade_in_credit: false,
+            ..rate("CA", None, 72_500)
+        };
+        let county = rate("CA", Some("Alameda"), 10_000);
+        let tax = compute_tax(
+            amounts(3_000_000, 1_000_000, 8_500),
+            &state,
+            Some(&county),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(tax.trade_in_credit_cents, 0);
+        assert_eq!(tax.taxable_cents, 3_008_500);
+        // 2181.1625 rounds to 2181.16
+        assert_eq!(tax.lines[0].tax_cents, 218_116);
+        assert_eq!(tax.lines[1].jurisdiction, "Alameda (CA)");
+        assert_eq!(tax.lines[1].tax_cents, 30_085);
+        assert_eq!(tax.total_tax_cents, 248_201);
+        assert_eq!(tax.county.as_deref(), Some("Alameda"));
+    }
+
+    #[test]
+    fn test_county_surtax_on_first_5000() {
+        // 6% state plus a 1% county surtax that only applies to the first $5,000
+        let state = rate("FL", None, 60_000);
+        let county = TaxRate {
+            max_taxable_cents: Some(500_000),
+            ..rate("FL", Some("Orange"), 10_000)
+        };
+        let tax = compute_tax(
+            amounts(2_000_000, 500_000, 99_900),
+            &state,
+            Some(&county),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(tax.taxable_cents, 1_599_900);
+        assert_eq!(tax.lines[0].tax_cents, 95_994);
+        assert_eq!(tax.lines[1].base_cents, 500_000);
+        assert_eq!(tax.lines[1].tax_cents, 5_000);
+        assert!(tax.lines[1].capped);
+        assert_eq!(tax.total_tax_cents, 100_994);
+    }
+
+    #[test]
+    fn test_tax_cap() {
+        // 3% with the tax capped at $2,000
+        let state = TaxRate {
+            max_tax_cents: Some(200_000),
+            ..rate("NC", None, 30_000)
+        };
+        let tax = compute_tax(amounts(9_000_000, 0, 0), &state, None, None).unwrap();
+        assert_eq!(tax.total_tax_cents, 200_000);
+        assert!(tax.lines[0].capped);
+
+        let tax = compute_tax(amounts(5_000_000, 0, 0), &state, None, None).unwrap();
+        assert_eq!(tax.total_tax_cents, 150_000);
+        assert!(!tax.lines[0].capped);
+    }
+
+    #[test]
+    fn test_rules_override_and_trade_in_larger_than_price() {
+        let state = rate("TX", None, 62_500);
+
+        // Doc fee not taxable and the trade-in is worth more than the car
+        let rules = TaxRules {
+            trade_in_credit: true,
+            doc_fee_taxable: false,
+            rounding: Rounding::HalfUp,
+        };
+        let tax =
+            compute_tax(amounts(400_000, 650_000, 15_000), &state, None, Some(rules)).unwrap();
+        assert_eq!(tax.doc_fee_taxed_cents, 0);
+        assert_eq!(tax.trade_in_credit_cents, 400_000);
+        assert_eq!(tax.taxable_cents, 0);
+        assert_eq!(tax.total_tax_cents, 0);
+
+        assert!(compute_tax(amounts(-1, 0, 0), &state, None, None).is_err());
+    }
+
+    #[test]
+    fn test_taxable_fee_lines_join_the_base() {
+        // GAP and similar taxable lines are taxed even where the doc fee isn't
+        let state = rate("TX", None, 62_500);
+        let rules = TaxRules {
+            trade_in_credit: true,
+            doc_fee_taxable: false,
+            rounding: Rounding::HalfUp,
+        };
+        let deal_amounts = DealAmounts {
+            taxable_fees_cents: 59_500,
+            ..amounts(2_000_000, 500_000, 15_000)
+        };
+        let tax = compute_tax(deal_amounts, &state, None, Some(rules)).unwrap();
+        assert_eq!(tax.doc_fee_taxed_cents, 0);
+        assert_eq!(tax.fees_taxed_cents, 59_500);
+        assert_eq!(tax.taxable_cents, 1_559_500);
+        assert_eq!(tax.total_tax_cents, 97_469);
+    }
+
+    #[test]
+    fn test_rounding_modes() {
+        // 50 cents at 1% is exactly half a cent; 150 cents is 1.5 cents
+        assert_eq!(apply_rate(50, 10_000, Rounding::HalfUp), 1);
+        assert_eq!(apply_rate(50, 10_000, Rounding::HalfEven), 0);
+        assert_eq!(apply_rate(150, 10_000, Rounding::HalfEven), 2);
+        assert_eq!(apply_rate(199, 10_000, Rounding::Down), 1);
+        // Large amounts don't overflow
+        assert_eq!(
+            apply_rate(i64::MAX / 4, MAX_RATE_PPM, Rounding::HalfUp),
+            (i64::MAX / 4) / 4 + 1
+        );
+    }
+
+    #[test]
+    fn test_validate_rate() {
+        let valid = validate_rate(TaxRate {
+            county: Some("  ".to_string()),
+            ..rate(" mi ", None, 60_000)
+        })
+        .unwrap();
+        assert_eq!(valid.state, "MI");
+        assert_eq!(valid.county, None);
+
+        assert!(validate_rate(rate("Michigan", None, 60_000)).is_err());
+        assert!(validate_rate(rate("MI", None, 6)).is_ok());
+        assert!(validate_rate(rate("MI", None, 600_000)).is_err());
+        assert!(validate_rate(TaxRate {
+            max_tax_cents: Some(-1),
+            ..rate("MI", None, 60_000)
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,534 @@
+// src-tauri/src/telemetry.rs
+//
+// Per-command timing for the diagnostics screen
+// Command handlers wrap their body in track()/track_async(), which records the command
+// name, duration and error code into an in-memory ring buffer. Arguments and error
+// messages are never recorded (they can hold customer PII); only the serde tag of a typed
+// error ("kind"/"code") is kept.
+// Optionally the samples are copied to the command_metrics table every few minutes.
+// Writes that had to wait on a locked database (db_busy.rs) are counted here too, and so are
+// slow statements (db_timeout.rs), by command name and duration only. track() also gives the
+// command its database time budget.
+
+use log::{info, warn};
+use rusqlite::params;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::database::{db_get_setting, get_db};
+use crate::db_timeout::{with_command_budget, SLOW_QUERY_THRESHOLD};
+use crate::settings_cache::set_setting;
+
+/// Settings keys
+pub const COMMAND_METRICS_SETTING: &str = "command_metrics_enabled"; // "false" = off
+pub const COMMAND_METRICS_PERSIST_SETTING: &str = "command_metrics_persist"; // "true" = on
+
+/// Samples kept for the percentiles (shared by all commands)
+const SAMPLE_CAPACITY: usize = 2000;
+const ERROR_CAPACITY: usize = 100;
+const SLOW_QUERY_CAPACITY: usize = 50;
+const PERSIST_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const PERSIST_RETENTION_SECS: i64 = 30 * 24 * 60 * 60;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+static PERSIST: AtomicBool = AtomicBool::new(false);
+static METRICS: Mutex<CommandMetrics> = Mutex::new(CommandMetrics::new());
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Sample {
+    command: &'static str,
+    duration_us: u64,
+    error_code: Option<String>,
+    recorded_at: i64, // Unix seconds
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CommandErrorRecord {
+    pub command: String,
+    pub code: String,
+    pub at: i64, // Unix seconds
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandStats {
+    pub command: String,
+    pub count: usize,
+    pub error_count: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Writes that found the database locked by another connection
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LockWaitStats {
+    pub count: u64,
+    /// Waits that ran out of retries and failed with DbError::Busy
+    pub gave_up: u64,
+    pub longest_wait_ms: f64,
+    pub last_category: Option<&'static str>,
+    pub last_at: Option<i64>, // Unix seconds
+}
+
+impl LockWaitStats {
+    const fn new() -> Self {
+        Self {
+            count: 0,
+            gave_up: 0,
+            longest_wait_ms: 0.0,
+            last_category: None,
+            last_at: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlowQueryRecord {
+    /// Command that ran the statement, or "background"
+    pub category: &'static str,
+    pub duration_ms: f64,
+    pub at: i64, // Unix seconds
+}
+
+/// Statements that ran longer than SLOW_QUERY_THRESHOLD
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlowQueryReport {
+    pub threshold_ms: f64,
+    pub count: u64,
+    /// Newest first
+    pub recent: Vec<SlowQueryRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CommandMetricsReport {
+    pub enabled: bool,
+    pub persist: bool,
+    /// Stats cover the most recent `sample_count` calls across all commands
+    pub sample_count: usize,
+    pub commands: Vec<CommandStats>,
+    /// Newest first
+    pub recent_errors: Vec<CommandErrorRecord>,
+    pub lock_waits: LockWaitStats,
+    pub slow_queries: SlowQueryReport,
+}
+
+/// Fixed-capacity buffer that overwrites its oldest entry when full
+#[derive(Debug)]
+struct RingBuffer<T> {
+    items: Vec<T>,
+    capacity: usize,
+    next: usize,
+}
+
+impl<T> RingBuffer<T> {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            items: Vec::new(),
+            capacity,
+            next: 0,
+        }
+    }
+
+    fn push(&mut self, item: T) {
+        if self.items.len() < self.capacity {
+            self.items.push(item);
+        } else {
+            self.items[self.next] = item;
+        }
+        self.next = (self.next + 1) % self.capacity;
+    }
+
+    /// Oldest to newest
+    fn iter(&self) -> impl DoubleEndedIterator<Item = &T> {
+        let (newer, older) = if self.items.len() < self.capacity {
+            (&self.items[..], &self.items[..0])
+        } else {
+            self.items.split_at(self.next)
+        };
+        older.iter().chain(newer.iter())
+    }
+
+    fn len(&self) -> usize {
+        self.items.len()
+    }
+}
+
+struct CommandMetrics {
+    samples: RingBuffer<Sample>,
+    errors: RingBuffer<CommandErrorRecord>,
+    /// Samples not yet written to the command_metrics table (only while persisting)
+    unpersisted: Vec<Sample>,
+    lock_waits: LockWaitStats,
+    slow_queries: RingBuffer<SlowQueryRecord>,
+    slow_query_count: u64,
+}
+
+impl CommandMetrics {
+    const fn new() -> Self {
+        Self {
+            samples: RingBuffer::new(SAMPLE_CAPACITY),
+            errors: RingBuffer::new(ERROR_CAPACITY),
+            unpersisted: Vec::new(),
+            lock_waits: LockWaitStats::new(),
+            slow_queries: RingBuffer::new(SLOW_QUERY_CAPACITY),
+            slow_query_count: 0,
+        }
+    }
+
+    fn record(&mut self, sample: Sample, persist: bool) {
+        if let Some(code) = &sample.error_code {
+            self.errors.push(CommandErrorRecord {
+                command: sample.command.to_string(),
+                code: code.clone(),
+                at: sample.recorded_at,
+            });
+        }
+        // Bounded like the ring so a failing flush can't grow it forever
+        if persist && self.unpersisted.len() < SAMPLE_CAPACITY {
+            self.unpersisted.push(sample.clone());
+        }
+        self.samples.push(sample);
+    }
+
+    fn stats(&self) -> Vec<CommandStats> {
+        let mut by_command: BTreeMap<&str, (Vec<u64>, usize)> = BTreeMap::new();
+        for sample in self.samples.iter() {
+            let entry = by_command.entry(sample.command).or_default();
+            entry.0.push(sample.duration_us);
+            if sample.error_code.is_some() {
+                entry.1 += 1;
+            }
+        }
+
+        by_command
+            .into_iter()
+            .map(|(command, (mut durations, error_count))| {
+                durations.sort_unstable();
+                CommandStats {
+                    command: command.to_string(),
+                    count: durations.len(),
+                    error_count,
+                    p50_ms: us_to_ms(percentile(&durations, 0.50)),
+                    p95_ms: us_to_ms(percentile(&durations, 0.95)),
+                    max_ms: us_to_ms(durations.last().copied().unwrap_or(0)),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Time a command body and record the outcome. The body's statements share the
+/// command's database time budget (db_timeout.rs)
+pub fn track<T, E: Serialize>(
+    command: &'static str,
+    body: impl FnOnce() -> Result<T, E>,
+) -> Result<T, E> {
+    let started = Instant::now();
+    let (result, timed_out) = with_command_budget(command, body);
+    if ENABLED.load(Ordering::Relaxed) {
+        let error_code = result.as_ref().err().map(|error| {
+            if timed_out {
+                "timeout".to_string()
+            } else {
+                error_code(error)
+            }
+        });
+        record(command, started.elapsed(), error_code);
+    }
+    result
+}
+
+/// track() for async commands. An async body can move between threads, so it gets no
+/// database time budget
+pub async fn track_async<T, E: Serialize>(
+    command: &'static str,
+    body: impl Future<Output = Result<T, E>>,
+) -> Result<T, E> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return body.await;
+    }
+    let started = Instant::now();
+    let result = body.await;
+    record(
+        command,
+        started.elapsed(),
+        result.as_ref().err().map(error_code),
+    );
+    result
+}
+
+/// Per-command count/p50/p95/max and the last errors
+#[tauri::command]
+pub fn get_command_metrics() -> CommandMetricsReport {
+    let metrics = metrics();
+    CommandMetricsReport {
+        enabled: ENABLED.load(Ordering::Relaxed),
+        persist: PERSIST.load(Ordering::Relaxed),
+        sample_count: metrics.samples.len(),
+        commands: metrics.stats(),
+        recent_errors: metrics.errors.iter().rev().cloned().collect(),
+        lock_waits: metrics.lock_waits.clone(),
+        slow_queries: SlowQueryReport {
+            threshold_ms: SLOW_QUERY_THRESHOLD.as_secs_f64() * 1000.0,
+            count: metrics.slow_query_count,
+            recent: metrics.slow_queries.iter().rev().cloned().collect(),
+        },
+    }
+}
+
+/// Turn timing on/off, and optionally the copy to the local command_metrics table
+#[tauri::command]
+pub fn set_command_metrics_enabled(enabled: bool, persist: Option<bool>) -> Result<(), String> {
+    set_setting(COMMAND_METRICS_SETTING, &enabled.to_string())?;
+    ENABLED.store(enabled, Ordering::Relaxed);
+
+    if let Some(persist) = persist {
+        set_setting(COMMAND_METRICS_PERSIST_SETTING, &persist.to_string())?;
+        PERSIST.store(persist, Ordering::Relaxed);
+    }
+    if !enabled || persist == Some(false) {
+        metrics().unpersisted.clear();
+    }
+
+    info!(
+        "📈 Command metrics {}",
+        if enabled { "enabled" } else { "disabled" }
+    );
+    Ok(())
+}
+
+/// Apply the saved settings. Call once the database is initialized
+pub fn apply_saved_settings() {
+    let setting = |key: &str| db_get_setting(key.to_string()).ok().flatten();
+
+    ENABLED.store(
+        setting(COMMAND_METRICS_SETTING).is_none_or(|value| value.trim() != "false"),
+        Ordering::Relaxed,
+    );
+    PERSIST.store(
+        setting(COMMAND_METRICS_PERSIST_SETTING).is_some_and(|value| value.trim() == "true"),
+        Ordering::Relaxed,
+    );
+}
+
+/// Periodically copy samples to the command_metrics table while persisting is on
+pub fn start_persistence() {
+    std::thread::spawn(|| loop {
+        std::thread::sleep(PERSIST_INTERVAL);
+        if !PERSIST.load(Ordering::Relaxed) {
+            continue;
+        }
+        if let Err(e) = persist_samples() {
+            warn!("⚠️  Failed to persist command metrics: {}", e);
+        }
+    });
+}
+
+fn persist_samples() -> Result<(), String> {
+    let samples = std::mem::take(&mut metrics().unpersisted);
+    let db = get_db().map_err(|e| e.to_string())?;
+    let mut conn = db.try_conn()?;
+    let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+    for sample in &samples {
+        tx.execute(
+            "INSERT INTO command_metrics (command, duration_us, error_code, recorded_at)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                sample.command,
+                sample.duration_us as i64,
+                sample.error_code,
+                sample.recorded_at
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.execute(
+        "DELETE FROM command_metrics WHERE recorded_at < ?1",
+        params![chrono::Utc::now().timestamp() - PERSIST_RETENTION_SECS],
+    )
+    .map_err(|e| e.to_string())?;
+
+    tx.commit().map_err(|e| e.to_string())
+}
+
+fn record(command: &'static str, elapsed: Duration, error_code: Option<String>) {
+    let sample = Sample {
+        command,
+        duration_us: elapsed.as_micros() as u64,
+        error_code,
+        recorded_at: chrono::Utc::now().timestamp(),
+    };
+    metrics().record(sample, PERSIST.load(Ordering::Relaxed));
+}
+
+/// Note a write that waited on a locked database (db_busy.rs)
+pub(crate) fn record_lock_wait(category: &'static str, waited: Duration, gave_up: bool) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut metrics = metrics();
+    let waits = &mut metrics.lock_waits;
+    waits.count += 1;
+    if gave_up {
+        waits.gave_up += 1;
+    }
+    waits.longest_wait_ms = waits
+        .longest_wait_ms
+        .max(us_to_ms(waited.as_micros() as u64));
+    waits.last_category = Some(category);
+    waits.last_at = Some(chrono::Utc::now().timestamp());
+}
+
+/// Note a statement slower than SLOW_QUERY_THRESHOLD (db_timeout.rs)
+pub(crate) fn record_slow_query(category: &'static str, elapsed: Duration) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut metrics = metrics();
+    metrics.slow_query_count += 1;
+    metrics.slow_queries.push(SlowQueryRecord {
+        category,
+        duration_ms: us_to_ms(elapsed.as_micros() as u64),
+        at: chrono::Utc::now().timestamp(),
+    });
+}
+
+/// A command's panic must not turn metrics off for the rest of the session
+fn metrics() -> MutexGuard<'static, CommandMetrics> {
+    METRICS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The serde tag of a typed error, or "error" for plain string errors (whose text may hold PII)
+fn error_code<E: Serialize>(error: &E) -> String {
+    serde_json::to_value(error)
+        .ok()
+        .and_then(|value| {
+            ["kind", "code", "status"]
+                .iter()
+                .find_map(|tag| value.get(*tag)?.as_str().map(str::to_string))
+        })
+        .unwrap_or_else(|| "error".to_string())
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], quantile: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (quantile * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn us_to_ms(us: u64) -> f64 {
+    us as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk_space::DiskSpaceError;
+
+    fn sample(command: &'static str, duration_us: u64, error_code: Option<&str>) -> Sample {
+        Sample {
+            command,
+            duration_us,
+            error_code: error_code.map(str::to_string),
+            recorded_at: 0,
+        }
+    }
+
+    #[test]
+    fn test_ring_buffer_wraps_around() {
+        let mut ring = RingBuffer::new(3);
+        assert_eq!(ring.iter().count(), 0);
+
+        ring.push(1);
+        ring.push(2);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), vec![1, 2]);
+
+        for n in 3..=7 {
+            ring.push(n);
+        }
+        assert_eq!(ring.len(), 3);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), vec![5, 6, 7]);
+        assert_eq!(
+            ring.iter().rev().copied().collect::<Vec<_>>(),
+            vec![7, 6, 5]
+        );
+
+        // Exactly full, pointer back at the start
+        ring.push(8);
+        ring.push(9);
+        assert_eq!(ring.iter().copied().collect::<Vec<_>>(), vec![7, 8, 9]);
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let values: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&values, 0.50), 50);
+        assert_eq!(percentile(&values, 0.95), 95);
+        assert_eq!(percentile(&values, 1.0), 100);
+
+        assert_eq!(percentile(&[7], 0.50), 7);
+        assert_eq!(percentile(&[7], 0.95), 7);
+        assert_eq!(percentile(&[10, 20, 30], 0.50), 20);
+        assert_eq!(percentile(&[10, 20, 30], 0.95), 30);
+        assert_eq!(percentile(&[], 0.50), 0);
+    }
+
+    #[test]
+    fn test_stats_errors_and_window() {
+        let mut metrics = CommandMetrics::new();
+        for ms in 1..=20 {
+            metrics.record(sample("db_get_client", ms * 1000, None), false);
+        }
+        metrics.record(sample("s3_upload_document", 900_000, Some("error")), true);
+
+        let stats = metrics.stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].command, "db_get_client");
+        assert_eq!(stats[0].count, 20);
+        assert_eq!(stats[0].p50_ms, 10.0);
+        assert_eq!(stats[0].p95_ms, 19.0);
+        assert_eq!(stats[0].max_ms, 20.0);
+        assert_eq!(stats[1].error_count, 1);
+        assert_eq!(metrics.unpersisted.len(), 1);
+
+        // Only the last ERROR_CAPACITY errors survive
+        for n in 0..ERROR_CAPACITY + 5 {
+            let code = if n == ERROR_CAPACITY + 4 {
+                "newest"
+            } else {
+                "old"
+            };
+            metrics.record(sample("db_create_document", 1, Some(code)), false);
+        }
+        assert_eq!(metrics.errors.len(), ERROR_CAPACITY);
+        assert_eq!(metrics.errors.iter().last().unwrap().code, "newest");
+    }
+
+    #[test]
+    fn test_error_codes_never_include_messages() {
+        let message = "Failed to open /Users/jane.doe/Clients/Smith.pdf".to_string();
+        assert_eq!(error_code(&message), "error");
+
+        let disk = DiskSpaceError::InsufficientSpace {
+            path: "/Users/jane.doe".to_string(),
+            available_bytes: 1,
+            required_bytes: 2,
+        };
+        assert_eq!(error_code(&disk), "insufficient_space");
+
+        let result: Result<(), String> = track("telemetry_test_command", || Err(message.clone()));
+        assert!(result.is_err());
+        let report = get_command_metrics();
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains("telemetry_test_command"));
+        assert!(!json.contains("jane.doe"));
+    }
+}
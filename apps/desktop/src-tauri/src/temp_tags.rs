@@ -0,0 +1,792 @@
+// src-tauri/src/temp_tags.rs
+//
+// The dealer's temporary tag inventory, for the state's audit of which tag went on which
+// deal. import_temp_tags adds a range of numbers ("TX0001" to "TX0250") as available;
+// issue_tag_to_deal hands the deal the next one (oldest import first, then in number order),
+// writes it to deals.temp_tag_number and, when temp_tag_fee_cents is set, adds a temp tag
+// fee line unless the deal already has one. void_tag takes a tag out of use for good, with
+// a reason. Tags are never deleted or reissued.
+//
+// Issuing picks and claims the tag inside one BEGIN IMMEDIATE transaction, so two deals
+// issued at once, from two windows or two machines on a shared database, never get the
+// same tag; the unique index on issued deal_id (migration 34) backs that up.
+//
+// Every issued tag gets a follow-up task on the deal, due temp_tag_reminder_days before the
+// tag expires (temp_tag_valid_days after issue), so start_task_reminders announces it with
+// the other due tasks. Voiding the tag removes the task.
+
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit_log::record_audit;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{begin_write, new_row_id, read_setting, DbState};
+use crate::datetime::{dealer_timezone, local_date};
+use crate::db_busy::DbError;
+use crate::deal_fees::{insert_fee, FeeCategory, NewDealFee};
+use crate::recent_items::EntityType;
+use crate::settings_cache::pending_setting;
+use crate::tasks::{create_task, NewTask};
+use crate::telemetry::track;
+
+/// Settings keys
+pub const TEMP_TAG_VALID_DAYS_SETTING: &str = "temp_tag_valid_days";
+/// Fee added to the deal with its tag, in cents; unset or 0 adds none
+pub const TEMP_TAG_FEE_SETTING: &str = "temp_tag_fee_cents";
+pub const TEMP_TAG_REMINDER_DAYS_SETTING: &str = "temp_tag_reminder_days";
+
+const DEFAULT_VALID_DAYS: i64 = 30;
+const DEFAULT_REMINDER_DAYS: i64 = 5;
+/// Most tags one import may add
+const MAX_IMPORT: u64 = 5_000;
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagStatus {
+    Available,
+    Issued,
+    Void,
+}
+
+impl TagStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TagStatus::Available => "available",
+            TagStatus::Issued => "issued",
+            TagStatus::Void => "void",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "available" => Some(TagStatus::Available),
+            "issued" => Some(TagStatus::Issued),
+            "void" => Some(TagStatus::Void),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TempTag {
+    pub id: String,
+    pub user_id: String,
+    pub tag_number: String,
+    pub status: TagStatus,
+    pub deal_id: Option<String>,
+    pub issued_at: Option<i64>,
+    pub expires_at: Option<i64>,
+    pub void_reason: Option<String>,
+    pub voided_at: Option<i64>,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl TempTag {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        let status: String = row.get("status")?;
+        Ok(TempTag {
+            id: row.get("id")?,
+            user_id: row.get("user_id")?,
+            tag_number: row.get("tag_number")?,
+            status: TagStatus::parse(&status).unwrap_or(TagStatus::Void),
+            deal_id: row.get("deal_id")?,
+            issued_at: row.get("issued_at")?,
+            expires_at: row.get("expires_at")?,
+            void_reason: row.get("void_reason")?,
+            voided_at: row.get("voided_at")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TempTagImport {
+    pub imported: usize,
+    pub first_tag: String,
+    pub last_tag: String,
+}
+
+/// One tag issued or voided in the report's range
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagReportRow {
+    pub tag_number: String,
+    pub status: TagStatus,
+    pub deal_id: Option<String>,
+    pub buyer_name: Option<String>,
+    pub vin: Option<String>,
+    pub issued_at: Option<i64>,
+    pub expires_at: Option<i64>,
+    pub void_reason: Option<String>,
+    pub voided_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagReport {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub issued: usize,
+    pub voided: usize,
+    /// Tags on hand now
+    pub available: i64,
+    pub rows: Vec<TagReportRow>,
+}
+
+fn setting_i64(conn: &Connection, key: &str) -> Option<i64> {
+    pending_setting(key)
+        .or_else(|| read_setting(conn, key).ok().flatten())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// "TX0042" -> ("TX", "0042"); None without a trailing number
+fn split_tag(tag: &str) -> Option<(&str, &str)> {
+    let (prefix, number) = tag.split_at(tag.trim_end_matches(|c: char| c.is_ascii_digit()).len());
+    (!number.is_empty()).then_some((prefix, number))
+}
+
+/// Every tag number from first to last: same prefix, same number of digits, first <= last
+fn tag_range(first: &str, last: &str) -> Result<Vec<String>, String> {
+    let (first, last) = (first.trim().to_uppercase(), last.trim().to_uppercase());
+    for tag in [&first, &last] {
+        if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("Invalid tag number '{}'", tag));
+        }
+    }
+    let (Some((prefix, start)), Some((last_prefix, end))) = (split_tag(&first), split_tag(&last))
+    else {
+        return Err("Tag numbers must end in a number".to_string());
+    };
+    if prefix != last_prefix || start.len() != end.len() {
+        return Err(format!(
+            "{} and {} aren't from the same series (same prefix and number of digits)",
+            first, last
+        ));
+    }
+    let width = start.len();
+    let parse = |digits: &str| {
+        digits
+            .parse::<u64>()
+            .map_err(|_| format!("Tag number {} is too long", digits))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start > end {
+        return Err(format!("{} comes after {}", first, last));
+    }
+    if end - start + 1 > MAX_IMPORT {
+        return Err(format!(
+            "At most {} tags can be imported at once",
+            MAX_IMPORT
+        ));
+    }
+    Ok((start..=end)
+        .map(|n| format!("{}{:0width$}", prefix, n, width = width))
+        .collect())
+}
+
+fn import_range(
+    conn: &Connection,
+    user_id: &str,
+    first: &str,
+    last: &str,
+    now: i64,
+) -> Result<TempTagImport, DbError> {
+    let tags = tag_range(first, last)?;
+    let (first_tag, last_tag) = (tags[0].clone(), tags[tags.len() - 1].clone());
+
+    let tx = begin_write(conn, "temp_tags")?;
+    {
+        let mut exists = tx
+            .prepare("SELECT 1 FROM temp_tags WHERE user_id = ?1 AND tag_number = ?2")
+            .map_err(|e| e.to_string())?;
+        for tag in &tags {
+            if exists
+                .exists(params![user_id, tag])
+                .map_err(|e| e.to_string())?
+            {
+                return Err(format!("Tag {} is already in the inventory", tag).into());
+            }
+        }
+        let mut insert = tx
+            .prepare(
+                "INSERT INTO temp_tags (id, user_id, tag_number, status, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, 'available', ?4, ?4)",
+            )
+            .map_err(|e| e.to_string())?;
+        for tag in &tags {
+            insert
+                .execute(params![new_row_id(), user_id, tag, now])
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    record_audit(
+        &tx,
+        user_id,
+        "temp_tags_imported",
+        &json!({ "first_tag": first_tag, "last_tag": last_tag, "count": tags.len() }),
+        now,
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(TempTagImport {
+        imported: tags.len(),
+        first_tag,
+        last_tag,
+    })
+}
+
+fn tag_by_number(conn: &Connection, user_id: &str, tag_number: &str) -> SqlResult<Option<TempTag>> {
+    conn.query_row(
+        "SELECT * FROM temp_tags WHERE user_id = ?1 AND tag_number = ?2",
+        params![user_id, tag_number],
+        TempTag::from_row,
+    )
+    .optional()
+}
+
+/// Give the deal the next available tag, with its fee line and expiry reminder
+fn issue_tag(
+    conn: &Connection,
+    user_id: &str,
+    deal_id: &str,
+    now: i64,
+) -> Result<TempTag, DbError> {
+    // BEGIN IMMEDIATE: nobody else can pick a tag between our SELECT and UPDATE
+    let tx = begin_write(conn, "temp_tags")?;
+    let deal_exists = tx
+        .query_row(
+            "SELECT 1 FROM deals WHERE id = ?1 AND user_id = ?2",
+            params![deal_id, user_id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if deal_exists.is_none() {
+        return Err("Deal not found or access denied".to_string().into());
+    }
+    let current: Option<String> = tx
+        .query_row(
+            "SELECT tag_number FROM temp_tags WHERE deal_id = ?1 AND status = 'issued'",
+            params![deal_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(current) = current {
+        return Err(format!("This deal already has temp tag {}", current).into());
+    }
+
+    let next: Option<(String, String)> = tx
+        .query_row(
+            "SELECT id, tag_number FROM temp_tags WHERE user_id = ?1 AND status = 'available'
+             ORDER BY created_at, LENGTH(tag_number), tag_number LIMIT 1",
+            params![user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let Some((id, tag_number)) = next else {
+        return Err("No temp tags left; import more first".to_string().into());
+    };
+
+    let valid_days = setting_i64(&tx, TEMP_TAG_VALID_DAYS_SETTING)
+        .filter(|days| *days > 0)
+        .unwrap_or(DEFAULT_VALID_DAYS);
+    let expires_at = now + valid_days * DAY_MS;
+    let claimed = tx
+        .execute(
+            "UPDATE temp_tags SET status = 'issued', deal_id = ?2, issued_at = ?3,
+                                  expires_at = ?4, updated_at = ?3
+             WHERE id = ?1 AND status = 'available'",
+            params![id, deal_id, now, expires_at],
+        )
+        .map_err(|e| e.to_string())?;
+    if claimed != 1 {
+        return Err(format!(
+            "Temp tag {} was just issued elsewhere, try again",
+            tag_number
+        )
+        .into());
+    }
+    tx.execute(
+        "UPDATE deals SET temp_tag_number = ?2, updated_at = ?3 WHERE id = ?1",
+        params![deal_id, tag_number, now],
+    )
+    .map_err(|e| e.to_string())?;
+
+    if let Some(fee_cents) = setting_i64(&tx, TEMP_TAG_FEE_SETTING).filter(|cents| *cents > 0) {
+        let has_fee = tx
+            .query_row(
+                "SELECT 1 FROM deal_fees WHERE deal_id = ?1 AND category = ?2",
+                params![deal_id, FeeCategory::TempTag.as_str()],
+                |_| Ok(()),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .is_some();
+        if !has_fee {
+            let fee = NewDealFee {
+                label: "Temporary tag".to_string(),
+                amount_cents: fee_cents,
+                taxable: false,
+                category: FeeCategory::TempTag,
+                sort_order: None,
+            };
+            insert_fee(&tx, user_id, deal_id, &fee, now).map_err(|e| e.to_string())?;
+        }
+    }
+
+    let reminder_days = setting_i64(&tx, TEMP_TAG_REMINDER_DAYS_SETTING)
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_REMINDER_DAYS);
+    let expires_on = local_date(expires_at, &dealer_timezone(&tx))
+        .map(|date| date.format("%m/%d/%Y").to_string())
+        .unwrap_or_default();
+    let reminder = NewTask {
+        title: format!("Temp tag {} expires {}", tag_number, expires_on),
+        body: Some("Finish the title and plates before the temp tag runs out.".to_string()),
+        entity_type: Some(EntityType::Deal),
+        entity_id: Some(deal_id.to_string()),
+        due_at: (expires_at - reminder_days * DAY_MS).max(now),
+    };
+    let task = create_task(&tx, user_id, reminder, now)?;
+    tx.execute(
+        "UPDATE temp_tags SET reminder_task_id = ?2 WHERE id = ?1",
+        params![id, task.id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    record_audit(
+        &tx,
+        user_id,
+        "temp_tag_issued",
+        &json!({ "tag_number": tag_number, "deal_id": deal_id }),
+        now,
+    )
+    .map_err(|e| e.to_string())?;
+    let tag = tag_by_number(&tx, user_id, &tag_number)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Temp tag not found".to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(tag)
+}
+
+/// Take a tag out of use for good; an issued tag comes off its deal
+fn void(
+    conn: &Connection,
+    user_id: &str,
+    tag_number: &str,
+    reason: &str,
+    now: i64,
+) -> Result<TempTag, DbError> {
+    let tag_number = tag_number.trim().to_uppercase();
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err("A reason is required to void a tag".to_string().into());
+    }
+    let tx = begin_write(conn, "temp_tags")?;
+    let tag = tag_by_number(&tx, user_id, &tag_number)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Temp tag {} is not in the inventory", tag_number))?;
+    if tag.status == TagStatus::Void {
+        return Err(format!("Temp tag {} is already void", tag_number).into());
+    }
+
+    if let Some(deal_id) = tag
+        .deal_id
+        .as_deref()
+        .filter(|_| tag.status == TagStatus::Issued)
+    {
+        tx.execute(
+            "UPDATE deals SET temp_tag_number = NULL, updated_at = ?3
+             WHERE id = ?1 AND temp_tag_number = ?2",
+            params![deal_id, tag_number, now],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute(
+            "DELETE FROM tasks WHERE id = (SELECT reminder_task_id FROM temp_tags WHERE id = ?1)
+                 AND completed_at IS NULL",
+            params![tag.id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    tx.execute(
+        "UPDATE temp_tags SET status = 'void', void_reason = ?2, voided_at = ?3, updated_at = ?3
+         WHERE id = ?1",
+        params![tag.id, reason, now],
+    )
+    .map_err(|e| e.to_string())?;
+    record_audit(
+        &tx,
+        user_id,
+        "temp_tag_voided",
+        &json!({ "tag_number": tag_number, "deal_id": tag.deal_id, "reason": reason }),
+        now,
+    )
+    .map_err(|e| e.to_string())?;
+    let tag = tag_by_number(&tx, user_id, &tag_number)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Temp tag not found".to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(tag)
+}
+
+/// Tags issued or voided in [start_ms, end_ms], in tag order
+fn report(conn: &Connection, user_id: &str, start_ms: i64, end_ms: i64) -> SqlResult<TagReport> {
+    let mut stmt = conn.prepare(
+        "SELECT t.tag_number, t.status, t.deal_id, t.issued_at, t.expires_at, t.void_reason,
+                t.voided_at, TRIM(c.first_name || ' ' || c.last_name), v.vin
+         FROM temp_tags t
+         LEFT JOIN deals d ON d.id = t.deal_id
+         LEFT JOIN clients c ON c.id = d.client_id
+         LEFT JOIN vehicles v ON v.id = d.vehicle_id
+         WHERE t.user_id = ?1
+           AND ((t.issued_at BETWEEN ?2 AND ?3) OR (t.voided_at BETWEEN ?2 AND ?3))
+         ORDER BY LENGTH(t.tag_number), t.tag_number",
+    )?;
+    let rows = stmt
+        .query_map(params![user_id, start_ms, end_ms], |row| {
+            let status: String = row.get(1)?;
+            Ok(TagReportRow {
+                tag_number: row.get(0)?,
+                status: TagStatus::parse(&status).unwrap_or(TagStatus::Void),
+                deal_id: row.get(2)?,
+                issued_at: row.get(3)?,
+                expires_at: row.get(4)?,
+                void_reason: row.get(5)?,
+                voided_at: row.get(6)?,
+                buyer_name: row.get(7)?,
+                vin: row.get(8)?,
+            })
+        })?
+        .collect::<SqlResult<Vec<_>>>()?;
+    let available = conn.query_row(
+        "SELECT COUNT(*) FROM temp_tags WHERE user_id = ?1 AND status = 'available'",
+        params![user_id],
+        |row| row.get(0),
+    )?;
+    let in_range = |at: Option<i64>| at.is_some_and(|at| (start_ms..=end_ms).contains(&at));
+    Ok(TagReport {
+        start_ms,
+        end_ms,
+        issued: rows.iter().filter(|row| in_range(row.issued_at)).count(),
+        voided: rows.iter().filter(|row| in_range(row.voided_at)).count(),
+        available,
+        rows,
+    })
+}
+
+/// Add tags first_tag through last_tag (e.g. "TX0001" to "TX0250") as available. Nothing
+/// is added if any of them is already in the inventory
+#[tauri::command]
+pub fn import_temp_tags(
+    first_tag: String,
+    last_tag: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<TempTagImport, DbError> {
+    track("import_temp_tags", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let import = import_range(
+            &conn,
+            &user_id_value,
+            &first_tag,
+            &last_tag,
+            Utc::now().timestamp_millis(),
+        )?;
+        info!(
+            "✅ Imported {} temp tags ({} to {})",
+            import.imported, import.first_tag, import.last_tag
+        );
+        Ok(import)
+    })
+}
+
+#[tauri::command]
+pub fn issue_tag_to_deal(
+    deal_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<TempTag, DbError> {
+    track("issue_tag_to_deal", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let tag = issue_tag(
+            &conn,
+            &user_id_value,
+            &deal_id,
+            Utc::now().timestamp_millis(),
+        )?;
+        data_changed(
+            &user_id_value,
+            ChangedEntity::Deal,
+            &deal_id,
+            Operation::Update,
+        );
+        info!("✅ Temp tag {} issued", tag.tag_number);
+        Ok(tag)
+    })
+}
+
+#[tauri::command]
+pub fn void_tag(
+    tag_number: String,
+    reason: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<TempTag, DbError> {
+    track("void_tag", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let tag = void(
+            &conn,
+            &user_id_value,
+            &tag_number,
+            &reason,
+            Utc::now().timestamp_millis(),
+        )?;
+        if let Some(deal_id) = &tag.deal_id {
+            data_changed(
+                &user_id_value,
+                ChangedEntity::Deal,
+                deal_id,
+                Operation::Update,
+            );
+        }
+        info!("✅ Temp tag {} voided", tag.tag_number);
+        Ok(tag)
+    })
+}
+
+/// Tags issued or voided between start_ms and end_ms, for the state's audit
+#[tauri::command]
+pub fn get_tag_report(
+    start_ms: i64,
+    end_ms: i64,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<TagReport, String> {
+    track("get_tag_report", || {
+        let user_id_value = state.require_user(user_id)?;
+        if end_ms < start_ms {
+            return Err("End date is before start date".to_string());
+        }
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        report(&conn, &user_id_value, start_ms, end_ms).map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{insert_deal, upsert_setting, Database};
+    use crate::deal_fees::list_fees;
+    use crate::test_support::{make_deal, temp_dir, TEST_USER};
+    use std::collections::HashSet;
+    use std::fs;
+    use std::thread;
+
+    const NOW: i64 = 1_790_000_000_000;
+
+    /// Deals d1..=d{count} for TEST_USER, on one client and vehicle
+    fn add_deals(conn: &Connection, count: usize) {
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, created_at, updated_at, user_id)
+             VALUES ('c1', 'Jane', 'Doe', 1, 1, 'test-user');
+             INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status,
+                 created_at, updated_at, user_id)
+             VALUES ('v1', '1HGCM82633A004352', 2021, 'Honda', 'Civic', 100, 18500,
+                 'sold', 1, 1, 'test-user');",
+        )
+        .unwrap();
+        for n in 1..=count {
+            insert_deal(conn, &make_deal(&format!("d{}", n), "c1", "v1"), TEST_USER).unwrap();
+        }
+    }
+
+    fn deal_tag(conn: &Connection, deal_id: &str) -> Option<String> {
+        conn.query_row(
+            "SELECT temp_tag_number FROM deals WHERE id = ?1",
+            [deal_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    fn message(error: DbError) -> String {
+        error.to_string()
+    }
+
+    #[test]
+    fn test_import_ranges_are_validated() {
+        assert_eq!(
+            tag_range("tx0998", "TX1001").unwrap(),
+            ["TX0998", "TX0999", "TX1000", "TX1001"]
+        );
+        assert_eq!(tag_range("42", "42").unwrap(), ["42"]);
+        assert!(tag_range("TX0005", "TX0001").is_err());
+        assert!(tag_range("TX0001", "TY0005").is_err());
+        assert!(tag_range("TX001", "TX0005").is_err());
+        assert!(tag_range("TX", "TX").is_err());
+        assert!(tag_range("TX 01", "TX 05").is_err());
+        assert!(tag_range("T00001", "T10000").is_err(), "over MAX_IMPORT");
+
+        let db = Database::init_in_memory().unwrap();
+        let conn = db.conn();
+        let import = import_range(&conn, TEST_USER, "TX0001", "TX0005", NOW).unwrap();
+        assert_eq!(import.imported, 5);
+
+        // An overlapping range adds nothing
+        let err = import_range(&conn, TEST_USER, "TX0004", "TX0008", NOW).unwrap_err();
+        assert!(message(err).contains("TX0004 is already in the inventory"));
+        let count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM temp_tags", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 5);
+        // Another dealer's numbers are their own
+        import_range(&conn, "other-user", "TX0004", "TX0008", NOW).unwrap();
+    }
+
+    #[test]
+    fn test_issuing_stamps_the_deal_with_a_fee_and_reminder() {
+        let db = Database::init_in_memory().unwrap();
+        let conn = db.conn();
+        add_deals(&conn, 3);
+        upsert_setting(&conn, TEMP_TAG_FEE_SETTING, "500", NOW).unwrap();
+        import_range(&conn, TEST_USER, "TX0009", "TX0010", NOW).unwrap();
+        import_range(&conn, TEST_USER, "A1", "A1", NOW + 1).unwrap();
+
+        let tag = issue_tag(&conn, TEST_USER, "d1", NOW).unwrap();
+        assert_eq!(tag.tag_number, "TX0009");
+        assert_eq!(tag.status, TagStatus::Issued);
+        assert_eq!(tag.expires_at, Some(NOW + 30 * DAY_MS));
+        assert_eq!(deal_tag(&conn, "d1").as_deref(), Some("TX0009"));
+        let fees = list_fees(&conn, "d1").unwrap();
+        assert_eq!(fees.len(), 1);
+        assert_eq!(
+            (fees[0].category, fees[0].amount_cents),
+            (FeeCategory::TempTag, 500)
+        );
+        let (title, due_at): (String, i64) = conn
+            .query_row(
+                "SELECT title, due_at FROM tasks WHERE entity_id = 'd1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert!(title.starts_with("Temp tag TX0009 expires "));
+        assert_eq!(due_at, NOW + 25 * DAY_MS);
+
+        let err = issue_tag(&conn, TEST_USER, "d1", NOW).unwrap_err();
+        assert!(message(err).contains("already has temp tag TX0009"));
+        assert!(issue_tag(&conn, "other-user", "d2", NOW).is_err());
+
+        // Voiding frees the deal for a new tag, without a second fee
+        let voided = void(&conn, TEST_USER, "tx0009", "Printed crooked", NOW + 5).unwrap();
+        assert_eq!(voided.status, TagStatus::Void);
+        assert_eq!(voided.deal_id.as_deref(), Some("d1"));
+        assert_eq!(deal_tag(&conn, "d1"), None);
+        let tasks: i64 = conn
+            .query_row("SELECT COUNT(*) FROM tasks", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tasks, 0);
+        assert!(void(&conn, TEST_USER, "TX0009", "again", NOW).is_err());
+        assert!(void(&conn, TEST_USER, "TX0010", " ", NOW).is_err());
+
+        assert_eq!(
+            issue_tag(&conn, TEST_USER, "d1", NOW + 10)
+                .unwrap()
+                .tag_number,
+            "TX0010"
+        );
+        assert_eq!(list_fees(&conn, "d1").unwrap().len(), 1);
+        assert_eq!(
+            issue_tag(&conn, TEST_USER, "d2", NOW + 20)
+                .unwrap()
+                .tag_number,
+            "A1"
+        );
+        let err = issue_tag(&conn, TEST_USER, "d3", NOW + 30).unwrap_err();
+        assert!(message(err).contains("No temp tags left"));
+
+        let report = report(&conn, TEST_USER, NOW, NOW + 15).unwrap();
+        assert_eq!((report.issued, report.voided, report.available), (2, 1, 0));
+        let rows: Vec<(&str, TagStatus)> = report
+            .rows
+            .iter()
+            .map(|row| (row.tag_number.as_str(), row.status))
+            .collect();
+        assert_eq!(
+            rows,
+            [("TX0009", TagStatus::Void), ("TX0010", TagStatus::Issued)]
+        );
+        assert_eq!(report.rows[1].buyer_name.as_deref(), Some("Jane Doe"));
+        assert_eq!(report.rows[1].vin.as_deref(), Some("1HGCM82633A004352"));
+    }
+
+    #[test]
+    fn test_concurrent_issues_never_share_a_tag() {
+        let dir = temp_dir("temp-tags", "concurrent-issues");
+        let path = dir.join("tags.db");
+        {
+            let db = Database::init_with_path(&path).unwrap();
+            let conn = db.conn();
+            add_deals(&conn, 8);
+            import_range(&conn, TEST_USER, "TX0001", "TX0005", NOW).unwrap();
+        }
+
+        // Each deal issued from its own connection, all at once
+        let results: Vec<Result<TempTag, DbError>> = thread::scope(|scope| {
+            let handles: Vec<_> = (1..=8)
+                .map(|n| {
+                    let path = &path;
+                    scope.spawn(move || {
+                        let db = Database::open(path).unwrap();
+                        let conn = db.conn();
+                        issue_tag(&conn, TEST_USER, &format!("d{}", n), NOW)
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let issued: Vec<String> = results
+            .iter()
+            .filter_map(|result| result.as_ref().ok())
+            .map(|tag| tag.tag_number.clone())
+            .collect();
+        assert_eq!(issued.len(), 5, "{:?}", results);
+        assert_eq!(issued.iter().collect::<HashSet<_>>().len(), 5);
+        for result in results.into_iter().filter_map(Result::err) {
+            assert!(message(result).contains("No temp tags left"));
+        }
+
+        let db = Database::open(&path).unwrap();
+        let stamped: i64 = db
+            .conn()
+            .query_row(
+                "SELECT COUNT(DISTINCT temp_tag_number) FROM deals WHERE temp_tag_number IS NOT NULL",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(stamped, 5);
+        drop(db);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
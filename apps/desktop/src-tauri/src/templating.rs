@@ -0,0 +1,703 @@
+// src-tauri/src/templating.rs
+//
+// Handlebars text templates for emails, document text blocks and webhook payloads, e.g.
+//   "Hi {{client.first_name}}, your {{vehicle.year}} {{vehicle.make}} is ready."
+// The context holds only the deal, client, vehicle and dealer fields in CONTEXT_FIELDS (no
+// vehicle cost, no driver's license), read for the signed-in user, plus today. Output is
+// HTML-escaped unless the text format is asked for, and in HTML templates {{{...}}} isn't
+// allowed. Helpers are limited to HELPERS: the if/unless/each/with blocks, comparisons and
+// money, date and upper (as in pdf_forms.rs); lookup and log are removed, no partials are
+// registered and partial or decorator tags are rejected, so a template can only read the
+// context. check_template reports unknown fields and helpers and syntax errors with their
+// line and column before anything is rendered.
+
+use chrono::{Local, TimeZone, Utc};
+use handlebars::{handlebars_helper, no_escape, Handlebars};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_export::to_json;
+use crate::database::DbState;
+use crate::money::to_cents;
+use crate::pdf_forms::format_money;
+use crate::telemetry::track;
+
+/// Longest template accepted, in bytes
+pub const MAX_TEMPLATE_BYTES: usize = 64 * 1024;
+
+/// Fields each context entity exposes, as (entity, table, columns)
+const CONTEXT_FIELDS: &[(&str, &str, &[&str])] = &[
+    (
+        "deal",
+        "deals",
+        &[
+            "type",
+            "status",
+            "sale_date",
+            "total_amount",
+            "sale_amount",
+            "sales_tax",
+            "doc_fee",
+            "trade_in_value",
+            "down_payment",
+            "financed_amount",
+        ],
+    ),
+    (
+        "client",
+        "clients",
+        &[
+            "first_name",
+            "last_name",
+            "email",
+            "phone",
+            "address",
+            "city",
+            "state",
+            "zip_code",
+        ],
+    ),
+    (
+        "vehicle",
+        "vehicles",
+        &[
+            "vin",
+            "stock_number",
+            "year",
+            "make",
+            "model",
+            "trim",
+            "color",
+            "mileage",
+            "price",
+        ],
+    ),
+    (
+        "dealer",
+        "dealer_profile",
+        &[
+            "legal_name",
+            "dba",
+            "license_number",
+            "address",
+            "city",
+            "state",
+            "zip_code",
+            "phone",
+            "email",
+        ],
+    ),
+];
+
+/// Fields added to the columns above
+const DERIVED_FIELDS: &[&str] = &["deal.number", "today"];
+
+/// Helpers templates may call
+pub const HELPERS: &[&str] = &[
+    "if", "unless", "each", "with", "eq", "ne", "gt", "gte", "lt", "lte", "and", "or", "not",
+    "len", "money", "date", "upper",
+];
+
+const BLOCK_HELPERS: &[&str] = &["if", "unless", "each", "with"];
+
+/// Built-in helpers that read outside the field list or write to the log
+const REMOVED_HELPERS: &[&str] = &["lookup", "log", "raw"];
+
+handlebars_helper!(money: |value: Json| match value.as_f64() {
+    Some(amount) => format_money(to_cents(amount)),
+    None => String::new(),
+});
+handlebars_helper!(date: |value: Json| value
+    .as_i64()
+    .and_then(|at| Local.timestamp_millis_opt(at).single())
+    .map(|at| at.format("%m/%d/%Y").to_string())
+    .unwrap_or_default());
+handlebars_helper!(upper: |value: Json| match value {
+    Value::String(text) => text.to_uppercase(),
+    Value::Null => String::new(),
+    other => other.to_string(),
+});
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateFormat {
+    /// Values are HTML-escaped
+    #[default]
+    Html,
+    /// Plain text (SMS, subjects, webhook payloads): values as they are
+    Text,
+}
+
+/// Which records fill the context; a deal brings its client and vehicle unless they're given
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ContextEntityIds {
+    #[serde(default)]
+    pub deal_id: Option<String>,
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub vehicle_id: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TemplateIssue {
+    /// 1-based
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// The registry templates render with
+fn engine(format: TemplateFormat) -> Handlebars<'static> {
+    let mut handlebars = Handlebars::new();
+    for name in REMOVED_HELPERS {
+        handlebars.unregister_helper(name);
+    }
+    handlebars.register_helper("money", Box::new(money));
+    handlebars.register_helper("date", Box::new(date));
+    handlebars.register_helper("upper", Box::new(upper));
+    if format == TemplateFormat::Text {
+        handlebars.register_escape_fn(no_escape);
+    }
+    handlebars
+}
+
+fn is_known_field(path: &str) -> bool {
+    DERIVED_FIELDS.contains(&path)
+        || CONTEXT_FIELDS.iter().any(|(entity, _, columns)| {
+            path == *entity
+                || path
+                    .strip_prefix(entity)
+                    .and_then(|rest| rest.strip_prefix('.'))
+                    .is_some_and(|column| columns.contains(&column))
+        })
+}
+
+/// 1-based line and column of a byte offset
+fn position(template: &str, offset: usize) -> (usize, usize) {
+    let before = &template[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before
+        .rsplit('\n')
+        .next()
+        .map_or(0, |text| text.chars().count())
+        + 1;
+    (line, column)
+}
+
+/// One {{...}} tag: what's between the braces, where it starts, and whether it was
+/// {{{triple}}}
+struct Tag<'a> {
+    body: &'a str,
+    offset: usize,
+    triple: bool,
+}
+
+/// The template's tags, comments left out. Stops at an unclosed tag, which the parser
+/// reports
+fn tags(template: &str) -> Vec<Tag<'_>> {
+    let mut tags = Vec::new();
+    let mut rest = 0;
+    while let Some(found) = template[rest..].find("{{") {
+        let offset = rest + found;
+        let after = &template[offset..];
+        let (open, close) = if after.starts_with("{{!--") {
+            ("{{!--", "--}}")
+        } else if after.starts_with("{{!") {
+            ("{{!", "}}")
+        } else if after.starts_with("{{{") {
+            ("{{{", "}}}")
+        } else {
+            ("{{", "}}")
+        };
+        let Some(end) = after[open.len()..].find(close) else {
+            break;
+        };
+        if !open.starts_with("{{!") {
+            tags.push(Tag {
+                body: &after[open.len()..open.len() + end],
+                offset,
+                triple: open == "{{{",
+            });
+        }
+        rest = offset + open.len() + end + close.len();
+    }
+    tags
+}
+
+/// Split an expression into tokens: words, "(" and ")"; string literals and block
+/// parameters (as |a b|) are dropped
+fn tokens(expression: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' | ')' => tokens.push(&expression[start..start + 1]),
+            '"' | '\'' | '|' => {
+                for (_, next) in chars.by_ref() {
+                    if next == c {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                let mut end = expression.len();
+                while let Some(&(i, next)) = chars.peek() {
+                    if next.is_whitespace() || next == '(' || next == ')' {
+                        end = i;
+                        break;
+                    }
+                    chars.next();
+                }
+                let token = &expression[start..end];
+                // hash=value: the value is what's read
+                match token.split_once('=') {
+                    Some((_, value)) if !value.is_empty() => tokens.push(value),
+                    Some(_) => {}
+                    None => tokens.push(token),
+                }
+            }
+        }
+    }
+    tokens
+}
+
+fn is_literal(token: &str) -> bool {
+    matches!(token, "true" | "false" | "null" | "undefined" | "as") || token.parse::<f64>().is_ok()
+}
+
+/// Context a block's contents see: a path into the context, or anything (inside each,
+/// whose items we can't check)
+#[derive(Clone)]
+enum Scope {
+    At(String),
+    Any,
+}
+
+/// Check a field reference against CONTEXT_FIELDS, relative to the innermost scope
+fn check_path(path: &str, scopes: &[Scope]) -> Result<(), String> {
+    if path.starts_with('@') && !path.starts_with("@root") {
+        // @index, @key, @first, @last
+        return Ok(());
+    }
+    let cleaned = path.replace(['[', ']'], "");
+    let mut depth = scopes.len() - 1;
+    let mut relative = cleaned.as_str();
+    if let Some(absolute) = relative.strip_prefix("@root") {
+        depth = 0;
+        relative = absolute.trim_start_matches('.');
+    }
+    while let Some(up) = relative.strip_prefix("../") {
+        depth = depth.saturating_sub(1);
+        relative = up;
+    }
+    let relative = match relative {
+        "this" | "." => "",
+        _ => ["this.", "this/", "./"]
+            .iter()
+            .find_map(|prefix| relative.strip_prefix(prefix))
+            .unwrap_or(relative),
+    };
+    let full = match &scopes[depth] {
+        Scope::Any => return Ok(()),
+        Scope::At(prefix) if relative.is_empty() => prefix.clone(),
+        Scope::At(prefix) if prefix.is_empty() => relative.replace('/', "."),
+        Scope::At(prefix) => format!("{}.{}", prefix, relative.replace('/', ".")),
+    };
+    if full.is_empty() || is_known_field(&full) {
+        Ok(())
+    } else {
+        Err(format!("Unknown field: {}", path))
+    }
+}
+
+/// Unknown fields and helpers in an expression; the first token is a helper when the
+/// expression has more than one (or follows "(")
+fn check_expression(expression: &str, scopes: &[Scope], issues: &mut Vec<String>) {
+    let tokens = tokens(expression);
+    let calls_helper = tokens.iter().filter(|token| **token != ")").count() > 1;
+    let mut helper_next = calls_helper;
+    for token in tokens {
+        match token {
+            "(" => helper_next = true,
+            ")" => {}
+            helper if helper_next => {
+                helper_next = false;
+                if !HELPERS.contains(&helper) {
+                    issues.push(format!("Unknown helper: {}", helper));
+                }
+            }
+            literal if is_literal(literal) => {}
+            path => {
+                if let Err(e) = check_path(path, scopes) {
+                    issues.push(e);
+                }
+            }
+        }
+    }
+}
+
+/// Syntax errors, unknown fields and helpers, and tags templates may not use
+pub(crate) fn check_template(template: &str, format: TemplateFormat) -> Vec<TemplateIssue> {
+    if template.len() > MAX_TEMPLATE_BYTES {
+        return vec![TemplateIssue {
+            line: 1,
+            column: 1,
+            message: format!("Template is over {} KB", MAX_TEMPLATE_BYTES / 1024),
+        }];
+    }
+    let mut issues = Vec::new();
+    let mut scopes = vec![Scope::At(String::new())];
+    for tag in tags(template) {
+        let mut found = Vec::new();
+        let body = tag
+            .body
+            .trim_matches(|c: char| c == '~' || c.is_whitespace());
+        if body.starts_with('{') {
+            found.push("Raw blocks aren't allowed".to_string());
+        } else if body.starts_with('>') || body.starts_with("#>") {
+            found.push("Partials aren't allowed".to_string());
+        } else if body.starts_with('*') || body.starts_with("#*") {
+            found.push("Inline partials and decorators aren't allowed".to_string());
+        } else if (tag.triple || body.starts_with('&')) && format == TemplateFormat::Html {
+            found.push("Unescaped output isn't allowed in HTML templates".to_string());
+        } else if body == "^" || body == "else" {
+            // The inverse section of the open block; same scope
+        } else if let Some(condition) = body.strip_prefix("else ") {
+            // {{else if ...}} chains
+            check_expression(condition, &scopes, &mut found);
+        } else if let Some(block) = body.strip_prefix('#').or_else(|| body.strip_prefix('^')) {
+            let name = tokens(block).first().copied().unwrap_or_default();
+            let inverted = body.starts_with('^');
+            if !inverted && !BLOCK_HELPERS.contains(&name) {
+                found.push(format!("Unknown block helper: {}", name));
+            } else {
+                check_expression(block, &scopes, &mut found);
+            }
+            let current = scopes.last().cloned().unwrap_or(Scope::Any);
+            let scope = match (name, current) {
+                _ if inverted || block.contains(" as |") => Scope::Any,
+                ("each", _) => Scope::Any,
+                ("with", Scope::At(prefix)) => match tokens(block).get(1) {
+                    Some(path) if !path.starts_with(['@', '.']) && prefix.is_empty() => {
+                        Scope::At(path.to_string())
+                    }
+                    Some(path) if !path.starts_with(['@', '.']) => {
+                        Scope::At(format!("{}.{}", prefix, path))
+                    }
+                    _ => Scope::Any,
+                },
+                (_, current) => current,
+            };
+            scopes.push(scope);
+        } else if body.starts_with('/') {
+            if scopes.len() > 1 {
+                scopes.pop();
+            }
+        } else {
+            check_expression(body.trim_start_matches('&'), &scopes, &mut found);
+        }
+
+        let (line, column) = position(template, tag.offset);
+        issues.extend(found.into_iter().map(|message| TemplateIssue {
+            line,
+            column,
+            message,
+        }));
+    }
+
+    if let Err(e) = engine(format).register_template_string("check", template) {
+        let (line, column) = e.pos().unwrap_or((1, 1));
+        issues.push(TemplateIssue {
+            line,
+            column,
+            message: e.reason().to_string(),
+        });
+    }
+    issues.sort_by_key(|issue| (issue.line, issue.column));
+    issues
+}
+
+/// CONTEXT_FIELDS of one row, if the user owns it
+fn entity_fields(
+    conn: &Connection,
+    table: &str,
+    columns: &[&str],
+    key_column: &str,
+    key: &str,
+    user_id: &str,
+) -> Result<Option<Map<String, Value>>, String> {
+    let sql = format!(
+        "SELECT {} FROM {} WHERE {} = ?1 AND user_id = ?2",
+        columns.join(", "),
+        table,
+        key_column
+    );
+    conn.query_row(&sql, params![key, user_id], |row| {
+        let mut fields = Map::new();
+        for (i, column) in columns.iter().enumerate() {
+            fields.insert(column.to_string(), to_json(row.get_ref(i)?));
+        }
+        Ok(fields)
+    })
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// The template context for the given records, which must be the user's
+pub(crate) fn build_context(
+    conn: &Connection,
+    user_id: &str,
+    ids: &ContextEntityIds,
+) -> Result<Value, String> {
+    let mut ids = ids.clone();
+    if let Some(deal_id) = &ids.deal_id {
+        let (client_id, vehicle_id): (String, String) = conn
+            .query_row(
+                "SELECT client_id, vehicle_id FROM deals WHERE id = ?1 AND user_id = ?2",
+                params![deal_id, user_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Deal not found or access denied".to_string())?;
+        ids.client_id.get_or_insert(client_id);
+        ids.vehicle_id.get_or_insert(vehicle_id);
+    }
+
+    let mut context = Map::new();
+    for (entity, table, columns) in CONTEXT_FIELDS {
+        let (key_column, key) = match *entity {
+            "deal" => ("id", ids.deal_id.as_deref()),
+            "client" => ("id", ids.client_id.as_deref()),
+            "vehicle" => ("id", ids.vehicle_id.as_deref()),
+            _ => ("user_id", Some(user_id)),
+        };
+        let Some(key) = key else {
+            continue;
+        };
+        match entity_fields(conn, table, columns, key_column, key, user_id)? {
+            Some(mut fields) => {
+                if *entity == "deal" {
+                    let number: String = key.chars().take(8).collect();
+                    fields.insert("number".to_string(), Value::from(number));
+                }
+                context.insert(entity.to_string(), Value::Object(fields));
+            }
+            // No dealer profile saved yet
+            None if *entity == "dealer" => {}
+            None => return Err(format!("{} not found or access denied", entity)),
+        }
+    }
+    context.insert(
+        "today".to_string(),
+        Value::from(Utc::now().timestamp_millis()),
+    );
+    Ok(Value::Object(context))
+}
+
+fn describe(issues: &[TemplateIssue]) -> String {
+    issues
+        .iter()
+        .map(|issue| {
+            format!(
+                "line {}, column {}: {}",
+                issue.line, issue.column, issue.message
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Render a checked template against context
+pub(crate) fn render(
+    template: &str,
+    context: &Value,
+    format: TemplateFormat,
+) -> Result<String, String> {
+    let issues = check_template(template, format);
+    if !issues.is_empty() {
+        return Err(describe(&issues));
+    }
+    engine(format)
+        .render_template(template, context)
+        .map_err(|e| e.to_string())
+}
+
+/// Render a template for a deal, client and/or vehicle of the user
+#[tauri::command]
+pub fn render_template(
+    template_string: String,
+    context_entity_ids: ContextEntityIds,
+    format: Option<TemplateFormat>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<String, String> {
+    track("render_template", || {
+        let user_id_value = state.require_user(user_id)?;
+        let context = {
+            let db = db.get().map_err(|e| e.to_string())?;
+            let conn = db.conn();
+            build_context(&conn, &user_id_value, &context_entity_ids)?
+        };
+        render(&template_string, &context, format.unwrap_or_default())
+    })
+}
+
+/// Problems in a template (empty when it's fine), for the template editor
+#[tauri::command]
+pub fn validate_template(
+    template_string: String,
+    format: Option<TemplateFormat>,
+) -> Result<Vec<TemplateIssue>, String> {
+    track("validate_template", || {
+        Ok(check_template(&template_string, format.unwrap_or_default()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{db_create_client, db_create_deal, db_create_vehicle};
+    use crate::test_support::{make_client, make_deal, make_vehicle, TestApp, TEST_USER};
+    use serde_json::json;
+
+    fn app_with_deal() -> TestApp {
+        let app = TestApp::new();
+        let mut client = make_client("c1");
+        client.first_name = "<b>Jo</b> & \"Al\"".to_string();
+        db_create_client(client, None, None, None, app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        let mut deal = make_deal("3f2a9c1e-0000", "c1", "v1");
+        deal.down_payment = Some(2500.0);
+        db_create_deal(deal, None, None, app.state(), app.db()).unwrap();
+        app
+    }
+
+    fn messages(template: &str) -> Vec<(usize, usize, String)> {
+        check_template(template, TemplateFormat::Html)
+            .into_iter()
+            .map(|issue| (issue.line, issue.column, issue.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_values_are_escaped_unless_plain_text() {
+        let app = app_with_deal();
+        let ids = ContextEntityIds {
+            deal_id: Some("3f2a9c1e-0000".to_string()),
+            ..Default::default()
+        };
+        let context = build_context(&app.conn(), TEST_USER, &ids).unwrap();
+        let template = "Hi {{client.first_name}}, deal #{{deal.number}}: \
+                        {{vehicle.year}} {{upper vehicle.make}}, {{money deal.down_payment}} down\
+                        {{#if vehicle.trim}} ({{vehicle.trim}}){{/if}}";
+
+        assert_eq!(
+            render(template, &context, TemplateFormat::Html).unwrap(),
+            "Hi &lt;b&gt;Jo&lt;/b&gt; &amp; &quot;Al&quot;, deal #3f2a9c1e: \
+             2020 TOYOTA, 2,500.00 down"
+        );
+        assert_eq!(
+            render(template, &context, TemplateFormat::Text).unwrap(),
+            "Hi <b>Jo</b> & \"Al\", deal #3f2a9c1e: 2020 TOYOTA, 2,500.00 down"
+        );
+        // Unescaped output only in plain text
+        assert!(render("{{{client.first_name}}}", &context, TemplateFormat::Html).is_err());
+        assert_eq!(
+            render("{{{client.first_name}}}", &context, TemplateFormat::Text).unwrap(),
+            "<b>Jo</b> & \"Al\""
+        );
+
+        // Someone else's records don't fill a context
+        let err = build_context(&app.conn(), "someone-else", &ids).unwrap_err();
+        assert!(err.contains("access denied"), "{}", err);
+    }
+
+    #[test]
+    fn test_unknown_fields_and_syntax_errors_have_positions() {
+        assert!(messages("{{client.first_name}} {{#with vehicle}}{{make}}{{/with}}").is_empty());
+        assert_eq!(
+            messages("Hi {{client.first_name}},\n  price {{vehicle.cost}}\n{{#with client}}{{drivers_license}}{{/with}}"),
+            [
+                (2, 9, "Unknown field: vehicle.cost".to_string()),
+                (3, 17, "Unknown field: drivers_license".to_string()),
+            ]
+        );
+        assert_eq!(
+            messages("{{shout client.first_name}}"),
+            [(1, 1, "Unknown helper: shout".to_string())]
+        );
+
+        assert_eq!(messages("Line one\n{{#if deal.status}}\nopen").len(), 1);
+        let broken = messages("ok\nok {{client.first_name");
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].0, 2, "{:?}", broken);
+    }
+
+    #[test]
+    fn test_templates_cannot_reach_past_the_context() {
+        for (template, expected) in [
+            ("{{> /etc/passwd}}", "Partials aren't allowed"),
+            ("{{#> layout}}x{{/layout}}", "Partials aren't allowed"),
+            (
+                "{{#*inline \"x\"}}{{> x}}{{/inline}}",
+                "Inline partials and decorators aren't allowed",
+            ),
+            ("{{lookup vehicle \"cost\"}}", "Unknown helper: lookup"),
+            ("{{log client.first_name}}", "Unknown helper: log"),
+            (
+                "{{#each (lookup this \"vehicle\")}}{{/each}}",
+                "Unknown helper: lookup",
+            ),
+            (
+                "{{@root.vehicle.cost}}",
+                "Unknown field: @root.vehicle.cost",
+            ),
+            (
+                "{{#with client}}{{../vehicle.cost}}{{/with}}",
+                "Unknown field: ../vehicle.cost",
+            ),
+            ("{{eq vehicle.cost 0}}", "Unknown field: vehicle.cost"),
+            (
+                "{{#if (gt vehicle.cost 1)}}x{{/if}}",
+                "Unknown field: vehicle.cost",
+            ),
+            ("{{{{raw}}}}{{/raw}}", "Raw blocks aren't allowed"),
+            (
+                "{{&client.first_name}}",
+                "Unescaped output isn't allowed in HTML templates",
+            ),
+        ] {
+            let issues = messages(template);
+            assert!(
+                issues.iter().any(|(_, _, message)| message == expected),
+                "{}: {:?}",
+                template,
+                issues
+            );
+        }
+        assert!(!messages(&"{{today}}".repeat(MAX_TEMPLATE_BYTES / 8 + 1)).is_empty());
+
+        // Even unchecked, the engine has no partials, lookup or log to call
+        let context = json!({ "vehicle": { "cost": 14000 } });
+        for template in [
+            "{{> secret}}",
+            "{{lookup vehicle \"cost\"}}",
+            "{{log vehicle}}",
+        ] {
+            assert!(
+                engine(TemplateFormat::Html)
+                    .render_template(template, &context)
+                    .is_err(),
+                "{}",
+                template
+            );
+        }
+    }
+}
@@ -0,0 +1,224 @@
+// src-tauri/src/test_support.rs
+//
+// Fixtures for testing the db_* commands (test builds only). TestApp is a mock Tauri app
+// managing an in-memory database (DbState) and a signed-in user, so commands are called the
+// way the frontend calls them:
+//
+//     let app = TestApp::new();
+//     db_create_client(make_client("c1"), None, None, None, app.state(), app.db())?;
+//
+// Code that still calls get_db() gets the test build's shared in-memory database, never the
+// on-disk one. seeded_db_at() builds a database as it was after any migration, for tests of
+// upgrades and of code that has to cope with older schemas. temp_dir() gives a test an empty
+// scratch directory.
+
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::MutexGuard;
+use tauri::test::{mock_app, MockRuntime};
+use tauri::{App, Manager, State};
+
+use crate::app_state::AppState;
+use crate::database::{Client, Database, DbState, Deal, Vehicle};
+use crate::encryption::{derive_key_from_password, KdfParams};
+use crate::kiosk::PinHash;
+use crate::permissions::Role;
+
+/// User TestApp signs in as
+pub(crate) const TEST_USER: &str = "test-user";
+/// Ids of the rows seeded_db_at() inserts
+pub(crate) const SEED_CLIENT: &str = "seed-client";
+pub(crate) const SEED_VEHICLE: &str = "seed-vehicle";
+pub(crate) const SEED_DEAL: &str = "seed-deal";
+/// Migration that added user_id to clients, vehicles and deals
+const USER_ID_VERSION: i32 = 5;
+const CREATED_AT: i64 = 1_700_000_000_000;
+
+pub(crate) struct TestApp {
+    app: App<MockRuntime>,
+}
+
+impl TestApp {
+    /// Fully migrated, empty database with TEST_USER signed in
+    pub fn new() -> Self {
+        Self::with_db(Database::init_in_memory().unwrap())
+    }
+
+    pub fn with_db(db: Database) -> Self {
+        let app = mock_app();
+        app.manage(AppState::default());
+        app.manage(DbState::new(db));
+        let test_app = TestApp { app };
+        test_app.sign_in(TEST_USER);
+        test_app
+    }
+
+    /// Switch the signed-in user (e.g. to check another user can't see the data)
+    pub fn sign_in(&self, user_id: &str) {
+        self.state().set_current_user(Some(user_id.to_string()));
+    }
+
+    /// Sign in with a role, whatever the database says (see permissions.rs)
+    pub fn sign_in_as(&self, user_id: &str, role: Role) {
+        self.sign_in(user_id);
+        self.state().set_role(Some(role));
+    }
+
+    pub fn state(&self) -> State<'_, AppState> {
+        self.app.state::<AppState>()
+    }
+
+    pub fn db(&self) -> State<'_, DbState> {
+        self.app.state::<DbState>()
+    }
+
+    /// The test database's connection, for setup and assertions the commands don't cover
+    pub fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.app.state::<DbState>().inner().get().unwrap().conn()
+    }
+}
+
+/// Empty directory under the OS temp dir, named after the module (prefix) and the test (name)
+/// and unique to this test run; whatever an earlier run left there is removed
+pub(crate) fn temp_dir(prefix: &str, name: &str) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("dealer-{}-{}-{}", prefix, name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Hash of `pin` as set_user_pin takes it, with cheap Argon2 settings so tests stay fast
+pub(crate) fn pin_hash(pin: &str) -> PinHash {
+    let params = KdfParams {
+        memory_kib: 8,
+        iterations: 1,
+        parallelism: 1,
+    };
+    let derived = derive_key_from_password(pin.to_string(), None, Some(params)).unwrap();
+    PinHash {
+        salt_base64: derived.salt_base64,
+        key_hash_base64: derived.key_hash_base64,
+        params: Some(params),
+    }
+}
+
+pub(crate) fn make_client(id: &str) -> Client {
+    Client {
+        id: id.to_string(),
+        user_id: None,
+        first_name: "Jordan".to_string(),
+        last_name: format!("Client {}", id),
+        email: Some(format!("{}@example.com", id)),
+        phone: Some("555-010-0100".to_string()),
+        address: Some("100 Main St".to_string()),
+        city: Some("Austin".to_string()),
+        state: Some("TX".to_string()),
+        zip_code: Some("78701".to_string()),
+        drivers_license: None,
+        created_at: CREATED_AT,
+        updated_at: CREATED_AT,
+        synced_at: None,
+    }
+}
+
+/// The VIN is derived from id, so vehicles with different ids never collide
+pub(crate) fn make_vehicle(id: &str) -> Vehicle {
+    Vehicle {
+        id: id.to_string(),
+        vin: format!("TESTVIN-{}", id),
+        stock_number: Some(format!("STK-{}", id)),
+        year: 2020,
+        make: "Toyota".to_string(),
+        model: "Camry".to_string(),
+        trim: None,
+        body: None,
+        doors: Some(4),
+        transmission: None,
+        engine: None,
+        cylinders: None,
+        title_number: None,
+        mileage: 42_000,
+        color: Some("Silver".to_string()),
+        price: 18_500.0,
+        cost: Some(14_000.0),
+        status: "available".to_string(),
+        description: None,
+        images: None,
+        created_at: CREATED_AT,
+        updated_at: CREATED_AT,
+        synced_at: None,
+    }
+}
+
+pub(crate) fn make_deal(id: &str, client_id: &str, vehicle_id: &str) -> Deal {
+    Deal {
+        id: id.to_string(),
+        user_id: None,
+        r#type: "cash".to_string(),
+        client_id: client_id.to_string(),
+        vehicle_id: vehicle_id.to_string(),
+        status: "quote".to_string(),
+        total_amount: 18_500.0,
+        sale_date: None,
+        sale_amount: Some(18_500.0),
+        sales_tax: None,
+        doc_fee: None,
+        trade_in_value: None,
+        down_payment: None,
+        financed_amount: None,
+        document_ids: "[]".to_string(),
+        cobuyer_data: None,
+        created_at: CREATED_AT,
+        updated_at: CREATED_AT,
+        synced_at: None,
+        odometer_at_sale: None,
+        odometer_disclosure: None,
+        title_status: None,
+        title_state: None,
+        lien_holder: None,
+        lender_id: None,
+    }
+}
+
+/// In-memory database migrated up to `version`, holding one client, vehicle and deal
+/// (SEED_*; owned by TEST_USER once the schema has user_id). Only columns from migration 1
+/// are filled in, so the same rows work at every version
+pub(crate) fn seeded_db_at(version: i32) -> Database {
+    let db = Database::init_in_memory_at(version).unwrap();
+    {
+        let conn = db.conn();
+        conn.execute(
+            "INSERT INTO clients (id, first_name, last_name, email, created_at, updated_at)
+             VALUES (?1, 'Seed', 'Client', 'seed@example.com', ?2, ?2)",
+            params![SEED_CLIENT, CREATED_AT],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO vehicles (id, vin, year, make, model, mileage, price, cost, status,
+                 created_at, updated_at)
+             VALUES (?1, 'SEEDVIN0000000001', 2018, 'Honda', 'Civic', 60000, 12000, 9000,
+                 'sold', ?2, ?2)",
+            params![SEED_VEHICLE, CREATED_AT],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount, doc_fee,
+                 document_ids, created_at, updated_at)
+             VALUES (?1, 'cash', ?2, ?3, 'sold', 12000, 150, '[]', ?4, ?4)",
+            params![SEED_DEAL, SEED_CLIENT, SEED_VEHICLE, CREATED_AT],
+        )
+        .unwrap();
+        if version >= USER_ID_VERSION {
+            for table in ["clients", "vehicles", "deals"] {
+                conn.execute(
+                    &format!("UPDATE {} SET user_id = ?1", table),
+                    params![TEST_USER],
+                )
+                .unwrap();
+            }
+        }
+    }
+    db
+}
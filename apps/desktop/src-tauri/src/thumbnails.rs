@@ -0,0 +1,260 @@
+// src-tauri/src/thumbnails.rs
+//
+// Thumbnail generation for vehicle images and PDF documents
+// Keeps the inventory grid from decoding full-resolution photos while scrolling
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageError, ImageReader, Rgb, RgbImage};
+use log::{error, info};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::storage::get_cache_path;
+use crate::telemetry::track;
+
+/// Subfolder of the cache directory holding generated thumbnails
+pub const THUMBNAILS_DIR: &str = "thumbnails";
+
+const JPEG_QUALITY: u8 = 80;
+const MAX_ALLOWED_DIMENSION: u32 = 2048;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+pub enum ThumbnailError {
+    NotFound(String),
+    UnsupportedFormat(String),
+    CorruptImage(String),
+    InvalidDimension(String),
+    Io(String),
+}
+
+impl std::fmt::Display for ThumbnailError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ThumbnailError::NotFound(msg) => write!(f, "Source not found: {}", msg),
+            ThumbnailError::UnsupportedFormat(msg) => write!(f, "Unsupported format: {}", msg),
+            ThumbnailError::CorruptImage(msg) => write!(f, "Corrupt image: {}", msg),
+            ThumbnailError::InvalidDimension(msg) => write!(f, "Invalid dimension: {}", msg),
+            ThumbnailError::Io(msg) => write!(f, "I/O error: {}", msg),
+        }
+    }
+}
+
+impl From<ImageError> for ThumbnailError {
+    fn from(e: ImageError) -> Self {
+        match e {
+            ImageError::Unsupported(e) => ThumbnailError::UnsupportedFormat(e.to_string()),
+            ImageError::IoError(e) => ThumbnailError::Io(e.to_string()),
+            other => ThumbnailError::CorruptImage(other.to_string()),
+        }
+    }
+}
+
+/// Generate a thumbnail no larger than max_dimension on either side
+/// Supports JPEG/PNG/WebP; PDFs get a blank page placeholder
+#[tauri::command]
+pub fn generate_thumbnail(
+    source_path: String,
+    max_dimension: u32,
+    output_path: String,
+) -> Result<String, ThumbnailError> {
+    track("generate_thumbnail", || {
+        info!(
+            "🖼️  Generating thumbnail: {} ({}px)",
+            source_path, max_dimension
+        );
+
+        write_thumbnail(
+            Path::new(&source_path),
+            max_dimension,
+            Path::new(&output_path),
+        )
+        .map_err(|e| {
+            error!("❌ Failed to generate thumbnail: {}", e);
+            e
+        })?;
+
+        Ok(output_path)
+    })
+}
+
+/// Return a cached thumbnail path, generating it on first request
+/// Cache key: source path + size + modified time, so edits invalidate the entry
+#[tauri::command]
+pub fn get_or_create_thumbnail(
+    source_path: String,
+    max_dimension: u32,
+) -> Result<String, ThumbnailError> {
+    track("get_or_create_thumbnail", || {
+        let cache_dir =
+            PathBuf::from(get_cache_path().map_err(ThumbnailError::Io)?).join(THUMBNAILS_DIR);
+
+        let thumbnail = get_or_create_thumbnail_in(&cache_dir, Path::new(&source_path), max_dimension)?;
+
+        Ok(thumbnail.to_string_lossy().to_string())
+    })
+}
+
+fn get_or_create_thumbnail_in(
+    cache_dir: &Path,
+    source: &Path,
+    max_dimension: u32,
+) -> Result<PathBuf, ThumbnailError> {
+    let key = cache_key(source, max_dimension)?;
+    let thumbnail = cache_dir.join(format!("{}.jpg", key));
+
+    if thumbnail.is_file() {
+        return Ok(thumbnail);
+    }
+
+    write_thumbnail(source, max_dimension, &thumbnail)?;
+    info!("✅ Thumbnail cached: {:?}", thumbnail);
+    Ok(thumbnail)
+}
+
+fn cache_key(source: &Path, max_dimension: u32) -> Result<String, ThumbnailError> {
+    let metadata = fs::metadata(source)
+        .map_err(|_| ThumbnailError::NotFound(source.to_string_lossy().to_string()))?;
+
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+
+    let canonical = source
+        .canonicalize()
+        .unwrap_or_else(|_| source.to_path_buf());
+
+    let mut hasher = Sha256::new();
+    hasher.update(canonical.to_string_lossy().as_bytes());
+    hasher.update(metadata.len().to_le_bytes());
+    hasher.update(modified.to_le_bytes());
+
+    Ok(format!("{:x}_{}", hasher.finalize(), max_dimension))
+}
+
+pub(crate) fn write_thumbnail(source: &Path, max_dimension: u32, output: &Path) -> Result<(), ThumbnailError> {
+    if max_dimension == 0 || max_dimension > MAX_ALLOWED_DIMENSION {
+        return Err(ThumbnailError::InvalidDimension(format!(
+            "max_dimension must be between 1 and {}",
+            MAX_ALLOWED_DIMENSION
+        )));
+    }
+
+    if !source.is_file() {
+        return Err(ThumbnailError::NotFound(
+            source.to_string_lossy().to_string(),
+        ));
+    }
+
+    let is_pdf = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.eq_ignore_ascii_case("pdf"))
+        .unwrap_or(false);
+
+    let thumbnail = if is_pdf {
+        pdf_placeholder(max_dimension)
+    } else {
+        ImageReader::open(source)
+            .map_err(|e| ThumbnailError::Io(e.to_string()))?
+            .with_guessed_format()
+            .map_err(|e| ThumbnailError::Io(e.to_string()))?
+            .decode()?
+            .thumbnail(max_dimension, max_dimension)
+    };
+
+    if let Some(parent) = output.parent() {
+        fs::create_dir_all(parent).map_err(|e| ThumbnailError::Io(e.to_string()))?;
+    }
+
+    let file = File::create(output).map_err(|e| ThumbnailError::Io(e.to_string()))?;
+    let mut encoder = JpegEncoder::new_with_quality(BufWriter::new(file), JPEG_QUALITY);
+    encoder.encode_image(&DynamicImage::ImageRgb8(thumbnail.to_rgb8()))?;
+
+    Ok(())
+}
+
+/// Raster fallback for PDFs: a blank letter-size page with a light border
+fn pdf_placeholder(max_dimension: u32) -> DynamicImage {
+    let height = max_dimension.max(2);
+    let width = ((height as f64) * 8.5 / 11.0).round().max(2.0) as u32;
+
+    let border = Rgb([200, 200, 200]);
+    let page = RgbImage::from_fn(width, height, |x, y| {
+        if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+            border
+        } else {
+            Rgb([255, 255, 255])
+        }
+    });
+
+    DynamicImage::ImageRgb8(page)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_thumbnail_dimensions_keep_aspect_ratio() {
+        let dir = temp_dir("thumbnail", "dimensions");
+        let source = dir.join("car.png");
+        RgbImage::from_pixel(400, 200, Rgb([10, 20, 30]))
+            .save(&source)
+            .unwrap();
+
+        let output = dir.join("car_thumb.jpg");
+        write_thumbnail(&source, 100, &output).unwrap();
+
+        assert_eq!(image::image_dimensions(&output).unwrap(), (100, 50));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_cache_hit_reuses_thumbnail() {
+        let dir = temp_dir("thumbnail", "cache");
+        let source = dir.join("car.png");
+        RgbImage::from_pixel(300, 300, Rgb([200, 0, 0]))
+            .save(&source)
+            .unwrap();
+
+        let cache = dir.join(THUMBNAILS_DIR);
+        let first = get_or_create_thumbnail_in(&cache, &source, 64).unwrap();
+        let first_modified = fs::metadata(&first).unwrap().modified().unwrap();
+
+        let second = get_or_create_thumbnail_in(&cache, &source, 64).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(
+            fs::metadata(&second).unwrap().modified().unwrap(),
+            first_modified
+        );
+
+        let other_size = get_or_create_thumbnail_in(&cache, &source, 32).unwrap();
+        assert_ne!(first, other_size);
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 2);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_corrupt_image_returns_typed_error() {
+        let dir = temp_dir("thumbnail", "corrupt");
+        let source = dir.join("broken.jpg");
+        let mut bytes = vec![0xFF, 0xD8, 0xFF, 0xE0];
+        bytes.extend_from_slice(&[0u8; 64]);
+        fs::write(&source, bytes).unwrap();
+
+        let result = write_thumbnail(&source, 100, &dir.join("out.jpg"));
+        assert!(matches!(result, Err(ThumbnailError::CorruptImage(_))));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,897 @@
+// src-tauri/src/title_export.rs
+//
+// Title applications for a state DMV's bulk e-titling upload. A format profile (a row of
+// title_export_formats, migration 32) describes the file: XML with one element per field,
+// or fixed-width text with one line per deal; which deal, client, vehicle, lender and dealer
+// values go where (SOURCES); which are required; and how money and dates are written.
+// Supporting another state means adding a profile with save_title_export_format, not code.
+// Each deal is checked against the profile first. One that's missing a required value, or
+// has a value too long for its fixed-width column, is left out of the file and reported
+// with every problem found; the others are exported.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{NaiveDate, Utc};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit_log::record_audit;
+use crate::database::{begin_write, DbState};
+use crate::datetime::{dealer_timezone, local_date};
+use crate::quickbooks::format_cents;
+use crate::reporting::{run_report_job, write_report_file};
+use crate::telemetry::{track, track_async};
+
+const LINE_ENDING: &str = "\r\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Text,
+    Money,
+    Date,
+    Number,
+}
+
+/// Values a profile field can take: (source, kind, SQL over deals d, clients c, vehicles v,
+/// lenders l and dealer_profile dp). Money is in cents, dates are the dealer's calendar days
+const SOURCES: &[(&str, Kind, &str)] = &[
+    ("deal.id", Kind::Text, "d.id"),
+    (
+        "deal.sale_date",
+        Kind::Date,
+        "COALESCE(d.sale_date, d.created_at)",
+    ),
+    (
+        "deal.sale_amount",
+        Kind::Money,
+        "COALESCE(d.sale_amount_cents, d.total_amount_cents)",
+    ),
+    ("deal.sales_tax", Kind::Money, "d.sales_tax_cents"),
+    ("deal.doc_fee", Kind::Money, "d.doc_fee_cents"),
+    ("deal.trade_in_value", Kind::Money, "d.trade_in_value_cents"),
+    ("deal.down_payment", Kind::Money, "d.down_payment_cents"),
+    (
+        "deal.financed_amount",
+        Kind::Money,
+        "d.financed_amount_cents",
+    ),
+    (
+        "deal.odometer",
+        Kind::Number,
+        "COALESCE(d.odometer_at_sale, v.mileage)",
+    ),
+    (
+        "deal.odometer_disclosure",
+        Kind::Text,
+        "d.odometer_disclosure",
+    ),
+    ("deal.title_state", Kind::Text, "d.title_state"),
+    ("deal.title_status", Kind::Text, "d.title_status"),
+    ("client.first_name", Kind::Text, "c.first_name"),
+    ("client.last_name", Kind::Text, "c.last_name"),
+    ("client.address", Kind::Text, "c.address"),
+    ("client.city", Kind::Text, "c.city"),
+    ("client.state", Kind::Text, "c.state"),
+    ("client.zip_code", Kind::Text, "c.zip_code"),
+    ("client.drivers_license", Kind::Text, "c.drivers_license"),
+    ("vehicle.vin", Kind::Text, "v.vin"),
+    ("vehicle.year", Kind::Number, "v.year"),
+    ("vehicle.make", Kind::Text, "v.make"),
+    ("vehicle.model", Kind::Text, "v.model"),
+    ("vehicle.trim", Kind::Text, "v.trim"),
+    ("vehicle.body", Kind::Text, "v.body"),
+    ("vehicle.color", Kind::Text, "v.color"),
+    ("vehicle.title_number", Kind::Text, "v.title_number"),
+    ("vehicle.stock_number", Kind::Text, "v.stock_number"),
+    // Deals from before the lender directory only have the lienholder's name
+    ("lender.name", Kind::Text, "COALESCE(l.name, d.lien_holder)"),
+    ("lender.address", Kind::Text, "l.address"),
+    ("lender.city", Kind::Text, "l.city"),
+    ("lender.state", Kind::Text, "l.state"),
+    ("lender.zip_code", Kind::Text, "l.zip_code"),
+    ("dealer.legal_name", Kind::Text, "dp.legal_name"),
+    ("dealer.license_number", Kind::Text, "dp.license_number"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Layout {
+    Xml,
+    FixedWidth,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MoneyStyle {
+    /// 1234.50
+    #[default]
+    Decimal,
+    /// 123450
+    Cents,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Align {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatField {
+    /// XML element, or the column's name in validation errors
+    pub name: String,
+    /// One of SOURCES; or else `value`, written as is
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    /// Column width (fixed_width only, where it's required)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<usize>,
+    /// Fixed-width padding; money and numbers default to right-aligned with zeros,
+    /// everything else to left-aligned with spaces
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub align: Option<Align>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pad: Option<char>,
+}
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FormatDefinition {
+    pub layout: Layout,
+    #[serde(default)]
+    pub money: MoneyStyle,
+    /// chrono pattern
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    /// Upper-case all text
+    #[serde(default)]
+    pub upper: bool,
+    /// Document and per-deal elements (xml only, where they're required)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub record: Option<String>,
+    pub fields: Vec<FormatField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TitleExportFormat {
+    pub id: String,
+    pub name: String,
+    pub definition: FormatDefinition,
+    pub built_in: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ExcludedDeal {
+    pub deal_id: String,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TitleExport {
+    /// None when no deal passed validation (no file is written)
+    pub path: Option<String>,
+    pub format_profile: String,
+    pub exported: Vec<String>,
+    pub excluded: Vec<ExcludedDeal>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FieldValue {
+    Text(String),
+    Cents(i64),
+    Date(NaiveDate),
+    Number(i64),
+}
+
+/// A deal's SOURCES values; missing and blank ones are left out
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TitleDeal {
+    deal_id: String,
+    values: BTreeMap<String, FieldValue>,
+}
+
+/// The file's content and which deals made it in
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rendered {
+    content: Option<String>,
+    exported: Vec<String>,
+    excluded: Vec<ExcludedDeal>,
+}
+
+fn source_kind(source: &str) -> Option<Kind> {
+    SOURCES
+        .iter()
+        .find(|(name, _, _)| *name == source)
+        .map(|(_, kind, _)| *kind)
+}
+
+/// Letters, digits, '_', '-' and '.', starting with a letter or '_'
+fn is_xml_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+fn check_definition(definition: &FormatDefinition) -> Result<(), String> {
+    if definition.fields.is_empty() {
+        return Err("A format needs at least one field".to_string());
+    }
+    if StrftimeItems::new(&definition.date_format).any(|item| matches!(item, Item::Error)) {
+        return Err(format!("Invalid date format: {}", definition.date_format));
+    }
+    if definition.layout == Layout::Xml {
+        for element in [&definition.root, &definition.record] {
+            match element {
+                Some(name) if is_xml_name(name) => {}
+                Some(name) => return Err(format!("Invalid XML element name: {}", name)),
+                None => return Err("XML formats need root and record elements".to_string()),
+            }
+        }
+    }
+
+    for field in &definition.fields {
+        match (&field.source, &field.value) {
+            (Some(source), None) if source_kind(source).is_none() => {
+                return Err(format!("{}: unknown source {}", field.name, source));
+            }
+            (Some(_), None) | (None, Some(_)) => {}
+            _ => {
+                return Err(format!(
+                    "{}: set either source or value, not both",
+                    field.name
+                ))
+            }
+        }
+        match definition.layout {
+            Layout::Xml if !is_xml_name(&field.name) => {
+                return Err(format!("Invalid XML element name: {}", field.name));
+            }
+            Layout::Xml => {}
+            Layout::FixedWidth => {
+                let width = field
+                    .width
+                    .filter(|width| *width > 0)
+                    .ok_or_else(|| format!("{}: fixed-width fields need a width", field.name))?;
+                if field
+                    .value
+                    .as_ref()
+                    .is_some_and(|value| value.chars().count() > width)
+                {
+                    return Err(format!("{}: value is wider than the field", field.name));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn load_format(conn: &Connection, id: &str) -> Result<TitleExportFormat, String> {
+    let row: Option<(String, String, bool)> = conn
+        .query_row(
+            "SELECT name, definition, built_in FROM title_export_formats WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let (name, definition, built_in) =
+        row.ok_or_else(|| format!("Unknown title export format: {}", id))?;
+    let definition: FormatDefinition = serde_json::from_str(&definition)
+        .map_err(|e| format!("Title export format {} is invalid: {}", id, e))?;
+    check_definition(&definition)
+        .map_err(|e| format!("Title export format {} is invalid: {}", id, e))?;
+    Ok(TitleExportFormat {
+        id: id.to_string(),
+        name,
+        definition,
+        built_in,
+    })
+}
+
+fn list_formats(conn: &Connection) -> Result<Vec<TitleExportFormat>, String> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM title_export_formats ORDER BY built_in DESC, name, id")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    ids.iter().map(|id| load_format(conn, id)).collect()
+}
+
+/// The user's deal with every SOURCES value; None if it isn't theirs or doesn't exist
+fn load_deal(conn: &Connection, user_id: &str, deal_id: &str) -> Result<Option<TitleDeal>, String> {
+    let tz = dealer_timezone(conn);
+    let columns: Vec<&str> = SOURCES.iter().map(|(_, _, sql)| *sql).collect();
+    let sql = format!(
+        "SELECT {}
+         FROM deals d
+         LEFT JOIN clients c ON c.id = d.client_id
+         LEFT JOIN vehicles v ON v.id = d.vehicle_id
+         LEFT JOIN lenders l ON l.id = d.lender_id
+         LEFT JOIN dealer_profile dp ON dp.user_id = d.user_id
+         WHERE d.id = ?1 AND d.user_id = ?2",
+        columns.join(", ")
+    );
+
+    conn.query_row(&sql, params![deal_id, user_id], |row| {
+        let mut values = BTreeMap::new();
+        for (i, (source, kind, _)) in SOURCES.iter().enumerate() {
+            let value = match kind {
+                Kind::Text => row
+                    .get::<_, Option<String>>(i)?
+                    .map(|text| text.trim().to_string())
+                    .filter(|text| !text.is_empty())
+                    .map(FieldValue::Text),
+                Kind::Money => row.get::<_, Option<i64>>(i)?.map(FieldValue::Cents),
+                Kind::Number => row.get::<_, Option<i64>>(i)?.map(FieldValue::Number),
+                Kind::Date => row
+                    .get::<_, Option<i64>>(i)?
+                    .and_then(|ms| local_date(ms, &tz))
+                    .map(FieldValue::Date),
+            };
+            if let Some(value) = value {
+                values.insert(source.to_string(), value);
+            }
+        }
+        Ok(TitleDeal {
+            deal_id: deal_id.to_string(),
+            values,
+        })
+    })
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// A value as the profile writes it; control characters become spaces
+fn format_value(value: &FieldValue, definition: &FormatDefinition) -> String {
+    let text = match value {
+        FieldValue::Text(text) if definition.upper => text.to_uppercase(),
+        FieldValue::Text(text) => text.clone(),
+        FieldValue::Cents(cents) => match definition.money {
+            MoneyStyle::Decimal => format_cents(*cents),
+            MoneyStyle::Cents => cents.to_string(),
+        },
+        FieldValue::Date(date) => date.format(&definition.date_format).to_string(),
+        FieldValue::Number(number) => number.to_string(),
+    };
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// The field's text for the deal; None when the deal has no value for it
+fn field_text(
+    field: &FormatField,
+    deal: &TitleDeal,
+    definition: &FormatDefinition,
+) -> Option<String> {
+    if let Some(value) = &field.value {
+        return Some(value.clone());
+    }
+    let source = field.source.as_deref()?;
+    deal.values
+        .get(source)
+        .map(|value| format_value(value, definition))
+        .filter(|text| !text.trim().is_empty())
+}
+
+/// Every reason the deal can't go in the file
+fn validate(deal: &TitleDeal, definition: &FormatDefinition) -> Vec<String> {
+    let mut errors = Vec::new();
+    for field in &definition.fields {
+        match field_text(field, deal, definition) {
+            None if field.required => errors.push(format!(
+                "{} is missing ({})",
+                field.name,
+                field.source.as_deref().unwrap_or_default()
+            )),
+            Some(text) if definition.layout == Layout::FixedWidth => {
+                let width = field.width.unwrap_or_default();
+                let length = text.chars().count();
+                if length > width {
+                    errors.push(format!(
+                        "{} is {} characters; the format allows {}",
+                        field.name, length, width
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    errors
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn to_xml(deals: &[TitleDeal], definition: &FormatDefinition) -> String {
+    let root = definition.root.as_deref().unwrap_or_default();
+    let record = definition.record.as_deref().unwrap_or_default();
+    let mut lines = vec![
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#.to_string(),
+        format!("<{}>", root),
+    ];
+    for deal in deals {
+        lines.push(format!("  <{}>", record));
+        for field in &definition.fields {
+            // Optional values a deal doesn't have are left out, not written empty
+            if let Some(text) = field_text(field, deal, definition) {
+                lines.push(format!("    <{0}>{1}</{0}>", field.name, xml_escape(&text)));
+            }
+        }
+        lines.push(format!("  </{}>", record));
+    }
+    lines.push(format!("</{}>", root));
+    lines.join("\n") + "\n"
+}
+
+fn to_fixed_width(deals: &[TitleDeal], definition: &FormatDefinition) -> String {
+    let lines: Vec<String> = deals
+        .iter()
+        .map(|deal| {
+            definition
+                .fields
+                .iter()
+                .map(|field| {
+                    let numeric = field
+                        .source
+                        .as_deref()
+                        .and_then(source_kind)
+                        .is_some_and(|kind| matches!(kind, Kind::Money | Kind::Number));
+                    let align =
+                        field
+                            .align
+                            .unwrap_or(if numeric { Align::Right } else { Align::Left });
+                    let pad = field.pad.unwrap_or(if numeric { '0' } else { ' ' });
+                    let text = field_text(field, deal, definition).unwrap_or_default();
+                    let fill: String = std::iter::repeat_n(
+                        pad,
+                        field
+                            .width
+                            .unwrap_or_default()
+                            .saturating_sub(text.chars().count()),
+                    )
+                    .collect();
+                    match align {
+                        Align::Left => text + &fill,
+                        Align::Right => fill + &text,
+                    }
+                })
+                .collect()
+        })
+        .collect();
+    lines.join(LINE_ENDING) + LINE_ENDING
+}
+
+/// Validate each deal and render the ones that pass; not_found are reported as excluded
+fn render(
+    definition: &FormatDefinition,
+    deals: Vec<TitleDeal>,
+    not_found: Vec<String>,
+) -> Rendered {
+    let mut passed = Vec::new();
+    let mut excluded: Vec<ExcludedDeal> = not_found
+        .into_iter()
+        .map(|deal_id| ExcludedDeal {
+            deal_id,
+            errors: vec!["Deal not found".to_string()],
+        })
+        .collect();
+    for deal in deals {
+        let errors = validate(&deal, definition);
+        if errors.is_empty() {
+            passed.push(deal);
+        } else {
+            excluded.push(ExcludedDeal {
+                deal_id: deal.deal_id,
+                errors,
+            });
+        }
+    }
+
+    let content = (!passed.is_empty()).then(|| match definition.layout {
+        Layout::Xml => to_xml(&passed, definition),
+        Layout::FixedWidth => to_fixed_width(&passed, definition),
+    });
+    Rendered {
+        content,
+        exported: passed.into_iter().map(|deal| deal.deal_id).collect(),
+        excluded,
+    }
+}
+
+/// Write the user's deals as title applications in the given format profile
+/// Deals that fail the profile's checks are left out and listed with their errors; the
+/// rest go to output_path. Runs as a report job (see reporting.rs), so job_id lets the UI
+/// cancel it.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn export_title_applications(
+    user_id: Option<String>,
+    deal_ids: Vec<String>,
+    format_profile: String,
+    output_path: String,
+    job_id: Option<String>,
+    window: tauri::Window,
+    state: State<'_, AppState>,
+) -> Result<TitleExport, String> {
+    let user_id_value = state.require_user(user_id)?;
+
+    track_async("export_title_applications", async move {
+        if deal_ids.is_empty() {
+            return Err("No deals selected".to_string());
+        }
+        let path = PathBuf::from(&output_path);
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            if !parent.is_dir() {
+                return Err(format!("Folder does not exist: {}", parent.display()));
+            }
+        }
+
+        let owner = user_id_value.clone();
+        let profile = format_profile.clone();
+        let rendered = run_report_job(
+            job_id,
+            "export_title_applications",
+            &owner,
+            window.label(),
+            move |job, conn| {
+                let format = load_format(conn, &profile)?;
+                let mut seen = HashSet::new();
+                let mut deals = Vec::new();
+                let mut not_found = Vec::new();
+                for deal_id in deal_ids.into_iter().filter(|id| seen.insert(id.clone())) {
+                    match load_deal(conn, &user_id_value, &deal_id)? {
+                        Some(deal) => deals.push(deal),
+                        None => not_found.push(deal_id),
+                    }
+                }
+                let rendered = render(&format.definition, deals, not_found);
+                if let Some(content) = &rendered.content {
+                    write_report_file(job, &path, content.as_bytes())?;
+                }
+                Ok(rendered)
+            },
+        )
+        .await?;
+
+        info!(
+            "✅ Exported {} title applications ({}), {} excluded",
+            rendered.exported.len(),
+            format_profile,
+            rendered.excluded.len()
+        );
+        Ok(TitleExport {
+            path: rendered.content.map(|_| output_path),
+            format_profile,
+            exported: rendered.exported,
+            excluded: rendered.excluded,
+        })
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn get_title_export_formats(db: State<'_, DbState>) -> Result<Vec<TitleExportFormat>, String> {
+    track("get_title_export_formats", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        list_formats(&conn)
+    })
+}
+
+/// Add a format profile, or replace one that isn't built in. id: lower-case letters,
+/// digits and '_' (e.g. "tx_webdealer"). The change is written to the audit log
+#[tauri::command]
+pub fn save_title_export_format(
+    id: String,
+    name: String,
+    definition: FormatDefinition,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<TitleExportFormat, String> {
+    track("save_title_export_format", || {
+        let user_id_value = state.require_user(user_id)?;
+        let id = id.trim();
+        if id.is_empty()
+            || !id
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err("Format ids use lower-case letters, digits and _".to_string());
+        }
+        if name.trim().is_empty() {
+            return Err("Format name is required".to_string());
+        }
+        check_definition(&definition)?;
+
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let now = Utc::now().timestamp_millis();
+        let tx = begin_write(&conn, "title_export_formats").map_err(|e| e.to_string())?;
+        let built_in: Option<bool> = tx
+            .query_row(
+                "SELECT built_in FROM title_export_formats WHERE id = ?1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| e.to_string())?;
+        if built_in == Some(true) {
+            return Err(format!(
+                "{} is built in; save your changes under a new id",
+                id
+            ));
+        }
+        let json = serde_json::to_string(&definition).map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT INTO title_export_formats (id, name, definition, built_in, created_at, updated_at)
+             VALUES (?1, ?2, ?3, 0, ?4, ?4)
+             ON CONFLICT(id) DO UPDATE SET name = excluded.name,
+                 definition = excluded.definition, updated_at = excluded.updated_at",
+            params![id, name.trim(), json, now],
+        )
+        .map_err(|e| e.to_string())?;
+        record_audit(
+            &tx,
+            &user_id_value,
+            "title_export_format_saved",
+            &json!({ "id": id, "name": name.trim() }),
+            now,
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        info!("✅ Title export format {} saved", id);
+        load_format(&conn, id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{db_create_client, db_create_deal, db_create_vehicle};
+    use crate::test_support::{make_client, make_deal, make_vehicle, TestApp, TEST_USER};
+
+    fn text(value: &str) -> FieldValue {
+        FieldValue::Text(value.to_string())
+    }
+
+    fn deal(deal_id: &str, values: Vec<(&str, FieldValue)>) -> TitleDeal {
+        TitleDeal {
+            deal_id: deal_id.to_string(),
+            values: values
+                .into_iter()
+                .map(|(source, value)| (source.to_string(), value))
+                .collect(),
+        }
+    }
+
+    fn deals() -> Vec<TitleDeal> {
+        let date = |day| FieldValue::Date(NaiveDate::from_ymd_opt(2026, 3, day).unwrap());
+        vec![
+            deal(
+                "deal-1001",
+                vec![
+                    ("deal.id", text("deal-1001")),
+                    ("deal.sale_date", date(2)),
+                    ("deal.sale_amount", FieldValue::Cents(1_850_000)),
+                    ("deal.sales_tax", FieldValue::Cents(120_250)),
+                    ("deal.trade_in_value", FieldValue::Cents(400_000)),
+                    ("deal.odometer", FieldValue::Number(23_456)),
+                    ("deal.odometer_disclosure", text("actual")),
+                    ("deal.title_state", text("IL")),
+                    ("client.first_name", text("Jane")),
+                    ("client.last_name", text("Doe")),
+                    ("client.address", text("123 Main St")),
+                    ("client.city", text("Springfield")),
+                    ("client.state", text("IL")),
+                    ("client.zip_code", text("62701")),
+                    ("client.drivers_license", text("D123-4567-8901")),
+                    ("vehicle.vin", text("1HGCM82633A004352")),
+                    ("vehicle.year", FieldValue::Number(2021)),
+                    ("vehicle.make", text("Honda")),
+                    ("vehicle.model", text("Civic")),
+                    ("vehicle.body", text("Sedan")),
+                    ("vehicle.color", text("Blue")),
+                    ("vehicle.title_number", text("T998877")),
+                    ("lender.name", text("First Bank & Trust")),
+                    ("lender.address", text("1 Bank Plaza")),
+                    ("lender.city", text("Chicago")),
+                    ("lender.state", text("IL")),
+                    ("lender.zip_code", text("60601")),
+                    ("dealer.license_number", text("DL-4455")),
+                ],
+            ),
+            // Cash deal: no lender, trade-in or license, and text that needs escaping
+            deal(
+                "deal-1002",
+                vec![
+                    ("deal.id", text("deal-1002")),
+                    ("deal.sale_date", date(15)),
+                    ("deal.sale_amount", FieldValue::Cents(3_299_999)),
+                    ("deal.sales_tax", FieldValue::Cents(214_499)),
+                    ("deal.odometer", FieldValue::Number(61_000)),
+                    ("client.first_name", text("José")),
+                    ("client.last_name", text("O'Brien <Jr>")),
+                    ("client.address", text("9 Elm Rd")),
+                    ("client.city", text("Peoria")),
+                    ("client.state", text("IL")),
+                    ("client.zip_code", text("61602")),
+                    ("vehicle.vin", text("1FTEW1EP5KFA00001")),
+                    ("vehicle.year", FieldValue::Number(2019)),
+                    ("vehicle.make", text("Ford")),
+                    ("vehicle.model", text("F-150")),
+                    ("vehicle.color", text("White")),
+                    ("dealer.license_number", text("DL-4455")),
+                ],
+            ),
+        ]
+    }
+
+    fn built_in(id: &str) -> FormatDefinition {
+        let app = TestApp::new();
+        let conn = app.conn();
+        load_format(&conn, id).unwrap().definition
+    }
+
+    #[test]
+    fn test_xml_profile_matches_golden_file() {
+        let rendered = render(&built_in("generic_xml"), deals(), Vec::new());
+        assert_eq!(rendered.exported, ["deal-1001", "deal-1002"]);
+        assert!(rendered.excluded.is_empty());
+        assert_eq!(
+            rendered.content.as_deref(),
+            Some(include_str!("../testdata/title_export/generic.xml"))
+        );
+    }
+
+    #[test]
+    fn test_fixed_width_profile_matches_golden_file() {
+        let definition = built_in("generic_fixed_width");
+        let rendered = render(&definition, deals(), Vec::new());
+        let content = rendered.content.unwrap();
+        assert_eq!(
+            content,
+            include_str!("../testdata/title_export/generic_fixed_width.txt")
+        );
+        let width: usize = definition.fields.iter().filter_map(|f| f.width).sum();
+        for line in content.split_terminator(LINE_ENDING) {
+            assert_eq!(line.chars().count(), width);
+        }
+    }
+
+    #[test]
+    fn test_failing_deals_are_excluded_with_every_error() {
+        let mut deals = deals();
+        deals[0].values.remove("vehicle.vin");
+        deals[0].values.remove("client.address");
+        deals[1].values.insert(
+            "vehicle.model".to_string(),
+            text("Super Duty F-350 King Ranch Crew Cab"),
+        );
+
+        let fixed = render(
+            &built_in("generic_fixed_width"),
+            deals.clone(),
+            vec!["deal-gone".to_string()],
+        );
+        assert!(fixed.exported.is_empty());
+        assert_eq!(fixed.content, None);
+        assert_eq!(
+            fixed.excluded,
+            [
+                ExcludedDeal {
+                    deal_id: "deal-gone".to_string(),
+                    errors: vec!["Deal not found".to_string()],
+                },
+                ExcludedDeal {
+                    deal_id: "deal-1001".to_string(),
+                    errors: vec![
+                        "VIN is missing (vehicle.vin)".to_string(),
+                        "OWNER_ADDRESS is missing (client.address)".to_string(),
+                    ],
+                },
+                ExcludedDeal {
+                    deal_id: "deal-1002".to_string(),
+                    errors: vec!["MODEL is 36 characters; the format allows 20".to_string()],
+                },
+            ]
+        );
+
+        // XML has no widths, so only the deal missing values is left out
+        let xml = render(&built_in("generic_xml"), deals, Vec::new());
+        assert_eq!(xml.exported, ["deal-1002"]);
+        assert_eq!(xml.excluded.len(), 1);
+        let content = xml.content.unwrap();
+        assert!(content.contains("<Model>Super Duty F-350 King Ranch Crew Cab</Model>"));
+        assert!(!content.contains("deal-1001"));
+    }
+
+    #[test]
+    fn test_deals_load_from_the_database_and_profiles_are_checked() {
+        let app = TestApp::new();
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        let mut sold = make_deal("d1", "c1", "v1");
+        sold.sale_date = Some(1_767_268_800_000);
+        sold.sales_tax = Some(1_202.5);
+        sold.lien_holder = Some("Credit Union".to_string());
+        db_create_deal(sold, None, None, app.state(), app.db()).unwrap();
+
+        let conn = app.conn();
+        let loaded = load_deal(&conn, TEST_USER, "d1").unwrap().unwrap();
+        assert_eq!(loaded.values["vehicle.vin"], text("TESTVIN-v1"));
+        assert_eq!(
+            loaded.values["deal.sale_amount"],
+            FieldValue::Cents(1_850_000)
+        );
+        assert_eq!(loaded.values["deal.sales_tax"], FieldValue::Cents(120_250));
+        assert_eq!(loaded.values["deal.odometer"], FieldValue::Number(42_000));
+        assert_eq!(loaded.values["lender.name"], text("Credit Union"));
+        assert!(!loaded.values.contains_key("client.drivers_license"));
+        assert!(matches!(
+            loaded.values["deal.sale_date"],
+            FieldValue::Date(_)
+        ));
+        assert_eq!(load_deal(&conn, "someone-else", "d1").unwrap(), None);
+
+        let formats = list_formats(&conn).unwrap();
+        assert_eq!(
+            formats.iter().map(|f| f.id.as_str()).collect::<Vec<_>>(),
+            ["generic_xml", "generic_fixed_width"]
+        );
+        assert!(formats.iter().all(|f| f.built_in));
+        assert!(load_format(&conn, "nowhere").is_err());
+
+        let mut bad = built_in("generic_fixed_width");
+        bad.fields[1].width = None;
+        assert!(check_definition(&bad).is_err());
+        let mut bad = built_in("generic_xml");
+        bad.fields[0].source = Some("deal.secret".to_string());
+        assert!(check_definition(&bad)
+            .unwrap_err()
+            .contains("unknown source"));
+        let mut bad = built_in("generic_xml");
+        bad.date_format = "%Q".to_string();
+        assert!(check_definition(&bad).is_err());
+        let mut bad = built_in("generic_xml");
+        bad.root = Some("Title Applications".to_string());
+        assert!(check_definition(&bad).is_err());
+    }
+}
@@ -0,0 +1,345 @@
+// src-tauri/src/trial.rs
+//
+// 14-day trial without a license key
+// The first-run timestamp is stored in three places (OS keyring, settings table,
+// hidden file in app data) and the earliest wins, so deleting one doesn't reset the trial.
+// The keyring entry survives uninstall/reinstall, which keeps reinstalls from restarting it.
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::database::db_get_setting;
+use crate::secret_store::{self, SecretKey};
+use crate::settings_cache::set_setting;
+use crate::storage::get_app_data_dir;
+
+/// Settings keys
+pub const TRIAL_STARTED_SETTING: &str = "trial_started_at";
+pub const TRIAL_LAST_SEEN_SETTING: &str = "trial_last_seen_at";
+
+const TRIAL_FILE_NAME: &str = ".trial";
+
+pub const TRIAL_DAYS: i64 = 14;
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+
+/// Clock corrections (NTP sync, DST mistakes) smaller than this aren't treated as rollback
+const CLOCK_ROLLBACK_TOLERANCE_SECS: i64 = 60 * 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrialState {
+    NotStarted,
+    Active,
+    Expired,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TrialStatus {
+    pub state: TrialState,
+    pub started_at: Option<i64>, // Unix seconds
+    pub expires_at: Option<i64>,
+    pub days_remaining: i64,
+    pub clock_rollback_detected: bool,
+}
+
+/// Contents of the hidden trial file in app data
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct TrialFile {
+    started_at: Option<i64>,
+    last_seen_at: Option<i64>,
+}
+
+/// Where each copy of the trial timestamps was found
+#[derive(Debug, Clone, Copy, Default)]
+struct TrialSources {
+    keyring: Option<i64>,
+    setting: Option<i64>,
+    file: TrialFile,
+    last_seen_setting: Option<i64>,
+}
+
+impl TrialSources {
+    fn started_at(&self) -> Option<i64> {
+        [self.keyring, self.setting, self.file.started_at]
+            .into_iter()
+            .flatten()
+            .min()
+    }
+
+    fn last_seen_at(&self) -> Option<i64> {
+        [self.last_seen_setting, self.file.last_seen_at]
+            .into_iter()
+            .flatten()
+            .max()
+    }
+}
+
+/// Start the trial on this machine. Never resets a trial that already started
+#[tauri::command]
+pub fn start_trial() -> Result<TrialStatus, String> {
+    let now = chrono::Utc::now().timestamp();
+    let sources = read_sources();
+
+    if sources.started_at().is_some() {
+        info!("ℹ️ [TRIAL] Trial already started");
+        return Ok(evaluate(&sources, now));
+    }
+
+    info!("🆕 [TRIAL] Starting {}-day trial", TRIAL_DAYS);
+    let started = TrialSources {
+        keyring: Some(now),
+        setting: Some(now),
+        file: TrialFile {
+            started_at: Some(now),
+            last_seen_at: Some(now),
+        },
+        last_seen_setting: Some(now),
+    };
+    write_sources(&sources, &started)?;
+
+    Ok(evaluate(&started, now))
+}
+
+/// Current trial state, cross-checking all stored copies
+#[tauri::command]
+pub fn get_trial_status() -> TrialStatus {
+    evaluate(&read_sources(), chrono::Utc::now().timestamp())
+}
+
+/// Record this run's time and restore any copy that went missing. Call at startup
+pub fn record_run() {
+    let now = chrono::Utc::now().timestamp();
+    let sources = read_sources();
+
+    let started_at = match sources.started_at() {
+        Some(started_at) => started_at,
+        None => return,
+    };
+
+    // Never move last_seen backwards, even when the clock was rolled back
+    let last_seen = sources.last_seen_at().map_or(now, |seen| seen.max(now));
+    let healed = TrialSources {
+        keyring: Some(started_at),
+        setting: Some(started_at),
+        file: TrialFile {
+            started_at: Some(started_at),
+            last_seen_at: Some(last_seen),
+        },
+        last_seen_setting: Some(last_seen),
+    };
+
+    if let Err(e) = write_sources(&sources, &healed) {
+        warn!("⚠️  [TRIAL] Failed to record run: {}", e);
+    }
+}
+
+fn evaluate(sources: &TrialSources, now: i64) -> TrialStatus {
+    let started_at = match sources.started_at() {
+        Some(started_at) => started_at,
+        None => {
+            return TrialStatus {
+                state: TrialState::NotStarted,
+                started_at: None,
+                expires_at: None,
+                days_remaining: TRIAL_DAYS,
+                clock_rollback_detected: false,
+            }
+        }
+    };
+
+    // A clock earlier than a run we already saw (or than the trial start) was rolled back;
+    // count from the latest trustworthy time instead so rollback can't extend the trial
+    let trusted_floor = sources.last_seen_at().unwrap_or(started_at).max(started_at);
+    let clock_rollback_detected = now + CLOCK_ROLLBACK_TOLERANCE_SECS < trusted_floor;
+    let effective_now = now.max(trusted_floor);
+
+    if clock_rollback_detected {
+        warn!("⚠️  [TRIAL] System clock is earlier than the last recorded run");
+    }
+
+    let expires_at = started_at + TRIAL_DAYS * DAY_SECS;
+    let remaining_secs = (expires_at - effective_now).max(0);
+    let days_remaining = (remaining_secs + DAY_SECS - 1) / DAY_SECS;
+
+    TrialStatus {
+        state: if remaining_secs > 0 {
+            TrialState::Active
+        } else {
+            TrialState::Expired
+        },
+        started_at: Some(started_at),
+        expires_at: Some(expires_at),
+        days_remaining,
+        clock_rollback_detected,
+    }
+}
+
+fn read_sources() -> TrialSources {
+    TrialSources {
+        keyring: read_keyring(),
+        setting: read_setting(TRIAL_STARTED_SETTING),
+        file: trial_file_path()
+            .map(|path| read_trial_file(&path))
+            .unwrap_or_default(),
+        last_seen_setting: read_setting(TRIAL_LAST_SEEN_SETTING),
+    }
+}
+
+/// Write only the copies that changed
+fn write_sources(current: &TrialSources, desired: &TrialSources) -> Result<(), String> {
+    if let Some(started_at) = desired
+        .keyring
+        .filter(|_| current.keyring != desired.keyring)
+    {
+        write_keyring(started_at)?;
+    }
+    if let Some(started_at) = desired
+        .setting
+        .filter(|_| current.setting != desired.setting)
+    {
+        set_setting(TRIAL_STARTED_SETTING, &started_at.to_string())?;
+    }
+    if let Some(last_seen) = desired
+        .last_seen_setting
+        .filter(|_| current.last_seen_setting != desired.last_seen_setting)
+    {
+        set_setting(TRIAL_LAST_SEEN_SETTING, &last_seen.to_string())?;
+    }
+    if current.file != desired.file {
+        write_trial_file(&trial_file_path()?, &desired.file)?;
+    }
+    Ok(())
+}
+
+fn read_setting(key: &str) -> Option<i64> {
+    db_get_setting(key.to_string())
+        .ok()
+        .flatten()
+        .and_then(|value| value.trim().parse().ok())
+}
+
+fn read_keyring() -> Option<i64> {
+    secret_store::get(SecretKey::TrialStartedAt)
+        .ok()??
+        .trim()
+        .parse()
+        .ok()
+}
+
+fn write_keyring(started_at: i64) -> Result<(), String> {
+    secret_store::store(SecretKey::TrialStartedAt, &started_at.to_string()).map_err(|e| format!("Failed to store trial start: {}", e))
+}
+
+fn trial_file_path() -> Result<PathBuf, String> {
+    Ok(get_app_data_dir()?.join(TRIAL_FILE_NAME))
+}
+
+fn read_trial_file(path: &Path) -> TrialFile {
+    fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_trial_file(path: &Path, file: &TrialFile) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string(file)
+        .map_err(|e| format!("Failed to serialize trial file: {}", e))?;
+    fs::write(path, json).map_err(|e| format!("Failed to write trial file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: i64 = 1_767_225_600; // 2026-01-01T00:00:00Z
+
+    fn sources(keyring: Option<i64>, setting: Option<i64>, file: Option<i64>) -> TrialSources {
+        TrialSources {
+            keyring,
+            setting,
+            file: TrialFile {
+                started_at: file,
+                last_seen_at: None,
+            },
+            last_seen_setting: None,
+        }
+    }
+
+    #[test]
+    fn test_earliest_source_wins_when_some_are_missing() {
+        let status = evaluate(&sources(None, None, None), START);
+        assert_eq!(status.state, TrialState::NotStarted);
+        assert_eq!(status.days_remaining, TRIAL_DAYS);
+
+        // Reinstall wiped settings and app data; the keyring copy still counts
+        let status = evaluate(&sources(Some(START), None, None), START + 3 * DAY_SECS);
+        assert_eq!(status.state, TrialState::Active);
+        assert_eq!(status.started_at, Some(START));
+        assert_eq!(status.days_remaining, 11);
+
+        // A tampered later start in one source doesn't extend the trial
+        let status = evaluate(
+            &sources(Some(START + 10 * DAY_SECS), Some(START), None),
+            START + 14 * DAY_SECS,
+        );
+        assert_eq!(status.state, TrialState::Expired);
+        assert_eq!(status.days_remaining, 0);
+
+        // Partial days round up
+        let status = evaluate(&sources(None, None, Some(START)), START + DAY_SECS / 2);
+        assert_eq!(status.days_remaining, 14);
+    }
+
+    #[test]
+    fn test_clock_rollback_detected_and_ignored() {
+        let mut trial = sources(Some(START), Some(START), Some(START));
+        trial.last_seen_setting = Some(START + 13 * DAY_SECS);
+
+        // Clock set back to day 2 after we already ran on day 13
+        let status = evaluate(&trial, START + 2 * DAY_SECS);
+        assert!(status.clock_rollback_detected);
+        assert_eq!(status.days_remaining, 1);
+
+        // Last-seen survives in the hidden file even if the settings copy is deleted
+        trial.last_seen_setting = None;
+        trial.file.last_seen_at = Some(START + 15 * DAY_SECS);
+        let status = evaluate(&trial, START + 2 * DAY_SECS);
+        assert!(status.clock_rollback_detected);
+        assert_eq!(status.state, TrialState::Expired);
+
+        // Small corrections are tolerated
+        trial.file.last_seen_at = Some(START + DAY_SECS + 60);
+        let status = evaluate(&trial, START + DAY_SECS);
+        assert!(!status.clock_rollback_detected);
+        assert_eq!(status.state, TrialState::Active);
+    }
+
+    #[test]
+    fn test_trial_file_round_trip() {
+        let path = std::env::temp_dir()
+            .join(format!("dealer-trial-test-{}", std::process::id()))
+            .join(TRIAL_FILE_NAME);
+        assert_eq!(read_trial_file(&path), TrialFile::default());
+
+        let file = TrialFile {
+            started_at: Some(START),
+            last_seen_at: Some(START + 60),
+        };
+        write_trial_file(&path, &file).unwrap();
+        assert_eq!(read_trial_file(&path), file);
+
+        // Garbage is treated as missing, not as a fresh trial start
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(read_trial_file(&path), TrialFile::default());
+
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,494 @@
+// src-tauri/src/updater.rs
+//
+// Command surface for tauri_plugin_updater: check a release channel, download with
+// progress, and restart only when the user says so.
+// The plugin verifies every download against the pubkey in tauri.conf.json before it
+// is installed; a failed verification is reported separately from network errors.
+
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_updater::{Update, UpdaterExt};
+use url::Url;
+
+use crate::database::db_get_setting;
+use crate::settings_cache::set_setting;
+
+/// Settings keys
+pub const UPDATE_CHANNEL_SETTING: &str = "update_channel";
+pub const UPDATE_CHECK_INTERVAL_SETTING: &str = "update_check_interval_hours"; // 0 = off
+
+pub const UPDATE_AVAILABLE_EVENT: &str = "update-available";
+pub const UPDATE_PROGRESS_EVENT: &str = "update-download-progress";
+pub const UPDATE_READY_EVENT: &str = "update-ready";
+
+const STABLE_ENDPOINT: &str =
+    "https://github.com/treyktw/dealer-applications/releases/latest/download/latest.json";
+/// Rolling "beta" release whose assets are replaced on every prerelease build
+const BETA_ENDPOINT: &str =
+    "https://github.com/treyktw/dealer-applications/releases/download/beta/latest.json";
+
+const DEFAULT_CHECK_INTERVAL_HOURS: u64 = 24;
+const CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+/// How often the background task wakes to see whether a check is due
+const BACKGROUND_POLL_INTERVAL: Duration = Duration::from_secs(15 * 60);
+const PROGRESS_EMIT_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Update found by the last check, waiting for download_and_install_update
+static PENDING_UPDATE: Mutex<Option<Update>> = Mutex::new(None);
+/// Downloaded update waiting for restart_to_update
+static READY_UPDATE: Mutex<Option<ReadyUpdate>> = Mutex::new(None);
+
+struct ReadyUpdate {
+    update: Update,
+    /// Windows installers close the app, so there the install itself waits for the restart
+    install_on_restart: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    Beta,
+}
+
+impl UpdateChannel {
+    fn endpoint(self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => STABLE_ENDPOINT,
+            UpdateChannel::Beta => BETA_ENDPOINT,
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "stable" => Some(UpdateChannel::Stable),
+            "beta" => Some(UpdateChannel::Beta),
+            _ => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "stable",
+            UpdateChannel::Beta => "beta",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct UpdateInfo {
+    pub available: bool,
+    pub version: Option<String>,
+    pub notes: Option<String>,
+    pub pub_date: Option<String>, // RFC 3339, as published in latest.json
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdatePreferences {
+    pub channel: UpdateChannel,
+    pub check_interval_hours: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum UpdateError {
+    /// Couldn't reach the update server or the download was interrupted
+    Network {
+        message: String,
+    },
+    /// The download didn't match the release signature; nothing was installed
+    Signature {
+        message: String,
+    },
+    /// download_and_install_update / restart_to_update called with nothing to act on
+    NoPendingUpdate,
+    Failed {
+        message: String,
+    },
+}
+
+impl std::fmt::Display for UpdateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UpdateError::Network { message } => write!(f, "Network error: {}", message),
+            UpdateError::Signature { message } => {
+                write!(f, "Update signature verification failed: {}", message)
+            }
+            UpdateError::NoPendingUpdate => write!(f, "No update is pending"),
+            UpdateError::Failed { message } => write!(f, "Update failed: {}", message),
+        }
+    }
+}
+
+impl From<tauri_plugin_updater::Error> for UpdateError {
+    fn from(e: tauri_plugin_updater::Error) -> Self {
+        use tauri_plugin_updater::Error;
+
+        let message = e.to_string();
+        match e {
+            Error::Reqwest(_) | Error::Network(_) | Error::ReleaseNotFound => {
+                UpdateError::Network { message }
+            }
+            Error::Minisign(_) | Error::Base64(_) | Error::SignatureUtf8(_) => {
+                UpdateError::Signature { message }
+            }
+            _ => UpdateError::Failed { message },
+        }
+    }
+}
+
+/// Ask the channel's endpoint whether a newer version exists
+#[tauri::command]
+pub async fn check_for_update(
+    app: AppHandle,
+    channel: UpdateChannel,
+) -> Result<UpdateInfo, UpdateError> {
+    check_channel(&app, channel).await
+}
+
+/// Download the update found by check_for_update, emitting update-download-progress.
+/// Doesn't restart: call restart_to_update once the user confirms
+#[tauri::command]
+pub async fn download_and_install_update(app: AppHandle) -> Result<UpdateInfo, UpdateError> {
+    // Taking it out also keeps a second click from starting a parallel download
+    let update = PENDING_UPDATE
+        .lock()
+        .map_err(|e| UpdateError::Failed {
+            message: e.to_string(),
+        })?
+        .take()
+        .ok_or(UpdateError::NoPendingUpdate)?;
+
+    info!("⬇️ [UPDATE] Downloading {}", update.version);
+    let mut throttle = ProgressThrottle::new(PROGRESS_EMIT_INTERVAL);
+    let progress_app = app.clone();
+    let bytes = update
+        .download(
+            |chunk, total| {
+                if let Some(progress) = throttle.record(chunk as u64, total, Instant::now()) {
+                    let _ = progress_app.emit(UPDATE_PROGRESS_EVENT, progress);
+                }
+            },
+            || info!("✅ [UPDATE] Download finished, verifying signature"),
+        )
+        .await
+        .map_err(|e| {
+            let e = UpdateError::from(e);
+            error!("❌ [UPDATE] {}", e);
+            e
+        })?;
+    let _ = app.emit(UPDATE_PROGRESS_EVENT, throttle.finish());
+
+    let install_on_restart = if cfg!(windows) {
+        Some(bytes)
+    } else {
+        update.install(&bytes).map_err(UpdateError::from)?;
+        None
+    };
+
+    let info = update_info(Some(&update));
+    *READY_UPDATE.lock().map_err(|e| UpdateError::Failed {
+        message: e.to_string(),
+    })? = Some(ReadyUpdate {
+        update,
+        install_on_restart,
+    });
+
+    info!("✅ [UPDATE] Update ready, waiting for restart");
+    let _ = app.emit(UPDATE_READY_EVENT, &info);
+    Ok(info)
+}
+
+/// Restart into the downloaded update. Only call after the user confirms
+#[tauri::command]
+pub fn restart_to_update(app: AppHandle) -> Result<(), UpdateError> {
+    let ready = READY_UPDATE
+        .lock()
+        .map_err(|e| UpdateError::Failed {
+            message: e.to_string(),
+        })?
+        .take()
+        .ok_or(UpdateError::NoPendingUpdate)?;
+
+    info!("🔄 [UPDATE] Restarting into {}", ready.update.version);
+    if let Some(bytes) = ready.install_on_restart {
+        ready.update.install(bytes).map_err(UpdateError::from)?;
+    }
+    app.restart()
+}
+
+#[tauri::command]
+pub fn get_update_preferences() -> Result<UpdatePreferences, String> {
+    Ok(UpdatePreferences::from_settings(
+        db_get_setting(UPDATE_CHANNEL_SETTING.to_string())?,
+        db_get_setting(UPDATE_CHECK_INTERVAL_SETTING.to_string())?,
+    ))
+}
+
+/// check_interval_hours = 0 turns background checks off
+#[tauri::command]
+pub fn set_update_preferences(preferences: UpdatePreferences) -> Result<(), String> {
+    set_setting(UPDATE_CHANNEL_SETTING, preferences.channel.as_str())?;
+    set_setting(
+        UPDATE_CHECK_INTERVAL_SETTING,
+        &preferences.check_interval_hours.to_string(),
+    )
+}
+
+/// Check on the configured interval and emit update-available once per new version
+pub fn start_update_checks(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let mut last_check: Option<Instant> = None;
+        let mut notified_for: Option<String> = None;
+
+        loop {
+            tokio::time::sleep(BACKGROUND_POLL_INTERVAL).await;
+
+            let preferences = match get_update_preferences() {
+                Ok(preferences) => preferences,
+                Err(e) => {
+                    warn!("⚠️  [UPDATE] Couldn't read update preferences: {}", e);
+                    continue;
+                }
+            };
+            if !check_due(last_check, Instant::now(), preferences.check_interval_hours) {
+                continue;
+            }
+            last_check = Some(Instant::now());
+
+            match check_channel(&app, preferences.channel).await {
+                Ok(info) if info.available && info.version != notified_for => {
+                    info!("🆕 [UPDATE] Update available: {:?}", info.version);
+                    if let Err(e) = app.emit(UPDATE_AVAILABLE_EVENT, &info) {
+                        error!("Failed to emit update status: {}", e);
+                    }
+                    notified_for = info.version;
+                }
+                Ok(_) => {}
+                Err(e) => warn!("⚠️  [UPDATE] Background check failed: {}", e),
+            }
+        }
+    });
+}
+
+async fn check_channel(app: &AppHandle, channel: UpdateChannel) -> Result<UpdateInfo, UpdateError> {
+    info!("🔍 [UPDATE] Checking {} channel", channel.as_str());
+    let endpoint = Url::parse(channel.endpoint()).map_err(|e| UpdateError::Failed {
+        message: e.to_string(),
+    })?;
+
+    let update = app
+        .updater_builder()
+        .endpoints(vec![endpoint])?
+        .timeout(CHECK_TIMEOUT)
+        .build()?
+        .check()
+        .await?;
+
+    let info = update_info(update.as_ref());
+    *PENDING_UPDATE.lock().map_err(|e| UpdateError::Failed {
+        message: e.to_string(),
+    })? = update;
+    Ok(info)
+}
+
+fn update_info(update: Option<&Update>) -> UpdateInfo {
+    match update {
+        Some(update) => UpdateInfo {
+            available: true,
+            version: Some(update.version.clone()),
+            notes: update.body.clone(),
+            pub_date: manifest_pub_date(&update.raw_json),
+        },
+        None => UpdateInfo::default(),
+    }
+}
+
+/// The plugin parses pub_date into its own type; the manifest's string is what we show
+fn manifest_pub_date(manifest: &serde_json::Value) -> Option<String> {
+    manifest
+        .get("pub_date")
+        .and_then(|date| date.as_str())
+        .map(str::to_string)
+}
+
+fn check_due(last_check: Option<Instant>, now: Instant, interval_hours: u64) -> bool {
+    if interval_hours == 0 {
+        return false;
+    }
+    last_check.is_none_or(|last| {
+        now.duration_since(last) >= Duration::from_secs(interval_hours * 60 * 60)
+    })
+}
+
+impl UpdatePreferences {
+    fn from_settings(channel: Option<String>, interval: Option<String>) -> Self {
+        Self {
+            channel: channel
+                .as_deref()
+                .and_then(UpdateChannel::parse)
+                .unwrap_or_default(),
+            check_interval_hours: interval
+                .and_then(|hours| hours.trim().parse().ok())
+                .unwrap_or(DEFAULT_CHECK_INTERVAL_HOURS),
+        }
+    }
+}
+
+/// Rate-limits progress events; the plugin reports every network chunk
+struct ProgressThrottle {
+    downloaded: u64,
+    total: Option<u64>,
+    interval: Duration,
+    last_emit: Option<Instant>,
+}
+
+impl ProgressThrottle {
+    fn new(interval: Duration) -> Self {
+        Self {
+            downloaded: 0,
+            total: None,
+            interval,
+            last_emit: None,
+        }
+    }
+
+    fn record(&mut self, chunk: u64, total: Option<u64>, now: Instant) -> Option<DownloadProgress> {
+        self.downloaded += chunk;
+        self.total = total.or(self.total);
+
+        if self
+            .last_emit
+            .is_some_and(|last| now.duration_since(last) < self.interval)
+        {
+            return None;
+        }
+        self.last_emit = Some(now);
+        Some(self.progress())
+    }
+
+    fn finish(&self) -> DownloadProgress {
+        self.progress()
+    }
+
+    fn progress(&self) -> DownloadProgress {
+        DownloadProgress {
+            downloaded: self.downloaded,
+            total: self.total,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tauri_plugin_updater::Error;
+
+    #[test]
+    fn test_error_codes_distinguish_network_and_signature() {
+        let network = UpdateError::from(Error::Network("connection reset".into()));
+        assert!(matches!(network, UpdateError::Network { .. }));
+        assert!(matches!(
+            UpdateError::from(Error::ReleaseNotFound),
+            UpdateError::Network { .. }
+        ));
+        assert!(matches!(
+            UpdateError::from(Error::SignatureUtf8("bad".into())),
+            UpdateError::Signature { .. }
+        ));
+        assert!(matches!(
+            UpdateError::from(Error::InsecureTransportProtocol),
+            UpdateError::Failed { .. }
+        ));
+
+        let json = serde_json::to_value(&network).unwrap();
+        assert_eq!(json["code"], "network");
+        let json = serde_json::to_value(UpdateError::NoPendingUpdate).unwrap();
+        assert_eq!(json, serde_json::json!({ "code": "no_pending_update" }));
+    }
+
+    #[test]
+    fn test_channels_and_preferences() {
+        assert_eq!(UpdateChannel::Stable.endpoint(), STABLE_ENDPOINT);
+        assert_eq!(UpdateChannel::Beta.endpoint(), BETA_ENDPOINT);
+        let channel: UpdateChannel = serde_json::from_str("\"beta\"").unwrap();
+        assert_eq!(channel, UpdateChannel::Beta);
+        assert!(serde_json::from_str::<UpdateChannel>("\"nightly\"").is_err());
+
+        assert_eq!(
+            UpdatePreferences::from_settings(None, None),
+            UpdatePreferences {
+                channel: UpdateChannel::Stable,
+                check_interval_hours: DEFAULT_CHECK_INTERVAL_HOURS,
+            }
+        );
+        assert_eq!(
+            UpdatePreferences::from_settings(Some("beta".into()), Some(" 6 ".into())),
+            UpdatePreferences {
+                channel: UpdateChannel::Beta,
+                check_interval_hours: 6,
+            }
+        );
+        // Garbage falls back to defaults rather than disabling updates
+        assert_eq!(
+            UpdatePreferences::from_settings(Some("nightly".into()), Some("soon".into())),
+            UpdatePreferences::from_settings(None, None)
+        );
+    }
+
+    #[test]
+    fn test_background_check_schedule() {
+        let now = Instant::now();
+        assert!(check_due(None, now, 24));
+        assert!(!check_due(None, now, 0));
+
+        let later = now + Duration::from_secs(2 * 60 * 60);
+        assert!(!check_due(Some(now), later, 3));
+        assert!(check_due(Some(now), later, 2));
+    }
+
+    #[test]
+    fn test_manifest_pub_date_and_progress_throttle() {
+        let manifest = serde_json::json!({
+            "version": "1.4.0",
+            "notes": "Bug fixes",
+            "pub_date": "2026-03-01T12:00:00Z",
+        });
+        assert_eq!(
+            manifest_pub_date(&manifest).as_deref(),
+            Some("2026-03-01T12:00:00Z")
+        );
+        assert_eq!(manifest_pub_date(&serde_json::json!({})), None);
+
+        let start = Instant::now();
+        let mut throttle = ProgressThrottle::new(Duration::from_millis(250));
+        assert_eq!(
+            throttle.record(100, Some(1000), start),
+            Some(DownloadProgress {
+                downloaded: 100,
+                total: Some(1000),
+            })
+        );
+        // Chunks inside the interval are counted but not emitted
+        assert_eq!(
+            throttle.record(100, None, start + Duration::from_millis(100)),
+            None
+        );
+        let progress = throttle
+            .record(100, None, start + Duration::from_millis(300))
+            .unwrap();
+        assert_eq!(progress.downloaded, 300);
+        assert_eq!(progress.total, Some(1000));
+        assert_eq!(throttle.finish().downloaded, 300);
+    }
+}
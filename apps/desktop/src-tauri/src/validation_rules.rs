@@ -0,0 +1,561 @@
+// src-tauri/src/validation_rules.rs
+//
+// Dealer-configured minimums for clients and vehicles (one dealer wants a driver's license
+// number before a test drive, another doesn't). The validation_rules setting holds JSON rules
+// per entity and field: required, a regex the value must match, and min/max (the value of a
+// number, the length of a string). An entity with no rules saved uses the built-in ones.
+// db_create_client, db_update_client, db_create_vehicle and db_update_vehicle check the
+// record they're about to write and fail with ValidationFailed, listing every broken rule;
+// validate_entity runs the same check on a form as the user types, without saving anything.
+// Rules are checked when saved: unknown fields, bad regexes and min above max are refused.
+
+use chrono::Utc;
+use log::{info, warn};
+use regex::Regex;
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::audit_log::record_audit;
+use crate::database::{begin_write, read_setting, upsert_setting, DbState};
+use crate::db_busy::DbError;
+use crate::settings_cache::pending_setting;
+use crate::telemetry::track;
+
+/// Settings key: the saved ValidationRules (JSON)
+pub const VALIDATION_RULES_SETTING: &str = "validation_rules";
+
+const CLIENT_FIELDS: &[&str] = &[
+    "first_name",
+    "last_name",
+    "email",
+    "phone",
+    "address",
+    "city",
+    "state",
+    "zip_code",
+    "drivers_license",
+];
+const VEHICLE_FIELDS: &[&str] = &[
+    "vin",
+    "stock_number",
+    "year",
+    "make",
+    "model",
+    "trim",
+    "body",
+    "doors",
+    "transmission",
+    "engine",
+    "cylinders",
+    "title_number",
+    "mileage",
+    "color",
+    "price",
+    "cost",
+    "status",
+    "description",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Entity {
+    Client,
+    Vehicle,
+}
+
+impl Entity {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Entity::Client => "client",
+            Entity::Vehicle => "vehicle",
+        }
+    }
+
+    /// Fields rules may name, in form order
+    fn fields(&self) -> &'static [&'static str] {
+        match self {
+            Entity::Client => CLIENT_FIELDS,
+            Entity::Vehicle => VEHICLE_FIELDS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldRule {
+    #[serde(default)]
+    pub required: bool,
+    /// Checked only when there's a value; anchor it (^...$) to match the whole value
+    #[serde(default)]
+    pub regex: Option<String>,
+    /// A number's value, or a string's length in characters
+    #[serde(default)]
+    pub min: Option<f64>,
+    #[serde(default)]
+    pub max: Option<f64>,
+    /// Shown instead of the generated message for any of this field's rules
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+/// Rules by field
+pub type EntityRules = BTreeMap<String, FieldRule>;
+
+/// The saved setting; an entity left out uses the built-in rules
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidationRules {
+    #[serde(default)]
+    pub client: Option<EntityRules>,
+    #[serde(default)]
+    pub vehicle: Option<EntityRules>,
+}
+
+impl ValidationRules {
+    fn for_entity(&self, entity: Entity) -> Option<&EntityRules> {
+        match entity {
+            Entity::Client => self.client.as_ref(),
+            Entity::Vehicle => self.vehicle.as_ref(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ValidationRulesView {
+    /// The rules enforced for each entity
+    pub rules: BTreeMap<Entity, EntityRules>,
+    /// Entities with no rules saved, so the built-in ones apply
+    pub using_defaults: Vec<Entity>,
+}
+
+/// One broken rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleViolation {
+    /// "{entity}.{field}.{required|regex|min|max}"
+    pub rule_id: String,
+    pub field: String,
+    pub message: String,
+}
+
+/// What applies with no rules saved: the columns the app can't do without
+fn built_in(entity: Entity) -> EntityRules {
+    let required = || FieldRule {
+        required: true,
+        ..FieldRule::default()
+    };
+    let at_least = |min: f64| FieldRule {
+        min: Some(min),
+        ..FieldRule::default()
+    };
+    let rules = match entity {
+        Entity::Client => vec![("first_name", required()), ("last_name", required())],
+        Entity::Vehicle => vec![
+            ("vin", required()),
+            (
+                "year",
+                FieldRule {
+                    required: true,
+                    min: Some(1900.0),
+                    max: Some(2100.0),
+                    ..FieldRule::default()
+                },
+            ),
+            ("make", required()),
+            ("model", required()),
+            ("mileage", at_least(0.0)),
+            ("price", at_least(0.0)),
+        ],
+    };
+    rules
+        .into_iter()
+        .map(|(field, rule)| (field.to_string(), rule))
+        .collect()
+}
+
+/// Refuse rules that name a field the entity doesn't have, or that can't be checked
+fn check_rules(rules: &ValidationRules) -> Result<(), String> {
+    for entity in [Entity::Client, Entity::Vehicle] {
+        for (field, rule) in rules.for_entity(entity).into_iter().flatten() {
+            if !entity.fields().contains(&field.as_str()) {
+                return Err(format!(
+                    "Unknown {} field '{}' (expected one of: {})",
+                    entity.as_str(),
+                    field,
+                    entity.fields().join(", ")
+                ));
+            }
+            if let Some(pattern) = &rule.regex {
+                Regex::new(pattern).map_err(|e| {
+                    format!("Invalid regex for {}.{}: {}", entity.as_str(), field, e)
+                })?;
+            }
+            if let (Some(min), Some(max)) = (rule.min, rule.max) {
+                if min > max {
+                    return Err(format!(
+                        "{}.{}: min ({}) is above max ({})",
+                        entity.as_str(),
+                        field,
+                        min,
+                        max
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The saved rules (or one not yet written); none if nothing valid is saved
+fn saved_rules(conn: &Connection) -> ValidationRules {
+    let value = pending_setting(VALIDATION_RULES_SETTING)
+        .or_else(|| read_setting(conn, VALIDATION_RULES_SETTING).ok().flatten())
+        .filter(|value| !value.trim().is_empty());
+    let Some(value) = value else {
+        return ValidationRules::default();
+    };
+    match serde_json::from_str(&value) {
+        Ok(rules) => rules,
+        Err(e) => {
+            warn!(
+                "⚠️  Invalid validation rules, using the built-in ones: {}",
+                e
+            );
+            ValidationRules::default()
+        }
+    }
+}
+
+/// The rules enforced for entity
+pub(crate) fn rules_for(conn: &Connection, entity: Entity) -> EntityRules {
+    saved_rules(conn)
+        .for_entity(entity)
+        .cloned()
+        .unwrap_or_else(|| built_in(entity))
+}
+
+/// "drivers_license" -> "Drivers license"
+fn label(field: &str) -> String {
+    let words = field.replace('_', " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => words,
+    }
+}
+
+/// Every rule the record (as JSON) breaks, in form order
+pub(crate) fn violations(
+    entity: Entity,
+    rules: &EntityRules,
+    record: &Value,
+) -> Vec<RuleViolation> {
+    let mut violations = Vec::new();
+    for field in entity.fields() {
+        let Some(rule) = rules.get(*field) else {
+            continue;
+        };
+        let mut violated = |kind: &str, message: String| {
+            violations.push(RuleViolation {
+                rule_id: format!("{}.{}.{}", entity.as_str(), field, kind),
+                field: field.to_string(),
+                message: rule.message.clone().unwrap_or(message),
+            })
+        };
+        let label = label(field);
+        let value = record.get(*field).filter(|value| match value {
+            Value::Null => false,
+            Value::String(text) => !text.trim().is_empty(),
+            _ => true,
+        });
+        let Some(value) = value else {
+            if rule.required {
+                violated("required", format!("{} is required", label));
+            }
+            continue;
+        };
+
+        let text = match value {
+            Value::String(text) => Some(text.clone()),
+            Value::Number(number) => Some(number.to_string()),
+            _ => None,
+        };
+        if let (Some(pattern), Some(text)) = (&rule.regex, &text) {
+            match Regex::new(pattern) {
+                Ok(regex) if !regex.is_match(text) => {
+                    violated("regex", format!("{} is not in the expected format", label))
+                }
+                Ok(_) => {}
+                Err(e) => warn!("⚠️  Skipping invalid regex for {}: {}", field, e),
+            }
+        }
+
+        let (size, unit) = match value {
+            Value::Number(number) => (number.as_f64(), ""),
+            Value::String(text) => (Some(text.chars().count() as f64), " characters"),
+            _ => (None, ""),
+        };
+        if let Some(size) = size {
+            if let Some(min) = rule.min.filter(|min| size < *min) {
+                violated("min", format!("{} must be at least {}{}", label, min, unit));
+            }
+            if let Some(max) = rule.max.filter(|max| size > *max) {
+                violated("max", format!("{} must be at most {}{}", label, max, unit));
+            }
+        }
+    }
+    violations
+}
+
+/// Fail with ValidationFailed if the record about to be written breaks any rule
+pub(crate) fn enforce_rules(
+    conn: &Connection,
+    entity: Entity,
+    record: &impl Serialize,
+) -> Result<(), DbError> {
+    let record = serde_json::to_value(record).map_err(|e| e.to_string())?;
+    let violations = violations(entity, &rules_for(conn, entity), &record);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(DbError::ValidationFailed { violations })
+    }
+}
+
+fn view(conn: &Connection) -> ValidationRulesView {
+    let saved = saved_rules(conn);
+    let mut rules = BTreeMap::new();
+    let mut using_defaults = Vec::new();
+    for entity in [Entity::Client, Entity::Vehicle] {
+        let entity_rules = match saved.for_entity(entity) {
+            Some(entity_rules) => entity_rules.clone(),
+            None => {
+                using_defaults.push(entity);
+                built_in(entity)
+            }
+        };
+        rules.insert(entity, entity_rules);
+    }
+    ValidationRulesView {
+        rules,
+        using_defaults,
+    }
+}
+
+#[tauri::command]
+pub fn get_validation_rules(db: State<'_, DbState>) -> Result<ValidationRulesView, String> {
+    track("get_validation_rules", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        Ok(view(&conn))
+    })
+}
+
+/// Replace the rules; an entity left out goes back to the built-in ones. The change is
+/// written to the audit log
+#[tauri::command]
+pub fn set_validation_rules(
+    rules: ValidationRules,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<ValidationRulesView, DbError> {
+    track("set_validation_rules", || {
+        let user_id_value = state.require_user(user_id)?;
+        check_rules(&rules)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let now = Utc::now().timestamp_millis();
+
+        let tx = begin_write(&conn, "settings")?;
+        let value = serde_json::to_string(&rules).map_err(|e| e.to_string())?;
+        upsert_setting(&tx, VALIDATION_RULES_SETTING, &value, now).map_err(|e| e.to_string())?;
+        record_audit(
+            &tx,
+            &user_id_value,
+            "validation_rules_changed",
+            &json!(rules),
+            now,
+        )
+        .map_err(|e| e.to_string())?;
+        tx.commit().map_err(|e| e.to_string())?;
+        info!("✅ Validation rules updated");
+        Ok(view(&conn))
+    })
+}
+
+/// Check a client or vehicle form without saving it; empty means it would save
+#[tauri::command]
+pub fn validate_entity(
+    entity: Entity,
+    payload: Value,
+    db: State<'_, DbState>,
+) -> Result<Vec<RuleViolation>, String> {
+    track("validate_entity", || {
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        Ok(violations(entity, &rules_for(&conn, entity), &payload))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{db_create_client, db_create_vehicle, db_update_client};
+    use crate::test_support::{make_client, make_vehicle, TestApp};
+
+    fn rules(value: Value) -> ValidationRules {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn rule_ids(error: DbError) -> Vec<String> {
+        match error {
+            DbError::ValidationFailed { violations } => {
+                violations.into_iter().map(|v| v.rule_id).collect()
+            }
+            other => panic!("expected ValidationFailed, got {:?}", other),
+        }
+    }
+
+    fn client_count(app: &TestApp) -> i64 {
+        app.conn()
+            .query_row("SELECT COUNT(*) FROM clients", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_a_missing_required_field_fails_the_save() {
+        let app = TestApp::new();
+        let required = rules(json!({
+            "client": {
+                "first_name": { "required": true },
+                "drivers_license": {
+                    "required": true,
+                    "message": "A driver's license is needed before a test drive"
+                }
+            }
+        }));
+        set_validation_rules(required, None, app.state(), app.db()).unwrap();
+
+        let err = db_create_client(make_client("c1"), None, None, None, app.state(), app.db())
+            .unwrap_err();
+        let DbError::ValidationFailed { violations } = err else {
+            panic!("expected ValidationFailed");
+        };
+        assert_eq!(
+            violations,
+            [RuleViolation {
+                rule_id: "client.drivers_license.required".to_string(),
+                field: "drivers_license".to_string(),
+                message: "A driver's license is needed before a test drive".to_string(),
+            }]
+        );
+        assert_eq!(client_count(&app), 0);
+
+        let mut client = make_client("c1");
+        client.drivers_license = Some("D1234567".to_string());
+        client.first_name = "  ".to_string();
+        let err =
+            db_create_client(client.clone(), None, None, None, app.state(), app.db()).unwrap_err();
+        assert_eq!(rule_ids(err), ["client.first_name.required"]);
+        client.first_name = "Jordan".to_string();
+        db_create_client(client, None, None, None, app.state(), app.db()).unwrap();
+        assert_eq!(client_count(&app), 1);
+    }
+
+    #[test]
+    fn test_regex_and_length_rules_are_checked_on_update() {
+        let app = TestApp::new();
+        let phone = rules(json!({
+            "client": {
+                "phone": { "regex": "^\\d{3}-\\d{3}-\\d{4}$" },
+                "zip_code": { "regex": "^\\d{5}$", "min": 5, "max": 5 }
+            }
+        }));
+        set_validation_rules(phone, None, app.state(), app.db()).unwrap();
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+
+        let update = |updates: Value| {
+            db_update_client("c1".into(), updates, None, None, app.state(), app.db())
+        };
+        let err = update(json!({ "phone": "555 0100", "zip_code": "787011" })).unwrap_err();
+        assert_eq!(
+            rule_ids(err),
+            [
+                "client.phone.regex",
+                "client.zip_code.regex",
+                "client.zip_code.max"
+            ]
+        );
+        let stored: String = app
+            .conn()
+            .query_row("SELECT phone FROM clients WHERE id = 'c1'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(stored, "(555) 010-0100");
+        // Rules other than required skip a field with no value
+        update(json!({ "phone": "555-867-5309", "zip_code": "" })).unwrap();
+    }
+
+    #[test]
+    fn test_the_dry_run_uses_built_in_rules_until_some_are_saved() {
+        let app = TestApp::new();
+        let dry_run = |entity, payload| validate_entity(entity, payload, app.db()).unwrap();
+        let mut vehicle = serde_json::to_value(make_vehicle("v1")).unwrap();
+        assert_eq!(dry_run(Entity::Vehicle, vehicle.clone()), []);
+
+        vehicle["vin"] = json!("");
+        vehicle["year"] = json!(1850);
+        let ids: Vec<String> = dry_run(Entity::Vehicle, vehicle.clone())
+            .into_iter()
+            .map(|violation| violation.rule_id)
+            .collect();
+        assert_eq!(ids, ["vehicle.vin.required", "vehicle.year.min"]);
+        let view = get_validation_rules(app.db()).unwrap();
+        assert_eq!(view.using_defaults, [Entity::Client, Entity::Vehicle]);
+
+        // Saved vehicle rules replace the built-in ones; clients keep theirs
+        let saved = rules(json!({ "vehicle": { "color": { "required": true } } }));
+        let view = set_validation_rules(saved, None, app.state(), app.db()).unwrap();
+        assert_eq!(view.using_defaults, [Entity::Client]);
+        let violations = dry_run(Entity::Vehicle, json!({ "vin": "", "color": null }));
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].message, "Color is required");
+        assert_eq!(
+            dry_run(Entity::Client, json!({}))[0].rule_id,
+            "client.first_name.required"
+        );
+
+        let mut no_color = make_vehicle("v1");
+        no_color.color = None;
+        let err = db_create_vehicle(no_color, app.state(), app.db()).unwrap_err();
+        assert_eq!(rule_ids(err), ["vehicle.color.required"]);
+    }
+
+    #[test]
+    fn test_rules_for_unknown_fields_are_refused() {
+        let app = TestApp::new();
+        let save = |value: Value| set_validation_rules(rules(value), None, app.state(), app.db());
+        let err = save(json!({ "client": { "shoe_size": { "required": true } } })).unwrap_err();
+        assert!(
+            err.to_string().contains("Unknown client field 'shoe_size'"),
+            "{}",
+            err
+        );
+        assert!(save(json!({ "vehicle": { "vin": { "regex": "([A-Z" } } })).is_err());
+        assert!(save(json!({ "vehicle": { "year": { "min": 2030, "max": 2000 } } })).is_err());
+        // A misspelled rule key never gets as far as saving
+        assert!(serde_json::from_value::<ValidationRules>(
+            json!({ "client": { "phone": { "requird": true } } })
+        )
+        .is_err());
+        assert_eq!(
+            get_validation_rules(app.db()).unwrap().using_defaults,
+            [Entity::Client, Entity::Vehicle]
+        );
+    }
+}
@@ -0,0 +1,703 @@
+// src-tauri/src/valuation.rs
+//
+// Market-value estimates for appraising a trade. get_vehicle_valuation asks the dealer's
+// valuation provider (URL and API key set with set_valuation_api and kept in the keyring)
+// for the low/average/high value of a VIN at a mileage and ZIP, and keeps the answer in the
+// valuations table. A cached answer younger than valuation_cache_ttl_hours is returned
+// without calling out. When the provider can't be reached, errors or the call would go over
+// the rate limit, the last cached answer is returned with stale set instead; only with
+// nothing cached does the lookup fail.
+//
+// API calls are limited to valuation_max_calls_per_minute across the whole app, here rather
+// than in the UI, so a re-render loop can't burn through the dealer's API credits.
+//
+// Provider contract: GET {url}?vin=&mileage=&zip= with the key as a bearer token, answering
+// {"low": 15000.0, "average": 17250.0, "high": 19000.0, "source": "...", "as_of": "2026-10-01"}
+// in dollars; source and as_of (RFC 3339 or a date) are optional.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::app_state::{AppState, AuthError};
+use crate::database::{db_get_setting, Database, DbState};
+use crate::money::to_cents;
+use crate::secret_store::{self, SecretKey};
+use crate::telemetry::{track, track_async};
+
+/// Settings keys
+pub const VALUATION_CACHE_TTL_SETTING: &str = "valuation_cache_ttl_hours";
+pub const VALUATION_RATE_LIMIT_SETTING: &str = "valuation_max_calls_per_minute";
+
+const DEFAULT_CACHE_TTL_HOURS: i64 = 24;
+const DEFAULT_MAX_CALLS_PER_MINUTE: u32 = 10;
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+/// Source reported when the provider doesn't name one
+const DEFAULT_SOURCE: &str = "valuation_api";
+
+/// API calls in the last minute, for every lookup in the app
+static RATE_LIMITER: Mutex<RateLimiter> = Mutex::new(RateLimiter::new());
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ValuationError {
+    /// No provider set up and nothing cached
+    NotConfigured,
+    InvalidInput {
+        message: String,
+    },
+    RateLimited {
+        retry_after_secs: u64,
+    },
+    Network {
+        message: String,
+    },
+    Server {
+        status: u16,
+        message: String,
+    },
+    Other {
+        message: String,
+    },
+}
+
+impl std::fmt::Display for ValuationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValuationError::NotConfigured => write!(f, "No valuation provider is set up"),
+            ValuationError::InvalidInput { message } => write!(f, "{}", message),
+            ValuationError::RateLimited { retry_after_secs } => write!(
+                f,
+                "Too many valuation lookups, try again in {} seconds",
+                retry_after_secs
+            ),
+            ValuationError::Network { message } => {
+                write!(f, "Could not reach the valuation provider: {}", message)
+            }
+            ValuationError::Server { status, message } => {
+                write!(f, "Valuation provider error ({}): {}", status, message)
+            }
+            ValuationError::Other { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<String> for ValuationError {
+    fn from(message: String) -> Self {
+        ValuationError::Other { message }
+    }
+}
+
+impl From<AuthError> for ValuationError {
+    fn from(error: AuthError) -> Self {
+        ValuationError::Other {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for ValuationError {
+    fn from(error: rusqlite::Error) -> Self {
+        ValuationError::Other {
+            message: error.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VehicleValuation {
+    pub vin: String,
+    pub mileage: i64,
+    pub zip: String,
+    pub low_cents: i64,
+    pub average_cents: i64,
+    pub high_cents: i64,
+    pub source: String,
+    /// When the provider priced it (ms)
+    pub as_of: i64,
+    /// When it was fetched (ms)
+    pub fetched_at: i64,
+    /// A cached answer returned because a fresh one couldn't be had
+    pub stale: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValuationApiStatus {
+    pub configured: bool,
+    pub url: Option<String>,
+}
+
+/// What's being valued, normalized: the cache key
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ValuationQuery {
+    vin: String,
+    mileage: i64,
+    zip: String,
+}
+
+impl ValuationQuery {
+    fn new(vin: &str, mileage: i64, zip: &str) -> Result<Self, ValuationError> {
+        let invalid = |message: String| ValuationError::InvalidInput { message };
+        let vin = vin.trim().to_ascii_uppercase();
+        if vin.len() != 17
+            || vin
+                .chars()
+                .any(|c| !c.is_ascii_alphanumeric() || matches!(c, 'I' | 'O' | 'Q'))
+        {
+            return Err(invalid(format!("{} is not a valid VIN", vin)));
+        }
+        if !(0..10_000_000).contains(&mileage) {
+            return Err(invalid(format!("Invalid mileage: {}", mileage)));
+        }
+        // ZIP+4 is priced like its ZIP
+        let zip: String = zip.trim().chars().take(5).collect();
+        if zip.len() != 5 || !zip.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid("A 5-digit ZIP code is required".to_string()));
+        }
+        Ok(ValuationQuery { vin, mileage, zip })
+    }
+}
+
+/// Sliding one-minute window of API calls
+#[derive(Debug)]
+struct RateLimiter {
+    calls: VecDeque<Instant>,
+}
+
+impl RateLimiter {
+    const fn new() -> Self {
+        RateLimiter {
+            calls: VecDeque::new(),
+        }
+    }
+
+    /// Count a call at `now` if fewer than max_per_minute were made in the last minute;
+    /// otherwise Err(how long until one may be)
+    fn try_acquire(&mut self, max_per_minute: u32, now: Instant) -> Result<(), Duration> {
+        while let Some(oldest) = self.calls.front() {
+            if now.saturating_duration_since(*oldest) >= RATE_WINDOW {
+                self.calls.pop_front();
+            } else {
+                break;
+            }
+        }
+        if self.calls.len() < max_per_minute as usize {
+            self.calls.push_back(now);
+            return Ok(());
+        }
+        let oldest = self.calls.front().copied().unwrap_or(now);
+        Err(RATE_WINDOW.saturating_sub(now.saturating_duration_since(oldest)))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ValuationApi {
+    url: String,
+    api_key: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ValuationConfig {
+    /// None until set_valuation_api has been called
+    api: Option<ValuationApi>,
+    ttl_ms: i64,
+    max_calls_per_minute: u32,
+}
+
+fn config() -> Result<ValuationConfig, String> {
+    let url = secret_store::get(SecretKey::ValuationApiUrl)?;
+    let api_key = secret_store::get(SecretKey::ValuationApiKey)?;
+    let setting = |key: &str| db_get_setting(key.to_string()).ok().flatten();
+    let ttl_hours = setting(VALUATION_CACHE_TTL_SETTING)
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .filter(|hours| *hours >= 0)
+        .unwrap_or(DEFAULT_CACHE_TTL_HOURS);
+    let max_calls_per_minute = setting(VALUATION_RATE_LIMIT_SETTING)
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .unwrap_or(DEFAULT_MAX_CALLS_PER_MINUTE);
+
+    Ok(ValuationConfig {
+        api: url
+            .zip(api_key)
+            .map(|(url, api_key)| ValuationApi { url, api_key }),
+        ttl_ms: ttl_hours * 60 * 60 * 1000,
+        max_calls_per_minute,
+    })
+}
+
+/// The provider's answer, in dollars
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    low: f64,
+    average: f64,
+    high: f64,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    as_of: Option<String>,
+}
+
+/// RFC 3339 or a plain date (midnight UTC), in ms
+fn parse_as_of(value: &str) -> Option<i64> {
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .map(|at| at.timestamp_millis())
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|at| at.and_utc().timestamp_millis())
+        })
+}
+
+async fn fetch(
+    api: &ValuationApi,
+    query: &ValuationQuery,
+    now_ms: i64,
+) -> Result<VehicleValuation, ValuationError> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let network = |e: reqwest::Error| ValuationError::Network {
+        message: e.to_string(),
+    };
+    let response = client
+        .get(&api.url)
+        .bearer_auth(&api.api_key)
+        .query(&[
+            ("vin", query.vin.clone()),
+            ("mileage", query.mileage.to_string()),
+            ("zip", query.zip.clone()),
+        ])
+        .send()
+        .await
+        .map_err(network)?;
+    let status = response.status();
+    let text = response.text().await.map_err(network)?;
+    let unexpected = |message: String| ValuationError::Server {
+        status: status.as_u16(),
+        message,
+    };
+    if !status.is_success() {
+        return Err(unexpected(text.chars().take(200).collect()));
+    }
+
+    let body: ApiResponse = serde_json::from_str(&text)
+        .map_err(|e| unexpected(format!("Unexpected response: {}", e)))?;
+    let (low, average, high) = (
+        to_cents(body.low),
+        to_cents(body.average),
+        to_cents(body.high),
+    );
+    if low < 0 || low > average || average > high {
+        return Err(unexpected(format!(
+            "Unexpected values: low {}, average {}, high {}",
+            body.low, body.average, body.high
+        )));
+    }
+    let as_of = match body.as_of.as_deref() {
+        Some(value) => parse_as_of(value).unwrap_or_else(|| {
+            warn!("⚠️  Ignoring unreadable valuation date {}", value);
+            now_ms
+        }),
+        None => now_ms,
+    };
+    Ok(VehicleValuation {
+        vin: query.vin.clone(),
+        mileage: query.mileage,
+        zip: query.zip.clone(),
+        low_cents: low,
+        average_cents: average,
+        high_cents: high,
+        source: body
+            .source
+            .map(|source| source.trim().to_string())
+            .filter(|source| !source.is_empty())
+            .unwrap_or_else(|| DEFAULT_SOURCE.to_string()),
+        as_of,
+        fetched_at: now_ms,
+        stale: false,
+    })
+}
+
+fn cached(conn: &Connection, query: &ValuationQuery) -> SqlResult<Option<VehicleValuation>> {
+    conn.query_row(
+        "SELECT low_cents, average_cents, high_cents, source, as_of, fetched_at
+         FROM valuations WHERE vin = ?1 AND mileage = ?2 AND zip = ?3",
+        params![query.vin, query.mileage, query.zip],
+        |row| {
+            Ok(VehicleValuation {
+                vin: query.vin.clone(),
+                mileage: query.mileage,
+                zip: query.zip.clone(),
+                low_cents: row.get(0)?,
+                average_cents: row.get(1)?,
+                high_cents: row.get(2)?,
+                source: row.get(3)?,
+                as_of: row.get(4)?,
+                fetched_at: row.get(5)?,
+                stale: false,
+            })
+        },
+    )
+    .optional()
+}
+
+fn store_cached(conn: &Connection, valuation: &VehicleValuation) -> SqlResult<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO valuations
+             (vin, mileage, zip, low_cents, average_cents, high_cents, source, as_of, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            valuation.vin,
+            valuation.mileage,
+            valuation.zip,
+            valuation.low_cents,
+            valuation.average_cents,
+            valuation.high_cents,
+            valuation.source,
+            valuation.as_of,
+            valuation.fetched_at
+        ],
+    )?;
+    Ok(())
+}
+
+/// The cached answer while it's fresh, else a new one from the provider, else the cached
+/// one marked stale. The connection is only held between awaits
+async fn lookup(
+    db: &Database,
+    config: &ValuationConfig,
+    limiter: &Mutex<RateLimiter>,
+    query: &ValuationQuery,
+    now_ms: i64,
+    now: Instant,
+) -> Result<VehicleValuation, ValuationError> {
+    let cached = cached(&db.conn(), query)?;
+    if let Some(cached) = cached
+        .clone()
+        .filter(|c| now_ms - c.fetched_at < config.ttl_ms)
+    {
+        return Ok(cached);
+    }
+    let fall_back = |error: ValuationError| match cached.clone() {
+        Some(cached) => {
+            warn!("⚠️  Using a cached valuation for {}: {}", query.vin, error);
+            Ok(VehicleValuation {
+                stale: true,
+                ..cached
+            })
+        }
+        None => Err(error),
+    };
+
+    let Some(api) = &config.api else {
+        return fall_back(ValuationError::NotConfigured);
+    };
+    let acquired = limiter
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .try_acquire(config.max_calls_per_minute, now);
+    if let Err(wait) = acquired {
+        return fall_back(ValuationError::RateLimited {
+            retry_after_secs: wait.as_secs_f64().ceil() as u64,
+        });
+    }
+
+    match fetch(api, query, now_ms).await {
+        Ok(valuation) => {
+            store_cached(&db.conn(), &valuation)?;
+            info!("✅ Valuation fetched for {}", query.vin);
+            Ok(valuation)
+        }
+        Err(error) => fall_back(error),
+    }
+}
+
+/// Low/average/high market value of the vehicle at this mileage around this ZIP
+#[tauri::command]
+pub async fn get_vehicle_valuation(
+    vin: String,
+    mileage: i64,
+    zip: String,
+    db: State<'_, DbState>,
+) -> Result<VehicleValuation, ValuationError> {
+    track_async("get_vehicle_valuation", async move {
+        let query = ValuationQuery::new(&vin, mileage, &zip)?;
+        let config = config()?;
+        let db = db.get()?;
+        lookup(
+            db,
+            &config,
+            &RATE_LIMITER,
+            &query,
+            Utc::now().timestamp_millis(),
+            Instant::now(),
+        )
+        .await
+    })
+    .await
+}
+
+#[tauri::command]
+pub fn get_valuation_api_status() -> Result<ValuationApiStatus, String> {
+    let url = secret_store::get(SecretKey::ValuationApiUrl)?;
+    let has_key = secret_store::get(SecretKey::ValuationApiKey)?.is_some();
+    Ok(ValuationApiStatus {
+        configured: url.is_some() && has_key,
+        url,
+    })
+}
+
+/// Point lookups at a provider. api_key None keeps the saved key
+#[tauri::command]
+pub fn set_valuation_api(
+    url: String,
+    api_key: Option<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ValuationApiStatus, String> {
+    track("set_valuation_api", || {
+        state.require_user(user_id)?;
+        let url = url.trim();
+        if !(url.starts_with("https://") || url.starts_with("http://")) {
+            return Err("The valuation URL must start with https://".to_string());
+        }
+        if let Some(api_key) = api_key {
+            if api_key.trim().is_empty() {
+                return Err("The API key can't be empty".to_string());
+            }
+            secret_store::store(SecretKey::ValuationApiKey, api_key.trim())?;
+        }
+        secret_store::store(SecretKey::ValuationApiUrl, url)?;
+        info!("✅ Valuation provider set");
+        get_valuation_api_status()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TestApp;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+
+    const VIN: &str = "1HGCM82633A004352";
+    const HOUR_MS: i64 = 60 * 60 * 1000;
+    const NOW_MS: i64 = 1_790_000_000_000;
+
+    /// One-shot HTTP server: answers the first request with the given status/body and
+    /// hands back the request line and headers
+    fn mock_server(status: &'static str, body: &'static str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1/value", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 4096];
+            while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+                let n = stream.read(&mut buffer).unwrap();
+                request.extend_from_slice(&buffer[..n]);
+            }
+            let _ = tx.send(String::from_utf8_lossy(&request).to_string());
+
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+
+        (url, rx)
+    }
+
+    /// A URL nothing listens on: the provider is offline
+    fn offline_url() -> String {
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1/value", closed.local_addr().unwrap());
+        drop(closed);
+        url
+    }
+
+    fn config(url: &str) -> ValuationConfig {
+        ValuationConfig {
+            api: Some(ValuationApi {
+                url: url.to_string(),
+                api_key: "test-key".to_string(),
+            }),
+            ttl_ms: 24 * HOUR_MS,
+            max_calls_per_minute: 10,
+        }
+    }
+
+    fn run(
+        app: &TestApp,
+        config: &ValuationConfig,
+        limiter: &Mutex<RateLimiter>,
+        now_ms: i64,
+    ) -> Result<VehicleValuation, ValuationError> {
+        let query = ValuationQuery::new(VIN, 42_000, "62701-1234").unwrap();
+        let db = app.db();
+        let db = db.get().unwrap();
+        tauri::async_runtime::block_on(lookup(db, config, limiter, &query, now_ms, Instant::now()))
+    }
+
+    #[test]
+    fn test_answers_are_cached_until_the_ttl_passes() {
+        let app = TestApp::new();
+        let limiter = Mutex::new(RateLimiter::new());
+        let (url, request) = mock_server(
+            "200 OK",
+            r#"{"low":15000,"average":17250.5,"high":19000,"source":"Acme Book","as_of":"2026-10-01"}"#,
+        );
+        let fresh = run(&app, &config(&url), &limiter, NOW_MS).unwrap();
+        assert_eq!(
+            (fresh.low_cents, fresh.average_cents, fresh.high_cents),
+            (1_500_000, 1_725_050, 1_900_000)
+        );
+        assert_eq!(fresh.source, "Acme Book");
+        assert_eq!(fresh.as_of, parse_as_of("2026-10-01T00:00:00Z").unwrap());
+        assert_eq!(fresh.zip, "62701");
+        assert!(!fresh.stale);
+        let sent = request.recv().unwrap();
+        assert!(sent.starts_with(&format!(
+            "GET /v1/value?vin={}&mileage=42000&zip=62701 ",
+            VIN
+        )));
+        assert!(sent
+            .to_lowercase()
+            .contains("authorization: bearer test-key"));
+
+        // Within the TTL the provider isn't asked (it's offline, and that doesn't matter)
+        let offline = config(&offline_url());
+        let cached = run(&app, &offline, &limiter, NOW_MS + 23 * HOUR_MS).unwrap();
+        assert_eq!(cached, fresh);
+
+        // Past it, a new answer replaces the cached one
+        let (url, _) = mock_server("200 OK", r#"{"low":14000,"average":16000,"high":18000}"#);
+        let later = NOW_MS + 25 * HOUR_MS;
+        let refreshed = run(&app, &config(&url), &limiter, later).unwrap();
+        assert_eq!(refreshed.average_cents, 1_600_000);
+        assert_eq!(refreshed.source, DEFAULT_SOURCE);
+        assert_eq!(refreshed.as_of, later);
+        assert_eq!(
+            cached_row(&app).map(|row| row.fetched_at),
+            Some(later),
+            "cache updated"
+        );
+    }
+
+    fn cached_row(app: &TestApp) -> Option<VehicleValuation> {
+        let query = ValuationQuery::new(VIN, 42_000, "62701").unwrap();
+        super::cached(&app.conn(), &query).unwrap()
+    }
+
+    #[test]
+    fn test_an_unreachable_provider_falls_back_to_the_stale_value() {
+        let app = TestApp::new();
+        let limiter = Mutex::new(RateLimiter::new());
+        let offline = config(&offline_url());
+        assert!(matches!(
+            run(&app, &offline, &limiter, NOW_MS),
+            Err(ValuationError::Network { .. })
+        ));
+
+        let (url, _) = mock_server("200 OK", r#"{"low":15000,"average":17000,"high":19000}"#);
+        let fresh = run(&app, &config(&url), &limiter, NOW_MS).unwrap();
+
+        let later = NOW_MS + 48 * HOUR_MS;
+        let stale = run(&app, &offline, &limiter, later).unwrap();
+        assert!(stale.stale);
+        assert_eq!(stale.average_cents, fresh.average_cents);
+        assert_eq!(stale.fetched_at, NOW_MS);
+
+        // Provider errors and a missing provider fall back the same way
+        let (url, _) = mock_server("503 Service Unavailable", "down for maintenance");
+        assert!(run(&app, &config(&url), &limiter, later).unwrap().stale);
+        let unconfigured = ValuationConfig {
+            api: None,
+            ..offline
+        };
+        assert!(run(&app, &unconfigured, &limiter, later).unwrap().stale);
+
+        // A nonsense answer isn't cached
+        let (url, _) = mock_server("200 OK", r#"{"low":20000,"average":17000,"high":19000}"#);
+        let answer = run(&app, &config(&url), &limiter, later).unwrap();
+        assert!(answer.stale);
+        assert_eq!(cached_row(&app).unwrap().fetched_at, NOW_MS);
+    }
+
+    #[test]
+    fn test_the_rate_limiter_caps_calls_per_minute() {
+        let start = Instant::now();
+        let mut limiter = RateLimiter::new();
+        for i in 0..3 {
+            assert_eq!(
+                limiter.try_acquire(3, start + Duration::from_secs(i * 10)),
+                Ok(())
+            );
+        }
+        assert_eq!(
+            limiter.try_acquire(3, start + Duration::from_secs(30)),
+            Err(Duration::from_secs(30))
+        );
+        // The first call leaves the window a minute after it was made
+        assert_eq!(
+            limiter.try_acquire(3, start + Duration::from_secs(60)),
+            Ok(())
+        );
+        assert!(limiter
+            .try_acquire(3, start + Duration::from_secs(61))
+            .is_err());
+        assert!(limiter
+            .try_acquire(0, start + Duration::from_secs(600))
+            .is_err());
+
+        // A lookup over the limit doesn't reach the provider
+        let app = TestApp::new();
+        let (url, request) = mock_server("200 OK", r#"{"low":1,"average":2,"high":3}"#);
+        let mut limited = config(&url);
+        limited.max_calls_per_minute = 1;
+        let limiter = Mutex::new(RateLimiter::new());
+        limiter
+            .lock()
+            .unwrap()
+            .try_acquire(1, Instant::now())
+            .unwrap();
+        assert!(matches!(
+            run(&app, &limited, &limiter, NOW_MS),
+            Err(ValuationError::RateLimited { retry_after_secs }) if retry_after_secs > 0
+        ));
+        assert!(request.try_recv().is_err());
+        assert_eq!(cached_row(&app), None);
+    }
+
+    #[test]
+    fn test_lookups_need_a_valid_vin_and_zip() {
+        assert!(ValuationQuery::new("1HGCM82633A00435", 1, "62701").is_err());
+        assert!(ValuationQuery::new("1HGCM82633A00435O", 1, "62701").is_err());
+        assert!(ValuationQuery::new(VIN, -1, "62701").is_err());
+        assert!(ValuationQuery::new(VIN, 1, "6270").is_err());
+        assert_eq!(
+            ValuationQuery::new(" 1hgcm82633a004352 ", 5, "62701"),
+            Ok(ValuationQuery {
+                vin: VIN.to_string(),
+                mileage: 5,
+                zip: "62701".to_string(),
+            })
+        );
+    }
+}
@@ -0,0 +1,753 @@
+// src-tauri/src/vcard.rs
+//
+// vCard (.vcf) import/export for clients, for dealers who keep customers in Outlook or
+// Google Contacts. Reads 2.1 (Outlook), 3.0 and 4.0: folded lines, grouped properties
+// (item1.EMAIL), quoted parameters and QUOTED-PRINTABLE values. Writes 3.0, which every
+// contacts app accepts.
+
+use chrono::Utc;
+use log::info;
+use serde::Serialize;
+use std::fs;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::client_duplicates::{DuplicateDetector, DuplicateMatch};
+use crate::contact_normalization::parse_phone;
+use crate::database::{bulk_create_clients, get_clients_for_user, get_db, new_row_id, Client};
+use crate::telemetry::track;
+
+/// Contacts exports run to thousands of cards; anything this big isn't one
+const MAX_VCF_BYTES: u64 = 20 * 1024 * 1024;
+/// RFC 6350 3.2: lines SHOULD NOT be longer than 75 octets
+const MAX_LINE_OCTETS: usize = 75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CardStatus {
+    Imported,
+    Duplicate,
+    Invalid,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CardReport {
+    pub index: usize, // 1-based position in the file
+    pub name: Option<String>,
+    pub status: CardStatus,
+    pub client_id: Option<String>,
+    pub duplicate_of: Option<DuplicateMatch>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VcardImportReport {
+    pub imported: usize,
+    pub duplicates: usize,
+    pub invalid: usize,
+    pub cards: Vec<CardReport>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VcardExportSummary {
+    pub path: String,
+    pub count: usize,
+}
+
+/// Create a client for every card that isn't a duplicate of an existing client
+/// (or of an earlier card in the same file)
+#[tauri::command]
+pub fn import_clients_vcf(
+    path: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<VcardImportReport, String> {
+    track("import_clients_vcf", || {
+        let user_id_value = state.require_user(user_id)?;
+        info!("📇 Importing vCards: {}", path);
+
+        let size = fs::metadata(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))?
+            .len();
+        if size > MAX_VCF_BYTES {
+            return Err(format!(
+                "File is too large for a contacts export ({} bytes)",
+                size
+            ));
+        }
+        let bytes = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        let text = String::from_utf8_lossy(&bytes);
+
+        let db = get_db().map_err(|e| e.to_string())?;
+        let detector = DuplicateDetector::for_user(&db.conn(), &user_id_value)?;
+        let (clients, report) = plan_import(&text, detector, Utc::now().timestamp_millis());
+        bulk_create_clients(&user_id_value, clients)?;
+
+        info!(
+            "✅ vCard import: {} imported, {} duplicates, {} invalid",
+            report.imported, report.duplicates, report.invalid
+        );
+        Ok(report)
+    })
+}
+
+/// Write all of the user's clients to a .vcf file
+#[tauri::command]
+pub fn export_clients_vcf(
+    user_id: Option<String>,
+    path: String,
+    state: State<'_, AppState>,
+) -> Result<VcardExportSummary, String> {
+    track("export_clients_vcf", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let mut clients = get_clients_for_user(db, &user_id_value)?;
+        clients.sort_by(|a, b| {
+            (&a.last_name, &a.first_name, a.created_at).cmp(&(
+                &b.last_name,
+                &b.first_name,
+                b.created_at,
+            ))
+        });
+
+        let content: String = clients.iter().map(render_vcard).collect();
+        fs::write(&path, content).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+        info!("✅ Exported {} clients to {}", clients.len(), path);
+        Ok(VcardExportSummary {
+            path,
+            count: clients.len(),
+        })
+    })
+}
+
+/// Clients to create plus the per-card report
+fn plan_import(
+    text: &str,
+    mut detector: DuplicateDetector,
+    now: i64,
+) -> (Vec<Client>, VcardImportReport) {
+    let mut clients = Vec::new();
+    let mut report = VcardImportReport {
+        imported: 0,
+        duplicates: 0,
+        invalid: 0,
+        cards: Vec::new(),
+    };
+
+    for (i, card) in parse_vcards(text).into_iter().enumerate() {
+        let mut card_report = CardReport {
+            index: i + 1,
+            name: None,
+            status: CardStatus::Invalid,
+            client_id: None,
+            duplicate_of: None,
+            error: None,
+        };
+
+        match card.and_then(|card| card_to_client(&card, now)) {
+            Err(e) => {
+                card_report.error = Some(e);
+                report.invalid += 1;
+            }
+            Ok(client) => {
+                card_report.name = Some(
+                    format!("{} {}", client.first_name, client.last_name)
+                        .trim()
+                        .to_string(),
+                );
+                if let Some(duplicate) = detector.find(&client) {
+                    card_report.status = CardStatus::Duplicate;
+                    card_report.duplicate_of = Some(duplicate);
+                    report.duplicates += 1;
+                } else {
+                    detector.add(&client);
+                    card_report.status = CardStatus::Imported;
+                    card_report.client_id = Some(client.id.clone());
+                    report.imported += 1;
+                    clients.push(client);
+                }
+            }
+        }
+        report.cards.push(card_report);
+    }
+
+    (clients, report)
+}
+
+// Parsing
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Property {
+    name: String,                  // Upper case, group prefix removed
+    params: Vec<(String, String)>, // Upper-case names; 2.1 bare params become TYPE
+    value: String,                 // Decoded from QUOTED-PRINTABLE, still vCard-escaped
+}
+
+impl Property {
+    fn param_values<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.params
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .flat_map(|(_, value)| value.split(','))
+            .map(str::trim)
+    }
+
+    fn has_type(&self, kind: &str) -> bool {
+        self.param_values("TYPE")
+            .any(|t| t.eq_ignore_ascii_case(kind))
+    }
+
+    /// 3.0 TYPE=PREF or 4.0 PREF=n
+    fn is_preferred(&self) -> bool {
+        self.has_type("pref") || self.params.iter().any(|(key, _)| key == "PREF")
+    }
+
+    fn text(&self) -> String {
+        unescape(&self.value)
+    }
+
+    /// Components of a structured value (N, ADR), split on unescaped ';'
+    fn components(&self) -> Vec<String> {
+        let mut parts = vec![String::new()];
+        let mut chars = self.value.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    parts.last_mut().unwrap().push(c);
+                    if let Some(next) = chars.next() {
+                        parts.last_mut().unwrap().push(next);
+                    }
+                }
+                ';' => parts.push(String::new()),
+                _ => parts.last_mut().unwrap().push(c),
+            }
+        }
+        parts.iter().map(|part| unescape(part)).collect()
+    }
+}
+
+type Card = Vec<Property>;
+
+/// Every BEGIN:VCARD..END:VCARD block in the text; malformed cards are errors, not failures
+fn parse_vcards(text: &str) -> Vec<Result<Card, String>> {
+    let mut cards = Vec::new();
+    let mut current: Option<Card> = None;
+
+    for line in unfold(text) {
+        let property = match parse_line(&line) {
+            Some(property) => property,
+            None => continue,
+        };
+        match (property.name.as_str(), current.as_mut()) {
+            ("BEGIN", None) if property.value.eq_ignore_ascii_case("VCARD") => {
+                current = Some(Vec::new())
+            }
+            ("END", Some(_)) if property.value.eq_ignore_ascii_case("VCARD") => {
+                cards.push(Ok(current.take().unwrap_or_default()))
+            }
+            ("BEGIN", Some(_)) if property.value.eq_ignore_ascii_case("VCARD") => {
+                cards.push(Err("Card is missing END:VCARD".to_string()));
+                current = Some(Vec::new());
+            }
+            (_, Some(card)) => card.push(property),
+            (_, None) => {}
+        }
+    }
+    if current.is_some() {
+        cards.push(Err("Card is missing END:VCARD".to_string()));
+    }
+    cards
+}
+
+/// Join folded lines (a leading space or tab continues the previous line) and
+/// QUOTED-PRINTABLE soft line breaks (a trailing '=')
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for raw in text.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match lines.last_mut() {
+            Some(last) if is_quoted_printable_line(last) && last.ends_with('=') => {
+                last.pop();
+                last.push_str(raw);
+            }
+            Some(last) if raw.starts_with([' ', '\t']) => last.push_str(&raw[1..]),
+            _ if raw.trim().is_empty() => {}
+            _ => lines.push(raw.to_string()),
+        }
+    }
+    lines
+}
+
+fn is_quoted_printable_line(line: &str) -> bool {
+    let head = split_unquoted(line, ':').0;
+    head.to_ascii_uppercase().contains("QUOTED-PRINTABLE")
+}
+
+/// Split at the first `separator` outside double quotes
+fn split_unquoted(text: &str, separator: char) -> (&str, Option<&str>) {
+    let mut quoted = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c == separator && !quoted => return (&text[..i], Some(&text[i + 1..])),
+            _ => {}
+        }
+    }
+    (text, None)
+}
+
+fn parse_line(line: &str) -> Option<Property> {
+    let (head, value) = split_unquoted(line, ':');
+    let value = value?;
+
+    let mut parts = Vec::new();
+    let mut rest = head;
+    loop {
+        let (part, next) = split_unquoted(rest, ';');
+        parts.push(part);
+        match next {
+            Some(next) => rest = next,
+            None => break,
+        }
+    }
+
+    let name = parts[0].rsplit('.').next()?.trim().to_ascii_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+    let params: Vec<(String, String)> = parts[1..]
+        .iter()
+        .filter(|param| !param.is_empty())
+        .map(|param| match param.split_once('=') {
+            Some((key, value)) => (
+                key.trim().to_ascii_uppercase(),
+                value.trim().trim_matches('"').to_string(),
+            ),
+            None if param.eq_ignore_ascii_case("QUOTED-PRINTABLE") => {
+                ("ENCODING".to_string(), param.to_string())
+            }
+            None => ("TYPE".to_string(), param.to_string()),
+        })
+        .collect();
+
+    let quoted_printable = params
+        .iter()
+        .any(|(key, value)| key == "ENCODING" && value.eq_ignore_ascii_case("QUOTED-PRINTABLE"));
+    let value = if quoted_printable {
+        let charset = params
+            .iter()
+            .find(|(key, _)| key == "CHARSET")
+            .map(|(_, value)| value.as_str())
+            .unwrap_or("UTF-8");
+        decode_quoted_printable(value, charset)
+    } else {
+        value.to_string()
+    };
+
+    Some(Property {
+        name,
+        params,
+        value,
+    })
+}
+
+fn decode_quoted_printable(value: &str, charset: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let raw = value.as_bytes();
+    let mut i = 0;
+    while i < raw.len() {
+        let hex = raw
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok());
+        match (raw[i], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'=', Some(byte)) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                bytes.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    match charset.to_ascii_lowercase().as_str() {
+        "windows-1252" | "cp1252" | "iso-8859-1" | "latin1" => {
+            bytes.into_iter().map(windows_1252_char).collect()
+        }
+        _ => String::from_utf8_lossy(&bytes).to_string(),
+    }
+}
+
+/// Windows-1252 is Latin-1 plus printable characters in 0x80..0x9F (Outlook's default)
+pub(crate) fn windows_1252_char(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8d}', 'Ž',
+        '\u{8f}', '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9d}',
+        'ž', 'Ÿ',
+    ];
+    match byte {
+        0x80..=0x9f => HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// First property named `name` marked preferred, else the first one
+fn preferred<'a>(card: &'a Card, name: &str) -> Option<&'a Property> {
+    let mut matching = card.iter().filter(|p| p.name == name);
+    let first = matching.clone().next();
+    matching.find(|p| p.is_preferred()).or(first)
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn card_to_client(card: &Card, now: i64) -> Result<Client, String> {
+    let n = card
+        .iter()
+        .find(|p| p.name == "N")
+        .map(Property::components)
+        .unwrap_or_default();
+    let (mut first_name, mut last_name) = (
+        n.get(1).map(|s| s.trim().to_string()).unwrap_or_default(),
+        n.first().map(|s| s.trim().to_string()).unwrap_or_default(),
+    );
+
+    if first_name.is_empty() && last_name.is_empty() {
+        let full_name = card
+            .iter()
+            .find(|p| p.name == "FN")
+            .map(Property::text)
+            .unwrap_or_default();
+        let full_name = full_name.trim();
+        match full_name.rsplit_once(char::is_whitespace) {
+            Some((first, last)) => {
+                first_name = first.trim().to_string();
+                last_name = last.to_string();
+            }
+            None => first_name = full_name.to_string(),
+        }
+    }
+    if first_name.is_empty() && last_name.is_empty() {
+        return Err("Card has no name".to_string());
+    }
+
+    // First mobile number, else the first number of any kind
+    let phone = card
+        .iter()
+        .filter(|p| p.name == "TEL")
+        .find(|p| p.has_type("cell"))
+        .or_else(|| card.iter().find(|p| p.name == "TEL"))
+        .and_then(|p| non_empty(p.text().trim().trim_start_matches("tel:")))
+        // Kept as entered when unusable, and flagged by bulk_create_clients
+        .map(|raw| parse_phone(&raw).display);
+
+    let email = preferred(card, "EMAIL")
+        .map(Property::text)
+        .filter(|email| email.contains('@'))
+        .and_then(|email| non_empty(&email));
+
+    // pobox; extended; street; locality; region; postal code; country
+    let adr = preferred(card, "ADR")
+        .map(Property::components)
+        .unwrap_or_default();
+    let part = |i: usize| adr.get(i).and_then(|value| non_empty(value));
+    let street = part(2).map(|street| {
+        let street = street.lines().map(str::trim).collect::<Vec<_>>().join(", ");
+        match part(1) {
+            Some(extended) => format!("{}, {}", street, extended),
+            None => street,
+        }
+    });
+    let address = street.or_else(|| part(0).map(|pobox| format!("PO Box {}", pobox)));
+
+    Ok(Client {
+        id: new_row_id(),
+        user_id: None,
+        first_name,
+        last_name,
+        email,
+        phone,
+        address,
+        city: part(3),
+        state: part(4),
+        zip_code: part(5),
+        drivers_license: None,
+        created_at: now,
+        updated_at: now,
+        synced_at: None,
+    })
+}
+
+// Writing
+
+fn render_vcard(client: &Client) -> String {
+    let full_name = format!("{} {}", client.first_name, client.last_name);
+    let mut lines = vec![
+        "BEGIN:VCARD".to_string(),
+        "VERSION:3.0".to_string(),
+        format!(
+            "N:{};{};;;",
+            escape(&client.last_name),
+            escape(&client.first_name)
+        ),
+        format!("FN:{}", escape(full_name.trim())),
+    ];
+    if let Some(phone) = client.phone.as_deref().and_then(non_empty) {
+        lines.push(format!("TEL;TYPE=CELL:{}", escape(&phone)));
+    }
+    if let Some(email) = client.email.as_deref().and_then(non_empty) {
+        lines.push(format!("EMAIL;TYPE=INTERNET:{}", escape(&email)));
+    }
+    let address = [
+        &client.address,
+        &client.city,
+        &client.state,
+        &client.zip_code,
+    ];
+    if address
+        .iter()
+        .any(|part| part.as_deref().and_then(non_empty).is_some())
+    {
+        let [street, city, state, zip] =
+            address.map(|part| escape(part.as_deref().unwrap_or("").trim()));
+        lines.push(format!(
+            "ADR;TYPE=HOME:;;{};{};{};{};",
+            street, city, state, zip
+        ));
+    }
+    lines.push(format!("UID:{}", escape(&client.id)));
+    lines.push(format!(
+        "REV:{}",
+        chrono::DateTime::from_timestamp_millis(client.updated_at)
+            .unwrap_or_default()
+            .format("%Y%m%dT%H%M%SZ")
+    ));
+    lines.push("END:VCARD".to_string());
+
+    lines.iter().map(|line| fold(line) + "\r\n").collect()
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace(';', "\\;")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold at 75 octets without splitting a UTF-8 character
+fn fold(line: &str) -> String {
+    let mut out = String::with_capacity(line.len() + line.len() / MAX_LINE_OCTETS * 3);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTLOOK: &str = include_str!("../testdata/vcard/outlook.vcf");
+    const GOOGLE: &str = include_str!("../testdata/vcard/google.vcf");
+    const IOS: &str = include_str!("../testdata/vcard/ios.vcf");
+
+    fn clients(text: &str) -> Vec<Result<Client, String>> {
+        parse_vcards(text)
+            .into_iter()
+            .map(|card| card.and_then(|card| card_to_client(&card, 1)))
+            .collect()
+    }
+
+    fn summary(client: &Client) -> [Option<&str>; 8] {
+        [
+            Some(client.first_name.as_str()),
+            Some(client.last_name.as_str()),
+            client.email.as_deref(),
+            client.phone.as_deref(),
+            client.address.as_deref(),
+            client.city.as_deref(),
+            client.state.as_deref(),
+            client.zip_code.as_deref(),
+        ]
+    }
+
+    #[test]
+    fn test_outlook_21_quoted_printable() {
+        let parsed = clients(OUTLOOK);
+        assert_eq!(parsed.len(), 2);
+
+        // Cell number wins over the work number listed first; QP address with a soft break
+        assert_eq!(
+            summary(parsed[0].as_ref().unwrap()),
+            [
+                Some("Jürgen"),
+                Some("Müller"),
+                Some("jmueller@example.com"),
+                Some("(248) 555-0199"),
+                Some("1200 Woodward Ave, Apt 4"),
+                Some("Detroit"),
+                Some("MI"),
+                Some("48226"),
+            ]
+        );
+
+        // Windows-1252 quoted-printable name, no mobile so the first number is used
+        let siobhan = parsed[1].as_ref().unwrap();
+        assert_eq!(siobhan.first_name, "Siobhán");
+        assert_eq!(siobhan.last_name, "O’Brien");
+        assert_eq!(siobhan.phone.as_deref(), Some("(313) 555-0142"));
+        assert_eq!(siobhan.address, None);
+    }
+
+    #[test]
+    fn test_google_30_export() {
+        let parsed = clients(GOOGLE);
+        assert_eq!(parsed.len(), 3);
+
+        assert_eq!(
+            summary(parsed[0].as_ref().unwrap()),
+            [
+                Some("María José"),
+                Some("García-López"),
+                Some("mjgarcia@example.com"),
+                Some("(734) 555-0188"),
+                Some("455 Maple St, Unit B"),
+                Some("Ann Arbor"),
+                Some("MI"),
+                Some("48104"),
+            ]
+        );
+        // Folded ZIP and the extended address line; the folded PHOTO doesn't leak into anything
+        assert_eq!(
+            summary(parsed[1].as_ref().unwrap()),
+            [
+                Some("Dwayne"),
+                Some("Washington"),
+                Some("dwayne.w@example.org"),
+                Some("(586) 555-0175"),
+                Some("24800 Northwestern Hwy, Suite 210"),
+                Some("Southfield"),
+                Some("MI"),
+                Some("48075-2233"),
+            ]
+        );
+        assert_eq!(parsed[2].as_ref().unwrap_err(), "Card has no name");
+    }
+
+    #[test]
+    fn test_ios_40_uri_phones_and_preferences() {
+        let parsed = clients(IOS);
+        assert_eq!(parsed.len(), 4);
+
+        let priya = parsed[0].as_ref().unwrap();
+        assert_eq!(
+            summary(priya),
+            [
+                Some("Priya"),
+                Some("Natarajan"),
+                Some("priya.n@example.com"),
+                Some("(248) 555-0162"),
+                Some("742 Evergreen Terrace"),
+                Some("Troy"),
+                Some("MI"),
+                Some("48083"),
+            ]
+        );
+
+        // FN only, single word
+        let cher = parsed[2].as_ref().unwrap();
+        assert_eq!(
+            (cher.first_name.as_str(), cher.last_name.as_str()),
+            ("Cher", "")
+        );
+        assert_eq!(cher.phone.as_deref(), Some("(248) 555-0177"));
+    }
+
+    #[test]
+    fn test_import_report_flags_duplicates_and_invalid_cards() {
+        let mut ada = card_to_client(
+            &parse_vcards("BEGIN:VCARD\nN:Lovelace;Ada\nEND:VCARD")
+                .remove(0)
+                .unwrap(),
+            1,
+        )
+        .unwrap();
+        ada.id = "existing-ada".to_string();
+        ada.email = Some("ada@example.com".to_string());
+
+        let text = format!("{}{}{}", OUTLOOK, GOOGLE, IOS);
+        let (created, report) = plan_import(&text, DuplicateDetector::new(&[ada]), 1);
+
+        assert_eq!(report.cards.len(), 9);
+        assert_eq!(
+            (report.imported, report.duplicates, report.invalid),
+            (6, 2, 1)
+        );
+        assert_eq!(created.len(), 6);
+
+        // Second Priya card repeats the first one's mobile; Ada matches an existing client
+        let priya_again = &report.cards[6];
+        assert_eq!(priya_again.status, CardStatus::Duplicate);
+        assert_eq!(
+            priya_again.duplicate_of.as_ref().unwrap().client_id,
+            report.cards[5].client_id.clone().unwrap()
+        );
+        let ada_again = &report.cards[8];
+        assert_eq!(ada_again.status, CardStatus::Duplicate);
+        assert_eq!(
+            ada_again.duplicate_of.as_ref().unwrap().client_id,
+            "existing-ada"
+        );
+        assert_eq!(report.cards[4].status, CardStatus::Invalid);
+        assert_eq!(report.cards[4].index, 5);
+    }
+
+    #[test]
+    fn test_export_roundtrip_escapes_and_folds() {
+        let mut client = card_to_client(&parse_vcards(GOOGLE).remove(0).unwrap(), 1).unwrap();
+        client.address = Some(
+            "455 Maple St, Unit B; rear entrance \\ blue door, next to the long driveway".into(),
+        );
+        client.city = Some("Ann Arbor".into());
+
+        let rendered = render_vcard(&client);
+        assert!(rendered.starts_with("BEGIN:VCARD\r\nVERSION:3.0\r\n"));
+        assert!(rendered.ends_with("END:VCARD\r\n"));
+        assert!(rendered
+            .split("\r\n")
+            .all(|line| line.len() <= MAX_LINE_OCTETS));
+        assert!(rendered.contains("ADR;TYPE=HOME:;;455 Maple St\\, Unit B\\; rear"));
+
+        let back = card_to_client(&parse_vcards(&rendered).remove(0).unwrap(), 1).unwrap();
+        assert_eq!(summary(&back), summary(&client));
+    }
+}
@@ -0,0 +1,858 @@
+// src-tauri/src/vehicle_costs.rs
+//
+// Cost history on a vehicle: purchase price, reconditioning, transport... Amounts are
+// integer cents. vehicles.cost is kept equal to the sum of the vehicle's rows (migration 23
+// turned each existing cost into a purchase row), updated in the same write transaction
+// as every cost change, so the inventory list and exports can keep reading the column.
+// Code that still writes vehicles.cost directly goes through set_total_cost, which moves
+// the purchase row to make the rows add up. Gross profit (db_get_deals_stats) is a sold
+// deal's sale amount minus the summed costs of its vehicle.
+
+use chrono::{TimeZone, Utc};
+use log::info;
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{begin_write, get_db, new_row_id, DEAL_STATUS_SOLD};
+use crate::datetime::format_local;
+use crate::money::to_cents;
+use crate::recent_items::{owned_by, EntityType};
+use crate::telemetry::track;
+
+const PURCHASE_DESCRIPTION: &str = "Purchase price";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CostCategory {
+    Purchase,
+    Recon,
+    Transport,
+    #[default]
+    Other,
+}
+
+impl CostCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CostCategory::Purchase => "purchase",
+            CostCategory::Recon => "recon",
+            CostCategory::Transport => "transport",
+            CostCategory::Other => "other",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "purchase" => Some(CostCategory::Purchase),
+            "recon" => Some(CostCategory::Recon),
+            "transport" => Some(CostCategory::Transport),
+            "other" => Some(CostCategory::Other),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VehicleCost {
+    pub id: String,
+    pub vehicle_id: String,
+    pub user_id: String,
+    pub category: CostCategory,
+    pub amount_cents: i64,
+    pub description: Option<String>,
+    pub incurred_at: i64,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl VehicleCost {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        let category: String = row.get("category")?;
+        Ok(VehicleCost {
+            id: row.get("id")?,
+            vehicle_id: row.get("vehicle_id")?,
+            user_id: row.get("user_id")?,
+            category: CostCategory::parse(&category).unwrap_or_default(),
+            amount_cents: row.get("amount_cents")?,
+            description: row.get("description")?,
+            incurred_at: row.get("incurred_at")?,
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewVehicleCost {
+    #[serde(default)]
+    pub category: CostCategory,
+    pub amount_cents: i64,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Defaults to now
+    #[serde(default)]
+    pub incurred_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CategoryTotal {
+    pub category: CostCategory,
+    pub amount_cents: i64,
+    pub count: usize,
+}
+
+/// Everything the vehicle detail screen shows about what the unit cost
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CostBreakdown {
+    pub vehicle_id: String,
+    pub total_cents: i64,
+    pub by_category: Vec<CategoryTotal>,
+    /// Oldest first
+    pub costs: Vec<VehicleCost>,
+    pub price_cents: i64,
+    /// Asking price minus total cost
+    pub expected_gross_cents: i64,
+    /// Sale amount minus total cost, once the vehicle is on a sold deal
+    pub gross_profit_cents: Option<i64>,
+}
+
+/// Sold deals in one calendar month (UTC) of their sale date
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MonthlyProfit {
+    pub month: String, // YYYY-MM
+    pub sold: i64,
+    pub sale_cents: i64,
+    pub cost_cents: i64,
+    pub gross_profit_cents: i64,
+}
+
+fn validate_cost(amount_cents: i64) -> Result<(), String> {
+    if amount_cents < 0 {
+        return Err("Cost amount can't be negative".to_string());
+    }
+    Ok(())
+}
+
+fn get_cost(conn: &Connection, user_id: &str, id: &str) -> Result<VehicleCost, String> {
+    conn.query_row(
+        "SELECT * FROM vehicle_costs WHERE id = ?1 AND user_id = ?2",
+        params![id, user_id],
+        VehicleCost::from_row,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| "Cost not found or access denied".to_string())
+}
+
+/// A vehicle's cost rows, oldest first (ownership checked by the caller)
+pub(crate) fn list_costs(conn: &Connection, vehicle_id: &str) -> SqlResult<Vec<VehicleCost>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM vehicle_costs WHERE vehicle_id = ?1 ORDER BY incurred_at, created_at, id",
+    )?;
+    let rows = stmt.query_map(params![vehicle_id], VehicleCost::from_row)?;
+    rows.collect()
+}
+
+/// Sum of the vehicle's cost rows, leaving out the `except` category
+fn sum_costs(conn: &Connection, vehicle_id: &str, except: Option<CostCategory>) -> SqlResult<i64> {
+    conn.query_row(
+        "SELECT COALESCE(SUM(amount_cents), 0) FROM vehicle_costs
+         WHERE vehicle_id = ?1 AND (?2 IS NULL OR category != ?2)",
+        params![vehicle_id, except.map(CostCategory::as_str)],
+        |row| row.get(0),
+    )
+}
+
+/// Set vehicles.cost to the sum of the vehicle's cost rows (NULL when there are none)
+fn sync_vehicle_cost(conn: &Connection, vehicle_id: &str) -> SqlResult<()> {
+    conn.execute(
+        "UPDATE vehicles SET cost = (
+             SELECT SUM(amount_cents) / 100.0 FROM vehicle_costs WHERE vehicle_id = ?1
+         ) WHERE id = ?1",
+        params![vehicle_id],
+    )?;
+    Ok(())
+}
+
+/// Make the vehicle's cost rows add up to `cost` by resizing its purchase row, for callers
+/// that still set the cost column directly (db_create_vehicle / db_update_vehicle / imports).
+/// Run inside the caller's transaction. A total below the non-purchase costs is refused.
+pub(crate) fn set_total_cost(
+    conn: &Connection,
+    vehicle_id: &str,
+    user_id: &str,
+    cost: Option<f64>,
+    now: i64,
+) -> Result<(), String> {
+    let total_cents = cost.map_or(0, to_cents);
+    let sql_err = |e: rusqlite::Error| e.to_string();
+    if sum_costs(conn, vehicle_id, None).map_err(sql_err)? == total_cents {
+        return Ok(());
+    }
+
+    let other_cents = sum_costs(conn, vehicle_id, Some(CostCategory::Purchase)).map_err(sql_err)?;
+    let purchase_cents = total_cents - other_cents;
+    if purchase_cents < 0 {
+        return Err(format!(
+            "Cost can't be less than the {:.2} already recorded for reconditioning, transport and other costs",
+            other_cents as f64 / 100.0
+        ));
+    }
+
+    conn.execute(
+        "DELETE FROM vehicle_costs WHERE vehicle_id = ?1 AND category = ?2",
+        params![vehicle_id, CostCategory::Purchase.as_str()],
+    )
+    .map_err(sql_err)?;
+    insert_purchase_cost(conn, vehicle_id, user_id, purchase_cents, now).map_err(sql_err)?;
+    sync_vehicle_cost(conn, vehicle_id).map_err(sql_err)
+}
+
+/// Give a vehicle that has a cost but no cost rows its purchase row
+/// (vehicles that arrive without their rows, e.g. from a data import)
+pub(crate) fn backfill_purchase_cost(
+    conn: &Connection,
+    vehicle_id: &str,
+    user_id: &str,
+) -> SqlResult<()> {
+    let has_rows: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM vehicle_costs WHERE vehicle_id = ?1)",
+        params![vehicle_id],
+        |row| row.get(0),
+    )?;
+    if has_rows {
+        return Ok(());
+    }
+    let (cost, created_at): (Option<f64>, i64) = conn.query_row(
+        "SELECT cost, created_at FROM vehicles WHERE id = ?1",
+        params![vehicle_id],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    insert_purchase_cost(
+        conn,
+        vehicle_id,
+        user_id,
+        cost.map_or(0, to_cents),
+        created_at,
+    )?;
+    sync_vehicle_cost(conn, vehicle_id)
+}
+
+fn insert_purchase_cost(
+    conn: &Connection,
+    vehicle_id: &str,
+    user_id: &str,
+    amount_cents: i64,
+    incurred_at: i64,
+) -> SqlResult<()> {
+    if amount_cents == 0 {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO vehicle_costs (id, vehicle_id, user_id, category, amount_cents,
+                                    description, incurred_at, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7, ?7)",
+        params![
+            new_row_id(),
+            vehicle_id,
+            user_id,
+            CostCategory::Purchase.as_str(),
+            amount_cents,
+            PURCHASE_DESCRIPTION,
+            incurred_at
+        ],
+    )?;
+    Ok(())
+}
+
+fn add_cost(
+    conn: &Connection,
+    user_id: &str,
+    vehicle_id: &str,
+    cost: NewVehicleCost,
+    now: i64,
+) -> Result<VehicleCost, String> {
+    validate_cost(cost.amount_cents)?;
+    owned_by(conn, user_id, EntityType::Vehicle, vehicle_id)?;
+
+    let tx = begin_write(conn, "vehicle_costs").map_err(|e| e.to_string())?;
+    let id = new_row_id();
+    tx.execute(
+        "INSERT INTO vehicle_costs (id, vehicle_id, user_id, category, amount_cents,
+                                    description, incurred_at, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)",
+        params![
+            id,
+            vehicle_id,
+            user_id,
+            cost.category.as_str(),
+            cost.amount_cents,
+            cost.description
+                .as_deref()
+                .map(str::trim)
+                .filter(|d| !d.is_empty()),
+            cost.incurred_at.unwrap_or(now),
+            now
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    sync_vehicle_cost(&tx, vehicle_id).map_err(|e| e.to_string())?;
+    let cost = get_cost(&tx, user_id, &id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(cost)
+}
+
+fn update_cost(
+    conn: &Connection,
+    user_id: &str,
+    id: &str,
+    updates: &Value,
+    now: i64,
+) -> Result<VehicleCost, String> {
+    let mut cost = get_cost(conn, user_id, id)?;
+
+    if let Some(category) = updates.get("category").and_then(|v| v.as_str()) {
+        cost.category = CostCategory::parse(category)
+            .ok_or_else(|| format!("Unknown cost category '{}'", category))?;
+    }
+    if let Some(amount_cents) = updates.get("amount_cents").and_then(|v| v.as_i64()) {
+        cost.amount_cents = amount_cents;
+    }
+    if let Some(description) = updates.get("description") {
+        cost.description = description
+            .as_str()
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+            .map(str::to_string);
+    }
+    if let Some(incurred_at) = updates.get("incurred_at").and_then(|v| v.as_i64()) {
+        cost.incurred_at = incurred_at;
+    }
+    validate_cost(cost.amount_cents)?;
+    cost.updated_at = now;
+
+    let tx = begin_write(conn, "vehicle_costs").map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE vehicle_costs SET category = ?3, amount_cents = ?4, description = ?5,
+                                  incurred_at = ?6, updated_at = ?7
+         WHERE id = ?1 AND user_id = ?2",
+        params![
+            id,
+            user_id,
+            cost.category.as_str(),
+            cost.amount_cents,
+            cost.description,
+            cost.incurred_at,
+            cost.updated_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    sync_vehicle_cost(&tx, &cost.vehicle_id).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(cost)
+}
+
+fn delete_cost(conn: &Connection, user_id: &str, id: &str) -> Result<VehicleCost, String> {
+    let cost = get_cost(conn, user_id, id)?;
+    let tx = begin_write(conn, "vehicle_costs").map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM vehicle_costs WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    sync_vehicle_cost(&tx, &cost.vehicle_id).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(cost)
+}
+
+fn cost_breakdown(
+    conn: &Connection,
+    user_id: &str,
+    vehicle_id: &str,
+) -> Result<CostBreakdown, String> {
+    let price_cents: i64 = conn
+        .query_row(
+            "SELECT price_cents FROM vehicles WHERE id = ?1 AND user_id = ?2",
+            params![vehicle_id, user_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Vehicle not found or access denied".to_string())?;
+    let costs = list_costs(conn, vehicle_id).map_err(|e| e.to_string())?;
+    let total_cents: i64 = costs.iter().map(|cost| cost.amount_cents).sum();
+
+    let mut by_category: Vec<CategoryTotal> = Vec::new();
+    for cost in &costs {
+        match by_category.iter_mut().find(|t| t.category == cost.category) {
+            Some(total) => {
+                total.amount_cents += cost.amount_cents;
+                total.count += 1;
+            }
+            None => by_category.push(CategoryTotal {
+                category: cost.category,
+                amount_cents: cost.amount_cents,
+                count: 1,
+            }),
+        }
+    }
+    by_category.sort_by_key(|total| total.category);
+
+    let sale_cents: Option<i64> = conn
+        .query_row(
+            "SELECT COALESCE(sale_amount_cents, total_amount_cents) FROM deals
+             WHERE vehicle_id = ?1 AND user_id = ?2 AND status = ?3
+             ORDER BY COALESCE(sale_date, created_at) DESC LIMIT 1",
+            params![vehicle_id, user_id, DEAL_STATUS_SOLD],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+    Ok(CostBreakdown {
+        vehicle_id: vehicle_id.to_string(),
+        total_cents,
+        by_category,
+        costs,
+        price_cents,
+        expected_gross_cents: price_cents - total_cents,
+        gross_profit_cents: sale_cents.map(|sale| sale - total_cents),
+    })
+}
+
+/// When a sold deal (`d`) counts as sold: its sale date, or its creation without one
+pub(crate) const SOLD_AT_SQL: &str = "COALESCE(d.sale_date, d.created_at)";
+/// A sold deal's sale amount, the deal total when it has none
+pub(crate) const SALE_CENTS_SQL: &str = "COALESCE(d.sale_amount_cents, d.total_amount_cents)";
+/// The summed costs of a deal's vehicle
+pub(crate) const VEHICLE_COST_CENTS_SQL: &str =
+    "(SELECT SUM(c.amount_cents) FROM vehicle_costs c WHERE c.vehicle_id = d.vehicle_id)";
+
+/// Sale amount minus the vehicle's summed costs, for each month (in tz) with sold deals,
+/// oldest first
+pub(crate) fn gross_profit_by_month<Tz: TimeZone>(
+    conn: &Connection,
+    user_id: &str,
+    tz: &Tz,
+) -> SqlResult<Vec<MonthlyProfit>>
+where
+    Tz::Offset: Display,
+{
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {sold_at}, COALESCE({sale}, 0), COALESCE({cost}, 0)
+         FROM deals d
+         WHERE d.user_id = ?1 AND d.status = ?2",
+        sold_at = SOLD_AT_SQL,
+        sale = SALE_CENTS_SQL,
+        cost = VEHICLE_COST_CENTS_SQL,
+    ))?;
+    let rows = stmt.query_map(params![user_id, DEAL_STATUS_SOLD], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, i64>(1)?,
+            row.get::<_, i64>(2)?,
+        ))
+    })?;
+    let mut months: BTreeMap<String, MonthlyProfit> = BTreeMap::new();
+    for row in rows {
+        let (sold_at, sale_cents, cost_cents) = row?;
+        let month = format_local(sold_at, tz, "%Y-%m");
+        let totals = months.entry(month.clone()).or_insert(MonthlyProfit {
+            month,
+            sold: 0,
+            sale_cents: 0,
+            cost_cents: 0,
+            gross_profit_cents: 0,
+        });
+        totals.sold += 1;
+        totals.sale_cents += sale_cents;
+        totals.cost_cents += cost_cents;
+        totals.gross_profit_cents += sale_cents - cost_cents;
+    }
+    Ok(months.into_values().collect())
+}
+
+fn notify_changed(user_id: &str, cost: &VehicleCost, operation: Operation) {
+    data_changed(user_id, ChangedEntity::VehicleCost, &cost.id, operation);
+    // vehicles.cost changed with it
+    data_changed(
+        user_id,
+        ChangedEntity::Vehicle,
+        &cost.vehicle_id,
+        Operation::Update,
+    );
+}
+
+#[tauri::command]
+pub fn db_add_vehicle_cost(
+    vehicle_id: String,
+    cost: NewVehicleCost,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<VehicleCost, String> {
+    track("db_add_vehicle_cost", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let cost = add_cost(
+            &conn,
+            &user_id_value,
+            &vehicle_id,
+            cost,
+            Utc::now().timestamp_millis(),
+        )?;
+        info!(
+            "✅ {} cost added to vehicle {}: {} cents",
+            cost.category.as_str(),
+            vehicle_id,
+            cost.amount_cents
+        );
+        notify_changed(&user_id_value, &cost, Operation::Create);
+        Ok(cost)
+    })
+}
+
+#[tauri::command]
+pub fn db_get_vehicle_costs(
+    vehicle_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<VehicleCost>, String> {
+    track("db_get_vehicle_costs", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        owned_by(&conn, &user_id_value, EntityType::Vehicle, &vehicle_id)?;
+        list_costs(&conn, &vehicle_id).map_err(|e| e.to_string())
+    })
+}
+
+/// Change category, amount_cents, description or incurred_at
+#[tauri::command]
+pub fn db_update_vehicle_cost(
+    id: String,
+    updates: Value,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<VehicleCost, String> {
+    track("db_update_vehicle_cost", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let cost = update_cost(
+            &conn,
+            &user_id_value,
+            &id,
+            &updates,
+            Utc::now().timestamp_millis(),
+        )?;
+        notify_changed(&user_id_value, &cost, Operation::Update);
+        Ok(cost)
+    })
+}
+
+#[tauri::command]
+pub fn db_delete_vehicle_cost(
+    id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    track("db_delete_vehicle_cost", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        let cost = delete_cost(&conn, &user_id_value, &id)?;
+        notify_changed(&user_id_value, &cost, Operation::Delete);
+        Ok(())
+    })
+}
+
+/// Cost rows, totals by category, and expected/realized gross for the vehicle detail screen
+#[tauri::command]
+pub fn db_get_vehicle_cost_breakdown(
+    vehicle_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CostBreakdown, String> {
+    track("db_get_vehicle_cost_breakdown", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        cost_breakdown(&conn, &user_id_value, &vehicle_id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use chrono_tz::{America::Los_Angeles, Tz};
+    use std::fs;
+    use std::path::Path;
+    use std::thread;
+    use std::time::Duration;
+
+    const MIGRATIONS: [&str; 9] = [
+        include_str!("../migrations/001_initial_schema.sql"),
+        include_str!("../migrations/002_add_sync_fields.sql"),
+        include_str!("../migrations/003_add_document_paths.sql"),
+        include_str!("../migrations/005_add_user_id.sql"),
+        include_str!("../migrations/013_add_odometer_title.sql"),
+        include_str!("../migrations/019_add_lenders.sql"),
+        include_str!("../migrations/020_add_deal_fees.sql"),
+        include_str!("../migrations/023_add_vehicle_costs.sql"),
+        include_str!("../migrations/026_money_cents.sql"),
+    ];
+
+    fn setup(conn: &Connection) {
+        conn.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+        // Migration 23 (then 26) runs against a vehicle that already has a cost
+        for sql in &MIGRATIONS[..7] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn.execute_batch(
+            "INSERT INTO clients (id, first_name, last_name, created_at, updated_at, user_id)
+                 VALUES ('c1', 'Jane', 'Doe', 0, 0, 'u1');
+             INSERT INTO vehicles (id, vin, year, make, model, mileage, price, cost, status,
+                                   created_at, updated_at, user_id)
+                 VALUES ('v1', 'VIN1', 2019, 'Honda', 'Civic', 0, 15000, 9500, 'available', 0, 0, 'u1'),
+                        ('v2', 'VIN2', 2020, 'Ford', 'F-150', 0, 30000, NULL, 'available', 0, 0, 'u1');",
+        )
+        .unwrap();
+        for sql in &MIGRATIONS[7..] {
+            conn.execute_batch(sql).unwrap();
+        }
+    }
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        setup(&conn);
+        conn
+    }
+
+    fn column_cents(conn: &Connection, vehicle_id: &str) -> Option<i64> {
+        conn.query_row(
+            "SELECT cost FROM vehicles WHERE id = ?1",
+            params![vehicle_id],
+            |row| row.get::<_, Option<f64>>(0),
+        )
+        .unwrap()
+        .map(to_cents)
+    }
+
+    fn new_cost(category: CostCategory, amount_cents: i64) -> NewVehicleCost {
+        NewVehicleCost {
+            category,
+            amount_cents,
+            description: None,
+            incurred_at: None,
+        }
+    }
+
+    #[test]
+    fn test_cost_rows_keep_the_vehicle_total() {
+        let conn = test_db();
+
+        // The migration turned the existing cost into a purchase row
+        let costs = list_costs(&conn, "v1").unwrap();
+        assert_eq!(costs.len(), 1);
+        assert_eq!(costs[0].category, CostCategory::Purchase);
+        assert_eq!(costs[0].amount_cents, 950_000);
+
+        let recon = add_cost(
+            &conn,
+            "u1",
+            "v1",
+            new_cost(CostCategory::Recon, 180_000),
+            10,
+        )
+        .unwrap();
+        add_cost(
+            &conn,
+            "u1",
+            "v1",
+            new_cost(CostCategory::Transport, 35_000),
+            11,
+        )
+        .unwrap();
+        assert_eq!(column_cents(&conn, "v1"), Some(1_165_000));
+
+        update_cost(
+            &conn,
+            "u1",
+            &recon.id,
+            &serde_json::json!({ "amount_cents": 200_000 }),
+            12,
+        )
+        .unwrap();
+        assert_eq!(column_cents(&conn, "v1"), Some(1_185_000));
+
+        // Writing the column directly resizes the purchase row; an unchanged total is a no-op
+        set_total_cost(&conn, "v1", "u1", Some(12_000.0), 13).unwrap();
+        set_total_cost(&conn, "v1", "u1", Some(12_000.0), 14).unwrap();
+        let breakdown = cost_breakdown(&conn, "u1", "v1").unwrap();
+        assert_eq!(breakdown.total_cents, 1_200_000);
+        assert_eq!(
+            breakdown.by_category,
+            vec![
+                CategoryTotal {
+                    category: CostCategory::Purchase,
+                    amount_cents: 965_000,
+                    count: 1
+                },
+                CategoryTotal {
+                    category: CostCategory::Recon,
+                    amount_cents: 200_000,
+                    count: 1
+                },
+                CategoryTotal {
+                    category: CostCategory::Transport,
+                    amount_cents: 35_000,
+                    count: 1
+                },
+            ]
+        );
+        assert_eq!(breakdown.expected_gross_cents, 300_000);
+        assert_eq!(breakdown.gross_profit_cents, None);
+        let err = set_total_cost(&conn, "v1", "u1", Some(1_000.0), 15).unwrap_err();
+        assert!(err.contains("2350.00"), "{}", err);
+
+        delete_cost(&conn, "u1", &recon.id).unwrap();
+        assert_eq!(column_cents(&conn, "v1"), Some(1_000_000));
+
+        // A vehicle without rows has no cost
+        assert_eq!(column_cents(&conn, "v2"), None);
+        assert!(add_cost(&conn, "u2", "v1", new_cost(CostCategory::Other, 1), 16).is_err());
+        assert!(add_cost(&conn, "u1", "v1", new_cost(CostCategory::Other, -1), 16).is_err());
+    }
+
+    #[test]
+    fn test_gross_profit_uses_summed_costs() {
+        let conn = test_db();
+        add_cost(&conn, "u1", "v1", new_cost(CostCategory::Recon, 180_000), 0).unwrap();
+        // 2026-03-10 and 2026-04-02, UTC
+        conn.execute_batch(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount, sale_amount,
+                                sale_date, created_at, updated_at, user_id)
+                 VALUES ('d1', 'retail', 'c1', 'v1', 'sold', 14900, 14500, 1773100800000, 0, 0, 'u1'),
+                        ('d2', 'retail', 'c1', 'v2', 'sold', 31000, NULL, 1775088000000, 0, 0, 'u1'),
+                        ('d3', 'retail', 'c1', 'v2', 'pending', 31000, 30000, 1775088000000, 0, 0, 'u1');",
+        )
+        .unwrap();
+
+        let months = gross_profit_by_month(&conn, "u1", &Tz::UTC).unwrap();
+        assert_eq!(
+            months,
+            vec![
+                MonthlyProfit {
+                    month: "2026-03".to_string(),
+                    sold: 1,
+                    sale_cents: 1_450_000,
+                    cost_cents: 1_130_000,
+                    gross_profit_cents: 320_000,
+                },
+                // No costs recorded and no sale amount: the deal total, all profit
+                MonthlyProfit {
+                    month: "2026-04".to_string(),
+                    sold: 1,
+                    sale_cents: 3_100_000,
+                    cost_cents: 0,
+                    gross_profit_cents: 3_100_000,
+                },
+            ]
+        );
+        assert_eq!(
+            cost_breakdown(&conn, "u1", "v1")
+                .unwrap()
+                .gross_profit_cents,
+            Some(320_000)
+        );
+        assert!(gross_profit_by_month(&conn, "u2", &Tz::UTC)
+            .unwrap()
+            .is_empty());
+
+        // 7pm on April 30 in Los Angeles is already May in UTC
+        conn.execute_batch(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount, sale_amount,
+                                sale_date, created_at, updated_at, user_id)
+                 VALUES ('d4', 'retail', 'c1', 'v2', 'sold', 1000, NULL, 1777600800000, 0, 0, 'u1');",
+        )
+        .unwrap();
+        let month_of = |tz: &Tz| -> Vec<(String, i64)> {
+            gross_profit_by_month(&conn, "u1", tz)
+                .unwrap()
+                .into_iter()
+                .map(|month| (month.month, month.sold))
+                .collect()
+        };
+        assert_eq!(
+            month_of(&Los_Angeles),
+            [("2026-03".to_string(), 1), ("2026-04".to_string(), 2)]
+        );
+        assert_eq!(month_of(&Tz::UTC)[2], ("2026-05".to_string(), 1));
+    }
+
+    fn open(path: &Path) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.busy_timeout(Duration::from_millis(5)).unwrap();
+        conn.execute_batch("PRAGMA foreign_keys = ON").unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_total_stays_consistent_under_concurrent_adds() {
+        let dir = temp_dir("vehicle-costs", "concurrent");
+        let path = dir.join("costs.db");
+        {
+            let conn = open(&path);
+            let _mode: String = conn
+                .query_row("PRAGMA journal_mode = WAL", [], |row| row.get(0))
+                .unwrap();
+            setup(&conn);
+        }
+
+        // Separate connections, so the adds really contend for the write lock
+        let writers: Vec<_> = (0..4)
+            .map(|writer| {
+                let path = path.clone();
+                thread::spawn(move || {
+                    let conn = open(&path);
+                    for n in 0..25 {
+                        let amount_cents = 100 * (writer * 25 + n + 1);
+                        add_cost(
+                            &conn,
+                            "u1",
+                            "v1",
+                            new_cost(CostCategory::Recon, amount_cents),
+                            n,
+                        )
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        let conn = open(&path);
+        let (rows, sum): (i64, i64) = conn
+            .query_row(
+                "SELECT COUNT(*), SUM(amount_cents) FROM vehicle_costs WHERE vehicle_id = 'v1'",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .unwrap();
+        assert_eq!(rows, 101);
+        // Purchase row plus 100 + 200 + ... + 10,000 cents
+        assert_eq!(sum, 950_000 + 100 * (100 * 101 / 2));
+        assert_eq!(column_cents(&conn, "v1"), Some(sum));
+
+        drop(conn);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,618 @@
+// src-tauri/src/vehicle_history.rs
+//
+// One vehicle across all of its deals. A buy-here-pay-here lot can sell the same car several
+// times (sold, repossessed, back in stock, sold again), so vehicle_events records what
+// happens to the vehicle itself: status changes, returns, repossessions and notes.
+// db_update_vehicle writes a status_change event for every status change in the same
+// transaction; sold -> available is a return or repossession and needs a return_reason.
+// db_get_vehicle_history merges the events with the vehicle's deals, their status changes and
+// down payments (there's no payments ledger) into one list, oldest first.
+// A return or repossession puts the vehicle back in stock: IN_STOCK_SINCE_SQL starts its
+// in-stock clock again from the latest one.
+
+use chrono::Utc;
+use log::info;
+use rusqlite::{params, Connection, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{begin_write, new_row_id, DbState, DEAL_STATUS_SOLD};
+use crate::recent_items::{owned_by, EntityType};
+use crate::telemetry::track;
+use crate::vehicle_costs::SOLD_AT_SQL;
+
+pub const VEHICLE_STATUS_SOLD: &str = "sold";
+pub const VEHICLE_STATUS_AVAILABLE: &str = "available";
+
+/// Error when a sold vehicle is made available again without saying why
+pub const RETURN_REASON_REQUIRED: &str =
+    "A sold vehicle coming back needs a return_reason (return or repossession)";
+
+/// When the vehicle (`v`) last came into stock: its latest return or repossession, or when
+/// it was added
+pub(crate) const IN_STOCK_SINCE_SQL: &str = "COALESCE(
+    (SELECT MAX(e.occurred_at) FROM vehicle_events e
+     WHERE e.vehicle_id = v.id AND e.type IN ('returned', 'repossessed')),
+    v.created_at)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VehicleEventType {
+    StatusChange,
+    Returned,
+    Repossessed,
+    Note,
+}
+
+impl VehicleEventType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            VehicleEventType::StatusChange => "status_change",
+            VehicleEventType::Returned => "returned",
+            VehicleEventType::Repossessed => "repossessed",
+            VehicleEventType::Note => "note",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "status_change" => Some(VehicleEventType::StatusChange),
+            "returned" => Some(VehicleEventType::Returned),
+            "repossessed" => Some(VehicleEventType::Repossessed),
+            "note" => Some(VehicleEventType::Note),
+            _ => None,
+        }
+    }
+
+    /// Puts the vehicle back in stock
+    pub fn restocks(self) -> bool {
+        matches!(
+            self,
+            VehicleEventType::Returned | VehicleEventType::Repossessed
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VehicleEvent {
+    pub id: String,
+    pub vehicle_id: String,
+    pub user_id: String,
+    #[serde(rename = "type")]
+    pub event_type: VehicleEventType,
+    pub from_status: Option<String>,
+    pub to_status: Option<String>,
+    pub notes: Option<String>,
+    pub occurred_at: i64,
+    pub created_at: i64,
+}
+
+impl VehicleEvent {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        let event_type: String = row.get("type")?;
+        Ok(VehicleEvent {
+            id: row.get("id")?,
+            vehicle_id: row.get("vehicle_id")?,
+            user_id: row.get("user_id")?,
+            event_type: VehicleEventType::parse(&event_type).unwrap_or(VehicleEventType::Note),
+            from_status: row.get("from_status")?,
+            to_status: row.get("to_status")?,
+            notes: row.get("notes")?,
+            occurred_at: row.get("occurred_at")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewVehicleEvent {
+    #[serde(rename = "type")]
+    pub event_type: VehicleEventType,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Defaults to now
+    #[serde(default)]
+    pub occurred_at: Option<i64>,
+}
+
+/// One line of a vehicle's history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum HistoryEntry {
+    /// A deal on the vehicle was created
+    Deal {
+        occurred_at: i64,
+        deal_id: String,
+        client_id: String,
+        status: String,
+        total_amount_cents: Option<i64>,
+    },
+    DealStatusChange {
+        occurred_at: i64,
+        deal_id: String,
+        from_status: Option<String>,
+        to_status: String,
+    },
+    /// Down payment on a sold deal, taken when it sold
+    Payment {
+        occurred_at: i64,
+        deal_id: String,
+        amount_cents: i64,
+    },
+    VehicleEvent {
+        occurred_at: i64,
+        event: VehicleEvent,
+    },
+}
+
+impl HistoryEntry {
+    pub fn occurred_at(&self) -> i64 {
+        match self {
+            HistoryEntry::Deal { occurred_at, .. }
+            | HistoryEntry::DealStatusChange { occurred_at, .. }
+            | HistoryEntry::Payment { occurred_at, .. }
+            | HistoryEntry::VehicleEvent { occurred_at, .. } => *occurred_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VehicleHistory {
+    pub vehicle_id: String,
+    pub status: String,
+    /// Start of the vehicle's current stay in stock (IN_STOCK_SINCE_SQL)
+    pub in_stock_since: i64,
+    /// Oldest first
+    pub entries: Vec<HistoryEntry>,
+}
+
+fn clean_notes(notes: Option<&str>) -> Option<String> {
+    notes
+        .map(str::trim)
+        .filter(|notes| !notes.is_empty())
+        .map(str::to_string)
+}
+
+fn insert_event(conn: &Connection, event: &VehicleEvent) -> SqlResult<()> {
+    conn.execute(
+        "INSERT INTO vehicle_events (id, vehicle_id, user_id, type, from_status, to_status,
+                                     notes, occurred_at, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            event.id,
+            event.vehicle_id,
+            event.user_id,
+            event.event_type.as_str(),
+            event.from_status,
+            event.to_status,
+            event.notes,
+            event.occurred_at,
+            event.created_at
+        ],
+    )?;
+    Ok(())
+}
+
+/// The event for db_update_vehicle moving a vehicle from `from` to `to`, written in the
+/// caller's transaction; None when the status didn't change. Sold -> available takes
+/// `return_reason` (required) and `repossessed` from the update
+pub(crate) fn record_status_change(
+    conn: &Connection,
+    vehicle_id: &str,
+    user_id: &str,
+    from: &str,
+    to: &str,
+    updates: &Value,
+    now: i64,
+) -> Result<Option<VehicleEvent>, String> {
+    if from == to {
+        return Ok(None);
+    }
+    let (event_type, notes) = if from == VEHICLE_STATUS_SOLD && to == VEHICLE_STATUS_AVAILABLE {
+        let reason = clean_notes(updates.get("return_reason").and_then(Value::as_str))
+            .ok_or_else(|| RETURN_REASON_REQUIRED.to_string())?;
+        let repossessed = updates
+            .get("repossessed")
+            .and_then(Value::as_bool)
+            .unwrap_or(false);
+        let event_type = if repossessed {
+            VehicleEventType::Repossessed
+        } else {
+            VehicleEventType::Returned
+        };
+        (event_type, Some(reason))
+    } else {
+        (VehicleEventType::StatusChange, None)
+    };
+
+    let event = VehicleEvent {
+        id: new_row_id(),
+        vehicle_id: vehicle_id.to_string(),
+        user_id: user_id.to_string(),
+        event_type,
+        from_status: Some(from.to_string()),
+        to_status: Some(to.to_string()),
+        notes,
+        occurred_at: now,
+        created_at: now,
+    };
+    insert_event(conn, &event).map_err(|e| e.to_string())?;
+    Ok(Some(event))
+}
+
+fn record_event(
+    conn: &Connection,
+    user_id: &str,
+    vehicle_id: &str,
+    event: NewVehicleEvent,
+    now: i64,
+) -> Result<VehicleEvent, String> {
+    if event.event_type == VehicleEventType::StatusChange {
+        return Err("Status changes are recorded by updating the vehicle's status".to_string());
+    }
+    let notes = clean_notes(event.notes.as_deref());
+    if event.event_type.restocks() && notes.is_none() {
+        return Err(format!(
+            "A {} event needs notes saying why",
+            event.event_type.as_str()
+        ));
+    }
+    owned_by(conn, user_id, EntityType::Vehicle, vehicle_id)?;
+
+    let event = VehicleEvent {
+        id: new_row_id(),
+        vehicle_id: vehicle_id.to_string(),
+        user_id: user_id.to_string(),
+        event_type: event.event_type,
+        from_status: None,
+        to_status: None,
+        notes,
+        occurred_at: event.occurred_at.unwrap_or(now),
+        created_at: now,
+    };
+    let tx = begin_write(conn, "vehicle_events").map_err(|e| e.to_string())?;
+    insert_event(&tx, &event).map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(event)
+}
+
+fn events_for(conn: &Connection, vehicle_id: &str) -> SqlResult<Vec<VehicleEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT * FROM vehicle_events WHERE vehicle_id = ?1 ORDER BY occurred_at, created_at, id",
+    )?;
+    let rows = stmt.query_map(params![vehicle_id], VehicleEvent::from_row)?;
+    rows.collect()
+}
+
+fn vehicle_history(
+    conn: &Connection,
+    user_id: &str,
+    vehicle_id: &str,
+) -> Result<VehicleHistory, String> {
+    owned_by(conn, user_id, EntityType::Vehicle, vehicle_id)?;
+    let sql_err = |e: rusqlite::Error| e.to_string();
+    let (status, in_stock_since): (String, i64) = conn
+        .query_row(
+            &format!(
+                "SELECT v.status, {} FROM vehicles v WHERE v.id = ?1",
+                IN_STOCK_SINCE_SQL
+            ),
+            params![vehicle_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .map_err(sql_err)?;
+
+    // Pushed in the order same-time entries should be listed; the sort below is stable
+    let mut entries = Vec::new();
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, client_id, status, total_amount_cents, created_at FROM deals
+             WHERE vehicle_id = ?1 AND user_id = ?2",
+        )
+        .map_err(sql_err)?;
+    let deals = stmt
+        .query_map(params![vehicle_id, user_id], |row| {
+            Ok(HistoryEntry::Deal {
+                deal_id: row.get(0)?,
+                client_id: row.get(1)?,
+                status: row.get(2)?,
+                total_amount_cents: row.get(3)?,
+                occurred_at: row.get(4)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<SqlResult<Vec<_>>>())
+        .map_err(sql_err)?;
+    entries.extend(deals);
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT h.deal_id, h.from_status, h.to_status, h.changed_at
+             FROM deal_status_history h JOIN deals d ON d.id = h.deal_id
+             WHERE d.vehicle_id = ?1 AND d.user_id = ?2
+             ORDER BY h.changed_at, h.id",
+        )
+        .map_err(sql_err)?;
+    let changes = stmt
+        .query_map(params![vehicle_id, user_id], |row| {
+            Ok(HistoryEntry::DealStatusChange {
+                deal_id: row.get(0)?,
+                from_status: row.get(1)?,
+                to_status: row.get(2)?,
+                occurred_at: row.get(3)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<SqlResult<Vec<_>>>())
+        .map_err(sql_err)?;
+    entries.extend(changes);
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT d.id, d.down_payment_cents, {sold_at} FROM deals d
+             WHERE d.vehicle_id = ?1 AND d.user_id = ?2 AND d.status IN (?3, 'completed')
+               AND d.down_payment_cents > 0",
+            sold_at = SOLD_AT_SQL,
+        ))
+        .map_err(sql_err)?;
+    let payments = stmt
+        .query_map(params![vehicle_id, user_id, DEAL_STATUS_SOLD], |row| {
+            Ok(HistoryEntry::Payment {
+                deal_id: row.get(0)?,
+                amount_cents: row.get(1)?,
+                occurred_at: row.get(2)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<SqlResult<Vec<_>>>())
+        .map_err(sql_err)?;
+    entries.extend(payments);
+
+    entries.extend(
+        events_for(conn, vehicle_id)
+            .map_err(sql_err)?
+            .into_iter()
+            .map(|event| HistoryEntry::VehicleEvent {
+                occurred_at: event.occurred_at,
+                event,
+            }),
+    );
+    entries.sort_by_key(HistoryEntry::occurred_at);
+
+    Ok(VehicleHistory {
+        vehicle_id: vehicle_id.to_string(),
+        status,
+        in_stock_since,
+        entries,
+    })
+}
+
+/// Record a return, repossession or note on a vehicle (status changes are recorded by
+/// db_update_vehicle). Returns and repossessions restart the in-stock clock
+#[tauri::command]
+pub fn db_record_vehicle_event(
+    vehicle_id: String,
+    event: NewVehicleEvent,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<VehicleEvent, String> {
+    track("db_record_vehicle_event", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let event = record_event(
+            &db.conn(),
+            &user_id_value,
+            &vehicle_id,
+            event,
+            Utc::now().timestamp_millis(),
+        )?;
+        info!(
+            "✅ {} recorded on vehicle {}",
+            event.event_type.as_str(),
+            vehicle_id
+        );
+        data_changed(
+            &user_id_value,
+            ChangedEntity::VehicleEvent,
+            &event.id,
+            Operation::Create,
+        );
+        Ok(event)
+    })
+}
+
+/// The vehicle's deals, deal status changes, payments and events, oldest first
+#[tauri::command]
+pub fn db_get_vehicle_history(
+    vehicle_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<VehicleHistory, String> {
+    track("db_get_vehicle_history", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        vehicle_history(&db.conn(), &user_id_value, &vehicle_id)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{db_create_client, db_create_vehicle, db_update_vehicle};
+    use crate::db_busy::DbError;
+    use crate::test_support::{make_client, make_vehicle, TestApp, TEST_USER};
+    use serde_json::json;
+
+    fn set_status(app: &TestApp, updates: Value) -> Result<(), DbError> {
+        db_update_vehicle("v1".into(), updates, app.state(), app.db()).map(|_| ())
+    }
+
+    fn events(app: &TestApp) -> Vec<VehicleEvent> {
+        events_for(&app.conn(), "v1").unwrap()
+    }
+
+    #[test]
+    fn test_status_changes_are_recorded_and_a_return_needs_a_reason() {
+        let app = TestApp::new();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+
+        // Other edits don't write events
+        set_status(&app, json!({ "price": 17_500.0 })).unwrap();
+        set_status(&app, json!({ "status": "available" })).unwrap();
+        assert!(events(&app).is_empty());
+
+        set_status(&app, json!({ "status": "sold" })).unwrap();
+        let recorded = events(&app);
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].event_type, VehicleEventType::StatusChange);
+        assert_eq!(recorded[0].from_status.as_deref(), Some("available"));
+        assert_eq!(recorded[0].to_status.as_deref(), Some("sold"));
+        assert_eq!(recorded[0].user_id, TEST_USER);
+
+        // Sold -> available is refused without a reason, and nothing is written
+        for updates in [
+            json!({ "status": "available" }),
+            json!({ "status": "available", "return_reason": "   " }),
+        ] {
+            let err = set_status(&app, updates).unwrap_err();
+            assert!(
+                matches!(&err, DbError::Other { message } if message == RETURN_REASON_REQUIRED),
+                "{:?}",
+                err
+            );
+        }
+        assert_eq!(
+            db_get_vehicle_history("v1".into(), None, app.state(), app.db())
+                .unwrap()
+                .status,
+            "sold"
+        );
+        assert_eq!(events(&app).len(), 1);
+
+        set_status(
+            &app,
+            json!({
+                "status": "available",
+                "return_reason": " Missed four payments ",
+                "repossessed": true,
+            }),
+        )
+        .unwrap();
+        let recorded = events(&app);
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(recorded[1].event_type, VehicleEventType::Repossessed);
+        assert_eq!(recorded[1].notes.as_deref(), Some("Missed four payments"));
+
+        let history = db_get_vehicle_history("v1".into(), None, app.state(), app.db()).unwrap();
+        assert_eq!(history.in_stock_since, recorded[1].occurred_at);
+    }
+
+    #[test]
+    fn test_history_lists_every_sale_in_order() {
+        let app = TestApp::new();
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_client(make_client("c2"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        let conn = app.conn();
+        let day = |n: i64| 1_700_000_000_000 + n * 86_400_000;
+        let created_at: i64 = conn
+            .query_row(
+                "SELECT created_at FROM vehicles WHERE id = 'v1'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        conn.execute(
+            "INSERT INTO deals (id, type, client_id, vehicle_id, status, total_amount,
+                                down_payment, sale_date, created_at, updated_at, user_id)
+             VALUES ('first', 'bhph', 'c1', 'v1', 'sold', 9000, 1500, ?1, ?2, ?2, ?4),
+                    ('second', 'bhph', 'c2', 'v1', 'quote', 9500, NULL, NULL, ?3, ?3, ?4),
+                    ('theirs', 'bhph', 'c2', 'v1', 'sold', 9500, 900, ?1, ?2, ?2, 'someone-else')",
+            params![day(12), day(10), day(40), TEST_USER],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO deal_status_history (deal_id, user_id, from_status, to_status,
+                                              overridden, reason, changed_at)
+             VALUES ('first', ?1, 'approved', 'sold', 0, NULL, ?2)",
+            params![TEST_USER, day(12)],
+        )
+        .unwrap();
+        // Recorded late: listed by when it happened, not when it was entered
+        record_event(
+            &conn,
+            TEST_USER,
+            "v1",
+            NewVehicleEvent {
+                event_type: VehicleEventType::Repossessed,
+                notes: Some("Picked up from the buyer's work".to_string()),
+                occurred_at: Some(day(30)),
+            },
+            day(45),
+        )
+        .unwrap();
+        record_event(
+            &conn,
+            TEST_USER,
+            "v1",
+            NewVehicleEvent {
+                event_type: VehicleEventType::Note,
+                notes: Some("New tires".to_string()),
+                occurred_at: Some(day(31)),
+            },
+            day(31),
+        )
+        .unwrap();
+        drop(conn);
+
+        let history = db_get_vehicle_history("v1".into(), None, app.state(), app.db()).unwrap();
+        let kinds: Vec<(String, i64)> = history
+            .entries
+            .iter()
+            .map(|entry| {
+                let json = serde_json::to_value(entry).unwrap();
+                (
+                    json["kind"].as_str().unwrap().to_string(),
+                    entry.occurred_at(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("deal".to_string(), day(10)),
+                ("deal_status_change".to_string(), day(12)),
+                ("payment".to_string(), day(12)),
+                ("vehicle_event".to_string(), day(30)),
+                ("vehicle_event".to_string(), day(31)),
+                ("deal".to_string(), day(40)),
+            ]
+        );
+        assert!(matches!(
+            &history.entries[2],
+            HistoryEntry::Payment { deal_id, amount_cents: 150_000, .. } if deal_id == "first"
+        ));
+        // The repossession restarted the clock; the note didn't
+        assert!(created_at < day(30));
+        assert_eq!(history.in_stock_since, day(30));
+
+        let err = record_event(
+            &app.conn(),
+            TEST_USER,
+            "v1",
+            NewVehicleEvent {
+                event_type: VehicleEventType::Returned,
+                notes: None,
+                occurred_at: None,
+            },
+            day(50),
+        )
+        .unwrap_err();
+        assert!(err.contains("needs notes"), "{}", err);
+
+        app.sign_in("someone-else");
+        assert!(db_get_vehicle_history("v1".into(), None, app.state(), app.db()).is_err());
+    }
+}
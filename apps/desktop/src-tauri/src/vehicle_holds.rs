@@ -0,0 +1,523 @@
+// src-tauri/src/vehicle_holds.rs
+//
+// Holds on vehicles, so two salespeople can't sell the same car: while a vehicle has an
+// active hold (expires_at in the future) nobody but the holder can create a deal on it
+// (db_create_deal returns DbError::VehicleOnHold). A vehicle has at most one active hold;
+// holding it again extends your own hold. The check and the write share one immediate
+// transaction, so of two near-simultaneous holds or deals only one gets through.
+//
+// Expired holds stop blocking at once; start_hold_expiry deletes them every minute and
+// emits "hold-expired" (payload: VehicleHold) for each.
+
+use chrono::Utc;
+use log::{error, info, warn};
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult, Row};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::app_state::AppState;
+use crate::data_events::{data_changed, ChangedEntity, Operation};
+use crate::database::{begin_write, get_db, new_row_id, DbState};
+use crate::db_busy::DbError;
+use crate::recent_items::{owned_by, EntityType};
+use crate::telemetry::track;
+
+/// Event emitted when the sweep deletes an expired hold (payload: VehicleHold)
+pub const HOLD_EXPIRED_EVENT: &str = "hold-expired";
+
+const HOLD_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Longest a hold can run
+const MAX_HOLD_MS: i64 = 14 * 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VehicleHold {
+    pub id: String,
+    pub vehicle_id: String,
+    pub user_id: String,
+    pub client_id: Option<String>,
+    pub expires_at: i64,
+    pub note: Option<String>,
+    pub created_at: i64,
+}
+
+impl VehicleHold {
+    fn from_row(row: &Row) -> SqlResult<Self> {
+        Ok(VehicleHold {
+            id: row.get("id")?,
+            vehicle_id: row.get("vehicle_id")?,
+            user_id: row.get("user_id")?,
+            client_id: row.get("client_id")?,
+            expires_at: row.get("expires_at")?,
+            note: row.get("note")?,
+            created_at: row.get("created_at")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct NewVehicleHold {
+    pub vehicle_id: String,
+    /// The customer it's held for
+    #[serde(default)]
+    pub client_id: Option<String>,
+    pub expires_at: i64,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+fn active_hold(conn: &Connection, vehicle_id: &str, now: i64) -> SqlResult<Option<VehicleHold>> {
+    conn.query_row(
+        "SELECT * FROM vehicle_holds WHERE vehicle_id = ?1 AND expires_at > ?2
+         ORDER BY expires_at DESC LIMIT 1",
+        params![vehicle_id, now],
+        VehicleHold::from_row,
+    )
+    .optional()
+}
+
+/// Err(VehicleOnHold) if someone other than user_id holds the vehicle at `now`
+/// Call it in the same transaction as the write it guards
+pub(crate) fn check_vehicle_hold(
+    conn: &Connection,
+    vehicle_id: &str,
+    user_id: &str,
+    now: i64,
+) -> Result<(), DbError> {
+    match active_hold(conn, vehicle_id, now).map_err(|e| e.to_string())? {
+        Some(hold) if hold.user_id != user_id => Err(DbError::VehicleOnHold {
+            vehicle_id: hold.vehicle_id,
+            hold_id: hold.id,
+            held_by: hold.user_id,
+            expires_at: hold.expires_at,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn place(
+    conn: &Connection,
+    user_id: &str,
+    hold: NewVehicleHold,
+    now: i64,
+) -> Result<VehicleHold, DbError> {
+    if hold.expires_at <= now {
+        return Err("A hold has to expire in the future".to_string().into());
+    }
+    if hold.expires_at - now > MAX_HOLD_MS {
+        return Err("Holds can't run longer than 14 days".to_string().into());
+    }
+    if let Some(client_id) = hold.client_id.as_deref() {
+        owned_by(conn, user_id, EntityType::Client, client_id)?;
+    }
+
+    let tx = begin_write(conn, "vehicle_holds")?;
+    let exists = tx
+        .query_row(
+            "SELECT 1 FROM vehicles WHERE id = ?1",
+            params![hold.vehicle_id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if exists.is_none() {
+        return Err("Vehicle not found".to_string().into());
+    }
+    check_vehicle_hold(&tx, &hold.vehicle_id, user_id, now)?;
+
+    // Holding it again replaces your own hold
+    tx.execute(
+        "DELETE FROM vehicle_holds WHERE vehicle_id = ?1 AND user_id = ?2",
+        params![hold.vehicle_id, user_id],
+    )
+    .map_err(|e| e.to_string())?;
+    let hold = VehicleHold {
+        id: new_row_id(),
+        vehicle_id: hold.vehicle_id,
+        user_id: user_id.to_string(),
+        client_id: hold.client_id,
+        expires_at: hold.expires_at,
+        note: hold
+            .note
+            .map(|note| note.trim().to_string())
+            .filter(|note| !note.is_empty()),
+        created_at: now,
+    };
+    tx.execute(
+        "INSERT INTO vehicle_holds (id, vehicle_id, user_id, client_id, expires_at, note, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            hold.id,
+            hold.vehicle_id,
+            hold.user_id,
+            hold.client_id,
+            hold.expires_at,
+            hold.note,
+            hold.created_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+    Ok(hold)
+}
+
+/// Only the holder can release a hold
+fn release(conn: &Connection, user_id: &str, id: &str) -> Result<VehicleHold, String> {
+    let hold = conn
+        .query_row(
+            "SELECT * FROM vehicle_holds WHERE id = ?1 AND user_id = ?2",
+            params![id, user_id],
+            VehicleHold::from_row,
+        )
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Hold not found or access denied".to_string())?;
+    conn.execute("DELETE FROM vehicle_holds WHERE id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    Ok(hold)
+}
+
+/// Active holds on one vehicle (whoever holds it), or all of user_id's active holds
+fn list_active(
+    conn: &Connection,
+    user_id: &str,
+    vehicle_id: Option<&str>,
+    now: i64,
+) -> SqlResult<Vec<VehicleHold>> {
+    let (sql, key) = match vehicle_id {
+        Some(vehicle_id) => (
+            "SELECT * FROM vehicle_holds WHERE vehicle_id = ?1 AND expires_at > ?2
+             ORDER BY expires_at",
+            vehicle_id,
+        ),
+        None => (
+            "SELECT * FROM vehicle_holds WHERE user_id = ?1 AND expires_at > ?2
+             ORDER BY expires_at",
+            user_id,
+        ),
+    };
+    let mut stmt = conn.prepare(sql)?;
+    let holds = stmt.query_map(params![key, now], VehicleHold::from_row)?;
+    holds.collect()
+}
+
+/// Delete every hold that has expired by `now` and return them
+pub(crate) fn expire_holds(conn: &Connection, now: i64) -> SqlResult<Vec<VehicleHold>> {
+    let tx = conn.unchecked_transaction()?;
+    let expired = {
+        let mut stmt = tx.prepare(
+            "SELECT * FROM vehicle_holds WHERE expires_at <= ?1 ORDER BY expires_at, id",
+        )?;
+        let holds = stmt.query_map(params![now], VehicleHold::from_row)?;
+        holds.collect::<SqlResult<Vec<_>>>()?
+    };
+    tx.execute(
+        "DELETE FROM vehicle_holds WHERE expires_at <= ?1",
+        params![now],
+    )?;
+    tx.commit()?;
+    Ok(expired)
+}
+
+/// Delete expired holds every minute while the app runs, for every user
+pub fn start_hold_expiry(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(HOLD_CHECK_INTERVAL).await;
+
+            let expired = match get_db() {
+                Ok(db) => expire_holds(&db.conn(), Utc::now().timestamp_millis()),
+                Err(e) => Err(e),
+            };
+            let expired = match expired {
+                Ok(expired) => expired,
+                Err(e) => {
+                    warn!("⚠️  Hold expiry check failed: {}", e);
+                    continue;
+                }
+            };
+
+            for hold in &expired {
+                info!("⌛ Hold expired on vehicle {}", hold.vehicle_id);
+                if let Err(e) = app.emit(HOLD_EXPIRED_EVENT, hold) {
+                    error!("Failed to emit hold-expired: {}", e);
+                }
+                data_changed(
+                    &hold.user_id,
+                    ChangedEntity::VehicleHold,
+                    &hold.id,
+                    Operation::Delete,
+                );
+            }
+        }
+    });
+}
+
+/// Hold a vehicle until expires_at (at most 14 days out); fails with VehicleOnHold if
+/// someone else already holds it
+#[tauri::command]
+pub fn place_hold(
+    hold: NewVehicleHold,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<VehicleHold, DbError> {
+    track("place_hold", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let hold = place(
+            &db.conn(),
+            &user_id_value,
+            hold,
+            Utc::now().timestamp_millis(),
+        )?;
+        info!(
+            "✅ Vehicle {} held until {}",
+            hold.vehicle_id, hold.expires_at
+        );
+        data_changed(
+            &user_id_value,
+            ChangedEntity::VehicleHold,
+            &hold.id,
+            Operation::Create,
+        );
+        Ok(hold)
+    })
+}
+
+#[tauri::command]
+pub fn release_hold(
+    id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<VehicleHold, String> {
+    track("release_hold", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let hold = release(&db.conn(), &user_id_value, &id)?;
+        data_changed(
+            &user_id_value,
+            ChangedEntity::VehicleHold,
+            &hold.id,
+            Operation::Delete,
+        );
+        Ok(hold)
+    })
+}
+
+/// Active holds on vehicle_id, or the user's own active holds when there's no vehicle_id
+#[tauri::command]
+pub fn get_active_holds(
+    vehicle_id: Option<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+    db: State<'_, DbState>,
+) -> Result<Vec<VehicleHold>, String> {
+    track("get_active_holds", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = db.get().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        list_active(
+            &conn,
+            &user_id_value,
+            vehicle_id.as_deref(),
+            Utc::now().timestamp_millis(),
+        )
+        .map_err(|e| e.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{db_create_client, db_create_deal, db_create_vehicle, Database};
+    use crate::test_support::{make_client, make_deal, make_vehicle, temp_dir, TestApp, TEST_USER};
+    use std::fs;
+    use std::sync::{Arc, Barrier};
+    use std::thread;
+
+    const OTHER_USER: &str = "other-user";
+    const HOUR: i64 = 60 * 60 * 1000;
+
+    fn hold_on(vehicle_id: &str, expires_at: i64) -> NewVehicleHold {
+        NewVehicleHold {
+            vehicle_id: vehicle_id.to_string(),
+            client_id: None,
+            expires_at,
+            note: Some("  Coming back Saturday ".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_someone_elses_hold_blocks_deals() {
+        let app = TestApp::new();
+        db_create_client(make_client("c1"), None, None, None, app.state(), app.db()).unwrap();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        let expires_at = Utc::now().timestamp_millis() + HOUR;
+        let hold = place_hold(hold_on("v1", expires_at), None, app.state(), app.db()).unwrap();
+        assert_eq!(hold.note.as_deref(), Some("Coming back Saturday"));
+
+        app.sign_in(OTHER_USER);
+        let err = db_create_deal(
+            make_deal("d1", "c1", "v1"),
+            None,
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            DbError::VehicleOnHold {
+                vehicle_id: "v1".to_string(),
+                hold_id: hold.id.clone(),
+                held_by: TEST_USER.to_string(),
+                expires_at,
+            }
+        );
+        assert!(place_hold(hold_on("v1", expires_at), None, app.state(), app.db()).is_err());
+        assert!(release_hold(hold.id.clone(), None, app.state(), app.db()).is_err());
+        let holds = get_active_holds(Some("v1".to_string()), None, app.state(), app.db()).unwrap();
+        assert_eq!(holds, vec![hold.clone()]);
+
+        // The holder isn't blocked, and holding again extends the hold
+        app.sign_in(TEST_USER);
+        db_create_deal(
+            make_deal("d2", "c1", "v1"),
+            None,
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        let extended = place_hold(
+            hold_on("v1", expires_at + HOUR),
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+        let holds = get_active_holds(None, None, app.state(), app.db()).unwrap();
+        assert_eq!(holds, vec![extended.clone()]);
+
+        release_hold(extended.id, None, app.state(), app.db()).unwrap();
+        app.sign_in(OTHER_USER);
+        db_create_deal(
+            make_deal("d3", "c1", "v1"),
+            None,
+            None,
+            app.state(),
+            app.db(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_expired_holds_stop_blocking_and_are_swept() {
+        let app = TestApp::new();
+        db_create_vehicle(make_vehicle("v1"), app.state(), app.db()).unwrap();
+        let conn = app.conn();
+        let now = 1_700_000_000_000;
+
+        assert!(place(&conn, TEST_USER, hold_on("v1", now), now).is_err());
+        assert!(place(&conn, TEST_USER, hold_on("v1", now + MAX_HOLD_MS + 1), now).is_err());
+        let hold = place(&conn, TEST_USER, hold_on("v1", now + HOUR), now).unwrap();
+
+        assert!(check_vehicle_hold(&conn, "v1", OTHER_USER, now + HOUR - 1).is_err());
+        check_vehicle_hold(&conn, "v1", OTHER_USER, now + HOUR).unwrap();
+        // Expired but not swept yet: someone else can hold it
+        let taken = place(
+            &conn,
+            OTHER_USER,
+            hold_on("v1", now + 3 * HOUR),
+            now + 2 * HOUR,
+        )
+        .unwrap();
+
+        assert_eq!(expire_holds(&conn, now + 2 * HOUR).unwrap(), vec![hold]);
+        assert!(expire_holds(&conn, now + 2 * HOUR).unwrap().is_empty());
+        assert_eq!(
+            list_active(&conn, OTHER_USER, Some("v1"), now + 2 * HOUR).unwrap(),
+            vec![taken]
+        );
+    }
+
+    /// Separate connections to one database file, as two windows' commands would be
+    #[test]
+    fn test_only_one_of_two_simultaneous_holds_or_deals_wins() {
+        let dir = temp_dir("holds", "simultaneous");
+        let path = dir.join("dealer.db");
+        {
+            let db = Database::init_with_path(&path).unwrap();
+            db.conn()
+                .execute_batch(
+                    "INSERT INTO clients (id, first_name, last_name, created_at, updated_at, user_id)
+                         VALUES ('c1', 'Jane', 'Doe', 0, 0, 'u1');
+                     INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status,
+                         created_at, updated_at, user_id)
+                     VALUES ('v1', 'VIN1', 2020, 'Ford', 'F-150', 0, 30000, 'available', 0, 0, 'u1');",
+                )
+                .unwrap();
+        }
+        let now = Utc::now().timestamp_millis();
+
+        let race = |work: fn(&Connection, &str, i64) -> Result<(), DbError>| {
+            let barrier = Arc::new(Barrier::new(2));
+            let handles: Vec<_> = ["u1", "u2"]
+                .into_iter()
+                .map(|user| {
+                    let (barrier, path) = (barrier.clone(), path.clone());
+                    thread::spawn(move || {
+                        let db = Database::init_with_path(&path).unwrap();
+                        barrier.wait();
+                        let result = work(&db.conn(), user, now);
+                        (user, result)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        let holds =
+            race(|conn, user, now| place(conn, user, hold_on("v1", now + HOUR), now).map(|_| ()));
+        let winners: Vec<&str> = holds
+            .iter()
+            .filter(|(_, result)| result.is_ok())
+            .map(|(user, _)| *user)
+            .collect();
+        assert_eq!(winners.len(), 1, "{:?}", holds);
+        assert!(holds.iter().any(|(_, result)| matches!(
+            result,
+            Err(DbError::VehicleOnHold { held_by, .. }) if held_by == winners[0]
+        )));
+
+        // What db_create_deal does: check the hold and insert in one immediate transaction
+        let deals = race(|conn, user, now| {
+            let tx = begin_write(conn, "deals")?;
+            check_vehicle_hold(&tx, "v1", user, now)?;
+            tx.execute(
+                "INSERT INTO deals (id, user_id, type, client_id, vehicle_id, status,
+                     total_amount, document_ids, created_at, updated_at)
+                 VALUES (?1, ?2, 'cash', 'c1', 'v1', 'draft', 30000, '[]', 0, 0)",
+                params![format!("deal-{}", user), user],
+            )
+            .map_err(|e| e.to_string())?;
+            tx.commit().map_err(|e| e.to_string())?;
+            Ok(())
+        });
+        for (user, result) in &deals {
+            assert_eq!(
+                result.is_ok(),
+                *user == winners[0],
+                "{}: {:?}",
+                user,
+                result
+            );
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,627 @@
+// src-tauri/src/vehicle_images.rs
+//
+// Vehicle photos kept under the documents root, one folder per vehicle:
+//   {documents_root}/vehicles/{vehicle_id}/{image_id}.{ext}
+//   {documents_root}/vehicles/{vehicle_id}/{image_id}_thumb.jpg
+// vehicles.images holds a JSON array of VehicleImage entries. Older builds stored a plain
+// array of paths/URLs; those read as entries with a stable id derived from the path and
+// are rewritten in the new format the first time the list changes. Photos are turned
+// upright, scrubbed of EXIF and scaled to fit the vehicle_image_max_dimension setting on
+// the way in (see image_import.rs).
+
+use chrono::Utc;
+use log::{info, warn};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::data_export::documents_root;
+use crate::database::{get_db, new_row_id, read_setting};
+use crate::disk_space::ensure_free_space;
+use crate::image_import::{import_image, reject_heic, Transformation};
+use crate::settings_cache::pending_setting;
+use crate::storage_usage::check_quota;
+use crate::telemetry::track;
+use crate::thumbnails::write_thumbnail;
+
+/// Subfolder of the documents root holding vehicle photos
+pub const VEHICLES_DIR: &str = "vehicles";
+
+/// Settings key: longest side, in pixels, a photo is stored at; 0 keeps full size
+pub const MAX_DIMENSION_SETTING: &str = "vehicle_image_max_dimension";
+
+const DEFAULT_MAX_DIMENSION: u32 = 4096;
+const THUMBNAIL_DIMENSION: u32 = 320;
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VehicleImage {
+    pub id: String,
+    /// Local file, or a URL for photos saved by older builds
+    pub path: String,
+    pub thumb_path: Option<String>,
+    pub order: u32,
+    /// 0 for photos saved by older builds
+    #[serde(default)]
+    pub added_at: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AddedVehicleImage {
+    pub images: Vec<VehicleImage>,
+    /// What was done to the new photo on import; empty if it was copied as is
+    pub transformations: Vec<Transformation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrphanCleanup {
+    /// Files under the vehicles folder that no vehicle references
+    pub orphans: Vec<String>,
+    pub removed: usize,
+    pub bytes: u64,
+}
+
+/// Id for an entry from the old plain-array format, stable across reads
+fn legacy_image_id(path: &str) -> String {
+    let digest = Sha256::digest(path.as_bytes());
+    let hex: String = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("legacy-{}", hex)
+}
+
+/// vehicles.images in either format, sorted by order
+pub fn parse_images(raw: Option<&str>) -> Result<Vec<VehicleImage>, String> {
+    let raw = match raw.map(str::trim) {
+        None | Some("") | Some("null") => return Ok(Vec::new()),
+        Some(raw) => raw,
+    };
+    let entries: Vec<Value> =
+        serde_json::from_str(raw).map_err(|e| format!("Invalid images JSON: {}", e))?;
+
+    let mut images = entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| match entry {
+            Value::String(path) => Ok(VehicleImage {
+                id: legacy_image_id(&path),
+                path,
+                thumb_path: None,
+                order: i as u32,
+                added_at: 0,
+            }),
+            entry => {
+                serde_json::from_value(entry).map_err(|e| format!("Invalid image entry: {}", e))
+            }
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    images.sort_by_key(|image| image.order);
+    Ok(images)
+}
+
+/// Renumbers order 0..n and serializes; an empty list is stored as NULL
+fn serialize_images(images: &mut [VehicleImage]) -> Result<Option<String>, String> {
+    for (i, image) in images.iter_mut().enumerate() {
+        image.order = i as u32;
+    }
+    if images.is_empty() {
+        return Ok(None);
+    }
+    serde_json::to_string(images)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+fn load_images(
+    conn: &Connection,
+    vehicle_id: &str,
+    user_id: &str,
+) -> Result<Vec<VehicleImage>, String> {
+    let raw: Option<Option<String>> = conn
+        .query_row(
+            "SELECT images FROM vehicles WHERE id = ?1 AND user_id = ?2",
+            params![vehicle_id, user_id],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    let raw = raw.ok_or_else(|| "Vehicle not found or access denied".to_string())?;
+    parse_images(raw.as_deref())
+}
+
+fn save_images(
+    conn: &Connection,
+    vehicle_id: &str,
+    user_id: &str,
+    images: &mut [VehicleImage],
+) -> Result<(), String> {
+    conn.execute(
+        "UPDATE vehicles SET images = ?1, updated_at = ?2 WHERE id = ?3 AND user_id = ?4",
+        params![
+            serialize_images(images)?,
+            Utc::now().timestamp_millis(),
+            vehicle_id,
+            user_id
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn vehicle_dir(root: &Path, vehicle_id: &str) -> PathBuf {
+    root.join(VEHICLES_DIR).join(vehicle_id)
+}
+
+/// The max dimension setting, read through conn; None when it's 0
+fn max_dimension(conn: &Connection) -> Option<u32> {
+    let value = pending_setting(MAX_DIMENSION_SETTING)
+        .or_else(|| read_setting(conn, MAX_DIMENSION_SETTING).ok().flatten());
+    let max = match value.as_deref().map(str::trim) {
+        None | Some("") => DEFAULT_MAX_DIMENSION,
+        Some(raw) => raw.parse().unwrap_or_else(|_| {
+            warn!(
+                "⚠️  Invalid {} {:?}; using {}",
+                MAX_DIMENSION_SETTING, raw, DEFAULT_MAX_DIMENSION
+            );
+            DEFAULT_MAX_DIMENSION
+        }),
+    };
+    Some(max).filter(|max| *max > 0)
+}
+
+fn add_image(
+    conn: &Connection,
+    root: &Path,
+    vehicle_id: &str,
+    user_id: &str,
+    source: &Path,
+) -> Result<AddedVehicleImage, String> {
+    let mut images = load_images(conn, vehicle_id, user_id)?;
+
+    reject_heic(source)?;
+    let extension = source
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .filter(|e| IMAGE_EXTENSIONS.contains(&e.as_str()))
+        .ok_or_else(|| {
+            format!(
+                "Unsupported image type (expected one of: {})",
+                IMAGE_EXTENSIONS.join(", ")
+            )
+        })?;
+    if !source.is_file() {
+        return Err(format!("Image not found: {}", source.display()));
+    }
+
+    let id = new_row_id();
+    let dir = vehicle_dir(root, vehicle_id);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+    let path = dir.join(format!("{}.{}", id, extension));
+    let thumb_path = dir.join(format!("{}_thumb.jpg", id));
+
+    let transformations = import_image(source, &path, max_dimension(conn))?;
+    // Also proves the file decodes; don't keep a copy we can't show
+    if let Err(e) = write_thumbnail(&path, THUMBNAIL_DIMENSION, &thumb_path) {
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&thumb_path);
+        return Err(e.to_string());
+    }
+
+    images.push(VehicleImage {
+        id,
+        path: path.to_string_lossy().to_string(),
+        thumb_path: Some(thumb_path.to_string_lossy().to_string()),
+        order: images.len() as u32,
+        added_at: Utc::now().timestamp_millis(),
+    });
+    if let Err(e) = save_images(conn, vehicle_id, user_id, &mut images) {
+        let _ = fs::remove_file(&path);
+        let _ = fs::remove_file(&thumb_path);
+        return Err(e);
+    }
+    Ok(AddedVehicleImage {
+        images,
+        transformations,
+    })
+}
+
+fn remove_image(
+    conn: &Connection,
+    root: &Path,
+    vehicle_id: &str,
+    user_id: &str,
+    image_id: &str,
+) -> Result<Vec<VehicleImage>, String> {
+    let mut images = load_images(conn, vehicle_id, user_id)?;
+    let index = images
+        .iter()
+        .position(|image| image.id == image_id)
+        .ok_or_else(|| format!("Image {} not found on vehicle {}", image_id, vehicle_id))?;
+    let removed = images.remove(index);
+    save_images(conn, vehicle_id, user_id, &mut images)?;
+
+    // Only files we manage; legacy entries may point anywhere (or at a URL)
+    let managed = vehicle_dir(root, vehicle_id);
+    for file in std::iter::once(&removed.path).chain(removed.thumb_path.as_ref()) {
+        let file = Path::new(file);
+        if file.starts_with(&managed) {
+            if let Err(e) = fs::remove_file(file) {
+                warn!(
+                    "⚠️  Could not delete vehicle image {}: {}",
+                    file.display(),
+                    e
+                );
+            }
+        }
+    }
+    Ok(images)
+}
+
+fn reorder_images(
+    conn: &Connection,
+    vehicle_id: &str,
+    user_id: &str,
+    image_ids: &[String],
+) -> Result<Vec<VehicleImage>, String> {
+    let mut images = load_images(conn, vehicle_id, user_id)?;
+
+    let unique: HashSet<&str> = image_ids.iter().map(String::as_str).collect();
+    let complete = unique.len() == image_ids.len()
+        && image_ids.len() == images.len()
+        && images
+            .iter()
+            .all(|image| unique.contains(image.id.as_str()));
+    if !complete {
+        return Err("image_ids must list every image on the vehicle exactly once".to_string());
+    }
+
+    images.sort_by_key(|image| image_ids.iter().position(|id| *id == image.id));
+    save_images(conn, vehicle_id, user_id, &mut images)?;
+    Ok(images)
+}
+
+/// Files under {root}/vehicles that no vehicle's images reference (any user)
+fn find_orphans(conn: &Connection, root: &Path) -> Result<Vec<PathBuf>, String> {
+    let base = root.join(VEHICLES_DIR);
+    if !base.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut referenced = HashSet::new();
+    let mut stmt = conn
+        .prepare("SELECT images FROM vehicles WHERE images IS NOT NULL")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, Option<String>>(0))
+        .map_err(|e| e.to_string())?;
+    for raw in rows {
+        let raw = raw.map_err(|e| e.to_string())?;
+        // A vehicle with unreadable images JSON shouldn't get other vehicles' files deleted
+        for image in parse_images(raw.as_deref()).unwrap_or_default() {
+            referenced.insert(PathBuf::from(image.path));
+            referenced.extend(image.thumb_path.map(PathBuf::from));
+        }
+    }
+
+    let mut orphans = Vec::new();
+    for vehicle_dir in fs::read_dir(&base).map_err(|e| e.to_string())? {
+        let vehicle_dir = vehicle_dir.map_err(|e| e.to_string())?.path();
+        if !vehicle_dir.is_dir() {
+            continue;
+        }
+        for file in fs::read_dir(&vehicle_dir).map_err(|e| e.to_string())? {
+            let file = file.map_err(|e| e.to_string())?.path();
+            if file.is_file() && !referenced.contains(&file) {
+                orphans.push(file);
+            }
+        }
+    }
+    orphans.sort();
+    Ok(orphans)
+}
+
+/// Copy a photo into the vehicle's folder, thumbnail it, and append it to the vehicle's images
+/// Returns the vehicle's images and what import did to the photo (rotation, EXIF, size)
+#[tauri::command]
+pub fn db_add_vehicle_image(
+    vehicle_id: String,
+    source_path: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<AddedVehicleImage, String> {
+    track("db_add_vehicle_image", || {
+        let user_id_value = state.require_user(user_id)?;
+        let root = PathBuf::from(documents_root()?);
+        let source = Path::new(&source_path);
+        let size = fs::metadata(source)
+            .map_err(|_| format!("Image not found: {}", source_path))?
+            .len();
+        ensure_free_space(&root, size).map_err(|e| e.to_string())?;
+
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        check_quota(&conn, &user_id_value, size).map_err(|e| e.to_string())?;
+        let added = add_image(&conn, &root, &vehicle_id, &user_id_value, source)?;
+        info!(
+            "✅ Image added to vehicle {} ({} total, {} transformations)",
+            vehicle_id,
+            added.images.len(),
+            added.transformations.len()
+        );
+        Ok(added)
+    })
+}
+
+/// Remove a photo from the vehicle, deleting its file and thumbnail
+#[tauri::command]
+pub fn db_remove_vehicle_image(
+    vehicle_id: String,
+    image_id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<VehicleImage>, String> {
+    track("db_remove_vehicle_image", || {
+        let user_id_value = state.require_user(user_id)?;
+        let root = PathBuf::from(documents_root()?);
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        remove_image(&conn, &root, &vehicle_id, &user_id_value, &image_id)
+    })
+}
+
+/// Put the vehicle's photos in the given order (every image id, once)
+#[tauri::command]
+pub fn db_reorder_vehicle_images(
+    vehicle_id: String,
+    image_ids: Vec<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<VehicleImage>, String> {
+    track("db_reorder_vehicle_images", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+        reorder_images(&conn, &vehicle_id, &user_id_value, &image_ids)
+    })
+}
+
+/// Find (and unless dry_run, delete) photo files no vehicle references anymore
+#[tauri::command]
+pub fn cleanup_orphan_vehicle_images(dry_run: Option<bool>) -> Result<OrphanCleanup, String> {
+    track("cleanup_orphan_vehicle_images", || {
+        let root = PathBuf::from(documents_root()?);
+        let orphans = {
+            let db = get_db().map_err(|e| e.to_string())?;
+            let conn = db.conn();
+            find_orphans(&conn, &root)?
+        };
+
+        let mut cleanup = OrphanCleanup {
+            orphans: Vec::new(),
+            removed: 0,
+            bytes: 0,
+        };
+        for orphan in orphans {
+            let size = fs::metadata(&orphan).map(|m| m.len()).unwrap_or(0);
+            cleanup.bytes += size;
+            if !dry_run.unwrap_or(false) {
+                match fs::remove_file(&orphan) {
+                    Ok(()) => cleanup.removed += 1,
+                    Err(e) => warn!("⚠️  Could not delete {}: {}", orphan.display(), e),
+                }
+            }
+            cleanup.orphans.push(orphan.to_string_lossy().to_string());
+        }
+        info!(
+            "🧹 Orphaned vehicle images: {} found, {} removed ({} bytes)",
+            cleanup.orphans.len(),
+            cleanup.removed,
+            cleanup.bytes
+        );
+        Ok(cleanup)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+    use image::{Rgb, RgbImage};
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        for sql in [
+            include_str!("../migrations/001_initial_schema.sql"),
+            include_str!("../migrations/002_add_sync_fields.sql"),
+            include_str!("../migrations/003_add_document_paths.sql"),
+            include_str!("../migrations/005_add_user_id.sql"),
+            include_str!("../migrations/004_add_vehicle_images.sql"),
+        ] {
+            conn.execute_batch(sql).unwrap();
+        }
+        conn
+    }
+
+    fn insert_vehicle(conn: &Connection, id: &str, images: Option<&str>) {
+        conn.execute(
+            "INSERT INTO vehicles (id, vin, year, make, model, mileage, price, status, images,
+                created_at, updated_at, user_id)
+             VALUES (?1, ?1, 2020, 'Honda', 'Civic', 0, 1.0, 'available', ?2, 1, 1, 'u1')",
+            params![id, images],
+        )
+        .unwrap();
+    }
+
+    fn photo(dir: &Path, name: &str) -> PathBuf {
+        let path = dir.join(name);
+        RgbImage::from_pixel(640, 480, Rgb([0, 90, 200]))
+            .save(&path)
+            .unwrap();
+        path
+    }
+
+    fn stored(conn: &Connection, id: &str) -> Value {
+        let raw: String = conn
+            .query_row("SELECT images FROM vehicles WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        serde_json::from_str(&raw).unwrap()
+    }
+
+    #[test]
+    fn test_reads_legacy_plain_array() {
+        let raw = r#"["https://cdn.test/1.jpg", "/photos/2.png"]"#;
+        let images = parse_images(Some(raw)).unwrap();
+
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0].path, "https://cdn.test/1.jpg");
+        assert_eq!(images[1].order, 1);
+        assert!(images.iter().all(|image| image.thumb_path.is_none()));
+        assert!(images[0].id.starts_with("legacy-"));
+        // Same path, same id, so a legacy image can be removed by the id the UI was given
+        assert_eq!(parse_images(Some(raw)).unwrap(), images);
+
+        assert!(parse_images(None).unwrap().is_empty());
+        assert!(parse_images(Some("null")).unwrap().is_empty());
+        assert!(parse_images(Some("{}")).is_err());
+    }
+
+    #[test]
+    fn test_structured_entries_sort_by_order() {
+        let raw = r#"[
+            {"id": "b", "path": "/p/b.jpg", "thumb_path": null, "order": 1, "added_at": 2},
+            {"id": "a", "path": "/p/a.jpg", "thumb_path": "/p/a_thumb.jpg", "order": 0, "added_at": 1}
+        ]"#;
+        let ids: Vec<String> = parse_images(Some(raw))
+            .unwrap()
+            .into_iter()
+            .map(|image| image.id)
+            .collect();
+        assert_eq!(ids, ["a", "b"]);
+    }
+
+    #[test]
+    fn test_adding_to_a_legacy_vehicle_rewrites_the_new_format() {
+        let dir = temp_dir("vehicle-images", "add");
+        let root = dir.join("docs");
+        let conn = test_db();
+        insert_vehicle(&conn, "v1", Some(r#"["https://cdn.test/1.jpg"]"#));
+
+        let added = add_image(&conn, &root, "v1", "u1", &photo(&dir, "front.PNG")).unwrap();
+        assert!(added.transformations.is_empty());
+        let images = added.images;
+        assert_eq!(images.len(), 2);
+        let added = &images[1];
+        assert_eq!(added.order, 1);
+        assert!(added.path.ends_with(".png"));
+        assert!(Path::new(&added.path).starts_with(root.join("vehicles").join("v1")));
+        assert!(Path::new(&added.path).is_file());
+        let thumb = added.thumb_path.as_deref().unwrap();
+        assert_eq!(image::image_dimensions(thumb).unwrap(), (320, 240));
+
+        let json = stored(&conn, "v1");
+        assert_eq!(json[0]["path"], "https://cdn.test/1.jpg");
+        assert_eq!(json[0]["id"], images[0].id.as_str());
+        assert_eq!(json[1]["id"], added.id.as_str());
+        assert_eq!(json[1]["order"], 1);
+
+        // Not an image we can show: nothing is kept
+        let fake = dir.join("notes.jpg");
+        fs::write(&fake, b"not a jpeg").unwrap();
+        assert!(add_image(&conn, &root, "v1", "u1", &fake).is_err());
+        assert!(add_image(&conn, &root, "v1", "u1", &dir.join("doc.pdf")).is_err());
+        let err = add_image(&conn, &root, "v1", "u1", &dir.join("IMG_0001.HEIC")).unwrap_err();
+        assert!(err.contains("HEIC photo"), "{}", err);
+        assert_eq!(
+            fs::read_dir(root.join("vehicles").join("v1"))
+                .unwrap()
+                .count(),
+            2
+        );
+
+        // Other users' vehicles are off limits
+        let err = add_image(&conn, &root, "v1", "u2", &photo(&dir, "x.png")).unwrap_err();
+        assert!(err.contains("access denied"), "{}", err);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_remove_and_reorder() {
+        let dir = temp_dir("vehicle-images", "remove");
+        let root = dir.join("docs");
+        let conn = test_db();
+        insert_vehicle(&conn, "v1", Some(r#"["https://cdn.test/1.jpg"]"#));
+        add_image(&conn, &root, "v1", "u1", &photo(&dir, "a.png")).unwrap();
+        let images = add_image(&conn, &root, "v1", "u1", &photo(&dir, "b.png"))
+            .unwrap()
+            .images;
+        let ids: Vec<String> = images.iter().map(|image| image.id.clone()).collect();
+
+        let reordered = vec![ids[2].clone(), ids[0].clone(), ids[1].clone()];
+        let images = reorder_images(&conn, "v1", "u1", &reordered).unwrap();
+        assert_eq!(
+            images.iter().map(|image| &image.id).collect::<Vec<_>>(),
+            reordered.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(stored(&conn, "v1")[0]["order"], 0);
+        assert!(reorder_images(&conn, "v1", "u1", &reordered[..2]).is_err());
+        let duplicated = vec![ids[0].clone(), ids[0].clone(), ids[1].clone()];
+        assert!(reorder_images(&conn, "v1", "u1", &duplicated).is_err());
+
+        let managed = images[0].clone();
+        let images = remove_image(&conn, &root, "v1", "u1", &managed.id).unwrap();
+        assert_eq!(images.len(), 2);
+        assert!(!Path::new(&managed.path).exists());
+        assert!(!Path::new(managed.thumb_path.as_deref().unwrap()).exists());
+        assert_eq!(images[0].order, 0);
+
+        // Legacy URL entries just leave the list
+        let images = remove_image(&conn, &root, "v1", "u1", &ids[0]).unwrap();
+        assert_eq!(images.len(), 1);
+        assert!(remove_image(&conn, &root, "v1", "u1", "missing").is_err());
+
+        let last = images[0].id.clone();
+        assert!(remove_image(&conn, &root, "v1", "u1", &last)
+            .unwrap()
+            .is_empty());
+        let raw: Option<String> = conn
+            .query_row("SELECT images FROM vehicles WHERE id = 'v1'", [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+        assert_eq!(raw, None);
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_finds_orphaned_files() {
+        let dir = temp_dir("vehicle-images", "orphans");
+        let root = dir.join("docs");
+        let conn = test_db();
+        insert_vehicle(&conn, "v1", None);
+        let images = add_image(&conn, &root, "v1", "u1", &photo(&dir, "a.png"))
+            .unwrap()
+            .images;
+
+        let stray = root.join("vehicles").join("v1").join("stray.jpg");
+        fs::write(&stray, b"x").unwrap();
+        let deleted_vehicle = root.join("vehicles").join("gone");
+        fs::create_dir_all(&deleted_vehicle).unwrap();
+        fs::write(deleted_vehicle.join("old.jpg"), b"x").unwrap();
+
+        let orphans = find_orphans(&conn, &root).unwrap();
+        assert_eq!(orphans, [deleted_vehicle.join("old.jpg"), stray]);
+        assert!(!orphans.contains(&PathBuf::from(&images[0].path)));
+
+        assert!(find_orphans(&conn, &dir.join("empty")).unwrap().is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
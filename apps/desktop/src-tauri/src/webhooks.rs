@@ -0,0 +1,745 @@
+// src-tauri/src/webhooks.rs
+//
+// Outbound webhooks for Zapier-style automation (e.g. "a deal was marked sold").
+// Commands that change deals or clients call notify() after their write succeeds; delivery
+// runs on the async runtime, so a slow or dead endpoint never holds up saving a deal.
+//
+// Each POST carries a JSON envelope {id, event, created_at, data} and these headers:
+//   X-Webhook-Id         delivery id (the same for every retry of one event)
+//   X-Webhook-Event      event name
+//   X-Webhook-Timestamp  unix seconds
+//   X-Webhook-Signature  "sha256=" + base64 HMAC-SHA256 of "<timestamp>.<body>" keyed with the secret
+// Network errors, 5xx, 408 and 429 are retried with exponential backoff; every attempt is
+// recorded in webhook_deliveries.
+
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::OsRng;
+use base64::{engine::general_purpose, Engine as _};
+use log::{info, warn};
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+use tauri::State;
+
+use crate::app_state::AppState;
+use crate::database::get_db;
+use crate::signing::hmac_sign_bytes;
+use crate::telemetry::track;
+
+const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+const DEFAULT_BASE_DELAY: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delivery attempts older than this are pruned
+const DELIVERY_RETENTION_SECS: i64 = 30 * 24 * 60 * 60;
+const DEFAULT_DELIVERIES_LIMIT: u32 = 100;
+const MAX_DELIVERIES_LIMIT: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WebhookEvent {
+    #[serde(rename = "deal.created")]
+    DealCreated,
+    #[serde(rename = "deal.updated")]
+    DealUpdated,
+    /// A deal's status changed to "sold"
+    #[serde(rename = "deal.sold")]
+    DealSold,
+    #[serde(rename = "client.created")]
+    ClientCreated,
+    #[serde(rename = "client.updated")]
+    ClientUpdated,
+    #[serde(rename = "client.deleted")]
+    ClientDeleted,
+}
+
+impl WebhookEvent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WebhookEvent::DealCreated => "deal.created",
+            WebhookEvent::DealUpdated => "deal.updated",
+            WebhookEvent::DealSold => "deal.sold",
+            WebhookEvent::ClientCreated => "client.created",
+            WebhookEvent::ClientUpdated => "client.updated",
+            WebhookEvent::ClientDeleted => "client.deleted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Webhook {
+    pub id: String,
+    pub url: String,
+    /// Shown so the receiver can be configured to check signatures
+    pub secret: String,
+    pub events: Vec<WebhookEvent>,
+    pub enabled: bool,
+    pub created_at: i64,
+    pub updated_at: i64,
+}
+
+impl Webhook {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let events: String = row.get(3)?;
+        Ok(Webhook {
+            id: row.get(0)?,
+            url: row.get(1)?,
+            secret: row.get(2)?,
+            // Unknown names (from a newer version) are dropped rather than hiding the webhook
+            events: serde_json::from_str::<Vec<Value>>(&events)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|event| serde_json::from_value(event).ok())
+                .collect(),
+            enabled: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+        })
+    }
+}
+
+const WEBHOOK_COLUMNS: &str = "id, url, secret, events, enabled, created_at, updated_at";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WebhookDelivery {
+    pub webhook_id: String,
+    pub delivery_id: String,
+    pub event: String,
+    pub attempt: u32,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub success: bool,
+    pub duration_ms: u64,
+    pub attempted_at: i64, // unix seconds
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: DEFAULT_BASE_DELAY,
+        }
+    }
+}
+
+/// Send `event` to the user's webhooks that subscribe to it. Returns immediately
+pub fn notify(user_id: &str, event: WebhookEvent, data: &impl Serialize) {
+    let data = match serde_json::to_value(data) {
+        Ok(data) => data,
+        Err(e) => {
+            warn!(
+                "⚠️  [WEBHOOK] Failed to serialize {} payload: {}",
+                event.as_str(),
+                e
+            );
+            return;
+        }
+    };
+    let user_id = user_id.to_string();
+
+    tauri::async_runtime::spawn(async move {
+        // The command that called us may still hold the database lock
+        let hooks =
+            tauri::async_runtime::spawn_blocking(move || subscribed_webhooks(&user_id, event))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|hooks| hooks);
+        let hooks = match hooks {
+            Ok(hooks) => hooks,
+            Err(e) => {
+                warn!("⚠️  [WEBHOOK] Failed to load webhooks: {}", e);
+                return;
+            }
+        };
+
+        for hook in hooks {
+            let data = data.clone();
+            tauri::async_runtime::spawn(async move {
+                let client = http_client();
+                let delivery_id = generate_id("whd");
+                let body = envelope(&delivery_id, event, data);
+                deliver(
+                    &client,
+                    &hook,
+                    &delivery_id,
+                    event,
+                    &body,
+                    RetryPolicy::default(),
+                    |attempt| {
+                        if let Err(e) = record_delivery(&attempt) {
+                            warn!("⚠️  [WEBHOOK] Failed to record delivery: {}", e);
+                        }
+                    },
+                )
+                .await;
+            });
+        }
+    });
+}
+
+#[tauri::command]
+pub fn create_webhook(
+    url: String,
+    events: Vec<WebhookEvent>,
+    secret: Option<String>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Webhook, String> {
+    track("create_webhook", || {
+        let user_id_value = state.require_user(user_id)?;
+        validate(&url, &events)?;
+
+        let now = chrono::Utc::now().timestamp_millis();
+        let webhook = Webhook {
+            id: generate_id("wh"),
+            url: url.trim().to_string(),
+            secret: secret
+                .filter(|secret| !secret.trim().is_empty())
+                .unwrap_or_else(generate_secret),
+            events,
+            enabled: true,
+            created_at: now,
+            updated_at: now,
+        };
+
+        let db = get_db().map_err(|e| e.to_string())?;
+        db.conn()
+            .execute(
+                "INSERT INTO webhooks (id, user_id, url, secret, events, enabled, created_at, updated_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    webhook.id,
+                    user_id_value,
+                    webhook.url,
+                    webhook.secret,
+                    events_json(&webhook.events)?,
+                    webhook.enabled,
+                    webhook.created_at,
+                    webhook.updated_at,
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+
+        info!("✅ [WEBHOOK] Created {}", webhook.id);
+        Ok(webhook)
+    })
+}
+
+#[tauri::command]
+pub fn get_webhooks(
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Webhook>, String> {
+    track("get_webhooks", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+
+        let mut stmt = conn
+            .prepare(&format!(
+                "SELECT {} FROM webhooks WHERE user_id = ?1 ORDER BY created_at",
+                WEBHOOK_COLUMNS
+            ))
+            .map_err(|e| e.to_string())?;
+        let webhooks = stmt
+            .query_map(params![user_id_value], Webhook::from_row)
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(webhooks)
+    })
+}
+
+/// Change a webhook's url, events or enabled flag; fields left out are unchanged
+#[tauri::command]
+pub fn update_webhook(
+    id: String,
+    url: Option<String>,
+    events: Option<Vec<WebhookEvent>>,
+    enabled: Option<bool>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Webhook, String> {
+    track("update_webhook", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+
+        let mut webhook = conn
+            .query_row(
+                &format!(
+                    "SELECT {} FROM webhooks WHERE id = ?1 AND user_id = ?2",
+                    WEBHOOK_COLUMNS
+                ),
+                params![id, user_id_value],
+                Webhook::from_row,
+            )
+            .optional()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "Webhook not found or access denied".to_string())?;
+
+        if let Some(url) = url {
+            webhook.url = url.trim().to_string();
+        }
+        if let Some(events) = events {
+            webhook.events = events;
+        }
+        if let Some(enabled) = enabled {
+            webhook.enabled = enabled;
+        }
+        validate(&webhook.url, &webhook.events)?;
+        webhook.updated_at = chrono::Utc::now().timestamp_millis();
+
+        conn.execute(
+            "UPDATE webhooks SET url = ?2, events = ?3, enabled = ?4, updated_at = ?5
+             WHERE id = ?1 AND user_id = ?6",
+            params![
+                webhook.id,
+                webhook.url,
+                events_json(&webhook.events)?,
+                webhook.enabled,
+                webhook.updated_at,
+                user_id_value,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(webhook)
+    })
+}
+
+#[tauri::command]
+pub fn delete_webhook(
+    id: String,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    track("delete_webhook", || {
+        let user_id_value = state.require_user(user_id)?;
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+
+        let deleted = conn
+            .execute(
+                "DELETE FROM webhooks WHERE id = ?1 AND user_id = ?2",
+                params![id, user_id_value],
+            )
+            .map_err(|e| e.to_string())?;
+        if deleted == 0 {
+            return Err("Webhook not found or access denied".to_string());
+        }
+        conn.execute(
+            "DELETE FROM webhook_deliveries WHERE webhook_id = ?1",
+            params![id],
+        )
+        .map_err(|e| e.to_string())?;
+
+        info!("🗑️ [WEBHOOK] Deleted {}", id);
+        Ok(())
+    })
+}
+
+/// Recent delivery attempts, newest first, for one webhook or all of the user's webhooks
+#[tauri::command]
+pub fn get_webhook_deliveries(
+    webhook_id: Option<String>,
+    limit: Option<u32>,
+    user_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<WebhookDelivery>, String> {
+    track("get_webhook_deliveries", || {
+        let user_id_value = state.require_user(user_id)?;
+        let limit = limit
+            .unwrap_or(DEFAULT_DELIVERIES_LIMIT)
+            .clamp(1, MAX_DELIVERIES_LIMIT);
+        let db = get_db().map_err(|e| e.to_string())?;
+        let conn = db.conn();
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT d.webhook_id, d.delivery_id, d.event, d.attempt, d.status_code, d.error,
+                        d.success, d.duration_ms, d.attempted_at
+                 FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id
+                 WHERE w.user_id = ?1 AND (?2 IS NULL OR d.webhook_id = ?2)
+                 ORDER BY d.attempted_at DESC, d.id DESC
+                 LIMIT ?3",
+            )
+            .map_err(|e| e.to_string())?;
+        let deliveries = stmt
+            .query_map(params![user_id_value, webhook_id, limit], |row| {
+                Ok(WebhookDelivery {
+                    webhook_id: row.get(0)?,
+                    delivery_id: row.get(1)?,
+                    event: row.get(2)?,
+                    attempt: row.get(3)?,
+                    status_code: row.get(4)?,
+                    error: row.get(5)?,
+                    success: row.get(6)?,
+                    duration_ms: row.get::<_, i64>(7)? as u64,
+                    attempted_at: row.get(8)?,
+                })
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| e.to_string())?;
+        Ok(deliveries)
+    })
+}
+
+fn subscribed_webhooks(user_id: &str, event: WebhookEvent) -> Result<Vec<Webhook>, String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    let conn = db.try_conn()?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM webhooks WHERE user_id = ?1 AND enabled = 1",
+            WEBHOOK_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let webhooks = stmt
+        .query_map(params![user_id], Webhook::from_row)
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+    Ok(webhooks
+        .into_iter()
+        .filter(|hook| hook.events.contains(&event))
+        .collect())
+}
+
+fn record_delivery(attempt: &WebhookDelivery) -> Result<(), String> {
+    let db = get_db().map_err(|e| e.to_string())?;
+    let conn = db.try_conn()?;
+    conn.execute(
+        "INSERT INTO webhook_deliveries (webhook_id, delivery_id, event, attempt, status_code,
+            error, success, duration_ms, attempted_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            attempt.webhook_id,
+            attempt.delivery_id,
+            attempt.event,
+            attempt.attempt,
+            attempt.status_code,
+            attempt.error,
+            attempt.success,
+            attempt.duration_ms as i64,
+            attempt.attempted_at,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    if attempt.attempt == 1 {
+        conn.execute(
+            "DELETE FROM webhook_deliveries WHERE attempted_at < ?1",
+            params![attempt.attempted_at - DELIVERY_RETENTION_SECS],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// POST `body` until it's accepted, the receiver rejects it, or attempts run out.
+/// Returns whether it was delivered
+async fn deliver(
+    client: &reqwest::Client,
+    hook: &Webhook,
+    delivery_id: &str,
+    event: WebhookEvent,
+    body: &[u8],
+    policy: RetryPolicy,
+    mut record: impl FnMut(WebhookDelivery),
+) -> bool {
+    for attempt in 1..=policy.max_attempts {
+        let timestamp = chrono::Utc::now().timestamp();
+        let started = Instant::now();
+        let result = client
+            .post(&hook.url)
+            .header("Content-Type", "application/json")
+            .header("X-Webhook-Id", delivery_id)
+            .header("X-Webhook-Event", event.as_str())
+            .header("X-Webhook-Timestamp", timestamp.to_string())
+            .header(
+                "X-Webhook-Signature",
+                signature(&hook.secret, timestamp, body),
+            )
+            .body(body.to_vec())
+            .send()
+            .await;
+
+        let (status_code, error) = match &result {
+            Ok(response) => (Some(response.status().as_u16()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        let success = status_code.is_some_and(|code| (200..300).contains(&code));
+        record(WebhookDelivery {
+            webhook_id: hook.id.clone(),
+            delivery_id: delivery_id.to_string(),
+            event: event.as_str().to_string(),
+            attempt,
+            status_code,
+            error,
+            success,
+            duration_ms: started.elapsed().as_millis() as u64,
+            attempted_at: timestamp,
+        });
+
+        if success {
+            return true;
+        }
+        if status_code.is_some_and(|code| !is_retryable(code)) {
+            warn!(
+                "⚠️  [WEBHOOK] {} rejected {} with {:?}",
+                hook.id,
+                event.as_str(),
+                status_code
+            );
+            return false;
+        }
+        if attempt < policy.max_attempts {
+            tokio::time::sleep(policy.base_delay * 2u32.pow(attempt - 1)).await;
+        }
+    }
+
+    warn!(
+        "⚠️  [WEBHOOK] Giving up on {} for {} after {} attempts",
+        event.as_str(),
+        hook.id,
+        policy.max_attempts
+    );
+    false
+}
+
+/// Value of X-Webhook-Signature for a body sent at `timestamp`
+fn signature(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut signed = format!("{}.", timestamp).into_bytes();
+    signed.extend_from_slice(body);
+    format!("sha256={}", hmac_sign_bytes(&signed, secret.as_bytes()))
+}
+
+fn is_retryable(status_code: u16) -> bool {
+    status_code >= 500 || status_code == 408 || status_code == 429
+}
+
+fn envelope(delivery_id: &str, event: WebhookEvent, data: Value) -> Vec<u8> {
+    json!({
+        "id": delivery_id,
+        "event": event.as_str(),
+        "created_at": chrono::Utc::now().timestamp_millis(),
+        "data": data,
+    })
+    .to_string()
+    .into_bytes()
+}
+
+fn validate(url: &str, events: &[WebhookEvent]) -> Result<(), String> {
+    let parsed = url::Url::parse(url.trim()).map_err(|e| format!("Invalid webhook URL: {}", e))?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host().is_none() {
+        return Err("Webhook URL must be an http(s) URL".to_string());
+    }
+    if events.is_empty() {
+        return Err("Pick at least one event".to_string());
+    }
+    Ok(())
+}
+
+fn events_json(events: &[WebhookEvent]) -> Result<String, String> {
+    serde_json::to_string(events).map_err(|e| e.to_string())
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .unwrap_or_default()
+}
+
+fn generate_id(prefix: &str) -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}_{}", prefix, hex)
+}
+
+fn generate_secret() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hmac::{Hmac, Mac};
+    use sha2::Sha256;
+    use std::sync::mpsc;
+
+    struct Received {
+        headers: Vec<(String, String)>,
+        body: Vec<u8>,
+    }
+
+    impl Received {
+        fn header(&self, name: &str) -> &str {
+            self.headers
+                .iter()
+                .find(|(field, _)| field.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+                .unwrap_or_default()
+        }
+    }
+
+    /// Answers requests with `statuses` in order, reporting each request it got
+    fn mock_server(statuses: Vec<u16>) -> (String, mpsc::Receiver<Received>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", server.server_addr());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            for status in statuses {
+                let mut request = server.recv().unwrap();
+                let mut body = Vec::new();
+                request.as_reader().read_to_end(&mut body).unwrap();
+                let headers = request
+                    .headers()
+                    .iter()
+                    .map(|h| (h.field.to_string(), h.value.to_string()))
+                    .collect();
+                let _ = tx.send(Received { headers, body });
+                request.respond(tiny_http::Response::empty(status)).unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    fn hook(url: &str) -> Webhook {
+        Webhook {
+            id: "wh_test".to_string(),
+            url: url.to_string(),
+            secret: "whsec_test".to_string(),
+            events: vec![WebhookEvent::DealSold],
+            enabled: true,
+            created_at: 0,
+            updated_at: 0,
+        }
+    }
+
+    fn run(url: &str, policy: RetryPolicy) -> (bool, Vec<WebhookDelivery>) {
+        let body = envelope("whd_1", WebhookEvent::DealSold, json!({ "id": "deal-1" }));
+        let mut attempts = Vec::new();
+        let delivered = tauri::async_runtime::block_on(deliver(
+            &http_client(),
+            &hook(url),
+            "whd_1",
+            WebhookEvent::DealSold,
+            &body,
+            policy,
+            |attempt| attempts.push(attempt),
+        ));
+        (delivered, attempts)
+    }
+
+    fn fast(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn test_signature_matches_body_and_timestamp() {
+        let (url, received) = mock_server(vec![200]);
+        let (delivered, attempts) = run(&url, fast(3));
+        assert!(delivered);
+        assert_eq!(attempts.len(), 1);
+
+        let request = received.recv().unwrap();
+        assert_eq!(request.header("X-Webhook-Event"), "deal.sold");
+        assert_eq!(request.header("X-Webhook-Id"), "whd_1");
+
+        // Recompute independently, the way a receiver would
+        let timestamp = request.header("X-Webhook-Timestamp");
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"whsec_test").unwrap();
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(&request.body);
+        let expected = format!(
+            "sha256={}",
+            general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+        );
+        assert_eq!(request.header("X-Webhook-Signature"), expected);
+
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body["event"], "deal.sold");
+        assert_eq!(body["data"]["id"], "deal-1");
+
+        // A tampered body no longer matches
+        assert_ne!(
+            signature("whsec_test", timestamp.parse().unwrap(), b"{}"),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_retries_server_errors_then_succeeds() {
+        let (url, received) = mock_server(vec![500, 503, 200]);
+        let (delivered, attempts) = run(&url, fast(5));
+
+        assert!(delivered);
+        let codes: Vec<Option<u16>> = attempts.iter().map(|a| a.status_code).collect();
+        assert_eq!(codes, vec![Some(500), Some(503), Some(200)]);
+        assert_eq!(
+            attempts.iter().map(|a| a.attempt).collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+        assert!(attempts.iter().all(|a| a.delivery_id == "whd_1"));
+        assert_eq!(received.try_iter().count(), 3);
+    }
+
+    #[test]
+    fn test_gives_up_on_rejection_and_exhausted_attempts() {
+        // 4xx other than 408/429 is final
+        let (url, _received) = mock_server(vec![400]);
+        let (delivered, attempts) = run(&url, fast(5));
+        assert!(!delivered);
+        assert_eq!(attempts.len(), 1);
+
+        let (url, _received) = mock_server(vec![500, 429, 502]);
+        let (delivered, attempts) = run(&url, fast(3));
+        assert!(!delivered);
+        assert_eq!(attempts.len(), 3);
+        assert!(attempts.iter().all(|a| !a.success));
+
+        // Nothing listening: network errors are recorded and retried too
+        let (delivered, attempts) = run("http://127.0.0.1:9/hook", fast(2));
+        assert!(!delivered);
+        assert_eq!(attempts.len(), 2);
+        assert!(attempts
+            .iter()
+            .all(|a| a.status_code.is_none() && a.error.is_some()));
+    }
+
+    #[test]
+    fn test_validation_and_event_names() {
+        assert!(validate("https://hooks.example.com/abc", &[WebhookEvent::DealSold]).is_ok());
+        assert!(validate("ftp://example.com", &[WebhookEvent::DealSold]).is_err());
+        assert!(validate("not a url", &[WebhookEvent::DealSold]).is_err());
+        assert!(validate("https://hooks.example.com", &[]).is_err());
+
+        for event in [
+            WebhookEvent::DealCreated,
+            WebhookEvent::DealUpdated,
+            WebhookEvent::DealSold,
+            WebhookEvent::ClientCreated,
+            WebhookEvent::ClientUpdated,
+            WebhookEvent::ClientDeleted,
+        ] {
+            assert_eq!(serde_json::to_value(event).unwrap(), json!(event.as_str()));
+        }
+        let events: Vec<WebhookEvent> = serde_json::from_str(r#"["deal.sold"]"#).unwrap();
+        assert_eq!(events, vec![WebhookEvent::DealSold]);
+        assert!(serde_json::from_str::<WebhookEvent>(r#""deal.exploded""#).is_err());
+    }
+}
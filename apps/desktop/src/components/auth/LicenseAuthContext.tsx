@@ -163,6 +163,21 @@ export function LicenseAuthProvider({ children }: { children: React.ReactNode })
         if (result) {
           console.log("✅ Standalone session validated for user:", result.user.email);
           setConvexAuth(storedSessionToken);
+
+          // Make this user current on the Rust side; db_* commands run as them from now on
+          // and ignore the userId the services pass
+          const isTauri = typeof window !== "undefined" && "__TAURI__" in window;
+          if (isTauri) {
+            try {
+              await invoke("auth_set_current_user", {
+                userId: result.user.id,
+                sessionToken: storedSessionToken,
+              });
+            } catch (error) {
+              console.error("❌ [AUTH] Failed to set the current user:", error);
+              return null;
+            }
+          }
           return result;
         } else {
           console.log("❌ Standalone session validation failed");
@@ -514,6 +529,14 @@ export function LicenseAuthProvider({ children }: { children: React.ReactNode })
       console.log("🗑️ [LOGOUT] Removing session tokens, isTauri:", isTauri);
       
       if (isTauri) {
+        if (authData?.user?.id) {
+          try {
+            // Also stops db_* commands running as this user
+            await invoke("remove_session_for_user", { userId: authData.user.id });
+          } catch (error) {
+            console.error("❌ [LOGOUT] Failed to remove the user's session:", error);
+          }
+        }
         try {
           await invoke("remove_secure", { key: "standalone_session_token" });
           console.log("✅ [LOGOUT] Token removed from secure storage");
@@ -11,6 +11,7 @@ import { EmailDialog } from "./EmailDialog";
 import { save } from "@tauri-apps/plugin-dialog";
 import { writeFile } from "@tauri-apps/plugin-fs";
 import { invoke } from "@tauri-apps/api/core";
+import { invokeDiskWrite } from "@/lib/disk-space";
 
 interface FinalizeStepProps {
   onBack: () => void;
@@ -276,7 +277,7 @@ export function FinalizeStep({
           const tempPath = `${tempDir}/${docName}.pdf`;
           
           // Use Rust command to write file (bypasses Tauri FS scope restrictions)
-          await invoke("write_file_to_path", {
+          await invokeDiskWrite("write_file_to_path", {
             filePath: tempPath,
             fileData: Array.from(uint8Array),
           });
@@ -311,7 +312,7 @@ export function FinalizeStep({
           const tempPath = `${tempDir}/${fileName}`;
           
           // Use Rust command to write file (bypasses Tauri FS scope restrictions)
-          await invoke("write_file_to_path", {
+          await invokeDiskWrite("write_file_to_path", {
             filePath: tempPath,
             fileData: Array.from(uint8Array),
           });
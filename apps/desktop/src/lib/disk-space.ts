@@ -0,0 +1,75 @@
+// src/lib/disk-space.ts
+// Typed errors from the Rust commands that write documents to disk
+// (write_file_to_path, db_create_document, s3_download_document).
+// Tauri rejects with a { kind, ...details } object rather than a string,
+// so String(error) would only show "[object Object]".
+
+import { invoke, type InvokeArgs } from "@tauri-apps/api/core";
+
+export type DiskSpaceError =
+  | {
+      kind: "insufficient_space";
+      path: string;
+      available_bytes: number;
+      required_bytes: number;
+    }
+  | {
+      kind: "quota_exceeded";
+      user_id: string;
+      used_bytes: number;
+      quota_bytes: number;
+      incoming_bytes: number;
+    }
+  | { kind: "other"; message: string };
+
+const DISK_SPACE_KINDS = ["insufficient_space", "quota_exceeded", "other"];
+
+export function isDiskSpaceError(error: unknown): error is DiskSpaceError {
+  return (
+    typeof error === "object" &&
+    error !== null &&
+    DISK_SPACE_KINDS.includes((error as { kind?: unknown }).kind as string)
+  );
+}
+
+function megabytes(bytes: number): string {
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`;
+}
+
+/**
+ * User-facing message for a disk space error
+ */
+export function describeDiskSpaceError(error: DiskSpaceError): string {
+  switch (error.kind) {
+    case "insufficient_space":
+      return `Not enough disk space at ${error.path}: ${megabytes(error.available_bytes)} free, ${megabytes(error.required_bytes)} needed`;
+    case "quota_exceeded":
+      return `Storage quota exceeded: ${megabytes(error.used_bytes)} of ${megabytes(error.quota_bytes)} used, ${megabytes(error.incoming_bytes)} more requested`;
+    case "other":
+      return error.message;
+  }
+}
+
+/**
+ * Error thrown in place of a DiskSpaceError; `detail` keeps the kind and figures
+ */
+export class DiskSpaceFailure extends Error {
+  readonly detail: DiskSpaceError;
+
+  constructor(detail: DiskSpaceError) {
+    super(describeDiskSpaceError(detail));
+    this.name = "DiskSpaceFailure";
+    this.detail = detail;
+  }
+}
+
+/**
+ * Invoke a command that returns DiskSpaceError, rejecting with a DiskSpaceFailure instead
+ */
+export async function invokeDiskWrite<T>(command: string, args: InvokeArgs): Promise<T> {
+  try {
+    return await invoke<T>(command, args);
+  } catch (error) {
+    throw isDiskSpaceError(error) ? new DiskSpaceFailure(error) : error;
+  }
+}
@@ -3,6 +3,7 @@
 
 import { invoke } from '@tauri-apps/api/core';
 import { toast } from 'sonner';
+import { invokeDiskWrite } from './disk-space';
 
 /**
  * Print a PDF file using the system's default PDF viewer
@@ -73,7 +74,7 @@ export async function downloadAndPrint(
     const finalFileName = fileName || `print-${Date.now()}.pdf`;
     const filePath = `${tempDir}/${finalFileName}`;
 
-    await invoke('write_file_to_path', {
+    await invokeDiskWrite('write_file_to_path', {
       path: filePath,
       contents: Array.from(uint8Array),
     });
@@ -23,10 +23,12 @@ export interface LocalClient {
 
 /**
  * Create a new client
+ * normalizeAddress cleans up the address (street suffixes, state code, city from ZIP)
  */
 export async function createClient(
   client: Omit<LocalClient, "id" | "created_at" | "updated_at"> & { id?: string },
-  userId?: string
+  userId?: string,
+  normalizeAddress = false
 ): Promise<LocalClient> {
   console.log("🔍 [CREATE-CLIENT] Function called with userId:", userId);
   console.log("  - typeof userId:", typeof userId);
@@ -56,6 +58,7 @@ export async function createClient(
   return await invoke<LocalClient>("db_create_client", {
     client: newClient,
     userId: userId, // camelCase - Tauri converts to user_id in Rust
+    normalizeAddress,
   });
 }
 
@@ -88,9 +91,10 @@ export async function getAllClients(userId?: string): Promise<LocalClient[]> {
  */
 export async function updateClient(
   id: string,
-  updates: Partial<LocalClient>
+  updates: Partial<LocalClient>,
+  normalizeAddress = false
 ): Promise<LocalClient> {
-  return await invoke<LocalClient>("db_update_client", { id, updates });
+  return await invoke<LocalClient>("db_update_client", { id, updates, normalizeAddress });
 }
 
 /**
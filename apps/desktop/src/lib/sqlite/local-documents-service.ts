@@ -5,6 +5,7 @@
  */
 
 import { invoke } from "@tauri-apps/api/core";
+import { invokeDiskWrite } from "@/lib/disk-space";
 
 export interface LocalDocument {
   id: string;
@@ -149,7 +150,7 @@ export async function createDocument(
     // Write file to disk using Tauri command
     // Tauri automatically converts camelCase to snake_case, so use camelCase here
     console.log("📄 [CREATE-DOCUMENT] Writing file to disk...");
-    await invoke("write_file_to_path", {
+    await invokeDiskWrite("write_file_to_path", {
       filePath: filePath,
       fileData: Array.from(uint8Array),
     });
@@ -169,7 +170,7 @@ export async function createDocument(
     };
 
     console.log("📄 [CREATE-DOCUMENT] Saving document metadata to database...");
-    const savedDocument = await invoke<LocalDocument>("db_create_document", {
+    const savedDocument = await invokeDiskWrite<LocalDocument>("db_create_document", {
       document: newDocument,
     });
     console.log("✅ [CREATE-DOCUMENT] Document saved to database:", savedDocument.id);
@@ -245,24 +246,21 @@ export async function updateDocument(
 
 /**
  * Delete a document
- * Deletes both the file and database record
+ * The backend deletes the record and moves the file to the documents trash,
+ * where restoreDeletedDocumentFile can bring it back until it's purged
  */
 export async function deleteDocument(id: string): Promise<void> {
-  const doc = await getDocument(id);
-  if (!doc) return;
-
-  // Delete file from disk
-  try {
-    await invoke("remove_file", { filePath: doc.file_path });
-  } catch (error) {
-    console.error("Error deleting document file:", error);
-    // Continue with database deletion even if file deletion fails
-  }
-
-  // Delete from database
   await invoke("db_delete_document", { id });
 }
 
+/**
+ * Put a deleted document's file back at its original path
+ * Returns that path
+ */
+export async function restoreDeletedDocumentFile(documentId: string): Promise<string> {
+  return await invoke<string>("restore_deleted_document_file", { documentId });
+}
+
 /**
  * Convert base64 string to Blob
  */
@@ -25,12 +25,21 @@ export interface LocalVehicle {
   cost?: number;
   status: string;
   description?: string;
-  images?: string[];
+  // Older vehicles store plain paths/URLs; photos added with db_add_vehicle_image are entries
+  images?: (string | VehicleImage)[];
   created_at: number;
   updated_at: number;
   synced_at?: number;
 }
 
+export interface VehicleImage {
+  id: string;
+  path: string;
+  thumb_path?: string | null;
+  order: number;
+  added_at: number;
+}
+
 /**
  * Create a new vehicle
  */